@@ -0,0 +1,15 @@
+-- Long-horizon behavioral profiles, recomputed nightly by the feature store
+CREATE TABLE user_profiles (
+    user_id UUID PRIMARY KEY,
+    account_id UUID NOT NULL,
+    transaction_count BIGINT NOT NULL DEFAULT 0,
+    avg_order_amount DOUBLE PRECISION,
+    order_amount_stddev DOUBLE PRECISION,
+    usual_purchase_hours INTEGER[] NOT NULL DEFAULT '{}',
+    usual_countries TEXT[] NOT NULL DEFAULT '{}',
+    typical_device_count INTEGER NOT NULL DEFAULT 0,
+    computed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_user_profiles_account_id ON user_profiles(account_id);
+CREATE INDEX idx_user_profiles_computed_at ON user_profiles(computed_at);
@@ -0,0 +1,34 @@
+-- End users of each account, tracked across transactions
+CREATE TABLE users (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    external_user_id VARCHAR(255),
+    user_hash VARCHAR(64),
+    risk_score DOUBLE PRECISION NOT NULL DEFAULT 0,
+    risk_level VARCHAR(20) NOT NULL DEFAULT 'low' CHECK (risk_level IN ('low', 'medium', 'high', 'very_high')),
+    total_transactions INTEGER NOT NULL DEFAULT 0,
+    successful_transactions INTEGER NOT NULL DEFAULT 0,
+    failed_transactions INTEGER NOT NULL DEFAULT 0,
+    chargeback_count INTEGER NOT NULL DEFAULT 0,
+    first_transaction_at TIMESTAMP WITH TIME ZONE,
+    last_transaction_at TIMESTAMP WITH TIME ZONE,
+    is_verified BOOLEAN NOT NULL DEFAULT false,
+    is_flagged BOOLEAN NOT NULL DEFAULT false,
+    flags JSONB NOT NULL DEFAULT '[]',
+    metadata JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (account_id, external_user_id),
+    UNIQUE (account_id, user_hash)
+);
+
+CREATE INDEX idx_users_risk_score ON users(account_id, risk_score DESC);
+CREATE INDEX idx_users_is_flagged ON users(account_id, is_flagged);
+CREATE INDEX idx_users_last_transaction_at ON users(last_transaction_at);
+
+CREATE TRIGGER update_users_updated_at BEFORE UPDATE ON users FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Profiles predate the users table, so the foreign key is attached here
+ALTER TABLE user_profiles
+    ADD CONSTRAINT user_profiles_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    ADD CONSTRAINT user_profiles_account_id_fkey FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE;
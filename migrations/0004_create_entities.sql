@@ -0,0 +1,103 @@
+-- Devices seen by each account, keyed by a fingerprint of their network identity
+CREATE TABLE devices (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    fingerprint_hash VARCHAR(64) NOT NULL,
+    ip_address INET NOT NULL,
+    user_agent TEXT,
+    accept_language VARCHAR(255),
+    session_id VARCHAR(255),
+    session_age DOUBLE PRECISION,
+    risk_score DOUBLE PRECISION,
+    location_data JSONB NOT NULL DEFAULT '{}',
+    traits_data JSONB NOT NULL DEFAULT '{}',
+    first_seen TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (account_id, fingerprint_hash)
+);
+
+CREATE INDEX idx_devices_user_id ON devices(user_id);
+CREATE INDEX idx_devices_ip_address ON devices(account_id, ip_address);
+CREATE INDEX idx_devices_last_seen ON devices(last_seen);
+
+-- Email addresses, stored only as SHA-256 hashes plus their domain
+CREATE TABLE email_addresses (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    email_hash VARCHAR(64) NOT NULL,
+    domain VARCHAR(255),
+    is_free BOOLEAN NOT NULL DEFAULT false,
+    is_disposable BOOLEAN NOT NULL DEFAULT false,
+    is_high_risk BOOLEAN NOT NULL DEFAULT false,
+    first_seen DATE NOT NULL DEFAULT CURRENT_DATE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (account_id, email_hash)
+);
+
+CREATE INDEX idx_email_addresses_user_id ON email_addresses(user_id);
+CREATE INDEX idx_email_addresses_domain ON email_addresses(domain);
+
+-- Billing and shipping addresses
+CREATE TABLE addresses (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    first_name VARCHAR(255),
+    last_name VARCHAR(255),
+    company VARCHAR(255),
+    address_line_1 VARCHAR(255),
+    address_line_2 VARCHAR(255),
+    city VARCHAR(255),
+    region VARCHAR(4),
+    postal_code VARCHAR(255),
+    country CHAR(2),
+    phone_number VARCHAR(255),
+    phone_country_code VARCHAR(4),
+    latitude DOUBLE PRECISION,
+    longitude DOUBLE PRECISION,
+    is_high_risk BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_addresses_user_id ON addresses(user_id);
+CREATE INDEX idx_addresses_country ON addresses(country);
+CREATE INDEX idx_addresses_postal_code ON addresses(postal_code);
+
+-- Payment cards, never stored beyond BIN and last digits
+CREATE TABLE credit_cards (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    issuer_id_number VARCHAR(8),
+    last_digits VARCHAR(4),
+    token_hash VARCHAR(64),
+    bank_name VARCHAR(255),
+    bank_phone_number VARCHAR(255),
+    bank_phone_country_code VARCHAR(4),
+    country CHAR(2),
+    avs_result CHAR(1),
+    cvv_result CHAR(1),
+    was_3d_secure_successful BOOLEAN,
+    brand VARCHAR(50),
+    card_type VARCHAR(20) CHECK (card_type IN ('credit', 'debit', 'charge')),
+    is_business BOOLEAN NOT NULL DEFAULT false,
+    is_prepaid BOOLEAN NOT NULL DEFAULT false,
+    is_virtual BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_credit_cards_user_id ON credit_cards(user_id);
+CREATE INDEX idx_credit_cards_issuer_id ON credit_cards(issuer_id_number);
+CREATE INDEX idx_credit_cards_token_hash ON credit_cards(account_id, token_hash);
+
+CREATE TRIGGER update_devices_updated_at BEFORE UPDATE ON devices FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_email_addresses_updated_at BEFORE UPDATE ON email_addresses FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_addresses_updated_at BEFORE UPDATE ON addresses FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_credit_cards_updated_at BEFORE UPDATE ON credit_cards FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
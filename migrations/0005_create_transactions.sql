@@ -0,0 +1,120 @@
+-- Scored transactions
+CREATE TABLE transactions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    external_transaction_id VARCHAR(255),
+    risk_score DOUBLE PRECISION NOT NULL,
+    risk_level VARCHAR(20) NOT NULL CHECK (risk_level IN ('low', 'medium', 'high', 'very_high')),
+    disposition VARCHAR(20) NOT NULL CHECK (disposition IN ('accept', 'reject', 'review', 'test')),
+    event_type VARCHAR(50) NOT NULL CHECK (event_type IN ('account_creation', 'account_login', 'email_change', 'password_reset', 'payout_change', 'purchase', 'recurring_purchase', 'referral', 'survey')),
+    shop_id VARCHAR(255),
+    event_time TIMESTAMP WITH TIME ZONE NOT NULL,
+    device_data JSONB NOT NULL DEFAULT '{}',
+    custom_inputs JSONB NOT NULL DEFAULT '{}',
+    warnings JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_transactions_account_created ON transactions(account_id, created_at DESC);
+CREATE INDEX idx_transactions_account_risk_score ON transactions(account_id, risk_score DESC);
+CREATE INDEX idx_transactions_user_created ON transactions(user_id, created_at);
+CREATE INDEX idx_transactions_event_time ON transactions(event_time);
+CREATE INDEX idx_transactions_external_id ON transactions(account_id, external_transaction_id);
+
+CREATE TABLE transaction_devices (
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    device_id UUID NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (transaction_id, device_id)
+);
+
+CREATE INDEX idx_transaction_devices_device_id ON transaction_devices(device_id);
+
+CREATE TABLE transaction_emails (
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    email_id UUID NOT NULL REFERENCES email_addresses(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (transaction_id, email_id)
+);
+
+CREATE INDEX idx_transaction_emails_email_id ON transaction_emails(email_id);
+
+CREATE TABLE transaction_addresses (
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    address_id UUID NOT NULL REFERENCES addresses(id) ON DELETE CASCADE,
+    address_type VARCHAR(20) NOT NULL CHECK (address_type IN ('billing', 'shipping')),
+    delivery_speed VARCHAR(20) CHECK (delivery_speed IN ('same_day', 'overnight', 'expedited', 'standard')),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (transaction_id, address_id, address_type)
+);
+
+CREATE TABLE transaction_credit_cards (
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    credit_card_id UUID NOT NULL REFERENCES credit_cards(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (transaction_id, credit_card_id)
+);
+
+CREATE TABLE orders (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    amount DOUBLE PRECISION NOT NULL,
+    currency CHAR(3) NOT NULL,
+    discount_code VARCHAR(255),
+    affiliate_id VARCHAR(255),
+    subaffiliate_id VARCHAR(255),
+    referrer_uri TEXT,
+    is_gift BOOLEAN NOT NULL DEFAULT false,
+    has_gift_message BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_orders_transaction_id ON orders(transaction_id);
+
+CREATE TABLE cart_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    item_id VARCHAR(255) NOT NULL,
+    category VARCHAR(255),
+    price DOUBLE PRECISION NOT NULL,
+    quantity BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_cart_items_order_id ON cart_items(order_id);
+
+-- Individual rule contributions behind each score
+CREATE TABLE risk_factors (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    factor_code VARCHAR(100) NOT NULL,
+    factor_type VARCHAR(50) NOT NULL,
+    multiplier DOUBLE PRECISION NOT NULL,
+    reason TEXT NOT NULL,
+    metadata JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_risk_factors_transaction_id ON risk_factors(transaction_id);
+
+-- Outcome reports fed back by customers
+CREATE TABLE transaction_reports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL UNIQUE REFERENCES transactions(id) ON DELETE CASCADE,
+    tag VARCHAR(50) NOT NULL CHECK (tag IN ('chargeback', 'not_fraud', 'suspected_fraud', 'spam_or_abuse')),
+    chargeback_code VARCHAR(32),
+    notes TEXT,
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'received' CHECK (status IN ('received', 'processing', 'processed')),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TRIGGER update_transactions_updated_at BEFORE UPDATE ON transactions FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_orders_updated_at BEFORE UPDATE ON orders FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_cart_items_updated_at BEFORE UPDATE ON cart_items FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_transaction_reports_updated_at BEFORE UPDATE ON transaction_reports FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
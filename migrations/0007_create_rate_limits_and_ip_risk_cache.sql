@@ -0,0 +1,31 @@
+-- Fixed-window request counters per account and endpoint
+CREATE TABLE rate_limits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    endpoint VARCHAR(255) NOT NULL,
+    requests_count INTEGER NOT NULL DEFAULT 0,
+    window_start TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(account_id, endpoint, window_start)
+);
+
+CREATE INDEX idx_rate_limits_window_start ON rate_limits(window_start);
+
+-- Cached IP intelligence lookups
+CREATE TABLE ip_risk_cache (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ip_address INET UNIQUE NOT NULL,
+    risk_score DOUBLE PRECISION,
+    risk_reasons JSONB NOT NULL DEFAULT '[]',
+    location_data JSONB NOT NULL DEFAULT '{}',
+    traits_data JSONB NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_ip_risk_cache_expires_at ON ip_risk_cache(expires_at);
+
+CREATE TRIGGER update_rate_limits_updated_at BEFORE UPDATE ON rate_limits FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_ip_risk_cache_updated_at BEFORE UPDATE ON ip_risk_cache FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
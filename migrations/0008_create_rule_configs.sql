@@ -0,0 +1,14 @@
+-- Per-account overrides for built-in fraud rules
+CREATE TABLE rule_configs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    rule_code VARCHAR(100) NOT NULL,
+    is_enabled BOOLEAN NOT NULL DEFAULT true,
+    score DOUBLE PRECISION CHECK (score >= 0 AND score <= 100),
+    parameters JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(account_id, rule_code)
+);
+
+CREATE TRIGGER update_rule_configs_updated_at BEFORE UPDATE ON rule_configs FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
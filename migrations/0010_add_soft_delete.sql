@@ -0,0 +1,6 @@
+-- Soft deletion: deleted users and devices stop influencing risk but stay available for audit
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE devices ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_users_deleted_at ON users(account_id, deleted_at);
+CREATE INDEX idx_devices_deleted_at ON devices(account_id, deleted_at);
@@ -0,0 +1,20 @@
+-- Statistically unusual hourly fraud metrics, flagged by the anomaly detection job
+CREATE TABLE analytics_anomalies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    metric VARCHAR(50) NOT NULL CHECK (metric IN ('reject_rate', 'average_risk_score', 'rule_hit_rate')),
+    rule_code VARCHAR(100),
+    bucket_start TIMESTAMP WITH TIME ZONE NOT NULL,
+    observed_value DOUBLE PRECISION NOT NULL,
+    baseline_mean DOUBLE PRECISION NOT NULL,
+    baseline_stddev DOUBLE PRECISION NOT NULL,
+    z_score DOUBLE PRECISION NOT NULL,
+    transaction_count BIGINT NOT NULL,
+    detected_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One anomaly per metric (and rule) per hour, however often the job runs
+CREATE UNIQUE INDEX idx_analytics_anomalies_unique
+    ON analytics_anomalies(account_id, metric, COALESCE(rule_code, ''), bucket_start);
+CREATE INDEX idx_analytics_anomalies_account_bucket
+    ON analytics_anomalies(account_id, bucket_start DESC);
@@ -0,0 +1,17 @@
+-- Daily and weekly fraud summaries generated for each account
+CREATE TABLE reports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    frequency VARCHAR(10) NOT NULL CHECK (frequency IN ('daily', 'weekly')),
+    period_start TIMESTAMP WITH TIME ZONE NOT NULL,
+    period_end TIMESTAMP WITH TIME ZONE NOT NULL,
+    content JSONB NOT NULL,
+    export_location TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One report per account and period, however often the generator runs
+CREATE UNIQUE INDEX idx_reports_unique ON reports(account_id, frequency, period_start);
+CREATE INDEX idx_reports_account_period ON reports(account_id, period_start DESC);
+-- Reports still waiting to be pushed to object storage
+CREATE INDEX idx_reports_pending_export ON reports(created_at) WHERE export_location IS NULL;
@@ -0,0 +1,7 @@
+-- Days of scored transactions exported to Parquet for offline model training
+CREATE TABLE feature_exports (
+    day DATE PRIMARY KEY,
+    locations TEXT[] NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,3 @@
+-- Scopes granted to each API key (e.g. 'transactions:write'); NULL grants every scope, which
+-- keeps keys issued before scopes existed working unchanged
+ALTER TABLE api_keys ADD COLUMN scopes TEXT[];
@@ -0,0 +1,3 @@
+-- Shared secret for HMAC-signed requests. Unlike the key itself it cannot be stored hashed,
+-- since the server must recompute signatures; NULL disables signing for the key
+ALTER TABLE api_keys ADD COLUMN signing_secret TEXT;
@@ -0,0 +1,5 @@
+-- Expiry a reminder was last sent for; comparing it with expires_at re-arms the reminder
+-- whenever a key's expiry is extended
+ALTER TABLE api_keys ADD COLUMN expiry_reminded_for TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_api_keys_expires_at ON api_keys(expires_at) WHERE expires_at IS NOT NULL;
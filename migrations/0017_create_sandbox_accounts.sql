@@ -0,0 +1,4 @@
+-- Each account may have a sandbox namespace: a separate account row pointing back at it.
+-- Sandbox API keys belong to the sandbox account, so test data never mixes with live users,
+-- devices, transactions, or analytics
+ALTER TABLE accounts ADD COLUMN sandbox_of UUID UNIQUE REFERENCES accounts(id) ON DELETE CASCADE;
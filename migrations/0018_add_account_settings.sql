@@ -0,0 +1,7 @@
+-- Settings customers manage themselves through PATCH /v1/account
+ALTER TABLE accounts
+    ADD COLUMN contact_email VARCHAR(255),
+    ADD COLUMN disposition_policy VARCHAR(20) NOT NULL DEFAULT 'standard'
+        CHECK (disposition_policy IN ('standard', 'review', 'monitor')),
+    ADD COLUMN notify_key_expiry BOOLEAN NOT NULL DEFAULT true,
+    ADD COLUMN notify_anomalies BOOLEAN NOT NULL DEFAULT true;
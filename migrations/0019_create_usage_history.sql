@@ -0,0 +1,19 @@
+-- Metered scoring requests per account and UTC day
+CREATE TABLE usage_daily (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    queries INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (account_id, day)
+);
+
+-- Closed billing cycles, archived when an account rolls over to the next cycle
+CREATE TABLE billing_cycles (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    cycle_start DATE NOT NULL,
+    cycle_end DATE NOT NULL,
+    subscription_tier VARCHAR(50) NOT NULL,
+    monthly_quota INTEGER NOT NULL,
+    queries_used INTEGER NOT NULL,
+    closed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, cycle_start)
+);
@@ -0,0 +1,28 @@
+-- Account lifecycle. Suspended accounts still authenticate but may not score; closed accounts
+-- may only manage their own account and are deleted once deletion_scheduled_at passes, unless
+-- reactivated first. A sandbox account mirrors the status of its live account
+ALTER TABLE accounts
+    ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'active'
+        CHECK (status IN ('active', 'suspended', 'closed')),
+    ADD COLUMN status_changed_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN deletion_scheduled_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_accounts_deletion_scheduled_at
+    ON accounts(deletion_scheduled_at) WHERE deletion_scheduled_at IS NOT NULL;
+
+-- Audit log of status changes. Not tied to accounts by foreign key, so the record of a closure
+-- and deletion outlives the account itself
+CREATE TABLE account_status_changes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL,
+    public_account_id VARCHAR(255) NOT NULL,
+    from_status VARCHAR(20) NOT NULL,
+    to_status VARCHAR(20) NOT NULL CHECK (to_status IN ('active', 'suspended', 'closed', 'deleted')),
+    reason TEXT,
+    -- Key that requested the change; NULL for client certificates and background jobs
+    api_key_id UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_account_status_changes_account_id
+    ON account_status_changes(account_id, created_at);
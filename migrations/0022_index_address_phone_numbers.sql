@@ -0,0 +1,3 @@
+-- Transaction insights count how often a phone number recurs within an account
+CREATE INDEX idx_addresses_phone_number
+    ON addresses(account_id, phone_number) WHERE phone_number IS NOT NULL;
@@ -0,0 +1,4 @@
+-- Cursor pagination of transaction listings seeks on (created_at, id) within an account; the
+-- ID breaks ties between transactions created in the same microsecond
+CREATE INDEX idx_transactions_account_created_id ON transactions(account_id, created_at DESC, id DESC);
+DROP INDEX idx_transactions_account_created;
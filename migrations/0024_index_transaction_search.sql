@@ -0,0 +1,9 @@
+-- Transaction listing filters. Entity filters probe the link tables by transaction ID, which
+-- their primary keys already cover; these index the remaining columns filtered on
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_transactions_account_shop ON transactions(account_id, shop_id, created_at DESC);
+-- Case-insensitive substring search on the customer's own transaction ID
+CREATE INDEX idx_transactions_external_id_trgm
+    ON transactions USING gin (external_transaction_id gin_trgm_ops);
+CREATE INDEX idx_risk_factors_factor_code ON risk_factors(factor_code, transaction_id);
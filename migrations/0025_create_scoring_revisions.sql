@@ -0,0 +1,19 @@
+-- Request each transaction was scored on, so it can be rescored later. Email addresses and
+-- card tokens are stored hashed, as in the entity tables. NULL for transactions scored before
+-- requests were kept
+ALTER TABLE transactions ADD COLUMN raw_request JSONB;
+
+-- Later scorings of a transaction. The original scoring stays on the transaction row as
+-- revision 1; each rescore adds the next revision instead of overwriting it
+CREATE TABLE scoring_revisions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL CHECK (revision > 1),
+    risk_score DOUBLE PRECISION NOT NULL,
+    risk_level VARCHAR(20) NOT NULL,
+    disposition VARCHAR(20) NOT NULL,
+    -- Risk factors behind the score, as {code, factor_type, score, reason} objects
+    factors JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (transaction_id, revision)
+);
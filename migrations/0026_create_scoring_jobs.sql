@@ -0,0 +1,22 @@
+-- Transactions submitted with mode=async, scored later by the job worker. The request is kept
+-- only until the job finishes; the stored transaction keeps its own redacted copy
+CREATE TABLE scoring_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'completed', 'failed')),
+    request JSONB,
+    -- Where the result is POSTed once the job finishes
+    callback_url TEXT,
+    transaction_id UUID REFERENCES transactions(id) ON DELETE SET NULL,
+    -- Error response of a failed job, as it would have been returned synchronously
+    error JSONB,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    available_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_scoring_jobs_pending ON scoring_jobs(available_at) WHERE status = 'pending';
+CREATE INDEX idx_scoring_jobs_account_id ON scoring_jobs(account_id);
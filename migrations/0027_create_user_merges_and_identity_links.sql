@@ -0,0 +1,22 @@
+-- A user merged into another is soft-deleted and points at the user that absorbed it, so its
+-- identifiers keep resolving to the surviving user
+ALTER TABLE users ADD COLUMN merged_into UUID REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_users_merged_into ON users(merged_into) WHERE merged_into IS NOT NULL;
+
+-- Pairs of live users found transacting with the same email address, payment card, or device.
+-- Each pair is stored once per link type, with the smaller user ID first
+CREATE TABLE user_identity_links (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    linked_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    link_type VARCHAR(20) NOT NULL CHECK (link_type IN ('email', 'card', 'device')),
+    -- Distinct emails, cards, or devices the two users have in common
+    shared_count INTEGER NOT NULL,
+    first_detected_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_detected_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, linked_user_id, link_type),
+    CHECK (user_id < linked_user_id)
+);
+
+CREATE INDEX idx_user_identity_links_linked_user_id ON user_identity_links(linked_user_id);
@@ -0,0 +1,25 @@
+-- Customer user bases submitted through POST /v1/users/batch, imported in the background. The
+-- submitted users are kept only until the import finishes
+CREATE TABLE user_imports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'processing', 'completed')),
+    users JSONB,
+    total_users INTEGER NOT NULL,
+    processed_users INTEGER NOT NULL DEFAULT 0,
+    created_users INTEGER NOT NULL DEFAULT 0,
+    updated_users INTEGER NOT NULL DEFAULT 0,
+    failed_users INTEGER NOT NULL DEFAULT 0,
+    -- The first failures, as {index, message} objects
+    errors JSONB NOT NULL DEFAULT '[]',
+    -- Refreshed as each chunk is imported; an import left processing without a heartbeat is
+    -- picked up again where it stopped
+    heartbeat_at TIMESTAMP WITH TIME ZONE,
+    started_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_user_imports_unfinished ON user_imports(created_at) WHERE status <> 'completed';
+CREATE INDEX idx_user_imports_account_id ON user_imports(account_id);
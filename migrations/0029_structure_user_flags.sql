@@ -0,0 +1,10 @@
+-- User flags become objects with a type, reason, actor, and expiry. Existing labels are kept as
+-- flags of that type, with no reason, actor, or expiry
+UPDATE users
+SET flags = (
+    SELECT jsonb_agg(
+        CASE WHEN jsonb_typeof(f) = 'string' THEN jsonb_build_object('type', f #>> '{}') ELSE f END
+    )
+    FROM jsonb_array_elements(flags) f
+)
+WHERE flags <> '[]'::jsonb;
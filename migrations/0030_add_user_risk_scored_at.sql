@@ -0,0 +1,5 @@
+-- When each user's risk score was last recalculated from their history. NULL marks a user due
+-- for recalculation: never scored, or with new activity since
+ALTER TABLE users ADD COLUMN risk_scored_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_users_risk_scored_at ON users(risk_scored_at NULLS FIRST) WHERE deleted_at IS NULL;
@@ -0,0 +1,3 @@
+-- What each device's user agent says about it: browser, operating system, device class, and
+-- any automation tool. Filled in as devices are next seen with a user agent.
+ALTER TABLE devices ADD COLUMN user_agent_details JSONB;
@@ -0,0 +1,21 @@
+-- Which users each device has been seen with, so devices shared between users can be spotted.
+-- Maintained as transactions are stored, and backfilled from the transactions stored so far.
+CREATE TABLE device_users (
+    device_id UUID NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    transaction_count INTEGER NOT NULL DEFAULT 1,
+    first_seen TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (device_id, user_id)
+);
+
+CREATE INDEX idx_device_users_user_id ON device_users(user_id);
+CREATE INDEX idx_device_users_device_last_seen ON device_users(device_id, last_seen);
+
+INSERT INTO device_users (device_id, user_id, account_id, transaction_count, first_seen, last_seen)
+SELECT td.device_id, t.user_id, t.account_id, COUNT(*), MIN(t.created_at), MAX(t.created_at)
+FROM transaction_devices td
+JOIN transactions t ON t.id = td.transaction_id
+WHERE t.user_id IS NOT NULL
+GROUP BY td.device_id, t.user_id, t.account_id;
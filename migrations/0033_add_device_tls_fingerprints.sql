@@ -0,0 +1,5 @@
+-- TLS and HTTP header fingerprints of each device, as last passed by the merchant's edge
+ALTER TABLE devices
+    ADD COLUMN ja3 VARCHAR(32),
+    ADD COLUMN ja4 VARCHAR(64),
+    ADD COLUMN header_order_hash VARCHAR(128);
@@ -0,0 +1,6 @@
+-- Analysts' verdict on each device: trusted devices score lower, and transactions from blocked
+-- devices are rejected outright
+ALTER TABLE devices
+    ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'normal'
+        CHECK (status IN ('normal', 'trusted', 'blocked')),
+    ADD COLUMN status_changed_at TIMESTAMP WITH TIME ZONE;
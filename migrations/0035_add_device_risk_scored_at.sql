@@ -0,0 +1,3 @@
+-- When each device's composite risk score was last computed; the score itself is recomputed
+-- whenever the device is seen
+ALTER TABLE devices ADD COLUMN risk_scored_at TIMESTAMP WITH TIME ZONE;
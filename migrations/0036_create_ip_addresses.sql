@@ -0,0 +1,45 @@
+-- IP address each transaction came from, so the history of an address can be gathered
+ALTER TABLE transactions ADD COLUMN ip_address INET;
+
+UPDATE transactions
+SET ip_address = (device_data->>'ip_address')::inet
+WHERE device_data ? 'ip_address';
+
+CREATE INDEX idx_transactions_account_ip_address ON transactions(account_id, ip_address);
+
+-- History of each IP address on each account, for IP reputation. Recomputed whenever the
+-- address is seen, and backfilled from the transactions stored so far; risk scores are
+-- computed as addresses are next seen.
+CREATE TABLE ip_addresses (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    ip_address INET NOT NULL,
+    transaction_count INTEGER NOT NULL DEFAULT 0,
+    reject_count INTEGER NOT NULL DEFAULT 0,
+    chargeback_count INTEGER NOT NULL DEFAULT 0,
+    user_count INTEGER NOT NULL DEFAULT 0,
+    card_count INTEGER NOT NULL DEFAULT 0,
+    risk_score DOUBLE PRECISION,
+    first_seen TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, ip_address)
+);
+
+CREATE INDEX idx_ip_addresses_ip_address ON ip_addresses(ip_address);
+
+INSERT INTO ip_addresses (
+    account_id, ip_address, transaction_count, reject_count, chargeback_count, user_count,
+    card_count, first_seen, last_seen
+)
+SELECT t.account_id, t.ip_address, COUNT(DISTINCT t.id),
+       COUNT(DISTINCT t.id) FILTER (WHERE t.disposition = 'reject'),
+       COUNT(DISTINCT t.id) FILTER (WHERE r.tag = 'chargeback'),
+       COUNT(DISTINCT t.user_id), COUNT(DISTINCT tc.credit_card_id),
+       MIN(t.created_at), MAX(t.created_at)
+FROM transactions t
+LEFT JOIN transaction_reports r ON r.transaction_id = t.id
+LEFT JOIN transaction_credit_cards tc ON tc.transaction_id = t.id
+WHERE t.ip_address IS NOT NULL
+GROUP BY t.account_id, t.ip_address;
+
+CREATE TRIGGER update_ip_addresses_updated_at BEFORE UPDATE ON ip_addresses FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
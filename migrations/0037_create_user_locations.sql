@@ -0,0 +1,8 @@
+-- Where each user's latest transaction came from, as located from its IP address, so the
+-- next one can be checked for travel faster than is possible
+CREATE TABLE user_locations (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    latitude DOUBLE PRECISION NOT NULL,
+    longitude DOUBLE PRECISION NOT NULL,
+    located_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
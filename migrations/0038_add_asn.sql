@@ -0,0 +1,18 @@
+-- Network each transaction and device came from, as resolved from its IP address
+ALTER TABLE transactions ADD COLUMN asn BIGINT, ADD COLUMN isp TEXT;
+ALTER TABLE devices ADD COLUMN asn BIGINT, ADD COLUMN isp TEXT;
+
+-- Networks an account blocks outright or scores higher, such as bulletproof hosters
+CREATE TABLE asn_list_entries (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    asn BIGINT NOT NULL CHECK (asn >= 0 AND asn <= 4294967295),
+    action VARCHAR(20) NOT NULL CHECK (action IN ('block', 'boost')),
+    score DOUBLE PRECISION CHECK (score >= 0 AND score <= 100),
+    reason TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, asn),
+    CHECK (action = 'block' OR score IS NOT NULL)
+);
+
+CREATE TRIGGER update_asn_list_entries_updated_at BEFORE UPDATE ON asn_list_entries FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,14 @@
+-- Countries an account blocks outright, sends to review, or scores higher
+CREATE TABLE country_list_entries (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    country VARCHAR(2) NOT NULL CHECK (country ~ '^[A-Z]{2}$'),
+    action VARCHAR(20) NOT NULL CHECK (action IN ('block', 'review', 'boost')),
+    score DOUBLE PRECISION CHECK (score >= 0 AND score <= 100),
+    reason TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, country),
+    CHECK ((action = 'boost') = (score IS NOT NULL))
+);
+
+CREATE TRIGGER update_country_list_entries_updated_at BEFORE UPDATE ON country_list_entries FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
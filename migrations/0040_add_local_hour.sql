@@ -0,0 +1,5 @@
+-- Hour of day in the time zone of the IP address each transaction came from
+ALTER TABLE transactions ADD COLUMN local_hour SMALLINT CHECK (local_hour >= 0 AND local_hour <= 23);
+
+-- Hours of day, local to where the user was, in which the user habitually purchases
+ALTER TABLE user_profiles ADD COLUMN usual_local_purchase_hours INTEGER[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,6 @@
+-- Hash of the mailbox each email address delivers to, without plus tags or Gmail dots, so
+-- variants of one mailbox can be found. Addresses stored before are only known by their hash
+-- and keep none until they are seen again.
+ALTER TABLE email_addresses ADD COLUMN mailbox_hash VARCHAR(64);
+
+CREATE INDEX idx_email_addresses_mailbox_hash ON email_addresses(account_id, mailbox_hash);
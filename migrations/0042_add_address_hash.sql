@@ -0,0 +1,5 @@
+-- Hash of each billing and shipping address in normal form, so repeat uses of an address can
+-- be found however it was written. Addresses stored before keep none.
+ALTER TABLE addresses ADD COLUMN address_hash VARCHAR(64);
+
+CREATE INDEX idx_addresses_address_hash ON addresses(account_id, address_hash);
@@ -0,0 +1,5 @@
+-- How closely the name on each card matched the billing name and the email address of its
+-- transaction, from 0 to 1. The names and addresses compared are not kept on the card.
+ALTER TABLE credit_cards
+    ADD COLUMN billing_name_match DOUBLE PRECISION,
+    ADD COLUMN email_name_match DOUBLE PRECISION;
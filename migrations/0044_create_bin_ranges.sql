@@ -0,0 +1,17 @@
+-- Ranges of cards an account's BIN table describes, by the leading digits of their numbers
+CREATE TABLE bin_ranges (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    prefix VARCHAR(8) NOT NULL CHECK (prefix ~ '^[0-9]{4,8}$'),
+    brand VARCHAR(50),
+    card_type VARCHAR(20) CHECK (card_type IN ('credit', 'debit', 'charge')),
+    country CHAR(2) CHECK (country ~ '^[A-Z]{2}$'),
+    bank_name VARCHAR(255),
+    is_prepaid BOOLEAN NOT NULL DEFAULT false,
+    is_business BOOLEAN NOT NULL DEFAULT false,
+    is_virtual BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, prefix)
+);
+
+CREATE TRIGGER update_bin_ranges_updated_at BEFORE UPDATE ON bin_ranges FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,3 @@
+-- Email addresses by hash across accounts, so the day any account first saw an address can be
+-- found when scoring
+CREATE INDEX idx_email_addresses_email_hash ON email_addresses(email_hash);
@@ -0,0 +1,23 @@
+-- Entities an account blocks, trusts, or watches: email addresses and card tokens by SHA-256
+-- hash, email domains, IP addresses and ranges, devices and users by ID, and countries. Values
+-- are stored in the normal form they are matched in. Entries past their expiry are ignored.
+CREATE TABLE list_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    list_type VARCHAR(20) NOT NULL CHECK (list_type IN ('blocklist', 'allowlist', 'watchlist')),
+    entity_type VARCHAR(20) NOT NULL CHECK (
+        entity_type IN (
+            'email', 'email_domain', 'ip', 'cidr', 'device', 'card_hash', 'user', 'country'
+        )
+    ),
+    value VARCHAR(255) NOT NULL,
+    reason TEXT,
+    expires_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (account_id, list_type, entity_type, value)
+);
+
+CREATE INDEX idx_list_entries_value ON list_entries(account_id, entity_type, value);
+
+CREATE TRIGGER update_list_entries_updated_at BEFORE UPDATE ON list_entries FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,26 @@
+-- List entries submitted through POST /v1/lists/{list_type}/import, imported in the
+-- background. The submitted entries are kept only until the import finishes
+CREATE TABLE list_imports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    list_type VARCHAR(20) NOT NULL CHECK (list_type IN ('blocklist', 'allowlist', 'watchlist')),
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'processing', 'completed')),
+    entries JSONB,
+    total_entries INTEGER NOT NULL,
+    processed_entries INTEGER NOT NULL DEFAULT 0,
+    created_entries INTEGER NOT NULL DEFAULT 0,
+    updated_entries INTEGER NOT NULL DEFAULT 0,
+    failed_entries INTEGER NOT NULL DEFAULT 0,
+    -- The first failures, as {index, message} objects
+    errors JSONB NOT NULL DEFAULT '[]',
+    -- Refreshed as each chunk is imported; an import left processing without a heartbeat is
+    -- picked up again where it stopped
+    heartbeat_at TIMESTAMP WITH TIME ZONE,
+    started_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_list_imports_unfinished ON list_imports(created_at) WHERE status <> 'completed';
+CREATE INDEX idx_list_imports_account_id ON list_imports(account_id);
@@ -0,0 +1,24 @@
+-- Automatic, time-limited blocklisting of IP addresses, devices, and cards behind repeated
+-- critical velocity breaches. Accounts opt in through PATCH /v1/account
+ALTER TABLE accounts
+    ADD COLUMN auto_block_enabled BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN auto_block_ttl_minutes INTEGER NOT NULL DEFAULT 60
+        CHECK (auto_block_ttl_minutes BETWEEN 5 AND 43200);
+
+-- Audit trail of the blocks added, kept after the entries themselves expire or are removed
+CREATE TABLE auto_blocks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    list_entry_id UUID REFERENCES list_entries(id) ON DELETE SET NULL,
+    entity_type VARCHAR(20) NOT NULL CHECK (entity_type IN ('ip', 'device', 'card_hash')),
+    value VARCHAR(255) NOT NULL,
+    -- Critical velocity factors of the transaction that triggered the block
+    factor_codes TEXT[] NOT NULL,
+    -- Transactions of the entity with a critical velocity factor within the window
+    breaches INTEGER NOT NULL,
+    transaction_id UUID REFERENCES transactions(id) ON DELETE SET NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_auto_blocks_account_id ON auto_blocks(account_id, created_at DESC);
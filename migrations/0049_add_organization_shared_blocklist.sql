@@ -0,0 +1,4 @@
+-- Organizations may share the blocklist entries of each member account with every other
+-- active member, as when several brands refuse the same fraudsters. Members keep their own
+-- entries; the others see them as entries added by that account.
+ALTER TABLE organizations ADD COLUMN share_blocklist BOOLEAN NOT NULL DEFAULT false;
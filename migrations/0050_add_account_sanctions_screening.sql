@@ -0,0 +1,2 @@
+-- Accounts opt into having the names on their transactions screened against sanctions lists
+ALTER TABLE accounts ADD COLUMN sanctions_screening BOOLEAN NOT NULL DEFAULT false;
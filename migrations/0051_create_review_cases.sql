@@ -0,0 +1,32 @@
+-- Manual review cases, opened for each transaction given the review disposition. A reviewer
+-- claims a case, annotates it, and resolves it by approving or declining the transaction
+CREATE TABLE review_cases (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    transaction_id UUID NOT NULL UNIQUE REFERENCES transactions(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'claimed', 'resolved')),
+    claimed_by VARCHAR(255),
+    claimed_at TIMESTAMP WITH TIME ZONE,
+    decision VARCHAR(20) CHECK (decision IN ('approve', 'decline')),
+    decision_reason TEXT,
+    resolved_by VARCHAR(255),
+    resolved_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((status = 'resolved') = (decision IS NOT NULL))
+);
+
+CREATE INDEX idx_review_cases_account_status ON review_cases(account_id, status, created_at);
+
+CREATE TRIGGER update_review_cases_updated_at BEFORE UPDATE ON review_cases FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Remarks reviewers leave on a case, in the order they were added
+CREATE TABLE review_case_annotations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    case_id UUID NOT NULL REFERENCES review_cases(id) ON DELETE CASCADE,
+    author VARCHAR(255) NOT NULL,
+    note TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_review_case_annotations_case_id ON review_case_annotations(case_id, created_at);
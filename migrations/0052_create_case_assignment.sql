@@ -0,0 +1,36 @@
+-- How new review cases reach reviewers: left for a reviewer to claim, handed to the reviewer
+-- assigned least recently, or handed to the reviewer holding the fewest cases
+ALTER TABLE accounts ADD COLUMN case_assignment VARCHAR(20) NOT NULL DEFAULT 'manual'
+    CHECK (case_assignment IN ('manual', 'round_robin', 'least_loaded'));
+
+-- Reviewers an account's cases can be assigned to
+CREATE TABLE case_reviewers (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    reviewer VARCHAR(255) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT true,
+    last_assigned_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, reviewer)
+);
+
+CREATE INDEX idx_review_cases_claimed_by ON review_cases(account_id, claimed_by) WHERE status = 'claimed';
+
+CREATE TRIGGER update_case_reviewers_updated_at BEFORE UPDATE ON case_reviewers FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Audit log of everything done to a case: who acted, if anyone, and who held the case after
+CREATE TABLE case_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    case_id UUID NOT NULL REFERENCES review_cases(id) ON DELETE CASCADE,
+    action VARCHAR(20) NOT NULL
+        CHECK (action IN ('opened', 'assigned', 'reassigned', 'claimed', 'annotated', 'resolved')),
+    actor VARCHAR(255),
+    reviewer VARCHAR(255),
+    -- The clock rather than the transaction start, so events of one transaction keep their order
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT clock_timestamp()
+);
+
+CREATE INDEX idx_case_events_case_id ON case_events(case_id, created_at);
+
+INSERT INTO case_events (case_id, action, created_at)
+SELECT id, 'opened', created_at FROM review_cases;
@@ -0,0 +1,16 @@
+-- Links reviewers attach to a case, such as a support ticket, a chargeback document, or a
+-- search of the customer's order history
+CREATE TABLE case_references (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    case_id UUID NOT NULL REFERENCES review_cases(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    label VARCHAR(255),
+    added_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_case_references_case_id ON case_references(case_id, created_at);
+
+ALTER TABLE case_events DROP CONSTRAINT case_events_action_check;
+ALTER TABLE case_events ADD CONSTRAINT case_events_action_check
+    CHECK (action IN ('opened', 'assigned', 'reassigned', 'claimed', 'annotated', 'referenced', 'resolved'));
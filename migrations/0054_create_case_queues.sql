@@ -0,0 +1,31 @@
+-- Named queues an account's review cases are routed into. A new case joins the first queue, by
+-- priority, whose conditions its transaction meets; empty conditions match anything
+CREATE TABLE case_queues (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    name VARCHAR(64) NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0,
+    -- Minutes a case may wait in the queue before it is overdue
+    sla_minutes INTEGER NOT NULL CHECK (sla_minutes > 0),
+    -- Reviewers the queue's cases are assigned to; empty for any of the account's reviewers
+    assignees TEXT[] NOT NULL DEFAULT '{}',
+    event_types VARCHAR(30)[] NOT NULL DEFAULT '{}',
+    -- Order amount bounds, in the order's own currency
+    min_amount DOUBLE PRECISION,
+    max_amount DOUBLE PRECISION,
+    -- Risk factor codes of which at least one must have fired
+    rule_codes TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (account_id, name)
+);
+
+CREATE INDEX idx_case_queues_account_id ON case_queues(account_id, priority);
+
+CREATE TRIGGER update_case_queues_updated_at BEFORE UPDATE ON case_queues FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE review_cases
+    ADD COLUMN queue_id UUID REFERENCES case_queues(id) ON DELETE SET NULL,
+    ADD COLUMN due_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_review_cases_queue_id ON review_cases(queue_id) WHERE queue_id IS NOT NULL;
@@ -0,0 +1,12 @@
+-- Appeals: second looks merchants ask for at rejected transactions. An appeal is a case of its
+-- own next to any review case of the same transaction, carrying the scoring being appealed as
+-- it stood when the appeal was made
+ALTER TABLE review_cases
+    ADD COLUMN kind VARCHAR(20) NOT NULL DEFAULT 'review' CHECK (kind IN ('review', 'appeal')),
+    ADD COLUMN appealed_by VARCHAR(255),
+    ADD COLUMN appeal_reason TEXT,
+    ADD COLUMN scoring_snapshot JSONB,
+    ADD CHECK ((kind = 'appeal') = (scoring_snapshot IS NOT NULL));
+
+ALTER TABLE review_cases DROP CONSTRAINT review_cases_transaction_id_key;
+ALTER TABLE review_cases ADD CONSTRAINT review_cases_transaction_id_kind_key UNIQUE (transaction_id, kind);
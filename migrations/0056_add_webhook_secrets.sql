@@ -0,0 +1,18 @@
+-- Deliveries are signed with the webhook's secret itself, so a hash of it is no use. Any
+-- webhook stored before now gets a fresh random secret, to be rotated before it is relied on
+ALTER TABLE webhooks
+    DROP COLUMN secret_hash,
+    ADD COLUMN secret TEXT NOT NULL
+        DEFAULT 'whsec_' || encode(sha256(gen_random_uuid()::text::bytea), 'hex'),
+    -- Secret replaced by the last rotation, still signed with until it expires
+    ADD COLUMN previous_secret TEXT,
+    ADD COLUMN previous_secret_expires_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN secret_rotated_at TIMESTAMP WITH TIME ZONE,
+    ADD CONSTRAINT webhooks_previous_secret_check
+        CHECK ((previous_secret IS NULL) = (previous_secret_expires_at IS NULL));
+
+ALTER TABLE webhooks ALTER COLUMN secret DROP DEFAULT;
+
+-- Counting deliveries does not change the webhook
+DROP TRIGGER update_webhooks_updated_at ON webhooks;
+CREATE TRIGGER update_webhooks_updated_at BEFORE UPDATE OF url, description, events, is_active, secret ON webhooks FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,20 @@
+-- Every attempt at delivering an event to a webhook, whether by the dispatcher or redriven by
+-- the account
+CREATE TABLE webhook_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    webhook_id UUID NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    event_id UUID NOT NULL REFERENCES outbox_events(id) ON DELETE CASCADE,
+    event_type VARCHAR(100) NOT NULL,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('succeeded', 'failed')),
+    -- HTTP status the endpoint answered with; NULL when it could not be reached
+    response_status INTEGER,
+    latency_ms INTEGER NOT NULL,
+    error TEXT,
+    -- Delivery this one redrove, if the account asked for it again
+    redrive_of UUID REFERENCES webhook_deliveries(id) ON DELETE SET NULL,
+    attempted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_webhook_deliveries_webhook_id ON webhook_deliveries(webhook_id, attempted_at DESC);
+CREATE INDEX idx_webhook_deliveries_event_id ON webhook_deliveries(event_id);
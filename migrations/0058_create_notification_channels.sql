@@ -0,0 +1,28 @@
+-- Built-in channels an account's high-severity events are announced on: an email address, or
+-- a Slack incoming webhook
+CREATE TABLE notification_channels (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    name VARCHAR(64) NOT NULL,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('email', 'slack')),
+    -- Email address, or Slack incoming webhook URL
+    target TEXT NOT NULL,
+    -- Event types announced on the channel; empty for every notifiable event
+    events JSONB NOT NULL DEFAULT '[]',
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    last_notified_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (account_id, name)
+);
+
+CREATE TRIGGER update_notification_channels_updated_at BEFORE UPDATE OF name, kind, target, events, is_active ON notification_channels FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Billing cycle, by its first day, the account was last told its quota ran out in
+ALTER TABLE accounts ADD COLUMN quota_exhausted_for DATE;
+
+-- When the case was flagged as having outlived its queue's SLA
+ALTER TABLE review_cases ADD COLUMN sla_breached_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_review_cases_due_at ON review_cases(due_at)
+    WHERE due_at IS NOT NULL AND sla_breached_at IS NULL AND status <> 'resolved';
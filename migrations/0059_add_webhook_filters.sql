@@ -0,0 +1,6 @@
+-- Conditions an event's payload must meet to be delivered to a webhook, on top of its type;
+-- empty conditions match any event
+ALTER TABLE webhooks ADD COLUMN filters JSONB NOT NULL DEFAULT '{}';
+
+DROP TRIGGER update_webhooks_updated_at ON webhooks;
+CREATE TRIGGER update_webhooks_updated_at BEFORE UPDATE OF url, description, events, filters, is_active, secret ON webhooks FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
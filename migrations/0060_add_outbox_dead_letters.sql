@@ -0,0 +1,9 @@
+-- Events the dispatcher gave up on after their last attempt, kept until they are redriven or
+-- purged. Events abandoned before now are dead-lettered as of their last attempt
+ALTER TABLE outbox_events ADD COLUMN dead_lettered_at TIMESTAMP WITH TIME ZONE;
+
+UPDATE outbox_events
+SET dead_lettered_at = available_at
+WHERE published_at IS NULL AND attempts >= 10;
+
+CREATE INDEX idx_outbox_events_dead_lettered ON outbox_events(account_id, dead_lettered_at DESC) WHERE dead_lettered_at IS NOT NULL;
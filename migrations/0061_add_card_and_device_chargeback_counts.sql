@@ -0,0 +1,26 @@
+-- Chargebacks reported for transactions paid with a card or sent from a device, kept like
+-- users' chargeback counts. Counts start from the chargebacks already on record
+ALTER TABLE credit_cards ADD COLUMN chargeback_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE devices ADD COLUMN chargeback_count INTEGER NOT NULL DEFAULT 0;
+
+UPDATE credit_cards c
+SET chargeback_count = counts.chargebacks
+FROM (
+    SELECT tc.credit_card_id, COUNT(*) AS chargebacks
+    FROM transaction_credit_cards tc
+    JOIN transaction_reports r ON r.transaction_id = tc.transaction_id
+    WHERE r.tag = 'chargeback'
+    GROUP BY tc.credit_card_id
+) counts
+WHERE c.id = counts.credit_card_id;
+
+UPDATE devices d
+SET chargeback_count = counts.chargebacks
+FROM (
+    SELECT td.device_id, COUNT(*) AS chargebacks
+    FROM transaction_devices td
+    JOIN transaction_reports r ON r.transaction_id = td.transaction_id
+    WHERE r.tag = 'chargeback'
+    GROUP BY td.device_id
+) counts
+WHERE d.id = counts.device_id;
@@ -0,0 +1,28 @@
+-- Versions of an account's rule weights, each scaling the scores of the rules it names. The
+-- latest version is in force; an account without one scores with the built-in weights
+CREATE TABLE rule_versions (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL CHECK (version > 0),
+    weights JSONB NOT NULL DEFAULT '{}',
+    suggestion_id UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, version)
+);
+
+-- Rule weights suggested by how often each rule fired on transactions later reported as fraud.
+-- Pending suggestions are replaced each time they are recomputed; applied ones are kept
+CREATE TABLE rule_suggestions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    rule_code VARCHAR(100) NOT NULL,
+    current_weight DOUBLE PRECISION NOT NULL,
+    suggested_weight DOUBLE PRECISION NOT NULL CHECK (suggested_weight >= 0),
+    reported_count INTEGER NOT NULL,
+    fraud_count INTEGER NOT NULL,
+    baseline_precision DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    applied_version INTEGER,
+    applied_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE UNIQUE INDEX idx_rule_suggestions_pending ON rule_suggestions(account_id, rule_code) WHERE applied_at IS NULL;
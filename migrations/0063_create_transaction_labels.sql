@@ -0,0 +1,46 @@
+-- Ground-truth labels of transactions for model training, derived from the outcome on record
+-- or set by hand. Manual labels are never overwritten by derived ones
+CREATE TABLE transaction_labels (
+    transaction_id UUID PRIMARY KEY REFERENCES transactions(id) ON DELETE CASCADE,
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    label VARCHAR(20) NOT NULL CHECK (label IN ('fraud', 'legit', 'friendly_fraud', 'unknown')),
+    source VARCHAR(20) NOT NULL CHECK (source IN ('report', 'case', 'manual')),
+    notes TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_transaction_labels_account_updated ON transaction_labels(account_id, updated_at DESC);
+
+CREATE TRIGGER update_transaction_labels_updated_at BEFORE UPDATE ON transaction_labels FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Label the outcomes on record. Chargebacks filed under a reason code other than fraud are
+-- friendly fraud; outcomes matching a resolved case's decision came from the reviewer
+INSERT INTO transaction_labels (transaction_id, account_id, label, source, created_at, updated_at)
+SELECT r.transaction_id,
+       t.account_id,
+       CASE
+           WHEN r.tag = 'not_fraud' THEN 'legit'
+           WHEN r.tag = 'chargeback'
+                AND r.chargeback_code IS NOT NULL
+                AND upper(r.chargeback_code) NOT IN (
+                    '10.1', '10.2', '10.3', '10.4', '10.5', '4837', '4840', '4849', '4863',
+                    '4870', '4871', 'F24', 'F29', 'FR2', 'FR4', 'FR6', 'UA01', 'UA02', 'UA05'
+                )
+               THEN 'friendly_fraud'
+           ELSE 'fraud'
+       END,
+       CASE
+           WHEN EXISTS (
+               SELECT 1
+               FROM review_cases c
+               WHERE c.transaction_id = r.transaction_id
+                 AND c.status = 'resolved'
+                 AND (c.decision, r.tag) IN (('approve', 'not_fraud'), ('decline', 'suspected_fraud'))
+           ) THEN 'case'
+           ELSE 'report'
+       END,
+       r.created_at,
+       r.updated_at
+FROM transaction_reports r
+JOIN transactions t ON t.id = r.transaction_id;
@@ -0,0 +1,24 @@
+-- Disputes payment processors report, tracked from receipt through representment to the
+-- outcome. Amounts are in major units of the disputed currency
+CREATE TABLE disputes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    processor VARCHAR(20) NOT NULL CHECK (processor IN ('stripe', 'adyen')),
+    -- Processor's ID for the dispute
+    reference VARCHAR(255) NOT NULL,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('received', 'representment', 'won', 'lost')),
+    amount DOUBLE PRECISION,
+    currency VARCHAR(3),
+    reason_code VARCHAR(32),
+    received_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    representment_at TIMESTAMP WITH TIME ZONE,
+    resolved_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (account_id, processor, reference)
+);
+
+CREATE INDEX idx_disputes_transaction ON disputes(transaction_id, received_at);
+
+CREATE TRIGGER update_disputes_updated_at BEFORE UPDATE ON disputes FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,11 @@
+-- Refunds and cancellations: events that undo an earlier purchase, linked to the transaction
+-- they undo when it was scored
+ALTER TABLE transactions DROP CONSTRAINT transactions_event_type_check;
+ALTER TABLE transactions ADD CONSTRAINT transactions_event_type_check
+    CHECK (event_type IN ('account_creation', 'account_login', 'email_change', 'password_reset', 'payout_change', 'purchase', 'recurring_purchase', 'referral', 'survey', 'refund', 'cancellation'));
+
+ALTER TABLE transactions
+    ADD COLUMN original_transaction_id UUID REFERENCES transactions(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_transactions_original_transaction_id ON transactions(original_transaction_id)
+    WHERE original_transaction_id IS NOT NULL;
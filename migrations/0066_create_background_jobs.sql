@@ -0,0 +1,26 @@
+-- Long-running work queued by accounts, such as rescoring many transactions, run by the
+-- background job workers. A running job's worker refreshes `heartbeat_at` as it records
+-- progress in `result`; once the heartbeat is stale another worker resumes the job
+CREATE TABLE background_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    kind VARCHAR(50) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'running', 'completed', 'failed')),
+    payload JSONB NOT NULL,
+    -- Output so far, in the kind's own shape; final once the job has completed
+    result JSONB,
+    -- Error response of a failed job
+    error JSONB,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    available_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    heartbeat_at TIMESTAMP WITH TIME ZONE,
+    started_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_background_jobs_pending ON background_jobs(available_at) WHERE status = 'pending';
+CREATE INDEX idx_background_jobs_running ON background_jobs(heartbeat_at) WHERE status = 'running';
+CREATE INDEX idx_background_jobs_account_id ON background_jobs(account_id);
@@ -0,0 +1,8 @@
+-- Files written by background jobs, such as the results of batch scoring jobs. Each chunk is
+-- recorded with the job's progress, so a resumed job appends after the last recorded chunk
+CREATE TABLE background_job_output (
+    job_id UUID NOT NULL REFERENCES background_jobs(id) ON DELETE CASCADE,
+    chunk INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    PRIMARY KEY (job_id, chunk)
+);
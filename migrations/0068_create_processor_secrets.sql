@@ -0,0 +1,12 @@
+-- Secrets payment processors sign an account's webhooks with: Stripe's endpoint secret, or
+-- Adyen's hex HMAC key
+CREATE TABLE processor_secrets (
+    account_id UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    processor VARCHAR(20) NOT NULL CHECK (processor IN ('stripe', 'adyen')),
+    secret TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, processor)
+);
+
+CREATE TRIGGER update_processor_secrets_updated_at BEFORE UPDATE ON processor_secrets FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
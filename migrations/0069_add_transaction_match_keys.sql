@@ -0,0 +1,20 @@
+-- Hashes each transaction's email address and card are matched on against the account's lists
+-- and email history, so rescoring can match them again: the request kept for rescoring only
+-- holds them hashed once more. Transactions stored before are filled in from the email address
+-- and card they were linked to.
+ALTER TABLE transactions
+    ADD COLUMN email_hash VARCHAR(64),
+    ADD COLUMN mailbox_hash VARCHAR(64),
+    ADD COLUMN card_hash VARCHAR(64);
+
+UPDATE transactions t
+SET email_hash = e.email_hash, mailbox_hash = e.mailbox_hash
+FROM transaction_emails te
+JOIN email_addresses e ON e.id = te.email_id
+WHERE te.transaction_id = t.id;
+
+UPDATE transactions t
+SET card_hash = c.token_hash
+FROM transaction_credit_cards tc
+JOIN credit_cards c ON c.id = tc.credit_card_id
+WHERE tc.transaction_id = t.id;
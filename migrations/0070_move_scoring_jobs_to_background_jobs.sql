@@ -0,0 +1,33 @@
+-- Transactions submitted with mode=async are scored by the background job workers as jobs of
+-- the `score` kind, whose payload is the request, so every job is followed at /v1/jobs/{id}.
+-- Like the request, the payload is only kept until the job finishes
+ALTER TABLE background_jobs ADD COLUMN callback_url TEXT;
+
+-- Queued scoring jobs keep their IDs. Completed ones carry the transaction they stored as
+-- their result, as the workers record it
+INSERT INTO background_jobs (id, account_id, kind, status, payload, result, error, attempts,
+                             last_error, available_at, completed_at, created_at, callback_url)
+SELECT j.id, j.account_id, 'score', j.status, COALESCE(j.request, '{}'::jsonb),
+       CASE WHEN t.id IS NOT NULL THEN jsonb_build_object('transaction', jsonb_strip_nulls(
+           jsonb_build_object(
+               'id', t.id,
+               'user_id', t.user_id,
+               'external_transaction_id', t.external_transaction_id,
+               'original_transaction_id', t.original_transaction_id,
+               'risk_score', t.risk_score,
+               'risk_level', t.risk_level,
+               'disposition', t.disposition,
+               'event_type', t.event_type,
+               'created_at', t.created_at,
+               'warnings', NULLIF(t.warnings, '[]'::jsonb),
+               '_links', jsonb_build_object(
+                   'self', jsonb_build_object('href', '/v1/transactions/' || t.id)
+               )
+           )
+       )) END,
+       j.error, j.attempts, j.last_error, j.available_at, j.completed_at, j.created_at,
+       j.callback_url
+FROM scoring_jobs j
+LEFT JOIN transactions t ON t.id = j.transaction_id;
+
+DROP TABLE scoring_jobs;
@@ -0,0 +1,398 @@
+//! Hourly anomaly detection on fraud metrics
+//!
+//! Every few minutes the job takes the last complete hour for each account and compares its
+//! reject rate, average risk score, and per-rule hit rates with the same metrics over the
+//! preceding baseline window. A metric is flagged when it sits more than
+//! `anomaly_z_threshold` standard deviations above its baseline mean. Only spikes are
+//! flagged: a sudden drop in fraud is not an incident.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Duration as ChronoDuration, DurationRound, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::{
+    config::AnalyticsConfig,
+    database::{
+        Tenant,
+        clickhouse::{ClickHouseClient, datetime_param},
+        repositories::{AccountRepo, AnomalyRepo, NewAnomaly, OutboxRepo},
+    },
+    models::analytics::AnomalyMetric,
+    outbox::ANOMALY_DETECTED,
+};
+
+/// Fewest qualifying baseline hours a metric needs before it can be judged
+const MIN_BASELINE_HOURS: usize = 24;
+
+/// Floor on the baseline standard deviation of rates, so a perfectly flat baseline does not
+/// turn any change at all into an anomaly
+const MIN_RATE_STDDEV: f64 = 0.01;
+
+/// Floor on the baseline standard deviation of the average risk score
+const MIN_SCORE_STDDEV: f64 = 1.0;
+
+#[derive(Debug, Deserialize)]
+struct HourlyRow {
+    account_id: Uuid,
+    hour: DateTime<Utc>,
+    transactions: u64,
+    rejected: u64,
+    risk_score_sum: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleHitRow {
+    account_id: Uuid,
+    hour: DateTime<Utc>,
+    rule_code: String,
+    hits: u64,
+}
+
+/// Aggregates for one account and hour
+#[derive(Debug, Clone, Default)]
+struct HourStats {
+    transactions: u64,
+    rejected: u64,
+    risk_score_sum: f64,
+    rule_hits: HashMap<String, u64>,
+}
+
+impl HourStats {
+    fn value(&self, metric: AnomalyMetric, rule_code: Option<&str>) -> f64 {
+        let transactions = self.transactions.max(1) as f64;
+        match metric {
+            AnomalyMetric::RejectRate => self.rejected as f64 / transactions,
+            AnomalyMetric::AverageRiskScore => self.risk_score_sum / transactions,
+            AnomalyMetric::RuleHitRate => {
+                let hits = rule_code
+                    .and_then(|code| self.rule_hits.get(code))
+                    .copied()
+                    .unwrap_or(0);
+                hits as f64 / transactions
+            },
+        }
+    }
+}
+
+/// Per-account hourly aggregates, keyed by the start of each hour
+type History = HashMap<Uuid, BTreeMap<DateTime<Utc>, HourStats>>;
+
+/// Spawn a background task that periodically checks for anomalies
+pub fn spawn_anomaly_detection(
+    pool: PgPool,
+    client: ClickHouseClient,
+    config: AnalyticsConfig,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = std::time::Duration::from_secs(config.anomaly_check_interval_seconds);
+        loop {
+            match detect_anomalies(&pool, &client, &config, Utc::now()).await {
+                Ok(0) => {},
+                Ok(recorded) => tracing::info!(recorded, "Anomaly detection flagged new anomalies"),
+                Err(e) => tracing::error!(error = %e, "Anomaly detection failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Check the last complete hour before `now`, returning how many new anomalies were recorded
+///
+/// Anomalies already recorded for the hour are skipped, so running more than once an hour is
+/// harmless.
+pub async fn detect_anomalies(
+    pool: &PgPool,
+    client: &ClickHouseClient,
+    config: &AnalyticsConfig,
+    now: DateTime<Utc>,
+) -> anyhow::Result<usize> {
+    let target = now.duration_trunc(ChronoDuration::hours(1))? - ChronoDuration::hours(1);
+    let start = target - ChronoDuration::hours(i64::from(config.anomaly_baseline_hours));
+    let end = target + ChronoDuration::hours(1);
+    let params = [
+        ("start", datetime_param(start)),
+        ("end", datetime_param(end)),
+    ];
+
+    let hourly: Vec<HourlyRow> = client
+        .query(
+            "SELECT account_id,
+                    toDateTime(toStartOfHour(event_time), 'UTC') AS hour,
+                    count() AS transactions,
+                    countIf(disposition = 'reject') AS rejected,
+                    sum(risk_score) AS risk_score_sum
+             FROM transaction_events FINAL
+             WHERE event_time >= {start:DateTime64(3, 'UTC')}
+               AND event_time < {end:DateTime64(3, 'UTC')}
+             GROUP BY account_id, hour",
+            &params,
+        )
+        .await?;
+    let rule_hits: Vec<RuleHitRow> = client
+        .query(
+            "SELECT account_id,
+                    toDateTime(toStartOfHour(event_time), 'UTC') AS hour,
+                    rule_code,
+                    count() AS hits
+             FROM transaction_events FINAL
+             ARRAY JOIN rule_codes AS rule_code
+             WHERE event_time >= {start:DateTime64(3, 'UTC')}
+               AND event_time < {end:DateTime64(3, 'UTC')}
+             GROUP BY account_id, hour, rule_code",
+            &params,
+        )
+        .await?;
+
+    let anomalies = find_anomalies(&build_history(hourly, rule_hits), target, config);
+
+    let mut recorded = 0;
+    for anomaly in &anomalies {
+        match record_anomaly(pool, anomaly, config.anomaly_alerts_enabled).await {
+            Ok(true) => recorded += 1,
+            Ok(false) => {},
+            Err(e) => tracing::error!(
+                account_id = %anomaly.tenant,
+                metric = ?anomaly.metric,
+                error = %e,
+                "Failed to record anomaly"
+            ),
+        }
+    }
+    Ok(recorded)
+}
+
+/// Store an anomaly and, if alerts are enabled and the account wants them, queue an event for
+/// it in the same transaction
+async fn record_anomaly(pool: &PgPool, anomaly: &NewAnomaly, alert: bool) -> sqlx::Result<bool> {
+    let mut tx = pool.begin().await?;
+    let Some(stored) = AnomalyRepo::insert(&mut *tx, anomaly).await? else {
+        return Ok(false);
+    };
+
+    tracing::warn!(
+        account_id = %anomaly.tenant,
+        metric = ?stored.metric,
+        rule_code = ?stored.rule_code,
+        bucket_start = %stored.bucket_start,
+        observed = stored.observed_value,
+        baseline_mean = stored.baseline_mean,
+        z_score = stored.z_score,
+        "Anomaly detected"
+    );
+    if alert && AccountRepo::notifies_anomalies(&mut *tx, anomaly.tenant.id()).await? {
+        let payload = serde_json::to_value(&stored).unwrap_or_default();
+        OutboxRepo::insert(
+            &mut *tx,
+            anomaly.tenant.id(),
+            ANOMALY_DETECTED,
+            stored.id,
+            payload,
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+fn build_history(hourly: Vec<HourlyRow>, rule_hits: Vec<RuleHitRow>) -> History {
+    let mut history = History::new();
+    for row in hourly {
+        let stats = history
+            .entry(row.account_id)
+            .or_default()
+            .entry(row.hour)
+            .or_default();
+        stats.transactions = row.transactions;
+        stats.rejected = row.rejected;
+        stats.risk_score_sum = row.risk_score_sum;
+    }
+    for row in rule_hits {
+        if let Some(stats) = history
+            .get_mut(&row.account_id)
+            .and_then(|hours| hours.get_mut(&row.hour))
+        {
+            stats.rule_hits.insert(row.rule_code, row.hits);
+        }
+    }
+    history
+}
+
+/// Metrics of the `target` hour that spiked relative to the hours before it
+fn find_anomalies(
+    history: &History,
+    target: DateTime<Utc>,
+    config: &AnalyticsConfig,
+) -> Vec<NewAnomaly> {
+    let min_transactions = config.anomaly_min_transactions;
+    let mut anomalies = Vec::new();
+
+    for (&account_id, hours) in history {
+        let Some(current) = hours
+            .get(&target)
+            .filter(|stats| stats.transactions >= min_transactions)
+        else {
+            continue;
+        };
+        let baseline: Vec<&HourStats> = hours
+            .range(..target)
+            .map(|(_, stats)| stats)
+            .filter(|stats| stats.transactions >= min_transactions)
+            .collect();
+        if baseline.len() < MIN_BASELINE_HOURS {
+            continue;
+        }
+
+        let mut rule_codes: Vec<&String> = current.rule_hits.keys().collect();
+        rule_codes.sort();
+        let metrics = [
+            (AnomalyMetric::RejectRate, None),
+            (AnomalyMetric::AverageRiskScore, None),
+        ]
+        .into_iter()
+        .chain(
+            rule_codes
+                .into_iter()
+                .map(|code| (AnomalyMetric::RuleHitRate, Some(code.as_str()))),
+        );
+
+        for (metric, rule_code) in metrics {
+            let observed = current.value(metric, rule_code);
+            let values: Vec<f64> = baseline
+                .iter()
+                .map(|stats| stats.value(metric, rule_code))
+                .collect();
+            let min_stddev = match metric {
+                AnomalyMetric::AverageRiskScore => MIN_SCORE_STDDEV,
+                AnomalyMetric::RejectRate | AnomalyMetric::RuleHitRate => MIN_RATE_STDDEV,
+            };
+            if let Some(spike) =
+                score_spike(&values, observed, min_stddev, config.anomaly_z_threshold)
+            {
+                anomalies.push(NewAnomaly {
+                    tenant: Tenant::trusted(account_id),
+                    metric,
+                    rule_code: rule_code.map(str::to_string),
+                    bucket_start: target,
+                    observed_value: observed,
+                    baseline_mean: spike.mean,
+                    baseline_stddev: spike.stddev,
+                    z_score: spike.z_score,
+                    transaction_count: current.transactions as i64,
+                });
+            }
+        }
+    }
+
+    anomalies
+}
+
+/// How far an observation sits above its baseline
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Spike {
+    mean: f64,
+    stddev: f64,
+    z_score: f64,
+}
+
+/// Compare `observed` with `baseline`, returning the spike if it clears `threshold`
+fn score_spike(baseline: &[f64], observed: f64, min_stddev: f64, threshold: f64) -> Option<Spike> {
+    if baseline.is_empty() {
+        return None;
+    }
+    let n = baseline.len() as f64;
+    let mean = baseline.iter().sum::<f64>() / n;
+    let variance = baseline.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    let stddev = variance.sqrt().max(min_stddev);
+    let z_score = (observed - mean) / stddev;
+    (z_score >= threshold).then_some(Spike {
+        mean,
+        stddev,
+        z_score,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::config::Config;
+
+    fn hour(offset: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 13, 0, 0, 0).unwrap() + ChronoDuration::hours(offset)
+    }
+
+    fn stats(transactions: u64, rejected: u64, cvv_hits: u64) -> HourStats {
+        HourStats {
+            transactions,
+            rejected,
+            risk_score_sum: transactions as f64 * 10.0,
+            rule_hits: HashMap::from([("CVV_MISMATCH".to_string(), cvv_hits)]),
+        }
+    }
+
+    #[test]
+    fn test_score_spike() {
+        let baseline = [0.04, 0.05, 0.06, 0.05];
+        let spike = score_spike(&baseline, 0.30, MIN_RATE_STDDEV, 3.0).unwrap();
+        assert!((spike.mean - 0.05).abs() < 1e-9);
+        assert_eq!(spike.stddev, MIN_RATE_STDDEV);
+        assert!(spike.z_score > 20.0);
+
+        assert_eq!(score_spike(&baseline, 0.055, MIN_RATE_STDDEV, 3.0), None);
+        // Drops are not anomalies
+        assert_eq!(score_spike(&baseline, 0.0, MIN_RATE_STDDEV, 3.0), None);
+        assert_eq!(score_spike(&[], 0.9, MIN_RATE_STDDEV, 3.0), None);
+    }
+
+    #[test]
+    fn test_find_anomalies_flags_reject_rate_and_rule_spikes() {
+        let config = Config::default().analytics;
+        let account_id = Uuid::new_v4();
+        let target = hour(48);
+
+        let mut hours: BTreeMap<_, _> = (0..48)
+            .map(|h| (hour(h), stats(100, 5 + (h % 3) as u64, 2)))
+            .collect();
+        hours.insert(target, stats(100, 40, 30));
+        let history = History::from([(account_id, hours)]);
+
+        let anomalies = find_anomalies(&history, target, &config);
+        let flagged: Vec<_> = anomalies
+            .iter()
+            .map(|a| (a.metric, a.rule_code.as_deref()))
+            .collect();
+        assert_eq!(
+            flagged,
+            vec![
+                (AnomalyMetric::RejectRate, None),
+                (AnomalyMetric::RuleHitRate, Some("CVV_MISMATCH")),
+            ]
+        );
+        assert!(anomalies.iter().all(|a| a.bucket_start == target));
+        assert!(anomalies.iter().all(|a| a.transaction_count == 100));
+    }
+
+    #[test]
+    fn test_find_anomalies_needs_volume_and_history() {
+        let config = Config::default().analytics;
+        let target = hour(48);
+
+        // Too few transactions in the hour being judged
+        let mut quiet: BTreeMap<_, _> = (0..48).map(|h| (hour(h), stats(100, 5, 2))).collect();
+        quiet.insert(target, stats(5, 5, 5));
+
+        // Not enough baseline hours
+        let mut new_account: BTreeMap<_, _> =
+            (40..48).map(|h| (hour(h), stats(100, 5, 2))).collect();
+        new_account.insert(target, stats(100, 90, 90));
+
+        let history = History::from([(Uuid::new_v4(), quiet), (Uuid::new_v4(), new_account)]);
+        assert!(find_anomalies(&history, target, &config).is_empty());
+    }
+}
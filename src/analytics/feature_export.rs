@@ -0,0 +1,277 @@
+//! Daily Parquet export of scored transactions for offline model training
+//!
+//! Every UTC day that ended at least `delay_minutes` ago is exported once, in order, picking up
+//! after the last exported day. ClickHouse renders the file itself: one row per transaction
+//! with its decision, the entities involved, the feature snapshot unpacked into typed columns,
+//! and the latest outcome reported by the time of export. Outcomes reported afterwards, such
+//! as chargebacks weeks later, are not reflected in files already written.
+
+use std::{path::Path, time::Duration};
+
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, NaiveTime, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use super::reports::PERIOD_FILTER;
+use crate::{
+    config::FeatureExportConfig,
+    database::{
+        clickhouse::{ClickHouseClient, datetime_param},
+        repositories::FeatureExportRepo,
+    },
+    storage::s3::S3Client,
+};
+
+/// How long ClickHouse may take to render one day
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Media type of uploaded files
+const PARQUET_CONTENT_TYPE: &str = "application/vnd.apache.parquet";
+
+/// Transactions of one day, deduplicated, with their latest reported outcome
+const EXPORT_QUERY: &str = "SELECT toString(e.transaction_id) AS transaction_id,
+        toString(e.account_id) AS account_id,
+        toString(e.user_id) AS user_id,
+        e.event_type AS event_type,
+        e.event_time AS event_time,
+        e.created_at AS scored_at,
+        e.risk_score AS risk_score,
+        e.risk_level AS risk_level,
+        e.disposition AS disposition,
+        e.rule_codes AS rule_codes,
+        e.shop_id AS shop_id,
+        e.ip_address AS ip_address,
+        toString(e.device_id) AS device_id,
+        e.email_domain AS email_domain,
+        e.card_bin AS card_bin,
+        JSONExtract(ifNull(e.features, ''), 'order_amount', 'Nullable(Float64)') AS order_amount,
+        JSONExtract(ifNull(e.features, ''), 'order_currency', 'Nullable(String)') AS order_currency,
+        JSONExtract(ifNull(e.features, ''), 'billing_country', 'Nullable(String)') AS billing_country,
+        JSONExtract(ifNull(e.features, ''), 'shipping_country', 'Nullable(String)') AS shipping_country,
+        JSONExtract(ifNull(e.features, ''), 'card_country', 'Nullable(String)') AS card_country,
+        JSONExtract(ifNull(e.features, ''), 'cvv_result', 'Nullable(String)') AS cvv_result,
+        JSONExtract(ifNull(e.features, ''), 'avs_result', 'Nullable(String)') AS avs_result,
+        JSONExtract(ifNull(e.features, ''), 'three_d_secure_successful', 'Nullable(Bool)')
+            AS three_d_secure_successful,
+        JSONExtract(ifNull(e.features, ''), 'has_user_agent', 'Nullable(Bool)') AS has_user_agent,
+        JSONExtract(ifNull(e.features, ''), 'active_user_flags', 'Nullable(UInt32)')
+            AS active_user_flags,
+        JSONExtract(ifNull(e.features, ''), 'device_user_count', 'Nullable(UInt32)')
+            AS device_user_count,
+        JSONExtract(ifNull(e.features, ''), 'device_chargebacks', 'Nullable(Bool)')
+            AS device_chargebacks,
+        JSONExtract(ifNull(e.features, ''), 'device_status', 'Nullable(String)')
+            AS device_status,
+        JSONExtract(ifNull(e.features, ''), 'session_seconds_after_signup', 'Nullable(Float64)')
+            AS session_seconds_after_signup,
+        JSONExtract(ifNull(e.features, ''), 'session_ip_addresses', 'Nullable(UInt32)')
+            AS session_ip_addresses,
+        JSONExtract(ifNull(e.features, ''), 'session_countries', 'Nullable(UInt32)')
+            AS session_countries,
+        JSONExtract(ifNull(e.features, ''), 'ip_subnet_transactions_last_hour', 'Nullable(UInt32)')
+            AS ip_subnet_transactions_last_hour,
+        JSONExtract(ifNull(e.features, ''), 'ip_subnet_users', 'Nullable(UInt32)')
+            AS ip_subnet_users,
+        JSONExtract(ifNull(e.features, ''), 'travel_speed_kmh', 'Nullable(Float64)')
+            AS travel_speed_kmh,
+        JSONExtract(ifNull(e.features, ''), 'ip_country', 'Nullable(String)') AS ip_country,
+        JSONExtract(ifNull(e.features, ''), 'billing_ip_distance_km', 'Nullable(Float64)')
+            AS billing_ip_distance_km,
+        JSONExtract(ifNull(e.features, ''), 'shipping_ip_distance_km', 'Nullable(Float64)')
+            AS shipping_ip_distance_km,
+        JSONExtract(ifNull(e.features, ''), 'local_hour', 'Nullable(UInt8)') AS local_hour,
+        JSONExtract(ifNull(e.features, ''), 'email_free', 'Nullable(Bool)') AS email_free,
+        JSONExtract(ifNull(e.features, ''), 'email_disposable', 'Nullable(Bool)')
+            AS email_disposable,
+        JSONExtract(ifNull(e.features, ''), 'email_variant_users', 'Nullable(UInt32)')
+            AS email_variant_users,
+        JSONExtract(ifNull(e.features, ''), 'email_age_days', 'Nullable(UInt32)')
+            AS email_age_days,
+        JSONExtract(ifNull(e.features, ''), 'email_good_transactions', 'Nullable(UInt32)')
+            AS email_good_transactions,
+        JSONExtract(ifNull(e.features, ''), 'address_transactions_last_hour', 'Nullable(UInt32)')
+            AS address_transactions_last_hour,
+        JSONExtract(ifNull(e.features, ''), 'address_users', 'Nullable(UInt32)') AS address_users,
+        JSONExtract(ifNull(e.features, ''), 'cardholder_billing_name_match', 'Nullable(Float64)')
+            AS cardholder_billing_name_match,
+        JSONExtract(ifNull(e.features, ''), 'cardholder_email_name_match', 'Nullable(Float64)')
+            AS cardholder_email_name_match,
+        JSONExtract(ifNull(e.features, ''), 'phone_country', 'Nullable(String)') AS phone_country,
+        JSONExtract(ifNull(e.features, ''), 'phone_line_type', 'Nullable(String)')
+            AS phone_line_type,
+        JSONExtract(ifNull(e.features, ''), 'card_type', 'Nullable(String)') AS card_type,
+        JSONExtract(ifNull(e.features, ''), 'card_prepaid', 'Nullable(Bool)') AS card_prepaid,
+        JSONExtract(ifNull(e.features, ''), 'card_business', 'Nullable(Bool)') AS card_business,
+        JSONExtract(ifNull(e.features, ''), 'card_virtual', 'Nullable(Bool)') AS card_virtual,
+        JSONExtract(ifNull(e.features, ''), 'card_testing_bins', 'Nullable(UInt32)')
+            AS card_testing_bins,
+        JSONExtract(ifNull(e.features, ''), 'card_testing_declined_small', 'Nullable(UInt32)')
+            AS card_testing_declined_small,
+        JSONExtract(ifNull(e.features, ''), 'card_testing_sequential', 'Nullable(UInt32)')
+            AS card_testing_sequential,
+        nullIf(o.tag, '') AS reported_outcome
+    FROM (
+        SELECT *
+        FROM transaction_events FINAL
+        WHERE {period_filter}
+    ) AS e
+    LEFT JOIN (
+        SELECT transaction_id, argMax(tag, reported_at) AS tag
+        FROM transaction_outcomes
+        WHERE transaction_id IN (
+            SELECT transaction_id FROM transaction_events WHERE {period_filter}
+        )
+        GROUP BY transaction_id
+    ) AS o ON o.transaction_id = e.transaction_id
+    ORDER BY e.account_id, e.event_time, e.transaction_id";
+
+/// Spawn a background task that periodically exports every day that is due
+pub fn spawn_feature_export(
+    pool: PgPool,
+    client: ClickHouseClient,
+    s3: Option<S3Client>,
+    config: FeatureExportConfig,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(config.check_interval_seconds);
+        loop {
+            match export_due_days(&pool, &client, s3.as_ref(), &config, Utc::now()).await {
+                Ok(0) => {},
+                Ok(exported) => tracing::info!(exported, "Feature export days written"),
+                Err(e) => tracing::error!(error = %e, "Feature export failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Export the days due at `now`, oldest first, returning how many were exported
+///
+/// Stops at the first failure so no day is skipped; it is retried on the next run.
+pub async fn export_due_days(
+    pool: &PgPool,
+    client: &ClickHouseClient,
+    s3: Option<&S3Client>,
+    config: &FeatureExportConfig,
+    now: DateTime<Utc>,
+) -> anyhow::Result<usize> {
+    let cutoff = now - ChronoDuration::minutes(config.delay_minutes as i64);
+    let last_exported = FeatureExportRepo::last_exported_day(pool).await?;
+    let days = days_to_export(last_exported, cutoff, config.backfill_days);
+
+    for day in &days {
+        export_day(pool, client, s3, config, *day).await?;
+    }
+    Ok(days.len())
+}
+
+async fn export_day(
+    pool: &PgPool,
+    client: &ClickHouseClient,
+    s3: Option<&S3Client>,
+    config: &FeatureExportConfig,
+    day: NaiveDate,
+) -> anyhow::Result<()> {
+    let start = day.and_time(NaiveTime::MIN).and_utc();
+    let params = [
+        ("start", datetime_param(start)),
+        ("end", datetime_param(start + ChronoDuration::days(1))),
+    ];
+    let parquet = client
+        .query_parquet(
+            &EXPORT_QUERY.replace("{period_filter}", PERIOD_FILTER),
+            &params,
+            EXPORT_TIMEOUT,
+        )
+        .await?;
+
+    let file = partition_path(day);
+    let mut locations = Vec::new();
+    if let Some(directory) = &config.directory {
+        let path = Path::new(directory).join(&file);
+        write_atomically(&path, &parquet).await?;
+        locations.push(path.display().to_string());
+    }
+    if let Some(s3) = s3 {
+        let key = match config.s3_prefix.trim_matches('/') {
+            "" => file,
+            prefix => format!("{prefix}/{file}"),
+        };
+        s3.put_object(&key, parquet.clone(), PARQUET_CONTENT_TYPE)
+            .await?;
+        locations.push(format!("s3://{}/{key}", s3.bucket()));
+    }
+
+    FeatureExportRepo::record(pool, day, &locations, parquet.len() as i64).await?;
+    tracing::debug!(%day, size_bytes = parquet.len(), "Feature export written");
+    Ok(())
+}
+
+/// Write `contents` to `path` via a temporary file, so readers never see a partial file
+async fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let temporary = path.with_extension("parquet.tmp");
+    tokio::fs::write(&temporary, contents).await?;
+    tokio::fs::rename(&temporary, path).await
+}
+
+/// Hive-style path of a day's file, relative to the export root
+fn partition_path(day: NaiveDate) -> String {
+    format!("dt={}/transactions.parquet", day.format("%Y-%m-%d"))
+}
+
+/// Days that ended before `cutoff` and come after the last exported day, oldest first
+///
+/// Without a previous export, only the last `backfill_days` complete days are exported.
+fn days_to_export(
+    last_exported: Option<NaiveDate>,
+    cutoff: DateTime<Utc>,
+    backfill_days: u32,
+) -> Vec<NaiveDate> {
+    let Some(latest) = cutoff.date_naive().pred_opt() else {
+        return Vec::new();
+    };
+    let first = match last_exported {
+        Some(day) => day + ChronoDuration::days(1),
+        None => latest - ChronoDuration::days(i64::from(backfill_days.max(1)) - 1),
+    };
+    first.iter_days().take_while(|day| *day <= latest).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, day).unwrap()
+    }
+
+    #[test]
+    fn test_days_to_export_resumes_after_last_export() {
+        let cutoff = Utc.with_ymd_and_hms(2025, 6, 13, 0, 30, 0).unwrap();
+        assert_eq!(
+            days_to_export(Some(day(9)), cutoff, 7),
+            vec![day(10), day(11), day(12)]
+        );
+        assert!(days_to_export(Some(day(12)), cutoff, 7).is_empty());
+    }
+
+    #[test]
+    fn test_days_to_export_backfills_first_run() {
+        let cutoff = Utc.with_ymd_and_hms(2025, 6, 13, 0, 30, 0).unwrap();
+        assert_eq!(days_to_export(None, cutoff, 2), vec![day(11), day(12)]);
+        assert_eq!(days_to_export(None, cutoff, 0), vec![day(12)]);
+    }
+
+    #[test]
+    fn test_partition_path() {
+        assert_eq!(
+            partition_path(day(13)),
+            "dt=2025-06-13/transactions.parquet"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Live per-second counts of scored transactions
+//!
+//! The scoring path publishes every scored transaction on an in-process broadcast channel;
+//! each dashboard stream subscribes to it and totals its own account's transactions once a
+//! second. Counts are per server instance and nothing is persisted.
+
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Utc};
+use futures_util::{Stream, stream};
+use tokio::{
+    sync::broadcast::{self, Receiver, Sender, error::RecvError},
+    time::{Instant, Interval, MissedTickBehavior},
+};
+use uuid::Uuid;
+
+use crate::models::{analytics::LiveCounts, transaction::Disposition};
+
+/// Scored transactions buffered per subscriber before it starts missing them
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// How often counts are pushed to subscribers
+const TICK: StdDuration = StdDuration::from_secs(1);
+
+/// A transaction as it leaves the scoring path
+#[derive(Debug, Clone, Copy)]
+struct ScoredTransaction {
+    account_id: Uuid,
+    disposition: Disposition,
+}
+
+/// In-process feed of scored transactions
+#[derive(Debug, Clone)]
+pub struct LiveFeed {
+    sender: Sender<ScoredTransaction>,
+}
+
+impl Default for LiveFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveFeed {
+    /// Create a feed with no subscribers
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Announce a scored transaction to current subscribers
+    pub fn publish(&self, account_id: Uuid, disposition: Disposition) {
+        // Sending only fails when nobody is subscribed, in which case there is nothing to do
+        let _ = self.sender.send(ScoredTransaction {
+            account_id,
+            disposition,
+        });
+    }
+
+    /// Stream of the account's counts, one item per second starting a second from now
+    pub fn counts(&self, account_id: Uuid) -> impl Stream<Item = LiveCounts> + use<> {
+        let mut ticker = tokio::time::interval_at(Instant::now() + TICK, TICK);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let subscription = Subscription {
+            receiver: self.sender.subscribe(),
+            ticker,
+            account_id,
+            second: Utc::now(),
+            counts: LiveCounts::default(),
+        };
+        stream::unfold(subscription, |mut subscription| async move {
+            let counts = subscription.next_second().await?;
+            Some((counts, subscription))
+        })
+    }
+}
+
+/// One subscriber's running totals
+struct Subscription {
+    receiver: Receiver<ScoredTransaction>,
+    ticker: Interval,
+    account_id: Uuid,
+    second: DateTime<Utc>,
+    counts: LiveCounts,
+}
+
+impl Subscription {
+    /// Count the account's transactions until the next tick, returning `None` once the feed
+    /// has shut down
+    async fn next_second(&mut self) -> Option<LiveCounts> {
+        loop {
+            tokio::select! {
+                _ = self.ticker.tick() => {
+                    let now = Utc::now();
+                    let counts = LiveCounts {
+                        timestamp: std::mem::replace(&mut self.second, now),
+                        ..std::mem::take(&mut self.counts)
+                    };
+                    return Some(counts);
+                },
+                received = self.receiver.recv() => match received {
+                    Ok(scored) if scored.account_id == self.account_id => {
+                        self.counts.transaction_count += 1;
+                        self.counts.disposition_counts.record(scored.disposition);
+                    },
+                    Ok(_) => {},
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!(missed, "Live stream fell behind; counts are incomplete");
+                    },
+                    Err(RecvError::Closed) => return None,
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::models::analytics::DispositionCounts;
+
+    #[tokio::test]
+    async fn test_counts_only_the_subscribed_account() {
+        let feed = LiveFeed::new();
+        let account_id = Uuid::new_v4();
+        let mut counts = Box::pin(feed.counts(account_id));
+
+        feed.publish(account_id, Disposition::Accept);
+        feed.publish(account_id, Disposition::Accept);
+        feed.publish(account_id, Disposition::Reject);
+        feed.publish(Uuid::new_v4(), Disposition::Review);
+
+        let second = counts.next().await.unwrap();
+        assert_eq!(second.transaction_count, 3);
+        assert_eq!(
+            second.disposition_counts,
+            DispositionCounts {
+                accept: 2,
+                reject: 1,
+                ..DispositionCounts::default()
+            }
+        );
+
+        let next = counts.next().await.unwrap();
+        assert_eq!(next.transaction_count, 0);
+        assert!(next.timestamp > second.timestamp);
+    }
+}
@@ -0,0 +1,6 @@
+//! Background analytics over the ClickHouse event store, and live in-process metrics
+
+pub mod anomalies;
+pub mod feature_export;
+pub mod live;
+pub mod reports;
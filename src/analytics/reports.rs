@@ -0,0 +1,421 @@
+//! Daily and weekly fraud summary reports
+//!
+//! The job looks for the most recent day and week that ended at least
+//! `generation_delay_minutes` ago and stores a report for every account with activity in that
+//! period. Stored reports are never regenerated, so the delay is what gives late events and the
+//! outbox's delivery lag time to reach ClickHouse. When an S3 bucket is configured, reports are
+//! then exported as JSON and CSV; failed exports are retried on the next run.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    config::ReportsConfig,
+    database::{
+        Tenant,
+        clickhouse::{ClickHouseClient, datetime_param},
+        repositories::{NewReport, ReportRecord, ReportRepo, UserRepo},
+    },
+    models::{
+        analytics::{AnalyticsSummary, DispositionCounts, RiskDistribution},
+        report::{Report, ReportContent, ReportFrequency, ReportRule, ReportUser},
+    },
+    scheduler::ScheduledTask,
+    services::analytics_service::{AVERAGE_RISK_SCORE, DISPOSITION_COUNTS, RISK_LEVEL_COUNTS},
+    storage::s3::S3Client,
+};
+
+/// Reports exported per run
+const EXPORT_BATCH_SIZE: i64 = 100;
+
+/// Restricts a query to the reported period, across all accounts
+pub(crate) const PERIOD_FILTER: &str = "event_time >= {start:DateTime64(3, 'UTC')}
+      AND event_time < {end:DateTime64(3, 'UTC')}";
+
+#[derive(Debug, Deserialize)]
+struct AccountSummaryRow {
+    account_id: Uuid,
+    total_transactions: u64,
+    total_users: u64,
+    average_risk_score: f64,
+    #[serde(flatten)]
+    risk_distribution: RiskDistribution,
+    #[serde(flatten)]
+    disposition_counts: DispositionCounts,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleRow {
+    account_id: Uuid,
+    rule_code: String,
+    hits: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserRow {
+    account_id: Uuid,
+    user_id: Uuid,
+    transactions: u64,
+    average_risk_score: f64,
+    max_risk_score: f64,
+    rejected: u64,
+}
+
+/// Task that generates due reports and exports them on `schedule`
+///
+/// Reports already generated are exported even when generating new ones fails.
+pub fn report_generation_task(
+    pool: PgPool,
+    client: ClickHouseClient,
+    s3: Option<S3Client>,
+    config: ReportsConfig,
+) -> ScheduledTask {
+    let schedule = config.schedule.clone();
+    ScheduledTask::new("report_generation", schedule, move || {
+        let (pool, client, s3, config) = (pool.clone(), client.clone(), s3.clone(), config.clone());
+        async move {
+            let generated = generate_reports(&pool, &client, &config, Utc::now()).await;
+            if let Ok(generated @ 1..) = generated {
+                tracing::info!(generated, "Reports generated");
+            }
+            if let Some(s3) = &s3 {
+                let exported = export_reports(&pool, s3, &config.s3_prefix)
+                    .await
+                    .context("report export failed")?;
+                if exported > 0 {
+                    tracing::info!(exported, "Reports exported");
+                }
+            }
+            generated.context("report generation failed")?;
+            Ok(())
+        }
+    })
+}
+
+/// Generate the reports due at `now`, returning how many were stored
+///
+/// Accounts that already have a report for a period are skipped, so running the job more
+/// often than reports fall due is harmless.
+pub async fn generate_reports(
+    pool: &PgPool,
+    client: &ClickHouseClient,
+    config: &ReportsConfig,
+    now: DateTime<Utc>,
+) -> anyhow::Result<usize> {
+    let cutoff = now - ChronoDuration::minutes(config.generation_delay_minutes as i64);
+    let mut generated = 0;
+    for frequency in [ReportFrequency::Daily, ReportFrequency::Weekly] {
+        let end = frequency.period_start(cutoff);
+        let start = end - frequency.duration();
+        generated += generate_period(pool, client, config, frequency, start, end).await?;
+    }
+    Ok(generated)
+}
+
+async fn generate_period(
+    pool: &PgPool,
+    client: &ClickHouseClient,
+    config: &ReportsConfig,
+    frequency: ReportFrequency,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> anyhow::Result<usize> {
+    let params = [
+        ("start", datetime_param(start)),
+        ("end", datetime_param(end)),
+    ];
+    let top_n = config.top_n;
+
+    let summaries: Vec<AccountSummaryRow> = client
+        .query(
+            &format!(
+                "SELECT account_id,
+                        count() AS total_transactions,
+                        uniqExact(user_id) AS total_users,
+                        {AVERAGE_RISK_SCORE},
+                        {RISK_LEVEL_COUNTS},
+                        {DISPOSITION_COUNTS}
+                 FROM transaction_events FINAL
+                 WHERE {PERIOD_FILTER}
+                 GROUP BY account_id"
+            ),
+            &params,
+        )
+        .await?;
+    let reported = ReportRepo::reported_accounts(pool, frequency, start).await?;
+    let summaries: Vec<AccountSummaryRow> = summaries
+        .into_iter()
+        .filter(|row| !reported.contains(&row.account_id))
+        .collect();
+    if summaries.is_empty() {
+        return Ok(0);
+    }
+
+    let rules: Vec<RuleRow> = client
+        .query(
+            &format!(
+                "SELECT account_id, rule_code, count() AS hits
+                 FROM transaction_events FINAL
+                 ARRAY JOIN rule_codes AS rule_code
+                 WHERE {PERIOD_FILTER}
+                 GROUP BY account_id, rule_code
+                 ORDER BY account_id, hits DESC, rule_code
+                 LIMIT {top_n} BY account_id"
+            ),
+            &params,
+        )
+        .await?;
+    let users: Vec<UserRow> = client
+        .query(
+            &format!(
+                "SELECT account_id,
+                        user_id,
+                        count() AS transactions,
+                        round(avg(risk_score), 2) AS average_risk_score,
+                        max(risk_score) AS max_risk_score,
+                        countIf(disposition = 'reject') AS rejected
+                 FROM transaction_events FINAL
+                 WHERE {PERIOD_FILTER} AND user_id IS NOT NULL
+                 GROUP BY account_id, user_id
+                 ORDER BY account_id, average_risk_score DESC, transactions DESC, user_id
+                 LIMIT {top_n} BY account_id"
+            ),
+            &params,
+        )
+        .await?;
+
+    let mut generated = 0;
+    for (account_id, mut content) in build_contents(summaries, rules, users) {
+        match store_report(pool, account_id, frequency, start, end, &mut content).await {
+            Ok(true) => generated += 1,
+            Ok(false) => {},
+            Err(e) => tracing::error!(
+                %account_id,
+                frequency = frequency.as_str(),
+                %start,
+                error = %e,
+                "Failed to store report"
+            ),
+        }
+    }
+    Ok(generated)
+}
+
+/// Attach external user IDs to the top users and store the report
+async fn store_report(
+    pool: &PgPool,
+    account_id: Uuid,
+    frequency: ReportFrequency,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    content: &mut ReportContent,
+) -> sqlx::Result<bool> {
+    let tenant = Tenant::trusted(account_id);
+    let user_ids: Vec<Uuid> = content.top_risky_users.iter().map(|u| u.user_id).collect();
+    let external_ids: HashMap<Uuid, Option<String>> =
+        UserRepo::external_ids(pool, tenant, &user_ids)
+            .await?
+            .into_iter()
+            .collect();
+    for user in &mut content.top_risky_users {
+        user.external_user_id = external_ids.get(&user.user_id).cloned().flatten();
+    }
+
+    let stored = ReportRepo::insert(
+        pool,
+        NewReport {
+            tenant,
+            frequency,
+            period_start: start,
+            period_end: end,
+            content,
+        },
+    )
+    .await?;
+    Ok(stored.is_some())
+}
+
+/// Assemble one report per account from the per-account query results
+fn build_contents(
+    summaries: Vec<AccountSummaryRow>,
+    rules: Vec<RuleRow>,
+    users: Vec<UserRow>,
+) -> HashMap<Uuid, ReportContent> {
+    let mut contents: HashMap<Uuid, ReportContent> = summaries
+        .into_iter()
+        .map(|row| {
+            let summary = AnalyticsSummary {
+                total_transactions: row.total_transactions,
+                total_users: row.total_users,
+                average_risk_score: row.average_risk_score,
+                risk_distribution: row.risk_distribution,
+                disposition_counts: row.disposition_counts,
+            };
+            (
+                row.account_id,
+                ReportContent {
+                    summary,
+                    ..ReportContent::default()
+                },
+            )
+        })
+        .collect();
+
+    for row in rules {
+        if let Some(content) = contents.get_mut(&row.account_id) {
+            let transactions = content.summary.total_transactions.max(1) as f64;
+            content.top_rules.push(ReportRule {
+                hit_rate: (row.hits as f64 / transactions * 10_000.0).round() / 10_000.0,
+                rule_code: row.rule_code,
+                hits: row.hits,
+            });
+        }
+    }
+    for row in users {
+        if let Some(content) = contents.get_mut(&row.account_id) {
+            content.top_risky_users.push(ReportUser {
+                user_id: row.user_id,
+                external_user_id: None,
+                transactions: row.transactions,
+                average_risk_score: row.average_risk_score,
+                max_risk_score: row.max_risk_score,
+                rejected: row.rejected,
+            });
+        }
+    }
+
+    contents
+}
+
+/// Push reports that have not been exported yet to S3, returning how many were exported
+///
+/// Stops at the first failure, which is usually a configuration or connectivity problem that
+/// would fail every other upload as well.
+pub async fn export_reports(pool: &PgPool, s3: &S3Client, prefix: &str) -> anyhow::Result<usize> {
+    let pending = ReportRepo::pending_export(pool, EXPORT_BATCH_SIZE).await?;
+    let mut exported = 0;
+    for record in pending {
+        let key = export_key(prefix, &record);
+        let report_id = record.id;
+        let report = Report::from(record);
+
+        s3.put_object(
+            &format!("{key}.json"),
+            serde_json::to_vec_pretty(&report)?,
+            "application/json",
+        )
+        .await?;
+        s3.put_object(
+            &format!("{key}.csv"),
+            report.to_csv().into_bytes(),
+            "text/csv",
+        )
+        .await?;
+
+        let location = format!("s3://{}/{key}", s3.bucket());
+        ReportRepo::mark_exported(pool, report_id, &location).await?;
+        exported += 1;
+    }
+    Ok(exported)
+}
+
+/// Object key of an exported report, without extension
+fn export_key(prefix: &str, record: &ReportRecord) -> String {
+    let name = format!(
+        "{}/{}/{}",
+        record.account_id,
+        record.frequency.as_str(),
+        record.period_start.format("%Y-%m-%d")
+    );
+    match prefix.trim_matches('/') {
+        "" => name,
+        prefix => format!("{prefix}/{name}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use sqlx::types::Json;
+
+    use super::*;
+
+    fn summary(account_id: Uuid, total_transactions: u64) -> AccountSummaryRow {
+        AccountSummaryRow {
+            account_id,
+            total_transactions,
+            total_users: 3,
+            average_risk_score: 20.0,
+            risk_distribution: RiskDistribution::default(),
+            disposition_counts: DispositionCounts::default(),
+        }
+    }
+
+    #[test]
+    fn test_build_contents_groups_by_account() {
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+
+        let contents = build_contents(
+            vec![summary(first, 200), summary(second, 10)],
+            vec![
+                RuleRow {
+                    account_id: first,
+                    rule_code: "CVV_MISMATCH".to_string(),
+                    hits: 30,
+                },
+                RuleRow {
+                    account_id: Uuid::new_v4(),
+                    rule_code: "ORPHAN".to_string(),
+                    hits: 1,
+                },
+            ],
+            vec![UserRow {
+                account_id: second,
+                user_id,
+                transactions: 2,
+                average_risk_score: 75.5,
+                max_risk_score: 80.0,
+                rejected: 2,
+            }],
+        );
+
+        assert_eq!(contents.len(), 2);
+        assert_eq!(contents[&first].top_rules.len(), 1);
+        assert_eq!(contents[&first].top_rules[0].hit_rate, 0.15);
+        assert!(contents[&first].top_risky_users.is_empty());
+        assert!(contents[&second].top_rules.is_empty());
+        assert_eq!(contents[&second].top_risky_users[0].user_id, user_id);
+    }
+
+    #[test]
+    fn test_export_key() {
+        let account_id = Uuid::new_v4();
+        let start = Utc.with_ymd_and_hms(2025, 6, 9, 0, 0, 0).unwrap();
+        let record = ReportRecord {
+            id: Uuid::new_v4(),
+            account_id,
+            frequency: ReportFrequency::Weekly,
+            period_start: start,
+            period_end: start + ChronoDuration::weeks(1),
+            content: Json(ReportContent::default()),
+            export_location: None,
+            created_at: Utc::now(),
+        };
+
+        assert_eq!(
+            export_key("/fraud/reports/", &record),
+            format!("fraud/reports/{account_id}/weekly/2025-06-09")
+        );
+        assert_eq!(
+            export_key("", &record),
+            format!("{account_id}/weekly/2025-06-09")
+        );
+    }
+}
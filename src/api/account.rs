@@ -0,0 +1,194 @@
+//! Account self-service endpoints
+
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+
+use super::{ApiError, ApiResult};
+use crate::{
+    auth::AuthContext,
+    models::account::{
+        Account, AccountStatus, AccountUpdate, StatusChange, UsageHistory, UsageHistoryQuery,
+    },
+    state::AppState,
+};
+
+/// Billing cycles returned when the query does not say
+const DEFAULT_CYCLES: i64 = 3;
+
+/// Most billing cycles returned in one response
+const MAX_CYCLES: i64 = 12;
+
+/// Fetch the calling account
+#[utoipa::path(
+    get,
+    path = "/v1/account",
+    tags = ["Account"],
+    summary = "Get account",
+    description = "Retrieve the calling account's settings, subscription tier, and usage of the current billing cycle. Sandbox keys see their sandbox account.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Account details", body = Account),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_account(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<Account>> {
+    Ok(Json(state.accounts.get_account(auth.tenant()).await?))
+}
+
+/// Update the calling account's settings
+#[utoipa::path(
+    patch,
+    path = "/v1/account",
+    tags = ["Account"],
+    summary = "Update account settings",
+    description = "Change the contact email, disposition policy, notification settings, automatic blocking, sanctions screening, or case assignment. Fields left out keep their current value. The disposition policy applies to transactions scored afterwards. With automatic blocking enabled, an IP address, device, or card whose transactions keep receiving critical velocity factors (`CARD_TESTING`, `IP_VELOCITY`, `IP_MANY_CARDS`) is put on the blocklist for `ttl_minutes`; each block is recorded at `GET /v1/lists/auto-blocks`. With sanctions screening enabled, the billing, shipping, and cardholder names of transactions are screened against the sanctions lists the service downloads, and a match sends the transaction to review with a `SANCTIONS_MATCH` factor. With `case_assignment` set to `round_robin` or `least_loaded`, each new review case is handed to one of the active reviewers registered at `/v1/cases/reviewers`. Changes are announced with an `account.updated` event.",
+    request_body = AccountUpdate,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated account", body = Account),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn update_account(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(update): Json<AccountUpdate>,
+) -> ApiResult<Json<Account>> {
+    Ok(Json(
+        state
+            .accounts
+            .update_account(auth.tenant(), &update)
+            .await?,
+    ))
+}
+
+/// Fetch usage and estimated charges per billing cycle
+#[utoipa::path(
+    get,
+    path = "/v1/account/usage",
+    tags = ["Account"],
+    summary = "Get usage history",
+    description = "Daily metered request counts, overage, and estimated charges for the current and recent billing cycles, newest first, for reconciling invoices. Estimates use list prices; the invoice is authoritative. Counts for the current cycle may lag live traffic by up to the usage sync interval (30 seconds by default).",
+    params(UsageHistoryQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Usage history", body = UsageHistory),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_usage(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<UsageHistoryQuery>,
+) -> ApiResult<Json<UsageHistory>> {
+    let cycles = query.cycles.unwrap_or(DEFAULT_CYCLES);
+    if !(1..=MAX_CYCLES).contains(&cycles) {
+        return Err(ApiError::BadRequest(format!(
+            "cycles must be between 1 and {MAX_CYCLES}"
+        )));
+    }
+    Ok(Json(
+        state.accounts.usage_history(auth.tenant(), cycles).await?,
+    ))
+}
+
+/// Suspend the calling account
+#[utoipa::path(
+    post,
+    path = "/v1/account/suspend",
+    tags = ["Account"],
+    summary = "Suspend account",
+    description = "Stop the account from scoring transactions, for example while investigating a leaked key. API keys keep working for everything else; scoring requests fail with 403 `account_suspended` until the account is reactivated. The change is kept in the audit log and announced with an `account.status_changed` event. The request body is optional.",
+    request_body(content = Option<StatusChange>, description = "Reason for the audit log"),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Suspended account", body = Account),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or belongs to a sandbox account", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The account is not active", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn suspend_account(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    change: Option<Json<StatusChange>>,
+) -> ApiResult<Json<Account>> {
+    change_status(&state, &auth, AccountStatus::Suspended, change).await
+}
+
+/// Close the calling account
+#[utoipa::path(
+    post,
+    path = "/v1/account/close",
+    tags = ["Account"],
+    summary = "Close account",
+    description = "Close the account and schedule it, its sandbox, and all their data for deletion at `deletion_scheduled_at` (30 days by default). Until then only account endpoints remain available, other requests fail with 403 `account_closed`, and the account can be reactivated. An account paying for an organization with other members must move billing first. The change is kept in the audit log and announced with an `account.status_changed` event. The request body is optional.",
+    request_body(content = Option<StatusChange>, description = "Reason for the audit log"),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Closed account", body = Account),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or belongs to a sandbox account", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The account is already closed, or pays for an organization with other members", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn close_account(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    change: Option<Json<StatusChange>>,
+) -> ApiResult<Json<Account>> {
+    change_status(&state, &auth, AccountStatus::Closed, change).await
+}
+
+/// Reactivate the calling account
+#[utoipa::path(
+    post,
+    path = "/v1/account/reactivate",
+    tags = ["Account"],
+    summary = "Reactivate account",
+    description = "Return a suspended or closed account to active use. Reactivating a closed account cancels its scheduled deletion. The change is kept in the audit log and announced with an `account.status_changed` event. The request body is optional.",
+    request_body(content = Option<StatusChange>, description = "Reason for the audit log"),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Reactivated account", body = Account),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or belongs to a sandbox account", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The account is already active", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn reactivate_account(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    change: Option<Json<StatusChange>>,
+) -> ApiResult<Json<Account>> {
+    change_status(&state, &auth, AccountStatus::Active, change).await
+}
+
+async fn change_status(
+    state: &AppState,
+    auth: &AuthContext,
+    to: AccountStatus,
+    change: Option<Json<StatusChange>>,
+) -> ApiResult<Json<Account>> {
+    let change = change.map(|Json(change)| change).unwrap_or_default();
+    Ok(Json(
+        state
+            .accounts
+            .change_status(auth.tenant(), auth.api_key_id, to, &change)
+            .await?,
+    ))
+}
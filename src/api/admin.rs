@@ -0,0 +1,28 @@
+//! Operator endpoints reporting on the server itself
+
+use axum::{Extension, Json};
+
+use crate::{models::scheduler::ScheduledTaskList, scheduler::SchedulerStatus};
+
+/// Status of the recurring tasks run by this server's scheduler
+#[utoipa::path(
+    get,
+    path = "/admin/scheduled-tasks",
+    tags = ["Admin"],
+    summary = "List scheduled tasks",
+    description = "Report the recurring tasks this server runs, such as list refreshes, user risk recalculation, and report generation, with the cron expression each runs on, when it runs next, and how its last run went.\n\nEvery server runs its own scheduler, so the status is that of the server answering the request and resets when it restarts. Requires the operator token configured as `ADMIN_TOKEN`; without one, the endpoint does not exist.",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The server's scheduled tasks, in name order", body = ScheduledTaskList),
+        (status = 401, description = "Missing or wrong admin token", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "No admin token is configured", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_scheduled_tasks(
+    scheduler: Option<Extension<SchedulerStatus>>,
+) -> Json<ScheduledTaskList> {
+    let tasks = scheduler
+        .map(|Extension(scheduler)| scheduler.tasks())
+        .unwrap_or_default();
+    Json(ScheduledTaskList { tasks })
+}
@@ -0,0 +1,309 @@
+//! Analytics endpoints
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::sse::{Event, Sse},
+};
+use chrono::Utc;
+use futures_util::{Stream, StreamExt};
+
+use super::{ApiError, ApiResult};
+use crate::{
+    auth::AuthContext,
+    models::{
+        analytics::{
+            Analytics, AnalyticsQuery, AnomalyList, CohortAnalysis, CohortQuery,
+            ListAnomaliesQuery, LiveCounts, Outcomes, OutcomesQuery, ReviewerQualityQuery,
+            ReviewerQualityReport, ShopAnalytics, ShopAnalyticsQuery, TopEntities,
+            TopEntitiesQuery,
+        },
+        common::Pagination,
+    },
+    services::AnalyticsService,
+    state::AppState,
+};
+
+/// Default page size for anomaly listings
+const DEFAULT_LIMIT: i64 = 20;
+/// Largest page size for anomaly listings
+const MAX_LIMIT: i64 = 100;
+/// Default number of shops in a per-shop breakdown
+const DEFAULT_SHOP_LIMIT: u32 = 50;
+/// Largest number of shops in a per-shop breakdown
+const MAX_SHOP_LIMIT: u32 = 500;
+/// Default number of entities on the risky-entity leaderboard
+const DEFAULT_ENTITY_LIMIT: u32 = 50;
+/// Largest number of entities on the risky-entity leaderboard
+const MAX_ENTITY_LIMIT: u32 = 500;
+/// Default number of calendar months covered by cohort analysis
+const DEFAULT_COHORT_MONTHS: u32 = 6;
+/// Most calendar months cohort analysis may cover
+const MAX_COHORT_MONTHS: u32 = 24;
+
+fn analytics_service(state: &AppState) -> ApiResult<&AnalyticsService> {
+    state.analytics.as_ref().ok_or_else(|| {
+        ApiError::ServiceUnavailable("Analytics is not enabled on this server".to_string())
+    })
+}
+
+/// Get transaction analytics
+#[utoipa::path(
+    get,
+    path = "/v1/analytics",
+    tags = ["Analytics"],
+    summary = "Get transaction analytics",
+    description = "Aggregate the calling account's scored transactions over a recent window: totals, risk and disposition distributions, a gap-free time series, and an optional breakdown by event type, risk level, disposition, or shop. `exposure` totals the accepted purchases per currency, net of the refunds and cancellations that name them in `event.original_transaction_id`. Pass `shop_id` to restrict every figure to a single shop.",
+    params(AnalyticsQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Analytics for the requested window", body = Analytics),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 503, description = "Analytics is disabled or the analytics store is unreachable", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_analytics(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<AnalyticsQuery>,
+) -> ApiResult<Json<Analytics>> {
+    let analytics = analytics_service(&state)?
+        .analytics(auth.tenant(), &query, Utc::now())
+        .await?;
+    Ok(Json(analytics))
+}
+
+/// Get per-shop analytics
+#[utoipa::path(
+    get,
+    path = "/v1/analytics/shops",
+    tags = ["Analytics"],
+    summary = "Get per-shop analytics",
+    description = "Break the calling account's transactions down by `shop_id` so marketplaces can compare sub-merchants: volume, average risk score, risk and disposition distributions, reject and review rates, and the share of transactions later reported as fraud. Transactions without a shop are left out.",
+    params(ShopAnalyticsQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Per-shop figures for the requested window", body = ShopAnalytics),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 503, description = "Analytics is disabled or the analytics store is unreachable", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_shop_analytics(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ShopAnalyticsQuery>,
+) -> ApiResult<Json<ShopAnalytics>> {
+    let limit = query.limit.unwrap_or(DEFAULT_SHOP_LIMIT);
+    if !(1..=MAX_SHOP_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_SHOP_LIMIT}"
+        )));
+    }
+
+    let shops = analytics_service(&state)?
+        .shops(auth.tenant(), &query, limit, Utc::now())
+        .await?;
+    Ok(Json(shops))
+}
+
+/// Get the risky-entity leaderboard
+#[utoipa::path(
+    get,
+    path = "/v1/analytics/top-entities",
+    tags = ["Analytics"],
+    summary = "Get top risky entities",
+    description = "Rank the IP addresses, devices, email domains, or card BINs seen in the calling account's transactions over a recent window by average risk score, with transaction and rule hit counts, to prioritise manual investigation.",
+    params(TopEntitiesQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Riskiest entities in the requested window", body = TopEntities),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 503, description = "Analytics is disabled or the analytics store is unreachable", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_top_entities(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<TopEntitiesQuery>,
+) -> ApiResult<Json<TopEntities>> {
+    let limit = query.limit.unwrap_or(DEFAULT_ENTITY_LIMIT);
+    if !(1..=MAX_ENTITY_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_ENTITY_LIMIT}"
+        )));
+    }
+
+    let entities = analytics_service(&state)?
+        .top_entities(auth.tenant(), &query, limit, Utc::now())
+        .await?;
+    Ok(Json(entities))
+}
+
+/// Stream live transaction counts
+#[utoipa::path(
+    get,
+    path = "/v1/analytics/stream",
+    tags = ["Analytics"],
+    summary = "Stream live transaction counts",
+    description = "Server-Sent Events stream pushing, once a second, the number of transactions scored for the calling account in that second, broken down by disposition. Each message is a `counts` event whose data is a JSON object. Counts cover transactions scored by the server instance the stream is connected to.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Event stream of per-second counts", content_type = "text/event-stream", body = LiveCounts),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn stream_analytics(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let events = state
+        .live
+        .counts(auth.account_id)
+        .map(|counts| Event::default().event("counts").json_data(counts));
+    Sse::new(events)
+}
+
+/// Get cohort analysis
+#[utoipa::path(
+    get,
+    path = "/v1/analytics/cohorts",
+    tags = ["Analytics"],
+    summary = "Get cohort analysis",
+    description = "Group the calling account's users by the month of their first transaction and follow each cohort month by month: active users, transaction volume, average risk score, share of transactions accepted, and chargebacks reported. Covers users who signed up within the requested number of calendar months.",
+    params(CohortQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Cohorts for the requested months", body = CohortAnalysis),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 503, description = "Analytics is disabled or the analytics store is unreachable", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_cohorts(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<CohortQuery>,
+) -> ApiResult<Json<CohortAnalysis>> {
+    let months = query.months.unwrap_or(DEFAULT_COHORT_MONTHS);
+    if !(1..=MAX_COHORT_MONTHS).contains(&months) {
+        return Err(ApiError::BadRequest(format!(
+            "months must be between 1 and {MAX_COHORT_MONTHS}"
+        )));
+    }
+
+    let cohorts = analytics_service(&state)?
+        .cohorts(auth.tenant(), months, Utc::now())
+        .await?;
+    Ok(Json(cohorts))
+}
+
+/// Get outcome metrics
+#[utoipa::path(
+    get,
+    path = "/v1/analytics/outcomes",
+    tags = ["Analytics"],
+    summary = "Get outcome metrics",
+    description = "Compare the calling account's dispositions over a recent window with the outcomes reported for those transactions afterwards: accepted transactions later charged back, rejected volume, review overturn rate, and precision and recall overall and per rule. Disputes received from payment processors in the window are totalled per currency into the amounts won, lost, and still open, and the net loss: the disputed amount not won back.",
+    params(OutcomesQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Outcome metrics for the requested window", body = Outcomes),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 503, description = "Analytics is disabled or the analytics store is unreachable", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_outcomes(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<OutcomesQuery>,
+) -> ApiResult<Json<Outcomes>> {
+    let outcomes = analytics_service(&state)?
+        .outcomes(auth.tenant(), &query, Utc::now())
+        .await?;
+    Ok(Json(outcomes))
+}
+
+/// Get reviewer decision quality
+#[utoipa::path(
+    get,
+    path = "/v1/analytics/reviewers",
+    tags = ["Analytics"],
+    summary = "Get reviewer decision quality",
+    description = "Compare the decisions each of the calling account's reviewers made on review cases resolved over a recent window with the outcomes reported for those transactions afterwards: approvals later charged back, declines later reported as not fraud, and the resulting overturn rates.",
+    params(ReviewerQualityQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Decision quality per reviewer", body = ReviewerQualityReport),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 503, description = "Analytics is disabled or the analytics store is unreachable", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_reviewer_quality(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ReviewerQualityQuery>,
+) -> ApiResult<Json<ReviewerQualityReport>> {
+    let report = analytics_service(&state)?
+        .reviewer_quality(auth.tenant(), &query, Utc::now())
+        .await?;
+    Ok(Json(report))
+}
+
+/// List detected anomalies
+#[utoipa::path(
+    get,
+    path = "/v1/analytics/anomalies",
+    tags = ["Analytics"],
+    summary = "List anomalies",
+    description = "Hours in which the calling account's reject rate, average risk score, or a rule's hit rate spiked well above its recent baseline, most recent first. Anomalies are detected by a background job shortly after each hour ends.",
+    params(ListAnomaliesQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of anomalies", body = AnomalyList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 503, description = "Analytics is disabled", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_anomalies(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ListAnomaliesQuery>,
+) -> ApiResult<Json<AnomalyList>> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let (anomalies, total) = analytics_service(&state)?
+        .list_anomalies(auth.tenant(), limit, offset)
+        .await?;
+
+    let pagination = Pagination::new(limit, offset, total);
+    Ok(Json(AnomalyList {
+        anomalies,
+        links: pagination.links("/v1/analytics/anomalies"),
+        pagination,
+    }))
+}
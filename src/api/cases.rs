@@ -0,0 +1,517 @@
+//! Manual review case endpoints
+
+use axum::{
+    Json,
+    extract::{Path, Query, RawQuery, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+use super::{ApiError, ApiResult, transactions::listing_base};
+use crate::{
+    auth::AuthContext,
+    models::{
+        case::{
+            CaseAnnotation, CaseAnnotationRequest, CaseAssignmentRequest, CaseClaim, CaseList,
+            CaseQueue, CaseQueueList, CaseQueueRequest, CaseReference, CaseReferenceRequest,
+            CaseResolution, ListCasesQuery, ReassignedCases, ReviewCase, Reviewer, ReviewerList,
+            ReviewerReassignment, ReviewerUpdate,
+        },
+        common::Pagination,
+    },
+    state::AppState,
+};
+
+/// Default page size for case listings
+const DEFAULT_LIMIT: i64 = 20;
+/// Largest page size a client may request
+const MAX_LIMIT: i64 = 100;
+
+/// List review cases
+#[utoipa::path(
+    get,
+    path = "/v1/cases",
+    tags = ["Cases"],
+    summary = "List cases",
+    description = "Retrieve a paginated list of the calling account's manual review cases, oldest first, so the queue is worked in the order transactions arrived. A case is opened for every transaction given the `review` disposition, whether when it is scored or rescored. Filter by `status` for the open queue, by `claimed_by` for the cases a reviewer holds, by `queue` for the cases routed into a named queue, by `overdue` for unresolved cases past their queue's SLA, or by `kind` for merchant appeals of rejected transactions. Requires the `cases:read` scope.",
+    params(ListCasesQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of cases", body = CaseList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_cases(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ListCasesQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Json<CaseList>> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let (cases, total) = state
+        .cases
+        .list_cases(auth.tenant(), &query, limit, offset)
+        .await?;
+
+    let pagination = Pagination::new(limit, offset, total);
+    Ok(Json(CaseList {
+        cases,
+        links: pagination.links(&listing_base("/v1/cases", raw_query.as_deref())),
+        pagination,
+    }))
+}
+
+/// Fetch a review case by ID
+#[utoipa::path(
+    get,
+    path = "/v1/cases/{case_id}",
+    tags = ["Cases"],
+    summary = "Get case by ID",
+    description = "Retrieve a case with the risk score of its transaction, who holds it, how it was decided, the annotations and references reviewers left on it, the entities its transaction involves, and its history: when it was opened, assigned, reassigned, claimed, annotated, and resolved, and by whom. The entities are the transaction's user, device, IP address, email address, and card as they stand now, such as the user's current risk score and whether the device has since been blocked, each linked to the endpoint with more about it, so the context of the investigation comes in one request. Requires the `cases:read` scope.",
+    params(("case_id" = Uuid, Path, description = "Unique identifier for the case")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The case", body = ReviewCase),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Case not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_case(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(case_id): Path<Uuid>,
+) -> ApiResult<Json<ReviewCase>> {
+    Ok(Json(state.cases.get_case(auth.tenant(), case_id).await?))
+}
+
+/// Claim a review case
+#[utoipa::path(
+    post,
+    path = "/v1/cases/{case_id}/claim",
+    tags = ["Cases"],
+    summary = "Claim case",
+    description = "Take an open case for review, so other reviewers leave it alone. Claiming a case the reviewer already holds changes nothing. Requires the `cases:write` scope.",
+    params(("case_id" = Uuid, Path, description = "Unique identifier for the case")),
+    request_body = CaseClaim,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The claimed case", body = ReviewCase),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Case not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "Another reviewer holds the case, or it is resolved", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn claim_case(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(case_id): Path<Uuid>,
+    Json(request): Json<CaseClaim>,
+) -> ApiResult<Json<ReviewCase>> {
+    request.validate().map_err(ApiError::Validation)?;
+    Ok(Json(
+        state.cases.claim(auth.tenant(), case_id, &request).await?,
+    ))
+}
+
+/// Annotate a review case
+#[utoipa::path(
+    post,
+    path = "/v1/cases/{case_id}/annotations",
+    tags = ["Cases"],
+    summary = "Annotate case",
+    description = "Leave a remark on a case, such as what a call to the cardholder turned up. Any reviewer may annotate a case in any status, including after it is resolved. Requires the `cases:write` scope.",
+    params(("case_id" = Uuid, Path, description = "Unique identifier for the case")),
+    request_body = CaseAnnotationRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "The annotation", body = CaseAnnotation),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Case not found", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn annotate_case(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(case_id): Path<Uuid>,
+    Json(request): Json<CaseAnnotationRequest>,
+) -> ApiResult<(StatusCode, Json<CaseAnnotation>)> {
+    request.validate().map_err(ApiError::Validation)?;
+    let annotation = state
+        .cases
+        .annotate(auth.tenant(), case_id, &request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(annotation)))
+}
+
+/// Attach a reference to a review case
+#[utoipa::path(
+    post,
+    path = "/v1/cases/{case_id}/references",
+    tags = ["Cases"],
+    summary = "Add case reference",
+    description = "Attach a link to a case, such as a support ticket, a chargeback document, or a search in another system, so other reviewers can find the material it was decided on. Documents are referenced by HTTP(S) URL rather than uploaded. Any reviewer may attach a reference to a case in any status. Requires the `cases:write` scope.",
+    params(("case_id" = Uuid, Path, description = "Unique identifier for the case")),
+    request_body = CaseReferenceRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "The reference", body = CaseReference),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Case not found", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn add_case_reference(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(case_id): Path<Uuid>,
+    Json(request): Json<CaseReferenceRequest>,
+) -> ApiResult<(StatusCode, Json<CaseReference>)> {
+    request.validate().map_err(ApiError::Validation)?;
+    let reference = state
+        .cases
+        .add_reference(auth.tenant(), case_id, &request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(reference)))
+}
+
+/// Resolve a review case
+#[utoipa::path(
+    post,
+    path = "/v1/cases/{case_id}/resolve",
+    tags = ["Cases"],
+    summary = "Resolve case",
+    description = "Close a case by approving or declining its transaction. The decision is recorded as the transaction's outcome, `not_fraud` for an approval and `suspected_fraud` for a decline, unless an outcome was already reported for it, and the transaction's user has its risk score recalculated. When declining, set `block_device` to also block the device the transaction came from. An open case may be resolved without claiming it first. Requires the `cases:write` scope.",
+    params(("case_id" = Uuid, Path, description = "Unique identifier for the case")),
+    request_body = CaseResolution,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The resolved case", body = ReviewCase),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Case not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "Another reviewer holds the case, or it is already resolved", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn resolve_case(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(case_id): Path<Uuid>,
+    Json(request): Json<CaseResolution>,
+) -> ApiResult<Json<ReviewCase>> {
+    request.validate().map_err(ApiError::Validation)?;
+    Ok(Json(
+        state
+            .cases
+            .resolve(auth.tenant(), case_id, &request)
+            .await?,
+    ))
+}
+
+/// Assign a review case to a reviewer
+#[utoipa::path(
+    post,
+    path = "/v1/cases/{case_id}/assign",
+    tags = ["Cases"],
+    summary = "Assign case",
+    description = "Hand an unresolved case to a reviewer, taking it from whoever holds it. The reviewer must be registered and active at `/v1/cases/reviewers`. Leave `reviewer` out to have one picked by the account's `case_assignment` strategy, other than the current holder; with manual assignment, the reviewer holding the fewest cases is picked. The assignment and who made it are kept in the case's history. Requires the `cases:write` scope.",
+    params(("case_id" = Uuid, Path, description = "Unique identifier for the case")),
+    request_body = CaseAssignmentRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The assigned case", body = ReviewCase),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Case not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The case is resolved, or there is no other active reviewer to pick", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed or the reviewer is not active", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn assign_case(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(case_id): Path<Uuid>,
+    Json(request): Json<CaseAssignmentRequest>,
+) -> ApiResult<Json<ReviewCase>> {
+    request.validate().map_err(ApiError::Validation)?;
+    Ok(Json(
+        state.cases.assign(auth.tenant(), case_id, &request).await?,
+    ))
+}
+
+/// List reviewers
+#[utoipa::path(
+    get,
+    path = "/v1/cases/reviewers",
+    tags = ["Cases"],
+    summary = "List reviewers",
+    description = "Retrieve the reviewers registered for the calling account, by name, with how many unresolved cases each holds and when each was last assigned a case. Requires the `cases:read` scope.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The reviewers", body = ReviewerList),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_reviewers(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<ReviewerList>> {
+    Ok(Json(ReviewerList {
+        reviewers: state.cases.list_reviewers(auth.tenant()).await?,
+    }))
+}
+
+/// Register or update a reviewer
+#[utoipa::path(
+    put,
+    path = "/v1/cases/reviewers/{reviewer}",
+    tags = ["Cases"],
+    summary = "Set reviewer",
+    description = "Register a reviewer cases can be assigned to, or change whether an existing one is active. New cases are assigned automatically only to active reviewers, and only when the account's `case_assignment` setting is `round_robin` or `least_loaded`. Deactivating a reviewer leaves the cases they hold with them; move those with `POST /v1/cases/reviewers/{reviewer}/reassign`. Requires the `cases:write` scope.",
+    params(("reviewer" = String, Path, description = "Name the reviewer works cases under")),
+    request_body = ReviewerUpdate,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The reviewer", body = Reviewer),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Invalid reviewer name", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn set_reviewer(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(reviewer): Path<String>,
+    Json(update): Json<ReviewerUpdate>,
+) -> ApiResult<Json<Reviewer>> {
+    Ok(Json(
+        state
+            .cases
+            .set_reviewer(auth.tenant(), &reviewer, &update)
+            .await?,
+    ))
+}
+
+/// Remove a reviewer
+#[utoipa::path(
+    delete,
+    path = "/v1/cases/reviewers/{reviewer}",
+    tags = ["Cases"],
+    summary = "Remove reviewer",
+    description = "Stop assigning cases to a reviewer. The cases they hold stay with them, and they may still claim and resolve cases by name. Requires the `cases:write` scope.",
+    params(("reviewer" = String, Path, description = "Name the reviewer works cases under")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Reviewer removed"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Reviewer not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn remove_reviewer(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(reviewer): Path<String>,
+) -> ApiResult<StatusCode> {
+    state
+        .cases
+        .remove_reviewer(auth.tenant(), &reviewer)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Reassign a reviewer's cases
+#[utoipa::path(
+    post,
+    path = "/v1/cases/reviewers/{reviewer}/reassign",
+    tags = ["Cases"],
+    summary = "Reassign reviewer's cases",
+    description = "Move every unresolved case a reviewer holds to other active reviewers, for example while they are away. Each case goes to the reviewer the account's `case_assignment` strategy picks, or the one holding the fewest cases when assignment is manual, among the assignees of the case's queue if it names any. Each move is kept in the case's history. Requires the `cases:write` scope.",
+    params(("reviewer" = String, Path, description = "Name the reviewer works cases under")),
+    request_body = ReviewerReassignment,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The moved cases", body = ReassignedCases),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "No other active reviewer to move the cases to", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn reassign_reviewer_cases(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(reviewer): Path<String>,
+    Json(request): Json<ReviewerReassignment>,
+) -> ApiResult<Json<ReassignedCases>> {
+    request.validate().map_err(ApiError::Validation)?;
+    Ok(Json(
+        state
+            .cases
+            .reassign_reviewer_cases(auth.tenant(), &reviewer, &request)
+            .await?,
+    ))
+}
+
+/// List case queues
+#[utoipa::path(
+    get,
+    path = "/v1/cases/queues",
+    tags = ["Cases"],
+    summary = "List queues",
+    description = "Retrieve the calling account's case queues in routing order, each with the number of unresolved cases in it and how many of those are past their SLA. Requires the `cases:read` scope.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The account's queues", body = CaseQueueList),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_queues(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<CaseQueueList>> {
+    Ok(Json(CaseQueueList {
+        queues: state.cases.list_queues(auth.tenant()).await?,
+    }))
+}
+
+/// Create a case queue
+#[utoipa::path(
+    post,
+    path = "/v1/cases/queues",
+    tags = ["Cases"],
+    summary = "Create queue",
+    description = "Create a named queue, such as `high-value` or `account-takeover`, that new review cases are routed into. Each new case joins the first queue, lowest `priority` first, whose routing conditions its transaction meets: its event type, order amount, and the rules that fired. Cases matching no queue stay outside any queue. A case is due `sla_minutes` after it was opened. When the queue names `assignees`, its cases are assigned among those of them that are active reviewers, picked by the account's `case_assignment` strategy or the least loaded when assignment is manual. Requires the `cases:write` scope.",
+    request_body = CaseQueueRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Queue created", body = CaseQueue),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "A queue with this name already exists", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn create_queue(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(request): Json<CaseQueueRequest>,
+) -> ApiResult<(StatusCode, Json<CaseQueue>)> {
+    request.validate().map_err(ApiError::Validation)?;
+    let queue = state.cases.create_queue(auth.tenant(), &request).await?;
+    Ok((StatusCode::CREATED, Json(queue)))
+}
+
+/// Fetch a case queue by ID
+#[utoipa::path(
+    get,
+    path = "/v1/cases/queues/{queue_id}",
+    tags = ["Cases"],
+    summary = "Get queue by ID",
+    description = "Retrieve a queue with its routing conditions, SLA, assignees, and the number of unresolved and overdue cases in it. Requires the `cases:read` scope.",
+    params(("queue_id" = Uuid, Path, description = "Unique identifier for the queue")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The queue", body = CaseQueue),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Queue not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_queue(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(queue_id): Path<Uuid>,
+) -> ApiResult<Json<CaseQueue>> {
+    Ok(Json(state.cases.get_queue(auth.tenant(), queue_id).await?))
+}
+
+/// Replace a case queue
+#[utoipa::path(
+    put,
+    path = "/v1/cases/queues/{queue_id}",
+    tags = ["Cases"],
+    summary = "Update queue",
+    description = "Replace a queue's name, priority, SLA, assignees, and routing conditions. Only cases opened afterwards are routed by the new conditions; cases already in the queue keep their due time. Requires the `cases:write` scope.",
+    params(("queue_id" = Uuid, Path, description = "Unique identifier for the queue")),
+    request_body = CaseQueueRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The updated queue", body = CaseQueue),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Queue not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "Another queue has this name", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn update_queue(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(queue_id): Path<Uuid>,
+    Json(request): Json<CaseQueueRequest>,
+) -> ApiResult<Json<CaseQueue>> {
+    request.validate().map_err(ApiError::Validation)?;
+    Ok(Json(
+        state
+            .cases
+            .update_queue(auth.tenant(), queue_id, &request)
+            .await?,
+    ))
+}
+
+/// Delete a case queue
+#[utoipa::path(
+    delete,
+    path = "/v1/cases/queues/{queue_id}",
+    tags = ["Cases"],
+    summary = "Delete queue",
+    description = "Delete a queue. Its cases stay as they are, outside any queue, and new cases are routed by the remaining queues. Requires the `cases:write` scope.",
+    params(("queue_id" = Uuid, Path, description = "Unique identifier for the queue")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Queue deleted"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Queue not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn delete_queue(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(queue_id): Path<Uuid>,
+) -> ApiResult<StatusCode> {
+    state.cases.delete_queue(auth.tenant(), queue_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
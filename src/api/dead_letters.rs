@@ -0,0 +1,211 @@
+//! Dead letter endpoints
+
+use axum::{
+    Json,
+    extract::{Path, Query, RawQuery, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+use super::{ApiError, ApiResult, transactions::listing_base};
+use crate::{
+    auth::AuthContext,
+    models::{
+        common::Pagination,
+        dead_letter::{
+            DeadLetter, DeadLetterBatch, DeadLetterList, DeadLetterSelection, ListDeadLettersQuery,
+        },
+    },
+    state::AppState,
+};
+
+/// Default page size for dead letter listings
+const DEFAULT_LIMIT: i64 = 20;
+/// Largest page size a client may request
+const MAX_LIMIT: i64 = 100;
+
+/// List the account's dead letters
+#[utoipa::path(
+    get,
+    path = "/v1/dead-letters",
+    tags = ["Dead Letters"],
+    summary = "List dead letters",
+    description = "Retrieve a paginated list of the calling account's events that could not be delivered after every retry, newest first, with the error of the last attempt. Such events are no longer sent to webhooks, callbacks, or the event stream until they are redriven. Filter by `event_type` to see one kind. Requires the `account:read` scope.",
+    params(ListDeadLettersQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of dead letters", body = DeadLetterList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_dead_letters(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ListDeadLettersQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Json<DeadLetterList>> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+    query.validate().map_err(ApiError::BadRequest)?;
+
+    let (dead_letters, total) = state
+        .dead_letters
+        .list_dead_letters(auth.tenant(), &query, limit, offset)
+        .await?;
+
+    let pagination = Pagination::new(limit, offset, total);
+    Ok(Json(DeadLetterList {
+        dead_letters,
+        links: pagination.links(&listing_base("/v1/dead-letters", raw_query.as_deref())),
+        pagination,
+    }))
+}
+
+/// Fetch a dead letter
+#[utoipa::path(
+    get,
+    path = "/v1/dead-letters/{event_id}",
+    tags = ["Dead Letters"],
+    summary = "Get dead letter by ID",
+    description = "Retrieve an undeliverable event with its payload, the number of attempts made, and what went wrong on the last one. Requires the `account:read` scope.",
+    params(("event_id" = Uuid, Path, description = "Unique identifier for the event")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The dead letter", body = DeadLetter),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Dead letter not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_dead_letter(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(event_id): Path<Uuid>,
+) -> ApiResult<Json<DeadLetter>> {
+    Ok(Json(
+        state
+            .dead_letters
+            .get_dead_letter(auth.tenant(), event_id)
+            .await?,
+    ))
+}
+
+/// Redrive a dead letter
+#[utoipa::path(
+    post,
+    path = "/v1/dead-letters/{event_id}/redrive",
+    tags = ["Dead Letters"],
+    summary = "Redrive dead letter",
+    description = "Hand an undeliverable event back for delivery, with a fresh set of retries, once whatever refused it is fixed. The event is delivered to every consumer again under its original ID, so endpoints that deduplicate on it are safe. Requires the `account:write` scope.",
+    params(("event_id" = Uuid, Path, description = "Unique identifier for the event")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 202, description = "Event queued for delivery"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Dead letter not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn redrive_dead_letter(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(event_id): Path<Uuid>,
+) -> ApiResult<StatusCode> {
+    state.dead_letters.redrive(auth.tenant(), event_id).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Purge a dead letter
+#[utoipa::path(
+    delete,
+    path = "/v1/dead-letters/{event_id}",
+    tags = ["Dead Letters"],
+    summary = "Delete dead letter",
+    description = "Give up on an undeliverable event for good, deleting it along with the log of its webhook deliveries. Requires the `account:write` scope.",
+    params(("event_id" = Uuid, Path, description = "Unique identifier for the event")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Dead letter deleted"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Dead letter not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn delete_dead_letter(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(event_id): Path<Uuid>,
+) -> ApiResult<StatusCode> {
+    state.dead_letters.purge(auth.tenant(), event_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Redrive every dead letter
+#[utoipa::path(
+    post,
+    path = "/v1/dead-letters/redrive",
+    tags = ["Dead Letters"],
+    summary = "Redrive dead letters",
+    description = "Hand every undeliverable event of the account, or those of one `event_type`, back for delivery with a fresh set of retries. Requires the `account:write` scope.",
+    params(DeadLetterSelection),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Number of events queued for delivery", body = DeadLetterBatch),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn redrive_dead_letters(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(selection): Query<DeadLetterSelection>,
+) -> ApiResult<Json<DeadLetterBatch>> {
+    selection.validate().map_err(ApiError::BadRequest)?;
+    let count = state
+        .dead_letters
+        .redrive_all(auth.tenant(), selection.event_type.as_deref())
+        .await?;
+    Ok(Json(DeadLetterBatch { count }))
+}
+
+/// Purge every dead letter
+#[utoipa::path(
+    delete,
+    path = "/v1/dead-letters",
+    tags = ["Dead Letters"],
+    summary = "Purge dead letters",
+    description = "Give up for good on every undeliverable event of the account, or those of one `event_type`, deleting them along with the log of their webhook deliveries. Requires the `account:write` scope.",
+    params(DeadLetterSelection),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Number of events deleted", body = DeadLetterBatch),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn purge_dead_letters(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(selection): Query<DeadLetterSelection>,
+) -> ApiResult<Json<DeadLetterBatch>> {
+    selection.validate().map_err(ApiError::BadRequest)?;
+    let count = state
+        .dead_letters
+        .purge_all(auth.tenant(), selection.event_type.as_deref())
+        .await?;
+    Ok(Json(DeadLetterBatch { count }))
+}
@@ -0,0 +1,204 @@
+//! Device endpoints
+
+use axum::{
+    Json,
+    extract::{Path, Query, RawQuery, State},
+};
+use uuid::Uuid;
+
+use super::{
+    ApiError, ApiResult,
+    transactions::{listing_base, transaction_page},
+};
+use crate::{
+    auth::AuthContext,
+    models::{
+        common::Pagination,
+        device::{Device, DeviceFingerprintRequest, DeviceList, DeviceUpdate, ListDevicesQuery},
+        transaction::{ListTransactionsQuery, TransactionList},
+    },
+    state::AppState,
+};
+
+/// Default page size for device listings
+const DEFAULT_LIMIT: i64 = 20;
+/// Largest page size a client may request
+const MAX_LIMIT: i64 = 100;
+
+/// Fingerprint a device from browser-collected signals
+#[utoipa::path(
+    post,
+    path = "/v1/devices/fingerprint",
+    tags = ["Devices"],
+    summary = "Fingerprint device",
+    description = "Submit the signals the fingerprinting script collected in a visitor's browser, such as canvas and audio hashes, WebGL renderer, screen, time zone, and fonts, together with the IP address and headers of the visitor's request. The signals are hashed into a fingerprint that stays the same across networks, browser updates, and page zoom, and the device it identifies is found or created and returned with the latest signals. Pass the returned `device_token` as `device.device_token` when scoring the visitor's transactions, so they are attributed to the same device wherever the visitor connects from; without a token, a device is identified by its IP address and headers.",
+    request_body = DeviceFingerprintRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The fingerprinted device", body = Device),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The device has been deleted", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn fingerprint_device(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(request): Json<DeviceFingerprintRequest>,
+) -> ApiResult<Json<Device>> {
+    Ok(Json(
+        state
+            .devices
+            .register_fingerprint(auth.tenant(), &request)
+            .await?,
+    ))
+}
+
+/// List devices
+#[utoipa::path(
+    get,
+    path = "/v1/devices",
+    tags = ["Devices"],
+    summary = "List devices",
+    description = "Retrieve a paginated list of the calling account's devices, most recently seen first. Filter by `status` for trusted or blocked devices, by `suspicious` to find devices whose user agent names an automation tool or that sent a high or very high risk transaction, by `ip_address` for devices last seen at an address or within a CIDR range, or by `user_id` for devices a user has been seen with. Requires the `transactions:read` scope.",
+    params(ListDevicesQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of devices", body = DeviceList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_devices(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ListDevicesQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Json<DeviceList>> {
+    query.validate().map_err(ApiError::BadRequest)?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let (devices, total) = state
+        .devices
+        .list_devices(auth.tenant(), &query, limit, offset)
+        .await?;
+
+    let pagination = Pagination::new(limit, offset, total);
+    Ok(Json(DeviceList {
+        devices,
+        links: pagination.links(&listing_base("/v1/devices", raw_query.as_deref())),
+        pagination,
+    }))
+}
+
+/// Fetch a device by ID
+#[utoipa::path(
+    get,
+    path = "/v1/devices/{device_id}",
+    tags = ["Devices"],
+    summary = "Get device by ID",
+    description = "Retrieve a device with its latest network details and signals, how many transactions and distinct users it was seen with, and whether it is suspicious. Requires the `transactions:read` scope.",
+    params(("device_id" = Uuid, Path, description = "Unique identifier for the device")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The device", body = Device),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Device not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_device(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(device_id): Path<Uuid>,
+) -> ApiResult<Json<Device>> {
+    Ok(Json(
+        state.devices.get_device(auth.tenant(), device_id).await?,
+    ))
+}
+
+/// Update a device
+#[utoipa::path(
+    patch,
+    path = "/v1/devices/{device_id}",
+    tags = ["Devices"],
+    summary = "Update device",
+    description = "Mark a device `trusted` or `blocked`, or return it to `normal`. Transactions from a trusted device score half as high as they otherwise would. Transactions from a blocked device receive the `BLOCKED_DEVICE` factor and are rejected whatever the account's disposition policy, except on sandbox keys. Devices belong to the calling account, so marking one affects no other account. Requires the `transactions:write` scope.",
+    params(("device_id" = Uuid, Path, description = "Unique identifier for the device")),
+    request_body = DeviceUpdate,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated device", body = Device),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Device not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn update_device(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(device_id): Path<Uuid>,
+    Json(update): Json<DeviceUpdate>,
+) -> ApiResult<Json<Device>> {
+    Ok(Json(
+        state
+            .devices
+            .update_device(auth.tenant(), device_id, &update)
+            .await?,
+    ))
+}
+
+/// List a device's transactions
+#[utoipa::path(
+    get,
+    path = "/v1/devices/{device_id}/transactions",
+    tags = ["Devices"],
+    summary = "List device transactions",
+    description = "Retrieve a paginated list of the transactions that came from a device, across every user it was seen with. Takes the same filters, sort orders, and `offset` or `cursor` pagination as `GET /v1/transactions`, except `device_id`, which comes from the path. Requires the `transactions:read` scope.",
+    params(
+        ("device_id" = Uuid, Path, description = "Unique identifier for the device"),
+        ListTransactionsQuery
+    ),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of the device's transactions", body = TransactionList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Device not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_device_transactions(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(device_id): Path<Uuid>,
+    Query(mut query): Query<ListTransactionsQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Json<TransactionList>> {
+    if query.device_id.is_some() {
+        return Err(ApiError::BadRequest(
+            "device_id is taken from the path and cannot be given as a filter".to_string(),
+        ));
+    }
+    state.devices.get_device(auth.tenant(), device_id).await?;
+    query.device_id = Some(device_id);
+    let path = format!("/v1/devices/{device_id}/transactions");
+    Ok(Json(
+        transaction_page(&state, auth.tenant(), &query, &path, raw_query.as_deref()).await?,
+    ))
+}
@@ -0,0 +1,72 @@
+//! Email address endpoints
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+use super::{ApiError, ApiResult};
+use crate::{
+    auth::AuthContext,
+    models::{insights::EmailAddressInsights, transaction::TransactionEmail},
+    state::AppState,
+};
+
+/// Look up an email address
+#[utoipa::path(
+    get,
+    path = "/v1/emails/{email}",
+    tags = ["Email Intelligence"],
+    summary = "Look up email address",
+    description = "Retrieve what is known about an email address without scoring a transaction: whether its domain is a free email provider or hands out disposable addresses, how many addresses at the domain the calling account has seen, the address's history on the account, and the account's most recent transactions with it. Addresses are stored only as SHA-256 hashes of their trimmed, lowercased form, so the address may be given either as itself or as that hash, as found in stored transaction requests; a hash alone only reveals the domain if the account has seen the address. Requires the `transactions:read` scope. Available on the Pro plan and above.",
+    params(("email" = String, Path, description = "Email address, or the SHA-256 hash of its trimmed, lowercased form")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "What is known about the email address", body = EmailAddressInsights),
+        (status = 400, description = "Neither an email address nor its hash", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the plan does not include insights", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_email_insights(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(email): Path<String>,
+) -> ApiResult<Json<EmailAddressInsights>> {
+    let email = email.trim();
+    let (email_hash, domain) = if is_email_hash(email) {
+        (email.to_ascii_lowercase(), None)
+    } else if email
+        .rsplit_once('@')
+        .is_some_and(|(local, domain)| !local.is_empty() && !domain.is_empty())
+    {
+        let email = TransactionEmail {
+            address: Some(email.to_string()),
+            domain: None,
+        };
+        (
+            email.address_hash().unwrap_or_default(),
+            email.resolved_domain(),
+        )
+    } else {
+        return Err(ApiError::BadRequest(
+            "email must be an email address or the SHA-256 hash of one".to_string(),
+        ));
+    };
+
+    let mut insights = state
+        .transactions
+        .email_insights(auth.tenant(), &email_hash, domain)
+        .await?;
+    if let Some(domain) = &mut insights.domain {
+        let traits = state.email_intel.traits(&domain.domain).await;
+        domain.is_free = traits.is_free;
+        domain.is_disposable = traits.is_disposable;
+    }
+    Ok(Json(insights))
+}
+
+/// Whether `email` is a hex-encoded SHA-256 hash rather than an address
+fn is_email_hash(email: &str) -> bool {
+    email.len() == 64 && email.bytes().all(|b| b.is_ascii_hexdigit())
+}
@@ -2,18 +2,24 @@
 
 use axum::{
     Json,
-    http::StatusCode,
+    http::{HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use utoipa::ToSchema;
 
+use crate::{
+    database::clickhouse::ClickHouseError,
+    models::account::{AccountStatus, Feature, SubscriptionTier},
+    services::ServiceError,
+};
+
 /// API result type alias
 pub type ApiResult<T> = Result<T, ApiError>;
 
 /// Error codes for machine-readable responses
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 #[schema(
     title = "ErrorCode",
@@ -25,12 +31,28 @@ pub enum ErrorCode {
     BadRequest,
     /// Authentication required - Authentication credentials required
     Unauthorized,
+    /// Permission denied - Credentials lack the scope the endpoint requires
+    Forbidden,
+    /// Upgrade required - The endpoint is not included in the account's subscription tier
+    UpgradeRequired,
     /// Resource not found - Requested resource does not exist
     NotFound,
+    /// Conflict - The request clashes with the current state of the resource
+    Conflict,
     /// Validation failed - Request validation failed
     ValidationError,
     /// Internal server error - Unexpected server error occurred
     InternalError,
+    /// Service unavailable - Temporarily overloaded, retry later
+    ServiceUnavailable,
+    /// Quota exceeded - The account's monthly request quota is used up
+    QuotaExceeded,
+    /// Account suspended - The account may not score transactions until it is reactivated
+    AccountSuspended,
+    /// Account closed - Only account endpoints are available until the account is reactivated
+    AccountClosed,
+    /// Rate limited - The account is sending requests faster than its tier allows
+    RateLimited,
 }
 
 /// API error types
@@ -52,17 +74,55 @@ pub enum ApiError {
     #[error("Not found")]
     NotFound,
 
+    /// The request clashes with the current state of the resource
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     /// Authentication required
     #[error("Unauthorized")]
     Unauthorized,
 
+    /// Authenticated, but not permitted to perform the request
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    /// The account's subscription tier does not include the feature
+    #[error("Upgrade required for {feature}")]
+    UpgradeRequired {
+        /// Feature the request needs
+        feature: Feature,
+        /// Tier the account is on
+        current_tier: SubscriptionTier,
+    },
+
     /// Validation error with details
     #[error("Validation error: {0}")]
     Validation(String),
+
+    /// Temporarily unable to serve the request
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    /// The account has used up its quota for the billing cycle
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    /// The account's status does not allow the request
+    #[error("Account {0}")]
+    AccountInactive(AccountStatus),
+
+    /// The account is sending requests faster than its rate limit allows
+    #[error("Rate limited; retry after {retry_after_seconds}s")]
+    RateLimited {
+        /// Requests allowed per second on the account's tier
+        per_second: u32,
+        /// Seconds until a request will be allowed again
+        retry_after_seconds: u64,
+    },
 }
 
 /// Error response structure
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[schema(
     title = "ErrorResponse",
     description = "Standard error response format",
@@ -78,10 +138,20 @@ pub struct ErrorResponse {
     /// Human-readable error message
     #[schema(example = "Invalid request parameters")]
     pub message: String,
+    /// Machine-readable context for some errors, such as the tier needed for
+    /// `upgrade_required`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>, example = json!({
+        "feature": "insights",
+        "current_tier": "free",
+        "required_tier": "pro"
+    }))]
+    pub details: Option<serde_json::Value>,
 }
 
 impl ApiError {
-    fn to_response(&self) -> (StatusCode, ErrorResponse) {
+    /// Status and body of the response the error is reported with
+    pub fn to_response(&self) -> (StatusCode, ErrorResponse) {
         match self {
             ApiError::Internal(e) => {
                 tracing::error!(error = %e, "Internal server error");
@@ -90,6 +160,7 @@ impl ApiError {
                     ErrorResponse {
                         error: ErrorCode::InternalError,
                         message: "Internal server error".to_string(),
+                        details: None,
                     },
                 )
             },
@@ -98,6 +169,7 @@ impl ApiError {
                 ErrorResponse {
                     error: ErrorCode::BadRequest,
                     message: format!("Invalid JSON: {}", e),
+                    details: None,
                 },
             ),
             ApiError::BadRequest(msg) => (
@@ -105,6 +177,7 @@ impl ApiError {
                 ErrorResponse {
                     error: ErrorCode::BadRequest,
                     message: msg.clone(),
+                    details: None,
                 },
             ),
             ApiError::NotFound => (
@@ -112,6 +185,15 @@ impl ApiError {
                 ErrorResponse {
                     error: ErrorCode::NotFound,
                     message: "Resource not found".to_string(),
+                    details: None,
+                },
+            ),
+            ApiError::Conflict(msg) => (
+                StatusCode::CONFLICT,
+                ErrorResponse {
+                    error: ErrorCode::Conflict,
+                    message: msg.clone(),
+                    details: None,
                 },
             ),
             ApiError::Unauthorized => (
@@ -119,22 +201,136 @@ impl ApiError {
                 ErrorResponse {
                     error: ErrorCode::Unauthorized,
                     message: "Authentication required".to_string(),
+                    details: None,
+                },
+            ),
+            ApiError::Forbidden(msg) => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    error: ErrorCode::Forbidden,
+                    message: msg.clone(),
+                    details: None,
                 },
             ),
+            ApiError::UpgradeRequired {
+                feature,
+                current_tier,
+            } => {
+                let required_tier = feature.minimum_tier();
+                (
+                    StatusCode::FORBIDDEN,
+                    ErrorResponse {
+                        error: ErrorCode::UpgradeRequired,
+                        message: format!(
+                            "The {feature} feature requires the {required_tier} plan or higher; \
+                             this account is on the {current_tier} plan"
+                        ),
+                        details: Some(serde_json::json!({
+                            "feature": feature,
+                            "current_tier": current_tier,
+                            "required_tier": required_tier,
+                        })),
+                    },
+                )
+            },
             ApiError::Validation(msg) => (
                 StatusCode::UNPROCESSABLE_ENTITY,
                 ErrorResponse {
                     error: ErrorCode::ValidationError,
                     message: msg.clone(),
+                    details: None,
+                },
+            ),
+            ApiError::ServiceUnavailable(msg) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                ErrorResponse {
+                    error: ErrorCode::ServiceUnavailable,
+                    message: msg.clone(),
+                    details: None,
+                },
+            ),
+            ApiError::QuotaExceeded(msg) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorResponse {
+                    error: ErrorCode::QuotaExceeded,
+                    message: msg.clone(),
+                    details: None,
+                },
+            ),
+            ApiError::AccountInactive(status) => {
+                let (error, message) = match status {
+                    AccountStatus::Closed => (
+                        ErrorCode::AccountClosed,
+                        "This account is closed; only account endpoints are available until it \
+                         is reactivated",
+                    ),
+                    _ => (
+                        ErrorCode::AccountSuspended,
+                        "This account is suspended and cannot score transactions until it is \
+                         reactivated",
+                    ),
+                };
+                (
+                    StatusCode::FORBIDDEN,
+                    ErrorResponse {
+                        error,
+                        message: message.to_string(),
+                        details: Some(serde_json::json!({ "status": status })),
+                    },
+                )
+            },
+            ApiError::RateLimited {
+                per_second,
+                retry_after_seconds,
+            } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorResponse {
+                    error: ErrorCode::RateLimited,
+                    message: format!(
+                        "Rate limit of {per_second} requests per second exceeded; retry after \
+                         {retry_after_seconds}s"
+                    ),
+                    details: Some(serde_json::json!({
+                        "retry_after_seconds": retry_after_seconds,
+                    })),
                 },
             ),
         }
     }
 }
 
+impl From<ServiceError> for ApiError {
+    fn from(error: ServiceError) -> Self {
+        match error {
+            ServiceError::NotFound => ApiError::NotFound,
+            ServiceError::Invalid(msg) => ApiError::Validation(msg),
+            ServiceError::Conflict(msg) => ApiError::Conflict(msg),
+            ServiceError::Forbidden(msg) => ApiError::Forbidden(msg),
+            ServiceError::Database(sqlx::Error::PoolTimedOut) => ApiError::ServiceUnavailable(
+                "Timed out waiting for a database connection".to_string(),
+            ),
+            ServiceError::Database(e) => ApiError::Internal(e.into()),
+            ServiceError::Analytics(ClickHouseError::Http(_)) => {
+                ApiError::ServiceUnavailable("Analytics store is unreachable".to_string())
+            },
+            ServiceError::Analytics(e) => ApiError::Internal(e.into()),
+        }
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let (status, error_response) = self.to_response();
-        (status, Json(error_response)).into_response()
+        let mut response = (status, Json(error_response)).into_response();
+        if let ApiError::RateLimited {
+            retry_after_seconds,
+            ..
+        } = self
+        {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_seconds));
+        }
+        response
     }
 }
@@ -1,9 +1,15 @@
 //! Health check endpoint for monitoring and load balancers
 
-use axum::Json;
+use axum::{Json, extract::State, http::StatusCode};
 
-use super::ApiResult;
-use crate::models::HealthResponse;
+use crate::{
+    database::PoolStats,
+    models::{
+        HealthResponse,
+        health::{DatabaseHealth, PoolHealth},
+    },
+    state::AppState,
+};
 
 /// Health check endpoint handler
 #[utoipa::path(
@@ -11,40 +17,82 @@ use crate::models::HealthResponse;
     path = "/health",
     tags = ["Health"],
     summary = "Health Check",
-    description = "Returns the current health status of the Fusegu API service. This endpoint is designed for use by load balancers, monitoring systems, and orchestration platforms.",
+    description = "Returns the current health status of the Fusegu API service, including PostgreSQL connectivity and connection pool usage. This endpoint is designed for use by load balancers, monitoring systems, and orchestration platforms: it answers 503 when the database is unreachable or the pool is saturated, so traffic can be shed instead of queueing.",
     responses(
-        (status = 200, description = "Service is healthy and operational", body = HealthResponse,
+        (status = 200, description = "Service is healthy and operational", body = HealthResponse),
+        (status = 503, description = "Database unreachable or connection pool saturated", body = HealthResponse,
             example = json!({
-                "status": "healthy",
-                "timestamp": "2025-01-21T10:00:00Z"
-            })
-        ),
-        (status = 500, description = "Service is experiencing issues", body = crate::api::errors::ErrorResponse,
-            example = json!({
-                "error": "internal_error",
-                "message": "Service temporarily unavailable"
+                "status": "degraded",
+                "timestamp": "2025-01-21T10:00:00Z",
+                "database": {
+                    "status": "saturated",
+                    "pool": {
+                        "size": 10,
+                        "idle": 0,
+                        "in_use": 10,
+                        "max_connections": 10,
+                        "utilization": 1.0
+                    }
+                }
             })
         )
     )
 )]
-pub async fn health_check() -> ApiResult<Json<HealthResponse>> {
+pub async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let threshold = state.config.database.postgres_saturation_threshold;
+
+    // A saturated pool would make the ping itself wait for a connection, so skip it
+    let saturated = state.database.pool_stats().is_saturated(threshold);
+    let reachable = saturated || state.database.ping().await.is_ok();
+    let stats = state.database.pool_stats();
+
+    let (code, status, database_status) = assess(reachable, saturated);
     let response = HealthResponse {
-        status: "healthy".to_string(),
+        status: status.to_string(),
         timestamp: chrono::Utc::now(),
+        database: DatabaseHealth {
+            status: database_status.to_string(),
+            pool: pool_health(stats),
+        },
     };
-    Ok(Json(response))
+    (code, Json(response))
+}
+
+/// Overall status code, service status, and database status
+fn assess(reachable: bool, saturated: bool) -> (StatusCode, &'static str, &'static str) {
+    if !reachable {
+        (StatusCode::SERVICE_UNAVAILABLE, "unhealthy", "down")
+    } else if saturated {
+        (StatusCode::SERVICE_UNAVAILABLE, "degraded", "saturated")
+    } else {
+        (StatusCode::OK, "healthy", "up")
+    }
+}
+
+fn pool_health(stats: PoolStats) -> PoolHealth {
+    PoolHealth {
+        size: stats.size,
+        idle: stats.idle,
+        in_use: stats.in_use(),
+        max_connections: stats.max_connections,
+        utilization: stats.utilization(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[tokio::test]
-    async fn test_health_check() {
-        let result = health_check().await;
-        assert!(result.is_ok());
-
-        let response = result.unwrap().0;
-        assert_eq!(response.status, "healthy");
+    #[test]
+    fn test_assess() {
+        assert_eq!(assess(true, false), (StatusCode::OK, "healthy", "up"));
+        assert_eq!(
+            assess(true, true),
+            (StatusCode::SERVICE_UNAVAILABLE, "degraded", "saturated")
+        );
+        assert_eq!(
+            assess(false, false),
+            (StatusCode::SERVICE_UNAVAILABLE, "unhealthy", "down")
+        );
     }
 }
@@ -0,0 +1,138 @@
+//! Payment processor webhook endpoints
+
+use std::time::Duration;
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use uuid::Uuid;
+
+use super::{ApiError, ApiResult};
+use crate::{
+    auth::AuthContext,
+    database::Tenant,
+    ingest::stripe,
+    models::processor_event::{
+        Processor, ProcessorEventQuery, ProcessorEventReceipt, ProcessorSecret,
+    },
+    state::AppState,
+};
+
+/// Receive a payment processor webhook
+#[utoipa::path(
+    post,
+    path = "/v1/ingest/{account_id}/processor-events",
+    tags = ["Processor Events"],
+    summary = "Receive processor events",
+    description = "Receive a dispute or refund webhook exactly as a payment processor sends it: a Stripe event object, or an Adyen standard notification with any number of items. Register this URL, with your account ID, as the webhook endpoint at the processor. The processor is detected from the body unless given as `processor`.\n\nThe request carries no API key: it is accepted only when the processor signed it with the secret stored for it with `PUT /v1/account/processor-secrets/{processor}`. A Stripe webhook must carry a `Stripe-Signature` header whose timestamp is within the signature tolerance; every item of an Adyen webhook must carry an `hmacSignature` made with the account's HMAC key.\n\nEach event is matched to the latest transaction scored with one of the payment's IDs as its `event.transaction_id`: for Stripe the PaymentIntent ID, then the charge ID; for Adyen the merchant reference, then the PSP reference of the original payment.\n\nA dispute (`charge.dispute.created`; `CHARGEBACK`, `NOTIFICATION_OF_CHARGEBACK`, or `SECOND_CHARGEBACK`) records a chargeback with the processor's reason code, replacing any other outcome on record, like one reported through `POST /v1/reports`: it counts against the transaction's user, card, and device and the reputation of its IP address. The dispute is also followed through its lifecycle: later events (`charge.dispute.updated` and `charge.dispute.closed`; `INFORMATION_SUPPLIED`, `CHARGEBACK_REVERSED`, `PREARBITRATION_WON`, and `PREARBITRATION_LOST`) move it to representment and then to won or lost, each change emitting a `dispute.updated` event; see `GET /v1/transactions/{transaction_id}/disputes`. A refund Stripe flags as `fraudulent` records suspected fraud unless an outcome is on record. Other events, including ordinary refunds, are acknowledged and ignored. Every newly recorded outcome emits a `transaction.reported` event; redelivered webhooks are recognized and not counted twice, so the processor may retry freely.",
+    params(
+        ("account_id" = Uuid, Path, description = "ID of the account the webhook is for"),
+        ProcessorEventQuery
+    ),
+    request_body(content = Object, description = "Webhook body as sent by the processor"),
+    responses(
+        (status = 200, description = "What became of each event", body = ProcessorEventReceipt),
+        (status = 400, description = "Malformed request body or query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid processor signature", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Body is not a webhook of a supported processor", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn receive_processor_events(
+    State(state): State<AppState>,
+    Path(account_id): Path<Uuid>,
+    Query(query): Query<ProcessorEventQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ApiResult<Json<ProcessorEventReceipt>> {
+    let value: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid JSON body: {e}")))?;
+    let processor = query
+        .processor
+        .or_else(|| Processor::detect(&value))
+        .ok_or_else(|| {
+            ApiError::Validation("Body is not a webhook of a supported processor".to_string())
+        })?;
+
+    // The account is only taken at its word once the processor's signature proves it
+    let tenant = Tenant::trusted(account_id);
+    let signature = headers
+        .get(stripe::SIGNATURE_HEADER)
+        .and_then(|v| v.to_str().ok());
+    let tolerance = Duration::from_secs(state.config.auth.signature_tolerance_seconds);
+    let signed = state
+        .processor_events
+        .verify(tenant, processor, signature, &body, tolerance)
+        .await?;
+    if !signed {
+        return Err(ApiError::Unauthorized);
+    }
+
+    Ok(Json(
+        state
+            .processor_events
+            .ingest(tenant, Some(processor), value)
+            .await?,
+    ))
+}
+
+/// Store the secret a processor signs webhooks with
+#[utoipa::path(
+    put,
+    path = "/v1/account/processor-secrets/{processor}",
+    tags = ["Processor Events"],
+    summary = "Set processor secret",
+    description = "Store the secret a payment processor signs the account's webhooks with, replacing any stored before: for Stripe the endpoint's signing secret (`whsec_...`), for Adyen the hex HMAC key. Webhooks sent to `POST /v1/ingest/{account_id}/processor-events` are accepted only when signed with it. Requires the `account:write` scope.",
+    params(("processor" = Processor, Path, description = "Processor the secret belongs to")),
+    request_body = ProcessorSecret,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Secret stored"),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Empty secret, or an Adyen key that is not hex", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn set_processor_secret(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(processor): Path<Processor>,
+    Json(request): Json<ProcessorSecret>,
+) -> ApiResult<StatusCode> {
+    state
+        .processor_events
+        .set_secret(auth.tenant(), processor, &request.secret)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Remove the secret a processor signs webhooks with
+#[utoipa::path(
+    delete,
+    path = "/v1/account/processor-secrets/{processor}",
+    tags = ["Processor Events"],
+    summary = "Remove processor secret",
+    description = "Forget the secret a payment processor signs the account's webhooks with, refusing its webhooks from then on. Requires the `account:write` scope.",
+    params(("processor" = Processor, Path, description = "Processor the secret belongs to")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Secret removed"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "No secret stored for the processor", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn remove_processor_secret(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(processor): Path<Processor>,
+) -> ApiResult<StatusCode> {
+    state
+        .processor_events
+        .remove_secret(auth.tenant(), processor)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,40 @@
+//! IP address endpoints
+
+use std::net::IpAddr;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+use super::{ApiError, ApiResult};
+use crate::{auth::AuthContext, models::insights::IpAddressInsights, state::AppState};
+
+/// Look up an IP address
+#[utoipa::path(
+    get,
+    path = "/v1/ip/{address}",
+    tags = ["IP Intelligence"],
+    summary = "Look up IP address",
+    description = "Retrieve what is known about an IPv4 or IPv6 address without scoring a transaction: its location and location risk, the network it belongs to, whether IP intelligence feeds list it as a Tor exit node, VPN, public proxy, or hosting provider, its history and reputation on the calling account, and the account's most recent transactions from it. Reserved and private addresses are neither located nor looked up in feeds. Requires the `transactions:read` scope. Available on the Pro plan and above.",
+    params(("address" = String, Path, description = "IPv4 or IPv6 address")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "What is known about the IP address", body = IpAddressInsights),
+        (status = 400, description = "Not an IP address", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the plan does not include insights", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_ip_insights(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(address): Path<String>,
+) -> ApiResult<Json<IpAddressInsights>> {
+    let ip: IpAddr = address
+        .parse()
+        .map_err(|_| ApiError::BadRequest("address must be an IP address".to_string()))?;
+    let mut insights = state.transactions.ip_insights(auth.tenant(), ip).await?;
+    insights.traits = state.ip_intel.lookup(&insights.ip_address).await;
+    Ok(Json(insights))
+}
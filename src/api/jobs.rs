@@ -0,0 +1,81 @@
+//! Background job endpoints
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use super::ApiResult;
+use crate::{
+    auth::AuthContext,
+    models::job::{BackgroundJob, BatchScoreFormat, BatchScoreRow},
+    state::AppState,
+};
+
+/// Fetch a background job by ID
+#[utoipa::path(
+    get,
+    path = "/v1/jobs/{job_id}",
+    tags = ["Transactions"],
+    summary = "Get job by ID",
+    description = "Retrieve a background job, such as a transaction submitted with `mode=async` or a job queued by `POST /v1/transactions/batch/rescore`. A job carries a `kind` and moves from `pending` through `running` to `completed` or `failed`. While running, `result` holds the output so far; once completed, the full output in the shape of the kind (`ScoreJobResult` for `score`, with the stored transaction; `RescoreJobResult` for `rescore`; `BatchScoreJobResult` for `batch_score`), and once failed, the error the equivalent request would have returned. A job interrupted by a transient error is retried with backoff, so `attempts` may exceed 1.",
+    params(("job_id" = Uuid, Path, description = "Unique identifier for the job")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The job", body = BackgroundJob),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Job not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_job(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(job_id): Path<Uuid>,
+) -> ApiResult<Json<BackgroundJob>> {
+    let job = state.jobs.get(auth.tenant(), job_id).await?;
+    Ok(Json(job))
+}
+
+/// Download the output file of a background job
+#[utoipa::path(
+    get,
+    path = "/v1/jobs/{job_id}/output",
+    tags = ["Transactions"],
+    summary = "Download job output",
+    description = "Download the file written by a `batch_score` job, in the format it was queued with: CSV with a header line, or one JSON object per line. Each line is one transaction scored, or the error it could not be scored with. The file is available once the job has finished; a failed job's file holds what it scored before failing.",
+    params(("job_id" = Uuid, Path, description = "Unique identifier for the job")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The job's output", content(
+            (String = "text/csv"),
+            (BatchScoreRow = "application/x-ndjson")
+        )),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Job not found, or not one that writes a file", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "Job has not finished", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_job_output(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(job_id): Path<Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let (format, body) = state.jobs.output(auth.tenant(), job_id).await?;
+    let (content_type, extension) = match format {
+        BatchScoreFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        BatchScoreFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+    };
+    let disposition = format!("attachment; filename=\"job-{job_id}.{extension}\"");
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    ))
+}
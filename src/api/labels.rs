@@ -0,0 +1,175 @@
+//! Transaction label endpoints
+
+use axum::{
+    Json,
+    extract::{Path, Query, RawQuery, State},
+    http::header,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use super::{ApiError, ApiResult, transactions::listing_base};
+use crate::{
+    auth::AuthContext,
+    models::{
+        common::Pagination,
+        label::{
+            LabelExportFormat, LabelExportQuery, LabelList, LabelRequest, LabeledTransaction,
+            ListLabelsQuery, TransactionLabel,
+        },
+    },
+    state::AppState,
+};
+
+/// Default page size for label listings
+const DEFAULT_LIMIT: i64 = 20;
+/// Largest page size a client may request
+const MAX_LIMIT: i64 = 100;
+
+/// List the account's transaction labels
+#[utoipa::path(
+    get,
+    path = "/v1/labels",
+    tags = ["Labels"],
+    summary = "List labels",
+    description = "Retrieve a page of the ground-truth labels of the calling account's transactions, most recently changed first. A transaction is labeled `fraud`, `legit`, or `friendly_fraud` when an outcome is reported for it or a reviewer resolves its case, and relabeled when the outcome changes; chargebacks filed under a reason code other than fraud are friendly fraud. Labels set by hand are kept whatever is reported later. Requires the `reports:read` scope.",
+    params(ListLabelsQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of labels", body = LabelList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_labels(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ListLabelsQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Json<LabelList>> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let (labels, total) = state
+        .labels
+        .list_labels(auth.tenant(), &query, limit, offset)
+        .await?;
+
+    let pagination = Pagination::new(limit, offset, total);
+    Ok(Json(LabelList {
+        labels,
+        links: pagination.links(&listing_base("/v1/labels", raw_query.as_deref())),
+        pagination,
+    }))
+}
+
+/// Export a labeled dataset
+#[utoipa::path(
+    get,
+    path = "/v1/labels/export",
+    tags = ["Labels"],
+    summary = "Export labeled transactions",
+    description = "Download the calling account's labeled transactions, oldest scored first, as CSV or newline-delimited JSON for model training. Each row carries the transaction's scoring, the codes of the rules that raised its score, and its label; join it with the feature export on `transaction_id` for the model inputs. Requires the `reports:read` scope.",
+    params(LabelExportQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The labeled transactions", content(
+            (String = "text/csv"),
+            (LabeledTransaction = "application/x-ndjson")
+        )),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn export_labels(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<LabelExportQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let body = state.labels.export(auth.tenant(), &query).await?;
+    let (content_type, extension) = match query.format {
+        LabelExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        LabelExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+    };
+    let disposition = format!("attachment; filename=\"labels.{extension}\"");
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    ))
+}
+
+/// Fetch the label of a transaction
+#[utoipa::path(
+    get,
+    path = "/v1/labels/{transaction_id}",
+    tags = ["Labels"],
+    summary = "Get transaction label",
+    description = "Retrieve the ground-truth label of one of the calling account's transactions. Requires the `reports:read` scope.",
+    params(("transaction_id" = Uuid, Path, description = "Unique identifier for the transaction")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The transaction's label", body = TransactionLabel),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Transaction not found or not labeled", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_label(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(transaction_id): Path<Uuid>,
+) -> ApiResult<Json<TransactionLabel>> {
+    let label = state
+        .labels
+        .get_label(auth.tenant(), transaction_id)
+        .await?;
+    Ok(Json(label))
+}
+
+/// Label a transaction by hand
+#[utoipa::path(
+    put,
+    path = "/v1/labels/{transaction_id}",
+    tags = ["Labels"],
+    summary = "Set transaction label",
+    description = "Label one of the calling account's transactions by hand, replacing any label it has. A manual label is kept when outcomes are reported for the transaction later, so it can correct what was reported or label transactions nothing was reported for, including as `unknown` to keep them out of training. Requires the `reports:write` scope.",
+    params(("transaction_id" = Uuid, Path, description = "Unique identifier for the transaction")),
+    request_body = LabelRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Label set", body = TransactionLabel),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Transaction not found", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn set_label(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(transaction_id): Path<Uuid>,
+    Json(request): Json<LabelRequest>,
+) -> ApiResult<Json<TransactionLabel>> {
+    request.validate().map_err(ApiError::Validation)?;
+    let label = state
+        .labels
+        .set_label(auth.tenant(), transaction_id, &request)
+        .await?;
+    Ok(Json(label))
+}
@@ -0,0 +1,618 @@
+//! List endpoints
+
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use super::{
+    ApiError, ApiResult,
+    users::{is_ndjson, parse_ndjson},
+};
+use crate::{
+    auth::AuthContext,
+    models::{
+        common::Pagination,
+        list::{
+            AsnListEntries, AsnListEntry, AsnListEntryRequest, AutoBlockList, BinRangeImport,
+            BinRangeList, CountryListEntries, CountryListEntry, CountryListEntryRequest,
+            DeleteListEntryQuery, ImportBinRangesQuery, ListAutoBlocksQuery, ListBinRangesQuery,
+            ListEntriesQuery, ListEntry, ListEntryList, ListEntryRequest, ListExportFormat,
+            ListExportQuery, ListImport, ListType,
+        },
+    },
+    services::list_service::parse_entries_csv,
+    state::AppState,
+};
+
+/// Default page size for BIN table listings
+const DEFAULT_BIN_LIMIT: i64 = 100;
+/// Largest page size a client may request of the BIN table
+const MAX_BIN_LIMIT: i64 = 1000;
+/// Default page size for automatic block listings
+const DEFAULT_AUTO_BLOCK_LIMIT: i64 = 100;
+/// Largest page size a client may request of the automatic blocks
+const MAX_AUTO_BLOCK_LIMIT: i64 = 1000;
+/// Default page size for list entry listings
+const DEFAULT_ENTRY_LIMIT: i64 = 100;
+/// Largest page size a client may request of a list's entries
+const MAX_ENTRY_LIMIT: i64 = 1000;
+
+/// List the account's ASN list
+#[utoipa::path(
+    get,
+    path = "/v1/lists/asn/entries",
+    tags = ["Lists"],
+    summary = "List ASN entries",
+    description = "Retrieve every network on the calling account's ASN list, by ascending AS number. Requires the `rules:admin` scope.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Listed networks", body = AsnListEntries),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_asn_entries(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<AsnListEntries>> {
+    Ok(Json(state.lists.asn_entries(auth.tenant()).await?))
+}
+
+/// Add a network to the account's ASN list
+#[utoipa::path(
+    post,
+    path = "/v1/lists/asn/entries",
+    tags = ["Lists"],
+    summary = "Set ASN entry",
+    description = "List a network by its autonomous system number, replacing any existing entry for it. Transactions from a `block` network receive the `BLOCKED_ASN` factor and are rejected whatever the account's disposition policy, except on sandbox keys. Transactions from a `boost` network receive the `LISTED_ASN` factor with the entry's score. Networks are resolved only when a GeoIP ASN database is configured. Requires the `rules:admin` scope.",
+    request_body = AsnListEntryRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The listed network", body = AsnListEntry),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn set_asn_entry(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(request): Json<AsnListEntryRequest>,
+) -> ApiResult<Json<AsnListEntry>> {
+    Ok(Json(
+        state.lists.set_asn_entry(auth.tenant(), &request).await?,
+    ))
+}
+
+/// Remove a network from the account's ASN list
+#[utoipa::path(
+    delete,
+    path = "/v1/lists/asn/entries/{asn}",
+    tags = ["Lists"],
+    summary = "Delete ASN entry",
+    description = "Remove a network from the calling account's ASN list, so its transactions are scored on their own merits again. Requires the `rules:admin` scope.",
+    params(("asn" = i64, Path, description = "Autonomous system number")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Entry removed"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Network not listed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn delete_asn_entry(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(asn): Path<i64>,
+) -> ApiResult<StatusCode> {
+    state.lists.delete_asn_entry(auth.tenant(), asn).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List the account's country list
+#[utoipa::path(
+    get,
+    path = "/v1/lists/country/entries",
+    tags = ["Lists"],
+    summary = "List country entries",
+    description = "Retrieve every country on the calling account's country list, in alphabetical order of country code. Requires the `rules:admin` scope.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Listed countries", body = CountryListEntries),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_country_entries(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<CountryListEntries>> {
+    Ok(Json(state.lists.country_entries(auth.tenant()).await?))
+}
+
+/// Add a country to the account's country list
+#[utoipa::path(
+    post,
+    path = "/v1/lists/country/entries",
+    tags = ["Lists"],
+    summary = "Set country entry",
+    description = "List a country by its ISO 3166-1 alpha-2 code, replacing any existing entry for it. A listed country applies wherever it turns up in a transaction: as the billing, shipping, or card country, or as the country of the IP address. Transactions involving a `block` country receive the `BLOCKED_COUNTRY` factor and are rejected whatever the account's disposition policy, except on sandbox keys. Transactions involving a `review` country receive the `REVIEW_COUNTRY` factor and are held for review where they would otherwise be accepted. Transactions involving a `boost` country receive the `HIGH_RISK_COUNTRY` factor with the highest score among their listed countries. A billing country that differs from the IP address country weighs more when either is listed. IP addresses are located only when a GeoIP database is configured. Requires the `rules:admin` scope.",
+    request_body = CountryListEntryRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The listed country", body = CountryListEntry),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn set_country_entry(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(request): Json<CountryListEntryRequest>,
+) -> ApiResult<Json<CountryListEntry>> {
+    Ok(Json(
+        state
+            .lists
+            .set_country_entry(auth.tenant(), &request)
+            .await?,
+    ))
+}
+
+/// Remove a country from the account's country list
+#[utoipa::path(
+    delete,
+    path = "/v1/lists/country/entries/{country}",
+    tags = ["Lists"],
+    summary = "Delete country entry",
+    description = "Remove a country from the calling account's country list, so transactions involving it are scored on their own merits again. Requires the `rules:admin` scope.",
+    params(("country" = String, Path, description = "ISO 3166-1 alpha-2 country code")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Entry removed"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Country not listed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn delete_country_entry(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(country): Path<String>,
+) -> ApiResult<StatusCode> {
+    state
+        .lists
+        .delete_country_entry(auth.tenant(), &country)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List the account's BIN table
+#[utoipa::path(
+    get,
+    path = "/v1/lists/bin/entries",
+    tags = ["Lists"],
+    summary = "List BIN ranges",
+    description = "Retrieve a page of the calling account's BIN table, in ascending order of prefix. Requires the `rules:admin` scope.",
+    params(ListBinRangesQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of BIN ranges", body = BinRangeList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_bin_ranges(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ListBinRangesQuery>,
+) -> ApiResult<Json<BinRangeList>> {
+    let limit = query.limit.unwrap_or(DEFAULT_BIN_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_BIN_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_BIN_LIMIT}"
+        )));
+    }
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let (ranges, total) = state.lists.bin_ranges(auth.tenant(), limit, offset).await?;
+    let pagination = Pagination::new(limit, offset, total);
+    Ok(Json(BinRangeList {
+        ranges,
+        links: pagination.links("/v1/lists/bin/entries"),
+        pagination,
+    }))
+}
+
+/// Import ranges into the account's BIN table
+#[utoipa::path(
+    post,
+    path = "/v1/lists/bin/import",
+    tags = ["Lists"],
+    summary = "Import BIN ranges",
+    description = "Import a BIN table, such as one licensed from a BIN data provider, as CSV. The first line is a header naming the columns, in any order: `prefix`, the 4 to 8 leading digits of the card numbers in a range, and optionally `brand`, `card_type` (`credit`, `debit`, or `charge`), `country` (ISO 3166-1 alpha-2), `bank_name`, `is_prepaid`, `is_business`, and `is_virtual`. Blank lines and lines starting with `#` are skipped. Imported ranges replace existing ones with the same prefix; with `replace=true`, ranges the import does not list are removed. A table with a malformed line is rejected whole, naming the line. A card takes its brand, type, issuing country and bank, and whether it is prepaid, a business card, or virtual from the range with the longest prefix of its issuer ID number, in the account's table or else in the starter table of published test cards bundled with the service. Transactions with prepaid cards receive the `PREPAID_CARD` factor, with virtual cards the `VIRTUAL_CARD` factor, and with business cards paid from a free email address the `BUSINESS_CARD_FREE_EMAIL` factor; a card issued outside the billing country receives `CARD_COUNTRY_MISMATCH` even without `credit_card.country`. Requires the `rules:admin` scope.",
+    params(ImportBinRangesQuery),
+    request_body(
+        description = "BIN table as CSV",
+        content = String,
+        content_type = "text/csv"
+    ),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Table imported", body = BinRangeImport),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "A line of the table is malformed, or the table is empty", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn import_bin_ranges(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ImportBinRangesQuery>,
+    body: Body,
+) -> ApiResult<Json<BinRangeImport>> {
+    let bytes = axum::body::to_bytes(body, state.config.server.max_request_size)
+        .await
+        .map_err(|_| ApiError::BadRequest("Request body is too large".to_string()))?;
+    let csv = std::str::from_utf8(&bytes)
+        .map_err(|_| ApiError::BadRequest("Request body must be UTF-8 text".to_string()))?;
+    Ok(Json(
+        state
+            .lists
+            .import_bin_ranges(auth.tenant(), csv, query.replace)
+            .await?,
+    ))
+}
+
+/// Remove a range from the account's BIN table
+#[utoipa::path(
+    delete,
+    path = "/v1/lists/bin/entries/{prefix}",
+    tags = ["Lists"],
+    summary = "Delete BIN range",
+    description = "Remove a range from the calling account's BIN table, so its cards are described by a shorter range of the table, or by the bundled starter table, again. Requires the `rules:admin` scope.",
+    params(("prefix" = String, Path, description = "Leading digits of the card numbers in the range")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Range removed"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Range not in the table", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn delete_bin_range(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(prefix): Path<String>,
+) -> ApiResult<StatusCode> {
+    state.lists.delete_bin_range(auth.tenant(), &prefix).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List the entries of one of the account's lists
+#[utoipa::path(
+    get,
+    path = "/v1/lists/{list_type}/entries",
+    tags = ["Lists"],
+    summary = "List entries",
+    description = "Retrieve a page of the entries still in force on the calling account's blocklist, allowlist, or watchlist, newest first, optionally only those naming one kind of entity. Email addresses and card tokens are listed by their SHA-256 hashes. Requires the `rules:admin` scope.",
+    params(
+        ("list_type" = ListType, Path, description = "List to read"),
+        ListEntriesQuery
+    ),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of list entries", body = ListEntryList),
+        (status = 400, description = "Invalid list type or query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_entries(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(list_type): Path<ListType>,
+    Query(query): Query<ListEntriesQuery>,
+) -> ApiResult<Json<ListEntryList>> {
+    let limit = query.limit.unwrap_or(DEFAULT_ENTRY_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_ENTRY_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_ENTRY_LIMIT}"
+        )));
+    }
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let (entries, total) = state
+        .lists
+        .entries(auth.tenant(), list_type, query.entity_type, limit, offset)
+        .await?;
+    let mut base = format!("/v1/lists/{}/entries", list_type.name());
+    if let Some(entity_type) = query.entity_type {
+        base.push_str(&format!("?entity_type={}", entity_type.name()));
+    }
+    let pagination = Pagination::new(limit, offset, total);
+    Ok(Json(ListEntryList {
+        entries,
+        links: pagination.links(&base),
+        pagination,
+    }))
+}
+
+/// Add an entity to one of the account's lists
+#[utoipa::path(
+    post,
+    path = "/v1/lists/{list_type}/entries",
+    tags = ["Lists"],
+    summary = "Set list entry",
+    description = "Put an entity on the calling account's blocklist, allowlist, or watchlist, replacing any existing entry for it on that list. An entity is an email address, an email domain, an IP address, a CIDR range, a device or user ID, a card token, a user, or an ISO 3166-1 alpha-2 country code. Email addresses and card tokens may be given in the clear or as SHA-256 hashes and are stored hashed; other values are stored in normal form. An entry with `expires_at` stops applying at that time. Transactions naming a blocklisted entity receive the `BLOCKLISTED` factor and are rejected whatever the account's disposition policy, except on sandbox keys. Transactions naming a watchlisted entity receive the `WATCHLISTED` factor, and those naming an allowlisted entity the `ALLOWLISTED` factor, which scales the rest of their score down; the entries matched are described in the factor's metadata. A transaction's IP address matches entries for the address itself and for any range containing it, and its device and user match only once they are known to fusegu. Requires the `rules:admin` scope.",
+    params(("list_type" = ListType, Path, description = "List to add to")),
+    request_body = ListEntryRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The listed entity", body = ListEntry),
+        (status = 400, description = "Invalid list type or malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn set_list_entry(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(list_type): Path<ListType>,
+    Json(request): Json<ListEntryRequest>,
+) -> ApiResult<Json<ListEntry>> {
+    Ok(Json(
+        state
+            .lists
+            .set_entry(auth.tenant(), list_type, &request)
+            .await?,
+    ))
+}
+
+/// Remove an entity from one of the account's lists
+#[utoipa::path(
+    delete,
+    path = "/v1/lists/{list_type}/entries",
+    tags = ["Lists"],
+    summary = "Delete list entry",
+    description = "Remove an entity from the calling account's blocklist, allowlist, or watchlist. The entity is named by its type and value, in any form accepted when listing it. Requires the `rules:admin` scope.",
+    params(
+        ("list_type" = ListType, Path, description = "List to remove from"),
+        DeleteListEntryQuery
+    ),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Entry removed"),
+        (status = 400, description = "Invalid list type or query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Entity not on the list", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Value is not valid for the entity type", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn delete_list_entry(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(list_type): Path<ListType>,
+    Query(query): Query<DeleteListEntryQuery>,
+) -> ApiResult<StatusCode> {
+    state
+        .lists
+        .delete_entry(auth.tenant(), list_type, query.entity_type, &query.value)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Import entries into one of the account's lists
+#[utoipa::path(
+    post,
+    path = "/v1/lists/{list_type}/import",
+    tags = ["Lists"],
+    summary = "Import list entries",
+    description = "Import entries into the calling account's blocklist, allowlist, or watchlist in bulk, such as a list exported from another fraud vendor. Send CSV with `Content-Type: text/csv`, a JSON array of entries, or one entry per line with `Content-Type: application/x-ndjson`. The first line of CSV is a header naming the columns, in any order: `entity_type` and `value`, and optionally `reason` and `expires_at`; blank fields are omitted. Entries take the same form as when set one at a time and replace any existing entry for the same entity. The entries are imported in the background; poll the import at the `Location` header for progress, or wait for the `list_import.completed` event. An entry that is malformed or fails validation is counted as failed without stopping the import, and the first failures are listed with their position among the submitted entries, not counting a CSV header. Requires the `rules:admin` scope.",
+    params(("list_type" = ListType, Path, description = "List to import into")),
+    request_body(
+        description = "Entries to import, as CSV, a JSON array, or newline-delimited JSON",
+        content(
+            (String = "text/csv"),
+            (Vec<ListEntryRequest> = "application/json"),
+            (ListEntryRequest = "application/x-ndjson")
+        )
+    ),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 202, description = "Import queued", body = ListImport,
+            headers(("Location" = String, description = "URI of the import"))
+        ),
+        (status = 400, description = "Invalid list type or malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Import is empty or too large", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn import_list_entries(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(list_type): Path<ListType>,
+    headers: HeaderMap,
+    body: Body,
+) -> ApiResult<impl IntoResponse> {
+    let bytes = axum::body::to_bytes(body, state.config.server.max_request_size)
+        .await
+        .map_err(|_| ApiError::BadRequest("Request body is too large".to_string()))?;
+    let entries = if is_csv(&headers) {
+        let csv = std::str::from_utf8(&bytes)
+            .map_err(|_| ApiError::BadRequest("Request body must be UTF-8 text".to_string()))?;
+        parse_entries_csv(csv).map_err(ApiError::BadRequest)?
+    } else if is_ndjson(&headers) {
+        parse_ndjson(&bytes)?
+    } else {
+        serde_json::from_slice::<Vec<serde_json::Value>>(&bytes).map_err(|e| {
+            ApiError::BadRequest(format!("Request body must be a JSON array of entries: {e}"))
+        })?
+    };
+    let max = state.config.imports.max_list_entries;
+    if !(1..=max).contains(&entries.len()) {
+        return Err(ApiError::Validation(format!(
+            "An import must contain between 1 and {max} entries"
+        )));
+    }
+
+    let import = state
+        .lists
+        .import_entries(auth.tenant(), list_type, &entries)
+        .await?;
+    let location = format!("/v1/lists/imports/{}", import.id);
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(import),
+    ))
+}
+
+/// Fetch the progress of a list import
+#[utoipa::path(
+    get,
+    path = "/v1/lists/imports/{import_id}",
+    tags = ["Lists"],
+    summary = "Get list import",
+    description = "Fetch the progress of a list import. Counts are updated as each chunk of entries is committed, so `processed_entries` out of `total_entries` gives the progress of an import being processed, and `errors` reports why entries failed. Requires the `rules:admin` scope.",
+    params(("import_id" = Uuid, Path, description = "Unique identifier for the import")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "List import", body = ListImport),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Import not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_list_import(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(import_id): Path<Uuid>,
+) -> ApiResult<Json<ListImport>> {
+    Ok(Json(
+        state.lists.get_import(auth.tenant(), import_id).await?,
+    ))
+}
+
+/// Download the entries of one of the account's lists
+#[utoipa::path(
+    get,
+    path = "/v1/lists/{list_type}/export",
+    tags = ["Lists"],
+    summary = "Export list entries",
+    description = "Download every entry still in force on the calling account's blocklist, allowlist, or watchlist, oldest first, as CSV or newline-delimited JSON in the form accepted by the import endpoint, optionally only those naming one kind of entity. Email addresses and card tokens are exported by their SHA-256 hashes. Requires the `rules:admin` scope.",
+    params(
+        ("list_type" = ListType, Path, description = "List to export"),
+        ListExportQuery
+    ),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The list's entries", content(
+            (String = "text/csv"),
+            (ListEntryRequest = "application/x-ndjson")
+        )),
+        (status = 400, description = "Invalid list type or query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn export_list_entries(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(list_type): Path<ListType>,
+    Query(query): Query<ListExportQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let body = state
+        .lists
+        .export_entries(auth.tenant(), list_type, &query)
+        .await?;
+    let (content_type, extension) = match query.format {
+        ListExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ListExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+    };
+    let disposition = format!("attachment; filename=\"{}.{extension}\"", list_type.name());
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    ))
+}
+
+/// List the entities the account blocked automatically
+#[utoipa::path(
+    get,
+    path = "/v1/lists/auto-blocks",
+    tags = ["Lists"],
+    summary = "List automatic blocks",
+    description = "Retrieve a page of the IP addresses, devices, and cards put on the calling account's blocklist automatically after repeatedly breaching critical velocity rules, newest first, including blocks that have since expired or been removed. Each block names the factors and transaction that triggered it and how many breaching transactions the entity had within the window. Automatic blocks are enabled, and their duration set, through `PATCH /v1/account`. Requires the `rules:admin` scope.",
+    params(ListAutoBlocksQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of automatic blocks", body = AutoBlockList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_auto_blocks(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ListAutoBlocksQuery>,
+) -> ApiResult<Json<AutoBlockList>> {
+    let limit = query.limit.unwrap_or(DEFAULT_AUTO_BLOCK_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_AUTO_BLOCK_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_AUTO_BLOCK_LIMIT}"
+        )));
+    }
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let (auto_blocks, total) = state
+        .lists
+        .auto_blocks(auth.tenant(), limit, offset)
+        .await?;
+    let pagination = Pagination::new(limit, offset, total);
+    Ok(Json(AutoBlockList {
+        auto_blocks,
+        links: pagination.links("/v1/lists/auto-blocks"),
+        pagination,
+    }))
+}
+
+/// Whether the request body is CSV
+fn is_csv(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("text/csv"))
+}
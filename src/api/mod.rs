@@ -1,7 +1,27 @@
 //! API endpoints and handlers
 
+pub mod account;
+pub mod admin;
+pub mod analytics;
+pub mod cases;
+pub mod dead_letters;
+pub mod devices;
+pub mod emails;
 pub mod errors;
 pub mod health;
+pub mod ingest;
+pub mod ip;
+pub mod jobs;
+pub mod labels;
+pub mod lists;
+pub mod notifications;
+pub mod organizations;
+pub mod reports;
+pub mod rules;
+pub mod screening;
+pub mod transactions;
+pub mod users;
+pub mod webhooks;
 
 // Re-export common types
 pub use errors::{ApiError, ApiResult};
@@ -0,0 +1,162 @@
+//! Notification channel endpoints
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+use super::{ApiError, ApiResult};
+use crate::{
+    auth::AuthContext,
+    models::notification::{
+        NotificationChannel, NotificationChannelList, NotificationChannelRequest,
+    },
+    state::AppState,
+};
+
+/// List the account's notification channels
+#[utoipa::path(
+    get,
+    path = "/v1/notification-channels",
+    tags = ["Notifications"],
+    summary = "List notification channels",
+    description = "Retrieve the email addresses and Slack channels the calling account's high-severity events are announced on, oldest first. Requires the `account:read` scope.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The account's channels", body = NotificationChannelList),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_notification_channels(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<NotificationChannelList>> {
+    let channels = state.notifications.list_channels(auth.tenant()).await?;
+    Ok(Json(NotificationChannelList { channels }))
+}
+
+/// Register a notification channel
+#[utoipa::path(
+    post,
+    path = "/v1/notification-channels",
+    tags = ["Notifications"],
+    summary = "Create notification channel",
+    description = "Register an email address or Slack incoming webhook to announce high-severity events on: anomaly alerts (`analytics.anomaly_detected`), the monthly quota running out (`account.quota_exhausted`), and review cases passing their queue's SLA (`case.sla_breached`), or only the listed ones. Each event is announced once, as a short plain-text message; use webhooks for the full payloads. Requires the `account:write` scope.",
+    request_body = NotificationChannelRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "The channel", body = NotificationChannel),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "A channel with this name already exists", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn create_notification_channel(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(request): Json<NotificationChannelRequest>,
+) -> ApiResult<(StatusCode, Json<NotificationChannel>)> {
+    request.validate().map_err(ApiError::Validation)?;
+    let channel = state
+        .notifications
+        .create_channel(auth.tenant(), &request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(channel)))
+}
+
+/// Fetch a notification channel
+#[utoipa::path(
+    get,
+    path = "/v1/notification-channels/{channel_id}",
+    tags = ["Notifications"],
+    summary = "Get notification channel by ID",
+    description = "Retrieve a notification channel with when an event was last announced on it. Requires the `account:read` scope.",
+    params(("channel_id" = Uuid, Path, description = "Unique identifier for the channel")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The channel", body = NotificationChannel),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Channel not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_notification_channel(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(channel_id): Path<Uuid>,
+) -> ApiResult<Json<NotificationChannel>> {
+    Ok(Json(
+        state
+            .notifications
+            .get_channel(auth.tenant(), channel_id)
+            .await?,
+    ))
+}
+
+/// Replace a notification channel
+#[utoipa::path(
+    put,
+    path = "/v1/notification-channels/{channel_id}",
+    tags = ["Notifications"],
+    summary = "Update notification channel",
+    description = "Replace a notification channel's name, kind, target, event types, and whether events are announced on it. Requires the `account:write` scope.",
+    params(("channel_id" = Uuid, Path, description = "Unique identifier for the channel")),
+    request_body = NotificationChannelRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The updated channel", body = NotificationChannel),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Channel not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "Another channel has this name", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn update_notification_channel(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(channel_id): Path<Uuid>,
+    Json(request): Json<NotificationChannelRequest>,
+) -> ApiResult<Json<NotificationChannel>> {
+    request.validate().map_err(ApiError::Validation)?;
+    Ok(Json(
+        state
+            .notifications
+            .update_channel(auth.tenant(), channel_id, &request)
+            .await?,
+    ))
+}
+
+/// Delete a notification channel
+#[utoipa::path(
+    delete,
+    path = "/v1/notification-channels/{channel_id}",
+    tags = ["Notifications"],
+    summary = "Delete notification channel",
+    description = "Stop announcing events on a channel and forget it. Requires the `account:write` scope.",
+    params(("channel_id" = Uuid, Path, description = "Unique identifier for the channel")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Channel deleted"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Channel not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn delete_notification_channel(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(channel_id): Path<Uuid>,
+) -> ApiResult<StatusCode> {
+    state
+        .notifications
+        .delete_channel(auth.tenant(), channel_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,279 @@
+//! Organization and member management endpoints
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+use super::ApiResult;
+use crate::{
+    auth::AuthContext,
+    models::organization::{
+        CreateOrganization, InvitationList, MemberInvitation, MemberUpdate, Organization,
+        OrganizationMember, OrganizationUpdate,
+    },
+    state::AppState,
+};
+
+/// Fetch the calling account's organization
+#[utoipa::path(
+    get,
+    path = "/v1/organization",
+    tags = ["Organizations"],
+    summary = "Get organization",
+    description = "Retrieve the organization the calling account belongs to, with its members, pending invitations, and combined usage of the current billing cycles.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Organization details", body = Organization),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "The account does not belong to an organization", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_organization(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<Organization>> {
+    Ok(Json(
+        state
+            .organizations
+            .get_organization(auth.account_id)
+            .await?,
+    ))
+}
+
+/// Create an organization
+#[utoipa::path(
+    post,
+    path = "/v1/organization",
+    tags = ["Organizations"],
+    summary = "Create organization",
+    description = "Create an organization with the calling account as its owner and billing account. The owner may look up users across all member accounts.",
+    request_body = CreateOrganization,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Organization created", body = Organization),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The account already belongs to an organization", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn create_organization(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(request): Json<CreateOrganization>,
+) -> ApiResult<(StatusCode, Json<Organization>)> {
+    let organization = state
+        .organizations
+        .create_organization(auth.account_id, auth.sandbox, &request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(organization)))
+}
+
+/// Update the calling account's organization
+#[utoipa::path(
+    patch,
+    path = "/v1/organization",
+    tags = ["Organizations"],
+    summary = "Update organization",
+    description = "Rename the organization, move its billing to another active member, or have members share their blocklist entries. Shared entries apply to every active member's scoring and blocklist listing, naming the member that added them; changes reach cached lookups within five minutes. Owners and admins may rename and change sharing; only the owner may change the billing account.",
+    request_body = OrganizationUpdate,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated organization", body = Organization),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the account's role does not allow the change", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "The account does not belong to an organization", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn update_organization(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(update): Json<OrganizationUpdate>,
+) -> ApiResult<Json<Organization>> {
+    Ok(Json(
+        state
+            .organizations
+            .update_organization(auth.account_id, &update)
+            .await?,
+    ))
+}
+
+/// Invite an account to the organization
+#[utoipa::path(
+    post,
+    path = "/v1/organization/members",
+    tags = ["Organizations"],
+    summary = "Invite member",
+    description = "Invite an account by its public identifier. The account is notified with an `organization.member_invited` event and joins once it accepts with one of its own API keys. Only the owner may invite admins.",
+    request_body = MemberInvitation,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Account invited", body = OrganizationMember),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the account's role does not allow the change", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "The account does not belong to an organization", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The account is already a member or invited", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn invite_member(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(invitation): Json<MemberInvitation>,
+) -> ApiResult<(StatusCode, Json<OrganizationMember>)> {
+    let member = state
+        .organizations
+        .invite_member(auth.account_id, &invitation)
+        .await?;
+    Ok((StatusCode::CREATED, Json(member)))
+}
+
+/// Change a member's role or permissions
+#[utoipa::path(
+    patch,
+    path = "/v1/organization/members/{account_id}",
+    tags = ["Organizations"],
+    summary = "Update member",
+    description = "Change a member's role or whether it may look up users of the other members. Only the owner may grant, revoke, or change admins; the owner role cannot be transferred.",
+    params(("account_id" = String, Path, description = "Public identifier of the member account")),
+    request_body = MemberUpdate,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated member", body = OrganizationMember),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the account's role does not allow the change", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Member not found", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn update_member(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(account_id): Path<String>,
+    Json(update): Json<MemberUpdate>,
+) -> ApiResult<Json<OrganizationMember>> {
+    Ok(Json(
+        state
+            .organizations
+            .update_member(auth.account_id, &account_id, &update)
+            .await?,
+    ))
+}
+
+/// Remove a member or withdraw an invitation
+#[utoipa::path(
+    delete,
+    path = "/v1/organization/members/{account_id}",
+    tags = ["Organizations"],
+    summary = "Remove member",
+    description = "Remove a member or withdraw a pending invitation. Any member except the owner may remove itself to leave the organization. The billing account cannot be removed until billing moves to another member.",
+    params(("account_id" = String, Path, description = "Public identifier of the member account")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Member removed"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the account's role does not allow the change", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Member not found", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "The owner or billing account cannot be removed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn remove_member(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(account_id): Path<String>,
+) -> ApiResult<StatusCode> {
+    state
+        .organizations
+        .remove_member(auth.account_id, &account_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List invitations of the calling account
+#[utoipa::path(
+    get,
+    path = "/v1/organization/invitations",
+    tags = ["Organizations"],
+    summary = "List invitations",
+    description = "List organizations that have invited the calling account and are waiting for it to accept.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Pending invitations", body = InvitationList),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_invitations(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<InvitationList>> {
+    let invitations = state.organizations.invitations(auth.account_id).await?;
+    Ok(Json(InvitationList { invitations }))
+}
+
+/// Accept an invitation
+#[utoipa::path(
+    post,
+    path = "/v1/organization/invitations/{organization_id}/accept",
+    tags = ["Organizations"],
+    summary = "Accept invitation",
+    description = "Join the organization that invited the calling account. An account belongs to at most one organization; the organization's billing account is notified with an `organization.member_joined` event.",
+    params(("organization_id" = Uuid, Path, description = "Inviting organization")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Joined organization", body = Organization),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "No pending invitation from the organization", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The account already belongs to an organization", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(organization_id): Path<Uuid>,
+) -> ApiResult<Json<Organization>> {
+    Ok(Json(
+        state
+            .organizations
+            .accept_invitation(auth.account_id, organization_id)
+            .await?,
+    ))
+}
+
+/// Decline an invitation
+#[utoipa::path(
+    delete,
+    path = "/v1/organization/invitations/{organization_id}",
+    tags = ["Organizations"],
+    summary = "Decline invitation",
+    description = "Decline an invitation; the organization can invite the account again later.",
+    params(("organization_id" = Uuid, Path, description = "Inviting organization")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Invitation declined"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "No pending invitation from the organization", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn decline_invitation(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(organization_id): Path<Uuid>,
+) -> ApiResult<StatusCode> {
+    state
+        .organizations
+        .decline_invitation(auth.account_id, organization_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,155 @@
+//! Generated report and transaction outcome endpoints
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use super::{ApiError, ApiResult};
+use crate::{
+    auth::AuthContext,
+    models::{
+        common::Pagination,
+        outcome::{OutcomeReportRequest, TransactionOutcome},
+        report::{GetReportQuery, ListReportsQuery, Report, ReportFormat, ReportList},
+    },
+    state::AppState,
+};
+
+/// Default page size for report listings
+const DEFAULT_LIMIT: i64 = 20;
+/// Largest page size a client may request
+const MAX_LIMIT: i64 = 100;
+
+/// List generated reports
+#[utoipa::path(
+    get,
+    path = "/v1/reports",
+    tags = ["Reports"],
+    summary = "List reports",
+    description = "Daily and weekly fraud summaries generated for the calling account, most recent period first. Each report covers volumes, risk and disposition distributions, the most frequently fired rules, and the riskiest users of its period.",
+    params(ListReportsQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of reports", body = ReportList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_reports(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ListReportsQuery>,
+) -> ApiResult<Json<ReportList>> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let (reports, total) = state
+        .reports
+        .list_reports(auth.tenant(), query.frequency, limit, offset)
+        .await?;
+
+    let pagination = Pagination::new(limit, offset, total);
+    Ok(Json(ReportList {
+        reports,
+        links: pagination.links("/v1/reports"),
+        pagination,
+    }))
+}
+
+/// Fetch a report by ID
+#[utoipa::path(
+    get,
+    path = "/v1/reports/{report_id}",
+    tags = ["Reports"],
+    summary = "Get report by ID",
+    description = "Retrieve one of the calling account's reports as JSON, or with `format=csv` as a CSV download with one `section,key,metric,value` row per figure.",
+    params(
+        ("report_id" = Uuid, Path, description = "Unique identifier for the report"),
+        GetReportQuery
+    ),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The report", content(
+            (Report = "application/json"),
+            (String = "text/csv")
+        )),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Report not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_report(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(report_id): Path<Uuid>,
+    Query(query): Query<GetReportQuery>,
+) -> ApiResult<Response> {
+    let report = state.reports.get_report(auth.tenant(), report_id).await?;
+
+    Ok(match query.format.unwrap_or_default() {
+        ReportFormat::Json => Json(report).into_response(),
+        ReportFormat::Csv => {
+            let filename = format!(
+                "fusegu-{}-report-{}.csv",
+                report.frequency.as_str(),
+                report.period_start.format("%Y-%m-%d")
+            );
+            (
+                [
+                    (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"{filename}\""),
+                    ),
+                ],
+                report.to_csv(),
+            )
+                .into_response()
+        },
+    })
+}
+
+/// Report the outcome of a transaction
+#[utoipa::path(
+    post,
+    path = "/v1/reports",
+    tags = ["Reports"],
+    summary = "Report transaction outcome",
+    description = "Report what became of a scored transaction: a `chargeback`, with the processor's reason code, confirmed or `suspected_fraud`, `spam_or_abuse`, or `not_fraud` for a false positive. The report replaces any outcome on record, including one a reviewer's case decision or a processor webhook recorded, since the merchant knows best; reporting the outcome already on record is a conflict. A chargeback counts against the transaction's user, card, and device and the reputation of its IP address, and reporting another outcome instead withdraws it. The user's risk score is recalculated and a `transaction.reported` event feeds outcome analytics. Requires the `reports:write` scope.",
+    request_body = OutcomeReportRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Outcome recorded", body = TransactionOutcome),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Transaction not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The transaction already has this outcome on record", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn report_outcome(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(request): Json<OutcomeReportRequest>,
+) -> ApiResult<(StatusCode, Json<TransactionOutcome>)> {
+    request.validate().map_err(ApiError::Validation)?;
+    let outcome = state.outcomes.report(auth.tenant(), &request).await?;
+    Ok((StatusCode::CREATED, Json(outcome)))
+}
@@ -0,0 +1,88 @@
+//! Rule weight endpoints
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+use super::ApiResult;
+use crate::{
+    auth::AuthContext,
+    models::rule::{RuleSuggestionList, RuleVersion, RuleVersionList},
+    state::AppState,
+};
+
+/// List the account's rule weight suggestions
+#[utoipa::path(
+    get,
+    path = "/v1/rules/suggestions",
+    tags = ["Rules"],
+    summary = "List rule weight suggestions",
+    description = "Retrieve the weights suggested for the calling account's rules from the outcomes reported for its transactions. Suggestions are recomputed periodically from the transactions scored over the lookback window: a rule's precision, the share of the reported transactions it raised the score of that were reported as fraud or abuse, is compared with the share of all reported transactions that were, and the rule is suggested that ratio as its weight, between 0.25 and 2. Rules with too few reported transactions, and rules whose weight in force is already close, get no suggestion. Requires the `rules:admin` scope.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Pending suggestions", body = RuleSuggestionList),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_rule_suggestions(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<RuleSuggestionList>> {
+    let suggestions = state.rules.list_suggestions(auth.tenant()).await?;
+    Ok(Json(RuleSuggestionList { suggestions }))
+}
+
+/// Apply a rule weight suggestion
+#[utoipa::path(
+    post,
+    path = "/v1/rules/suggestions/{suggestion_id}/apply",
+    tags = ["Rules"],
+    summary = "Apply a rule weight suggestion",
+    description = "Create a new version of the calling account's rule weights, carrying over the weights of the version in force with the suggested rule reweighted. Transactions are scored under the new version from then on: the rule's score is scaled by its weight, though a rule that rejects transactions outright or sends them to review still does. Earlier versions stay on record. Requires the `rules:admin` scope.",
+    params(("suggestion_id" = Uuid, Path, description = "Unique identifier for the suggestion")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Rule version created", body = RuleVersion),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Suggestion not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "Suggestion already applied, or the rule weights changed meanwhile", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn apply_rule_suggestion(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(suggestion_id): Path<Uuid>,
+) -> ApiResult<(StatusCode, Json<RuleVersion>)> {
+    let version = state
+        .rules
+        .apply_suggestion(auth.tenant(), suggestion_id)
+        .await?;
+    Ok((StatusCode::CREATED, Json(version)))
+}
+
+/// List the versions of the account's rule weights
+#[utoipa::path(
+    get,
+    path = "/v1/rules/versions",
+    tags = ["Rules"],
+    summary = "List rule versions",
+    description = "Retrieve every version of the calling account's rule weights, newest first. The newest is in force; an account without versions scores with the built-in weights. Requires the `rules:admin` scope.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Rule versions", body = RuleVersionList),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_rule_versions(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<RuleVersionList>> {
+    let versions = state.rules.list_versions(auth.tenant()).await?;
+    Ok(Json(RuleVersionList { versions }))
+}
@@ -0,0 +1,45 @@
+//! Sanctions screening endpoints
+
+use axum::{Json, extract::State};
+
+use super::{ApiError, ApiResult};
+use crate::{
+    auth::AuthContext,
+    models::screening::{ScreeningRequest, ScreeningResult},
+    state::AppState,
+};
+
+/// Screen a name against sanctions lists
+#[utoipa::path(
+    post,
+    path = "/v1/screening",
+    tags = ["Screening"],
+    summary = "Screen name",
+    description = "Screen the name of a person or organization, optionally with its country, against the OFAC and EU sanctions lists the server has downloaded. Names are compared word by word, tolerating spelling variants, and matches are reported closest first with the listed party's programs and countries. Where both the screened party and a listed one have countries, a match also needs a country in common. Screening one name does not depend on the account's `sanctions_screening` setting, which only decides whether scored transactions are screened. Requires the `transactions:write` scope.",
+    request_body = ScreeningRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Listed parties matching the name, if any", body = ScreeningResult),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse),
+        (status = 503, description = "No sanctions list has been downloaded", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn screen(
+    State(state): State<AppState>,
+    _auth: AuthContext,
+    Json(request): Json<ScreeningRequest>,
+) -> ApiResult<Json<ScreeningResult>> {
+    request.validate().map_err(ApiError::Validation)?;
+    let result = state
+        .screening
+        .screen(request.name.trim(), request.country.as_deref())
+        .ok_or_else(|| {
+            ApiError::ServiceUnavailable(
+                "Sanctions screening is not available: no list has been downloaded".to_string(),
+            )
+        })?;
+    Ok(Json(result))
+}
@@ -0,0 +1,838 @@
+//! Transaction scoring and lookup endpoints
+
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, RawQuery, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use futures_util::{StreamExt, stream};
+use uuid::Uuid;
+
+use super::{ApiError, ApiResult};
+use crate::{
+    auth::AuthContext,
+    database::{Tenant, repositories::TransactionRecord},
+    metering::{Metered, Usage, middleware::quota_exceeded},
+    models::{
+        account::{DispositionPolicy, Feature},
+        case::{AppealRequest, ReviewCase},
+        common::{Cursor, Pagination},
+        dispute::DisputeList,
+        insights::TransactionInsights,
+        job::{BackgroundJob, BatchScoreJobRequest, BatchScoreTarget, JobKind, RescoreJobRequest},
+        transaction::{
+            BatchItemResult, BatchTransactionRequest, BatchTransactionResponse,
+            CreateTransactionQuery, Disposition, ListTransactionsQuery, ScoringMode,
+            ScoringRevision, StoredTransactionRequest, TransactionList, TransactionRequest,
+            TransactionResponse,
+        },
+    },
+    scoring::RuleWeights,
+    services::ServiceError,
+    state::AppState,
+};
+
+/// Default page size for transaction listings
+const DEFAULT_LIMIT: i64 = 20;
+/// Largest page size a client may request
+const MAX_LIMIT: i64 = 100;
+
+/// Score and store a transaction
+#[utoipa::path(
+    post,
+    path = "/v1/transactions",
+    tags = ["Transactions"],
+    summary = "Create and score a transaction",
+    description = "Submit a new transaction for fraud analysis and receive a risk assessment. The transaction, its user, device, and related entities are stored for cross-transaction analysis. The disposition follows the account's disposition policy, except that transactions from blocked devices, and from devices or IP addresses testing cards (`CARD_TESTING`), are always rejected. Refunds and cancellations name the purchase they undo in `event.original_transaction_id` and are linked to it when it was scored, otherwise they are stored with an `ORIGINAL_TRANSACTION_NOT_FOUND` warning; users with more than 30% of their purchases refunded raise `REFUND_ABUSE`. Sandbox keys store into a separate namespace and always receive the `test` disposition. Each request counts against the account's monthly quota, except from sandbox keys; once it is used up requests are refused with `quota_exceeded` until the billing cycle resets.\n\nWith `mode=async` the transaction is validated and queued, and the response is a `202` with a background job of the `score` kind; poll `GET /v1/jobs/{job_id}` for the result, or pass a `callback_url` (Pro plan and above) to have the finished job POSTed to it. Queued transactions count against the quota when they are accepted.",
+    params(CreateTransactionQuery),
+    request_body = TransactionRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Transaction created and scored", body = TransactionResponse,
+            headers(("Location" = String, description = "URI of the created transaction"))
+        ),
+        (status = 202, description = "Transaction queued for scoring (`mode=async`)", body = BackgroundJob,
+            headers(("Location" = String, description = "URI of the job"))
+        ),
+        (status = 400, description = "Invalid mode or callback URL", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the plan does not include callbacks", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse),
+        (status = 429, description = "Monthly quota used up", body = crate::api::errors::ErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::errors::ErrorResponse),
+        (status = 503, description = "Database overloaded; retry later", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn create_transaction(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    usage: Option<Extension<Usage>>,
+    Query(query): Query<CreateTransactionQuery>,
+    Json(request): Json<TransactionRequest>,
+) -> ApiResult<Response> {
+    query.validate().map_err(ApiError::BadRequest)?;
+    if query.callback_url.is_some() {
+        auth.require_feature(Feature::Webhooks)?;
+    }
+    request.validate().map_err(ApiError::Validation)?;
+    check_pool_saturation(&state)?;
+
+    if query.mode == Some(ScoringMode::Async) {
+        let job = state
+            .jobs
+            .enqueue(
+                auth.tenant(),
+                JobKind::Score,
+                &request,
+                query.callback_url.as_deref(),
+            )
+            .await?;
+        tracing::info!(
+            job_id = %job.id,
+            account_id = %auth.account_id,
+            "Transaction queued for scoring"
+        );
+        let location = format!("/v1/jobs/{}", job.id);
+        return Ok((
+            StatusCode::ACCEPTED,
+            [(header::LOCATION, location)],
+            Json(job),
+        )
+            .into_response());
+    }
+
+    let policy = state.accounts.disposition_policy(auth.tenant()).await?;
+    let weights = state.rules.weights(auth.tenant()).await?;
+    let record = score_transaction(&state, &auth, &policy, &weights, &request).await?;
+
+    let response = TransactionResponse {
+        queries_remaining: usage.map(|Extension(usage)| usage.remaining()),
+        ..TransactionResponse::from(record)
+    };
+    let location = format!("/v1/transactions/{}", response.id);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(response),
+    )
+        .into_response())
+}
+
+/// Score and store a batch of transactions
+#[utoipa::path(
+    post,
+    path = "/v1/transactions/batch",
+    tags = ["Transactions"],
+    summary = "Create and score a batch of transactions",
+    description = "Submit many transactions in one request, for example when importing an order file. Each is validated, scored, and stored exactly as by `POST /v1/transactions`, several at a time, and gets its own result: a transaction that fails does not affect the others, and results come back in submission order. Every valid transaction counts against the monthly quota; if the quota cannot cover all of them the whole batch is refused. Transactions that fail after validation are not counted. Available on the Pro plan and above.",
+    request_body = BatchTransactionRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Batch processed; see each result for its outcome", body = BatchTransactionResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the plan does not include batch scoring", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Batch is empty or too large", body = crate::api::errors::ErrorResponse),
+        (status = 429, description = "Monthly quota cannot cover the batch", body = crate::api::errors::ErrorResponse),
+        (status = 503, description = "Database overloaded; retry later", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn create_transaction_batch(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(batch): Json<BatchTransactionRequest>,
+) -> ApiResult<Json<BatchTransactionResponse>> {
+    let max = state.config.batch.max_transactions;
+    if !(1..=max).contains(&batch.transactions.len()) {
+        return Err(ApiError::Validation(format!(
+            "transactions must contain between 1 and {max} items"
+        )));
+    }
+    check_pool_saturation(&state)?;
+
+    let requests: Vec<ApiResult<TransactionRequest>> = batch
+        .transactions
+        .into_iter()
+        .map(|value| {
+            let request: TransactionRequest = serde_json::from_value(value)
+                .map_err(|e| ApiError::Validation(format!("Invalid transaction: {e}")))?;
+            request.validate().map_err(ApiError::Validation)?;
+            Ok(request)
+        })
+        .collect();
+
+    // Charge for every valid transaction up front, so the batch cannot overshoot the quota
+    let valid = requests.iter().filter(|request| request.is_ok()).count();
+    let units = i32::try_from(valid).unwrap_or(i32::MAX);
+    let usage = if auth.sandbox || valid == 0 {
+        None
+    } else {
+        match state
+            .meter
+            .consume(auth.tenant(), units)
+            .await
+            .map_err(ServiceError::Database)?
+        {
+            Metered::Allowed(usage) => Some(usage),
+            Metered::QuotaExceeded(usage) => return Err(quota_exceeded(&usage)),
+        }
+    };
+
+    let policy = state.accounts.disposition_policy(auth.tenant()).await?;
+    let weights = state.rules.weights(auth.tenant()).await?;
+    let outcomes: Vec<ApiResult<TransactionRecord>> = stream::iter(requests)
+        .map(|request| {
+            let (state, auth, policy, weights) = (&state, &auth, &policy, &weights);
+            async move { score_transaction(state, auth, policy, weights, &request?).await }
+        })
+        .buffered(state.config.batch.concurrency)
+        .collect()
+        .await;
+
+    let results: Vec<BatchItemResult> = outcomes
+        .into_iter()
+        .enumerate()
+        .map(|(index, outcome)| match outcome {
+            Ok(record) => BatchItemResult {
+                index,
+                status: StatusCode::CREATED.as_u16(),
+                transaction: Some(record.into()),
+                error: None,
+            },
+            Err(error) => {
+                let (status, error) = error.to_response();
+                BatchItemResult {
+                    index,
+                    status: status.as_u16(),
+                    transaction: None,
+                    error: Some(error),
+                }
+            },
+        })
+        .collect();
+    let succeeded = results.iter().filter(|r| r.transaction.is_some()).count();
+    let failed = results.len() - succeeded;
+
+    // Give back the units of valid transactions that could not be stored
+    let unused = valid - succeeded;
+    let usage = match usage {
+        Some(usage) if unused > 0 => {
+            let unused = i32::try_from(unused).unwrap_or(i32::MAX);
+            state.meter.release(auth.tenant(), &usage, unused).await;
+            Some(Usage {
+                used: usage.used - i64::from(unused),
+                ..usage
+            })
+        },
+        usage => usage,
+    };
+
+    tracing::info!(
+        account_id = %auth.account_id,
+        succeeded,
+        failed,
+        "Transaction batch scored"
+    );
+
+    Ok(Json(BatchTransactionResponse {
+        results,
+        succeeded,
+        failed,
+        queries_remaining: usage.map(|usage| usage.remaining()),
+    }))
+}
+
+/// Fetch a transaction by ID
+#[utoipa::path(
+    get,
+    path = "/v1/transactions/{transaction_id}",
+    tags = ["Transactions"],
+    summary = "Get transaction by ID",
+    description = "Retrieve the stored risk assessment for a transaction belonging to the calling account.",
+    params(("transaction_id" = Uuid, Path, description = "Unique identifier for the transaction")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Transaction details", body = TransactionResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Transaction not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_transaction(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(transaction_id): Path<Uuid>,
+) -> ApiResult<Json<TransactionResponse>> {
+    let record = state
+        .transactions
+        .get_transaction(auth.tenant(), transaction_id)
+        .await?;
+    Ok(Json(record.into()))
+}
+
+/// Score a stored transaction again under the current rule set
+#[utoipa::path(
+    post,
+    path = "/v1/transactions/{transaction_id}/rescore",
+    tags = ["Transactions"],
+    summary = "Rescore a transaction",
+    description = "Re-run the current rule set, rule weights, and disposition policy against the request a transaction was originally scored on, for example after rules have changed. The result is stored as a new scoring revision; the original assessment and earlier revisions are kept unchanged. Sandbox keys always receive the `test` disposition. Each rescore counts against the monthly quota like a new transaction. Transactions scored before requests were kept for rescoring cannot be rescored.\n\nEvery signal is gathered again as it stands now: the user's and device's history, IP reputation, network, and location, list matches, email, phone, and card intelligence, sanctions screening, velocity, card testing, travel, and local time. History recorded since, including the transaction itself, therefore counts. Only session signals are left out, by design: session history is not replayed, so session factors are not reproduced.",
+    params(("transaction_id" = Uuid, Path, description = "Unique identifier for the transaction")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Transaction rescored", body = ScoringRevision),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Transaction not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The transaction's request was not kept", body = crate::api::errors::ErrorResponse),
+        (status = 429, description = "Monthly quota used up", body = crate::api::errors::ErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn rescore_transaction(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    usage: Option<Extension<Usage>>,
+    Path(transaction_id): Path<Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let policy = state.accounts.disposition_policy(auth.tenant()).await?;
+    let weights = state.rules.weights(auth.tenant()).await?;
+    let revision = state
+        .transactions
+        .rescore(auth.tenant(), transaction_id, |request, user| {
+            let mut assessment = state.risk_engine.assess_weighted(request, user, &weights);
+            assessment.disposition = if auth.sandbox {
+                Disposition::Test
+            } else {
+                assessment.disposition_under(policy)
+            };
+            assessment
+        })
+        .await?;
+
+    tracing::info!(
+        transaction_id = %revision.transaction_id,
+        account_id = %auth.account_id,
+        revision = revision.revision,
+        risk_score = revision.risk_score,
+        "Transaction rescored"
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ScoringRevision {
+            queries_remaining: usage.map(|Extension(usage)| usage.remaining()),
+            ..revision
+        }),
+    ))
+}
+
+/// Queue many stored transactions for rescoring
+#[utoipa::path(
+    post,
+    path = "/v1/transactions/batch/rescore",
+    tags = ["Transactions"],
+    summary = "Rescore a batch of transactions",
+    description = "Queue stored transactions to be rescored in the background, for example after rules have changed. The response is a `202` with a background job of the `rescore` kind; poll `GET /v1/jobs/{job_id}` for its progress. Each transaction is rescored exactly as by `POST /v1/transactions/{transaction_id}/rescore` and gets its own result, in the order the transactions were named: a transaction that cannot be rescored does not affect the others. Every named transaction counts against the monthly quota when the job is accepted; if the quota cannot cover all of them the job is refused. Available on the Pro plan and above.",
+    request_body = RescoreJobRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 202, description = "Transactions queued for rescoring", body = BackgroundJob,
+            headers(("Location" = String, description = "URI of the background job"))
+        ),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the plan does not include batch scoring", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "No transactions, too many, or a transaction named twice", body = crate::api::errors::ErrorResponse),
+        (status = 429, description = "Monthly quota cannot cover the batch", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn rescore_transaction_batch(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(request): Json<RescoreJobRequest>,
+) -> ApiResult<impl IntoResponse> {
+    let max = state.config.batch.max_transactions;
+    let count = request.transaction_ids.len();
+    if !(1..=max).contains(&count) {
+        return Err(ApiError::Validation(format!(
+            "transaction_ids must contain between 1 and {max} items"
+        )));
+    }
+    let mut named = request.transaction_ids.clone();
+    named.sort_unstable();
+    named.dedup();
+    if named.len() != count {
+        return Err(ApiError::Validation(
+            "transaction_ids must not name a transaction twice".to_string(),
+        ));
+    }
+
+    // Charge for every transaction up front, so the job cannot overshoot the quota
+    let units = i32::try_from(count).unwrap_or(i32::MAX);
+    let usage = if auth.sandbox {
+        None
+    } else {
+        match state
+            .meter
+            .consume(auth.tenant(), units)
+            .await
+            .map_err(ServiceError::Database)?
+        {
+            Metered::Allowed(usage) => Some(usage),
+            Metered::QuotaExceeded(usage) => return Err(quota_exceeded(&usage)),
+        }
+    };
+
+    let job = match state
+        .jobs
+        .enqueue(auth.tenant(), JobKind::Rescore, &request, None)
+        .await
+    {
+        Ok(job) => job,
+        Err(e) => {
+            if let Some(usage) = usage {
+                state.meter.release(auth.tenant(), &usage, units).await;
+            }
+            return Err(e.into());
+        },
+    };
+
+    tracing::info!(
+        job_id = %job.id,
+        account_id = %auth.account_id,
+        transactions = count,
+        "Transactions queued for rescoring"
+    );
+
+    let location = format!("/v1/jobs/{}", job.id);
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(job),
+    ))
+}
+
+/// Queue stored transactions or users for scoring into a downloadable file
+#[utoipa::path(
+    post,
+    path = "/v1/transactions/batch/score",
+    tags = ["Transactions"],
+    summary = "Score stored transactions into a file",
+    description = "Queue stored transactions to be scored again under the account's current rules and disposition policy without recording anything, for example to screen dormant users before they return. With the `transactions` target every transaction stored in the `created_after`/`created_before` range is scored; with the `users` target, the latest such transaction of each user not deleted, optionally only of users without a transaction since `inactive_since`. `created_before` defaults to when the job is queued, so transactions arriving while it runs are left out.\n\nThe response is a `202` with a background job of the `batch_score` kind; poll `GET /v1/jobs/{job_id}` for its progress, reported as `processed` out of `total`. Once the job has finished, download one line per transaction, as CSV or newline-delimited JSON, from `GET /v1/jobs/{job_id}/output`. A transaction that cannot be scored, such as one stored before requests were kept, is a line with its error and does not affect the others; the first of them are also listed in the job's `failures`.\n\nEvery matching transaction or user counts against the monthly quota when the job is accepted; if the quota cannot cover all of them the job is refused. A job scores at most the configured maximum, the first in creation order. Available on the Pro plan and above.",
+    request_body = BatchScoreJobRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 202, description = "Scoring queued", body = BackgroundJob,
+            headers(("Location" = String, description = "URI of the background job"))
+        ),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the plan does not include batch scoring", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Nothing matches, the limit is out of range, or `inactive_since` is given for transactions", body = crate::api::errors::ErrorResponse),
+        (status = 429, description = "Monthly quota cannot cover the job", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn score_stored_batch(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(mut request): Json<BatchScoreJobRequest>,
+) -> ApiResult<impl IntoResponse> {
+    let max = state.config.batch.max_job_items;
+    let limit = request.limit.unwrap_or(max);
+    if !(1..=max).contains(&limit) {
+        return Err(ApiError::Validation(format!(
+            "limit must be between 1 and {max}"
+        )));
+    }
+    if request.target == BatchScoreTarget::Transactions && request.inactive_since.is_some() {
+        return Err(ApiError::Validation(
+            "inactive_since applies to the users target only".to_string(),
+        ));
+    }
+    request.created_before = Some(request.created_before.unwrap_or_else(Utc::now));
+
+    let count = state
+        .jobs
+        .count_batch_score_targets(auth.tenant(), &request)
+        .await?
+        .min(limit);
+    if count == 0 {
+        return Err(ApiError::Validation(
+            "No stored transactions match the request".to_string(),
+        ));
+    }
+    // The job scores no more than it was charged for
+    request.limit = Some(count);
+
+    let units = i32::try_from(count).unwrap_or(i32::MAX);
+    let usage = if auth.sandbox {
+        None
+    } else {
+        match state
+            .meter
+            .consume(auth.tenant(), units)
+            .await
+            .map_err(ServiceError::Database)?
+        {
+            Metered::Allowed(usage) => Some(usage),
+            Metered::QuotaExceeded(usage) => return Err(quota_exceeded(&usage)),
+        }
+    };
+
+    let job = match state
+        .jobs
+        .enqueue(auth.tenant(), JobKind::BatchScore, &request, None)
+        .await
+    {
+        Ok(job) => job,
+        Err(e) => {
+            if let Some(usage) = usage {
+                state.meter.release(auth.tenant(), &usage, units).await;
+            }
+            return Err(e.into());
+        },
+    };
+
+    tracing::info!(
+        job_id = %job.id,
+        account_id = %auth.account_id,
+        target = ?request.target,
+        count,
+        "Stored transactions queued for batch scoring"
+    );
+
+    let location = format!("/v1/jobs/{}", job.id);
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(job),
+    ))
+}
+
+/// Appeal a rejected transaction
+#[utoipa::path(
+    post,
+    path = "/v1/transactions/{transaction_id}/appeal",
+    tags = ["Transactions"],
+    summary = "Appeal a transaction",
+    description = "Ask for a second look at a transaction that was rejected, whether by its latest scoring or by a reviewer declining its case. The appeal opens a case of the `appeal` kind in the case queue, carrying a snapshot of the scoring being appealed: its risk score, disposition, and contributing factors. Unless the account assigns cases manually, the appeal is assigned to a reviewer other than the one who declined the transaction. Approving the appeal replaces the transaction's reported outcome with `not_fraud`. A transaction can be appealed once. Requires the `transactions:write` scope.",
+    params(("transaction_id" = Uuid, Path, description = "Unique identifier for the transaction")),
+    request_body = AppealRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Appeal opened", body = ReviewCase),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Transaction not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "Transaction was not rejected or was already appealed", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn appeal_transaction(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(transaction_id): Path<Uuid>,
+    Json(request): Json<AppealRequest>,
+) -> ApiResult<impl IntoResponse> {
+    request.validate().map_err(ApiError::Validation)?;
+    let case = state
+        .cases
+        .appeal(auth.tenant(), transaction_id, &request)
+        .await?;
+
+    tracing::info!(
+        transaction_id = %transaction_id,
+        account_id = %auth.account_id,
+        case_id = %case.id,
+        "Transaction appealed"
+    );
+
+    Ok((StatusCode::CREATED, Json(case)))
+}
+
+/// List the disputes of a transaction
+#[utoipa::path(
+    get,
+    path = "/v1/transactions/{transaction_id}/disputes",
+    tags = ["Transactions"],
+    summary = "List transaction disputes",
+    description = "Retrieve the disputes payment processors reported for a transaction, oldest first, each with the stage it reached (`received`, `representment` once evidence was submitted, then `won` or `lost`), when it reached each stage, and the disputed amount. Disputes are followed from the processor webhooks received at `POST /v1/ingest/{account_id}/processor-events`.",
+    params(("transaction_id" = Uuid, Path, description = "Unique identifier for the transaction")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The transaction's disputes", body = DisputeList),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Transaction not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_transaction_disputes(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(transaction_id): Path<Uuid>,
+) -> ApiResult<Json<DisputeList>> {
+    let disputes = state
+        .processor_events
+        .disputes(auth.tenant(), transaction_id)
+        .await?;
+    Ok(Json(DisputeList { disputes }))
+}
+
+/// Fetch the stored request of a transaction
+#[utoipa::path(
+    get,
+    path = "/v1/transactions/{transaction_id}/request",
+    tags = ["Transactions"],
+    summary = "Get the stored transaction request",
+    description = "Retrieve the request a transaction was scored on, as stored: email addresses and card tokens are SHA-256 hashes, card numbers are cut down to the BIN prefix and last digits, and custom input fields the operator marked as sensitive are removed. Requires the `raw_requests:read` scope. Available on the Enterprise plan.",
+    params(("transaction_id" = Uuid, Path, description = "Unique identifier for the transaction")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The stored request", body = StoredTransactionRequest),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the plan does not include stored requests", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Transaction not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "The transaction's request was not kept", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_transaction_request(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(transaction_id): Path<Uuid>,
+) -> ApiResult<Json<StoredTransactionRequest>> {
+    let stored = state
+        .transactions
+        .get_raw_request(auth.tenant(), transaction_id)
+        .await?;
+    Ok(Json(stored))
+}
+
+/// Fetch insights into the entities behind a transaction
+#[utoipa::path(
+    get,
+    path = "/v1/transactions/{transaction_id}/insights",
+    tags = ["Transactions"],
+    summary = "Get transaction insights",
+    description = "Retrieve what is known about the device, email address, billing and shipping addresses, phone number, and payment card of a transaction: their attributes, IP intelligence and card network where available, and how many of the account's transactions shared each of them. Addresses are placed at the centroid of their postal code when postal centroids are configured, and compared with the country and location of the transaction's IP address. Sections the transaction did not supply are omitted. Available on the Pro plan and above.",
+    params(("transaction_id" = Uuid, Path, description = "Unique identifier for the transaction")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Transaction insights", body = TransactionInsights),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the plan does not include insights", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Transaction not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_transaction_insights(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(transaction_id): Path<Uuid>,
+) -> ApiResult<Json<TransactionInsights>> {
+    let insights = state
+        .transactions
+        .insights(auth.tenant(), transaction_id)
+        .await?;
+    Ok(Json(insights))
+}
+
+/// List transactions
+#[utoipa::path(
+    get,
+    path = "/v1/transactions",
+    tags = ["Transactions"],
+    summary = "List transactions",
+    description = "Retrieve a paginated list of the calling account's transactions, optionally filtered by risk level, disposition, creation date, user, shop, order amount and currency, address country, IP address or range, email domain, and rules fired, or searched by external transaction ID. Filters combine with AND. Pages are fetched by `offset` or, for listings sorted by creation time, by `cursor`: pass a page's `pagination.next_cursor` to get the page after it. Cursor pages stay fast however deep they go and do not shift when transactions are added meanwhile; they omit `offset` and `total`. Offset pages sorted by creation time also carry `next_cursor`, so a listing can switch to cursors after its first page.",
+    params(ListTransactionsQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of transactions", body = TransactionList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_transactions(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<ListTransactionsQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Json<TransactionList>> {
+    Ok(Json(
+        transaction_page(
+            &state,
+            auth.tenant(),
+            &query,
+            "/v1/transactions",
+            raw_query.as_deref(),
+        )
+        .await?,
+    ))
+}
+
+/// Fetch the page of transactions `query` asks for, with links under `path`
+///
+/// Shared by every transaction listing, so they page and filter alike.
+pub(super) async fn transaction_page(
+    state: &AppState,
+    tenant: Tenant,
+    query: &ListTransactionsQuery,
+    path: &str,
+    raw_query: Option<&str>,
+) -> ApiResult<TransactionList> {
+    query.validate().map_err(ApiError::BadRequest)?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    let cursor_order = query.sort.unwrap_or_default().cursor_ascending();
+
+    let (records, pagination) = match &query.cursor {
+        Some(encoded) => {
+            if query.offset.is_some() {
+                return Err(ApiError::BadRequest(
+                    "cursor and offset cannot be combined".to_string(),
+                ));
+            }
+            let ascending = cursor_order.ok_or_else(|| {
+                ApiError::BadRequest(
+                    "cursor pagination requires sorting by created_at or -created_at".to_string(),
+                )
+            })?;
+            let cursor = Cursor::decode(encoded)
+                .ok_or_else(|| ApiError::BadRequest("cursor is not valid".to_string()))?;
+
+            // One extra row tells whether another page follows
+            let mut records = state
+                .transactions
+                .list_transactions_after(tenant, query, Some(cursor), ascending, limit + 1)
+                .await?;
+            let has_more = records.len() as i64 > limit;
+            records.truncate(limit as usize);
+            let next = has_more.then(|| last_cursor(&records)).flatten();
+            (records, Pagination::cursor(limit, next))
+        },
+        None => {
+            let offset = query.offset.unwrap_or(0);
+            if offset < 0 {
+                return Err(ApiError::BadRequest(
+                    "offset must not be negative".to_string(),
+                ));
+            }
+            let (records, total) = state
+                .transactions
+                .list_transactions(tenant, query, limit, offset)
+                .await?;
+            let pagination = Pagination::new(limit, offset, total);
+            let next = (pagination.has_more && cursor_order.is_some())
+                .then(|| last_cursor(&records))
+                .flatten();
+            (records, pagination.with_next_cursor(next))
+        },
+    };
+
+    Ok(TransactionList {
+        transactions: records.into_iter().map(Into::into).collect(),
+        links: pagination.links(&listing_base(path, raw_query)),
+        pagination,
+    })
+}
+
+/// Refuse new work rather than queueing behind a saturated connection pool
+fn check_pool_saturation(state: &AppState) -> ApiResult<()> {
+    let threshold = state.config.database.postgres_saturation_threshold;
+    if state.database.pool_stats().is_saturated(threshold) {
+        return Err(ApiError::ServiceUnavailable(
+            "Database connection pool is saturated; retry shortly".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Score a validated request under the account's disposition policy and rule weights and
+/// store it
+async fn score_transaction(
+    state: &AppState,
+    auth: &AuthContext,
+    policy: &DispositionPolicy,
+    weights: &RuleWeights,
+    request: &TransactionRequest,
+) -> ApiResult<TransactionRecord> {
+    let (user, ip_location) = state
+        .transactions
+        .gather_signals(auth.tenant(), request)
+        .await?;
+    let mut assessment = state.risk_engine.assess_weighted(request, &user, weights);
+    assessment.disposition = assessment.disposition_under(*policy);
+    if auth.sandbox {
+        // Scored as usual so integrators see realistic results, but never acted upon
+        assessment.disposition = Disposition::Test;
+    }
+    let warnings = request.warnings();
+    let record = state
+        .transactions
+        .store_transaction(auth.tenant(), request, &assessment, &warnings)
+        .await?;
+    state.live.publish(auth.account_id, record.disposition);
+    state
+        .transactions
+        .record_location(record.user_id, ip_location.as_ref(), record.event_time)
+        .await;
+
+    tracing::info!(
+        transaction_id = %record.id,
+        account_id = %auth.account_id,
+        risk_score = record.risk_score,
+        "Transaction scored"
+    );
+    Ok(record)
+}
+
+/// Listing URI at `path` with the request's filters and sort, for pagination links to build on
+pub(super) fn listing_base(path: &str, raw_query: Option<&str>) -> String {
+    let kept: Vec<&str> = raw_query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            !key.is_empty() && !matches!(key, "offset" | "limit" | "cursor")
+        })
+        .collect();
+    if kept.is_empty() {
+        path.to_string()
+    } else {
+        format!("{path}?{}", kept.join("&"))
+    }
+}
+
+/// Cursor continuing after the last transaction of a page
+fn last_cursor(records: &[TransactionRecord]) -> Option<Cursor> {
+    records.last().map(|record| Cursor {
+        created_at: record.created_at,
+        id: record.id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_base_keeps_filters_only() {
+        let path = "/v1/transactions";
+        assert_eq!(listing_base(path, None), "/v1/transactions");
+        assert_eq!(
+            listing_base(path, Some("offset=20&limit=10&cursor=abc")),
+            "/v1/transactions"
+        );
+        assert_eq!(
+            listing_base(
+                path,
+                Some("currency=USD&offset=20&q=txn%201&sort=-created_at")
+            ),
+            "/v1/transactions?currency=USD&q=txn%201&sort=-created_at"
+        );
+    }
+}
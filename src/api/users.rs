@@ -0,0 +1,449 @@
+//! User management endpoints
+
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, Query, RawQuery, State},
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use super::{ApiError, ApiResult, transactions::transaction_page};
+use crate::{
+    auth::AuthContext,
+    models::{
+        transaction::{ListTransactionsQuery, TransactionList},
+        user::{
+            CreateUser, ImportUser, LinkedUserList, MergeUsers, User, UserImport, UserLookupQuery,
+            UserRiskAnalysis, UserUpdate,
+        },
+    },
+    state::AppState,
+};
+
+/// Register a user
+#[utoipa::path(
+    post,
+    path = "/v1/users",
+    tags = ["Users"],
+    summary = "Create user",
+    description = "Register a user under your own user ID before their first transaction, for example to mark them verified or attach metadata. Transactions whose `account.user_id` matches are attributed to the user. Users are otherwise created automatically as transactions name them.",
+    request_body = CreateUser,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "User created", body = User,
+            headers(("Location" = String, description = "URI of the created user"))
+        ),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "A user with this external_user_id already exists", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn create_user(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(request): Json<CreateUser>,
+) -> ApiResult<impl IntoResponse> {
+    let user = state.users.create_user(auth.tenant(), &request).await?;
+    let location = format!("/v1/users/{}", user.id);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(user),
+    ))
+}
+
+/// Fetch a user by ID
+#[utoipa::path(
+    get,
+    path = "/v1/users/{user_id}",
+    tags = ["Users"],
+    summary = "Get user by ID",
+    description = "Retrieve a user with their risk score, transaction totals, verification status, flags, and metadata. Deleted users are not found.",
+    params(("user_id" = Uuid, Path, description = "Unique identifier for the user")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The user", body = User),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_user(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+) -> ApiResult<Json<User>> {
+    Ok(Json(state.users.get_user(auth.tenant(), user_id).await?))
+}
+
+/// Look up a user by one of your own identifiers
+#[utoipa::path(
+    get,
+    path = "/v1/users/lookup",
+    tags = ["Users"],
+    summary = "Look up user",
+    description = "Find a user by the ID you gave them in `account.user_id` or by the `account.user_hash` they were identified by, without storing Fusegu user IDs. Give exactly one of the two. An identifier of a user merged into another finds the surviving user. Deleted users are not found.",
+    params(UserLookupQuery),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The user", body = User),
+        (status = 400, description = "Not exactly one identifier given", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn lookup_user(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Query(query): Query<UserLookupQuery>,
+) -> ApiResult<Json<User>> {
+    query.validate().map_err(ApiError::BadRequest)?;
+    Ok(Json(state.users.lookup_user(auth.tenant(), &query).await?))
+}
+
+/// Update a user
+#[utoipa::path(
+    patch,
+    path = "/v1/users/{user_id}",
+    tags = ["Users"],
+    summary = "Update user",
+    description = "Change a user's verification status, review flag, flags, or metadata. Fields left out are kept; `flags` and `metadata` replace the stored values as a whole. Each flag has a type, unique among the user's flags, and optionally a reason, the actor who set it, and an expiry. Transactions of a user carrying any flag that has not expired score higher; expired flags stay on the user for the record but no longer count.",
+    params(("user_id" = Uuid, Path, description = "Unique identifier for the user")),
+    request_body = UserUpdate,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated user", body = User),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn update_user(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+    Json(update): Json<UserUpdate>,
+) -> ApiResult<Json<User>> {
+    Ok(Json(
+        state
+            .users
+            .update_user(auth.tenant(), user_id, &update)
+            .await?,
+    ))
+}
+
+/// Analyze a user's recent behavior
+#[utoipa::path(
+    get,
+    path = "/v1/users/{user_id}/risk-analysis",
+    tags = ["Users"],
+    summary = "Get user risk analysis",
+    description = "Analyze a user's transactions over the last 30 days: transaction velocity over the last hour, day, week, and month; how consistently they use the same devices; and which billing countries they transact from. Where a behavioral profile has been computed for the user (nightly, from their recent purchases), it is returned as the baseline and departures from it are reported as indicators.",
+    params(("user_id" = Uuid, Path, description = "Unique identifier for the user")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Risk analysis of the user", body = UserRiskAnalysis),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_user_risk_analysis(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+) -> ApiResult<Json<UserRiskAnalysis>> {
+    Ok(Json(
+        state.users.risk_analysis(auth.tenant(), user_id).await?,
+    ))
+}
+
+/// List a user's transactions
+#[utoipa::path(
+    get,
+    path = "/v1/users/{user_id}/transactions",
+    tags = ["Users"],
+    summary = "List user transactions",
+    description = "Retrieve a paginated list of a user's transactions. Takes the same filters, sort orders, and `offset` or `cursor` pagination as `GET /v1/transactions`, except `user_id`, which comes from the path. Requires the `transactions:read` scope.",
+    params(
+        ("user_id" = Uuid, Path, description = "Unique identifier for the user"),
+        ListTransactionsQuery
+    ),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of the user's transactions", body = TransactionList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_user_transactions(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+    Query(mut query): Query<ListTransactionsQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Json<TransactionList>> {
+    if query.user_id.is_some() {
+        return Err(ApiError::BadRequest(
+            "user_id is taken from the path and cannot be given as a filter".to_string(),
+        ));
+    }
+    state.users.get_user(auth.tenant(), user_id).await?;
+    query.user_id = Some(user_id);
+    let path = format!("/v1/users/{user_id}/transactions");
+    Ok(Json(
+        transaction_page(&state, auth.tenant(), &query, &path, raw_query.as_deref()).await?,
+    ))
+}
+
+/// Merge a duplicate user into this one
+#[utoipa::path(
+    post,
+    path = "/v1/users/{user_id}/merge",
+    tags = ["Users"],
+    summary = "Merge users",
+    description = "Fold a duplicate user, named by `source_user_id`, into the user in the path. The duplicate's transactions, devices, emails, addresses, and cards move over; transaction totals add up, the higher risk score wins, the user stays flagged if either was, flags are combined with the surviving user's flag kept where both carry one of the same type, and metadata keys present on both keep the surviving user's value. The surviving user keeps its own identifiers and verification status. The duplicate is deleted, but transactions naming its `external_user_id`, `user_hash`, or ID are attributed to the surviving user from then on. A `user.merged` event is emitted. Behavioral profiles catch up at the next nightly refresh.",
+    params(("user_id" = Uuid, Path, description = "User that absorbs the duplicate")),
+    request_body = MergeUsers,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The merged user", body = User),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "The source user is unknown or the same as the target", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn merge_users(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+    Json(request): Json<MergeUsers>,
+) -> ApiResult<Json<User>> {
+    Ok(Json(
+        state
+            .users
+            .merge_users(auth.tenant(), user_id, &request)
+            .await?,
+    ))
+}
+
+/// Recalculate a user's risk score now
+#[utoipa::path(
+    post,
+    path = "/v1/users/{user_id}/recalculate",
+    tags = ["Users"],
+    summary = "Recalculate user risk score",
+    description = "Recalculate the user's `risk_score` and `risk_level` immediately rather than waiting for the periodic recalculation. The score combines the user's transaction scores over the lookback window, weighted so older transactions count less and averaged towards 0 for users with little recent activity, with the fraud, abuse, and chargeback outcomes reported for those transactions and the chargebacks on record. Transactions reported as not fraud no longer count against the user. Users are recalculated automatically shortly after each transaction, import, or merge, and at least daily otherwise.",
+    params(("user_id" = Uuid, Path, description = "Unique identifier for the user")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The user with their recalculated score", body = User),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn recalculate_user_risk(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+) -> ApiResult<Json<User>> {
+    Ok(Json(
+        state.users.recalculate_risk(auth.tenant(), user_id).await?,
+    ))
+}
+
+/// List users linked to a user by identity resolution
+#[utoipa::path(
+    get,
+    path = "/v1/users/{user_id}/linked-users",
+    tags = ["Users"],
+    summary = "List linked users",
+    description = "List other users found transacting with the same email address, payment card, or device as this user, which often means one person was identified two ways. Links are found by a periodic identity resolution pass over recent transactions; emails, cards, and devices shared by many users are ignored. Links are suggestions only: review them and merge true duplicates with `POST /v1/users/{user_id}/merge`.",
+    params(("user_id" = Uuid, Path, description = "Unique identifier for the user")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Linked users", body = LinkedUserList),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_linked_users(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+) -> ApiResult<Json<LinkedUserList>> {
+    Ok(Json(
+        state.users.linked_users(auth.tenant(), user_id).await?,
+    ))
+}
+
+/// Import an existing user base
+#[utoipa::path(
+    post,
+    path = "/v1/users/batch",
+    tags = ["Users"],
+    summary = "Import users",
+    description = "Import users from an existing customer base, with their historical transaction counts, chargebacks, and flags, so they are not scored as brand new. Send a JSON array of users, or one user per line with `Content-Type: application/x-ndjson`. The users are imported in the background; poll the import at the `Location` header for progress, or wait for the `user_import.completed` event. Users are matched on `external_user_id`: new users are created, and existing ones keep the larger of their own and the imported counts, the wider transaction period, and their flags plus imported ones of types they do not carry. A user that is malformed, fails validation, or matches a deleted user is counted as failed without stopping the import, and the first failures are listed with their position in the batch. Available on the Pro plan and above.",
+    request_body(
+        description = "Users to import, as a JSON array or newline-delimited JSON",
+        content(
+            (Vec<ImportUser> = "application/json"),
+            (ImportUser = "application/x-ndjson")
+        )
+    ),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 202, description = "Import queued", body = UserImport,
+            headers(("Location" = String, description = "URI of the import"))
+        ),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope, or the plan does not include batch operations", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Batch is empty or too large", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn import_users(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    headers: HeaderMap,
+    body: Body,
+) -> ApiResult<impl IntoResponse> {
+    let bytes = axum::body::to_bytes(body, state.config.server.max_request_size)
+        .await
+        .map_err(|_| ApiError::BadRequest("Request body is too large".to_string()))?;
+    let users = if is_ndjson(&headers) {
+        parse_ndjson(&bytes)?
+    } else {
+        serde_json::from_slice::<Vec<serde_json::Value>>(&bytes).map_err(|e| {
+            ApiError::BadRequest(format!("Request body must be a JSON array of users: {e}"))
+        })?
+    };
+    let max = state.config.imports.max_users;
+    if !(1..=max).contains(&users.len()) {
+        return Err(ApiError::Validation(format!(
+            "An import must contain between 1 and {max} users"
+        )));
+    }
+
+    let import = state.users.import_users(auth.tenant(), &users).await?;
+    let location = format!("/v1/users/imports/{}", import.id);
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(import),
+    ))
+}
+
+/// Fetch the progress of a user import
+#[utoipa::path(
+    get,
+    path = "/v1/users/imports/{import_id}",
+    tags = ["Users"],
+    summary = "Get user import",
+    description = "Fetch the progress of a user import. Counts are updated as each chunk of users is committed, so `processed_users` out of `total_users` gives the progress of an import being processed.",
+    params(("import_id" = Uuid, Path, description = "Unique identifier for the import")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "User import", body = UserImport),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Import not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_user_import(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(import_id): Path<Uuid>,
+) -> ApiResult<Json<UserImport>> {
+    Ok(Json(
+        state.users.get_import(auth.tenant(), import_id).await?,
+    ))
+}
+
+/// Soft-delete a user
+#[utoipa::path(
+    delete,
+    path = "/v1/users/{user_id}",
+    tags = ["Users"],
+    summary = "Delete user",
+    description = "Soft-delete a user. The user stops influencing risk scores and is no longer matched by new transactions, but its record and transaction history remain available for audit.",
+    params(("user_id" = Uuid, Path, description = "Unique identifier for the user")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "User deleted"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "User not found or already deleted", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn delete_user(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(user_id): Path<Uuid>,
+) -> ApiResult<StatusCode> {
+    state.users.delete_user(auth.tenant(), user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Whether the request body is newline-delimited JSON
+pub(super) fn is_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/x-ndjson"))
+}
+
+/// Parse one JSON value per non-blank line
+pub(super) fn parse_ndjson(body: &[u8]) -> ApiResult<Vec<serde_json::Value>> {
+    body.split(|&byte| byte == b'\n')
+        .enumerate()
+        .filter(|(_, line)| !line.trim_ascii().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_slice(line)
+                .map_err(|e| ApiError::BadRequest(format!("Line {}: {e}", i + 1)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_ndjson_lines_are_numbered_from_one() {
+        let users =
+            parse_ndjson(b"{\"external_user_id\":\"a\"}\r\n\n{\"external_user_id\":\"b\"}\n")
+                .unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[1]["external_user_id"], "b");
+
+        let Err(ApiError::BadRequest(message)) = parse_ndjson(b"{}\n\n{oops}") else {
+            panic!("expected a malformed line to be refused");
+        };
+        assert!(message.starts_with("Line 3:"), "{message}");
+
+        let mut headers = HeaderMap::new();
+        assert!(!is_ndjson(&headers));
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("Application/X-NDJSON; charset=utf-8"),
+        );
+        assert!(is_ndjson(&headers));
+    }
+}
@@ -0,0 +1,283 @@
+//! Webhook endpoints
+
+use axum::{
+    Json,
+    extract::{Path, Query, RawQuery, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+use super::{ApiError, ApiResult, transactions::listing_base};
+use crate::{
+    auth::AuthContext,
+    models::{
+        common::Pagination,
+        webhook::{
+            ListDeliveriesQuery, SecretRotationRequest, Webhook, WebhookDelivery,
+            WebhookDeliveryList, WebhookList, WebhookRequest,
+        },
+    },
+    state::AppState,
+};
+
+/// Default page size for delivery listings
+const DEFAULT_LIMIT: i64 = 20;
+/// Largest page size a client may request
+const MAX_LIMIT: i64 = 100;
+
+/// List the account's webhooks
+#[utoipa::path(
+    get,
+    path = "/v1/webhooks",
+    tags = ["Webhooks"],
+    summary = "List webhooks",
+    description = "Retrieve the endpoints the calling account's events are delivered to, oldest first, with the number of deliveries each accepted and failed. Secrets are not included. Requires the `account:read` scope. Available on the Pro plan and above.",
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The account's webhooks", body = WebhookList),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope or plan", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+    auth: AuthContext,
+) -> ApiResult<Json<WebhookList>> {
+    let webhooks = state.webhooks.list_webhooks(auth.tenant()).await?;
+    Ok(Json(WebhookList { webhooks }))
+}
+
+/// Register a webhook
+#[utoipa::path(
+    post,
+    path = "/v1/webhooks",
+    tags = ["Webhooks"],
+    summary = "Create webhook",
+    description = "Register an endpoint to POST the account's events to, either every event or only the listed types. `filters` narrow deliveries further by the event's payload, e.g. to rejected transactions over an amount or in one shop, so low-value events do not flood the endpoint. Each delivery carries an `X-Fusegu-Timestamp` header and an `X-Fusegu-Signature` header of the form `v1={signature}`, the hex HMAC-SHA256 of `{timestamp}.{body}` under the webhook's secret; refuse deliveries whose signature does not match or whose timestamp is more than a few minutes old. The secret is returned only in this response, so store it now. Requires the `account:write` scope. Available on the Pro plan and above.",
+    request_body = WebhookRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "The webhook, with its secret", body = Webhook),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope or plan", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Json(request): Json<WebhookRequest>,
+) -> ApiResult<(StatusCode, Json<Webhook>)> {
+    request.validate().map_err(ApiError::Validation)?;
+    let webhook = state
+        .webhooks
+        .create_webhook(auth.tenant(), &request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(webhook)))
+}
+
+/// Fetch a webhook
+#[utoipa::path(
+    get,
+    path = "/v1/webhooks/{webhook_id}",
+    tags = ["Webhooks"],
+    summary = "Get webhook by ID",
+    description = "Retrieve a webhook with when its secret was last rotated and until when the secret it replaced still signs deliveries. The secret itself is not included. Requires the `account:read` scope. Available on the Pro plan and above.",
+    params(("webhook_id" = Uuid, Path, description = "Unique identifier for the webhook")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The webhook", body = Webhook),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope or plan", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Webhook not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn get_webhook(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(webhook_id): Path<Uuid>,
+) -> ApiResult<Json<Webhook>> {
+    Ok(Json(
+        state
+            .webhooks
+            .get_webhook(auth.tenant(), webhook_id)
+            .await?,
+    ))
+}
+
+/// Replace a webhook
+#[utoipa::path(
+    put,
+    path = "/v1/webhooks/{webhook_id}",
+    tags = ["Webhooks"],
+    summary = "Update webhook",
+    description = "Replace a webhook's URL, description, event types, filters, and whether events are delivered to it. The secret is kept; rotate it separately. Requires the `account:write` scope. Available on the Pro plan and above.",
+    params(("webhook_id" = Uuid, Path, description = "Unique identifier for the webhook")),
+    request_body = WebhookRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The updated webhook", body = Webhook),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope or plan", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Webhook not found", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn update_webhook(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(webhook_id): Path<Uuid>,
+    Json(request): Json<WebhookRequest>,
+) -> ApiResult<Json<Webhook>> {
+    request.validate().map_err(ApiError::Validation)?;
+    Ok(Json(
+        state
+            .webhooks
+            .update_webhook(auth.tenant(), webhook_id, &request)
+            .await?,
+    ))
+}
+
+/// Delete a webhook
+#[utoipa::path(
+    delete,
+    path = "/v1/webhooks/{webhook_id}",
+    tags = ["Webhooks"],
+    summary = "Delete webhook",
+    description = "Stop delivering events to an endpoint and forget it. Requires the `account:write` scope. Available on the Pro plan and above.",
+    params(("webhook_id" = Uuid, Path, description = "Unique identifier for the webhook")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Webhook deleted"),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope or plan", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Webhook not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(webhook_id): Path<Uuid>,
+) -> ApiResult<StatusCode> {
+    state
+        .webhooks
+        .delete_webhook(auth.tenant(), webhook_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Rotate a webhook's signing secret
+#[utoipa::path(
+    post,
+    path = "/v1/webhooks/{webhook_id}/rotate-secret",
+    tags = ["Webhooks"],
+    summary = "Rotate webhook secret",
+    description = "Give a webhook a new signing secret, returned only in this response. For `overlap_hours` afterwards (24 by default, at most 168), deliveries are signed with both the new and the replaced secret, as `v1={new},v1={replaced}`, so the endpoint can move to the new secret without refusing events; an overlap of 0 retires the replaced secret at once. A secret still overlapping from an earlier rotation is retired. Requires the `account:write` scope. Available on the Pro plan and above.",
+    params(("webhook_id" = Uuid, Path, description = "Unique identifier for the webhook")),
+    request_body = SecretRotationRequest,
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The webhook, with its new secret", body = Webhook),
+        (status = 400, description = "Malformed request body", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope or plan", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Webhook not found", body = crate::api::errors::ErrorResponse),
+        (status = 422, description = "Request validation failed", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn rotate_webhook_secret(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(webhook_id): Path<Uuid>,
+    Json(request): Json<SecretRotationRequest>,
+) -> ApiResult<Json<Webhook>> {
+    request.validate().map_err(ApiError::Validation)?;
+    Ok(Json(
+        state
+            .webhooks
+            .rotate_secret(auth.tenant(), webhook_id, &request)
+            .await?,
+    ))
+}
+
+/// List a webhook's deliveries
+#[utoipa::path(
+    get,
+    path = "/v1/webhooks/{webhook_id}/deliveries",
+    tags = ["Webhooks"],
+    summary = "List webhook deliveries",
+    description = "Retrieve a paginated log of the attempts at delivering events to a webhook, newest first, with the HTTP status the endpoint answered with, how long it took, and what went wrong for failed attempts. Filter by `status` for the failed deliveries to redrive, or by `event_id` for every attempt at one event. Requires the `account:read` scope. Available on the Pro plan and above.",
+    params(
+        ("webhook_id" = Uuid, Path, description = "Unique identifier for the webhook"),
+        ListDeliveriesQuery
+    ),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Page of deliveries", body = WebhookDeliveryList),
+        (status = 400, description = "Invalid query parameters", body = crate::api::errors::ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope or plan", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Webhook not found", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(webhook_id): Path<Uuid>,
+    Query(query): Query<ListDeliveriesQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Json<WebhookDeliveryList>> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let (deliveries, total) = state
+        .webhooks
+        .list_deliveries(auth.tenant(), webhook_id, &query, limit, offset)
+        .await?;
+
+    let pagination = Pagination::new(limit, offset, total);
+    let path = format!("/v1/webhooks/{webhook_id}/deliveries");
+    Ok(Json(WebhookDeliveryList {
+        deliveries,
+        links: pagination.links(&listing_base(&path, raw_query.as_deref())),
+        pagination,
+    }))
+}
+
+/// Redrive a failed delivery
+#[utoipa::path(
+    post,
+    path = "/v1/webhooks/deliveries/{delivery_id}/redrive",
+    tags = ["Webhooks"],
+    summary = "Redrive webhook delivery",
+    description = "Send the event of a failed delivery to its webhook again, signed with the webhook's current secrets, once the endpoint is fixed. The attempt is logged as a new delivery whose `redrive_of` names the failed one, and returned whether or not it succeeded; the event keeps its ID, so endpoints that deduplicate on it are safe. Deliveries that succeeded, and deliveries to webhooks that have since been deactivated, cannot be redriven. Requires the `account:write` scope. Available on the Pro plan and above.",
+    params(("delivery_id" = Uuid, Path, description = "Unique identifier for the failed delivery")),
+    security(("api_key" = []), ("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "The new attempt", body = WebhookDelivery),
+        (status = 401, description = "Missing or invalid API key", body = crate::api::errors::ErrorResponse),
+        (status = 403, description = "API key lacks the required scope or plan", body = crate::api::errors::ErrorResponse),
+        (status = 404, description = "Delivery not found", body = crate::api::errors::ErrorResponse),
+        (status = 409, description = "Delivery succeeded or its webhook is not active", body = crate::api::errors::ErrorResponse)
+    )
+)]
+pub async fn redrive_webhook_delivery(
+    State(state): State<AppState>,
+    auth: AuthContext,
+    Path(delivery_id): Path<Uuid>,
+) -> ApiResult<(StatusCode, Json<WebhookDelivery>)> {
+    let delivery = state.webhooks.redrive(auth.tenant(), delivery_id).await?;
+    Ok((StatusCode::CREATED, Json(delivery)))
+}
@@ -0,0 +1,40 @@
+//! Operator access to the `/admin` endpoints
+//!
+//! Admin endpoints report on the server itself rather than on an account, so they take a
+//! single operator token (`ADMIN_TOKEN`) instead of an API key. Without a token configured
+//! they do not exist.
+
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{api::ApiError, state::AppState, utils::sha256_hex};
+
+/// Middleware admitting requests that present the admin token as a bearer token
+pub async fn authorize_admin(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let Some(expected) = state.config.auth.admin_token.as_deref() else {
+        return Err(ApiError::NotFound);
+    };
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+    match presented {
+        Some(token) if tokens_match(token, expected) => Ok(next.run(request).await),
+        _ => Err(ApiError::Unauthorized),
+    }
+}
+
+/// Compare tokens by hash, so the comparison takes the same time wherever they differ
+fn tokens_match(presented: &str, expected: &str) -> bool {
+    sha256_hex(presented) == sha256_hex(expected)
+}
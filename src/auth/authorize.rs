@@ -0,0 +1,343 @@
+//! Scope checks for API routes
+//!
+//! Every `/v1` route is mapped to the scope it needs in [`route_access`]; the [`authorize`]
+//! middleware authenticates the caller, by API key or request signature, and checks that scope
+//! before the handler runs. Routes
+//! missing from the map are refused, so a new endpoint cannot be exposed without deciding who
+//! may call it.
+
+use axum::{
+    body::Body,
+    extract::{FromRequestParts, MatchedPath, Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+
+use super::{AuthContext, Scope, signature};
+use crate::{
+    api::ApiError,
+    metering::middleware::is_metered,
+    models::account::{AccountStatus, Feature},
+    state::AppState,
+};
+
+/// Who may call a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Anyone, without authenticating
+    Public,
+    /// Callers whose key holds the scope
+    Requires(Scope),
+}
+
+/// Access rule for a route, or `None` if the route has not been mapped
+///
+/// `path` is the route template, e.g. `/v1/transactions/{transaction_id}`. Reads need the
+/// resource's read scope and anything else its write scope.
+pub fn route_access(method: &Method, path: &str) -> Option<Access> {
+    let path = path.strip_prefix("/v1").unwrap_or(path);
+    let resource = path.trim_start_matches('/').split('/').next()?;
+    let read = matches!(*method, Method::GET | Method::HEAD);
+
+    // Stored requests may hold personal data, so reading them takes a scope of its own
+    if resource == "transactions" && read && path.ends_with("/request") {
+        return Some(Access::Requires(Scope::RawRequestsRead));
+    }
+    // A user's transaction history is transaction data, whichever resource it is listed under
+    if resource == "users" && read && path.ends_with("/transactions") {
+        return Some(Access::Requires(Scope::TransactionsRead));
+    }
+
+    let scope = match (resource, read) {
+        ("health", _) => return Some(Access::Public),
+        ("transactions", true) => Scope::TransactionsRead,
+        ("transactions", false) => Scope::TransactionsWrite,
+        ("jobs", true) => Scope::TransactionsRead,
+        // Devices are identified to score transactions from them
+        ("devices", true) => Scope::TransactionsRead,
+        ("devices", false) => Scope::TransactionsWrite,
+        // IP addresses are known from the transactions that came from them
+        ("ip", true) => Scope::TransactionsRead,
+        // So are email addresses
+        ("emails", true) => Scope::TransactionsRead,
+        // Names are screened as part of scoring, so screening one alone takes the same scope
+        ("screening", false) => Scope::TransactionsWrite,
+        // Processors sign their webhooks with the secret of the account in the path instead
+        ("ingest", false) => return Some(Access::Public),
+        ("users", true) => Scope::UsersRead,
+        ("users", false) => Scope::UsersWrite,
+        ("analytics", true) => Scope::AnalyticsRead,
+        ("reports", true) => Scope::ReportsRead,
+        ("reports", false) => Scope::ReportsWrite,
+        // Labels are the outcomes reported, or corrections of them
+        ("labels", true) => Scope::ReportsRead,
+        ("labels", false) => Scope::ReportsWrite,
+        ("rules", _) => Scope::RulesAdmin,
+        // Lists change how transactions are scored
+        ("lists", _) => Scope::RulesAdmin,
+        ("account", true) => Scope::AccountRead,
+        ("account", false) => Scope::AccountWrite,
+        ("organization", true) => Scope::OrganizationRead,
+        ("organization", false) => Scope::OrganizationWrite,
+        ("cases", true) => Scope::CasesRead,
+        ("cases", false) => Scope::CasesWrite,
+        // Webhooks are part of the account's configuration
+        ("webhooks", true) => Scope::AccountRead,
+        ("webhooks", false) => Scope::AccountWrite,
+        // So are the channels high-severity events are announced on
+        ("notification-channels", true) => Scope::AccountRead,
+        ("notification-channels", false) => Scope::AccountWrite,
+        // And the events that could not be delivered to any of them
+        ("dead-letters", true) => Scope::AccountRead,
+        ("dead-letters", false) => Scope::AccountWrite,
+        _ => return None,
+    };
+    Some(Access::Requires(scope))
+}
+
+/// Paid feature a route belongs to, or `None` if every tier may call it
+///
+/// `path` is the route template, as in [`route_access`].
+pub fn route_feature(path: &str) -> Option<Feature> {
+    let path = path.strip_prefix("/v1").unwrap_or(path);
+    let mut segments = path.trim_start_matches('/').split('/');
+    match segments.next()? {
+        "webhooks" => return Some(Feature::Webhooks),
+        "ip" | "emails" => return Some(Feature::Insights),
+        _ => {},
+    }
+    segments.find_map(|segment| match segment {
+        "insights" => Some(Feature::Insights),
+        "factors" => Some(Feature::Factors),
+        "request" => Some(Feature::RawRequests),
+        "batch" => Some(Feature::Batch),
+        _ => None,
+    })
+}
+
+/// Whether an account in `status` may call a route
+///
+/// Suspended accounts may do anything but score, i.e. call metered routes. Closed accounts may
+/// only read and reactivate their own account. `path` is the route template, as in
+/// [`route_access`].
+pub fn status_allows(status: AccountStatus, method: &Method, path: &str) -> bool {
+    match status {
+        AccountStatus::Active => true,
+        AccountStatus::Suspended => !is_metered(method, path),
+        AccountStatus::Closed => {
+            let path = path.strip_prefix("/v1").unwrap_or(path);
+            let account = path == "/account" || path.starts_with("/account/");
+            let read = matches!(*method, Method::GET | Method::HEAD);
+            account && (read || path == "/account/reactivate")
+        },
+    }
+}
+
+/// Authenticate the caller and check the scope, subscription tier, and account status the
+/// matched route requires
+///
+/// The resolved [`AuthContext`] is stored in the request extensions, where the handler's
+/// extractor picks it up instead of authenticating again.
+pub async fn authorize(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let (mut parts, mut body) = request.into_parts();
+    let path = parts
+        .extensions
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| parts.uri.path().to_string());
+
+    match route_access(&parts.method, &path) {
+        Some(Access::Public) => {},
+        Some(Access::Requires(scope)) => {
+            let auth = if signature::is_signed(&parts.headers) {
+                // The signature covers the body, so it has to be read before the handler runs
+                let bytes = axum::body::to_bytes(body, state.config.server.max_request_size)
+                    .await
+                    .map_err(|_| ApiError::BadRequest("Request body is too large".to_string()))?;
+                let auth = signature::authenticate(&state, &parts, &bytes).await?;
+                body = Body::from(bytes);
+                auth
+            } else {
+                AuthContext::from_request_parts(&mut parts, &state).await?
+            };
+            auth.require(scope)?;
+            if let Some(feature) = route_feature(&path) {
+                auth.require_feature(feature)?;
+            }
+            if !status_allows(auth.status, &parts.method, &path) {
+                return Err(ApiError::AccountInactive(auth.status));
+            }
+            parts.extensions.insert(auth);
+        },
+        None => {
+            tracing::error!(method = %parts.method, %path, "Route has no access rule");
+            return Err(ApiError::Forbidden(
+                "This endpoint is not available to API keys".to_string(),
+            ));
+        },
+    }
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_and_writes_need_different_scopes() {
+        assert_eq!(
+            route_access(&Method::GET, "/v1/transactions/{transaction_id}"),
+            Some(Access::Requires(Scope::TransactionsRead))
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/v1/transactions"),
+            Some(Access::Requires(Scope::TransactionsWrite))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/transactions/{transaction_id}/request"),
+            Some(Access::Requires(Scope::RawRequestsRead))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/jobs/{job_id}"),
+            Some(Access::Requires(Scope::TransactionsRead))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/jobs/{job_id}/output"),
+            Some(Access::Requires(Scope::TransactionsRead))
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/v1/devices/fingerprint"),
+            Some(Access::Requires(Scope::TransactionsWrite))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/devices/{device_id}/transactions"),
+            Some(Access::Requires(Scope::TransactionsRead))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/ip/{address}"),
+            Some(Access::Requires(Scope::TransactionsRead))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/emails/{email}"),
+            Some(Access::Requires(Scope::TransactionsRead))
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/v1/screening"),
+            Some(Access::Requires(Scope::TransactionsWrite))
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/v1/ingest/{account_id}/processor-events"),
+            Some(Access::Public)
+        );
+        assert_eq!(
+            route_access(&Method::PUT, "/v1/account/processor-secrets/{processor}"),
+            Some(Access::Requires(Scope::AccountWrite))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/rules/suggestions"),
+            Some(Access::Requires(Scope::RulesAdmin))
+        );
+        assert_eq!(
+            route_access(&Method::PUT, "/v1/labels/{transaction_id}"),
+            Some(Access::Requires(Scope::ReportsWrite))
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/v1/reports"),
+            Some(Access::Requires(Scope::ReportsWrite))
+        );
+        assert_eq!(
+            route_access(&Method::PATCH, "/v1/devices/{device_id}"),
+            Some(Access::Requires(Scope::TransactionsWrite))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/users/{user_id}/transactions"),
+            Some(Access::Requires(Scope::TransactionsRead))
+        );
+        assert_eq!(
+            route_access(&Method::DELETE, "/v1/users/{user_id}"),
+            Some(Access::Requires(Scope::UsersWrite))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/cases"),
+            Some(Access::Requires(Scope::CasesRead))
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/v1/cases/{case_id}/resolve"),
+            Some(Access::Requires(Scope::CasesWrite))
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/v1/webhooks/{webhook_id}/rotate-secret"),
+            Some(Access::Requires(Scope::AccountWrite))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/notification-channels"),
+            Some(Access::Requires(Scope::AccountRead))
+        );
+        assert_eq!(
+            route_access(&Method::POST, "/v1/dead-letters/redrive"),
+            Some(Access::Requires(Scope::AccountWrite))
+        );
+        assert_eq!(
+            route_access(&Method::GET, "/v1/health"),
+            Some(Access::Public)
+        );
+    }
+
+    #[test]
+    fn test_paid_features_by_route() {
+        assert_eq!(
+            route_feature("/v1/transactions/{transaction_id}/insights"),
+            Some(Feature::Insights)
+        );
+        assert_eq!(
+            route_feature("/v1/transactions/{transaction_id}/factors"),
+            Some(Feature::Factors)
+        );
+        assert_eq!(route_feature("/v1/users/batch"), Some(Feature::Batch));
+        assert_eq!(route_feature("/v1/ip/{address}"), Some(Feature::Insights));
+        assert_eq!(route_feature("/v1/emails/{email}"), Some(Feature::Insights));
+        assert_eq!(
+            route_feature("/v1/transactions/{transaction_id}/request"),
+            Some(Feature::RawRequests)
+        );
+        assert_eq!(
+            route_feature("/v1/webhooks/{webhook_id}/deliveries"),
+            Some(Feature::Webhooks)
+        );
+        assert_eq!(route_feature("/v1/transactions/{transaction_id}"), None);
+        assert_eq!(route_feature("/v1/analytics"), None);
+    }
+
+    #[test]
+    fn test_account_status_limits_routes() {
+        use AccountStatus::*;
+        let post = Method::POST;
+        let get = Method::GET;
+        assert!(status_allows(Active, &post, "/v1/transactions"));
+        assert!(!status_allows(Suspended, &post, "/v1/transactions"));
+        assert!(!status_allows(Suspended, &post, "/v1/transactions/batch"));
+        assert!(status_allows(Suspended, &get, "/v1/transactions"));
+        assert!(status_allows(Suspended, &post, "/v1/account/close"));
+        assert!(!status_allows(Closed, &get, "/v1/transactions"));
+        assert!(!status_allows(Closed, &Method::PATCH, "/v1/account"));
+        assert!(status_allows(Closed, &get, "/v1/account"));
+        assert!(status_allows(Closed, &get, "/v1/account/usage"));
+        assert!(status_allows(Closed, &post, "/v1/account/reactivate"));
+        assert!(!status_allows(Closed, &post, "/v1/accountant"));
+    }
+
+    #[test]
+    fn test_unmapped_routes_have_no_rule() {
+        assert_eq!(route_access(&Method::POST, "/v1/analytics"), None);
+        assert_eq!(route_access(&Method::GET, "/v1/billing"), None);
+        assert_eq!(route_access(&Method::POST, "/v1/jobs/{job_id}"), None);
+        assert_eq!(route_access(&Method::DELETE, "/v1/ip/{address}"), None);
+        assert_eq!(route_access(&Method::POST, "/v1/emails/{email}"), None);
+    }
+}
@@ -0,0 +1,73 @@
+//! Client certificate authentication
+//!
+//! When the server terminates mutual TLS (see [`crate::tls`]), the certificate a client
+//! presented is attached to each of its requests. Certificates listed in
+//! `MTLS_CLIENT_CERTIFICATES` authenticate as their account with every scope, which suits
+//! server-to-server scoring traffic that should not handle API keys.
+
+use sha2::{Digest, Sha256};
+
+use super::{AuthContext, ScopeSet};
+use crate::{api::ApiError, database::repositories::AccountRepo, state::AppState};
+
+/// Certificate presented by the client over mutual TLS, stored in request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// Lowercase hex SHA-256 of the DER-encoded certificate
+    pub fingerprint: String,
+}
+
+impl ClientCertificate {
+    /// Describe a DER-encoded certificate
+    pub fn from_der(der: &[u8]) -> Self {
+        Self {
+            fingerprint: hex::encode(Sha256::digest(der)),
+        }
+    }
+}
+
+/// Authenticate the account mapped to `certificate`
+pub async fn authenticate(
+    state: &AppState,
+    certificate: &ClientCertificate,
+) -> Result<AuthContext, ApiError> {
+    let Some(&account_id) = state
+        .config
+        .auth
+        .mtls_client_certificates
+        .get(&certificate.fingerprint)
+    else {
+        tracing::debug!(fingerprint = %certificate.fingerprint, "Unmapped client certificate");
+        return Err(ApiError::Unauthorized);
+    };
+
+    let access = AccountRepo::find_access(state.database.pool(), account_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.into()))?
+        .ok_or_else(|| {
+            tracing::warn!(%account_id, "Client certificate mapped to an unknown account");
+            ApiError::Unauthorized
+        })?;
+
+    Ok(AuthContext {
+        account_id,
+        api_key_id: None,
+        scopes: ScopeSet::all(),
+        sandbox: access.sandbox,
+        tier: access.subscription_tier,
+        status: access.status,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_is_lowercase_sha256() {
+        assert_eq!(
+            ClientCertificate::from_der(b"certificate").fingerprint,
+            "03d66dd08835c1ca3f128cceacd1f31ac94163096b20f445ae84285bc0832d72"
+        );
+    }
+}
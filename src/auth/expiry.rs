@@ -0,0 +1,64 @@
+//! Reminders for API keys nearing expiry
+//!
+//! Expired keys are already refused at authentication; this job gives their owners notice
+//! beforehand. Each key is flagged once per expiry date, and again if the expiry is extended
+//! and approaches a second time. Accounts that turned off key expiry notifications are still
+//! logged but get no event.
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    config::AuthConfig,
+    database::repositories::{AccountRepo, OutboxRepo},
+    outbox::{API_KEY_EXPIRING, ApiKeyExpiring},
+};
+
+/// Spawn a background task that periodically flags keys nearing expiry
+pub fn spawn_key_expiry_reminders(pool: PgPool, config: AuthConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = std::time::Duration::from_secs(config.key_expiry_check_interval_seconds);
+        loop {
+            match remind_expiring_keys(&pool, &config, Utc::now()).await {
+                Ok(0) => {},
+                Ok(flagged) => tracing::info!(flagged, "Flagged API keys nearing expiry"),
+                Err(e) => tracing::error!(error = %e, "API key expiry check failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Flag keys expiring within the warning window after `now`, returning how many were flagged
+pub async fn remind_expiring_keys(
+    pool: &PgPool,
+    config: &AuthConfig,
+    now: DateTime<Utc>,
+) -> anyhow::Result<usize> {
+    let cutoff = now + ChronoDuration::days(i64::from(config.key_expiry_warning_days));
+
+    let mut tx = pool.begin().await?;
+    let keys = AccountRepo::claim_expiring_keys(&mut *tx, cutoff).await?;
+    for key in &keys {
+        tracing::warn!(
+            account_id = %key.account_id,
+            api_key_id = %key.id,
+            name = %key.name,
+            expires_at = %key.expires_at,
+            "API key expires soon"
+        );
+        if config.key_expiry_alerts_enabled && key.notify {
+            let payload = serde_json::to_value(ApiKeyExpiring {
+                api_key_id: key.id,
+                name: key.name.clone(),
+                expires_at: key.expires_at,
+            })
+            .unwrap_or_default();
+            OutboxRepo::insert(&mut *tx, key.account_id, API_KEY_EXPIRING, key.id, payload).await?;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(keys.len())
+}
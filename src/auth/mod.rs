@@ -0,0 +1,172 @@
+//! API key authentication
+//!
+//! Keys are accepted either in the configured API key header (`X-API-Key` by default) or as an
+//! `Authorization: Bearer` token. Only SHA-256 hashes of keys are stored. Keys with a signing
+//! secret may instead sign requests (see [`signature`]), and requests without a key may
+//! authenticate with a mutual TLS client certificate (see [`client_cert`]). Each caller carries
+//! a set of [`Scope`]s, checked per route by the [`authorize`] middleware. Operator endpoints
+//! take a separate admin token (see [`admin`]).
+
+pub mod admin;
+mod authorize;
+pub mod client_cert;
+pub mod expiry;
+mod nonces;
+mod scopes;
+pub mod signature;
+
+pub use authorize::{Access, authorize, route_access, route_feature, status_allows};
+pub use client_cert::ClientCertificate;
+pub use nonces::NonceCache;
+pub use scopes::{Scope, ScopeSet};
+
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, header, request::Parts},
+};
+use uuid::Uuid;
+
+use crate::{
+    api::ApiError,
+    database::{Tenant, repositories::AccountRepo},
+    models::account::{AccountStatus, Feature, SubscriptionTier},
+    state::AppState,
+    utils::sha256_hex,
+};
+
+/// Identity of an authenticated API caller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthContext {
+    /// Account the key belongs to
+    pub account_id: Uuid,
+    /// API key used for the request, or `None` for a client certificate
+    pub api_key_id: Option<Uuid>,
+    /// Scopes granted to the key
+    pub scopes: ScopeSet,
+    /// The key belongs to a sandbox account: transactions it scores are marked as tests and
+    /// do not count towards quotas
+    pub sandbox: bool,
+    /// Subscription tier of the account, which decides the features it may use
+    pub tier: SubscriptionTier,
+    /// Lifecycle status of the account, which decides whether it may score
+    pub status: AccountStatus,
+}
+
+impl AuthContext {
+    /// Fail with 403 unless the key holds `scope`
+    pub fn require(&self, scope: Scope) -> Result<(), ApiError> {
+        if self.scopes.contains(scope) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden(format!(
+                "API key lacks the '{scope}' scope"
+            )))
+        }
+    }
+
+    /// Scope for repository queries made on behalf of the caller
+    pub fn tenant(&self) -> Tenant {
+        Tenant::from(self)
+    }
+
+    /// Fail with 403 `upgrade_required` unless the account's tier includes `feature`
+    pub fn require_feature(&self, feature: Feature) -> Result<(), ApiError> {
+        if self.tier.can_access_feature(feature) {
+            Ok(())
+        } else {
+            Err(ApiError::UpgradeRequired {
+                feature,
+                current_tier: self.tier,
+            })
+        }
+    }
+}
+
+impl FromRequestParts<AppState> for AuthContext {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, ApiError> {
+        // Already resolved by the authorization middleware
+        if let Some(auth) = parts.extensions.get::<AuthContext>() {
+            return Ok(*auth);
+        }
+
+        let Some(key) = extract_api_key(&parts.headers, &state.config.auth.api_key_header) else {
+            // An API key takes precedence, so a certificate only counts when none was sent
+            return match parts.extensions.get::<ClientCertificate>() {
+                Some(certificate) => client_cert::authenticate(state, certificate).await,
+                None => Err(ApiError::Unauthorized),
+            };
+        };
+
+        let api_key = AccountRepo::authenticate_api_key(state.database.pool(), &hash_api_key(key))
+            .await
+            .map_err(|e| ApiError::Internal(e.into()))?
+            .ok_or(ApiError::Unauthorized)?;
+
+        Ok(AuthContext {
+            account_id: api_key.account_id,
+            api_key_id: Some(api_key.id),
+            scopes: ScopeSet::from_stored(api_key.scopes.as_deref()),
+            sandbox: api_key.sandbox,
+            tier: api_key.subscription_tier,
+            status: api_key.status,
+        })
+    }
+}
+
+/// Pull the raw API key from the request headers
+pub fn extract_api_key<'a>(headers: &'a HeaderMap, api_key_header: &str) -> Option<&'a str> {
+    let from_header = headers
+        .get(api_key_header)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim);
+
+    let from_bearer = || {
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+    };
+
+    from_header.or_else(from_bearer).filter(|k| !k.is_empty())
+}
+
+/// Hash an API key for storage and lookup
+pub fn hash_api_key(key: &str) -> String {
+    sha256_hex(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_extract_from_api_key_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-API-Key", HeaderValue::from_static("fsg_live_abc"));
+        assert_eq!(extract_api_key(&headers, "X-API-Key"), Some("fsg_live_abc"));
+    }
+
+    #[test]
+    fn test_extract_from_bearer_token() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer fsg_live_abc"),
+        );
+        assert_eq!(extract_api_key(&headers, "X-API-Key"), Some("fsg_live_abc"));
+    }
+
+    #[test]
+    fn test_missing_or_empty_key() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(extract_api_key(&headers, "X-API-Key"), None);
+
+        headers.insert("X-API-Key", HeaderValue::from_static(" "));
+        assert_eq!(extract_api_key(&headers, "X-API-Key"), None);
+    }
+}
@@ -0,0 +1,94 @@
+//! Replay protection for signed requests
+//!
+//! Each accepted signature is remembered until its timestamp falls outside the tolerance
+//! window, after which the timestamp check alone rejects it. Redis shares the record across
+//! instances; the in-memory fallback only covers the instance that saw the request.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use redis::{RedisResult, aio::ConnectionManager};
+
+/// Entries kept in memory before expired ones are swept
+const MEMORY_SWEEP_THRESHOLD: usize = 10_000;
+
+/// Record of nonces already used
+#[derive(Clone)]
+pub enum NonceCache {
+    /// Shared through Redis
+    Redis(ConnectionManager),
+    /// Local to this process
+    Memory(Arc<Mutex<HashMap<String, Instant>>>),
+}
+
+impl NonceCache {
+    /// Share nonces through `redis`, or keep them in memory when it is `None`
+    pub fn new(redis: Option<ConnectionManager>) -> Self {
+        redis.map_or_else(Self::memory, Self::Redis)
+    }
+
+    /// Cache local to this process
+    pub fn memory() -> Self {
+        Self::Memory(Arc::default())
+    }
+
+    /// Record `nonce` for `ttl`, returning `false` if it was already recorded
+    pub async fn claim(&self, nonce: &str, ttl: Duration) -> RedisResult<bool> {
+        match self {
+            Self::Redis(connection) => {
+                let stored: Option<String> = redis::cmd("SET")
+                    .arg(format!("fusegu:nonce:{nonce}"))
+                    .arg(1)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(ttl.as_secs().max(1))
+                    .query_async(&mut connection.clone())
+                    .await?;
+                Ok(stored.is_some())
+            },
+            Self::Memory(nonces) => {
+                let now = Instant::now();
+                let mut nonces = nonces.lock().unwrap_or_else(|e| e.into_inner());
+                if nonces.len() >= MEMORY_SWEEP_THRESHOLD {
+                    nonces.retain(|_, expires_at| *expires_at > now);
+                }
+                match nonces.get(nonce) {
+                    Some(expires_at) if *expires_at > now => Ok(false),
+                    _ => {
+                        nonces.insert(nonce.to_string(), now + ttl);
+                        Ok(true)
+                    },
+                }
+            },
+        }
+    }
+}
+
+impl fmt::Debug for NonceCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Redis(_) => f.write_str("NonceCache::Redis"),
+            Self::Memory(_) => f.write_str("NonceCache::Memory"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_cache_rejects_reuse_until_expiry() {
+        let cache = NonceCache::memory();
+        assert!(cache.claim("a", Duration::from_secs(60)).await.unwrap());
+        assert!(!cache.claim("a", Duration::from_secs(60)).await.unwrap());
+        assert!(cache.claim("b", Duration::from_secs(60)).await.unwrap());
+
+        assert!(cache.claim("c", Duration::ZERO).await.unwrap());
+        assert!(cache.claim("c", Duration::ZERO).await.unwrap());
+    }
+}
@@ -0,0 +1,194 @@
+//! Permissions an API key can be granted
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A permission granted to an API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub enum Scope {
+    /// Read transactions and their assessments
+    #[serde(rename = "transactions:read")]
+    TransactionsRead,
+    /// Submit transactions for scoring
+    #[serde(rename = "transactions:write")]
+    TransactionsWrite,
+    /// Read the stored, redacted requests transactions were scored on
+    #[serde(rename = "raw_requests:read")]
+    RawRequestsRead,
+    /// Read users
+    #[serde(rename = "users:read")]
+    UsersRead,
+    /// Modify and delete users
+    #[serde(rename = "users:write")]
+    UsersWrite,
+    /// Read analytics
+    #[serde(rename = "analytics:read")]
+    AnalyticsRead,
+    /// Read generated reports
+    #[serde(rename = "reports:read")]
+    ReportsRead,
+    /// Report transaction outcomes
+    #[serde(rename = "reports:write")]
+    ReportsWrite,
+    /// Manage scoring rules
+    #[serde(rename = "rules:admin")]
+    RulesAdmin,
+    /// Read account settings and usage
+    #[serde(rename = "account:read")]
+    AccountRead,
+    /// Change account settings
+    #[serde(rename = "account:write")]
+    AccountWrite,
+    /// Read the account's organization, its members, and invitations
+    #[serde(rename = "organization:read")]
+    OrganizationRead,
+    /// Manage the organization, its members, and invitations
+    #[serde(rename = "organization:write")]
+    OrganizationWrite,
+    /// Read manual review cases
+    #[serde(rename = "cases:read")]
+    CasesRead,
+    /// Claim, annotate, and resolve manual review cases
+    #[serde(rename = "cases:write")]
+    CasesWrite,
+}
+
+impl Scope {
+    /// Every scope, in declaration order
+    pub const ALL: [Scope; 15] = [
+        Scope::TransactionsRead,
+        Scope::TransactionsWrite,
+        Scope::RawRequestsRead,
+        Scope::UsersRead,
+        Scope::UsersWrite,
+        Scope::AnalyticsRead,
+        Scope::ReportsRead,
+        Scope::ReportsWrite,
+        Scope::RulesAdmin,
+        Scope::AccountRead,
+        Scope::AccountWrite,
+        Scope::OrganizationRead,
+        Scope::OrganizationWrite,
+        Scope::CasesRead,
+        Scope::CasesWrite,
+    ];
+
+    /// Name used in storage and error messages
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::TransactionsRead => "transactions:read",
+            Scope::TransactionsWrite => "transactions:write",
+            Scope::RawRequestsRead => "raw_requests:read",
+            Scope::UsersRead => "users:read",
+            Scope::UsersWrite => "users:write",
+            Scope::AnalyticsRead => "analytics:read",
+            Scope::ReportsRead => "reports:read",
+            Scope::ReportsWrite => "reports:write",
+            Scope::RulesAdmin => "rules:admin",
+            Scope::AccountRead => "account:read",
+            Scope::AccountWrite => "account:write",
+            Scope::OrganizationRead => "organization:read",
+            Scope::OrganizationWrite => "organization:write",
+            Scope::CasesRead => "cases:read",
+            Scope::CasesWrite => "cases:write",
+        }
+    }
+
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == name)
+            .ok_or_else(|| format!("unknown scope '{name}'"))
+    }
+}
+
+/// Set of scopes held by a caller
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScopeSet(u16);
+
+impl ScopeSet {
+    /// Every scope
+    pub fn all() -> Self {
+        Scope::ALL.into_iter().collect()
+    }
+
+    /// Resolve the scopes stored for an API key, where `None` grants every scope
+    ///
+    /// Unknown names are skipped with a warning, so a scope removed from the code does not
+    /// lock its holders out of everything else.
+    pub fn from_stored(names: Option<&[String]>) -> Self {
+        let Some(names) = names else {
+            return Self::all();
+        };
+        names
+            .iter()
+            .filter_map(|name| {
+                name.parse::<Scope>()
+                    .inspect_err(|e| tracing::warn!(error = %e, "Ignoring stored API key scope"))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Whether `scope` is in the set
+    pub fn contains(self, scope: Scope) -> bool {
+        self.0 & scope.bit() != 0
+    }
+}
+
+impl FromIterator<Scope> for ScopeSet {
+    fn from_iter<I: IntoIterator<Item = Scope>>(scopes: I) -> Self {
+        Self(scopes.into_iter().fold(0, |bits, scope| bits | scope.bit()))
+    }
+}
+
+impl fmt::Debug for ScopeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(Scope::ALL.into_iter().filter(|scope| self.contains(*scope)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_names_round_trip() {
+        for scope in Scope::ALL {
+            assert_eq!(scope.as_str().parse::<Scope>(), Ok(scope));
+            assert_eq!(
+                serde_json::to_value(scope).unwrap(),
+                serde_json::json!(scope.as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn test_scopes_from_storage() {
+        assert_eq!(ScopeSet::from_stored(None), ScopeSet::all());
+
+        let names = ["users:read".to_string(), "users:admin".to_string()];
+        let scopes = ScopeSet::from_stored(Some(&names));
+        assert!(scopes.contains(Scope::UsersRead));
+        assert!(!scopes.contains(Scope::UsersWrite));
+        assert_eq!(ScopeSet::from_stored(Some(&[])), ScopeSet::default());
+    }
+}
@@ -0,0 +1,239 @@
+//! HMAC-signed requests
+//!
+//! Callers that cannot safely hold a bearer key may sign each request with their key's signing
+//! secret instead. The signature is the hex HMAC-SHA256 of
+//!
+//! ```text
+//! {timestamp}\n{METHOD}\n{path and query}\n{hex SHA-256 of the body}
+//! ```
+//!
+//! sent in `X-Fusegu-Signature`, with the key ID in `X-Fusegu-Key-Id` and the Unix timestamp
+//! in `X-Fusegu-Timestamp`. Requests outside the configured clock tolerance are rejected, and
+//! a signature is accepted only once within it.
+
+use std::time::Duration;
+
+use axum::{
+    extract::OriginalUri,
+    http::{HeaderMap, Method, request::Parts},
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::{AuthContext, ScopeSet};
+use crate::{api::ApiError, database::repositories::AccountRepo, state::AppState};
+
+/// Header carrying the ID of the signing key
+pub const KEY_ID_HEADER: &str = "x-fusegu-key-id";
+/// Header carrying the Unix timestamp the request was signed at
+pub const TIMESTAMP_HEADER: &str = "x-fusegu-timestamp";
+/// Header carrying the hex signature
+pub const SIGNATURE_HEADER: &str = "x-fusegu-signature";
+
+/// Whether the request claims to be signed
+pub fn is_signed(headers: &HeaderMap) -> bool {
+    headers.contains_key(SIGNATURE_HEADER)
+}
+
+/// String covered by the signature
+pub fn signing_payload(
+    timestamp: i64,
+    method: &Method,
+    path_and_query: &str,
+    body: &[u8],
+) -> String {
+    format!(
+        "{timestamp}\n{method}\n{path_and_query}\n{}",
+        hex::encode(Sha256::digest(body))
+    )
+}
+
+/// Authenticate a signed request whose body has been read into `body`
+pub async fn authenticate(
+    state: &AppState,
+    parts: &Parts,
+    body: &[u8],
+) -> Result<AuthContext, ApiError> {
+    let key_id: Uuid = header(&parts.headers, KEY_ID_HEADER)?
+        .parse()
+        .map_err(|_| ApiError::Unauthorized)?;
+    let timestamp: i64 = header(&parts.headers, TIMESTAMP_HEADER)?
+        .parse()
+        .map_err(|_| ApiError::Unauthorized)?;
+    let signature = header(&parts.headers, SIGNATURE_HEADER)?;
+
+    let tolerance = state.config.auth.signature_tolerance_seconds;
+    if Utc::now().timestamp().abs_diff(timestamp) > tolerance {
+        tracing::debug!(%key_id, timestamp, "Signed request outside the clock tolerance");
+        return Err(ApiError::Unauthorized);
+    }
+
+    let key = AccountRepo::find_signing_key(state.database.pool(), key_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.into()))?
+        .ok_or(ApiError::Unauthorized)?;
+
+    // Nested routers see the URI with their prefix stripped; the client signed the full path
+    let uri = parts
+        .extensions
+        .get::<OriginalUri>()
+        .map_or(&parts.uri, |original| &original.0);
+    let path_and_query = uri
+        .path_and_query()
+        .map_or_else(|| uri.path(), |p| p.as_str());
+    let payload = signing_payload(timestamp, &parts.method, path_and_query, body);
+    let Some(mac) = verify(&key.signing_secret, signature, &payload) else {
+        tracing::debug!(%key_id, "Request signature mismatch");
+        return Err(ApiError::Unauthorized);
+    };
+
+    // Past the tolerance on either side the timestamp check rejects the request by itself. The
+    // nonce is the decoded MAC, since the same bytes can be sent in either hex case
+    let ttl = Duration::from_secs(tolerance.saturating_mul(2));
+    let fresh = state
+        .nonces
+        .claim(&format!("{key_id}:{}", hex::encode(mac)), ttl)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Nonce cache unavailable");
+            ApiError::ServiceUnavailable("Signed requests are temporarily unavailable".to_string())
+        })?;
+    if !fresh {
+        tracing::debug!(%key_id, "Replayed signed request");
+        return Err(ApiError::Unauthorized);
+    }
+
+    AccountRepo::touch_api_key(state.database.pool(), key.id)
+        .await
+        .map_err(|e| ApiError::Internal(e.into()))?;
+
+    Ok(AuthContext {
+        account_id: key.account_id,
+        api_key_id: Some(key.id),
+        scopes: ScopeSet::from_stored(key.scopes.as_deref()),
+        sandbox: key.sandbox,
+        tier: key.subscription_tier,
+        status: key.status,
+    })
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, ApiError> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .ok_or(ApiError::Unauthorized)
+}
+
+/// Check a hex signature of `payload` in constant time, returning the decoded MAC if it matches
+fn verify(secret: &str, signature: &str, payload: &str) -> Option<Vec<u8>> {
+    let signature = hex::decode(signature).ok()?;
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&signature).ok()?;
+    Some(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        database::Database,
+        models::account::SubscriptionTier,
+        services::{EmailIntelService, IpIntelService},
+        test_support::{create_account, test_pool},
+        utils::geo::GeoIpDatabase,
+    };
+
+    const BODY: &[u8] = br#"{"event":{"type":"purchase"}}"#;
+    const SIGNATURE: &str = "1f8dcb60d43b4bbcf7268feb2d844fd3e94e89c3df7720582ef6630506305661";
+
+    fn payload(body: &[u8]) -> String {
+        signing_payload(
+            1760000000,
+            &Method::POST,
+            "/v1/transactions?dry_run=true",
+            body,
+        )
+    }
+
+    #[test]
+    fn test_valid_signature() {
+        assert!(verify("whsec_test", SIGNATURE, &payload(BODY)).is_some());
+        assert!(verify("whsec_test", &SIGNATURE.to_uppercase(), &payload(BODY)).is_some());
+    }
+
+    #[test]
+    fn test_rejects_tampering() {
+        assert!(verify("whsec_test", SIGNATURE, &payload(b"{}")).is_none());
+        assert!(verify("whsec_other", SIGNATURE, &payload(BODY)).is_none());
+        assert!(verify("whsec_test", "not-hex", &payload(BODY)).is_none());
+        assert!(verify("whsec_test", &SIGNATURE[..32], &payload(BODY)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recased_signature_is_a_replay() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let key_id = AccountRepo::insert_api_key(
+            &pool,
+            tenant.id(),
+            &Uuid::new_v4().to_string(),
+            "Signed",
+            None,
+        )
+        .await
+        .unwrap();
+        sqlx::query("UPDATE api_keys SET signing_secret = 'whsec_test' WHERE id = $1")
+            .bind(key_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let config = Config::default();
+        let ip_intel = IpIntelService::new(pool.clone(), None, &config.ip_intel);
+        let email_intel = EmailIntelService::new(&config.email_intel);
+        let state = AppState::new(
+            config,
+            Database::from_pool(pool.clone()),
+            None,
+            None,
+            GeoIpDatabase::disabled(),
+            ip_intel,
+            email_intel,
+        );
+
+        let timestamp = Utc::now().timestamp();
+        let payload = signing_payload(timestamp, &Method::POST, "/v1/transactions", BODY);
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"whsec_test").unwrap();
+        mac.update(payload.as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+        let signed = |signature: String| {
+            Request::post("/v1/transactions")
+                .header(KEY_ID_HEADER, key_id.to_string())
+                .header(TIMESTAMP_HEADER, timestamp)
+                .header(SIGNATURE_HEADER, signature)
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0
+        };
+
+        let auth = authenticate(&state, &signed(signature.clone()), BODY)
+            .await
+            .unwrap();
+        assert_eq!(auth.account_id, tenant.id());
+        let replay = authenticate(&state, &signed(signature.to_uppercase()), BODY).await;
+        assert!(matches!(replay, Err(ApiError::Unauthorized)));
+
+        AccountRepo::delete(&pool, tenant.id()).await.unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+//! Monitoring of review cases against their queue's SLA
+//!
+//! Overdue cases already show in queue listings; this job announces each one once, as a
+//! [`CASE_SLA_BREACHED`] event, when it first passes its due time unresolved. Cases outside
+//! any queue have no due time and are never flagged.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    config::CasesConfig,
+    database::repositories::{CaseRepo, OutboxRepo},
+    outbox::{CASE_SLA_BREACHED, CaseSlaBreached},
+};
+
+/// Spawn a background task that periodically flags cases past their SLA
+pub fn spawn_case_sla_monitor(pool: PgPool, config: CasesConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = std::time::Duration::from_secs(config.sla_check_interval_seconds);
+        loop {
+            match flag_sla_breaches(&pool, Utc::now()).await {
+                Ok(0) => {},
+                Ok(flagged) => tracing::info!(flagged, "Flagged cases past their SLA"),
+                Err(e) => tracing::error!(error = %e, "Case SLA check failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Flag unresolved cases due before `now`, returning how many were flagged
+pub async fn flag_sla_breaches(pool: &PgPool, now: DateTime<Utc>) -> anyhow::Result<usize> {
+    let mut tx = pool.begin().await?;
+    let breaches = CaseRepo::claim_sla_breaches(&mut *tx, now).await?;
+    for breach in &breaches {
+        tracing::warn!(
+            account_id = %breach.account_id,
+            case_id = %breach.id,
+            queue = %breach.queue_name,
+            due_at = %breach.due_at,
+            "Review case past its SLA"
+        );
+        let payload = serde_json::to_value(CaseSlaBreached {
+            case_id: breach.id,
+            transaction_id: breach.transaction_id,
+            queue_id: breach.queue_id,
+            queue_name: breach.queue_name.clone(),
+            reviewer: breach.claimed_by.clone(),
+            due_at: breach.due_at,
+        })
+        .unwrap_or_default();
+        OutboxRepo::insert(
+            &mut *tx,
+            breach.account_id,
+            CASE_SLA_BREACHED,
+            breach.id,
+            payload,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(breaches.len())
+}
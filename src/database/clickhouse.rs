@@ -0,0 +1,421 @@
+//! ClickHouse analytics store
+//!
+//! Scored transactions are streamed into ClickHouse by the outbox dispatcher and aggregated
+//! there for `/v1/analytics`, together with the outcomes customers later report for them, the
+//! decisions reviewers made on review cases, and the disputes processors reported. The client
+//! talks to ClickHouse's HTTP interface; queries bind values as server-side parameters
+//! (`{name:Type}` placeholders sent as `param_<name>`), so nothing caller-supplied is ever
+//! interpolated into SQL.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    config::DatabaseConfig,
+    models::{
+        case::{CaseDecision, CaseKind},
+        dispute::DisputeStatus,
+        transaction::{Disposition, EventType, ReportTag, RiskLevel},
+    },
+};
+
+/// Table holding one row per scored transaction
+pub const TRANSACTION_EVENTS_TABLE: &str = "transaction_events";
+
+/// Table holding the latest reported outcome per transaction
+pub const TRANSACTION_OUTCOMES_TABLE: &str = "transaction_outcomes";
+
+/// Table holding one row per resolved review case
+pub const CASE_DECISIONS_TABLE: &str = "case_decisions";
+
+/// Table holding the latest stage of each dispute
+pub const DISPUTES_TABLE: &str = "disputes";
+
+/// Idempotent DDL applied by [`ClickHouseClient::migrate`]
+///
+/// `ReplacingMergeTree` collapses the duplicates that at-least-once outbox delivery can
+/// produce, since a redelivered event has the same sorting key as the original. For outcomes it
+/// also keeps only the most recent report of each transaction.
+const SCHEMA: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS transaction_events (
+        transaction_id UUID,
+        account_id UUID,
+        user_id Nullable(UUID),
+        event_type LowCardinality(String),
+        risk_score Float64,
+        risk_level LowCardinality(String),
+        disposition LowCardinality(String),
+        shop_id Nullable(String),
+        rule_codes Array(LowCardinality(String)),
+        event_time DateTime64(3, 'UTC'),
+        created_at DateTime64(3, 'UTC')
+    )
+    ENGINE = ReplacingMergeTree
+    PARTITION BY toYYYYMM(event_time)
+    ORDER BY (account_id, event_time, transaction_id)",
+    // Lets per-shop queries skip granules of other shops, since shop_id is not in the sort key
+    "ALTER TABLE transaction_events
+        ADD INDEX IF NOT EXISTS idx_transaction_events_shop_id shop_id TYPE bloom_filter GRANULARITY 4",
+    "ALTER TABLE transaction_events
+        ADD COLUMN IF NOT EXISTS ip_address Nullable(String),
+        ADD COLUMN IF NOT EXISTS device_id Nullable(UUID),
+        ADD COLUMN IF NOT EXISTS email_domain Nullable(String),
+        ADD COLUMN IF NOT EXISTS card_bin Nullable(String)",
+    // JSON feature snapshot; unpacked into typed columns when exported
+    "ALTER TABLE transaction_events ADD COLUMN IF NOT EXISTS features Nullable(String)",
+    // Order value, and the purchase a refund or cancellation undoes, for exposure
+    "ALTER TABLE transaction_events
+        ADD COLUMN IF NOT EXISTS amount Nullable(Float64),
+        ADD COLUMN IF NOT EXISTS currency Nullable(String),
+        ADD COLUMN IF NOT EXISTS original_transaction_id Nullable(UUID)",
+    "CREATE TABLE IF NOT EXISTS transaction_outcomes (
+        transaction_id UUID,
+        account_id UUID,
+        tag LowCardinality(String),
+        occurred_at DateTime64(3, 'UTC'),
+        reported_at DateTime64(3, 'UTC')
+    )
+    ENGINE = ReplacingMergeTree(reported_at)
+    ORDER BY (account_id, transaction_id)",
+    "CREATE TABLE IF NOT EXISTS case_decisions (
+        case_id UUID,
+        account_id UUID,
+        transaction_id UUID,
+        reviewer String,
+        decision LowCardinality(String),
+        resolved_at DateTime64(3, 'UTC')
+    )
+    ENGINE = ReplacingMergeTree
+    PARTITION BY toYYYYMM(resolved_at)
+    ORDER BY (account_id, resolved_at, case_id)",
+    "ALTER TABLE case_decisions
+        ADD COLUMN IF NOT EXISTS kind LowCardinality(String) DEFAULT 'review'",
+    "CREATE TABLE IF NOT EXISTS disputes (
+        dispute_id UUID,
+        account_id UUID,
+        transaction_id UUID,
+        status LowCardinality(String),
+        amount Nullable(Float64),
+        currency Nullable(String),
+        received_at DateTime64(3, 'UTC'),
+        resolved_at Nullable(DateTime64(3, 'UTC')),
+        updated_at DateTime64(3, 'UTC')
+    )
+    ENGINE = ReplacingMergeTree(updated_at)
+    ORDER BY (account_id, dispute_id)",
+];
+
+/// Settings sent with every request so JSON round-trips cleanly through serde
+const DEFAULT_SETTINGS: &[(&str, &str)] = &[
+    ("date_time_input_format", "best_effort"),
+    ("date_time_output_format", "iso"),
+    ("output_format_json_quote_64bit_integers", "0"),
+];
+
+/// Errors returned by [`ClickHouseClient`]
+#[derive(Error, Debug)]
+pub enum ClickHouseError {
+    /// ClickHouse could not be reached or the request timed out
+    #[error("ClickHouse request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// ClickHouse rejected the request
+    #[error("ClickHouse returned {status}: {message}")]
+    Server {
+        /// HTTP status code
+        status: u16,
+        /// Error text reported by ClickHouse
+        message: String,
+    },
+
+    /// A row could not be encoded or decoded
+    #[error("Malformed ClickHouse data: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Result type for ClickHouse operations
+pub type ClickHouseResult<T> = Result<T, ClickHouseError>;
+
+/// Row of [`TRANSACTION_EVENTS_TABLE`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionEventRow {
+    /// Transaction ID
+    pub transaction_id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// User the transaction belongs to, if known
+    pub user_id: Option<Uuid>,
+    /// Type of event
+    pub event_type: EventType,
+    /// Combined risk score
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Recommended action
+    pub disposition: Disposition,
+    /// Shop or merchant identifier
+    pub shop_id: Option<String>,
+    /// Codes of the rules that fired
+    pub rule_codes: Vec<String>,
+    /// When the event happened
+    pub event_time: DateTime<Utc>,
+    /// When the transaction was stored
+    pub created_at: DateTime<Utc>,
+    /// IP address the transaction came from
+    pub ip_address: Option<String>,
+    /// Device the transaction came from
+    pub device_id: Option<Uuid>,
+    /// Lowercased email domain
+    pub email_domain: Option<String>,
+    /// Issuer identification number (BIN) of the payment card
+    pub card_bin: Option<String>,
+    /// Feature snapshot as a JSON object
+    pub features: Option<String>,
+    /// Order amount, in the order's currency
+    pub amount: Option<f64>,
+    /// ISO 4217 currency code of the order
+    pub currency: Option<String>,
+    /// Purchase a refund or cancellation undoes
+    pub original_transaction_id: Option<Uuid>,
+}
+
+/// Row of [`TRANSACTION_OUTCOMES_TABLE`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionOutcomeRow {
+    /// Transaction the outcome belongs to
+    pub transaction_id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Reported outcome
+    pub tag: ReportTag,
+    /// When the reported outcome occurred
+    pub occurred_at: DateTime<Utc>,
+    /// When the report was received; the latest report of a transaction wins
+    pub reported_at: DateTime<Utc>,
+}
+
+/// Row of [`CASE_DECISIONS_TABLE`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaseDecisionRow {
+    /// Resolved case
+    pub case_id: Uuid,
+    /// Whether the case reviewed the transaction or an appeal of it
+    pub kind: CaseKind,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Transaction under review
+    pub transaction_id: Uuid,
+    /// Reviewer who decided the case
+    pub reviewer: String,
+    /// Reviewer's decision
+    pub decision: CaseDecision,
+    /// When the case was resolved
+    pub resolved_at: DateTime<Utc>,
+}
+
+/// Row of [`DISPUTES_TABLE`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisputeRow {
+    /// The dispute
+    pub dispute_id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Disputed transaction
+    pub transaction_id: Uuid,
+    /// Stage the dispute reached
+    pub status: DisputeStatus,
+    /// Disputed amount, in major units
+    pub amount: Option<f64>,
+    /// ISO 4217 currency code of the amount
+    pub currency: Option<String>,
+    /// When the dispute was received
+    pub received_at: DateTime<Utc>,
+    /// When the dispute was won or lost
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// When the dispute last changed; the latest row of a dispute wins
+    pub updated_at: DateTime<Utc>,
+}
+
+/// HTTP client for a single ClickHouse database
+#[derive(Debug, Clone)]
+pub struct ClickHouseClient {
+    http: reqwest::Client,
+    url: String,
+    user: String,
+    password: String,
+    database: String,
+}
+
+impl ClickHouseClient {
+    /// Create a client from the ClickHouse settings in `config`
+    pub fn new(config: &DatabaseConfig) -> ClickHouseResult<Self> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(5))
+            .timeout(Duration::from_secs(30))
+            .build()?;
+        Ok(Self {
+            http,
+            url: config.clickhouse_url.trim_end_matches('/').to_string(),
+            user: config.clickhouse_user.clone(),
+            password: config.clickhouse_password.clone(),
+            database: config.clickhouse_database.clone(),
+        })
+    }
+
+    /// Create the analytics tables if they do not exist yet
+    pub async fn migrate(&self) -> ClickHouseResult<()> {
+        for statement in SCHEMA {
+            self.send(statement.to_string(), &[], &[]).await?;
+        }
+        tracing::info!(database = %self.database, "ClickHouse schema applied");
+        Ok(())
+    }
+
+    /// Run a query and decode each result row as `T`
+    ///
+    /// `params` bind the `{name:Type}` placeholders in `sql`.
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        sql: &str,
+        params: &[(&str, String)],
+    ) -> ClickHouseResult<Vec<T>> {
+        let body = self
+            .send(format!("{sql}\nFORMAT JSONEachRow"), &[], params)
+            .await?;
+        body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(ClickHouseError::from))
+            .collect()
+    }
+
+    /// Run a query and return its result as a Parquet file
+    ///
+    /// Exports can take far longer than interactive queries, so this call waits up to
+    /// `timeout` instead of the client's default.
+    pub async fn query_parquet(
+        &self,
+        sql: &str,
+        params: &[(&str, String)],
+        timeout: Duration,
+    ) -> ClickHouseResult<Vec<u8>> {
+        self.send_bytes(format!("{sql}\nFORMAT Parquet"), &[], params, Some(timeout))
+            .await
+    }
+
+    /// Insert rows into `table`
+    ///
+    /// Uses asynchronous inserts so that many single-row inserts are batched server-side
+    /// instead of each creating a new part.
+    pub async fn insert<T: Serialize>(&self, table: &str, rows: &[T]) -> ClickHouseResult<()> {
+        let mut body = format!("INSERT INTO {table} FORMAT JSONEachRow\n");
+        for row in rows {
+            body.push_str(&serde_json::to_string(row)?);
+            body.push('\n');
+        }
+        self.send(
+            body,
+            &[("async_insert", "1"), ("wait_for_async_insert", "1")],
+            &[],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn send(
+        &self,
+        body: String,
+        settings: &[(&str, &str)],
+        params: &[(&str, String)],
+    ) -> ClickHouseResult<String> {
+        let bytes = self.send_bytes(body, settings, params, None).await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    async fn send_bytes(
+        &self,
+        body: String,
+        settings: &[(&str, &str)],
+        params: &[(&str, String)],
+        timeout: Option<Duration>,
+    ) -> ClickHouseResult<Vec<u8>> {
+        let mut query: Vec<(String, &str)> = vec![("database".to_string(), &self.database)];
+        query.extend(
+            DEFAULT_SETTINGS
+                .iter()
+                .chain(settings)
+                .map(|(name, value)| (name.to_string(), *value)),
+        );
+        query.extend(
+            params
+                .iter()
+                .map(|(name, value)| (format!("param_{name}"), value.as_str())),
+        );
+
+        let mut request = self
+            .http
+            .post(&self.url)
+            .query(&query)
+            .header("X-ClickHouse-User", &self.user)
+            .header("X-ClickHouse-Key", &self.password)
+            .body(body);
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
+
+        let status = response.status();
+        let bytes = response.bytes().await?;
+        if !status.is_success() {
+            return Err(ClickHouseError::Server {
+                status: status.as_u16(),
+                message: String::from_utf8_lossy(&bytes).trim().to_string(),
+            });
+        }
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Format a timestamp for a `DateTime64(3, 'UTC')` query parameter
+pub fn datetime_param(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_datetime_param() {
+        let time = Utc.with_ymd_and_hms(2025, 6, 13, 10, 30, 0).unwrap();
+        assert_eq!(datetime_param(time), "2025-06-13 10:30:00.000");
+    }
+
+    #[test]
+    fn test_row_round_trips_through_clickhouse_json() {
+        // ClickHouse renders DateTime64 with `date_time_output_format=iso` like this
+        let row: TransactionEventRow = serde_json::from_str(
+            r#"{"transaction_id":"550e8400-e29b-41d4-a716-446655440000",
+                "account_id":"6ba7b810-9dad-11d1-80b4-00c04fd430c8","user_id":null,
+                "event_type":"purchase","risk_score":42.5,"risk_level":"medium",
+                "disposition":"review","shop_id":null,"rule_codes":["CVV_MISMATCH"],
+                "event_time":"2025-06-13T10:30:00.000Z","created_at":"2025-06-13T10:30:01.250Z"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(row.rule_codes, vec!["CVV_MISMATCH"]);
+        assert_eq!(
+            row.event_time,
+            Utc.with_ymd_and_hms(2025, 6, 13, 10, 30, 0).unwrap()
+        );
+        let encoded = serde_json::to_string(&row).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TransactionEventRow>(&encoded).unwrap(),
+            row
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! Embedded schema migrations
+//!
+//! Migration files live in the top-level `migrations/` directory and are compiled into the
+//! binary, so a deployed server can always bring its schema up to date on its own.
+
+use sqlx::{PgPool, migrate::Migrator};
+
+/// Migrations embedded from the `migrations/` directory
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Apply all pending migrations to the database
+pub async fn run_migrations(pool: &PgPool) -> anyhow::Result<()> {
+    MIGRATOR.run(pool).await?;
+    tracing::info!("Database migrations applied");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migration_versions_are_sequential() {
+        let versions: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+        let expected: Vec<i64> = (1..=versions.len() as i64).collect();
+        assert_eq!(versions, expected);
+    }
+}
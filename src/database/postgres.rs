@@ -0,0 +1,137 @@
+//! PostgreSQL connection pool setup
+
+use std::time::Duration;
+
+use sqlx::{
+    PgPool,
+    postgres::{PgConnectOptions, PgPoolOptions},
+};
+
+use crate::{config::DatabaseConfig, utils::backoff};
+
+/// Delay before the first startup reconnect attempt
+const BASE_RETRY_DELAY: Duration = Duration::from_millis(500);
+/// Longest delay between startup reconnect attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Create a PostgreSQL connection pool and verify connectivity
+///
+/// The initial connection is retried with exponential backoff so the server can start
+/// alongside a database that is still booting. Once running, the pool replaces broken
+/// connections on its own.
+pub async fn create_postgres_pool(config: &DatabaseConfig) -> anyhow::Result<PgPool> {
+    let options = connect_options(&config.postgres_url, config)?;
+    let pool = connect_with_retry(config, || {
+        pool_options(config).connect_with(options.clone())
+    })
+    .await?;
+
+    tracing::info!(
+        max_connections = config.postgres_max_connections,
+        "PostgreSQL connection pool established"
+    );
+
+    Ok(pool)
+}
+
+/// Create a pool for the read replica, if one is configured, and verify connectivity
+pub async fn create_replica_pool(config: &DatabaseConfig) -> anyhow::Result<Option<PgPool>> {
+    let Some(url) = &config.postgres_replica_url else {
+        return Ok(None);
+    };
+
+    let options = replica_connect_options(url, config)?;
+    let pool = connect_with_retry(config, || {
+        pool_options(config).connect_with(options.clone())
+    })
+    .await?;
+
+    tracing::info!(
+        max_connections = config.postgres_max_connections,
+        "PostgreSQL read replica pool established"
+    );
+
+    Ok(Some(pool))
+}
+
+/// Pool settings shared by the primary and the replica
+pub(crate) fn pool_options(config: &DatabaseConfig) -> PgPoolOptions {
+    PgPoolOptions::new()
+        .max_connections(config.postgres_max_connections)
+        .acquire_timeout(Duration::from_secs(config.postgres_acquire_timeout_seconds))
+        .idle_timeout(Duration::from_secs(config.postgres_idle_timeout_seconds))
+        .test_before_acquire(true)
+}
+
+/// Connection options for the primary
+pub(crate) fn connect_options(
+    url: &str,
+    config: &DatabaseConfig,
+) -> anyhow::Result<PgConnectOptions> {
+    let options: PgConnectOptions = url.parse()?;
+    if config.postgres_statement_timeout_ms == 0 {
+        return Ok(options);
+    }
+    Ok(options.options([(
+        "statement_timeout",
+        config.postgres_statement_timeout_ms.to_string(),
+    )]))
+}
+
+/// Connection options for the replica
+///
+/// Sessions default to read-only so a query routed to the replica by mistake fails loudly
+/// instead of writing to a server that may not accept it.
+pub(crate) fn replica_connect_options(
+    url: &str,
+    config: &DatabaseConfig,
+) -> anyhow::Result<PgConnectOptions> {
+    Ok(connect_options(url, config)?.options([("default_transaction_read_only", "on")]))
+}
+
+/// Run `connect` until it succeeds or the configured number of attempts is used up
+async fn connect_with_retry<F, Fut>(config: &DatabaseConfig, mut connect: F) -> sqlx::Result<PgPool>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = sqlx::Result<PgPool>>,
+{
+    let attempts = config.postgres_connect_retries.max(1);
+    let mut attempt = 1;
+    loop {
+        match connect().await {
+            Ok(pool) => return Ok(pool),
+            Err(e) if attempt < attempts => {
+                let delay = backoff(attempt, BASE_RETRY_DELAY, MAX_RETRY_DELAY);
+                tracing::warn!(
+                    attempt,
+                    max_attempts = attempts,
+                    retry_in_ms = delay.as_millis() as u64,
+                    error = %e,
+                    "PostgreSQL connection failed; retrying"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_timeout_option() {
+        let config = crate::config::Config::default().database;
+        let options = connect_options(&config.postgres_url, &config).unwrap();
+        assert_eq!(options.get_options(), Some("-c statement_timeout=5000"));
+
+        let disabled = DatabaseConfig {
+            postgres_statement_timeout_ms: 0,
+            ..config
+        };
+        let options = connect_options(&disabled.postgres_url, &disabled).unwrap();
+        assert_eq!(options.get_options(), None);
+    }
+}
@@ -0,0 +1,770 @@
+//! Accounts and their API keys
+
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{
+    database::Tenant,
+    models::{
+        account::{AccountStatus, DispositionPolicy, SubscriptionTier},
+        case::CaseAssignment,
+    },
+};
+
+/// Stored account row
+#[derive(Debug, Clone)]
+pub struct AccountRecord {
+    /// Internal account ID
+    pub id: Uuid,
+    /// Public account identifier
+    pub account_id: String,
+    /// Subscription tier
+    pub subscription_tier: SubscriptionTier,
+    /// Whether this is the sandbox namespace of a live account
+    pub sandbox: bool,
+    /// Lifecycle status
+    pub status: AccountStatus,
+    /// When the status last changed
+    pub status_changed_at: Option<DateTime<Utc>>,
+    /// When the closed account is due to be deleted
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
+    /// Address for operational notices
+    pub contact_email: Option<String>,
+    /// How risk levels translate into dispositions
+    pub disposition_policy: DispositionPolicy,
+    /// Notify about API keys approaching expiry
+    pub notify_key_expiry: bool,
+    /// Notify about detected anomalies
+    pub notify_anomalies: bool,
+    /// Block entities behind repeated critical velocity breaches
+    pub auto_block_enabled: bool,
+    /// Minutes an automatic block lasts
+    pub auto_block_ttl_minutes: i32,
+    /// Screen the names on transactions against sanctions lists
+    pub sanctions_screening: bool,
+    /// How new review cases reach reviewers
+    pub case_assignment: CaseAssignment,
+    /// Prepaid funds left
+    pub funds_remaining: f64,
+    /// Scoring requests allowed per billing cycle
+    pub monthly_quota: i32,
+    /// Scoring requests used in the current billing cycle
+    pub queries_used_this_month: i32,
+    /// First day of the current billing cycle
+    pub billing_cycle_start: NaiveDate,
+    /// Day the next billing cycle starts
+    pub billing_cycle_end: NaiveDate,
+    /// When the account was created
+    pub created_at: DateTime<Utc>,
+    /// When the account was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Settings to change on an account; `None` keeps the stored value
+#[derive(Debug, Clone, Default)]
+pub struct AccountSettingsUpdate<'a> {
+    /// New contact address; `Some(None)` removes it
+    pub contact_email: Option<Option<&'a str>>,
+    /// New disposition policy
+    pub disposition_policy: Option<DispositionPolicy>,
+    /// Notify about API keys approaching expiry
+    pub notify_key_expiry: Option<bool>,
+    /// Notify about detected anomalies
+    pub notify_anomalies: Option<bool>,
+    /// Block entities behind repeated critical velocity breaches
+    pub auto_block_enabled: Option<bool>,
+    /// Minutes an automatic block lasts
+    pub auto_block_ttl_minutes: Option<i32>,
+    /// Screen the names on transactions against sanctions lists
+    pub sanctions_screening: Option<bool>,
+    /// How new review cases reach reviewers
+    pub case_assignment: Option<CaseAssignment>,
+}
+
+/// Sandbox flag, tier, and status of an account, for authenticating callers without an API
+/// key
+#[derive(Debug, Clone, Copy)]
+pub struct AccountAccessRecord {
+    /// Whether the account is a sandbox namespace
+    pub sandbox: bool,
+    /// Subscription tier
+    pub subscription_tier: SubscriptionTier,
+    /// Lifecycle status
+    pub status: AccountStatus,
+}
+
+/// Status change to record in the audit log
+#[derive(Debug, Clone)]
+pub struct NewStatusChange<'a> {
+    /// Internal ID of the account
+    pub account_id: Uuid,
+    /// Public identifier of the account, kept in case the account is deleted
+    pub public_account_id: &'a str,
+    /// Status before the change
+    pub from_status: AccountStatus,
+    /// Status after the change, or `"deleted"`
+    pub to_status: &'a str,
+    /// Reason given for the change
+    pub reason: Option<&'a str>,
+    /// Key that requested the change, if any
+    pub api_key_id: Option<Uuid>,
+}
+
+/// Closed account whose deletion is due
+#[derive(Debug, Clone)]
+pub struct DueDeletionRecord {
+    /// Internal account ID
+    pub id: Uuid,
+    /// Public account identifier
+    pub account_id: String,
+    /// When the account was scheduled to be deleted
+    pub deletion_scheduled_at: DateTime<Utc>,
+}
+
+/// Quota and usage of an account's current billing cycle
+#[derive(Debug, Clone)]
+pub struct AccountUsageRecord {
+    /// Scoring requests allowed per billing cycle
+    pub monthly_quota: i32,
+    /// Scoring requests used in the current billing cycle
+    pub queries_used_this_month: i32,
+    /// First day of the billing cycle
+    pub billing_cycle_start: NaiveDate,
+    /// Day the next billing cycle starts
+    pub billing_cycle_end: NaiveDate,
+    /// Whether the cycle is over and due to be rolled over
+    pub cycle_ended: bool,
+}
+
+/// API key resolved during authentication
+#[derive(Debug, Clone)]
+pub struct ApiKeyRecord {
+    /// API key ID
+    pub id: Uuid,
+    /// Account the key belongs to
+    pub account_id: Uuid,
+    /// Scopes granted to the key, or `None` for an unrestricted key
+    pub scopes: Option<Vec<String>>,
+    /// Whether the key belongs to a sandbox account
+    pub sandbox: bool,
+    /// Subscription tier of the key's account
+    pub subscription_tier: SubscriptionTier,
+    /// Lifecycle status of the key's account
+    pub status: AccountStatus,
+}
+
+/// API key able to sign requests, resolved by ID
+#[derive(Debug, Clone)]
+pub struct SigningKeyRecord {
+    /// API key ID
+    pub id: Uuid,
+    /// Account the key belongs to
+    pub account_id: Uuid,
+    /// Scopes granted to the key, or `None` for an unrestricted key
+    pub scopes: Option<Vec<String>>,
+    /// Whether the key belongs to a sandbox account
+    pub sandbox: bool,
+    /// Subscription tier of the key's account
+    pub subscription_tier: SubscriptionTier,
+    /// Lifecycle status of the key's account
+    pub status: AccountStatus,
+    /// Shared HMAC secret
+    pub signing_secret: String,
+}
+
+/// API key nearing expiry
+#[derive(Debug, Clone)]
+pub struct ExpiringKeyRecord {
+    /// API key ID
+    pub id: Uuid,
+    /// Account the key belongs to
+    pub account_id: Uuid,
+    /// Key name
+    pub name: String,
+    /// When the key stops working
+    pub expires_at: DateTime<Utc>,
+    /// Whether the account wants to be notified about expiring keys
+    pub notify: bool,
+}
+
+/// Queries over `accounts` and `api_keys`
+pub struct AccountRepo;
+
+impl AccountRepo {
+    /// Fetch an account by its internal ID
+    pub async fn find_by_id(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Option<AccountRecord>> {
+        sqlx::query_as!(
+            AccountRecord,
+            r#"
+            SELECT id, account_id, subscription_tier AS "subscription_tier: SubscriptionTier",
+                   sandbox_of IS NOT NULL AS "sandbox!", status AS "status: AccountStatus",
+                   status_changed_at, deletion_scheduled_at, contact_email, disposition_policy AS "disposition_policy: DispositionPolicy",
+                   notify_key_expiry, notify_anomalies, auto_block_enabled,
+                   auto_block_ttl_minutes, sanctions_screening,
+                   case_assignment AS "case_assignment: CaseAssignment", funds_remaining,
+                   monthly_quota, queries_used_this_month, billing_cycle_start, billing_cycle_end, created_at,
+                   updated_at
+            FROM accounts
+            WHERE id = $1
+            "#,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Change an account's self-service settings, returning the updated account
+    pub async fn update_settings(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        update: &AccountSettingsUpdate<'_>,
+    ) -> sqlx::Result<Option<AccountRecord>> {
+        sqlx::query_as!(
+            AccountRecord,
+            r#"
+            UPDATE accounts
+            SET contact_email = CASE WHEN $2 THEN $3 ELSE contact_email END,
+                disposition_policy = COALESCE($4, disposition_policy),
+                notify_key_expiry = COALESCE($5, notify_key_expiry),
+                notify_anomalies = COALESCE($6, notify_anomalies),
+                auto_block_enabled = COALESCE($7, auto_block_enabled),
+                auto_block_ttl_minutes = COALESCE($8, auto_block_ttl_minutes),
+                sanctions_screening = COALESCE($9, sanctions_screening),
+                case_assignment = COALESCE($10, case_assignment)
+            WHERE id = $1
+            RETURNING id, account_id, subscription_tier AS "subscription_tier: SubscriptionTier",
+                      sandbox_of IS NOT NULL AS "sandbox!", status AS "status: AccountStatus",
+                      status_changed_at, deletion_scheduled_at, contact_email,
+                      disposition_policy AS "disposition_policy: DispositionPolicy",
+                      notify_key_expiry, notify_anomalies, auto_block_enabled,
+                      auto_block_ttl_minutes, sanctions_screening,
+                      case_assignment AS "case_assignment: CaseAssignment", funds_remaining,
+                      monthly_quota, queries_used_this_month, billing_cycle_start,
+                      billing_cycle_end, created_at, updated_at
+            "#,
+            tenant.id(),
+            update.contact_email.is_some(),
+            update.contact_email.flatten(),
+            update.disposition_policy as _,
+            update.notify_key_expiry,
+            update.notify_anomalies,
+            update.auto_block_enabled,
+            update.auto_block_ttl_minutes,
+            update.sanctions_screening,
+            update.case_assignment as _
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Move a live account and its sandbox from status `from` to `to`, returning `false` if
+    /// the account is not in `from`
+    ///
+    /// `deletion_scheduled_at` replaces the stored deletion time, so leaving it out cancels a
+    /// scheduled deletion.
+    pub async fn set_status(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        from: AccountStatus,
+        to: AccountStatus,
+        deletion_scheduled_at: Option<DateTime<Utc>>,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET status = $3, status_changed_at = CURRENT_TIMESTAMP, deletion_scheduled_at = $4
+            WHERE ((id = $1 AND sandbox_of IS NULL) OR sandbox_of = $1) AND status = $2
+            "#,
+            tenant.id(),
+            from as _,
+            to as _,
+            deletion_scheduled_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Append a status change to the audit log
+    pub async fn log_status_change(
+        executor: impl PgExecutor<'_>,
+        change: &NewStatusChange<'_>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO account_status_changes
+                (account_id, public_account_id, from_status, to_status, reason, api_key_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            change.account_id,
+            change.public_account_id,
+            change.from_status as _,
+            change.to_status,
+            change.reason,
+            change.api_key_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Lock the closed live account whose deletion has been due the longest at `now`, if any
+    ///
+    /// Locked rows are skipped, so several instances can delete accounts side by side.
+    pub async fn claim_due_deletion(
+        executor: impl PgExecutor<'_>,
+        now: DateTime<Utc>,
+    ) -> sqlx::Result<Option<DueDeletionRecord>> {
+        sqlx::query_as!(
+            DueDeletionRecord,
+            r#"
+            SELECT id, account_id, deletion_scheduled_at AS "deletion_scheduled_at!"
+            FROM accounts
+            WHERE status = 'closed' AND sandbox_of IS NULL AND deletion_scheduled_at <= $1
+            ORDER BY deletion_scheduled_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+            "#,
+            now
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Delete an account; its sandbox and all data of both go with it
+    pub async fn delete(executor: impl PgExecutor<'_>, id: Uuid) -> sqlx::Result<bool> {
+        let result = sqlx::query!("DELETE FROM accounts WHERE id = $1", id)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Whether an account wants to be notified about detected anomalies
+    pub async fn notifies_anomalies(executor: impl PgExecutor<'_>, id: Uuid) -> sqlx::Result<bool> {
+        let notify = sqlx::query_scalar!("SELECT notify_anomalies FROM accounts WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await?;
+        Ok(notify.unwrap_or(false))
+    }
+
+    /// Minutes an account's automatic blocks last, or `None` if it does not block
+    /// automatically
+    pub async fn auto_block_ttl_minutes(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Option<i32>> {
+        let ttl = sqlx::query_scalar!(
+            r#"
+            SELECT auto_block_ttl_minutes
+            FROM accounts
+            WHERE id = $1 AND auto_block_enabled
+            "#,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(ttl)
+    }
+
+    /// Whether an account screens the names on its transactions against sanctions lists
+    pub async fn sanctions_screening(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<bool> {
+        let screening = sqlx::query_scalar!(
+            "SELECT sanctions_screening FROM accounts WHERE id = $1",
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(screening.unwrap_or(false))
+    }
+
+    /// How an account's new review cases reach reviewers, or manual if the account is gone
+    pub async fn case_assignment(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<CaseAssignment> {
+        let assignment = sqlx::query_scalar!(
+            r#"SELECT case_assignment AS "case_assignment: CaseAssignment" FROM accounts WHERE id = $1"#,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(assignment.unwrap_or_default())
+    }
+
+    /// Disposition policy of an account, or the default if the account is gone
+    pub async fn disposition_policy(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<DispositionPolicy> {
+        let policy = sqlx::query_scalar!(
+            r#"
+            SELECT disposition_policy AS "disposition_policy: DispositionPolicy"
+            FROM accounts
+            WHERE id = $1
+            "#,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(policy.unwrap_or_default())
+    }
+
+    /// Resolve an active, unexpired API key by hash and record that it was used
+    pub async fn authenticate_api_key(
+        executor: impl PgExecutor<'_>,
+        key_hash: &str,
+    ) -> sqlx::Result<Option<ApiKeyRecord>> {
+        sqlx::query_as!(
+            ApiKeyRecord,
+            r#"
+            UPDATE api_keys k
+            SET last_used_at = CURRENT_TIMESTAMP
+            FROM accounts a
+            WHERE a.id = k.account_id
+              AND k.key_hash = $1
+              AND k.is_active
+              AND (k.expires_at IS NULL OR k.expires_at > CURRENT_TIMESTAMP)
+            RETURNING k.id, k.account_id, k.scopes, a.sandbox_of IS NOT NULL AS "sandbox!",
+                      a.subscription_tier AS "subscription_tier: SubscriptionTier",
+                      a.status AS "status: AccountStatus"
+            "#,
+            key_hash
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Find an active, unexpired API key that has a signing secret
+    pub async fn find_signing_key(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+    ) -> sqlx::Result<Option<SigningKeyRecord>> {
+        sqlx::query_as!(
+            SigningKeyRecord,
+            r#"
+            SELECT k.id, k.account_id, k.scopes, a.sandbox_of IS NOT NULL AS "sandbox!",
+                   a.subscription_tier AS "subscription_tier: SubscriptionTier",
+                   a.status AS "status: AccountStatus", k.signing_secret AS "signing_secret!"
+            FROM api_keys k
+            JOIN accounts a ON a.id = k.account_id
+            WHERE k.id = $1
+              AND k.signing_secret IS NOT NULL
+              AND k.is_active
+              AND (k.expires_at IS NULL OR k.expires_at > CURRENT_TIMESTAMP)
+            "#,
+            id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Record that an API key was used
+    pub async fn touch_api_key(executor: impl PgExecutor<'_>, id: Uuid) -> sqlx::Result<()> {
+        sqlx::query!(
+            "UPDATE api_keys SET last_used_at = CURRENT_TIMESTAMP WHERE id = $1",
+            id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Mark the account's quota as used up in the cycle starting `cycle_start`, returning
+    /// whether it was not marked for that cycle yet
+    pub async fn flag_quota_exhausted(
+        executor: impl PgExecutor<'_>,
+        account_id: Uuid,
+        cycle_start: NaiveDate,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET quota_exhausted_for = $2
+            WHERE id = $1 AND quota_exhausted_for IS DISTINCT FROM $2
+            "#,
+            account_id,
+            cycle_start
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Mark active keys expiring before `cutoff` as reminded, returning those not yet reminded
+    /// about their current expiry
+    pub async fn claim_expiring_keys(
+        executor: impl PgExecutor<'_>,
+        cutoff: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<ExpiringKeyRecord>> {
+        sqlx::query_as!(
+            ExpiringKeyRecord,
+            r#"
+            UPDATE api_keys
+            SET expiry_reminded_for = expires_at
+            WHERE is_active
+              AND expires_at > CURRENT_TIMESTAMP
+              AND expires_at <= $1
+              AND expiry_reminded_for IS DISTINCT FROM expires_at
+            RETURNING id, account_id, name, expires_at AS "expires_at!",
+                      (SELECT notify_key_expiry
+                       FROM accounts
+                       WHERE accounts.id = api_keys.account_id) AS "notify!"
+            "#,
+            cutoff
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Create an account, returning `None` if the public identifier is already taken
+    pub async fn create(
+        executor: impl PgExecutor<'_>,
+        account_id: &str,
+        subscription_tier: SubscriptionTier,
+        monthly_quota: i32,
+    ) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO accounts (account_id, subscription_tier, monthly_quota)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (account_id) DO NOTHING
+            RETURNING id
+            "#,
+            account_id,
+            subscription_tier as _,
+            monthly_quota
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Internal ID of a live account by its public identifier; sandbox accounts are not found
+    pub async fn find_live_id(
+        executor: impl PgExecutor<'_>,
+        account_id: &str,
+    ) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT id
+            FROM accounts
+            WHERE account_id = $1 AND sandbox_of IS NULL
+            "#,
+            account_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// What an account may access: whether it is a sandbox namespace and its tier, or `None`
+    /// if it does not exist
+    pub async fn find_access(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+    ) -> sqlx::Result<Option<AccountAccessRecord>> {
+        sqlx::query_as!(
+            AccountAccessRecord,
+            r#"
+            SELECT sandbox_of IS NOT NULL AS "sandbox!",
+                   subscription_tier AS "subscription_tier: SubscriptionTier",
+                   status AS "status: AccountStatus"
+            FROM accounts
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Current quota and usage of an account
+    pub async fn usage(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+    ) -> sqlx::Result<Option<AccountUsageRecord>> {
+        sqlx::query_as!(
+            AccountUsageRecord,
+            r#"
+            SELECT monthly_quota, queries_used_this_month, billing_cycle_start, billing_cycle_end,
+                   billing_cycle_end <= CURRENT_DATE AS "cycle_ended!"
+            FROM accounts
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Add `units` to the usage of an account's current cycle, returning the updated usage
+    ///
+    /// Returns `None`, leaving usage untouched, if the cycle has ended or, when `enforce` is
+    /// set, if the quota would be exceeded.
+    pub async fn consume_quota(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+        units: i32,
+        enforce: bool,
+    ) -> sqlx::Result<Option<AccountUsageRecord>> {
+        sqlx::query_as!(
+            AccountUsageRecord,
+            r#"
+            UPDATE accounts
+            SET queries_used_this_month = queries_used_this_month + $2
+            WHERE id = $1
+              AND billing_cycle_end > CURRENT_DATE
+              AND (NOT $3 OR queries_used_this_month + $2 <= monthly_quota)
+            RETURNING monthly_quota, queries_used_this_month, billing_cycle_start,
+                      billing_cycle_end, false AS "cycle_ended!"
+            "#,
+            id,
+            units,
+            enforce
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Set the usage of an account's cycle starting `cycle_start`, if it is still current
+    pub async fn set_usage(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+        cycle_start: NaiveDate,
+        used: i32,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET queries_used_this_month = $3
+            WHERE id = $1 AND billing_cycle_start = $2
+            "#,
+            id,
+            cycle_start,
+            used
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Give back `units` to the cycle starting `cycle_start`, if it is still current
+    pub async fn release_quota(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+        cycle_start: NaiveDate,
+        units: i32,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET queries_used_this_month = GREATEST(queries_used_this_month - $3, 0)
+            WHERE id = $1 AND billing_cycle_start = $2
+            "#,
+            id,
+            cycle_start,
+            units
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Archive ended billing cycles to `billing_cycles`, then advance them by one month and
+    /// reset their usage, for one account or all of them, returning how many accounts moved
+    ///
+    /// An account idle for several cycles needs one call per cycle missed.
+    pub async fn roll_over_billing_cycles(
+        executor: impl PgExecutor<'_>,
+        id: Option<Uuid>,
+    ) -> sqlx::Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            WITH ended AS (
+                SELECT id, billing_cycle_start, billing_cycle_end, subscription_tier,
+                       monthly_quota, queries_used_this_month
+                FROM accounts
+                WHERE billing_cycle_end <= CURRENT_DATE
+                  AND ($1::uuid IS NULL OR id = $1)
+                FOR UPDATE
+            ),
+            archived AS (
+                INSERT INTO billing_cycles (
+                    account_id, cycle_start, cycle_end, subscription_tier, monthly_quota,
+                    queries_used
+                )
+                SELECT id, billing_cycle_start, billing_cycle_end, subscription_tier,
+                       monthly_quota, queries_used_this_month
+                FROM ended
+                ON CONFLICT (account_id, cycle_start) DO NOTHING
+            )
+            UPDATE accounts a
+            SET queries_used_this_month = 0,
+                billing_cycle_start = e.billing_cycle_end,
+                billing_cycle_end = (e.billing_cycle_end + INTERVAL '1 month')::date
+            FROM ended e
+            WHERE a.id = e.id
+            "#,
+            id
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Get the sandbox namespace of a live account, creating it on first use
+    ///
+    /// The sandbox account copies the live account's tier, quota, and status and is identified
+    /// publicly as `{account_id}:sandbox`. Keys for the sandbox are inserted under the returned
+    /// ID.
+    pub async fn get_or_create_sandbox(
+        executor: impl PgExecutor<'_>,
+        live_account_id: Uuid,
+    ) -> sqlx::Result<Uuid> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO accounts (
+                account_id, subscription_tier, monthly_quota, sandbox_of, status,
+                status_changed_at, deletion_scheduled_at
+            )
+            SELECT account_id || ':sandbox', subscription_tier, monthly_quota, id, status,
+                   status_changed_at, deletion_scheduled_at
+            FROM accounts
+            WHERE id = $1 AND sandbox_of IS NULL
+            ON CONFLICT (sandbox_of) DO UPDATE SET sandbox_of = EXCLUDED.sandbox_of
+            RETURNING id
+            "#,
+            live_account_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Store a new API key by hash, restricted to `scopes` unless `None`
+    pub async fn insert_api_key(
+        executor: impl PgExecutor<'_>,
+        account_id: Uuid,
+        key_hash: &str,
+        name: &str,
+        scopes: Option<&[String]>,
+    ) -> sqlx::Result<Uuid> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO api_keys (account_id, key_hash, name, scopes)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+            account_id,
+            key_hash,
+            name,
+            scopes
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
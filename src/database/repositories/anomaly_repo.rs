@@ -0,0 +1,105 @@
+//! Detected analytics anomalies
+
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+
+use crate::{
+    database::Tenant,
+    models::analytics::{Anomaly, AnomalyMetric},
+};
+
+/// Anomaly to be recorded
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewAnomaly {
+    /// Account whose metric spiked
+    pub tenant: Tenant,
+    /// Metric that spiked
+    pub metric: AnomalyMetric,
+    /// Rule the hit rate refers to, for rule hit rate anomalies
+    pub rule_code: Option<String>,
+    /// Start of the hour in which the spike occurred
+    pub bucket_start: DateTime<Utc>,
+    /// Value of the metric in that hour
+    pub observed_value: f64,
+    /// Baseline mean of the metric
+    pub baseline_mean: f64,
+    /// Baseline standard deviation of the metric
+    pub baseline_stddev: f64,
+    /// Standard deviations above the baseline mean
+    pub z_score: f64,
+    /// Transactions scored in that hour
+    pub transaction_count: i64,
+}
+
+/// Queries over `analytics_anomalies`
+pub struct AnomalyRepo;
+
+impl AnomalyRepo {
+    /// Record an anomaly, returning `None` if it was already recorded for that hour
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        anomaly: &NewAnomaly,
+    ) -> sqlx::Result<Option<Anomaly>> {
+        sqlx::query_as!(
+            Anomaly,
+            r#"
+            INSERT INTO analytics_anomalies (
+                account_id, metric, rule_code, bucket_start, observed_value, baseline_mean,
+                baseline_stddev, z_score, transaction_count
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (account_id, metric, (COALESCE(rule_code, '')), bucket_start) DO NOTHING
+            RETURNING id, metric AS "metric: AnomalyMetric", rule_code, bucket_start,
+                      observed_value, baseline_mean, baseline_stddev, z_score,
+                      transaction_count, detected_at
+            "#,
+            anomaly.tenant.id(),
+            anomaly.metric as _,
+            anomaly.rule_code,
+            anomaly.bucket_start,
+            anomaly.observed_value,
+            anomaly.baseline_mean,
+            anomaly.baseline_stddev,
+            anomaly.z_score,
+            anomaly.transaction_count
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Page through an account's anomalies, most recent hour first
+    pub async fn list(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<Anomaly>> {
+        sqlx::query_as!(
+            Anomaly,
+            r#"
+            SELECT id, metric AS "metric: AnomalyMetric", rule_code, bucket_start,
+                   observed_value, baseline_mean, baseline_stddev, z_score,
+                   transaction_count, detected_at
+            FROM analytics_anomalies
+            WHERE account_id = $1
+            ORDER BY bucket_start DESC, detected_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            tenant.id(),
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Number of anomalies recorded for an account
+    pub async fn count(executor: impl PgExecutor<'_>, tenant: Tenant) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM analytics_anomalies WHERE account_id = $1"#,
+            tenant.id()
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
@@ -0,0 +1,208 @@
+//! Entities blocklisted automatically after repeated critical velocity breaches
+
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{database::Tenant, models::list::ListEntityType};
+
+/// An automatic block, as recorded for audit
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoBlockRecord {
+    /// Block ID
+    pub id: Uuid,
+    /// Blocklist entry added, unless since removed
+    pub list_entry_id: Option<Uuid>,
+    /// Kind of entity blocked
+    pub entity_type: ListEntityType,
+    /// The entity, in normal form
+    pub value: String,
+    /// Critical velocity factors of the triggering transaction
+    pub factor_codes: Vec<String>,
+    /// Breaching transactions of the entity within the window
+    pub breaches: i32,
+    /// Triggering transaction, unless since deleted
+    pub transaction_id: Option<Uuid>,
+    /// When the block stops applying
+    pub expires_at: DateTime<Utc>,
+    /// When the entity was blocked
+    pub created_at: DateTime<Utc>,
+}
+
+/// An entity of a transaction with the number of its recent transactions that breached
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreachCountRecord {
+    /// Kind of entity
+    pub entity_type: ListEntityType,
+    /// The entity, as stored with its transactions
+    pub value: String,
+    /// Transactions of the entity with one of the factors counted against its kind
+    pub breaches: i64,
+}
+
+/// Entity to block, with its value in normal form
+#[derive(Debug, Clone, Copy)]
+pub struct NewAutoBlock<'a> {
+    /// Owning account
+    pub tenant: Tenant,
+    /// Kind of entity
+    pub entity_type: ListEntityType,
+    /// The entity, in normal form
+    pub value: &'a str,
+    /// Reason recorded on the blocklist entry
+    pub reason: &'a str,
+    /// Critical velocity factors of the triggering transaction
+    pub factor_codes: &'a [String],
+    /// Breaching transactions of the entity within the window
+    pub breaches: i32,
+    /// Triggering transaction
+    pub transaction_id: Uuid,
+    /// When the block stops applying
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Queries over `auto_blocks` and the velocity breaches behind them
+pub struct AutoBlockRepo;
+
+impl AutoBlockRepo {
+    /// For the IP address, device, and card of a transaction, the number of the account's
+    /// transactions since `since` from the same entity carrying one of the factors counted
+    /// against that kind of entity; kinds with no factors are skipped
+    pub async fn count_breaches(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        ip_codes: &[&str],
+        device_codes: &[&str],
+        card_codes: &[&str],
+        since: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<BreachCountRecord>> {
+        sqlx::query_as!(
+            BreachCountRecord,
+            r#"
+            SELECT 'ip' AS "entity_type!: ListEntityType", host(t.ip_address) AS "value!",
+                   COUNT(*) AS "breaches!"
+            FROM transactions this
+            JOIN transactions t
+              ON t.account_id = this.account_id AND t.ip_address = this.ip_address
+            WHERE this.id = $2 AND this.account_id = $1 AND t.created_at >= $6
+              AND EXISTS (
+                  SELECT 1 FROM risk_factors f
+                  WHERE f.transaction_id = t.id AND f.factor_code = ANY($3)
+              )
+            GROUP BY t.ip_address
+            UNION ALL
+            SELECT 'device', td.device_id::text, COUNT(*)
+            FROM transaction_devices this_td
+            JOIN transaction_devices td ON td.device_id = this_td.device_id
+            JOIN transactions t ON t.id = td.transaction_id
+            WHERE this_td.transaction_id = $2 AND t.account_id = $1 AND t.created_at >= $6
+              AND EXISTS (
+                  SELECT 1 FROM risk_factors f
+                  WHERE f.transaction_id = t.id AND f.factor_code = ANY($4)
+              )
+            GROUP BY td.device_id
+            UNION ALL
+            SELECT 'card_hash', c.token_hash, COUNT(DISTINCT t.id)
+            FROM transaction_credit_cards this_tc
+            JOIN credit_cards this_c ON this_c.id = this_tc.credit_card_id
+            JOIN credit_cards c
+              ON c.account_id = this_c.account_id AND c.token_hash = this_c.token_hash
+            JOIN transaction_credit_cards tc ON tc.credit_card_id = c.id
+            JOIN transactions t ON t.id = tc.transaction_id
+            WHERE this_tc.transaction_id = $2 AND this_c.account_id = $1
+              AND t.created_at >= $6
+              AND EXISTS (
+                  SELECT 1 FROM risk_factors f
+                  WHERE f.transaction_id = t.id AND f.factor_code = ANY($5)
+              )
+            GROUP BY c.token_hash
+            "#,
+            tenant.id(),
+            transaction_id,
+            ip_codes as _,
+            device_codes as _,
+            card_codes as _,
+            since
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Put an entity on the account's blocklist until `expires_at` and record the block,
+    /// unless the blocklist already has an entry for it in force
+    pub async fn block(
+        executor: impl PgExecutor<'_>,
+        block: NewAutoBlock<'_>,
+    ) -> sqlx::Result<Option<AutoBlockRecord>> {
+        sqlx::query_as!(
+            AutoBlockRecord,
+            r#"
+            WITH entry AS (
+                INSERT INTO list_entries (
+                    account_id, list_type, entity_type, value, reason, expires_at
+                )
+                VALUES ($1, 'blocklist', $2, $3, $4, $8)
+                ON CONFLICT (account_id, list_type, entity_type, value) DO UPDATE SET
+                    reason = EXCLUDED.reason,
+                    expires_at = EXCLUDED.expires_at
+                WHERE list_entries.expires_at <= NOW()
+                RETURNING id
+            )
+            INSERT INTO auto_blocks (
+                account_id, list_entry_id, entity_type, value, factor_codes, breaches,
+                transaction_id, expires_at
+            )
+            SELECT $1, entry.id, $2, $3, $5, $6, $7, $8
+            FROM entry
+            RETURNING id, list_entry_id, entity_type AS "entity_type: ListEntityType", value,
+                      factor_codes, breaches, transaction_id, expires_at, created_at
+            "#,
+            block.tenant.id(),
+            block.entity_type as _,
+            block.value,
+            block.reason,
+            block.factor_codes,
+            block.breaches,
+            block.transaction_id,
+            block.expires_at
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// A page of the account's automatic blocks, newest first
+    pub async fn list(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<AutoBlockRecord>> {
+        sqlx::query_as!(
+            AutoBlockRecord,
+            r#"
+            SELECT id, list_entry_id, entity_type AS "entity_type: ListEntityType", value,
+                   factor_codes, breaches, transaction_id, expires_at, created_at
+            FROM auto_blocks
+            WHERE account_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            tenant.id(),
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Automatic blocks recorded for the account
+    pub async fn count(executor: impl PgExecutor<'_>, tenant: Tenant) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM auto_blocks WHERE account_id = $1"#,
+            tenant.id()
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
@@ -0,0 +1,306 @@
+//! Long-running work queued for the background job workers
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    api::errors::ErrorResponse,
+    database::{Tenant, TenantOwned},
+    models::job::{JobKind, JobStatus},
+};
+
+/// Stored background job row, without its payload
+#[derive(Debug, Clone)]
+pub struct BackgroundJobRecord {
+    /// Job ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Work the job does
+    pub kind: JobKind,
+    /// Progress of the job
+    pub status: JobStatus,
+    /// Output so far; final once the job has completed
+    pub result: Option<serde_json::Value>,
+    /// Why the job failed, if it has failed
+    pub error: Option<Json<ErrorResponse>>,
+    /// Where the finished job is POSTed
+    pub callback_url: Option<String>,
+    /// Times a worker has started the job
+    pub attempts: i32,
+    /// When the job was queued
+    pub created_at: DateTime<Utc>,
+    /// When a worker first started the job
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the job completed or failed
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl TenantOwned for BackgroundJobRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Job claimed by a worker, with everything needed to run or resume it
+#[derive(Debug, Clone)]
+pub struct ClaimedBackgroundJobRecord {
+    /// Job ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Whether the owning account is a sandbox
+    pub sandbox: bool,
+    /// Work the job does
+    pub kind: JobKind,
+    /// What to work on, in the kind's own shape
+    pub payload: serde_json::Value,
+    /// Output recorded by earlier attempts
+    pub result: Option<serde_json::Value>,
+    /// Times a worker has started the job, counting this claim
+    pub attempts: i32,
+}
+
+/// Queries over `background_jobs`
+pub struct BackgroundJobRepo;
+
+impl BackgroundJobRepo {
+    /// Queue a job for the workers
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        kind: JobKind,
+        payload: &serde_json::Value,
+        callback_url: Option<&str>,
+    ) -> sqlx::Result<BackgroundJobRecord> {
+        let record = sqlx::query_as!(
+            BackgroundJobRecord,
+            r#"
+            INSERT INTO background_jobs (account_id, kind, payload, callback_url)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, account_id, kind AS "kind: JobKind", status AS "status: JobStatus",
+                      result, error AS "error: Json<ErrorResponse>", callback_url, attempts,
+                      created_at, started_at, completed_at
+            "#,
+            tenant.id(),
+            kind as _,
+            payload,
+            callback_url
+        )
+        .fetch_one(executor)
+        .await?;
+        tenant.check(record)
+    }
+
+    /// Fetch one of an account's jobs
+    pub async fn find_by_id(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        job_id: Uuid,
+    ) -> sqlx::Result<Option<BackgroundJobRecord>> {
+        sqlx::query_as!(
+            BackgroundJobRecord,
+            r#"
+            SELECT id, account_id, kind AS "kind: JobKind", status AS "status: JobStatus",
+                   result, error AS "error: Json<ErrorResponse>", callback_url, attempts,
+                   created_at, started_at, completed_at
+            FROM background_jobs
+            WHERE id = $1 AND account_id = $2
+            "#,
+            job_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Start the oldest due pending job, or take over a running one whose heartbeat is older
+    /// than `stale_after_seconds`, skipping jobs other workers are claiming
+    pub async fn claim_next(
+        executor: impl PgExecutor<'_>,
+        stale_after_seconds: f64,
+    ) -> sqlx::Result<Option<ClaimedBackgroundJobRecord>> {
+        sqlx::query_as!(
+            ClaimedBackgroundJobRecord,
+            r#"
+            UPDATE background_jobs j
+            SET status = 'running', attempts = j.attempts + 1, heartbeat_at = NOW(),
+                started_at = COALESCE(j.started_at, NOW())
+            FROM accounts a
+            WHERE a.id = j.account_id
+              AND j.id = (
+                SELECT id
+                FROM background_jobs
+                WHERE (status = 'pending' AND available_at <= NOW())
+                   OR (status = 'running'
+                       AND heartbeat_at < NOW() - make_interval(secs => $1))
+                ORDER BY available_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+              )
+            RETURNING j.id, j.account_id, a.sandbox_of IS NOT NULL AS "sandbox!",
+                      j.kind AS "kind: JobKind", j.payload, j.result, j.attempts
+            "#,
+            stale_after_seconds
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Record a running job's output so far and refresh its heartbeat, returning `false` if
+    /// the job is no longer held by the given attempt
+    ///
+    /// Each claim counts an attempt, so the attempt a worker claimed the job as fences off
+    /// the worker it took the job over from, and the other way round.
+    pub async fn record_progress(
+        executor: impl PgExecutor<'_>,
+        job_id: Uuid,
+        attempt: i32,
+        result: &serde_json::Value,
+    ) -> sqlx::Result<bool> {
+        let updated = sqlx::query!(
+            r#"
+            UPDATE background_jobs
+            SET result = $3, heartbeat_at = NOW()
+            WHERE id = $1 AND attempts = $2 AND status = 'running'
+            "#,
+            job_id,
+            attempt,
+            result
+        )
+        .execute(executor)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    /// Write a chunk of a running job's output file
+    ///
+    /// Record it in the same transaction as the job's progress, so a resumed job writes the
+    /// next chunk after it.
+    pub async fn append_output(
+        executor: impl PgExecutor<'_>,
+        job_id: Uuid,
+        chunk: i32,
+        content: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO background_job_output (job_id, chunk, content)
+            VALUES ($1, $2, $3)
+            "#,
+            job_id,
+            chunk,
+            content
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Output file of one of an account's jobs, in order of its chunks
+    pub async fn output(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        job_id: Uuid,
+    ) -> sqlx::Result<Vec<String>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT o.content
+            FROM background_job_output o
+            JOIN background_jobs j ON j.id = o.job_id
+            WHERE o.job_id = $1 AND j.account_id = $2
+            ORDER BY o.chunk
+            "#,
+            job_id,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Mark a job completed with its final output, or return `None` if the job is no longer
+    /// held by the given attempt
+    ///
+    /// The job's payload is dropped, as it is once the job has failed: a scoring job's is the
+    /// transaction request as submitted, before redaction.
+    pub async fn complete(
+        executor: impl PgExecutor<'_>,
+        job_id: Uuid,
+        attempt: i32,
+        result: &serde_json::Value,
+    ) -> sqlx::Result<Option<BackgroundJobRecord>> {
+        sqlx::query_as!(
+            BackgroundJobRecord,
+            r#"
+            UPDATE background_jobs
+            SET status = 'completed', result = $3, payload = '{}', heartbeat_at = NULL,
+                completed_at = NOW()
+            WHERE id = $1 AND attempts = $2 AND status = 'running'
+            RETURNING id, account_id, kind AS "kind: JobKind", status AS "status: JobStatus",
+                      result, error AS "error: Json<ErrorResponse>", callback_url, attempts,
+                      created_at, started_at, completed_at
+            "#,
+            job_id,
+            attempt,
+            result
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Mark a job failed for good, or return `None` if the job is no longer held by the
+    /// given attempt
+    pub async fn fail(
+        executor: impl PgExecutor<'_>,
+        job_id: Uuid,
+        attempt: i32,
+        error: &ErrorResponse,
+    ) -> sqlx::Result<Option<BackgroundJobRecord>> {
+        sqlx::query_as!(
+            BackgroundJobRecord,
+            r#"
+            UPDATE background_jobs
+            SET status = 'failed', error = $3, payload = '{}', heartbeat_at = NULL,
+                completed_at = NOW()
+            WHERE id = $1 AND attempts = $2 AND status = 'running'
+            RETURNING id, account_id, kind AS "kind: JobKind", status AS "status: JobStatus",
+                      result, error AS "error: Json<ErrorResponse>", callback_url, attempts,
+                      created_at, started_at, completed_at
+            "#,
+            job_id,
+            attempt,
+            Json(error) as _
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Record a failed attempt and leave the job pending until `available_at`, keeping its
+    /// output so far for the next attempt to resume from, returning `false` if the job is no
+    /// longer held by the given attempt
+    pub async fn retry_later(
+        executor: impl PgExecutor<'_>,
+        job_id: Uuid,
+        attempt: i32,
+        error: &str,
+        available_at: DateTime<Utc>,
+    ) -> sqlx::Result<bool> {
+        let updated = sqlx::query!(
+            r#"
+            UPDATE background_jobs
+            SET status = 'pending', last_error = $3, available_at = $4, heartbeat_at = NULL
+            WHERE id = $1 AND attempts = $2 AND status = 'running'
+            "#,
+            job_id,
+            attempt,
+            error,
+            available_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,1070 @@
+//! Manual review cases, the annotations reviewers leave on them, and the queues they are
+//! routed into
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::{
+        case::{
+            CaseAction, CaseAssignment, CaseDecision, CaseKind, CaseQueueRequest, CaseStatus,
+            ListCasesQuery, ScoringSnapshot,
+        },
+        device::DeviceStatus,
+        transaction::{EventType, RiskLevel},
+    },
+};
+
+/// Stored case, with the details of its transaction reviewers triage by
+#[derive(Debug, Clone)]
+pub struct CaseRecord {
+    /// Case ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Why the case was opened
+    pub kind: CaseKind,
+    /// Transaction under review
+    pub transaction_id: Uuid,
+    /// User the transaction belongs to
+    pub user_id: Option<Uuid>,
+    /// Device the transaction came from
+    pub device_id: Option<Uuid>,
+    /// Risk score the transaction was given
+    pub risk_score: f64,
+    /// Risk level the transaction was given
+    pub risk_level: RiskLevel,
+    /// Where the case stands
+    pub status: CaseStatus,
+    /// Reviewer working on the case
+    pub claimed_by: Option<String>,
+    /// When the case was claimed
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// What the reviewer decided
+    pub decision: Option<CaseDecision>,
+    /// Why the reviewer decided as they did
+    pub decision_reason: Option<String>,
+    /// Reviewer who resolved the case
+    pub resolved_by: Option<String>,
+    /// When the case was resolved
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// Name of the queue the case was routed into
+    pub queue: Option<String>,
+    /// When the case breaches its queue's SLA
+    pub due_at: Option<DateTime<Utc>>,
+    /// Who appealed, for appeals
+    pub appealed_by: Option<String>,
+    /// Why they appealed
+    pub appeal_reason: Option<String>,
+    /// Scoring that was appealed
+    pub scoring_snapshot: Option<Json<ScoringSnapshot>>,
+    /// When the case was opened
+    pub created_at: DateTime<Utc>,
+    /// When the case last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TenantOwned for CaseRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Stored annotation
+#[derive(Debug, Clone)]
+pub struct CaseAnnotationRecord {
+    /// Annotation ID
+    pub id: Uuid,
+    /// Reviewer who left it
+    pub author: String,
+    /// The remark
+    pub note: String,
+    /// When it was left
+    pub created_at: DateTime<Utc>,
+}
+
+/// Stored reference
+#[derive(Debug, Clone)]
+pub struct CaseReferenceRecord {
+    /// Reference ID
+    pub id: Uuid,
+    /// Where the referenced material lives
+    pub url: String,
+    /// What the reference is
+    pub label: Option<String>,
+    /// Reviewer who attached it
+    pub added_by: String,
+    /// When it was attached
+    pub created_at: DateTime<Utc>,
+}
+
+/// Entities linked to a transaction, flattened; the fields of entities the transaction does
+/// not name are all `None`
+#[derive(Debug, Clone)]
+pub struct CaseEntitiesRecord {
+    /// Owning account
+    pub account_id: Uuid,
+    /// User ID
+    pub user_id: Option<Uuid>,
+    /// User identifier in the account's system
+    pub external_user_id: Option<String>,
+    /// Risk score of the user
+    pub user_risk_score: Option<f64>,
+    /// Risk level of the user
+    pub user_risk_level: Option<RiskLevel>,
+    /// Chargebacks recorded against the user
+    pub user_chargeback_count: Option<i32>,
+    /// Whether the user is flagged
+    pub user_flagged: Option<bool>,
+    /// Device ID
+    pub device_id: Option<Uuid>,
+    /// Status of the device
+    pub device_status: Option<DeviceStatus>,
+    /// Composite risk score of the device
+    pub device_risk_score: Option<f64>,
+    /// IP address of the transaction
+    pub ip_address: Option<String>,
+    /// Reputation score of the address on the account
+    pub ip_risk_score: Option<f64>,
+    /// Transactions the account has seen from the address
+    pub ip_transaction_count: Option<i32>,
+    /// Chargebacks on those transactions
+    pub ip_chargeback_count: Option<i32>,
+    /// Hash of the email address
+    pub email_hash: Option<String>,
+    /// Domain of the email address
+    pub email_domain: Option<String>,
+    /// Whether the email domain is disposable
+    pub email_disposable: Option<bool>,
+    /// Issuer identification number of the card
+    pub card_issuer_id_number: Option<String>,
+    /// Last digits of the card number
+    pub card_last_digits: Option<String>,
+    /// Card brand
+    pub card_brand: Option<String>,
+    /// Country of the issuing bank
+    pub card_country: Option<String>,
+    /// Whether the card is prepaid
+    pub card_prepaid: Option<bool>,
+}
+
+impl TenantOwned for CaseEntitiesRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Stored entry in a case's history
+#[derive(Debug, Clone)]
+pub struct CaseEventRecord {
+    /// What was done
+    pub action: CaseAction,
+    /// Reviewer who did it, unless it was done automatically
+    pub actor: Option<String>,
+    /// Reviewer holding the case afterwards
+    pub reviewer: Option<String>,
+    /// When it was done
+    pub created_at: DateTime<Utc>,
+}
+
+/// Stored reviewer, with the number of unresolved cases they hold
+#[derive(Debug, Clone)]
+pub struct CaseReviewerRecord {
+    /// Owning account
+    pub account_id: Uuid,
+    /// Reviewer name
+    pub reviewer: String,
+    /// Whether cases are assigned to the reviewer
+    pub active: bool,
+    /// Unresolved cases the reviewer holds
+    pub open_cases: i64,
+    /// When the reviewer was last assigned a case
+    pub last_assigned_at: Option<DateTime<Utc>>,
+    /// When the reviewer was added
+    pub created_at: DateTime<Utc>,
+}
+
+impl TenantOwned for CaseReviewerRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Stored queue, with the number of unresolved and overdue cases in it
+#[derive(Debug, Clone)]
+pub struct CaseQueueRecord {
+    /// Queue ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Queue name
+    pub name: String,
+    /// Routing order, lowest first
+    pub priority: i32,
+    /// Minutes a case may wait before it is overdue
+    pub sla_minutes: i32,
+    /// Reviewers the queue's cases are assigned to
+    pub assignees: Vec<String>,
+    /// Event types routed into the queue
+    pub event_types: Vec<EventType>,
+    /// Smallest order amount routed into the queue
+    pub min_amount: Option<f64>,
+    /// Largest order amount routed into the queue
+    pub max_amount: Option<f64>,
+    /// Risk factor codes of which one must have fired
+    pub rule_codes: Vec<String>,
+    /// Unresolved cases in the queue
+    pub open_cases: i64,
+    /// Unresolved cases past their due time
+    pub overdue_cases: i64,
+    /// When the queue was created
+    pub created_at: DateTime<Utc>,
+    /// When the queue last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TenantOwned for CaseQueueRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Unresolved case newly found past its queue's SLA
+#[derive(Debug, Clone)]
+pub struct SlaBreachRecord {
+    /// Case ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Transaction under review
+    pub transaction_id: Uuid,
+    /// Queue whose SLA the case outlived
+    pub queue_id: Uuid,
+    /// Name of the queue
+    pub queue_name: String,
+    /// Reviewer holding the case
+    pub claimed_by: Option<String>,
+    /// When the case was due
+    pub due_at: DateTime<Utc>,
+}
+
+/// Queries over `review_cases`, their annotations, references, and history, `case_reviewers`,
+/// and `case_queues`
+pub struct CaseRepo;
+
+impl CaseRepo {
+    /// Open a review case for a transaction unless it already has one, returning the new
+    /// case's ID
+    pub async fn open(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO review_cases (account_id, transaction_id)
+            VALUES ($1, $2)
+            ON CONFLICT (transaction_id, kind) DO NOTHING
+            RETURNING id
+            "#,
+            tenant.id(),
+            transaction_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Open an appeal of a transaction's `scoring` unless it was already appealed, returning
+    /// the new case's ID
+    pub async fn open_appeal(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        appealed_by: &str,
+        reason: &str,
+        scoring: &ScoringSnapshot,
+    ) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO review_cases (
+                account_id, transaction_id, kind, appealed_by, appeal_reason, scoring_snapshot
+            )
+            VALUES ($1, $2, 'appeal', $3, $4, $5)
+            ON CONFLICT (transaction_id, kind) DO NOTHING
+            RETURNING id
+            "#,
+            tenant.id(),
+            transaction_id,
+            appealed_by,
+            reason,
+            Json(scoring) as _
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// ID of a transaction's case of the given kind, if it has one
+    pub async fn find_id_by_transaction(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        kind: CaseKind,
+    ) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT id FROM review_cases
+            WHERE transaction_id = $1 AND account_id = $2 AND kind = $3
+            "#,
+            transaction_id,
+            tenant.id(),
+            kind as _
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Fetch a case, locking it against concurrent changes when `executor` is a transaction
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        case_id: Uuid,
+    ) -> sqlx::Result<Option<CaseRecord>> {
+        sqlx::query_as!(
+            CaseRecord,
+            r#"
+            SELECT c.id, c.account_id, c.kind AS "kind: CaseKind", c.transaction_id, t.user_id,
+                   (SELECT td.device_id FROM transaction_devices td
+                    WHERE td.transaction_id = c.transaction_id LIMIT 1) AS device_id,
+                   t.risk_score, t.risk_level AS "risk_level: RiskLevel",
+                   c.status AS "status: CaseStatus", c.claimed_by, c.claimed_at,
+                   c.decision AS "decision: CaseDecision", c.decision_reason, c.resolved_by,
+                   c.resolved_at, q.name AS "queue?", c.due_at, c.appealed_by, c.appeal_reason,
+                   c.scoring_snapshot AS "scoring_snapshot: Json<ScoringSnapshot>",
+                   c.created_at, c.updated_at
+            FROM review_cases c
+            JOIN transactions t ON t.id = c.transaction_id
+            LEFT JOIN case_queues q ON q.id = c.queue_id
+            WHERE c.id = $1 AND c.account_id = $2
+            FOR UPDATE OF c
+            "#,
+            case_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Fetch a page of an account's cases matching the listing filters, oldest first
+    pub async fn list(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        query: &ListCasesQuery,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<CaseRecord>> {
+        sqlx::query_as!(
+            CaseRecord,
+            r#"
+            SELECT c.id, c.account_id, c.kind AS "kind: CaseKind", c.transaction_id, t.user_id,
+                   (SELECT td.device_id FROM transaction_devices td
+                    WHERE td.transaction_id = c.transaction_id LIMIT 1) AS device_id,
+                   t.risk_score, t.risk_level AS "risk_level: RiskLevel",
+                   c.status AS "status: CaseStatus", c.claimed_by, c.claimed_at,
+                   c.decision AS "decision: CaseDecision", c.decision_reason, c.resolved_by,
+                   c.resolved_at, q.name AS "queue?", c.due_at, c.appealed_by, c.appeal_reason,
+                   c.scoring_snapshot AS "scoring_snapshot: Json<ScoringSnapshot>",
+                   c.created_at, c.updated_at
+            FROM review_cases c
+            JOIN transactions t ON t.id = c.transaction_id
+            LEFT JOIN case_queues q ON q.id = c.queue_id
+            WHERE c.account_id = $1
+              AND ($2::varchar IS NULL OR c.status = $2)
+              AND ($3::varchar IS NULL OR c.claimed_by = $3)
+              AND ($4::varchar IS NULL OR q.name = $4)
+              AND ($5::boolean IS NULL
+                   OR COALESCE(c.status <> 'resolved' AND c.due_at < CURRENT_TIMESTAMP, false) = $5)
+              AND ($6::varchar IS NULL OR c.kind = $6)
+            ORDER BY c.created_at, c.id
+            LIMIT $7 OFFSET $8
+            "#,
+            tenant.id(),
+            query.status as _,
+            query.claimed_by,
+            query.queue,
+            query.overdue,
+            query.kind as _,
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Count an account's cases matching the listing filters
+    pub async fn count(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        query: &ListCasesQuery,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM review_cases c
+            LEFT JOIN case_queues q ON q.id = c.queue_id
+            WHERE c.account_id = $1
+              AND ($2::varchar IS NULL OR c.status = $2)
+              AND ($3::varchar IS NULL OR c.claimed_by = $3)
+              AND ($4::varchar IS NULL OR q.name = $4)
+              AND ($5::boolean IS NULL
+                   OR COALESCE(c.status <> 'resolved' AND c.due_at < CURRENT_TIMESTAMP, false) = $5)
+              AND ($6::varchar IS NULL OR c.kind = $6)
+            "#,
+            tenant.id(),
+            query.status as _,
+            query.claimed_by,
+            query.queue,
+            query.overdue,
+            query.kind as _
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Hand a case to `reviewer`
+    pub async fn claim(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        case_id: Uuid,
+        reviewer: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE review_cases
+            SET status = 'claimed', claimed_by = $3, claimed_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND account_id = $2
+            "#,
+            case_id,
+            tenant.id(),
+            reviewer
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Close a case with `reviewer`'s decision; an unclaimed case counts as claimed by them
+    pub async fn resolve(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        case_id: Uuid,
+        reviewer: &str,
+        decision: CaseDecision,
+        reason: Option<&str>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE review_cases
+            SET status = 'resolved',
+                claimed_by = COALESCE(claimed_by, $3),
+                claimed_at = COALESCE(claimed_at, CURRENT_TIMESTAMP),
+                decision = $4,
+                decision_reason = $5,
+                resolved_by = $3,
+                resolved_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND account_id = $2
+            "#,
+            case_id,
+            tenant.id(),
+            reviewer,
+            decision as _,
+            reason
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Leave a remark on a case, which the caller found through a scoped query
+    pub async fn annotate(
+        executor: impl PgExecutor<'_>,
+        case_id: Uuid,
+        author: &str,
+        note: &str,
+    ) -> sqlx::Result<CaseAnnotationRecord> {
+        sqlx::query_as!(
+            CaseAnnotationRecord,
+            r#"
+            INSERT INTO review_case_annotations (case_id, author, note)
+            VALUES ($1, $2, $3)
+            RETURNING id, author, note, created_at
+            "#,
+            case_id,
+            author,
+            note
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Remarks left on a case, oldest first
+    pub async fn annotations(
+        executor: impl PgExecutor<'_>,
+        case_id: Uuid,
+    ) -> sqlx::Result<Vec<CaseAnnotationRecord>> {
+        sqlx::query_as!(
+            CaseAnnotationRecord,
+            r#"
+            SELECT id, author, note, created_at
+            FROM review_case_annotations
+            WHERE case_id = $1
+            ORDER BY created_at, id
+            "#,
+            case_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+    /// Attach a link to a case the caller found through a scoped query
+    pub async fn add_reference(
+        executor: impl PgExecutor<'_>,
+        case_id: Uuid,
+        added_by: &str,
+        url: &str,
+        label: Option<&str>,
+    ) -> sqlx::Result<CaseReferenceRecord> {
+        sqlx::query_as!(
+            CaseReferenceRecord,
+            r#"
+            INSERT INTO case_references (case_id, added_by, url, label)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, url, label, added_by, created_at
+            "#,
+            case_id,
+            added_by,
+            url,
+            label
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Links attached to a case, oldest first
+    pub async fn references(
+        executor: impl PgExecutor<'_>,
+        case_id: Uuid,
+    ) -> sqlx::Result<Vec<CaseReferenceRecord>> {
+        sqlx::query_as!(
+            CaseReferenceRecord,
+            r#"
+            SELECT id, url, label, added_by, created_at
+            FROM case_references
+            WHERE case_id = $1
+            ORDER BY created_at, id
+            "#,
+            case_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// User, device, IP address, email address, and card of a transaction, as they stand now
+    pub async fn entities(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<CaseEntitiesRecord>> {
+        sqlx::query_as!(
+            CaseEntitiesRecord,
+            r#"
+            SELECT t.account_id,
+                   u.id AS "user_id?", u.external_user_id AS "external_user_id?",
+                   u.risk_score AS "user_risk_score?",
+                   u.risk_level AS "user_risk_level?: RiskLevel",
+                   u.chargeback_count AS "user_chargeback_count?",
+                   u.is_flagged AS "user_flagged?",
+                   d.id AS "device_id?", d.status AS "device_status?: DeviceStatus",
+                   d.risk_score AS "device_risk_score?",
+                   host(t.ip_address) AS "ip_address?", ia.risk_score AS "ip_risk_score?",
+                   ia.transaction_count AS "ip_transaction_count?",
+                   ia.chargeback_count AS "ip_chargeback_count?",
+                   e.email_hash AS "email_hash?", e.domain AS "email_domain?",
+                   e.is_disposable AS "email_disposable?",
+                   c.issuer_id_number AS "card_issuer_id_number?",
+                   c.last_digits AS "card_last_digits?", c.brand AS "card_brand?",
+                   c.country::text AS "card_country?", c.is_prepaid AS "card_prepaid?"
+            FROM transactions t
+            LEFT JOIN users u ON u.id = t.user_id AND u.deleted_at IS NULL
+            LEFT JOIN LATERAL (
+                SELECT d.id, d.status, d.risk_score
+                FROM transaction_devices td
+                JOIN devices d ON d.id = td.device_id
+                WHERE td.transaction_id = t.id AND d.deleted_at IS NULL
+                LIMIT 1
+            ) d ON true
+            LEFT JOIN ip_addresses ia
+                ON ia.account_id = t.account_id AND ia.ip_address = t.ip_address
+            LEFT JOIN LATERAL (
+                SELECT e.email_hash, e.domain, e.is_disposable
+                FROM transaction_emails te
+                JOIN email_addresses e ON e.id = te.email_id
+                WHERE te.transaction_id = t.id
+                LIMIT 1
+            ) e ON true
+            LEFT JOIN LATERAL (
+                SELECT c.issuer_id_number, c.last_digits, c.brand, c.country, c.is_prepaid
+                FROM transaction_credit_cards tc
+                JOIN credit_cards c ON c.id = tc.credit_card_id
+                WHERE tc.transaction_id = t.id
+                LIMIT 1
+            ) c ON true
+            WHERE t.id = $1 AND t.account_id = $2
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Lock the unresolved cases `reviewer` holds, oldest first
+    pub async fn held_by(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        reviewer: &str,
+    ) -> sqlx::Result<Vec<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT id FROM review_cases
+            WHERE account_id = $1 AND status = 'claimed' AND claimed_by = $2
+            ORDER BY created_at, id
+            FOR UPDATE
+            "#,
+            tenant.id(),
+            reviewer
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Append an entry to the history of a case the caller found through a scoped query
+    pub async fn log_event(
+        executor: impl PgExecutor<'_>,
+        case_id: Uuid,
+        action: CaseAction,
+        actor: Option<&str>,
+        reviewer: Option<&str>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO case_events (case_id, action, actor, reviewer)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            case_id,
+            action as _,
+            actor,
+            reviewer
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// History of a case, oldest first
+    pub async fn events(
+        executor: impl PgExecutor<'_>,
+        case_id: Uuid,
+    ) -> sqlx::Result<Vec<CaseEventRecord>> {
+        sqlx::query_as!(
+            CaseEventRecord,
+            r#"
+            SELECT action AS "action: CaseAction", actor, reviewer, created_at
+            FROM case_events
+            WHERE case_id = $1
+            ORDER BY created_at, id
+            "#,
+            case_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// An account's reviewers, by name
+    pub async fn reviewers(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Vec<CaseReviewerRecord>> {
+        sqlx::query_as!(
+            CaseReviewerRecord,
+            r#"
+            SELECT r.account_id, r.reviewer, r.active,
+                   (SELECT COUNT(*) FROM review_cases c
+                    WHERE c.account_id = r.account_id AND c.status = 'claimed'
+                      AND c.claimed_by = r.reviewer) AS "open_cases!",
+                   r.last_assigned_at, r.created_at
+            FROM case_reviewers r
+            WHERE r.account_id = $1
+            ORDER BY r.reviewer
+            "#,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Fetch one of an account's reviewers
+    pub async fn find_reviewer(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        reviewer: &str,
+    ) -> sqlx::Result<Option<CaseReviewerRecord>> {
+        sqlx::query_as!(
+            CaseReviewerRecord,
+            r#"
+            SELECT r.account_id, r.reviewer, r.active,
+                   (SELECT COUNT(*) FROM review_cases c
+                    WHERE c.account_id = r.account_id AND c.status = 'claimed'
+                      AND c.claimed_by = r.reviewer) AS "open_cases!",
+                   r.last_assigned_at, r.created_at
+            FROM case_reviewers r
+            WHERE r.account_id = $1 AND r.reviewer = $2
+            "#,
+            tenant.id(),
+            reviewer
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Add a reviewer, active unless `active` says otherwise, or change an existing one
+    pub async fn upsert_reviewer(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        reviewer: &str,
+        active: Option<bool>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO case_reviewers (account_id, reviewer, active)
+            VALUES ($1, $2, COALESCE($3, true))
+            ON CONFLICT (account_id, reviewer)
+            DO UPDATE SET active = COALESCE($3, case_reviewers.active)
+            "#,
+            tenant.id(),
+            reviewer,
+            active
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Remove a reviewer, returning whether it existed
+    pub async fn delete_reviewer(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        reviewer: &str,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM case_reviewers WHERE account_id = $1 AND reviewer = $2",
+            tenant.id(),
+            reviewer
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Lock the active reviewer `strategy` picks for the next case, other than `except` and,
+    /// unless `among` is empty, one of `among`
+    ///
+    /// Round-robin picks the reviewer assigned a case least recently; least-loaded the one
+    /// holding the fewest unresolved cases, then the one assigned least recently. Manual
+    /// assignment picks like least-loaded.
+    pub async fn next_reviewer(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        strategy: CaseAssignment,
+        except: Option<&str>,
+        among: &[String],
+    ) -> sqlx::Result<Option<String>> {
+        let by_load = strategy != CaseAssignment::RoundRobin;
+        sqlx::query_scalar!(
+            r#"
+            SELECT r.reviewer
+            FROM case_reviewers r
+            WHERE r.account_id = $1 AND r.active
+              AND ($3::varchar IS NULL OR r.reviewer <> $3)
+              AND (cardinality($4::text[]) = 0 OR r.reviewer = ANY($4))
+            ORDER BY
+                CASE WHEN $2 THEN
+                    (SELECT COUNT(*) FROM review_cases c
+                     WHERE c.account_id = r.account_id AND c.status = 'claimed'
+                       AND c.claimed_by = r.reviewer)
+                ELSE 0 END,
+                r.last_assigned_at NULLS FIRST, r.reviewer
+            LIMIT 1
+            FOR UPDATE OF r
+            "#,
+            tenant.id(),
+            by_load,
+            except,
+            among
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Note that `reviewer` was just assigned a case, moving them to the back of the
+    /// round-robin
+    pub async fn mark_assigned(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        reviewer: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE case_reviewers SET last_assigned_at = clock_timestamp()
+            WHERE account_id = $1 AND reviewer = $2
+            "#,
+            tenant.id(),
+            reviewer
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Route a new case into the first queue, by priority, whose conditions its transaction
+    /// meets given the risk factors in `rule_codes` fired, starting the queue's SLA clock
+    ///
+    /// Returns the queue, or `None` when no queue takes the case.
+    pub async fn route(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        case_id: Uuid,
+        transaction_id: Uuid,
+        rule_codes: &[String],
+    ) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            UPDATE review_cases c
+            SET queue_id = m.id, due_at = c.created_at + make_interval(mins => m.sla_minutes)
+            FROM (
+                SELECT q.id, q.sla_minutes
+                FROM case_queues q
+                JOIN transactions t ON t.id = $3 AND t.account_id = q.account_id
+                LEFT JOIN orders o ON o.transaction_id = t.id
+                WHERE q.account_id = $1
+                  AND (cardinality(q.event_types) = 0 OR t.event_type = ANY(q.event_types))
+                  AND (q.min_amount IS NULL OR o.amount >= q.min_amount)
+                  AND (q.max_amount IS NULL OR o.amount <= q.max_amount)
+                  AND (cardinality(q.rule_codes) = 0 OR q.rule_codes && $4::text[])
+                ORDER BY q.priority, q.created_at, q.id
+                LIMIT 1
+            ) m
+            WHERE c.id = $2 AND c.account_id = $1
+            RETURNING m.id
+            "#,
+            tenant.id(),
+            case_id,
+            transaction_id,
+            rule_codes
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Assignees of the queue a case is in; empty when it is in none or the queue takes any
+    /// reviewer
+    pub async fn queue_assignees(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        case_id: Uuid,
+    ) -> sqlx::Result<Vec<String>> {
+        Ok(sqlx::query_scalar!(
+            r#"
+            SELECT q.assignees
+            FROM review_cases c
+            JOIN case_queues q ON q.id = c.queue_id
+            WHERE c.id = $1 AND c.account_id = $2
+            "#,
+            case_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .unwrap_or_default())
+    }
+
+    /// An account's queues, in routing order
+    pub async fn queues(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Vec<CaseQueueRecord>> {
+        sqlx::query_as!(
+            CaseQueueRecord,
+            r#"
+            SELECT q.id, q.account_id, q.name, q.priority, q.sla_minutes, q.assignees,
+                   q.event_types AS "event_types: Vec<EventType>", q.min_amount, q.max_amount,
+                   q.rule_codes,
+                   (SELECT COUNT(*) FROM review_cases c
+                    WHERE c.queue_id = q.id AND c.status <> 'resolved') AS "open_cases!",
+                   (SELECT COUNT(*) FROM review_cases c
+                    WHERE c.queue_id = q.id AND c.status <> 'resolved'
+                      AND c.due_at < CURRENT_TIMESTAMP) AS "overdue_cases!",
+                   q.created_at, q.updated_at
+            FROM case_queues q
+            WHERE q.account_id = $1
+            ORDER BY q.priority, q.created_at, q.id
+            "#,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Fetch one of an account's queues
+    pub async fn find_queue(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        queue_id: Uuid,
+    ) -> sqlx::Result<Option<CaseQueueRecord>> {
+        sqlx::query_as!(
+            CaseQueueRecord,
+            r#"
+            SELECT q.id, q.account_id, q.name, q.priority, q.sla_minutes, q.assignees,
+                   q.event_types AS "event_types: Vec<EventType>", q.min_amount, q.max_amount,
+                   q.rule_codes,
+                   (SELECT COUNT(*) FROM review_cases c
+                    WHERE c.queue_id = q.id AND c.status <> 'resolved') AS "open_cases!",
+                   (SELECT COUNT(*) FROM review_cases c
+                    WHERE c.queue_id = q.id AND c.status <> 'resolved'
+                      AND c.due_at < CURRENT_TIMESTAMP) AS "overdue_cases!",
+                   q.created_at, q.updated_at
+            FROM case_queues q
+            WHERE q.id = $1 AND q.account_id = $2
+            "#,
+            queue_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Create a queue; fails with a unique violation when the account has one of that name
+    pub async fn insert_queue(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        queue: &CaseQueueRequest,
+    ) -> sqlx::Result<Uuid> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO case_queues (
+                account_id, name, priority, sla_minutes, assignees, event_types, min_amount,
+                max_amount, rule_codes
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id
+            "#,
+            tenant.id(),
+            queue.name,
+            queue.priority,
+            queue.sla_minutes,
+            &queue.assignees,
+            &queue.routing.event_types as _,
+            queue.routing.min_amount,
+            queue.routing.max_amount,
+            &queue.routing.rule_codes
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Replace a queue, returning whether it existed; fails with a unique violation when the
+    /// account has another queue of the new name
+    ///
+    /// Cases already in the queue keep their due time.
+    pub async fn update_queue(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        queue_id: Uuid,
+        queue: &CaseQueueRequest,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE case_queues
+            SET name = $3, priority = $4, sla_minutes = $5, assignees = $6, event_types = $7,
+                min_amount = $8, max_amount = $9, rule_codes = $10
+            WHERE id = $1 AND account_id = $2
+            "#,
+            queue_id,
+            tenant.id(),
+            queue.name,
+            queue.priority,
+            queue.sla_minutes,
+            &queue.assignees,
+            &queue.routing.event_types as _,
+            queue.routing.min_amount,
+            queue.routing.max_amount,
+            &queue.routing.rule_codes
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete a queue, returning whether it existed; its cases leave the queue but keep their
+    /// due time
+    pub async fn delete_queue(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        queue_id: Uuid,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM case_queues WHERE id = $1 AND account_id = $2",
+            queue_id,
+            tenant.id()
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Mark unresolved cases due before `now` as breaching their SLA, returning those not
+    /// marked before
+    pub async fn claim_sla_breaches(
+        executor: impl PgExecutor<'_>,
+        now: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<SlaBreachRecord>> {
+        sqlx::query_as!(
+            SlaBreachRecord,
+            r#"
+            UPDATE review_cases c
+            SET sla_breached_at = $1
+            FROM case_queues q
+            WHERE q.id = c.queue_id
+              AND c.due_at < $1
+              AND c.sla_breached_at IS NULL
+              AND c.status <> 'resolved'
+            RETURNING c.id, c.account_id, c.transaction_id, q.id AS queue_id,
+                      q.name AS queue_name, c.claimed_by, c.due_at AS "due_at!"
+            "#,
+            now
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,536 @@
+//! Devices seen by each account
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::device::{DeviceStatus, ListDevicesQuery, UserAgentDetails},
+};
+
+/// Stored device row
+#[derive(Debug, Clone)]
+pub struct DeviceRecord {
+    /// Device ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// User the device was last seen with
+    pub user_id: Option<Uuid>,
+    /// Fingerprint identifying the device within the account
+    pub fingerprint_hash: String,
+    /// IP address the device last used, in textual form
+    pub ip_address: String,
+    /// Autonomous system the IP address belongs to, if resolved
+    pub asn: Option<i64>,
+    /// Internet service provider or organization of the autonomous system
+    pub isp: Option<String>,
+    /// User agent string
+    pub user_agent: Option<String>,
+    /// What the user agent says about the device, once parsed
+    pub user_agent_details: Option<Json<UserAgentDetails>>,
+    /// Accept-Language header
+    pub accept_language: Option<String>,
+    /// JA3 fingerprint of the device's TLS handshake
+    pub ja3: Option<String>,
+    /// JA4 fingerprint of the device's TLS handshake
+    pub ja4: Option<String>,
+    /// Hash of the device's HTTP header order
+    pub header_order_hash: Option<String>,
+    /// Browser signals the device was fingerprinted from, or an empty object
+    pub traits_data: serde_json::Value,
+    /// Whether the device is trusted, blocked, or neither
+    pub status: DeviceStatus,
+    /// When the status was last changed
+    pub status_changed_at: Option<DateTime<Utc>>,
+    /// Composite risk score, once computed
+    pub risk_score: Option<f64>,
+    /// When the account first saw the device
+    pub first_seen: DateTime<Utc>,
+    /// When the account last saw the device
+    pub last_seen: DateTime<Utc>,
+    /// Transactions that came from the device
+    pub transaction_count: i64,
+    /// Distinct users the device has been seen with
+    pub user_count: i64,
+    /// Whether the user agent names an automation tool or a transaction from the device was
+    /// high risk
+    pub suspicious: bool,
+}
+
+impl TenantOwned for DeviceRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// What is stored about a device, as of scoring a transaction from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeviceHistoryRecord {
+    /// Device ID
+    pub id: Uuid,
+    /// Whether the device is trusted, blocked, or neither
+    pub status: DeviceStatus,
+    /// Distinct users seen with the device since the given time, counting the transaction's
+    /// user
+    pub recent_users: i64,
+    /// Whether a user seen with the device, or the device itself, has chargebacks on record
+    pub chargebacks: bool,
+}
+
+/// What a device's composite risk score is computed from
+#[derive(Debug, Clone)]
+pub struct DeviceRiskInputsRecord {
+    /// When the account first saw the device
+    pub first_seen: DateTime<Utc>,
+    /// When the account last saw the device
+    pub last_seen: DateTime<Utc>,
+    /// What the user agent says about the device, once parsed
+    pub user_agent_details: Option<Json<UserAgentDetails>>,
+    /// Browser signals the device was fingerprinted from, or an empty object
+    pub traits_data: serde_json::Value,
+    /// Distinct users the device has been seen with
+    pub user_count: i64,
+    /// Whether a current IP intelligence lookup places the device's IP address at a hosting
+    /// provider
+    pub hosting_ip: bool,
+}
+
+/// Device attributes captured from a transaction
+#[derive(Debug, Clone, Copy)]
+pub struct NewDevice<'a> {
+    /// Owning account
+    pub tenant: Tenant,
+    /// User the device was last seen with
+    pub user_id: Option<Uuid>,
+    /// Fingerprint identifying the device within the account
+    pub fingerprint_hash: &'a str,
+    /// IP address, in textual form
+    pub ip_address: &'a str,
+    /// Autonomous system the IP address belongs to, if resolved
+    pub asn: Option<i64>,
+    /// Internet service provider or organization of the autonomous system
+    pub isp: Option<&'a str>,
+    /// User agent string
+    pub user_agent: Option<&'a str>,
+    /// What the user agent says about the device
+    pub user_agent_details: Option<&'a UserAgentDetails>,
+    /// Accept-Language header
+    pub accept_language: Option<&'a str>,
+    /// JA3 fingerprint of the TLS handshake, in lowercase
+    pub ja3: Option<&'a str>,
+    /// JA4 fingerprint of the TLS handshake
+    pub ja4: Option<&'a str>,
+    /// Hash of the HTTP header order
+    pub header_order_hash: Option<&'a str>,
+    /// Session identifier
+    pub session_id: Option<&'a str>,
+    /// Session age in seconds
+    pub session_age: Option<f64>,
+    /// Browser signals the fingerprint was computed from
+    pub traits_data: Option<&'a serde_json::Value>,
+}
+
+/// Queries over `devices`
+pub struct DeviceRepo;
+
+impl DeviceRepo {
+    /// Find or create a device by fingerprint, refreshing its network and session details and
+    /// last-seen time
+    ///
+    /// Returns `None` when the matching device has been deleted.
+    pub async fn upsert(
+        executor: impl PgExecutor<'_>,
+        device: NewDevice<'_>,
+    ) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO devices (
+                account_id, user_id, fingerprint_hash, ip_address, user_agent, accept_language,
+                session_id, session_age, traits_data, user_agent_details, ja3, ja4,
+                header_order_hash, asn, isp
+            )
+            VALUES (
+                $1, $2, $3, $4::text::inet, $5, $6, $7, $8, COALESCE($9::jsonb, '{}'::jsonb), $10,
+                $11, $12, $13, $14, $15
+            )
+            ON CONFLICT (account_id, fingerprint_hash) DO UPDATE SET
+                user_id = COALESCE(EXCLUDED.user_id, devices.user_id),
+                ip_address = EXCLUDED.ip_address,
+                -- The network follows the address; an unresolved sighting from the same
+                -- address keeps what is known
+                asn = CASE WHEN EXCLUDED.ip_address = devices.ip_address
+                    THEN COALESCE(EXCLUDED.asn, devices.asn) ELSE EXCLUDED.asn END,
+                isp = CASE WHEN EXCLUDED.ip_address = devices.ip_address
+                    THEN COALESCE(EXCLUDED.isp, devices.isp) ELSE EXCLUDED.isp END,
+                user_agent = COALESCE(EXCLUDED.user_agent, devices.user_agent),
+                user_agent_details = COALESCE(
+                    EXCLUDED.user_agent_details, devices.user_agent_details
+                ),
+                accept_language = COALESCE(EXCLUDED.accept_language, devices.accept_language),
+                ja3 = COALESCE(EXCLUDED.ja3, devices.ja3),
+                ja4 = COALESCE(EXCLUDED.ja4, devices.ja4),
+                header_order_hash = COALESCE(
+                    EXCLUDED.header_order_hash, devices.header_order_hash
+                ),
+                session_id = COALESCE(EXCLUDED.session_id, devices.session_id),
+                session_age = COALESCE(EXCLUDED.session_age, devices.session_age),
+                traits_data = COALESCE($9::jsonb, devices.traits_data),
+                last_seen = CURRENT_TIMESTAMP
+            WHERE devices.deleted_at IS NULL
+            RETURNING id
+            "#,
+            device.tenant.id(),
+            device.user_id,
+            device.fingerprint_hash,
+            device.ip_address,
+            device.user_agent,
+            device.accept_language,
+            device.session_id,
+            device.session_age,
+            device.traits_data,
+            device.user_agent_details.map(Json) as _,
+            device.ja3,
+            device.ja4,
+            device.header_order_hash,
+            device.asn,
+            device.isp
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Fetch a live device of an account
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        device_id: Uuid,
+    ) -> sqlx::Result<Option<DeviceRecord>> {
+        sqlx::query_as!(
+            DeviceRecord,
+            r#"
+            SELECT d.id, d.account_id, d.user_id, d.fingerprint_hash,
+                   host(d.ip_address) AS "ip_address!", d.asn, d.isp, d.user_agent,
+                   d.user_agent_details AS "user_agent_details: Json<UserAgentDetails>",
+                   d.accept_language, d.ja3, d.ja4, d.header_order_hash, d.traits_data,
+                   d.status AS "status: DeviceStatus", d.status_changed_at, d.risk_score,
+                   d.first_seen, d.last_seen,
+                   stats.transaction_count AS "transaction_count!",
+                   stats.user_count AS "user_count!",
+                   (d.user_agent_details->>'automation' IS NOT NULL OR stats.high_risk)
+                       AS "suspicious!"
+            FROM devices d
+            CROSS JOIN LATERAL (
+                SELECT COUNT(*) AS transaction_count,
+                       (SELECT COUNT(*) FROM device_users du WHERE du.device_id = d.id)
+                           AS user_count,
+                       COALESCE(bool_or(t.risk_level IN ('high', 'very_high')), FALSE)
+                           AS high_risk
+                FROM transaction_devices td
+                JOIN transactions t ON t.id = td.transaction_id
+                WHERE td.device_id = d.id
+            ) stats
+            WHERE d.id = $1 AND d.account_id = $2 AND d.deleted_at IS NULL
+            "#,
+            device_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Fetch a page of an account's live devices matching the listing filters, most recently
+    /// seen first
+    pub async fn list(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        query: &ListDevicesQuery,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<DeviceRecord>> {
+        sqlx::query_as!(
+            DeviceRecord,
+            r#"
+            SELECT d.id, d.account_id, d.user_id, d.fingerprint_hash,
+                   host(d.ip_address) AS "ip_address!", d.asn, d.isp, d.user_agent,
+                   d.user_agent_details AS "user_agent_details: Json<UserAgentDetails>",
+                   d.accept_language, d.ja3, d.ja4, d.header_order_hash, d.traits_data,
+                   d.status AS "status: DeviceStatus", d.status_changed_at, d.risk_score,
+                   d.first_seen, d.last_seen,
+                   stats.transaction_count AS "transaction_count!",
+                   stats.user_count AS "user_count!",
+                   (d.user_agent_details->>'automation' IS NOT NULL OR stats.high_risk)
+                       AS "suspicious!"
+            FROM devices d
+            CROSS JOIN LATERAL (
+                SELECT COUNT(*) AS transaction_count,
+                       (SELECT COUNT(*) FROM device_users du WHERE du.device_id = d.id)
+                           AS user_count,
+                       COALESCE(bool_or(t.risk_level IN ('high', 'very_high')), FALSE)
+                           AS high_risk
+                FROM transaction_devices td
+                JOIN transactions t ON t.id = td.transaction_id
+                WHERE td.device_id = d.id
+            ) stats
+            WHERE d.account_id = $1 AND d.deleted_at IS NULL
+              AND (
+                  $2::bool IS NULL
+                  OR (d.user_agent_details->>'automation' IS NOT NULL OR stats.high_risk) = $2
+              )
+              AND ($3::text IS NULL OR d.ip_address <<= $3::text::inet)
+              AND ($4::uuid IS NULL OR d.user_id = $4 OR EXISTS (
+                  SELECT 1 FROM device_users du WHERE du.device_id = d.id AND du.user_id = $4
+              ))
+              AND ($5::varchar IS NULL OR d.status = $5)
+            ORDER BY d.last_seen DESC, d.id
+            LIMIT $6 OFFSET $7
+            "#,
+            tenant.id(),
+            query.suspicious,
+            query.ip_address,
+            query.user_id,
+            query.status as _,
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Count an account's live devices matching the listing filters
+    pub async fn count(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        query: &ListDevicesQuery,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM devices d
+            WHERE d.account_id = $1 AND d.deleted_at IS NULL
+              AND (
+                  $2::bool IS NULL
+                  OR (
+                      d.user_agent_details->>'automation' IS NOT NULL
+                      OR EXISTS (
+                          SELECT 1 FROM transaction_devices td
+                          JOIN transactions t ON t.id = td.transaction_id
+                          WHERE td.device_id = d.id AND t.risk_level IN ('high', 'very_high')
+                      )
+                  ) = $2
+              )
+              AND ($3::text IS NULL OR d.ip_address <<= $3::text::inet)
+              AND ($4::uuid IS NULL OR d.user_id = $4 OR EXISTS (
+                  SELECT 1 FROM device_users du WHERE du.device_id = d.id AND du.user_id = $4
+              ))
+              AND ($5::varchar IS NULL OR d.status = $5)
+            "#,
+            tenant.id(),
+            query.suspicious,
+            query.ip_address,
+            query.user_id,
+            query.status as _
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Record that a device was seen with a user, counting the transaction towards the link
+    pub async fn link_user(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        device_id: Uuid,
+        user_id: Uuid,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO device_users (device_id, user_id, account_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (device_id, user_id) DO UPDATE SET
+                transaction_count = device_users.transaction_count + 1,
+                last_seen = CURRENT_TIMESTAMP
+            "#,
+            device_id,
+            user_id,
+            tenant.id()
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Status of the live device with the given ID or fingerprint, and the users it has been
+    /// seen with, counting `user_id` among the recent users whether or not it has been seen
+    /// with the device yet
+    ///
+    /// Pass exactly one of the device ID and fingerprint. Returns `None` for unknown and
+    /// deleted devices.
+    pub async fn history(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        device_id: Option<Uuid>,
+        fingerprint_hash: Option<&str>,
+        user_id: Option<Uuid>,
+        since: DateTime<Utc>,
+    ) -> sqlx::Result<Option<DeviceHistoryRecord>> {
+        sqlx::query_as!(
+            DeviceHistoryRecord,
+            r#"
+            SELECT
+                d.id,
+                d.status AS "status: DeviceStatus",
+                (
+                    SELECT COUNT(*) FROM (
+                        SELECT du.user_id FROM device_users du
+                        WHERE du.device_id = d.id AND du.last_seen >= $4::timestamptz
+                        UNION
+                        SELECT $5::uuid WHERE $5::uuid IS NOT NULL
+                    ) recent
+                ) AS "recent_users!",
+                (
+                    EXISTS (
+                        SELECT 1 FROM device_users du
+                        JOIN users u ON u.id = du.user_id
+                        WHERE du.device_id = d.id AND u.chargeback_count > 0
+                    )
+                    OR d.chargeback_count > 0
+                ) AS "chargebacks!"
+            FROM devices d
+            WHERE d.account_id = $1 AND d.deleted_at IS NULL
+              AND (d.id = $2 OR d.fingerprint_hash = $3)
+            LIMIT 1
+            "#,
+            tenant.id(),
+            device_id,
+            fingerprint_hash,
+            since,
+            user_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Add `delta` chargebacks to the counts of the devices a transaction came from
+    pub async fn add_chargebacks(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        delta: i32,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE devices d
+            SET chargeback_count = GREATEST(d.chargeback_count + $2, 0)
+            FROM transaction_devices td
+            WHERE td.transaction_id = $1 AND td.device_id = d.id
+            "#,
+            transaction_id,
+            delta
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Set a live device's status, returning whether the device was found
+    pub async fn set_status(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        device_id: Uuid,
+        status: DeviceStatus,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE devices
+            SET status = $3::VARCHAR,
+                status_changed_at = CASE
+                    WHEN status = $3::VARCHAR THEN status_changed_at ELSE CURRENT_TIMESTAMP
+                END
+            WHERE id = $1 AND account_id = $2 AND deleted_at IS NULL
+            "#,
+            device_id,
+            tenant.id(),
+            status as _
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Inputs to the composite risk score of a live device
+    pub async fn risk_inputs(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        device_id: Uuid,
+    ) -> sqlx::Result<Option<DeviceRiskInputsRecord>> {
+        sqlx::query_as!(
+            DeviceRiskInputsRecord,
+            r#"
+            SELECT d.first_seen, d.last_seen,
+                   d.user_agent_details AS "user_agent_details: Json<UserAgentDetails>",
+                   d.traits_data,
+                   (SELECT COUNT(*) FROM device_users du WHERE du.device_id = d.id)
+                       AS "user_count!",
+                   EXISTS (
+                       SELECT 1 FROM ip_risk_cache c
+                       WHERE c.ip_address = d.ip_address AND c.expires_at > NOW()
+                         AND (
+                             c.traits_data->>'is_hosting_provider' = 'true'
+                             OR c.traits_data->>'user_type' = 'hosting'
+                         )
+                   ) AS "hosting_ip!"
+            FROM devices d
+            WHERE d.id = $1 AND d.account_id = $2 AND d.deleted_at IS NULL
+            "#,
+            device_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Store the composite risk score of a device
+    pub async fn set_risk_score(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        device_id: Uuid,
+        risk_score: f64,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE devices
+            SET risk_score = $3, risk_scored_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND account_id = $2
+            "#,
+            device_id,
+            tenant.id(),
+            risk_score
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Soft-delete a device, returning whether a live device was deleted
+    pub async fn soft_delete(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        device_id: Uuid,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE devices
+            SET deleted_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND account_id = $2 AND deleted_at IS NULL
+            "#,
+            device_id,
+            tenant.id()
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,166 @@
+//! Disputes of transactions
+
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::{dispute::DisputeStatus, processor_event::Processor},
+};
+
+/// Stored dispute row
+#[derive(Debug, Clone)]
+pub struct DisputeRecord {
+    /// Dispute ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Disputed transaction
+    pub transaction_id: Uuid,
+    /// Processor that reported the dispute
+    pub processor: Processor,
+    /// Processor's ID for the dispute
+    pub reference: String,
+    /// Stage the dispute has reached
+    pub status: DisputeStatus,
+    /// Disputed amount, in major units
+    pub amount: Option<f64>,
+    /// ISO 4217 currency code of the amount
+    pub currency: Option<String>,
+    /// Processor's reason code
+    pub reason_code: Option<String>,
+    /// When the dispute was received
+    pub received_at: DateTime<Utc>,
+    /// When evidence was submitted
+    pub representment_at: Option<DateTime<Utc>>,
+    /// When the dispute was won or lost
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// When the dispute last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TenantOwned for DisputeRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Stage of a dispute a processor reported
+#[derive(Debug, Clone)]
+pub struct DisputeProgress<'a> {
+    /// Disputed transaction
+    pub transaction_id: Uuid,
+    /// Processor that reported the dispute
+    pub processor: Processor,
+    /// Processor's ID for the dispute
+    pub reference: &'a str,
+    /// Stage the dispute reached
+    pub status: DisputeStatus,
+    /// Disputed amount, in major units
+    pub amount: Option<f64>,
+    /// ISO 4217 currency code of the amount
+    pub currency: Option<&'a str>,
+    /// Processor's reason code
+    pub reason_code: Option<&'a str>,
+    /// When the dispute reached the stage
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Queries over `disputes`
+pub struct DisputeRepo;
+
+impl DisputeRepo {
+    /// Record the stage a dispute reached, returning the dispute, or `None` if nothing changed
+    ///
+    /// A decided dispute does not go back to an earlier stage when events arrive out of order,
+    /// though one decision may overturn the other. Amounts and reason codes the event leaves
+    /// out are kept.
+    pub async fn record_progress(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        progress: &DisputeProgress<'_>,
+    ) -> sqlx::Result<Option<DisputeRecord>> {
+        let representment_at =
+            (progress.status == DisputeStatus::Representment).then_some(progress.occurred_at);
+        let resolved_at = progress
+            .status
+            .is_resolved()
+            .then_some(progress.occurred_at);
+        sqlx::query_as!(
+            DisputeRecord,
+            r#"
+            INSERT INTO disputes (
+                account_id, transaction_id, processor, reference, status, amount, currency,
+                reason_code, received_at, representment_at, resolved_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (account_id, processor, reference) DO UPDATE
+            SET status = CASE
+                    WHEN disputes.status IN ('won', 'lost')
+                         AND EXCLUDED.status IN ('received', 'representment')
+                        THEN disputes.status
+                    ELSE EXCLUDED.status
+                END,
+                amount = COALESCE(EXCLUDED.amount, disputes.amount),
+                currency = COALESCE(EXCLUDED.currency, disputes.currency),
+                reason_code = COALESCE(EXCLUDED.reason_code, disputes.reason_code),
+                received_at = LEAST(disputes.received_at, EXCLUDED.received_at),
+                representment_at = COALESCE(disputes.representment_at, EXCLUDED.representment_at),
+                resolved_at = COALESCE(EXCLUDED.resolved_at, disputes.resolved_at)
+            WHERE (
+                    EXCLUDED.status IS DISTINCT FROM disputes.status
+                    AND NOT (
+                        disputes.status IN ('won', 'lost')
+                        AND EXCLUDED.status IN ('received', 'representment')
+                    )
+                )
+               OR (EXCLUDED.amount IS NOT NULL AND EXCLUDED.amount IS DISTINCT FROM disputes.amount)
+               OR (EXCLUDED.reason_code IS NOT NULL
+                   AND EXCLUDED.reason_code IS DISTINCT FROM disputes.reason_code)
+            RETURNING id, account_id, transaction_id, processor AS "processor: Processor",
+                      reference, status AS "status: DisputeStatus", amount, currency,
+                      reason_code, received_at, representment_at, resolved_at, updated_at
+            "#,
+            tenant.id(),
+            progress.transaction_id,
+            progress.processor as _,
+            progress.reference,
+            progress.status as _,
+            progress.amount,
+            progress.currency,
+            progress.reason_code,
+            progress.occurred_at,
+            representment_at,
+            resolved_at
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Disputes of one of an account's transactions, oldest first
+    pub async fn for_transaction(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Vec<DisputeRecord>> {
+        sqlx::query_as!(
+            DisputeRecord,
+            r#"
+            SELECT id, account_id, transaction_id, processor AS "processor: Processor",
+                   reference, status AS "status: DisputeStatus", amount, currency,
+                   reason_code, received_at, representment_at, resolved_at, updated_at
+            FROM disputes
+            WHERE transaction_id = $1 AND account_id = $2
+            ORDER BY received_at, id
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+}
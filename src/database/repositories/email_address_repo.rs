@@ -0,0 +1,225 @@
+//! History of the email addresses seen by each account
+
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use super::TransactionRecord;
+use crate::{
+    database::Tenant,
+    models::transaction::{Disposition, EventType, RiskLevel, Warning},
+};
+
+/// Stored email address and what the account's transactions with it say about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailAddressRecord {
+    /// Domain of the address
+    pub domain: Option<String>,
+    /// Whether the domain was a free provider when the address was last used
+    pub is_free: bool,
+    /// Whether the domain handed out disposable addresses when the address was last used
+    pub is_disposable: bool,
+    /// Whether the address is known to be high risk
+    pub is_high_risk: bool,
+    /// Transactions that used the address
+    pub transaction_count: i64,
+    /// Of those, transactions rejected
+    pub reject_count: i64,
+    /// Of those, transactions reported as chargebacks
+    pub chargeback_count: i64,
+    /// Distinct users the transactions belonged to
+    pub user_count: i64,
+    /// Distinct payment cards the transactions used
+    pub card_count: i64,
+    /// Day the account first saw the address
+    pub first_seen: NaiveDate,
+    /// When the account's latest transaction with the address was created
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// Other addresses of an account delivering to the same mailbox as an address
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmailVariantsRecord {
+    /// Distinct other addresses delivering to the mailbox
+    pub address_count: i64,
+    /// Users other than the given one those addresses were last used by
+    pub user_count: i64,
+}
+
+/// When an email address was first seen, and the good history it has built up since
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmailAgeRecord {
+    /// Day the account first saw the address, unless it never has
+    pub first_seen: Option<NaiveDate>,
+    /// Day any live account first saw the address, unless none has
+    pub global_first_seen: Option<NaiveDate>,
+    /// The account's transactions with the address that were accepted and not reported as
+    /// fraud
+    pub good_transaction_count: i64,
+}
+
+/// Queries over `email_addresses`
+pub struct EmailAddressRepo;
+
+impl EmailAddressRepo {
+    /// History of the address hashed to `email_hash` on an account, or `None` if the account
+    /// has not seen it
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        email_hash: &str,
+    ) -> sqlx::Result<Option<EmailAddressRecord>> {
+        sqlx::query_as!(
+            EmailAddressRecord,
+            r#"
+            SELECT e.domain, e.is_free, e.is_disposable, e.is_high_risk,
+                   COUNT(DISTINCT t.id) AS "transaction_count!",
+                   COUNT(DISTINCT t.id) FILTER (WHERE t.disposition = 'reject')
+                       AS "reject_count!",
+                   COUNT(DISTINCT t.id) FILTER (WHERE r.tag = 'chargeback')
+                       AS "chargeback_count!",
+                   COUNT(DISTINCT t.user_id) AS "user_count!",
+                   COUNT(DISTINCT tc.credit_card_id) AS "card_count!",
+                   e.first_seen, MAX(t.created_at) AS last_seen
+            FROM email_addresses e
+            LEFT JOIN transaction_emails te ON te.email_id = e.id
+            LEFT JOIN transactions t ON t.id = te.transaction_id
+            LEFT JOIN transaction_reports r ON r.transaction_id = t.id
+            LEFT JOIN transaction_credit_cards tc ON tc.transaction_id = t.id
+            WHERE e.account_id = $1 AND e.email_hash = $2
+            GROUP BY e.id
+            "#,
+            tenant.id(),
+            email_hash
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Addresses of the account other than the one hashed to `email_hash` that deliver to the
+    /// mailbox hashed to `mailbox_hash`, and the users other than `user_id` they were last
+    /// used by
+    pub async fn variants(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        mailbox_hash: &str,
+        email_hash: &str,
+        user_id: Option<Uuid>,
+    ) -> sqlx::Result<EmailVariantsRecord> {
+        sqlx::query_as!(
+            EmailVariantsRecord,
+            r#"
+            SELECT COUNT(*) AS "address_count!",
+                   COUNT(DISTINCT user_id) FILTER (WHERE user_id IS DISTINCT FROM $4)
+                       AS "user_count!"
+            FROM email_addresses
+            WHERE account_id = $1 AND mailbox_hash = $2 AND email_hash <> $3
+            "#,
+            tenant.id(),
+            mailbox_hash,
+            email_hash,
+            user_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// When the address hashed to `email_hash` was first seen by the account and by any live
+    /// account, and the account's good transactions with it, before a transaction with it is
+    /// stored
+    ///
+    /// Sandbox accounts are left out of the global first sighting, though a sandbox account
+    /// still counts its own.
+    pub async fn age(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        email_hash: &str,
+    ) -> sqlx::Result<EmailAgeRecord> {
+        sqlx::query_as!(
+            EmailAgeRecord,
+            r#"
+            WITH own AS (
+                SELECT e.first_seen,
+                       COUNT(DISTINCT t.id) FILTER (
+                           WHERE t.disposition = 'accept'
+                             AND (r.tag IS NULL OR r.tag = 'not_fraud')
+                       ) AS good
+                FROM email_addresses e
+                LEFT JOIN transaction_emails te ON te.email_id = e.id
+                LEFT JOIN transactions t ON t.id = te.transaction_id
+                LEFT JOIN transaction_reports r ON r.transaction_id = t.id
+                WHERE e.account_id = $1 AND e.email_hash = $2
+                GROUP BY e.id
+            ),
+            other AS (
+                SELECT MIN(e.first_seen) AS first_seen
+                FROM email_addresses e
+                JOIN accounts a ON a.id = e.account_id
+                WHERE e.email_hash = $2 AND a.sandbox_of IS NULL
+            )
+            SELECT own.first_seen AS "first_seen?",
+                   other.first_seen AS "global_first_seen?",
+                   COALESCE(own.good, 0) AS "good_transaction_count!"
+            FROM other
+            LEFT JOIN own ON true
+            "#,
+            tenant.id(),
+            email_hash
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Distinct addresses at `domain` the account has seen
+    pub async fn count_by_domain(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        domain: &str,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM email_addresses
+            WHERE account_id = $1 AND lower(domain) = lower($2)
+            "#,
+            tenant.id(),
+            domain
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// The account's `limit` most recent transactions that used the address hashed to
+    /// `email_hash`, newest first
+    pub async fn recent_transactions(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        email_hash: &str,
+        limit: i64,
+    ) -> sqlx::Result<Vec<TransactionRecord>> {
+        let records = sqlx::query_as!(
+            TransactionRecord,
+            r#"
+            SELECT t.id, t.account_id, t.user_id, t.external_transaction_id, t.risk_score,
+                   t.risk_level AS "risk_level: RiskLevel",
+                   t.disposition AS "disposition: Disposition",
+                   t.event_type AS "event_type: EventType",
+                   t.shop_id, t.event_time, t.original_transaction_id,
+                   t.warnings AS "warnings: Json<Vec<Warning>>",
+                   t.created_at
+            FROM transactions t
+            JOIN transaction_emails te ON te.transaction_id = t.id
+            JOIN email_addresses e ON e.id = te.email_id
+            WHERE t.account_id = $1 AND e.account_id = $1 AND e.email_hash = $2
+            ORDER BY t.created_at DESC, t.id DESC
+            LIMIT $3
+            "#,
+            tenant.id(),
+            email_hash,
+            limit
+        )
+        .fetch_all(executor)
+        .await?;
+        tenant.check_all(records)
+    }
+}
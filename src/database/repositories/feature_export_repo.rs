@@ -0,0 +1,42 @@
+//! Record of days exported to Parquet
+
+use chrono::NaiveDate;
+use sqlx::PgExecutor;
+
+/// Queries over `feature_exports`
+pub struct FeatureExportRepo;
+
+impl FeatureExportRepo {
+    /// Most recent day that has been exported
+    pub async fn last_exported_day(
+        executor: impl PgExecutor<'_>,
+    ) -> sqlx::Result<Option<NaiveDate>> {
+        sqlx::query_scalar!("SELECT MAX(day) FROM feature_exports")
+            .fetch_one(executor)
+            .await
+    }
+
+    /// Record that `day` was exported to `locations`
+    pub async fn record(
+        executor: impl PgExecutor<'_>,
+        day: NaiveDate,
+        locations: &[String],
+        size_bytes: i64,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO feature_exports (day, locations, size_bytes)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (day) DO UPDATE
+            SET locations = EXCLUDED.locations, size_bytes = EXCLUDED.size_bytes,
+                created_at = CURRENT_TIMESTAMP
+            "#,
+            day,
+            locations,
+            size_bytes
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
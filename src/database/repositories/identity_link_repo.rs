@@ -0,0 +1,148 @@
+//! Users linked by the emails, cards, and devices they share
+
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{database::Tenant, models::user::LinkType};
+
+/// Link from a user to another live user
+#[derive(Debug, Clone)]
+pub struct LinkedUserRecord {
+    /// The other user
+    pub user_id: Uuid,
+    /// The customer's own identifier for the other user
+    pub external_user_id: Option<String>,
+    /// What the users have in common
+    pub link_type: LinkType,
+    /// Distinct emails, cards, or devices in common
+    pub shared_count: i32,
+    /// When the link was first found
+    pub first_detected_at: DateTime<Utc>,
+    /// When the link was last found
+    pub last_detected_at: DateTime<Utc>,
+}
+
+/// Queries over `user_identity_links`
+pub struct IdentityLinkRepo;
+
+impl IdentityLinkRepo {
+    /// Link every pair of live users, across all accounts, that transacted with the same email
+    /// address, card token, or live device in the last `lookback_days`
+    ///
+    /// Entities used by more than `max_users_per_entity` users are skipped, as are pairs
+    /// already linked, whose counts and detection time are refreshed instead. Returns the
+    /// number of links written.
+    pub async fn refresh(
+        executor: impl PgExecutor<'_>,
+        lookback_days: i32,
+        max_users_per_entity: i64,
+    ) -> sqlx::Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            WITH usage AS (
+                SELECT t.account_id, 'email' AS link_type, te.email_id::text AS entity,
+                       t.user_id
+                FROM transactions t
+                JOIN transaction_emails te ON te.transaction_id = t.id
+                WHERE t.user_id IS NOT NULL
+                  AND t.event_time >= NOW() - make_interval(days => $1)
+                UNION
+                SELECT t.account_id, 'card', c.token_hash, t.user_id
+                FROM transactions t
+                JOIN transaction_credit_cards tc ON tc.transaction_id = t.id
+                JOIN credit_cards c ON c.id = tc.credit_card_id
+                WHERE t.user_id IS NOT NULL AND c.token_hash IS NOT NULL
+                  AND t.event_time >= NOW() - make_interval(days => $1)
+                UNION
+                SELECT t.account_id, 'device', td.device_id::text, t.user_id
+                FROM transactions t
+                JOIN transaction_devices td ON td.transaction_id = t.id
+                JOIN devices d ON d.id = td.device_id AND d.deleted_at IS NULL
+                WHERE t.user_id IS NOT NULL
+                  AND t.event_time >= NOW() - make_interval(days => $1)
+            ),
+            live AS (
+                SELECT s.*
+                FROM usage s
+                JOIN users u ON u.id = s.user_id AND u.deleted_at IS NULL
+            ),
+            entities AS (
+                SELECT account_id, link_type, entity
+                FROM live
+                GROUP BY account_id, link_type, entity
+                HAVING COUNT(*) BETWEEN 2 AND $2
+            ),
+            pairs AS (
+                SELECT e.account_id, e.link_type, a.user_id, b.user_id AS linked_user_id,
+                       COUNT(*) AS shared_count
+                FROM entities e
+                JOIN live a
+                    ON a.account_id = e.account_id AND a.link_type = e.link_type
+                   AND a.entity = e.entity
+                JOIN live b
+                    ON b.account_id = e.account_id AND b.link_type = e.link_type
+                   AND b.entity = e.entity AND a.user_id < b.user_id
+                GROUP BY e.account_id, e.link_type, a.user_id, b.user_id
+            )
+            INSERT INTO user_identity_links
+                (account_id, user_id, linked_user_id, link_type, shared_count)
+            SELECT account_id, user_id, linked_user_id, link_type, shared_count::int
+            FROM pairs
+            ON CONFLICT (user_id, linked_user_id, link_type) DO UPDATE SET
+                shared_count = EXCLUDED.shared_count,
+                last_detected_at = EXCLUDED.last_detected_at
+            "#,
+            lookback_days,
+            max_users_per_entity
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Live users linked to a user of the account, most recently confirmed first
+    pub async fn for_user(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+    ) -> sqlx::Result<Vec<LinkedUserRecord>> {
+        sqlx::query_as!(
+            LinkedUserRecord,
+            r#"
+            SELECT u.id AS user_id, u.external_user_id,
+                   l.link_type AS "link_type: LinkType", l.shared_count,
+                   l.first_detected_at, l.last_detected_at
+            FROM user_identity_links l
+            JOIN users u
+                ON u.id = CASE WHEN l.user_id = $2 THEN l.linked_user_id ELSE l.user_id END
+               AND u.deleted_at IS NULL
+            WHERE l.account_id = $1 AND (l.user_id = $2 OR l.linked_user_id = $2)
+            ORDER BY l.last_detected_at DESC, u.id, l.link_type
+            "#,
+            tenant.id(),
+            user_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Drop every link of a user of the account
+    pub async fn delete_for_user(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            DELETE FROM user_identity_links
+            WHERE account_id = $1 AND (user_id = $2 OR linked_user_id = $2)
+            "#,
+            tenant.id(),
+            user_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
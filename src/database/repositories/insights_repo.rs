@@ -0,0 +1,305 @@
+//! Entities linked to a transaction, with how often the account has seen them
+
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::Tenant,
+    models::{insights::IpHistoryInsights, transaction::DeliverySpeed},
+};
+
+/// Device a transaction came from
+#[derive(Debug, Clone)]
+pub struct DeviceInsightRecord {
+    /// IP address, without a prefix length
+    pub ip_address: String,
+    /// Autonomous system the IP address belongs to, if resolved
+    pub asn: Option<i64>,
+    /// Internet service provider or organization of the autonomous system
+    pub isp: Option<String>,
+    /// User agent string
+    pub user_agent: Option<String>,
+    /// Accept-Language header
+    pub accept_language: Option<String>,
+    /// When the account first saw the device
+    pub first_seen: DateTime<Utc>,
+    /// When the account last saw the device
+    pub last_seen: DateTime<Utc>,
+    /// Transactions linked to the device
+    pub transaction_count: i64,
+    /// Risk score from a current IP intelligence lookup
+    pub ip_risk_score: Option<f64>,
+    /// Location from a current IP intelligence lookup
+    pub ip_location: Option<serde_json::Value>,
+    /// Traits from a current IP intelligence lookup
+    pub ip_traits: Option<serde_json::Value>,
+    /// History of the transaction's IP address on the account
+    pub ip_history: Option<Json<IpHistoryInsights>>,
+    /// Composite risk score of the device, once computed
+    pub risk_score: Option<f64>,
+}
+
+/// Email address a transaction used
+#[derive(Debug, Clone)]
+pub struct EmailInsightRecord {
+    /// Domain of the address
+    pub domain: Option<String>,
+    /// Day the account first saw the address
+    pub first_seen: NaiveDate,
+    /// Whether the domain is a free provider
+    pub is_free: bool,
+    /// Whether the domain hands out disposable addresses
+    pub is_disposable: bool,
+    /// Whether the address is known to be high risk
+    pub is_high_risk: bool,
+    /// Transactions linked to the address
+    pub transaction_count: i64,
+}
+
+/// Billing or shipping address of a transaction
+#[derive(Debug, Clone)]
+pub struct AddressInsightRecord {
+    /// `billing` or `shipping`
+    pub address_type: String,
+    /// Requested delivery speed, for shipping addresses
+    pub delivery_speed: Option<DeliverySpeed>,
+    /// City name
+    pub city: Option<String>,
+    /// ISO 3166-2 subdivision code
+    pub region: Option<String>,
+    /// Postal code
+    pub postal_code: Option<String>,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: Option<String>,
+    /// Phone number, without the country code
+    pub phone_number: Option<String>,
+    /// Phone country calling code
+    pub phone_country_code: Option<String>,
+    /// Latitude, when geocoded
+    pub latitude: Option<f64>,
+    /// Longitude, when geocoded
+    pub longitude: Option<f64>,
+    /// Whether the address is known to be high risk
+    pub is_high_risk: bool,
+    /// Transactions linked to an address with the same normal form, or for addresses stored
+    /// before addresses were normalized the same street, postal code, and country; 1 without a
+    /// street address
+    pub transaction_count: i64,
+}
+
+/// Payment card of a transaction
+#[derive(Debug, Clone)]
+pub struct CreditCardInsightRecord {
+    /// First 6-8 digits of the card
+    pub issuer_id_number: Option<String>,
+    /// Last 2-4 digits of the card
+    pub last_digits: Option<String>,
+    /// Name of the issuing bank
+    pub bank_name: Option<String>,
+    /// Country where the card was issued
+    pub country: Option<String>,
+    /// Address Verification System result
+    pub avs_result: Option<String>,
+    /// CVV verification result
+    pub cvv_result: Option<String>,
+    /// Whether 3D Secure verification was successful
+    pub was_3d_secure_successful: Option<bool>,
+    /// Card network
+    pub brand: Option<String>,
+    /// Credit, debit, or charge
+    pub card_type: Option<String>,
+    /// Whether the card is a business card
+    pub is_business: bool,
+    /// Whether the card is prepaid
+    pub is_prepaid: bool,
+    /// Whether the card is a virtual card
+    pub is_virtual: bool,
+    /// How closely the name on the card matched the billing name
+    pub billing_name_match: Option<f64>,
+    /// How closely the name on the card matched the email address
+    pub email_name_match: Option<f64>,
+    /// Transactions paid with the same card token, or 1 without a token
+    pub transaction_count: i64,
+}
+
+/// How widely a phone number has been used within an account
+#[derive(Debug, Clone, Copy)]
+pub struct PhoneUsageRecord {
+    /// Transactions with an address having the number
+    pub transaction_count: i64,
+    /// Distinct users with an address having the number
+    pub user_count: i64,
+}
+
+/// Queries for transaction insights
+///
+/// The transaction itself must already have been found through a tenant-scoped query; the
+/// entities are additionally filtered on the tenant, as are the counts.
+pub struct InsightsRepo;
+
+impl InsightsRepo {
+    /// Device a transaction came from, unless it has since been deleted
+    pub async fn device(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<DeviceInsightRecord>> {
+        sqlx::query_as!(
+            DeviceInsightRecord,
+            r#"
+            SELECT host(d.ip_address) AS "ip_address!", d.asn, d.isp, d.user_agent,
+                   d.accept_language, d.first_seen, d.last_seen,
+                   (SELECT COUNT(*) FROM transaction_devices seen WHERE seen.device_id = d.id)
+                       AS "transaction_count!",
+                   c.risk_score AS "ip_risk_score?",
+                   NULLIF(c.location_data, '{}') AS "ip_location?",
+                   NULLIF(c.traits_data, '{}') AS "ip_traits?",
+                   CASE WHEN ia.account_id IS NOT NULL THEN jsonb_build_object(
+                       'risk', ia.risk_score,
+                       'transaction_count', ia.transaction_count,
+                       'reject_count', ia.reject_count,
+                       'chargeback_count', ia.chargeback_count,
+                       'user_count', ia.user_count,
+                       'card_count', ia.card_count,
+                       'first_seen', ia.first_seen,
+                       'last_seen', ia.last_seen
+                   ) END AS "ip_history?: Json<IpHistoryInsights>",
+                   d.risk_score
+            FROM transaction_devices td
+            JOIN devices d ON d.id = td.device_id
+            JOIN transactions t ON t.id = td.transaction_id
+            LEFT JOIN ip_risk_cache c ON c.ip_address = d.ip_address AND c.expires_at > NOW()
+            LEFT JOIN ip_addresses ia ON ia.account_id = t.account_id AND ia.ip_address = t.ip_address
+            WHERE td.transaction_id = $1 AND d.account_id = $2 AND d.deleted_at IS NULL
+            LIMIT 1
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Email address a transaction used
+    pub async fn email(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<EmailInsightRecord>> {
+        sqlx::query_as!(
+            EmailInsightRecord,
+            r#"
+            SELECT e.domain, e.first_seen, e.is_free, e.is_disposable, e.is_high_risk,
+                   (SELECT COUNT(*) FROM transaction_emails seen WHERE seen.email_id = e.id)
+                       AS "transaction_count!"
+            FROM transaction_emails te
+            JOIN email_addresses e ON e.id = te.email_id
+            WHERE te.transaction_id = $1 AND e.account_id = $2
+            LIMIT 1
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Billing and shipping addresses of a transaction
+    pub async fn addresses(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Vec<AddressInsightRecord>> {
+        sqlx::query_as!(
+            AddressInsightRecord,
+            r#"
+            SELECT ta.address_type,
+                   ta.delivery_speed AS "delivery_speed: DeliverySpeed",
+                   a.city, a.region, a.postal_code, a.country, a.phone_number,
+                   a.phone_country_code, a.latitude, a.longitude, a.is_high_risk,
+                   CASE WHEN a.address_line_1 IS NULL THEN 1
+                   WHEN a.address_hash IS NOT NULL THEN (
+                       SELECT COUNT(DISTINCT link.transaction_id)
+                       FROM addresses seen
+                       JOIN transaction_addresses link ON link.address_id = seen.id
+                       WHERE seen.account_id = a.account_id
+                         AND seen.address_hash = a.address_hash
+                   ) ELSE (
+                       SELECT COUNT(DISTINCT link.transaction_id)
+                       FROM addresses seen
+                       JOIN transaction_addresses link ON link.address_id = seen.id
+                       WHERE seen.account_id = a.account_id
+                         AND seen.address_line_1 = a.address_line_1
+                         AND seen.postal_code IS NOT DISTINCT FROM a.postal_code
+                         AND seen.country IS NOT DISTINCT FROM a.country
+                   ) END AS "transaction_count!"
+            FROM transaction_addresses ta
+            JOIN addresses a ON a.id = ta.address_id
+            WHERE ta.transaction_id = $1 AND a.account_id = $2
+            ORDER BY ta.address_type
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Payment card of a transaction
+    pub async fn credit_card(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<CreditCardInsightRecord>> {
+        sqlx::query_as!(
+            CreditCardInsightRecord,
+            r#"
+            SELECT c.issuer_id_number, c.last_digits, c.bank_name, c.country, c.avs_result,
+                   c.cvv_result, c.was_3d_secure_successful, c.brand, c.card_type,
+                   c.is_business, c.is_prepaid, c.is_virtual, c.billing_name_match,
+                   c.email_name_match,
+                   CASE WHEN c.token_hash IS NULL THEN 1 ELSE (
+                       SELECT COUNT(DISTINCT link.transaction_id)
+                       FROM credit_cards seen
+                       JOIN transaction_credit_cards link ON link.credit_card_id = seen.id
+                       WHERE seen.account_id = c.account_id AND seen.token_hash = c.token_hash
+                   ) END AS "transaction_count!"
+            FROM transaction_credit_cards tc
+            JOIN credit_cards c ON c.id = tc.credit_card_id
+            WHERE tc.transaction_id = $1 AND c.account_id = $2
+            LIMIT 1
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// How many of an account's transactions and users had addresses with a phone number
+    pub async fn phone_usage(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        phone_number: &str,
+        phone_country_code: Option<&str>,
+    ) -> sqlx::Result<PhoneUsageRecord> {
+        sqlx::query_as!(
+            PhoneUsageRecord,
+            r#"
+            SELECT COUNT(DISTINCT link.transaction_id) AS "transaction_count!",
+                   COUNT(DISTINCT a.user_id) AS "user_count!"
+            FROM addresses a
+            JOIN transaction_addresses link ON link.address_id = a.id
+            WHERE a.account_id = $1
+              AND a.phone_number = $2
+              AND a.phone_country_code IS NOT DISTINCT FROM $3
+            "#,
+            tenant.id(),
+            phone_number,
+            phone_country_code
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
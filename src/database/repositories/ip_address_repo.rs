@@ -0,0 +1,219 @@
+//! History of the IP addresses seen by each account
+
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+
+use crate::database::Tenant;
+
+/// What earlier transactions from an IP address say about it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IpHistoryRecord {
+    /// Transactions from the address
+    pub transaction_count: i64,
+    /// Of those, transactions rejected
+    pub reject_count: i64,
+    /// Of those, transactions reported as chargebacks
+    pub chargeback_count: i64,
+    /// Distinct users the transactions belonged to
+    pub user_count: i64,
+    /// Distinct payment cards the transactions used
+    pub card_count: i64,
+}
+
+/// History of an IP address on one account and on every other live account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IpReputationRecord {
+    /// History on the account itself, as of now
+    pub account: IpHistoryRecord,
+    /// History on other accounts, as of when each last saw the address; sandbox accounts are
+    /// left out
+    pub global: IpHistoryRecord,
+}
+
+/// Stored history and reputation of an IP address on an account
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IpAddressRecord {
+    /// What the account's transactions from the address say about it
+    pub history: IpHistoryRecord,
+    /// Reputation risk score, once computed
+    pub risk_score: Option<f64>,
+    /// When the account first saw the address
+    pub first_seen: DateTime<Utc>,
+    /// When the account last saw the address
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Queries over `ip_addresses`
+pub struct IpAddressRepo;
+
+impl IpAddressRepo {
+    /// Recompute the history of `ip_address` on an account from its transactions
+    ///
+    /// Returns `None` if the account has no transactions from the address.
+    pub async fn refresh(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        ip_address: &str,
+    ) -> sqlx::Result<Option<IpHistoryRecord>> {
+        sqlx::query_as!(
+            IpHistoryRecord,
+            r#"
+            INSERT INTO ip_addresses (
+                account_id, ip_address, transaction_count, reject_count, chargeback_count,
+                user_count, card_count, first_seen, last_seen
+            )
+            SELECT $1, $2::text::inet, COUNT(DISTINCT t.id),
+                   COUNT(DISTINCT t.id) FILTER (WHERE t.disposition = 'reject'),
+                   COUNT(DISTINCT t.id) FILTER (WHERE r.tag = 'chargeback'),
+                   COUNT(DISTINCT t.user_id), COUNT(DISTINCT tc.credit_card_id),
+                   MIN(t.created_at), MAX(t.created_at)
+            FROM transactions t
+            LEFT JOIN transaction_reports r ON r.transaction_id = t.id
+            LEFT JOIN transaction_credit_cards tc ON tc.transaction_id = t.id
+            WHERE t.account_id = $1 AND t.ip_address = $2::text::inet
+            HAVING COUNT(*) > 0
+            ON CONFLICT (account_id, ip_address) DO UPDATE SET
+                transaction_count = EXCLUDED.transaction_count,
+                reject_count = EXCLUDED.reject_count,
+                chargeback_count = EXCLUDED.chargeback_count,
+                user_count = EXCLUDED.user_count,
+                card_count = EXCLUDED.card_count,
+                first_seen = EXCLUDED.first_seen,
+                last_seen = EXCLUDED.last_seen
+            RETURNING transaction_count::BIGINT AS "transaction_count!",
+                      reject_count::BIGINT AS "reject_count!",
+                      chargeback_count::BIGINT AS "chargeback_count!",
+                      user_count::BIGINT AS "user_count!",
+                      card_count::BIGINT AS "card_count!"
+            "#,
+            tenant.id(),
+            ip_address
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Stored history of `ip_address` on an account, or `None` if the account has not seen it
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        ip_address: &str,
+    ) -> sqlx::Result<Option<IpAddressRecord>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT transaction_count::BIGINT AS "transaction_count!",
+                   reject_count::BIGINT AS "reject_count!",
+                   chargeback_count::BIGINT AS "chargeback_count!",
+                   user_count::BIGINT AS "user_count!",
+                   card_count::BIGINT AS "card_count!",
+                   risk_score, first_seen, last_seen
+            FROM ip_addresses
+            WHERE account_id = $1 AND ip_address = $2::text::inet
+            "#,
+            tenant.id(),
+            ip_address
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(row.map(|row| IpAddressRecord {
+            history: IpHistoryRecord {
+                transaction_count: row.transaction_count,
+                reject_count: row.reject_count,
+                chargeback_count: row.chargeback_count,
+                user_count: row.user_count,
+                card_count: row.card_count,
+            },
+            risk_score: row.risk_score,
+            first_seen: row.first_seen,
+            last_seen: row.last_seen,
+        }))
+    }
+
+    /// Store the reputation risk score of an IP address on an account
+    pub async fn set_risk_score(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        ip_address: &str,
+        risk_score: f64,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE ip_addresses
+            SET risk_score = $3
+            WHERE account_id = $1 AND ip_address = $2::text::inet
+            "#,
+            tenant.id(),
+            ip_address,
+            risk_score
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// History of `ip_address` before a transaction from it is stored
+    ///
+    /// The account's own history is counted from its transactions, so outcomes reported since
+    /// the address was last seen already count.
+    pub async fn reputation(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        ip_address: &str,
+    ) -> sqlx::Result<IpReputationRecord> {
+        let row = sqlx::query!(
+            r#"
+            WITH own AS (
+                SELECT COUNT(DISTINCT t.id) AS transactions,
+                       COUNT(DISTINCT t.id) FILTER (WHERE t.disposition = 'reject') AS rejects,
+                       COUNT(DISTINCT t.id) FILTER (WHERE r.tag = 'chargeback') AS chargebacks,
+                       COUNT(DISTINCT t.user_id) AS users,
+                       COUNT(DISTINCT tc.credit_card_id) AS cards
+                FROM transactions t
+                LEFT JOIN transaction_reports r ON r.transaction_id = t.id
+                LEFT JOIN transaction_credit_cards tc ON tc.transaction_id = t.id
+                WHERE t.account_id = $1 AND t.ip_address = $2::text::inet
+            ),
+            other AS (
+                SELECT COALESCE(SUM(i.transaction_count), 0) AS transactions,
+                       COALESCE(SUM(i.reject_count), 0) AS rejects,
+                       COALESCE(SUM(i.chargeback_count), 0) AS chargebacks,
+                       COALESCE(SUM(i.user_count), 0) AS users,
+                       COALESCE(SUM(i.card_count), 0) AS cards
+                FROM ip_addresses i
+                JOIN accounts a ON a.id = i.account_id
+                WHERE i.ip_address = $2::text::inet AND i.account_id <> $1
+                  AND a.sandbox_of IS NULL
+            )
+            SELECT own.transactions AS "transactions!", own.rejects AS "rejects!",
+                   own.chargebacks AS "chargebacks!", own.users AS "users!",
+                   own.cards AS "cards!",
+                   other.transactions::BIGINT AS "other_transactions!",
+                   other.rejects::BIGINT AS "other_rejects!",
+                   other.chargebacks::BIGINT AS "other_chargebacks!",
+                   other.users::BIGINT AS "other_users!",
+                   other.cards::BIGINT AS "other_cards!"
+            FROM own, other
+            "#,
+            tenant.id(),
+            ip_address
+        )
+        .fetch_one(executor)
+        .await?;
+        Ok(IpReputationRecord {
+            account: IpHistoryRecord {
+                transaction_count: row.transactions,
+                reject_count: row.rejects,
+                chargeback_count: row.chargebacks,
+                user_count: row.users,
+                card_count: row.cards,
+            },
+            global: IpHistoryRecord {
+                transaction_count: row.other_transactions,
+                reject_count: row.other_rejects,
+                chargeback_count: row.other_chargebacks,
+                user_count: row.other_users,
+                card_count: row.other_cards,
+            },
+        })
+    }
+}
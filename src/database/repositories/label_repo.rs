@@ -0,0 +1,263 @@
+//! Ground-truth transaction labels
+
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::{
+        label::{Label, LabelExportQuery, LabelSource, ListLabelsQuery},
+        transaction::{Disposition, EventType, RiskLevel},
+    },
+};
+
+/// Stored label row
+#[derive(Debug, Clone)]
+pub struct LabelRecord {
+    /// Labeled transaction
+    pub transaction_id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// What the transaction turned out to be
+    pub label: Label,
+    /// Where the label came from
+    pub source: LabelSource,
+    /// Notes given with a manual label
+    pub notes: Option<String>,
+    /// When the transaction was first labeled
+    pub created_at: DateTime<Utc>,
+    /// When the label last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TenantOwned for LabelRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Labeled transaction, as exported for model training
+#[derive(Debug, Clone)]
+pub struct LabeledTransactionRecord {
+    /// Transaction ID
+    pub transaction_id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Merchant's identifier for the transaction
+    pub external_transaction_id: Option<String>,
+    /// Type of event scored
+    pub event_type: EventType,
+    /// When the event occurred
+    pub event_time: DateTime<Utc>,
+    /// When the transaction was scored
+    pub scored_at: DateTime<Utc>,
+    /// Risk score
+    pub risk_score: f64,
+    /// Risk level of the score
+    pub risk_level: RiskLevel,
+    /// Disposition
+    pub disposition: Disposition,
+    /// Codes of the factors that raised the score, in order
+    pub rule_codes: Vec<String>,
+    /// What the transaction turned out to be
+    pub label: Label,
+    /// Where the label came from
+    pub source: LabelSource,
+    /// When the label last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TenantOwned for LabeledTransactionRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Queries over `transaction_labels`
+pub struct LabelRepo;
+
+impl LabelRepo {
+    /// Label a transaction from the outcome on record, unless it was labeled by hand
+    pub async fn upsert_derived(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        label: Label,
+        source: LabelSource,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO transaction_labels (transaction_id, account_id, label, source)
+            SELECT id, account_id, $2, $3
+            FROM transactions
+            WHERE id = $1
+            ON CONFLICT (transaction_id) DO UPDATE
+            SET label = EXCLUDED.label, source = EXCLUDED.source, notes = NULL
+            WHERE transaction_labels.source <> 'manual'
+            "#,
+            transaction_id,
+            label as _,
+            source as _
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Label one of an account's transactions by hand, returning `None` if there is no such
+    /// transaction
+    pub async fn set_manual(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        label: Label,
+        notes: Option<&str>,
+    ) -> sqlx::Result<Option<LabelRecord>> {
+        sqlx::query_as!(
+            LabelRecord,
+            r#"
+            INSERT INTO transaction_labels (transaction_id, account_id, label, source, notes)
+            SELECT id, account_id, $3, 'manual', $4
+            FROM transactions
+            WHERE id = $1 AND account_id = $2
+            ON CONFLICT (transaction_id) DO UPDATE
+            SET label = EXCLUDED.label, source = EXCLUDED.source, notes = EXCLUDED.notes
+            RETURNING transaction_id, account_id, label AS "label: Label",
+                      source AS "source: LabelSource", notes, created_at, updated_at
+            "#,
+            transaction_id,
+            tenant.id(),
+            label as _,
+            notes
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Fetch the label of one of an account's transactions
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<LabelRecord>> {
+        sqlx::query_as!(
+            LabelRecord,
+            r#"
+            SELECT transaction_id, account_id, label AS "label: Label",
+                   source AS "source: LabelSource", notes, created_at, updated_at
+            FROM transaction_labels
+            WHERE transaction_id = $1 AND account_id = $2
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Page through an account's labels, most recently changed first
+    pub async fn list(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        query: &ListLabelsQuery,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<LabelRecord>> {
+        sqlx::query_as!(
+            LabelRecord,
+            r#"
+            SELECT l.transaction_id, l.account_id, l.label AS "label: Label",
+                   l.source AS "source: LabelSource", l.notes, l.created_at, l.updated_at
+            FROM transaction_labels l
+            JOIN transactions t ON t.id = l.transaction_id
+            WHERE l.account_id = $1
+              AND ($2::varchar IS NULL OR l.label = $2)
+              AND ($3::varchar IS NULL OR l.source = $3)
+              AND ($4::timestamptz IS NULL OR t.created_at >= $4)
+              AND ($5::timestamptz IS NULL OR t.created_at < $5)
+            ORDER BY l.updated_at DESC, l.transaction_id
+            LIMIT $6 OFFSET $7
+            "#,
+            tenant.id(),
+            query.label as _,
+            query.source as _,
+            query.from_date,
+            query.to_date,
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Number of an account's labels matching the listing filter
+    pub async fn count(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        query: &ListLabelsQuery,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM transaction_labels l
+            JOIN transactions t ON t.id = l.transaction_id
+            WHERE l.account_id = $1
+              AND ($2::varchar IS NULL OR l.label = $2)
+              AND ($3::varchar IS NULL OR l.source = $3)
+              AND ($4::timestamptz IS NULL OR t.created_at >= $4)
+              AND ($5::timestamptz IS NULL OR t.created_at < $5)
+            "#,
+            tenant.id(),
+            query.label as _,
+            query.source as _,
+            query.from_date,
+            query.to_date
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// An account's labeled transactions, oldest scored first
+    pub async fn export(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        query: &LabelExportQuery,
+    ) -> sqlx::Result<Vec<LabeledTransactionRecord>> {
+        sqlx::query_as!(
+            LabeledTransactionRecord,
+            r#"
+            SELECT t.id AS transaction_id, t.account_id, t.external_transaction_id,
+                   t.event_type AS "event_type: EventType", t.event_time,
+                   t.created_at AS scored_at, t.risk_score,
+                   t.risk_level AS "risk_level: RiskLevel",
+                   t.disposition AS "disposition: Disposition",
+                   ARRAY(
+                       SELECT f.factor_code
+                       FROM risk_factors f
+                       WHERE f.transaction_id = t.id AND f.multiplier > 0
+                       ORDER BY f.created_at, f.factor_code
+                   ) AS "rule_codes!",
+                   l.label AS "label: Label", l.source AS "source: LabelSource", l.updated_at
+            FROM transaction_labels l
+            JOIN transactions t ON t.id = l.transaction_id
+            WHERE l.account_id = $1
+              AND ($2::varchar IS NULL OR l.label = $2)
+              AND ($3::timestamptz IS NULL OR t.created_at >= $3)
+              AND ($4::timestamptz IS NULL OR t.created_at < $4)
+            ORDER BY t.created_at, t.id
+            "#,
+            tenant.id(),
+            query.label as _,
+            query.from_date,
+            query.to_date
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+}
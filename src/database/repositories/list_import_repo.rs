@@ -0,0 +1,223 @@
+//! List entries imported in the background
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::list::{ListImportError, ListImportStatus, ListType},
+};
+
+/// Stored import row, without the submitted entries
+#[derive(Debug, Clone)]
+pub struct ListImportRecord {
+    /// Import ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// List the entries are imported into
+    pub list_type: ListType,
+    /// Progress of the import
+    pub status: ListImportStatus,
+    /// Entries submitted
+    pub total_entries: i32,
+    /// Entries imported or failed so far
+    pub processed_entries: i32,
+    /// Entities newly listed
+    pub created_entries: i32,
+    /// Entities already listed whose entries were replaced
+    pub updated_entries: i32,
+    /// Entries that could not be imported
+    pub failed_entries: i32,
+    /// The first failures
+    pub errors: Json<Vec<ListImportError>>,
+    /// When the import was submitted
+    pub created_at: DateTime<Utc>,
+    /// When processing started
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the import finished
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl TenantOwned for ListImportRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// List import claimed by the worker, with the entries still to import
+#[derive(Debug, Clone)]
+pub struct ClaimedListImportRecord {
+    /// Import ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// List the entries are imported into
+    pub list_type: ListType,
+    /// Every submitted entry, as submitted
+    pub entries: Json<Vec<serde_json::Value>>,
+    /// Entries already processed by an earlier run
+    pub processed_entries: i32,
+    /// Failures already listed
+    pub reported_errors: i32,
+}
+
+/// Progress made on one chunk of a list import
+#[derive(Debug, Clone, Default)]
+pub struct ListImportProgress {
+    /// Entries processed
+    pub processed: i32,
+    /// Entities newly listed
+    pub created: i32,
+    /// Entities already listed whose entries were replaced
+    pub updated: i32,
+    /// Entries that failed
+    pub failed: i32,
+    /// Failures to list
+    pub errors: Vec<ListImportError>,
+}
+
+/// Queries over `list_imports`
+pub struct ListImportRepo;
+
+impl ListImportRepo {
+    /// Queue entries for import into one of an account's lists
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        list_type: ListType,
+        entries: &[serde_json::Value],
+    ) -> sqlx::Result<ListImportRecord> {
+        let total_entries = i32::try_from(entries.len()).unwrap_or(i32::MAX);
+        let record = sqlx::query_as!(
+            ListImportRecord,
+            r#"
+            INSERT INTO list_imports (account_id, list_type, entries, total_entries)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, account_id, list_type AS "list_type: ListType",
+                      status AS "status: ListImportStatus", total_entries, processed_entries,
+                      created_entries, updated_entries, failed_entries,
+                      errors AS "errors: Json<Vec<ListImportError>>", created_at, started_at,
+                      completed_at
+            "#,
+            tenant.id(),
+            list_type as _,
+            Json(entries) as _,
+            total_entries
+        )
+        .fetch_one(executor)
+        .await?;
+        tenant.check(record)
+    }
+
+    /// Fetch one of an account's list imports
+    pub async fn find_by_id(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        import_id: Uuid,
+    ) -> sqlx::Result<Option<ListImportRecord>> {
+        sqlx::query_as!(
+            ListImportRecord,
+            r#"
+            SELECT id, account_id, list_type AS "list_type: ListType",
+                   status AS "status: ListImportStatus", total_entries, processed_entries,
+                   created_entries, updated_entries, failed_entries,
+                   errors AS "errors: Json<Vec<ListImportError>>", created_at, started_at,
+                   completed_at
+            FROM list_imports
+            WHERE id = $1 AND account_id = $2
+            "#,
+            import_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Mark the oldest pending list import processing and return it, or else one whose worker
+    /// has not reported progress for `stale_after_seconds`
+    pub async fn claim_next(
+        executor: impl PgExecutor<'_>,
+        stale_after_seconds: f64,
+    ) -> sqlx::Result<Option<ClaimedListImportRecord>> {
+        sqlx::query_as!(
+            ClaimedListImportRecord,
+            r#"
+            UPDATE list_imports
+            SET status = 'processing', heartbeat_at = NOW(),
+                started_at = COALESCE(started_at, NOW())
+            WHERE id = (
+                SELECT id
+                FROM list_imports
+                WHERE status = 'pending'
+                   OR (status = 'processing'
+                       AND heartbeat_at < NOW() - make_interval(secs => $1))
+                ORDER BY created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, account_id, list_type AS "list_type: ListType",
+                      entries AS "entries!: Json<Vec<serde_json::Value>>", processed_entries,
+                      jsonb_array_length(errors) AS "reported_errors!"
+            "#,
+            stale_after_seconds
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Add a chunk's progress to a list import and refresh its heartbeat
+    pub async fn record_progress(
+        executor: impl PgExecutor<'_>,
+        import_id: Uuid,
+        progress: &ListImportProgress,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE list_imports
+            SET processed_entries = processed_entries + $2,
+                created_entries = created_entries + $3,
+                updated_entries = updated_entries + $4,
+                failed_entries = failed_entries + $5,
+                errors = errors || $6,
+                heartbeat_at = NOW()
+            WHERE id = $1
+            "#,
+            import_id,
+            progress.processed,
+            progress.created,
+            progress.updated,
+            progress.failed,
+            Json(&progress.errors) as _
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Mark a list import completed, dropping the submitted entries
+    pub async fn complete(
+        executor: impl PgExecutor<'_>,
+        import_id: Uuid,
+    ) -> sqlx::Result<ListImportRecord> {
+        sqlx::query_as!(
+            ListImportRecord,
+            r#"
+            UPDATE list_imports
+            SET status = 'completed', entries = NULL, completed_at = NOW()
+            WHERE id = $1
+            RETURNING id, account_id, list_type AS "list_type: ListType",
+                      status AS "status: ListImportStatus", total_entries, processed_entries,
+                      created_entries, updated_entries, failed_entries,
+                      errors AS "errors: Json<Vec<ListImportError>>", created_at, started_at,
+                      completed_at
+            "#,
+            import_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
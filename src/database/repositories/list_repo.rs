@@ -0,0 +1,713 @@
+//! Lists of entities each account treats specially when scoring
+
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{
+    database::Tenant,
+    models::list::{
+        AsnListAction, AsnListEntryRequest, CountryListAction, CountryListEntryRequest,
+        ListEntityType, ListType,
+    },
+    scoring::BinInfo,
+};
+
+/// A network on an account's ASN list
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsnListEntryRecord {
+    /// Autonomous system number
+    pub asn: i64,
+    /// What listing the network does to transactions from it
+    pub action: AsnListAction,
+    /// Risk added to transactions from the network, for `boost` entries
+    pub score: Option<f64>,
+    /// Why the network was listed
+    pub reason: Option<String>,
+    /// When the network was listed
+    pub created_at: DateTime<Utc>,
+    /// When the entry was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A country on an account's country list
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountryListEntryRecord {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    /// What listing the country does to transactions involving it
+    pub action: CountryListAction,
+    /// Risk added to transactions involving the country, for `boost` entries
+    pub score: Option<f64>,
+    /// Why the country was listed
+    pub reason: Option<String>,
+    /// When the country was listed
+    pub created_at: DateTime<Utc>,
+    /// When the entry was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A range of cards in an account's BIN table
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinRangeRecord {
+    /// Leading digits of the card numbers in the range
+    pub prefix: String,
+    /// Card network, in lowercase
+    pub brand: Option<String>,
+    /// `credit`, `debit`, or `charge`
+    pub card_type: Option<String>,
+    /// ISO 3166-1 alpha-2 country the cards are issued in
+    pub country: Option<String>,
+    /// Name of the issuing bank
+    pub bank_name: Option<String>,
+    /// Whether the cards are prepaid
+    pub is_prepaid: bool,
+    /// Whether the cards are issued to businesses
+    pub is_business: bool,
+    /// Whether the cards are virtual
+    pub is_virtual: bool,
+    /// When the range was imported
+    pub created_at: DateTime<Utc>,
+    /// When the range was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An entity on one of an account's blocklist, allowlist, and watchlist
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListEntryRecord {
+    /// Entry ID
+    pub id: Uuid,
+    /// List the entity is on
+    pub list_type: ListType,
+    /// Kind of entity
+    pub entity_type: ListEntityType,
+    /// The entity, in normal form
+    pub value: String,
+    /// Why the entity was listed
+    pub reason: Option<String>,
+    /// When the entry stops applying, if ever
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Public identifier of the account that listed the entity
+    pub added_by: String,
+    /// When the entity was listed
+    pub created_at: DateTime<Utc>,
+    /// When the entry was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Entity to put on a list, with its value in normal form
+#[derive(Debug, Clone, Copy)]
+pub struct NewListEntry<'a> {
+    /// Owning account
+    pub tenant: Tenant,
+    /// List to put the entity on
+    pub list_type: ListType,
+    /// Kind of entity
+    pub entity_type: ListEntityType,
+    /// The entity, in normal form
+    pub value: &'a str,
+    /// Why the entity is listed
+    pub reason: Option<&'a str>,
+    /// When the entry stops applying, if ever
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Entity imported onto a list, with its value in normal form
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedListEntry {
+    /// Kind of entity
+    pub entity_type: ListEntityType,
+    /// The entity, in normal form
+    pub value: String,
+    /// Why the entity is listed
+    pub reason: Option<String>,
+    /// When the entry stops applying, if ever
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Queries over the list tables
+pub struct ListRepo;
+
+impl ListRepo {
+    /// Every network on the account's ASN list, in ascending order
+    pub async fn asn_entries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Vec<AsnListEntryRecord>> {
+        sqlx::query_as!(
+            AsnListEntryRecord,
+            r#"
+            SELECT asn, action AS "action: AsnListAction", score, reason, created_at, updated_at
+            FROM asn_list_entries
+            WHERE account_id = $1
+            ORDER BY asn
+            "#,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// The account's entry for network `asn`, if listed
+    pub async fn find_asn_entry(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        asn: i64,
+    ) -> sqlx::Result<Option<AsnListEntryRecord>> {
+        sqlx::query_as!(
+            AsnListEntryRecord,
+            r#"
+            SELECT asn, action AS "action: AsnListAction", score, reason, created_at, updated_at
+            FROM asn_list_entries
+            WHERE account_id = $1 AND asn = $2
+            "#,
+            tenant.id(),
+            asn
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// List a network, replacing the account's existing entry for it
+    pub async fn upsert_asn_entry(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        entry: &AsnListEntryRequest,
+    ) -> sqlx::Result<AsnListEntryRecord> {
+        sqlx::query_as!(
+            AsnListEntryRecord,
+            r#"
+            INSERT INTO asn_list_entries (account_id, asn, action, score, reason)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (account_id, asn) DO UPDATE SET
+                action = EXCLUDED.action,
+                score = EXCLUDED.score,
+                reason = EXCLUDED.reason
+            RETURNING asn, action AS "action: AsnListAction", score, reason, created_at,
+                      updated_at
+            "#,
+            tenant.id(),
+            i64::from(entry.asn),
+            entry.action as _,
+            entry.score,
+            entry.reason.as_deref()
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Remove network `asn` from the account's list, returning whether it was listed
+    pub async fn delete_asn_entry(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        asn: i64,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM asn_list_entries WHERE account_id = $1 AND asn = $2",
+            tenant.id(),
+            asn
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+    /// Every country on the account's country list, in alphabetical order
+    pub async fn country_entries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Vec<CountryListEntryRecord>> {
+        sqlx::query_as!(
+            CountryListEntryRecord,
+            r#"
+            SELECT country, action AS "action: CountryListAction", score, reason, created_at,
+                   updated_at
+            FROM country_list_entries
+            WHERE account_id = $1
+            ORDER BY country
+            "#,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// The account's entries for whichever of `countries` are listed
+    pub async fn find_country_entries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        countries: &[String],
+    ) -> sqlx::Result<Vec<CountryListEntryRecord>> {
+        sqlx::query_as!(
+            CountryListEntryRecord,
+            r#"
+            SELECT country, action AS "action: CountryListAction", score, reason, created_at,
+                   updated_at
+            FROM country_list_entries
+            WHERE account_id = $1 AND country = ANY($2)
+            ORDER BY country
+            "#,
+            tenant.id(),
+            countries
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// List a country, replacing the account's existing entry for it
+    pub async fn upsert_country_entry(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        entry: &CountryListEntryRequest,
+    ) -> sqlx::Result<CountryListEntryRecord> {
+        sqlx::query_as!(
+            CountryListEntryRecord,
+            r#"
+            INSERT INTO country_list_entries (account_id, country, action, score, reason)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (account_id, country) DO UPDATE SET
+                action = EXCLUDED.action,
+                score = EXCLUDED.score,
+                reason = EXCLUDED.reason
+            RETURNING country, action AS "action: CountryListAction", score, reason, created_at,
+                      updated_at
+            "#,
+            tenant.id(),
+            entry.country,
+            entry.action as _,
+            entry.score,
+            entry.reason.as_deref()
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Remove `country` from the account's list, returning whether it was listed
+    pub async fn delete_country_entry(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        country: &str,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM country_list_entries WHERE account_id = $1 AND country = $2",
+            tenant.id(),
+            country
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A page of the account's BIN table, in ascending order of prefix
+    pub async fn bin_ranges(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<BinRangeRecord>> {
+        sqlx::query_as!(
+            BinRangeRecord,
+            r#"
+            SELECT prefix, brand, card_type, country, bank_name, is_prepaid, is_business,
+                   is_virtual, created_at, updated_at
+            FROM bin_ranges
+            WHERE account_id = $1
+            ORDER BY prefix
+            LIMIT $2 OFFSET $3
+            "#,
+            tenant.id(),
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Ranges in the account's BIN table
+    pub async fn count_bin_ranges(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM bin_ranges WHERE account_id = $1"#,
+            tenant.id()
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// The account's range with the longest of `prefixes`, if it has one
+    pub async fn find_bin_range(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        prefixes: &[String],
+    ) -> sqlx::Result<Option<BinRangeRecord>> {
+        sqlx::query_as!(
+            BinRangeRecord,
+            r#"
+            SELECT prefix, brand, card_type, country, bank_name, is_prepaid, is_business,
+                   is_virtual, created_at, updated_at
+            FROM bin_ranges
+            WHERE account_id = $1 AND prefix = ANY($2)
+            ORDER BY length(prefix) DESC
+            LIMIT 1
+            "#,
+            tenant.id(),
+            prefixes
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Add `ranges` to the account's BIN table, replacing existing ranges with the same prefix,
+    /// and return how many were written
+    ///
+    /// The prefixes of `ranges` must be distinct.
+    pub async fn upsert_bin_ranges(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        ranges: &[BinInfo],
+    ) -> sqlx::Result<u64> {
+        // Blank for NULL, which text arrays cannot be bound with
+        let column = |field: fn(&BinInfo) -> Option<&str>| -> Vec<String> {
+            ranges
+                .iter()
+                .map(|range| field(range).unwrap_or_default().to_string())
+                .collect()
+        };
+        let flag =
+            |field: fn(&BinInfo) -> bool| -> Vec<bool> { ranges.iter().map(field).collect() };
+        let prefixes: Vec<String> = ranges.iter().map(|range| range.prefix.clone()).collect();
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO bin_ranges (
+                account_id, prefix, brand, card_type, country, bank_name, is_prepaid,
+                is_business, is_virtual
+            )
+            SELECT $1, prefix, NULLIF(brand, ''), NULLIF(card_type, ''), NULLIF(country, ''),
+                   NULLIF(bank_name, ''), is_prepaid, is_business, is_virtual
+            FROM UNNEST(
+                $2::text[], $3::text[], $4::text[], $5::text[], $6::text[], $7::bool[],
+                $8::bool[], $9::bool[]
+            ) AS r(prefix, brand, card_type, country, bank_name, is_prepaid, is_business,
+                   is_virtual)
+            ON CONFLICT (account_id, prefix) DO UPDATE SET
+                brand = EXCLUDED.brand,
+                card_type = EXCLUDED.card_type,
+                country = EXCLUDED.country,
+                bank_name = EXCLUDED.bank_name,
+                is_prepaid = EXCLUDED.is_prepaid,
+                is_business = EXCLUDED.is_business,
+                is_virtual = EXCLUDED.is_virtual
+            "#,
+            tenant.id(),
+            &prefixes,
+            &column(|range| range.brand.as_deref()),
+            &column(|range| range.card_type.as_deref()),
+            &column(|range| range.country.as_deref()),
+            &column(|range| range.bank_name.as_deref()),
+            &flag(|range| range.is_prepaid),
+            &flag(|range| range.is_business),
+            &flag(|range| range.is_virtual)
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Remove every range from the account's BIN table but those with one of `prefixes`,
+    /// returning how many were removed
+    pub async fn delete_bin_ranges_except(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        prefixes: &[String],
+    ) -> sqlx::Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM bin_ranges WHERE account_id = $1 AND prefix <> ALL($2)",
+            tenant.id(),
+            prefixes
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Remove the range with `prefix` from the account's BIN table, returning whether it was
+    /// there
+    pub async fn delete_bin_range(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        prefix: &str,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM bin_ranges WHERE account_id = $1 AND prefix = $2",
+            tenant.id(),
+            prefix
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// A page of the entries of one of the account's lists still in force, with those of the
+    /// blocklist its organization shares, optionally only those naming `entity_type`, newest
+    /// first
+    pub async fn list_entries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        list_type: ListType,
+        entity_type: Option<ListEntityType>,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<ListEntryRecord>> {
+        sqlx::query_as!(
+            ListEntryRecord,
+            r#"
+            SELECT e.id, e.list_type AS "list_type: ListType",
+                   e.entity_type AS "entity_type: ListEntityType", e.value, e.reason,
+                   e.expires_at, a.account_id AS added_by, e.created_at, e.updated_at
+            FROM list_entries e
+            JOIN accounts a ON a.id = e.account_id
+            WHERE e.list_type = $2
+              AND (e.account_id = $1 OR (e.list_type = 'blocklist' AND e.account_id IN (
+                  SELECT other.account_id
+                  FROM organization_members me
+                  JOIN organizations o ON o.id = me.organization_id AND o.share_blocklist
+                  JOIN organization_members other
+                    ON other.organization_id = me.organization_id AND other.status = 'active'
+                  WHERE me.account_id = $1 AND me.status = 'active'
+              )))
+              AND ($3::text IS NULL OR e.entity_type = $3)
+              AND (e.expires_at IS NULL OR e.expires_at > NOW())
+            ORDER BY e.created_at DESC, e.id DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            tenant.id(),
+            list_type as _,
+            entity_type.map(ListEntityType::name),
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Entries of one of the account's lists still in force, with those of the blocklist its
+    /// organization shares, optionally only those naming `entity_type`
+    pub async fn count_list_entries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        list_type: ListType,
+        entity_type: Option<ListEntityType>,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM list_entries e
+            WHERE e.list_type = $2
+              AND (e.account_id = $1 OR (e.list_type = 'blocklist' AND e.account_id IN (
+                  SELECT other.account_id
+                  FROM organization_members me
+                  JOIN organizations o ON o.id = me.organization_id AND o.share_blocklist
+                  JOIN organization_members other
+                    ON other.organization_id = me.organization_id AND other.status = 'active'
+                  WHERE me.account_id = $1 AND me.status = 'active'
+              )))
+              AND ($3::text IS NULL OR e.entity_type = $3)
+              AND (e.expires_at IS NULL OR e.expires_at > NOW())
+            "#,
+            tenant.id(),
+            list_type as _,
+            entity_type.map(ListEntityType::name)
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// The account's entries in force, on any list, and those of the blocklist its
+    /// organization shares, naming any of `entities`, given as pairs of entity type and
+    /// normalized value
+    pub async fn find_list_entries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        entities: &[(ListEntityType, String)],
+    ) -> sqlx::Result<Vec<ListEntryRecord>> {
+        let entity_types: Vec<&str> = entities
+            .iter()
+            .map(|(entity_type, _)| entity_type.name())
+            .collect();
+        let values: Vec<&str> = entities.iter().map(|(_, value)| value.as_str()).collect();
+        sqlx::query_as!(
+            ListEntryRecord,
+            r#"
+            SELECT e.id, e.list_type AS "list_type: ListType",
+                   e.entity_type AS "entity_type: ListEntityType", e.value, e.reason,
+                   e.expires_at, a.account_id AS added_by, e.created_at, e.updated_at
+            FROM list_entries e
+            JOIN UNNEST($2::text[], $3::text[]) AS wanted(entity_type, value)
+              ON wanted.entity_type = e.entity_type AND wanted.value = e.value
+            JOIN accounts a ON a.id = e.account_id
+            WHERE (e.expires_at IS NULL OR e.expires_at > NOW())
+              AND (e.account_id = $1 OR (e.list_type = 'blocklist' AND e.account_id IN (
+                  SELECT other.account_id
+                  FROM organization_members me
+                  JOIN organizations o ON o.id = me.organization_id AND o.share_blocklist
+                  JOIN organization_members other
+                    ON other.organization_id = me.organization_id AND other.status = 'active'
+                  WHERE me.account_id = $1 AND me.status = 'active'
+              )))
+            ORDER BY e.created_at, e.id
+            "#,
+            tenant.id(),
+            &entity_types as _,
+            &values as _
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Put an entity on a list, replacing the account's existing entry for it on that list
+    pub async fn upsert_list_entry(
+        executor: impl PgExecutor<'_>,
+        entry: NewListEntry<'_>,
+    ) -> sqlx::Result<ListEntryRecord> {
+        sqlx::query_as!(
+            ListEntryRecord,
+            r#"
+            WITH e AS (
+                INSERT INTO list_entries (
+                    account_id, list_type, entity_type, value, reason, expires_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (account_id, list_type, entity_type, value) DO UPDATE SET
+                    reason = EXCLUDED.reason,
+                    expires_at = EXCLUDED.expires_at
+                RETURNING *
+            )
+            SELECT e.id, e.list_type AS "list_type: ListType",
+                   e.entity_type AS "entity_type: ListEntityType", e.value, e.reason,
+                   e.expires_at, a.account_id AS added_by, e.created_at, e.updated_at
+            FROM e
+            JOIN accounts a ON a.id = e.account_id
+            "#,
+            entry.tenant.id(),
+            entry.list_type as _,
+            entry.entity_type as _,
+            entry.value,
+            entry.reason,
+            entry.expires_at
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Every entry of one of the account's lists still in force, optionally only those naming
+    /// `entity_type`, oldest first
+    pub async fn all_list_entries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        list_type: ListType,
+        entity_type: Option<ListEntityType>,
+    ) -> sqlx::Result<Vec<ListEntryRecord>> {
+        sqlx::query_as!(
+            ListEntryRecord,
+            r#"
+            SELECT e.id, e.list_type AS "list_type: ListType",
+                   e.entity_type AS "entity_type: ListEntityType", e.value, e.reason,
+                   e.expires_at, a.account_id AS added_by, e.created_at, e.updated_at
+            FROM list_entries e
+            JOIN accounts a ON a.id = e.account_id
+            WHERE e.account_id = $1 AND e.list_type = $2
+              AND ($3::text IS NULL OR e.entity_type = $3)
+              AND (e.expires_at IS NULL OR e.expires_at > NOW())
+            ORDER BY e.created_at, e.id
+            "#,
+            tenant.id(),
+            list_type as _,
+            entity_type.map(ListEntityType::name)
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Put `entries` on a list, replacing the account's existing entries for the same
+    /// entities, and return how many entities were newly listed
+    ///
+    /// The entities of `entries` must be distinct.
+    pub async fn upsert_list_entries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        list_type: ListType,
+        entries: &[ImportedListEntry],
+    ) -> sqlx::Result<i64> {
+        let entity_types: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry.entity_type.name())
+            .collect();
+        let values: Vec<&str> = entries.iter().map(|entry| entry.value.as_str()).collect();
+        // Blank for NULL, which text arrays cannot be bound with
+        let reasons: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry.reason.as_deref().unwrap_or_default())
+            .collect();
+        let expiries: Vec<String> = entries
+            .iter()
+            .map(|entry| {
+                entry
+                    .expires_at
+                    .map(|expires_at| expires_at.to_rfc3339())
+                    .unwrap_or_default()
+            })
+            .collect();
+        sqlx::query_scalar!(
+            r#"
+            WITH upserted AS (
+                INSERT INTO list_entries (
+                    account_id, list_type, entity_type, value, reason, expires_at
+                )
+                SELECT $1, $2, entity_type, value, NULLIF(reason, ''),
+                       NULLIF(expires_at, '')::timestamptz
+                FROM UNNEST($3::text[], $4::text[], $5::text[], $6::text[])
+                    AS e(entity_type, value, reason, expires_at)
+                ON CONFLICT (account_id, list_type, entity_type, value) DO UPDATE SET
+                    reason = EXCLUDED.reason,
+                    expires_at = EXCLUDED.expires_at
+                RETURNING xmax = 0 AS created
+            )
+            SELECT COUNT(*) FILTER (WHERE created) AS "created!" FROM upserted
+            "#,
+            tenant.id(),
+            list_type as _,
+            &entity_types as _,
+            &values as _,
+            &reasons as _,
+            &expiries
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Remove an entity from one of the account's lists, returning whether it was on it and
+    /// still in force
+    pub async fn delete_list_entry(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        list_type: ListType,
+        entity_type: ListEntityType,
+        value: &str,
+    ) -> sqlx::Result<bool> {
+        let deleted = sqlx::query_scalar!(
+            r#"
+            DELETE FROM list_entries
+            WHERE account_id = $1 AND list_type = $2 AND entity_type = $3 AND value = $4
+            RETURNING expires_at IS NULL OR expires_at > NOW() AS "active!"
+            "#,
+            tenant.id(),
+            list_type as _,
+            entity_type as _,
+            value
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(deleted.unwrap_or(false))
+    }
+}
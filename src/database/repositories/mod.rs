@@ -0,0 +1,95 @@
+//! Data access for the PostgreSQL schema
+//!
+//! Every query goes through `sqlx::query!`/`query_as!`, so the compiler checks it against the
+//! migrated schema (or the committed `.sqlx` offline data when no database is available).
+//! Repositories are stateless and take any executor, letting callers compose them inside a
+//! single database transaction.
+
+pub mod account_repo;
+pub mod anomaly_repo;
+pub mod auto_block_repo;
+pub mod background_job_repo;
+pub mod case_repo;
+pub mod device_repo;
+pub mod dispute_repo;
+pub mod email_address_repo;
+pub mod feature_export_repo;
+pub mod identity_link_repo;
+pub mod insights_repo;
+pub mod ip_address_repo;
+pub mod label_repo;
+pub mod list_import_repo;
+pub mod list_repo;
+pub mod notification_channel_repo;
+pub mod organization_repo;
+pub mod outbox_repo;
+pub mod processor_secret_repo;
+pub mod report_repo;
+pub mod rule_repo;
+pub mod scoring_revision_repo;
+pub mod transaction_repo;
+pub mod usage_repo;
+pub mod user_import_repo;
+pub mod user_repo;
+pub mod webhook_repo;
+
+pub use account_repo::{
+    AccountAccessRecord, AccountRecord, AccountRepo, AccountSettingsUpdate, AccountUsageRecord,
+    ApiKeyRecord, DueDeletionRecord, ExpiringKeyRecord, NewStatusChange, SigningKeyRecord,
+};
+pub use anomaly_repo::{AnomalyRepo, NewAnomaly};
+pub use auto_block_repo::{AutoBlockRecord, AutoBlockRepo, BreachCountRecord, NewAutoBlock};
+pub use background_job_repo::{BackgroundJobRecord, BackgroundJobRepo, ClaimedBackgroundJobRecord};
+pub use case_repo::{
+    CaseAnnotationRecord, CaseEntitiesRecord, CaseEventRecord, CaseQueueRecord, CaseRecord,
+    CaseReferenceRecord, CaseRepo, CaseReviewerRecord, SlaBreachRecord,
+};
+pub use device_repo::{
+    DeviceHistoryRecord, DeviceRecord, DeviceRepo, DeviceRiskInputsRecord, NewDevice,
+};
+pub use dispute_repo::{DisputeProgress, DisputeRecord, DisputeRepo};
+pub use email_address_repo::{
+    EmailAddressRecord, EmailAddressRepo, EmailAgeRecord, EmailVariantsRecord,
+};
+pub use feature_export_repo::FeatureExportRepo;
+pub use identity_link_repo::{IdentityLinkRepo, LinkedUserRecord};
+pub use insights_repo::{
+    AddressInsightRecord, CreditCardInsightRecord, DeviceInsightRecord, EmailInsightRecord,
+    InsightsRepo, PhoneUsageRecord,
+};
+pub use ip_address_repo::{IpAddressRecord, IpAddressRepo, IpHistoryRecord, IpReputationRecord};
+pub use label_repo::{LabelRecord, LabelRepo, LabeledTransactionRecord};
+pub use list_import_repo::{
+    ClaimedListImportRecord, ListImportProgress, ListImportRecord, ListImportRepo,
+};
+pub use list_repo::{
+    AsnListEntryRecord, BinRangeRecord, CountryListEntryRecord, ImportedListEntry, ListEntryRecord,
+    ListRepo, NewListEntry,
+};
+pub use notification_channel_repo::{NotificationChannelRecord, NotificationChannelRepo};
+pub use organization_repo::{
+    InvitationRecord, MemberRecord, MembershipRecord, OrganizationRecord, OrganizationRepo,
+};
+pub use outbox_repo::{DeadLetterRecord, OutboxRecord, OutboxRepo};
+pub use processor_secret_repo::ProcessorSecretRepo;
+pub use report_repo::{NewReport, ReportRecord, ReportRepo};
+pub use rule_repo::{
+    NewRuleSuggestion, OutcomeCountRecord, RuleRepo, RuleSuggestionRecord, RuleVersionRecord,
+};
+pub use scoring_revision_repo::{
+    LatestScoringRecord, NewScoringRevision, RescoreSourceRecord, ScoringRevisionRecord,
+    ScoringRevisionRepo,
+};
+pub use transaction_repo::{
+    BatchScoreTargetRecord, NewCreditCard, NewTransaction, ReportTargetRecord, TransactionRecord,
+    TransactionRepo, TransactionReportRecord,
+};
+pub use usage_repo::{BillingCycleRecord, DailyUsageRecord, UsageRepo};
+pub use user_import_repo::{ClaimedImportRecord, ImportProgress, UserImportRecord, UserImportRepo};
+pub use user_repo::{
+    CountryCountRecord, NewUser, UserDeviceUsageRecord, UserFlagsRecord, UserRecord, UserRepo,
+    UserRiskInputsRecord, UserVelocityRecord,
+};
+pub use webhook_repo::{
+    NewWebhookDelivery, WebhookDeliveryRecord, WebhookRecord, WebhookRepo, WebhookTargetRecord,
+};
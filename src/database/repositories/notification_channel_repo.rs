@@ -0,0 +1,195 @@
+//! Email and Slack channels high-severity events are announced on
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::notification::{ChannelKind, NotificationChannelRequest},
+};
+
+/// Stored notification channel
+#[derive(Debug, Clone)]
+pub struct NotificationChannelRecord {
+    /// Channel ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Channel name
+    pub name: String,
+    /// How announcements are delivered
+    pub kind: ChannelKind,
+    /// Email address or Slack incoming webhook URL
+    pub target: String,
+    /// Event types announced; empty for every notifiable event
+    pub events: Json<Vec<String>>,
+    /// Whether events are announced
+    pub is_active: bool,
+    /// When an event was last announced
+    pub last_notified_at: Option<DateTime<Utc>>,
+    /// When the channel was created
+    pub created_at: DateTime<Utc>,
+    /// When the channel last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TenantOwned for NotificationChannelRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Queries over `notification_channels`
+#[derive(Debug, Clone)]
+pub struct NotificationChannelRepo;
+
+impl NotificationChannelRepo {
+    /// An account's channels, oldest first
+    pub async fn list(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Vec<NotificationChannelRecord>> {
+        sqlx::query_as!(
+            NotificationChannelRecord,
+            r#"
+            SELECT id, account_id, name, kind AS "kind: ChannelKind", target,
+                   events AS "events: Json<Vec<String>>", is_active, last_notified_at,
+                   created_at, updated_at
+            FROM notification_channels
+            WHERE account_id = $1
+            ORDER BY created_at, id
+            "#,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Fetch one of an account's channels
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        channel_id: Uuid,
+    ) -> sqlx::Result<Option<NotificationChannelRecord>> {
+        sqlx::query_as!(
+            NotificationChannelRecord,
+            r#"
+            SELECT id, account_id, name, kind AS "kind: ChannelKind", target,
+                   events AS "events: Json<Vec<String>>", is_active, last_notified_at,
+                   created_at, updated_at
+            FROM notification_channels
+            WHERE id = $1 AND account_id = $2
+            "#,
+            channel_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Create a channel; fails with a unique violation when the account has one of that name
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        channel: &NotificationChannelRequest,
+    ) -> sqlx::Result<Uuid> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO notification_channels (account_id, name, kind, target, events, is_active)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+            "#,
+            tenant.id(),
+            channel.name,
+            channel.kind as _,
+            channel.target,
+            Json(&channel.events) as _,
+            channel.active
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Replace a channel, returning whether it existed; fails with a unique violation when the
+    /// account has another channel of the new name
+    pub async fn update(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        channel_id: Uuid,
+        channel: &NotificationChannelRequest,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE notification_channels
+            SET name = $3, kind = $4, target = $5, events = $6, is_active = $7
+            WHERE id = $1 AND account_id = $2
+            "#,
+            channel_id,
+            tenant.id(),
+            channel.name,
+            channel.kind as _,
+            channel.target,
+            Json(&channel.events) as _,
+            channel.active
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete a channel, returning whether it existed
+    pub async fn delete(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        channel_id: Uuid,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM notification_channels WHERE id = $1 AND account_id = $2",
+            channel_id,
+            tenant.id()
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Active channels of an account that announce events of `event_type`
+    pub async fn recipients(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        event_type: &str,
+    ) -> sqlx::Result<Vec<NotificationChannelRecord>> {
+        sqlx::query_as!(
+            NotificationChannelRecord,
+            r#"
+            SELECT id, account_id, name, kind AS "kind: ChannelKind", target,
+                   events AS "events: Json<Vec<String>>", is_active, last_notified_at,
+                   created_at, updated_at
+            FROM notification_channels
+            WHERE account_id = $1 AND is_active
+              AND (events = '[]'::jsonb OR events ? $2)
+            ORDER BY created_at, id
+            "#,
+            tenant.id(),
+            event_type
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Record that an event was announced on a channel
+    pub async fn touch(executor: impl PgExecutor<'_>, channel_id: Uuid) -> sqlx::Result<()> {
+        sqlx::query!(
+            "UPDATE notification_channels SET last_notified_at = CURRENT_TIMESTAMP WHERE id = $1",
+            channel_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,407 @@
+//! Organizations and their member accounts
+
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::models::{
+    account::SubscriptionTier,
+    organization::{MemberRole, MembershipStatus},
+};
+
+/// Organization with its billing account resolved to the public identifier
+#[derive(Debug, Clone)]
+pub struct OrganizationRecord {
+    /// Organization ID
+    pub id: Uuid,
+    /// Display name
+    pub name: String,
+    /// Internal ID of the account invoiced for every member
+    pub billing_account_id: Uuid,
+    /// Public identifier of the billing account
+    pub billing_account: String,
+    /// Whether members share their blocklist entries
+    pub share_blocklist: bool,
+    /// When the organization was created
+    pub created_at: DateTime<Utc>,
+    /// When the organization was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An account's active membership
+#[derive(Debug, Clone, Copy)]
+pub struct MembershipRecord {
+    /// Organization the account belongs to
+    pub organization_id: Uuid,
+    /// Role of the account
+    pub role: MemberRole,
+}
+
+/// Member or invited account, with the account details shown in listings
+#[derive(Debug, Clone)]
+pub struct MemberRecord {
+    /// Internal account ID
+    pub account_id: Uuid,
+    /// Public account identifier
+    pub public_account_id: String,
+    /// Role of the account
+    pub role: MemberRole,
+    /// Whether the account has joined
+    pub status: MembershipStatus,
+    /// Whether the account may look up users of the other members
+    pub user_lookup: bool,
+    /// Subscription tier of the account
+    pub subscription_tier: SubscriptionTier,
+    /// Scoring requests used in the account's current billing cycle
+    pub queries_used_this_month: i32,
+    /// When the account was invited
+    pub invited_at: DateTime<Utc>,
+    /// When the account joined
+    pub joined_at: Option<DateTime<Utc>>,
+}
+
+/// Pending invitation of an account
+#[derive(Debug, Clone)]
+pub struct InvitationRecord {
+    /// Inviting organization
+    pub organization_id: Uuid,
+    /// Display name of the organization
+    pub organization_name: String,
+    /// Role offered
+    pub role: MemberRole,
+    /// Whether user lookup is offered
+    pub user_lookup: bool,
+    /// When the invitation was sent
+    pub invited_at: DateTime<Utc>,
+}
+
+/// Queries over `organizations` and `organization_members`
+pub struct OrganizationRepo;
+
+impl OrganizationRepo {
+    /// Create an organization billed to `billing_account_id`, returning its ID
+    ///
+    /// The billing account is not made a member; add it with [`OrganizationRepo::add_member`]
+    /// in the same transaction.
+    pub async fn create(
+        executor: impl PgExecutor<'_>,
+        name: &str,
+        billing_account_id: Uuid,
+    ) -> sqlx::Result<Uuid> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO organizations (name, billing_account_id)
+            VALUES ($1, $2)
+            RETURNING id
+            "#,
+            name,
+            billing_account_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Fetch an organization
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+    ) -> sqlx::Result<Option<OrganizationRecord>> {
+        sqlx::query_as!(
+            OrganizationRecord,
+            r#"
+            SELECT o.id, o.name, o.billing_account_id, a.account_id AS billing_account,
+                   o.share_blocklist, o.created_at, o.updated_at
+            FROM organizations o
+            JOIN accounts a ON a.id = o.billing_account_id
+            WHERE o.id = $1
+            "#,
+            id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Change an organization's name, billing account, or blocklist sharing; `None` keeps the
+    /// stored value
+    pub async fn update(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+        name: Option<&str>,
+        billing_account_id: Option<Uuid>,
+        share_blocklist: Option<bool>,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE organizations
+            SET name = COALESCE($2, name),
+                billing_account_id = COALESCE($3, billing_account_id),
+                share_blocklist = COALESCE($4, share_blocklist)
+            WHERE id = $1
+            "#,
+            id,
+            name,
+            billing_account_id,
+            share_blocklist
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The organization an account belongs to and its role there, if any
+    pub async fn membership(
+        executor: impl PgExecutor<'_>,
+        account_id: Uuid,
+    ) -> sqlx::Result<Option<MembershipRecord>> {
+        sqlx::query_as!(
+            MembershipRecord,
+            r#"
+            SELECT organization_id, role AS "role: MemberRole"
+            FROM organization_members
+            WHERE account_id = $1 AND status = 'active'
+            "#,
+            account_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Add an account to an organization, returning `false` if it is already a member or
+    /// invited
+    pub async fn add_member(
+        executor: impl PgExecutor<'_>,
+        organization_id: Uuid,
+        account_id: Uuid,
+        role: MemberRole,
+        status: MembershipStatus,
+        user_lookup: bool,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO organization_members
+                (organization_id, account_id, role, status, user_lookup, joined_at)
+            VALUES ($1, $2, $3, $4, $5,
+                    CASE WHEN $4::VARCHAR = 'active' THEN CURRENT_TIMESTAMP END)
+            ON CONFLICT (organization_id, account_id) DO NOTHING
+            "#,
+            organization_id,
+            account_id,
+            role as _,
+            status as _,
+            user_lookup
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Members and invited accounts of an organization, owner first
+    pub async fn members(
+        executor: impl PgExecutor<'_>,
+        organization_id: Uuid,
+    ) -> sqlx::Result<Vec<MemberRecord>> {
+        sqlx::query_as!(
+            MemberRecord,
+            r#"
+            SELECT m.account_id, a.account_id AS public_account_id,
+                   m.role AS "role: MemberRole", m.status AS "status: MembershipStatus",
+                   m.user_lookup,
+                   a.subscription_tier AS "subscription_tier: SubscriptionTier",
+                   a.queries_used_this_month, m.invited_at, m.joined_at
+            FROM organization_members m
+            JOIN accounts a ON a.id = m.account_id
+            WHERE m.organization_id = $1
+            ORDER BY CASE m.role WHEN 'owner' THEN 0 WHEN 'admin' THEN 1 ELSE 2 END,
+                     m.status, a.account_id
+            "#,
+            organization_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// A member or invited account of an organization
+    pub async fn find_member(
+        executor: impl PgExecutor<'_>,
+        organization_id: Uuid,
+        account_id: Uuid,
+    ) -> sqlx::Result<Option<MemberRecord>> {
+        sqlx::query_as!(
+            MemberRecord,
+            r#"
+            SELECT m.account_id, a.account_id AS public_account_id,
+                   m.role AS "role: MemberRole", m.status AS "status: MembershipStatus",
+                   m.user_lookup,
+                   a.subscription_tier AS "subscription_tier: SubscriptionTier",
+                   a.queries_used_this_month, m.invited_at, m.joined_at
+            FROM organization_members m
+            JOIN accounts a ON a.id = m.account_id
+            WHERE m.organization_id = $1 AND m.account_id = $2
+            "#,
+            organization_id,
+            account_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Change a member's role or user lookup permission; `None` keeps the stored value
+    pub async fn update_member(
+        executor: impl PgExecutor<'_>,
+        organization_id: Uuid,
+        account_id: Uuid,
+        role: Option<MemberRole>,
+        user_lookup: Option<bool>,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE organization_members
+            SET role = COALESCE($3, role), user_lookup = COALESCE($4, user_lookup)
+            WHERE organization_id = $1 AND account_id = $2
+            "#,
+            organization_id,
+            account_id,
+            role as _,
+            user_lookup
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove a member, or withdraw or decline an invitation
+    pub async fn remove_member(
+        executor: impl PgExecutor<'_>,
+        organization_id: Uuid,
+        account_id: Uuid,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM organization_members
+            WHERE organization_id = $1 AND account_id = $2
+            "#,
+            organization_id,
+            account_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Pending invitations of an account, newest first
+    pub async fn invitations(
+        executor: impl PgExecutor<'_>,
+        account_id: Uuid,
+    ) -> sqlx::Result<Vec<InvitationRecord>> {
+        sqlx::query_as!(
+            InvitationRecord,
+            r#"
+            SELECT m.organization_id, o.name AS organization_name,
+                   m.role AS "role: MemberRole", m.user_lookup, m.invited_at
+            FROM organization_members m
+            JOIN organizations o ON o.id = m.organization_id
+            WHERE m.account_id = $1 AND m.status = 'invited'
+            ORDER BY m.invited_at DESC
+            "#,
+            account_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Turn an invitation into an active membership, returning `false` if there is none
+    pub async fn accept_invitation(
+        executor: impl PgExecutor<'_>,
+        organization_id: Uuid,
+        account_id: Uuid,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE organization_members
+            SET status = 'active', joined_at = CURRENT_TIMESTAMP
+            WHERE organization_id = $1 AND account_id = $2 AND status = 'invited'
+            "#,
+            organization_id,
+            account_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Active members, other than the account itself, of organizations billed to an account
+    pub async fn members_billed_to(
+        executor: impl PgExecutor<'_>,
+        account_id: Uuid,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM organization_members m
+            JOIN organizations o ON o.id = m.organization_id
+            WHERE o.billing_account_id = $1 AND m.status = 'active' AND m.account_id <> $1
+            "#,
+            account_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Delete the organizations billed to an account, with their memberships and invitations
+    pub async fn delete_billed_to(
+        executor: impl PgExecutor<'_>,
+        account_id: Uuid,
+    ) -> sqlx::Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM organizations WHERE billing_account_id = $1",
+            account_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Accounts whose users `account_id` may look up: itself, plus every other active member
+    /// of its organization if it holds the user lookup permission
+    pub async fn user_lookup_accounts(
+        executor: impl PgExecutor<'_>,
+        account_id: Uuid,
+    ) -> sqlx::Result<Vec<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT other.account_id AS "account_id!"
+            FROM organization_members me
+            JOIN organization_members other
+              ON other.organization_id = me.organization_id AND other.status = 'active'
+            WHERE me.account_id = $1 AND me.status = 'active' AND me.user_lookup
+              AND other.account_id <> $1
+            UNION ALL
+            SELECT $1
+            "#,
+            account_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Other active members of an account's organization when it shares blocklists, whose
+    /// scoring sees the account's blocklist entries
+    pub async fn blocklist_sharing_accounts(
+        executor: impl PgExecutor<'_>,
+        account_id: Uuid,
+    ) -> sqlx::Result<Vec<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT other.account_id
+            FROM organization_members me
+            JOIN organizations o ON o.id = me.organization_id AND o.share_blocklist
+            JOIN organization_members other
+              ON other.organization_id = me.organization_id AND other.status = 'active'
+            WHERE me.account_id = $1 AND me.status = 'active' AND other.account_id <> $1
+            "#,
+            account_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,344 @@
+//! Transactional outbox
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::database::{Tenant, TenantOwned};
+
+/// Stored outbox event
+#[derive(Debug, Clone)]
+pub struct OutboxRecord {
+    /// Event ID, stable across redeliveries
+    pub id: Uuid,
+    /// Account the event belongs to
+    pub account_id: Uuid,
+    /// Event type, e.g. `transaction.scored`
+    pub event_type: String,
+    /// ID of the entity the event describes
+    pub aggregate_id: Uuid,
+    /// Event body
+    pub payload: Json<serde_json::Value>,
+    /// Delivery attempts made so far
+    pub attempts: i32,
+    /// When the event was recorded
+    pub created_at: DateTime<Utc>,
+}
+
+impl TenantOwned for OutboxRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Stored event the dispatcher gave up on
+#[derive(Debug, Clone)]
+pub struct DeadLetterRecord {
+    /// Event ID
+    pub id: Uuid,
+    /// Account the event belongs to
+    pub account_id: Uuid,
+    /// Event type
+    pub event_type: String,
+    /// ID of the entity the event describes
+    pub aggregate_id: Uuid,
+    /// Event body
+    pub payload: Json<serde_json::Value>,
+    /// Delivery attempts made
+    pub attempts: i32,
+    /// Error of the last attempt
+    pub last_error: Option<String>,
+    /// When the event was recorded
+    pub created_at: DateTime<Utc>,
+    /// When the dispatcher gave up on the event
+    pub dead_lettered_at: DateTime<Utc>,
+}
+
+impl TenantOwned for DeadLetterRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Queries over `outbox_events`
+pub struct OutboxRepo;
+
+impl OutboxRepo {
+    /// Record an event for later delivery
+    ///
+    /// Call this with the same database transaction as the change the event describes.
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        account_id: Uuid,
+        event_type: &str,
+        aggregate_id: Uuid,
+        payload: serde_json::Value,
+    ) -> sqlx::Result<Uuid> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO outbox_events (account_id, event_type, aggregate_id, payload)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+            account_id,
+            event_type,
+            aggregate_id,
+            payload
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Lock a batch of due events, oldest first
+    ///
+    /// Rows are locked with `SKIP LOCKED`, so several dispatchers can poll concurrently without
+    /// delivering the same event twice in the same round. Dead letters are left alone until
+    /// they are redriven.
+    pub async fn claim_due(
+        executor: impl PgExecutor<'_>,
+        batch_size: i64,
+        max_attempts: i32,
+    ) -> sqlx::Result<Vec<OutboxRecord>> {
+        sqlx::query_as!(
+            OutboxRecord,
+            r#"
+            SELECT id, account_id, event_type, aggregate_id,
+                   payload AS "payload: Json<serde_json::Value>",
+                   attempts, created_at
+            FROM outbox_events
+            WHERE published_at IS NULL
+              AND dead_lettered_at IS NULL
+              AND available_at <= CURRENT_TIMESTAMP
+              AND attempts < $2
+            ORDER BY available_at, created_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+            batch_size,
+            max_attempts
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Fetch one of an account's events, whether or not it has been delivered
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        id: Uuid,
+    ) -> sqlx::Result<Option<OutboxRecord>> {
+        sqlx::query_as!(
+            OutboxRecord,
+            r#"
+            SELECT id, account_id, event_type, aggregate_id,
+                   payload AS "payload: Json<serde_json::Value>",
+                   attempts, created_at
+            FROM outbox_events
+            WHERE id = $1 AND account_id = $2
+            "#,
+            id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Mark an event as delivered
+    pub async fn mark_published(executor: impl PgExecutor<'_>, id: Uuid) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE outbox_events
+            SET published_at = CURRENT_TIMESTAMP, attempts = attempts + 1, last_error = NULL
+            WHERE id = $1
+            "#,
+            id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Record a failed delivery and schedule the next attempt
+    pub async fn mark_failed(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+        error: &str,
+        retry_at: DateTime<Utc>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE outbox_events
+            SET attempts = attempts + 1, last_error = $2, available_at = $3
+            WHERE id = $1
+            "#,
+            id,
+            error,
+            retry_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Record an event's last failed attempt and move it to the dead letters
+    pub async fn mark_dead_lettered(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+        error: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE outbox_events
+            SET attempts = attempts + 1, last_error = $2, dead_lettered_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+            id,
+            error
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Page of an account's dead letters, optionally of one event type, newest first
+    pub async fn dead_letters(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        event_type: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<DeadLetterRecord>> {
+        sqlx::query_as!(
+            DeadLetterRecord,
+            r#"
+            SELECT id, account_id, event_type, aggregate_id,
+                   payload AS "payload: Json<serde_json::Value>",
+                   attempts, last_error, created_at, dead_lettered_at AS "dead_lettered_at!"
+            FROM outbox_events
+            WHERE account_id = $1 AND dead_lettered_at IS NOT NULL
+              AND ($2::varchar IS NULL OR event_type = $2)
+            ORDER BY dead_lettered_at DESC, id
+            LIMIT $3 OFFSET $4
+            "#,
+            tenant.id(),
+            event_type,
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Number of an account's dead letters, optionally of one event type
+    pub async fn count_dead_letters(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        event_type: Option<&str>,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM outbox_events
+            WHERE account_id = $1 AND dead_lettered_at IS NOT NULL
+              AND ($2::varchar IS NULL OR event_type = $2)
+            "#,
+            tenant.id(),
+            event_type
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Number of dead letters across every account
+    pub async fn count_all_dead_letters(executor: impl PgExecutor<'_>) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM outbox_events
+            WHERE dead_lettered_at IS NOT NULL
+            "#
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Fetch one of an account's dead letters
+    pub async fn find_dead_letter(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        id: Uuid,
+    ) -> sqlx::Result<Option<DeadLetterRecord>> {
+        sqlx::query_as!(
+            DeadLetterRecord,
+            r#"
+            SELECT id, account_id, event_type, aggregate_id,
+                   payload AS "payload: Json<serde_json::Value>",
+                   attempts, last_error, created_at, dead_lettered_at AS "dead_lettered_at!"
+            FROM outbox_events
+            WHERE id = $1 AND account_id = $2 AND dead_lettered_at IS NOT NULL
+            "#,
+            id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Hand an account's dead letters back to the dispatcher with a fresh set of attempts,
+    /// returning how many there were
+    ///
+    /// `id` picks a single dead letter and `event_type` those of one type; with neither,
+    /// every dead letter of the account is redriven.
+    pub async fn redrive_dead_letters(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        id: Option<Uuid>,
+        event_type: Option<&str>,
+    ) -> sqlx::Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE outbox_events
+            SET attempts = 0, dead_lettered_at = NULL, available_at = CURRENT_TIMESTAMP
+            WHERE account_id = $1 AND dead_lettered_at IS NOT NULL
+              AND ($2::uuid IS NULL OR id = $2)
+              AND ($3::varchar IS NULL OR event_type = $3)
+            "#,
+            tenant.id(),
+            id,
+            event_type
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Delete an account's dead letters, returning how many there were
+    ///
+    /// `id` and `event_type` pick them as for [`OutboxRepo::redrive_dead_letters`].
+    pub async fn purge_dead_letters(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        id: Option<Uuid>,
+        event_type: Option<&str>,
+    ) -> sqlx::Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM outbox_events
+            WHERE account_id = $1 AND dead_lettered_at IS NOT NULL
+              AND ($2::uuid IS NULL OR id = $2)
+              AND ($3::varchar IS NULL OR event_type = $3)
+            "#,
+            tenant.id(),
+            id,
+            event_type
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
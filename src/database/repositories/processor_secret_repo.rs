@@ -0,0 +1,68 @@
+//! Secrets payment processors sign webhooks with
+
+use sqlx::PgExecutor;
+
+use crate::{database::Tenant, models::processor_event::Processor};
+
+/// Queries over `processor_secrets`
+pub struct ProcessorSecretRepo;
+
+impl ProcessorSecretRepo {
+    /// Store the secret `processor` signs an account's webhooks with, replacing any other
+    pub async fn upsert(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        processor: Processor,
+        secret: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO processor_secrets (account_id, processor, secret)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (account_id, processor) DO UPDATE SET secret = EXCLUDED.secret
+            "#,
+            tenant.id(),
+            processor as _,
+            secret
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Forget the secret of `processor`, returning whether one was stored
+    pub async fn delete(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        processor: Processor,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM processor_secrets WHERE account_id = $1 AND processor = $2",
+            tenant.id(),
+            processor as _
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Secret `processor` signs the webhooks of an account that is not closed with, if stored
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        processor: Processor,
+    ) -> sqlx::Result<Option<String>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT s.secret
+            FROM processor_secrets s
+            JOIN accounts a ON a.id = s.account_id
+            WHERE s.account_id = $1 AND s.processor = $2 AND a.status <> 'closed'
+            "#,
+            tenant.id(),
+            processor as _
+        )
+        .fetch_optional(executor)
+        .await
+    }
+}
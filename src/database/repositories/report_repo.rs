@@ -0,0 +1,211 @@
+//! Generated fraud summary reports
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::report::{ReportContent, ReportFrequency},
+};
+
+/// Stored report row
+#[derive(Debug, Clone)]
+pub struct ReportRecord {
+    /// Report ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Daily or weekly
+    pub frequency: ReportFrequency,
+    /// Inclusive start of the period
+    pub period_start: DateTime<Utc>,
+    /// Exclusive end of the period
+    pub period_end: DateTime<Utc>,
+    /// Figures making up the report
+    pub content: Json<ReportContent>,
+    /// Object storage location, once exported
+    pub export_location: Option<String>,
+    /// When the report was generated
+    pub created_at: DateTime<Utc>,
+}
+
+impl TenantOwned for ReportRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Report row to insert
+#[derive(Debug, Clone)]
+pub struct NewReport<'a> {
+    /// Owning account
+    pub tenant: Tenant,
+    /// Daily or weekly
+    pub frequency: ReportFrequency,
+    /// Inclusive start of the period
+    pub period_start: DateTime<Utc>,
+    /// Exclusive end of the period
+    pub period_end: DateTime<Utc>,
+    /// Figures making up the report
+    pub content: &'a ReportContent,
+}
+
+/// Queries over `reports`
+pub struct ReportRepo;
+
+impl ReportRepo {
+    /// Store a report, returning `None` if the account already has one for that period
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        report: NewReport<'_>,
+    ) -> sqlx::Result<Option<ReportRecord>> {
+        sqlx::query_as!(
+            ReportRecord,
+            r#"
+            INSERT INTO reports (account_id, frequency, period_start, period_end, content)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (account_id, frequency, period_start) DO NOTHING
+            RETURNING id, account_id, frequency AS "frequency: ReportFrequency", period_start,
+                      period_end, content AS "content: Json<ReportContent>", export_location,
+                      created_at
+            "#,
+            report.tenant.id(),
+            report.frequency as _,
+            report.period_start,
+            report.period_end,
+            Json(report.content) as _
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| report.tenant.check(record))
+        .transpose()
+    }
+
+    /// Fetch one of an account's reports
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        report_id: Uuid,
+    ) -> sqlx::Result<Option<ReportRecord>> {
+        sqlx::query_as!(
+            ReportRecord,
+            r#"
+            SELECT id, account_id, frequency AS "frequency: ReportFrequency", period_start,
+                   period_end, content AS "content: Json<ReportContent>", export_location,
+                   created_at
+            FROM reports
+            WHERE id = $1 AND account_id = $2
+            "#,
+            report_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Page through an account's reports, most recent period first
+    pub async fn list(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        frequency: Option<ReportFrequency>,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<ReportRecord>> {
+        sqlx::query_as!(
+            ReportRecord,
+            r#"
+            SELECT id, account_id, frequency AS "frequency: ReportFrequency", period_start,
+                   period_end, content AS "content: Json<ReportContent>", export_location,
+                   created_at
+            FROM reports
+            WHERE account_id = $1
+              AND ($2::varchar IS NULL OR frequency = $2)
+            ORDER BY period_start DESC, frequency
+            LIMIT $3 OFFSET $4
+            "#,
+            tenant.id(),
+            frequency as _,
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Number of an account's reports matching the listing filter
+    pub async fn count(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        frequency: Option<ReportFrequency>,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM reports
+            WHERE account_id = $1
+              AND ($2::varchar IS NULL OR frequency = $2)
+            "#,
+            tenant.id(),
+            frequency as _
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Accounts that already have a report for the period starting at `period_start`
+    pub async fn reported_accounts(
+        executor: impl PgExecutor<'_>,
+        frequency: ReportFrequency,
+        period_start: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<Uuid>> {
+        sqlx::query_scalar!(
+            "SELECT account_id FROM reports WHERE frequency = $1 AND period_start = $2",
+            frequency as _,
+            period_start
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Oldest reports not yet exported to object storage
+    pub async fn pending_export(
+        executor: impl PgExecutor<'_>,
+        limit: i64,
+    ) -> sqlx::Result<Vec<ReportRecord>> {
+        sqlx::query_as!(
+            ReportRecord,
+            r#"
+            SELECT id, account_id, frequency AS "frequency: ReportFrequency", period_start,
+                   period_end, content AS "content: Json<ReportContent>", export_location,
+                   created_at
+            FROM reports
+            WHERE export_location IS NULL
+            ORDER BY created_at
+            LIMIT $1
+            "#,
+            limit
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Record where a report was exported to
+    pub async fn mark_exported(
+        executor: impl PgExecutor<'_>,
+        report_id: Uuid,
+        location: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            "UPDATE reports SET export_location = $2 WHERE id = $1",
+            report_id,
+            location
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,357 @@
+//! Versions of an account's rule weights and the suggestions they are tuned from
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    scoring::RuleWeights,
+};
+
+/// Stored version of an account's rule weights
+#[derive(Debug, Clone)]
+pub struct RuleVersionRecord {
+    /// Owning account
+    pub account_id: Uuid,
+    /// Version number, counting up from 1
+    pub version: i32,
+    /// Weight of each reweighted rule, by factor code
+    pub weights: Json<HashMap<String, f64>>,
+    /// Suggestion whose application created the version
+    pub suggestion_id: Option<Uuid>,
+    /// When the version was created
+    pub created_at: DateTime<Utc>,
+}
+
+impl TenantOwned for RuleVersionRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Stored rule weight suggestion
+#[derive(Debug, Clone)]
+pub struct RuleSuggestionRecord {
+    /// Suggestion ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Factor code of the rule
+    pub rule_code: String,
+    /// Weight of the rule when the suggestion was computed
+    pub current_weight: f64,
+    /// Weight suggested for the rule
+    pub suggested_weight: f64,
+    /// Reported transactions the rule fired on
+    pub reported_count: i32,
+    /// Of those, transactions reported as fraud or abuse
+    pub fraud_count: i32,
+    /// Share of all the account's reported transactions reported as fraud or abuse
+    pub baseline_precision: f64,
+    /// When the suggestion was computed
+    pub created_at: DateTime<Utc>,
+    /// Version created by applying the suggestion
+    pub applied_version: Option<i32>,
+    /// When the suggestion was applied
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+impl TenantOwned for RuleSuggestionRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Rule weight suggestion to store
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewRuleSuggestion {
+    /// Factor code of the rule
+    pub rule_code: String,
+    /// Weight of the rule in force
+    pub current_weight: f64,
+    /// Weight suggested for the rule
+    pub suggested_weight: f64,
+    /// Reported transactions the rule fired on
+    pub reported_count: i32,
+    /// Of those, transactions reported as fraud or abuse
+    pub fraud_count: i32,
+    /// Share of all the account's reported transactions reported as fraud or abuse
+    pub baseline_precision: f64,
+}
+
+/// Reported transactions, in total or of those a rule fired on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutcomeCountRecord {
+    /// Factor code of the rule, or `None` for the account's total
+    pub rule_code: Option<String>,
+    /// Reported transactions
+    pub reported: i64,
+    /// Of those, transactions reported as fraud or abuse
+    pub fraudulent: i64,
+}
+
+/// Queries over `rule_versions` and `rule_suggestions`
+#[derive(Debug, Clone)]
+pub struct RuleRepo;
+
+impl RuleRepo {
+    /// Latest version of an account's rule weights, if it has any
+    pub async fn current_version(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Option<RuleVersionRecord>> {
+        sqlx::query_as!(
+            RuleVersionRecord,
+            r#"
+            SELECT account_id, version, weights AS "weights: Json<HashMap<String, f64>>",
+                   suggestion_id, created_at
+            FROM rule_versions
+            WHERE account_id = $1
+            ORDER BY version DESC
+            LIMIT 1
+            "#,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Weights of an account's latest version, or the built-in weights if it has none
+    pub async fn weights(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<RuleWeights> {
+        Ok(Self::current_version(executor, tenant)
+            .await?
+            .map(|record| RuleWeights {
+                version: record.version,
+                weights: record.weights.0,
+            })
+            .unwrap_or_default())
+    }
+
+    /// Every version of an account's rule weights, newest first
+    pub async fn versions(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Vec<RuleVersionRecord>> {
+        sqlx::query_as!(
+            RuleVersionRecord,
+            r#"
+            SELECT account_id, version, weights AS "weights: Json<HashMap<String, f64>>",
+                   suggestion_id, created_at
+            FROM rule_versions
+            WHERE account_id = $1
+            ORDER BY version DESC
+            "#,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Store a version of an account's rule weights; fails with a unique violation when the
+    /// version exists
+    pub async fn insert_version(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        version: i32,
+        weights: &HashMap<String, f64>,
+        suggestion_id: Option<Uuid>,
+    ) -> sqlx::Result<RuleVersionRecord> {
+        sqlx::query_as!(
+            RuleVersionRecord,
+            r#"
+            INSERT INTO rule_versions (account_id, version, weights, suggestion_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING account_id, version, weights AS "weights: Json<HashMap<String, f64>>",
+                      suggestion_id, created_at
+            "#,
+            tenant.id(),
+            version,
+            Json(weights) as _,
+            suggestion_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// An account's suggestions not yet applied, in order of rule code
+    pub async fn pending_suggestions(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Vec<RuleSuggestionRecord>> {
+        sqlx::query_as!(
+            RuleSuggestionRecord,
+            r#"
+            SELECT id, account_id, rule_code, current_weight, suggested_weight, reported_count,
+                   fraud_count, baseline_precision, created_at, applied_version, applied_at
+            FROM rule_suggestions
+            WHERE account_id = $1 AND applied_at IS NULL
+            ORDER BY rule_code
+            "#,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Fetch one of an account's suggestions, locking it until the surrounding transaction
+    /// ends
+    pub async fn lock_suggestion(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        suggestion_id: Uuid,
+    ) -> sqlx::Result<Option<RuleSuggestionRecord>> {
+        sqlx::query_as!(
+            RuleSuggestionRecord,
+            r#"
+            SELECT id, account_id, rule_code, current_weight, suggested_weight, reported_count,
+                   fraud_count, baseline_precision, created_at, applied_version, applied_at
+            FROM rule_suggestions
+            WHERE id = $1 AND account_id = $2
+            FOR UPDATE
+            "#,
+            suggestion_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Record that a suggestion was applied as `version`
+    pub async fn mark_applied(
+        executor: impl PgExecutor<'_>,
+        suggestion_id: Uuid,
+        version: i32,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE rule_suggestions
+            SET applied_version = $2, applied_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+            suggestion_id,
+            version
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Delete an account's pending suggestions
+    pub async fn clear_pending_suggestions(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            "DELETE FROM rule_suggestions WHERE account_id = $1 AND applied_at IS NULL",
+            tenant.id()
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Store suggestions for an account; fails with a unique violation when a rule has a
+    /// pending suggestion already
+    pub async fn insert_suggestions(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        suggestions: &[NewRuleSuggestion],
+    ) -> sqlx::Result<()> {
+        let rule_codes: Vec<&str> = suggestions.iter().map(|s| s.rule_code.as_str()).collect();
+        let current: Vec<f64> = suggestions.iter().map(|s| s.current_weight).collect();
+        let suggested: Vec<f64> = suggestions.iter().map(|s| s.suggested_weight).collect();
+        let reported: Vec<i32> = suggestions.iter().map(|s| s.reported_count).collect();
+        let fraud: Vec<i32> = suggestions.iter().map(|s| s.fraud_count).collect();
+        let baseline: Vec<f64> = suggestions.iter().map(|s| s.baseline_precision).collect();
+        sqlx::query!(
+            r#"
+            INSERT INTO rule_suggestions (
+                account_id, rule_code, current_weight, suggested_weight, reported_count,
+                fraud_count, baseline_precision
+            )
+            SELECT $1, * FROM UNNEST(
+                $2::varchar[], $3::float8[], $4::float8[], $5::int4[], $6::int4[], $7::float8[]
+            )
+            "#,
+            tenant.id(),
+            &rule_codes as _,
+            &current,
+            &suggested,
+            &reported,
+            &fraud,
+            &baseline
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Live accounts with transactions scored since `since` that have a reported outcome
+    pub async fn accounts_with_outcomes(
+        executor: impl PgExecutor<'_>,
+        since: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT a.id
+            FROM accounts a
+            WHERE a.status = 'active'
+              AND EXISTS (
+                  SELECT 1
+                  FROM transactions t
+                  JOIN transaction_reports r ON r.transaction_id = t.id
+                  WHERE t.account_id = a.id AND t.created_at >= $1
+              )
+            ORDER BY a.id
+            "#,
+            since
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// How the account's transactions scored since `since` were reported: in total, and for
+    /// each rule that raised the score of any of them
+    pub async fn outcome_counts(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        since: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<OutcomeCountRecord>> {
+        sqlx::query_as!(
+            OutcomeCountRecord,
+            r#"
+            WITH reported AS (
+                SELECT t.id, r.tag <> 'not_fraud' AS fraudulent
+                FROM transactions t
+                JOIN transaction_reports r ON r.transaction_id = t.id
+                WHERE t.account_id = $1 AND t.created_at >= $2
+            )
+            SELECT NULL::varchar AS rule_code,
+                   COUNT(*) AS "reported!",
+                   COUNT(*) FILTER (WHERE fraudulent) AS "fraudulent!"
+            FROM reported
+            UNION ALL
+            SELECT f.factor_code, COUNT(DISTINCT rp.id), COUNT(DISTINCT rp.id) FILTER (WHERE rp.fraudulent)
+            FROM reported rp
+            JOIN risk_factors f ON f.transaction_id = rp.id AND f.multiplier > 0
+            GROUP BY f.factor_code
+            "#,
+            tenant.id(),
+            since
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,244 @@
+//! Later scorings of stored transactions
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::Tenant,
+    models::transaction::{Disposition, RiskLevel, TransactionRequest},
+    scoring::RiskFactor,
+};
+
+/// Stored request of a transaction and its latest scoring, fetched for rescoring
+#[derive(Debug, Clone)]
+pub struct RescoreSourceRecord {
+    /// Transaction ID
+    pub transaction_id: Uuid,
+    /// User the transaction is attributed to
+    pub user_id: Option<Uuid>,
+    /// Device the transaction came from
+    pub device_id: Option<Uuid>,
+    /// Request the transaction was scored on, if it was kept
+    pub raw_request: Option<Json<TransactionRequest>>,
+    /// Hash of the transaction's email address
+    pub email_hash: Option<String>,
+    /// Hash of the mailbox the email address delivers to
+    pub mailbox_hash: Option<String>,
+    /// Card token as normalized for card hash list entries
+    pub card_hash: Option<String>,
+    /// Latest revision; 1 for the original scoring
+    pub revision: i32,
+    /// Risk score of the latest revision
+    pub risk_score: f64,
+}
+
+/// Latest scoring of a transaction: its latest revision, or the original scoring when it was
+/// never rescored
+#[derive(Debug, Clone)]
+pub struct LatestScoringRecord {
+    /// Latest revision; 1 for the original scoring
+    pub revision: i32,
+    /// Combined risk score
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Recommended action
+    pub disposition: Disposition,
+    /// Factors that contributed to the score
+    pub factors: Json<Vec<RiskFactor>>,
+    /// When the transaction was given this scoring
+    pub scored_at: DateTime<Utc>,
+}
+
+/// Stored scoring revision row
+#[derive(Debug, Clone)]
+pub struct ScoringRevisionRecord {
+    /// Revision row ID
+    pub id: Uuid,
+    /// Transaction that was rescored
+    pub transaction_id: Uuid,
+    /// Revision number, counting the original scoring as 1
+    pub revision: i32,
+    /// Combined risk score
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Recommended action
+    pub disposition: Disposition,
+    /// Factors that contributed to the score
+    pub factors: Json<Vec<RiskFactor>>,
+    /// When the transaction was rescored
+    pub created_at: DateTime<Utc>,
+}
+
+/// Scoring revision row to insert
+#[derive(Debug, Clone)]
+pub struct NewScoringRevision<'a> {
+    /// Transaction that was rescored
+    pub transaction_id: Uuid,
+    /// Revision number, one past the latest
+    pub revision: i32,
+    /// Combined risk score
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Recommended action
+    pub disposition: Disposition,
+    /// Factors that contributed to the score
+    pub factors: &'a [RiskFactor],
+}
+
+/// Queries over `scoring_revisions`
+///
+/// Revisions carry no account ID of their own; the transaction is looked up through a
+/// tenant-scoped query first.
+pub struct ScoringRevisionRepo;
+
+impl ScoringRevisionRepo {
+    /// Lock a transaction of an account and fetch what rescoring it needs
+    ///
+    /// The row lock serializes concurrent rescores so each gets its own revision number.
+    pub async fn lock_source(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<RescoreSourceRecord>> {
+        sqlx::query_as!(
+            RescoreSourceRecord,
+            r#"
+            SELECT t.id AS transaction_id, t.user_id,
+                   (
+                       SELECT td.device_id FROM transaction_devices td
+                       WHERE td.transaction_id = t.id
+                       LIMIT 1
+                   ) AS device_id,
+                   t.raw_request AS "raw_request: Json<TransactionRequest>",
+                   t.email_hash, t.mailbox_hash, t.card_hash,
+                   COALESCE(r.revision, 1) AS "revision!",
+                   COALESCE(r.risk_score, t.risk_score) AS "risk_score!"
+            FROM transactions t
+            LEFT JOIN LATERAL (
+                SELECT revision, risk_score
+                FROM scoring_revisions
+                WHERE transaction_id = t.id
+                ORDER BY revision DESC
+                LIMIT 1
+            ) r ON TRUE
+            WHERE t.id = $1 AND t.account_id = $2
+            FOR UPDATE OF t
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Fetch what scoring a transaction of an account again needs, without locking it
+    ///
+    /// For scoring that records nothing; see [`ScoringRevisionRepo::lock_source`] otherwise.
+    pub async fn find_source(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<RescoreSourceRecord>> {
+        sqlx::query_as!(
+            RescoreSourceRecord,
+            r#"
+            SELECT t.id AS transaction_id, t.user_id,
+                   (
+                       SELECT td.device_id FROM transaction_devices td
+                       WHERE td.transaction_id = t.id
+                       LIMIT 1
+                   ) AS device_id,
+                   t.raw_request AS "raw_request: Json<TransactionRequest>",
+                   t.email_hash, t.mailbox_hash, t.card_hash,
+                   COALESCE(r.revision, 1) AS "revision!",
+                   COALESCE(r.risk_score, t.risk_score) AS "risk_score!"
+            FROM transactions t
+            LEFT JOIN LATERAL (
+                SELECT revision, risk_score
+                FROM scoring_revisions
+                WHERE transaction_id = t.id
+                ORDER BY revision DESC
+                LIMIT 1
+            ) r ON TRUE
+            WHERE t.id = $1 AND t.account_id = $2
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Insert a scoring revision
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        revision: NewScoringRevision<'_>,
+    ) -> sqlx::Result<ScoringRevisionRecord> {
+        sqlx::query_as!(
+            ScoringRevisionRecord,
+            r#"
+            INSERT INTO scoring_revisions (
+                transaction_id, revision, risk_score, risk_level, disposition, factors
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, transaction_id, revision, risk_score,
+                      risk_level AS "risk_level: RiskLevel",
+                      disposition AS "disposition: Disposition",
+                      factors AS "factors: Json<Vec<RiskFactor>>",
+                      created_at
+            "#,
+            revision.transaction_id,
+            revision.revision,
+            revision.risk_score,
+            revision.risk_level as _,
+            revision.disposition as _,
+            Json(revision.factors) as _
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Latest scoring of one of an account's transactions
+    pub async fn latest(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<LatestScoringRecord>> {
+        sqlx::query_as!(
+            LatestScoringRecord,
+            r#"
+            SELECT COALESCE(r.revision, 1) AS "revision!",
+                   COALESCE(r.risk_score, t.risk_score) AS "risk_score!",
+                   COALESCE(r.risk_level, t.risk_level) AS "risk_level!: RiskLevel",
+                   COALESCE(r.disposition, t.disposition) AS "disposition!: Disposition",
+                   COALESCE(
+                       r.factors,
+                       (SELECT jsonb_agg(jsonb_build_object(
+                                   'code', f.factor_code, 'factor_type', f.factor_type,
+                                   'score', f.multiplier, 'reason', f.reason)
+                               ORDER BY f.created_at, f.id)
+                        FROM risk_factors f WHERE f.transaction_id = t.id),
+                       '[]'
+                   ) AS "factors!: Json<Vec<RiskFactor>>",
+                   COALESCE(r.created_at, t.created_at) AS "scored_at!"
+            FROM transactions t
+            LEFT JOIN LATERAL (
+                SELECT revision, risk_score, risk_level, disposition, factors, created_at
+                FROM scoring_revisions
+                WHERE transaction_id = t.id
+                ORDER BY revision DESC
+                LIMIT 1
+            ) r ON TRUE
+            WHERE t.id = $1 AND t.account_id = $2
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+}
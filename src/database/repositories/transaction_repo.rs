@@ -0,0 +1,1217 @@
+//! Scored transactions and the entities recorded alongside them
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::{
+        common::Cursor,
+        transaction::{
+            Address, CartItem, CreditCard, DeliverySpeed, Disposition, EventType,
+            ListTransactionsQuery, MatchKeys, Order, ReportTag, RiskLevel, TransactionRequest,
+            Warning,
+        },
+    },
+    scoring::{BinInfo, EmailTraits, RefundHistory, RiskFactor},
+};
+
+/// Stored transaction row
+#[derive(Debug, Clone)]
+pub struct TransactionRecord {
+    /// Transaction ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// User the transaction belongs to
+    pub user_id: Option<Uuid>,
+    /// Customer's own transaction ID
+    pub external_transaction_id: Option<String>,
+    /// Combined risk score
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Recommended action
+    pub disposition: Disposition,
+    /// Type of event that was scored
+    pub event_type: EventType,
+    /// Shop the event occurred in
+    pub shop_id: Option<String>,
+    /// When the event occurred
+    pub event_time: DateTime<Utc>,
+    /// Purchase a refund or cancellation undoes
+    pub original_transaction_id: Option<Uuid>,
+    /// Non-fatal issues found in the request
+    pub warnings: Json<Vec<Warning>>,
+    /// When the transaction was stored
+    pub created_at: DateTime<Utc>,
+}
+
+impl TenantOwned for TransactionRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Stored transaction picked for a batch scoring job
+#[derive(Debug, Clone)]
+pub struct BatchScoreTargetRecord {
+    /// Creation time of the transaction, or of its user when scoring users
+    pub created_at: DateTime<Utc>,
+    /// ID of the transaction, or of its user when scoring users
+    pub id: Uuid,
+    /// Transaction to score
+    pub transaction_id: Uuid,
+}
+
+/// Transaction an outcome is reported for, with what recording the outcome touches
+#[derive(Debug, Clone)]
+pub struct ReportTargetRecord {
+    /// Transaction ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// User the transaction belongs to
+    pub user_id: Option<Uuid>,
+    /// IP address the transaction came from
+    pub ip_address: Option<String>,
+}
+
+impl TenantOwned for ReportTargetRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Stored outcome of a transaction
+#[derive(Debug, Clone)]
+pub struct TransactionReportRecord {
+    /// Report ID
+    pub id: Uuid,
+    /// Transaction the outcome is recorded for
+    pub transaction_id: Uuid,
+    /// Recorded outcome
+    pub tag: ReportTag,
+    /// Processor reason code, for chargebacks
+    pub chargeback_code: Option<String>,
+    /// Free-form notes
+    pub notes: Option<String>,
+    /// When the outcome occurred
+    pub occurred_at: DateTime<Utc>,
+    /// When the outcome was last recorded
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Transaction row to insert
+#[derive(Debug, Clone)]
+pub struct NewTransaction<'a> {
+    /// Owning account
+    pub tenant: Tenant,
+    /// User the transaction belongs to
+    pub user_id: Option<Uuid>,
+    /// Customer's own transaction ID
+    pub external_transaction_id: Option<&'a str>,
+    /// Combined risk score
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Recommended action
+    pub disposition: Disposition,
+    /// Type of event that was scored
+    pub event_type: EventType,
+    /// Shop the event occurred in
+    pub shop_id: Option<&'a str>,
+    /// When the event occurred
+    pub event_time: DateTime<Utc>,
+    /// Purchase a refund or cancellation undoes
+    pub original_transaction_id: Option<Uuid>,
+    /// IP address the transaction came from
+    pub ip_address: &'a str,
+    /// Autonomous system the IP address belongs to, if resolved
+    pub asn: Option<i64>,
+    /// Internet service provider or organization of the autonomous system
+    pub isp: Option<&'a str>,
+    /// Hour of day (0-23) in the time zone of the IP address, if it could be located
+    pub local_hour: Option<i16>,
+    /// Raw device details as submitted
+    pub device_data: serde_json::Value,
+    /// Account-defined custom inputs
+    pub custom_inputs: serde_json::Value,
+    /// Non-fatal issues found in the request
+    pub warnings: &'a [Warning],
+    /// Request as kept for rescoring, with the email address and card token hashed
+    pub raw_request: serde_json::Value,
+    /// Hashes the email address and card are matched on
+    pub match_keys: &'a MatchKeys,
+}
+
+/// Payment card row to insert
+#[derive(Debug, Clone, Copy)]
+pub struct NewCreditCard<'a> {
+    /// Owning account
+    pub tenant: Tenant,
+    /// User the card was used by
+    pub user_id: Option<Uuid>,
+    /// Card as submitted
+    pub card: &'a CreditCard,
+    /// Hash of the card's token
+    pub token_hash: Option<&'a str>,
+    /// What the BIN table says about the card, filling in its issuing country and bank where
+    /// the request leaves them out
+    pub bin: Option<&'a BinInfo>,
+    /// How closely the name on the card matched the transaction's billing name
+    pub billing_name_match: Option<f64>,
+    /// How closely the name on the card matched the transaction's email address
+    pub email_name_match: Option<f64>,
+}
+
+/// Queries over `transactions` and the tables that hang off it
+pub struct TransactionRepo;
+
+impl TransactionRepo {
+    /// Insert a transaction row
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        transaction: NewTransaction<'_>,
+    ) -> sqlx::Result<TransactionRecord> {
+        sqlx::query_as!(
+            TransactionRecord,
+            r#"
+            INSERT INTO transactions (
+                account_id, user_id, external_transaction_id, risk_score, risk_level,
+                disposition, event_type, shop_id, event_time, device_data, custom_inputs, warnings,
+                raw_request, ip_address, asn, isp, local_hour, original_transaction_id, email_hash,
+                mailbox_hash, card_hash
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14::text::inet, $15, $16,
+                $17, $18, $19, $20, $21
+            )
+            RETURNING id, account_id, user_id, external_transaction_id, risk_score,
+                      risk_level AS "risk_level: RiskLevel",
+                      disposition AS "disposition: Disposition",
+                      event_type AS "event_type: EventType",
+                      shop_id, event_time, original_transaction_id,
+                      warnings AS "warnings: Json<Vec<Warning>>",
+                      created_at
+            "#,
+            transaction.tenant.id(),
+            transaction.user_id,
+            transaction.external_transaction_id,
+            transaction.risk_score,
+            transaction.risk_level as _,
+            transaction.disposition as _,
+            transaction.event_type as _,
+            transaction.shop_id,
+            transaction.event_time,
+            transaction.device_data,
+            transaction.custom_inputs,
+            Json(transaction.warnings) as _,
+            transaction.raw_request,
+            transaction.ip_address,
+            transaction.asn,
+            transaction.isp,
+            transaction.local_hour,
+            transaction.original_transaction_id,
+            transaction.match_keys.email_hash,
+            transaction.match_keys.mailbox_hash,
+            transaction.match_keys.card_hash
+        )
+        .fetch_one(executor)
+        .await
+        .and_then(|record| transaction.tenant.check(record))
+    }
+
+    /// Fetch a transaction belonging to an account
+    pub async fn find_by_id(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<TransactionRecord>> {
+        sqlx::query_as!(
+            TransactionRecord,
+            r#"
+            SELECT id, account_id, user_id, external_transaction_id, risk_score,
+                   risk_level AS "risk_level: RiskLevel",
+                   disposition AS "disposition: Disposition",
+                   event_type AS "event_type: EventType",
+                   shop_id, event_time, original_transaction_id,
+                   warnings AS "warnings: Json<Vec<Warning>>",
+                   created_at
+            FROM transactions
+            WHERE id = $1 AND account_id = $2
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Fetch a transaction belonging to an account, to report its outcome
+    pub async fn find_report_target(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<ReportTargetRecord>> {
+        sqlx::query_as!(
+            ReportTargetRecord,
+            r#"
+            SELECT id, account_id, user_id, host(ip_address) AS ip_address
+            FROM transactions
+            WHERE id = $1 AND account_id = $2
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Latest transaction of an account scored under the first of `external_ids` that any
+    /// was scored under
+    pub async fn find_by_external_ids(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        external_ids: &[String],
+    ) -> sqlx::Result<Option<ReportTargetRecord>> {
+        sqlx::query_as!(
+            ReportTargetRecord,
+            r#"
+            SELECT id, account_id, user_id, host(ip_address) AS ip_address
+            FROM transactions
+            WHERE account_id = $1 AND external_transaction_id = ANY($2)
+            ORDER BY array_position($2, external_transaction_id::text), created_at DESC
+            LIMIT 1
+            "#,
+            tenant.id(),
+            external_ids
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Latest purchase of an account scored under `external_transaction_id`
+    pub async fn find_purchase_by_external_id(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        external_transaction_id: &str,
+    ) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT id
+            FROM transactions
+            WHERE account_id = $1 AND external_transaction_id = $2
+              AND event_type IN ('purchase', 'recurring_purchase')
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+            tenant.id(),
+            external_transaction_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Up to `limit` of an account's transactions stored in `[created_after, created_before)`,
+    /// in creation order after `cursor`
+    pub async fn batch_score_transactions(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        created_after: Option<DateTime<Utc>>,
+        created_before: Option<DateTime<Utc>>,
+        cursor: Option<Cursor>,
+        limit: i64,
+    ) -> sqlx::Result<Vec<BatchScoreTargetRecord>> {
+        let (after_time, after_id) = cursor.map(|c| (c.created_at, c.id)).unzip();
+        sqlx::query_as!(
+            BatchScoreTargetRecord,
+            r#"
+            SELECT t.created_at, t.id, t.id AS "transaction_id!"
+            FROM transactions t
+            WHERE t.account_id = $1
+              AND ($2::timestamptz IS NULL OR t.created_at >= $2)
+              AND ($3::timestamptz IS NULL OR t.created_at < $3)
+              AND ($4::timestamptz IS NULL OR (t.created_at, t.id) > ($4, $5::uuid))
+            ORDER BY t.created_at, t.id
+            LIMIT $6
+            "#,
+            tenant.id(),
+            created_after,
+            created_before,
+            after_time,
+            after_id,
+            limit
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Count the transactions [`TransactionRepo::batch_score_transactions`] goes through
+    pub async fn count_batch_score_transactions(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        created_after: Option<DateTime<Utc>>,
+        created_before: Option<DateTime<Utc>>,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM transactions t
+            WHERE t.account_id = $1
+              AND ($2::timestamptz IS NULL OR t.created_at >= $2)
+              AND ($3::timestamptz IS NULL OR t.created_at < $3)
+            "#,
+            tenant.id(),
+            created_after,
+            created_before
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Up to `limit` of an account's users, in creation order after `cursor`, each with their
+    /// latest transaction stored in `[created_after, created_before)`
+    ///
+    /// Deleted users and users without such a transaction are skipped, as are users with a
+    /// transaction since `inactive_since`.
+    pub async fn batch_score_users(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        created_after: Option<DateTime<Utc>>,
+        created_before: Option<DateTime<Utc>>,
+        inactive_since: Option<DateTime<Utc>>,
+        cursor: Option<Cursor>,
+        limit: i64,
+    ) -> sqlx::Result<Vec<BatchScoreTargetRecord>> {
+        let (after_time, after_id) = cursor.map(|c| (c.created_at, c.id)).unzip();
+        sqlx::query_as!(
+            BatchScoreTargetRecord,
+            r#"
+            SELECT u.created_at, u.id, latest.id AS "transaction_id!"
+            FROM users u
+            JOIN LATERAL (
+                SELECT t.id
+                FROM transactions t
+                WHERE t.user_id = u.id AND t.account_id = $1
+                  AND ($2::timestamptz IS NULL OR t.created_at >= $2)
+                  AND ($3::timestamptz IS NULL OR t.created_at < $3)
+                ORDER BY t.created_at DESC
+                LIMIT 1
+            ) latest ON TRUE
+            WHERE u.account_id = $1 AND u.deleted_at IS NULL
+              AND ($4::timestamptz IS NULL OR u.last_transaction_at < $4)
+              AND ($5::timestamptz IS NULL OR (u.created_at, u.id) > ($5, $6::uuid))
+            ORDER BY u.created_at, u.id
+            LIMIT $7
+            "#,
+            tenant.id(),
+            created_after,
+            created_before,
+            inactive_since,
+            after_time,
+            after_id,
+            limit
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Count the users [`TransactionRepo::batch_score_users`] goes through
+    pub async fn count_batch_score_users(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        created_after: Option<DateTime<Utc>>,
+        created_before: Option<DateTime<Utc>>,
+        inactive_since: Option<DateTime<Utc>>,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM users u
+            WHERE u.account_id = $1 AND u.deleted_at IS NULL
+              AND ($4::timestamptz IS NULL OR u.last_transaction_at < $4)
+              AND EXISTS (
+                  SELECT 1
+                  FROM transactions t
+                  WHERE t.user_id = u.id AND t.account_id = $1
+                    AND ($2::timestamptz IS NULL OR t.created_at >= $2)
+                    AND ($3::timestamptz IS NULL OR t.created_at < $3)
+              )
+            "#,
+            tenant.id(),
+            created_after,
+            created_before,
+            inactive_since
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Purchases of one of an account's users and how many of them were refunded
+    pub async fn refund_history(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+    ) -> sqlx::Result<RefundHistory> {
+        sqlx::query_as!(
+            RefundHistory,
+            r#"
+            SELECT COUNT(*) AS "purchases!",
+                   COUNT(*) FILTER (
+                       WHERE EXISTS (
+                           SELECT 1 FROM transactions r
+                           WHERE r.original_transaction_id = p.id AND r.event_type = 'refund'
+                       )
+                   ) AS "refunded!"
+            FROM transactions p
+            WHERE p.account_id = $1 AND p.user_id = $2
+              AND p.event_type IN ('purchase', 'recurring_purchase')
+            "#,
+            tenant.id(),
+            user_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Fetch the stored request of a transaction belonging to an account
+    ///
+    /// The inner `None` is a transaction scored before requests were kept.
+    pub async fn find_raw_request(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<Option<Json<TransactionRequest>>>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT raw_request AS "raw_request: Json<TransactionRequest>"
+            FROM transactions
+            WHERE id = $1 AND account_id = $2
+            "#,
+            transaction_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Fetch one page of an account's transactions matching the listing filters
+    pub async fn list(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        query: &ListTransactionsQuery,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<TransactionRecord>> {
+        let q_pattern = query.q_pattern();
+        sqlx::query_as!(
+            TransactionRecord,
+            r#"
+            SELECT id, account_id, user_id, external_transaction_id, risk_score,
+                   risk_level AS "risk_level: RiskLevel",
+                   disposition AS "disposition: Disposition",
+                   event_type AS "event_type: EventType",
+                   shop_id, event_time, original_transaction_id,
+                   warnings AS "warnings: Json<Vec<Warning>>",
+                   created_at
+            FROM transactions t
+            WHERE t.account_id = $1
+              AND ($2::varchar IS NULL OR t.risk_level = $2)
+              AND ($3::varchar IS NULL OR t.disposition = $3)
+              AND ($4::timestamptz IS NULL OR t.created_at >= $4)
+              AND ($5::timestamptz IS NULL OR t.created_at < $5)
+              AND ($6::uuid IS NULL OR t.user_id = $6)
+              AND ($7::varchar IS NULL OR t.shop_id = $7)
+              AND ($8::text IS NULL OR t.external_transaction_id ILIKE $8)
+              AND (
+                  ($9::float8 IS NULL AND $10::float8 IS NULL AND $11::varchar IS NULL)
+                  OR EXISTS (
+                      SELECT 1 FROM orders o
+                      WHERE o.transaction_id = t.id
+                        AND ($9::float8 IS NULL OR o.amount >= $9)
+                        AND ($10::float8 IS NULL OR o.amount <= $10)
+                        AND ($11::varchar IS NULL OR o.currency = $11)
+                  )
+              )
+              AND ($12::varchar IS NULL OR EXISTS (
+                  SELECT 1 FROM transaction_addresses ta
+                  JOIN addresses a ON a.id = ta.address_id
+                  WHERE ta.transaction_id = t.id AND a.country = $12
+              ))
+              AND ($13::text IS NULL OR EXISTS (
+                  SELECT 1 FROM transaction_devices td
+                  JOIN devices d ON d.id = td.device_id
+                  WHERE td.transaction_id = t.id AND d.ip_address <<= $13::text::inet
+              ))
+              AND ($14::varchar IS NULL OR EXISTS (
+                  SELECT 1 FROM transaction_emails te
+                  JOIN email_addresses e ON e.id = te.email_id
+                  WHERE te.transaction_id = t.id AND lower(e.domain) = lower($14)
+              ))
+              AND ($15::varchar IS NULL OR EXISTS (
+                  SELECT 1 FROM risk_factors rf
+                  WHERE rf.transaction_id = t.id AND rf.factor_code = $15
+              ))
+              AND ($16::uuid IS NULL OR EXISTS (
+                  SELECT 1 FROM transaction_devices td
+                  WHERE td.transaction_id = t.id AND td.device_id = $16
+              ))
+            ORDER BY
+                CASE WHEN $17 = 'risk_score' THEN risk_score END ASC,
+                CASE WHEN $17 = '-risk_score' THEN risk_score END DESC,
+                CASE WHEN $17 = 'created_at' THEN created_at END ASC,
+                created_at DESC
+            LIMIT $18 OFFSET $19
+            "#,
+            tenant.id(),
+            query.risk_level as _,
+            query.disposition as _,
+            query.from_date,
+            query.to_date,
+            query.user_id,
+            query.shop_id,
+            q_pattern,
+            query.min_amount,
+            query.max_amount,
+            query.currency,
+            query.country,
+            query.ip_address,
+            query.email_domain,
+            query.rule,
+            query.device_id,
+            query.sort.unwrap_or_default().as_str(),
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Fetch up to `limit` of an account's transactions created after `cursor`, in creation order
+    ///
+    /// Keyset pagination: each page continues from the creation time and ID of the last row of
+    /// the page before, so deep pages cost as little as the first and rows inserted meanwhile
+    /// neither shift nor repeat items. `ascending` runs oldest first; "after" follows that
+    /// direction. Without a cursor the listing starts from its beginning.
+    pub async fn list_after(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        query: &ListTransactionsQuery,
+        cursor: Option<Cursor>,
+        ascending: bool,
+        limit: i64,
+    ) -> sqlx::Result<Vec<TransactionRecord>> {
+        let q_pattern = query.q_pattern();
+        let (after_time, after_id) = cursor.map(|c| (c.created_at, c.id)).unzip();
+        let records = if ascending {
+            sqlx::query_as!(
+                TransactionRecord,
+                r#"
+                SELECT id, account_id, user_id, external_transaction_id, risk_score,
+                       risk_level AS "risk_level: RiskLevel",
+                       disposition AS "disposition: Disposition",
+                       event_type AS "event_type: EventType",
+                       shop_id, event_time, original_transaction_id,
+                       warnings AS "warnings: Json<Vec<Warning>>",
+                       created_at
+                FROM transactions t
+                WHERE t.account_id = $1
+                  AND ($2::varchar IS NULL OR t.risk_level = $2)
+                  AND ($3::varchar IS NULL OR t.disposition = $3)
+                  AND ($4::timestamptz IS NULL OR t.created_at >= $4)
+                  AND ($5::timestamptz IS NULL OR t.created_at < $5)
+                  AND ($6::uuid IS NULL OR t.user_id = $6)
+                  AND ($7::varchar IS NULL OR t.shop_id = $7)
+                  AND ($8::text IS NULL OR t.external_transaction_id ILIKE $8)
+                  AND (
+                      ($9::float8 IS NULL AND $10::float8 IS NULL AND $11::varchar IS NULL)
+                      OR EXISTS (
+                          SELECT 1 FROM orders o
+                          WHERE o.transaction_id = t.id
+                            AND ($9::float8 IS NULL OR o.amount >= $9)
+                            AND ($10::float8 IS NULL OR o.amount <= $10)
+                            AND ($11::varchar IS NULL OR o.currency = $11)
+                      )
+                  )
+                  AND ($12::varchar IS NULL OR EXISTS (
+                      SELECT 1 FROM transaction_addresses ta
+                      JOIN addresses a ON a.id = ta.address_id
+                      WHERE ta.transaction_id = t.id AND a.country = $12
+                  ))
+                  AND ($13::text IS NULL OR EXISTS (
+                      SELECT 1 FROM transaction_devices td
+                      JOIN devices d ON d.id = td.device_id
+                      WHERE td.transaction_id = t.id AND d.ip_address <<= $13::text::inet
+                  ))
+                  AND ($14::varchar IS NULL OR EXISTS (
+                      SELECT 1 FROM transaction_emails te
+                      JOIN email_addresses e ON e.id = te.email_id
+                      WHERE te.transaction_id = t.id AND lower(e.domain) = lower($14)
+                  ))
+                  AND ($15::varchar IS NULL OR EXISTS (
+                      SELECT 1 FROM risk_factors rf
+                      WHERE rf.transaction_id = t.id AND rf.factor_code = $15
+                  ))
+                  AND ($16::uuid IS NULL OR EXISTS (
+                      SELECT 1 FROM transaction_devices td
+                      WHERE td.transaction_id = t.id AND td.device_id = $16
+                  ))
+                  AND ($17::timestamptz IS NULL OR (t.created_at, t.id) > ($17, $18::uuid))
+                ORDER BY created_at ASC, id ASC
+                LIMIT $19
+                "#,
+                tenant.id(),
+                query.risk_level as _,
+                query.disposition as _,
+                query.from_date,
+                query.to_date,
+                query.user_id,
+                query.shop_id,
+                q_pattern,
+                query.min_amount,
+                query.max_amount,
+                query.currency,
+                query.country,
+                query.ip_address,
+                query.email_domain,
+                query.rule,
+                query.device_id,
+                after_time,
+                after_id,
+                limit
+            )
+            .fetch_all(executor)
+            .await?
+        } else {
+            sqlx::query_as!(
+                TransactionRecord,
+                r#"
+                SELECT id, account_id, user_id, external_transaction_id, risk_score,
+                       risk_level AS "risk_level: RiskLevel",
+                       disposition AS "disposition: Disposition",
+                       event_type AS "event_type: EventType",
+                       shop_id, event_time, original_transaction_id,
+                       warnings AS "warnings: Json<Vec<Warning>>",
+                       created_at
+                FROM transactions t
+                WHERE t.account_id = $1
+                  AND ($2::varchar IS NULL OR t.risk_level = $2)
+                  AND ($3::varchar IS NULL OR t.disposition = $3)
+                  AND ($4::timestamptz IS NULL OR t.created_at >= $4)
+                  AND ($5::timestamptz IS NULL OR t.created_at < $5)
+                  AND ($6::uuid IS NULL OR t.user_id = $6)
+                  AND ($7::varchar IS NULL OR t.shop_id = $7)
+                  AND ($8::text IS NULL OR t.external_transaction_id ILIKE $8)
+                  AND (
+                      ($9::float8 IS NULL AND $10::float8 IS NULL AND $11::varchar IS NULL)
+                      OR EXISTS (
+                          SELECT 1 FROM orders o
+                          WHERE o.transaction_id = t.id
+                            AND ($9::float8 IS NULL OR o.amount >= $9)
+                            AND ($10::float8 IS NULL OR o.amount <= $10)
+                            AND ($11::varchar IS NULL OR o.currency = $11)
+                      )
+                  )
+                  AND ($12::varchar IS NULL OR EXISTS (
+                      SELECT 1 FROM transaction_addresses ta
+                      JOIN addresses a ON a.id = ta.address_id
+                      WHERE ta.transaction_id = t.id AND a.country = $12
+                  ))
+                  AND ($13::text IS NULL OR EXISTS (
+                      SELECT 1 FROM transaction_devices td
+                      JOIN devices d ON d.id = td.device_id
+                      WHERE td.transaction_id = t.id AND d.ip_address <<= $13::text::inet
+                  ))
+                  AND ($14::varchar IS NULL OR EXISTS (
+                      SELECT 1 FROM transaction_emails te
+                      JOIN email_addresses e ON e.id = te.email_id
+                      WHERE te.transaction_id = t.id AND lower(e.domain) = lower($14)
+                  ))
+                  AND ($15::varchar IS NULL OR EXISTS (
+                      SELECT 1 FROM risk_factors rf
+                      WHERE rf.transaction_id = t.id AND rf.factor_code = $15
+                  ))
+                  AND ($16::uuid IS NULL OR EXISTS (
+                      SELECT 1 FROM transaction_devices td
+                      WHERE td.transaction_id = t.id AND td.device_id = $16
+                  ))
+                  AND ($17::timestamptz IS NULL OR (t.created_at, t.id) < ($17, $18::uuid))
+                ORDER BY created_at DESC, id DESC
+                LIMIT $19
+                "#,
+                tenant.id(),
+                query.risk_level as _,
+                query.disposition as _,
+                query.from_date,
+                query.to_date,
+                query.user_id,
+                query.shop_id,
+                q_pattern,
+                query.min_amount,
+                query.max_amount,
+                query.currency,
+                query.country,
+                query.ip_address,
+                query.email_domain,
+                query.rule,
+                query.device_id,
+                after_time,
+                after_id,
+                limit
+            )
+            .fetch_all(executor)
+            .await?
+        };
+        tenant.check_all(records)
+    }
+
+    /// Count an account's transactions matching the listing filters
+    pub async fn count(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        query: &ListTransactionsQuery,
+    ) -> sqlx::Result<i64> {
+        let q_pattern = query.q_pattern();
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM transactions t
+            WHERE t.account_id = $1
+              AND ($2::varchar IS NULL OR t.risk_level = $2)
+              AND ($3::varchar IS NULL OR t.disposition = $3)
+              AND ($4::timestamptz IS NULL OR t.created_at >= $4)
+              AND ($5::timestamptz IS NULL OR t.created_at < $5)
+              AND ($6::uuid IS NULL OR t.user_id = $6)
+              AND ($7::varchar IS NULL OR t.shop_id = $7)
+              AND ($8::text IS NULL OR t.external_transaction_id ILIKE $8)
+              AND (
+                  ($9::float8 IS NULL AND $10::float8 IS NULL AND $11::varchar IS NULL)
+                  OR EXISTS (
+                      SELECT 1 FROM orders o
+                      WHERE o.transaction_id = t.id
+                        AND ($9::float8 IS NULL OR o.amount >= $9)
+                        AND ($10::float8 IS NULL OR o.amount <= $10)
+                        AND ($11::varchar IS NULL OR o.currency = $11)
+                  )
+              )
+              AND ($12::varchar IS NULL OR EXISTS (
+                  SELECT 1 FROM transaction_addresses ta
+                  JOIN addresses a ON a.id = ta.address_id
+                  WHERE ta.transaction_id = t.id AND a.country = $12
+              ))
+              AND ($13::text IS NULL OR EXISTS (
+                  SELECT 1 FROM transaction_devices td
+                  JOIN devices d ON d.id = td.device_id
+                  WHERE td.transaction_id = t.id AND d.ip_address <<= $13::text::inet
+              ))
+              AND ($14::varchar IS NULL OR EXISTS (
+                  SELECT 1 FROM transaction_emails te
+                  JOIN email_addresses e ON e.id = te.email_id
+                  WHERE te.transaction_id = t.id AND lower(e.domain) = lower($14)
+              ))
+              AND ($15::varchar IS NULL OR EXISTS (
+                  SELECT 1 FROM risk_factors rf
+                  WHERE rf.transaction_id = t.id AND rf.factor_code = $15
+              ))
+              AND ($16::uuid IS NULL OR EXISTS (
+                  SELECT 1 FROM transaction_devices td
+                  WHERE td.transaction_id = t.id AND td.device_id = $16
+              ))
+            "#,
+            tenant.id(),
+            query.risk_level as _,
+            query.disposition as _,
+            query.from_date,
+            query.to_date,
+            query.user_id,
+            query.shop_id,
+            q_pattern,
+            query.min_amount,
+            query.max_amount,
+            query.currency,
+            query.country,
+            query.ip_address,
+            query.email_domain,
+            query.rule,
+            query.device_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Link a transaction to the device it came from
+    pub async fn link_device(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        device_id: Uuid,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO transaction_devices (transaction_id, device_id) VALUES ($1, $2)",
+            transaction_id,
+            device_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Insert the order placed in a transaction
+    pub async fn insert_order(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        order: &Order,
+    ) -> sqlx::Result<Uuid> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO orders (
+                transaction_id, amount, currency, discount_code, affiliate_id, subaffiliate_id,
+                referrer_uri, is_gift, has_gift_message
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id
+            "#,
+            transaction_id,
+            order.amount,
+            order.currency,
+            order.discount_code,
+            order.affiliate_id,
+            order.subaffiliate_id,
+            order.referrer_uri,
+            order.is_gift,
+            order.has_gift_message
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Insert a shopping cart line for an order
+    pub async fn insert_cart_item(
+        executor: impl PgExecutor<'_>,
+        order_id: Uuid,
+        item: &CartItem,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO cart_items (order_id, item_id, category, price, quantity)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            order_id,
+            item.item_id,
+            item.category,
+            item.price,
+            item.quantity
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Find or create an email address by hash, recording the hash of the mailbox it delivers
+    /// to and what is currently known about its domain
+    pub async fn upsert_email(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Option<Uuid>,
+        email_hash: &str,
+        mailbox_hash: &str,
+        domain: Option<&str>,
+        traits: EmailTraits,
+    ) -> sqlx::Result<Uuid> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO email_addresses (
+                account_id, user_id, email_hash, mailbox_hash, domain, is_free, is_disposable
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (account_id, email_hash) DO UPDATE SET
+                user_id = COALESCE(EXCLUDED.user_id, email_addresses.user_id),
+                mailbox_hash = EXCLUDED.mailbox_hash,
+                domain = COALESCE(EXCLUDED.domain, email_addresses.domain),
+                is_free = EXCLUDED.is_free,
+                is_disposable = EXCLUDED.is_disposable
+            RETURNING id
+            "#,
+            tenant.id(),
+            user_id,
+            email_hash,
+            mailbox_hash,
+            domain,
+            traits.is_free,
+            traits.is_disposable
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Link a transaction to an email address
+    pub async fn link_email(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        email_id: Uuid,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO transaction_emails (transaction_id, email_id) VALUES ($1, $2)",
+            transaction_id,
+            email_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Insert a billing or shipping address with the hash of its normal form, placed at
+    /// `location` if it could be geocoded
+    pub async fn insert_address(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Option<Uuid>,
+        address: &Address,
+        address_hash: Option<&str>,
+        location: Option<(f64, f64)>,
+    ) -> sqlx::Result<Uuid> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO addresses (
+                account_id, user_id, first_name, last_name, company, address_line_1,
+                address_line_2, city, region, postal_code, country, phone_number,
+                phone_country_code, latitude, longitude, address_hash
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            RETURNING id
+            "#,
+            tenant.id(),
+            user_id,
+            address.first_name,
+            address.last_name,
+            address.company,
+            address.address,
+            address.address_2,
+            address.city,
+            address.region,
+            address.postal,
+            address.country,
+            address.phone_number,
+            address.phone_country_code,
+            location.map(|(latitude, _)| latitude),
+            location.map(|(_, longitude)| longitude),
+            address_hash
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Link a transaction to one of its addresses
+    pub async fn link_address(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        address_id: Uuid,
+        address_type: &str,
+        delivery_speed: Option<DeliverySpeed>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO transaction_addresses (transaction_id, address_id, address_type, delivery_speed)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            transaction_id,
+            address_id,
+            address_type,
+            delivery_speed as _
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Insert a payment card, storing only a hash of its token
+    pub async fn insert_credit_card(
+        executor: impl PgExecutor<'_>,
+        new: NewCreditCard<'_>,
+    ) -> sqlx::Result<Uuid> {
+        let NewCreditCard { card, bin, .. } = new;
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO credit_cards (
+                account_id, user_id, issuer_id_number, last_digits, token_hash, bank_name,
+                bank_phone_number, bank_phone_country_code, country, avs_result, cvv_result,
+                was_3d_secure_successful, brand, card_type, is_business, is_prepaid, is_virtual,
+                billing_name_match, email_name_match
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,
+                $19
+            )
+            RETURNING id
+            "#,
+            new.tenant.id(),
+            new.user_id,
+            card.issuer_id_number,
+            card.last_digits,
+            new.token_hash,
+            card.bank_name
+                .as_deref()
+                .or_else(|| bin?.bank_name.as_deref()),
+            card.bank_phone_number,
+            card.bank_phone_country_code,
+            card.country.as_deref().or_else(|| bin?.country.as_deref()),
+            card.avs_result,
+            card.cvv_result,
+            card.was_3d_secure_successful,
+            bin.and_then(|bin| bin.brand.as_deref()),
+            bin.and_then(|bin| bin.card_type.as_deref()),
+            bin.is_some_and(|bin| bin.is_business),
+            bin.is_some_and(|bin| bin.is_prepaid),
+            bin.is_some_and(|bin| bin.is_virtual),
+            new.billing_name_match,
+            new.email_name_match
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Link a transaction to the card used to pay
+    pub async fn link_credit_card(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        credit_card_id: Uuid,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO transaction_credit_cards (transaction_id, credit_card_id) VALUES ($1, $2)",
+            transaction_id,
+            credit_card_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Record a factor that contributed to a transaction's score
+    pub async fn insert_risk_factor(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        factor: &RiskFactor,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO risk_factors (
+                transaction_id, factor_code, factor_type, multiplier, reason, metadata
+            )
+            VALUES ($1, $2, $3, $4, $5, COALESCE($6, '{}'::jsonb))
+            "#,
+            transaction_id,
+            factor.code,
+            factor.factor_type,
+            factor.score,
+            factor.reason,
+            factor.metadata
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Record the outcome of a transaction unless one is already recorded, returning whether
+    /// it was
+    pub async fn insert_report(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        tag: ReportTag,
+        notes: Option<&str>,
+        occurred_at: DateTime<Utc>,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO transaction_reports (transaction_id, tag, notes, occurred_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (transaction_id) DO NOTHING
+            "#,
+            transaction_id,
+            tag as _,
+            notes,
+            occurred_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Recorded outcome of a transaction, locked until the end of the database transaction
+    pub async fn lock_report(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+    ) -> sqlx::Result<Option<TransactionReportRecord>> {
+        sqlx::query_as!(
+            TransactionReportRecord,
+            r#"
+            SELECT id, transaction_id, tag AS "tag: ReportTag", chargeback_code, notes,
+                   occurred_at, updated_at
+            FROM transaction_reports
+            WHERE transaction_id = $1
+            FOR UPDATE
+            "#,
+            transaction_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Record the outcome of a transaction, replacing any outcome recorded before
+    pub async fn replace_report(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        tag: ReportTag,
+        chargeback_code: Option<&str>,
+        notes: Option<&str>,
+        occurred_at: DateTime<Utc>,
+    ) -> sqlx::Result<TransactionReportRecord> {
+        sqlx::query_as!(
+            TransactionReportRecord,
+            r#"
+            INSERT INTO transaction_reports (transaction_id, tag, chargeback_code, notes, occurred_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (transaction_id)
+            DO UPDATE SET tag = $2, chargeback_code = $3, notes = $4, occurred_at = $5,
+                          status = 'received'
+            RETURNING id, transaction_id, tag AS "tag: ReportTag", chargeback_code, notes,
+                      occurred_at, updated_at
+            "#,
+            transaction_id,
+            tag as _,
+            chargeback_code,
+            notes,
+            occurred_at
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Add `delta` chargebacks to the counts of the cards a transaction was paid with
+    pub async fn add_card_chargebacks(
+        executor: impl PgExecutor<'_>,
+        transaction_id: Uuid,
+        delta: i32,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE credit_cards c
+            SET chargeback_count = GREATEST(c.chargeback_count + $2, 0)
+            FROM transaction_credit_cards tc
+            WHERE tc.transaction_id = $1 AND tc.credit_card_id = c.id
+            "#,
+            transaction_id,
+            delta
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
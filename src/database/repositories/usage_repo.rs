@@ -0,0 +1,129 @@
+//! Daily usage counts and archived billing cycles
+
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::PgExecutor;
+
+use crate::{database::Tenant, models::account::SubscriptionTier};
+
+/// Metered requests of one account on one UTC day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DailyUsageRecord {
+    /// The day
+    pub day: NaiveDate,
+    /// Metered requests that day
+    pub queries: i32,
+}
+
+/// Billing cycle that has ended
+#[derive(Debug, Clone)]
+pub struct BillingCycleRecord {
+    /// First day of the cycle
+    pub cycle_start: NaiveDate,
+    /// Day the next cycle started
+    pub cycle_end: NaiveDate,
+    /// Subscription tier at the end of the cycle
+    pub subscription_tier: SubscriptionTier,
+    /// Quota of the cycle
+    pub monthly_quota: i32,
+    /// Metered requests in the cycle
+    pub queries_used: i32,
+    /// When the cycle was archived
+    pub closed_at: DateTime<Utc>,
+}
+
+/// Queries over `usage_daily` and `billing_cycles`
+pub struct UsageRepo;
+
+impl UsageRepo {
+    /// Add `delta` to an account's count for `day`, which may be negative to give usage back
+    pub async fn add_daily(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        day: NaiveDate,
+        delta: i32,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO usage_daily (account_id, day, queries)
+            VALUES ($1, $2, GREATEST($3, 0))
+            ON CONFLICT (account_id, day)
+            DO UPDATE SET queries = GREATEST(usage_daily.queries + $3, 0)
+            "#,
+            tenant.id(),
+            day,
+            delta
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Overwrite an account's count for `day` with a value counted elsewhere
+    pub async fn set_daily(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        day: NaiveDate,
+        queries: i32,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO usage_daily (account_id, day, queries)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (account_id, day) DO UPDATE SET queries = EXCLUDED.queries
+            "#,
+            tenant.id(),
+            day,
+            queries
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Daily counts of an account from `from` up to, but excluding, `to`, oldest first
+    pub async fn daily(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> sqlx::Result<Vec<DailyUsageRecord>> {
+        sqlx::query_as!(
+            DailyUsageRecord,
+            r#"
+            SELECT day, queries
+            FROM usage_daily
+            WHERE account_id = $1 AND day >= $2 AND day < $3
+            ORDER BY day
+            "#,
+            tenant.id(),
+            from,
+            to
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// The most recent `limit` archived cycles of an account, newest first
+    pub async fn billing_cycles(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        limit: i64,
+    ) -> sqlx::Result<Vec<BillingCycleRecord>> {
+        sqlx::query_as!(
+            BillingCycleRecord,
+            r#"
+            SELECT cycle_start, cycle_end,
+                   subscription_tier AS "subscription_tier: SubscriptionTier",
+                   monthly_quota, queries_used, closed_at
+            FROM billing_cycles
+            WHERE account_id = $1
+            ORDER BY cycle_start DESC
+            LIMIT $2
+            "#,
+            tenant.id(),
+            limit
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,213 @@
+//! User bases imported in the background
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::user::{UserImportError, UserImportStatus},
+};
+
+/// Stored import row, without the submitted users
+#[derive(Debug, Clone)]
+pub struct UserImportRecord {
+    /// Import ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Progress of the import
+    pub status: UserImportStatus,
+    /// Users submitted
+    pub total_users: i32,
+    /// Users imported or failed so far
+    pub processed_users: i32,
+    /// Users created
+    pub created_users: i32,
+    /// Existing users updated
+    pub updated_users: i32,
+    /// Users that could not be imported
+    pub failed_users: i32,
+    /// The first failures
+    pub errors: Json<Vec<UserImportError>>,
+    /// When the import was submitted
+    pub created_at: DateTime<Utc>,
+    /// When processing started
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the import finished
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl TenantOwned for UserImportRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Import claimed by the worker, with the users still to import
+#[derive(Debug, Clone)]
+pub struct ClaimedImportRecord {
+    /// Import ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Every submitted user, as submitted
+    pub users: Json<Vec<serde_json::Value>>,
+    /// Users already processed by an earlier run
+    pub processed_users: i32,
+    /// Failures already listed
+    pub reported_errors: i32,
+}
+
+/// Progress made on one chunk of an import
+#[derive(Debug, Clone, Default)]
+pub struct ImportProgress {
+    /// Users processed
+    pub processed: i32,
+    /// Users created
+    pub created: i32,
+    /// Existing users updated
+    pub updated: i32,
+    /// Users that failed
+    pub failed: i32,
+    /// Failures to list
+    pub errors: Vec<UserImportError>,
+}
+
+/// Queries over `user_imports`
+pub struct UserImportRepo;
+
+impl UserImportRepo {
+    /// Queue users for import
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        users: &[serde_json::Value],
+    ) -> sqlx::Result<UserImportRecord> {
+        let total_users = i32::try_from(users.len()).unwrap_or(i32::MAX);
+        let record = sqlx::query_as!(
+            UserImportRecord,
+            r#"
+            INSERT INTO user_imports (account_id, users, total_users)
+            VALUES ($1, $2, $3)
+            RETURNING id, account_id, status AS "status: UserImportStatus", total_users,
+                      processed_users, created_users, updated_users, failed_users,
+                      errors AS "errors: Json<Vec<UserImportError>>", created_at, started_at,
+                      completed_at
+            "#,
+            tenant.id(),
+            Json(users) as _,
+            total_users
+        )
+        .fetch_one(executor)
+        .await?;
+        tenant.check(record)
+    }
+
+    /// Fetch one of an account's imports
+    pub async fn find_by_id(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        import_id: Uuid,
+    ) -> sqlx::Result<Option<UserImportRecord>> {
+        sqlx::query_as!(
+            UserImportRecord,
+            r#"
+            SELECT id, account_id, status AS "status: UserImportStatus", total_users,
+                   processed_users, created_users, updated_users, failed_users,
+                   errors AS "errors: Json<Vec<UserImportError>>", created_at, started_at,
+                   completed_at
+            FROM user_imports
+            WHERE id = $1 AND account_id = $2
+            "#,
+            import_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Mark the oldest pending import processing and return it, or else one whose worker has
+    /// not reported progress for `stale_after_seconds`
+    pub async fn claim_next(
+        executor: impl PgExecutor<'_>,
+        stale_after_seconds: f64,
+    ) -> sqlx::Result<Option<ClaimedImportRecord>> {
+        sqlx::query_as!(
+            ClaimedImportRecord,
+            r#"
+            UPDATE user_imports
+            SET status = 'processing', heartbeat_at = NOW(),
+                started_at = COALESCE(started_at, NOW())
+            WHERE id = (
+                SELECT id
+                FROM user_imports
+                WHERE status = 'pending'
+                   OR (status = 'processing'
+                       AND heartbeat_at < NOW() - make_interval(secs => $1))
+                ORDER BY created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, account_id, users AS "users!: Json<Vec<serde_json::Value>>",
+                      processed_users, jsonb_array_length(errors) AS "reported_errors!"
+            "#,
+            stale_after_seconds
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Add a chunk's progress to an import and refresh its heartbeat
+    pub async fn record_progress(
+        executor: impl PgExecutor<'_>,
+        import_id: Uuid,
+        progress: &ImportProgress,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE user_imports
+            SET processed_users = processed_users + $2,
+                created_users = created_users + $3,
+                updated_users = updated_users + $4,
+                failed_users = failed_users + $5,
+                errors = errors || $6,
+                heartbeat_at = NOW()
+            WHERE id = $1
+            "#,
+            import_id,
+            progress.processed,
+            progress.created,
+            progress.updated,
+            progress.failed,
+            Json(&progress.errors) as _
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Mark an import completed, dropping the submitted users
+    pub async fn complete(
+        executor: impl PgExecutor<'_>,
+        import_id: Uuid,
+    ) -> sqlx::Result<UserImportRecord> {
+        sqlx::query_as!(
+            UserImportRecord,
+            r#"
+            UPDATE user_imports
+            SET status = 'completed', users = NULL, completed_at = NOW()
+            WHERE id = $1
+            RETURNING id, account_id, status AS "status: UserImportStatus", total_users,
+                      processed_users, created_users, updated_users, failed_users,
+                      errors AS "errors: Json<Vec<UserImportError>>", created_at, started_at,
+                      completed_at
+            "#,
+            import_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
@@ -0,0 +1,983 @@
+//! End users of each account
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::{
+        transaction::RiskLevel,
+        user::{ImportUser, UserFlag, UserUpdate},
+    },
+};
+
+/// Stored user row
+#[derive(Debug, Clone)]
+pub struct UserRecord {
+    /// User ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// The customer's own identifier for the user
+    pub external_user_id: Option<String>,
+    /// Hash identifying the user
+    pub user_hash: Option<String>,
+    /// Risk score of the user
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Transactions scored for the user
+    pub total_transactions: i32,
+    /// Transactions that went through
+    pub successful_transactions: i32,
+    /// Transactions that did not go through
+    pub failed_transactions: i32,
+    /// Chargebacks reported for the user's transactions
+    pub chargeback_count: i32,
+    /// When the user's first transaction happened
+    pub first_transaction_at: Option<DateTime<Utc>>,
+    /// When the user's latest transaction happened
+    pub last_transaction_at: Option<DateTime<Utc>>,
+    /// Whether the customer has verified the user's identity
+    pub is_verified: bool,
+    /// Whether the user is flagged for review
+    pub is_flagged: bool,
+    /// Labels the customer attached to the user
+    pub flags: Json<Vec<UserFlag>>,
+    /// Free-form data the customer attached to the user
+    pub metadata: serde_json::Value,
+    /// When the user was first seen
+    pub created_at: DateTime<Utc>,
+    /// When the user last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TenantOwned for UserRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Transaction counts of a user over windows ending at a given time
+#[derive(Debug, Clone)]
+pub struct UserVelocityRecord {
+    /// Transactions in the last hour
+    pub last_hour: i64,
+    /// Transactions in the last 24 hours
+    pub last_24_hours: i64,
+    /// Transactions in the last 7 days
+    pub last_7_days: i64,
+    /// Transactions in the last 30 days
+    pub last_30_days: i64,
+    /// Transactions rejected in the last 24 hours
+    pub rejected_last_24_hours: i64,
+    /// Distinct live devices used in the last 24 hours
+    pub devices_last_24_hours: i64,
+}
+
+/// Devices behind a user's transactions over the last 30 days
+#[derive(Debug, Clone)]
+pub struct UserDeviceUsageRecord {
+    /// Transactions linked to a live device
+    pub device_transactions: i64,
+    /// Distinct live devices used
+    pub distinct_devices: i64,
+    /// Transactions from the most used device
+    pub primary_device_transactions: i64,
+    /// Devices the account first saw in the last 7 days
+    pub new_devices: i64,
+    /// Distinct IP addresses used
+    pub distinct_ip_addresses: i64,
+}
+
+/// Transactions from one billing country
+#[derive(Debug, Clone)]
+pub struct CountryCountRecord {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    /// Transactions billed to the country
+    pub transactions: i64,
+}
+
+/// Flags of a live user, as read for scoring
+#[derive(Debug, Clone)]
+pub struct UserFlagsRecord {
+    /// User ID
+    pub id: Uuid,
+    /// Flags set on the user, including expired ones
+    pub flags: Json<Vec<UserFlag>>,
+}
+
+/// What a user's risk score is recalculated from
+#[derive(Debug, Clone)]
+pub struct UserRiskInputsRecord {
+    /// User ID
+    pub user_id: Uuid,
+    /// Chargebacks on record, including imported ones
+    pub chargeback_count: i32,
+    /// Total decay weight of the user's recent transactions
+    pub transaction_weight: f64,
+    /// Sum of the recent transactions' latest risk scores times their decay weights, with
+    /// transactions reported as not fraud scoring 0
+    pub weighted_risk_sum: f64,
+    /// Decay weight of each recent outcome report other than `not_fraud`
+    pub fraud_report_weights: Vec<f64>,
+}
+
+/// User row to insert
+#[derive(Debug, Clone)]
+pub struct NewUser<'a> {
+    /// Owning account
+    pub tenant: Tenant,
+    /// The customer's own identifier for the user
+    pub external_user_id: &'a str,
+    /// Whether the customer has verified the user's identity
+    pub is_verified: bool,
+    /// Whether the user is flagged for review
+    pub is_flagged: bool,
+    /// Labels to attach
+    pub flags: &'a [UserFlag],
+    /// Free-form data to attach
+    pub metadata: &'a serde_json::Value,
+}
+
+/// Queries over `users`
+pub struct UserRepo;
+
+impl UserRepo {
+    /// Return the user's ID if it exists within the account and has not been deleted
+    pub async fn find_id(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+    ) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_scalar!(
+            "SELECT id FROM users WHERE id = $1 AND account_id = $2 AND deleted_at IS NULL",
+            user_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Fetch a live user of an account
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+    ) -> sqlx::Result<Option<UserRecord>> {
+        sqlx::query_as!(
+            UserRecord,
+            r#"
+            SELECT id, account_id, external_user_id, user_hash, risk_score,
+                   risk_level AS "risk_level: RiskLevel",
+                   total_transactions, successful_transactions, failed_transactions,
+                   chargeback_count, first_transaction_at, last_transaction_at, is_verified,
+                   is_flagged, flags AS "flags: Json<Vec<UserFlag>>", metadata, created_at,
+                   updated_at
+            FROM users
+            WHERE id = $1 AND account_id = $2 AND deleted_at IS NULL
+            "#,
+            user_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Insert a user, failing with a unique violation if the account already has a user,
+    /// live or deleted, with the same external user ID
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        user: NewUser<'_>,
+    ) -> sqlx::Result<UserRecord> {
+        let record = sqlx::query_as!(
+            UserRecord,
+            r#"
+            INSERT INTO users (account_id, external_user_id, is_verified, is_flagged, flags,
+                               metadata)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, account_id, external_user_id, user_hash, risk_score,
+                      risk_level AS "risk_level: RiskLevel",
+                      total_transactions, successful_transactions, failed_transactions,
+                      chargeback_count, first_transaction_at, last_transaction_at, is_verified,
+                      is_flagged, flags AS "flags: Json<Vec<UserFlag>>", metadata, created_at,
+                      updated_at
+            "#,
+            user.tenant.id(),
+            user.external_user_id,
+            user.is_verified,
+            user.is_flagged,
+            Json(user.flags) as _,
+            user.metadata
+        )
+        .fetch_one(executor)
+        .await?;
+        user.tenant.check(record)
+    }
+
+    /// Apply changes to a live user, returning it as updated
+    pub async fn update(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+        update: &UserUpdate,
+    ) -> sqlx::Result<Option<UserRecord>> {
+        sqlx::query_as!(
+            UserRecord,
+            r#"
+            UPDATE users
+            SET is_verified = COALESCE($3, is_verified),
+                is_flagged = COALESCE($4, is_flagged),
+                flags = COALESCE($5, flags),
+                metadata = COALESCE($6, metadata)
+            WHERE id = $1 AND account_id = $2 AND deleted_at IS NULL
+            RETURNING id, account_id, external_user_id, user_hash, risk_score,
+                      risk_level AS "risk_level: RiskLevel",
+                      total_transactions, successful_transactions, failed_transactions,
+                      chargeback_count, first_transaction_at, last_transaction_at, is_verified,
+                      is_flagged, flags AS "flags: Json<Vec<UserFlag>>", metadata, created_at,
+                      updated_at
+            "#,
+            user_id,
+            tenant.id(),
+            update.is_verified,
+            update.is_flagged,
+            update.flags.as_ref().map(Json) as _,
+            update.metadata.as_ref()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Map the account's live users among `user_ids` to their external user IDs
+    pub async fn external_ids(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_ids: &[Uuid],
+    ) -> sqlx::Result<Vec<(Uuid, Option<String>)>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, external_user_id
+            FROM users
+            WHERE account_id = $1 AND id = ANY($2) AND deleted_at IS NULL
+            "#,
+            tenant.id(),
+            user_ids
+        )
+        .fetch_all(executor)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.id, row.external_user_id))
+            .collect())
+    }
+
+    /// Resolve a user ID of the account to the live user it stands for: the user itself, or
+    /// the user it was merged into
+    ///
+    /// Returns `None` for unknown users and users deleted without being merged.
+    pub async fn resolve_id(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+    ) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT u.id
+            FROM users s
+            JOIN users u ON u.id = COALESCE(s.merged_into, s.id) AND u.deleted_at IS NULL
+            WHERE s.id = $1 AND s.account_id = $2
+            "#,
+            user_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Fetch the live user standing for an external user ID or user hash of the account,
+    /// following merges like [`UserRepo::resolve_id`]
+    ///
+    /// Pass exactly one identifier. Returns `None` for unknown users and users deleted without
+    /// being merged.
+    pub async fn find_by_identifier(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        external_user_id: Option<&str>,
+        user_hash: Option<&str>,
+    ) -> sqlx::Result<Option<UserRecord>> {
+        sqlx::query_as!(
+            UserRecord,
+            r#"
+            SELECT u.id, u.account_id, u.external_user_id, u.user_hash, u.risk_score,
+                   u.risk_level AS "risk_level: RiskLevel",
+                   u.total_transactions, u.successful_transactions, u.failed_transactions,
+                   u.chargeback_count, u.first_transaction_at, u.last_transaction_at,
+                   u.is_verified, u.is_flagged, u.flags AS "flags: Json<Vec<UserFlag>>",
+                   u.metadata, u.created_at, u.updated_at
+            FROM users s
+            JOIN users u ON u.id = COALESCE(s.merged_into, s.id) AND u.deleted_at IS NULL
+            WHERE s.account_id = $1 AND (s.external_user_id = $2 OR s.user_hash = $3)
+            LIMIT 1
+            "#,
+            tenant.id(),
+            external_user_id,
+            user_hash
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// ID and flags of the live user standing for the given identifier, following merges like
+    /// [`UserRepo::resolve_id`]
+    ///
+    /// Pass exactly one identifier. Returns `None` for unknown users and users deleted without
+    /// being merged.
+    pub async fn find_flags(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Option<Uuid>,
+        external_user_id: Option<&str>,
+        user_hash: Option<&str>,
+    ) -> sqlx::Result<Option<UserFlagsRecord>> {
+        sqlx::query_as!(
+            UserFlagsRecord,
+            r#"
+            SELECT u.id, u.flags AS "flags: Json<Vec<UserFlag>>"
+            FROM users s
+            JOIN users u ON u.id = COALESCE(s.merged_into, s.id) AND u.deleted_at IS NULL
+            WHERE s.account_id = $1
+              AND (s.id = $2 OR s.external_user_id = $3 OR s.user_hash = $4)
+            LIMIT 1
+            "#,
+            tenant.id(),
+            user_id,
+            external_user_id,
+            user_hash
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Find or create a user by the customer's own user ID
+    ///
+    /// An ID of a merged user resolves to the user it was merged into. Returns `None` when
+    /// the matching user has been deleted.
+    pub async fn upsert_by_external_id(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        external_user_id: &str,
+    ) -> sqlx::Result<Option<Uuid>> {
+        let resolved = sqlx::query_scalar!(
+            r#"
+            WITH upserted AS (
+                INSERT INTO users (account_id, external_user_id)
+                VALUES ($1, $2)
+                ON CONFLICT (account_id, external_user_id)
+                DO UPDATE SET external_user_id = EXCLUDED.external_user_id
+                WHERE users.deleted_at IS NULL OR users.merged_into IS NOT NULL
+                RETURNING id, merged_into
+            )
+            SELECT CASE
+                       WHEN merged_into IS NULL THEN id
+                       ELSE (SELECT m.id FROM users m
+                             WHERE m.id = upserted.merged_into AND m.deleted_at IS NULL)
+                   END AS user_id
+            FROM upserted
+            "#,
+            tenant.id(),
+            external_user_id
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(resolved.flatten())
+    }
+
+    /// Find or create a user by the customer-supplied user hash
+    ///
+    /// A hash of a merged user resolves to the user it was merged into. Returns `None` when
+    /// the matching user has been deleted.
+    pub async fn upsert_by_hash(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_hash: &str,
+    ) -> sqlx::Result<Option<Uuid>> {
+        let resolved = sqlx::query_scalar!(
+            r#"
+            WITH upserted AS (
+                INSERT INTO users (account_id, user_hash)
+                VALUES ($1, $2)
+                ON CONFLICT (account_id, user_hash)
+                DO UPDATE SET user_hash = EXCLUDED.user_hash
+                WHERE users.deleted_at IS NULL OR users.merged_into IS NOT NULL
+                RETURNING id, merged_into
+            )
+            SELECT CASE
+                       WHEN merged_into IS NULL THEN id
+                       ELSE (SELECT m.id FROM users m
+                             WHERE m.id = upserted.merged_into AND m.deleted_at IS NULL)
+                   END AS user_id
+            FROM upserted
+            "#,
+            tenant.id(),
+            user_hash
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(resolved.flatten())
+    }
+
+    /// Create or update a user from an imported record, returning whether it was created
+    ///
+    /// See [`ImportUser`] for how imported values combine with an existing user's. Returns
+    /// `None` when the matching user has been deleted or merged into another.
+    pub async fn import(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user: &ImportUser,
+    ) -> sqlx::Result<Option<bool>> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO users (account_id, external_user_id, is_verified, is_flagged, flags,
+                               metadata, total_transactions, successful_transactions,
+                               failed_transactions, chargeback_count, first_transaction_at,
+                               last_transaction_at)
+            VALUES ($1, $2, COALESCE($3, false), COALESCE($4, false), COALESCE($5::jsonb, '[]'::jsonb),
+                    COALESCE($6::jsonb, '{}'::jsonb), COALESCE($7, 0), COALESCE($8, 0), COALESCE($9, 0),
+                    COALESCE($10, 0), $11, $12)
+            ON CONFLICT (account_id, external_user_id) DO UPDATE SET
+                is_verified = COALESCE($3, users.is_verified),
+                is_flagged = COALESCE($4, users.is_flagged),
+                flags = users.flags || COALESCE(
+                    (SELECT jsonb_agg(f) FROM jsonb_array_elements(EXCLUDED.flags) f
+                     WHERE NOT EXISTS (SELECT 1 FROM jsonb_array_elements(users.flags) e
+                                       WHERE e->>'type' = f->>'type')),
+                    '[]'
+                ),
+                metadata = users.metadata || EXCLUDED.metadata,
+                total_transactions =
+                    GREATEST(users.total_transactions, EXCLUDED.total_transactions),
+                successful_transactions =
+                    GREATEST(users.successful_transactions, EXCLUDED.successful_transactions),
+                failed_transactions =
+                    GREATEST(users.failed_transactions, EXCLUDED.failed_transactions),
+                chargeback_count = GREATEST(users.chargeback_count, EXCLUDED.chargeback_count),
+                first_transaction_at =
+                    LEAST(users.first_transaction_at, EXCLUDED.first_transaction_at),
+                last_transaction_at =
+                    GREATEST(users.last_transaction_at, EXCLUDED.last_transaction_at),
+                risk_scored_at = NULL
+            WHERE users.deleted_at IS NULL
+            RETURNING (xmax = 0) AS "created!"
+            "#,
+            tenant.id(),
+            user.external_user_id.trim(),
+            user.is_verified,
+            user.is_flagged,
+            user.flags.as_ref().map(Json) as _,
+            user.metadata.as_ref(),
+            user.total_transactions,
+            user.successful_transactions,
+            user.failed_transactions,
+            user.chargeback_count,
+            user.first_transaction_at,
+            user.last_transaction_at
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Lock live users of the account for a merge, in ID order so concurrent merges of the
+    /// same users cannot deadlock
+    pub async fn lock_live(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_ids: &[Uuid],
+    ) -> sqlx::Result<Vec<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT id
+            FROM users
+            WHERE account_id = $1 AND id = ANY($2) AND deleted_at IS NULL
+            ORDER BY id
+            FOR UPDATE
+            "#,
+            tenant.id(),
+            user_ids
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Fold `source`'s totals, flags, and metadata into `target`, returning `target` updated
+    ///
+    /// Totals add up and the transaction period widens to cover both users. The higher risk
+    /// score wins until `target` is next recalculated, and the user stays flagged if either
+    /// was. Flags are combined, keeping
+    /// `target`'s flag of a type both users carry; metadata keys present on both users keep
+    /// `target`'s value. `target` keeps its own identifiers
+    /// and verification status.
+    pub async fn absorb(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        target: Uuid,
+        source: Uuid,
+    ) -> sqlx::Result<UserRecord> {
+        let record = sqlx::query_as!(
+            UserRecord,
+            r#"
+            UPDATE users t
+            SET total_transactions = t.total_transactions + s.total_transactions,
+                successful_transactions = t.successful_transactions + s.successful_transactions,
+                failed_transactions = t.failed_transactions + s.failed_transactions,
+                chargeback_count = t.chargeback_count + s.chargeback_count,
+                first_transaction_at = LEAST(t.first_transaction_at, s.first_transaction_at),
+                last_transaction_at = GREATEST(t.last_transaction_at, s.last_transaction_at),
+                risk_score = GREATEST(t.risk_score, s.risk_score),
+                risk_level = CASE
+                    WHEN s.risk_score > t.risk_score THEN s.risk_level
+                    ELSE t.risk_level
+                END,
+                is_flagged = t.is_flagged OR s.is_flagged,
+                flags = t.flags || COALESCE(
+                    (SELECT jsonb_agg(f) FROM jsonb_array_elements(s.flags) f
+                     WHERE NOT EXISTS (SELECT 1 FROM jsonb_array_elements(t.flags) e
+                                       WHERE e->>'type' = f->>'type')),
+                    '[]'
+                ),
+                metadata = s.metadata || t.metadata,
+                risk_scored_at = NULL
+            FROM users s
+            WHERE t.id = $2 AND t.account_id = $1 AND s.id = $3 AND s.account_id = $1
+            RETURNING t.id, t.account_id, t.external_user_id, t.user_hash, t.risk_score,
+                      t.risk_level AS "risk_level: RiskLevel",
+                      t.total_transactions, t.successful_transactions, t.failed_transactions,
+                      t.chargeback_count, t.first_transaction_at, t.last_transaction_at,
+                      t.is_verified, t.is_flagged, t.flags AS "flags: Json<Vec<UserFlag>>",
+                      t.metadata, t.created_at, t.updated_at
+            "#,
+            tenant.id(),
+            target,
+            source
+        )
+        .fetch_one(executor)
+        .await?;
+        tenant.check(record)
+    }
+
+    /// Reattribute `source`'s transactions, devices, device links, emails, addresses, and cards
+    /// to `target` and drop `source`'s behavioral profile, returning the number of transactions
+    /// moved
+    pub async fn move_history(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        source: Uuid,
+        target: Uuid,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            WITH moved AS (
+                UPDATE transactions SET user_id = $3
+                WHERE account_id = $1 AND user_id = $2
+                RETURNING id
+            ),
+            moved_devices AS (
+                UPDATE devices SET user_id = $3 WHERE account_id = $1 AND user_id = $2
+            ),
+            merged_device_users AS (
+                INSERT INTO device_users (
+                    device_id, user_id, account_id, transaction_count, first_seen, last_seen
+                )
+                SELECT device_id, $3, account_id, transaction_count, first_seen, last_seen
+                FROM device_users
+                WHERE account_id = $1 AND user_id = $2
+                ON CONFLICT (device_id, user_id) DO UPDATE SET
+                    transaction_count =
+                        device_users.transaction_count + EXCLUDED.transaction_count,
+                    first_seen = LEAST(device_users.first_seen, EXCLUDED.first_seen),
+                    last_seen = GREATEST(device_users.last_seen, EXCLUDED.last_seen)
+            ),
+            dropped_device_users AS (
+                DELETE FROM device_users WHERE account_id = $1 AND user_id = $2
+            ),
+            moved_emails AS (
+                UPDATE email_addresses SET user_id = $3 WHERE account_id = $1 AND user_id = $2
+            ),
+            moved_addresses AS (
+                UPDATE addresses SET user_id = $3 WHERE account_id = $1 AND user_id = $2
+            ),
+            moved_cards AS (
+                UPDATE credit_cards SET user_id = $3 WHERE account_id = $1 AND user_id = $2
+            ),
+            dropped_profile AS (
+                DELETE FROM user_profiles WHERE account_id = $1 AND user_id = $2
+            )
+            SELECT COUNT(*) AS "moved!" FROM moved
+            "#,
+            tenant.id(),
+            source,
+            target
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Soft-delete `source` as merged into `target`, along with users merged into `source`
+    /// earlier, so every merged identifier resolves to `target` directly
+    pub async fn mark_merged(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        source: Uuid,
+        target: Uuid,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            WITH repointed AS (
+                UPDATE users SET merged_into = $3
+                WHERE account_id = $1 AND merged_into = $2
+            )
+            UPDATE users
+            SET merged_into = $3, deleted_at = CURRENT_TIMESTAMP
+            WHERE account_id = $1 AND id = $2
+            "#,
+            tenant.id(),
+            source,
+            target
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Count a new transaction against the user's running totals
+    pub async fn record_transaction(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+        event_time: DateTime<Utc>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET total_transactions = total_transactions + 1,
+                first_transaction_at = COALESCE(first_transaction_at, $2),
+                last_transaction_at = GREATEST(last_transaction_at, $2),
+                risk_scored_at = NULL
+            WHERE id = $1 AND account_id = $3
+            "#,
+            user_id,
+            event_time,
+            tenant.id()
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Have the user's risk score recalculated on the next pass, as when an outcome of one of
+    /// its transactions is recorded
+    pub async fn mark_for_risk_scoring(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            "UPDATE users SET risk_scored_at = NULL WHERE id = $1 AND account_id = $2",
+            user_id,
+            tenant.id()
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Add `delta` chargebacks to the user's count and have its risk score recalculated
+    pub async fn add_chargebacks(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+        delta: i32,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET chargeback_count = GREATEST(chargeback_count + $3, 0), risk_scored_at = NULL
+            WHERE id = $1 AND account_id = $2
+            "#,
+            user_id,
+            tenant.id(),
+            delta
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Live users whose risk score is due for recalculation: never calculated, changed by new
+    /// activity since, or last calculated before `scored_before`
+    pub async fn due_for_risk_scoring(
+        executor: impl PgExecutor<'_>,
+        scored_before: DateTime<Utc>,
+        limit: i64,
+    ) -> sqlx::Result<Vec<Uuid>> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT id
+            FROM users
+            WHERE deleted_at IS NULL AND (risk_scored_at IS NULL OR risk_scored_at < $1)
+            ORDER BY risk_scored_at NULLS FIRST
+            LIMIT $2
+            "#,
+            scored_before,
+            limit
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Gather what the risk scores of `user_ids` are computed from, as of `now`
+    ///
+    /// Transactions and outcome reports from the last `lookback_days` count, each weighted by
+    /// half for every `half_life_days` of age.
+    pub async fn risk_inputs(
+        executor: impl PgExecutor<'_>,
+        user_ids: &[Uuid],
+        now: DateTime<Utc>,
+        lookback_days: i32,
+        half_life_days: f64,
+    ) -> sqlx::Result<Vec<UserRiskInputsRecord>> {
+        sqlx::query_as!(
+            UserRiskInputsRecord,
+            r#"
+            SELECT u.id AS user_id, u.chargeback_count,
+                   COALESCE(tx.weight, 0) AS "transaction_weight!",
+                   COALESCE(tx.weighted_risk_sum, 0) AS "weighted_risk_sum!",
+                   COALESCE(rep.weights, '{}') AS "fraud_report_weights!"
+            FROM users u
+            LEFT JOIN LATERAL (
+                SELECT SUM(w.weight) AS weight,
+                       SUM(w.weight * CASE WHEN r.tag = 'not_fraud' THEN 0
+                                           ELSE COALESCE(sr.risk_score, t.risk_score) END)
+                           AS weighted_risk_sum
+                FROM transactions t
+                CROSS JOIN LATERAL (
+                    SELECT power(0.5, GREATEST(EXTRACT(EPOCH FROM $2::timestamptz - t.event_time), 0)
+                                      / 86400.0 / $4::float8)::float8 AS weight
+                ) w
+                LEFT JOIN transaction_reports r ON r.transaction_id = t.id
+                LEFT JOIN LATERAL (
+                    SELECT risk_score
+                    FROM scoring_revisions
+                    WHERE transaction_id = t.id
+                    ORDER BY revision DESC
+                    LIMIT 1
+                ) sr ON TRUE
+                WHERE t.user_id = u.id
+                  AND t.event_time > $2::timestamptz - make_interval(days => $3)
+            ) tx ON TRUE
+            LEFT JOIN LATERAL (
+                SELECT array_agg(
+                           power(0.5, GREATEST(EXTRACT(EPOCH FROM $2::timestamptz - r.occurred_at), 0)
+                                      / 86400.0 / $4::float8)::float8
+                       ) AS weights
+                FROM transaction_reports r
+                JOIN transactions t ON t.id = r.transaction_id
+                WHERE t.user_id = u.id AND r.tag <> 'not_fraud'
+                  AND r.occurred_at > $2::timestamptz - make_interval(days => $3)
+            ) rep ON TRUE
+            WHERE u.id = ANY($1)
+            "#,
+            user_ids,
+            now,
+            lookback_days,
+            half_life_days
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Store a recalculated risk score
+    pub async fn set_risk_score(
+        executor: impl PgExecutor<'_>,
+        user_id: Uuid,
+        risk_score: f64,
+        risk_level: RiskLevel,
+        scored_at: DateTime<Utc>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET risk_score = $2, risk_level = $3, risk_scored_at = $4
+            WHERE id = $1
+            "#,
+            user_id,
+            risk_score,
+            risk_level as _,
+            scored_at
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Count a user's transactions over the hour, day, week, and 30 days before `now`
+    pub async fn velocity(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> sqlx::Result<UserVelocityRecord> {
+        sqlx::query_as!(
+            UserVelocityRecord,
+            r#"
+            SELECT
+                COUNT(*) FILTER (WHERE t.event_time > $3::timestamptz - INTERVAL '1 hour') AS "last_hour!",
+                COUNT(*) FILTER (WHERE t.event_time > $3::timestamptz - INTERVAL '24 hours')
+                    AS "last_24_hours!",
+                COUNT(*) FILTER (WHERE t.event_time > $3::timestamptz - INTERVAL '7 days') AS "last_7_days!",
+                COUNT(*) AS "last_30_days!",
+                COUNT(*) FILTER (
+                    WHERE t.event_time > $3::timestamptz - INTERVAL '24 hours' AND t.disposition = 'reject'
+                ) AS "rejected_last_24_hours!",
+                (
+                    SELECT COUNT(DISTINCT td.device_id)
+                    FROM transactions t2
+                    JOIN transaction_devices td ON td.transaction_id = t2.id
+                    JOIN devices d ON d.id = td.device_id AND d.deleted_at IS NULL
+                    WHERE t2.account_id = $1 AND t2.user_id = $2
+                      AND t2.event_time > $3::timestamptz - INTERVAL '24 hours' AND t2.event_time <= $3
+                ) AS "devices_last_24_hours!"
+            FROM transactions t
+            WHERE t.account_id = $1 AND t.user_id = $2
+              AND t.event_time > $3::timestamptz - INTERVAL '30 days' AND t.event_time <= $3
+            "#,
+            tenant.id(),
+            user_id,
+            now
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Summarize the live devices behind a user's transactions in the 30 days before `now`
+    pub async fn device_usage(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> sqlx::Result<UserDeviceUsageRecord> {
+        sqlx::query_as!(
+            UserDeviceUsageRecord,
+            r#"
+            WITH usage AS (
+                SELECT d.id, d.ip_address, d.first_seen, COUNT(*) AS transactions
+                FROM transactions t
+                JOIN transaction_devices td ON td.transaction_id = t.id
+                JOIN devices d ON d.id = td.device_id AND d.deleted_at IS NULL
+                WHERE t.account_id = $1 AND t.user_id = $2
+                  AND t.event_time > $3::timestamptz - INTERVAL '30 days' AND t.event_time <= $3
+                GROUP BY d.id, d.ip_address, d.first_seen
+            )
+            SELECT COALESCE(SUM(transactions), 0)::int8 AS "device_transactions!",
+                   COUNT(*) AS "distinct_devices!",
+                   COALESCE(MAX(transactions), 0) AS "primary_device_transactions!",
+                   COUNT(*) FILTER (WHERE first_seen > $3::timestamptz - INTERVAL '7 days') AS "new_devices!",
+                   COUNT(DISTINCT ip_address) AS "distinct_ip_addresses!"
+            FROM usage
+            "#,
+            tenant.id(),
+            user_id,
+            now
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Count a user's transactions in the 30 days before `now` by billing country, most used
+    /// first
+    pub async fn billing_countries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<CountryCountRecord>> {
+        sqlx::query_as!(
+            CountryCountRecord,
+            r#"
+            SELECT a.country::text AS "country!", COUNT(*) AS "transactions!"
+            FROM transactions t
+            JOIN transaction_addresses ta
+                ON ta.transaction_id = t.id AND ta.address_type = 'billing'
+            JOIN addresses a ON a.id = ta.address_id
+            WHERE t.account_id = $1 AND t.user_id = $2
+              AND t.event_time > $3::timestamptz - INTERVAL '30 days' AND t.event_time <= $3
+              AND a.country IS NOT NULL
+            GROUP BY a.country
+            ORDER BY 2 DESC, 1
+            "#,
+            tenant.id(),
+            user_id,
+            now
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    /// Count a user's transactions in the 30 days before `now` whose billing and shipping
+    /// countries differ
+    pub async fn country_mismatches(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(DISTINCT t.id) AS "mismatches!"
+            FROM transactions t
+            JOIN transaction_addresses tb
+                ON tb.transaction_id = t.id AND tb.address_type = 'billing'
+            JOIN addresses b ON b.id = tb.address_id
+            JOIN transaction_addresses ts
+                ON ts.transaction_id = t.id AND ts.address_type = 'shipping'
+            JOIN addresses s ON s.id = ts.address_id
+            WHERE t.account_id = $1 AND t.user_id = $2
+              AND t.event_time > $3::timestamptz - INTERVAL '30 days' AND t.event_time <= $3
+              AND b.country <> s.country
+            "#,
+            tenant.id(),
+            user_id,
+            now
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Soft-delete a user, returning whether a live user was deleted
+    pub async fn soft_delete(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        user_id: Uuid,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET deleted_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND account_id = $2 AND deleted_at IS NULL
+            "#,
+            user_id,
+            tenant.id()
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,447 @@
+//! Webhook endpoints, their signing secrets, and the deliveries made to them
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    database::{Tenant, TenantOwned},
+    models::webhook::{DeliveryStatus, ListDeliveriesQuery, WebhookFilters, WebhookRequest},
+};
+
+/// Stored webhook, without its secrets
+#[derive(Debug, Clone)]
+pub struct WebhookRecord {
+    /// Webhook ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// URL events are POSTed to
+    pub url: String,
+    /// Note on what the endpoint is for
+    pub description: Option<String>,
+    /// Event types delivered; empty for every event
+    pub events: Json<Vec<String>>,
+    /// Conditions events must also meet
+    pub filters: Json<WebhookFilters>,
+    /// Whether events are delivered
+    pub is_active: bool,
+    /// When the secret was last rotated
+    pub secret_rotated_at: Option<DateTime<Utc>>,
+    /// Until when the replaced secret still signs deliveries
+    pub previous_secret_expires_at: Option<DateTime<Utc>>,
+    /// When an event was last delivered
+    pub last_triggered: Option<DateTime<Utc>>,
+    /// Deliveries the endpoint accepted
+    pub success_count: i32,
+    /// Deliveries that failed
+    pub failure_count: i32,
+    /// When the webhook was created
+    pub created_at: DateTime<Utc>,
+    /// When the webhook last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TenantOwned for WebhookRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Active webhook an event is due at, with the secrets its delivery is signed with
+#[derive(Debug, Clone)]
+pub struct WebhookTargetRecord {
+    /// Webhook ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// URL the event is POSTed to
+    pub url: String,
+    /// Conditions the event must meet besides its type
+    pub filters: Json<WebhookFilters>,
+    /// Current secret
+    pub secret: String,
+    /// Secret replaced by the last rotation, while it is still within its overlap window
+    pub previous_secret: Option<String>,
+}
+
+impl TenantOwned for WebhookTargetRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Stored attempt at delivering an event to a webhook
+#[derive(Debug, Clone)]
+pub struct WebhookDeliveryRecord {
+    /// Delivery ID
+    pub id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Webhook the event was POSTed to
+    pub webhook_id: Uuid,
+    /// Outbox event delivered
+    pub event_id: Uuid,
+    /// Type of the event
+    pub event_type: String,
+    /// How the attempt went
+    pub status: DeliveryStatus,
+    /// HTTP status the endpoint answered with
+    pub response_status: Option<i32>,
+    /// Milliseconds until the endpoint answered or the attempt gave up
+    pub latency_ms: i32,
+    /// What went wrong
+    pub error: Option<String>,
+    /// Delivery this one redrove
+    pub redrive_of: Option<Uuid>,
+    /// When the attempt was made
+    pub attempted_at: DateTime<Utc>,
+}
+
+impl TenantOwned for WebhookDeliveryRecord {
+    fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+}
+
+/// Attempt at delivering an event, to be logged
+#[derive(Debug, Clone)]
+pub struct NewWebhookDelivery<'a> {
+    /// Webhook the event was POSTed to
+    pub webhook_id: Uuid,
+    /// Outbox event delivered
+    pub event_id: Uuid,
+    /// Type of the event
+    pub event_type: &'a str,
+    /// How the attempt went
+    pub status: DeliveryStatus,
+    /// HTTP status the endpoint answered with
+    pub response_status: Option<i32>,
+    /// Milliseconds until the endpoint answered or the attempt gave up
+    pub latency_ms: i32,
+    /// What went wrong
+    pub error: Option<&'a str>,
+    /// Delivery being redriven
+    pub redrive_of: Option<Uuid>,
+}
+
+/// Queries over `webhooks` and `webhook_deliveries`
+pub struct WebhookRepo;
+
+impl WebhookRepo {
+    /// An account's webhooks, oldest first
+    pub async fn list(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+    ) -> sqlx::Result<Vec<WebhookRecord>> {
+        sqlx::query_as!(
+            WebhookRecord,
+            r#"
+            SELECT id, account_id, url, description, events AS "events: Json<Vec<String>>",
+                   filters AS "filters: Json<WebhookFilters>", is_active, secret_rotated_at, previous_secret_expires_at, last_triggered,
+                   success_count, failure_count, created_at, updated_at
+            FROM webhooks
+            WHERE account_id = $1
+            ORDER BY created_at, id
+            "#,
+            tenant.id()
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Fetch one of an account's webhooks
+    pub async fn find(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        webhook_id: Uuid,
+    ) -> sqlx::Result<Option<WebhookRecord>> {
+        sqlx::query_as!(
+            WebhookRecord,
+            r#"
+            SELECT id, account_id, url, description, events AS "events: Json<Vec<String>>",
+                   filters AS "filters: Json<WebhookFilters>", is_active, secret_rotated_at, previous_secret_expires_at, last_triggered,
+                   success_count, failure_count, created_at, updated_at
+            FROM webhooks
+            WHERE id = $1 AND account_id = $2
+            "#,
+            webhook_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Create a webhook signing with `secret`
+    pub async fn insert(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        webhook: &WebhookRequest,
+        secret: &str,
+    ) -> sqlx::Result<Uuid> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO webhooks (account_id, url, description, events, filters, is_active, secret)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#,
+            tenant.id(),
+            webhook.url,
+            webhook.description,
+            Json(&webhook.events) as _,
+            Json(&webhook.filters) as _,
+            webhook.active,
+            secret
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Replace a webhook's URL, description, events, filters, and whether it is active, returning
+    /// whether it existed; its secrets are kept
+    pub async fn update(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        webhook_id: Uuid,
+        webhook: &WebhookRequest,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE webhooks
+            SET url = $3, description = $4, events = $5, filters = $6, is_active = $7
+            WHERE id = $1 AND account_id = $2
+            "#,
+            webhook_id,
+            tenant.id(),
+            webhook.url,
+            webhook.description,
+            Json(&webhook.events) as _,
+            Json(&webhook.filters) as _,
+            webhook.active
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete a webhook, returning whether it existed
+    pub async fn delete(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        webhook_id: Uuid,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM webhooks WHERE id = $1 AND account_id = $2",
+            webhook_id,
+            tenant.id()
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replace a webhook's secret with `secret`, returning whether it existed
+    ///
+    /// The replaced secret keeps signing deliveries until `overlap_until`; one already past
+    /// that time is dropped. A secret still overlapping from an earlier rotation is dropped
+    /// either way, so deliveries carry at most two signatures.
+    pub async fn rotate_secret(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        webhook_id: Uuid,
+        secret: &str,
+        overlap_until: DateTime<Utc>,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE webhooks
+            SET previous_secret = CASE WHEN $4 > CURRENT_TIMESTAMP THEN secret END,
+                previous_secret_expires_at = CASE WHEN $4 > CURRENT_TIMESTAMP THEN $4 END,
+                secret = $3,
+                secret_rotated_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND account_id = $2
+            "#,
+            webhook_id,
+            tenant.id(),
+            secret,
+            overlap_until
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Active webhooks of an account that take events of `event_type`, whatever their filters
+    pub async fn targets(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        event_type: &str,
+    ) -> sqlx::Result<Vec<WebhookTargetRecord>> {
+        sqlx::query_as!(
+            WebhookTargetRecord,
+            r#"
+            SELECT id, account_id, url, filters AS "filters: Json<WebhookFilters>", secret,
+                   CASE WHEN previous_secret_expires_at > CURRENT_TIMESTAMP
+                        THEN previous_secret END AS previous_secret
+            FROM webhooks
+            WHERE account_id = $1 AND is_active
+              AND (events = '[]'::jsonb OR events ? $2)
+            ORDER BY created_at, id
+            "#,
+            tenant.id(),
+            event_type
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Active webhook of an account, with its secrets, whatever events it takes
+    pub async fn target(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        webhook_id: Uuid,
+    ) -> sqlx::Result<Option<WebhookTargetRecord>> {
+        sqlx::query_as!(
+            WebhookTargetRecord,
+            r#"
+            SELECT id, account_id, url, filters AS "filters: Json<WebhookFilters>", secret,
+                   CASE WHEN previous_secret_expires_at > CURRENT_TIMESTAMP
+                        THEN previous_secret END AS previous_secret
+            FROM webhooks
+            WHERE id = $1 AND account_id = $2 AND is_active
+            "#,
+            webhook_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+
+    /// Log a delivery attempt and count it against its webhook, returning its ID, or `None`
+    /// if the webhook has since been deleted
+    pub async fn insert_delivery(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        delivery: &NewWebhookDelivery<'_>,
+    ) -> sqlx::Result<Option<Uuid>> {
+        let delivered = delivery.status == DeliveryStatus::Succeeded;
+        sqlx::query_scalar!(
+            r#"
+            WITH counted AS (
+                UPDATE webhooks
+                SET last_triggered = CURRENT_TIMESTAMP,
+                    success_count = success_count + CASE WHEN $10 THEN 1 ELSE 0 END,
+                    failure_count = failure_count + CASE WHEN $10 THEN 0 ELSE 1 END
+                WHERE id = $2 AND account_id = $1
+                RETURNING id
+            )
+            INSERT INTO webhook_deliveries (
+                account_id, webhook_id, event_id, event_type, status, response_status,
+                latency_ms, error, redrive_of
+            )
+            SELECT $1, id, $3, $4, $5, $6, $7, $8, $9 FROM counted
+            RETURNING id
+            "#,
+            tenant.id(),
+            delivery.webhook_id,
+            delivery.event_id,
+            delivery.event_type,
+            delivery.status as _,
+            delivery.response_status,
+            delivery.latency_ms,
+            delivery.error,
+            delivery.redrive_of,
+            delivered
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Page of a webhook's deliveries matching `query`, newest first
+    pub async fn deliveries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        webhook_id: Uuid,
+        query: &ListDeliveriesQuery,
+        limit: i64,
+        offset: i64,
+    ) -> sqlx::Result<Vec<WebhookDeliveryRecord>> {
+        sqlx::query_as!(
+            WebhookDeliveryRecord,
+            r#"
+            SELECT id, account_id, webhook_id, event_id, event_type,
+                   status AS "status: DeliveryStatus", response_status, latency_ms, error,
+                   redrive_of, attempted_at
+            FROM webhook_deliveries
+            WHERE webhook_id = $1 AND account_id = $2
+              AND ($3::varchar IS NULL OR status = $3)
+              AND ($4::uuid IS NULL OR event_id = $4)
+            ORDER BY attempted_at DESC, id
+            LIMIT $5 OFFSET $6
+            "#,
+            webhook_id,
+            tenant.id(),
+            query.status as _,
+            query.event_id,
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await
+        .and_then(|records| tenant.check_all(records))
+    }
+
+    /// Number of a webhook's deliveries matching `query`
+    pub async fn count_deliveries(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        webhook_id: Uuid,
+        query: &ListDeliveriesQuery,
+    ) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM webhook_deliveries
+            WHERE webhook_id = $1 AND account_id = $2
+              AND ($3::varchar IS NULL OR status = $3)
+              AND ($4::uuid IS NULL OR event_id = $4)
+            "#,
+            webhook_id,
+            tenant.id(),
+            query.status as _,
+            query.event_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Fetch one of an account's deliveries
+    pub async fn find_delivery(
+        executor: impl PgExecutor<'_>,
+        tenant: Tenant,
+        delivery_id: Uuid,
+    ) -> sqlx::Result<Option<WebhookDeliveryRecord>> {
+        sqlx::query_as!(
+            WebhookDeliveryRecord,
+            r#"
+            SELECT id, account_id, webhook_id, event_id, event_type,
+                   status AS "status: DeliveryStatus", response_status, latency_ms, error,
+                   redrive_of, attempted_at
+            FROM webhook_deliveries
+            WHERE id = $1 AND account_id = $2
+            "#,
+            delivery_id,
+            tenant.id()
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|record| tenant.check(record))
+        .transpose()
+    }
+}
@@ -0,0 +1,460 @@
+//! Demo data for local development
+//!
+//! Seeds a demo account with well-known live and sandbox API keys, a few hundred users with
+//! their devices, and a few thousand transactions spread over the last ninety days.
+//! Transactions are scored by the real [`RiskEngine`] and stored through
+//! [`TransactionService`], so users, devices, addresses, cards, risk factors, and outbox events
+//! are populated exactly as they would be by the API.
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{Tenant, repositories::AccountRepo};
+use crate::{
+    config::Config,
+    models::{account::SubscriptionTier, transaction::TransactionRequest},
+    scoring::{RiskEngine, UserSignals},
+    services::TransactionService,
+    utils::sha256_hex,
+};
+
+/// Public identifier of the seeded demo account
+pub const DEMO_ACCOUNT_ID: &str = "demo";
+
+/// API key for the seeded demo account
+pub const DEMO_API_KEY: &str = "fusegu_demo_key";
+
+/// Sandbox API key for the seeded demo account
+pub const DEMO_SANDBOX_API_KEY: &str = "fusegu_demo_sandbox_key";
+
+const USER_COUNT: usize = 250;
+const TRANSACTION_COUNT: usize = 3_000;
+const HISTORY_DAYS: i64 = 90;
+
+/// Fixed seed so every developer gets the same demo data
+const RNG_SEED: u64 = 0x5eed_f05e;
+
+/// Share of transactions with no known user
+const ANONYMOUS_RATE: f64 = 0.08;
+
+/// Share of transactions carrying the hallmarks of card fraud
+const FRAUD_RATE: f64 = 0.07;
+
+const COUNTRIES: &[(&str, &str, &str, &str)] = &[
+    ("US", "New York", "NY", "10001"),
+    ("US", "Austin", "TX", "73301"),
+    ("US", "Seattle", "WA", "98101"),
+    ("GB", "London", "LND", "EC1A"),
+    ("DE", "Berlin", "BE", "10115"),
+    ("FR", "Paris", "IDF", "75001"),
+    ("CA", "Toronto", "ON", "M5H"),
+    ("AU", "Sydney", "NSW", "2000"),
+];
+
+const RISKY_COUNTRIES: &[&str] = &["NG", "RU", "VN", "BR", "ID"];
+
+const FIRST_NAMES: &[&str] = &[
+    "Alex", "Sam", "Jordan", "Taylor", "Morgan", "Casey", "Riley", "Jamie", "Avery", "Quinn",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Smith", "Garcia", "Müller", "Martin", "Brown", "Nguyen", "Wilson", "Kowalski", "Rossi",
+    "Tanaka",
+];
+
+const EMAIL_DOMAINS: &[&str] = &[
+    "gmail.com",
+    "outlook.com",
+    "yahoo.com",
+    "proton.me",
+    "icloud.com",
+];
+
+const DISPOSABLE_EMAIL_DOMAINS: &[&str] =
+    &["mailinator.com", "guerrillamail.com", "10minutemail.com"];
+
+const USER_AGENTS: &[&str] = &[
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_4) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15",
+    "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Mobile/15E148",
+    "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0 Mobile Safari/537.36",
+    "Mozilla/5.0 (X11; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0",
+];
+
+const ACCEPT_LANGUAGES: &[&str] = &[
+    "en-US,en;q=0.9",
+    "en-GB,en;q=0.8",
+    "de-DE,de;q=0.9",
+    "fr-FR,fr;q=0.9",
+];
+
+/// Card BINs (issuer identification numbers) used for demo cards
+const CARD_BINS: &[&str] = &["411111", "510510", "371449", "601100"];
+
+/// First octets of publicly routable IPv4 ranges used for demo devices
+const IP_FIRST_OCTETS: &[u8] = &[23, 45, 62, 81, 98, 142, 176, 203];
+
+const CATALOG: &[(&str, &str, f64)] = &[
+    ("sku_headphones", "electronics", 129.99),
+    ("sku_phone_case", "accessories", 24.50),
+    ("sku_sneakers", "apparel", 89.00),
+    ("sku_coffee_beans", "grocery", 18.75),
+    ("sku_backpack", "apparel", 64.00),
+    ("sku_smartwatch", "electronics", 249.00),
+    ("sku_gift_card", "gift_cards", 100.00),
+    ("sku_novel", "books", 14.99),
+];
+
+/// Result of a seeding run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedOutcome {
+    /// Demo data was created
+    Seeded {
+        /// Internal ID of the demo account
+        account_id: Uuid,
+        /// Number of transactions stored
+        transactions: usize,
+    },
+    /// The demo account already exists, so nothing was changed
+    AlreadySeeded,
+}
+
+/// Populate the demo account and its sample data
+///
+/// Idempotent: if the demo account already exists the database is left untouched.
+pub async fn seed(pool: &PgPool) -> anyhow::Result<SeedOutcome> {
+    let mut tx = pool.begin().await?;
+    let Some(account_id) = AccountRepo::create(
+        &mut *tx,
+        DEMO_ACCOUNT_ID,
+        SubscriptionTier::Enterprise,
+        1_000_000,
+    )
+    .await?
+    else {
+        return Ok(SeedOutcome::AlreadySeeded);
+    };
+    AccountRepo::insert_api_key(
+        &mut *tx,
+        account_id,
+        &sha256_hex(DEMO_API_KEY),
+        "Demo key",
+        None,
+    )
+    .await?;
+    let sandbox_id = AccountRepo::get_or_create_sandbox(&mut *tx, account_id).await?;
+    AccountRepo::insert_api_key(
+        &mut *tx,
+        sandbox_id,
+        &sha256_hex(DEMO_SANDBOX_API_KEY),
+        "Demo sandbox key",
+        None,
+    )
+    .await?;
+    tx.commit().await?;
+
+    let engine = RiskEngine::new();
+    let transactions =
+        TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+    let mut generator = DemoGenerator::new(RNG_SEED, Utc::now());
+
+    for index in 0..TRANSACTION_COUNT {
+        let request = generator.request(index)?;
+        let assessment = engine.assess(&request, &UserSignals::default());
+        let warnings = request.warnings();
+        transactions
+            .store_transaction(
+                Tenant::trusted(account_id),
+                &request,
+                &assessment,
+                &warnings,
+            )
+            .await?;
+
+        if (index + 1) % 500 == 0 {
+            tracing::info!(
+                stored = index + 1,
+                total = TRANSACTION_COUNT,
+                "Seeding transactions"
+            );
+        }
+    }
+
+    Ok(SeedOutcome::Seeded {
+        account_id,
+        transactions: TRANSACTION_COUNT,
+    })
+}
+
+/// SplitMix64, which is plenty for plausible-looking demo data
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `[0, 1)`
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        self.unit() < probability
+    }
+
+    fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + self.unit() * (high - low)
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DemoDevice {
+    ip_address: String,
+    user_agent: &'static str,
+    accept_language: &'static str,
+}
+
+#[derive(Debug, Clone)]
+struct DemoUser {
+    external_id: String,
+    first_name: &'static str,
+    last_name: &'static str,
+    email: String,
+    location: (&'static str, &'static str, &'static str, &'static str),
+    card_bin: &'static str,
+    card_last_digits: String,
+    devices: Vec<DemoDevice>,
+}
+
+/// Deterministic source of demo users and transaction requests
+#[derive(Debug, Clone)]
+struct DemoGenerator {
+    rng: Rng,
+    now: DateTime<Utc>,
+    users: Vec<DemoUser>,
+}
+
+impl DemoGenerator {
+    fn new(seed: u64, now: DateTime<Utc>) -> Self {
+        let mut rng = Rng(seed);
+        let users = (0..USER_COUNT).map(|i| demo_user(&mut rng, i)).collect();
+        Self { rng, now, users }
+    }
+
+    /// Build the `index`-th demo transaction request
+    fn request(&mut self, index: usize) -> serde_json::Result<TransactionRequest> {
+        let rng = &mut self.rng;
+        let time = self.now - Duration::seconds(rng.below(HISTORY_DAYS as usize * 86_400) as i64);
+        let transaction_id = format!("demo_txn_{index:05}");
+
+        if rng.chance(ANONYMOUS_RATE) {
+            let device = random_device(rng);
+            // Scripted traffic often omits the user agent entirely
+            let user_agent = (!rng.chance(0.4)).then_some(device.user_agent);
+            return serde_json::from_value(json!({
+                "device": { "ip_address": device.ip_address, "user_agent": user_agent },
+                "event": { "type": "purchase", "transaction_id": transaction_id, "time": time },
+                "order": { "amount": round_cents(rng.range(20.0, 400.0)), "currency": "USD" },
+            }));
+        }
+
+        let user = &self.users[rng.below(self.users.len())];
+        let device = &user.devices[rng.below(user.devices.len())];
+        let (country, city, region, postal) = user.location;
+        let event_type = match rng.below(100) {
+            0..=79 => "purchase",
+            80..=89 => "account_login",
+            90..=94 => "recurring_purchase",
+            95..=97 => "email_change",
+            _ => "password_reset",
+        };
+        let device_json = json!({
+            "ip_address": device.ip_address,
+            "user_agent": device.user_agent,
+            "accept_language": device.accept_language,
+            "session_id": format!("sess_{:016x}", rng.next_u64()),
+            "session_age": round_cents(rng.range(5.0, 7_200.0)),
+        });
+        let event = json!({
+            "type": event_type,
+            "transaction_id": transaction_id,
+            "shop_id": "shop_main",
+            "time": time,
+        });
+
+        if !matches!(event_type, "purchase" | "recurring_purchase") {
+            return serde_json::from_value(json!({
+                "device": device_json,
+                "event": event,
+                "account": { "user_id": user.external_id },
+                "email": { "address": user.email },
+            }));
+        }
+
+        let fraud = rng.chance(FRAUD_RATE);
+        let billing = json!({
+            "first_name": user.first_name,
+            "last_name": user.last_name,
+            "address": format!("{} Main Street", 1 + rng.below(400)),
+            "city": city,
+            "region": region,
+            "postal": postal,
+            "country": country,
+        });
+        let (shipping_country, card_country, amount) = if fraud {
+            (
+                rng.pick(RISKY_COUNTRIES),
+                rng.pick(RISKY_COUNTRIES),
+                round_cents(rng.range(900.0, 7_500.0)),
+            )
+        } else {
+            (country, country, 0.0)
+        };
+
+        let mut cart = Vec::new();
+        let mut total = 0.0;
+        for _ in 0..1 + rng.below(3) {
+            let (item_id, category, price) = rng.pick(CATALOG);
+            let quantity = 1 + rng.below(2) as i64;
+            total += price * quantity as f64;
+            cart.push(json!({
+                "item_id": item_id,
+                "category": category,
+                "price": price,
+                "quantity": quantity,
+            }));
+        }
+        let amount = if fraud { amount } else { round_cents(total) };
+
+        serde_json::from_value(json!({
+            "device": device_json,
+            "event": event,
+            "account": { "user_id": user.external_id },
+            "email": {
+                "address": if fraud {
+                    format!("{}@{}", user.external_id, rng.pick(DISPOSABLE_EMAIL_DOMAINS))
+                } else {
+                    user.email.clone()
+                },
+            },
+            "billing": billing,
+            "shipping": {
+                "first_name": user.first_name,
+                "last_name": user.last_name,
+                "city": if fraud { "Lagos" } else { city },
+                "country": shipping_country,
+                "delivery_speed": if fraud { "same_day" } else { "standard" },
+            },
+            "credit_card": {
+                "issuer_id_number": user.card_bin,
+                "last_digits": user.card_last_digits,
+                "token": format!("tok_{}_{}", user.external_id, user.card_last_digits),
+                "country": card_country,
+                "avs_result": if fraud && rng.chance(0.6) { "N" } else { "Y" },
+                "cvv_result": if fraud && rng.chance(0.5) { "N" } else { "M" },
+                "was_3d_secure_successful": !(fraud && rng.chance(0.4)),
+            },
+            "order": { "amount": amount, "currency": "USD", "is_gift": fraud && rng.chance(0.5) },
+            "shopping_cart": cart,
+        }))
+    }
+}
+
+fn demo_user(rng: &mut Rng, index: usize) -> DemoUser {
+    let first_name = rng.pick(FIRST_NAMES);
+    let last_name = rng.pick(LAST_NAMES);
+    let external_id = format!("demo_user_{index:04}");
+    let email = format!(
+        "{}.{}{}@{}",
+        first_name.to_lowercase(),
+        last_name.to_lowercase(),
+        index,
+        rng.pick(EMAIL_DOMAINS)
+    );
+    let devices = (0..1 + rng.below(3)).map(|_| random_device(rng)).collect();
+
+    DemoUser {
+        external_id,
+        first_name,
+        last_name,
+        email,
+        location: rng.pick(COUNTRIES),
+        card_bin: rng.pick(CARD_BINS),
+        card_last_digits: format!("{:04}", rng.below(10_000)),
+        devices,
+    }
+}
+
+fn random_device(rng: &mut Rng) -> DemoDevice {
+    DemoDevice {
+        ip_address: format!(
+            "{}.{}.{}.{}",
+            rng.pick(IP_FIRST_OCTETS),
+            rng.below(256),
+            rng.below(256),
+            1 + rng.below(254)
+        ),
+        user_agent: rng.pick(USER_AGENTS),
+        accept_language: rng.pick(ACCEPT_LANGUAGES),
+    }
+}
+
+fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::transaction::RiskLevel;
+
+    #[test]
+    fn test_generated_requests_are_valid_and_deterministic() {
+        let now = Utc::now();
+        let mut first = DemoGenerator::new(RNG_SEED, now);
+        let mut second = DemoGenerator::new(RNG_SEED, now);
+
+        for index in 0..500 {
+            let request = first.request(index).unwrap();
+            assert_eq!(request.validate(), Ok(()), "request {index}");
+            assert!(request.warnings().is_empty(), "request {index}");
+            assert_eq!(
+                serde_json::to_value(&request).unwrap(),
+                serde_json::to_value(second.request(index).unwrap()).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_generated_requests_span_risk_levels() {
+        let mut generator = DemoGenerator::new(RNG_SEED, Utc::now());
+        let engine = RiskEngine::new();
+        let levels: Vec<RiskLevel> = (0..TRANSACTION_COUNT)
+            .map(|i| {
+                engine
+                    .assess(&generator.request(i).unwrap(), &UserSignals::default())
+                    .risk_level
+            })
+            .collect();
+
+        for level in [RiskLevel::Low, RiskLevel::Medium, RiskLevel::High] {
+            assert!(levels.contains(&level), "no {level:?} transactions");
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Tenant scoping for repository queries
+//!
+//! Every table holding customer data carries an `account_id`, and every repository function
+//! reading or writing such a table takes a [`Tenant`] and filters on it. Request handlers get
+//! their `Tenant` from the caller's [`AuthContext`], so a handler cannot reach another
+//! account's rows by passing the wrong ID. Background jobs that discover accounts in the
+//! database itself use [`Tenant::trusted`], which keeps those call sites easy to audit.
+//!
+//! Rows beneath a scoped row, such as order items or transaction links, are keyed by the
+//! parent's ID, which the caller can only have obtained through a scoped query. Records that
+//! carry their owner implement [`TenantOwned`], and repositories pass them through
+//! [`Tenant::check`] as a second line of defence should a query ever lose its filter.
+
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::auth::AuthContext;
+
+/// Account whose data a query may touch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tenant(Uuid);
+
+impl Tenant {
+    /// Scope queries to an account that did not come from an authenticated caller
+    ///
+    /// Only for background jobs and tooling acting on accounts they found themselves; request
+    /// handlers must use [`AuthContext::tenant`].
+    pub fn trusted(account_id: Uuid) -> Self {
+        Self(account_id)
+    }
+
+    /// Internal ID of the account
+    pub fn id(self) -> Uuid {
+        self.0
+    }
+
+    /// Pass `record` through if it belongs to this tenant
+    ///
+    /// A record of another tenant means a query is missing its filter; it is logged and
+    /// turned into an error rather than returned.
+    pub fn check<T: TenantOwned>(self, record: T) -> sqlx::Result<T> {
+        let owner = record.account_id();
+        if owner == self.0 {
+            return Ok(record);
+        }
+        tracing::error!(tenant = %self.0, %owner, "Query returned a row of another tenant");
+        Err(sqlx::Error::Protocol(format!(
+            "row of account {owner} returned to account {}",
+            self.0
+        )))
+    }
+
+    /// [`Tenant::check`] every record, failing if any belongs to another tenant
+    pub fn check_all<T: TenantOwned>(self, records: Vec<T>) -> sqlx::Result<Vec<T>> {
+        records
+            .into_iter()
+            .map(|record| self.check(record))
+            .collect()
+    }
+}
+
+impl From<&AuthContext> for Tenant {
+    fn from(auth: &AuthContext) -> Self {
+        Self(auth.account_id)
+    }
+}
+
+impl fmt::Display for Tenant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Record that belongs to one account
+pub trait TenantOwned {
+    /// Internal ID of the owning account
+    fn account_id(&self) -> Uuid;
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        auth::ScopeSet,
+        database::repositories::{NewTransaction, TransactionRecord, TransactionRepo, UserRepo},
+        models::{
+            account::{AccountStatus, SubscriptionTier},
+            transaction::{Disposition, EventType, ListTransactionsQuery, MatchKeys, RiskLevel},
+        },
+        test_support::{create_account, test_pool},
+    };
+
+    fn record(account_id: Uuid) -> TransactionRecord {
+        TransactionRecord {
+            id: Uuid::new_v4(),
+            account_id,
+            user_id: None,
+            external_transaction_id: None,
+            risk_score: 12.5,
+            risk_level: RiskLevel::Low,
+            disposition: Disposition::Accept,
+            event_type: EventType::Purchase,
+            shop_id: None,
+            event_time: Utc::now(),
+            original_transaction_id: None,
+            warnings: sqlx::types::Json(Vec::new()),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_check_rejects_other_tenants() {
+        let tenant = Tenant::trusted(Uuid::new_v4());
+        let other = Uuid::new_v4();
+
+        assert!(tenant.check(record(tenant.id())).is_ok());
+        assert!(tenant.check(record(other)).is_err());
+        assert_eq!(
+            tenant
+                .check_all(vec![record(tenant.id()), record(tenant.id())])
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(
+            tenant
+                .check_all(vec![record(tenant.id()), record(other)])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_tenant_from_auth_context() {
+        let auth = AuthContext {
+            account_id: Uuid::new_v4(),
+            api_key_id: None,
+            scopes: ScopeSet::all(),
+            sandbox: false,
+            tier: SubscriptionTier::Free,
+            status: AccountStatus::Active,
+        };
+        assert_eq!(auth.tenant().id(), auth.account_id);
+    }
+
+    #[tokio::test]
+    async fn test_cross_tenant_reads_fail() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let owner = create_account(&pool, SubscriptionTier::Free).await;
+        let intruder = create_account(&pool, SubscriptionTier::Free).await;
+
+        let user_id = UserRepo::upsert_by_external_id(&pool, owner, "user-1")
+            .await
+            .unwrap()
+            .unwrap();
+        let transaction = TransactionRepo::insert(
+            &pool,
+            NewTransaction {
+                tenant: owner,
+                user_id: Some(user_id),
+                external_transaction_id: Some("order-1"),
+                risk_score: 12.5,
+                risk_level: RiskLevel::Low,
+                disposition: Disposition::Accept,
+                event_type: EventType::Purchase,
+                shop_id: None,
+                event_time: Utc::now(),
+                original_transaction_id: None,
+                ip_address: "198.51.100.1",
+                asn: None,
+                isp: None,
+                local_hour: None,
+                device_data: serde_json::json!({}),
+                custom_inputs: serde_json::json!({}),
+                warnings: &[],
+                raw_request: serde_json::json!({}),
+                match_keys: &MatchKeys::default(),
+            },
+        )
+        .await
+        .unwrap();
+        let query = ListTransactionsQuery::default();
+
+        let found = TransactionRepo::find_by_id(&pool, owner, transaction.id)
+            .await
+            .unwrap();
+        assert_eq!(found.map(|t| t.id), Some(transaction.id));
+        assert_eq!(
+            TransactionRepo::count(&pool, owner, &query).await.unwrap(),
+            1
+        );
+
+        assert!(
+            TransactionRepo::find_by_id(&pool, intruder, transaction.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            TransactionRepo::list(&pool, intruder, &query, 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            TransactionRepo::count(&pool, intruder, &query)
+                .await
+                .unwrap(),
+            0
+        );
+        assert!(
+            UserRepo::find_id(&pool, intruder, user_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            !UserRepo::soft_delete(&pool, intruder, user_id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            UserRepo::find_id(&pool, owner, user_id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+}
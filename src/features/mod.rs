@@ -0,0 +1,15 @@
+//! Feature store for risk scoring
+//!
+//! Long-horizon behavioral profiles are precomputed nightly into the `user_profiles` table so
+//! rules can compare a transaction against the user's own baseline without scanning history on
+//! the scoring path. Each scored transaction also carries a [`FeatureSnapshot`] of the inputs
+//! it was scored on, for offline model training.
+
+pub mod profile;
+pub mod refresh;
+pub mod snapshot;
+pub mod store;
+
+pub use profile::UserProfile;
+pub use snapshot::FeatureSnapshot;
+pub use store::FeatureStore;
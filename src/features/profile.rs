@@ -0,0 +1,146 @@
+//! Long-horizon user behavioral profile
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Minimum number of purchases before a profile is considered a reliable baseline
+pub const MIN_BASELINE_TRANSACTIONS: i64 = 5;
+
+/// Behavioral baseline for a single user, computed from their recent purchase history
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserProfile {
+    /// Internal user identifier
+    pub user_id: Uuid,
+    /// Owning account
+    pub account_id: Uuid,
+    /// Number of purchases in the profile window
+    pub transaction_count: i64,
+    /// Mean order amount in the profile window
+    pub avg_order_amount: Option<f64>,
+    /// Sample standard deviation of order amounts
+    pub order_amount_stddev: Option<f64>,
+    /// Hours of day (UTC, 0-23) in which the user habitually purchases
+    pub usual_purchase_hours: Vec<i32>,
+    /// Hours of day (0-23), in the time zone of each purchase's IP address, in which the user
+    /// habitually purchases
+    pub usual_local_purchase_hours: Vec<i32>,
+    /// Billing countries (ISO 3166-1 alpha-2) the user habitually purchases from
+    pub usual_countries: Vec<String>,
+    /// Number of distinct devices seen in the profile window
+    pub typical_device_count: i32,
+    /// When the profile was last recomputed
+    pub computed_at: DateTime<Utc>,
+}
+
+impl UserProfile {
+    /// Whether enough history exists for deviation checks to be meaningful
+    pub fn has_baseline(&self) -> bool {
+        self.transaction_count >= MIN_BASELINE_TRANSACTIONS
+    }
+
+    /// Number of standard deviations `amount` lies from the user's mean order amount
+    ///
+    /// Returns `None` when there is no usable baseline or the user's amounts never vary.
+    pub fn amount_zscore(&self, amount: f64) -> Option<f64> {
+        if !self.has_baseline() {
+            return None;
+        }
+        let mean = self.avg_order_amount?;
+        let stddev = self.order_amount_stddev?;
+        if stddev <= f64::EPSILON {
+            return None;
+        }
+        Some((amount - mean) / stddev)
+    }
+
+    /// Whether a purchase at `hour` (UTC) falls outside the user's usual hours
+    pub fn is_unusual_hour(&self, hour: u32) -> bool {
+        self.has_baseline()
+            && !self.usual_purchase_hours.is_empty()
+            && !self.usual_purchase_hours.iter().any(|&h| h as u32 == hour)
+    }
+
+    /// Whether a purchase at `hour` of local time falls outside the user's usual local hours
+    pub fn is_unusual_local_hour(&self, hour: u32) -> bool {
+        self.has_baseline()
+            && !self.usual_local_purchase_hours.is_empty()
+            && !self
+                .usual_local_purchase_hours
+                .iter()
+                .any(|&h| h as u32 == hour)
+    }
+
+    /// Whether `country` is outside the set of countries the user usually purchases from
+    pub fn is_unusual_country(&self, country: &str) -> bool {
+        self.has_baseline()
+            && !self.usual_countries.is_empty()
+            && !self
+                .usual_countries
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(country))
+    }
+
+    /// Whether `device_count` exceeds the user's typical number of devices
+    pub fn exceeds_typical_devices(&self, device_count: i32) -> bool {
+        self.has_baseline() && device_count > self.typical_device_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(transaction_count: i64) -> UserProfile {
+        UserProfile {
+            user_id: Uuid::new_v4(),
+            account_id: Uuid::new_v4(),
+            transaction_count,
+            avg_order_amount: Some(50.0),
+            order_amount_stddev: Some(10.0),
+            usual_purchase_hours: vec![9, 10, 20],
+            usual_local_purchase_hours: vec![11, 12, 22],
+            usual_countries: vec!["US".to_string(), "CA".to_string()],
+            typical_device_count: 2,
+            computed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_amount_zscore() {
+        let profile = profile(10);
+        assert_eq!(profile.amount_zscore(80.0), Some(3.0));
+        assert_eq!(profile.amount_zscore(50.0), Some(0.0));
+    }
+
+    #[test]
+    fn test_amount_zscore_without_variance() {
+        let mut profile = profile(10);
+        profile.order_amount_stddev = Some(0.0);
+        assert_eq!(profile.amount_zscore(80.0), None);
+    }
+
+    #[test]
+    fn test_deviation_checks() {
+        let profile = profile(10);
+        assert!(profile.is_unusual_hour(3));
+        assert!(!profile.is_unusual_hour(20));
+        assert!(profile.is_unusual_local_hour(20));
+        assert!(!profile.is_unusual_local_hour(22));
+        assert!(profile.is_unusual_country("NG"));
+        assert!(!profile.is_unusual_country("us"));
+        assert!(profile.exceeds_typical_devices(3));
+        assert!(!profile.exceeds_typical_devices(2));
+    }
+
+    #[test]
+    fn test_no_deviation_without_baseline() {
+        let profile = profile(MIN_BASELINE_TRANSACTIONS - 1);
+        assert!(!profile.has_baseline());
+        assert_eq!(profile.amount_zscore(1000.0), None);
+        assert!(!profile.is_unusual_hour(3));
+        assert!(!profile.is_unusual_local_hour(3));
+        assert!(!profile.is_unusual_country("NG"));
+        assert!(!profile.exceeds_typical_devices(10));
+    }
+}
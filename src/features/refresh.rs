@@ -0,0 +1,16 @@
+//! Nightly recomputation of user profiles
+
+use super::FeatureStore;
+use crate::{config::FeaturesConfig, scheduler::ScheduledTask};
+
+/// Task that refreshes user profiles on `profile_refresh_schedule`, nightly by default
+pub fn profile_refresh_task(store: FeatureStore, config: FeaturesConfig) -> ScheduledTask {
+    let schedule = config.profile_refresh_schedule.clone();
+    ScheduledTask::new("profile_refresh", schedule, move || {
+        let (store, lookback_days) = (store.clone(), config.profile_lookback_days);
+        async move {
+            store.refresh_user_profiles(lookback_days).await?;
+            Ok(())
+        }
+    })
+}
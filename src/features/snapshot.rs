@@ -0,0 +1,218 @@
+//! Point-in-time record of the inputs a transaction was scored on
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{device::DeviceStatus, insights::PhoneLineType, transaction::TransactionRequest},
+    scoring::{LocalTime, UserSignals},
+};
+
+/// The inputs the built-in rules read, as they were when the transaction was scored
+///
+/// Snapshots travel with the `transaction.scored` event into the analytics store, so models
+/// can be trained offline on the same inputs the engine decided on.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeatureSnapshot {
+    /// Order amount
+    pub order_amount: Option<f64>,
+    /// ISO 4217 currency of the order amount
+    pub order_currency: Option<String>,
+    /// Billing country (ISO 3166-1 alpha-2)
+    pub billing_country: Option<String>,
+    /// Shipping country (ISO 3166-1 alpha-2)
+    pub shipping_country: Option<String>,
+    /// Country that issued the payment card
+    pub card_country: Option<String>,
+    /// Card security code check result from the processor
+    pub cvv_result: Option<String>,
+    /// Address verification result from the processor
+    pub avs_result: Option<String>,
+    /// Whether 3D Secure authentication succeeded, if it was attempted
+    pub three_d_secure_successful: Option<bool>,
+    /// Whether the device sent a non-empty user agent
+    pub has_user_agent: bool,
+    /// Unexpired flags on the transaction's user
+    #[serde(default)]
+    pub active_user_flags: usize,
+    /// Distinct users recently seen with the transaction's device, counting its own user
+    #[serde(default)]
+    pub device_user_count: i64,
+    /// Whether the transaction's device was previously tied to a chargeback
+    #[serde(default)]
+    pub device_chargebacks: bool,
+    /// Whether the transaction's device was trusted, blocked, or neither
+    #[serde(default)]
+    pub device_status: DeviceStatus,
+    /// Seconds since the account was created in the same session, for purchases
+    #[serde(default)]
+    pub session_seconds_after_signup: Option<f64>,
+    /// Distinct IP addresses seen in the transaction's session
+    #[serde(default)]
+    pub session_ip_addresses: usize,
+    /// Distinct countries seen in the transaction's session
+    #[serde(default)]
+    pub session_countries: usize,
+    /// Earlier transactions of the account from the IP address's subnet in the last hour
+    #[serde(default)]
+    pub ip_subnet_transactions_last_hour: i64,
+    /// Distinct users of the account's recent transactions from the IP address's subnet
+    #[serde(default)]
+    pub ip_subnet_users: i64,
+    /// Speed in km/h the user would have travelled at since their last located transaction
+    #[serde(default)]
+    pub travel_speed_kmh: Option<f64>,
+    /// Country the IP address is located in (ISO 3166-1 alpha-2)
+    #[serde(default)]
+    pub ip_country: Option<String>,
+    /// Distance in kilometres from the billing address to the IP address location
+    #[serde(default)]
+    pub billing_ip_distance_km: Option<f64>,
+    /// Distance in kilometres from the shipping address to the IP address location
+    #[serde(default)]
+    pub shipping_ip_distance_km: Option<f64>,
+    /// Hour of day (0-23) in the time zone of the IP address
+    #[serde(default)]
+    pub local_hour: Option<u32>,
+    /// Whether the email domain is a free email provider
+    #[serde(default)]
+    pub email_free: bool,
+    /// Whether the email domain hands out disposable addresses
+    #[serde(default)]
+    pub email_disposable: bool,
+    /// Other users seen with variants of the email mailbox
+    #[serde(default)]
+    pub email_variant_users: i64,
+    /// Whole days since the email address was first seen by any account
+    #[serde(default)]
+    pub email_age_days: Option<i64>,
+    /// The account's earlier accepted transactions with the email address not reported as fraud
+    #[serde(default)]
+    pub email_good_transactions: i64,
+    /// Earlier transactions of the account with the billing or shipping address in the last hour
+    #[serde(default)]
+    pub address_transactions_last_hour: i64,
+    /// Distinct users of the account's recent transactions with the billing or shipping address
+    #[serde(default)]
+    pub address_users: i64,
+    /// How closely the cardholder name matches the billing name, from 0 to 1
+    #[serde(default)]
+    pub cardholder_billing_name_match: Option<f64>,
+    /// How closely the cardholder name matches the email address, from 0 to 1
+    #[serde(default)]
+    pub cardholder_email_name_match: Option<f64>,
+    /// Country the phone number is allocated to (ISO 3166-1 alpha-2)
+    #[serde(default)]
+    pub phone_country: Option<String>,
+    /// Kind of line the phone number belongs to, if the number is valid
+    #[serde(default)]
+    pub phone_line_type: Option<PhoneLineType>,
+    /// Card type of the card's BIN range: `credit`, `debit`, or `charge`
+    #[serde(default)]
+    pub card_type: Option<String>,
+    /// Whether the card's BIN range holds prepaid cards
+    #[serde(default)]
+    pub card_prepaid: bool,
+    /// Whether the card's BIN range holds business cards
+    #[serde(default)]
+    pub card_business: bool,
+    /// Whether the card's BIN range holds virtual cards
+    #[serde(default)]
+    pub card_virtual: bool,
+    /// Distinct BINs of the cards recently tried from the device or IP address
+    #[serde(default)]
+    pub card_testing_bins: i64,
+    /// Small payments recently declined from the device or IP address
+    #[serde(default)]
+    pub card_testing_declined_small: i64,
+    /// Most cards of one BIN with sequential numbers recently tried from the device or IP address
+    #[serde(default)]
+    pub card_testing_sequential: i64,
+}
+
+impl FeatureSnapshot {
+    /// Capture the scoring inputs of `request` from a user with the given signals
+    pub fn capture(request: &TransactionRequest, user: &UserSignals) -> Self {
+        let card = request.credit_card.as_ref();
+        Self {
+            order_amount: request.order.as_ref().map(|order| order.amount),
+            order_currency: request.order.as_ref().map(|order| order.currency.clone()),
+            billing_country: request
+                .billing
+                .as_ref()
+                .and_then(|billing| billing.country.clone()),
+            shipping_country: request
+                .shipping
+                .as_ref()
+                .and_then(|shipping| shipping.address.country.clone()),
+            card_country: card.and_then(|card| card.country.clone()),
+            cvv_result: card.and_then(|card| card.cvv_result.clone()),
+            avs_result: card.and_then(|card| card.avs_result.clone()),
+            three_d_secure_successful: card.and_then(|card| card.was_3d_secure_successful),
+            has_user_agent: request
+                .device
+                .user_agent
+                .as_deref()
+                .is_some_and(|ua| !ua.trim().is_empty()),
+            active_user_flags: user.active_flags.len(),
+            device_user_count: user.device_user_count,
+            device_chargebacks: user.device_chargebacks,
+            device_status: user.device_status,
+            session_seconds_after_signup: user.session.purchase_seconds_after_signup,
+            session_ip_addresses: user.session.ip_addresses,
+            session_countries: user.session.countries,
+            ip_subnet_transactions_last_hour: user.ip_velocity.last_hour,
+            ip_subnet_users: user.ip_velocity.users_last_day,
+            travel_speed_kmh: user.travel.map(|travel| travel.speed_kmh()),
+            ip_country: user.ip_country.clone(),
+            billing_ip_distance_km: user.billing_ip_distance_km,
+            shipping_ip_distance_km: user.shipping_ip_distance_km,
+            local_hour: user.local_time.as_ref().map(LocalTime::hour),
+            email_free: user.email_traits.is_free,
+            email_disposable: user.email_traits.is_disposable,
+            email_variant_users: user.email_variants.users,
+            email_age_days: user.email_age.map(|age| age.days),
+            email_good_transactions: user.email_age.map_or(0, |age| age.good_transactions),
+            address_transactions_last_hour: user.address_velocity.last_hour,
+            address_users: user.address_velocity.users_last_day,
+            cardholder_billing_name_match: request.cardholder_billing_match(),
+            cardholder_email_name_match: request.cardholder_email_match(),
+            phone_country: user.phone.as_ref().and_then(|phone| phone.country.clone()),
+            phone_line_type: user.phone.as_ref().and_then(|phone| phone.line_type),
+            card_type: user.card_bin.as_ref().and_then(|bin| bin.card_type.clone()),
+            card_prepaid: user.card_bin.as_ref().is_some_and(|bin| bin.is_prepaid),
+            card_business: user.card_bin.as_ref().is_some_and(|bin| bin.is_business),
+            card_virtual: user.card_bin.as_ref().is_some_and(|bin| bin.is_virtual),
+            card_testing_bins: user.card_testing.distinct_bins,
+            card_testing_declined_small: user.card_testing.declined_small_attempts,
+            card_testing_sequential: user.card_testing.sequential_cards,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_capture() {
+        let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "  " },
+            "event": { "type": "purchase" },
+            "order": { "amount": 1250.0, "currency": "EUR" },
+            "billing": { "country": "DE" },
+            "credit_card": { "country": "US", "cvv_result": "N", "was_3d_secure_successful": false }
+        }))
+        .unwrap();
+
+        let snapshot = FeatureSnapshot::capture(&request, &UserSignals::default());
+        assert_eq!(snapshot.order_amount, Some(1250.0));
+        assert_eq!(snapshot.billing_country.as_deref(), Some("DE"));
+        assert_eq!(snapshot.shipping_country, None);
+        assert_eq!(snapshot.card_country.as_deref(), Some("US"));
+        assert_eq!(snapshot.cvv_result.as_deref(), Some("N"));
+        assert_eq!(snapshot.three_d_secure_successful, Some(false));
+        assert!(!snapshot.has_user_agent);
+        assert_eq!(snapshot.active_user_flags, 0);
+        assert_eq!(snapshot.local_hour, None);
+    }
+}
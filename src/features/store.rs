@@ -0,0 +1,1071 @@
+//! PostgreSQL-backed feature store
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::UserProfile;
+use crate::{
+    database::Tenant,
+    models::transaction::{TransactionRequest, is_reserved_ip},
+    scoring::{
+        ADDRESS_VELOCITY_WINDOW_HOURS, AddressVelocity, CARD_TESTING_WINDOW_MINUTES, CardAttempt,
+        CardTesting, GeoTravel, IP_VELOCITY_WINDOW_HOURS, IpVelocity, LocalTime,
+    },
+    utils::{
+        address::generate_address_hash,
+        geo::{
+            GeoIpDatabase, IpAddressInfo, distance_km, get_location_risk_score, local_time,
+            location_risk_reasons,
+        },
+        ip::SubnetPrefixes,
+    },
+};
+
+/// Minimum share of a user's purchases an hour or country needs to count as "usual"
+const USUAL_SHARE_THRESHOLD: f64 = 0.1;
+
+/// How long a GeoIP enrichment is cached in `ip_risk_cache`
+const IP_ENRICHMENT_TTL_HOURS: i32 = 24;
+
+/// Caches the GeoIP enrichment of an IP address, unless a live entry already exists
+const UPSERT_IP_ENRICHMENT_SQL: &str = r#"
+INSERT INTO ip_risk_cache (ip_address, risk_score, risk_reasons, location_data, traits_data, expires_at)
+VALUES ($1::inet, $2, $3, $4, $5, NOW() + make_interval(hours => $6))
+ON CONFLICT (ip_address) DO UPDATE SET
+    risk_score = EXCLUDED.risk_score,
+    risk_reasons = EXCLUDED.risk_reasons,
+    location_data = EXCLUDED.location_data,
+    traits_data = ip_risk_cache.traits_data || EXCLUDED.traits_data,
+    expires_at = EXCLUDED.expires_at
+WHERE ip_risk_cache.expires_at <= NOW()
+"#;
+
+/// Counts an account's transactions from a subnet
+///
+/// `$2` is the subnet in CIDR notation and `$3` the counting window in hours.
+const IP_VELOCITY_SQL: &str = r#"
+SELECT COUNT(*) FILTER (WHERE created_at >= NOW() - INTERVAL '1 hour'),
+       COUNT(*),
+       COUNT(DISTINCT user_id)
+FROM transactions
+WHERE account_id = $1
+  AND ip_address <<= $2::cidr
+  AND created_at >= NOW() - make_interval(hours => $3)
+"#;
+
+/// Counts an account's transactions with an address
+///
+/// `$2` holds the hashes of the addresses in normal form and `$3` is the counting window in
+/// hours.
+const ADDRESS_VELOCITY_SQL: &str = r#"
+SELECT COUNT(DISTINCT t.id) FILTER (WHERE t.created_at >= NOW() - INTERVAL '1 hour'),
+       COUNT(DISTINCT t.id),
+       COUNT(DISTINCT t.user_id)
+FROM transactions t
+JOIN transaction_addresses ta ON ta.transaction_id = t.id
+JOIN addresses a ON a.id = ta.address_id
+WHERE t.account_id = $1
+  AND a.account_id = $1
+  AND a.address_hash = ANY($2)
+  AND t.created_at >= NOW() - make_interval(hours => $3)
+"#;
+
+/// Most card payments from a device or IP address read for card testing
+const MAX_CARD_ATTEMPTS: i64 = 500;
+
+/// Issuer ID number, last digits, order amount, and whether declined of a card payment
+type CardAttemptRow = (Option<String>, Option<String>, Option<f64>, bool);
+
+/// Lists the card payments made to an account from a device or IP address
+///
+/// `$2` is the IP address, or `NULL` to match on the device alone, `$3` the device fingerprint,
+/// `$4` the counting window in minutes, and `$5` the most payments to list. A payment counts as
+/// declined if it was rejected or failed the card's security code or 3-D Secure check.
+const CARD_ATTEMPTS_SQL: &str = r#"
+SELECT c.issuer_id_number,
+       c.last_digits,
+       o.amount,
+       COALESCE(t.disposition = 'reject'
+                OR upper(c.cvv_result) = 'N'
+                OR c.was_3d_secure_successful = false, false)
+FROM transactions t
+JOIN transaction_credit_cards tc ON tc.transaction_id = t.id
+JOIN credit_cards c ON c.id = tc.credit_card_id
+LEFT JOIN orders o ON o.transaction_id = t.id
+WHERE t.account_id = $1
+  AND t.created_at >= NOW() - make_interval(mins => $4)
+  AND (t.ip_address = $2::inet
+       OR EXISTS (SELECT 1
+                  FROM transaction_devices td
+                  JOIN devices d ON d.id = td.device_id
+                  WHERE td.transaction_id = t.id
+                    AND d.account_id = $1
+                    AND d.fingerprint_hash = $3))
+ORDER BY t.created_at DESC
+LIMIT $5
+"#;
+
+/// Recomputes every profile from purchases inside the lookback window
+///
+/// `$1` is the lookback window in days and `$2` the usual-share threshold. Billing addresses
+/// provide the purchase country; devices are counted through the transaction junction table.
+/// Deleted users and devices are left out, so their profiles are dropped on the next run.
+const REFRESH_USER_PROFILES_SQL: &str = r#"
+WITH recent AS (
+    SELECT t.id, t.account_id, t.user_id, t.event_time, t.local_hour, o.amount::float8 AS amount
+    FROM transactions t
+    JOIN users u ON u.id = t.user_id AND u.deleted_at IS NULL
+    LEFT JOIN orders o ON o.transaction_id = t.id
+    WHERE t.user_id IS NOT NULL
+      AND t.event_type IN ('purchase', 'recurring_purchase')
+      AND t.event_time >= NOW() - make_interval(days => $1)
+),
+totals AS (
+    SELECT user_id,
+           account_id,
+           COUNT(*) AS transaction_count,
+           AVG(amount) AS avg_order_amount,
+           STDDEV_SAMP(amount) AS order_amount_stddev
+    FROM recent
+    GROUP BY user_id, account_id
+),
+hours AS (
+    SELECT user_id, EXTRACT(HOUR FROM event_time AT TIME ZONE 'UTC')::int AS hour, COUNT(*) AS n
+    FROM recent
+    GROUP BY 1, 2
+),
+usual_hours AS (
+    SELECT h.user_id, ARRAY_AGG(h.hour ORDER BY h.hour) AS hours
+    FROM hours h
+    JOIN totals t USING (user_id)
+    WHERE h.n::float8 / t.transaction_count >= $2
+    GROUP BY h.user_id
+),
+local_hours AS (
+    SELECT user_id,
+           local_hour::int AS hour,
+           COUNT(*) AS n,
+           SUM(COUNT(*)) OVER (PARTITION BY user_id) AS located
+    FROM recent
+    WHERE local_hour IS NOT NULL
+    GROUP BY 1, 2
+),
+usual_local_hours AS (
+    SELECT user_id, ARRAY_AGG(hour ORDER BY hour) AS hours
+    FROM local_hours
+    WHERE n::float8 / located >= $2
+    GROUP BY user_id
+),
+countries AS (
+    SELECT r.user_id, a.country::text AS country, COUNT(*) AS n
+    FROM recent r
+    JOIN transaction_addresses ta ON ta.transaction_id = r.id AND ta.address_type = 'billing'
+    JOIN addresses a ON a.id = ta.address_id
+    WHERE a.country IS NOT NULL
+    GROUP BY 1, 2
+),
+usual_countries AS (
+    SELECT c.user_id, ARRAY_AGG(c.country ORDER BY c.n DESC, c.country) AS countries
+    FROM countries c
+    JOIN totals t USING (user_id)
+    WHERE c.n::float8 / t.transaction_count >= $2
+    GROUP BY c.user_id
+),
+devices AS (
+    SELECT r.user_id, COUNT(DISTINCT td.device_id)::int AS device_count
+    FROM recent r
+    JOIN transaction_devices td ON td.transaction_id = r.id
+    JOIN devices dv ON dv.id = td.device_id AND dv.deleted_at IS NULL
+    GROUP BY r.user_id
+)
+INSERT INTO user_profiles (
+    user_id, account_id, transaction_count, avg_order_amount, order_amount_stddev,
+    usual_purchase_hours, usual_local_purchase_hours, usual_countries, typical_device_count,
+    computed_at
+)
+SELECT t.user_id, t.account_id, t.transaction_count, t.avg_order_amount, t.order_amount_stddev,
+       COALESCE(uh.hours, '{}'), COALESCE(ulh.hours, '{}'), COALESCE(uc.countries, '{}'),
+       COALESCE(d.device_count, 0), NOW()
+FROM totals t
+LEFT JOIN usual_hours uh USING (user_id)
+LEFT JOIN usual_local_hours ulh USING (user_id)
+LEFT JOIN usual_countries uc USING (user_id)
+LEFT JOIN devices d USING (user_id)
+ON CONFLICT (user_id) DO UPDATE SET
+    account_id = EXCLUDED.account_id,
+    transaction_count = EXCLUDED.transaction_count,
+    avg_order_amount = EXCLUDED.avg_order_amount,
+    order_amount_stddev = EXCLUDED.order_amount_stddev,
+    usual_purchase_hours = EXCLUDED.usual_purchase_hours,
+    usual_local_purchase_hours = EXCLUDED.usual_local_purchase_hours,
+    usual_countries = EXCLUDED.usual_countries,
+    typical_device_count = EXCLUDED.typical_device_count,
+    computed_at = EXCLUDED.computed_at
+"#;
+
+/// Read access to precomputed risk features
+#[derive(Debug, Clone)]
+pub struct FeatureStore {
+    pool: PgPool,
+    geoip: GeoIpDatabase,
+    velocity_subnets: SubnetPrefixes,
+}
+
+impl FeatureStore {
+    /// Create a feature store backed by the given pool, without IP geolocation, counting IP
+    /// velocity over the default subnets
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            geoip: GeoIpDatabase::disabled(),
+            velocity_subnets: SubnetPrefixes::default(),
+        }
+    }
+
+    /// Locate IP addresses with `geoip`
+    pub fn with_geoip(mut self, geoip: GeoIpDatabase) -> Self {
+        self.geoip = geoip;
+        self
+    }
+
+    /// Count IP velocity over `subnets`
+    pub fn with_velocity_subnets(mut self, subnets: SubnetPrefixes) -> Self {
+        self.velocity_subnets = subnets;
+        self
+    }
+
+    /// ISO 3166-1 alpha-2 code of the country `ip_address` is used in, if it can be located
+    pub fn get_ip_country(&self, ip_address: &str) -> Option<String> {
+        self.geoip.lookup(ip_address.parse().ok()?)?.country
+    }
+
+    /// Locate `ip_address` and cache its location and location risk in `ip_risk_cache`
+    ///
+    /// Reserved addresses and addresses the database has no record of are not cached.
+    pub async fn enrich_ip(&self, ip_address: &str) -> sqlx::Result<Option<IpAddressInfo>> {
+        let Some(info) = ip_address
+            .parse::<IpAddr>()
+            .ok()
+            .filter(|ip| !is_reserved_ip(*ip))
+            .and_then(|ip| self.geoip.lookup(ip))
+        else {
+            return Ok(None);
+        };
+        let reasons: Vec<_> = location_risk_reasons(&info)
+            .into_iter()
+            .map(|(score, reason)| json!({ "score": score, "reason": reason }))
+            .collect();
+        sqlx::query(UPSERT_IP_ENRICHMENT_SQL)
+            .bind(ip_address)
+            .bind(get_location_risk_score(&info))
+            .bind(json!(reasons))
+            .bind(json!(info))
+            .bind(json!({
+                "is_anonymous_proxy": info.is_anonymous_proxy,
+                "is_satellite_provider": info.is_satellite_provider,
+            }))
+            .bind(IP_ENRICHMENT_TTL_HOURS)
+            .execute(&self.pool)
+            .await?;
+        Ok(Some(info))
+    }
+
+    /// Location of `ip_address`, enriching it on the way with [`FeatureStore::enrich_ip`]
+    ///
+    /// Failures to cache the enrichment are logged and do not hold up scoring.
+    pub async fn locate_ip(&self, ip_address: &str) -> Option<IpAddressInfo> {
+        match self.enrich_ip(ip_address).await {
+            Ok(info) => info,
+            Err(e) => {
+                tracing::warn!(error = %e, ip_address, "IP enrichment failed");
+                self.geoip.lookup(ip_address.parse().ok()?)
+            },
+        }
+    }
+
+    /// Travel of a user from their last located transaction to `location` at `at`
+    ///
+    /// Failures are logged and do not hold up scoring. Locations recorded after `at`, as when
+    /// events are sent out of order, are not compared against.
+    pub async fn get_travel(
+        &self,
+        user_id: Option<Uuid>,
+        location: Option<&IpAddressInfo>,
+        at: DateTime<Utc>,
+    ) -> Option<GeoTravel> {
+        let to = location?.coordinates()?;
+        let (latitude, longitude, located_at): (f64, f64, DateTime<Utc>) = sqlx::query_as(
+            "SELECT latitude, longitude, located_at FROM user_locations WHERE user_id = $1",
+        )
+        .bind(user_id?)
+        .fetch_optional(&self.pool)
+        .await
+        .inspect_err(|e| tracing::warn!(error = %e, "User location lookup failed"))
+        .ok()??;
+        (located_at <= at).then(|| GeoTravel {
+            distance_km: distance_km((latitude, longitude), to),
+            hours: (at - located_at).num_seconds() as f64 / 3600.0,
+        })
+    }
+
+    /// Local time at `at` in the time zone of `location`, with the hours the user usually
+    /// purchases in there
+    ///
+    /// Failures are logged and do not hold up scoring.
+    pub async fn get_local_time(
+        &self,
+        user_id: Option<Uuid>,
+        location: Option<&IpAddressInfo>,
+        at: DateTime<Utc>,
+    ) -> Option<LocalTime> {
+        let time_zone = location?.time_zone.as_deref()?;
+        let time = local_time(&self.pool, time_zone, at)
+            .await
+            .inspect_err(|e| tracing::warn!(error = %e, time_zone, "Local time lookup failed"))
+            .ok()??;
+        let profile = match user_id {
+            Some(user_id) => self
+                .get_user_profile(user_id)
+                .await
+                .inspect_err(|e| tracing::warn!(error = %e, %user_id, "User profile lookup failed"))
+                .ok()
+                .flatten(),
+            None => None,
+        };
+        let usual_hours = profile
+            .filter(UserProfile::has_baseline)
+            .map(|profile| {
+                profile
+                    .usual_local_purchase_hours
+                    .into_iter()
+                    .filter_map(|hour| u32::try_from(hour).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(LocalTime { time, usual_hours })
+    }
+
+    /// Transactions of an account so far from the subnet of `ip_address`
+    ///
+    /// Reserved addresses, which may be shared by any number of clients behind a proxy, have
+    /// no velocity. Failures are logged and do not hold up scoring.
+    pub async fn ip_velocity(&self, tenant: Tenant, ip_address: &str) -> IpVelocity {
+        let Some(ip) = ip_address
+            .parse::<IpAddr>()
+            .ok()
+            .filter(|ip| !is_reserved_ip(*ip))
+        else {
+            return IpVelocity::default();
+        };
+        let subnet = self.velocity_subnets.subnet(ip);
+        let counts: sqlx::Result<(i64, i64, i64)> = sqlx::query_as(IP_VELOCITY_SQL)
+            .bind(tenant.id())
+            .bind(&subnet)
+            .bind(IP_VELOCITY_WINDOW_HOURS as i32)
+            .fetch_one(&self.pool)
+            .await;
+        match counts {
+            Ok((last_hour, last_day, users_last_day)) => IpVelocity {
+                subnet,
+                last_hour,
+                last_day,
+                users_last_day,
+            },
+            Err(e) => {
+                tracing::warn!(error = %e, subnet, "IP velocity lookup failed");
+                IpVelocity {
+                    subnet,
+                    ..IpVelocity::default()
+                }
+            },
+        }
+    }
+
+    /// Transactions of an account so far with the billing or shipping address of `request`
+    ///
+    /// Addresses are matched in normal form, so rewritten or abbreviated variants of one address
+    /// count together; addresses without a street have no velocity. Failures are logged and do
+    /// not hold up scoring.
+    pub async fn address_velocity(
+        &self,
+        tenant: Tenant,
+        request: &TransactionRequest,
+    ) -> AddressVelocity {
+        let hashes: Vec<String> = [
+            request.billing.as_ref(),
+            request.shipping.as_ref().map(|shipping| &shipping.address),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(generate_address_hash)
+        .collect();
+        if hashes.is_empty() {
+            return AddressVelocity::default();
+        }
+        let counts: sqlx::Result<(i64, i64, i64)> = sqlx::query_as(ADDRESS_VELOCITY_SQL)
+            .bind(tenant.id())
+            .bind(&hashes)
+            .bind(ADDRESS_VELOCITY_WINDOW_HOURS as i32)
+            .fetch_one(&self.pool)
+            .await;
+        match counts {
+            Ok((last_hour, last_day, users_last_day)) => AddressVelocity {
+                last_hour,
+                last_day,
+                users_last_day,
+            },
+            Err(e) => {
+                tracing::warn!(error = %e, "Address velocity lookup failed");
+                AddressVelocity::default()
+            },
+        }
+    }
+
+    /// Cards tried at an account from the device with `device_fingerprint` or the IP address
+    /// of `request` in the last [`CARD_TESTING_WINDOW_MINUTES`], counting the request's own card
+    ///
+    /// Reserved IP addresses, which may be shared by any number of clients behind a proxy, are
+    /// matched on the device alone. Failures are logged and do not hold up scoring.
+    pub async fn card_testing(
+        &self,
+        tenant: Tenant,
+        request: &TransactionRequest,
+        device_fingerprint: &str,
+    ) -> CardTesting {
+        let ip_address = request
+            .device
+            .ip_address
+            .parse::<IpAddr>()
+            .ok()
+            .filter(|ip| !is_reserved_ip(*ip))
+            .map(|ip| ip.to_string());
+        let rows: sqlx::Result<Vec<CardAttemptRow>> = sqlx::query_as(CARD_ATTEMPTS_SQL)
+            .bind(tenant.id())
+            .bind(ip_address)
+            .bind(device_fingerprint)
+            .bind(CARD_TESTING_WINDOW_MINUTES as i32)
+            .bind(MAX_CARD_ATTEMPTS)
+            .fetch_all(&self.pool)
+            .await;
+        let mut attempts: Vec<CardAttempt> = match rows {
+            Ok(rows) => rows
+                .into_iter()
+                .map(
+                    |(issuer_id_number, last_digits, amount, declined)| CardAttempt {
+                        issuer_id_number,
+                        last_digits,
+                        amount,
+                        declined,
+                    },
+                )
+                .collect(),
+            Err(e) => {
+                tracing::warn!(error = %e, "Card testing lookup failed");
+                return CardTesting::default();
+            },
+        };
+        attempts.extend(CardAttempt::from_request(request));
+        CardTesting::from_attempts(&attempts)
+    }
+
+    /// Remember `location` as where a user's transaction at `at` came from
+    ///
+    /// Only the latest location is kept. Failures are logged and do not fail the transaction.
+    pub async fn record_location(
+        &self,
+        user_id: Option<Uuid>,
+        location: Option<&IpAddressInfo>,
+        at: DateTime<Utc>,
+    ) {
+        let (Some(user_id), Some((latitude, longitude))) =
+            (user_id, location.and_then(IpAddressInfo::coordinates))
+        else {
+            return;
+        };
+        let recorded = sqlx::query(
+            r#"
+            INSERT INTO user_locations (user_id, latitude, longitude, located_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id) DO UPDATE SET
+                latitude = EXCLUDED.latitude,
+                longitude = EXCLUDED.longitude,
+                located_at = EXCLUDED.located_at
+            WHERE user_locations.located_at <= EXCLUDED.located_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(latitude)
+        .bind(longitude)
+        .bind(at)
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = recorded {
+            tracing::warn!(error = %e, %user_id, "Recording user location failed");
+        }
+    }
+
+    /// Fetch the behavioral profile for a user, if one has been computed
+    ///
+    /// Profiles of deleted users are never returned, even before the nightly refresh drops them.
+    pub async fn get_user_profile(&self, user_id: Uuid) -> sqlx::Result<Option<UserProfile>> {
+        sqlx::query_as::<_, UserProfile>(
+            r#"
+            SELECT p.user_id, p.account_id, p.transaction_count, p.avg_order_amount,
+                   p.order_amount_stddev, p.usual_purchase_hours, p.usual_local_purchase_hours,
+                   p.usual_countries, p.typical_device_count, p.computed_at
+            FROM user_profiles p
+            JOIN users u ON u.id = p.user_id AND u.deleted_at IS NULL
+            WHERE p.user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Recompute all user profiles from the last `lookback_days` of purchases
+    ///
+    /// Profiles of users with no purchases in the window are dropped so stale baselines never
+    /// feed into scoring. Returns the number of profiles written.
+    pub async fn refresh_user_profiles(&self, lookback_days: u32) -> anyhow::Result<u64> {
+        let lookback_days = i32::try_from(lookback_days)?;
+        let mut tx = self.pool.begin().await?;
+
+        let written = sqlx::query(REFRESH_USER_PROFILES_SQL)
+            .bind(lookback_days)
+            .bind(USUAL_SHARE_THRESHOLD)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        // NOW() is fixed for the whole transaction, so anything older was not rewritten above
+        let removed = sqlx::query("DELETE FROM user_profiles WHERE computed_at < NOW()")
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+
+        tracing::info!(
+            profiles_written = written,
+            profiles_removed = removed,
+            lookback_days,
+            "User profiles refreshed"
+        );
+
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::{
+        database::repositories::{
+            AccountRepo, DeviceRepo, NewCreditCard, NewDevice, NewTransaction, TransactionRepo,
+            UserRepo,
+        },
+        models::{
+            account::SubscriptionTier,
+            transaction::{
+                Address, CreditCard, Disposition, EventType, MatchKeys, Order, RiskLevel,
+            },
+        },
+        test_support::{create_account, test_pool},
+        utils::geo::tests::mmdb,
+    };
+
+    #[tokio::test]
+    async fn test_enriched_ips_are_cached_with_their_location_risk() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let path = std::env::temp_dir().join(format!("geoip-{}.mmdb", Uuid::new_v4()));
+        std::fs::write(&path, mmdb("DE")).unwrap();
+        let geoip = GeoIpDatabase::new(Some(path.clone()));
+        geoip.reload().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let store = FeatureStore::new(pool.clone()).with_geoip(geoip);
+
+        let octets = Uuid::new_v4().into_bytes();
+        let ip = format!("45.{}.{}.{}", octets[0], octets[1], octets[2]);
+        assert_eq!(store.get_ip_country(&ip).as_deref(), Some("DE"));
+        assert_eq!(store.get_ip_country("not an ip"), None);
+        assert_eq!(store.enrich_ip("10.0.0.1").await.unwrap(), None);
+        assert_eq!(store.enrich_ip("200.1.1.1").await.unwrap(), None);
+
+        let info = store.enrich_ip(&ip).await.unwrap().unwrap();
+        let (risk_score, country, proxy): (Option<f64>, Option<String>, Option<String>) =
+            sqlx::query_as(
+                "SELECT risk_score, location_data->>'country', traits_data->>'is_anonymous_proxy'
+                 FROM ip_risk_cache WHERE ip_address = $1::inet AND expires_at > NOW()",
+            )
+            .bind(&ip)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(risk_score, Some(get_location_risk_score(&info)));
+        assert_eq!(country.as_deref(), Some("DE"));
+        assert_eq!(proxy.as_deref(), Some("true"));
+
+        // A live entry is kept as it is
+        assert_eq!(store.locate_ip(&ip).await, Some(info));
+
+        sqlx::query("DELETE FROM ip_risk_cache WHERE ip_address = $1::inet")
+            .bind(&ip)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_travel_is_measured_from_the_last_recorded_location() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Free).await;
+        let user_id = UserRepo::upsert_by_external_id(&pool, tenant, "u-1")
+            .await
+            .unwrap();
+        let store = FeatureStore::new(pool.clone());
+        let berlin = IpAddressInfo {
+            latitude: Some(52.52),
+            longitude: Some(13.405),
+            ..IpAddressInfo::default()
+        };
+        let new_york = IpAddressInfo {
+            latitude: Some(40.713),
+            longitude: Some(-74.006),
+            ..IpAddressInfo::default()
+        };
+        let now = Utc::now();
+
+        assert_eq!(store.get_travel(user_id, Some(&berlin), now).await, None);
+        store
+            .record_location(user_id, Some(&berlin), now - Duration::hours(2))
+            .await;
+        // Unlocated transactions and older events leave the last location as it is
+        store.record_location(user_id, None, now).await;
+        store
+            .record_location(user_id, Some(&new_york), now - Duration::hours(3))
+            .await;
+
+        let travel = store
+            .get_travel(user_id, Some(&new_york), now)
+            .await
+            .unwrap();
+        assert!((travel.distance_km - 6_385.0).abs() < 10.0);
+        assert_eq!(travel.hours, 2.0);
+        assert_eq!(
+            store
+                .get_travel(user_id, Some(&new_york), now - Duration::hours(3))
+                .await,
+            None
+        );
+        assert_eq!(store.get_travel(user_id, None, now).await, None);
+
+        AccountRepo::delete(&pool, tenant.id()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ip_velocity_is_counted_per_subnet() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Free).await;
+        let account_id = tenant.id();
+        for (external_id, ip_address) in [
+            ("u-1", "2001:db8:0:1::1"),
+            ("u-2", "2001:db8:0:1:ffff::2"),
+            ("u-2", "2001:db8:0:2::1"),
+            ("u-3", "198.51.100.1"),
+        ] {
+            let user_id = UserRepo::upsert_by_external_id(&pool, tenant, external_id)
+                .await
+                .unwrap();
+            TransactionRepo::insert(
+                &pool,
+                NewTransaction {
+                    tenant,
+                    user_id,
+                    external_transaction_id: None,
+                    risk_score: 10.0,
+                    risk_level: RiskLevel::Low,
+                    disposition: Disposition::Accept,
+                    event_type: EventType::Purchase,
+                    shop_id: None,
+                    event_time: Utc::now(),
+                    original_transaction_id: None,
+                    ip_address,
+                    asn: None,
+                    isp: None,
+                    local_hour: None,
+                    device_data: serde_json::json!({}),
+                    custom_inputs: serde_json::json!({}),
+                    warnings: &[],
+                    raw_request: serde_json::json!({}),
+                    match_keys: &MatchKeys::default(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let store = FeatureStore::new(pool.clone());
+        let velocity = store.ip_velocity(tenant, "2001:db8:0:1::abcd").await;
+        assert_eq!(velocity.subnet, "2001:db8:0:1::/64");
+        assert_eq!(
+            (
+                velocity.last_hour,
+                velocity.last_day,
+                velocity.users_last_day
+            ),
+            (2, 2, 2)
+        );
+        assert_eq!(store.ip_velocity(tenant, "198.51.100.2").await.last_day, 0);
+        assert_eq!(
+            store.ip_velocity(tenant, "10.0.0.1").await,
+            IpVelocity::default()
+        );
+
+        let wide =
+            FeatureStore::new(pool.clone()).with_velocity_subnets(SubnetPrefixes::new(24, 48));
+        assert_eq!(wide.ip_velocity(tenant, "198.51.100.2").await.last_day, 1);
+        assert_eq!(wide.ip_velocity(tenant, "2001:db8::1").await.last_day, 3);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_address_velocity_counts_variants_of_an_address() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Free).await;
+        let account_id = tenant.id();
+        let address = |line: &str, postal: &str| Address {
+            address: Some(line.to_string()),
+            postal: Some(postal.to_string()),
+            country: Some("US".to_string()),
+            ..Address::default()
+        };
+        for (external_id, line, postal) in [
+            ("u-1", "123 Main St.", "10001"),
+            ("u-2", "123 MAIN STREET", "10001-1234"),
+            ("u-2", "125 Main Street", "10001"),
+        ] {
+            let user_id = UserRepo::upsert_by_external_id(&pool, tenant, external_id)
+                .await
+                .unwrap();
+            let record = TransactionRepo::insert(
+                &pool,
+                NewTransaction {
+                    tenant,
+                    user_id,
+                    external_transaction_id: None,
+                    risk_score: 10.0,
+                    risk_level: RiskLevel::Low,
+                    disposition: Disposition::Accept,
+                    event_type: EventType::Purchase,
+                    shop_id: None,
+                    event_time: Utc::now(),
+                    original_transaction_id: None,
+                    ip_address: "198.51.100.1",
+                    asn: None,
+                    isp: None,
+                    local_hour: None,
+                    device_data: serde_json::json!({}),
+                    custom_inputs: serde_json::json!({}),
+                    warnings: &[],
+                    raw_request: serde_json::json!({}),
+                    match_keys: &MatchKeys::default(),
+                },
+            )
+            .await
+            .unwrap();
+            let address = address(line, postal);
+            let address_id = TransactionRepo::insert_address(
+                &pool,
+                tenant,
+                user_id,
+                &address,
+                generate_address_hash(&address).as_deref(),
+                None,
+            )
+            .await
+            .unwrap();
+            TransactionRepo::link_address(&pool, record.id, address_id, "shipping", None)
+                .await
+                .unwrap();
+        }
+
+        let request = |billing: Address| -> TransactionRequest {
+            let mut request: TransactionRequest = serde_json::from_value(serde_json::json!({
+                "device": { "ip_address": "198.51.100.1" },
+                "event": { "type": "purchase" }
+            }))
+            .unwrap();
+            request.billing = Some(billing);
+            request
+        };
+        let store = FeatureStore::new(pool.clone());
+        let velocity = store
+            .address_velocity(tenant, &request(address("123 main st", "10001")))
+            .await;
+        assert_eq!(
+            velocity,
+            AddressVelocity {
+                last_hour: 2,
+                last_day: 2,
+                users_last_day: 2,
+            }
+        );
+        assert_eq!(
+            store
+                .address_velocity(tenant, &request(address("127 Main St", "10001")))
+                .await,
+            AddressVelocity::default()
+        );
+        // Without a street there is nothing to match on
+        assert_eq!(
+            store
+                .address_velocity(tenant, &request(address("", "10001")))
+                .await,
+            AddressVelocity::default()
+        );
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_card_testing_counts_cards_from_the_device_or_ip_address() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Free).await;
+        let account_id = tenant.id();
+        let device_id = DeviceRepo::upsert(
+            &pool,
+            NewDevice {
+                tenant,
+                user_id: None,
+                fingerprint_hash: "fp-tester",
+                ip_address: "10.0.0.5",
+                asn: None,
+                isp: None,
+                user_agent: None,
+                user_agent_details: None,
+                accept_language: None,
+                ja3: None,
+                ja4: None,
+                header_order_hash: None,
+                session_id: None,
+                session_age: None,
+                traits_data: None,
+            },
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        // Three cards of one BIN counted up from the device behind a proxy, two rejected, one
+        // card from the IP address, and one from elsewhere
+        for (ip_address, on_device, bin, last_digits, amount, disposition) in [
+            ("10.0.0.5", true, "411111", "1234", 1.0, Disposition::Reject),
+            ("10.0.0.5", true, "411111", "1242", 1.0, Disposition::Reject),
+            ("10.0.0.5", true, "411111", "1257", 1.0, Disposition::Accept),
+            (
+                "198.51.100.7",
+                false,
+                "510510",
+                "5100",
+                2.0,
+                Disposition::Accept,
+            ),
+            (
+                "198.51.100.8",
+                false,
+                "371449",
+                "8431",
+                1.0,
+                Disposition::Reject,
+            ),
+        ] {
+            let record = TransactionRepo::insert(
+                &pool,
+                NewTransaction {
+                    tenant,
+                    user_id: None,
+                    external_transaction_id: None,
+                    risk_score: 10.0,
+                    risk_level: RiskLevel::Low,
+                    disposition,
+                    event_type: EventType::Purchase,
+                    shop_id: None,
+                    event_time: Utc::now(),
+                    original_transaction_id: None,
+                    ip_address,
+                    asn: None,
+                    isp: None,
+                    local_hour: None,
+                    device_data: serde_json::json!({}),
+                    custom_inputs: serde_json::json!({}),
+                    warnings: &[],
+                    raw_request: serde_json::json!({}),
+                    match_keys: &MatchKeys::default(),
+                },
+            )
+            .await
+            .unwrap();
+            let card = CreditCard {
+                issuer_id_number: Some(bin.to_string()),
+                last_digits: Some(last_digits.to_string()),
+                ..CreditCard::default()
+            };
+            let card_id = TransactionRepo::insert_credit_card(
+                &pool,
+                NewCreditCard {
+                    tenant,
+                    user_id: None,
+                    card: &card,
+                    token_hash: None,
+                    bin: None,
+                    billing_name_match: None,
+                    email_name_match: None,
+                },
+            )
+            .await
+            .unwrap();
+            TransactionRepo::link_credit_card(&pool, record.id, card_id)
+                .await
+                .unwrap();
+            let order: Order =
+                serde_json::from_value(serde_json::json!({ "amount": amount, "currency": "USD" }))
+                    .unwrap();
+            TransactionRepo::insert_order(&pool, record.id, &order)
+                .await
+                .unwrap();
+            if on_device {
+                TransactionRepo::link_device(&pool, record.id, device_id)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let request = |ip_address: &str| -> TransactionRequest {
+            serde_json::from_value(serde_json::json!({
+                "device": { "ip_address": ip_address },
+                "event": { "type": "purchase" },
+                "credit_card": {
+                    "issuer_id_number": "400005",
+                    "last_digits": "0001",
+                    "cvv_result": "N"
+                },
+                "order": { "amount": 1.0, "currency": "USD" }
+            }))
+            .unwrap()
+        };
+        let store = FeatureStore::new(pool.clone());
+        let testing = store
+            .card_testing(tenant, &request("198.51.100.7"), "fp-tester")
+            .await;
+        assert_eq!(
+            testing,
+            CardTesting {
+                attempts: 5,
+                distinct_bins: 3,
+                distinct_cards: 5,
+                small_attempts: 5,
+                declined_small_attempts: 3,
+                sequential_cards: 3,
+            }
+        );
+        // A reserved address is shared behind the proxy, so only the device is matched
+        let testing = store
+            .card_testing(tenant, &request("10.0.0.5"), "fp-other")
+            .await;
+        assert_eq!((testing.attempts, testing.declined_small_attempts), (1, 1));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_local_time_is_compared_with_the_usual_local_hours() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Free).await;
+        let account_id = tenant.id();
+        let user_id = UserRepo::upsert_by_external_id(&pool, tenant, "u-1")
+            .await
+            .unwrap();
+        for local_hour in [21, 21, 21, 22, 22, 9] {
+            TransactionRepo::insert(
+                &pool,
+                NewTransaction {
+                    tenant,
+                    user_id,
+                    external_transaction_id: None,
+                    risk_score: 10.0,
+                    risk_level: RiskLevel::Low,
+                    disposition: Disposition::Accept,
+                    event_type: EventType::Purchase,
+                    shop_id: None,
+                    event_time: Utc::now(),
+                    original_transaction_id: None,
+                    ip_address: "198.51.100.1",
+                    asn: None,
+                    isp: None,
+                    local_hour: Some(local_hour),
+                    device_data: serde_json::json!({}),
+                    custom_inputs: serde_json::json!({}),
+                    warnings: &[],
+                    raw_request: serde_json::json!({}),
+                    match_keys: &MatchKeys::default(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let store = FeatureStore::new(pool.clone());
+        store.refresh_user_profiles(30).await.unwrap();
+        let tokyo = IpAddressInfo {
+            time_zone: Some("Asia/Tokyo".to_string()),
+            ..IpAddressInfo::default()
+        };
+        let at = "2025-06-13T18:30:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let local_time = store
+            .get_local_time(user_id, Some(&tokyo), at)
+            .await
+            .unwrap();
+        assert_eq!(local_time.time.to_rfc3339(), "2025-06-14T03:30:00+09:00");
+        assert_eq!(local_time.usual_hours, [9, 21, 22]);
+        assert!(local_time.is_unusual_hour());
+        let evening = store
+            .get_local_time(user_id, Some(&tokyo), at - Duration::hours(6))
+            .await
+            .unwrap();
+        assert!(!evening.is_unusual_hour());
+
+        // Anonymous users have no usual hours to compare against
+        let anonymous = store.get_local_time(None, Some(&tokyo), at).await.unwrap();
+        assert!(anonymous.usual_hours.is_empty());
+        let nowhere = IpAddressInfo {
+            time_zone: Some("Mars/Olympus_Mons".to_string()),
+            ..IpAddressInfo::default()
+        };
+        assert_eq!(
+            store.get_local_time(user_id, Some(&nowhere), at).await,
+            None
+        );
+        assert_eq!(store.get_local_time(user_id, None, at).await, None);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
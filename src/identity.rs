@@ -0,0 +1,35 @@
+//! Identity resolution between users
+//!
+//! Merchants that identify users inconsistently, by `external_user_id` on some events and
+//! `user_hash` on others, end up with several users for one person. A periodic pass links
+//! users that transacted with the same email address, card token, or device, so duplicates
+//! can be reviewed under `GET /v1/users/{user_id}/linked-users` and folded together with
+//! `POST /v1/users/{user_id}/merge`. Links are only suggestions: a shared device may well be
+//! a household's, so users are never merged automatically.
+
+use sqlx::PgPool;
+
+use crate::{
+    config::IdentityConfig, database::repositories::IdentityLinkRepo, scheduler::ScheduledTask,
+};
+
+/// Task that links users sharing emails, cards, or devices on `resolution_schedule`
+pub fn identity_resolution_task(pool: PgPool, config: IdentityConfig) -> ScheduledTask {
+    let schedule = config.resolution_schedule.clone();
+    ScheduledTask::new("identity_resolution", schedule, move || {
+        let (pool, config) = (pool.clone(), config.clone());
+        async move {
+            let links = resolve_identities(&pool, &config).await?;
+            if links > 0 {
+                tracing::info!(links, "Identity resolution linked users");
+            }
+            Ok(())
+        }
+    })
+}
+
+/// Run one identity resolution pass, returning the number of links written or refreshed
+pub async fn resolve_identities(pool: &PgPool, config: &IdentityConfig) -> sqlx::Result<u64> {
+    let lookback_days = i32::try_from(config.lookback_days).unwrap_or(i32::MAX);
+    IdentityLinkRepo::refresh(pool, lookback_days, config.max_users_per_entity).await
+}
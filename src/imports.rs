@@ -0,0 +1,471 @@
+//! Background import of customers' existing user bases and list entries
+//!
+//! `POST /v1/users/batch` only stores the submitted users; this worker imports them in chunks
+//! of `chunk_size`, one database transaction per chunk. Each chunk's users and the import's
+//! progress counts are committed together, so a worker that dies mid-import leaves the import
+//! resumable from its last committed chunk: once its heartbeat is stale, another worker picks
+//! it up where it stopped. A user that cannot be parsed, fails validation, or matches a deleted
+//! user is counted as failed without affecting the rest of its chunk.
+//!
+//! Entries submitted to `POST /v1/lists/{list_type}/import` are imported the same way, each
+//! chunk's valid entries written by a single statement. An entity listed twice in one chunk
+//! takes its last entry.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::{Acquire, PgPool, Postgres, Transaction};
+use tokio::task::JoinHandle;
+
+use crate::{
+    config::ImportsConfig,
+    database::{
+        Tenant,
+        repositories::{
+            ImportProgress, ImportedListEntry, ListImportProgress, ListImportRepo, ListRepo,
+            OutboxRepo, UserImportRepo, UserRepo,
+        },
+    },
+    models::{
+        list::{ListEntityType, ListEntryRequest, ListImport, ListImportError},
+        user::{ImportUser, UserImport, UserImportError},
+    },
+    outbox::{LIST_IMPORT_COMPLETED, USER_IMPORT_COMPLETED},
+    services::ListService,
+};
+
+/// Seconds without progress after which an import being processed is taken over
+const STALE_AFTER_SECS: f64 = 5.0 * 60.0;
+/// Most failed users listed on an import; the rest are only counted
+const MAX_REPORTED_ERRORS: i32 = 100;
+
+/// Spawn a background task that keeps processing pending imports
+pub fn spawn_user_import_worker(pool: PgPool, config: ImportsConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let idle = Duration::from_millis(config.poll_interval_ms);
+        loop {
+            match process_next_import(&pool, &config).await {
+                // Keep going while there is a backlog
+                Ok(true) => continue,
+                Ok(false) => {},
+                Err(e) => tracing::error!(error = %e, "User import processing failed"),
+            }
+            tokio::time::sleep(idle).await;
+        }
+    })
+}
+
+/// Run the oldest pending import to completion, returning `false` if there was none
+///
+/// Completing an import records a `user_import.completed` outbox event in the same
+/// transaction.
+pub async fn process_next_import(pool: &PgPool, config: &ImportsConfig) -> sqlx::Result<bool> {
+    let Some(import) = UserImportRepo::claim_next(pool, STALE_AFTER_SECS).await? else {
+        return Ok(false);
+    };
+    let tenant = Tenant::trusted(import.account_id);
+    let users = &import.users.0;
+    let resumed_at = usize::try_from(import.processed_users).unwrap_or(0);
+    let mut reported_errors = import.reported_errors;
+
+    let mut index = resumed_at;
+    for chunk in users
+        .get(resumed_at..)
+        .unwrap_or_default()
+        .chunks(config.chunk_size)
+    {
+        let mut tx = pool.begin().await?;
+        let mut progress = ImportProgress::default();
+        for user in chunk {
+            match import_user(&mut tx, tenant, user).await? {
+                Ok(true) => progress.created += 1,
+                Ok(false) => progress.updated += 1,
+                Err(message) => {
+                    progress.failed += 1;
+                    if reported_errors < MAX_REPORTED_ERRORS {
+                        reported_errors += 1;
+                        progress.errors.push(UserImportError { index, message });
+                    }
+                },
+            }
+            progress.processed += 1;
+            index += 1;
+        }
+        UserImportRepo::record_progress(&mut *tx, import.id, &progress).await?;
+        tx.commit().await?;
+    }
+
+    let mut tx = pool.begin().await?;
+    let finished = UserImport::from(UserImportRepo::complete(&mut *tx, import.id).await?);
+    let payload = serde_json::to_value(&finished).unwrap_or_default();
+    OutboxRepo::insert(
+        &mut *tx,
+        import.account_id,
+        USER_IMPORT_COMPLETED,
+        import.id,
+        payload,
+    )
+    .await?;
+    tx.commit().await?;
+    tracing::info!(
+        import_id = %import.id,
+        account_id = %import.account_id,
+        created = finished.created_users,
+        updated = finished.updated_users,
+        failed = finished.failed_users,
+        "User import completed"
+    );
+    Ok(true)
+}
+
+/// Import one submitted user under a savepoint, returning whether it was created, or why it
+/// could not be imported
+async fn import_user(
+    tx: &mut Transaction<'_, Postgres>,
+    tenant: Tenant,
+    user: &serde_json::Value,
+) -> sqlx::Result<Result<bool, String>> {
+    let user = match ImportUser::deserialize(user) {
+        Ok(user) => user,
+        Err(e) => return Ok(Err(format!("Invalid user: {e}"))),
+    };
+    if let Err(message) = user.validate() {
+        return Ok(Err(message));
+    }
+
+    let mut savepoint = tx.begin().await?;
+    match UserRepo::import(&mut *savepoint, tenant, &user).await {
+        Ok(Some(created)) => {
+            savepoint.commit().await?;
+            Ok(Ok(created))
+        },
+        Ok(None) => {
+            savepoint.rollback().await?;
+            Ok(Err(format!(
+                "user {} has been deleted or merged into another",
+                user.external_user_id.trim()
+            )))
+        },
+        Err(e) => {
+            // A broken connection fails the rollback too, stopping the import for a retry
+            savepoint.rollback().await?;
+            tracing::warn!(error = %e, "Imported user could not be stored");
+            Ok(Err("user could not be stored".to_string()))
+        },
+    }
+}
+
+/// Spawn a background task that keeps processing pending list imports
+pub fn spawn_list_import_worker(
+    pool: PgPool,
+    lists: ListService,
+    config: ImportsConfig,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let idle = Duration::from_millis(config.poll_interval_ms);
+        loop {
+            match process_next_list_import(&pool, &lists, &config).await {
+                // Keep going while there is a backlog
+                Ok(true) => continue,
+                Ok(false) => {},
+                Err(e) => tracing::error!(error = %e, "List import processing failed"),
+            }
+            tokio::time::sleep(idle).await;
+        }
+    })
+}
+
+/// Run the oldest pending list import to completion, returning `false` if there was none
+///
+/// The cached entries of each chunk's entities are dropped once the chunk is committed.
+/// Completing an import records a `list_import.completed` outbox event in the same
+/// transaction.
+pub async fn process_next_list_import(
+    pool: &PgPool,
+    lists: &ListService,
+    config: &ImportsConfig,
+) -> sqlx::Result<bool> {
+    let Some(import) = ListImportRepo::claim_next(pool, STALE_AFTER_SECS).await? else {
+        return Ok(false);
+    };
+    let tenant = Tenant::trusted(import.account_id);
+    let entries = &import.entries.0;
+    let resumed_at = usize::try_from(import.processed_entries).unwrap_or(0);
+    let mut reported_errors = import.reported_errors;
+
+    let mut index = resumed_at;
+    for chunk in entries
+        .get(resumed_at..)
+        .unwrap_or_default()
+        .chunks(config.chunk_size)
+    {
+        let now = Utc::now();
+        let mut progress = ListImportProgress::default();
+        let mut valid: Vec<ImportedListEntry> = Vec::with_capacity(chunk.len());
+        let mut positions: HashMap<(ListEntityType, String), usize> = HashMap::new();
+        for entry in chunk {
+            match parse_list_entry(entry, now) {
+                Ok(entry) => {
+                    let entity = (entry.entity_type, entry.value.clone());
+                    match positions.get(&entity) {
+                        // Replaced by the later entry, as if imported one at a time
+                        Some(&position) => {
+                            valid[position] = entry;
+                            progress.updated += 1;
+                        },
+                        None => {
+                            positions.insert(entity, valid.len());
+                            valid.push(entry);
+                        },
+                    }
+                },
+                Err(message) => {
+                    progress.failed += 1;
+                    if reported_errors < MAX_REPORTED_ERRORS {
+                        reported_errors += 1;
+                        progress.errors.push(ListImportError { index, message });
+                    }
+                },
+            }
+            progress.processed += 1;
+            index += 1;
+        }
+
+        let mut tx = pool.begin().await?;
+        if !valid.is_empty() {
+            let created =
+                ListRepo::upsert_list_entries(&mut *tx, tenant, import.list_type, &valid).await?;
+            let created = i32::try_from(created).unwrap_or(i32::MAX);
+            let written = i32::try_from(valid.len()).unwrap_or(i32::MAX);
+            progress.created += created;
+            progress.updated += written - created;
+        }
+        ListImportRepo::record_progress(&mut *tx, import.id, &progress).await?;
+        tx.commit().await?;
+
+        let entities: Vec<(ListEntityType, &str)> = valid
+            .iter()
+            .map(|entry| (entry.entity_type, entry.value.as_str()))
+            .collect();
+        lists.invalidate(tenant, &entities).await;
+    }
+
+    let mut tx = pool.begin().await?;
+    let finished = ListImport::from(ListImportRepo::complete(&mut *tx, import.id).await?);
+    let payload = serde_json::to_value(&finished).unwrap_or_default();
+    OutboxRepo::insert(
+        &mut *tx,
+        import.account_id,
+        LIST_IMPORT_COMPLETED,
+        import.id,
+        payload,
+    )
+    .await?;
+    tx.commit().await?;
+    tracing::info!(
+        import_id = %import.id,
+        account_id = %import.account_id,
+        list_type = ?import.list_type,
+        created = finished.created_entries,
+        updated = finished.updated_entries,
+        failed = finished.failed_entries,
+        "List import completed"
+    );
+    Ok(true)
+}
+
+/// One submitted list entry with its value in normal form, or why it cannot be imported
+fn parse_list_entry(
+    entry: &serde_json::Value,
+    now: DateTime<Utc>,
+) -> Result<ImportedListEntry, String> {
+    let entry = ListEntryRequest::deserialize(entry).map_err(|e| format!("Invalid entry: {e}"))?;
+    let value = entry.validate(now)?;
+    Ok(ImportedListEntry {
+        entity_type: entry.entity_type,
+        value,
+        reason: entry.reason,
+        expires_at: entry.expires_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        database::repositories::AccountRepo,
+        models::{
+            account::SubscriptionTier,
+            list::{ListImportStatus, ListType},
+            user::UserImportStatus,
+        },
+        services::UserService,
+        test_support::{create_account, test_pool},
+    };
+
+    #[tokio::test]
+    async fn test_imports_create_update_and_report_failed_users() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let users = UserService::new(pool.clone(), Config::default().user_risk);
+
+        let existing = users
+            .create_user(
+                tenant,
+                &serde_json::from_value(json!({
+                    "external_user_id": "existing",
+                    "flags": [{ "type": "vip", "reason": "Top spender" }],
+                    "metadata": { "segment": "retail" }
+                }))
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+        let import = users
+            .import_users(
+                tenant,
+                &[
+                    json!({ "external_user_id": "new", "chargeback_count": 2, "is_flagged": true }),
+                    json!({ "external_user_id": "bad", "chargeback_count": -1 }),
+                    json!({
+                        "external_user_id": "existing",
+                        "total_transactions": 10,
+                        "successful_transactions": 9,
+                        "flags": [{ "type": "vip" }, { "type": "wholesale" }],
+                        "metadata": { "region": "emea" }
+                    }),
+                    json!("not a user"),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(import.status, UserImportStatus::Pending);
+        assert_eq!(import.total_users, 4);
+
+        let config = ImportsConfig {
+            max_users: 10,
+            max_list_entries: 10,
+            chunk_size: 3,
+            poll_interval_ms: 10,
+        };
+        while process_next_import(&pool, &config).await.unwrap() {}
+
+        let import = users.get_import(tenant, import.id).await.unwrap();
+        assert_eq!(import.status, UserImportStatus::Completed);
+        assert_eq!(import.processed_users, 4);
+        assert_eq!(import.created_users, 1);
+        assert_eq!(import.updated_users, 1);
+        assert_eq!(import.failed_users, 2);
+        let failed: Vec<usize> = import.errors.iter().map(|error| error.index).collect();
+        assert_eq!(failed, [1, 3]);
+
+        let updated = users.get_user(tenant, existing.id).await.unwrap();
+        assert_eq!(updated.total_transactions, 10);
+        let flags: Vec<(&str, Option<&str>)> = updated
+            .flags
+            .iter()
+            .map(|flag| (flag.flag_type.as_str(), flag.reason.as_deref()))
+            .collect();
+        assert_eq!(flags, [("vip", Some("Top spender")), ("wholesale", None)]);
+        assert_eq!(
+            updated.metadata,
+            json!({ "segment": "retail", "region": "emea" })
+        );
+
+        let event_type: String =
+            sqlx::query_scalar("SELECT event_type FROM outbox_events WHERE aggregate_id = $1")
+                .bind(import.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(event_type, USER_IMPORT_COMPLETED);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_imports_upsert_entries_and_report_failed_entries() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let lists = ListService::new(pool.clone());
+
+        lists
+            .set_entry(
+                tenant,
+                ListType::Blocklist,
+                &serde_json::from_value(json!({ "entity_type": "country", "value": "NG" }))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let import = lists
+            .import_entries(
+                tenant,
+                ListType::Blocklist,
+                &[
+                    json!({ "entity_type": "ip", "value": "198.51.100.7", "reason": "Old" }),
+                    json!({ "entity_type": "cidr", "value": "not a range" }),
+                    json!({ "entity_type": "country", "value": "ng", "reason": "Imported" }),
+                    json!({ "entity_type": "ip", "value": "198.51.100.7", "reason": "New" }),
+                    json!({ "entity_type": "email", "value": "Fraud@Example.com" }),
+                    json!("not an entry"),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(import.status, ListImportStatus::Pending);
+        assert_eq!(import.total_entries, 6);
+
+        let config = ImportsConfig {
+            max_users: 10,
+            max_list_entries: 10,
+            chunk_size: 4,
+            poll_interval_ms: 10,
+        };
+        while process_next_list_import(&pool, &lists, &config)
+            .await
+            .unwrap()
+        {}
+
+        let import = lists.get_import(tenant, import.id).await.unwrap();
+        assert_eq!(import.status, ListImportStatus::Completed);
+        assert_eq!(import.processed_entries, 6);
+        assert_eq!(import.created_entries, 2);
+        assert_eq!(import.updated_entries, 2);
+        assert_eq!(import.failed_entries, 2);
+        let failed: Vec<usize> = import.errors.iter().map(|error| error.index).collect();
+        assert_eq!(failed, [1, 5]);
+
+        let (entries, total) = lists
+            .entries(tenant, ListType::Blocklist, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 3);
+        let listed: Vec<(&str, Option<&str>)> = entries
+            .iter()
+            .map(|entry| (entry.value.as_str(), entry.reason.as_deref()))
+            .collect();
+        assert!(listed.contains(&("198.51.100.7", Some("New"))));
+        assert!(listed.contains(&("NG", Some("Imported"))));
+
+        let event_type: String =
+            sqlx::query_scalar("SELECT event_type FROM outbox_events WHERE aggregate_id = $1")
+                .bind(import.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(event_type, LIST_IMPORT_COMPLETED);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
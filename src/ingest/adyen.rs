@@ -0,0 +1,276 @@
+//! Adapter for Adyen standard notifications
+//!
+//! An Adyen webhook batches notification items. Successful `CHARGEBACK`,
+//! `NOTIFICATION_OF_CHARGEBACK`, and `SECOND_CHARGEBACK` items report a dispute, whose
+//! `chargebackReasonCode` becomes the chargeback code; a second chargeback also means the
+//! merchant's defense failed. `INFORMATION_SUPPLIED`, `CHARGEBACK_REVERSED`,
+//! `PREARBITRATION_WON`, and `PREARBITRATION_LOST` items report the dispute's progress. Adyen
+//! has no ID of its own for a dispute, so disputes are told apart by the payment they dispute.
+//! Successful `REFUND` items report a refund, which Adyen does not flag as fraudulent.
+//! Payments are matched by the merchant reference, then the PSP reference of the original
+//! payment.
+//!
+//! Adyen signs each item with the merchant's HMAC key: its `hmacSignature` additional data is
+//! the base64 HMAC-SHA256 of the item's PSP reference, original reference, merchant account,
+//! merchant reference, amount value and currency, event code, and success flag, joined by
+//! colons.
+
+use std::collections::HashMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use super::{DisputeEvent, ProcessorEvent, external_ids, major_units, reason_code};
+use crate::models::{dispute::DisputeStatus, processor_event::ProcessorEventKind};
+
+/// Stage of the dispute notifications of each event code report
+fn dispute_status(event_code: &str) -> Option<DisputeStatus> {
+    match event_code {
+        "CHARGEBACK" | "NOTIFICATION_OF_CHARGEBACK" => Some(DisputeStatus::Received),
+        "INFORMATION_SUPPLIED" => Some(DisputeStatus::Representment),
+        "CHARGEBACK_REVERSED" | "PREARBITRATION_WON" => Some(DisputeStatus::Won),
+        "SECOND_CHARGEBACK" | "PREARBITRATION_LOST" => Some(DisputeStatus::Lost),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Notification {
+    notification_items: Vec<NotificationItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationItem {
+    #[serde(rename = "NotificationRequestItem")]
+    item: NotificationRequestItem,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotificationRequestItem {
+    event_code: String,
+    psp_reference: String,
+    original_reference: Option<String>,
+    merchant_account_code: Option<String>,
+    merchant_reference: Option<String>,
+    success: String,
+    reason: Option<String>,
+    event_date: DateTime<Utc>,
+    amount: Option<Amount>,
+    #[serde(default)]
+    additional_data: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Amount {
+    value: i64,
+    currency: String,
+}
+
+impl NotificationRequestItem {
+    /// String Adyen signs for the item
+    fn signing_string(&self) -> String {
+        let (value, currency) = self.amount.as_ref().map_or((String::new(), ""), |amount| {
+            (amount.value.to_string(), amount.currency.as_str())
+        });
+        [
+            self.psp_reference.as_str(),
+            self.original_reference.as_deref().unwrap_or_default(),
+            self.merchant_account_code.as_deref().unwrap_or_default(),
+            self.merchant_reference.as_deref().unwrap_or_default(),
+            &value,
+            currency,
+            &self.event_code,
+            &self.success,
+        ]
+        .join(":")
+    }
+}
+
+/// Check that every item of an Adyen webhook is signed with the merchant's hex HMAC key
+pub fn verify_signature(hmac_key: &str, body: &serde_json::Value) -> Result<(), String> {
+    let key = hex::decode(hmac_key).map_err(|_| "Adyen HMAC key is not hex".to_string())?;
+    let notification =
+        Notification::deserialize(body).map_err(|e| format!("Invalid Adyen notification: {e}"))?;
+    for NotificationItem { item } in &notification.notification_items {
+        let signature = item
+            .additional_data
+            .get("hmacSignature")
+            .and_then(|signature| STANDARD.decode(signature).ok())
+            .ok_or_else(|| format!("Item {} is not signed", item.psp_reference))?;
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&key).expect("HMAC accepts keys of any length");
+        mac.update(item.signing_string().as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| format!("Signature of item {} does not match", item.psp_reference))?;
+    }
+    Ok(())
+}
+
+/// Read the notification items of an Adyen webhook
+pub fn parse(body: serde_json::Value) -> Result<Vec<ProcessorEvent>, String> {
+    let notification: Notification =
+        serde_json::from_value(body).map_err(|e| format!("Invalid Adyen notification: {e}"))?;
+    Ok(notification
+        .notification_items
+        .into_iter()
+        .map(|NotificationItem { mut item }| {
+            let status = dispute_status(&item.event_code).filter(|_| item.success == "true");
+            let kind = match item.event_code.as_str() {
+                _ if item.success != "true" => ProcessorEventKind::Other,
+                "CHARGEBACK" | "NOTIFICATION_OF_CHARGEBACK" | "SECOND_CHARGEBACK" => {
+                    ProcessorEventKind::Dispute
+                },
+                _ if status.is_some() => ProcessorEventKind::DisputeUpdate,
+                "REFUND" => ProcessorEventKind::Refund,
+                _ => ProcessorEventKind::Other,
+            };
+            let dispute = status.map(|status| DisputeEvent {
+                reference: item
+                    .original_reference
+                    .clone()
+                    .unwrap_or_else(|| item.psp_reference.clone()),
+                status,
+                amount: item
+                    .amount
+                    .as_ref()
+                    .map(|amount| major_units(amount.value, &amount.currency)),
+                currency: item
+                    .amount
+                    .as_ref()
+                    .map(|amount| amount.currency.to_uppercase()),
+            });
+            let reason = item
+                .additional_data
+                .remove("chargebackReasonCode")
+                .or(item.reason);
+            ProcessorEvent {
+                id: format!("{}:{}", item.event_code, item.psp_reference),
+                kind,
+                external_ids: external_ids([
+                    item.merchant_reference.as_ref(),
+                    item.original_reference.as_ref(),
+                    Some(&item.psp_reference),
+                ]),
+                fraudulent: false,
+                reason: match kind {
+                    ProcessorEventKind::Dispute => reason_code(reason),
+                    _ => None,
+                },
+                dispute,
+                occurred_at: item.event_date,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_successful_chargebacks_are_read() {
+        let events = parse(json!({
+            "live": "false",
+            "notificationItems": [
+                { "NotificationRequestItem": {
+                    "eventCode": "CHARGEBACK",
+                    "pspReference": "9915555555555555",
+                    "originalReference": "9913333333333333",
+                    "merchantReference": "order-42",
+                    "success": "true",
+                    "reason": "Fraud",
+                    "eventDate": "2025-06-13T10:42:07+02:00",
+                    "amount": { "value": 2500, "currency": "EUR" },
+                    "additionalData": { "chargebackReasonCode": "10.4" }
+                } },
+                { "NotificationRequestItem": {
+                    "eventCode": "CHARGEBACK_REVERSED",
+                    "pspReference": "9917777777777777",
+                    "originalReference": "9913333333333333",
+                    "merchantReference": "order-42",
+                    "success": "true",
+                    "eventDate": "2025-07-02T09:00:00+02:00"
+                } },
+                { "NotificationRequestItem": {
+                    "eventCode": "REFUND",
+                    "pspReference": "9916666666666666",
+                    "originalReference": "9913333333333333",
+                    "merchantReference": "order-42",
+                    "success": "false",
+                    "eventDate": "2025-06-13T10:42:07+02:00"
+                } }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].id, "CHARGEBACK:9915555555555555");
+        assert_eq!(events[0].kind, ProcessorEventKind::Dispute);
+        assert_eq!(
+            events[0].external_ids,
+            ["order-42", "9913333333333333", "9915555555555555"]
+        );
+        assert_eq!(events[0].reason.as_deref(), Some("10.4"));
+        assert_eq!(
+            events[0].occurred_at.to_rfc3339(),
+            "2025-06-13T08:42:07+00:00"
+        );
+        assert_eq!(
+            events[0].dispute,
+            Some(DisputeEvent {
+                reference: "9913333333333333".to_string(),
+                status: DisputeStatus::Received,
+                amount: Some(25.0),
+                currency: Some("EUR".to_string()),
+            })
+        );
+        // The reversal is the same dispute, won
+        assert_eq!(events[1].kind, ProcessorEventKind::DisputeUpdate);
+        let dispute = events[1].dispute.as_ref().unwrap();
+        assert_eq!(dispute.reference, "9913333333333333");
+        assert_eq!(dispute.status, DisputeStatus::Won);
+        // A failed refund reports nothing
+        assert_eq!(events[2].kind, ProcessorEventKind::Other);
+        assert_eq!(events[2].dispute, None);
+    }
+
+    const HMAC_KEY: &str = "44782DEF547AAA06C910C43932B1EB0C71FC68D9D0C057550C48EC2ACF6BA056";
+
+    fn notification(value: i64, signature: &str) -> serde_json::Value {
+        json!({
+            "live": "false",
+            "notificationItems": [
+                { "NotificationRequestItem": {
+                    "eventCode": "CHARGEBACK",
+                    "pspReference": "9915555555555555",
+                    "originalReference": "9913333333333333",
+                    "merchantAccountCode": "AcmeShop",
+                    "merchantReference": "order-42",
+                    "success": "true",
+                    "eventDate": "2025-06-13T10:42:07+02:00",
+                    "amount": { "value": value, "currency": "EUR" },
+                    "additionalData": { "hmacSignature": signature }
+                } }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_signatures_are_checked() {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&hex::decode(HMAC_KEY).unwrap()).unwrap();
+        mac.update(b"9915555555555555:9913333333333333:AcmeShop:order-42:2500:EUR:CHARGEBACK:true");
+        let signature = STANDARD.encode(mac.finalize().into_bytes());
+
+        assert!(verify_signature(HMAC_KEY, &notification(2500, &signature)).is_ok());
+        assert!(verify_signature(HMAC_KEY, &notification(250, &signature)).is_err());
+        assert!(verify_signature(&"AB".repeat(32), &notification(2500, &signature)).is_err());
+        assert!(verify_signature(HMAC_KEY, &notification(2500, "not base64")).is_err());
+        assert!(verify_signature("not hex", &notification(2500, &signature)).is_err());
+    }
+}
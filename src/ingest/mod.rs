@@ -0,0 +1,136 @@
+//! Ingestion of payment processor webhooks
+//!
+//! Processors announce disputes and refunds in webhooks of their own format. An adapter per
+//! processor reads its format into [`ProcessorEvent`]s, each naming the IDs the payment is
+//! known by on the processor's side and the merchant's. The merchant scored the payment under
+//! one of them as its `event.transaction_id`, which is how an event finds its transaction.
+//! Events about disputes also carry the processor's ID for the dispute and the stage it
+//! reached, so a dispute can be followed from receipt to its outcome.
+//!
+//! Webhooks are sent to an account-scoped URL and are accepted only once the adapter has
+//! checked the processor's signature against the secret the account stored for it.
+
+pub mod adyen;
+pub mod stripe;
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::models::{
+    dispute::DisputeStatus,
+    processor_event::{Processor, ProcessorEventKind},
+};
+
+/// Longest chargeback reason code stored with a report
+const MAX_REASON_LENGTH: usize = 32;
+
+/// Currencies without minor units, by ISO 4217 code
+const ZERO_DECIMAL_CURRENCIES: &[&str] = &[
+    "BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "VND", "VUV",
+    "XAF", "XOF", "XPF",
+];
+
+/// Currencies with three decimal places, by ISO 4217 code
+const THREE_DECIMAL_CURRENCIES: &[&str] = &["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+
+/// A processor event, read from the processor's webhook format
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessorEvent {
+    /// Processor's ID for the event
+    pub id: String,
+    /// What the event reports
+    pub kind: ProcessorEventKind,
+    /// IDs the payment may have been scored under, most specific first
+    pub external_ids: Vec<String>,
+    /// Whether the processor flagged the payment as fraudulent, for refunds
+    pub fraudulent: bool,
+    /// Processor's reason code, for disputes
+    pub reason: Option<String>,
+    /// Dispute the event is about, if any
+    pub dispute: Option<DisputeEvent>,
+    /// When the processor recorded the event
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// What a processor event says about a dispute
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisputeEvent {
+    /// Processor's ID for the dispute
+    pub reference: String,
+    /// Stage the dispute reached
+    pub status: DisputeStatus,
+    /// Disputed amount, in major units of `currency`
+    pub amount: Option<f64>,
+    /// ISO 4217 currency code of the amount, uppercased
+    pub currency: Option<String>,
+}
+
+/// Read the events of a webhook `body` from `processor`
+pub fn parse(processor: Processor, body: serde_json::Value) -> Result<Vec<ProcessorEvent>, String> {
+    match processor {
+        Processor::Stripe => stripe::parse(body),
+        Processor::Adyen => adyen::parse(body),
+    }
+}
+
+/// Check that `processor` signed a webhook `body` with `secret`
+///
+/// `signature` is the `Stripe-Signature` header, which Stripe webhooks must carry; Adyen signs
+/// the items in the body instead. Stripe signatures outside `tolerance` are refused.
+pub fn verify_signature(
+    processor: Processor,
+    secret: &str,
+    signature: Option<&str>,
+    body: &[u8],
+    tolerance: Duration,
+) -> Result<(), String> {
+    match processor {
+        Processor::Stripe => {
+            let signature = signature.ok_or("Stripe-Signature header is missing")?;
+            stripe::verify_signature(secret, signature, body, tolerance)
+        },
+        Processor::Adyen => {
+            let body = serde_json::from_slice(body)
+                .map_err(|e| format!("Invalid Adyen notification: {e}"))?;
+            adyen::verify_signature(secret, &body)
+        },
+    }
+}
+
+/// Reason code trimmed to what fits a report
+fn reason_code(reason: Option<String>) -> Option<String> {
+    reason
+        .map(|reason| {
+            reason
+                .trim()
+                .chars()
+                .take(MAX_REASON_LENGTH)
+                .collect::<String>()
+        })
+        .filter(|reason| !reason.is_empty())
+}
+
+/// Amount in major units of `currency` of `value` minor units, as processors send them
+fn major_units(value: i64, currency: &str) -> f64 {
+    let currency = currency.to_uppercase();
+    let decimals = if ZERO_DECIMAL_CURRENCIES.contains(&currency.as_str()) {
+        0
+    } else if THREE_DECIMAL_CURRENCIES.contains(&currency.as_str()) {
+        3
+    } else {
+        2
+    };
+    value as f64 / 10_f64.powi(decimals)
+}
+
+/// Non-empty IDs, in order and without repeats
+fn external_ids<'a>(ids: impl IntoIterator<Item = Option<&'a String>>) -> Vec<String> {
+    let mut external_ids: Vec<String> = Vec::new();
+    for id in ids.into_iter().flatten() {
+        if !id.is_empty() && !external_ids.contains(id) {
+            external_ids.push(id.clone());
+        }
+    }
+    external_ids
+}
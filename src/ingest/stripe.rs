@@ -0,0 +1,319 @@
+//! Adapter for Stripe event objects
+//!
+//! A Stripe webhook carries one event. `charge.dispute.created` reports a dispute, whose
+//! `reason` becomes the chargeback code; `charge.dispute.updated` and `charge.dispute.closed`
+//! report its progress, read from the dispute's `status`. `charge.refunded` and
+//! `refund.created` report a refund, fraudulent when a refund's `reason` is `fraudulent`.
+//! Payments are matched by their PaymentIntent ID, then their charge ID.
+//!
+//! Stripe signs each webhook with the endpoint's secret: the `Stripe-Signature` header reads
+//! `t={timestamp},v1={signature}`, the signature being the hex HMAC-SHA256 of
+//! `{timestamp}.{body}`. It may carry several `v1` signatures while a secret is rolled.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use super::{DisputeEvent, ProcessorEvent, external_ids, major_units, reason_code};
+use crate::models::{dispute::DisputeStatus, processor_event::ProcessorEventKind};
+
+/// Refund reason Stripe records for refunds of fraudulent payments
+const FRAUDULENT: &str = "fraudulent";
+
+/// Header Stripe signs webhooks in
+pub const SIGNATURE_HEADER: &str = "stripe-signature";
+
+#[derive(Debug, Deserialize)]
+struct Event {
+    id: String,
+    #[serde(rename = "type")]
+    event_type: String,
+    created: i64,
+    data: EventData,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventData {
+    object: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Dispute {
+    id: String,
+    charge: Option<String>,
+    payment_intent: Option<String>,
+    reason: Option<String>,
+    amount: Option<i64>,
+    currency: Option<String>,
+    status: Option<String>,
+}
+
+impl Dispute {
+    /// Stage of the dispute, by its Stripe status
+    ///
+    /// A closed inquiry (`warning_closed`) never became a chargeback, so the merchant keeps the
+    /// funds as if the dispute were won.
+    fn status(&self) -> DisputeStatus {
+        match self.status.as_deref() {
+            Some("under_review" | "warning_under_review") => DisputeStatus::Representment,
+            Some("won" | "warning_closed") => DisputeStatus::Won,
+            Some("lost") => DisputeStatus::Lost,
+            _ => DisputeStatus::Received,
+        }
+    }
+
+    fn event(&self, status: DisputeStatus) -> DisputeEvent {
+        let currency = self.currency.as_deref().map(str::to_uppercase);
+        DisputeEvent {
+            reference: self.id.clone(),
+            status,
+            amount: self
+                .amount
+                .zip(currency.as_deref())
+                .map(|(amount, currency)| major_units(amount, currency)),
+            currency,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Charge {
+    id: String,
+    payment_intent: Option<String>,
+    #[serde(default)]
+    refunds: Option<RefundList>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RefundList {
+    data: Vec<Refund>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Refund {
+    charge: Option<String>,
+    payment_intent: Option<String>,
+    reason: Option<String>,
+}
+
+impl Refund {
+    fn fraudulent(&self) -> bool {
+        self.reason.as_deref() == Some(FRAUDULENT)
+    }
+}
+
+/// Check the `Stripe-Signature` header of a webhook against the endpoint's secret
+///
+/// Signatures older or newer than `tolerance` are refused, so a captured webhook cannot be
+/// replayed later.
+pub fn verify_signature(
+    secret: &str,
+    header: &str,
+    body: &[u8],
+    tolerance: Duration,
+) -> Result<(), String> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.extend(hex::decode(value).ok()),
+            _ => {},
+        }
+    }
+    let timestamp = timestamp.ok_or("Stripe-Signature has no timestamp")?;
+    if Utc::now().timestamp().abs_diff(timestamp) > tolerance.as_secs() {
+        return Err(format!(
+            "Stripe-Signature timestamp {timestamp} is outside the tolerance"
+        ));
+    }
+    let signed = signatures.iter().any(|signature| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac.verify_slice(signature).is_ok()
+    });
+    if !signed {
+        return Err("No Stripe-Signature signature matches the body".to_string());
+    }
+    Ok(())
+}
+
+/// Read the event of a Stripe webhook
+pub fn parse(body: serde_json::Value) -> Result<Vec<ProcessorEvent>, String> {
+    let event: Event =
+        serde_json::from_value(body).map_err(|e| format!("Invalid Stripe event: {e}"))?;
+    let occurred_at = DateTime::<Utc>::from_timestamp(event.created, 0)
+        .ok_or_else(|| format!("Invalid Stripe event creation time {}", event.created))?;
+    let invalid = |e: serde_json::Error| format!("Invalid {} object: {e}", event.event_type);
+    let mut processed = ProcessorEvent {
+        id: event.id.clone(),
+        kind: ProcessorEventKind::Other,
+        external_ids: Vec::new(),
+        fraudulent: false,
+        reason: None,
+        dispute: None,
+        occurred_at,
+    };
+    match event.event_type.as_str() {
+        "charge.dispute.created" => {
+            let dispute: Dispute = serde_json::from_value(event.data.object).map_err(invalid)?;
+            processed.kind = ProcessorEventKind::Dispute;
+            processed.external_ids =
+                external_ids([dispute.payment_intent.as_ref(), dispute.charge.as_ref()]);
+            processed.dispute = Some(dispute.event(DisputeStatus::Received));
+            processed.reason = reason_code(dispute.reason);
+        },
+        "charge.dispute.updated" | "charge.dispute.closed" => {
+            let dispute: Dispute = serde_json::from_value(event.data.object).map_err(invalid)?;
+            processed.kind = ProcessorEventKind::DisputeUpdate;
+            processed.external_ids =
+                external_ids([dispute.payment_intent.as_ref(), dispute.charge.as_ref()]);
+            processed.dispute = Some(dispute.event(dispute.status()));
+        },
+        "charge.refunded" => {
+            let charge: Charge = serde_json::from_value(event.data.object).map_err(invalid)?;
+            processed.kind = ProcessorEventKind::Refund;
+            processed.external_ids =
+                external_ids([charge.payment_intent.as_ref(), Some(&charge.id)]);
+            processed.fraudulent = charge
+                .refunds
+                .is_some_and(|refunds| refunds.data.iter().any(Refund::fraudulent));
+        },
+        "refund.created" => {
+            let refund: Refund = serde_json::from_value(event.data.object).map_err(invalid)?;
+            processed.kind = ProcessorEventKind::Refund;
+            processed.external_ids =
+                external_ids([refund.payment_intent.as_ref(), refund.charge.as_ref()]);
+            processed.fraudulent = refund.fraudulent();
+        },
+        _ => {},
+    }
+    Ok(vec![processed])
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_disputes_and_fraudulent_refunds_are_read() {
+        let events = parse(json!({
+            "id": "evt_1",
+            "object": "event",
+            "type": "charge.dispute.created",
+            "created": 1_750_000_000,
+            "data": { "object": {
+                "id": "dp_1",
+                "object": "dispute",
+                "charge": "ch_1",
+                "payment_intent": "pi_1",
+                "reason": "fraudulent",
+                "amount": 14999,
+                "currency": "usd",
+                "status": "needs_response"
+            } }
+        }))
+        .unwrap();
+        assert_eq!(events[0].kind, ProcessorEventKind::Dispute);
+        assert_eq!(events[0].external_ids, ["pi_1", "ch_1"]);
+        assert_eq!(events[0].reason.as_deref(), Some("fraudulent"));
+        assert_eq!(
+            events[0].dispute,
+            Some(DisputeEvent {
+                reference: "dp_1".to_string(),
+                status: DisputeStatus::Received,
+                amount: Some(149.99),
+                currency: Some("USD".to_string()),
+            })
+        );
+        assert_eq!(events[0].occurred_at.timestamp(), 1_750_000_000);
+
+        let events = parse(json!({
+            "id": "evt_2",
+            "object": "event",
+            "type": "charge.refunded",
+            "created": 1_750_000_000,
+            "data": { "object": {
+                "id": "ch_2",
+                "object": "charge",
+                "payment_intent": null,
+                "refunds": { "data": [{ "charge": "ch_2", "reason": "fraudulent" }] }
+            } }
+        }))
+        .unwrap();
+        assert_eq!(events[0].kind, ProcessorEventKind::Refund);
+        assert_eq!(events[0].external_ids, ["ch_2"]);
+        assert!(events[0].fraudulent);
+
+        // Closing reports how the dispute was decided
+        let events = parse(json!({
+            "id": "evt_3",
+            "object": "event",
+            "type": "charge.dispute.closed",
+            "created": 1_750_000_000,
+            "data": { "object": {
+                "id": "dp_1",
+                "object": "dispute",
+                "charge": "ch_1",
+                "amount": 5000,
+                "currency": "jpy",
+                "status": "won"
+            } }
+        }))
+        .unwrap();
+        assert_eq!(events[0].kind, ProcessorEventKind::DisputeUpdate);
+        let dispute = events[0].dispute.as_ref().unwrap();
+        assert_eq!(dispute.status, DisputeStatus::Won);
+        assert_eq!(dispute.amount, Some(5000.0));
+
+        // Other event types are read but report nothing
+        let events = parse(json!({
+            "id": "evt_4",
+            "object": "event",
+            "type": "customer.created",
+            "created": 1_750_000_000,
+            "data": { "object": {} }
+        }))
+        .unwrap();
+        assert_eq!(events[0].kind, ProcessorEventKind::Other);
+        assert!(parse(json!({ "id": "evt_5", "object": "event" })).is_err());
+    }
+
+    fn signed(secret: &str, timestamp: i64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{timestamp}.").as_bytes());
+        mac.update(body);
+        format!(
+            "t={timestamp},v1={}",
+            hex::encode(mac.finalize().into_bytes())
+        )
+    }
+
+    #[test]
+    fn test_signatures_are_checked() {
+        let body = br#"{"id":"evt_1","object":"event"}"#;
+        let tolerance = Duration::from_secs(300);
+        let now = Utc::now().timestamp();
+
+        let header = signed("whsec_test", now, body);
+        assert!(verify_signature("whsec_test", &header, body, tolerance).is_ok());
+        // A rolled secret signs alongside the new one
+        let rolled = format!("{header},v1={}", "00".repeat(32));
+        assert!(verify_signature("whsec_test", &rolled, body, tolerance).is_ok());
+
+        let tampered = br#"{"id":"evt_2","object":"event"}"#;
+        assert!(verify_signature("whsec_test", &header, tampered, tolerance).is_err());
+        assert!(verify_signature("whsec_other", &header, body, tolerance).is_err());
+        let stale = signed("whsec_test", now - 600, body);
+        assert!(verify_signature("whsec_test", &stale, body, tolerance).is_err());
+        assert!(verify_signature("whsec_test", "v1=abc", body, tolerance).is_err());
+    }
+}
@@ -2,11 +2,36 @@
 //!
 //! Environment-based configuration with security-first design.
 
+pub mod analytics;
 pub mod api;
+pub mod auth;
+pub mod case_sla;
 pub mod config;
+pub mod database;
+pub mod features;
+pub mod identity;
+pub mod imports;
+pub mod ingest;
+pub mod lifecycle;
+pub mod metering;
 pub mod models;
+pub mod outbox;
+pub mod rate_limit;
+pub mod rule_tuning;
+pub mod scheduler;
+pub mod scoring;
 pub mod server;
+pub mod services;
+pub mod sessions;
+pub mod state;
+pub mod storage;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod tls;
+pub mod user_risk;
+pub mod utils;
 
 // Re-export commonly used types
 pub use config::Config;
 pub use server::create_app;
+pub use state::AppState;
@@ -0,0 +1,158 @@
+//! Deletion of closed accounts
+//!
+//! Closing an account schedules its deletion; until then it can be reactivated. Once the time
+//! passes, this job deletes the account row, which cascades to its sandbox and every row of
+//! both in PostgreSQL, along with any organization billed to it. Each deletion is recorded in
+//! the status audit log, which is not tied to the account and so survives it.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    config::LifecycleConfig,
+    database::repositories::{AccountRepo, NewStatusChange, OrganizationRepo},
+    models::account::AccountStatus,
+};
+
+/// Spawn a background task that periodically deletes closed accounts whose deletion is due
+pub fn spawn_account_deletion(pool: PgPool, config: LifecycleConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = std::time::Duration::from_secs(config.deletion_check_interval_seconds);
+        loop {
+            match delete_due_accounts(&pool, Utc::now()).await {
+                Ok(0) => {},
+                Ok(deleted) => tracing::info!(deleted, "Deleted closed accounts"),
+                Err(e) => tracing::error!(error = %e, "Closed account deletion failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Delete every closed account whose deletion is due at `now`, returning how many were deleted
+///
+/// Each account is deleted in its own database transaction, so a long backlog does not hold
+/// one transaction open.
+pub async fn delete_due_accounts(pool: &PgPool, now: DateTime<Utc>) -> sqlx::Result<usize> {
+    let mut deleted = 0;
+    loop {
+        let mut tx = pool.begin().await?;
+        let Some(account) = AccountRepo::claim_due_deletion(&mut *tx, now).await? else {
+            return Ok(deleted);
+        };
+        let organizations = OrganizationRepo::delete_billed_to(&mut *tx, account.id).await?;
+        AccountRepo::log_status_change(
+            &mut *tx,
+            &NewStatusChange {
+                account_id: account.id,
+                public_account_id: &account.account_id,
+                from_status: AccountStatus::Closed,
+                to_status: "deleted",
+                reason: None,
+                api_key_id: None,
+            },
+        )
+        .await?;
+        AccountRepo::delete(&mut *tx, account.id).await?;
+        tx.commit().await?;
+
+        tracing::warn!(
+            account_id = %account.id,
+            public_account_id = %account.account_id,
+            scheduled_at = %account.deletion_scheduled_at,
+            organizations,
+            "Deleted closed account"
+        );
+        deleted += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::{
+        database::Tenant,
+        models::{
+            account::SubscriptionTier,
+            organization::{MemberRole, MembershipStatus},
+        },
+        test_support::{create_account, test_pool},
+    };
+
+    async fn closed_account(pool: &PgPool, deletion_at: DateTime<Utc>) -> Tenant {
+        let tenant = create_account(pool, SubscriptionTier::Free).await;
+        AccountRepo::get_or_create_sandbox(pool, tenant.id())
+            .await
+            .unwrap();
+        let closed = AccountRepo::set_status(
+            pool,
+            tenant,
+            AccountStatus::Active,
+            AccountStatus::Closed,
+            Some(deletion_at),
+        )
+        .await
+        .unwrap();
+        assert!(closed);
+        tenant
+    }
+
+    #[tokio::test]
+    async fn test_deletes_only_accounts_past_their_deletion_time() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let now = Utc::now();
+        let due = closed_account(&pool, now - Duration::hours(1)).await;
+        let pending = closed_account(&pool, now + Duration::days(1)).await;
+        let organization = OrganizationRepo::create(&pool, "Doomed", due.id())
+            .await
+            .unwrap();
+        OrganizationRepo::add_member(
+            &pool,
+            organization,
+            due.id(),
+            MemberRole::Owner,
+            MembershipStatus::Active,
+            true,
+        )
+        .await
+        .unwrap();
+
+        assert!(delete_due_accounts(&pool, now).await.unwrap() >= 1);
+
+        assert!(AccountRepo::find_by_id(&pool, due).await.unwrap().is_none());
+        assert!(
+            OrganizationRepo::find(&pool, organization)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let sandboxes: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM accounts WHERE sandbox_of = $1")
+                .bind(due.id())
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(sandboxes, 0);
+        let audited: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM account_status_changes \
+             WHERE account_id = $1 AND to_status = 'deleted'",
+        )
+        .bind(due.id())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(audited, 1);
+
+        let kept = AccountRepo::find_by_id(&pool, pending)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(kept.status, AccountStatus::Closed);
+        AccountRepo::delete(&pool, pending.id()).await.unwrap();
+    }
+}
@@ -1,6 +1,56 @@
 //! Fusegu
 
-use fusegu::{config::Config, server::create_app};
+use std::{path::PathBuf, time::Duration};
+
+use axum::Extension;
+use fusegu::{
+    analytics::{
+        anomalies::spawn_anomaly_detection, feature_export::spawn_feature_export,
+        reports::report_generation_task,
+    },
+    auth::expiry::spawn_key_expiry_reminders,
+    case_sla::spawn_case_sla_monitor,
+    config::Config,
+    database::{
+        Database,
+        clickhouse::ClickHouseClient,
+        connect_redis,
+        seed::{self, DEMO_API_KEY, DEMO_SANDBOX_API_KEY, SeedOutcome},
+    },
+    features::{FeatureStore, refresh::profile_refresh_task},
+    identity::identity_resolution_task,
+    imports::{spawn_list_import_worker, spawn_user_import_worker},
+    lifecycle::spawn_account_deletion,
+    metering::sync::spawn_usage_sync,
+    outbox::{
+        EventPublisher, LoggingPublisher,
+        callbacks::CallbackPublisher,
+        clickhouse::ClickHousePublisher,
+        dead_letters::spawn_dead_letter_monitor,
+        dispatcher::spawn_outbox_dispatcher,
+        nats::NatsPublisher,
+        notifications::{NotificationPublisher, Notifier},
+        webhooks::{WebhookPublisher, WebhookSender},
+    },
+    rule_tuning::spawn_rule_suggestions,
+    scheduler::Scheduler,
+    server::create_app,
+    services::{
+        EmailIntelService, IpIntelService, ListService, ScreeningService, TransactionService,
+        email_intel::email_intel_refresh_task,
+        ip_intel::ip_intel_refresh_task,
+        jobs::{JobRunner, spawn_job_workers},
+        screening::screening_refresh_task,
+        transaction_service::SignalSources,
+    },
+    sessions::SessionStore,
+    storage::s3::S3Client,
+    tls,
+    user_risk::user_risk_recalculation_task,
+    utils::geo::{GeoIpDatabase, spawn_geoip_reload},
+};
+use redis::aio::ConnectionManager;
+use sqlx::PgPool;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 /// Application exit codes following Unix conventions
@@ -19,6 +69,8 @@ enum ExitCode {
     InitializationError = 4,
     /// Unexpected server runtime error
     ServerError = 5,
+    /// Database connection or migration error
+    DatabaseError = 6,
 }
 
 impl ExitCode {
@@ -31,6 +83,7 @@ impl ExitCode {
             ExitCode::NetworkError => "Network binding error",
             ExitCode::InitializationError => "Application initialization error",
             ExitCode::ServerError => "Server runtime error",
+            ExitCode::DatabaseError => "Database error",
         }
     }
 }
@@ -55,38 +108,62 @@ async fn main() {
         }
     }
 
-    run_server().await;
-}
-
-async fn run_server() {
-    // Load configuration from .env
-    let config = match Config::load() {
-        Ok(config) => config,
-        Err(e) => {
+    match std::env::args().nth(1).as_deref() {
+        None | Some("serve") => run_server().await,
+        Some("migrate") => run_migrate().await,
+        Some("seed") => run_seed().await,
+        Some(command) => {
             eprintln!();
-            eprintln!("❌ Error: Failed to load configuration");
-            eprintln!("   Reason: {}", e);
+            eprintln!("❌ Error: Unknown command '{}'", command);
             eprintln!();
-            eprintln!("💡 Solutions:");
-            eprintln!("   1. Check your .env file format");
-            eprintln!("   2. Verify environment variables are set correctly");
-            eprintln!("   3. See env.sample for reference");
+            eprintln!("💡 Usage: fusegu [serve|migrate|seed]");
+            eprintln!("   serve    Start the API server (default)");
+            eprintln!("   migrate  Apply pending database migrations and exit");
+            eprintln!("   seed     Populate demo data for local development and exit");
             eprintln!();
-            exit_gracefully(ExitCode::ConfigError);
+            exit_gracefully(ExitCode::GeneralError);
         },
-    };
+    }
+}
 
-    // Initialize structured logging with JSON format for production
-    let log_level = std::env::var("RUST_LOG")
-        .unwrap_or_else(|_| "fusegu=info,tower_http=debug,axum::rejection=trace".to_string());
+/// Apply pending migrations without starting the server
+async fn run_migrate() {
+    let config = load_config();
+    init_tracing();
 
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| log_level.into()),
-        )
-        .with(tracing_subscriber::fmt::layer().json())
-        .init();
+    let database = connect_database(&config).await;
+    apply_migrations(&database).await;
+    if config.database.clickhouse_enabled {
+        connect_clickhouse(&config).await;
+    }
+    exit_gracefully(ExitCode::Success);
+}
+
+/// Populate demo data without starting the server
+async fn run_seed() {
+    let config = load_config();
+    init_tracing();
+
+    if config.server.environment != "development" {
+        eprintln!();
+        eprintln!("❌ Error: Refusing to seed demo data outside development");
+        eprintln!("   RUST_ENV is '{}'", config.server.environment);
+        eprintln!();
+        exit_gracefully(ExitCode::ConfigError);
+    }
+
+    let database = connect_database(&config).await;
+    apply_migrations(&database).await;
+    seed_demo_data(&database).await;
+    exit_gracefully(ExitCode::Success);
+}
+
+async fn run_server() {
+    // Load configuration from .env
+    let config = load_config();
+
+    // Initialize structured logging with JSON format for production
+    init_tracing();
 
     tracing::info!(
         version = env!("CARGO_PKG_VERSION"),
@@ -96,9 +173,209 @@ async fn run_server() {
         "Starting Fusegu API server"
     );
 
+    // Connect to PostgreSQL and bring the schema up to date
+    let database = connect_database(&config).await;
+    if config.database.run_migrations {
+        apply_migrations(&database).await;
+    }
+    if config.database.seed_demo_data && config.server.environment == "development" {
+        seed_demo_data(&database).await;
+    }
+
+    // Shared counters for replay protection and quota usage, and session histories
+    let redis = connect_shared_cache(&config).await;
+
+    // Persist usage counters and reset quotas when billing cycles end
+    spawn_usage_sync(
+        database.pool().clone(),
+        redis.clone(),
+        config.metering.clone(),
+    );
+
+    // Warn about API keys before they expire
+    spawn_key_expiry_reminders(database.pool().clone(), config.auth.clone());
+
+    // Count the events the outbox dispatcher gave up on, alerting when they pile up
+    spawn_dead_letter_monitor(database.pool().clone(), config.outbox.clone());
+
+    // Announce review cases that outlive their queue's SLA
+    spawn_case_sla_monitor(database.pool().clone(), config.cases.clone());
+
+    // Delete closed accounts once their retention period ends
+    spawn_account_deletion(database.pool().clone(), config.lifecycle.clone());
+
+    // Recurring work run on cron schedules, reported by GET /admin/scheduled-tasks
+    let mut scheduler = Scheduler::new();
+
+    // Nightly recomputation of long-horizon user profiles
+    scheduler.add(profile_refresh_task(
+        FeatureStore::new(database.pool().clone()),
+        config.features.clone(),
+    ));
+
+    // Link users that share emails, cards, or devices
+    scheduler.add(identity_resolution_task(
+        database.pool().clone(),
+        config.identity.clone(),
+    ));
+
+    // Import user bases submitted to POST /v1/users/batch
+    spawn_user_import_worker(database.pool().clone(), config.imports.clone());
+
+    // Import list entries submitted to POST /v1/lists/{list_type}/import, dropping their cached
+    // lookups
+    spawn_list_import_worker(
+        database.pool().clone(),
+        ListService::new(database.pool().clone()).with_cache(redis.clone()),
+        config.imports.clone(),
+    );
+
+    // Keep user risk scores current as transactions arrive and age
+    scheduler.add(user_risk_recalculation_task(
+        database.pool().clone(),
+        config.user_risk.clone(),
+    ));
+
+    // Suggest rule weights from the outcomes reported for transactions
+    spawn_rule_suggestions(database.pool().clone(), config.rule_suggestions.clone());
+
+    // Locate IP addresses, picking up database updates without a restart
+    let geoip = load_geoip_database(&config);
+
+    // Keep Tor, hosting, VPN, and proxy feeds current for anonymous IP lookups
+    let ip_intel = IpIntelService::new(database.pool().clone(), redis.clone(), &config.ip_intel);
+    if config.ip_intel.is_enabled() {
+        match ip_intel_refresh_task(ip_intel.clone(), config.ip_intel.clone()) {
+            Ok(task) => scheduler.add(task),
+            Err(e) => tracing::error!(error = %e, "Failed to create IP intelligence HTTP client"),
+        }
+    }
+
+    // Keep disposable and free email domain lists current
+    let email_intel = EmailIntelService::new(&config.email_intel);
+    if config.email_intel.is_enabled() {
+        match email_intel_refresh_task(email_intel.clone(), config.email_intel.clone()) {
+            Ok(task) => scheduler.add(task),
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to create email intelligence HTTP client")
+            },
+        }
+    }
+
+    // Keep the OFAC and EU sanctions lists current for name screening
+    let screening = ScreeningService::new(&config.screening);
+    if config.screening.is_enabled() {
+        match screening_refresh_task(screening.clone(), config.screening.clone()) {
+            Ok(task) => scheduler.add(task),
+            Err(e) => tracing::error!(error = %e, "Failed to create screening HTTP client"),
+        }
+    }
+
+    // Transactions are scored in the background as synchronously, signal sources included
+    let scoring = TransactionService::new(
+        database.pool().clone(),
+        database.pool().clone(),
+        config.redaction.clone(),
+    )
+    .with_geoip(geoip.clone())
+    .with_lists(
+        ListService::new(database.pool().clone())
+            .with_cache(redis.clone())
+            .with_auto_block(config.auto_block.clone()),
+    )
+    .with_signal_sources(SignalSources {
+        sessions: SessionStore::new(redis.clone()),
+        features: FeatureStore::new(database.pool().clone())
+            .with_geoip(geoip.clone())
+            .with_velocity_subnets(config.features.ip_velocity_subnets()),
+        ip_intel: ip_intel.clone(),
+        email_intel: email_intel.clone(),
+        screening: screening.clone(),
+    });
+
+    // Run background jobs, such as scoring transactions submitted with mode=async
+    spawn_job_workers(
+        database.pool().clone(),
+        config.jobs.clone(),
+        JobRunner::new(database.pool().clone(), scoring),
+    );
+
+    // Deliver events recorded alongside scored transactions
+    if config.database.clickhouse_enabled {
+        let clickhouse = connect_clickhouse(&config).await;
+        // Flag spikes in fraud metrics shortly after each hour ends
+        spawn_anomaly_detection(
+            database.pool().clone(),
+            clickhouse.clone(),
+            config.analytics.clone(),
+        );
+        // Daily and weekly summaries, optionally exported to S3
+        scheduler.add(report_generation_task(
+            database.pool().clone(),
+            clickhouse.clone(),
+            s3_exporter(&config, config.reports.s3_bucket.as_deref(), "REPORT_S3_*"),
+            config.reports.clone(),
+        ));
+        // Daily Parquet files of scored transactions for offline model training
+        if config.feature_export.is_enabled() {
+            spawn_feature_export(
+                database.pool().clone(),
+                clickhouse.clone(),
+                s3_exporter(
+                    &config,
+                    config.feature_export.s3_bucket.as_deref(),
+                    "FEATURE_EXPORT_S3_* and REPORT_S3_*",
+                ),
+                config.feature_export.clone(),
+            );
+        }
+        spawn_outbox_dispatcher(
+            database.pool().clone(),
+            webhook_publisher(
+                &config,
+                database.pool(),
+                notification_publisher(
+                    &config,
+                    database.pool(),
+                    callback_publisher(
+                        &config,
+                        nats_publisher(&config, ClickHousePublisher::new(clickhouse)).await,
+                    ),
+                ),
+            ),
+            config.outbox.clone(),
+        );
+    } else {
+        spawn_outbox_dispatcher(
+            database.pool().clone(),
+            webhook_publisher(
+                &config,
+                database.pool(),
+                notification_publisher(
+                    &config,
+                    database.pool(),
+                    callback_publisher(&config, nats_publisher(&config, LoggingPublisher).await),
+                ),
+            ),
+            config.outbox.clone(),
+        );
+    }
+
+    let scheduler_status = scheduler.status();
+    scheduler.spawn();
+
     // Create the application
-    let app = match create_app(config.clone()).await {
-        Ok(app) => app,
+    let app = match create_app(
+        config.clone(),
+        database,
+        redis,
+        geoip,
+        ip_intel,
+        email_intel,
+        screening,
+    ) {
+        // Handlers read the scheduler's status from a request extension
+        Ok(app) => app.layer(Extension(scheduler_status)),
         Err(e) => {
             tracing::error!(error = %e, "Failed to create application");
             eprintln!();
@@ -108,6 +385,29 @@ async fn run_server() {
             exit_gracefully(ExitCode::InitializationError);
         },
     };
+    // Load certificates before binding, so a bad path fails fast
+    let tls_config = if config.server.tls_enabled() {
+        match tls::tls_config(&config.server, &config.auth) {
+            Ok(tls_config) => Some(tls_config),
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to load TLS configuration");
+                eprintln!();
+                eprintln!("❌ Error: Failed to load TLS configuration");
+                eprintln!("   Reason: {:#}", e);
+                eprintln!();
+                eprintln!("💡 Check TLS_CERT_PATH, TLS_KEY_PATH, and MTLS_CLIENT_CA_PATH");
+                eprintln!();
+                exit_gracefully(ExitCode::ConfigError);
+            },
+        }
+    } else {
+        None
+    };
+    let scheme = if tls_config.is_some() {
+        "https"
+    } else {
+        "http"
+    };
     let addr = format!("{}:{}", config.server.host, config.server.port);
 
     // Try to bind to the address with better error handling
@@ -164,18 +464,33 @@ async fn run_server() {
         },
     };
 
-    // Start the server with graceful shutdown handling
-    let server = axum::serve(listener, app).with_graceful_shutdown(shutdown_signal());
-
     tracing::info!("🚀 Fusegu server started successfully");
     tracing::info!(
-        "📋 OpenAPI documentation available at: http://{}/openapi.json",
+        "📋 OpenAPI documentation available at: {}://{}/openapi.json",
+        scheme,
+        addr
+    );
+    tracing::info!(
+        "❤️  Health check available at: {}://{}/health",
+        scheme,
         addr
     );
-    tracing::info!("❤️  Health check available at: http://{}/health", addr);
     tracing::info!("🛑 Press Ctrl+C to shut down");
 
-    if let Err(e) = server.await {
+    // Start the server with graceful shutdown handling
+    let result = match tls_config {
+        Some(tls_config) => {
+            tls::serve(listener, app, tls_config, shutdown_signal()).await;
+            Ok(())
+        },
+        None => {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+        },
+    };
+
+    if let Err(e) = result {
         tracing::error!(error = %e, "Server error occurred");
         eprintln!();
         eprintln!("❌ Error: Server encountered an unexpected error");
@@ -193,6 +508,274 @@ async fn run_server() {
     exit_gracefully(ExitCode::Success);
 }
 
+/// Load configuration from .env, exiting on failure
+fn load_config() -> Config {
+    match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!();
+            eprintln!("❌ Error: Failed to load configuration");
+            eprintln!("   Reason: {}", e);
+            eprintln!();
+            eprintln!("💡 Solutions:");
+            eprintln!("   1. Check your .env file format");
+            eprintln!("   2. Verify environment variables are set correctly");
+            eprintln!("   3. See env.sample for reference");
+            eprintln!();
+            exit_gracefully(ExitCode::ConfigError);
+        },
+    }
+}
+
+/// Initialize structured logging with JSON format for production
+fn init_tracing() {
+    let log_level = std::env::var("RUST_LOG")
+        .unwrap_or_else(|_| "fusegu=info,tower_http=debug,axum::rejection=trace".to_string());
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| log_level.into()),
+        )
+        .with(tracing_subscriber::fmt::layer().json())
+        .init();
+}
+
+/// Connect to PostgreSQL, exiting on failure
+async fn connect_database(config: &Config) -> Database {
+    match Database::connect(&config.database).await {
+        Ok(database) => database,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to connect to PostgreSQL");
+            eprintln!();
+            eprintln!("❌ Error: Failed to connect to PostgreSQL");
+            eprintln!("   Reason: {}", e);
+            eprintln!();
+            eprintln!("💡 Solutions:");
+            eprintln!("   1. Check that PostgreSQL is running");
+            eprintln!("   2. Verify POSTGRES_URL in your .env file");
+            eprintln!();
+            exit_gracefully(ExitCode::DatabaseError);
+        },
+    }
+}
+
+/// Apply pending schema migrations, exiting on failure
+async fn apply_migrations(database: &Database) {
+    if let Err(e) = database.migrate().await {
+        tracing::error!(error = %e, "Failed to apply database migrations");
+        eprintln!();
+        eprintln!("❌ Error: Failed to apply database migrations");
+        eprintln!("   Reason: {}", e);
+        eprintln!();
+        exit_gracefully(ExitCode::DatabaseError);
+    }
+}
+
+/// S3 client for an export to `bucket`, if one is configured, exiting on invalid settings
+///
+/// `settings` names the environment variables to point the operator at.
+fn s3_exporter(config: &Config, bucket: Option<&str>, settings: &str) -> Option<S3Client> {
+    let bucket = bucket?;
+    match S3Client::new(&config.reports, bucket) {
+        Ok(client) => Some(client),
+        Err(e) => {
+            tracing::error!(error = %e, bucket, "Invalid S3 export settings");
+            eprintln!();
+            eprintln!("❌ Error: Invalid S3 export settings for bucket {}", bucket);
+            eprintln!("   Reason: {}", e);
+            eprintln!();
+            eprintln!("💡 Check the {} settings in your .env file", settings);
+            eprintln!();
+            exit_gracefully(ExitCode::ConfigError);
+        },
+    }
+}
+
+/// Wrap `publisher` so finished jobs reach their callback URLs, exiting on failure
+fn callback_publisher<P: EventPublisher>(config: &Config, publisher: P) -> CallbackPublisher<P> {
+    match CallbackPublisher::new(publisher, &config.jobs) {
+        Ok(publisher) => publisher,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to create callback HTTP client");
+            eprintln!();
+            eprintln!("❌ Error: Failed to create the HTTP client for job callbacks");
+            eprintln!("   Reason: {}", e);
+            eprintln!();
+            exit_gracefully(ExitCode::InitializationError);
+        },
+    }
+}
+
+/// Wrap `publisher` so events also reach the configured NATS JetStream stream, exiting on
+/// failure
+async fn nats_publisher<P: EventPublisher>(config: &Config, publisher: P) -> NatsPublisher<P> {
+    match NatsPublisher::connect(publisher, &config.event_stream).await {
+        Ok(publisher) => publisher,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to connect to NATS");
+            eprintln!();
+            eprintln!("❌ Error: Failed to connect to NATS");
+            eprintln!("   Reason: {}", e);
+            eprintln!();
+            eprintln!("💡 Solutions:");
+            eprintln!("   1. Check that the NATS server is running with JetStream enabled");
+            eprintln!("   2. Verify EVENT_STREAM_NATS_URL in your .env file");
+            eprintln!("   3. Leave EVENT_STREAM_NATS_URL empty to run without an event stream");
+            eprintln!();
+            exit_gracefully(ExitCode::InitializationError);
+        },
+    }
+}
+
+/// Wrap `publisher` so high-severity events are also announced on the notification channels
+/// accounts registered, exiting on failure
+fn notification_publisher<P: EventPublisher>(
+    config: &Config,
+    pool: &PgPool,
+    publisher: P,
+) -> NotificationPublisher<P> {
+    match Notifier::new(pool.clone(), &config.notifications) {
+        Ok(notifier) => NotificationPublisher::new(publisher, notifier),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to create notifier");
+            eprintln!();
+            eprintln!("❌ Error: Failed to set up notification channels");
+            eprintln!("   Reason: {}", e);
+            eprintln!("   Check SMTP_HOST and SMTP_FROM");
+            eprintln!();
+            exit_gracefully(ExitCode::InitializationError);
+        },
+    }
+}
+
+/// Wrap `publisher` so events also reach the webhooks accounts registered, exiting on failure
+fn webhook_publisher<P: EventPublisher>(
+    config: &Config,
+    pool: &PgPool,
+    publisher: P,
+) -> WebhookPublisher<P> {
+    match WebhookSender::new(pool.clone(), &config.outbox) {
+        Ok(sender) => WebhookPublisher::new(publisher, sender),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to create webhook HTTP client");
+            eprintln!();
+            eprintln!("❌ Error: Failed to create the HTTP client for webhooks");
+            eprintln!("   Reason: {}", e);
+            eprintln!();
+            exit_gracefully(ExitCode::InitializationError);
+        },
+    }
+}
+
+/// Connect to ClickHouse and create the analytics tables, exiting on failure
+async fn connect_clickhouse(config: &Config) -> ClickHouseClient {
+    let result = match ClickHouseClient::new(&config.database) {
+        Ok(client) => client.migrate().await.map(|()| client),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to initialize ClickHouse");
+            eprintln!();
+            eprintln!("❌ Error: Failed to initialize ClickHouse");
+            eprintln!("   Reason: {}", e);
+            eprintln!();
+            eprintln!("💡 Solutions:");
+            eprintln!("   1. Check that ClickHouse is running");
+            eprintln!("   2. Verify the CLICKHOUSE_* settings in your .env file");
+            eprintln!("   3. Set CLICKHOUSE_ENABLED=false to run without analytics");
+            eprintln!();
+            exit_gracefully(ExitCode::DatabaseError);
+        },
+    }
+}
+
+/// Connect to Redis if configured, exiting on failure
+async fn connect_shared_cache(config: &Config) -> Option<ConnectionManager> {
+    match connect_redis(&config.database).await {
+        Ok(redis) => redis,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to connect to Redis");
+            eprintln!();
+            eprintln!("❌ Error: Failed to connect to Redis");
+            eprintln!("   Reason: {}", e);
+            eprintln!();
+            eprintln!("💡 Solutions:");
+            eprintln!("   1. Check that Redis is running");
+            eprintln!("   2. Verify REDIS_URL in your .env file");
+            eprintln!("   3. Unset REDIS_URL to count usage in PostgreSQL instead");
+            eprintln!();
+            exit_gracefully(ExitCode::DatabaseError);
+        },
+    }
+}
+
+/// Open the configured GeoIP databases and keep reloading them as their files change
+///
+/// A missing or unreadable file is only warned about: lookups find nothing until a later
+/// reload succeeds.
+fn load_geoip_database(config: &Config) -> GeoIpDatabase {
+    let features = &config.features;
+    if features.geoip_database_path.is_none() {
+        tracing::info!("GeoIP database not configured; IP addresses will not be located");
+    }
+    if features.geoip_asn_database_path.is_none() {
+        tracing::info!("GeoIP ASN database not configured; IP networks will not be resolved");
+    }
+    if features.postal_centroids_path.is_none() {
+        tracing::info!("Postal centroids not configured; addresses will not be located");
+    }
+    if features.geoip_database_path.is_none()
+        && features.geoip_asn_database_path.is_none()
+        && features.postal_centroids_path.is_none()
+    {
+        return GeoIpDatabase::disabled();
+    }
+    let geoip = GeoIpDatabase::new(features.geoip_database_path.as_ref().map(PathBuf::from))
+        .with_asn_database(features.geoip_asn_database_path.as_ref().map(PathBuf::from))
+        .with_postal_centroids(features.postal_centroids_path.as_ref().map(PathBuf::from));
+    match geoip.reload() {
+        Ok(_) => tracing::info!("GeoIP databases loaded"),
+        Err(e) => tracing::warn!(error = %e, "Failed to load GeoIP databases"),
+    }
+    spawn_geoip_reload(
+        geoip.clone(),
+        Duration::from_secs(config.features.geoip_reload_interval_seconds),
+    );
+    geoip
+}
+
+/// Seed the demo account and sample data, exiting on failure
+async fn seed_demo_data(database: &Database) {
+    match seed::seed(database.pool()).await {
+        Ok(SeedOutcome::Seeded {
+            account_id,
+            transactions,
+        }) => tracing::info!(
+            %account_id,
+            transactions,
+            api_key = DEMO_API_KEY,
+            sandbox_api_key = DEMO_SANDBOX_API_KEY,
+            "Seeded demo data"
+        ),
+        Ok(SeedOutcome::AlreadySeeded) => tracing::info!(
+            api_key = DEMO_API_KEY,
+            sandbox_api_key = DEMO_SANDBOX_API_KEY,
+            "Demo data already present"
+        ),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to seed demo data");
+            eprintln!();
+            eprintln!("❌ Error: Failed to seed demo data");
+            eprintln!("   Reason: {}", e);
+            eprintln!();
+            exit_gracefully(ExitCode::DatabaseError);
+        },
+    }
+}
+
 /// Handle graceful shutdown on Ctrl+C
 async fn shutdown_signal() {
     let ctrl_c = async {
@@ -0,0 +1,113 @@
+//! Quota checks for metered routes
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+
+use super::{Metered, Usage};
+use crate::{api::ApiError, auth::AuthContext, services::ServiceError, state::AppState};
+
+/// Quota units a route consumes, or `None` if it is not metered
+///
+/// `path` is the route template, as in [`crate::auth::route_access`].
+pub fn route_units(method: &Method, path: &str) -> Option<i32> {
+    let path = path.strip_prefix("/v1").unwrap_or(path);
+    match (method, path) {
+        (&Method::POST, "/transactions") => Some(1),
+        (&Method::POST, "/transactions/{transaction_id}/rescore") => Some(1),
+        _ => None,
+    }
+}
+
+/// Whether a route consumes quota, through [`meter`] or, for batches, per transaction in the
+/// handler
+///
+/// `path` is the route template, as in [`crate::auth::route_access`].
+pub fn is_metered(method: &Method, path: &str) -> bool {
+    let batch = matches!(
+        path.strip_prefix("/v1").unwrap_or(path),
+        "/transactions/batch" | "/transactions/batch/rescore" | "/transactions/batch/score"
+    );
+    route_units(method, path).is_some() || (method == Method::POST && batch)
+}
+
+/// Count the request against the caller's quota, refusing it with 429 once the quota is used up
+///
+/// Must run inside [`crate::auth::authorize`], which supplies the [`AuthContext`]. The
+/// resulting [`Usage`] is stored in the request extensions for the handler to report, and
+/// given back if the handler does not succeed. Sandbox keys are not metered.
+pub async fn meter(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let units = request
+        .extensions()
+        .get::<MatchedPath>()
+        .and_then(|path| route_units(request.method(), path.as_str()));
+    let auth = request.extensions().get::<AuthContext>().copied();
+    let (Some(units), Some(auth)) = (units, auth) else {
+        return Ok(next.run(request).await);
+    };
+    if auth.sandbox {
+        return Ok(next.run(request).await);
+    }
+
+    let usage = match state
+        .meter
+        .consume(auth.tenant(), units)
+        .await
+        .map_err(ServiceError::Database)?
+    {
+        Metered::Allowed(usage) => usage,
+        Metered::QuotaExceeded(usage) => {
+            tracing::info!(account_id = %auth.account_id, quota = usage.quota, "Quota exceeded");
+            return Err(quota_exceeded(&usage));
+        },
+    };
+
+    request.extensions_mut().insert(usage);
+    let response = next.run(request).await;
+    if !response.status().is_success() {
+        state.meter.release(auth.tenant(), &usage, units).await;
+    }
+    Ok(response)
+}
+
+/// Error refusing a request once the quota is used up
+pub fn quota_exceeded(usage: &Usage) -> ApiError {
+    ApiError::QuotaExceeded(format!(
+        "Monthly quota of {} requests used up; it resets on {}",
+        usage.quota, usage.cycle_end
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_scoring_is_metered() {
+        assert_eq!(route_units(&Method::POST, "/v1/transactions"), Some(1));
+        assert_eq!(
+            route_units(&Method::POST, "/v1/transactions/{transaction_id}/rescore"),
+            Some(1)
+        );
+        assert_eq!(route_units(&Method::GET, "/v1/transactions"), None);
+        assert_eq!(
+            route_units(&Method::GET, "/v1/transactions/{transaction_id}"),
+            None
+        );
+        assert_eq!(route_units(&Method::DELETE, "/v1/users/{user_id}"), None);
+
+        assert_eq!(route_units(&Method::POST, "/v1/transactions/batch"), None);
+        assert!(is_metered(&Method::POST, "/v1/transactions/batch"));
+        assert!(is_metered(&Method::POST, "/v1/transactions/batch/rescore"));
+        assert!(is_metered(&Method::POST, "/v1/transactions/batch/score"));
+        assert!(is_metered(&Method::POST, "/v1/transactions"));
+        assert!(!is_metered(&Method::GET, "/v1/transactions"));
+    }
+}
@@ -0,0 +1,396 @@
+//! Monthly quota metering
+//!
+//! Every scoring request counts against the account's `monthly_quota`. With Redis configured the
+//! count is kept in a per-cycle Redis counter, seeded from PostgreSQL on first use and copied
+//! back by [`sync::spawn_usage_sync`]; without it, PostgreSQL is updated directly. Either way
+//! the check and the increment happen in one atomic step, so concurrent requests cannot
+//! overshoot the quota together. Usage is also counted per UTC day, for the usage history.
+//! The first request of a cycle to find the quota used up emits a [`QUOTA_EXHAUSTED`] event.
+
+pub mod middleware;
+pub mod sync;
+
+use chrono::{NaiveDate, Utc};
+use redis::aio::ConnectionManager;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        Tenant,
+        repositories::{AccountRepo, AccountUsageRecord, OutboxRepo, UsageRepo},
+    },
+    outbox::{QUOTA_EXHAUSTED, QuotaExhausted},
+};
+
+pub use middleware::meter;
+
+/// Prefix of the Redis usage counters, followed by `{account_id}:{billing_cycle_start}`
+pub const USAGE_KEY_PREFIX: &str = "fusegu:usage:";
+
+/// Prefix of the Redis daily usage counters, followed by `{account_id}:{day}`
+pub const DAILY_USAGE_KEY_PREFIX: &str = "fusegu:usage-daily:";
+
+/// Lifetime of a Redis usage counter, comfortably longer than a billing cycle plus the sync
+/// interval
+const USAGE_KEY_TTL_SECONDS: u64 = 40 * 24 * 60 * 60;
+
+/// Rollovers attempted for one request before giving up on reaching the current cycle
+const MAX_ROLLOVERS: usize = 24;
+
+/// Quota and usage of an account's current billing cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    /// Scoring requests allowed per billing cycle
+    pub quota: i64,
+    /// Scoring requests used so far, including the one being metered
+    pub used: i64,
+    /// First day of the billing cycle
+    pub cycle_start: NaiveDate,
+    /// Day the quota resets
+    pub cycle_end: NaiveDate,
+    /// UTC day the usage was recorded on
+    pub day: NaiveDate,
+}
+
+impl Usage {
+    /// Scoring requests left in the cycle
+    pub fn remaining(&self) -> i64 {
+        (self.quota - self.used).max(0)
+    }
+}
+
+impl Usage {
+    fn new(record: AccountUsageRecord, day: NaiveDate) -> Self {
+        Self {
+            quota: i64::from(record.monthly_quota),
+            used: i64::from(record.queries_used_this_month),
+            cycle_start: record.billing_cycle_start,
+            cycle_end: record.billing_cycle_end,
+            day,
+        }
+    }
+}
+
+/// Outcome of metering a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metered {
+    /// Usage was recorded; the request may proceed
+    Allowed(Usage),
+    /// The quota is used up; nothing was recorded
+    QuotaExceeded(Usage),
+}
+
+/// Records usage against account quotas
+#[derive(Clone)]
+pub struct Meter {
+    pool: PgPool,
+    redis: Option<ConnectionManager>,
+    enforce: bool,
+}
+
+impl std::fmt::Debug for Meter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Meter")
+            .field("redis", &self.redis.is_some())
+            .field("enforce", &self.enforce)
+            .finish()
+    }
+}
+
+impl Meter {
+    /// Meter against accounts in `pool`, counting in `redis` when available
+    ///
+    /// With `enforce` unset usage is still recorded but requests are never refused.
+    pub fn new(pool: PgPool, redis: Option<ConnectionManager>, enforce: bool) -> Self {
+        Self {
+            pool,
+            redis,
+            enforce,
+        }
+    }
+
+    /// Record `units` of usage for an account, unless that would exceed its quota
+    ///
+    /// Redis failures fall back to counting in PostgreSQL, so metering keeps working through a
+    /// Redis outage at the cost of extra database writes.
+    pub async fn consume(&self, tenant: Tenant, units: i32) -> sqlx::Result<Metered> {
+        let metered = self.count(tenant, units).await?;
+        let (Metered::Allowed(usage) | Metered::QuotaExceeded(usage)) = metered;
+        if usage.used >= usage.quota
+            && let Err(e) = self.flag_exhausted(tenant, &usage).await
+        {
+            tracing::error!(error = %e, account_id = %tenant, "Failed to flag exhausted quota");
+        }
+        Ok(metered)
+    }
+
+    async fn count(&self, tenant: Tenant, units: i32) -> sqlx::Result<Metered> {
+        let account_id = tenant.id();
+        let day = Utc::now().date_naive();
+        if let Some(redis) = &self.redis {
+            let usage = self.current_usage(redis.clone(), account_id, day).await?;
+            match self
+                .consume_redis(redis.clone(), account_id, usage, units)
+                .await
+            {
+                Ok(metered) => return Ok(metered),
+                Err(e) => tracing::warn!(
+                    error = %e,
+                    %account_id,
+                    "Redis metering failed; counting in PostgreSQL"
+                ),
+            }
+        }
+        self.consume_postgres(tenant, units, day).await
+    }
+
+    /// Give back `units` recorded by [`Meter::consume`] for a request that did not complete
+    pub async fn release(&self, tenant: Tenant, usage: &Usage, units: i32) {
+        let account_id = tenant.id();
+        if let Some(redis) = &self.redis {
+            let mut conn = redis.clone();
+            match redis::pipe()
+                .cmd("DECRBY")
+                .arg(usage_key(account_id, usage.cycle_start))
+                .arg(units)
+                .ignore()
+                .cmd("DECRBY")
+                .arg(daily_usage_key(account_id, usage.day))
+                .arg(units)
+                .ignore()
+                .query_async::<()>(&mut conn)
+                .await
+            {
+                Ok(()) => return,
+                Err(e) => tracing::warn!(
+                    error = %e,
+                    %account_id,
+                    "Redis usage release failed; releasing in PostgreSQL"
+                ),
+            }
+        }
+        if let Err(e) = self.release_postgres(tenant, usage, units).await {
+            tracing::error!(error = %e, %account_id, "Failed to release metered usage");
+        }
+    }
+
+    /// Usage of the account's current cycle, rolling over cycles that have ended
+    ///
+    /// Before a cycle is archived, its Redis count is copied to PostgreSQL so the archive
+    /// does not miss requests made since the last sync.
+    async fn current_usage(
+        &self,
+        mut conn: ConnectionManager,
+        account_id: Uuid,
+        day: NaiveDate,
+    ) -> sqlx::Result<Usage> {
+        for _ in 0..MAX_ROLLOVERS {
+            let record = AccountRepo::usage(&self.pool, account_id)
+                .await?
+                .ok_or(sqlx::Error::RowNotFound)?;
+            if !record.cycle_ended {
+                return Ok(Usage::new(record, day));
+            }
+            let key = usage_key(account_id, record.billing_cycle_start);
+            match redis::cmd("GET")
+                .arg(&key)
+                .query_async::<Option<i64>>(&mut conn)
+                .await
+            {
+                Ok(Some(used)) => {
+                    let used = i32::try_from(used.max(0)).unwrap_or(i32::MAX);
+                    AccountRepo::set_usage(
+                        &self.pool,
+                        account_id,
+                        record.billing_cycle_start,
+                        used,
+                    )
+                    .await?;
+                },
+                Ok(None) => {},
+                Err(e) => tracing::warn!(
+                    error = %e,
+                    %account_id,
+                    "Could not read usage of the ended cycle from Redis"
+                ),
+            }
+            AccountRepo::roll_over_billing_cycles(&self.pool, Some(account_id)).await?;
+        }
+        Err(sqlx::Error::Protocol(format!(
+            "billing cycle of account {account_id} is more than {MAX_ROLLOVERS} months behind"
+        )))
+    }
+
+    async fn consume_redis(
+        &self,
+        mut conn: ConnectionManager,
+        account_id: Uuid,
+        usage: Usage,
+        units: i32,
+    ) -> redis::RedisResult<Metered> {
+        let key = usage_key(account_id, usage.cycle_start);
+        let daily_key = daily_usage_key(account_id, usage.day);
+        // Seed the counter from PostgreSQL on first use in the cycle, then count atomically
+        let (used,): (i64,) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(&key)
+            .arg(usage.used)
+            .arg("NX")
+            .arg("EX")
+            .arg(USAGE_KEY_TTL_SECONDS)
+            .ignore()
+            .cmd("INCRBY")
+            .arg(&key)
+            .arg(units)
+            .cmd("INCRBY")
+            .arg(&daily_key)
+            .arg(units)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&daily_key)
+            .arg(USAGE_KEY_TTL_SECONDS)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        if self.enforce && used > usage.quota {
+            redis::pipe()
+                .cmd("DECRBY")
+                .arg(&key)
+                .arg(units)
+                .ignore()
+                .cmd("DECRBY")
+                .arg(&daily_key)
+                .arg(units)
+                .ignore()
+                .query_async::<()>(&mut conn)
+                .await?;
+            return Ok(Metered::QuotaExceeded(Usage {
+                used: used - i64::from(units),
+                ..usage
+            }));
+        }
+        Ok(Metered::Allowed(Usage { used, ..usage }))
+    }
+
+    async fn consume_postgres(
+        &self,
+        tenant: Tenant,
+        units: i32,
+        day: NaiveDate,
+    ) -> sqlx::Result<Metered> {
+        let account_id = tenant.id();
+        for _ in 0..MAX_ROLLOVERS {
+            let mut tx = self.pool.begin().await?;
+            if let Some(record) =
+                AccountRepo::consume_quota(&mut *tx, account_id, units, self.enforce).await?
+            {
+                UsageRepo::add_daily(&mut *tx, tenant, day, units).await?;
+                tx.commit().await?;
+                return Ok(Metered::Allowed(Usage::new(record, day)));
+            }
+            tx.rollback().await?;
+
+            let record = AccountRepo::usage(&self.pool, account_id)
+                .await?
+                .ok_or(sqlx::Error::RowNotFound)?;
+            if !record.cycle_ended {
+                return Ok(Metered::QuotaExceeded(Usage::new(record, day)));
+            }
+            AccountRepo::roll_over_billing_cycles(&self.pool, Some(account_id)).await?;
+        }
+        Err(sqlx::Error::Protocol(format!(
+            "billing cycle of account {account_id} is more than {MAX_ROLLOVERS} months behind"
+        )))
+    }
+
+    /// Emit [`QUOTA_EXHAUSTED`] unless it was already emitted for the usage's cycle
+    async fn flag_exhausted(&self, tenant: Tenant, usage: &Usage) -> sqlx::Result<()> {
+        let account_id = tenant.id();
+        let mut tx = self.pool.begin().await?;
+        if AccountRepo::flag_quota_exhausted(&mut *tx, account_id, usage.cycle_start).await? {
+            tracing::info!(%account_id, quota = usage.quota, "Quota used up");
+            let payload = serde_json::to_value(QuotaExhausted {
+                quota: usage.quota,
+                used: usage.used,
+                cycle_start: usage.cycle_start,
+                cycle_end: usage.cycle_end,
+            })
+            .unwrap_or_default();
+            OutboxRepo::insert(&mut *tx, account_id, QUOTA_EXHAUSTED, account_id, payload).await?;
+        }
+        tx.commit().await
+    }
+
+    async fn release_postgres(
+        &self,
+        tenant: Tenant,
+        usage: &Usage,
+        units: i32,
+    ) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        AccountRepo::release_quota(&mut *tx, tenant.id(), usage.cycle_start, units).await?;
+        UsageRepo::add_daily(&mut *tx, tenant, usage.day, -units).await?;
+        tx.commit().await
+    }
+}
+
+/// Redis key counting an account's usage in the cycle starting `cycle_start`
+pub fn usage_key(account_id: Uuid, cycle_start: NaiveDate) -> String {
+    format!("{USAGE_KEY_PREFIX}{account_id}:{cycle_start}")
+}
+
+/// Redis key counting an account's usage on `day`
+pub fn daily_usage_key(account_id: Uuid, day: NaiveDate) -> String {
+    format!("{DAILY_USAGE_KEY_PREFIX}{account_id}:{day}")
+}
+
+/// Account and date encoded in a key starting with `prefix`, or `None` for a foreign key
+pub fn parse_usage_key(prefix: &str, key: &str) -> Option<(Uuid, NaiveDate)> {
+    let (account_id, date) = key.strip_prefix(prefix)?.split_once(':')?;
+    Some((account_id.parse().ok()?, date.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_key_round_trip() {
+        let account_id = Uuid::new_v4();
+        let cycle_start = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        let key = usage_key(account_id, cycle_start);
+        assert_eq!(key, format!("fusegu:usage:{account_id}:2026-10-01"));
+        assert_eq!(
+            parse_usage_key(USAGE_KEY_PREFIX, &key),
+            Some((account_id, cycle_start))
+        );
+        assert_eq!(
+            parse_usage_key(
+                DAILY_USAGE_KEY_PREFIX,
+                &daily_usage_key(account_id, cycle_start)
+            ),
+            Some((account_id, cycle_start))
+        );
+        assert_eq!(parse_usage_key(USAGE_KEY_PREFIX, "fusegu:nonce:abc"), None);
+        assert_eq!(
+            parse_usage_key(USAGE_KEY_PREFIX, "fusegu:usage:not-a-uuid:2026-10-01"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_remaining_never_negative() {
+        let usage = Usage {
+            quota: 100,
+            used: 120,
+            cycle_start: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            cycle_end: NaiveDate::from_ymd_opt(2026, 11, 1).unwrap(),
+            day: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(),
+        };
+        assert_eq!(usage.remaining(), 0);
+        assert_eq!(Usage { used: 40, ..usage }.remaining(), 60);
+    }
+}
@@ -0,0 +1,119 @@
+//! Periodic persistence of usage counters
+//!
+//! Redis holds the live counts; this job copies them to `accounts.queries_used_this_month` and
+//! `usage_daily` so usage survives a Redis flush and is visible to anything reading
+//! PostgreSQL. It then rolls over billing cycles that have ended, archiving and resetting usage
+//! for accounts that have not made a request since.
+
+use chrono::NaiveDate;
+use redis::aio::ConnectionManager;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::{DAILY_USAGE_KEY_PREFIX, USAGE_KEY_PREFIX, parse_usage_key};
+use crate::{
+    config::MeteringConfig,
+    database::{
+        Tenant,
+        repositories::{AccountRepo, UsageRepo},
+    },
+};
+
+/// Keys requested per SCAN round trip
+const SCAN_COUNT: usize = 500;
+
+/// Spawn a background task that periodically syncs usage and rolls over billing cycles
+pub fn spawn_usage_sync(
+    pool: PgPool,
+    redis: Option<ConnectionManager>,
+    config: MeteringConfig,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = std::time::Duration::from_secs(config.sync_interval_seconds);
+        loop {
+            // Sync first, so cycles are archived with their final counts
+            if let Some(redis) = &redis {
+                match sync_usage(&pool, redis.clone()).await {
+                    Ok(synced) => tracing::debug!(synced, "Synced usage counters"),
+                    Err(e) => tracing::error!(error = %e, "Usage sync failed"),
+                }
+            }
+            match roll_over_billing_cycles(&pool).await {
+                Ok(0) => {},
+                Ok(accounts) => tracing::info!(accounts, "Rolled over billing cycles"),
+                Err(e) => tracing::error!(error = %e, "Billing cycle rollover failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Advance every ended billing cycle to the current one, returning how many rollovers were made
+pub async fn roll_over_billing_cycles(pool: &PgPool) -> sqlx::Result<u64> {
+    let mut total = 0;
+    loop {
+        let moved = AccountRepo::roll_over_billing_cycles(pool, None).await?;
+        if moved == 0 {
+            return Ok(total);
+        }
+        total += moved;
+    }
+}
+
+/// Copy every Redis usage counter to PostgreSQL, returning how many were copied
+///
+/// Counters for cycles that have since rolled over no longer match the account's cycle start
+/// and are skipped; they expire from Redis on their own.
+pub async fn sync_usage(pool: &PgPool, mut conn: ConnectionManager) -> anyhow::Result<usize> {
+    let mut synced = 0;
+    for (account_id, cycle_start, used) in read_counters(&mut conn, USAGE_KEY_PREFIX).await? {
+        AccountRepo::set_usage(pool, account_id, cycle_start, used).await?;
+        synced += 1;
+    }
+    for (account_id, day, queries) in read_counters(&mut conn, DAILY_USAGE_KEY_PREFIX).await? {
+        UsageRepo::set_daily(pool, Tenant::trusted(account_id), day, queries).await?;
+        synced += 1;
+    }
+    Ok(synced)
+}
+
+/// Read every counter under `prefix` as account, date, and count
+async fn read_counters(
+    conn: &mut ConnectionManager,
+    prefix: &str,
+) -> redis::RedisResult<Vec<(Uuid, NaiveDate, i32)>> {
+    let pattern = format!("{prefix}*");
+    let mut cursor = 0u64;
+    let mut counters = Vec::new();
+    loop {
+        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(&pattern)
+            .arg("COUNT")
+            .arg(SCAN_COUNT)
+            .query_async(conn)
+            .await?;
+
+        for key in keys {
+            let Some((account_id, date)) = parse_usage_key(prefix, &key) else {
+                continue;
+            };
+            let Some(count) = redis::cmd("GET")
+                .arg(&key)
+                .query_async::<Option<i64>>(conn)
+                .await?
+            else {
+                continue;
+            };
+            let count = i32::try_from(count.max(0)).unwrap_or(i32::MAX);
+            counters.push((account_id, date, count));
+        }
+
+        if next == 0 {
+            return Ok(counters);
+        }
+        cursor = next;
+    }
+}
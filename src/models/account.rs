@@ -0,0 +1,514 @@
+//! Account settings and subscription status
+
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::{
+    case::CaseAssignment,
+    common::{Link, Links},
+    transaction::{Disposition, RiskLevel},
+};
+
+/// Shortest an automatic block can be set to last, in minutes
+const MIN_AUTO_BLOCK_TTL_MINUTES: i32 = 5;
+/// Longest an automatic block can be set to last, in minutes: 30 days
+const MAX_AUTO_BLOCK_TTL_MINUTES: i32 = 43_200;
+
+/// Subscription plan, in ascending order
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum SubscriptionTier {
+    /// Core scoring
+    Free,
+    /// Adds transaction insights, batch endpoints, and webhooks
+    Pro,
+    /// Adds risk factor explanations and stored requests
+    Enterprise,
+}
+
+impl SubscriptionTier {
+    /// Name used in storage and error messages
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SubscriptionTier::Free => "free",
+            SubscriptionTier::Pro => "pro",
+            SubscriptionTier::Enterprise => "enterprise",
+        }
+    }
+
+    /// Whether the tier includes `feature`
+    pub fn can_access_feature(self, feature: Feature) -> bool {
+        self >= feature.minimum_tier()
+    }
+}
+
+impl fmt::Display for SubscriptionTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Where an account is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum AccountStatus {
+    /// In use
+    Active,
+    /// Authenticates and can read its data, but may not score transactions
+    Suspended,
+    /// May only manage its own account; deleted with all its data once the retention period
+    /// ends
+    Closed,
+}
+
+impl AccountStatus {
+    /// Name used in storage and messages
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AccountStatus::Active => "active",
+            AccountStatus::Suspended => "suspended",
+            AccountStatus::Closed => "closed",
+        }
+    }
+
+    /// Whether an account in this status may move to `to`
+    ///
+    /// Active accounts may be suspended, active and suspended accounts closed, and suspended
+    /// and closed accounts reactivated.
+    pub fn can_become(self, to: AccountStatus) -> bool {
+        use AccountStatus::*;
+        matches!(
+            (self, to),
+            (Active, Suspended) | (Active | Suspended, Closed) | (Suspended | Closed, Active)
+        )
+    }
+}
+
+impl fmt::Display for AccountStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Capability reserved for paid tiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Card, device, email, address, and phone insights for a transaction
+    Insights,
+    /// Explanations of the factors behind a risk score
+    Factors,
+    /// Submitting many transactions or users in one call
+    Batch,
+    /// Event delivery to customer endpoints
+    Webhooks,
+    /// The stored, redacted requests transactions were scored on
+    RawRequests,
+}
+
+impl Feature {
+    /// Lowest tier that includes the feature
+    pub fn minimum_tier(self) -> SubscriptionTier {
+        match self {
+            Feature::Insights | Feature::Batch | Feature::Webhooks => SubscriptionTier::Pro,
+            Feature::Factors | Feature::RawRequests => SubscriptionTier::Enterprise,
+        }
+    }
+
+    /// Name used in error messages
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Feature::Insights => "insights",
+            Feature::Factors => "factors",
+            Feature::Batch => "batch",
+            Feature::Webhooks => "webhooks",
+            Feature::RawRequests => "raw_requests",
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How risk levels translate into the disposition returned for live transactions
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum DispositionPolicy {
+    /// Accept low, review medium, and reject high and very high risk
+    #[default]
+    Standard,
+    /// Like `standard`, but hold high and very high risk for review instead of rejecting
+    Review,
+    /// Accept everything; scores are recorded for evaluation only
+    Monitor,
+}
+
+impl DispositionPolicy {
+    /// Disposition for a transaction at `level` under this policy
+    pub fn disposition(self, level: RiskLevel) -> Disposition {
+        match (self, Disposition::for_risk_level(level)) {
+            (DispositionPolicy::Monitor, _) => Disposition::Accept,
+            (DispositionPolicy::Review, Disposition::Reject) => Disposition::Review,
+            (_, disposition) => disposition,
+        }
+    }
+}
+
+/// Events the account is notified about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct NotificationSettings {
+    /// API keys approaching their expiry date
+    pub key_expiry: bool,
+    /// Spikes in fraud metrics found by anomaly detection
+    pub anomalies: bool,
+}
+
+/// Automatic blocking of entities behind repeated critical velocity breaches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AutoBlockSettings {
+    /// Whether IP addresses, devices, and cards that keep breaching critical velocity rules
+    /// are put on the blocklist automatically
+    pub enabled: bool,
+    /// Minutes an automatic block lasts
+    #[schema(example = 60)]
+    pub ttl_minutes: i32,
+}
+
+/// Account settings, subscription, and usage of the current billing cycle
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Account {
+    /// Public account identifier
+    #[schema(example = "acme-payments")]
+    pub account_id: String,
+    /// Subscription tier
+    pub subscription_tier: SubscriptionTier,
+    /// Whether this is the sandbox namespace of a live account
+    pub sandbox: bool,
+    /// Lifecycle status; a sandbox account has the status of its live account
+    pub status: AccountStatus,
+    /// When the status last changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_changed_at: Option<DateTime<Utc>>,
+    /// When the closed account and all its data will be deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
+    /// Address for operational notices
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "fraud-ops@example.com")]
+    pub contact_email: Option<String>,
+    /// How risk levels translate into dispositions
+    pub disposition_policy: DispositionPolicy,
+    /// Events the account is notified about
+    pub notifications: NotificationSettings,
+    /// Automatic blocking of entities behind repeated velocity breaches
+    pub auto_block: AutoBlockSettings,
+    /// Whether the names on transactions are screened against sanctions lists
+    pub sanctions_screening: bool,
+    /// How new review cases reach reviewers
+    pub case_assignment: CaseAssignment,
+    /// Prepaid funds left
+    #[schema(example = 9850.75)]
+    pub funds_remaining: f64,
+    /// Scoring requests allowed per billing cycle
+    #[schema(example = 100000)]
+    pub monthly_quota: i32,
+    /// Scoring requests used in the current billing cycle
+    #[schema(example = 15642)]
+    pub queries_used_this_month: i32,
+    /// Scoring requests left in the current billing cycle
+    #[schema(example = 84358)]
+    pub queries_remaining_this_month: i32,
+    /// First day of the current billing cycle
+    pub billing_cycle_start: NaiveDate,
+    /// Day the quota resets
+    pub billing_cycle_end: NaiveDate,
+    /// When the account was created
+    pub created_at: DateTime<Utc>,
+    /// When the account was last changed
+    pub updated_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl Account {
+    /// Whether the account's tier includes `feature`
+    pub fn can_access_feature(&self, feature: Feature) -> bool {
+        self.subscription_tier.can_access_feature(feature)
+    }
+
+    /// Links of the account resource
+    pub fn links() -> Links {
+        Links {
+            self_link: Some(Link::new("/v1/account")),
+            ..Links::default()
+        }
+    }
+}
+
+/// Metered requests on one UTC day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DailyUsage {
+    /// The day
+    pub date: NaiveDate,
+    /// Metered requests that day
+    #[schema(example = 523)]
+    pub queries: i32,
+}
+
+/// Usage and estimated charges of one billing cycle
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BillingCycleUsage {
+    /// First day of the cycle
+    pub cycle_start: NaiveDate,
+    /// Day the next cycle starts
+    pub cycle_end: NaiveDate,
+    /// Whether this is the cycle in progress
+    pub current: bool,
+    /// Subscription tier billed for the cycle
+    pub subscription_tier: SubscriptionTier,
+    /// Requests included in the cycle
+    #[schema(example = 100000)]
+    pub monthly_quota: i32,
+    /// Metered requests in the cycle
+    #[schema(example = 15642)]
+    pub queries_used: i32,
+    /// Requests beyond the quota
+    #[schema(example = 0)]
+    pub overage: i32,
+    /// Tier fee plus overage charges; final figures are on the invoice
+    #[schema(example = 99.0)]
+    pub estimated_charges: f64,
+    /// Metered requests per day, including days without any; for the current cycle, up to
+    /// today
+    pub daily: Vec<DailyUsage>,
+}
+
+/// Usage and estimated charges of the calling account's recent billing cycles
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UsageHistory {
+    /// Currency of the estimated charges
+    #[schema(example = "USD")]
+    pub currency: String,
+    /// Billing cycles, current first
+    pub cycles: Vec<BillingCycleUsage>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Query parameters for the usage history
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UsageHistoryQuery {
+    /// Number of billing cycles to return, including the current one (1-12, default: 3)
+    pub cycles: Option<i64>,
+}
+
+/// Changes to the account's settings; fields left out are kept as they are
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+#[schema(example = json!({
+    "contact_email": "fraud-ops@example.com",
+    "disposition_policy": "review",
+    "notifications": { "anomalies": false }
+}))]
+pub struct AccountUpdate {
+    /// Address for operational notices; an empty string removes it
+    pub contact_email: Option<String>,
+    /// How risk levels translate into dispositions
+    pub disposition_policy: Option<DispositionPolicy>,
+    /// Notification settings to change
+    pub notifications: Option<NotificationSettingsUpdate>,
+    /// Automatic blocking settings to change
+    pub auto_block: Option<AutoBlockSettingsUpdate>,
+    /// Whether the names on transactions are screened against sanctions lists
+    pub sanctions_screening: Option<bool>,
+    /// How new review cases reach reviewers
+    pub case_assignment: Option<CaseAssignment>,
+}
+
+/// Suspension, closure, or reactivation of the calling account
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StatusChange {
+    /// Why the status is changing, kept in the audit log
+    #[schema(example = "Investigating a leaked API key")]
+    pub reason: Option<String>,
+}
+
+impl StatusChange {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .reason
+            .as_deref()
+            .is_some_and(|r| r.chars().count() > 1000)
+        {
+            return Err("reason must be at most 1000 characters".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Changes to notification settings; fields left out are kept as they are
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationSettingsUpdate {
+    /// API keys approaching their expiry date
+    pub key_expiry: Option<bool>,
+    /// Spikes in fraud metrics found by anomaly detection
+    pub anomalies: Option<bool>,
+}
+
+/// Changes to automatic blocking; fields left out are kept as they are
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AutoBlockSettingsUpdate {
+    /// Whether entities are blocked automatically
+    pub enabled: Option<bool>,
+    /// Minutes an automatic block lasts, from 5 to 43200 (30 days)
+    pub ttl_minutes: Option<i32>,
+}
+
+impl AccountUpdate {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(email) = self.contact_email.as_deref().filter(|e| !e.is_empty()) {
+            if email.len() > 255 {
+                return Err("contact_email must be at most 255 characters".to_string());
+            }
+            let valid = email.split_once('@').is_some_and(|(local, domain)| {
+                !local.is_empty() && domain.contains('.') && !domain.starts_with('.')
+            }) && !email.chars().any(char::is_whitespace);
+            if !valid {
+                return Err("contact_email is not a valid email address".to_string());
+            }
+        }
+        if let Some(ttl_minutes) = self.auto_block.and_then(|a| a.ttl_minutes)
+            && !(MIN_AUTO_BLOCK_TTL_MINUTES..=MAX_AUTO_BLOCK_TTL_MINUTES).contains(&ttl_minutes)
+        {
+            return Err(format!(
+                "auto_block.ttl_minutes must be between {MIN_AUTO_BLOCK_TTL_MINUTES} and \
+                 {MAX_AUTO_BLOCK_TTL_MINUTES}"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the update changes nothing
+    pub fn is_empty(&self) -> bool {
+        let notifications = self
+            .notifications
+            .is_none_or(|n| n.key_expiry.is_none() && n.anomalies.is_none());
+        let auto_block = self
+            .auto_block
+            .is_none_or(|a| a.enabled.is_none() && a.ttl_minutes.is_none());
+        self.contact_email.is_none()
+            && self.disposition_policy.is_none()
+            && notifications
+            && auto_block
+            && self.sanctions_screening.is_none()
+            && self.case_assignment.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disposition_policies() {
+        use DispositionPolicy::*;
+        assert_eq!(
+            Standard.disposition(RiskLevel::VeryHigh),
+            Disposition::Reject
+        );
+        assert_eq!(Review.disposition(RiskLevel::VeryHigh), Disposition::Review);
+        assert_eq!(Review.disposition(RiskLevel::Low), Disposition::Accept);
+        assert_eq!(Monitor.disposition(RiskLevel::Medium), Disposition::Accept);
+    }
+
+    #[test]
+    fn test_feature_gating_by_tier() {
+        use SubscriptionTier::*;
+        assert!(!Free.can_access_feature(Feature::Insights));
+        assert!(Pro.can_access_feature(Feature::Insights));
+        assert!(Pro.can_access_feature(Feature::Webhooks));
+        assert!(!Pro.can_access_feature(Feature::Factors));
+        assert!(Enterprise.can_access_feature(Feature::Factors));
+        assert!(!Pro.can_access_feature(Feature::RawRequests));
+        assert!(Enterprise.can_access_feature(Feature::RawRequests));
+        assert!(Enterprise.can_access_feature(Feature::Batch));
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        use AccountStatus::*;
+        assert!(Active.can_become(Suspended));
+        assert!(Active.can_become(Closed));
+        assert!(Suspended.can_become(Closed));
+        assert!(Suspended.can_become(Active));
+        assert!(Closed.can_become(Active));
+        assert!(!Closed.can_become(Suspended));
+        assert!(!Active.can_become(Active));
+        assert!(!Closed.can_become(Closed));
+    }
+
+    #[test]
+    fn test_update_validation() {
+        let update = |email: &str| AccountUpdate {
+            contact_email: Some(email.to_string()),
+            ..AccountUpdate::default()
+        };
+        assert!(update("ops@example.com").validate().is_ok());
+        assert!(update("").validate().is_ok());
+        assert!(update("ops@localhost").validate().is_err());
+        assert!(update("ops @example.com").validate().is_err());
+        assert!(update("@example.com").validate().is_err());
+        assert!(AccountUpdate::default().is_empty());
+        assert!(
+            !AccountUpdate {
+                notifications: Some(NotificationSettingsUpdate {
+                    anomalies: Some(false),
+                    ..Default::default()
+                }),
+                ..AccountUpdate::default()
+            }
+            .is_empty()
+        );
+
+        let ttl = |ttl_minutes| AccountUpdate {
+            auto_block: Some(AutoBlockSettingsUpdate {
+                ttl_minutes: Some(ttl_minutes),
+                ..Default::default()
+            }),
+            ..AccountUpdate::default()
+        };
+        assert!(ttl(5).validate().is_ok());
+        assert!(ttl(43_200).validate().is_ok());
+        assert!(ttl(4).validate().is_err());
+        assert!(ttl(43_201).validate().is_err());
+        assert!(!ttl(60).is_empty());
+        assert!(
+            AccountUpdate {
+                auto_block: Some(AutoBlockSettingsUpdate::default()),
+                ..AccountUpdate::default()
+            }
+            .is_empty()
+        );
+    }
+}
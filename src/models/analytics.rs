@@ -0,0 +1,717 @@
+//! Analytics request and response models
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{
+    common::{Links, Pagination},
+    transaction::Disposition,
+};
+
+/// Time window covered by an analytics request, ending now
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum AnalyticsPeriod {
+    /// Last 24 hours
+    #[serde(rename = "last_24h")]
+    Last24h,
+    /// Last 7 days
+    #[serde(rename = "last_7d")]
+    Last7d,
+    /// Last 30 days
+    #[default]
+    #[serde(rename = "last_30d")]
+    Last30d,
+    /// Last 90 days
+    #[serde(rename = "last_90d")]
+    Last90d,
+}
+
+impl AnalyticsPeriod {
+    /// Length of the window
+    pub fn duration(self) -> Duration {
+        match self {
+            AnalyticsPeriod::Last24h => Duration::hours(24),
+            AnalyticsPeriod::Last7d => Duration::days(7),
+            AnalyticsPeriod::Last30d => Duration::days(30),
+            AnalyticsPeriod::Last90d => Duration::days(90),
+        }
+    }
+}
+
+/// Bucket size of the time series
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    /// Hourly buckets
+    Hour,
+    /// Daily buckets (UTC midnight)
+    #[default]
+    Day,
+    /// Weekly buckets starting on Monday (UTC)
+    Week,
+}
+
+/// Dimension to break the summary down by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyticsGroupBy {
+    /// Type of event
+    EventType,
+    /// Risk level
+    RiskLevel,
+    /// Recommended action
+    Disposition,
+    /// Shop or merchant identifier
+    ShopId,
+}
+
+/// Query parameters for analytics
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AnalyticsQuery {
+    /// Time window to analyse (default: last_30d)
+    pub period: Option<AnalyticsPeriod>,
+    /// Time series bucket size (default: day)
+    pub granularity: Option<Granularity>,
+    /// Break the summary down by this dimension
+    pub group_by: Option<AnalyticsGroupBy>,
+    /// Only include transactions from this shop
+    pub shop_id: Option<String>,
+}
+
+/// Start and end of the analysed window
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AnalyticsRange {
+    /// Inclusive start of the window
+    pub start: DateTime<Utc>,
+    /// Exclusive end of the window
+    pub end: DateTime<Utc>,
+}
+
+/// Transaction counts per risk level
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RiskDistribution {
+    /// Low-risk transactions
+    #[schema(example = 12456)]
+    pub low: u64,
+    /// Medium-risk transactions
+    #[schema(example = 2854)]
+    pub medium: u64,
+    /// High-risk transactions
+    #[schema(example = 298)]
+    pub high: u64,
+    /// Very-high-risk transactions
+    #[schema(example = 34)]
+    pub very_high: u64,
+}
+
+/// Transaction counts per recommended action
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DispositionCounts {
+    /// Accepted transactions
+    #[schema(example = 485)]
+    pub accept: u64,
+    /// Rejected transactions
+    #[schema(example = 34)]
+    pub reject: u64,
+    /// Transactions sent to manual review
+    #[schema(example = 23)]
+    pub review: u64,
+    /// Test transactions
+    #[schema(example = 0)]
+    pub test: u64,
+}
+
+impl DispositionCounts {
+    /// Count one more transaction with `disposition`
+    pub fn record(&mut self, disposition: Disposition) {
+        match disposition {
+            Disposition::Accept => self.accept += 1,
+            Disposition::Reject => self.reject += 1,
+            Disposition::Review => self.review += 1,
+            Disposition::Test => self.test += 1,
+        }
+    }
+}
+
+/// Totals over the whole window
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AnalyticsSummary {
+    /// Transactions scored in the window
+    #[schema(example = 15642)]
+    pub total_transactions: u64,
+    /// Distinct users with at least one transaction
+    #[schema(example = 3456)]
+    pub total_users: u64,
+    /// Mean risk score
+    #[schema(example = 12.45)]
+    pub average_risk_score: f64,
+    /// Transactions per risk level
+    pub risk_distribution: RiskDistribution,
+    /// Transactions per recommended action
+    pub disposition_counts: DispositionCounts,
+}
+
+/// Transactions scored for the account during one second, pushed on the live stream
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LiveCounts {
+    /// Start of the one-second window
+    pub timestamp: DateTime<Utc>,
+    /// Transactions scored
+    #[schema(example = 42)]
+    pub transaction_count: u64,
+    /// Those transactions per recommended action
+    pub disposition_counts: DispositionCounts,
+}
+
+/// One bucket of the time series
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TimeSeriesPoint {
+    /// Start of the bucket
+    pub timestamp: DateTime<Utc>,
+    /// Transactions scored in the bucket
+    #[schema(example = 542)]
+    pub transaction_count: u64,
+    /// Distinct users in the bucket
+    #[schema(example = 123)]
+    pub user_count: u64,
+    /// Mean risk score in the bucket
+    #[schema(example = 11.23)]
+    pub average_risk_score: f64,
+    /// Transactions per recommended action
+    pub disposition_counts: DispositionCounts,
+}
+
+/// Summary for one value of the `group_by` dimension
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AnalyticsGroup {
+    /// Value of the grouped dimension (empty when the value was not supplied)
+    #[schema(example = "purchase")]
+    pub key: String,
+    /// Transactions in the group
+    #[schema(example = 9812)]
+    pub transaction_count: u64,
+    /// Mean risk score in the group
+    #[schema(example = 14.02)]
+    pub average_risk_score: f64,
+    /// Transactions per risk level
+    pub risk_distribution: RiskDistribution,
+}
+
+/// Value of the purchases accepted in the window, in one currency, net of what was refunded or
+/// cancelled since
+///
+/// Reversals count when they name the purchase in `event.original_transaction_id`; one without
+/// an amount undoes the whole purchase.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Exposure {
+    /// ISO 4217 currency code
+    #[schema(example = "USD")]
+    pub currency: String,
+    /// Accepted purchases
+    #[schema(example = 9812)]
+    pub purchases: u64,
+    /// Total amount of those purchases
+    #[schema(example = 1_284_310.5)]
+    pub purchase_amount: f64,
+    /// Part of that amount refunded or cancelled
+    #[schema(example = 61_022.75)]
+    pub refunded_amount: f64,
+    /// Amount still at risk
+    #[schema(example = 1_223_287.75)]
+    pub exposure: f64,
+}
+
+/// Transaction analytics for the calling account
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Analytics {
+    /// Window the figures cover
+    pub period: AnalyticsRange,
+    /// Bucket size of `time_series`
+    pub granularity: Granularity,
+    /// Totals over the window
+    pub summary: AnalyticsSummary,
+    /// Per-bucket figures, oldest first, with empty buckets included
+    pub time_series: Vec<TimeSeriesPoint>,
+    /// Accepted purchase value per currency, largest first
+    pub exposure: Vec<Exposure>,
+    /// Breakdown by the requested `group_by` dimension, largest group first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<AnalyticsGroup>>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Order of the shops in a per-shop breakdown, highest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ShopSort {
+    /// Most transactions first
+    #[default]
+    TransactionCount,
+    /// Highest share of rejected transactions first
+    RejectRate,
+    /// Highest share of transactions reported as fraud first
+    FraudRate,
+    /// Highest mean risk score first
+    AverageRiskScore,
+}
+
+/// Query parameters for the per-shop breakdown
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ShopAnalyticsQuery {
+    /// Time window to analyse (default: last_30d)
+    pub period: Option<AnalyticsPeriod>,
+    /// Order of the shops (default: transaction_count)
+    pub sort: Option<ShopSort>,
+    /// Leave out shops with fewer transactions in the window (default 1)
+    pub min_transactions: Option<u64>,
+    /// Maximum number of shops to return (1-500, default 50)
+    pub limit: Option<u32>,
+}
+
+/// Kind of entity ranked by the risky-entity leaderboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    /// IP address
+    Ip,
+    /// Device
+    Device,
+    /// Email domain
+    EmailDomain,
+    /// Card issuer identification number
+    Bin,
+}
+
+/// Query parameters for the risky-entity leaderboard
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TopEntitiesQuery {
+    /// Kind of entity to rank
+    pub entity: EntityKind,
+    /// Time window to analyse (default: last_30d)
+    pub period: Option<AnalyticsPeriod>,
+    /// Leave out entities with fewer transactions in the window (default 1)
+    pub min_transactions: Option<u64>,
+    /// Maximum number of entities to return (1-500, default 50)
+    pub limit: Option<u32>,
+}
+
+/// Risk figures for one entity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RiskyEntity {
+    /// IP address, device ID, email domain, or BIN
+    #[schema(example = "203.0.113.42")]
+    pub value: String,
+    /// Transactions involving the entity
+    #[schema(example = 37)]
+    pub transaction_count: u64,
+    /// Distinct users behind those transactions
+    #[schema(example = 9)]
+    pub user_count: u64,
+    /// Mean risk score of those transactions
+    #[schema(example = 78.3)]
+    pub average_risk_score: f64,
+    /// Highest risk score of those transactions
+    #[schema(example = 96.5)]
+    pub max_risk_score: f64,
+    /// Rule hits across those transactions
+    #[schema(example = 64)]
+    pub rule_hits: u64,
+    /// Those transactions that were rejected
+    #[schema(example = 21)]
+    pub rejected: u64,
+    /// Most recent of those transactions
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Entities with the highest average risk score over a window
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TopEntities {
+    /// Window covered
+    pub period: AnalyticsRange,
+    /// Kind of entity ranked
+    pub entity: EntityKind,
+    /// Entities, riskiest first
+    pub entities: Vec<RiskyEntity>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Fraud figures for one shop
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ShopStats {
+    /// Shop or merchant identifier
+    #[schema(example = "shop_eu_42")]
+    pub shop_id: String,
+    /// Transactions scored for the shop
+    #[schema(example = 1204)]
+    pub transaction_count: u64,
+    /// Distinct users with at least one transaction at the shop
+    #[schema(example = 803)]
+    pub user_count: u64,
+    /// Mean risk score
+    #[schema(example = 17.8)]
+    pub average_risk_score: f64,
+    /// Share of transactions rejected
+    #[schema(example = 0.041)]
+    pub reject_rate: f64,
+    /// Share of transactions sent to manual review
+    #[schema(example = 0.066)]
+    pub review_rate: f64,
+    /// Transactions later reported as fraud
+    #[schema(example = 9)]
+    pub fraud_reports: u64,
+    /// Share of transactions later reported as fraud
+    #[schema(example = 0.0075)]
+    pub fraud_rate: f64,
+    /// Transactions per risk level
+    pub risk_distribution: RiskDistribution,
+    /// Transactions per recommended action
+    pub disposition_counts: DispositionCounts,
+}
+
+/// Per-shop fraud figures for the calling account
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ShopAnalytics {
+    /// Window the figures cover
+    pub period: AnalyticsRange,
+    /// Shops in the requested order; transactions without a shop are left out
+    pub shops: Vec<ShopStats>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Query parameters for cohort analysis
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CohortQuery {
+    /// Number of calendar months to cover, including the current one (1-24, default 6)
+    pub months: Option<u32>,
+}
+
+/// Activity of a cohort in one calendar month
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CohortMonth {
+    /// First day of the month
+    pub month: NaiveDate,
+    /// Whole months between the cohort's signup month and this one
+    #[schema(example = 2)]
+    pub months_since_signup: u32,
+    /// Cohort members with at least one transaction in the month
+    #[schema(example = 412)]
+    pub active_users: u64,
+    /// Transactions by cohort members in the month
+    #[schema(example = 1250)]
+    pub transaction_count: u64,
+    /// Mean risk score of those transactions
+    #[schema(example = 14.2)]
+    pub average_risk_score: f64,
+    /// Share of those transactions that were accepted
+    #[schema(example = 0.93)]
+    pub success_rate: f64,
+    /// Those transactions later reported as chargebacks
+    #[schema(example = 3)]
+    pub chargebacks: u64,
+}
+
+/// Users whose first transaction fell in the same month, followed over time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Cohort {
+    /// First day of the month in which the cohort's users signed up
+    pub signup_month: NaiveDate,
+    /// Users in the cohort
+    #[schema(example = 980)]
+    pub users: u64,
+    /// One entry per month from the signup month to the current month, with empty months
+    /// included
+    pub months: Vec<CohortMonth>,
+}
+
+/// Cohort analysis of the calling account's users
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CohortAnalysis {
+    /// First day of the earliest month covered
+    pub start_month: NaiveDate,
+    /// Cohorts, oldest first
+    pub cohorts: Vec<Cohort>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Hourly metric watched by anomaly detection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum AnomalyMetric {
+    /// Share of transactions rejected
+    RejectRate,
+    /// Mean risk score
+    AverageRiskScore,
+    /// Share of transactions on which a given rule fired
+    RuleHitRate,
+}
+
+/// Hour in which a metric spiked well above its recent baseline
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Anomaly {
+    /// Unique anomaly identifier
+    pub id: Uuid,
+    /// Metric that spiked
+    pub metric: AnomalyMetric,
+    /// Rule the hit rate refers to, for `rule_hit_rate` anomalies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "CVV_MISMATCH")]
+    pub rule_code: Option<String>,
+    /// Start of the hour in which the spike occurred
+    pub bucket_start: DateTime<Utc>,
+    /// Value of the metric in that hour
+    #[schema(example = 0.42)]
+    pub observed_value: f64,
+    /// Mean of the metric over the baseline window
+    #[schema(example = 0.05)]
+    pub baseline_mean: f64,
+    /// Standard deviation of the metric over the baseline window
+    #[schema(example = 0.03)]
+    pub baseline_stddev: f64,
+    /// Standard deviations between the observed value and the baseline mean
+    #[schema(example = 12.3)]
+    pub z_score: f64,
+    /// Transactions scored in that hour
+    #[schema(example = 240)]
+    pub transaction_count: i64,
+    /// When the anomaly was detected
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Query parameters for listing anomalies
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListAnomaliesQuery {
+    /// Maximum number of anomalies to return (1-100, default 20)
+    pub limit: Option<i64>,
+    /// Number of anomalies to skip
+    pub offset: Option<i64>,
+}
+
+/// Page of anomalies, most recent hour first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AnomalyList {
+    /// Anomalies on this page
+    pub anomalies: Vec<Anomaly>,
+    /// Pagination metadata
+    pub pagination: Pagination,
+    /// Navigation links
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Query parameters for outcome metrics
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OutcomesQuery {
+    /// Window of scored transactions to evaluate (default: last_30d)
+    pub period: Option<AnalyticsPeriod>,
+}
+
+/// How the account's decisions held up against the outcomes reported afterwards
+///
+/// A transaction counts as fraud when it was reported as a chargeback, suspected fraud, or
+/// spam/abuse, and as legitimate when it was reported as not fraud. Rates are `null` when
+/// their denominator is zero.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct OutcomeSummary {
+    /// Transactions scored in the window
+    #[schema(example = 15642)]
+    pub total_transactions: u64,
+    /// Transactions with a reported outcome
+    #[schema(example = 412)]
+    pub reported_transactions: u64,
+    /// Transactions reported as fraud
+    #[schema(example = 187)]
+    pub fraud_transactions: u64,
+    /// Accepted transactions
+    #[schema(example = 14380)]
+    pub accepted: u64,
+    /// Accepted transactions that were later charged back
+    #[schema(example = 41)]
+    pub accepted_then_charged_back: u64,
+    /// Share of accepted transactions that were later charged back
+    #[schema(example = 0.0029)]
+    pub accepted_chargeback_rate: Option<f64>,
+    /// Rejected transactions
+    #[schema(example = 720)]
+    pub rejected: u64,
+    /// Rejected transactions later reported as legitimate
+    #[schema(example = 18)]
+    pub rejected_then_reported_legitimate: u64,
+    /// Transactions sent to manual review
+    #[schema(example = 542)]
+    pub reviewed: u64,
+    /// Reviewed transactions with a reported outcome
+    #[schema(example = 96)]
+    pub reviewed_with_outcome: u64,
+    /// Share of reviewed transactions with an outcome that turned out legitimate
+    #[schema(example = 0.3125)]
+    pub review_overturn_rate: Option<f64>,
+    /// Share of rejected or reviewed transactions with an outcome that were fraud
+    #[schema(example = 0.83)]
+    pub precision: Option<f64>,
+    /// Share of fraud transactions that were rejected or reviewed
+    #[schema(example = 0.78)]
+    pub recall: Option<f64>,
+}
+
+/// Outcome metrics for the transactions on which one rule fired
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RuleOutcome {
+    /// Rule code
+    #[schema(example = "CVV_MISMATCH")]
+    pub rule_code: String,
+    /// Transactions on which the rule fired
+    #[schema(example = 934)]
+    pub transactions: u64,
+    /// Of those, transactions with a reported outcome
+    #[schema(example = 61)]
+    pub reported_transactions: u64,
+    /// Of those, transactions reported as fraud
+    #[schema(example = 52)]
+    pub fraud_transactions: u64,
+    /// Share of the rule's reported transactions that were fraud
+    #[schema(example = 0.85)]
+    pub precision: Option<f64>,
+    /// Share of all fraud transactions on which the rule fired
+    #[schema(example = 0.28)]
+    pub recall: Option<f64>,
+}
+
+/// Money lost to the disputes received in the window, in one currency
+///
+/// Amounts are in major units. Open disputes count towards the loss, as the processor has
+/// already withdrawn their funds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DisputeLosses {
+    /// ISO 4217 currency code, or `null` for disputes reported without an amount
+    #[schema(example = "USD")]
+    pub currency: Option<String>,
+    /// Disputes received
+    #[schema(example = 48)]
+    pub disputes: u64,
+    /// Total disputed amount
+    #[schema(example = 7412.5)]
+    pub disputed_amount: f64,
+    /// Amount of disputes won, which the merchant recovered
+    #[schema(example = 1830.0)]
+    pub won_amount: f64,
+    /// Amount of disputes lost
+    #[schema(example = 4210.25)]
+    pub lost_amount: f64,
+    /// Amount of disputes not yet decided
+    #[schema(example = 1372.25)]
+    pub open_amount: f64,
+    /// Disputed amount not recovered
+    #[schema(example = 5582.5)]
+    pub net_loss: f64,
+}
+
+/// Decision quality of the calling account measured against reported outcomes
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Outcomes {
+    /// Window of scored transactions evaluated
+    pub period: AnalyticsRange,
+    /// Account-wide figures
+    pub summary: OutcomeSummary,
+    /// Per-rule figures, most frequently fired rule first
+    pub rules: Vec<RuleOutcome>,
+    /// Losses to the disputes received in the window, per currency, largest first
+    pub dispute_losses: Vec<DisputeLosses>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Query parameters for reviewer decision quality
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReviewerQualityQuery {
+    /// Window of resolved cases to evaluate (default: last_30d)
+    pub period: Option<AnalyticsPeriod>,
+}
+
+/// How one reviewer's decisions held up against the outcomes reported afterwards
+///
+/// An approval is overturned when its transaction is later charged back, and a decline when
+/// its transaction is later reported as not fraud, as happens when an appeal succeeds. Rates
+/// are `null` when their denominator is zero.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReviewerQuality {
+    /// Reviewer
+    #[schema(example = "analyst@example.com")]
+    pub reviewer: String,
+    /// Cases the reviewer resolved in the window
+    #[schema(example = 212)]
+    pub decisions: u64,
+    /// Cases approved
+    #[schema(example = 148)]
+    pub approved: u64,
+    /// Approved cases whose transaction was later charged back
+    #[schema(example = 6)]
+    pub approved_then_charged_back: u64,
+    /// Share of approved cases later charged back
+    #[schema(example = 0.0405)]
+    pub approved_chargeback_rate: Option<f64>,
+    /// Cases declined
+    #[schema(example = 64)]
+    pub declined: u64,
+    /// Declined cases whose transaction was later reported as not fraud
+    #[schema(example = 3)]
+    pub declined_then_overturned: u64,
+    /// Share of declined cases later reported as not fraud
+    #[schema(example = 0.0469)]
+    pub declined_overturn_rate: Option<f64>,
+    /// Share of all the reviewer's decisions that were overturned
+    #[schema(example = 0.0425)]
+    pub overturn_rate: Option<f64>,
+}
+
+/// Decision quality of the calling account's reviewers
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReviewerQualityReport {
+    /// Window of resolved cases evaluated
+    pub period: AnalyticsRange,
+    /// Per-reviewer figures, busiest reviewer first
+    pub reviewers: Vec<ReviewerQuality>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_params_deserialize() {
+        let query: AnalyticsQuery = serde_json::from_value(serde_json::json!({
+            "period": "last_7d",
+            "granularity": "hour",
+            "group_by": "event_type",
+            "shop_id": "shop_eu_42"
+        }))
+        .unwrap();
+
+        assert_eq!(query.period, Some(AnalyticsPeriod::Last7d));
+        assert_eq!(query.granularity, Some(Granularity::Hour));
+        assert_eq!(query.group_by, Some(AnalyticsGroupBy::EventType));
+        assert_eq!(query.shop_id.as_deref(), Some("shop_eu_42"));
+        assert_eq!(AnalyticsPeriod::Last24h.duration(), Duration::hours(24));
+    }
+}
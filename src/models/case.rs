@@ -0,0 +1,924 @@
+//! Manual review cases
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{
+    common::{Link, Links, Pagination},
+    device::DeviceStatus,
+    transaction::{Disposition, EventType, ReportTag, RiskLevel},
+};
+
+/// Longest reviewer name, in characters
+const MAX_REVIEWER_CHARS: usize = 255;
+/// Longest annotation or decision reason, in characters
+const MAX_NOTE_CHARS: usize = 10_000;
+/// Longest reference URL, in characters
+const MAX_URL_CHARS: usize = 2048;
+/// Longest reference label, in characters
+const MAX_LABEL_CHARS: usize = 255;
+/// Longest queue name, in characters
+const MAX_QUEUE_NAME_CHARS: usize = 64;
+/// Longest queue SLA, in minutes: 30 days
+const MAX_SLA_MINUTES: i32 = 43_200;
+/// Most assignees or rule codes a queue may list
+const MAX_QUEUE_ENTRIES: usize = 50;
+/// Longest risk factor code, in characters
+const MAX_RULE_CODE_CHARS: usize = 100;
+
+/// Where a case stands in review
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum CaseStatus {
+    /// Waiting for a reviewer
+    Open,
+    /// Being reviewed
+    Claimed,
+    /// Approved or declined
+    Resolved,
+}
+
+/// Why a case was opened
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum CaseKind {
+    /// The transaction was sent to manual review when scored
+    #[default]
+    Review,
+    /// The merchant asked for a second look at a rejected transaction
+    Appeal,
+}
+
+/// What a reviewer decided about a case's transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum CaseDecision {
+    /// The transaction is legitimate
+    Approve,
+    /// The transaction is fraudulent
+    Decline,
+}
+
+impl CaseDecision {
+    /// Outcome recorded for the transaction when the case is resolved
+    pub fn report_tag(self) -> ReportTag {
+        match self {
+            CaseDecision::Approve => ReportTag::NotFraud,
+            CaseDecision::Decline => ReportTag::SuspectedFraud,
+        }
+    }
+}
+
+/// How new cases reach reviewers
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum CaseAssignment {
+    /// Cases wait for a reviewer to claim them
+    #[default]
+    Manual,
+    /// Each case goes to the active reviewer assigned a case least recently
+    RoundRobin,
+    /// Each case goes to the active reviewer holding the fewest unresolved cases
+    LeastLoaded,
+}
+
+/// Something done to a case, as kept in its history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum CaseAction {
+    /// The case was opened for a transaction sent to review, or appealed
+    Opened,
+    /// The case was handed to a reviewer while nobody held it
+    Assigned,
+    /// The case was moved from one reviewer to another
+    Reassigned,
+    /// A reviewer took the case
+    Claimed,
+    /// A reviewer left a remark
+    Annotated,
+    /// A reviewer attached a reference
+    Referenced,
+    /// A reviewer decided the case
+    Resolved,
+}
+
+/// Entry in a case's history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CaseEvent {
+    /// What was done
+    pub action: CaseAction,
+    /// Reviewer who did it; absent when it was done automatically
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "lead@example.com")]
+    pub actor: Option<String>,
+    /// Reviewer holding the case afterwards
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "analyst@example.com")]
+    pub reviewer: Option<String>,
+    /// When it was done
+    pub created_at: DateTime<Utc>,
+}
+
+/// A remark a reviewer left on a case
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CaseAnnotation {
+    /// Unique annotation identifier
+    pub id: Uuid,
+    /// Reviewer who left the remark
+    #[schema(example = "analyst@example.com")]
+    pub author: String,
+    /// The remark
+    #[schema(example = "Called the cardholder; they confirmed the order")]
+    pub note: String,
+    /// When the remark was left
+    pub created_at: DateTime<Utc>,
+}
+
+/// A link a reviewer attached to a case
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CaseReference {
+    /// Unique reference identifier
+    pub id: Uuid,
+    /// Where the referenced material lives
+    #[schema(example = "https://support.example.com/tickets/4821")]
+    pub url: String,
+    /// What the reference is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Support ticket")]
+    pub label: Option<String>,
+    /// Reviewer who attached it
+    #[schema(example = "analyst@example.com")]
+    pub added_by: String,
+    /// When it was attached
+    pub created_at: DateTime<Utc>,
+}
+
+/// User a case's transaction belongs to
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CaseUser {
+    /// Unique user identifier
+    pub id: Uuid,
+    /// User identifier in your system
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "user_12345")]
+    pub external_user_id: Option<String>,
+    /// Current risk score of the user
+    #[schema(example = 35.5)]
+    pub risk_score: f64,
+    /// Current risk level of the user
+    pub risk_level: RiskLevel,
+    /// Chargebacks recorded against the user
+    pub chargeback_count: i32,
+    /// Whether the user is flagged
+    pub is_flagged: bool,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Device a case's transaction came from
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CaseDevice {
+    /// Unique device identifier
+    pub id: Uuid,
+    /// Whether the device is trusted, blocked, or neither
+    pub status: DeviceStatus,
+    /// Composite risk score of the device
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 25.0)]
+    pub risk_score: Option<f64>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// IP address a case's transaction came from, with the account's history of it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CaseIpAddress {
+    /// The address
+    #[schema(example = "203.0.113.9")]
+    pub ip_address: String,
+    /// Reputation score of the address on the account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 12.5)]
+    pub risk_score: Option<f64>,
+    /// Transactions the account has seen from the address
+    pub transaction_count: i32,
+    /// Chargebacks on those transactions
+    pub chargeback_count: i32,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Email address a case's transaction gave
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CaseEmail {
+    /// SHA-256 hash of the trimmed, lowercased address, the form it is stored in
+    #[schema(example = "973dfe463ec85785f5f95af5ba3906eedb2d931c24e69824a89ea65dba4e813b")]
+    pub email_hash: String,
+    /// Domain of the address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "example.com")]
+    pub domain: Option<String>,
+    /// Whether the domain hands out disposable addresses
+    pub is_disposable: bool,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Card a case's transaction was paid with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CaseCreditCard {
+    /// Issuer identification number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "411111")]
+    pub issuer_id_number: Option<String>,
+    /// Last digits of the card number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "1234")]
+    pub last_digits: Option<String>,
+    /// Card brand
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Visa")]
+    pub brand: Option<String>,
+    /// Country of the issuing bank
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "US")]
+    pub country: Option<String>,
+    /// Whether the card is prepaid
+    pub is_prepaid: bool,
+}
+
+/// Entities involved in a case's transaction, so its investigation context is in one place
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CaseEntities {
+    /// User the transaction belongs to, unless anonymous or deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<CaseUser>,
+    /// Device the transaction came from, unless deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<CaseDevice>,
+    /// IP address the transaction came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_address: Option<CaseIpAddress>,
+    /// Email address the transaction gave
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<CaseEmail>,
+    /// Card the transaction was paid with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credit_card: Option<CaseCreditCard>,
+}
+
+/// One rule's contribution to a snapshotted score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SnapshotFactor {
+    /// Machine-readable factor code
+    #[schema(example = "CVV_MISMATCH")]
+    pub code: String,
+    /// Category of signal
+    #[schema(example = "payment")]
+    pub factor_type: String,
+    /// Contribution of the factor, on the 0-100 scale
+    #[schema(example = 22.5)]
+    pub score: f64,
+    /// Human-readable explanation
+    #[schema(example = "Card verification code did not match")]
+    pub reason: String,
+}
+
+/// Scoring of a transaction as it stood when it was appealed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ScoringSnapshot {
+    /// Scoring revision, counting the original scoring as 1
+    #[schema(example = 1)]
+    pub revision: i32,
+    /// Combined risk score
+    #[schema(example = 82.4)]
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Recommended action
+    pub disposition: Disposition,
+    /// Rules behind the score
+    pub factors: Vec<SnapshotFactor>,
+    /// When the transaction was given this scoring
+    pub scored_at: DateTime<Utc>,
+}
+
+/// The merchant's side of an appeal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CaseAppeal {
+    /// Who appealed
+    #[schema(example = "merchant-ops@example.com")]
+    pub appealed_by: String,
+    /// Why the transaction should not have been rejected
+    #[schema(example = "Long-standing customer; order confirmed by phone")]
+    pub reason: String,
+    /// The scoring being appealed
+    pub scoring: ScoringSnapshot,
+}
+
+/// A transaction sent to manual review, or appealed
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReviewCase {
+    /// Unique case identifier
+    pub id: Uuid,
+    /// Why the case was opened
+    pub kind: CaseKind,
+    /// Transaction under review
+    pub transaction_id: Uuid,
+    /// User the transaction belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<Uuid>,
+    /// Device the transaction came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<Uuid>,
+    /// Risk score the transaction was given
+    #[schema(example = 45.2)]
+    pub risk_score: f64,
+    /// Risk level the transaction was given
+    pub risk_level: RiskLevel,
+    /// Where the case stands
+    pub status: CaseStatus,
+    /// Reviewer working on the case, or who resolved it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "analyst@example.com")]
+    pub claimed_by: Option<String>,
+    /// When the case was claimed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// What the reviewer decided, once resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<CaseDecision>,
+    /// Why the reviewer decided as they did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Cardholder confirmed the order by phone")]
+    pub decision_reason: Option<String>,
+    /// Reviewer who resolved the case
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_by: Option<String>,
+    /// When the case was resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// Name of the queue the case was routed into
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "high-value")]
+    pub queue: Option<String>,
+    /// When the case breaches its queue's SLA unless resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<Utc>>,
+    /// What was appealed and why; only present on appeals
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appeal: Option<CaseAppeal>,
+    /// Remarks left on the case, oldest first; only included when fetching a single case
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Vec<CaseAnnotation>>,
+    /// Links reviewers attached to the case, oldest first; only included when fetching a
+    /// single case
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub references: Option<Vec<CaseReference>>,
+    /// Entities involved in the transaction; only included when fetching a single case
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entities: Option<CaseEntities>,
+    /// Everything done to the case, oldest first; only included when fetching a single case
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<Vec<CaseEvent>>,
+    /// When the case was opened
+    pub created_at: DateTime<Utc>,
+    /// When the case last changed
+    pub updated_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl ReviewCase {
+    /// Links of the case with the given ID
+    pub fn links(case_id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/cases/{case_id}"))),
+            ..Links::default()
+        }
+    }
+}
+
+/// Query parameters for listing cases
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListCasesQuery {
+    /// Maximum number of cases to return (1-100)
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    pub limit: Option<i64>,
+    /// Number of cases to skip
+    #[param(minimum = 0, default = 0)]
+    pub offset: Option<i64>,
+    /// Only cases with this status
+    pub status: Option<CaseStatus>,
+    /// Only review cases or only appeals
+    pub kind: Option<CaseKind>,
+    /// Only cases claimed by this reviewer
+    #[param(example = "analyst@example.com")]
+    pub claimed_by: Option<String>,
+    /// Only cases routed into the queue with this name
+    #[param(example = "high-value")]
+    pub queue: Option<String>,
+    /// Only unresolved cases past their due time, or only those that are not
+    pub overdue: Option<bool>,
+}
+
+/// Page of cases, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CaseList {
+    /// Cases on this page
+    pub cases: Vec<ReviewCase>,
+    /// Pagination metadata
+    pub pagination: Pagination,
+    /// Navigation links
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Request to claim a case for review
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CaseClaim {
+    /// Reviewer taking the case
+    #[schema(example = "analyst@example.com")]
+    pub reviewer: String,
+}
+
+impl CaseClaim {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        validate_reviewer("reviewer", &self.reviewer)
+    }
+}
+
+/// Remark to leave on a case
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CaseAnnotationRequest {
+    /// Reviewer leaving the remark
+    #[schema(example = "analyst@example.com")]
+    pub author: String,
+    /// The remark
+    #[schema(example = "Called the cardholder; they confirmed the order")]
+    pub note: String,
+}
+
+impl CaseAnnotationRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        validate_reviewer("author", &self.author)?;
+        validate_note("note", Some(&self.note))
+    }
+}
+
+/// Link to attach to a case
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CaseReferenceRequest {
+    /// Reviewer attaching the link
+    #[schema(example = "analyst@example.com")]
+    pub added_by: String,
+    /// HTTP(S) URL of the referenced material
+    #[schema(example = "https://support.example.com/tickets/4821")]
+    pub url: String,
+    /// What the reference is
+    #[schema(example = "Support ticket")]
+    pub label: Option<String>,
+}
+
+impl CaseReferenceRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        validate_reviewer("added_by", &self.added_by)?;
+        if self.url.chars().count() > MAX_URL_CHARS {
+            return Err(format!("url must be at most {MAX_URL_CHARS} characters"));
+        }
+        let url = reqwest::Url::parse(&self.url)
+            .map_err(|_| "url must be an absolute URL".to_string())?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("url must use http or https".to_string());
+        }
+        if let Some(label) = &self.label {
+            if label.trim().is_empty() {
+                return Err("label must not be empty".to_string());
+            }
+            if label.chars().count() > MAX_LABEL_CHARS {
+                return Err(format!(
+                    "label must be at most {MAX_LABEL_CHARS} characters"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decision closing a case
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CaseResolution {
+    /// Reviewer deciding the case
+    #[schema(example = "analyst@example.com")]
+    pub reviewer: String,
+    /// Approve or decline the transaction
+    pub decision: CaseDecision,
+    /// Why
+    #[schema(example = "Cardholder confirmed the order by phone")]
+    pub reason: Option<String>,
+    /// Also block the device the transaction came from; only allowed when declining
+    #[serde(default)]
+    pub block_device: bool,
+}
+
+impl CaseResolution {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        validate_reviewer("reviewer", &self.reviewer)?;
+        validate_note("reason", self.reason.as_deref())?;
+        if self.block_device && self.decision != CaseDecision::Decline {
+            return Err("block_device is only allowed when declining".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Request for a second look at a rejected transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AppealRequest {
+    /// Who is appealing
+    #[schema(example = "merchant-ops@example.com")]
+    pub appealed_by: String,
+    /// Why the transaction should not have been rejected
+    #[schema(example = "Long-standing customer; order confirmed by phone")]
+    pub reason: String,
+}
+
+impl AppealRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        validate_reviewer("appealed_by", &self.appealed_by)?;
+        validate_note("reason", Some(&self.reason))
+    }
+}
+
+/// Request to hand a case to a reviewer, whether or not another reviewer holds it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CaseAssignmentRequest {
+    /// Active reviewer to hand the case to; left out, one is picked by the account's
+    /// `case_assignment` strategy, or the least loaded when assignment is manual
+    #[schema(example = "analyst@example.com")]
+    pub reviewer: Option<String>,
+    /// Reviewer making the assignment, kept in the case's history
+    #[schema(example = "lead@example.com")]
+    pub assigned_by: String,
+}
+
+impl CaseAssignmentRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(reviewer) = &self.reviewer {
+            validate_reviewer("reviewer", reviewer)?;
+        }
+        validate_reviewer("assigned_by", &self.assigned_by)
+    }
+}
+
+/// A reviewer cases can be assigned to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Reviewer {
+    /// Name the reviewer claims, annotates, and resolves cases under
+    #[schema(example = "analyst@example.com")]
+    pub reviewer: String,
+    /// Whether cases are assigned to the reviewer
+    pub active: bool,
+    /// Unresolved cases the reviewer holds
+    #[schema(example = 3)]
+    pub open_cases: i64,
+    /// When the reviewer was last assigned a case
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_assigned_at: Option<DateTime<Utc>>,
+    /// When the reviewer was added
+    pub created_at: DateTime<Utc>,
+}
+
+/// An account's reviewers, by name
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReviewerList {
+    /// The reviewers
+    pub reviewers: Vec<Reviewer>,
+}
+
+/// Reviewer to add, or changes to an existing one
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReviewerUpdate {
+    /// Whether cases are assigned to the reviewer; defaults to true for a new reviewer
+    pub active: Option<bool>,
+}
+
+/// Request to move every case a reviewer holds to other active reviewers
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReviewerReassignment {
+    /// Reviewer moving the cases, kept in their history
+    #[schema(example = "lead@example.com")]
+    pub assigned_by: String,
+}
+
+impl ReviewerReassignment {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        validate_reviewer("assigned_by", &self.assigned_by)
+    }
+}
+
+/// Cases moved off a reviewer
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReassignedCases {
+    /// The cases, with the reviewers now holding them
+    pub cases: Vec<ReviewCase>,
+}
+
+/// Conditions a case's transaction must meet to be routed into a queue
+///
+/// Every condition given must hold; an empty list or a missing bound matches any transaction.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CaseRouting {
+    /// Types of event the transaction may be
+    #[serde(default)]
+    pub event_types: Vec<EventType>,
+    /// Smallest order amount, in the order's currency; transactions without an order never
+    /// match an amount bound
+    #[schema(example = 1000.0)]
+    pub min_amount: Option<f64>,
+    /// Largest order amount, in the order's currency
+    pub max_amount: Option<f64>,
+    /// Risk factor codes of which at least one must have fired
+    #[serde(default)]
+    pub rule_codes: Vec<String>,
+}
+
+impl CaseRouting {
+    fn validate(&self) -> Result<(), String> {
+        for (field, amount) in [
+            ("min_amount", self.min_amount),
+            ("max_amount", self.max_amount),
+        ] {
+            if amount.is_some_and(|a| !a.is_finite() || a < 0.0) {
+                return Err(format!("routing.{field} must be a non-negative number"));
+            }
+        }
+        if self
+            .min_amount
+            .zip(self.max_amount)
+            .is_some_and(|(min, max)| min > max)
+        {
+            return Err("routing.min_amount must not exceed routing.max_amount".to_string());
+        }
+        if self.rule_codes.len() > MAX_QUEUE_ENTRIES {
+            return Err(format!(
+                "routing.rule_codes must list at most {MAX_QUEUE_ENTRIES} codes"
+            ));
+        }
+        if self
+            .rule_codes
+            .iter()
+            .any(|code| code.trim().is_empty() || code.chars().count() > MAX_RULE_CODE_CHARS)
+        {
+            return Err(format!(
+                "routing.rule_codes must be non-empty codes of at most {MAX_RULE_CODE_CHARS} characters"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A named queue review cases are routed into
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CaseQueue {
+    /// Unique queue identifier
+    pub id: Uuid,
+    /// Name of the queue, unique within the account
+    #[schema(example = "high-value")]
+    pub name: String,
+    /// Order in which queues are tried when routing a case, lowest first
+    #[schema(example = 10)]
+    pub priority: i32,
+    /// Minutes a case may wait before it is overdue
+    #[schema(example = 60)]
+    pub sla_minutes: i32,
+    /// Reviewers the queue's cases are assigned to; empty for any of the account's reviewers
+    pub assignees: Vec<String>,
+    /// Which cases the queue takes
+    pub routing: CaseRouting,
+    /// Unresolved cases in the queue
+    #[schema(example = 4)]
+    pub open_cases: i64,
+    /// Of those, cases past their due time
+    #[schema(example = 1)]
+    pub overdue_cases: i64,
+    /// When the queue was created
+    pub created_at: DateTime<Utc>,
+    /// When the queue last changed
+    pub updated_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl CaseQueue {
+    /// Links of the queue with the given ID
+    pub fn links(queue_id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/cases/queues/{queue_id}"))),
+            ..Links::default()
+        }
+    }
+}
+
+/// An account's queues, in routing order
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CaseQueueList {
+    /// The queues
+    pub queues: Vec<CaseQueue>,
+}
+
+/// Queue to create, or the full replacement of an existing one
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CaseQueueRequest {
+    /// Name of the queue: lowercase letters, digits, hyphens, and underscores
+    #[schema(example = "high-value")]
+    pub name: String,
+    /// Order in which queues are tried when routing a case, lowest first (default: 0)
+    #[serde(default)]
+    #[schema(example = 10)]
+    pub priority: i32,
+    /// Minutes a case may wait before it is overdue, up to 30 days
+    #[schema(example = 60)]
+    pub sla_minutes: i32,
+    /// Reviewers the queue's cases are assigned to; empty for any of the account's reviewers
+    #[serde(default)]
+    pub assignees: Vec<String>,
+    /// Which cases the queue takes
+    #[serde(default)]
+    pub routing: CaseRouting,
+}
+
+impl CaseQueueRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        let name_ok = self.name.chars().count() <= MAX_QUEUE_NAME_CHARS
+            && self
+                .name
+                .starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !name_ok {
+            return Err(format!(
+                "name must be 1 to {MAX_QUEUE_NAME_CHARS} lowercase letters, digits, hyphens, or underscores"
+            ));
+        }
+        if !(1..=MAX_SLA_MINUTES).contains(&self.sla_minutes) {
+            return Err(format!(
+                "sla_minutes must be between 1 and {MAX_SLA_MINUTES}"
+            ));
+        }
+        if self.assignees.len() > MAX_QUEUE_ENTRIES {
+            return Err(format!(
+                "assignees must list at most {MAX_QUEUE_ENTRIES} reviewers"
+            ));
+        }
+        for assignee in &self.assignees {
+            validate_reviewer("assignees", assignee)?;
+        }
+        self.routing.validate()
+    }
+}
+
+/// Check a reviewer name given in a request path or body
+pub fn validate_reviewer(field: &str, reviewer: &str) -> Result<(), String> {
+    if reviewer.trim().is_empty() {
+        return Err(format!("{field} must not be empty"));
+    }
+    if reviewer.chars().count() > MAX_REVIEWER_CHARS {
+        return Err(format!(
+            "{field} must be at most {MAX_REVIEWER_CHARS} characters"
+        ));
+    }
+    Ok(())
+}
+
+fn validate_note(field: &str, note: Option<&str>) -> Result<(), String> {
+    match note {
+        Some(note) if note.trim().is_empty() => Err(format!("{field} must not be empty")),
+        Some(note) if note.chars().count() > MAX_NOTE_CHARS => Err(format!(
+            "{field} must be at most {MAX_NOTE_CHARS} characters"
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_requests_are_validated() {
+        let claim = |reviewer: &str| CaseClaim {
+            reviewer: reviewer.to_string(),
+        };
+        assert!(claim("analyst@example.com").validate().is_ok());
+        assert!(claim(" ").validate().is_err());
+        assert!(claim(&"a".repeat(256)).validate().is_err());
+
+        let annotation = CaseAnnotationRequest {
+            author: "analyst@example.com".to_string(),
+            note: String::new(),
+        };
+        assert!(annotation.validate().is_err());
+
+        let resolution = |decision, block_device| CaseResolution {
+            reviewer: "analyst@example.com".to_string(),
+            decision,
+            reason: None,
+            block_device,
+        };
+        assert!(resolution(CaseDecision::Decline, true).validate().is_ok());
+        assert!(resolution(CaseDecision::Approve, false).validate().is_ok());
+        assert!(resolution(CaseDecision::Approve, true).validate().is_err());
+
+        let assignment = |reviewer: Option<&str>| CaseAssignmentRequest {
+            reviewer: reviewer.map(str::to_string),
+            assigned_by: "lead@example.com".to_string(),
+        };
+        assert!(assignment(None).validate().is_ok());
+        assert!(assignment(Some("analyst@example.com")).validate().is_ok());
+        assert!(assignment(Some("")).validate().is_err());
+
+        let reference = |url: &str| CaseReferenceRequest {
+            added_by: "analyst@example.com".to_string(),
+            url: url.to_string(),
+            label: Some("Support ticket".to_string()),
+        };
+        assert!(
+            reference("https://support.example.com/tickets/4821")
+                .validate()
+                .is_ok()
+        );
+        assert!(reference("support.example.com/tickets").validate().is_err());
+        assert!(reference("javascript:alert(1)").validate().is_err());
+
+        let appeal = |reason: &str| AppealRequest {
+            appealed_by: "merchant-ops@example.com".to_string(),
+            reason: reason.to_string(),
+        };
+        assert!(appeal("Order confirmed by phone").validate().is_ok());
+        assert!(appeal(" ").validate().is_err());
+
+        let queue = |name: &str, sla_minutes, min_amount, max_amount| CaseQueueRequest {
+            name: name.to_string(),
+            priority: 0,
+            sla_minutes,
+            assignees: vec!["analyst@example.com".to_string()],
+            routing: CaseRouting {
+                min_amount,
+                max_amount,
+                ..CaseRouting::default()
+            },
+        };
+        assert!(
+            queue("high-value", 60, Some(1000.0), None)
+                .validate()
+                .is_ok()
+        );
+        assert!(queue("High Value", 60, None, None).validate().is_err());
+        assert!(queue("-ato", 60, None, None).validate().is_err());
+        assert!(queue("ato", 0, None, None).validate().is_err());
+        assert!(
+            queue("ato", 60, Some(500.0), Some(100.0))
+                .validate()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Shared response building blocks
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Hypermedia link to a related resource
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Link {
+    /// Relative URI of the linked resource
+    #[schema(example = "/v1/transactions/550e8400-e29b-41d4-a716-446655440000")]
+    pub href: String,
+}
+
+impl Link {
+    /// Create a link to `href`
+    pub fn new(href: impl Into<String>) -> Self {
+        Self { href: href.into() }
+    }
+}
+
+/// HATEOAS links for resource navigation
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct Links {
+    /// The resource itself
+    #[serde(rename = "self", skip_serializing_if = "Option::is_none")]
+    pub self_link: Option<Link>,
+    /// Next page of a collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<Link>,
+    /// Previous page of a collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev: Option<Link>,
+}
+
+/// Pagination metadata, by offset or by cursor
+///
+/// Offset pages report how many items were skipped and how many match in total. Cursor pages
+/// omit both, since keyset pagination neither skips nor counts; they continue from the
+/// `next_cursor` of the page before.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "limit": 20,
+    "offset": 0,
+    "total": 1543,
+    "has_more": true,
+    "next_cursor": "00063f1a2b3c4d5e550e8400e29b41d4a716446655440000"
+}))]
+pub struct Pagination {
+    /// Maximum number of items returned
+    pub limit: i64,
+    /// Number of items skipped; absent on cursor pages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+    /// Total number of matching items; absent on cursor pages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    /// Whether more items exist after this page
+    pub has_more: bool,
+    /// Opaque cursor for the next page, where the listing supports cursors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl Pagination {
+    /// Build pagination metadata for a page fetched by offset
+    pub fn new(limit: i64, offset: i64, total: i64) -> Self {
+        Self {
+            limit,
+            offset: Some(offset),
+            total: Some(total),
+            has_more: offset + limit < total,
+            next_cursor: None,
+        }
+    }
+
+    /// Build pagination metadata for a page fetched by cursor, given the next page's cursor
+    pub fn cursor(limit: i64, next_cursor: Option<Cursor>) -> Self {
+        Self {
+            limit,
+            offset: None,
+            total: None,
+            has_more: next_cursor.is_some(),
+            next_cursor: next_cursor.map(|cursor| cursor.encode()),
+        }
+    }
+
+    /// Also offer `next_cursor`, letting a client continue an offset listing by cursor
+    pub fn with_next_cursor(mut self, next_cursor: Option<Cursor>) -> Self {
+        self.next_cursor = next_cursor.map(|cursor| cursor.encode());
+        self
+    }
+
+    /// Links to the neighbouring pages under `base`
+    ///
+    /// Offset pages link to the previous and next offsets; cursor pages only to the next page.
+    /// `base` may carry a query string, such as the listing's filters, which the links keep.
+    pub fn links(&self, base: &str) -> Links {
+        let separator = if base.contains('?') { '&' } else { '?' };
+        let Some(offset) = self.offset else {
+            return Links {
+                next: self.next_cursor.as_ref().map(|cursor| {
+                    Link::new(format!(
+                        "{base}{separator}cursor={cursor}&limit={}",
+                        self.limit
+                    ))
+                }),
+                ..Links::default()
+            };
+        };
+        let page = |offset: i64| {
+            Link::new(format!(
+                "{base}{separator}offset={offset}&limit={}",
+                self.limit
+            ))
+        };
+        Links {
+            self_link: Some(page(offset)),
+            next: self.has_more.then(|| page(offset + self.limit)),
+            prev: (offset > 0).then(|| page((offset - self.limit).max(0))),
+        }
+    }
+}
+
+/// Position in a listing ordered by creation time, for keyset pagination
+///
+/// The ID breaks ties between items created in the same microsecond. Clients see it only in
+/// its encoded, opaque form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    /// Creation time of the last item on the page
+    pub created_at: DateTime<Utc>,
+    /// ID of the last item on the page
+    pub id: Uuid,
+}
+
+impl Cursor {
+    /// Opaque form handed to clients
+    pub fn encode(&self) -> String {
+        format!(
+            "{:016x}{}",
+            self.created_at.timestamp_micros(),
+            self.id.simple()
+        )
+    }
+
+    /// Parse a cursor previously produced by [`Cursor::encode`]
+    pub fn decode(encoded: &str) -> Option<Self> {
+        if encoded.len() != 48 {
+            return None;
+        }
+        let (micros, id) = encoded.split_at_checked(16)?;
+        let micros = u64::from_str_radix(micros, 16).ok()? as i64;
+        Some(Self {
+            created_at: DateTime::from_timestamp_micros(micros)?,
+            id: Uuid::try_parse(id).ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pagination_links() {
+        let pagination = Pagination::new(20, 20, 45);
+        assert!(pagination.has_more);
+
+        let links = pagination.links("/v1/transactions");
+        assert_eq!(
+            links.next.unwrap().href,
+            "/v1/transactions?offset=40&limit=20"
+        );
+        assert_eq!(
+            links.prev.unwrap().href,
+            "/v1/transactions?offset=0&limit=20"
+        );
+    }
+
+    #[test]
+    fn test_cursor_round_trips() {
+        let cursor = Cursor {
+            created_at: DateTime::from_timestamp_micros(1_749_810_600_123_456).unwrap(),
+            id: Uuid::new_v4(),
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(Cursor::decode("not-a-cursor"), None);
+        assert_eq!(
+            Cursor::decode(&format!("{}z", &cursor.encode()[..47])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cursor_pages_link_forward_only() {
+        let cursor = Cursor {
+            created_at: Utc::now(),
+            id: Uuid::new_v4(),
+        };
+        let pagination = Pagination::cursor(20, Some(cursor));
+        assert!(pagination.has_more);
+        let links = pagination.links("/v1/transactions");
+        assert_eq!(
+            links.next.unwrap().href,
+            format!("/v1/transactions?cursor={}&limit=20", cursor.encode())
+        );
+        assert!(links.prev.is_none());
+        assert!(!Pagination::cursor(20, None).has_more);
+    }
+
+    #[test]
+    fn test_links_keep_the_base_query() {
+        let links = Pagination::new(20, 0, 45).links("/v1/transactions?currency=USD");
+        assert_eq!(
+            links.next.unwrap().href,
+            "/v1/transactions?currency=USD&offset=20&limit=20"
+        );
+    }
+
+    #[test]
+    fn test_last_page_has_no_next() {
+        let pagination = Pagination::new(20, 40, 45);
+        assert!(!pagination.has_more);
+        assert!(pagination.links("/v1/transactions").next.is_none());
+    }
+}
@@ -0,0 +1,114 @@
+//! Dead letters: events the dispatcher gave up on after their last delivery attempt
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::common::{Link, Links, Pagination};
+use crate::outbox::EVENT_TYPES;
+
+/// An event that could not be delivered
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeadLetter {
+    /// Event ID, as delivered in webhook bodies and the `X-Fusegu-Event-Id` header
+    pub id: Uuid,
+    /// Event type
+    #[schema(example = "transaction.scored")]
+    pub event_type: String,
+    /// ID of the entity the event describes
+    pub aggregate_id: Uuid,
+    /// Event payload, shaped by its type
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    /// Delivery attempts made
+    #[schema(example = 10)]
+    pub attempts: i32,
+    /// What went wrong on the last attempt
+    #[schema(example = "1 of 2 webhooks failed")]
+    pub last_error: Option<String>,
+    /// When the event was recorded
+    pub created_at: DateTime<Utc>,
+    /// When delivery was given up on
+    pub dead_lettered_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl DeadLetter {
+    /// Links of the dead letter with the given ID
+    pub fn links(event_id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/dead-letters/{event_id}"))),
+            ..Links::default()
+        }
+    }
+}
+
+/// Page of the account's dead letters, newest first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeadLetterList {
+    /// Dead letters on this page
+    pub dead_letters: Vec<DeadLetter>,
+    /// Pagination metadata
+    pub pagination: Pagination,
+    /// Navigation links
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Query parameters for listing dead letters
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListDeadLettersQuery {
+    /// Maximum number of dead letters to return (1-100)
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    pub limit: Option<i64>,
+    /// Number of dead letters to skip
+    #[param(minimum = 0, default = 0)]
+    pub offset: Option<i64>,
+    /// Filter by event type
+    #[param(example = "transaction.scored")]
+    pub event_type: Option<String>,
+}
+
+/// Query parameters picking the dead letters to redrive or purge
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeadLetterSelection {
+    /// Only dead letters of this event type; every dead letter when absent
+    #[param(example = "transaction.scored")]
+    pub event_type: Option<String>,
+}
+
+impl DeadLetterSelection {
+    /// Check the event type is one events are recorded with
+    pub fn validate(&self) -> Result<(), String> {
+        validate_event_type(self.event_type.as_deref())
+    }
+}
+
+impl ListDeadLettersQuery {
+    /// Check the event type is one events are recorded with
+    pub fn validate(&self) -> Result<(), String> {
+        validate_event_type(self.event_type.as_deref())
+    }
+}
+
+fn validate_event_type(event_type: Option<&str>) -> Result<(), String> {
+    match event_type {
+        Some(event_type) if !EVENT_TYPES.contains(&event_type) => {
+            Err(format!("event_type {event_type} is not a known event type"))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Dead letters redriven or purged at once
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeadLetterBatch {
+    /// How many dead letters were redriven or purged
+    #[schema(example = 42)]
+    pub count: u64,
+}
@@ -0,0 +1,475 @@
+//! Devices seen by each account
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{
+    common::{Links, Pagination},
+    transaction::is_ip_or_network,
+};
+
+/// Prefix of device tokens
+pub const DEVICE_TOKEN_PREFIX: &str = "dev_";
+/// Most fonts accepted in a fingerprint payload
+const MAX_FONTS: usize = 1000;
+/// Most languages accepted in a fingerprint payload
+const MAX_LANGUAGES: usize = 50;
+/// Longest signal value accepted
+const MAX_SIGNAL_LEN: usize = 255;
+
+/// Token naming the device with the given fingerprint hash
+pub fn device_token(fingerprint_hash: &str) -> String {
+    format!("{DEVICE_TOKEN_PREFIX}{fingerprint_hash}")
+}
+
+/// Fingerprint hash a device token names, or `None` if the token is malformed
+pub fn token_fingerprint(token: &str) -> Option<&str> {
+    token.strip_prefix(DEVICE_TOKEN_PREFIX).filter(|hash| {
+        hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    })
+}
+
+/// Signals collected in the browser by the fingerprinting script
+///
+/// Every signal is optional, since browsers block or randomize some of them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceSignals {
+    /// Hash of an image rendered to a canvas
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "2b6f0cc904d137be2e1730235f5664094b831186")]
+    pub canvas_hash: Option<String>,
+    /// Hash of a rendered audio sample
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "124.04347527516074")]
+    pub audio_hash: Option<String>,
+    /// WebGL graphics driver details
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webgl: Option<WebGlSignals>,
+    /// Screen dimensions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen: Option<ScreenSignals>,
+    /// IANA time zone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Europe/Berlin")]
+    pub timezone: Option<String>,
+    /// Preferred languages, most preferred first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(example = json!(["de-DE", "en-US"]))]
+    pub languages: Vec<String>,
+    /// Operating system platform reported by the browser
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "MacIntel")]
+    pub platform: Option<String>,
+    /// Logical processor count
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 8)]
+    pub hardware_concurrency: Option<u32>,
+    /// Approximate memory in gigabytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 8)]
+    pub device_memory: Option<f64>,
+    /// Simultaneous touch points supported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 0)]
+    pub touch_points: Option<u32>,
+    /// Installed fonts detected
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(example = json!(["Arial", "Helvetica Neue", "Menlo"]))]
+    pub fonts: Vec<String>,
+}
+
+/// WebGL graphics driver details
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WebGlSignals {
+    /// Unmasked vendor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Apple Inc.")]
+    pub vendor: Option<String>,
+    /// Unmasked renderer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Apple M1")]
+    pub renderer: Option<String>,
+}
+
+/// Screen dimensions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ScreenSignals {
+    /// Width in CSS pixels
+    #[schema(example = 1512)]
+    pub width: u32,
+    /// Height in CSS pixels
+    #[schema(example = 982)]
+    pub height: u32,
+    /// Color depth in bits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 30)]
+    pub color_depth: Option<u32>,
+    /// Ratio of device pixels to CSS pixels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 2.0)]
+    pub pixel_ratio: Option<f64>,
+}
+
+/// Analysts' verdict on a device
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum DeviceStatus {
+    /// Scored on its own merits
+    #[default]
+    Normal,
+    /// Known good; its transactions score lower
+    Trusted,
+    /// Known bad; its transactions are rejected
+    Blocked,
+}
+
+/// Kind of device a user agent belongs to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceClass {
+    /// Desktop or laptop computer
+    Desktop,
+    /// Phone
+    Mobile,
+    /// Tablet
+    Tablet,
+    /// Headless browser, automation tool, HTTP library, or crawler
+    Bot,
+    /// Not recognized
+    #[default]
+    Unknown,
+}
+
+/// What a device's user agent says about it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UserAgentDetails {
+    /// Browser name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Chrome")]
+    pub browser: Option<String>,
+    /// Browser version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "126.0.6478.61")]
+    pub browser_version: Option<String>,
+    /// Operating system name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Windows")]
+    pub os: Option<String>,
+    /// Kind of device
+    pub device_class: DeviceClass,
+    /// Headless browser, automation tool, HTTP library, or crawler the user agent names
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "HeadlessChrome")]
+    pub automation: Option<String>,
+}
+
+/// Browser-collected signals to fingerprint a device by
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "ip_address": "198.51.100.1",
+    "user_agent": "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36",
+    "accept_language": "de-DE,de;q=0.9,en;q=0.8",
+    "signals": {
+        "canvas_hash": "2b6f0cc904d137be2e1730235f5664094b831186",
+        "webgl": { "vendor": "Apple Inc.", "renderer": "Apple M1" },
+        "screen": { "width": 1512, "height": 982, "color_depth": 30, "pixel_ratio": 2.0 },
+        "timezone": "Europe/Berlin",
+        "languages": ["de-DE", "en-US"],
+        "platform": "MacIntel",
+        "hardware_concurrency": 8,
+        "fonts": ["Arial", "Helvetica Neue", "Menlo"]
+    }
+}))]
+pub struct DeviceFingerprintRequest {
+    /// IPv4 or IPv6 address the signals were collected from
+    pub ip_address: String,
+    /// HTTP User-Agent header of the browser
+    pub user_agent: Option<String>,
+    /// HTTP Accept-Language header of the browser
+    pub accept_language: Option<String>,
+    /// Signals collected in the browser
+    pub signals: DeviceSignals,
+}
+
+impl DeviceFingerprintRequest {
+    /// Check field formats, and that the signals include at least one that tells devices
+    /// apart
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ip_address.parse::<IpAddr>().is_err() {
+            return Err("ip_address must be a valid IPv4 or IPv6 address".to_string());
+        }
+        check_len("user_agent", self.user_agent.as_deref(), 512)?;
+        check_len("accept_language", self.accept_language.as_deref(), 255)?;
+
+        let signals = &self.signals;
+        let webgl = signals.webgl.as_ref();
+        check_len(
+            "signals.canvas_hash",
+            signals.canvas_hash.as_deref(),
+            MAX_SIGNAL_LEN,
+        )?;
+        check_len(
+            "signals.audio_hash",
+            signals.audio_hash.as_deref(),
+            MAX_SIGNAL_LEN,
+        )?;
+        check_len(
+            "signals.webgl.vendor",
+            webgl.and_then(|w| w.vendor.as_deref()),
+            MAX_SIGNAL_LEN,
+        )?;
+        check_len(
+            "signals.webgl.renderer",
+            webgl.and_then(|w| w.renderer.as_deref()),
+            MAX_SIGNAL_LEN,
+        )?;
+        check_len(
+            "signals.timezone",
+            signals.timezone.as_deref(),
+            MAX_SIGNAL_LEN,
+        )?;
+        check_len(
+            "signals.platform",
+            signals.platform.as_deref(),
+            MAX_SIGNAL_LEN,
+        )?;
+        if signals.languages.len() > MAX_LANGUAGES {
+            return Err(format!(
+                "signals.languages may list at most {MAX_LANGUAGES} languages"
+            ));
+        }
+        if signals.fonts.len() > MAX_FONTS {
+            return Err(format!("signals.fonts may list at most {MAX_FONTS} fonts"));
+        }
+        for value in signals.languages.iter().chain(&signals.fonts) {
+            check_len(
+                "signals.languages and signals.fonts entries",
+                Some(value),
+                MAX_SIGNAL_LEN,
+            )?;
+        }
+        if signals
+            .device_memory
+            .is_some_and(|memory| !memory.is_finite() || memory < 0.0)
+        {
+            return Err("signals.device_memory must be a non-negative number".to_string());
+        }
+
+        let present = |value: Option<&str>| value.is_some_and(|v| !v.trim().is_empty());
+        if !present(signals.canvas_hash.as_deref())
+            && !present(signals.audio_hash.as_deref())
+            && !present(webgl.and_then(|w| w.renderer.as_deref()))
+        {
+            return Err(
+                "signals must include at least one of canvas_hash, audio_hash, and webgl.renderer"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Device seen by the account
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "id": "3d0f6a4e-2b1c-4f7e-9a8d-5c6b7e8f9a0b",
+    "device_token": "dev_9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+    "ip_address": "198.51.100.1",
+    "user_agent": "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36",
+    "user_agent_details": { "os": "macOS", "device_class": "desktop" },
+    "accept_language": "de-DE,de;q=0.9,en;q=0.8",
+    "signals": {
+        "canvas_hash": "2b6f0cc904d137be2e1730235f5664094b831186",
+        "webgl": { "vendor": "Apple Inc.", "renderer": "Apple M1" },
+        "timezone": "Europe/Berlin"
+    },
+    "first_seen": "2025-06-13T10:30:00Z",
+    "last_seen": "2025-06-13T10:30:00Z",
+    "status": "normal",
+    "risk_score": 15.0,
+    "transaction_count": 3,
+    "user_count": 1,
+    "suspicious": false
+}))]
+pub struct Device {
+    /// Unique device identifier
+    pub id: Uuid,
+    /// Token to pass as `device.device_token` when scoring transactions from the device
+    pub device_token: String,
+    /// User the device was last seen with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<Uuid>,
+    /// IP address the device last used
+    #[schema(example = "198.51.100.1")]
+    pub ip_address: String,
+    /// Autonomous system the IP address belongs to, when a GeoIP ASN database is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 64500)]
+    pub asn: Option<i64>,
+    /// Internet service provider or organization of the autonomous system
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Example Telecom")]
+    pub isp: Option<String>,
+    /// HTTP User-Agent header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// What the user agent says about the device
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent_details: Option<UserAgentDetails>,
+    /// HTTP Accept-Language header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accept_language: Option<String>,
+    /// JA3 fingerprint of the device's TLS handshake
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "cd08e31494f9531f560d64c695473da9")]
+    pub ja3: Option<String>,
+    /// JA4 fingerprint of the device's TLS handshake
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "t13d1516h2_8daaf6152771_e5627efa2ab1")]
+    pub ja4: Option<String>,
+    /// Hash of the names of the HTTP headers the device sent, in order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_order_hash: Option<String>,
+    /// Browser signals, for devices fingerprinted from them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signals: Option<DeviceSignals>,
+    /// Whether the device is trusted, blocked, or neither
+    pub status: DeviceStatus,
+    /// When the status was last changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_changed_at: Option<DateTime<Utc>>,
+    /// Composite risk of the device from 0 to 100, recomputed whenever it is seen; see
+    /// `DeviceInsights.risk_score`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 15.0)]
+    pub risk_score: Option<f64>,
+    /// When the account first saw the device
+    pub first_seen: DateTime<Utc>,
+    /// When the account last saw the device
+    pub last_seen: DateTime<Utc>,
+    /// Transactions that came from the device
+    pub transaction_count: i64,
+    /// Distinct users the device has been seen with
+    pub user_count: i64,
+    /// Whether the user agent names an automation tool, or a transaction from the device was
+    /// rated high or very high risk
+    pub suspicious: bool,
+}
+
+/// Query parameters for listing devices
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListDevicesQuery {
+    /// Maximum number of devices to return (1-100)
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    pub limit: Option<i64>,
+    /// Number of devices to skip
+    #[param(minimum = 0, default = 0)]
+    pub offset: Option<i64>,
+    /// Only devices that are, or only those that are not, suspicious
+    pub suspicious: Option<bool>,
+    /// Only devices last seen at this IP address, or within this CIDR range
+    #[param(example = "198.51.100.0/24")]
+    pub ip_address: Option<String>,
+    /// Only devices that have been seen with this user
+    pub user_id: Option<Uuid>,
+    /// Only devices with this status
+    pub status: Option<DeviceStatus>,
+}
+
+impl ListDevicesQuery {
+    /// Check filter formats that the query string alone cannot express
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .ip_address
+            .as_deref()
+            .is_some_and(|ip| !is_ip_or_network(ip))
+        {
+            return Err("ip_address must be an IP address or CIDR range".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Changes to a device; fields left out are kept as they are
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceUpdate {
+    /// Mark the device trusted or blocked, or return it to `normal`
+    pub status: Option<DeviceStatus>,
+}
+
+/// Page of devices, most recently seen first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeviceList {
+    /// Devices on this page
+    pub devices: Vec<Device>,
+    /// Pagination metadata
+    pub pagination: Pagination,
+    /// Navigation links
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+fn check_len(field: &str, value: Option<&str>, max: usize) -> Result<(), String> {
+    match value {
+        Some(v) if v.chars().count() > max => {
+            Err(format!("{field} must be at most {max} characters"))
+        },
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_device_tokens_round_trip() {
+        let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        assert_eq!(token_fingerprint(&device_token(hash)), Some(hash));
+        assert_eq!(token_fingerprint(hash), None);
+        assert_eq!(token_fingerprint("dev_9f86d081"), None);
+        assert_eq!(token_fingerprint(&device_token(&hash.to_uppercase())), None);
+    }
+
+    #[test]
+    fn test_device_listing_filters_are_validated() {
+        let query = |ip: &str| ListDevicesQuery {
+            ip_address: Some(ip.to_string()),
+            ..Default::default()
+        };
+        assert!(query("198.51.100.7").validate().is_ok());
+        assert!(query("2001:db8::/32").validate().is_ok());
+        assert!(query("198.51.100.0/33").validate().is_err());
+        assert!(query("not-an-ip").validate().is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_requests_need_a_distinguishing_signal() {
+        let mut request: DeviceFingerprintRequest = serde_json::from_value(json!({
+            "ip_address": "198.51.100.1",
+            "signals": { "timezone": "Europe/Berlin", "webgl": { "renderer": "Apple M1" } }
+        }))
+        .unwrap();
+        assert!(request.validate().is_ok());
+
+        request.signals.webgl = None;
+        assert!(request.validate().is_err());
+
+        request.signals.canvas_hash = Some("2b6f0cc9".to_string());
+        assert!(request.validate().is_ok());
+
+        request.ip_address = "not an address".to_string();
+        assert!(request.validate().is_err());
+    }
+}
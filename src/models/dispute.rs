@@ -0,0 +1,75 @@
+//! Disputes of transactions, tracked through their lifecycle
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::processor_event::Processor;
+
+/// Stage a dispute has reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum DisputeStatus {
+    /// The cardholder's bank disputed the payment and the funds were withdrawn
+    Received,
+    /// The merchant submitted evidence contesting the dispute
+    Representment,
+    /// The dispute was decided for the merchant, who keeps the funds
+    Won,
+    /// The dispute was decided for the cardholder
+    Lost,
+}
+
+impl DisputeStatus {
+    /// Whether the dispute was decided
+    pub fn is_resolved(self) -> bool {
+        matches!(self, DisputeStatus::Won | DisputeStatus::Lost)
+    }
+}
+
+/// Dispute of a transaction reported by a payment processor
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Dispute {
+    /// Unique dispute identifier
+    pub id: Uuid,
+    /// Disputed transaction
+    pub transaction_id: Uuid,
+    /// Processor that reported the dispute
+    pub processor: Processor,
+    /// Processor's ID for the dispute
+    #[schema(example = "dp_1NG8Du2eZvKYlo2C9sdbNP8Y")]
+    pub reference: String,
+    /// Stage the dispute has reached
+    pub status: DisputeStatus,
+    /// Disputed amount, in major units of `currency`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 149.99)]
+    pub amount: Option<f64>,
+    /// ISO 4217 currency code of the amount
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "USD")]
+    pub currency: Option<String>,
+    /// Processor's reason code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "10.4")]
+    pub reason_code: Option<String>,
+    /// When the dispute was received
+    pub received_at: DateTime<Utc>,
+    /// When evidence was submitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub representment_at: Option<DateTime<Utc>>,
+    /// When the dispute was won or lost
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// When the dispute last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Disputes of a transaction, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DisputeList {
+    /// The disputes
+    pub disputes: Vec<Dispute>,
+}
@@ -11,14 +11,51 @@ use utoipa::ToSchema;
     description = "Health check endpoint response",
     example = json!({
         "status": "healthy",
-        "timestamp": "2025-01-21T10:00:00Z"
+        "timestamp": "2025-01-21T10:00:00Z",
+        "database": {
+            "status": "up",
+            "pool": {
+                "size": 4,
+                "idle": 3,
+                "in_use": 1,
+                "max_connections": 10,
+                "utilization": 0.1
+            }
+        }
     })
 )]
 pub struct HealthResponse {
-    /// Service health status
+    /// Service health status: healthy, degraded, or unhealthy
     #[schema(example = "healthy")]
     pub status: String,
     /// Response timestamp in ISO 8601 format
     #[schema(example = "2025-01-21T10:00:00Z")]
     pub timestamp: DateTime<Utc>,
+    /// PostgreSQL connectivity and pool usage
+    pub database: DatabaseHealth,
+}
+
+/// PostgreSQL health details
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DatabaseHealth {
+    /// Database status: up, saturated, or down
+    #[schema(example = "up")]
+    pub status: String,
+    /// Connection pool usage
+    pub pool: PoolHealth,
+}
+
+/// Connection pool usage
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PoolHealth {
+    /// Open connections, idle or in use
+    pub size: u32,
+    /// Open connections not currently checked out
+    pub idle: u32,
+    /// Connections currently checked out
+    pub in_use: u32,
+    /// Upper bound on open connections
+    pub max_connections: u32,
+    /// Share of the pool's capacity in use, from 0.0 to 1.0
+    pub utilization: f64,
 }
@@ -0,0 +1,570 @@
+//! Insights into the entities behind a scored transaction
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    common::Links,
+    transaction::{DeliverySpeed, TransactionResponse},
+};
+
+/// What is known about the device, email, addresses, phone, and card of a transaction
+///
+/// Each section is present only if the transaction supplied that entity. Counts cover every
+/// transaction of the calling account that shared the entity, including this one.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "transaction_id": "550e8400-e29b-41d4-a716-446655440000",
+    "device": {
+        "ip_address": "198.51.100.1",
+        "ip_reserved": false,
+        "user_agent": "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
+        "first_seen": "2025-05-02T08:11:00Z",
+        "last_seen": "2025-06-13T10:30:00Z",
+        "transaction_count": 7
+    },
+    "email": {
+        "domain": "example.com",
+        "first_seen": "2025-05-02",
+        "is_free": false,
+        "is_disposable": false,
+        "is_high_risk": false,
+        "transaction_count": 4
+    },
+    "credit_card": {
+        "issuer_id_number": "411111",
+        "last_digits": "1111",
+        "brand": "visa",
+        "country": "US",
+        "matches_billing_country": true,
+        "is_business": false,
+        "is_prepaid": false,
+        "is_virtual": false,
+        "transaction_count": 3
+    },
+    "_links": {
+        "self": { "href": "/v1/transactions/550e8400-e29b-41d4-a716-446655440000/insights" }
+    }
+}))]
+pub struct TransactionInsights {
+    /// Transaction the insights are about
+    pub transaction_id: Uuid,
+    /// Device the transaction came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<DeviceInsights>,
+    /// Email address used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<EmailInsights>,
+    /// Billing address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing_address: Option<AddressInsights>,
+    /// Shipping address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shipping_address: Option<AddressInsights>,
+    /// Phone number of the billing address, or else of the shipping address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<PhoneInsights>,
+    /// Payment card used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credit_card: Option<CreditCardInsights>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Device of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeviceInsights {
+    /// IP address the device used
+    #[schema(example = "198.51.100.1")]
+    pub ip_address: String,
+    /// Whether the IP address is in a reserved or private range
+    pub ip_reserved: bool,
+    /// Autonomous system the IP address belongs to, when a GeoIP ASN database is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 64500)]
+    pub asn: Option<i64>,
+    /// Internet service provider or organization of the autonomous system
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Example Telecom")]
+    pub isp: Option<String>,
+    /// Risk score of the IP address from IP intelligence, when a current lookup is cached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 12.5)]
+    pub ip_risk_score: Option<f64>,
+    /// Location of the IP address from IP intelligence, when a current lookup is cached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub ip_location: Option<serde_json::Value>,
+    /// Anonymity traits of the IP address from IP intelligence feeds, when a current lookup is
+    /// cached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_traits: Option<IpTraits>,
+    /// History of the transaction's IP address on the account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_history: Option<IpHistoryInsights>,
+    /// Composite risk of the device from 0 to 100, combining how recently it appeared, an IP
+    /// address at a hosting provider, an automated user agent, too few browser signals to tell
+    /// it apart, and use by several users
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 15.0)]
+    pub risk_score: Option<f64>,
+    /// HTTP User-Agent header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// HTTP Accept-Language header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accept_language: Option<String>,
+    /// When the account first saw the device
+    pub first_seen: DateTime<Utc>,
+    /// When the account last saw the device
+    pub last_seen: DateTime<Utc>,
+    /// Transactions that came from the device
+    #[schema(example = 7)]
+    pub transaction_count: i64,
+}
+
+/// What anonymous IP intelligence feeds say about an IP address
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct IpTraits {
+    /// Whether the address hides its user: a Tor exit node, VPN, or public proxy
+    pub is_anonymous: bool,
+    /// Whether the address is a Tor exit node
+    pub is_tor_exit_node: bool,
+    /// Whether the address belongs to a commercial VPN service
+    pub is_anonymous_vpn: bool,
+    /// Whether the address is an open or public proxy
+    pub is_public_proxy: bool,
+    /// Whether the address belongs to a datacenter or hosting provider
+    pub is_hosting_provider: bool,
+}
+
+/// History of an IP address on the calling account, as of now
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IpHistoryInsights {
+    /// Reputation risk of the address from 0 to 100, combining chargebacks, mostly rejected
+    /// transactions, and many payment cards from it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 30.0)]
+    pub risk: Option<f64>,
+    /// Transactions from the address
+    #[schema(example = 9)]
+    pub transaction_count: i64,
+    /// Of those, transactions rejected
+    #[schema(example = 1)]
+    pub reject_count: i64,
+    /// Of those, transactions reported as chargebacks
+    #[schema(example = 0)]
+    pub chargeback_count: i64,
+    /// Distinct users the transactions belonged to
+    #[schema(example = 2)]
+    pub user_count: i64,
+    /// Distinct payment cards the transactions used
+    #[schema(example = 3)]
+    pub card_count: i64,
+    /// When the account first saw the address
+    pub first_seen: DateTime<Utc>,
+    /// When the account last saw the address
+    pub last_seen: DateTime<Utc>,
+}
+
+/// What is known about an IP address, and how the calling account has seen it used
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "ip_address": "198.51.100.1",
+    "ip_reserved": false,
+    "location": {
+        "country": "US",
+        "city": "Chicago",
+        "accuracy_radius": 20,
+        "time_zone": "America/Chicago",
+        "local_time": "2025-06-13T05:30:00-05:00"
+    },
+    "location_risk": 0.0,
+    "asn": 64500,
+    "isp": "Example Telecom",
+    "traits": {
+        "is_anonymous": false,
+        "is_tor_exit_node": false,
+        "is_anonymous_vpn": false,
+        "is_public_proxy": false,
+        "is_hosting_provider": false
+    },
+    "reputation": {
+        "transaction_count": 9,
+        "reject_count": 1,
+        "chargeback_count": 0,
+        "user_count": 2,
+        "card_count": 3,
+        "first_seen": "2025-05-02T08:11:00Z",
+        "last_seen": "2025-06-13T10:30:00Z"
+    },
+    "recent_transactions": [],
+    "_links": { "self": { "href": "/v1/ip/198.51.100.1" } }
+}))]
+pub struct IpAddressInsights {
+    /// The IP address, in canonical form
+    #[schema(example = "198.51.100.1")]
+    pub ip_address: String,
+    /// Whether the IP address is in a reserved or private range, which is neither located nor
+    /// looked up in IP intelligence feeds
+    pub ip_reserved: bool,
+    /// Location of the IP address, when the GeoIP database has a record of it, with the
+    /// current local time in its time zone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub location: Option<serde_json::Value>,
+    /// Risk of the location from 0 to 100: anonymizing proxies, satellite providers, use
+    /// outside the registered country, and imprecise locations raise it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 0.0)]
+    pub location_risk: Option<f64>,
+    /// Autonomous system the IP address belongs to, when a GeoIP ASN database is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 64500)]
+    pub asn: Option<i64>,
+    /// Internet service provider or organization of the autonomous system
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Example Telecom")]
+    pub isp: Option<String>,
+    /// Anonymity traits of the IP address from IP intelligence feeds, when any are configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub traits: Option<IpTraits>,
+    /// History and reputation of the IP address on the account, if it has seen the address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reputation: Option<IpHistoryInsights>,
+    /// The account's most recent transactions from the IP address, newest first
+    pub recent_transactions: Vec<TransactionResponse>,
+    /// Links to the IP address itself
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// What is known about an email address, and how the calling account has seen it used
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "email_hash": "e233d4a29013e9d87150c6237c6777bedf379ebf1acdc5d6126fec7e8bb74fb5",
+    "domain": {
+        "domain": "example.com",
+        "is_free": false,
+        "is_disposable": false,
+        "address_count": 12
+    },
+    "history": {
+        "transaction_count": 4,
+        "reject_count": 0,
+        "chargeback_count": 0,
+        "user_count": 1,
+        "card_count": 2,
+        "is_high_risk": false,
+        "first_seen": "2025-05-02",
+        "last_seen": "2025-06-13T10:30:00Z"
+    },
+    "recent_transactions": [],
+    "_links": {
+        "self": {
+            "href": "/v1/emails/e233d4a29013e9d87150c6237c6777bedf379ebf1acdc5d6126fec7e8bb74fb5"
+        }
+    }
+}))]
+pub struct EmailAddressInsights {
+    /// SHA-256 hash of the trimmed, lowercased address, which is how addresses are stored
+    pub email_hash: String,
+    /// Domain of the address, when the address was given or the account has seen it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<EmailDomainInsights>,
+    /// History of the address on the account, if it has seen the address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<EmailHistoryInsights>,
+    /// The account's most recent transactions that used the address, newest first
+    pub recent_transactions: Vec<TransactionResponse>,
+    /// Links to the email address itself, by hash
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Domain of an email address
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmailDomainInsights {
+    /// The domain
+    #[schema(example = "example.com")]
+    pub domain: String,
+    /// Whether the domain is a free email provider
+    pub is_free: bool,
+    /// Whether the domain hands out disposable addresses
+    pub is_disposable: bool,
+    /// Distinct addresses at the domain the account has seen
+    #[schema(example = 12)]
+    pub address_count: i64,
+}
+
+/// History of an email address on the calling account, as of now
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmailHistoryInsights {
+    /// Transactions that used the address
+    #[schema(example = 4)]
+    pub transaction_count: i64,
+    /// Of those, transactions rejected
+    #[schema(example = 0)]
+    pub reject_count: i64,
+    /// Of those, transactions reported as chargebacks
+    #[schema(example = 0)]
+    pub chargeback_count: i64,
+    /// Distinct users the transactions belonged to
+    #[schema(example = 1)]
+    pub user_count: i64,
+    /// Distinct payment cards the transactions used
+    #[schema(example = 2)]
+    pub card_count: i64,
+    /// Whether the address is known to be high risk
+    pub is_high_risk: bool,
+    /// Day the account first saw the address
+    pub first_seen: NaiveDate,
+    /// When the account last saw the address, unless its transactions have since been deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// Email address of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmailInsights {
+    /// Domain of the address; the address itself is stored only as a hash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "example.com")]
+    pub domain: Option<String>,
+    /// Day the account first saw the address
+    pub first_seen: NaiveDate,
+    /// Whether the domain is a free email provider
+    pub is_free: bool,
+    /// Whether the domain hands out disposable addresses
+    pub is_disposable: bool,
+    /// Whether the address is known to be high risk
+    pub is_high_risk: bool,
+    /// Transactions that used the address
+    #[schema(example = 4)]
+    pub transaction_count: i64,
+}
+
+/// Billing or shipping address of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AddressInsights {
+    /// City name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// ISO 3166-2 subdivision code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Postal code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postal: Option<String>,
+    /// ISO 3166-1 alpha-2 country code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// Latitude of the centroid of the address's postal code, when postal centroids are
+    /// configured and list it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    /// Longitude of the centroid of the address's postal code, when postal centroids are
+    /// configured and list it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+    /// Whether the address is in the country the transaction's IP address is located in,
+    /// when both countries are known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_in_ip_country: Option<bool>,
+    /// Distance in kilometres from the address to the location of the transaction's IP
+    /// address, when both could be located
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 12.0)]
+    pub distance_to_ip_location: Option<f64>,
+    /// Whether the address is known to be high risk
+    pub is_high_risk: bool,
+    /// Requested delivery speed, for shipping addresses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delivery_speed: Option<DeliverySpeed>,
+    /// Transactions that used the same address, however it was written; 1 when no street
+    /// address was supplied
+    #[schema(example = 2)]
+    pub transaction_count: i64,
+}
+
+/// Phone number of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PhoneInsights {
+    /// Phone number, without the country code
+    #[schema(example = "212-555-0123")]
+    pub number: String,
+    /// Phone country calling code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "1")]
+    pub country_code: Option<String>,
+    /// The number in E.164 form, when its country calling code is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "+12125550123")]
+    pub e164: Option<String>,
+    /// Whether the number fits the numbering plan of its country calling code; omitted for
+    /// calling codes the bundled numbering plan does not cover
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_valid: Option<bool>,
+    /// ISO 3166-1 alpha-2 country the number is allocated to, for valid numbers outside ranges
+    /// shared by several countries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "US")]
+    pub country: Option<String>,
+    /// Kind of line the number belongs to, for valid numbers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_type: Option<PhoneLineType>,
+    /// Operator the number's range was originally allocated to, when known; the number may
+    /// since have been ported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    /// Transactions whose billing or shipping address had the number
+    #[schema(example = 2)]
+    pub transaction_count: i64,
+    /// Distinct users whose addresses had the number
+    #[schema(example = 1)]
+    pub user_count: i64,
+}
+
+/// Kind of line a phone number belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PhoneLineType {
+    /// Landline
+    FixedLine,
+    /// Mobile phone
+    Mobile,
+    /// Landline or mobile phone, where the numbering plan does not tell them apart
+    FixedLineOrMobile,
+    /// Internet telephony, not tied to a line or SIM card
+    Voip,
+    /// Free for the caller
+    TollFree,
+    /// Charged at a premium rate
+    PremiumRate,
+    /// Cost shared between caller and recipient
+    SharedCost,
+    /// Personal number, forwarded wherever the owner chooses
+    PersonalNumber,
+    /// Pager
+    Pager,
+}
+
+impl PhoneLineType {
+    /// The line type named `name`, as serialized
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "fixed_line" => PhoneLineType::FixedLine,
+            "mobile" => PhoneLineType::Mobile,
+            "fixed_line_or_mobile" => PhoneLineType::FixedLineOrMobile,
+            "voip" => PhoneLineType::Voip,
+            "toll_free" => PhoneLineType::TollFree,
+            "premium_rate" => PhoneLineType::PremiumRate,
+            "shared_cost" => PhoneLineType::SharedCost,
+            "personal_number" => PhoneLineType::PersonalNumber,
+            "pager" => PhoneLineType::Pager,
+            _ => return None,
+        })
+    }
+}
+
+/// Payment card of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreditCardInsights {
+    /// First 6-8 digits of the card (BIN)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "411111")]
+    pub issuer_id_number: Option<String>,
+    /// Last 2-4 digits of the card
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "1111")]
+    pub last_digits: Option<String>,
+    /// Card network, derived from the issuer ID number when not otherwise known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "visa")]
+    pub brand: Option<String>,
+    /// Credit, debit, or charge card
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "credit")]
+    pub card_type: Option<String>,
+    /// Name of the issuing bank
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bank_name: Option<String>,
+    /// Country where the card was issued
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "US")]
+    pub country: Option<String>,
+    /// Whether the card was issued in the billing country; absent if either is unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matches_billing_country: Option<bool>,
+    /// Address Verification System result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avs_result: Option<String>,
+    /// CVV verification result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cvv_result: Option<String>,
+    /// Whether 3D Secure verification was successful
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub was_3d_secure_successful: Option<bool>,
+    /// Whether the card is a business card
+    pub is_business: bool,
+    /// Whether the card is prepaid
+    pub is_prepaid: bool,
+    /// Whether the card is a virtual card
+    pub is_virtual: bool,
+    /// How closely the cardholder name matches the billing name, from 0 for unrelated names to
+    /// 1 for the same name; absent unless both were supplied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 0.95)]
+    pub billing_name_match: Option<f64>,
+    /// How closely the cardholder name matches the local part of the email address, from 0 to
+    /// 1; absent unless both were supplied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 1.0)]
+    pub email_name_match: Option<f64>,
+    /// Transactions paid with the same card token; 1 when no token was supplied
+    #[schema(example = 3)]
+    pub transaction_count: i64,
+}
+
+/// Card network of an issuer ID number, from the networks' published prefix ranges
+pub fn card_brand(issuer_id_number: &str) -> Option<&'static str> {
+    let prefix = |len: usize| issuer_id_number.get(..len)?.parse::<u32>().ok();
+    let (two, four, six) = (prefix(2)?, prefix(4), prefix(6));
+    let brand = match two {
+        34 | 37 => "amex",
+        36 | 38 | 39 | 30 => "diners_club",
+        35 if four.is_some_and(|p| (3528..=3589).contains(&p)) => "jcb",
+        51..=55 => "mastercard",
+        22..=27 if four.is_some_and(|p| (2221..=2720).contains(&p)) => "mastercard",
+        62 if six.is_some_and(|p| (622126..=622925).contains(&p)) => "discover",
+        62 => "unionpay",
+        60 if four == Some(6011) => "discover",
+        64 | 65 => "discover",
+        40..=49 => "visa",
+        _ => return None,
+    };
+    Some(brand)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_brand_from_issuer_id_number() {
+        assert_eq!(card_brand("411111"), Some("visa"));
+        assert_eq!(card_brand("555555"), Some("mastercard"));
+        assert_eq!(card_brand("222100"), Some("mastercard"));
+        assert_eq!(card_brand("378282"), Some("amex"));
+        assert_eq!(card_brand("601100"), Some("discover"));
+        assert_eq!(card_brand("622126"), Some("discover"));
+        assert_eq!(card_brand("620000"), Some("unionpay"));
+        assert_eq!(card_brand("353011"), Some("jcb"));
+        assert_eq!(card_brand("305693"), Some("diners_club"));
+        assert_eq!(card_brand("999999"), None);
+        assert_eq!(card_brand("4"), None);
+    }
+}
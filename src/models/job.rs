@@ -0,0 +1,315 @@
+//! Background jobs, such as transactions submitted for asynchronous scoring
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    common::Links,
+    transaction::{Disposition, RiskLevel, ScoringRevision, TransactionResponse},
+};
+use crate::api::errors::ErrorResponse;
+
+/// Progress of a background job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting to be run
+    Pending,
+    /// Being run by a worker
+    Running,
+    /// Run to the end; the job carries its result
+    Completed,
+    /// Could not be run; the job carries the error
+    Failed,
+}
+
+/// Work a background job does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum JobKind {
+    /// Score and store a transaction submitted with `mode=async`; the payload is the
+    /// [`TransactionRequest`](super::transaction::TransactionRequest) and the result a
+    /// [`ScoreJobResult`]
+    Score,
+    /// Rescore stored transactions; see [`RescoreJobRequest`]
+    Rescore,
+    /// Score stored transactions or users without recording the results, writing them to a
+    /// file; see [`BatchScoreJobRequest`]
+    BatchScore,
+}
+
+/// Long-running work queued by an account and run by the background job workers
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "id": "0b6f9d0e-4b8e-4a8f-9a53-1f1d6f0c2a77",
+    "kind": "rescore",
+    "status": "completed",
+    "attempts": 1,
+    "result": {
+        "results": [
+            {
+                "transaction_id": "550e8400-e29b-41d4-a716-446655440000",
+                "status": 201,
+                "revision": {
+                    "transaction_id": "550e8400-e29b-41d4-a716-446655440000",
+                    "revision": 2,
+                    "risk_score": 27.8,
+                    "risk_level": "low",
+                    "disposition": "accept",
+                    "previous_risk_score": 15.42,
+                    "created_at": "2025-06-13T10:30:01.456Z"
+                }
+            }
+        ],
+        "succeeded": 1,
+        "failed": 0
+    },
+    "created_at": "2025-06-13T10:30:00.123Z",
+    "started_at": "2025-06-13T10:30:00.512Z",
+    "completed_at": "2025-06-13T10:30:01.456Z",
+    "_links": {
+        "self": { "href": "/v1/jobs/0b6f9d0e-4b8e-4a8f-9a53-1f1d6f0c2a77" }
+    }
+}))]
+pub struct BackgroundJob {
+    /// Unique job identifier
+    pub id: Uuid,
+    /// Work the job does
+    pub kind: JobKind,
+    /// Progress of the job
+    pub status: JobStatus,
+    /// Times a worker has started the job
+    pub attempts: i32,
+    /// Output of the job, once it has completed, in the shape of its kind
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<serde_json::Value>,
+    /// Why the job failed, if it has failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+    /// Where the finished job is POSTed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
+    /// When the job was queued
+    pub created_at: DateTime<Utc>,
+    /// When a worker first started the job
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the job completed or failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Result of a scoring job: the transaction it scored and stored
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "transaction": {
+        "id": "550e8400-e29b-41d4-a716-446655440000",
+        "risk_score": 2.45,
+        "risk_level": "low",
+        "disposition": "accept",
+        "event_type": "purchase",
+        "created_at": "2025-06-13T10:30:01.456Z",
+        "_links": {
+            "self": { "href": "/v1/transactions/550e8400-e29b-41d4-a716-446655440000" }
+        }
+    }
+}))]
+pub struct ScoreJobResult {
+    /// Risk assessment of the stored transaction
+    pub transaction: TransactionResponse,
+}
+
+/// Stored transactions to rescore in the background
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "transaction_ids": [
+        "550e8400-e29b-41d4-a716-446655440000",
+        "6fa459ea-ee8a-3ca4-894e-db77e160355e"
+    ]
+}))]
+pub struct RescoreJobRequest {
+    /// Transactions to rescore, each once
+    pub transaction_ids: Vec<Uuid>,
+}
+
+/// Result of a rescore job, one item per transaction in request order
+///
+/// While the job runs, `results` covers the transactions rescored so far.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RescoreJobResult {
+    /// Results in the order the transactions were named
+    pub results: Vec<RescoreItemResult>,
+    /// Transactions rescored
+    pub succeeded: usize,
+    /// Transactions that could not be rescored
+    pub failed: usize,
+}
+
+/// Outcome of rescoring one transaction of a rescore job
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RescoreItemResult {
+    /// Transaction rescored
+    pub transaction_id: Uuid,
+    /// HTTP status rescoring the transaction on its own would have received
+    #[schema(example = 201)]
+    pub status: u16,
+    /// New scoring revision, if the transaction was rescored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<ScoringRevision>,
+    /// Why the transaction could not be rescored, otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+}
+
+/// What a batch scoring job scores
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchScoreTarget {
+    /// Each stored transaction
+    Transactions,
+    /// The latest stored transaction of each user
+    Users,
+}
+
+/// File format of a batch scoring job's output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchScoreFormat {
+    /// Comma-separated values with a header line
+    #[default]
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+/// Stored transactions or users to score in the background without recording the results
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "target": "users",
+    "inactive_since": "2025-03-01T00:00:00Z",
+    "format": "csv"
+}))]
+pub struct BatchScoreJobRequest {
+    /// Whether to score transactions, or the latest transaction of each user
+    pub target: BatchScoreTarget,
+    /// Only transactions stored at or after this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only transactions stored before this time; defaults to when the job is queued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<DateTime<Utc>>,
+    /// Only users whose last transaction was before this time, to screen dormant users;
+    /// `users` target only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inactive_since: Option<DateTime<Utc>>,
+    /// Most transactions or users to score, the first in creation order; defaults to the most
+    /// allowed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// File format of the output
+    #[serde(default)]
+    pub format: BatchScoreFormat,
+}
+
+/// Progress and outcome of a batch scoring job
+///
+/// Each scored transaction is a line of the job's output file, downloaded from `output` once
+/// the job has completed. Transactions that could not be scored are lines too, with their
+/// error; the first of them are also listed in `failures`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "total": 1200,
+    "processed": 1200,
+    "succeeded": 1198,
+    "failed": 2,
+    "failures": [
+        {
+            "transaction_id": "550e8400-e29b-41d4-a716-446655440000",
+            "status": 409,
+            "message": "Transaction was scored before requests were kept for rescoring"
+        }
+    ],
+    "resume_after": "00063f1a2b3c4d5effe1c7a251f24b6b9f0e2a1d8b7c3e90",
+    "chunks": 6,
+    "format": "csv",
+    "output": "/v1/jobs/0b6f9d0e-4b8e-4a8f-9a53-1f1d6f0c2a77/output"
+}))]
+pub struct BatchScoreJobResult {
+    /// Transactions or users to score, counted when the job was queued
+    pub total: usize,
+    /// Transactions or users scored so far, successfully or not
+    pub processed: usize,
+    /// Transactions scored
+    pub succeeded: usize,
+    /// Transactions that could not be scored
+    pub failed: usize,
+    /// The first transactions that could not be scored
+    pub failures: Vec<BatchScoreFailure>,
+    /// Position of the last transaction or user processed, which an interrupted job resumes
+    /// after
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_after: Option<String>,
+    /// Chunks of the output file written so far
+    pub chunks: i32,
+    /// File format of the output
+    pub format: BatchScoreFormat,
+    /// Where the output file is downloaded from
+    pub output: String,
+}
+
+/// Transaction a batch scoring job could not score
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchScoreFailure {
+    /// Transaction that could not be scored
+    pub transaction_id: Uuid,
+    /// HTTP status rescoring the transaction on its own would have received
+    #[schema(example = 409)]
+    pub status: u16,
+    /// Why the transaction could not be scored
+    pub message: String,
+}
+
+/// One line of a batch scoring job's output file
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "transaction_id": "550e8400-e29b-41d4-a716-446655440000",
+    "user_id": "6fa459ea-ee8a-3ca4-894e-db77e160355e",
+    "previous_risk_score": 15.42,
+    "risk_score": 61.3,
+    "risk_level": "high",
+    "disposition": "review",
+    "rule_codes": ["CVV_MISMATCH", "LARGE_AMOUNT"]
+}))]
+pub struct BatchScoreRow {
+    /// Transaction scored
+    pub transaction_id: Uuid,
+    /// User the transaction is attributed to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<Uuid>,
+    /// Risk score of the transaction's latest stored scoring
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_risk_score: Option<f64>,
+    /// Risk score under the current rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub risk_score: Option<f64>,
+    /// Risk level under the current rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub risk_level: Option<RiskLevel>,
+    /// Disposition under the account's current policy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disposition: Option<Disposition>,
+    /// Codes of the rules that raised the score
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rule_codes: Vec<String>,
+    /// Why the transaction could not be scored, if it could not
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
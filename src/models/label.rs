@@ -0,0 +1,271 @@
+//! Ground-truth labels of transactions, the training data of fraud models
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{
+    common::{Link, Links, Pagination},
+    transaction::{Disposition, EventType, ReportTag, RiskLevel},
+};
+
+/// Longest notes accepted with a manual label
+const MAX_NOTES_LENGTH: usize = 1000;
+
+/// Chargeback reason codes of card networks that dispute a payment as fraud: Visa 10.x,
+/// Mastercard, American Express, and Discover fraud codes
+const FRAUD_REASON_CODES: &[&str] = &[
+    "10.1", "10.2", "10.3", "10.4", "10.5", "4837", "4840", "4849", "4863", "4870", "4871", "F24",
+    "F29", "FR2", "FR4", "FR6", "UA01", "UA02", "UA05",
+];
+
+/// What a transaction turned out to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum Label {
+    /// Fraud or abuse
+    Fraud,
+    /// A legitimate transaction
+    Legit,
+    /// A legitimate customer disputed the payment for a reason other than fraud, such as
+    /// claiming the goods never arrived
+    FriendlyFraud,
+    /// Nobody can tell
+    Unknown,
+}
+
+impl Label {
+    /// Label of a transaction with the given outcome on record
+    ///
+    /// A chargeback is fraud unless its reason code is known to dispute something else.
+    pub fn from_outcome(tag: ReportTag, chargeback_code: Option<&str>) -> Self {
+        match tag {
+            ReportTag::NotFraud => Label::Legit,
+            ReportTag::Chargeback
+                if chargeback_code.is_some_and(|code| {
+                    !FRAUD_REASON_CODES
+                        .iter()
+                        .any(|fraud| fraud.eq_ignore_ascii_case(code.trim()))
+                }) =>
+            {
+                Label::FriendlyFraud
+            },
+            ReportTag::Chargeback | ReportTag::SuspectedFraud | ReportTag::SpamOrAbuse => {
+                Label::Fraud
+            },
+        }
+    }
+
+    /// Name used in storage and exports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Label::Fraud => "fraud",
+            Label::Legit => "legit",
+            Label::FriendlyFraud => "friendly_fraud",
+            Label::Unknown => "unknown",
+        }
+    }
+}
+
+/// Where a label came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum LabelSource {
+    /// An outcome the merchant or its payment processor reported
+    Report,
+    /// A reviewer's decision on the transaction's case
+    Case,
+    /// Set by hand, and kept whatever outcomes are reported later
+    Manual,
+}
+
+impl LabelSource {
+    /// Name used in storage and exports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LabelSource::Report => "report",
+            LabelSource::Case => "case",
+            LabelSource::Manual => "manual",
+        }
+    }
+}
+
+/// Ground-truth label of a transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionLabel {
+    /// Labeled transaction
+    pub transaction_id: Uuid,
+    /// What the transaction turned out to be
+    pub label: Label,
+    /// Where the label came from
+    pub source: LabelSource,
+    /// Notes given with a manual label
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// When the transaction was first labeled
+    pub created_at: DateTime<Utc>,
+    /// When the label last changed
+    pub updated_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl TransactionLabel {
+    /// Links of the label of the given transaction
+    pub fn links(transaction_id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/labels/{transaction_id}"))),
+            ..Links::default()
+        }
+    }
+}
+
+/// Page of the account's labels, most recently changed first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LabelList {
+    /// Labels on this page
+    pub labels: Vec<TransactionLabel>,
+    /// Pagination metadata
+    pub pagination: Pagination,
+    /// Navigation links
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Query parameters for listing labels
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListLabelsQuery {
+    /// Maximum number of labels to return (1-100)
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    pub limit: Option<i64>,
+    /// Number of labels to skip
+    #[param(minimum = 0, default = 0)]
+    pub offset: Option<i64>,
+    /// Filter by label
+    pub label: Option<Label>,
+    /// Filter by where the label came from
+    pub source: Option<LabelSource>,
+    /// Only labels of transactions scored at or after this time
+    pub from_date: Option<DateTime<Utc>>,
+    /// Only labels of transactions scored before this time
+    pub to_date: Option<DateTime<Utc>>,
+}
+
+/// Label to set by hand
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LabelRequest {
+    /// What the transaction turned out to be
+    pub label: Label,
+    /// Free-form notes
+    #[schema(example = "Confirmed with the cardholder by phone")]
+    pub notes: Option<String>,
+}
+
+impl LabelRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .notes
+            .as_ref()
+            .is_some_and(|notes| notes.chars().count() > MAX_NOTES_LENGTH)
+        {
+            return Err(format!(
+                "notes must be at most {MAX_NOTES_LENGTH} characters"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Form of a labeled dataset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelExportFormat {
+    /// CSV with a header line, rule codes separated by spaces
+    #[default]
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+/// Query parameters for exporting a labeled dataset
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LabelExportQuery {
+    /// Form of the export (default `csv`)
+    #[serde(default)]
+    pub format: LabelExportFormat,
+    /// Only transactions with this label
+    pub label: Option<Label>,
+    /// Only transactions scored at or after this time
+    pub from_date: Option<DateTime<Utc>>,
+    /// Only transactions scored before this time
+    pub to_date: Option<DateTime<Utc>>,
+}
+
+/// A labeled transaction, as exported for model training
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LabeledTransaction {
+    /// Unique transaction identifier
+    pub transaction_id: Uuid,
+    /// Merchant's identifier for the transaction
+    pub external_transaction_id: Option<String>,
+    /// Type of event scored
+    pub event_type: EventType,
+    /// When the event occurred
+    pub event_time: DateTime<Utc>,
+    /// When the transaction was scored
+    pub scored_at: DateTime<Utc>,
+    /// Risk score the transaction received
+    pub risk_score: f64,
+    /// Risk level of the score
+    pub risk_level: RiskLevel,
+    /// Disposition the transaction received
+    pub disposition: Disposition,
+    /// Codes of the risk factors the transaction was scored with
+    #[schema(example = json!(["MISSING_USER_AGENT", "HOSTING_IP"]))]
+    pub rule_codes: Vec<String>,
+    /// What the transaction turned out to be
+    pub label: Label,
+    /// Where the label came from
+    pub label_source: LabelSource,
+    /// When the label last changed
+    pub labeled_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chargebacks_for_other_reasons_are_friendly_fraud() {
+        assert_eq!(
+            Label::from_outcome(ReportTag::Chargeback, Some("10.4")),
+            Label::Fraud
+        );
+        assert_eq!(
+            Label::from_outcome(ReportTag::Chargeback, Some("fr2")),
+            Label::Fraud
+        );
+        assert_eq!(
+            Label::from_outcome(ReportTag::Chargeback, None),
+            Label::Fraud
+        );
+        // Visa 13.1: merchandise not received
+        assert_eq!(
+            Label::from_outcome(ReportTag::Chargeback, Some("13.1")),
+            Label::FriendlyFraud
+        );
+        assert_eq!(Label::from_outcome(ReportTag::NotFraud, None), Label::Legit);
+        assert_eq!(
+            Label::from_outcome(ReportTag::SuspectedFraud, None),
+            Label::Fraud
+        );
+    }
+}
@@ -0,0 +1,807 @@
+//! Lists of entities an account treats specially when scoring
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::common::{Link, Links, Pagination};
+use crate::utils::{
+    ip::{max_prefix_len, network},
+    sha256_hex,
+};
+
+/// Longest reason that can be recorded on a list entry
+const MAX_REASON_LEN: usize = 500;
+/// Longest email domain that can be listed
+const MAX_DOMAIN_LEN: usize = 253;
+
+/// What listing a network does to transactions from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum AsnListAction {
+    /// Reject transactions from the network outright
+    Block,
+    /// Add the entry's score to transactions from the network
+    Boost,
+}
+
+/// A network on the account's ASN list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AsnListEntry {
+    /// Autonomous system number
+    #[schema(example = 64500)]
+    pub asn: i64,
+    /// What listing the network does to transactions from it
+    pub action: AsnListAction,
+    /// Risk added to transactions from the network, for `boost` entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 40.0)]
+    pub score: Option<f64>,
+    /// Why the network was listed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Bulletproof hoster")]
+    pub reason: Option<String>,
+    /// When the network was listed
+    pub created_at: DateTime<Utc>,
+    /// When the entry was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Network to list, replacing any existing entry for it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AsnListEntryRequest {
+    /// Autonomous system number
+    #[schema(example = 64500)]
+    pub asn: u32,
+    /// What listing the network does to transactions from it
+    pub action: AsnListAction,
+    /// Risk from 0 to 100 added to transactions from the network; required for `boost`
+    /// entries and not allowed for `block` ones
+    #[schema(example = 40.0)]
+    pub score: Option<f64>,
+    /// Why the network is listed, up to 500 characters
+    #[schema(example = "Bulletproof hoster")]
+    pub reason: Option<String>,
+}
+
+impl AsnListEntryRequest {
+    /// Check the score matches the action and the reason fits
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.action, self.score) {
+            (AsnListAction::Boost, None) => {
+                return Err("score is required for boost entries".to_string());
+            },
+            (AsnListAction::Boost, Some(score)) => validate_score(score)?,
+            (AsnListAction::Block, Some(_)) => {
+                return Err("score is only allowed for boost entries".to_string());
+            },
+            (AsnListAction::Block, None) => {},
+        }
+        validate_reason(self.reason.as_deref())
+    }
+}
+
+/// Every network on the account's ASN list, in ascending order
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AsnListEntries {
+    /// Listed networks
+    pub entries: Vec<AsnListEntry>,
+}
+
+/// What listing a country does to transactions involving it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum CountryListAction {
+    /// Reject transactions involving the country outright
+    Block,
+    /// Send transactions involving the country to manual review
+    Review,
+    /// Add the entry's score to transactions involving the country
+    Boost,
+}
+
+/// A country on the account's country list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CountryListEntry {
+    /// ISO 3166-1 alpha-2 country code
+    #[schema(example = "NG")]
+    pub country: String,
+    /// What listing the country does to transactions involving it
+    pub action: CountryListAction,
+    /// Risk added to transactions involving the country, for `boost` entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 25.0)]
+    pub score: Option<f64>,
+    /// Why the country was listed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "High chargeback rate")]
+    pub reason: Option<String>,
+    /// When the country was listed
+    pub created_at: DateTime<Utc>,
+    /// When the entry was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Country to list, replacing any existing entry for it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CountryListEntryRequest {
+    /// ISO 3166-1 alpha-2 country code, in upper case
+    #[schema(example = "NG")]
+    pub country: String,
+    /// What listing the country does to transactions involving it
+    pub action: CountryListAction,
+    /// Risk from 0 to 100 added to transactions involving the country; required for `boost`
+    /// entries and not allowed for others
+    #[schema(example = 25.0)]
+    pub score: Option<f64>,
+    /// Why the country is listed, up to 500 characters
+    #[schema(example = "High chargeback rate")]
+    pub reason: Option<String>,
+}
+
+impl CountryListEntryRequest {
+    /// Check the country code, that the score matches the action, and that the reason fits
+    pub fn validate(&self) -> Result<(), String> {
+        if self.country.len() != 2 || !self.country.bytes().all(|b| b.is_ascii_uppercase()) {
+            return Err("country must be an ISO 3166-1 alpha-2 country code".to_string());
+        }
+        match (self.action, self.score) {
+            (CountryListAction::Boost, None) => {
+                return Err("score is required for boost entries".to_string());
+            },
+            (CountryListAction::Boost, Some(score)) => validate_score(score)?,
+            (CountryListAction::Block | CountryListAction::Review, Some(_)) => {
+                return Err("score is only allowed for boost entries".to_string());
+            },
+            (CountryListAction::Block | CountryListAction::Review, None) => {},
+        }
+        validate_reason(self.reason.as_deref())
+    }
+}
+
+/// Every country on the account's country list, in alphabetical order
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CountryListEntries {
+    /// Listed countries
+    pub entries: Vec<CountryListEntry>,
+}
+
+/// A range of cards in the account's BIN table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BinRange {
+    /// Leading digits of the card numbers in the range, 4 to 8 of them
+    #[schema(example = "400000")]
+    pub prefix: String,
+    /// Card network, in lowercase
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "visa")]
+    pub brand: Option<String>,
+    /// `credit`, `debit`, or `charge`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "debit")]
+    pub card_type: Option<String>,
+    /// ISO 3166-1 alpha-2 country the cards are issued in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "US")]
+    pub country: Option<String>,
+    /// Name of the issuing bank
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "First Bank, N.A.")]
+    pub bank_name: Option<String>,
+    /// Whether the cards are prepaid
+    pub is_prepaid: bool,
+    /// Whether the cards are issued to businesses
+    pub is_business: bool,
+    /// Whether the cards are virtual, issued for online payments without a physical card
+    pub is_virtual: bool,
+    /// When the range was imported
+    pub created_at: DateTime<Utc>,
+    /// When the range was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Query parameters for listing the BIN table
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListBinRangesQuery {
+    /// Maximum number of ranges to return (1-1000, default 100)
+    pub limit: Option<i64>,
+    /// Number of ranges to skip
+    pub offset: Option<i64>,
+}
+
+/// Page of the account's BIN table, in ascending order of prefix
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BinRangeList {
+    /// Ranges on this page
+    pub ranges: Vec<BinRange>,
+    /// Pagination metadata
+    pub pagination: Pagination,
+    /// Navigation links
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Query parameters for importing a BIN table
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportBinRangesQuery {
+    /// Remove every range not in the import, rather than adding the import to the table
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// Outcome of a BIN table import
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct BinRangeImport {
+    /// Ranges added or updated
+    #[schema(example = 1250)]
+    pub imported: u64,
+    /// Ranges removed because the import replaced the table
+    #[schema(example = 0)]
+    pub removed: u64,
+}
+
+/// Which of an account's lists an entry is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum ListType {
+    /// Entities the account refuses to deal with
+    Blocklist,
+    /// Entities the account trusts
+    Allowlist,
+    /// Entities the account keeps an eye on
+    Watchlist,
+}
+
+impl ListType {
+    /// Name of the list in the API
+    pub fn name(self) -> &'static str {
+        match self {
+            ListType::Blocklist => "blocklist",
+            ListType::Allowlist => "allowlist",
+            ListType::Watchlist => "watchlist",
+        }
+    }
+}
+
+/// Kind of entity a list entry names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum ListEntityType {
+    /// Email address, stored as the SHA-256 hash of its lowercase form
+    Email,
+    /// Domain of email addresses
+    EmailDomain,
+    /// Single IP address
+    Ip,
+    /// Range of IP addresses in CIDR notation
+    Cidr,
+    /// Device, by its fusegu ID
+    Device,
+    /// Payment card, by the SHA-256 hash of its token
+    CardHash,
+    /// User, by their fusegu ID
+    User,
+    /// ISO 3166-1 alpha-2 country
+    Country,
+}
+
+impl ListEntityType {
+    /// Name of the entity type in the API, logs, and cache keys
+    pub fn name(self) -> &'static str {
+        match self {
+            ListEntityType::Email => "email",
+            ListEntityType::EmailDomain => "email_domain",
+            ListEntityType::Ip => "ip",
+            ListEntityType::Cidr => "cidr",
+            ListEntityType::Device => "device",
+            ListEntityType::CardHash => "card_hash",
+            ListEntityType::User => "user",
+            ListEntityType::Country => "country",
+        }
+    }
+
+    /// Normal form of `value`, in which entries are stored and matched, or why it is not a
+    /// value of the type
+    ///
+    /// Email addresses and card tokens are hashed, unless given as a SHA-256 hash already; IP
+    /// addresses and ranges are written canonically, with the host bits of ranges cleared.
+    pub fn normalize(self, value: &str) -> Result<String, String> {
+        let value = value.trim();
+        let normalized = match self {
+            ListEntityType::Email if is_sha256_hex(value) => Some(value.to_lowercase()),
+            ListEntityType::Email => value
+                .contains('@')
+                .then(|| sha256_hex(&value.to_lowercase())),
+            ListEntityType::EmailDomain => {
+                let domain = value.trim_start_matches('@').to_lowercase();
+                (domain.contains('.')
+                    && !domain.contains(['@', ' '])
+                    && domain.len() <= MAX_DOMAIN_LEN)
+                    .then_some(domain)
+            },
+            ListEntityType::Ip => value
+                .parse::<IpAddr>()
+                .ok()
+                .map(|ip| ip.to_canonical().to_string()),
+            ListEntityType::Cidr => value.split_once('/').and_then(|(address, prefix_len)| {
+                let ip = address.parse::<IpAddr>().ok()?.to_canonical();
+                let prefix_len: u32 = prefix_len.parse().ok()?;
+                (prefix_len <= max_prefix_len(ip)).then(|| network(ip, prefix_len))
+            }),
+            ListEntityType::Device | ListEntityType::User => {
+                value.parse::<Uuid>().ok().map(|id| id.to_string())
+            },
+            ListEntityType::CardHash if is_sha256_hex(value) => Some(value.to_lowercase()),
+            ListEntityType::CardHash => (!value.is_empty()).then(|| sha256_hex(value)),
+            ListEntityType::Country => (value.len() == 2
+                && value.bytes().all(|b| b.is_ascii_alphabetic()))
+            .then(|| value.to_uppercase()),
+        };
+        normalized.ok_or_else(|| format!("value is not a valid {}", self.name()))
+    }
+}
+
+/// An entity on one of the account's lists
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ListEntry {
+    /// Unique entry identifier
+    pub id: Uuid,
+    /// List the entity is on
+    pub list_type: ListType,
+    /// Kind of entity
+    pub entity_type: ListEntityType,
+    /// The entity, in normal form: hashes for email addresses and card tokens
+    #[schema(example = "203.0.113.0/24")]
+    pub value: String,
+    /// Why the entity was listed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Chargebacks from this range")]
+    pub reason: Option<String>,
+    /// When the entry stops applying; entries without one apply until removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Public identifier of the account that listed the entity: the calling account, or for
+    /// a blocklist its organization shares, another member
+    #[schema(example = "acme-payments")]
+    pub added_by: String,
+    /// When the entity was listed
+    pub created_at: DateTime<Utc>,
+    /// When the entry was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ListEntry {
+    /// Whether the entry still applies at `at`
+    pub fn is_active(&self, at: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > at)
+    }
+}
+
+/// Entity to list, replacing any existing entry for it on the same list
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ListEntryRequest {
+    /// Kind of entity
+    pub entity_type: ListEntityType,
+    /// The entity: an email address or its SHA-256 hash, an email domain, an IP address, a
+    /// CIDR range, a device or user ID, a card token or its SHA-256 hash, or an ISO 3166-1
+    /// alpha-2 country code
+    #[schema(example = "203.0.113.0/24")]
+    pub value: String,
+    /// Why the entity is listed, up to 500 characters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Chargebacks from this range")]
+    pub reason: Option<String>,
+    /// When the entry stops applying, in the future; omit to keep it until removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl ListEntryRequest {
+    /// Normal form of the value, checking it, the expiry, and the reason
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<String, String> {
+        let value = self.entity_type.normalize(&self.value)?;
+        if self.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err("expires_at must be in the future".to_string());
+        }
+        validate_reason(self.reason.as_deref())?;
+        Ok(value)
+    }
+}
+
+/// Query parameters for listing the entries of a list
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListEntriesQuery {
+    /// Only entries naming this kind of entity
+    pub entity_type: Option<ListEntityType>,
+    /// Maximum number of entries to return (1-1000, default 100)
+    pub limit: Option<i64>,
+    /// Number of entries to skip
+    pub offset: Option<i64>,
+}
+
+/// Query parameters naming the entry to remove from a list
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteListEntryQuery {
+    /// Kind of entity
+    pub entity_type: ListEntityType,
+    /// The entity, in any form accepted when listing it
+    pub value: String,
+}
+
+/// Page of the entries of a list still in force, newest first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListEntryList {
+    /// Entries on this page
+    pub entries: Vec<ListEntry>,
+    /// Pagination metadata
+    pub pagination: Pagination,
+    /// Navigation links
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Progress of a list import
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum ListImportStatus {
+    /// Waiting to be processed
+    Pending,
+    /// Entries are being imported; see the counts for progress
+    Processing,
+    /// Every entry has been imported or has failed
+    Completed,
+}
+
+/// Entry of a list import that could not be imported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ListImportError {
+    /// Position of the entry among the submitted entries, from 0, not counting a CSV header
+    pub index: usize,
+    /// Why the entry was not imported
+    pub message: String,
+}
+
+/// Batch of list entries imported in the background
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "id": "8d1e4b2a-6c3f-4a5e-9b7d-2e3f4a5b6c7d",
+    "list_type": "blocklist",
+    "status": "processing",
+    "total_entries": 40000,
+    "processed_entries": 20000,
+    "created_entries": 19650,
+    "updated_entries": 340,
+    "failed_entries": 10,
+    "errors": [{ "index": 1204, "message": "value is not a valid cidr" }],
+    "created_at": "2025-06-13T10:30:00Z",
+    "started_at": "2025-06-13T10:30:01Z",
+    "_links": {
+        "self": { "href": "/v1/lists/imports/8d1e4b2a-6c3f-4a5e-9b7d-2e3f4a5b6c7d" }
+    }
+}))]
+pub struct ListImport {
+    /// Unique import identifier
+    pub id: Uuid,
+    /// List the entries are imported into
+    pub list_type: ListType,
+    /// Progress of the import
+    pub status: ListImportStatus,
+    /// Entries submitted
+    pub total_entries: i32,
+    /// Entries imported or failed so far
+    pub processed_entries: i32,
+    /// Entities newly listed
+    pub created_entries: i32,
+    /// Entities already on the list whose entries were replaced
+    pub updated_entries: i32,
+    /// Entries that could not be imported
+    pub failed_entries: i32,
+    /// Why entries could not be imported; only the first failures are listed
+    pub errors: Vec<ListImportError>,
+    /// When the import was submitted
+    pub created_at: DateTime<Utc>,
+    /// When processing started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the import finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl ListImport {
+    /// Links of the import with the given ID
+    pub fn links(id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/lists/imports/{id}"))),
+            ..Links::default()
+        }
+    }
+}
+
+/// Form a list is exported in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ListExportFormat {
+    /// CSV with a header line
+    #[default]
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+/// Query parameters for exporting a list
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListExportQuery {
+    /// Form of the export (default `csv`)
+    #[serde(default)]
+    pub format: ListExportFormat,
+    /// Only entries naming this kind of entity
+    pub entity_type: Option<ListEntityType>,
+}
+
+/// Entity put on the blocklist automatically after repeated critical velocity breaches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "id": "5b0c7e1d-2f3a-4b5c-8d9e-0f1a2b3c4d5e",
+    "list_entry_id": "0e9d8c7b-6a5f-4e3d-2c1b-0a9f8e7d6c5b",
+    "entity_type": "ip",
+    "value": "198.51.100.7",
+    "factor_codes": ["CARD_TESTING"],
+    "breaches": 3,
+    "transaction_id": "7c9e6679-7425-40de-944b-e07fc1f90ae7",
+    "expires_at": "2025-06-13T11:30:00Z",
+    "created_at": "2025-06-13T10:30:00Z"
+}))]
+pub struct AutoBlock {
+    /// Unique identifier of the block
+    pub id: Uuid,
+    /// Blocklist entry added, unless it has since been removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_entry_id: Option<Uuid>,
+    /// Kind of entity blocked: `ip`, `device`, or `card_hash`
+    pub entity_type: ListEntityType,
+    /// The entity, in normal form
+    pub value: String,
+    /// Critical velocity factors of the transaction that triggered the block
+    pub factor_codes: Vec<String>,
+    /// Transactions of the entity with a critical velocity factor within the window, counting
+    /// the one that triggered the block
+    pub breaches: i32,
+    /// Transaction that triggered the block, unless it has since been deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<Uuid>,
+    /// When the block stops applying
+    pub expires_at: DateTime<Utc>,
+    /// When the entity was blocked
+    pub created_at: DateTime<Utc>,
+}
+
+/// Query parameters for listing automatic blocks
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListAutoBlocksQuery {
+    /// Maximum number of blocks to return (1-1000, default 100)
+    pub limit: Option<i64>,
+    /// Number of blocks to skip
+    pub offset: Option<i64>,
+}
+
+/// Page of the account's automatic blocks, newest first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AutoBlockList {
+    /// Blocks on this page
+    pub auto_blocks: Vec<AutoBlock>,
+    /// Pagination metadata
+    pub pagination: Pagination,
+    /// Navigation links
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Whether `value` is a SHA-256 hash in hex
+fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn validate_score(score: f64) -> Result<(), String> {
+    if !(0.0..=100.0).contains(&score) {
+        return Err("score must be between 0 and 100".to_string());
+    }
+    Ok(())
+}
+
+fn validate_reason(reason: Option<&str>) -> Result<(), String> {
+    if reason.is_some_and(|reason| reason.chars().count() > MAX_REASON_LEN) {
+        return Err(format!(
+            "reason must be at most {MAX_REASON_LEN} characters"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn request(value: serde_json::Value) -> AsnListEntryRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_asn_list_entry_validation() {
+        assert!(
+            request(json!({ "asn": 64500, "action": "block" }))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            request(json!({ "asn": 64500, "action": "boost", "score": 40.0 }))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            request(json!({ "asn": 64500, "action": "boost" }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            request(json!({ "asn": 64500, "action": "boost", "score": 140.0 }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            request(json!({ "asn": 64500, "action": "block", "score": 40.0 }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            request(json!({ "asn": 64500, "action": "block", "reason": "x".repeat(501) }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            serde_json::from_value::<AsnListEntryRequest>(json!({ "asn": -1, "action": "block" }))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_list_entry_values_are_normalized() {
+        let normalize = ListEntityType::normalize;
+        assert_eq!(
+            normalize(ListEntityType::Email, " Jane@Example.com "),
+            Ok(sha256_hex("jane@example.com"))
+        );
+        let hash = sha256_hex("jane@example.com");
+        assert_eq!(
+            normalize(ListEntityType::Email, &hash.to_uppercase()),
+            Ok(hash.clone())
+        );
+        assert_eq!(
+            normalize(ListEntityType::EmailDomain, "@Mailinator.COM"),
+            Ok("mailinator.com".to_string())
+        );
+        assert_eq!(
+            normalize(ListEntityType::Ip, "::ffff:198.51.100.7"),
+            Ok("198.51.100.7".to_string())
+        );
+        assert_eq!(
+            normalize(ListEntityType::Cidr, "203.0.113.77/24"),
+            Ok("203.0.113.0/24".to_string())
+        );
+        assert_eq!(
+            normalize(ListEntityType::Cidr, "2001:db8::1/32"),
+            Ok("2001:db8::/32".to_string())
+        );
+        assert_eq!(
+            normalize(ListEntityType::CardHash, "tok_123"),
+            Ok(sha256_hex("tok_123"))
+        );
+        assert_eq!(
+            normalize(ListEntityType::Country, "ng"),
+            Ok("NG".to_string())
+        );
+        for (entity_type, value) in [
+            (ListEntityType::Email, "not-an-address"),
+            (ListEntityType::EmailDomain, "localhost"),
+            (ListEntityType::Ip, "203.0.113.0/24"),
+            (ListEntityType::Cidr, "203.0.113.0/33"),
+            (ListEntityType::Cidr, "203.0.113.7"),
+            (ListEntityType::Device, "device-1"),
+            (ListEntityType::User, ""),
+            (ListEntityType::CardHash, " "),
+            (ListEntityType::Country, "NGA"),
+        ] {
+            assert_eq!(
+                normalize(entity_type, value),
+                Err(format!("value is not a valid {}", entity_type.name()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_list_entry_validation() {
+        let now = Utc::now();
+        let entry = |value| serde_json::from_value::<ListEntryRequest>(value).unwrap();
+        assert_eq!(
+            entry(json!({ "entity_type": "ip", "value": "198.51.100.7" })).validate(now),
+            Ok("198.51.100.7".to_string())
+        );
+        let expired = entry(json!({
+            "entity_type": "ip",
+            "value": "198.51.100.7",
+            "expires_at": "2020-01-01T00:00:00Z"
+        }));
+        assert!(expired.validate(now).is_err());
+        let long_reason = entry(json!({
+            "entity_type": "country",
+            "value": "NG",
+            "reason": "x".repeat(501)
+        }));
+        assert!(long_reason.validate(now).is_err());
+        assert!(
+            serde_json::from_value::<ListEntryRequest>(
+                json!({ "entity_type": "phone", "value": "+15555550100" })
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_country_list_entry_validation() {
+        let country = |value| serde_json::from_value::<CountryListEntryRequest>(value).unwrap();
+        assert!(
+            country(json!({ "country": "NG", "action": "block" }))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            country(json!({ "country": "NG", "action": "review", "reason": "Chargebacks" }))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            country(json!({ "country": "NG", "action": "boost", "score": 25.0 }))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            country(json!({ "country": "NG", "action": "boost" }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            country(json!({ "country": "NG", "action": "review", "score": 25.0 }))
+                .validate()
+                .is_err()
+        );
+        for code in ["ng", "NGA", "N", "N1"] {
+            assert!(
+                country(json!({ "country": code, "action": "block" }))
+                    .validate()
+                    .is_err()
+            );
+        }
+    }
+}
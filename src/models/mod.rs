@@ -1,6 +1,29 @@
 //! Data models and types
 
+pub mod account;
+pub mod analytics;
+pub mod case;
+pub mod common;
+pub mod dead_letter;
+pub mod device;
+pub mod dispute;
 pub mod health;
+pub mod insights;
+pub mod job;
+pub mod label;
+pub mod list;
+pub mod notification;
+pub mod organization;
+pub mod outcome;
+pub mod processor_event;
+pub mod report;
+pub mod rule;
+pub mod scheduler;
+pub mod screening;
+pub mod transaction;
+pub mod user;
+pub mod webhook;
 
 // Re-export commonly used models
 pub use health::HealthResponse;
+pub use transaction::{TransactionRequest, TransactionResponse};
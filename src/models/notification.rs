@@ -0,0 +1,202 @@
+//! Built-in channels high-severity events are announced on: email and Slack
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::common::{Link, Links};
+use crate::outbox::NOTIFICATION_EVENT_TYPES;
+
+/// Longest name of a notification channel, in characters
+pub const MAX_CHANNEL_NAME_CHARS: usize = 64;
+/// Longest target of a notification channel, in characters
+pub const MAX_TARGET_CHARS: usize = 2048;
+/// Prefix of Slack incoming webhook URLs
+pub const SLACK_WEBHOOK_PREFIX: &str = "https://hooks.slack.com/";
+
+/// How a channel delivers its announcements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum ChannelKind {
+    /// Email to an address, sent through the server's SMTP relay
+    Email,
+    /// Message posted to a Slack incoming webhook
+    Slack,
+}
+
+/// Email address or Slack channel the account's high-severity events are announced on
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NotificationChannel {
+    /// Unique channel identifier
+    pub id: Uuid,
+    /// Name of the channel, unique within the account
+    #[schema(example = "fraud-oncall")]
+    pub name: String,
+    /// How announcements are delivered
+    pub kind: ChannelKind,
+    /// Email address, or Slack incoming webhook URL
+    #[schema(example = "fraud-oncall@merchant.example.com")]
+    pub target: String,
+    /// Event types announced; empty for every notifiable event
+    #[schema(example = json!(["analytics.anomaly_detected", "account.quota_exhausted"]))]
+    pub events: Vec<String>,
+    /// Whether events are announced on the channel
+    pub active: bool,
+    /// When an event was last announced on the channel
+    pub last_notified_at: Option<DateTime<Utc>>,
+    /// When the channel was created
+    pub created_at: DateTime<Utc>,
+    /// When the channel last changed
+    pub updated_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl NotificationChannel {
+    /// Links of the channel with the given ID
+    pub fn links(channel_id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/notification-channels/{channel_id}"))),
+            ..Links::default()
+        }
+    }
+}
+
+/// An account's notification channels, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NotificationChannelList {
+    /// The channels
+    pub channels: Vec<NotificationChannel>,
+}
+
+/// Request to create or replace a notification channel
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationChannelRequest {
+    /// Name of the channel: lowercase letters, digits, hyphens, and underscores
+    #[schema(example = "fraud-oncall")]
+    pub name: String,
+    /// How announcements are delivered
+    pub kind: ChannelKind,
+    /// Email address for `email` channels; Slack incoming webhook URL, starting
+    /// `https://hooks.slack.com/`, for `slack` channels
+    #[schema(example = "fraud-oncall@merchant.example.com")]
+    pub target: String,
+    /// Event types to announce, of `analytics.anomaly_detected`, `account.quota_exhausted`,
+    /// and `case.sla_breached`; empty for all of them
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Whether events are announced on the channel
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl NotificationChannelRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        let name_ok = self.name.chars().count() <= MAX_CHANNEL_NAME_CHARS
+            && self
+                .name
+                .starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !name_ok {
+            return Err(format!(
+                "name must be 1 to {MAX_CHANNEL_NAME_CHARS} lowercase letters, digits, hyphens, or underscores"
+            ));
+        }
+        if self.target.chars().count() > MAX_TARGET_CHARS {
+            return Err(format!(
+                "target must be at most {MAX_TARGET_CHARS} characters"
+            ));
+        }
+        match self.kind {
+            ChannelKind::Email => {
+                if self.target.parse::<lettre::Address>().is_err() {
+                    return Err("target must be an email address".to_string());
+                }
+            },
+            ChannelKind::Slack => {
+                let url_ok = self.target.starts_with(SLACK_WEBHOOK_PREFIX)
+                    && reqwest::Url::parse(&self.target).is_ok();
+                if !url_ok {
+                    return Err(format!(
+                        "target must be a Slack incoming webhook URL starting {SLACK_WEBHOOK_PREFIX}"
+                    ));
+                }
+            },
+        }
+        if let Some(unknown) = self
+            .events
+            .iter()
+            .find(|event| !NOTIFICATION_EVENT_TYPES.contains(&event.as_str()))
+        {
+            return Err(format!("events contains unnotifiable event type {unknown}"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_targets_match_their_kind() {
+        let request = |kind, target: &str, events: &[&str]| NotificationChannelRequest {
+            name: "fraud-oncall".to_string(),
+            kind,
+            target: target.to_string(),
+            events: events.iter().map(|event| event.to_string()).collect(),
+            active: true,
+        };
+        let slack_url = "https://hooks.slack.com/services/T000/B000/XXXX";
+        assert!(
+            request(ChannelKind::Email, "oncall@merchant.example.com", &[])
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            request(ChannelKind::Slack, slack_url, &[])
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            request(ChannelKind::Email, slack_url, &[])
+                .validate()
+                .is_err()
+        );
+        // Slack channels post only to Slack, never to an arbitrary host
+        assert!(
+            request(
+                ChannelKind::Slack,
+                "https://hooks.example.com/services/x",
+                &[]
+            )
+            .validate()
+            .is_err()
+        );
+        assert!(
+            request(ChannelKind::Slack, slack_url, &["case.sla_breached"])
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            request(ChannelKind::Slack, slack_url, &["transaction.scored"])
+                .validate()
+                .is_err()
+        );
+        let mut bad_name = request(ChannelKind::Slack, slack_url, &[]);
+        bad_name.name = "Fraud On-call".to_string();
+        assert!(bad_name.validate().is_err());
+    }
+}
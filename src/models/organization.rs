@@ -0,0 +1,203 @@
+//! Organizations grouping several accounts
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    account::SubscriptionTier,
+    common::{Link, Links},
+};
+
+/// What a member account may do in its organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum MemberRole {
+    /// Created the organization; manages members, admins, and billing
+    Owner,
+    /// Invites, changes, and removes members other than the owner and admins
+    Admin,
+    /// Belongs to the organization without managing it
+    Member,
+}
+
+impl MemberRole {
+    /// Whether the role may invite, change, and remove members
+    pub fn can_manage_members(self) -> bool {
+        matches!(self, MemberRole::Owner | MemberRole::Admin)
+    }
+}
+
+/// Whether a member has joined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum MembershipStatus {
+    /// Invited, waiting for the account to accept
+    Invited,
+    /// Part of the organization
+    Active,
+}
+
+/// Account that belongs to, or is invited to, an organization
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OrganizationMember {
+    /// Public identifier of the member account
+    #[schema(example = "acme-payments-staging")]
+    pub account_id: String,
+    /// What the member may do in the organization
+    pub role: MemberRole,
+    /// Whether the member has joined
+    pub status: MembershipStatus,
+    /// Whether the member may look up users of the other member accounts
+    pub user_lookup: bool,
+    /// Subscription tier of the member account
+    pub subscription_tier: SubscriptionTier,
+    /// Scoring requests the member has used in its current billing cycle
+    #[schema(example = 15642)]
+    pub queries_used_this_month: i32,
+    /// When the account was invited
+    pub invited_at: DateTime<Utc>,
+    /// When the account accepted the invitation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub joined_at: Option<DateTime<Utc>>,
+}
+
+/// Group of accounts sharing billing
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Organization {
+    /// Organization identifier
+    pub organization_id: Uuid,
+    /// Display name
+    #[schema(example = "Acme Group")]
+    pub name: String,
+    /// Public identifier of the account invoiced for every member's usage
+    #[schema(example = "acme-payments")]
+    pub billing_account_id: String,
+    /// Role of the calling account
+    pub role: MemberRole,
+    /// Whether the blocklist entries of each active member apply to the others too
+    pub share_blocklist: bool,
+    /// Scoring requests used by all active members in their current billing cycles
+    #[schema(example = 48210)]
+    pub total_queries_used_this_month: i64,
+    /// Members and pending invitations
+    pub members: Vec<OrganizationMember>,
+    /// When the organization was created
+    pub created_at: DateTime<Utc>,
+    /// When the organization was last changed
+    pub updated_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl Organization {
+    /// Links of the organization resource
+    pub fn links() -> Links {
+        Links {
+            self_link: Some(Link::new("/v1/organization")),
+            ..Links::default()
+        }
+    }
+}
+
+/// Request to create an organization with the calling account as its owner
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CreateOrganization {
+    /// Display name
+    #[schema(example = "Acme Group")]
+    pub name: String,
+}
+
+/// Changes to an organization; fields left out are kept as they are
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OrganizationUpdate {
+    /// Display name
+    pub name: Option<String>,
+    /// Public identifier of an active member to invoice instead; owner only
+    pub billing_account_id: Option<String>,
+    /// Whether the blocklist entries of each active member apply to the others too
+    pub share_blocklist: Option<bool>,
+}
+
+/// Invitation of an account to the caller's organization
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MemberInvitation {
+    /// Public identifier of the account to invite
+    #[schema(example = "acme-payments-staging")]
+    pub account_id: String,
+    /// Role the account gets once it accepts (default: `member`)
+    pub role: Option<MemberRole>,
+    /// Whether the account may look up users of the other members (default: false)
+    pub user_lookup: Option<bool>,
+}
+
+/// Changes to a member; fields left out are kept as they are
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MemberUpdate {
+    /// New role; the owner's role cannot be changed or given away
+    pub role: Option<MemberRole>,
+    /// Whether the member may look up users of the other members
+    pub user_lookup: Option<bool>,
+}
+
+/// Pending invitation of the calling account
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Invitation {
+    /// Inviting organization
+    pub organization_id: Uuid,
+    /// Display name of the organization
+    #[schema(example = "Acme Group")]
+    pub organization_name: String,
+    /// Role offered
+    pub role: MemberRole,
+    /// Whether user lookup across members is offered
+    pub user_lookup: bool,
+    /// When the invitation was sent
+    pub invited_at: DateTime<Utc>,
+}
+
+/// Pending invitations of the calling account
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct InvitationList {
+    /// Invitations, newest first
+    pub invitations: Vec<Invitation>,
+}
+
+/// Check an organization name, returning it trimmed
+pub fn validate_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    if name.chars().count() > 255 {
+        return Err("name must be at most 255 characters".to_string());
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert_eq!(validate_name("  Acme Group "), Ok("Acme Group"));
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name(&"a".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn test_member_management_roles() {
+        assert!(MemberRole::Owner.can_manage_members());
+        assert!(MemberRole::Admin.can_manage_members());
+        assert!(!MemberRole::Member.can_manage_members());
+    }
+}
@@ -0,0 +1,120 @@
+//! Outcomes merchants report for scored transactions
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    common::{Link, Links},
+    transaction::ReportTag,
+};
+
+/// Longest chargeback reason code accepted
+const MAX_CHARGEBACK_CODE_LENGTH: usize = 32;
+/// Longest notes accepted
+const MAX_NOTES_LENGTH: usize = 1000;
+
+/// Outcome to report for a transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OutcomeReportRequest {
+    /// Transaction the outcome is reported for
+    pub transaction_id: Uuid,
+    /// What happened
+    pub tag: ReportTag,
+    /// Processor reason code, for chargebacks
+    #[schema(example = "4837")]
+    pub chargeback_code: Option<String>,
+    /// Free-form notes
+    #[schema(example = "Customer disputed the transaction claiming non-receipt")]
+    pub notes: Option<String>,
+    /// When the outcome occurred; now if absent
+    pub occurred_at: Option<DateTime<Utc>>,
+}
+
+impl OutcomeReportRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(code) = &self.chargeback_code {
+            if self.tag != ReportTag::Chargeback {
+                return Err("chargeback_code is only accepted for chargebacks".to_string());
+            }
+            if code.trim().is_empty() || code.chars().count() > MAX_CHARGEBACK_CODE_LENGTH {
+                return Err(format!(
+                    "chargeback_code must be between 1 and {MAX_CHARGEBACK_CODE_LENGTH} characters"
+                ));
+            }
+        }
+        if self
+            .notes
+            .as_ref()
+            .is_some_and(|notes| notes.chars().count() > MAX_NOTES_LENGTH)
+        {
+            return Err(format!(
+                "notes must be at most {MAX_NOTES_LENGTH} characters"
+            ));
+        }
+        if self.occurred_at.is_some_and(|at| at > Utc::now()) {
+            return Err("occurred_at must not be in the future".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Outcome recorded for a transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionOutcome {
+    /// Unique report identifier
+    pub id: Uuid,
+    /// Transaction the outcome is recorded for
+    pub transaction_id: Uuid,
+    /// What happened
+    pub tag: ReportTag,
+    /// Processor reason code, for chargebacks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "4837")]
+    pub chargeback_code: Option<String>,
+    /// Free-form notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// When the outcome occurred
+    pub occurred_at: DateTime<Utc>,
+    /// When the outcome was recorded
+    pub submitted_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl TransactionOutcome {
+    /// Links of an outcome of the given transaction
+    pub fn links(transaction_id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/transactions/{transaction_id}"))),
+            ..Links::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chargeback_codes_only_come_with_chargebacks() {
+        let mut request = OutcomeReportRequest {
+            transaction_id: Uuid::nil(),
+            tag: ReportTag::Chargeback,
+            chargeback_code: Some("4837".to_string()),
+            notes: None,
+            occurred_at: None,
+        };
+        assert!(request.validate().is_ok());
+        request.tag = ReportTag::NotFraud;
+        assert!(request.validate().is_err());
+        request.chargeback_code = None;
+        request.occurred_at = Some(Utc::now() + chrono::Duration::days(1));
+        assert!(request.validate().is_err());
+    }
+}
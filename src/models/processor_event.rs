@@ -0,0 +1,126 @@
+//! Dispute and refund webhooks received from payment processors
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{dispute::DisputeStatus, transaction::ReportTag};
+
+/// Payment processor a webhook comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum Processor {
+    /// Stripe event objects
+    Stripe,
+    /// Adyen standard notifications
+    Adyen,
+}
+
+impl Processor {
+    /// Processor whose webhook format `body` has, if any
+    pub fn detect(body: &serde_json::Value) -> Option<Self> {
+        if body.get("notificationItems").is_some() {
+            Some(Processor::Adyen)
+        } else if body.get("object").and_then(|object| object.as_str()) == Some("event") {
+            Some(Processor::Stripe)
+        } else {
+            None
+        }
+    }
+}
+
+/// Query parameters for receiving processor webhooks
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProcessorEventQuery {
+    /// Processor the webhook comes from; detected from the body when absent
+    pub processor: Option<Processor>,
+}
+
+/// Secret a processor signs an account's webhooks with
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ProcessorSecret {
+    /// Stripe's endpoint signing secret, or Adyen's hex HMAC key
+    #[schema(example = "whsec_5bN2mRq8TzVyX4cLp0Kd")]
+    pub secret: String,
+}
+
+/// What a processor event reports about a payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessorEventKind {
+    /// The cardholder disputed the payment
+    Dispute,
+    /// A dispute moved on: the merchant submitted evidence, or it was won or lost
+    DisputeUpdate,
+    /// The merchant refunded the payment
+    Refund,
+    /// Anything else
+    Other,
+}
+
+/// What became of a processor event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestOutcome {
+    /// The transaction's outcome, or the progress of its dispute, was recorded
+    Recorded,
+    /// The transaction already had this outcome and its dispute this stage, as when the
+    /// processor redelivers an event
+    AlreadyRecorded,
+    /// The event says nothing about fraud, such as a refund for a returned item
+    Ignored,
+    /// No transaction was scored under any of the payment's IDs
+    Unmatched,
+}
+
+/// One event of a processor webhook and what became of it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IngestedEvent {
+    /// Processor's ID for the event
+    #[schema(example = "evt_1NG8Du2eZvKYlo2CUI79vXWy")]
+    pub event_id: String,
+    /// What the event reports
+    pub kind: ProcessorEventKind,
+    /// What became of it
+    pub outcome: IngestOutcome,
+    /// Transaction the event was matched to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<Uuid>,
+    /// Outcome recorded for the transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<ReportTag>,
+    /// Stage the event moved the transaction's dispute to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dispute_status: Option<DisputeStatus>,
+}
+
+/// Result of receiving a processor webhook
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProcessorEventReceipt {
+    /// Processor the webhook was read as
+    pub processor: Processor,
+    /// Events the webhook carried, in order
+    pub events: Vec<IngestedEvent>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_processor_is_detected_from_the_body() {
+        assert_eq!(
+            Processor::detect(&json!({ "id": "evt_1", "object": "event" })),
+            Some(Processor::Stripe)
+        );
+        assert_eq!(
+            Processor::detect(&json!({ "live": "false", "notificationItems": [] })),
+            Some(Processor::Adyen)
+        );
+        assert_eq!(Processor::detect(&json!({ "object": "charge" })), None);
+    }
+}
@@ -0,0 +1,307 @@
+//! Generated fraud summary reports
+
+use std::fmt::{Display, Write};
+
+use chrono::{DateTime, Datelike, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{
+    analytics::AnalyticsSummary,
+    common::{Links, Pagination},
+};
+use crate::utils::csv::spreadsheet_field;
+
+/// How often a report is generated, and so how long a period it covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum ReportFrequency {
+    /// One UTC day
+    Daily,
+    /// One week starting on Monday (UTC)
+    Weekly,
+}
+
+impl ReportFrequency {
+    /// Start of the period containing `time`
+    pub fn period_start(self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let day = time.duration_trunc(Duration::days(1)).unwrap_or(time);
+        match self {
+            ReportFrequency::Daily => day,
+            ReportFrequency::Weekly => {
+                day - Duration::days(i64::from(day.weekday().num_days_from_monday()))
+            },
+        }
+    }
+
+    /// Length of the period
+    pub fn duration(self) -> Duration {
+        match self {
+            ReportFrequency::Daily => Duration::days(1),
+            ReportFrequency::Weekly => Duration::weeks(1),
+        }
+    }
+
+    /// Name used in export paths and file names
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReportFrequency::Daily => "daily",
+            ReportFrequency::Weekly => "weekly",
+        }
+    }
+}
+
+/// Representation a report is served in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    /// JSON document
+    #[default]
+    Json,
+    /// CSV with one `section,key,metric,value` row per figure
+    Csv,
+}
+
+/// A rule among the most frequently fired in the period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReportRule {
+    /// Rule code
+    #[schema(example = "CVV_MISMATCH")]
+    pub rule_code: String,
+    /// Transactions on which the rule fired
+    #[schema(example = 934)]
+    pub hits: u64,
+    /// Share of the period's transactions on which the rule fired
+    #[schema(example = 0.0597)]
+    pub hit_rate: f64,
+}
+
+/// A user among the riskiest in the period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReportUser {
+    /// Fusegu user ID
+    pub user_id: Uuid,
+    /// The customer's own user ID, if one was supplied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "user_12345")]
+    pub external_user_id: Option<String>,
+    /// Transactions scored for the user in the period
+    #[schema(example = 6)]
+    pub transactions: u64,
+    /// Mean risk score of those transactions
+    #[schema(example = 71.4)]
+    pub average_risk_score: f64,
+    /// Highest risk score of those transactions
+    #[schema(example = 88.2)]
+    pub max_risk_score: f64,
+    /// Transactions rejected
+    #[schema(example = 4)]
+    pub rejected: u64,
+}
+
+/// Figures making up a report, as stored
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportContent {
+    /// Totals over the period
+    pub summary: AnalyticsSummary,
+    /// Most frequently fired rules, most hits first
+    pub top_rules: Vec<ReportRule>,
+    /// Users with the highest average risk score, riskiest first
+    pub top_risky_users: Vec<ReportUser>,
+}
+
+/// Fraud summary of one account over one day or week
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Report {
+    /// Unique report identifier
+    pub id: Uuid,
+    /// Daily or weekly
+    pub frequency: ReportFrequency,
+    /// Inclusive start of the period
+    pub period_start: DateTime<Utc>,
+    /// Exclusive end of the period
+    pub period_end: DateTime<Utc>,
+    /// Totals over the period
+    pub summary: AnalyticsSummary,
+    /// Most frequently fired rules, most hits first
+    pub top_rules: Vec<ReportRule>,
+    /// Users with the highest average risk score, riskiest first
+    pub top_risky_users: Vec<ReportUser>,
+    /// Object storage location the report was exported to, without the `.json`/`.csv`
+    /// extension
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(
+        example = "s3://fraud-reports/reports/6ba7b810-9dad-11d1-80b4-00c04fd430c8/daily/2025-06-13"
+    )]
+    pub export_location: Option<String>,
+    /// When the report was generated
+    pub created_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl Report {
+    /// Render the report as CSV with one `section,key,metric,value` row per figure
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("section,key,metric,value\n");
+        let mut row = |section: &str, key: &str, metric: &str, value: &dyn Display| {
+            let _ = writeln!(csv, "{section},{},{metric},{value}", spreadsheet_field(key));
+        };
+
+        row("report", "", "frequency", &self.frequency.as_str());
+        row(
+            "report",
+            "",
+            "period_start",
+            &self.period_start.to_rfc3339(),
+        );
+        row("report", "", "period_end", &self.period_end.to_rfc3339());
+
+        let summary = &self.summary;
+        row(
+            "summary",
+            "",
+            "total_transactions",
+            &summary.total_transactions,
+        );
+        row("summary", "", "total_users", &summary.total_users);
+        row(
+            "summary",
+            "",
+            "average_risk_score",
+            &summary.average_risk_score,
+        );
+        let risk = summary.risk_distribution;
+        for (level, count) in [
+            ("low", risk.low),
+            ("medium", risk.medium),
+            ("high", risk.high),
+            ("very_high", risk.very_high),
+        ] {
+            row("risk_level", level, "transactions", &count);
+        }
+        let dispositions = summary.disposition_counts;
+        for (disposition, count) in [
+            ("accept", dispositions.accept),
+            ("reject", dispositions.reject),
+            ("review", dispositions.review),
+            ("test", dispositions.test),
+        ] {
+            row("disposition", disposition, "transactions", &count);
+        }
+
+        for rule in &self.top_rules {
+            row("top_rule", &rule.rule_code, "hits", &rule.hits);
+            row("top_rule", &rule.rule_code, "hit_rate", &rule.hit_rate);
+        }
+        for user in &self.top_risky_users {
+            let key = user
+                .external_user_id
+                .clone()
+                .unwrap_or_else(|| user.user_id.to_string());
+            row("top_risky_user", &key, "transactions", &user.transactions);
+            row(
+                "top_risky_user",
+                &key,
+                "average_risk_score",
+                &user.average_risk_score,
+            );
+            row(
+                "top_risky_user",
+                &key,
+                "max_risk_score",
+                &user.max_risk_score,
+            );
+            row("top_risky_user", &key, "rejected", &user.rejected);
+        }
+
+        csv
+    }
+}
+
+/// Query parameters for listing reports
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListReportsQuery {
+    /// Only return daily or only weekly reports
+    pub frequency: Option<ReportFrequency>,
+    /// Maximum number of reports to return (1-100, default 20)
+    pub limit: Option<i64>,
+    /// Number of reports to skip
+    pub offset: Option<i64>,
+}
+
+/// Query parameters for fetching a report
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetReportQuery {
+    /// Representation to return (default: json)
+    pub format: Option<ReportFormat>,
+}
+
+/// Page of reports, most recent period first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReportList {
+    /// Reports on this page
+    pub reports: Vec<Report>,
+    /// Pagination metadata
+    pub pagination: Pagination,
+    /// Navigation links
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_period_start() {
+        // 13 June 2025 is a Friday
+        let time = Utc.with_ymd_and_hms(2025, 6, 13, 10, 42, 7).unwrap();
+        assert_eq!(
+            ReportFrequency::Daily.period_start(time),
+            Utc.with_ymd_and_hms(2025, 6, 13, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            ReportFrequency::Weekly.period_start(time),
+            Utc.with_ymd_and_hms(2025, 6, 9, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_csv_escapes_customer_supplied_keys() {
+        let start = Utc.with_ymd_and_hms(2025, 6, 13, 0, 0, 0).unwrap();
+        let report = Report {
+            id: Uuid::new_v4(),
+            frequency: ReportFrequency::Daily,
+            period_start: start,
+            period_end: start + Duration::days(1),
+            summary: AnalyticsSummary::default(),
+            top_rules: vec![],
+            top_risky_users: vec![ReportUser {
+                user_id: Uuid::new_v4(),
+                external_user_id: Some("=HYPERLINK(\"x\",\"y\")".to_string()),
+                transactions: 2,
+                average_risk_score: 80.5,
+                max_risk_score: 91.0,
+                rejected: 1,
+            }],
+            export_location: None,
+            created_at: start,
+            links: Links::default(),
+        };
+
+        let csv = report.to_csv();
+        assert!(csv.starts_with("section,key,metric,value\nreport,,frequency,daily\n"));
+        assert!(csv.contains("risk_level,very_high,transactions,0\n"));
+        assert!(csv.contains(
+            "top_risky_user,\"'=HYPERLINK(\"\"x\"\",\"\"y\"\")\",average_risk_score,80.5\n"
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+//! Versions of an account's rule weights and the suggestions they are tuned from
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Weight suggested for a rule from the outcomes reported for transactions it fired on
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuleSuggestion {
+    /// Unique suggestion identifier
+    pub id: Uuid,
+    /// Factor code of the rule
+    #[schema(example = "FLAGGED_USER")]
+    pub rule_code: String,
+    /// Weight of the rule in force when the suggestion was computed
+    #[schema(example = 1.0)]
+    pub current_weight: f64,
+    /// Weight suggested for the rule
+    #[schema(example = 1.6)]
+    pub suggested_weight: f64,
+    /// Reported transactions the rule raised the score of
+    #[schema(example = 48)]
+    pub reported_transactions: i32,
+    /// Of those, transactions reported as fraud or abuse
+    #[schema(example = 30)]
+    pub fraudulent_transactions: i32,
+    /// Share of the reported transactions the rule fired on that were fraud or abuse
+    #[schema(example = 0.625)]
+    pub precision: f64,
+    /// Share of all the account's reported transactions that were fraud or abuse
+    #[schema(example = 0.39)]
+    pub baseline_precision: f64,
+    /// When the suggestion was computed
+    pub computed_at: DateTime<Utc>,
+}
+
+/// An account's pending rule weight suggestions, in order of rule code
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuleSuggestionList {
+    /// The suggestions
+    pub suggestions: Vec<RuleSuggestion>,
+}
+
+/// Version of an account's rule weights
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuleVersion {
+    /// Version number, counting up from 1
+    #[schema(example = 3)]
+    pub version: i32,
+    /// Weight each reweighted rule's score is scaled by, by factor code; other rules keep
+    /// their built-in score
+    #[schema(example = json!({ "FLAGGED_USER": 1.6, "HOSTING_IP": 0.5 }))]
+    pub weights: BTreeMap<String, f64>,
+    /// Suggestion whose application created the version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion_id: Option<Uuid>,
+    /// When the version was created
+    pub created_at: DateTime<Utc>,
+}
+
+/// Versions of an account's rule weights, newest first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuleVersionList {
+    /// The versions
+    pub versions: Vec<RuleVersion>,
+}
@@ -0,0 +1,67 @@
+//! Recurring tasks run by the in-process scheduler
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// How a scheduled task's last run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskOutcome {
+    /// The run did all its work
+    Succeeded,
+    /// The run stopped on an error
+    Failed,
+}
+
+/// Schedule and last run of a recurring task on this server
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "name": "user_risk_recalculation",
+    "schedule": "0 */15 * * * *",
+    "running": false,
+    "next_run_at": "2025-06-13T10:45:00Z",
+    "last_started_at": "2025-06-13T10:30:00Z",
+    "last_finished_at": "2025-06-13T10:30:02.314Z",
+    "last_outcome": "succeeded",
+    "last_duration_ms": 2314,
+    "runs": 42,
+    "failures": 1
+}))]
+pub struct ScheduledTaskStatus {
+    /// Task name
+    pub name: String,
+    /// Cron expression the task runs on, with a leading seconds field
+    pub schedule: String,
+    /// Whether a run is in progress
+    pub running: bool,
+    /// When the task runs next, unless a run is in progress
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_run_at: Option<DateTime<Utc>>,
+    /// When the last run started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_started_at: Option<DateTime<Utc>>,
+    /// When the last run finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_finished_at: Option<DateTime<Utc>>,
+    /// How the last run ended
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_outcome: Option<TaskOutcome>,
+    /// Why the last run failed, if it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// How long the last run took
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_duration_ms: Option<u64>,
+    /// Runs finished since the server started
+    pub runs: u64,
+    /// Runs that failed since the server started
+    pub failures: u64,
+}
+
+/// Recurring tasks of this server, by name
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduledTaskList {
+    /// Tasks in name order
+    pub tasks: Vec<ScheduledTaskStatus>,
+}
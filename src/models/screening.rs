@@ -0,0 +1,142 @@
+//! Screening of names against sanctions lists
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Longest name that can be screened, in characters
+const MAX_NAME_CHARS: usize = 500;
+
+/// A sanctions list names are screened against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SanctionsList {
+    /// OFAC's Specially Designated Nationals and consolidated sanctions lists
+    Ofac,
+    /// The EU consolidated list of persons, groups, and entities subject to financial
+    /// sanctions
+    Eu,
+}
+
+impl SanctionsList {
+    /// Every list
+    pub const ALL: [SanctionsList; 2] = [SanctionsList::Ofac, SanctionsList::Eu];
+
+    /// Name of the list in logs and factor reasons
+    pub fn name(self) -> &'static str {
+        match self {
+            SanctionsList::Ofac => "OFAC",
+            SanctionsList::Eu => "EU",
+        }
+    }
+}
+
+/// Party to screen
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ScreeningRequest {
+    /// Full name of the person or organization
+    #[schema(example = "Ivan Petrov")]
+    pub name: String,
+    /// ISO 3166-1 alpha-2 code of the party's country; parties listed with countries must
+    /// share it to match
+    #[schema(example = "RU")]
+    pub country: Option<String>,
+}
+
+impl ScreeningRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        if self.name.chars().count() > MAX_NAME_CHARS {
+            return Err(format!("name must be at most {MAX_NAME_CHARS} characters"));
+        }
+        if let Some(country) = &self.country
+            && !(country.len() == 2 && country.bytes().all(|b| b.is_ascii_alphabetic()))
+        {
+            return Err("country must be an ISO 3166-1 alpha-2 code".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A listed party whose name matches the screened one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ScreeningMatch {
+    /// List the party is on
+    pub list: SanctionsList,
+    /// Identifier of the party on the list
+    #[schema(example = "36092")]
+    pub entry_id: String,
+    /// Name the party is listed under
+    #[schema(example = "PETROV, Ivan Ivanovich")]
+    pub name: String,
+    /// The listed name or alias that matched
+    #[schema(example = "PETROV, Ivan Ivanovich")]
+    pub matched_name: String,
+    /// Kind of party, as the list describes it: individual, entity, vessel, ...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "individual")]
+    pub party_type: Option<String>,
+    /// Sanctions programs or regimes the party is listed under
+    #[schema(example = json!(["RUSSIA-EO14024"]))]
+    pub programs: Vec<String>,
+    /// ISO 3166-1 alpha-2 codes of the countries of its addresses and citizenships
+    #[schema(example = json!(["RU"]))]
+    pub countries: Vec<String>,
+    /// How closely the names match, from 0 to 1
+    #[schema(example = 0.95)]
+    pub score: f64,
+}
+
+/// A list screened against
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScreeningListStatus {
+    /// The list
+    pub list: SanctionsList,
+    /// Parties on it
+    #[schema(example = 17_842)]
+    pub parties: usize,
+    /// When it was last downloaded
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Outcome of screening a party
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScreeningResult {
+    /// Name screened
+    #[schema(example = "Ivan Petrov")]
+    pub name: String,
+    /// Country screened, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "RU")]
+    pub country: Option<String>,
+    /// Whether any listed party matched
+    pub matched: bool,
+    /// Matching parties, closest first, at most 10
+    pub matches: Vec<ScreeningMatch>,
+    /// Lists screened against
+    pub lists: Vec<ScreeningListStatus>,
+    /// When the party was screened
+    pub screened_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screening_request_validation() {
+        let request = |name: &str, country: Option<&str>| ScreeningRequest {
+            name: name.to_string(),
+            country: country.map(str::to_string),
+        };
+        assert!(request("Ivan Petrov", Some("ru")).validate().is_ok());
+        assert!(request("Ivan Petrov", None).validate().is_ok());
+        assert!(request("  ", None).validate().is_err());
+        assert!(request(&"a".repeat(501), None).validate().is_err());
+        assert!(request("Ivan Petrov", Some("RUS")).validate().is_err());
+    }
+}
@@ -0,0 +1,1492 @@
+//! Transaction scoring request and response models
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{
+    common::{Links, Pagination},
+    device::token_fingerprint,
+    list::ListEntityType,
+};
+use crate::{
+    api::errors::ErrorResponse,
+    config::RedactionConfig,
+    utils::{matching, sha256_hex, tls},
+};
+
+/// Type of event being scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum EventType {
+    /// New account sign-up
+    AccountCreation,
+    /// Login to an existing account
+    AccountLogin,
+    /// Email address change
+    EmailChange,
+    /// Password reset
+    PasswordReset,
+    /// Change of payout details
+    PayoutChange,
+    /// One-off purchase
+    Purchase,
+    /// Subscription or other recurring purchase
+    RecurringPurchase,
+    /// Referral reward
+    Referral,
+    /// Survey submission
+    Survey,
+    /// Money returned to the customer for an earlier purchase
+    Refund,
+    /// Cancellation of an earlier purchase
+    Cancellation,
+}
+
+impl EventType {
+    /// Whether the event moves money from the customer
+    pub fn is_purchase(self) -> bool {
+        matches!(self, EventType::Purchase | EventType::RecurringPurchase)
+    }
+
+    /// Whether the event undoes an earlier purchase
+    pub fn is_reversal(self) -> bool {
+        matches!(self, EventType::Refund | EventType::Cancellation)
+    }
+}
+
+/// Risk level classification
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum RiskLevel {
+    /// Score below 30
+    Low,
+    /// Score from 30 up to 60
+    Medium,
+    /// Score from 60 up to 85
+    High,
+    /// Score of 85 or more
+    VeryHigh,
+}
+
+impl RiskLevel {
+    /// Classify a risk score
+    pub fn from_score(score: f64) -> Self {
+        if score < 30.0 {
+            RiskLevel::Low
+        } else if score < 60.0 {
+            RiskLevel::Medium
+        } else if score < 85.0 {
+            RiskLevel::High
+        } else {
+            RiskLevel::VeryHigh
+        }
+    }
+}
+
+/// Recommended action for a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum Disposition {
+    /// Let the transaction through
+    Accept,
+    /// Block the transaction
+    Reject,
+    /// Hold the transaction for manual review
+    Review,
+    /// Test transaction, not acted upon
+    Test,
+}
+
+impl Disposition {
+    /// Default action for a risk level
+    pub fn for_risk_level(level: RiskLevel) -> Self {
+        match level {
+            RiskLevel::Low => Disposition::Accept,
+            RiskLevel::Medium => Disposition::Review,
+            RiskLevel::High | RiskLevel::VeryHigh => Disposition::Reject,
+        }
+    }
+}
+
+/// Outcome a customer reports for a scored transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum ReportTag {
+    /// The payment was disputed and charged back
+    Chargeback,
+    /// The transaction was legitimate
+    NotFraud,
+    /// Fraud is suspected but no chargeback has been filed
+    SuspectedFraud,
+    /// The event was spam or abuse rather than payment fraud
+    SpamOrAbuse,
+}
+
+impl ReportTag {
+    /// Whether the report confirms the transaction was fraudulent or abusive
+    pub fn is_fraud(self) -> bool {
+        !matches!(self, ReportTag::NotFraud)
+    }
+}
+
+/// Shipping speed requested for an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum DeliverySpeed {
+    /// Same-day delivery
+    SameDay,
+    /// Overnight delivery
+    Overnight,
+    /// Expedited delivery
+    Expedited,
+    /// Standard delivery
+    Standard,
+}
+
+/// Device the event originated from
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionDevice {
+    /// IPv4 or IPv6 address of the device
+    #[schema(example = "198.51.100.1")]
+    pub ip_address: String,
+    /// HTTP User-Agent header
+    #[schema(example = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")]
+    pub user_agent: Option<String>,
+    /// HTTP Accept-Language header
+    #[schema(example = "en-US,en;q=0.9")]
+    pub accept_language: Option<String>,
+    /// Unique session identifier; events sharing one are scored on what happened earlier in
+    /// the session, such as a sign-up moments before a purchase
+    #[schema(example = "sess_abc123def456")]
+    pub session_id: Option<String>,
+    /// Session age in seconds
+    #[schema(example = 1800)]
+    pub session_age: Option<f64>,
+    /// Token from `POST /v1/devices/fingerprint`, identifying the device wherever it connects
+    /// from; without one, the device is identified by its IP address and headers
+    #[schema(example = "dev_9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")]
+    pub device_token: Option<String>,
+    /// JA3 fingerprint of the device's TLS handshake, as computed by your edge
+    #[schema(example = "cd08e31494f9531f560d64c695473da9")]
+    pub ja3: Option<String>,
+    /// JA4 fingerprint of the device's TLS handshake, as computed by your edge
+    #[schema(example = "t13d1516h2_8daaf6152771_e5627efa2ab1")]
+    pub ja4: Option<String>,
+    /// Hash of the names of the HTTP headers the device sent, in the order it sent them
+    #[schema(example = "a6c2b0f84e3d9f1c")]
+    pub header_order_hash: Option<String>,
+}
+
+/// Event being scored
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionEvent {
+    /// Type of event
+    #[serde(rename = "type")]
+    pub event_type: EventType,
+    /// Your internal transaction ID
+    #[schema(example = "txn_123456789")]
+    pub transaction_id: Option<String>,
+    /// Your internal transaction ID of the purchase a refund or cancellation undoes; required
+    /// for those events and not accepted on others
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "txn_123456788")]
+    pub original_transaction_id: Option<String>,
+    /// Shop or merchant identifier
+    #[schema(example = "shop_main")]
+    pub shop_id: Option<String>,
+    /// Event timestamp, defaults to the time of the request
+    pub time: Option<DateTime<Utc>>,
+}
+
+/// Customer account details in your system
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TransactionAccount {
+    /// Unique user identifier in your system
+    #[schema(example = "user_12345")]
+    pub user_id: Option<String>,
+    /// Hash of a stable user identifier (email, phone, etc.)
+    #[schema(example = "098f6bcd4621d373cade4e832627b4f6")]
+    pub user_hash: Option<String>,
+}
+
+/// Email address used for the event
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionEmail {
+    /// Email address
+    #[schema(example = "customer@example.com")]
+    pub address: Option<String>,
+    /// Email domain, derived from the address when omitted
+    #[schema(example = "example.com")]
+    pub domain: Option<String>,
+}
+
+impl TransactionEmail {
+    /// The supplied domain, or else the domain part of the address
+    pub fn resolved_domain(&self) -> Option<String> {
+        self.domain.clone().or_else(|| {
+            let address = self.address.as_deref()?.trim().to_lowercase();
+            address
+                .rsplit_once('@')
+                .map(|(_, domain)| domain.to_string())
+        })
+    }
+
+    /// SHA-256 hash of the trimmed, lowercased address, which is how addresses are stored
+    pub fn address_hash(&self) -> Option<String> {
+        let address = self.address.as_deref()?;
+        Some(sha256_hex(&address.trim().to_lowercase()))
+    }
+
+    /// SHA-256 hash of the mailbox the address delivers to, shared by its variants
+    ///
+    /// See [`canonical_mailbox`].
+    pub fn mailbox_hash(&self) -> Option<String> {
+        let address = self.address.as_deref()?;
+        Some(sha256_hex(&canonical_mailbox(address)))
+    }
+}
+
+/// Domains whose mailboxes ignore dots in the local part
+const DOTLESS_DOMAINS: &[&str] = &["gmail.com", "googlemail.com"];
+
+/// The mailbox `address` delivers to, in lowercase
+///
+/// Providers deliver `jane+promo@example.com` to `jane@example.com`, and Gmail also ignores
+/// dots, so `j.a.n.e@gmail.com` is `jane@gmail.com`; `googlemail.com` is the same as
+/// `gmail.com`. Addresses that are nothing but a tag are left as they are.
+pub fn canonical_mailbox(address: &str) -> String {
+    let address = address.trim().to_lowercase();
+    let Some((local, domain)) = address.rsplit_once('@') else {
+        return address;
+    };
+    let mut local = match local.split_once('+') {
+        Some((base, _)) if !base.is_empty() => base.to_string(),
+        _ => local.to_string(),
+    };
+    if DOTLESS_DOMAINS.contains(&domain) {
+        local.retain(|c| c != '.');
+    }
+    let domain = if domain == "googlemail.com" {
+        "gmail.com"
+    } else {
+        domain
+    };
+    format!("{local}@{domain}")
+}
+
+/// Postal address
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct Address {
+    /// First name
+    #[schema(example = "John")]
+    pub first_name: Option<String>,
+    /// Last name
+    #[schema(example = "Doe")]
+    pub last_name: Option<String>,
+    /// Company name
+    #[schema(example = "Acme Corp")]
+    pub company: Option<String>,
+    /// First line of the street address
+    #[schema(example = "123 Main St")]
+    pub address: Option<String>,
+    /// Second line of the street address
+    #[schema(example = "Apt 4B")]
+    pub address_2: Option<String>,
+    /// City name
+    #[schema(example = "New York")]
+    pub city: Option<String>,
+    /// ISO 3166-2 subdivision code
+    #[schema(example = "NY")]
+    pub region: Option<String>,
+    /// Postal code
+    #[schema(example = "10001")]
+    pub postal: Option<String>,
+    /// ISO 3166-1 alpha-2 country code
+    #[schema(example = "US")]
+    pub country: Option<String>,
+    /// Phone number, without the country code
+    #[schema(example = "212-555-0123")]
+    pub phone_number: Option<String>,
+    /// Phone country calling code
+    #[schema(example = "1")]
+    pub phone_country_code: Option<String>,
+}
+
+/// Shipping address with delivery preferences
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ShippingAddress {
+    /// Destination address
+    #[serde(flatten)]
+    pub address: Address,
+    /// Requested delivery speed
+    pub delivery_speed: Option<DeliverySpeed>,
+}
+
+impl Address {
+    /// First and last name, if either is given
+    pub fn full_name(&self) -> Option<String> {
+        let name = [self.first_name.as_deref(), self.last_name.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!name.is_empty()).then_some(name)
+    }
+}
+
+/// Payment card details
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CreditCard {
+    /// First 6-8 digits of the card (BIN)
+    #[schema(example = "411111")]
+    pub issuer_id_number: Option<String>,
+    /// Last 2-4 digits of the card
+    #[schema(example = "1111")]
+    pub last_digits: Option<String>,
+    /// Tokenized card identifier
+    #[schema(example = "tok_abc123def456")]
+    pub token: Option<String>,
+    /// Name of the cardholder, as printed on the card
+    #[schema(example = "JOHN DOE")]
+    pub holder_name: Option<String>,
+    /// Name of the issuing bank
+    #[schema(example = "Chase Bank")]
+    pub bank_name: Option<String>,
+    /// Customer service phone number of the issuing bank
+    #[schema(example = "1-800-432-3117")]
+    pub bank_phone_number: Option<String>,
+    /// Country calling code of the bank phone number
+    #[schema(example = "1")]
+    pub bank_phone_country_code: Option<String>,
+    /// Country where the card was issued
+    #[schema(example = "US")]
+    pub country: Option<String>,
+    /// Address Verification System result
+    #[schema(example = "Y")]
+    pub avs_result: Option<String>,
+    /// CVV verification result
+    #[schema(example = "M")]
+    pub cvv_result: Option<String>,
+    /// Whether 3D Secure verification was successful
+    pub was_3d_secure_successful: Option<bool>,
+}
+
+/// Order details
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Order {
+    /// Order amount before taxes and discounts
+    #[schema(example = 199.99)]
+    pub amount: f64,
+    /// ISO 4217 currency code
+    #[schema(example = "USD")]
+    pub currency: String,
+    /// Discount code applied to the order
+    #[schema(example = "SAVE10")]
+    pub discount_code: Option<String>,
+    /// Affiliate that referred the order
+    #[schema(example = "aff_partner_001")]
+    pub affiliate_id: Option<String>,
+    /// Sub-affiliate that referred the order
+    #[schema(example = "sub_social_media")]
+    pub subaffiliate_id: Option<String>,
+    /// URI of the page that referred the customer
+    #[schema(example = "https://google.com/search")]
+    pub referrer_uri: Option<String>,
+    /// Whether the order is a gift
+    #[serde(default)]
+    pub is_gift: bool,
+    /// Whether the order includes a gift message
+    #[serde(default)]
+    pub has_gift_message: bool,
+}
+
+/// Shopping cart line item
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CartItem {
+    /// Product identifier
+    #[schema(example = "prod_abc123")]
+    pub item_id: String,
+    /// Product category
+    #[schema(example = "electronics")]
+    pub category: Option<String>,
+    /// Price per unit
+    #[schema(example = 99.99)]
+    pub price: f64,
+    /// Number of units purchased
+    #[schema(example = 2)]
+    pub quantity: i64,
+}
+
+/// Transaction submitted for scoring
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "device": {
+        "ip_address": "198.51.100.1",
+        "user_agent": "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36"
+    },
+    "event": {
+        "type": "purchase",
+        "transaction_id": "txn_123456789",
+        "time": "2025-06-13T10:30:00Z"
+    },
+    "account": { "user_id": "user_12345" },
+    "order": { "amount": 199.99, "currency": "USD" },
+    "email": { "address": "customer@example.com" },
+    "billing": { "country": "US", "postal": "10001" }
+}))]
+pub struct TransactionRequest {
+    /// Device the transaction originated from
+    pub device: TransactionDevice,
+    /// Event being scored
+    pub event: TransactionEvent,
+    /// Existing fusegu user ID to associate with this transaction
+    pub user_id: Option<Uuid>,
+    /// Customer account identifiers
+    pub account: Option<TransactionAccount>,
+    /// Customer email
+    pub email: Option<TransactionEmail>,
+    /// Billing address
+    pub billing: Option<Address>,
+    /// Shipping address
+    pub shipping: Option<ShippingAddress>,
+    /// Payment card
+    pub credit_card: Option<CreditCard>,
+    /// Order details
+    pub order: Option<Order>,
+    /// Items in the shopping cart
+    #[serde(default)]
+    pub shopping_cart: Vec<CartItem>,
+    /// Custom input fields defined for your account
+    #[schema(value_type = Object)]
+    pub custom_inputs: Option<serde_json::Value>,
+}
+
+impl TransactionRequest {
+    /// Check field formats that the JSON schema alone cannot express
+    pub fn validate(&self) -> Result<(), String> {
+        if self.device.ip_address.parse::<IpAddr>().is_err() {
+            return Err("device.ip_address must be a valid IPv4 or IPv6 address".to_string());
+        }
+        check_len("device.user_agent", &self.device.user_agent, 512)?;
+        check_len("device.accept_language", &self.device.accept_language, 255)?;
+        check_len("device.session_id", &self.device.session_id, 255)?;
+        if let Some(token) = &self.device.device_token {
+            if token_fingerprint(token).is_none() {
+                return Err("device.device_token is not a valid device token".to_string());
+            }
+        }
+        if self
+            .device
+            .ja3
+            .as_deref()
+            .is_some_and(|ja3| !tls::is_ja3(ja3))
+        {
+            return Err("device.ja3 must be a JA3 fingerprint of 32 hex digits".to_string());
+        }
+        if self
+            .device
+            .ja4
+            .as_deref()
+            .is_some_and(|ja4| tls::parse_ja4(ja4).is_none())
+        {
+            return Err("device.ja4 is not a valid JA4 fingerprint".to_string());
+        }
+        check_len(
+            "device.header_order_hash",
+            &self.device.header_order_hash,
+            128,
+        )?;
+        check_len("event.transaction_id", &self.event.transaction_id, 255)?;
+        check_len(
+            "event.original_transaction_id",
+            &self.event.original_transaction_id,
+            255,
+        )?;
+        if self.event.event_type.is_reversal() != self.event.original_transaction_id.is_some() {
+            return Err(
+                "event.original_transaction_id is required on refund and cancellation \
+                        events and not accepted on others"
+                    .to_string(),
+            );
+        }
+        check_len("event.shop_id", &self.event.shop_id, 255)?;
+
+        if let Some(account) = &self.account {
+            check_len("account.user_id", &account.user_id, 255)?;
+            check_len("account.user_hash", &account.user_hash, 64)?;
+        }
+        if let Some(email) = &self.email {
+            check_len("email.address", &email.address, 255)?;
+            check_len("email.domain", &email.domain, 255)?;
+        }
+        if let Some(billing) = &self.billing {
+            validate_address("billing", billing)?;
+        }
+        if let Some(shipping) = &self.shipping {
+            validate_address("shipping", &shipping.address)?;
+        }
+        if let Some(card) = &self.credit_card {
+            check_digits("credit_card.issuer_id_number", &card.issuer_id_number, 6, 8)?;
+            check_digits("credit_card.last_digits", &card.last_digits, 2, 4)?;
+            check_country("credit_card.country", &card.country)?;
+            check_len("credit_card.token", &card.token, 255)?;
+            check_len("credit_card.avs_result", &card.avs_result, 1)?;
+            check_len("credit_card.cvv_result", &card.cvv_result, 1)?;
+        }
+        if let Some(order) = &self.order {
+            if !order.amount.is_finite() || order.amount < 0.0 {
+                return Err("order.amount must be a non-negative number".to_string());
+            }
+            if order.currency.len() != 3 || !order.currency.chars().all(|c| c.is_ascii_uppercase())
+            {
+                return Err("order.currency must be an ISO 4217 currency code".to_string());
+            }
+        }
+        for (i, item) in self.shopping_cart.iter().enumerate() {
+            if !item.price.is_finite() || item.price < 0.0 || item.quantity < 0 {
+                return Err(format!(
+                    "shopping_cart[{i}] must have a non-negative price and quantity"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parsed device IP address
+    pub fn ip_address(&self) -> Option<IpAddr> {
+        self.device.ip_address.parse().ok()
+    }
+
+    /// The address whose phone number is the transaction's: the billing address if it has a
+    /// phone number, or else the shipping address if it has one
+    pub fn phone_address(&self) -> Option<&Address> {
+        [
+            self.billing.as_ref(),
+            self.shipping.as_ref().map(|s| &s.address),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|address| address.phone_number.is_some())
+    }
+
+    /// How closely the name on the card matches the billing name, from 0 to 1, if both are given
+    pub fn cardholder_billing_match(&self) -> Option<f64> {
+        let holder = self.credit_card.as_ref()?.holder_name.as_deref()?;
+        matching::name_match(holder, &self.billing.as_ref()?.full_name()?)
+    }
+
+    /// How closely the name on the card matches the local part of the email address, from 0 to
+    /// 1, if both are given
+    ///
+    /// Stored requests only keep the hash of the address, so they have nothing to match.
+    pub fn cardholder_email_match(&self) -> Option<f64> {
+        let holder = self.credit_card.as_ref()?.holder_name.as_deref()?;
+        matching::email_name_match(holder, self.email.as_ref()?.address.as_deref()?)
+    }
+
+    /// Non-fatal data quality problems worth reporting back to the caller
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+
+        if self.ip_address().is_some_and(is_reserved_ip) {
+            warnings.push(Warning {
+                code: "IP_ADDRESS_RESERVED".to_string(),
+                message: "The IP address is in a reserved or private network range".to_string(),
+                input_path: Some("/device/ip_address".to_string()),
+            });
+        }
+        if self.billing.as_ref().is_some_and(|b| b.country.is_none()) {
+            warnings.push(Warning {
+                code: "BILLING_COUNTRY_MISSING".to_string(),
+                message: "A billing address was supplied without a country".to_string(),
+                input_path: Some("/billing/country".to_string()),
+            });
+        }
+        if self
+            .shipping
+            .as_ref()
+            .is_some_and(|s| s.address.country.is_none())
+        {
+            warnings.push(Warning {
+                code: "SHIPPING_COUNTRY_MISSING".to_string(),
+                message: "A shipping address was supplied without a country".to_string(),
+                input_path: Some("/shipping/country".to_string()),
+            });
+        }
+
+        warnings
+    }
+
+    /// Copy of the request fit to keep for rescoring and for Enterprise review
+    ///
+    /// The email address and card token are replaced by SHA-256 hashes of their stored forms,
+    /// as in the entity tables, and the email domain is resolved so rules can still see it.
+    /// Card digits are cut down to the configured BIN prefix and trailing digits, and custom
+    /// input fields on the deny-list are dropped. None of these feed the risk rules, and the
+    /// hashes lists and email history match on are kept alongside as
+    /// [`TransactionRequest::match_keys`], so a rescore of the redacted copy scores like the
+    /// original.
+    pub fn redacted(&self, config: &RedactionConfig) -> TransactionRequest {
+        let mut stored = self.clone();
+        if let Some(email) = &mut stored.email {
+            email.domain = email.resolved_domain();
+            email.address = email.address_hash();
+        }
+        if let Some(card) = &mut stored.credit_card {
+            card.token = card.token.as_deref().map(sha256_hex);
+            if let Some(bin) = &mut card.issuer_id_number {
+                *bin = bin.chars().take(config.card_bin_digits).collect();
+            }
+            if let Some(digits) = &mut card.last_digits {
+                let skip = digits
+                    .chars()
+                    .count()
+                    .saturating_sub(config.card_last_digits);
+                *digits = digits.chars().skip(skip).collect();
+            }
+        }
+        if let Some(custom_inputs) = &mut stored.custom_inputs {
+            drop_denied_fields(custom_inputs, &config.custom_input_deny_list);
+        }
+        stored
+    }
+
+    /// Hashes the request's email address and card are matched on against the account's
+    /// lists and email history
+    pub fn match_keys(&self) -> MatchKeys {
+        MatchKeys {
+            email_hash: self.email.as_ref().and_then(TransactionEmail::address_hash),
+            mailbox_hash: self.email.as_ref().and_then(TransactionEmail::mailbox_hash),
+            card_hash: self
+                .credit_card
+                .as_ref()
+                .and_then(|card| card.token.as_deref())
+                .and_then(|token| ListEntityType::CardHash.normalize(token).ok()),
+        }
+    }
+}
+
+/// Hashes a transaction's email address and card are matched on, which its
+/// [redacted](TransactionRequest::redacted) copy no longer yields
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchKeys {
+    /// Hash of the email address, as stored
+    pub email_hash: Option<String>,
+    /// Hash of the mailbox the email address delivers to
+    pub mailbox_hash: Option<String>,
+    /// Card token as normalized for card hash list entries
+    pub card_hash: Option<String>,
+}
+
+/// Remove object fields named in `deny_list` (lowercase) from `value`, at any depth
+fn drop_denied_fields(value: &mut serde_json::Value, deny_list: &[String]) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            fields.retain(|name, _| !deny_list.contains(&name.to_lowercase()));
+            for field in fields.values_mut() {
+                drop_denied_fields(field, deny_list);
+            }
+        },
+        serde_json::Value::Array(items) => {
+            for item in items {
+                drop_denied_fields(item, deny_list);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Whether an address can never belong to a real customer on the public internet
+pub fn is_reserved_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [first, second, ..] = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                // "This network" (0.0.0.0/8) and carrier-grade NAT (100.64.0.0/10) ranges
+                || first == 0
+                || (first == 100 && (second & 0xc0) == 64)
+        },
+        IpAddr::V6(v6) => {
+            v6.is_loopback()
+                || v6.is_unspecified()
+                // Unique local (fc00::/7) and link-local (fe80::/10) ranges
+                || (v6.segments()[0] & 0xfe00) == 0xfc00
+                || (v6.segments()[0] & 0xffc0) == 0xfe80
+                // IPv4-mapped and IPv4-compatible addresses reach the IPv4 address they embed
+                || v6.to_ipv4().is_some_and(|v4| is_reserved_ip(IpAddr::V4(v4)))
+        },
+    }
+}
+
+fn validate_address(prefix: &str, address: &Address) -> Result<(), String> {
+    check_country(&format!("{prefix}.country"), &address.country)?;
+    check_len(&format!("{prefix}.region"), &address.region, 4)?;
+    check_len(
+        &format!("{prefix}.phone_country_code"),
+        &address.phone_country_code,
+        4,
+    )?;
+    Ok(())
+}
+
+fn check_len(field: &str, value: &Option<String>, max: usize) -> Result<(), String> {
+    match value {
+        Some(v) if v.chars().count() > max => {
+            Err(format!("{field} must be at most {max} characters"))
+        },
+        _ => Ok(()),
+    }
+}
+
+fn check_country(field: &str, value: &Option<String>) -> Result<(), String> {
+    match value {
+        Some(v) if v.len() != 2 || !v.chars().all(|c| c.is_ascii_uppercase()) => Err(format!(
+            "{field} must be an ISO 3166-1 alpha-2 country code"
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_digits(field: &str, value: &Option<String>, min: usize, max: usize) -> Result<(), String> {
+    match value {
+        Some(v) if v.len() < min || v.len() > max || !v.chars().all(|c| c.is_ascii_digit()) => {
+            Err(format!("{field} must be {min}-{max} digits"))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Non-fatal problem found in the submitted data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Warning {
+    /// Machine-readable warning code
+    #[schema(example = "IP_ADDRESS_RESERVED")]
+    pub code: String,
+    /// Human-readable explanation
+    #[schema(example = "The IP address is in a reserved network range")]
+    pub message: String,
+    /// JSON pointer to the problematic input field
+    #[schema(example = "/device/ip_address")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_path: Option<String>,
+}
+
+/// Risk assessment for a transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "id": "550e8400-e29b-41d4-a716-446655440000",
+    "risk_score": 2.45,
+    "risk_level": "low",
+    "disposition": "accept",
+    "event_type": "purchase",
+    "created_at": "2025-06-13T10:30:00.123Z",
+    "_links": {
+        "self": { "href": "/v1/transactions/550e8400-e29b-41d4-a716-446655440000" }
+    }
+}))]
+pub struct TransactionResponse {
+    /// Unique transaction identifier
+    pub id: Uuid,
+    /// User associated with this transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<Uuid>,
+    /// Your internal transaction ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_transaction_id: Option<String>,
+    /// Purchase a refund or cancellation undoes, if it was scored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_transaction_id: Option<Uuid>,
+    /// Fraud risk score (0.01 = low risk, 99.99 = high risk)
+    #[schema(example = 15.42, minimum = 0.01, maximum = 99.99)]
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Recommended action
+    pub disposition: Disposition,
+    /// Type of event that was scored
+    pub event_type: EventType,
+    /// Transaction creation timestamp
+    pub created_at: DateTime<Utc>,
+    /// Non-fatal issues found in the request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+    /// Scoring requests left in the account's billing cycle; present only on newly scored
+    /// transactions from metered keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 9876)]
+    pub queries_remaining: Option<i64>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Transactions submitted for scoring in one request
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchTransactionRequest {
+    /// Transactions to score, each in the form accepted by `POST /v1/transactions`
+    ///
+    /// Items are checked one by one, so a malformed item fails on its own rather than
+    /// rejecting the whole batch.
+    #[schema(value_type = Vec<TransactionRequest>)]
+    pub transactions: Vec<serde_json::Value>,
+}
+
+/// Outcome of a batch of transactions, one result per submitted item
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "results": [
+        {
+            "index": 0,
+            "status": 201,
+            "transaction": {
+                "id": "550e8400-e29b-41d4-a716-446655440000",
+                "risk_score": 2.45,
+                "risk_level": "low",
+                "disposition": "accept",
+                "event_type": "purchase",
+                "created_at": "2025-06-13T10:30:00.123Z",
+                "_links": {
+                    "self": { "href": "/v1/transactions/550e8400-e29b-41d4-a716-446655440000" }
+                }
+            }
+        },
+        {
+            "index": 1,
+            "status": 422,
+            "error": {
+                "error": "validation_error",
+                "message": "device.ip_address must be a valid IPv4 or IPv6 address"
+            }
+        }
+    ],
+    "succeeded": 1,
+    "failed": 1,
+    "queries_remaining": 9876
+}))]
+pub struct BatchTransactionResponse {
+    /// Results in the order the transactions were submitted
+    pub results: Vec<BatchItemResult>,
+    /// Transactions scored and stored
+    pub succeeded: usize,
+    /// Transactions that failed
+    pub failed: usize,
+    /// Scoring requests left in the account's billing cycle; present only for metered keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 9876)]
+    pub queries_remaining: Option<i64>,
+}
+
+/// Outcome of one transaction of a batch
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchItemResult {
+    /// Position of the transaction in the request, from 0
+    pub index: usize,
+    /// HTTP status the transaction would have received on its own
+    #[schema(example = 201)]
+    pub status: u16,
+    /// Risk assessment, if the transaction was scored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TransactionResponse>,
+    /// Why the transaction failed, otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+}
+
+/// Request a transaction was scored on, as stored after redaction
+///
+/// Email addresses and card tokens are SHA-256 hashes, card digits are truncated, and
+/// custom input fields on the operator's deny-list are missing.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StoredTransactionRequest {
+    /// Transaction the request belongs to
+    pub transaction_id: Uuid,
+    /// The redacted request
+    pub request: TransactionRequest,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// New risk assessment of a stored transaction
+///
+/// The original assessment is revision 1 and stays on the transaction; each rescore is kept
+/// as the next revision.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "transaction_id": "550e8400-e29b-41d4-a716-446655440000",
+    "revision": 2,
+    "risk_score": 27.8,
+    "risk_level": "low",
+    "disposition": "accept",
+    "previous_risk_score": 15.42,
+    "created_at": "2025-06-20T09:12:00.456Z"
+}))]
+pub struct ScoringRevision {
+    /// Transaction that was rescored
+    pub transaction_id: Uuid,
+    /// Revision number, counting the original scoring as 1
+    #[schema(example = 2, minimum = 2)]
+    pub revision: i32,
+    /// Fraud risk score under the current rule set
+    #[schema(example = 27.8, minimum = 0.01, maximum = 99.99)]
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Recommended action
+    pub disposition: Disposition,
+    /// Risk score of the revision before this one
+    #[schema(example = 15.42)]
+    pub previous_risk_score: f64,
+    /// When the transaction was rescored
+    pub created_at: DateTime<Utc>,
+    /// Scoring requests left in the account's billing cycle; present only for metered keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = 9875)]
+    pub queries_remaining: Option<i64>,
+}
+
+/// Sort order for transaction listings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum TransactionSort {
+    /// Oldest first
+    #[serde(rename = "created_at")]
+    CreatedAtAsc,
+    /// Newest first
+    #[default]
+    #[serde(rename = "-created_at")]
+    CreatedAtDesc,
+    /// Lowest risk first
+    #[serde(rename = "risk_score")]
+    RiskScoreAsc,
+    /// Highest risk first
+    #[serde(rename = "-risk_score")]
+    RiskScoreDesc,
+}
+
+impl TransactionSort {
+    /// Query-string form of the sort order
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransactionSort::CreatedAtAsc => "created_at",
+            TransactionSort::CreatedAtDesc => "-created_at",
+            TransactionSort::RiskScoreAsc => "risk_score",
+            TransactionSort::RiskScoreDesc => "-risk_score",
+        }
+    }
+
+    /// Whether cursor pages run oldest first, or `None` if the order does not support cursors
+    ///
+    /// Cursors are positions in creation order, so only the creation time orders have them.
+    pub fn cursor_ascending(self) -> Option<bool> {
+        match self {
+            TransactionSort::CreatedAtAsc => Some(true),
+            TransactionSort::CreatedAtDesc => Some(false),
+            TransactionSort::RiskScoreAsc | TransactionSort::RiskScoreDesc => None,
+        }
+    }
+}
+
+/// When a submitted transaction is scored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoringMode {
+    /// Score before responding
+    #[default]
+    Sync,
+    /// Respond at once with a job, and score in the background
+    Async,
+}
+
+/// Query parameters for submitting a transaction
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CreateTransactionQuery {
+    /// Score before responding (`sync`, the default) or in the background (`async`)
+    pub mode: Option<ScoringMode>,
+    /// HTTP(S) URL the finished job is POSTed to; only with `mode=async`, on plans with
+    /// webhooks
+    #[param(example = "https://merchant.example.com/fusegu/callback")]
+    pub callback_url: Option<String>,
+}
+
+impl CreateTransactionQuery {
+    /// Check the parameters fit together and the callback URL can be delivered to
+    pub fn validate(&self) -> Result<(), String> {
+        let Some(callback_url) = &self.callback_url else {
+            return Ok(());
+        };
+        if self.mode != Some(ScoringMode::Async) {
+            return Err("callback_url requires mode=async".to_string());
+        }
+        validate_delivery_url("callback_url", callback_url)
+    }
+}
+
+/// Check that events can be POSTed to `url`: it must use HTTP(S) and may not name a reserved
+/// or private IP address, which would let deliveries probe the internal network
+///
+/// Hostnames are checked again when deliveries connect, by
+/// [`PublicResolver`](crate::outbox::resolver::PublicResolver).
+pub fn validate_delivery_url(field: &str, url: &str) -> Result<(), String> {
+    let url = reqwest::Url::parse(url).map_err(|_| format!("{field} must be an absolute URL"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("{field} must use http or https"));
+    }
+    let host = url.host_str().unwrap_or_default();
+    let reserved = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) => is_reserved_ip(ip),
+        Err(_) => host.is_empty() || host.eq_ignore_ascii_case("localhost"),
+    };
+    if reserved {
+        return Err(format!("{field} must name a public host"));
+    }
+    Ok(())
+}
+
+/// Query parameters for listing transactions
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListTransactionsQuery {
+    /// Maximum number of transactions to return (1-100)
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    pub limit: Option<i64>,
+    /// Number of transactions to skip
+    #[param(minimum = 0, default = 0)]
+    pub offset: Option<i64>,
+    /// Opaque `next_cursor` of the previous page, to page by cursor instead of offset; only
+    /// for sorting by creation time, and not combined with `offset`
+    pub cursor: Option<String>,
+    /// Filter by risk level
+    pub risk_level: Option<RiskLevel>,
+    /// Filter by disposition
+    pub disposition: Option<Disposition>,
+    /// Only transactions created at or after this time
+    pub from_date: Option<DateTime<Utc>>,
+    /// Only transactions created before this time
+    pub to_date: Option<DateTime<Utc>>,
+    /// Only transactions of this user
+    pub user_id: Option<Uuid>,
+    /// Only transactions from this device
+    pub device_id: Option<Uuid>,
+    /// Only transactions in this shop
+    pub shop_id: Option<String>,
+    /// Only orders of at least this amount
+    #[param(minimum = 0)]
+    pub min_amount: Option<f64>,
+    /// Only orders of at most this amount
+    #[param(minimum = 0)]
+    pub max_amount: Option<f64>,
+    /// Only orders in this ISO 4217 currency
+    #[param(example = "USD")]
+    pub currency: Option<String>,
+    /// Only transactions with a billing or shipping address in this ISO 3166-1 alpha-2 country
+    #[param(example = "US")]
+    pub country: Option<String>,
+    /// Only transactions from this IP address, or from within this CIDR range
+    #[param(example = "198.51.100.0/24")]
+    pub ip_address: Option<String>,
+    /// Only transactions whose email address has this domain
+    #[param(example = "example.com")]
+    pub email_domain: Option<String>,
+    /// Only transactions on which this rule fired, by risk factor code
+    #[param(example = "CVV_MISMATCH")]
+    pub rule: Option<String>,
+    /// Only transactions whose external transaction ID contains this text, ignoring case
+    #[param(example = "txn_1234")]
+    pub q: Option<String>,
+    /// Sort order
+    #[param(inline)]
+    pub sort: Option<TransactionSort>,
+}
+
+impl ListTransactionsQuery {
+    /// Check filter formats that the query string alone cannot express
+    pub fn validate(&self) -> Result<(), String> {
+        check_len("shop_id", &self.shop_id, 255)?;
+        check_len("email_domain", &self.email_domain, 255)?;
+        check_len("rule", &self.rule, 100)?;
+        check_len("q", &self.q, 255)?;
+        check_country("country", &self.country)?;
+        if self
+            .currency
+            .as_deref()
+            .is_some_and(|c| c.len() != 3 || !c.chars().all(|c| c.is_ascii_uppercase()))
+        {
+            return Err("currency must be an ISO 4217 currency code".to_string());
+        }
+        for (field, amount) in [
+            ("min_amount", self.min_amount),
+            ("max_amount", self.max_amount),
+        ] {
+            if amount.is_some_and(|a| !a.is_finite() || a < 0.0) {
+                return Err(format!("{field} must be a non-negative number"));
+            }
+        }
+        if self
+            .min_amount
+            .zip(self.max_amount)
+            .is_some_and(|(min, max)| min > max)
+        {
+            return Err("min_amount must not exceed max_amount".to_string());
+        }
+        if self
+            .ip_address
+            .as_deref()
+            .is_some_and(|ip| !is_ip_or_network(ip))
+        {
+            return Err("ip_address must be an IP address or CIDR range".to_string());
+        }
+        Ok(())
+    }
+
+    /// `LIKE` pattern matching external transaction IDs that contain [`Self::q`]
+    pub fn q_pattern(&self) -> Option<String> {
+        let q = self.q.as_deref()?;
+        let escaped = q
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        Some(format!("%{escaped}%"))
+    }
+}
+
+/// Whether `value` is an IP address, or one followed by a prefix length that fits it
+pub fn is_ip_or_network(value: &str) -> bool {
+    let (address, prefix) = match value.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix)),
+        None => (value, None),
+    };
+    let Ok(address) = address.parse::<IpAddr>() else {
+        return false;
+    };
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    prefix.is_none_or(|prefix| prefix.parse::<u8>().is_ok_and(|p| p <= max_prefix))
+}
+
+/// Page of transactions
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionList {
+    /// Transactions on this page
+    pub transactions: Vec<TransactionResponse>,
+    /// Pagination details
+    pub pagination: Pagination,
+    /// Links to adjacent pages
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> TransactionRequest {
+        serde_json::from_value(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1" },
+            "event": { "type": "purchase" },
+            "order": { "amount": 199.99, "currency": "USD" },
+            "billing": { "country": "US" }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_valid_request() {
+        assert!(request().validate().is_ok());
+    }
+
+    #[test]
+    fn test_invalid_ip_address() {
+        let mut request = request();
+        request.device.ip_address = "not-an-ip".to_string();
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn test_invalid_tls_fingerprints() {
+        let mut request = request();
+        request.device.ja3 = Some("CD08E31494F9531F560D64C695473DA9".to_string());
+        request.device.ja4 = Some("t13d1516h2_8daaf6152771_e5627efa2ab1".to_string());
+        assert!(request.validate().is_ok());
+
+        let mut bad_ja3 = request.clone();
+        bad_ja3.device.ja3 = Some("not-a-hash".to_string());
+        assert!(bad_ja3.validate().is_err());
+
+        request.device.ja4 = Some("t13d1516h2".to_string());
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn test_invalid_country_and_currency() {
+        let mut bad_country = request();
+        bad_country.billing.as_mut().unwrap().country = Some("usa".to_string());
+        assert!(bad_country.validate().is_err());
+
+        let mut bad_currency = request();
+        bad_currency.order.as_mut().unwrap().currency = "usd".to_string();
+        assert!(bad_currency.validate().is_err());
+    }
+
+    #[test]
+    fn test_reversals_name_the_original_purchase() {
+        let mut refund = request();
+        refund.event.event_type = EventType::Refund;
+        assert!(refund.validate().is_err());
+        refund.event.original_transaction_id = Some("txn_123456788".to_string());
+        assert!(refund.validate().is_ok());
+
+        let mut purchase = refund.clone();
+        purchase.event.event_type = EventType::Purchase;
+        assert!(purchase.validate().is_err());
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mut request = request();
+        assert!(request.warnings().is_empty());
+
+        request.device.ip_address = "192.168.1.10".to_string();
+        request.billing.as_mut().unwrap().country = None;
+        let codes: Vec<_> = request.warnings().into_iter().map(|w| w.code).collect();
+        assert_eq!(
+            codes,
+            vec!["IP_ADDRESS_RESERVED", "BILLING_COUNTRY_MISSING"]
+        );
+    }
+
+    #[test]
+    fn test_risk_level_thresholds() {
+        assert_eq!(RiskLevel::from_score(0.01), RiskLevel::Low);
+        assert_eq!(RiskLevel::from_score(30.0), RiskLevel::Medium);
+        assert_eq!(RiskLevel::from_score(60.0), RiskLevel::High);
+        assert_eq!(RiskLevel::from_score(99.99), RiskLevel::VeryHigh);
+    }
+
+    #[test]
+    fn test_sort_parses_from_query_form() {
+        let sort: TransactionSort = serde_json::from_str("\"-risk_score\"").unwrap();
+        assert_eq!(sort, TransactionSort::RiskScoreDesc);
+    }
+
+    #[test]
+    fn test_listing_filters() {
+        let query = |value: serde_json::Value| -> ListTransactionsQuery {
+            serde_json::from_value(value).unwrap()
+        };
+        assert!(
+            query(serde_json::json!({
+                "currency": "USD",
+                "country": "US",
+                "min_amount": 10.0,
+                "max_amount": 20.0,
+                "ip_address": "198.51.100.0/24"
+            }))
+            .validate()
+            .is_ok()
+        );
+        assert!(
+            query(serde_json::json!({ "ip_address": "2001:db8::1" }))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            query(serde_json::json!({ "ip_address": "10.0.0.0/33" }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            query(serde_json::json!({ "ip_address": "nope" }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            query(serde_json::json!({ "currency": "usd" }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            query(serde_json::json!({ "min_amount": 20.0, "max_amount": 10.0 }))
+                .validate()
+                .is_err()
+        );
+
+        let search = query(serde_json::json!({ "q": "50%_off" }));
+        assert_eq!(search.q_pattern().as_deref(), Some("%50\\%\\_off%"));
+    }
+
+    #[test]
+    fn test_resolved_email_domain() {
+        let email = TransactionEmail {
+            address: Some(" Jane.Doe@Example.COM ".to_string()),
+            domain: None,
+        };
+        assert_eq!(email.resolved_domain().as_deref(), Some("example.com"));
+
+        let email = TransactionEmail {
+            domain: Some("example.org".to_string()),
+            ..email
+        };
+        assert_eq!(email.resolved_domain().as_deref(), Some("example.org"));
+    }
+
+    #[test]
+    fn test_canonical_mailbox() {
+        assert_eq!(
+            canonical_mailbox(" J.A.N.E+promo1@GoogleMail.com"),
+            "jane@gmail.com"
+        );
+        assert_eq!(
+            canonical_mailbox("jane.doe+x+y@example.com"),
+            "jane.doe@example.com"
+        );
+        assert_eq!(canonical_mailbox("+tag@example.com"), "+tag@example.com");
+        assert_eq!(canonical_mailbox("not-an-address"), "not-an-address");
+
+        let variant = |address: &str| TransactionEmail {
+            address: Some(address.to_string()),
+            domain: None,
+        };
+        let jane = variant("jane@gmail.com");
+        let tumbled = variant("ja.ne+2@gmail.com");
+        assert_ne!(jane.address_hash(), tumbled.address_hash());
+        assert_eq!(jane.mailbox_hash(), tumbled.mailbox_hash());
+    }
+
+    #[test]
+    fn test_callback_url_must_be_public() {
+        let query = |mode, callback_url: &str| CreateTransactionQuery {
+            mode,
+            callback_url: Some(callback_url.to_string()),
+        };
+        let async_mode = Some(ScoringMode::Async);
+        assert!(
+            query(async_mode, "https://merchant.example.com/hook")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            query(None, "https://merchant.example.com/hook")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            query(async_mode, "ftp://merchant.example.com/hook")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            query(async_mode, "http://10.0.0.5/hook")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            query(async_mode, "http://[::1]:8080/hook")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            query(async_mode, "http://localhost/hook")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            query(async_mode, "http://[::ffff:127.0.0.1]/hook")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            query(async_mode, "http://[::ffff:169.254.169.254]/hook")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            query(async_mode, "http://100.64.0.1/hook")
+                .validate()
+                .is_err()
+        );
+        assert!(query(async_mode, "http://0.1.2.3/hook").validate().is_err());
+        assert!(query(async_mode, "not a url").validate().is_err());
+    }
+
+    #[test]
+    fn test_reserved_ips() {
+        for reserved in [
+            "10.1.2.3",
+            "0.0.0.1",
+            "100.64.0.1",
+            "100.127.255.254",
+            "::ffff:10.0.0.1",
+            "::127.0.0.1",
+            "fd00::1",
+        ] {
+            assert!(is_reserved_ip(reserved.parse().unwrap()), "{reserved}");
+        }
+        for public in [
+            "100.63.255.255",
+            "100.128.0.1",
+            "8.8.8.8",
+            "::ffff:8.8.8.8",
+            "2001:4860::8888",
+        ] {
+            assert!(!is_reserved_ip(public.parse().unwrap()), "{public}");
+        }
+    }
+
+    #[test]
+    fn test_stored_request_is_redacted() {
+        let mut request = request();
+        request.email = Some(TransactionEmail {
+            address: Some("Jane@Example.com".to_string()),
+            domain: None,
+        });
+        request.credit_card = Some(CreditCard {
+            issuer_id_number: Some("41111122".to_string()),
+            last_digits: Some("1234".to_string()),
+            token: Some("tok_abc".to_string()),
+            ..CreditCard::default()
+        });
+        request.custom_inputs = Some(serde_json::json!({
+            "loyalty_tier": "gold",
+            "National_ID": "123-45-6789",
+            "applicants": [{ "national_id": "987-65-4321", "age": 40 }]
+        }));
+        let config = RedactionConfig {
+            card_bin_digits: 6,
+            card_last_digits: 2,
+            custom_input_deny_list: vec!["national_id".to_string()],
+        };
+
+        let stored = request.redacted(&config);
+        let email = stored.email.unwrap();
+        assert_eq!(email.address, Some(sha256_hex("jane@example.com")));
+        assert_eq!(email.domain.as_deref(), Some("example.com"));
+        let card = stored.credit_card.unwrap();
+        assert_eq!(card.token, Some(sha256_hex("tok_abc")));
+        assert_eq!(card.issuer_id_number.as_deref(), Some("411111"));
+        assert_eq!(card.last_digits.as_deref(), Some("34"));
+        assert_eq!(
+            stored.custom_inputs,
+            Some(serde_json::json!({
+                "loyalty_tier": "gold",
+                "applicants": [{ "age": 40 }]
+            }))
+        );
+    }
+}
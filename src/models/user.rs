@@ -0,0 +1,792 @@
+//! End users tracked across transactions
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{
+    common::{Link, Links},
+    transaction::RiskLevel,
+};
+
+/// Longest external user ID accepted
+const MAX_EXTERNAL_USER_ID_LEN: usize = 255;
+/// Most flags a user may carry
+const MAX_FLAGS: usize = 50;
+/// Longest flag type accepted
+const MAX_FLAG_TYPE_LEN: usize = 64;
+/// Longest flag reason accepted
+const MAX_FLAG_REASON_LEN: usize = 500;
+/// Longest flag actor accepted
+const MAX_FLAG_ACTOR_LEN: usize = 255;
+/// Largest serialized metadata object accepted, in bytes
+const MAX_METADATA_BYTES: usize = 16 * 1024;
+
+/// End user of an account, with running totals over their transactions
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "id": "8f14e45f-ceea-467f-a0d5-2c1b2a3e4f50",
+    "external_user_id": "customer-1042",
+    "risk_score": 12.5,
+    "risk_level": "low",
+    "total_transactions": 14,
+    "successful_transactions": 13,
+    "failed_transactions": 1,
+    "chargeback_count": 0,
+    "first_transaction_at": "2025-03-02T14:21:09Z",
+    "last_transaction_at": "2025-06-13T10:30:00Z",
+    "is_verified": true,
+    "is_flagged": false,
+    "flags": [{
+        "type": "watch",
+        "reason": "Shares a device with a confirmed fraudster",
+        "actor": "analyst@example.com",
+        "expires_at": "2025-07-13T00:00:00Z"
+    }],
+    "metadata": { "segment": "loyalty" },
+    "created_at": "2025-03-02T14:21:09Z",
+    "updated_at": "2025-06-13T10:30:00Z",
+    "_links": {
+        "self": { "href": "/v1/users/8f14e45f-ceea-467f-a0d5-2c1b2a3e4f50" }
+    }
+}))]
+pub struct User {
+    /// Unique user identifier
+    pub id: Uuid,
+    /// The customer's own identifier for the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_user_id: Option<String>,
+    /// Hash identifying the user, for users only known by hash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_hash: Option<String>,
+    /// Risk score of the user
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Transactions scored for the user
+    pub total_transactions: i32,
+    /// Transactions that went through
+    pub successful_transactions: i32,
+    /// Transactions that did not go through
+    pub failed_transactions: i32,
+    /// Chargebacks reported for the user's transactions
+    pub chargeback_count: i32,
+    /// When the user's first transaction happened
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_transaction_at: Option<DateTime<Utc>>,
+    /// When the user's latest transaction happened
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_transaction_at: Option<DateTime<Utc>>,
+    /// Whether the customer has verified the user's identity
+    pub is_verified: bool,
+    /// Whether the user is flagged for review
+    pub is_flagged: bool,
+    /// Flags the customer attached to the user, including expired ones
+    pub flags: Vec<UserFlag>,
+    /// Free-form data the customer attached to the user
+    #[schema(value_type = Object)]
+    pub metadata: serde_json::Value,
+    /// When the user was first seen
+    pub created_at: DateTime<Utc>,
+    /// When the user last changed
+    pub updated_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl User {
+    /// Links of the user with the given ID
+    pub fn links(id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/users/{id}"))),
+            ..Links::default()
+        }
+    }
+}
+
+/// Flag attached to a user, for example to keep watch on them
+///
+/// Transactions of a user carrying a flag that has not expired score higher.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UserFlag {
+    /// Kind of flag, such as `watch` or `manual_review`; unique among the user's flags
+    #[serde(rename = "type")]
+    #[schema(example = "watch")]
+    pub flag_type: String,
+    /// Why the user was flagged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Shares a device with a confirmed fraudster")]
+    pub reason: Option<String>,
+    /// Who flagged the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "analyst@example.com")]
+    pub actor: Option<String>,
+    /// When the flag lapses; flags without one stay until removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl UserFlag {
+    /// Whether the flag is still in force at `at`
+    pub fn is_active(&self, at: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > at)
+    }
+}
+
+/// Request to register a user before their first transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CreateUser {
+    /// The customer's own identifier for the user; unique within the account
+    #[schema(example = "customer-1042")]
+    pub external_user_id: String,
+    /// Whether the customer has verified the user's identity (default: false)
+    pub is_verified: Option<bool>,
+    /// Whether the user is flagged for review (default: false)
+    pub is_flagged: Option<bool>,
+    /// Flags to attach to the user
+    #[schema(example = json!([{ "type": "watch", "reason": "Referred by a flagged user" }]))]
+    pub flags: Option<Vec<UserFlag>>,
+    /// Free-form data to attach to the user; must be an object
+    #[schema(value_type = Option<Object>, example = json!({ "segment": "loyalty" }))]
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl CreateUser {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        let external_user_id = self.external_user_id.trim();
+        if external_user_id.is_empty() {
+            return Err("external_user_id must not be empty".to_string());
+        }
+        if external_user_id.chars().count() > MAX_EXTERNAL_USER_ID_LEN {
+            return Err(format!(
+                "external_user_id must be at most {MAX_EXTERNAL_USER_ID_LEN} characters"
+            ));
+        }
+        validate_flags(self.flags.as_deref())?;
+        validate_metadata(self.metadata.as_ref())
+    }
+}
+
+/// Changes to a user; fields left out are kept as they are
+///
+/// `flags` and `metadata` replace the stored values as a whole.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UserUpdate {
+    /// Whether the customer has verified the user's identity
+    pub is_verified: Option<bool>,
+    /// Whether the user is flagged for review
+    pub is_flagged: Option<bool>,
+    /// Flags attached to the user
+    #[schema(example = json!([{
+        "type": "manual_review",
+        "reason": "Disputed two orders this month",
+        "actor": "analyst@example.com",
+        "expires_at": "2025-07-13T00:00:00Z"
+    }]))]
+    pub flags: Option<Vec<UserFlag>>,
+    /// Free-form data attached to the user; must be an object
+    #[schema(value_type = Option<Object>, example = json!({ "segment": "loyalty" }))]
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl UserUpdate {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        validate_flags(self.flags.as_deref())?;
+        validate_metadata(self.metadata.as_ref())
+    }
+
+    /// Whether the update changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.is_verified.is_none()
+            && self.is_flagged.is_none()
+            && self.flags.is_none()
+            && self.metadata.is_none()
+    }
+}
+
+/// User record from a customer's existing user base, one item of `POST /v1/users/batch`
+///
+/// Users are matched on `external_user_id`. A new user is created with the given values; an
+/// existing one keeps the larger of its own and the imported totals, the wider transaction
+/// period, and its flags plus imported ones of types it does not carry, and takes the imported
+/// metadata keys and verification and review status.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ImportUser {
+    /// The customer's own identifier for the user
+    #[schema(example = "customer-1042")]
+    pub external_user_id: String,
+    /// Whether the customer has verified the user's identity
+    pub is_verified: Option<bool>,
+    /// Whether the user is flagged for review
+    pub is_flagged: Option<bool>,
+    /// Flags the user carried in the customer's own system
+    #[schema(example = json!([{ "type": "watch", "reason": "Chargeback in 2024" }]))]
+    pub flags: Option<Vec<UserFlag>>,
+    /// Free-form data to attach to the user; must be an object
+    #[schema(value_type = Option<Object>, example = json!({ "segment": "loyalty" }))]
+    pub metadata: Option<serde_json::Value>,
+    /// Transactions the user made before the import
+    #[schema(minimum = 0, example = 42)]
+    pub total_transactions: Option<i32>,
+    /// Of those, transactions that went through
+    #[schema(minimum = 0)]
+    pub successful_transactions: Option<i32>,
+    /// Of those, transactions that did not go through
+    #[schema(minimum = 0)]
+    pub failed_transactions: Option<i32>,
+    /// Chargebacks the user has had
+    #[schema(minimum = 0)]
+    pub chargeback_count: Option<i32>,
+    /// When the user's first transaction happened
+    pub first_transaction_at: Option<DateTime<Utc>>,
+    /// When the user's latest transaction happened
+    pub last_transaction_at: Option<DateTime<Utc>>,
+}
+
+impl ImportUser {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        CreateUser {
+            external_user_id: self.external_user_id.clone(),
+            is_verified: None,
+            is_flagged: None,
+            flags: self.flags.clone(),
+            metadata: self.metadata.clone(),
+        }
+        .validate()?;
+        for (field, count) in [
+            ("total_transactions", self.total_transactions),
+            ("successful_transactions", self.successful_transactions),
+            ("failed_transactions", self.failed_transactions),
+            ("chargeback_count", self.chargeback_count),
+        ] {
+            if count.is_some_and(|count| count < 0) {
+                return Err(format!("{field} must not be negative"));
+            }
+        }
+        let outcomes = i64::from(self.successful_transactions.unwrap_or(0))
+            + i64::from(self.failed_transactions.unwrap_or(0));
+        if self
+            .total_transactions
+            .is_some_and(|total| outcomes > i64::from(total))
+        {
+            return Err(
+                "successful_transactions and failed_transactions must not exceed total_transactions"
+                    .to_string(),
+            );
+        }
+        if self
+            .first_transaction_at
+            .zip(self.last_transaction_at)
+            .is_some_and(|(first, last)| first > last)
+        {
+            return Err("first_transaction_at must not be after last_transaction_at".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Progress of a user import
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum UserImportStatus {
+    /// Waiting to be processed
+    Pending,
+    /// Users are being imported; see the counts for progress
+    Processing,
+    /// Every user has been imported or has failed
+    Completed,
+}
+
+/// User of an import that could not be imported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UserImportError {
+    /// Position of the user in the submitted batch, from 0
+    pub index: usize,
+    /// Why the user was not imported
+    pub message: String,
+}
+
+/// Batch of users imported in the background
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "id": "3f2b8c1e-9a4d-4e6f-8b7a-1c2d3e4f5a6b",
+    "status": "processing",
+    "total_users": 8000,
+    "processed_users": 4000,
+    "created_users": 3640,
+    "updated_users": 356,
+    "failed_users": 4,
+    "errors": [{ "index": 811, "message": "chargeback_count must not be negative" }],
+    "created_at": "2025-06-13T10:30:00Z",
+    "started_at": "2025-06-13T10:30:01Z",
+    "_links": {
+        "self": { "href": "/v1/users/imports/3f2b8c1e-9a4d-4e6f-8b7a-1c2d3e4f5a6b" }
+    }
+}))]
+pub struct UserImport {
+    /// Unique import identifier
+    pub id: Uuid,
+    /// Progress of the import
+    pub status: UserImportStatus,
+    /// Users submitted
+    pub total_users: i32,
+    /// Users imported or failed so far
+    pub processed_users: i32,
+    /// Users created
+    pub created_users: i32,
+    /// Existing users updated
+    pub updated_users: i32,
+    /// Users that could not be imported
+    pub failed_users: i32,
+    /// Why users could not be imported; only the first failures are listed
+    pub errors: Vec<UserImportError>,
+    /// When the import was submitted
+    pub created_at: DateTime<Utc>,
+    /// When processing started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the import finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl UserImport {
+    /// Links of the import with the given ID
+    pub fn links(id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/users/imports/{id}"))),
+            ..Links::default()
+        }
+    }
+}
+
+/// Query parameters for looking up a user by one of the customer's own identifiers
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserLookupQuery {
+    /// The customer's own identifier for the user
+    #[param(example = "user_12345")]
+    pub external_user_id: Option<String>,
+    /// Hash the user was identified by in transactions
+    #[param(example = "098f6bcd4621d373cade4e832627b4f6")]
+    pub user_hash: Option<String>,
+}
+
+impl UserLookupQuery {
+    /// Check that exactly one identifier is given
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.external_user_id, &self.user_hash) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => Err("Give exactly one of external_user_id and user_hash".to_string()),
+        }
+    }
+}
+
+/// Request to merge a duplicate user into the user named in the path
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MergeUsers {
+    /// User to absorb; it is deleted once its history has moved over
+    pub source_user_id: Uuid,
+}
+
+/// What two users were found to have in common
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum LinkType {
+    /// The same email address
+    Email,
+    /// The same payment card, by token
+    Card,
+    /// The same device
+    Device,
+}
+
+/// Another user found transacting with the same email address, payment card, or device
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LinkedUser {
+    /// The linked user
+    pub user_id: Uuid,
+    /// The customer's own identifier for the linked user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_user_id: Option<String>,
+    /// What the users have in common
+    pub link_type: LinkType,
+    /// How many emails, cards, or devices the users have in common
+    pub shared_count: i32,
+    /// When identity resolution first linked the users
+    pub first_detected_at: DateTime<Utc>,
+    /// When identity resolution last confirmed the link
+    pub last_detected_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Users linked to a user by identity resolution
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "linked_users": [{
+        "user_id": "1b4e28ba-2fa1-41d2-883f-0016d3cca427",
+        "external_user_id": "customer-2210",
+        "link_type": "card",
+        "shared_count": 1,
+        "first_detected_at": "2025-06-12T03:00:00Z",
+        "last_detected_at": "2025-06-13T03:00:00Z",
+        "_links": {
+            "self": { "href": "/v1/users/1b4e28ba-2fa1-41d2-883f-0016d3cca427" }
+        }
+    }]
+}))]
+pub struct LinkedUserList {
+    /// Linked users, most recently confirmed first; a pair linked in several ways is listed
+    /// once per link type
+    pub linked_users: Vec<LinkedUser>,
+}
+
+/// Risk analysis of a user, built from their stored transactions and behavioral profile
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "user_id": "8f14e45f-ceea-467f-a0d5-2c1b2a3e4f50",
+    "risk_score": 12.5,
+    "risk_level": "low",
+    "velocity": {
+        "last_hour": 1,
+        "last_24_hours": 4,
+        "last_7_days": 6,
+        "last_30_days": 11,
+        "daily_average_30_days": 0.37,
+        "rejected_last_24_hours": 0,
+        "devices_last_24_hours": 2
+    },
+    "behavioral_patterns": {
+        "device_consistency": {
+            "distinct_devices": 2,
+            "primary_device_share": 0.82,
+            "new_devices_last_7_days": 1,
+            "distinct_ip_addresses": 2
+        },
+        "location": {
+            "billing_countries": [{ "country": "US", "transactions": 10 }],
+            "billing_shipping_mismatches": 0
+        },
+        "baseline": {
+            "transaction_count": 23,
+            "avg_order_amount": 54.2,
+            "usual_purchase_hours": [9, 10, 20],
+            "usual_local_purchase_hours": [10, 11, 21],
+            "usual_countries": ["US"],
+            "typical_device_count": 2,
+            "computed_at": "2025-06-13T02:00:00Z"
+        }
+    },
+    "indicators": ["velocity_spike"],
+    "analyzed_at": "2025-06-13T10:30:00Z",
+    "_links": {
+        "self": { "href": "/v1/users/8f14e45f-ceea-467f-a0d5-2c1b2a3e4f50/risk-analysis" }
+    }
+}))]
+pub struct UserRiskAnalysis {
+    /// User analyzed
+    pub user_id: Uuid,
+    /// Risk score of the user
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// How many transactions the user made over recent windows
+    pub velocity: VelocityAnalysis,
+    /// Devices and locations the user transacted from over the last 30 days
+    pub behavioral_patterns: BehavioralPatterns,
+    /// Departures from the user's usual behavior worth a closer look
+    pub indicators: Vec<RiskIndicator>,
+    /// When the analysis was computed
+    pub analyzed_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl UserRiskAnalysis {
+    /// Links of the risk analysis of the user with the given ID
+    pub fn links(user_id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/users/{user_id}/risk-analysis"))),
+            ..Links::default()
+        }
+    }
+}
+
+/// Transactions a user made over sliding windows ending at the time of analysis
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct VelocityAnalysis {
+    /// Transactions in the last hour
+    pub last_hour: i64,
+    /// Transactions in the last 24 hours
+    pub last_24_hours: i64,
+    /// Transactions in the last 7 days
+    pub last_7_days: i64,
+    /// Transactions in the last 30 days
+    pub last_30_days: i64,
+    /// Average transactions per day over the last 30 days
+    pub daily_average_30_days: f64,
+    /// Transactions rejected in the last 24 hours
+    pub rejected_last_24_hours: i64,
+    /// Distinct devices used in the last 24 hours
+    pub devices_last_24_hours: i64,
+}
+
+impl VelocityAnalysis {
+    /// Whether the last 24 hours saw at least `min_transactions` and several times the user's
+    /// 30-day daily average
+    pub fn is_spike(&self, min_transactions: i64, factor: f64) -> bool {
+        self.last_24_hours >= min_transactions
+            && self.last_24_hours as f64 > factor * self.daily_average_30_days
+    }
+}
+
+/// Device and location habits of a user
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct BehavioralPatterns {
+    /// How consistently the user transacts from the same devices
+    pub device_consistency: DeviceConsistency,
+    /// Where the user transacts from
+    pub location: LocationPatterns,
+    /// Long-horizon baseline from the feature store, once computed for the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<BehavioralBaseline>,
+}
+
+/// Devices behind a user's transactions over the last 30 days
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct DeviceConsistency {
+    /// Distinct devices used
+    pub distinct_devices: i64,
+    /// Share of device-linked transactions made from the most used device, from 0 to 1
+    pub primary_device_share: f64,
+    /// Devices the account first saw in the last 7 days
+    pub new_devices_last_7_days: i64,
+    /// Distinct IP addresses used
+    pub distinct_ip_addresses: i64,
+}
+
+/// Countries behind a user's transactions over the last 30 days
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct LocationPatterns {
+    /// Billing countries, most used first
+    pub billing_countries: Vec<CountryActivity>,
+    /// Transactions whose billing and shipping countries differ
+    pub billing_shipping_mismatches: i64,
+}
+
+/// Transactions from one country
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CountryActivity {
+    /// ISO 3166-1 alpha-2 country code
+    #[schema(example = "US")]
+    pub country: String,
+    /// Transactions from the country
+    pub transactions: i64,
+}
+
+/// Behavioral profile the feature store computed from the user's recent purchases
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BehavioralBaseline {
+    /// Purchases the profile was computed from
+    pub transaction_count: i64,
+    /// Mean order amount
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_order_amount: Option<f64>,
+    /// Hours of day (UTC, 0-23) in which the user habitually purchases
+    pub usual_purchase_hours: Vec<i32>,
+    /// Hours of day (0-23), local to where the user was, in which the user habitually purchases
+    pub usual_local_purchase_hours: Vec<i32>,
+    /// Billing countries the user habitually purchases from
+    pub usual_countries: Vec<String>,
+    /// Distinct devices in the profile window
+    pub typical_device_count: i32,
+    /// When the profile was computed
+    pub computed_at: DateTime<Utc>,
+}
+
+/// Departure from a user's usual behavior
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskIndicator {
+    /// Far more transactions in the last 24 hours than the user's daily average
+    VelocitySpike,
+    /// More devices in the last 30 days than the user's profile considers typical
+    ExcessiveDevices,
+    /// A device first seen in the last 7 days, for a user with older history
+    NewDevice,
+    /// A billing country outside the countries the user usually purchases from
+    UnusualCountry,
+    /// Billing and shipping countries that differ
+    CountryMismatch,
+}
+
+fn validate_flags(flags: Option<&[UserFlag]>) -> Result<(), String> {
+    let Some(flags) = flags else {
+        return Ok(());
+    };
+    if flags.len() > MAX_FLAGS {
+        return Err(format!("flags must hold at most {MAX_FLAGS} entries"));
+    }
+    for (i, flag) in flags.iter().enumerate() {
+        let flag_type = flag.flag_type.trim();
+        if flag_type.is_empty() || flag_type.chars().count() > MAX_FLAG_TYPE_LEN {
+            return Err(format!(
+                "flag type must be non-empty and at most {MAX_FLAG_TYPE_LEN} characters"
+            ));
+        }
+        if flags[..i]
+            .iter()
+            .any(|other| other.flag_type.trim() == flag_type)
+        {
+            return Err(format!("flags must not repeat the type {flag_type}"));
+        }
+        if flag
+            .reason
+            .as_ref()
+            .is_some_and(|reason| reason.chars().count() > MAX_FLAG_REASON_LEN)
+        {
+            return Err(format!(
+                "flag reason must be at most {MAX_FLAG_REASON_LEN} characters"
+            ));
+        }
+        if flag
+            .actor
+            .as_ref()
+            .is_some_and(|actor| actor.chars().count() > MAX_FLAG_ACTOR_LEN)
+        {
+            return Err(format!(
+                "flag actor must be at most {MAX_FLAG_ACTOR_LEN} characters"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_metadata(metadata: Option<&serde_json::Value>) -> Result<(), String> {
+    let Some(metadata) = metadata else {
+        return Ok(());
+    };
+    if !metadata.is_object() {
+        return Err("metadata must be a JSON object".to_string());
+    }
+    if metadata.to_string().len() > MAX_METADATA_BYTES {
+        return Err(format!(
+            "metadata must be at most {MAX_METADATA_BYTES} bytes"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_create_user_validation() {
+        let mut request: CreateUser =
+            serde_json::from_value(json!({ "external_user_id": "customer-1" })).unwrap();
+        assert!(request.validate().is_ok());
+
+        request.external_user_id = "  ".to_string();
+        assert!(request.validate().is_err());
+
+        request.external_user_id = "customer-1".to_string();
+        request.metadata = Some(json!(["not", "an", "object"]));
+        assert!(request.validate().is_err());
+
+        request.metadata = Some(json!({ "segment": "loyalty" }));
+        request.flags = Some(serde_json::from_value(json!([{ "type": " " }])).unwrap());
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn test_lookup_takes_exactly_one_identifier() {
+        let query = |external_user_id: Option<&str>, user_hash: Option<&str>| UserLookupQuery {
+            external_user_id: external_user_id.map(str::to_string),
+            user_hash: user_hash.map(str::to_string),
+        };
+        assert!(query(Some("customer-1"), None).validate().is_ok());
+        assert!(query(None, Some("098f6bcd")).validate().is_ok());
+        assert!(query(None, None).validate().is_err());
+        assert!(
+            query(Some("customer-1"), Some("098f6bcd"))
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_flags_are_unique_by_type_and_expire() {
+        let mut update: UserUpdate = serde_json::from_value(json!({
+            "flags": [
+                { "type": "watch", "actor": "analyst@example.com" },
+                { "type": "manual_review", "expires_at": "2025-07-13T00:00:00Z" }
+            ]
+        }))
+        .unwrap();
+        assert!(update.validate().is_ok());
+
+        let flags = update.flags.as_mut().unwrap();
+        let expires_at = flags[1].expires_at.unwrap();
+        assert!(flags[0].is_active(expires_at));
+        assert!(flags[1].is_active(expires_at - chrono::Duration::seconds(1)));
+        assert!(!flags[1].is_active(expires_at));
+
+        flags[1].flag_type = "watch".to_string();
+        assert!(update.validate().is_err());
+        assert!(serde_json::from_value::<UserUpdate>(json!({ "flags": ["watch"] })).is_err());
+    }
+
+    #[test]
+    fn test_import_user_validation() {
+        let mut user: ImportUser = serde_json::from_value(json!({
+            "external_user_id": "customer-1",
+            "total_transactions": 10,
+            "successful_transactions": 9,
+            "failed_transactions": 1,
+            "chargeback_count": 2
+        }))
+        .unwrap();
+        assert!(user.validate().is_ok());
+
+        user.failed_transactions = Some(2);
+        assert!(user.validate().is_err());
+
+        user.failed_transactions = Some(1);
+        user.chargeback_count = Some(-1);
+        assert!(user.validate().is_err());
+
+        user.chargeback_count = None;
+        user.first_transaction_at = Some(Utc::now());
+        user.last_transaction_at = Some(Utc::now() - chrono::Duration::days(1));
+        assert!(user.validate().is_err());
+    }
+
+    #[test]
+    fn test_user_update_is_empty() {
+        assert!(UserUpdate::default().is_empty());
+        let update: UserUpdate = serde_json::from_value(json!({ "flags": [] })).unwrap();
+        assert!(!update.is_empty());
+        assert!(serde_json::from_value::<UserUpdate>(json!({ "risk_score": 1 })).is_err());
+    }
+}
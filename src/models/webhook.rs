@@ -0,0 +1,423 @@
+//! Webhook endpoints, the signing secrets their deliveries carry, and the log of deliveries
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{
+    common::{Link, Links, Pagination},
+    transaction::{Disposition, validate_delivery_url},
+};
+use crate::outbox::EVENT_TYPES;
+
+/// Longest description of a webhook, in characters
+pub const MAX_DESCRIPTION_CHARS: usize = 255;
+/// Longest URL of a webhook, in characters
+pub const MAX_URL_CHARS: usize = 2048;
+/// Hours a rotated-out secret keeps signing deliveries when the rotation does not say
+pub const DEFAULT_SECRET_OVERLAP_HOURS: i64 = 24;
+/// Longest a rotated-out secret may keep signing deliveries, in hours
+pub const MAX_SECRET_OVERLAP_HOURS: i64 = 7 * 24;
+/// Most shops a webhook's filters may list
+pub const MAX_FILTER_SHOP_IDS: usize = 50;
+/// Longest shop identifier in a webhook's filters, in characters
+const MAX_SHOP_ID_CHARS: usize = 255;
+
+/// An endpoint the account's events are POSTed to
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Webhook {
+    /// Unique webhook identifier
+    pub id: Uuid,
+    /// HTTP(S) URL events are POSTed to
+    #[schema(example = "https://merchant.example.com/fusegu/events")]
+    pub url: String,
+    /// Note on what the endpoint is for
+    #[schema(example = "Order pipeline")]
+    pub description: Option<String>,
+    /// Event types delivered to the endpoint; empty for every event
+    #[schema(example = json!(["transaction.scored", "case.resolved"]))]
+    pub events: Vec<String>,
+    /// Conditions events must also meet to be delivered
+    pub filters: WebhookFilters,
+    /// Whether events are delivered to the endpoint
+    pub active: bool,
+    /// Signing secret; only returned when the webhook is created or its secret rotated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "whsec_3f9c2b7e41d84a0c9e6f1d2a5b8c7e0f")]
+    pub secret: Option<String>,
+    /// When the secret was last rotated
+    pub secret_rotated_at: Option<DateTime<Utc>>,
+    /// Until when deliveries are also signed with the secret the last rotation replaced
+    pub previous_secret_expires_at: Option<DateTime<Utc>>,
+    /// When an event was last delivered to the endpoint, successfully or not
+    pub last_triggered: Option<DateTime<Utc>>,
+    /// Deliveries the endpoint accepted
+    #[schema(example = 1280)]
+    pub success_count: i32,
+    /// Deliveries that failed or the endpoint refused
+    #[schema(example = 3)]
+    pub failure_count: i32,
+    /// When the webhook was created
+    pub created_at: DateTime<Utc>,
+    /// When the webhook last changed
+    pub updated_at: DateTime<Utc>,
+    /// Related resources
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+impl Webhook {
+    /// Links of the webhook with the given ID
+    pub fn links(webhook_id: Uuid) -> Links {
+        Links {
+            self_link: Some(Link::new(format!("/v1/webhooks/{webhook_id}"))),
+            ..Links::default()
+        }
+    }
+}
+
+/// An account's webhooks, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookList {
+    /// The webhooks
+    pub webhooks: Vec<Webhook>,
+}
+
+/// Request to create or replace a webhook
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookRequest {
+    /// HTTP(S) URL events are POSTed to; may not name a private or reserved address
+    #[schema(example = "https://merchant.example.com/fusegu/events")]
+    pub url: String,
+    /// Note on what the endpoint is for
+    #[schema(example = "Order pipeline")]
+    pub description: Option<String>,
+    /// Event types to deliver; empty for every event
+    #[serde(default)]
+    #[schema(example = json!(["transaction.scored", "case.resolved"]))]
+    pub events: Vec<String>,
+    /// Conditions events must also meet to be delivered; none by default
+    #[serde(default)]
+    pub filters: WebhookFilters,
+    /// Whether events are delivered to the endpoint
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl WebhookRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        if self.url.chars().count() > MAX_URL_CHARS {
+            return Err(format!("url must be at most {MAX_URL_CHARS} characters"));
+        }
+        validate_delivery_url("url", &self.url)?;
+        if let Some(description) = &self.description
+            && description.chars().count() > MAX_DESCRIPTION_CHARS
+        {
+            return Err(format!(
+                "description must be at most {MAX_DESCRIPTION_CHARS} characters"
+            ));
+        }
+        if let Some(unknown) = self
+            .events
+            .iter()
+            .find(|event| !EVENT_TYPES.contains(&event.as_str()))
+        {
+            return Err(format!("events contains unknown event type {unknown}"));
+        }
+        self.filters.validate()
+    }
+}
+
+/// Conditions an event must meet, besides its type, to be delivered to a webhook
+///
+/// Conditions are checked against the event's payload; `transaction.scored` events carry
+/// all three fields. Every condition given must hold; an empty list or a missing bound matches
+/// any event, and an event without the field a condition checks never matches it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookFilters {
+    /// Dispositions the event's transaction may have
+    #[serde(default)]
+    #[schema(example = json!(["reject"]))]
+    pub dispositions: Vec<Disposition>,
+    /// Smallest order amount, in the order's currency
+    #[schema(example = 1000.0)]
+    pub min_amount: Option<f64>,
+    /// Largest order amount, in the order's currency
+    pub max_amount: Option<f64>,
+    /// Shops the event's transaction may belong to
+    #[serde(default)]
+    #[schema(example = json!(["shop-eu-1"]))]
+    pub shop_ids: Vec<String>,
+}
+
+impl WebhookFilters {
+    fn validate(&self) -> Result<(), String> {
+        for (field, amount) in [
+            ("min_amount", self.min_amount),
+            ("max_amount", self.max_amount),
+        ] {
+            if amount.is_some_and(|a| !a.is_finite() || a < 0.0) {
+                return Err(format!("filters.{field} must be a non-negative number"));
+            }
+        }
+        if self
+            .min_amount
+            .zip(self.max_amount)
+            .is_some_and(|(min, max)| min > max)
+        {
+            return Err("filters.min_amount must not exceed filters.max_amount".to_string());
+        }
+        if self.shop_ids.len() > MAX_FILTER_SHOP_IDS {
+            return Err(format!(
+                "filters.shop_ids must list at most {MAX_FILTER_SHOP_IDS} shops"
+            ));
+        }
+        if self
+            .shop_ids
+            .iter()
+            .any(|shop_id| shop_id.is_empty() || shop_id.chars().count() > MAX_SHOP_ID_CHARS)
+        {
+            return Err(format!(
+                "filters.shop_ids must be non-empty identifiers of at most {MAX_SHOP_ID_CHARS} characters"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether an event with `payload` meets every condition
+    pub fn matches(&self, payload: &serde_json::Value) -> bool {
+        if !self.dispositions.is_empty() {
+            let disposition = payload
+                .get("disposition")
+                .and_then(|value| Disposition::deserialize(value).ok());
+            if !disposition.is_some_and(|disposition| self.dispositions.contains(&disposition)) {
+                return false;
+            }
+        }
+        if self.min_amount.is_some() || self.max_amount.is_some() {
+            let Some(amount) = payload.get("amount").and_then(serde_json::Value::as_f64) else {
+                return false;
+            };
+            if self.min_amount.is_some_and(|min| amount < min)
+                || self.max_amount.is_some_and(|max| amount > max)
+            {
+                return false;
+            }
+        }
+        if !self.shop_ids.is_empty() {
+            let shop_id = payload.get("shop_id").and_then(serde_json::Value::as_str);
+            if !shop_id.is_some_and(|shop_id| self.shop_ids.iter().any(|id| id == shop_id)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Request to replace a webhook's signing secret
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SecretRotationRequest {
+    /// Hours the replaced secret keeps signing deliveries alongside the new one, so the
+    /// endpoint can switch over without rejecting events; 0 retires it at once. Defaults to 24
+    #[schema(minimum = 0, maximum = 168, example = 24)]
+    pub overlap_hours: Option<i64>,
+}
+
+impl SecretRotationRequest {
+    /// Check field values, returning a description of the first problem found
+    pub fn validate(&self) -> Result<(), String> {
+        match self.overlap_hours {
+            Some(hours) if !(0..=MAX_SECRET_OVERLAP_HOURS).contains(&hours) => Err(format!(
+                "overlap_hours must be between 0 and {MAX_SECRET_OVERLAP_HOURS}"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Hours the replaced secret keeps signing
+    pub fn overlap_hours(&self) -> i64 {
+        self.overlap_hours.unwrap_or(DEFAULT_SECRET_OVERLAP_HOURS)
+    }
+}
+
+/// How an attempt at delivering an event went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// The endpoint answered with a 2xx status
+    Succeeded,
+    /// The endpoint could not be reached, timed out, or answered with another status
+    Failed,
+}
+
+/// One attempt at delivering an event to a webhook
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    /// Unique delivery identifier
+    pub id: Uuid,
+    /// Webhook the event was POSTed to
+    pub webhook_id: Uuid,
+    /// Event delivered, as in the body's `id`
+    pub event_id: Uuid,
+    /// Type of the event delivered
+    #[schema(example = "transaction.scored")]
+    pub event_type: String,
+    /// How the attempt went
+    pub status: DeliveryStatus,
+    /// HTTP status the endpoint answered with; absent when it could not be reached
+    #[schema(example = 503)]
+    pub response_status: Option<i32>,
+    /// Milliseconds from sending the event to the endpoint's answer, or to giving up
+    #[schema(example = 184)]
+    pub latency_ms: i32,
+    /// What went wrong, for failed attempts
+    #[schema(example = "HTTP status server error (503 Service Unavailable)")]
+    pub error: Option<String>,
+    /// Delivery this attempt redrove, when the account asked for it
+    pub redrive_of: Option<Uuid>,
+    /// When the attempt was made
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// Page of a webhook's deliveries, newest first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDeliveryList {
+    /// Deliveries on this page
+    pub deliveries: Vec<WebhookDelivery>,
+    /// Pagination metadata
+    pub pagination: Pagination,
+    /// Navigation links
+    #[serde(rename = "_links")]
+    pub links: Links,
+}
+
+/// Query parameters for listing a webhook's deliveries
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListDeliveriesQuery {
+    /// Maximum number of deliveries to return (1-100)
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    pub limit: Option<i64>,
+    /// Number of deliveries to skip
+    #[param(minimum = 0, default = 0)]
+    pub offset: Option<i64>,
+    /// Filter by how the attempt went, e.g. `failed` for the deliveries to redrive
+    pub status: Option<DeliveryStatus>,
+    /// Filter by event
+    pub event_id: Option<Uuid>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_requests_are_validated() {
+        let request = |url: &str, events: &[&str]| WebhookRequest {
+            url: url.to_string(),
+            description: None,
+            events: events.iter().map(|event| event.to_string()).collect(),
+            filters: WebhookFilters::default(),
+            active: true,
+        };
+        assert!(
+            request("https://merchant.example.com/hook", &[])
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            request("https://merchant.example.com/hook", &["case.resolved"])
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            request("https://merchant.example.com/hook", &["case.opened"])
+                .validate()
+                .is_err()
+        );
+        assert!(request("http://127.0.0.1/hook", &[]).validate().is_err());
+        assert!(request("merchant.example.com", &[]).validate().is_err());
+        let filtered = |filters| WebhookRequest {
+            filters,
+            ..request("https://merchant.example.com/hook", &[])
+        };
+        assert!(
+            filtered(WebhookFilters {
+                min_amount: Some(20.0),
+                max_amount: Some(10.0),
+                ..WebhookFilters::default()
+            })
+            .validate()
+            .is_err()
+        );
+        assert!(
+            filtered(WebhookFilters {
+                shop_ids: vec![String::new()],
+                ..WebhookFilters::default()
+            })
+            .validate()
+            .is_err()
+        );
+
+        let rotation = |overlap_hours| SecretRotationRequest { overlap_hours };
+        assert!(rotation(None).validate().is_ok());
+        assert_eq!(rotation(None).overlap_hours(), DEFAULT_SECRET_OVERLAP_HOURS);
+        assert!(rotation(Some(0)).validate().is_ok());
+        assert!(rotation(Some(-1)).validate().is_err());
+        assert!(
+            rotation(Some(MAX_SECRET_OVERLAP_HOURS + 1))
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_webhook_filters_match_event_payloads() {
+        let payload = serde_json::json!({
+            "disposition": "reject",
+            "amount": 1500.0,
+            "shop_id": "shop-eu-1",
+        });
+        assert!(WebhookFilters::default().matches(&payload));
+        assert!(WebhookFilters::default().matches(&serde_json::json!({})));
+
+        let filters = WebhookFilters {
+            dispositions: vec![Disposition::Reject],
+            min_amount: Some(1000.0),
+            max_amount: None,
+            shop_ids: vec!["shop-eu-1".to_string()],
+        };
+        assert!(filters.matches(&payload));
+        let low_value = serde_json::json!({
+            "disposition": "reject",
+            "amount": 999.99,
+            "shop_id": "shop-eu-1",
+        });
+        assert!(!filters.matches(&low_value));
+        let accepted = serde_json::json!({
+            "disposition": "accept",
+            "amount": 1500.0,
+            "shop_id": "shop-eu-1",
+        });
+        assert!(!filters.matches(&accepted));
+        let other_shop = serde_json::json!({
+            "disposition": "reject",
+            "amount": 1500.0,
+            "shop_id": "shop-us-1",
+        });
+        assert!(!filters.matches(&other_shop));
+
+        // Events without the fields a condition checks never meet it
+        assert!(!filters.matches(&serde_json::json!({ "case_id": "4f1c" })));
+    }
+}
@@ -0,0 +1,67 @@
+//! Delivery of finished background jobs to their callback URLs
+
+use std::{sync::Arc, time::Duration};
+
+use reqwest::redirect::Policy;
+
+use super::{EventPublisher, JOB_COMPLETED, JOB_FAILED, OutboxRecord, resolver::PublicResolver};
+use crate::config::JobsConfig;
+
+/// Header naming the event type of a callback
+pub const EVENT_HEADER: &str = "X-Fusegu-Event";
+/// Header carrying the outbox event ID, for deduplicating redelivered callbacks
+pub const EVENT_ID_HEADER: &str = "X-Fusegu-Event-Id";
+
+/// Publisher that POSTs finished jobs to the callback URL they were queued with, then hands
+/// every event on to `inner`
+///
+/// A callback that fails or answers with a non-2xx status fails the delivery, so the
+/// dispatcher retries it with backoff. Redirects are not followed, since the callback URL was
+/// only checked as submitted, and hostnames resolving to reserved addresses are refused.
+#[derive(Debug, Clone)]
+pub struct CallbackPublisher<P> {
+    inner: P,
+    http: reqwest::Client,
+}
+
+impl<P: EventPublisher> CallbackPublisher<P> {
+    /// Wrap `inner`, giving callback endpoints the configured time to respond
+    pub fn new(inner: P, config: &JobsConfig) -> reqwest::Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.callback_timeout_seconds))
+            .redirect(Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()?;
+        Ok(Self { inner, http })
+    }
+
+    async fn deliver(&self, event: &OutboxRecord) -> anyhow::Result<()> {
+        let Some(callback_url) = event.payload.0.get("callback_url").and_then(|v| v.as_str())
+        else {
+            return Ok(());
+        };
+        self.http
+            .post(callback_url)
+            .header(EVENT_HEADER, event.event_type.as_str())
+            .header(EVENT_ID_HEADER, event.id.to_string())
+            .json(&event.payload.0)
+            .send()
+            .await?
+            .error_for_status()?;
+        tracing::info!(
+            event_id = %event.id,
+            job_id = %event.aggregate_id,
+            "Job callback delivered"
+        );
+        Ok(())
+    }
+}
+
+impl<P: EventPublisher> EventPublisher for CallbackPublisher<P> {
+    async fn publish(&self, event: &OutboxRecord) -> anyhow::Result<()> {
+        if matches!(event.event_type.as_str(), JOB_COMPLETED | JOB_FAILED) {
+            self.deliver(event).await?;
+        }
+        self.inner.publish(event).await
+    }
+}
@@ -0,0 +1,232 @@
+//! Outbox sink that records events in ClickHouse for analytics
+
+use super::{
+    CASE_RESOLVED, CaseResolved, DISPUTE_UPDATED, EventPublisher, OutboxRecord,
+    TRANSACTION_REPORTED, TRANSACTION_SCORED, TransactionReported, TransactionScored,
+};
+use crate::{
+    database::clickhouse::{
+        CASE_DECISIONS_TABLE, CaseDecisionRow, ClickHouseClient, DISPUTES_TABLE, DisputeRow,
+        TRANSACTION_EVENTS_TABLE, TRANSACTION_OUTCOMES_TABLE, TransactionEventRow,
+        TransactionOutcomeRow,
+    },
+    models::dispute::Dispute,
+};
+
+/// Publisher that appends scored transactions, their reported outcomes and disputes, and review
+/// case decisions to the ClickHouse event store
+///
+/// Other event types carry nothing analytics needs and are acknowledged without a write.
+#[derive(Debug, Clone)]
+pub struct ClickHousePublisher {
+    client: ClickHouseClient,
+}
+
+impl ClickHousePublisher {
+    /// Create a publisher writing through `client`
+    pub fn new(client: ClickHouseClient) -> Self {
+        Self { client }
+    }
+}
+
+impl EventPublisher for ClickHousePublisher {
+    async fn publish(&self, event: &OutboxRecord) -> anyhow::Result<()> {
+        match event.event_type.as_str() {
+            TRANSACTION_SCORED => {
+                let row = transaction_event_row(event)?;
+                self.client.insert(TRANSACTION_EVENTS_TABLE, &[row]).await?;
+            },
+            TRANSACTION_REPORTED => {
+                let row = transaction_outcome_row(event)?;
+                self.client
+                    .insert(TRANSACTION_OUTCOMES_TABLE, &[row])
+                    .await?;
+            },
+            CASE_RESOLVED => {
+                let row = case_decision_row(event)?;
+                self.client.insert(CASE_DECISIONS_TABLE, &[row]).await?;
+            },
+            DISPUTE_UPDATED => {
+                let row = dispute_row(event)?;
+                self.client.insert(DISPUTES_TABLE, &[row]).await?;
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+}
+
+fn transaction_event_row(event: &OutboxRecord) -> serde_json::Result<TransactionEventRow> {
+    let scored: TransactionScored = serde_json::from_value(event.payload.0.clone())?;
+    let transaction = scored.transaction;
+    Ok(TransactionEventRow {
+        transaction_id: transaction.id,
+        account_id: event.account_id,
+        user_id: transaction.user_id,
+        event_type: transaction.event_type,
+        risk_score: transaction.risk_score,
+        risk_level: transaction.risk_level,
+        disposition: transaction.disposition,
+        shop_id: scored.shop_id,
+        rule_codes: scored.rule_codes,
+        event_time: scored.event_time,
+        created_at: transaction.created_at,
+        ip_address: scored.ip_address,
+        device_id: scored.device_id,
+        email_domain: scored.email_domain,
+        card_bin: scored.card_bin,
+        features: scored
+            .features
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?,
+        amount: scored.amount,
+        currency: scored.currency,
+        original_transaction_id: transaction.original_transaction_id,
+    })
+}
+
+fn transaction_outcome_row(event: &OutboxRecord) -> serde_json::Result<TransactionOutcomeRow> {
+    let reported: TransactionReported = serde_json::from_value(event.payload.0.clone())?;
+    Ok(TransactionOutcomeRow {
+        transaction_id: reported.transaction_id,
+        account_id: event.account_id,
+        tag: reported.tag,
+        occurred_at: reported.occurred_at,
+        reported_at: event.created_at,
+    })
+}
+
+fn case_decision_row(event: &OutboxRecord) -> serde_json::Result<CaseDecisionRow> {
+    let resolved: CaseResolved = serde_json::from_value(event.payload.0.clone())?;
+    Ok(CaseDecisionRow {
+        case_id: resolved.case_id,
+        kind: resolved.kind,
+        account_id: event.account_id,
+        transaction_id: resolved.transaction_id,
+        reviewer: resolved.reviewer,
+        decision: resolved.decision,
+        resolved_at: resolved.resolved_at,
+    })
+}
+
+fn dispute_row(event: &OutboxRecord) -> serde_json::Result<DisputeRow> {
+    let dispute: Dispute = serde_json::from_value(event.payload.0.clone())?;
+    Ok(DisputeRow {
+        dispute_id: dispute.id,
+        account_id: event.account_id,
+        transaction_id: dispute.transaction_id,
+        status: dispute.status,
+        amount: dispute.amount,
+        currency: dispute.currency,
+        received_at: dispute.received_at,
+        resolved_at: dispute.resolved_at,
+        updated_at: dispute.updated_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use sqlx::types::Json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::models::{
+        case::{CaseDecision, CaseKind},
+        transaction::{Disposition, ReportTag, RiskLevel},
+    };
+
+    #[test]
+    fn test_transaction_event_row_from_payload() {
+        let account_id = Uuid::new_v4();
+        let transaction_id = Uuid::new_v4();
+        let event = OutboxRecord {
+            id: Uuid::new_v4(),
+            account_id,
+            event_type: TRANSACTION_SCORED.to_string(),
+            aggregate_id: transaction_id,
+            payload: Json(serde_json::json!({
+                "id": transaction_id,
+                "risk_score": 61.5,
+                "risk_level": "high",
+                "disposition": "reject",
+                "event_type": "purchase",
+                "created_at": "2025-06-13T10:30:01Z",
+                "_links": { "self": { "href": format!("/v1/transactions/{transaction_id}") } },
+                "shop_id": "shop_main",
+                "event_time": "2025-06-13T10:30:00Z",
+                "rule_codes": ["CVV_MISMATCH", "LARGE_AMOUNT"]
+            })),
+            attempts: 0,
+            created_at: Utc::now(),
+        };
+
+        let row = transaction_event_row(&event).unwrap();
+        assert_eq!(row.transaction_id, transaction_id);
+        assert_eq!(row.account_id, account_id);
+        assert_eq!(row.user_id, None);
+        assert_eq!(row.risk_level, RiskLevel::High);
+        assert_eq!(row.disposition, Disposition::Reject);
+        assert_eq!(row.shop_id.as_deref(), Some("shop_main"));
+        assert_eq!(row.rule_codes, vec!["CVV_MISMATCH", "LARGE_AMOUNT"]);
+        // Events written before entities were recorded still load
+        assert_eq!(row.ip_address, None);
+        assert_eq!(row.card_bin, None);
+        assert_eq!(row.features, None);
+        assert_eq!(row.amount, None);
+    }
+
+    #[test]
+    fn test_transaction_outcome_row_from_payload() {
+        let transaction_id = Uuid::new_v4();
+        let event = OutboxRecord {
+            id: Uuid::new_v4(),
+            account_id: Uuid::new_v4(),
+            event_type: TRANSACTION_REPORTED.to_string(),
+            aggregate_id: transaction_id,
+            payload: Json(serde_json::json!({
+                "transaction_id": transaction_id,
+                "tag": "chargeback",
+                "chargeback_code": "10.4",
+                "occurred_at": "2025-06-20T08:00:00Z"
+            })),
+            attempts: 0,
+            created_at: Utc::now(),
+        };
+
+        let row = transaction_outcome_row(&event).unwrap();
+        assert_eq!(row.transaction_id, transaction_id);
+        assert_eq!(row.account_id, event.account_id);
+        assert_eq!(row.tag, ReportTag::Chargeback);
+        assert_eq!(row.reported_at, event.created_at);
+    }
+
+    #[test]
+    fn test_case_decision_row_from_payload() {
+        let case_id = Uuid::new_v4();
+        let event = OutboxRecord {
+            id: Uuid::new_v4(),
+            account_id: Uuid::new_v4(),
+            event_type: CASE_RESOLVED.to_string(),
+            aggregate_id: case_id,
+            payload: Json(serde_json::json!({
+                "case_id": case_id,
+                "transaction_id": Uuid::new_v4(),
+                "reviewer": "analyst@example.com",
+                "decision": "approve",
+                "resolved_at": "2025-06-14T09:15:00Z"
+            })),
+            attempts: 0,
+            created_at: Utc::now(),
+        };
+
+        let row = case_decision_row(&event).unwrap();
+        assert_eq!(row.case_id, case_id);
+        assert_eq!(row.account_id, event.account_id);
+        assert_eq!(row.reviewer, "analyst@example.com");
+        assert_eq!(row.decision, CaseDecision::Approve);
+        // Decisions published before appeals existed were all reviews
+        assert_eq!(row.kind, CaseKind::Review);
+    }
+}
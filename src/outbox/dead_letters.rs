@@ -0,0 +1,62 @@
+//! Monitoring of dead letters, the events the dispatcher gave up on
+//!
+//! The number of dead letters across every account is logged as the `dead_letters` field at
+//! each check, for log-based metrics to chart. A count that grows while at or above the
+//! configured threshold is logged as an error, for alerting: something downstream keeps
+//! refusing events and they need redriving or purging once it is fixed.
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{config::OutboxConfig, database::repositories::OutboxRepo};
+
+/// Spawn a background task that periodically counts dead letters
+pub fn spawn_dead_letter_monitor(pool: PgPool, config: OutboxConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = std::time::Duration::from_secs(config.dead_letter_check_interval_seconds);
+        let mut previous = None;
+        loop {
+            match OutboxRepo::count_all_dead_letters(&pool).await {
+                Ok(dead_letters) => {
+                    if is_growing(previous, dead_letters, config.dead_letter_alert_threshold) {
+                        tracing::error!(
+                            dead_letters,
+                            added = dead_letters - previous.unwrap_or(0),
+                            "Outbox dead letters are growing"
+                        );
+                    } else {
+                        tracing::info!(dead_letters, "Outbox dead letters counted");
+                    }
+                    previous = Some(dead_letters);
+                },
+                Err(e) => tracing::error!(error = %e, "Dead letter count failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Whether `current` dead letters, up from `previous` at the last check, call for an alert
+///
+/// The first count after startup has nothing to compare with and alerts only if it is at or
+/// above the threshold, so a backlog left from before a restart is not missed.
+fn is_growing(previous: Option<i64>, current: i64, threshold: i64) -> bool {
+    current >= threshold && previous.is_none_or(|previous| current > previous)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_growth_is_alerted_above_the_threshold() {
+        assert!(!is_growing(None, 0, 1));
+        assert!(is_growing(None, 3, 1));
+        assert!(is_growing(Some(3), 4, 1));
+        assert!(!is_growing(Some(4), 4, 1));
+        assert!(!is_growing(Some(4), 2, 1));
+        // Below the threshold, growth is only logged
+        assert!(!is_growing(Some(2), 5, 10));
+        assert!(is_growing(Some(9), 10, 10));
+    }
+}
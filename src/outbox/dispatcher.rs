@@ -0,0 +1,82 @@
+//! Background delivery of outbox events
+
+use std::time::Duration;
+
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use super::EventPublisher;
+use crate::{config::OutboxConfig, database::repositories::OutboxRepo, utils::backoff};
+
+/// Delay before the first retry of a failed delivery
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Longest delay between retries
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Spawn a background task that keeps delivering pending outbox events
+pub fn spawn_outbox_dispatcher<P: EventPublisher>(
+    pool: PgPool,
+    publisher: P,
+    config: OutboxConfig,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let idle = Duration::from_millis(config.poll_interval_ms);
+        loop {
+            match dispatch_batch(&pool, &publisher, &config).await {
+                // A full batch suggests a backlog, so poll again straight away
+                Ok(delivered) if delivered as i64 >= config.batch_size => continue,
+                Ok(_) => {},
+                Err(e) => tracing::error!(error = %e, "Outbox dispatch failed"),
+            }
+            tokio::time::sleep(idle).await;
+        }
+    })
+}
+
+/// Deliver one batch of due events, returning how many were attempted
+///
+/// Events stay locked until the batch commits. An event is only marked published after the
+/// publisher accepts it, so a crash mid-batch leads to redelivery rather than loss. One that
+/// fails its last attempt becomes a dead letter, kept until it is redriven or purged.
+pub async fn dispatch_batch<P: EventPublisher>(
+    pool: &PgPool,
+    publisher: &P,
+    config: &OutboxConfig,
+) -> sqlx::Result<usize> {
+    let mut tx = pool.begin().await?;
+    let events = OutboxRepo::claim_due(&mut *tx, config.batch_size, config.max_attempts).await?;
+
+    for event in &events {
+        match publisher.publish(event).await {
+            Ok(()) => OutboxRepo::mark_published(&mut *tx, event.id).await?,
+            Err(e) => {
+                let attempt = event.attempts + 1;
+                if attempt >= config.max_attempts {
+                    tracing::error!(
+                        event_id = %event.id,
+                        event_type = %event.event_type,
+                        attempts = attempt,
+                        error = %e,
+                        "Outbox event dead-lettered after repeated failures"
+                    );
+                    OutboxRepo::mark_dead_lettered(&mut *tx, event.id, &e.to_string()).await?;
+                } else {
+                    tracing::warn!(
+                        event_id = %event.id,
+                        event_type = %event.event_type,
+                        attempts = attempt,
+                        error = %e,
+                        "Outbox event delivery failed; will retry"
+                    );
+                    let retry_at = Utc::now()
+                        + backoff(attempt.max(1) as u32, BASE_RETRY_DELAY, MAX_RETRY_DELAY);
+                    OutboxRepo::mark_failed(&mut *tx, event.id, &e.to_string(), retry_at).await?;
+                }
+            },
+        }
+    }
+
+    tx.commit().await?;
+    Ok(events.len())
+}
@@ -0,0 +1,306 @@
+//! Transactional outbox for downstream events
+//!
+//! Events are written to `outbox_events` inside the same database transaction as the change
+//! they describe, so an event exists if and only if the change was committed. A background
+//! dispatcher then delivers them to an [`EventPublisher`] with at-least-once semantics:
+//! consumers must tolerate duplicates and can deduplicate on the event ID.
+
+pub mod callbacks;
+pub mod clickhouse;
+pub mod dead_letters;
+pub mod dispatcher;
+pub mod nats;
+pub mod notifications;
+pub mod resolver;
+pub mod webhooks;
+
+use std::future::Future;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use crate::database::repositories::OutboxRecord;
+use crate::{
+    database::repositories::TransactionRecord,
+    features::FeatureSnapshot,
+    models::{
+        account::AccountStatus,
+        case::{CaseDecision, CaseKind},
+        organization::MemberRole,
+        transaction::{ReportTag, TransactionRequest, TransactionResponse},
+    },
+    scoring::RiskAssessment,
+};
+
+/// Emitted when a transaction has been scored and stored
+pub const TRANSACTION_SCORED: &str = "transaction.scored";
+
+/// Emitted when a customer reports the outcome of a transaction (chargeback, false positive, ...)
+pub const TRANSACTION_REPORTED: &str = "transaction.reported";
+
+/// Emitted when a reviewer resolves a review case
+pub const CASE_RESOLVED: &str = "case.resolved";
+
+/// Emitted when a dispute is received or moves to another stage; the payload is the dispute
+pub const DISPUTE_UPDATED: &str = "dispute.updated";
+
+/// Emitted when a background job, such as one scoring a transaction submitted with
+/// `mode=async`, has completed; the payload is the job
+pub const JOB_COMPLETED: &str = "job.completed";
+
+/// Emitted when a background job has failed; the payload is the job
+pub const JOB_FAILED: &str = "job.failed";
+
+/// Emitted when a duplicate user is merged into another
+pub const USER_MERGED: &str = "user.merged";
+
+/// Emitted when a user import has processed every user; the payload is the import
+pub const USER_IMPORT_COMPLETED: &str = "user_import.completed";
+
+/// Emitted when a list import has processed every entry; the payload is the import
+pub const LIST_IMPORT_COMPLETED: &str = "list_import.completed";
+
+/// Emitted when anomaly detection flags a spike in an account's fraud metrics
+pub const ANOMALY_DETECTED: &str = "analytics.anomaly_detected";
+
+/// Emitted when an API key is about to expire and should be rotated
+pub const API_KEY_EXPIRING: &str = "api_key.expiring";
+
+/// Emitted when an account changes its settings; the payload is the updated account
+pub const ACCOUNT_UPDATED: &str = "account.updated";
+
+/// Emitted when an account is suspended, closed, or reactivated
+pub const ACCOUNT_STATUS_CHANGED: &str = "account.status_changed";
+
+/// Emitted to an account invited to an organization
+pub const ORGANIZATION_MEMBER_INVITED: &str = "organization.member_invited";
+
+/// Emitted to an organization's billing account when an invited account joins
+pub const ORGANIZATION_MEMBER_JOINED: &str = "organization.member_joined";
+
+/// Emitted once per billing cycle when an account uses up its monthly quota
+pub const QUOTA_EXHAUSTED: &str = "account.quota_exhausted";
+
+/// Emitted once when an unresolved review case passes its queue's SLA
+pub const CASE_SLA_BREACHED: &str = "case.sla_breached";
+
+/// Every event type, as webhooks may subscribe to them
+pub const EVENT_TYPES: &[&str] = &[
+    TRANSACTION_SCORED,
+    TRANSACTION_REPORTED,
+    CASE_RESOLVED,
+    DISPUTE_UPDATED,
+    JOB_COMPLETED,
+    JOB_FAILED,
+    USER_MERGED,
+    USER_IMPORT_COMPLETED,
+    LIST_IMPORT_COMPLETED,
+    ANOMALY_DETECTED,
+    API_KEY_EXPIRING,
+    ACCOUNT_UPDATED,
+    ACCOUNT_STATUS_CHANGED,
+    ORGANIZATION_MEMBER_INVITED,
+    ORGANIZATION_MEMBER_JOINED,
+    QUOTA_EXHAUSTED,
+    CASE_SLA_BREACHED,
+];
+
+/// High-severity event types, as notification channels may announce them
+pub const NOTIFICATION_EVENT_TYPES: &[&str] =
+    &[ANOMALY_DETECTED, QUOTA_EXHAUSTED, CASE_SLA_BREACHED];
+
+/// Payload of [`TRANSACTION_SCORED`] events
+///
+/// The API representation of the transaction plus the context analytics consumers need.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionScored {
+    /// The stored assessment, as returned by the API
+    #[serde(flatten)]
+    pub transaction: TransactionResponse,
+    /// Shop or merchant identifier
+    pub shop_id: Option<String>,
+    /// Order amount, in the order's currency
+    pub amount: Option<f64>,
+    /// ISO 4217 currency code of the order
+    pub currency: Option<String>,
+    /// When the scored event happened
+    pub event_time: DateTime<Utc>,
+    /// Codes of the rules that fired
+    pub rule_codes: Vec<String>,
+    /// IP address the transaction came from
+    pub ip_address: Option<String>,
+    /// Device the transaction came from, if it was resolved
+    pub device_id: Option<Uuid>,
+    /// Lowercased email domain
+    pub email_domain: Option<String>,
+    /// Issuer identification number (BIN) of the payment card
+    pub card_bin: Option<String>,
+    /// Inputs the transaction was scored on
+    pub features: Option<FeatureSnapshot>,
+}
+
+impl TransactionScored {
+    /// Describe a freshly stored transaction, how it was scored, and the entities it involved
+    pub fn new(
+        record: TransactionRecord,
+        assessment: &RiskAssessment,
+        request: &TransactionRequest,
+        device_id: Option<Uuid>,
+    ) -> Self {
+        Self {
+            features: Some(assessment.features.clone()),
+            ip_address: Some(request.device.ip_address.clone()),
+            device_id,
+            email_domain: request
+                .email
+                .as_ref()
+                .and_then(|email| email.resolved_domain())
+                .map(|domain| domain.to_lowercase()),
+            card_bin: request
+                .credit_card
+                .as_ref()
+                .and_then(|card| card.issuer_id_number.clone()),
+            shop_id: record.shop_id.clone(),
+            amount: request.order.as_ref().map(|order| order.amount),
+            currency: request.order.as_ref().map(|order| order.currency.clone()),
+            event_time: record.event_time,
+            rule_codes: assessment.factors.iter().map(|f| f.code.clone()).collect(),
+            transaction: record.into(),
+        }
+    }
+}
+
+/// Payload of [`TRANSACTION_REPORTED`] events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionReported {
+    /// Transaction the report refers to
+    pub transaction_id: Uuid,
+    /// Reported outcome
+    pub tag: ReportTag,
+    /// Processor reason code, for chargebacks
+    pub chargeback_code: Option<String>,
+    /// When the reported outcome occurred
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Payload of [`CASE_RESOLVED`] events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseResolved {
+    /// Resolved case
+    pub case_id: Uuid,
+    /// Whether the case reviewed the transaction or an appeal of it
+    #[serde(default)]
+    pub kind: CaseKind,
+    /// Transaction under review
+    pub transaction_id: Uuid,
+    /// Reviewer who decided the case
+    pub reviewer: String,
+    /// Reviewer's decision
+    pub decision: CaseDecision,
+    /// When the case was resolved
+    pub resolved_at: DateTime<Utc>,
+}
+
+/// Payload of [`USER_MERGED`] events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserMerged {
+    /// User that absorbed the duplicate
+    pub user_id: Uuid,
+    /// Duplicate user, now deleted; its identifiers resolve to `user_id`
+    pub merged_user_id: Uuid,
+    /// Transactions moved from the duplicate
+    pub transactions_moved: i64,
+}
+
+/// Payload of [`API_KEY_EXPIRING`] events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyExpiring {
+    /// Key that is expiring
+    pub api_key_id: Uuid,
+    /// Name given to the key
+    pub name: String,
+    /// When the key stops working
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Payload of [`ACCOUNT_STATUS_CHANGED`] events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountStatusChanged {
+    /// Public identifier of the account
+    pub account_id: String,
+    /// Status before the change
+    pub previous_status: AccountStatus,
+    /// Status after the change
+    pub status: AccountStatus,
+    /// Reason given for the change
+    pub reason: Option<String>,
+    /// When the account will be deleted, if it was closed
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
+}
+
+/// Payload of [`ORGANIZATION_MEMBER_INVITED`] and [`ORGANIZATION_MEMBER_JOINED`] events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganizationMembership {
+    /// The organization
+    pub organization_id: Uuid,
+    /// Display name of the organization
+    pub organization_name: String,
+    /// Public identifier of the invited or joining account
+    pub account_id: String,
+    /// Role of the account
+    pub role: MemberRole,
+}
+
+/// Payload of [`QUOTA_EXHAUSTED`] events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaExhausted {
+    /// Scoring requests allowed per billing cycle
+    pub quota: i64,
+    /// Scoring requests used so far in the cycle
+    pub used: i64,
+    /// First day of the billing cycle
+    pub cycle_start: NaiveDate,
+    /// Day the quota resets
+    pub cycle_end: NaiveDate,
+}
+
+/// Payload of [`CASE_SLA_BREACHED`] events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseSlaBreached {
+    /// Overdue case
+    pub case_id: Uuid,
+    /// Transaction under review
+    pub transaction_id: Uuid,
+    /// Queue whose SLA the case outlived
+    pub queue_id: Uuid,
+    /// Name of the queue
+    pub queue_name: String,
+    /// Reviewer holding or assigned the case, if any
+    pub reviewer: Option<String>,
+    /// When the case was due
+    pub due_at: DateTime<Utc>,
+}
+
+/// Destination for outbox events (webhooks, analytics, message brokers, ...)
+pub trait EventPublisher: Send + Sync + 'static {
+    /// Deliver a single event, returning an error if it should be retried
+    fn publish(&self, event: &OutboxRecord) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+/// Publisher that only logs events, used until a real sink is configured
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingPublisher;
+
+impl EventPublisher for LoggingPublisher {
+    async fn publish(&self, event: &OutboxRecord) -> anyhow::Result<()> {
+        tracing::info!(
+            event_id = %event.id,
+            event_type = %event.event_type,
+            account_id = %event.account_id,
+            aggregate_id = %event.aggregate_id,
+            "Outbox event published"
+        );
+        Ok(())
+    }
+}
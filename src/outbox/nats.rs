@@ -0,0 +1,154 @@
+//! Publishing of outbox events to a NATS JetStream stream, for consumers that want every
+//! event of a type rather than one account's webhooks
+//!
+//! Each event of the configured types is published on `{prefix}.{account_id}.{event_type}`,
+//! so consumers can subscribe to one account with `{prefix}.{account_id}.>` or to one type
+//! across accounts with `{prefix}.*.{event_type}`. The outbox event ID is sent as the
+//! `Nats-Msg-Id` header: redeliveries after a lost acknowledgement fall within the stream's
+//! duplicate window and are stored once. An event only counts as delivered once JetStream
+//! acknowledges storing it, so events are never lost between the outbox and the stream.
+
+use std::time::Duration;
+
+use async_nats::{
+    ConnectError, ConnectOptions,
+    jetstream::{self, context::Publish},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{
+    EventPublisher, OutboxRecord,
+    callbacks::{EVENT_HEADER, EVENT_ID_HEADER},
+};
+use crate::config::EventStreamConfig;
+
+/// Body of a message published to the stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamEvent {
+    /// Event ID, stable across redeliveries, for deduplicating
+    pub id: Uuid,
+    /// Account the event belongs to
+    pub account_id: Uuid,
+    /// Event type, e.g. `transaction.scored`
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// When the event was recorded
+    pub created_at: DateTime<Utc>,
+    /// Event payload, shaped by its type
+    pub data: serde_json::Value,
+}
+
+impl From<&OutboxRecord> for StreamEvent {
+    fn from(event: &OutboxRecord) -> Self {
+        Self {
+            id: event.id,
+            account_id: event.account_id,
+            event_type: event.event_type.clone(),
+            created_at: event.created_at,
+            data: event.payload.0.clone(),
+        }
+    }
+}
+
+/// Subject an event of `event_type` for `account_id` is published on
+pub fn subject(prefix: &str, account_id: Uuid, event_type: &str) -> String {
+    format!("{prefix}.{account_id}.{event_type}")
+}
+
+/// Publisher that hands every event on to `inner`, then publishes those of the configured
+/// types to JetStream
+///
+/// Without a NATS URL configured it only hands events on. An event JetStream does not
+/// acknowledge in time fails the delivery, so the dispatcher retries it with backoff.
+#[derive(Debug, Clone)]
+pub struct NatsPublisher<P> {
+    inner: P,
+    jetstream: Option<jetstream::Context>,
+    subject_prefix: String,
+    event_types: Vec<String>,
+}
+
+impl<P: EventPublisher> NatsPublisher<P> {
+    /// Wrap `inner`, connecting to the configured NATS server if there is one
+    ///
+    /// The client reconnects by itself if the server goes away later; events published
+    /// meanwhile fail and are retried.
+    pub async fn connect(inner: P, config: &EventStreamConfig) -> Result<Self, ConnectError> {
+        let jetstream = match &config.nats_url {
+            Some(url) => {
+                let timeout = Duration::from_secs(config.ack_timeout_seconds);
+                let client = ConnectOptions::new()
+                    .name("fusegu-outbox")
+                    .connection_timeout(timeout)
+                    .connect(url.as_str())
+                    .await?;
+                let mut jetstream = jetstream::new(client);
+                jetstream.set_timeout(timeout);
+                Some(jetstream)
+            },
+            None => None,
+        };
+        Ok(Self {
+            inner,
+            jetstream,
+            subject_prefix: config.subject_prefix.clone(),
+            event_types: config.event_types.clone(),
+        })
+    }
+
+    async fn stream(
+        &self,
+        jetstream: &jetstream::Context,
+        event: &OutboxRecord,
+    ) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(&StreamEvent::from(event))?;
+        let message = Publish::build()
+            .message_id(event.id.to_string())
+            .header(EVENT_HEADER, event.event_type.as_str())
+            .header(EVENT_ID_HEADER, event.id.to_string())
+            .payload(body.into());
+        let ack = jetstream
+            .send_publish(
+                subject(&self.subject_prefix, event.account_id, &event.event_type),
+                message,
+            )
+            .await?
+            .await?;
+        if ack.duplicate {
+            tracing::debug!(
+                event_id = %event.id,
+                stream = %ack.stream,
+                "Event already in stream"
+            );
+        }
+        Ok(())
+    }
+}
+
+impl<P: EventPublisher> EventPublisher for NatsPublisher<P> {
+    async fn publish(&self, event: &OutboxRecord) -> anyhow::Result<()> {
+        self.inner.publish(event).await?;
+        match &self.jetstream {
+            Some(jetstream) if self.event_types.contains(&event.event_type) => {
+                self.stream(jetstream, event).await
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subjects_scope_events_by_account_and_type() {
+        let account_id = Uuid::nil();
+        assert_eq!(
+            subject("fusegu.events", account_id, "transaction.scored"),
+            "fusegu.events.00000000-0000-0000-0000-000000000000.transaction.scored"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+//! Announcement of high-severity events on the email and Slack channels accounts register
+//!
+//! Events of the [`NOTIFICATION_EVENT_TYPES`] are rendered as a short subject and text and
+//! sent to every active channel of their account that takes their type: as a plain-text
+//! email through the configured SMTP relay, or as a message posted to a Slack incoming
+//! webhook. Email channels are skipped while no relay is configured.
+
+use std::time::Duration;
+
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, header::ContentType},
+    transport::smtp::authentication::Credentials,
+};
+use reqwest::redirect::Policy;
+use serde::de::DeserializeOwned;
+use sqlx::PgPool;
+
+use super::{
+    ANOMALY_DETECTED, CASE_SLA_BREACHED, CaseSlaBreached, EventPublisher, NOTIFICATION_EVENT_TYPES,
+    OutboxRecord, QUOTA_EXHAUSTED, QuotaExhausted,
+};
+use crate::{
+    config::NotificationsConfig,
+    database::{
+        Tenant,
+        repositories::{NotificationChannelRecord, NotificationChannelRepo},
+    },
+    models::{
+        analytics::{Anomaly, AnomalyMetric},
+        notification::ChannelKind,
+    },
+};
+
+/// Announcement of an event, as sent on every channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// One-line summary, used as the email subject
+    pub subject: String,
+    /// What happened, in a sentence or two
+    pub text: String,
+}
+
+impl Notification {
+    /// Announcement of `event`, or `None` if its type is not announced or its payload is
+    /// malformed
+    pub fn for_event(event: &OutboxRecord) -> Option<Self> {
+        match event.event_type.as_str() {
+            ANOMALY_DETECTED => {
+                let anomaly: Anomaly = payload(event)?;
+                let metric = match anomaly.metric {
+                    AnomalyMetric::RejectRate => "reject rate",
+                    AnomalyMetric::AverageRiskScore => "average risk score",
+                    AnomalyMetric::RuleHitRate => "hit rate",
+                };
+                let rule = anomaly
+                    .rule_code
+                    .map(|code| format!(" of rule {code}"))
+                    .unwrap_or_default();
+                Some(Self {
+                    subject: format!("Anomaly detected: {metric}"),
+                    text: format!(
+                        "The {metric}{rule} was {:.3} in the hour from {}, against a baseline \
+                         of {:.3} ({:.1} standard deviations above, over {} transactions).",
+                        anomaly.observed_value,
+                        anomaly.bucket_start.format("%Y-%m-%d %H:%M UTC"),
+                        anomaly.baseline_mean,
+                        anomaly.z_score,
+                        anomaly.transaction_count,
+                    ),
+                })
+            },
+            QUOTA_EXHAUSTED => {
+                let quota: QuotaExhausted = payload(event)?;
+                Some(Self {
+                    subject: "Monthly quota used up".to_string(),
+                    text: format!(
+                        "All {} scoring requests of the billing cycle that started on {} are \
+                         used. Further requests may be refused until the quota resets on {}.",
+                        quota.quota, quota.cycle_start, quota.cycle_end,
+                    ),
+                })
+            },
+            CASE_SLA_BREACHED => {
+                let breach: CaseSlaBreached = payload(event)?;
+                let held = breach
+                    .reviewer
+                    .map(|reviewer| format!(", held by {reviewer}"))
+                    .unwrap_or_default();
+                Some(Self {
+                    subject: format!("Review case past its SLA in queue {}", breach.queue_name),
+                    text: format!(
+                        "Case {} of transaction {} was due at {} and is still unresolved{held}.",
+                        breach.case_id,
+                        breach.transaction_id,
+                        breach.due_at.format("%Y-%m-%d %H:%M UTC"),
+                    ),
+                })
+            },
+            _ => None,
+        }
+    }
+}
+
+fn payload<T: DeserializeOwned>(event: &OutboxRecord) -> Option<T> {
+    match serde_json::from_value(event.payload.0.clone()) {
+        Ok(payload) => Some(payload),
+        Err(e) => {
+            tracing::warn!(
+                event_id = %event.id,
+                event_type = %event.event_type,
+                error = %e,
+                "Malformed payload; not announced"
+            );
+            None
+        },
+    }
+}
+
+/// Sender of announcements over SMTP and to Slack
+#[derive(Clone)]
+pub struct Notifier {
+    pool: PgPool,
+    http: reqwest::Client,
+    mailer: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    from: Mailbox,
+}
+
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notifier")
+            .field("smtp", &self.mailer.is_some())
+            .field("from", &self.from)
+            .finish()
+    }
+}
+
+impl Notifier {
+    /// Notifier looking up channels in `pool`, sending email through the configured relay
+    ///
+    /// Fails if the sender address is malformed or the HTTP client cannot be built.
+    pub fn new(pool: PgPool, config: &NotificationsConfig) -> anyhow::Result<Self> {
+        let timeout = Duration::from_secs(config.timeout_seconds);
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(Policy::none())
+            .build()?;
+        let from = config.smtp_from.parse::<Mailbox>()?;
+        let mailer = match &config.smtp_host {
+            Some(host) => {
+                let mut builder = if config.smtp_tls {
+                    AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+                } else {
+                    AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+                };
+                builder = builder.port(config.smtp_port).timeout(Some(timeout));
+                if let (Some(username), Some(password)) =
+                    (&config.smtp_username, &config.smtp_password)
+                {
+                    builder =
+                        builder.credentials(Credentials::new(username.clone(), password.clone()));
+                }
+                Some(builder.build())
+            },
+            None => None,
+        };
+        Ok(Self {
+            pool,
+            http,
+            mailer,
+            from,
+        })
+    }
+
+    /// Announce `event` on every channel of its account that takes it
+    ///
+    /// A channel that cannot be reached is logged and skipped; only looking up the channels
+    /// can fail.
+    pub async fn notify(&self, event: &OutboxRecord) -> sqlx::Result<()> {
+        if !NOTIFICATION_EVENT_TYPES.contains(&event.event_type.as_str()) {
+            return Ok(());
+        }
+        let tenant = Tenant::trusted(event.account_id);
+        let channels =
+            NotificationChannelRepo::recipients(&self.pool, tenant, &event.event_type).await?;
+        if channels.is_empty() {
+            return Ok(());
+        }
+        let Some(notification) = Notification::for_event(event) else {
+            return Ok(());
+        };
+        for channel in &channels {
+            match self.send(channel, &notification).await {
+                Ok(true) => NotificationChannelRepo::touch(&self.pool, channel.id).await?,
+                Ok(false) => {},
+                Err(e) => tracing::warn!(
+                    event_id = %event.id,
+                    channel_id = %channel.id,
+                    error = %e,
+                    "Notification failed"
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Send `notification` on `channel`, returning whether it was sent
+    async fn send(
+        &self,
+        channel: &NotificationChannelRecord,
+        notification: &Notification,
+    ) -> anyhow::Result<bool> {
+        match channel.kind {
+            ChannelKind::Email => {
+                let Some(mailer) = &self.mailer else {
+                    tracing::debug!(channel_id = %channel.id, "No SMTP relay; email skipped");
+                    return Ok(false);
+                };
+                let message = Message::builder()
+                    .from(self.from.clone())
+                    .to(channel.target.parse()?)
+                    .subject(&notification.subject)
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(notification.text.clone())?;
+                mailer.send(message).await?;
+            },
+            ChannelKind::Slack => {
+                let text = format!("*{}*\n{}", notification.subject, notification.text);
+                self.http
+                    .post(&channel.target)
+                    .json(&serde_json::json!({ "text": text }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            },
+        }
+        Ok(true)
+    }
+}
+
+/// Publisher that hands every event on to `inner`, then announces high-severity ones on the
+/// account's notification channels
+///
+/// Announcements are best effort: a channel that fails is logged rather than failing the
+/// event, so a broken Slack webhook does not make the dispatcher redeliver the event to
+/// every other consumer.
+#[derive(Debug, Clone)]
+pub struct NotificationPublisher<P> {
+    inner: P,
+    notifier: Notifier,
+}
+
+impl<P: EventPublisher> NotificationPublisher<P> {
+    /// Wrap `inner`, announcing events with `notifier`
+    pub fn new(inner: P, notifier: Notifier) -> Self {
+        Self { inner, notifier }
+    }
+}
+
+impl<P: EventPublisher> EventPublisher for NotificationPublisher<P> {
+    async fn publish(&self, event: &OutboxRecord) -> anyhow::Result<()> {
+        self.inner.publish(event).await?;
+        self.notifier.notify(event).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use sqlx::types::Json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::outbox::TRANSACTION_SCORED;
+
+    fn event(event_type: &str, payload: serde_json::Value) -> OutboxRecord {
+        OutboxRecord {
+            id: Uuid::new_v4(),
+            account_id: Uuid::new_v4(),
+            event_type: event_type.to_string(),
+            aggregate_id: Uuid::new_v4(),
+            payload: Json(payload),
+            attempts: 0,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_high_severity_events_are_rendered() {
+        let quota = QuotaExhausted {
+            quota: 10_000,
+            used: 10_000,
+            cycle_start: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            cycle_end: NaiveDate::from_ymd_opt(2026, 11, 1).unwrap(),
+        };
+        let notification = Notification::for_event(&event(
+            QUOTA_EXHAUSTED,
+            serde_json::to_value(&quota).unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(notification.subject, "Monthly quota used up");
+        assert!(notification.text.contains("10000 scoring requests"));
+        assert!(notification.text.contains("2026-11-01"));
+
+        let breach = CaseSlaBreached {
+            case_id: Uuid::new_v4(),
+            transaction_id: Uuid::new_v4(),
+            queue_id: Uuid::new_v4(),
+            queue_name: "high-value".to_string(),
+            reviewer: Some("ana".to_string()),
+            due_at: Utc.with_ymd_and_hms(2026, 10, 19, 9, 30, 0).unwrap(),
+        };
+        let notification = Notification::for_event(&event(
+            CASE_SLA_BREACHED,
+            serde_json::to_value(&breach).unwrap(),
+        ))
+        .unwrap();
+        assert!(notification.subject.ends_with("queue high-value"));
+        assert!(notification.text.contains("2026-10-19 09:30 UTC"));
+        assert!(notification.text.ends_with("held by ana."));
+
+        // Other events, and payloads that do not parse, are not announced
+        assert!(
+            Notification::for_event(&event(TRANSACTION_SCORED, serde_json::json!({}))).is_none()
+        );
+        assert!(Notification::for_event(&event(QUOTA_EXHAUSTED, serde_json::json!({}))).is_none());
+    }
+}
@@ -0,0 +1,45 @@
+//! DNS resolution that refuses reserved addresses
+//!
+//! Webhook and callback URLs are checked when they are submitted, but a public hostname can
+//! be pointed at a private address afterwards. Clients that POST to customer URLs resolve
+//! through [`PublicResolver`], so a delivery never connects to the internal network.
+
+use std::net::SocketAddr;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+use crate::models::transaction::is_reserved_ip;
+
+/// Resolver that drops reserved and private addresses, failing names that have no other
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| !is_reserved_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_names_of_reserved_addresses_do_not_resolve() {
+        let resolved = PublicResolver.resolve("localhost".parse().unwrap()).await;
+        let error = resolved
+            .err()
+            .expect("localhost has only loopback addresses");
+        assert_eq!(error.to_string(), "localhost has no public address");
+    }
+}
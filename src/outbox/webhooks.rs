@@ -0,0 +1,396 @@
+//! Signed delivery of outbox events to the webhooks accounts register
+//!
+//! Each event is POSTed as a [`WebhookEvent`] to every active webhook of its account that
+//! takes its type and whose filters its payload meets. The body is signed with the webhook's
+//! secret: the signature is the hex HMAC-SHA256 of
+//!
+//! ```text
+//! {timestamp}.{body}
+//! ```
+//!
+//! sent in `X-Fusegu-Signature` as `v1={signature}`, with the Unix timestamp it was signed at
+//! in `X-Fusegu-Timestamp`. For a while after a secret is rotated, deliveries are signed with
+//! both the new and the replaced secret, as `v1={new},v1={replaced}`, so the endpoint can move
+//! to the new secret without refusing events. Consumers check deliveries with
+//! [`verify_signature`], which also refuses timestamps outside a tolerance so captured
+//! deliveries cannot be replayed later.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::redirect::Policy;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::PgPool;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::{
+    EventPublisher, OutboxRecord,
+    callbacks::{EVENT_HEADER, EVENT_ID_HEADER},
+    resolver::PublicResolver,
+};
+use crate::{
+    config::OutboxConfig,
+    database::{
+        Tenant,
+        repositories::{
+            NewWebhookDelivery, WebhookDeliveryRecord, WebhookRepo, WebhookTargetRecord,
+        },
+    },
+    models::webhook::DeliveryStatus,
+};
+
+/// Header carrying the Unix timestamp a delivery was signed at
+pub const TIMESTAMP_HEADER: &str = "X-Fusegu-Timestamp";
+/// Header carrying the delivery's signatures
+pub const SIGNATURE_HEADER: &str = "X-Fusegu-Signature";
+/// Scheme of the signatures in [`SIGNATURE_HEADER`]
+pub const SIGNATURE_SCHEME: &str = "v1";
+/// Clock difference [`verify_signature`] callers are advised to tolerate
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+/// Body of a webhook delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    /// Event ID, stable across redeliveries, for deduplicating
+    pub id: Uuid,
+    /// Event type, e.g. `transaction.scored`
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// When the event was recorded
+    pub created_at: DateTime<Utc>,
+    /// Event payload, shaped by its type
+    pub data: serde_json::Value,
+}
+
+impl From<&OutboxRecord> for WebhookEvent {
+    fn from(event: &OutboxRecord) -> Self {
+        Self {
+            id: event.id,
+            event_type: event.event_type.clone(),
+            created_at: event.created_at,
+            data: event.payload.0.clone(),
+        }
+    }
+}
+
+/// Why a delivery's signature was refused
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The timestamp header is not a Unix timestamp
+    #[error("timestamp is not a Unix timestamp")]
+    InvalidTimestamp,
+    /// The delivery was signed further from now than the tolerance allows
+    #[error("timestamp is outside the tolerance")]
+    OutsideTolerance,
+    /// No signature in the header was made with the secret
+    #[error("no signature matches")]
+    Mismatch,
+}
+
+/// Hex signature of `body` as signed at `timestamp` with `secret`
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    hex::encode(mac(secret, timestamp, body).finalize().into_bytes())
+}
+
+/// Value of [`SIGNATURE_HEADER`] for `body`, signed with each of `secrets`
+pub fn signature_header(secrets: &[&str], timestamp: i64, body: &[u8]) -> String {
+    secrets
+        .iter()
+        .map(|secret| format!("{SIGNATURE_SCHEME}={}", sign(secret, timestamp, body)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Check a delivery was signed with `secret` no further than `tolerance` from now
+///
+/// `timestamp` and `signature` are the values of [`TIMESTAMP_HEADER`] and
+/// [`SIGNATURE_HEADER`], and `body` the raw request body, before any parsing. The delivery is
+/// accepted when any of its signatures matches, so verifying with either secret works while
+/// a rotation overlaps.
+pub fn verify_signature(
+    secret: &str,
+    timestamp: &str,
+    signature: &str,
+    body: &[u8],
+    tolerance: Duration,
+) -> Result<(), SignatureError> {
+    verify_signature_at(
+        secret,
+        timestamp,
+        signature,
+        body,
+        tolerance,
+        Utc::now().timestamp(),
+    )
+}
+
+fn verify_signature_at(
+    secret: &str,
+    timestamp: &str,
+    signature: &str,
+    body: &[u8],
+    tolerance: Duration,
+    now: i64,
+) -> Result<(), SignatureError> {
+    let timestamp: i64 = timestamp
+        .trim()
+        .parse()
+        .map_err(|_| SignatureError::InvalidTimestamp)?;
+    if now.abs_diff(timestamp) > tolerance.as_secs() {
+        return Err(SignatureError::OutsideTolerance);
+    }
+    let matches = signature
+        .split(',')
+        .filter_map(|part| part.trim().split_once('='))
+        .filter(|(scheme, _)| *scheme == SIGNATURE_SCHEME)
+        .filter_map(|(_, signature)| hex::decode(signature).ok())
+        .any(|signature| {
+            mac(secret, timestamp, body)
+                .verify_slice(&signature)
+                .is_ok()
+        });
+    if matches {
+        Ok(())
+    } else {
+        Err(SignatureError::Mismatch)
+    }
+}
+
+fn mac(secret: &str, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// HTTP client that POSTs signed events to webhooks and logs every attempt
+///
+/// Redirects are not followed, since only the registered URL was checked, and hostnames
+/// resolving to reserved addresses are refused.
+#[derive(Debug, Clone)]
+pub struct WebhookSender {
+    pool: PgPool,
+    http: reqwest::Client,
+}
+
+impl WebhookSender {
+    /// Sender logging to `pool`, giving endpoints the configured time to respond
+    pub fn new(pool: PgPool, config: &OutboxConfig) -> reqwest::Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.webhook_timeout_seconds))
+            .redirect(Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()?;
+        Ok(Self { pool, http })
+    }
+
+    /// POST `event` to `target` and log the attempt, returning it, or `None` if the webhook
+    /// was deleted meanwhile
+    ///
+    /// Only logging can fail; how the attempt went is in the log.
+    pub async fn send(
+        &self,
+        target: &WebhookTargetRecord,
+        event: &OutboxRecord,
+        redrive_of: Option<Uuid>,
+    ) -> sqlx::Result<Option<WebhookDeliveryRecord>> {
+        let started = Instant::now();
+        let result = self.post(target, event).await;
+        let latency_ms = i32::try_from(started.elapsed().as_millis()).unwrap_or(i32::MAX);
+        let (status, response_status, error) = match &result {
+            Ok(status) => (DeliveryStatus::Succeeded, Some(*status), None),
+            Err(e) => {
+                tracing::warn!(
+                    event_id = %event.id,
+                    webhook_id = %target.id,
+                    error = %e,
+                    "Webhook delivery failed"
+                );
+                (
+                    DeliveryStatus::Failed,
+                    e.status().map(|status| status.as_u16()),
+                    Some(e.to_string()),
+                )
+            },
+        };
+        let delivery = NewWebhookDelivery {
+            webhook_id: target.id,
+            event_id: event.id,
+            event_type: &event.event_type,
+            status,
+            response_status: response_status.map(i32::from),
+            latency_ms,
+            error: error.as_deref(),
+            redrive_of,
+        };
+        let tenant = Tenant::trusted(event.account_id);
+        let mut tx = self.pool.begin().await?;
+        let Some(delivery_id) = WebhookRepo::insert_delivery(&mut *tx, tenant, &delivery).await?
+        else {
+            return Ok(None);
+        };
+        let record = WebhookRepo::find_delivery(&mut *tx, tenant, delivery_id).await?;
+        tx.commit().await?;
+        Ok(record)
+    }
+
+    /// POST `event`, returning the endpoint's 2xx status
+    async fn post(
+        &self,
+        target: &WebhookTargetRecord,
+        event: &OutboxRecord,
+    ) -> reqwest::Result<u16> {
+        // A JSON value always serializes
+        let body = serde_json::to_vec(&WebhookEvent::from(event)).unwrap_or_default();
+        let timestamp = Utc::now().timestamp();
+        let secrets: Vec<&str> = std::iter::once(target.secret.as_str())
+            .chain(target.previous_secret.as_deref())
+            .collect();
+        let response = self
+            .http
+            .post(&target.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event.event_type.as_str())
+            .header(EVENT_ID_HEADER, event.id.to_string())
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(
+                SIGNATURE_HEADER,
+                signature_header(&secrets, timestamp, &body),
+            )
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.status().as_u16())
+    }
+}
+
+/// Publisher that hands every event on to `inner`, then POSTs it to the account's webhooks
+///
+/// `inner` goes first, so an endpoint that keeps failing holds back nothing but its own
+/// events. A webhook that fails or answers with a non-2xx status fails the delivery, and the
+/// dispatcher retries the event at every webhook that takes it; endpoints deduplicate on the
+/// event ID. Each attempt at each webhook is logged.
+#[derive(Debug, Clone)]
+pub struct WebhookPublisher<P> {
+    inner: P,
+    sender: WebhookSender,
+}
+
+impl<P: EventPublisher> WebhookPublisher<P> {
+    /// Wrap `inner`, sending events with `sender`
+    pub fn new(inner: P, sender: WebhookSender) -> Self {
+        Self { inner, sender }
+    }
+
+    async fn deliver(&self, event: &OutboxRecord) -> anyhow::Result<()> {
+        let tenant = Tenant::trusted(event.account_id);
+        let targets: Vec<_> = WebhookRepo::targets(&self.sender.pool, tenant, &event.event_type)
+            .await?
+            .into_iter()
+            .filter(|target| target.filters.matches(&event.payload))
+            .collect();
+
+        let mut failed = 0;
+        for target in &targets {
+            let delivery = self.sender.send(target, event, None).await?;
+            if delivery.is_some_and(|delivery| delivery.status == DeliveryStatus::Failed) {
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            anyhow::bail!("{failed} of {} webhooks failed", targets.len());
+        }
+        Ok(())
+    }
+}
+
+impl<P: EventPublisher> EventPublisher for WebhookPublisher<P> {
+    async fn publish(&self, event: &OutboxRecord) -> anyhow::Result<()> {
+        self.inner.publish(event).await?;
+        self.deliver(event).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = br#"{"id":"4f1c","type":"case.resolved"}"#;
+    const TIMESTAMP: i64 = 1760000000;
+
+    fn verify(secret: &str, signature: &str, body: &[u8], now: i64) -> Result<(), SignatureError> {
+        verify_signature_at(
+            secret,
+            &TIMESTAMP.to_string(),
+            signature,
+            body,
+            DEFAULT_TOLERANCE,
+            now,
+        )
+    }
+
+    #[test]
+    fn test_signatures_verify_with_either_secret() {
+        let header = signature_header(&["whsec_new", "whsec_old"], TIMESTAMP, BODY);
+        assert!(header.starts_with("v1="));
+        assert_eq!(header.matches("v1=").count(), 2);
+        assert_eq!(verify("whsec_new", &header, BODY, TIMESTAMP), Ok(()));
+        assert_eq!(verify("whsec_old", &header, BODY, TIMESTAMP + 60), Ok(()));
+
+        let single = signature_header(&["whsec_new"], TIMESTAMP, BODY);
+        assert_eq!(single, format!("v1={}", sign("whsec_new", TIMESTAMP, BODY)));
+        assert_eq!(
+            verify("whsec_old", &single, BODY, TIMESTAMP),
+            Err(SignatureError::Mismatch)
+        );
+    }
+
+    #[test]
+    fn test_rejects_tampering_and_replays() {
+        let header = signature_header(&["whsec_test"], TIMESTAMP, BODY);
+        assert_eq!(
+            verify("whsec_test", &header, b"{}", TIMESTAMP),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify("whsec_test", "v0=abc,v1=not-hex", BODY, TIMESTAMP),
+            Err(SignatureError::Mismatch)
+        );
+        // The timestamp is covered by the signature, so it cannot be moved forward
+        assert_eq!(
+            verify_signature_at(
+                "whsec_test",
+                &(TIMESTAMP + 600).to_string(),
+                &header,
+                BODY,
+                DEFAULT_TOLERANCE,
+                TIMESTAMP + 600,
+            ),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify("whsec_test", &header, BODY, TIMESTAMP + 301),
+            Err(SignatureError::OutsideTolerance)
+        );
+        assert_eq!(
+            verify_signature_at(
+                "whsec_test",
+                "yesterday",
+                &header,
+                BODY,
+                DEFAULT_TOLERANCE,
+                TIMESTAMP
+            ),
+            Err(SignatureError::InvalidTimestamp)
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! Rate limit checks for authenticated routes
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use super::RateLimitStatus;
+use crate::{api::ApiError, auth::AuthContext, state::AppState};
+
+/// Requests the account may make at once with a full bucket
+pub const LIMIT_HEADER: &str = "x-ratelimit-limit";
+/// Requests the account may make right now
+pub const REMAINING_HEADER: &str = "x-ratelimit-remaining";
+/// Seconds until the account's bucket is full again
+pub const RESET_HEADER: &str = "x-ratelimit-reset";
+
+/// Take a token from the caller's bucket, refusing the request with 429 once it is empty
+///
+/// Must run inside [`crate::auth::authorize`], which supplies the [`AuthContext`]. Every
+/// response, refused or not, carries the `X-RateLimit-*` headers; refusals also carry
+/// `Retry-After`.
+pub async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(auth) = request.extensions().get::<AuthContext>().copied() else {
+        return next.run(request).await;
+    };
+    let Some(status) = state.rate_limiter.check(auth.account_id, auth.tier).await else {
+        return next.run(request).await;
+    };
+
+    let mut response = if status.allowed {
+        next.run(request).await
+    } else {
+        tracing::info!(account_id = %auth.account_id, tier = %auth.tier, "Rate limited");
+        ApiError::RateLimited {
+            per_second: state.config.rate_limit.per_second(auth.tier),
+            retry_after_seconds: status.retry_after_seconds,
+        }
+        .into_response()
+    };
+    set_headers(response.headers_mut(), &status);
+    response
+}
+
+fn set_headers(headers: &mut HeaderMap, status: &RateLimitStatus) {
+    for (name, value) in [
+        (LIMIT_HEADER, u64::from(status.limit)),
+        (REMAINING_HEADER, u64::from(status.remaining)),
+        (RESET_HEADER, status.reset_seconds),
+    ] {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
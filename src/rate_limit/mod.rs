@@ -0,0 +1,283 @@
+//! Per-account request rate limiting
+//!
+//! Separate from monthly quotas (see [`crate::metering`]): every authenticated request takes a
+//! token from its account's bucket, which refills continuously at the rate of the account's
+//! subscription tier and holds up to [`RateLimitConfig::burst_seconds`] worth of requests.
+//! With Redis configured the bucket is shared by all instances; without it, and while Redis is
+//! unreachable, each instance keeps its own buckets.
+
+pub mod middleware;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use redis::{RedisResult, aio::ConnectionManager};
+use uuid::Uuid;
+
+use crate::{config::RateLimitConfig, models::account::SubscriptionTier};
+
+pub use middleware::{LIMIT_HEADER, REMAINING_HEADER, RESET_HEADER, rate_limit};
+
+/// Prefix of the Redis buckets, followed by the internal account ID
+pub const RATE_LIMIT_KEY_PREFIX: &str = "fusegu:rate:";
+
+/// Buckets kept in memory before idle ones are swept
+const MEMORY_SWEEP_THRESHOLD: usize = 10_000;
+
+/// Refill the bucket for the time since its last update, using the Redis clock so instances
+/// agree, then take a token if one is left. Returns whether a token was taken and the tokens
+/// left.
+const TAKE_TOKEN_SCRIPT: &str = r#"
+local rate = tonumber(ARGV[1])
+local burst = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+local tokens = tonumber(bucket[1]) or burst
+local updated = tonumber(bucket[2]) or now
+tokens = math.min(burst, tokens + math.max(0, now - updated) * rate / 1000)
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(burst / rate * 1000) + 1000)
+return {allowed, tostring(tokens)}
+"#;
+
+/// Refill rate and capacity of an account's bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+    /// Tokens added per second
+    pub per_second: u32,
+    /// Tokens the bucket holds when full
+    pub burst: u32,
+}
+
+impl Limit {
+    /// Limit of accounts on `tier`
+    pub fn for_tier(config: &RateLimitConfig, tier: SubscriptionTier) -> Self {
+        let per_second = config.per_second(tier).max(1);
+        Self {
+            per_second,
+            burst: per_second.saturating_mul(config.burst_seconds).max(1),
+        }
+    }
+}
+
+/// Outcome of checking a request against its account's limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    /// Whether the request may proceed
+    pub allowed: bool,
+    /// Requests the account may make at once with a full bucket
+    pub limit: u32,
+    /// Requests the account may make right now
+    pub remaining: u32,
+    /// Seconds until the bucket is full again
+    pub reset_seconds: u64,
+    /// Seconds until the next request will be allowed, if this one was refused
+    pub retry_after_seconds: u64,
+}
+
+impl RateLimitStatus {
+    fn new(limit: Limit, allowed: bool, tokens: f64) -> Self {
+        let rate = f64::from(limit.per_second);
+        let seconds_until = |target: f64| ((target - tokens).max(0.0) / rate).ceil() as u64;
+        Self {
+            allowed,
+            limit: limit.burst,
+            remaining: tokens.floor().clamp(0.0, f64::from(limit.burst)) as u32,
+            reset_seconds: seconds_until(f64::from(limit.burst)),
+            retry_after_seconds: if allowed {
+                0
+            } else {
+                seconds_until(1.0).max(1)
+            },
+        }
+    }
+}
+
+/// Token bucket kept in memory
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Refill for the time since the last update, then take a token if one is left
+    fn take(&mut self, limit: Limit, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * f64::from(limit.per_second)).min(f64::from(limit.burst));
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Applies per-account rate limits
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    redis: Option<ConnectionManager>,
+    local: Arc<Mutex<HashMap<Uuid, Bucket>>>,
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("enabled", &self.config.enabled)
+            .field("redis", &self.redis.is_some())
+            .finish()
+    }
+}
+
+impl RateLimiter {
+    /// Limit per `config`, sharing buckets through `redis` when available
+    pub fn new(config: RateLimitConfig, redis: Option<ConnectionManager>) -> Self {
+        Self {
+            config,
+            redis,
+            local: Arc::default(),
+        }
+    }
+
+    /// Take a token for a request of an account on `tier`, or `None` if limiting is disabled
+    ///
+    /// Redis failures fall back to the instance's own buckets, so an outage loosens the limit
+    /// instead of refusing traffic.
+    pub async fn check(&self, account_id: Uuid, tier: SubscriptionTier) -> Option<RateLimitStatus> {
+        if !self.config.enabled {
+            return None;
+        }
+        let limit = Limit::for_tier(&self.config, tier);
+        if let Some(redis) = &self.redis {
+            match take_redis(redis.clone(), account_id, limit).await {
+                Ok(status) => return Some(status),
+                Err(e) => tracing::warn!(
+                    error = %e,
+                    %account_id,
+                    "Redis rate limiting failed; limiting per instance"
+                ),
+            }
+        }
+        Some(self.take_local(account_id, limit, Instant::now()))
+    }
+
+    fn take_local(&self, account_id: Uuid, limit: Limit, now: Instant) -> RateLimitStatus {
+        let mut buckets = self.local.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MEMORY_SWEEP_THRESHOLD {
+            // A bucket idle for longer than the burst window is full, same as a new one
+            let idle = Duration::from_secs(u64::from(self.config.burst_seconds) + 1);
+            buckets.retain(|_, bucket| now.saturating_duration_since(bucket.updated) < idle);
+        }
+        let bucket = buckets.entry(account_id).or_insert(Bucket {
+            tokens: f64::from(limit.burst),
+            updated: now,
+        });
+        let allowed = bucket.take(limit, now);
+        RateLimitStatus::new(limit, allowed, bucket.tokens)
+    }
+}
+
+async fn take_redis(
+    mut conn: ConnectionManager,
+    account_id: Uuid,
+    limit: Limit,
+) -> RedisResult<RateLimitStatus> {
+    let (allowed, tokens): (i64, String) = redis::cmd("EVAL")
+        .arg(TAKE_TOKEN_SCRIPT)
+        .arg(1)
+        .arg(format!("{RATE_LIMIT_KEY_PREFIX}{account_id}"))
+        .arg(limit.per_second)
+        .arg(limit.burst)
+        .query_async(&mut conn)
+        .await?;
+    let tokens = tokens.parse().unwrap_or(0.0);
+    Ok(RateLimitStatus::new(limit, allowed == 1, tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn limiter(free_per_second: u32, burst_seconds: u32) -> RateLimiter {
+        RateLimiter::new(
+            RateLimitConfig {
+                free_per_second,
+                burst_seconds,
+                ..Config::default().rate_limit
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn test_limits_by_tier() {
+        let config = Config::default().rate_limit;
+        assert_eq!(
+            Limit::for_tier(&config, SubscriptionTier::Free),
+            Limit {
+                per_second: 10,
+                burst: 10
+            }
+        );
+        assert_eq!(
+            Limit::for_tier(&config, SubscriptionTier::Enterprise).per_second,
+            500
+        );
+    }
+
+    #[test]
+    fn test_bucket_empties_and_refills() {
+        let limiter = limiter(2, 2);
+        let limit = Limit::for_tier(&limiter.config, SubscriptionTier::Free);
+        let account = Uuid::new_v4();
+        let start = Instant::now();
+
+        for remaining in (0..4).rev() {
+            let status = limiter.take_local(account, limit, start);
+            assert!(status.allowed);
+            assert_eq!(status.remaining, remaining);
+        }
+        let refused = limiter.take_local(account, limit, start);
+        assert!(!refused.allowed);
+        assert_eq!(refused.retry_after_seconds, 1);
+        assert_eq!(refused.reset_seconds, 2);
+
+        // Half a second refills one token at two per second
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.take_local(account, limit, later).allowed);
+        assert!(!limiter.take_local(account, limit, later).allowed);
+
+        // Other accounts have their own buckets
+        assert!(limiter.take_local(Uuid::new_v4(), limit, later).allowed);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_limiter_reports_nothing() {
+        let limiter = RateLimiter::new(
+            RateLimitConfig {
+                enabled: false,
+                ..Config::default().rate_limit
+            },
+            None,
+        );
+        assert_eq!(
+            limiter.check(Uuid::new_v4(), SubscriptionTier::Free).await,
+            None
+        );
+    }
+}
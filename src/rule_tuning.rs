@@ -0,0 +1,148 @@
+//! Suggestion of rule weights from reported outcomes
+//!
+//! A rule earns its weight by how often the transactions it fired on turn out to be fraud.
+//! Each pass compares, per account, the precision of every rule that raised a score (the share
+//! of its reported transactions reported as fraud or abuse) with the precision of the
+//! account's reported transactions overall, and suggests weighting the rule by the ratio: a
+//! rule twice as precise as the baseline is suggested twice its built-in score, one half as
+//! precise half of it. Suggestions change nothing by themselves; applying one creates a new
+//! version of the account's rule weights.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    config::RuleSuggestionsConfig,
+    database::{
+        Tenant,
+        repositories::{NewRuleSuggestion, OutcomeCountRecord, RuleRepo},
+    },
+};
+
+/// Lowest weight suggested for a rule
+const MIN_WEIGHT: f64 = 0.25;
+/// Highest weight suggested for a rule
+const MAX_WEIGHT: f64 = 2.0;
+/// Smallest change from the weight in force worth suggesting
+const MIN_WEIGHT_CHANGE: f64 = 0.1;
+
+/// Spawn a background task that periodically recomputes every account's suggestions
+pub fn spawn_rule_suggestions(pool: PgPool, config: RuleSuggestionsConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(config.interval_hours * 3600);
+        loop {
+            match recompute_suggestions(&pool, &config, Utc::now()).await {
+                Ok(0) => {},
+                Ok(accounts) => tracing::info!(accounts, "Rule weight suggestions recomputed"),
+                Err(e) => tracing::error!(error = %e, "Rule weight suggestion pass failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Recompute the suggestions of every account with outcomes reported for transactions scored
+/// within the lookback window ending at `now`, returning how many accounts there were
+pub async fn recompute_suggestions(
+    pool: &PgPool,
+    config: &RuleSuggestionsConfig,
+    now: DateTime<Utc>,
+) -> sqlx::Result<usize> {
+    let since = now - ChronoDuration::days(i64::from(config.lookback_days));
+    let accounts = RuleRepo::accounts_with_outcomes(pool, since).await?;
+    for &account_id in &accounts {
+        let tenant = Tenant::trusted(account_id);
+        let mut tx = pool.begin().await?;
+        let counts = RuleRepo::outcome_counts(&mut *tx, tenant, since).await?;
+        let weights = RuleRepo::weights(&mut *tx, tenant).await?;
+        let suggestions = suggest(&counts, |code| weights.weight(code), config.min_reported);
+        RuleRepo::clear_pending_suggestions(&mut *tx, tenant).await?;
+        RuleRepo::insert_suggestions(&mut *tx, tenant, &suggestions).await?;
+        tx.commit().await?;
+    }
+    Ok(accounts.len())
+}
+
+/// Weights worth suggesting for the rules among `counts`, given the weight of each in force
+fn suggest(
+    counts: &[OutcomeCountRecord],
+    weight: impl Fn(&str) -> f64,
+    min_reported: i64,
+) -> Vec<NewRuleSuggestion> {
+    let Some(total) = counts.iter().find(|count| count.rule_code.is_none()) else {
+        return Vec::new();
+    };
+    if total.fraudulent == 0 {
+        return Vec::new();
+    }
+    let baseline = total.fraudulent as f64 / total.reported as f64;
+    counts
+        .iter()
+        .filter(|count| count.reported >= min_reported)
+        .filter_map(|count| {
+            let rule_code = count.rule_code.as_deref()?;
+            let current_weight = weight(rule_code);
+            let precision = count.fraudulent as f64 / count.reported as f64;
+            let suggested_weight =
+                ((precision / baseline).clamp(MIN_WEIGHT, MAX_WEIGHT) * 100.0).round() / 100.0;
+            ((suggested_weight - current_weight).abs() >= MIN_WEIGHT_CHANGE).then(|| {
+                NewRuleSuggestion {
+                    rule_code: rule_code.to_string(),
+                    current_weight,
+                    suggested_weight,
+                    reported_count: i32::try_from(count.reported).unwrap_or(i32::MAX),
+                    fraud_count: i32::try_from(count.fraudulent).unwrap_or(i32::MAX),
+                    baseline_precision: baseline,
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(rule_code: Option<&str>, reported: i64, fraudulent: i64) -> OutcomeCountRecord {
+        OutcomeCountRecord {
+            rule_code: rule_code.map(str::to_string),
+            reported,
+            fraudulent,
+        }
+    }
+
+    #[test]
+    fn test_rules_are_weighted_by_precision_over_the_baseline() {
+        let counts = [
+            count(None, 100, 25),
+            // Four times as precise as the baseline, capped
+            count(Some("FLAGGED_USER"), 20, 20),
+            // As precise as the baseline, which its weight already reflects
+            count(Some("HOSTING_IP"), 40, 10),
+            // Less precise than the baseline
+            count(Some("LARGE_AMOUNT"), 50, 5),
+            // Too few reports to judge
+            count(Some("SHARED_DEVICE"), 10, 0),
+        ];
+        let suggestions = suggest(&counts, |_| 1.0, 20);
+        let suggested: Vec<(&str, f64)> = suggestions
+            .iter()
+            .map(|s| (s.rule_code.as_str(), s.suggested_weight))
+            .collect();
+        assert_eq!(suggested, [("FLAGGED_USER", 2.0), ("LARGE_AMOUNT", 0.4)]);
+        assert_eq!(suggestions[0].baseline_precision, 0.25);
+
+        // Without any fraud reported there is nothing to compare with
+        assert!(
+            suggest(
+                &[count(None, 30, 0), count(Some("HOSTING_IP"), 30, 0)],
+                |_| 1.0,
+                20
+            )
+            .is_empty()
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! In-process scheduler for recurring work
+//!
+//! Periodic maintenance, such as recalculating decaying user risk scores, refreshing
+//! downloaded lists, and generating reports, runs as [`ScheduledTask`]s on cron expressions
+//! from configuration. Each task runs on its own Tokio task, one run at a time: a run that
+//! outlasts its interval delays the next rather than overlapping it, and missed occurrences
+//! are not caught up. Every server runs every task, so tasks must tolerate running on several
+//! servers at once, as the interval loops they replaced did.
+//!
+//! The outcome of each task's last run is kept in a [`SchedulerStatus`], which the admin
+//! endpoint `GET /admin/scheduled-tasks` reports.
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Instant,
+};
+
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use tokio::task::JoinHandle;
+
+use crate::models::scheduler::{ScheduledTaskStatus, TaskOutcome};
+
+/// Longest error message kept in a task's status
+const MAX_ERROR_LEN: usize = 500;
+
+/// Future of one run of a scheduled task
+pub type TaskRun = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
+
+/// Parse a cron expression, with or without a leading seconds field
+///
+/// Five-field expressions (`minute hour day-of-month month day-of-week`) run at second 0.
+/// Days of the week are best given by name, as numbers count from Sunday as 1.
+pub fn parse_schedule(spec: &str) -> Result<Schedule, cron::error::Error> {
+    let spec = spec.trim();
+    if spec.split_whitespace().count() == 5 {
+        Schedule::from_str(&format!("0 {spec}"))
+    } else {
+        Schedule::from_str(spec)
+    }
+}
+
+/// Recurring unit of work and when to run it
+pub struct ScheduledTask {
+    name: &'static str,
+    schedule: Schedule,
+    run_on_start: bool,
+    run: Box<dyn Fn() -> TaskRun + Send + Sync>,
+}
+
+impl ScheduledTask {
+    /// Task called `name` running `run` on `schedule`
+    pub fn new<F, Fut>(name: &'static str, schedule: Schedule, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        Self {
+            name,
+            schedule,
+            run_on_start: false,
+            run: Box::new(move || Box::pin(run())),
+        }
+    }
+
+    /// Also run the task as soon as the scheduler starts, for work the server needs before
+    /// its first scheduled occurrence
+    pub fn run_on_start(mut self) -> Self {
+        self.run_on_start = true;
+        self
+    }
+}
+
+/// Last run of every scheduled task, shared with request handlers
+#[derive(Debug, Clone, Default)]
+pub struct SchedulerStatus {
+    tasks: Arc<RwLock<BTreeMap<&'static str, ScheduledTaskStatus>>>,
+}
+
+impl SchedulerStatus {
+    /// Status of every task, in name order
+    pub fn tasks(&self) -> Vec<ScheduledTaskStatus> {
+        let tasks = self.tasks.read().unwrap_or_else(|e| e.into_inner());
+        tasks.values().cloned().collect()
+    }
+
+    fn update(&self, name: &'static str, update: impl FnOnce(&mut ScheduledTaskStatus)) {
+        let mut tasks = self.tasks.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(status) = tasks.get_mut(name) {
+            update(status);
+        }
+    }
+
+    fn register(&self, task: &ScheduledTask) {
+        let mut tasks = self.tasks.write().unwrap_or_else(|e| e.into_inner());
+        tasks.insert(
+            task.name,
+            ScheduledTaskStatus {
+                name: task.name.to_string(),
+                schedule: task.schedule.source().to_string(),
+                running: false,
+                next_run_at: None,
+                last_started_at: None,
+                last_finished_at: None,
+                last_outcome: None,
+                last_error: None,
+                last_duration_ms: None,
+                runs: 0,
+                failures: 0,
+            },
+        );
+    }
+}
+
+/// Recurring tasks waiting to be started
+#[derive(Default)]
+pub struct Scheduler {
+    tasks: Vec<ScheduledTask>,
+    status: SchedulerStatus,
+}
+
+impl Scheduler {
+    /// Scheduler without tasks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a task, replacing any earlier task of the same name
+    pub fn add(&mut self, task: ScheduledTask) {
+        self.tasks.retain(|existing| existing.name != task.name);
+        self.status.register(&task);
+        self.tasks.push(task);
+    }
+
+    /// Status of the tasks, kept current once the scheduler is spawned
+    pub fn status(&self) -> SchedulerStatus {
+        self.status.clone()
+    }
+
+    /// Spawn one background task per scheduled task
+    pub fn spawn(self) -> Vec<JoinHandle<()>> {
+        self.tasks
+            .into_iter()
+            .map(|task| {
+                let status = self.status.clone();
+                tokio::spawn(async move {
+                    if task.run_on_start {
+                        run_task(&task, &status).await;
+                    }
+                    loop {
+                        let Some(next) = task.schedule.upcoming(Utc).next() else {
+                            tracing::warn!(task = task.name, "Scheduled task has no further runs");
+                            return;
+                        };
+                        status.update(task.name, |s| s.next_run_at = Some(next));
+                        let delay = (next - Utc::now()).to_std().unwrap_or_default();
+                        tokio::time::sleep(delay).await;
+                        run_task(&task, &status).await;
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+/// Run a task once, recording its outcome
+async fn run_task(task: &ScheduledTask, status: &SchedulerStatus) {
+    let started_at = Utc::now();
+    status.update(task.name, |s| {
+        s.running = true;
+        s.next_run_at = None;
+        s.last_started_at = Some(started_at);
+    });
+    let timer = Instant::now();
+    let outcome = (task.run)().await;
+    let elapsed = timer.elapsed();
+    if let Err(e) = &outcome {
+        tracing::error!(task = task.name, error = %e, "Scheduled task failed");
+    } else {
+        tracing::debug!(
+            task = task.name,
+            duration_ms = elapsed.as_millis() as u64,
+            "Scheduled task finished"
+        );
+    }
+    status.update(task.name, |s| record_run(s, Utc::now(), elapsed, outcome));
+}
+
+/// Record a finished run in a task's status
+fn record_run(
+    status: &mut ScheduledTaskStatus,
+    finished_at: DateTime<Utc>,
+    elapsed: std::time::Duration,
+    outcome: anyhow::Result<()>,
+) {
+    status.running = false;
+    status.last_finished_at = Some(finished_at);
+    status.last_duration_ms = Some(u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX));
+    status.runs += 1;
+    match outcome {
+        Ok(()) => {
+            status.last_outcome = Some(TaskOutcome::Succeeded);
+            status.last_error = None;
+        },
+        Err(e) => {
+            status.failures += 1;
+            status.last_outcome = Some(TaskOutcome::Failed);
+            let mut message = format!("{e:#}");
+            if message.len() > MAX_ERROR_LEN {
+                let mut end = MAX_ERROR_LEN;
+                while !message.is_char_boundary(end) {
+                    end -= 1;
+                }
+                message.truncate(end);
+            }
+            status.last_error = Some(message);
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_five_field_schedules_run_on_the_minute() {
+        let schedule = parse_schedule("*/15 * * * *").unwrap();
+        let after = Utc.with_ymd_and_hms(2025, 1, 21, 1, 31, 20).unwrap();
+        let next = schedule.after(&after).next().unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 21, 1, 45, 0).unwrap());
+
+        let schedule = parse_schedule("30 0 3 * * *").unwrap();
+        let next = schedule.after(&after).next().unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 21, 3, 0, 30).unwrap());
+
+        assert!(parse_schedule("every hour").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_runs_are_recorded_in_the_status() {
+        let mut scheduler = Scheduler::new();
+        let schedule = parse_schedule("0 0 1 1 *").unwrap();
+        scheduler.add(ScheduledTask::new("ok", schedule.clone(), || async {
+            Ok(())
+        }));
+        scheduler.add(ScheduledTask::new("broken", schedule, || async {
+            anyhow::bail!("feed unreachable")
+        }));
+        let status = scheduler.status();
+
+        for task in &scheduler.tasks {
+            run_task(task, &status).await;
+        }
+
+        let tasks = status.tasks();
+        let names: Vec<_> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["broken", "ok"]);
+        assert_eq!(tasks[0].last_outcome, Some(TaskOutcome::Failed));
+        assert_eq!(tasks[0].last_error.as_deref(), Some("feed unreachable"));
+        assert_eq!((tasks[0].runs, tasks[0].failures), (1, 1));
+        assert_eq!(tasks[1].last_outcome, Some(TaskOutcome::Succeeded));
+        assert!(!tasks[1].running);
+        assert_eq!(tasks[1].schedule, "0 0 0 1 1 *");
+    }
+}
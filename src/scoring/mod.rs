@@ -0,0 +1,871 @@
+//! Transaction risk scoring
+//!
+//! Each rule inspects the request, or what is stored about the user, device, and session it
+//! names, and may contribute a risk factor. Contributions are combined as independent
+//! probabilities, so no single rule can push the score past the ceiling and adding a factor
+//! always increases the score. Factors with negative scores vouch for a transaction instead,
+//! scaling the combined score down. A few rules reject a transaction outright, whatever the
+//! account's disposition policy, and a few send it to review when the policy would accept it.
+
+pub mod rules;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    features::FeatureSnapshot,
+    models::{
+        account::DispositionPolicy,
+        device::DeviceStatus,
+        insights::{IpTraits, PhoneLineType},
+        list::{AsnListEntry, CountryListEntry, ListEntry},
+        screening::ScreeningMatch,
+        transaction::{Disposition, RiskLevel, TransactionRequest},
+        user::UserFlag,
+    },
+    sessions::SessionSignals,
+};
+
+/// Lowest score a transaction can receive
+pub const MIN_RISK_SCORE: f64 = 0.01;
+/// Highest score a transaction can receive
+pub const MAX_RISK_SCORE: f64 = 99.99;
+
+/// A single reason contributing to a risk score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskFactor {
+    /// Machine-readable factor code
+    pub code: String,
+    /// Category of signal (amount, address, device, payment, ...)
+    pub factor_type: String,
+    /// Contribution of this factor, as a score on the 0-100 scale
+    pub score: f64,
+    /// Human-readable explanation
+    pub reason: String,
+    /// Machine-readable details of what the rule matched, for rules that have any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl RiskFactor {
+    /// Create a risk factor
+    pub fn new(code: &str, factor_type: &str, score: f64, reason: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            factor_type: factor_type.to_string(),
+            score,
+            reason: reason.into(),
+            metadata: None,
+        }
+    }
+
+    /// Attach details of what the rule matched
+    pub fn with_metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+}
+
+/// Outcome of scoring a transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskAssessment {
+    /// Combined risk score
+    pub risk_score: f64,
+    /// Risk level derived from the score
+    pub risk_level: RiskLevel,
+    /// Recommended action
+    pub disposition: Disposition,
+    /// Factors that contributed to the score
+    pub factors: Vec<RiskFactor>,
+    /// Whether a factor rejects the transaction outright, whatever the disposition policy
+    pub hard_reject: bool,
+    /// Whether a factor sends the transaction to review where it would otherwise be accepted
+    pub hard_review: bool,
+    /// Inputs the score was computed from
+    pub features: FeatureSnapshot,
+}
+
+impl RiskAssessment {
+    /// Combine factors into an assessment, with an empty feature snapshot
+    pub fn from_factors(factors: Vec<RiskFactor>) -> Self {
+        let risk_score =
+            combine_scores(factors.iter().map(|f| f.score)).clamp(MIN_RISK_SCORE, MAX_RISK_SCORE);
+        let risk_level = RiskLevel::from_score(risk_score);
+        let hard_reject = factors.iter().any(rules::rejects_outright);
+        let hard_review = factors.iter().any(rules::forces_review);
+        Self {
+            risk_score,
+            risk_level,
+            disposition: if hard_reject {
+                Disposition::Reject
+            } else {
+                review_if(hard_review, Disposition::for_risk_level(risk_level))
+            },
+            factors,
+            hard_reject,
+            hard_review,
+            features: FeatureSnapshot::default(),
+        }
+    }
+
+    /// Disposition for the assessment under an account's disposition policy
+    pub fn disposition_under(&self, policy: DispositionPolicy) -> Disposition {
+        if self.hard_reject {
+            Disposition::Reject
+        } else {
+            review_if(self.hard_review, policy.disposition(self.risk_level))
+        }
+    }
+}
+
+/// `disposition`, with acceptance turned into review if `forced`
+fn review_if(forced: bool, disposition: Disposition) -> Disposition {
+    match disposition {
+        Disposition::Accept if forced => Disposition::Review,
+        disposition => disposition,
+    }
+}
+
+/// Hours over which the distinct users of a device are counted
+pub const DEVICE_USERS_WINDOW_HOURS: i64 = 24;
+/// Hours over which transactions from an IP subnet are counted for velocity
+pub const IP_VELOCITY_WINDOW_HOURS: i64 = 24;
+/// Hours over which transactions with an address are counted for velocity
+pub const ADDRESS_VELOCITY_WINDOW_HOURS: i64 = 24;
+/// Minutes over which the cards tried from a device or IP address are counted for card testing
+pub const CARD_TESTING_WINDOW_MINUTES: i64 = 10;
+/// Order amount below which a payment counts as a small one, as card testers use to check a
+/// card works without drawing attention; applied in whatever currency the order is in
+pub const CARD_TESTING_SMALL_AMOUNT: f64 = 5.0;
+/// Most apart two card numbers of one BIN can be, without their check digit, to count as
+/// consecutive numbers of an enumeration
+const SEQUENTIAL_CARD_GAP: u32 = 2;
+/// Rejected transactions an IP address needs before its rejections count against it
+const MIN_IP_REJECTS: i64 = 3;
+/// Distinct cards above which an IP address looks like it is testing cards
+const MANY_IP_CARDS: i64 = 5;
+/// Purchases a user needs before their refunds count against them
+const MIN_REFUND_PURCHASES: i64 = 3;
+/// Share of a user's purchases refunded above which the user looks like they abuse refunds
+const REFUND_ABUSE_RATE: f64 = 0.3;
+
+/// What earlier transactions from an IP address say about it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IpHistory {
+    /// Transactions from the address
+    pub transactions: i64,
+    /// Of those, transactions rejected
+    pub rejects: i64,
+    /// Of those, transactions reported as chargebacks
+    pub chargebacks: i64,
+    /// Distinct users the transactions belonged to
+    pub users: i64,
+    /// Distinct payment cards the transactions used
+    pub cards: i64,
+}
+
+impl IpHistory {
+    /// Whether at least half of several transactions from the address were rejected
+    pub fn mostly_rejected(&self) -> bool {
+        self.rejects >= MIN_IP_REJECTS && self.rejects * 2 >= self.transactions
+    }
+
+    /// Whether the address was used with more cards than a household plausibly owns
+    pub fn many_cards(&self) -> bool {
+        self.cards > MANY_IP_CARDS
+    }
+}
+
+/// Earlier purchases of a user and how many of them were refunded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefundHistory {
+    /// Purchases the user made
+    pub purchases: i64,
+    /// Of those, purchases with a refund
+    pub refunded: i64,
+}
+
+impl RefundHistory {
+    /// Whether more than [`REFUND_ABUSE_RATE`] of several purchases were refunded
+    pub fn frequent_refunds(&self) -> bool {
+        self.purchases >= MIN_REFUND_PURCHASES
+            && self.refunded as f64 > self.purchases as f64 * REFUND_ABUSE_RATE
+    }
+}
+
+/// Recent transactions of the account from the subnet of an IP address
+///
+/// Subnet prefix lengths are configured, by default a /64 for IPv6 and the single address for
+/// IPv4, so clients rotating addresses within their assignment still count as one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpVelocity {
+    /// The subnet, in CIDR notation
+    pub subnet: String,
+    /// Transactions from the subnet in the last hour
+    pub last_hour: i64,
+    /// Transactions from the subnet in the last [`IP_VELOCITY_WINDOW_HOURS`]
+    pub last_day: i64,
+    /// Distinct users of those transactions
+    pub users_last_day: i64,
+}
+
+/// Recent transactions of the account with the billing or shipping address of a transaction,
+/// however the address was written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AddressVelocity {
+    /// Transactions with either address in the last hour
+    pub last_hour: i64,
+    /// Transactions with either address in the last [`ADDRESS_VELOCITY_WINDOW_HOURS`]
+    pub last_day: i64,
+    /// Distinct users of those transactions
+    pub users_last_day: i64,
+}
+
+/// A card payment tried from a device or IP address
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardAttempt {
+    /// Issuer ID number of the card
+    pub issuer_id_number: Option<String>,
+    /// Last digits of the card
+    pub last_digits: Option<String>,
+    /// Order amount, in the order's currency
+    pub amount: Option<f64>,
+    /// Whether the payment was rejected, or failed the card's security code or 3-D Secure check
+    pub declined: bool,
+}
+
+impl CardAttempt {
+    /// The card payment `request` tries, if it has a card; whether it is rejected is not known
+    /// until it is scored
+    pub fn from_request(request: &TransactionRequest) -> Option<Self> {
+        let card = request.credit_card.as_ref()?;
+        let cvv_failed = card
+            .cvv_result
+            .as_deref()
+            .is_some_and(|cvv| cvv.eq_ignore_ascii_case("N"));
+        Some(Self {
+            issuer_id_number: card.issuer_id_number.clone(),
+            last_digits: card.last_digits.clone(),
+            amount: request.order.as_ref().map(|order| order.amount),
+            declined: cvv_failed || card.was_3d_secure_successful == Some(false),
+        })
+    }
+
+    /// Whether the payment was for less than [`CARD_TESTING_SMALL_AMOUNT`]
+    fn is_small(&self) -> bool {
+        self.amount
+            .is_some_and(|amount| amount < CARD_TESTING_SMALL_AMOUNT)
+    }
+}
+
+/// Cards tried from the device or IP address of a transaction in the last
+/// [`CARD_TESTING_WINDOW_MINUTES`], counting the transaction's own
+///
+/// Card testers run lists of stolen or generated card numbers through a checkout, often from one
+/// device, with small payments that many issuers decline, and sometimes with numbers counted up
+/// from one card.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CardTesting {
+    /// Card payments tried
+    pub attempts: i64,
+    /// Distinct issuer ID numbers of the cards
+    pub distinct_bins: i64,
+    /// Distinct cards, by issuer ID number and last digits
+    pub distinct_cards: i64,
+    /// Payments for less than [`CARD_TESTING_SMALL_AMOUNT`]
+    pub small_attempts: i64,
+    /// Of those, payments declined
+    pub declined_small_attempts: i64,
+    /// Most cards of one BIN whose numbers run in sequence
+    pub sequential_cards: i64,
+}
+
+impl CardTesting {
+    /// Count the patterns of card testing among `attempts`
+    pub fn from_attempts(attempts: &[CardAttempt]) -> Self {
+        let bins: HashSet<&str> = attempts
+            .iter()
+            .filter_map(|attempt| attempt.issuer_id_number.as_deref())
+            .collect();
+        let cards: HashSet<(&str, &str)> = attempts
+            .iter()
+            .filter_map(|attempt| {
+                Some((
+                    attempt.issuer_id_number.as_deref()?,
+                    attempt.last_digits.as_deref()?,
+                ))
+            })
+            .collect();
+        let small = attempts.iter().filter(|attempt| attempt.is_small());
+
+        // Card numbers of a BIN without their check digit, which Luhn makes jump around
+        let mut numbers: HashMap<&str, BTreeSet<u32>> = HashMap::new();
+        for (bin, last_digits) in &cards {
+            let account_digits = last_digits.get(..last_digits.len().saturating_sub(1));
+            if let Some(number) = account_digits
+                .filter(|digits| digits.len() >= 2)
+                .and_then(|digits| digits.parse().ok())
+            {
+                numbers.entry(*bin).or_default().insert(number);
+            }
+        }
+        let sequential_cards = numbers
+            .values()
+            .map(|numbers| longest_run(numbers, SEQUENTIAL_CARD_GAP))
+            .max()
+            .unwrap_or_default();
+
+        Self {
+            attempts: attempts.len() as i64,
+            distinct_bins: bins.len() as i64,
+            distinct_cards: cards.len() as i64,
+            small_attempts: small.clone().count() as i64,
+            declined_small_attempts: small.filter(|attempt| attempt.declined).count() as i64,
+            sequential_cards: sequential_cards as i64,
+        }
+    }
+}
+
+/// Most of `numbers` in a run where each is at most `gap` above the one before
+fn longest_run(numbers: &BTreeSet<u32>, gap: u32) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<u32> = None;
+    for &number in numbers {
+        run = match previous {
+            Some(previous) if number - previous <= gap => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(number);
+    }
+    longest
+}
+
+/// How far and how fast a user moved since their last located transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GeoTravel {
+    /// Distance in kilometres between the two transactions' IP locations
+    pub distance_km: f64,
+    /// Hours between the two transactions
+    pub hours: f64,
+}
+
+impl GeoTravel {
+    /// Average speed in km/h the user would have travelled at
+    pub fn speed_kmh(&self) -> f64 {
+        self.distance_km / self.hours.max(f64::EPSILON)
+    }
+}
+
+/// What email domain lists and mail servers say about the domain of an email address
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmailTraits {
+    /// Whether anyone can sign up for an address at the domain
+    pub is_free: bool,
+    /// Whether the domain hands out throwaway addresses
+    pub is_disposable: bool,
+}
+
+/// Other addresses of the account delivering to the same mailbox as the transaction's email
+///
+/// Plus tags and, on Gmail, dots make any number of addresses out of one mailbox, a common way
+/// to open many accounts for one person's promotions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmailVariants {
+    /// Distinct other addresses delivering to the mailbox
+    pub addresses: i64,
+    /// Users other than the transaction's own those addresses were last used by
+    pub users: i64,
+}
+
+/// How long ago the transaction's email address was first seen, and the good history it has
+/// built up on the account since
+///
+/// Fraudsters open fresh addresses for each run of stolen cards, so an address nobody has seen
+/// before is riskier until its purchases go through without complaint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmailAge {
+    /// Whole days since the account or any other live account first saw the address, 0 for an
+    /// address never seen before
+    pub days: i64,
+    /// Whole days since the account itself first saw the address, 0 if it never has
+    pub account_days: i64,
+    /// The account's earlier transactions with the address that were accepted and not reported
+    /// as fraud
+    pub good_transactions: i64,
+}
+
+impl EmailAge {
+    /// Age as of `today` of an address the account first saw on `first_seen` and any live
+    /// account on `global_first_seen`
+    pub fn new(
+        first_seen: Option<NaiveDate>,
+        global_first_seen: Option<NaiveDate>,
+        good_transactions: i64,
+        today: NaiveDate,
+    ) -> Self {
+        let days_since =
+            |day: Option<NaiveDate>| day.map_or(0, |day| (today - day).num_days().max(0));
+        let earliest = first_seen.into_iter().chain(global_first_seen).min();
+        Self {
+            days: days_since(earliest),
+            account_days: days_since(first_seen),
+            good_transactions,
+        }
+    }
+}
+
+/// What the numbering plan says about a phone number
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhoneNumberInfo {
+    /// The number in E.164 form
+    pub e164: String,
+    /// Whether the number fits a range of the numbering plan of its country calling code
+    pub is_valid: bool,
+    /// ISO 3166-1 alpha-2 country the number's range is allocated to, unless the range is
+    /// shared by several countries
+    pub country: Option<String>,
+    /// Kind of line, for valid numbers
+    pub line_type: Option<PhoneLineType>,
+    /// Operator the number's range was originally allocated to, if known
+    pub operator: Option<String>,
+}
+
+/// What a BIN table says about the range of cards an issuer ID number belongs to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BinInfo {
+    /// Leading digits of the card numbers in the range, 4 to 8 of them
+    pub prefix: String,
+    /// Card network, in lowercase, such as `visa`
+    pub brand: Option<String>,
+    /// `credit`, `debit`, or `charge`
+    pub card_type: Option<String>,
+    /// ISO 3166-1 alpha-2 country the cards are issued in
+    pub country: Option<String>,
+    /// Name of the issuing bank
+    pub bank_name: Option<String>,
+    /// Whether the cards are prepaid
+    pub is_prepaid: bool,
+    /// Whether the cards are issued to businesses
+    pub is_business: bool,
+    /// Whether the cards are virtual, issued for online payments without a physical card
+    pub is_virtual: bool,
+}
+
+/// When a transaction happened for the customer, in the time zone of its IP address
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalTime {
+    /// Wall-clock time of the transaction
+    pub time: DateTime<FixedOffset>,
+    /// Local hours of day (0-23) the user habitually purchases in, empty without a reliable
+    /// baseline
+    pub usual_hours: Vec<u32>,
+}
+
+impl LocalTime {
+    /// Hour of day (0-23) of the transaction
+    pub fn hour(&self) -> u32 {
+        self.time.hour()
+    }
+
+    /// Whether the transaction falls outside the hours the user habitually purchases in
+    pub fn is_unusual_hour(&self) -> bool {
+        !self.usual_hours.is_empty() && !self.usual_hours.contains(&self.hour())
+    }
+}
+
+/// A name a transaction gives that matches a party on a sanctions list
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanctionsHit {
+    /// Part of the transaction the name comes from, such as `Billing` or `Cardholder`
+    pub role: &'static str,
+    /// The name as the transaction gives it
+    pub name: String,
+    /// The closest matching party
+    pub party: ScreeningMatch,
+}
+
+/// What is stored about the user a transaction names, the device and IP address it comes
+/// from, and the session it belongs to, as of when it is scored
+///
+/// Anonymous transactions and users, devices, and sessions seen for the first time have no
+/// signals.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserSignals {
+    /// Existing user the transaction belongs to
+    pub user_id: Option<Uuid>,
+    /// Flags still in force
+    pub active_flags: Vec<UserFlag>,
+    /// Distinct users seen with the transaction's device in the last
+    /// [`DEVICE_USERS_WINDOW_HOURS`], counting the transaction's own user
+    pub device_user_count: i64,
+    /// Whether the transaction's device was previously tied to a chargeback, through a user
+    /// seen with it or a transaction from it
+    pub device_chargebacks: bool,
+    /// Whether the transaction's device is trusted, blocked, or neither
+    pub device_status: DeviceStatus,
+    /// Earlier purchases of the transaction's user and how many of them were refunded
+    pub refunds: RefundHistory,
+    /// What earlier events of the transaction's session say about it
+    pub session: SessionSignals,
+    /// What anonymous IP feeds say about the transaction's IP address
+    pub ip_traits: IpTraits,
+    /// What email domain lists say about the transaction's email address
+    pub email_traits: EmailTraits,
+    /// Other addresses of the account delivering to the mailbox of the transaction's email
+    pub email_variants: EmailVariants,
+    /// When the transaction's email address was first seen, if it has one that could be hashed
+    pub email_age: Option<EmailAge>,
+    /// What the numbering plan says about the transaction's phone number, if it has one with
+    /// a country calling code the plan covers
+    pub phone: Option<PhoneNumberInfo>,
+    /// What the account's BIN table, or else the bundled one, says about the transaction's card
+    pub card_bin: Option<BinInfo>,
+    /// Earlier transactions of the account from the transaction's IP address
+    pub ip_history: IpHistory,
+    /// Transactions of other accounts from the transaction's IP address
+    pub ip_global_history: IpHistory,
+    /// Earlier transactions of the account from the subnet of the transaction's IP address
+    pub ip_velocity: IpVelocity,
+    /// Earlier transactions of the account with the transaction's billing or shipping address
+    pub address_velocity: AddressVelocity,
+    /// Cards recently tried from the transaction's device or IP address
+    pub card_testing: CardTesting,
+    /// Travel since the user's last located transaction, if both could be located
+    pub travel: Option<GeoTravel>,
+    /// The account's listing of the network the transaction's IP address belongs to
+    pub asn_listing: Option<AsnListEntry>,
+    /// ISO 3166-1 alpha-2 code of the country the transaction's IP address is located in
+    pub ip_country: Option<String>,
+    /// The account's listings of the countries the transaction involves
+    pub country_listings: Vec<CountryListEntry>,
+    /// The account's blocklist, allowlist, and watchlist entries in force naming the
+    /// transaction's email address or domain, IP address or a range around it, device, card,
+    /// user, or countries, along with the blocklist entries its organization shares
+    pub list_entries: Vec<ListEntry>,
+    /// Distance in kilometres from the billing address to the IP address location, if both
+    /// could be located
+    pub billing_ip_distance_km: Option<f64>,
+    /// Distance in kilometres from the shipping address to the IP address location, if both
+    /// could be located
+    pub shipping_ip_distance_km: Option<f64>,
+    /// Local time of the transaction, if its IP address could be located in a time zone
+    pub local_time: Option<LocalTime>,
+    /// Names the transaction gives that match parties on sanctions lists, when the account
+    /// screens them
+    pub sanctions_hits: Vec<SanctionsHit>,
+}
+
+impl UserSignals {
+    /// Signals of a user carrying `flags`, leaving out those expired by `at`
+    pub fn new(flags: Vec<UserFlag>, at: DateTime<Utc>) -> Self {
+        Self {
+            active_flags: flags
+                .into_iter()
+                .filter(|flag| flag.is_active(at))
+                .collect(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Combine independent factor scores into a single 0-100 score
+///
+/// Negative scores scale the combined positive scores down: -50 halves the result.
+pub fn combine_scores(scores: impl IntoIterator<Item = f64>) -> f64 {
+    let (clean, relief) = scores.into_iter().fold((1.0, 1.0), |(clean, relief), s| {
+        let p = (s / 100.0).clamp(-1.0, 1.0);
+        if p >= 0.0 {
+            (clean * (1.0 - p), relief)
+        } else {
+            (clean, relief * (1.0 + p))
+        }
+    });
+    let score = (1.0 - clean) * relief * 100.0;
+    (score * 100.0).round() / 100.0
+}
+
+/// Weights an account's rule version scales the scores of its rules by
+///
+/// Rules without a weight keep their built-in score, as do all rules of an account that never
+/// versioned its weights, which scores under version 0.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleWeights {
+    /// Version the weights belong to
+    pub version: i32,
+    /// Weight of each reweighted rule, by factor code
+    pub weights: HashMap<String, f64>,
+}
+
+impl RuleWeights {
+    /// Weight of the rule producing factors with `code`
+    pub fn weight(&self, code: &str) -> f64 {
+        self.weights.get(code).copied().unwrap_or(1.0)
+    }
+
+    /// `factor` with its score scaled by the weight of its rule
+    fn apply(&self, mut factor: RiskFactor) -> RiskFactor {
+        let weight = self.weight(&factor.code);
+        if weight != 1.0 {
+            factor.score = (factor.score * weight * 100.0).round() / 100.0;
+        }
+        factor
+    }
+}
+
+/// Rule-based scoring engine
+#[derive(Debug, Clone, Default)]
+pub struct RiskEngine;
+
+impl RiskEngine {
+    /// Create a scoring engine with the built-in rule set
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Score a transaction request from a user with the given signals
+    pub fn assess(&self, request: &TransactionRequest, user: &UserSignals) -> RiskAssessment {
+        self.assess_weighted(request, user, &RuleWeights::default())
+    }
+
+    /// Score a transaction request from a user with the given signals, scaling each rule's
+    /// score by its weight
+    ///
+    /// Weights change how much a rule adds to the score, never whether its factor rejects the
+    /// transaction outright or sends it to review.
+    pub fn assess_weighted(
+        &self,
+        request: &TransactionRequest,
+        user: &UserSignals,
+        weights: &RuleWeights,
+    ) -> RiskAssessment {
+        let mut factors = rules::evaluate_all(request);
+        factors.extend(rules::evaluate_user(user));
+        factors.extend(rules::evaluate_context(request, user));
+        let factors = factors
+            .into_iter()
+            .map(|factor| weights.apply(factor))
+            .collect();
+        RiskAssessment {
+            features: FeatureSnapshot::capture(request, user),
+            ..RiskAssessment::from_factors(factors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::list::CountryListAction;
+
+    #[test]
+    fn test_combine_scores() {
+        assert_eq!(combine_scores([]), 0.0);
+        assert_eq!(combine_scores([50.0]), 50.0);
+        assert_eq!(combine_scores([50.0, 50.0]), 75.0);
+        assert_eq!(combine_scores([150.0]), 100.0);
+        assert_eq!(combine_scores([50.0, 50.0, -50.0]), 37.5);
+        assert_eq!(combine_scores([-50.0]), 0.0);
+    }
+
+    #[test]
+    fn test_assessment_is_clamped() {
+        let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+
+        let assessment = RiskEngine::new().assess(&request, &UserSignals::default());
+        assert_eq!(assessment.risk_score, MIN_RISK_SCORE);
+        assert_eq!(assessment.risk_level, RiskLevel::Low);
+        assert_eq!(assessment.disposition, Disposition::Accept);
+    }
+
+    #[test]
+    fn test_only_active_flags_raise_the_score() {
+        let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let flags: Vec<UserFlag> = serde_json::from_value(serde_json::json!([
+            { "type": "watch", "expires_at": "2025-07-13T00:00:00Z" }
+        ]))
+        .unwrap();
+        let expires_at = flags[0].expires_at.unwrap();
+        let engine = RiskEngine::new();
+
+        let active = UserSignals::new(flags.clone(), expires_at - chrono::Duration::hours(1));
+        let assessment = engine.assess(&request, &active);
+        assert!(assessment.risk_score > MIN_RISK_SCORE);
+        assert_eq!(assessment.features.active_user_flags, 1);
+
+        let expired = UserSignals::new(flags, expires_at);
+        assert!(expired.active_flags.is_empty());
+        assert_eq!(engine.assess(&request, &expired).risk_score, MIN_RISK_SCORE);
+    }
+
+    #[test]
+    fn test_rule_weights_scale_scores_but_not_hard_rejections() {
+        let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let flags: Vec<UserFlag> =
+            serde_json::from_value(serde_json::json!([{ "type": "watch" }])).unwrap();
+        let user = UserSignals {
+            device_status: DeviceStatus::Blocked,
+            ..UserSignals::new(flags, Utc::now())
+        };
+        let weights = RuleWeights {
+            version: 2,
+            weights: HashMap::from([
+                ("FLAGGED_USER".to_string(), 0.5),
+                ("BLOCKED_DEVICE".to_string(), 0.0),
+            ]),
+        };
+
+        let assessment = RiskEngine::new().assess_weighted(&request, &user, &weights);
+        let flagged = assessment
+            .factors
+            .iter()
+            .find(|factor| factor.code == "FLAGGED_USER")
+            .unwrap();
+        assert_eq!(flagged.score, 15.0);
+        assert_eq!(assessment.risk_score, 15.0);
+        assert!(assessment.hard_reject);
+        assert_eq!(assessment.disposition, Disposition::Reject);
+    }
+
+    #[test]
+    fn test_blocked_device_rejects_under_any_policy() {
+        let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let engine = RiskEngine::new();
+
+        let blocked = UserSignals {
+            device_status: DeviceStatus::Blocked,
+            ..UserSignals::default()
+        };
+        let assessment = engine.assess(&request, &blocked);
+        assert!(assessment.hard_reject);
+        assert_eq!(
+            assessment.disposition_under(DispositionPolicy::Monitor),
+            Disposition::Reject
+        );
+
+        let trusted = UserSignals {
+            device_status: DeviceStatus::Trusted,
+            ..UserSignals::default()
+        };
+        let assessment = engine.assess(&request, &trusted);
+        assert!(!assessment.hard_reject);
+        assert_eq!(assessment.risk_score, MIN_RISK_SCORE);
+        assert_eq!(
+            assessment.disposition_under(DispositionPolicy::Standard),
+            Disposition::Accept
+        );
+    }
+
+    #[test]
+    fn test_email_age_counts_from_the_earliest_sighting() {
+        let day = |d: u32| NaiveDate::from_ymd_opt(2025, 7, d).unwrap();
+        // Seen elsewhere before the account saw it
+        assert_eq!(
+            EmailAge::new(Some(day(10)), Some(day(1)), 2, day(12)),
+            EmailAge {
+                days: 11,
+                account_days: 2,
+                good_transactions: 2,
+            }
+        );
+        // Only ever seen by a sandbox account itself
+        assert_eq!(EmailAge::new(Some(day(10)), None, 0, day(12)).days, 2);
+        // Never seen before
+        assert_eq!(EmailAge::new(None, None, 0, day(12)), EmailAge::default());
+        // Clock skew does not make an address younger than new
+        assert_eq!(EmailAge::new(None, Some(day(13)), 0, day(12)).days, 0);
+    }
+
+    #[test]
+    fn test_card_testing_patterns() {
+        let attempt = |bin: &str, last_digits: &str, amount: f64, declined: bool| CardAttempt {
+            issuer_id_number: Some(bin.to_string()),
+            last_digits: Some(last_digits.to_string()),
+            amount: Some(amount),
+            declined,
+        };
+        let testing = CardTesting::from_attempts(&[
+            attempt("411111", "1234", 1.0, true),
+            attempt("411111", "1242", 1.0, true),
+            attempt("411111", "1257", 0.5, false),
+            attempt("411111", "1257", 0.5, true),
+            attempt("510510", "5100", 20.0, true),
+            attempt("371449", "8431", 2.0, false),
+        ]);
+        assert_eq!(testing.attempts, 6);
+        assert_eq!(testing.distinct_bins, 3);
+        assert_eq!(testing.distinct_cards, 5);
+        assert_eq!(testing.small_attempts, 5);
+        assert_eq!(testing.declined_small_attempts, 3);
+        // 123, 124, and 125 without their check digits
+        assert_eq!(testing.sequential_cards, 3);
+
+        // Numbers far apart, or of different BINs, are not a sequence
+        let testing = CardTesting::from_attempts(&[
+            attempt("411111", "1234", 50.0, false),
+            attempt("411111", "9876", 50.0, false),
+            attempt("400005", "1242", 50.0, false),
+        ]);
+        assert_eq!(testing.sequential_cards, 1);
+        assert_eq!(testing.small_attempts, 0);
+        assert_eq!(CardTesting::from_attempts(&[]), CardTesting::default());
+    }
+
+    #[test]
+    fn test_review_country_only_raises_the_disposition() {
+        let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "billing": { "country": "NG" }
+        }))
+        .unwrap();
+        let listed = |action| UserSignals {
+            country_listings: vec![CountryListEntry {
+                country: "NG".to_string(),
+                action,
+                score: None,
+                reason: None,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            }],
+            ..UserSignals::default()
+        };
+        let engine = RiskEngine::new();
+
+        let review = engine.assess(&request, &listed(CountryListAction::Review));
+        assert!(review.hard_review);
+        assert_eq!(review.risk_level, RiskLevel::Low);
+        assert_eq!(review.disposition, Disposition::Review);
+        assert_eq!(
+            review.disposition_under(DispositionPolicy::Monitor),
+            Disposition::Review
+        );
+
+        let blocked = engine.assess(&request, &listed(CountryListAction::Block));
+        assert!(blocked.hard_reject);
+        assert_eq!(
+            blocked.disposition_under(DispositionPolicy::Monitor),
+            Disposition::Reject
+        );
+    }
+}
@@ -0,0 +1,2121 @@
+//! Built-in fraud rules
+
+use serde_json::json;
+
+use crate::{
+    models::{
+        device::DeviceStatus,
+        insights::PhoneLineType,
+        list::{AsnListAction, CountryListAction, CountryListEntry, ListEntityType, ListType},
+        transaction::{EventType, TransactionRequest},
+    },
+    scoring::{
+        ADDRESS_VELOCITY_WINDOW_HOURS, CARD_TESTING_WINDOW_MINUTES, DEVICE_USERS_WINDOW_HOURS,
+        IP_VELOCITY_WINDOW_HOURS, MAX_RISK_SCORE, RiskFactor, UserSignals,
+    },
+    sessions::SessionSignals,
+    utils::{geo::calculate_velocity_risk, tls, ua},
+};
+
+/// Order amount above which a purchase is considered large
+const LARGE_AMOUNT_THRESHOLD: f64 = 1_000.0;
+/// Order amount above which a purchase is considered very large
+const VERY_LARGE_AMOUNT_THRESHOLD: f64 = 5_000.0;
+/// Distinct users a device may be seen with in the counting window before it counts as shared
+const SHARED_DEVICE_MAX_USERS: i64 = 5;
+/// Earlier transactions from an IP subnet in the last hour above which it is scripted
+const IP_SUBNET_MAX_HOURLY: i64 = 10;
+/// Distinct users of an IP subnet's transactions in the counting window above which it looks
+/// like it is cycling through accounts
+const IP_SUBNET_MAX_USERS: i64 = 5;
+/// Other users seen with variants of an email mailbox above which the mailbox looks tumbled
+/// across accounts
+const EMAIL_VARIANT_MAX_USERS: i64 = 1;
+/// Days since an email address was first seen below which it is brand new
+const NEW_EMAIL_MAX_DAYS: i64 = 7;
+/// Score of a brand-new email address on a large order, halved for each good transaction the
+/// address has had on the account
+const NEW_EMAIL_LARGE_AMOUNT_SCORE: f64 = 30.0;
+/// Good transactions after which a brand-new email address no longer counts against an order
+const NEW_EMAIL_MAX_GOOD_TRANSACTIONS: i64 = 3;
+/// Earlier transactions with a billing or shipping address in the last hour above which it is
+/// scripted
+const ADDRESS_MAX_HOURLY: i64 = 5;
+/// Distinct users of a billing or shipping address's transactions in the counting window above
+/// which it looks like a drop address
+const ADDRESS_MAX_USERS: i64 = 3;
+/// Distinct BINs tried from a device or IP address within minutes above which it is testing
+/// cards
+const CARD_TESTING_MAX_BINS: i64 = 3;
+/// Declined small payments from a device or IP address within minutes at which it is testing
+/// cards
+const CARD_TESTING_MIN_DECLINED_SMALL: i64 = 3;
+/// Cards of one BIN with numbers in sequence tried from a device or IP address within minutes at
+/// which it is testing cards
+const CARD_TESTING_MIN_SEQUENTIAL: i64 = 3;
+/// Match between the cardholder and billing names below which they are different people
+const CARDHOLDER_NAME_MIN_MATCH: f64 = 0.5;
+/// Score of a billing country that differs from the IP address country
+const BILLING_IP_COUNTRY_MISMATCH_SCORE: f64 = 20.0;
+/// Score of a billing country that differs from the IP address country when either is on the
+/// account's country list
+const LISTED_BILLING_IP_COUNTRY_MISMATCH_SCORE: f64 = 35.0;
+/// Distance in kilometres from the IP address location beyond which an address in the same
+/// country is far from where the customer is
+const ADDRESS_IP_MAX_DISTANCE_KM: f64 = 1_000.0;
+/// Seconds after sign-up within which a purchase in the same session is suspicious
+const SESSION_SIGNUP_PURCHASE_SECONDS: f64 = 60.0;
+/// Score of a transaction naming an entity on the account's watchlist
+const WATCHLIST_SCORE: f64 = 40.0;
+/// Score of a transaction naming an entity on the account's allowlist, scaling the rest of its
+/// score down by four fifths
+const ALLOWLIST_SCORE: f64 = -80.0;
+/// Score of a transaction giving a name that matches a party on a sanctions list
+const SANCTIONS_MATCH_SCORE: f64 = 50.0;
+
+/// A stateless rule over the submitted request
+type Rule = fn(&TransactionRequest) -> Option<RiskFactor>;
+
+/// A rule over what is stored about the transaction's user and device
+type UserRule = fn(&UserSignals) -> Option<RiskFactor>;
+
+/// A rule over the submitted request together with what is stored about it
+type ContextRule = fn(&TransactionRequest, &UserSignals) -> Option<RiskFactor>;
+
+/// Built-in rules, evaluated in order
+const RULES: &[Rule] = &[
+    large_amount,
+    billing_shipping_country_mismatch,
+    card_country_mismatch,
+    cardholder_name_mismatch,
+    cvv_mismatch,
+    avs_mismatch,
+    failed_3d_secure,
+    missing_user_agent,
+    bot_user_agent,
+    known_bad_tls_fingerprint,
+    tls_user_agent_mismatch,
+];
+
+/// Built-in user rules, evaluated in order after the request rules
+const USER_RULES: &[UserRule] = &[
+    blocked_device,
+    blocklisted,
+    card_testing,
+    trusted_device,
+    allowlisted,
+    watchlisted,
+    flagged_user,
+    refund_abuse,
+    shared_device,
+    chargeback_device,
+    session_signup_purchase,
+    session_country_switch,
+    session_ip_switch,
+    tor_exit_node,
+    anonymous_ip,
+    hosting_ip,
+    ip_chargeback_history,
+    ip_reject_history,
+    ip_many_cards,
+    ip_global_history,
+    ip_subnet_velocity,
+    ip_subnet_many_users,
+    address_velocity,
+    address_many_users,
+    impossible_travel,
+    blocked_asn,
+    listed_asn,
+    email_variants,
+    voip_phone,
+    prepaid_card,
+    virtual_card,
+    sanctions_match,
+];
+
+/// Built-in context rules, evaluated in order after the user rules
+const CONTEXT_RULES: &[ContextRule] = &[
+    disposable_email,
+    new_email_large_amount,
+    blocked_country,
+    review_country,
+    high_risk_country,
+    billing_ip_country_mismatch,
+    shipping_ip_country_mismatch,
+    billing_far_from_ip,
+    shipping_far_from_ip,
+    unusual_local_hour,
+    phone_country_mismatch,
+    bin_country_mismatch,
+    business_card_free_email,
+];
+
+/// Codes of the factors that reject a transaction outright
+const HARD_REJECT_CODES: &[&str] = &[
+    "BLOCKED_DEVICE",
+    "BLOCKLISTED",
+    "CARD_TESTING",
+    "BLOCKED_ASN",
+    "BLOCKED_COUNTRY",
+];
+
+/// Codes of the factors that send a transaction the policy would accept to review
+const HARD_REVIEW_CODES: &[&str] = &["REVIEW_COUNTRY", "SANCTIONS_MATCH"];
+
+/// Evaluate every built-in rule against a request
+pub fn evaluate_all(request: &TransactionRequest) -> Vec<RiskFactor> {
+    RULES.iter().filter_map(|rule| rule(request)).collect()
+}
+
+/// Evaluate every built-in user rule against the transaction's user and device
+pub fn evaluate_user(user: &UserSignals) -> Vec<RiskFactor> {
+    USER_RULES.iter().filter_map(|rule| rule(user)).collect()
+}
+
+/// Evaluate every built-in context rule against a request and its user and device
+pub fn evaluate_context(request: &TransactionRequest, user: &UserSignals) -> Vec<RiskFactor> {
+    CONTEXT_RULES
+        .iter()
+        .filter_map(|rule| rule(request, user))
+        .collect()
+}
+
+/// Whether a factor rejects its transaction outright, whatever the disposition policy
+pub fn rejects_outright(factor: &RiskFactor) -> bool {
+    HARD_REJECT_CODES.contains(&factor.code.as_str())
+}
+
+/// Whether a factor sends its transaction to review where the disposition policy would accept
+/// it
+pub fn forces_review(factor: &RiskFactor) -> bool {
+    HARD_REVIEW_CODES.contains(&factor.code.as_str())
+}
+
+/// Countries a transaction involves, each with the part it plays: the billing, shipping, and
+/// card countries of the request, and the country its IP address is located in
+pub fn transaction_countries<'a>(
+    request: &'a TransactionRequest,
+    ip_country: Option<&'a str>,
+) -> impl Iterator<Item = (&'static str, &'a str)> {
+    [
+        (
+            "Billing",
+            request.billing.as_ref().and_then(|b| b.country.as_deref()),
+        ),
+        (
+            "Shipping",
+            request
+                .shipping
+                .as_ref()
+                .and_then(|s| s.address.country.as_deref()),
+        ),
+        (
+            "Card",
+            request
+                .credit_card
+                .as_ref()
+                .and_then(|c| c.country.as_deref()),
+        ),
+        ("IP address", ip_country),
+    ]
+    .into_iter()
+    .filter_map(|(role, country)| Some((role, country?)))
+}
+
+fn large_amount(request: &TransactionRequest) -> Option<RiskFactor> {
+    let order = request.order.as_ref()?;
+    if order.amount >= VERY_LARGE_AMOUNT_THRESHOLD {
+        Some(RiskFactor::new(
+            "VERY_LARGE_AMOUNT",
+            "amount",
+            35.0,
+            format!(
+                "Order amount {:.2} {} is very large",
+                order.amount, order.currency
+            ),
+        ))
+    } else if order.amount >= LARGE_AMOUNT_THRESHOLD {
+        Some(RiskFactor::new(
+            "LARGE_AMOUNT",
+            "amount",
+            20.0,
+            format!(
+                "Order amount {:.2} {} is large",
+                order.amount, order.currency
+            ),
+        ))
+    } else {
+        None
+    }
+}
+
+fn billing_shipping_country_mismatch(request: &TransactionRequest) -> Option<RiskFactor> {
+    let billing = request.billing.as_ref()?.country.as_deref()?;
+    let shipping = request.shipping.as_ref()?.address.country.as_deref()?;
+    (billing != shipping).then(|| {
+        RiskFactor::new(
+            "BILLING_SHIPPING_COUNTRY_MISMATCH",
+            "address",
+            15.0,
+            format!("Billing country {billing} differs from shipping country {shipping}"),
+        )
+    })
+}
+
+fn card_country_mismatch(request: &TransactionRequest) -> Option<RiskFactor> {
+    let card = request.credit_card.as_ref()?.country.as_deref()?;
+    let billing = request.billing.as_ref()?.country.as_deref()?;
+    (card != billing).then(|| {
+        RiskFactor::new(
+            "CARD_COUNTRY_MISMATCH",
+            "payment",
+            15.0,
+            format!("Card issued in {card} but billing country is {billing}"),
+        )
+    })
+}
+
+fn cardholder_name_mismatch(request: &TransactionRequest) -> Option<RiskFactor> {
+    let score = request.cardholder_billing_match()?;
+    (score < CARDHOLDER_NAME_MIN_MATCH).then(|| {
+        RiskFactor::new(
+            "CARDHOLDER_NAME_MISMATCH",
+            "payment",
+            20.0,
+            format!(
+                "Cardholder name does not match the billing name ({:.0}% similar)",
+                score * 100.0
+            ),
+        )
+    })
+}
+
+fn cvv_mismatch(request: &TransactionRequest) -> Option<RiskFactor> {
+    let cvv = request.credit_card.as_ref()?.cvv_result.as_deref()?;
+    cvv.eq_ignore_ascii_case("N").then(|| {
+        RiskFactor::new(
+            "CVV_MISMATCH",
+            "payment",
+            25.0,
+            "Card security code did not match",
+        )
+    })
+}
+
+fn avs_mismatch(request: &TransactionRequest) -> Option<RiskFactor> {
+    let avs = request.credit_card.as_ref()?.avs_result.as_deref()?;
+    avs.eq_ignore_ascii_case("N").then(|| {
+        RiskFactor::new(
+            "AVS_MISMATCH",
+            "payment",
+            10.0,
+            "Neither billing address nor postal code matched the card",
+        )
+    })
+}
+
+fn failed_3d_secure(request: &TransactionRequest) -> Option<RiskFactor> {
+    let successful = request.credit_card.as_ref()?.was_3d_secure_successful?;
+    (!successful).then(|| {
+        RiskFactor::new(
+            "FAILED_3D_SECURE",
+            "payment",
+            20.0,
+            "3D Secure authentication failed",
+        )
+    })
+}
+
+fn missing_user_agent(request: &TransactionRequest) -> Option<RiskFactor> {
+    let missing = request
+        .device
+        .user_agent
+        .as_deref()
+        .is_none_or(|ua| ua.trim().is_empty());
+    missing.then(|| {
+        RiskFactor::new(
+            "MISSING_USER_AGENT",
+            "device",
+            10.0,
+            "No user agent was supplied for the device",
+        )
+    })
+}
+
+fn bot_user_agent(request: &TransactionRequest) -> Option<RiskFactor> {
+    let user_agent = request.device.user_agent.as_deref()?;
+    let automation = ua::parse(user_agent).automation?;
+    Some(RiskFactor::new(
+        "BOT_USER_AGENT",
+        "device",
+        40.0,
+        format!("User agent identifies an automated client: {automation}"),
+    ))
+}
+
+fn known_bad_tls_fingerprint(request: &TransactionRequest) -> Option<RiskFactor> {
+    let client = tls::known_bad_client(request.device.ja3.as_deref()?)?;
+    Some(RiskFactor::new(
+        "KNOWN_BAD_TLS_FINGERPRINT",
+        "device",
+        60.0,
+        format!("TLS fingerprint belongs to a known-bad client: {client}"),
+    ))
+}
+
+fn tls_user_agent_mismatch(request: &TransactionRequest) -> Option<RiskFactor> {
+    let ja4 = tls::parse_ja4(request.device.ja4.as_deref()?)?;
+    let details = ua::parse(request.device.user_agent.as_deref()?);
+    // Self-declared automation is left to the user agent rule
+    if details.automation.is_some() {
+        return None;
+    }
+    let browser = details.browser?;
+    let reason = tls::non_browser_reason(&ja4)?;
+    Some(RiskFactor::new(
+        "TLS_USER_AGENT_MISMATCH",
+        "device",
+        45.0,
+        format!("User agent claims to be {browser}, but the TLS handshake {reason}"),
+    ))
+}
+
+fn blocked_device(user: &UserSignals) -> Option<RiskFactor> {
+    (user.device_status == DeviceStatus::Blocked).then(|| {
+        RiskFactor::new(
+            "BLOCKED_DEVICE",
+            "device",
+            MAX_RISK_SCORE,
+            "Device is blocked",
+        )
+    })
+}
+
+fn trusted_device(user: &UserSignals) -> Option<RiskFactor> {
+    (user.device_status == DeviceStatus::Trusted)
+        .then(|| RiskFactor::new("TRUSTED_DEVICE", "device", -50.0, "Device is trusted"))
+}
+
+/// Entity on the account's blocklist, which the account refuses to deal with whatever else the
+/// transaction looks like
+fn blocklisted(user: &UserSignals) -> Option<RiskFactor> {
+    list_factor("BLOCKLISTED", MAX_RISK_SCORE, ListType::Blocklist, user)
+}
+
+/// Entity on the account's allowlist, vouching for the transaction
+///
+/// A blocklisted entity, or any other rule that rejects outright, still rejects an allowlisted
+/// transaction.
+fn allowlisted(user: &UserSignals) -> Option<RiskFactor> {
+    list_factor("ALLOWLISTED", ALLOWLIST_SCORE, ListType::Allowlist, user)
+}
+
+fn watchlisted(user: &UserSignals) -> Option<RiskFactor> {
+    list_factor("WATCHLISTED", WATCHLIST_SCORE, ListType::Watchlist, user)
+}
+
+/// Factor for the transaction's entities on one of the account's lists, naming them in its
+/// reason and describing each entry in its metadata
+fn list_factor(
+    code: &str,
+    score: f64,
+    list_type: ListType,
+    user: &UserSignals,
+) -> Option<RiskFactor> {
+    let entries: Vec<_> = user
+        .list_entries
+        .iter()
+        .filter(|entry| entry.list_type == list_type)
+        .collect();
+    let first = entries.first()?;
+    let mut labels: Vec<&str> = Vec::new();
+    for entry in &entries {
+        let label = entity_label(entry.entity_type);
+        if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+    let list = list_type.name();
+    let reason = match (labels.as_slice(), first.reason.as_deref()) {
+        ([label], Some(reason)) if entries.len() == 1 => {
+            format!("{} is on the account's {list}: {reason}", capitalize(label))
+        },
+        ([label], _) if entries.len() == 1 => {
+            format!("{} is on the account's {list}", capitalize(label))
+        },
+        ([label], _) => format!(
+            "{} {label} entries on the account's {list} match the transaction",
+            entries.len()
+        ),
+        ([rest @ .., last], _) => format!(
+            "{} and {last} are on the account's {list}",
+            capitalize(&rest.join(", "))
+        ),
+        ([], _) => return None,
+    };
+    let matches: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            json!({
+                "entry_id": entry.id,
+                "list_type": entry.list_type,
+                "entity_type": entry.entity_type,
+                "value": entry.value,
+                "reason": entry.reason,
+                "expires_at": entry.expires_at,
+                "added_by": entry.added_by,
+            })
+        })
+        .collect();
+    Some(RiskFactor::new(code, "list", score, reason).with_metadata(json!({ "matches": matches })))
+}
+
+fn sanctions_match(user: &UserSignals) -> Option<RiskFactor> {
+    let first = user.sanctions_hits.first()?;
+    let reason = if user.sanctions_hits.len() == 1 {
+        format!(
+            "{} name matches {} on the {} sanctions list",
+            first.role,
+            first.party.name,
+            first.party.list.name()
+        )
+    } else {
+        format!(
+            "{} names match parties on sanctions lists",
+            user.sanctions_hits.len()
+        )
+    };
+    let matches: Vec<_> = user
+        .sanctions_hits
+        .iter()
+        .map(|hit| {
+            json!({
+                "role": hit.role,
+                "name": hit.name,
+                "list": hit.party.list,
+                "entry_id": hit.party.entry_id,
+                "listed_name": hit.party.name,
+                "matched_name": hit.party.matched_name,
+                "programs": hit.party.programs,
+                "score": hit.party.score,
+            })
+        })
+        .collect();
+    Some(
+        RiskFactor::new(
+            "SANCTIONS_MATCH",
+            "compliance",
+            SANCTIONS_MATCH_SCORE,
+            reason,
+        )
+        .with_metadata(json!({ "matches": matches })),
+    )
+}
+
+/// How a list entry's entity is named in factor reasons
+fn entity_label(entity_type: ListEntityType) -> &'static str {
+    match entity_type {
+        ListEntityType::Email => "email address",
+        ListEntityType::EmailDomain => "email domain",
+        ListEntityType::Ip => "IP address",
+        ListEntityType::Cidr => "IP address range",
+        ListEntityType::Device => "device",
+        ListEntityType::CardHash => "card",
+        ListEntityType::User => "user",
+        ListEntityType::Country => "country",
+    }
+}
+
+/// `text` with its first letter in upper case
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn flagged_user(user: &UserSignals) -> Option<RiskFactor> {
+    if user.active_flags.is_empty() {
+        return None;
+    }
+    let types: Vec<&str> = user
+        .active_flags
+        .iter()
+        .map(|flag| flag.flag_type.as_str())
+        .collect();
+    Some(RiskFactor::new(
+        "FLAGGED_USER",
+        "user",
+        30.0,
+        format!("User is flagged: {}", types.join(", ")),
+    ))
+}
+
+fn refund_abuse(user: &UserSignals) -> Option<RiskFactor> {
+    let refunds = user.refunds;
+    refunds.frequent_refunds().then(|| {
+        RiskFactor::new(
+            "REFUND_ABUSE",
+            "user",
+            30.0,
+            format!(
+                "User had {} of {} earlier purchases refunded",
+                refunds.refunded, refunds.purchases
+            ),
+        )
+    })
+}
+
+fn shared_device(user: &UserSignals) -> Option<RiskFactor> {
+    (user.device_user_count > SHARED_DEVICE_MAX_USERS).then(|| {
+        RiskFactor::new(
+            "SHARED_DEVICE",
+            "device",
+            35.0,
+            format!(
+                "Device was used by {} distinct users in the last {DEVICE_USERS_WINDOW_HOURS} \
+                 hours",
+                user.device_user_count
+            ),
+        )
+    })
+}
+
+fn chargeback_device(user: &UserSignals) -> Option<RiskFactor> {
+    user.device_chargebacks.then(|| {
+        RiskFactor::new(
+            "CHARGEBACK_DEVICE",
+            "device",
+            45.0,
+            "Device was previously tied to a chargeback",
+        )
+    })
+}
+
+fn session_signup_purchase(user: &UserSignals) -> Option<RiskFactor> {
+    let seconds = user.session.purchase_seconds_after_signup?;
+    (seconds <= SESSION_SIGNUP_PURCHASE_SECONDS).then(|| {
+        RiskFactor::new(
+            "SESSION_SIGNUP_PURCHASE",
+            "session",
+            40.0,
+            format!(
+                "Purchase {seconds:.0} seconds after the account was created in the same session"
+            ),
+        )
+    })
+}
+
+fn session_country_switch(user: &UserSignals) -> Option<RiskFactor> {
+    let countries = user.session.countries;
+    (countries > 1).then(|| {
+        RiskFactor::new(
+            "SESSION_COUNTRY_SWITCH",
+            "session",
+            40.0,
+            format!("Session moved between {countries} countries"),
+        )
+    })
+}
+
+/// A session changing IP address within one country is weaker evidence, as mobile devices do
+/// so between networks
+fn session_ip_switch(user: &UserSignals) -> Option<RiskFactor> {
+    let SessionSignals {
+        ip_addresses,
+        countries,
+        ..
+    } = user.session;
+    (ip_addresses > 1 && countries <= 1).then(|| {
+        RiskFactor::new(
+            "SESSION_IP_SWITCH",
+            "session",
+            20.0,
+            format!("Session moved between {ip_addresses} IP addresses"),
+        )
+    })
+}
+
+fn tor_exit_node(user: &UserSignals) -> Option<RiskFactor> {
+    user.ip_traits
+        .is_tor_exit_node
+        .then(|| RiskFactor::new("TOR_EXIT_NODE", "ip", 50.0, "IP address is a Tor exit node"))
+}
+
+fn anonymous_ip(user: &UserSignals) -> Option<RiskFactor> {
+    let traits = user.ip_traits;
+    let service = if traits.is_tor_exit_node {
+        return None;
+    } else if traits.is_anonymous_vpn {
+        "a VPN service"
+    } else if traits.is_public_proxy {
+        "a public proxy"
+    } else {
+        return None;
+    };
+    Some(RiskFactor::new(
+        "ANONYMOUS_IP",
+        "ip",
+        35.0,
+        format!("IP address belongs to {service}"),
+    ))
+}
+
+/// Hosting addresses are weaker evidence than anonymizers, as corporate gateways and privacy
+/// relays run in datacenters too
+fn hosting_ip(user: &UserSignals) -> Option<RiskFactor> {
+    let traits = user.ip_traits;
+    (traits.is_hosting_provider && !traits.is_anonymous).then(|| {
+        RiskFactor::new(
+            "HOSTING_IP",
+            "ip",
+            20.0,
+            "IP address belongs to a datacenter or hosting provider",
+        )
+    })
+}
+
+fn ip_chargeback_history(user: &UserSignals) -> Option<RiskFactor> {
+    let chargebacks = user.ip_history.chargebacks;
+    (chargebacks > 0).then(|| {
+        RiskFactor::new(
+            "IP_CHARGEBACK_HISTORY",
+            "ip",
+            45.0,
+            format!("IP address was tied to {chargebacks} chargebacks on this account"),
+        )
+    })
+}
+
+fn ip_reject_history(user: &UserSignals) -> Option<RiskFactor> {
+    let history = user.ip_history;
+    history.mostly_rejected().then(|| {
+        RiskFactor::new(
+            "IP_REJECT_HISTORY",
+            "ip",
+            25.0,
+            format!(
+                "{} of {} earlier transactions from the IP address were rejected",
+                history.rejects, history.transactions
+            ),
+        )
+    })
+}
+
+fn ip_many_cards(user: &UserSignals) -> Option<RiskFactor> {
+    let history = user.ip_history;
+    history.many_cards().then(|| {
+        RiskFactor::new(
+            "IP_MANY_CARDS",
+            "ip",
+            30.0,
+            format!("IP address was used with {} payment cards", history.cards),
+        )
+    })
+}
+
+/// Other accounts' history is weaker evidence, as they may serve different customers from
+/// shared networks
+fn ip_global_history(user: &UserSignals) -> Option<RiskFactor> {
+    let history = user.ip_global_history;
+    (history.chargebacks > 0 || history.mostly_rejected()).then(|| {
+        RiskFactor::new(
+            "IP_GLOBAL_BAD_HISTORY",
+            "ip",
+            20.0,
+            "IP address has a history of chargebacks or rejections on other accounts",
+        )
+    })
+}
+
+fn ip_subnet_velocity(user: &UserSignals) -> Option<RiskFactor> {
+    let velocity = &user.ip_velocity;
+    (velocity.last_hour > IP_SUBNET_MAX_HOURLY).then(|| {
+        RiskFactor::new(
+            "IP_VELOCITY",
+            "ip",
+            35.0,
+            format!(
+                "{} transactions came from {} in the last hour",
+                velocity.last_hour, velocity.subnet
+            ),
+        )
+    })
+}
+
+fn ip_subnet_many_users(user: &UserSignals) -> Option<RiskFactor> {
+    let velocity = &user.ip_velocity;
+    (velocity.users_last_day > IP_SUBNET_MAX_USERS).then(|| {
+        RiskFactor::new(
+            "IP_MANY_USERS",
+            "ip",
+            30.0,
+            format!(
+                "{} distinct users transacted from {} in the last {IP_VELOCITY_WINDOW_HOURS} \
+                 hours",
+                velocity.users_last_day, velocity.subnet
+            ),
+        )
+    })
+}
+
+/// Travel between two transactions' IP locations faster than by air, or by air within hours
+fn address_velocity(user: &UserSignals) -> Option<RiskFactor> {
+    let velocity = user.address_velocity;
+    (velocity.last_hour > ADDRESS_MAX_HOURLY).then(|| {
+        RiskFactor::new(
+            "ADDRESS_VELOCITY",
+            "address",
+            25.0,
+            format!(
+                "{} transactions used the billing or shipping address in the last hour",
+                velocity.last_hour
+            ),
+        )
+    })
+}
+
+fn address_many_users(user: &UserSignals) -> Option<RiskFactor> {
+    let velocity = user.address_velocity;
+    (velocity.users_last_day > ADDRESS_MAX_USERS).then(|| {
+        RiskFactor::new(
+            "ADDRESS_MANY_USERS",
+            "address",
+            30.0,
+            format!(
+                "{} distinct users used the billing or shipping address in the last \
+                 {ADDRESS_VELOCITY_WINDOW_HOURS} hours",
+                velocity.users_last_day
+            ),
+        )
+    })
+}
+
+fn impossible_travel(user: &UserSignals) -> Option<RiskFactor> {
+    let travel = user.travel?;
+    let score = calculate_velocity_risk(travel.distance_km, travel.hours);
+    (score > 0.0).then(|| {
+        RiskFactor::new(
+            "IMPOSSIBLE_TRAVEL",
+            "location",
+            score,
+            format!(
+                "IP address is {:.0} km from the user's last transaction {:.1} hours earlier",
+                travel.distance_km, travel.hours
+            ),
+        )
+    })
+}
+
+fn blocked_asn(user: &UserSignals) -> Option<RiskFactor> {
+    let entry = user.asn_listing.as_ref()?;
+    (entry.action == AsnListAction::Block).then(|| {
+        RiskFactor::new(
+            "BLOCKED_ASN",
+            "ip",
+            MAX_RISK_SCORE,
+            asn_reason(entry.asn, "is blocked", entry.reason.as_deref()),
+        )
+    })
+}
+
+fn listed_asn(user: &UserSignals) -> Option<RiskFactor> {
+    let entry = user.asn_listing.as_ref()?;
+    let score = entry
+        .score
+        .filter(|_| entry.action == AsnListAction::Boost)?;
+    Some(RiskFactor::new(
+        "LISTED_ASN",
+        "ip",
+        score,
+        asn_reason(
+            entry.asn,
+            "is on the account's watch list",
+            entry.reason.as_deref(),
+        ),
+    ))
+}
+
+/// Reason for a factor on a listed network, with the reason it was listed if one was given
+fn asn_reason(asn: i64, listing: &str, reason: Option<&str>) -> String {
+    match reason {
+        Some(reason) => format!("IP address belongs to AS{asn}, which {listing}: {reason}"),
+        None => format!("IP address belongs to AS{asn}, which {listing}"),
+    }
+}
+
+fn email_variants(user: &UserSignals) -> Option<RiskFactor> {
+    let variants = user.email_variants;
+    (variants.users > EMAIL_VARIANT_MAX_USERS).then(|| {
+        RiskFactor::new(
+            "EMAIL_VARIANTS",
+            "email",
+            35.0,
+            format!(
+                "{} other variants of the email mailbox were used by {} other users",
+                variants.addresses, variants.users
+            ),
+        )
+    })
+}
+
+/// Phone number on a VoIP range, which anyone can get without a SIM card or an address
+fn voip_phone(user: &UserSignals) -> Option<RiskFactor> {
+    let phone = user.phone.as_ref()?;
+    (phone.is_valid && phone.line_type == Some(PhoneLineType::Voip))
+        .then(|| RiskFactor::new("VOIP_PHONE", "phone", 20.0, "Phone number is a VoIP number"))
+}
+
+/// Device or IP address running cards through the checkout: cards of many BINs, small payments
+/// that keep being declined, or card numbers counted up one after another
+///
+/// Card testing is critical: every payment the tester gets through confirms a stolen card and
+/// costs the merchant authorization fees, so it rejects the transaction outright.
+fn card_testing(user: &UserSignals) -> Option<RiskFactor> {
+    let testing = user.card_testing;
+    let reason = if testing.sequential_cards >= CARD_TESTING_MIN_SEQUENTIAL {
+        format!(
+            "{} cards with sequential numbers were tried from the device or IP address in \
+             {CARD_TESTING_WINDOW_MINUTES} minutes",
+            testing.sequential_cards
+        )
+    } else if testing.distinct_bins > CARD_TESTING_MAX_BINS {
+        format!(
+            "Cards of {} BINs were tried from the device or IP address in \
+             {CARD_TESTING_WINDOW_MINUTES} minutes",
+            testing.distinct_bins
+        )
+    } else if testing.declined_small_attempts >= CARD_TESTING_MIN_DECLINED_SMALL {
+        format!(
+            "{} small payments were tried from the device or IP address in \
+             {CARD_TESTING_WINDOW_MINUTES} minutes, {} of them declined",
+            testing.small_attempts, testing.declined_small_attempts
+        )
+    } else {
+        return None;
+    };
+    Some(RiskFactor::new("CARD_TESTING", "payment", 90.0, reason))
+}
+
+/// Prepaid card, which can be bought with cash and carries no credit check
+fn prepaid_card(user: &UserSignals) -> Option<RiskFactor> {
+    let bin = user.card_bin.as_ref()?;
+    bin.is_prepaid
+        .then(|| RiskFactor::new("PREPAID_CARD", "payment", 25.0, "Card is a prepaid card"))
+}
+
+/// Virtual card, which can be issued in numbers and discarded after one use
+fn virtual_card(user: &UserSignals) -> Option<RiskFactor> {
+    let bin = user.card_bin.as_ref()?;
+    bin.is_virtual
+        .then(|| RiskFactor::new("VIRTUAL_CARD", "payment", 15.0, "Card is a virtual card"))
+}
+
+/// The account's listings of the countries a transaction involves, each with the part the
+/// country plays in it
+fn listed_countries<'a>(
+    request: &'a TransactionRequest,
+    user: &'a UserSignals,
+) -> impl Iterator<Item = (&'static str, &'a CountryListEntry)> {
+    transaction_countries(request, user.ip_country.as_deref()).filter_map(|(role, country)| {
+        let entry = user
+            .country_listings
+            .iter()
+            .find(|entry| entry.country == country)?;
+        Some((role, entry))
+    })
+}
+
+fn blocked_country(request: &TransactionRequest, user: &UserSignals) -> Option<RiskFactor> {
+    let (role, entry) = listed_countries(request, user)
+        .find(|(_, entry)| entry.action == CountryListAction::Block)?;
+    Some(RiskFactor::new(
+        "BLOCKED_COUNTRY",
+        "location",
+        MAX_RISK_SCORE,
+        country_reason(role, entry, "is blocked"),
+    ))
+}
+
+fn review_country(request: &TransactionRequest, user: &UserSignals) -> Option<RiskFactor> {
+    let (role, entry) = listed_countries(request, user)
+        .find(|(_, entry)| entry.action == CountryListAction::Review)?;
+    Some(RiskFactor::new(
+        "REVIEW_COUNTRY",
+        "location",
+        20.0,
+        country_reason(role, entry, "requires review"),
+    ))
+}
+
+fn high_risk_country(request: &TransactionRequest, user: &UserSignals) -> Option<RiskFactor> {
+    let (role, entry, score) = listed_countries(request, user)
+        .filter_map(|(role, entry)| {
+            let score = entry
+                .score
+                .filter(|_| entry.action == CountryListAction::Boost)?;
+            Some((role, entry, score))
+        })
+        .max_by(|a, b| a.2.total_cmp(&b.2))?;
+    Some(RiskFactor::new(
+        "HIGH_RISK_COUNTRY",
+        "location",
+        score,
+        country_reason(role, entry, "is high risk"),
+    ))
+}
+
+fn billing_ip_country_mismatch(
+    request: &TransactionRequest,
+    user: &UserSignals,
+) -> Option<RiskFactor> {
+    let billing = request.billing.as_ref()?.country.as_deref()?;
+    let ip = user.ip_country.as_deref()?;
+    if billing == ip {
+        return None;
+    }
+    let listed = user
+        .country_listings
+        .iter()
+        .any(|entry| entry.country == billing || entry.country == ip);
+    Some(RiskFactor::new(
+        "BILLING_IP_COUNTRY_MISMATCH",
+        "location",
+        if listed {
+            LISTED_BILLING_IP_COUNTRY_MISMATCH_SCORE
+        } else {
+            BILLING_IP_COUNTRY_MISMATCH_SCORE
+        },
+        format!("Billing country {billing} differs from IP address country {ip}"),
+    ))
+}
+
+fn shipping_ip_country_mismatch(
+    request: &TransactionRequest,
+    user: &UserSignals,
+) -> Option<RiskFactor> {
+    let shipping = request.shipping.as_ref()?.address.country.as_deref()?;
+    let ip = user.ip_country.as_deref()?;
+    (shipping != ip).then(|| {
+        RiskFactor::new(
+            "SHIPPING_IP_COUNTRY_MISMATCH",
+            "location",
+            10.0,
+            format!("Shipping country {shipping} differs from IP address country {ip}"),
+        )
+    })
+}
+
+// Addresses in another country than the IP address are left to the country mismatch rules
+
+fn billing_far_from_ip(request: &TransactionRequest, user: &UserSignals) -> Option<RiskFactor> {
+    let billing = request.billing.as_ref()?.country.as_deref()?;
+    let distance = user.billing_ip_distance_km?;
+    (user.ip_country.as_deref() == Some(billing) && distance > ADDRESS_IP_MAX_DISTANCE_KM).then(
+        || {
+            RiskFactor::new(
+                "BILLING_FAR_FROM_IP",
+                "location",
+                15.0,
+                format!("Billing address is {distance:.0} km from the IP address location"),
+            )
+        },
+    )
+}
+
+fn shipping_far_from_ip(request: &TransactionRequest, user: &UserSignals) -> Option<RiskFactor> {
+    let shipping = request.shipping.as_ref()?.address.country.as_deref()?;
+    let distance = user.shipping_ip_distance_km?;
+    (user.ip_country.as_deref() == Some(shipping) && distance > ADDRESS_IP_MAX_DISTANCE_KM).then(
+        || {
+            RiskFactor::new(
+                "SHIPPING_FAR_FROM_IP",
+                "location",
+                10.0,
+                format!("Shipping address is {distance:.0} km from the IP address location"),
+            )
+        },
+    )
+}
+
+/// Email address at a domain that hands out throwaway addresses
+fn disposable_email(request: &TransactionRequest, user: &UserSignals) -> Option<RiskFactor> {
+    let domain = request.email.as_ref()?.resolved_domain()?;
+    user.email_traits.is_disposable.then(|| {
+        RiskFactor::new(
+            "DISPOSABLE_EMAIL",
+            "email",
+            40.0,
+            format!("Email domain {domain} hands out disposable addresses"),
+        )
+    })
+}
+
+/// Phone number allocated to another country than the address it was given with
+fn phone_country_mismatch(request: &TransactionRequest, user: &UserSignals) -> Option<RiskFactor> {
+    let phone = user.phone.as_ref()?.country.as_deref()?;
+    let address = request.phone_address()?.country.as_deref()?;
+    (!phone.eq_ignore_ascii_case(address)).then(|| {
+        RiskFactor::new(
+            "PHONE_COUNTRY_MISMATCH",
+            "phone",
+            15.0,
+            format!("Phone number is from {phone}, but its address is in {address}"),
+        )
+    })
+}
+
+/// Card issued in another country than the billing address, by the BIN table, where the
+/// request does not give the card's country for [`card_country_mismatch`] to compare
+fn bin_country_mismatch(request: &TransactionRequest, user: &UserSignals) -> Option<RiskFactor> {
+    if request.credit_card.as_ref()?.country.is_some() {
+        return None;
+    }
+    let card = user.card_bin.as_ref()?.country.as_deref()?;
+    let billing = request.billing.as_ref()?.country.as_deref()?;
+    (!card.eq_ignore_ascii_case(billing)).then(|| {
+        RiskFactor::new(
+            "CARD_COUNTRY_MISMATCH",
+            "payment",
+            15.0,
+            format!("Card issued in {card} but billing country is {billing}"),
+        )
+    })
+}
+
+/// Large order with an email address first seen less than [`NEW_EMAIL_MAX_DAYS`] ago
+///
+/// The score halves with each good transaction the address has had on the account, and the
+/// factor is dropped once it has had [`NEW_EMAIL_MAX_GOOD_TRANSACTIONS`].
+fn new_email_large_amount(request: &TransactionRequest, user: &UserSignals) -> Option<RiskFactor> {
+    let order = request.order.as_ref()?;
+    let age = user.email_age?;
+    if order.amount < LARGE_AMOUNT_THRESHOLD
+        || age.days >= NEW_EMAIL_MAX_DAYS
+        || age.good_transactions >= NEW_EMAIL_MAX_GOOD_TRANSACTIONS
+    {
+        return None;
+    }
+    let seen = match age.days {
+        0 => "first seen today".to_string(),
+        1 => "first seen 1 day ago".to_string(),
+        days => format!("first seen {days} days ago"),
+    };
+    let history = match age.good_transactions {
+        0 => String::new(),
+        1 => " and 1 good transaction since".to_string(),
+        good => format!(" and {good} good transactions since"),
+    };
+    Some(RiskFactor::new(
+        "NEW_EMAIL_LARGE_AMOUNT",
+        "email",
+        NEW_EMAIL_LARGE_AMOUNT_SCORE / 2f64.powi(age.good_transactions as i32),
+        format!(
+            "Order amount {:.2} {} is large for an email address {seen}{history}",
+            order.amount, order.currency
+        ),
+    ))
+}
+
+/// Business card used with a free email address rather than one at the business's domain
+fn business_card_free_email(
+    request: &TransactionRequest,
+    user: &UserSignals,
+) -> Option<RiskFactor> {
+    let domain = request.email.as_ref()?.resolved_domain()?;
+    (user.card_bin.as_ref()?.is_business && user.email_traits.is_free).then(|| {
+        RiskFactor::new(
+            "BUSINESS_CARD_FREE_EMAIL",
+            "payment",
+            10.0,
+            format!("Business card used with a free email address at {domain}"),
+        )
+    })
+}
+
+/// One-off purchase at a local hour the user does not usually purchase in
+///
+/// Recurring purchases are charged on the merchant's schedule, so their hour says nothing about
+/// the customer.
+fn unusual_local_hour(request: &TransactionRequest, user: &UserSignals) -> Option<RiskFactor> {
+    let local_time = user.local_time.as_ref()?;
+    (request.event.event_type == EventType::Purchase && local_time.is_unusual_hour()).then(|| {
+        RiskFactor::new(
+            "UNUSUAL_LOCAL_HOUR",
+            "user",
+            15.0,
+            format!(
+                "Purchase at {} local time, outside the hours the user usually purchases in",
+                local_time.time.format("%H:%M")
+            ),
+        )
+    })
+}
+
+/// Reason for a factor on a listed country, with the reason it was listed if one was given
+fn country_reason(role: &str, entry: &CountryListEntry, listing: &str) -> String {
+    match &entry.reason {
+        Some(reason) => format!("{role} country {} {listing}: {reason}", entry.country),
+        None => format!("{role} country {} {listing}", entry.country),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+    use crate::{
+        models::{
+            insights::IpTraits,
+            list::{AsnListEntry, ListEntry},
+            screening::{SanctionsList, ScreeningMatch},
+        },
+        scoring::{
+            AddressVelocity, BinInfo, CardTesting, EmailAge, EmailTraits, EmailVariants, GeoTravel,
+            IpHistory, IpVelocity, LocalTime, PhoneNumberInfo, RefundHistory, SanctionsHit,
+        },
+    };
+
+    fn request(value: serde_json::Value) -> TransactionRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn codes(request: &TransactionRequest) -> Vec<String> {
+        evaluate_all(request).into_iter().map(|f| f.code).collect()
+    }
+
+    #[test]
+    fn test_clean_request_has_no_factors() {
+        let request = request(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "order": { "amount": 50.0, "currency": "USD" }
+        }));
+        assert!(codes(&request).is_empty());
+    }
+
+    #[test]
+    fn test_risky_request_factors() {
+        let request = request(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1" },
+            "event": { "type": "purchase" },
+            "order": { "amount": 7500.0, "currency": "USD" },
+            "billing": { "country": "US" },
+            "shipping": { "country": "NG" },
+            "credit_card": { "country": "GB", "cvv_result": "N" }
+        }));
+        assert_eq!(
+            codes(&request),
+            vec![
+                "VERY_LARGE_AMOUNT",
+                "BILLING_SHIPPING_COUNTRY_MISMATCH",
+                "CARD_COUNTRY_MISMATCH",
+                "CVV_MISMATCH",
+                "MISSING_USER_AGENT",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cardholder_name_mismatch_rule() {
+        let codes = |holder_name: &str| {
+            codes(&request(serde_json::json!({
+                "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+                "event": { "type": "purchase" },
+                "billing": { "first_name": "Jonathan", "last_name": "Smith" },
+                "credit_card": { "holder_name": holder_name }
+            })))
+        };
+        assert!(codes("SMITH/JONATHAN").is_empty());
+        assert!(codes("J SMITH").is_empty());
+        assert!(codes("Jon Smyth").is_empty());
+        assert_eq!(codes("MARIA GARCIA"), ["CARDHOLDER_NAME_MISMATCH"]);
+        // Without a name to compare there is no mismatch
+        assert!(codes("").is_empty());
+    }
+
+    #[test]
+    fn test_bot_user_agent() {
+        let request = request(serde_json::json!({
+            "device": {
+                "ip_address": "198.51.100.1",
+                "user_agent": "Mozilla/5.0 (X11; Linux x86_64) HeadlessChrome/126.0.0.0 Safari/537.36"
+            },
+            "event": { "type": "purchase" }
+        }));
+        let factors = evaluate_all(&request);
+        assert_eq!(factors.len(), 1);
+        assert_eq!(factors[0].code, "BOT_USER_AGENT");
+        assert!(factors[0].reason.contains("HeadlessChrome"));
+    }
+
+    #[test]
+    fn test_tls_fingerprint_rules() {
+        let chrome = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like \
+                      Gecko) Chrome/126.0.0.0 Safari/537.36";
+        let device = |ja3: &str, ja4: &str| {
+            request(serde_json::json!({
+                "device": {
+                    "ip_address": "198.51.100.1",
+                    "user_agent": chrome,
+                    "ja3": ja3,
+                    "ja4": ja4
+                },
+                "event": { "type": "purchase" }
+            }))
+        };
+        assert!(
+            codes(&device(
+                "cd08e31494f9531f560d64c695473da9",
+                "t13d1516h2_8daaf6152771_e5627efa2ab1"
+            ))
+            .is_empty()
+        );
+        assert_eq!(
+            codes(&device(
+                "e7d705a3286e19ea42f587b344ee6865",
+                "t12i190800_d83cc789557e_7af1ed941c26"
+            )),
+            ["KNOWN_BAD_TLS_FINGERPRINT", "TLS_USER_AGENT_MISMATCH"]
+        );
+    }
+
+    #[test]
+    fn test_device_link_rules() {
+        let codes = |user: &UserSignals| -> Vec<String> {
+            evaluate_user(user).into_iter().map(|f| f.code).collect()
+        };
+        let user = UserSignals {
+            device_user_count: SHARED_DEVICE_MAX_USERS,
+            ..UserSignals::default()
+        };
+        assert!(codes(&user).is_empty());
+
+        let user = UserSignals {
+            device_user_count: SHARED_DEVICE_MAX_USERS + 1,
+            device_chargebacks: true,
+            ..UserSignals::default()
+        };
+        assert_eq!(codes(&user), ["SHARED_DEVICE", "CHARGEBACK_DEVICE"]);
+    }
+
+    #[test]
+    fn test_refund_abuse_rule() {
+        let codes = |purchases: i64, refunded: i64| -> Vec<String> {
+            let user = UserSignals {
+                refunds: RefundHistory {
+                    purchases,
+                    refunded,
+                },
+                ..UserSignals::default()
+            };
+            evaluate_user(&user).into_iter().map(|f| f.code).collect()
+        };
+        // Too few purchases to judge, and refunds within the usual share
+        assert!(codes(2, 2).is_empty());
+        assert!(codes(10, 3).is_empty());
+        assert_eq!(codes(10, 4), ["REFUND_ABUSE"]);
+    }
+
+    #[test]
+    fn test_email_variants_rule() {
+        let codes = |users: i64| -> Vec<String> {
+            let user = UserSignals {
+                email_variants: EmailVariants {
+                    addresses: users + 1,
+                    users,
+                },
+                ..UserSignals::default()
+            };
+            evaluate_user(&user).into_iter().map(|f| f.code).collect()
+        };
+        assert!(codes(EMAIL_VARIANT_MAX_USERS).is_empty());
+        assert_eq!(codes(EMAIL_VARIANT_MAX_USERS + 1), ["EMAIL_VARIANTS"]);
+    }
+
+    #[test]
+    fn test_device_status_rules() {
+        let codes = |status: DeviceStatus| -> Vec<String> {
+            let user = UserSignals {
+                device_status: status,
+                ..UserSignals::default()
+            };
+            evaluate_user(&user).into_iter().map(|f| f.code).collect()
+        };
+        assert!(codes(DeviceStatus::Normal).is_empty());
+        assert_eq!(codes(DeviceStatus::Trusted), ["TRUSTED_DEVICE"]);
+        assert_eq!(codes(DeviceStatus::Blocked), ["BLOCKED_DEVICE"]);
+    }
+
+    #[test]
+    fn test_session_rules() {
+        let codes = |session: SessionSignals| -> Vec<String> {
+            let user = UserSignals {
+                session,
+                ..UserSignals::default()
+            };
+            evaluate_user(&user).into_iter().map(|f| f.code).collect()
+        };
+        assert!(codes(SessionSignals::default()).is_empty());
+        assert!(
+            codes(SessionSignals {
+                purchase_seconds_after_signup: Some(600.0),
+                ip_addresses: 1,
+                countries: 1,
+            })
+            .is_empty()
+        );
+        assert_eq!(
+            codes(SessionSignals {
+                purchase_seconds_after_signup: Some(8.0),
+                ip_addresses: 2,
+                countries: 1,
+            }),
+            ["SESSION_SIGNUP_PURCHASE", "SESSION_IP_SWITCH"]
+        );
+        assert_eq!(
+            codes(SessionSignals {
+                purchase_seconds_after_signup: None,
+                ip_addresses: 2,
+                countries: 2,
+            }),
+            ["SESSION_COUNTRY_SWITCH"]
+        );
+    }
+
+    #[test]
+    fn test_anonymous_ip_rules() {
+        let codes = |ip_traits: IpTraits| -> Vec<String> {
+            let user = UserSignals {
+                ip_traits,
+                ..UserSignals::default()
+            };
+            evaluate_user(&user).into_iter().map(|f| f.code).collect()
+        };
+        assert!(codes(IpTraits::default()).is_empty());
+        let hosting = IpTraits {
+            is_hosting_provider: true,
+            ..IpTraits::default()
+        };
+        assert_eq!(codes(hosting), ["HOSTING_IP"]);
+        let tor = IpTraits {
+            is_anonymous: true,
+            is_tor_exit_node: true,
+            ..hosting
+        };
+        assert_eq!(codes(tor), ["TOR_EXIT_NODE"]);
+        let vpn = IpTraits {
+            is_anonymous: true,
+            is_anonymous_vpn: true,
+            ..hosting
+        };
+        assert_eq!(codes(vpn), ["ANONYMOUS_IP"]);
+    }
+
+    #[test]
+    fn test_ip_history_rules() {
+        let codes = |ip_history: IpHistory, ip_global_history: IpHistory| -> Vec<String> {
+            let user = UserSignals {
+                ip_history,
+                ip_global_history,
+                ..UserSignals::default()
+            };
+            evaluate_user(&user).into_iter().map(|f| f.code).collect()
+        };
+        let clean = IpHistory {
+            transactions: 8,
+            rejects: 2,
+            users: 3,
+            cards: 3,
+            ..IpHistory::default()
+        };
+        assert!(codes(clean, clean).is_empty());
+        let bad = IpHistory {
+            rejects: 4,
+            chargebacks: 1,
+            cards: 6,
+            ..clean
+        };
+        assert_eq!(
+            codes(bad, IpHistory::default()),
+            [
+                "IP_CHARGEBACK_HISTORY",
+                "IP_REJECT_HISTORY",
+                "IP_MANY_CARDS"
+            ]
+        );
+        assert_eq!(codes(clean, bad), ["IP_GLOBAL_BAD_HISTORY"]);
+    }
+
+    #[test]
+    fn test_ip_velocity_rules() {
+        let codes = |ip_velocity: IpVelocity| -> Vec<String> {
+            let user = UserSignals {
+                ip_velocity,
+                ..UserSignals::default()
+            };
+            evaluate_user(&user).into_iter().map(|f| f.code).collect()
+        };
+        let busy = IpVelocity {
+            subnet: "2001:db8::/64".to_string(),
+            last_hour: IP_SUBNET_MAX_HOURLY,
+            last_day: 40,
+            users_last_day: IP_SUBNET_MAX_USERS,
+        };
+        assert!(codes(busy.clone()).is_empty());
+        assert_eq!(
+            codes(IpVelocity {
+                last_hour: IP_SUBNET_MAX_HOURLY + 1,
+                users_last_day: IP_SUBNET_MAX_USERS + 1,
+                ..busy.clone()
+            }),
+            ["IP_VELOCITY", "IP_MANY_USERS"]
+        );
+        let factor = ip_subnet_velocity(&UserSignals {
+            ip_velocity: IpVelocity {
+                last_hour: 12,
+                ..busy
+            },
+            ..UserSignals::default()
+        })
+        .unwrap();
+        assert_eq!(
+            factor.reason,
+            "12 transactions came from 2001:db8::/64 in the last hour"
+        );
+    }
+
+    #[test]
+    fn test_address_velocity_rules() {
+        let codes = |address_velocity: AddressVelocity| -> Vec<String> {
+            let user = UserSignals {
+                address_velocity,
+                ..UserSignals::default()
+            };
+            evaluate_user(&user).into_iter().map(|f| f.code).collect()
+        };
+        let busy = AddressVelocity {
+            last_hour: ADDRESS_MAX_HOURLY,
+            last_day: 20,
+            users_last_day: ADDRESS_MAX_USERS,
+        };
+        assert!(codes(busy).is_empty());
+        assert_eq!(
+            codes(AddressVelocity {
+                last_hour: ADDRESS_MAX_HOURLY + 1,
+                users_last_day: ADDRESS_MAX_USERS + 1,
+                ..busy
+            }),
+            ["ADDRESS_VELOCITY", "ADDRESS_MANY_USERS"]
+        );
+        let factor = address_many_users(&UserSignals {
+            address_velocity: AddressVelocity {
+                users_last_day: 4,
+                ..busy
+            },
+            ..UserSignals::default()
+        })
+        .unwrap();
+        assert_eq!(
+            factor.reason,
+            "4 distinct users used the billing or shipping address in the last 24 hours"
+        );
+    }
+
+    #[test]
+    fn test_impossible_travel_rule() {
+        let travelled = |distance_km, hours| {
+            evaluate_user(&UserSignals {
+                travel: Some(GeoTravel { distance_km, hours }),
+                ..UserSignals::default()
+            })
+        };
+        assert!(evaluate_user(&UserSignals::default()).is_empty());
+        assert!(travelled(6_400.0, 24.0).is_empty());
+        assert!(travelled(50.0, 0.01).is_empty());
+        let factors = travelled(6_400.0, 2.0);
+        assert_eq!(factors.len(), 1);
+        assert_eq!(factors[0].code, "IMPOSSIBLE_TRAVEL");
+        assert_eq!(
+            factors[0].reason,
+            "IP address is 6400 km from the user's last transaction 2.0 hours earlier"
+        );
+        assert!(travelled(6_400.0, 9.0)[0].score < factors[0].score);
+    }
+
+    #[test]
+    fn test_asn_list_rules() {
+        let listed = |action, score, reason: Option<&str>| {
+            evaluate_user(&UserSignals {
+                asn_listing: Some(AsnListEntry {
+                    asn: 64_500,
+                    action,
+                    score,
+                    reason: reason.map(str::to_string),
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                }),
+                ..UserSignals::default()
+            })
+        };
+        let blocked = listed(AsnListAction::Block, None, Some("Bulletproof hoster"));
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].code, "BLOCKED_ASN");
+        assert_eq!(
+            blocked[0].reason,
+            "IP address belongs to AS64500, which is blocked: Bulletproof hoster"
+        );
+        assert!(rejects_outright(&blocked[0]));
+
+        let boosted = listed(AsnListAction::Boost, Some(40.0), None);
+        assert_eq!(boosted.len(), 1);
+        assert_eq!(boosted[0].code, "LISTED_ASN");
+        assert_eq!(boosted[0].score, 40.0);
+        assert!(!rejects_outright(&boosted[0]));
+    }
+    #[test]
+    fn test_list_entry_rules() {
+        let entry = |list_type, entity_type, value: &str, reason: Option<&str>| ListEntry {
+            id: uuid::Uuid::new_v4(),
+            list_type,
+            entity_type,
+            value: value.to_string(),
+            reason: reason.map(str::to_string),
+            expires_at: None,
+            added_by: "acme-payments".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let listed = |list_entries| {
+            evaluate_user(&UserSignals {
+                list_entries,
+                ..UserSignals::default()
+            })
+        };
+
+        let blocked = listed(vec![entry(
+            ListType::Blocklist,
+            ListEntityType::Cidr,
+            "203.0.113.0/24",
+            Some("Chargebacks"),
+        )]);
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].code, "BLOCKLISTED");
+        assert_eq!(
+            blocked[0].reason,
+            "IP address range is on the account's blocklist: Chargebacks"
+        );
+        assert!(rejects_outright(&blocked[0]));
+        let matches = &blocked[0].metadata.as_ref().unwrap()["matches"];
+        assert_eq!(matches[0]["entity_type"], "cidr");
+        assert_eq!(matches[0]["value"], "203.0.113.0/24");
+        assert_eq!(matches[0]["reason"], "Chargebacks");
+        assert_eq!(matches[0]["added_by"], "acme-payments");
+
+        let factors = listed(vec![
+            entry(ListType::Watchlist, ListEntityType::Email, "ab", None),
+            entry(ListType::Watchlist, ListEntityType::Country, "NG", None),
+            entry(ListType::Watchlist, ListEntityType::Device, "cd", None),
+            entry(ListType::Allowlist, ListEntityType::User, "ef", None),
+        ]);
+        let codes: Vec<&str> = factors.iter().map(|f| f.code.as_str()).collect();
+        assert_eq!(codes, ["ALLOWLISTED", "WATCHLISTED"]);
+        assert_eq!(factors[0].score, ALLOWLIST_SCORE);
+        assert_eq!(factors[0].reason, "User is on the account's allowlist");
+        assert_eq!(
+            factors[1].reason,
+            "Email address, country and device are on the account's watchlist"
+        );
+        assert_eq!(
+            factors[1].metadata.as_ref().unwrap()["matches"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // Blocklisted entities still reject an allowlisted transaction
+        let assessment = crate::scoring::RiskAssessment::from_factors(listed(vec![
+            entry(ListType::Allowlist, ListEntityType::User, "ef", None),
+            entry(ListType::Blocklist, ListEntityType::CardHash, "01", None),
+        ]));
+        assert!(assessment.hard_reject);
+    }
+
+    #[test]
+    fn test_country_list_rules() {
+        let request = request(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "billing": { "country": "US" },
+            "shipping": { "country": "NG" },
+            "credit_card": { "country": "US" }
+        }));
+        let entry = |country: &str, action, score| CountryListEntry {
+            country: country.to_string(),
+            action,
+            score,
+            reason: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let codes = |user: &UserSignals| -> Vec<String> {
+            evaluate_context(&request, user)
+                .into_iter()
+                .map(|factor| factor.code)
+                .collect()
+        };
+
+        let unlisted = UserSignals {
+            ip_country: Some("US".to_string()),
+            ..UserSignals::default()
+        };
+        assert_eq!(codes(&unlisted), ["SHIPPING_IP_COUNTRY_MISMATCH"]);
+
+        let blocked = UserSignals {
+            country_listings: vec![entry("NG", CountryListAction::Block, None)],
+            ..unlisted.clone()
+        };
+        let factors = evaluate_context(&request, &blocked);
+        assert_eq!(factors.len(), 2);
+        assert_eq!(factors[0].code, "BLOCKED_COUNTRY");
+        assert_eq!(factors[0].reason, "Shipping country NG is blocked");
+        assert!(rejects_outright(&factors[0]));
+
+        let review = UserSignals {
+            country_listings: vec![entry("NG", CountryListAction::Review, None)],
+            ..unlisted.clone()
+        };
+        let factors = evaluate_context(&request, &review);
+        assert_eq!(factors[0].code, "REVIEW_COUNTRY");
+        assert!(forces_review(&factors[0]));
+
+        let weighted = UserSignals {
+            ip_country: Some("RO".to_string()),
+            country_listings: vec![
+                entry("NG", CountryListAction::Boost, Some(25.0)),
+                entry("RO", CountryListAction::Boost, Some(40.0)),
+            ],
+            ..UserSignals::default()
+        };
+        let factors = evaluate_context(&request, &weighted);
+        assert_eq!(factors[0].code, "HIGH_RISK_COUNTRY");
+        assert_eq!(factors[0].score, 40.0);
+        assert_eq!(factors[0].reason, "IP address country RO is high risk");
+        assert_eq!(factors[1].code, "BILLING_IP_COUNTRY_MISMATCH");
+        assert_eq!(factors[1].score, LISTED_BILLING_IP_COUNTRY_MISMATCH_SCORE);
+
+        let mismatch = UserSignals {
+            ip_country: Some("DE".to_string()),
+            ..UserSignals::default()
+        };
+        let factors = evaluate_context(&request, &mismatch);
+        assert_eq!(factors.len(), 2);
+        assert_eq!(factors[0].score, BILLING_IP_COUNTRY_MISMATCH_SCORE);
+        assert_eq!(
+            factors[0].reason,
+            "Billing country US differs from IP address country DE"
+        );
+    }
+
+    #[test]
+    fn test_address_ip_location_rules() {
+        let request = request(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "billing": { "country": "US" },
+            "shipping": { "country": "US" }
+        }));
+        let codes = |user: &UserSignals| -> Vec<String> {
+            evaluate_context(&request, user)
+                .into_iter()
+                .map(|factor| factor.code)
+                .collect()
+        };
+
+        let nearby = UserSignals {
+            ip_country: Some("US".to_string()),
+            billing_ip_distance_km: Some(12.0),
+            shipping_ip_distance_km: Some(800.0),
+            ..UserSignals::default()
+        };
+        assert!(codes(&nearby).is_empty());
+
+        let far = UserSignals {
+            billing_ip_distance_km: Some(3_940.4),
+            shipping_ip_distance_km: Some(1_200.0),
+            ..nearby.clone()
+        };
+        let factors = evaluate_context(&request, &far);
+        assert_eq!(
+            factors
+                .iter()
+                .map(|factor| factor.code.as_str())
+                .collect::<Vec<_>>(),
+            ["BILLING_FAR_FROM_IP", "SHIPPING_FAR_FROM_IP"]
+        );
+        assert_eq!(
+            factors[0].reason,
+            "Billing address is 3940 km from the IP address location"
+        );
+
+        // Abroad, the country mismatch rules apply instead of the distance rules
+        let abroad = UserSignals {
+            ip_country: Some("MX".to_string()),
+            ..far
+        };
+        assert_eq!(
+            codes(&abroad),
+            [
+                "BILLING_IP_COUNTRY_MISMATCH",
+                "SHIPPING_IP_COUNTRY_MISMATCH"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unusual_local_hour_rule() {
+        let purchase = |event_type: &str| {
+            request(serde_json::json!({
+                "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+                "event": { "type": event_type }
+            }))
+        };
+        let at = |hour, usual_hours: Vec<u32>| UserSignals {
+            local_time: Some(LocalTime {
+                time: DateTime::parse_from_rfc3339(&format!("2025-06-13T{hour:02}:17:00+09:00"))
+                    .unwrap(),
+                usual_hours,
+            }),
+            ..UserSignals::default()
+        };
+
+        let factors = evaluate_context(&purchase("purchase"), &at(3, vec![19, 20, 21]));
+        assert_eq!(factors.len(), 1);
+        assert_eq!(factors[0].code, "UNUSUAL_LOCAL_HOUR");
+        assert_eq!(
+            factors[0].reason,
+            "Purchase at 03:17 local time, outside the hours the user usually purchases in"
+        );
+
+        assert!(evaluate_context(&purchase("purchase"), &at(20, vec![19, 20, 21])).is_empty());
+        // Without a baseline every hour is as usual as any other
+        assert!(evaluate_context(&purchase("purchase"), &at(3, vec![])).is_empty());
+        assert!(
+            evaluate_context(&purchase("recurring_purchase"), &at(3, vec![19, 20, 21])).is_empty()
+        );
+        assert!(evaluate_context(&purchase("purchase"), &UserSignals::default()).is_empty());
+    }
+
+    #[test]
+    fn test_disposable_email_rule() {
+        let request = request(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "email": { "address": "someone@Mailinator.com" }
+        }));
+        let disposable = UserSignals {
+            email_traits: EmailTraits {
+                is_free: false,
+                is_disposable: true,
+            },
+            ..UserSignals::default()
+        };
+        let factors = evaluate_context(&request, &disposable);
+        assert_eq!(factors.len(), 1);
+        assert_eq!(factors[0].code, "DISPOSABLE_EMAIL");
+        assert_eq!(
+            factors[0].reason,
+            "Email domain mailinator.com hands out disposable addresses"
+        );
+
+        // Free providers are no risk by themselves
+        let free = UserSignals {
+            email_traits: EmailTraits {
+                is_free: true,
+                is_disposable: false,
+            },
+            ..UserSignals::default()
+        };
+        assert!(evaluate_context(&request, &free).is_empty());
+    }
+
+    #[test]
+    fn test_new_email_large_amount_rule_decays_with_good_history() {
+        let order = |amount: f64| {
+            request(serde_json::json!({
+                "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+                "event": { "type": "purchase" },
+                "email": { "address": "jane@example.com" },
+                "order": { "amount": amount, "currency": "USD" }
+            }))
+        };
+        let new_email = |days: i64, good_transactions: i64| -> Option<RiskFactor> {
+            let user = UserSignals {
+                email_age: Some(EmailAge {
+                    days,
+                    account_days: days,
+                    good_transactions,
+                }),
+                ..UserSignals::default()
+            };
+            new_email_large_amount(&order(1500.0), &user)
+        };
+        let fresh = new_email(0, 0).unwrap();
+        assert_eq!(fresh.code, "NEW_EMAIL_LARGE_AMOUNT");
+        assert_eq!(fresh.score, 30.0);
+        assert_eq!(
+            fresh.reason,
+            "Order amount 1500.00 USD is large for an email address first seen today"
+        );
+        let vouched = new_email(2, 1).unwrap();
+        assert_eq!(vouched.score, 15.0);
+        assert_eq!(
+            vouched.reason,
+            "Order amount 1500.00 USD is large for an email address first seen 2 days ago and 1 \
+             good transaction since"
+        );
+        assert_eq!(new_email(2, 2).unwrap().score, 7.5);
+        assert!(new_email(2, 3).is_none());
+        assert!(new_email(7, 0).is_none());
+
+        // Small orders and transactions without an email address are left alone
+        let user = UserSignals {
+            email_age: Some(EmailAge::default()),
+            ..UserSignals::default()
+        };
+        assert!(new_email_large_amount(&order(50.0), &user).is_none());
+        assert!(new_email_large_amount(&order(1500.0), &UserSignals::default()).is_none());
+    }
+
+    fn phone(country: &str, line_type: PhoneLineType) -> UserSignals {
+        UserSignals {
+            phone: Some(PhoneNumberInfo {
+                e164: "+445612345678".to_string(),
+                is_valid: true,
+                country: Some(country.to_string()),
+                line_type: Some(line_type),
+                operator: None,
+            }),
+            ..UserSignals::default()
+        }
+    }
+
+    #[test]
+    fn test_voip_phone_rule() {
+        let codes = |user: &UserSignals| -> Vec<String> {
+            evaluate_user(user).into_iter().map(|f| f.code).collect()
+        };
+        assert_eq!(codes(&phone("GB", PhoneLineType::Voip)), ["VOIP_PHONE"]);
+        assert!(codes(&phone("GB", PhoneLineType::Mobile)).is_empty());
+
+        // Numbers off the plan are not trusted to be of any line type
+        let mut invalid = phone("GB", PhoneLineType::Voip);
+        invalid.phone.as_mut().unwrap().is_valid = false;
+        assert!(codes(&invalid).is_empty());
+        assert!(codes(&UserSignals::default()).is_empty());
+    }
+
+    #[test]
+    fn test_phone_country_mismatch_rule() {
+        let request = request(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "billing": { "country": "GB" },
+            "shipping": { "country": "fr", "phone_number": "06 12 34 56 78" }
+        }));
+        // The shipping address has the phone number
+        assert!(evaluate_context(&request, &phone("FR", PhoneLineType::Mobile)).is_empty());
+        let factors = evaluate_context(&request, &phone("DE", PhoneLineType::Mobile));
+        assert_eq!(factors.len(), 1);
+        assert_eq!(factors[0].code, "PHONE_COUNTRY_MISMATCH");
+        assert_eq!(
+            factors[0].reason,
+            "Phone number is from DE, but its address is in fr"
+        );
+
+        // Shared ranges belong to no one country
+        let mut shared = phone("DE", PhoneLineType::TollFree);
+        shared.phone.as_mut().unwrap().country = None;
+        assert!(evaluate_context(&request, &shared).is_empty());
+    }
+
+    fn card_bin(bin: BinInfo) -> UserSignals {
+        UserSignals {
+            card_bin: Some(BinInfo {
+                prefix: "400000".to_string(),
+                ..bin
+            }),
+            ..UserSignals::default()
+        }
+    }
+
+    #[test]
+    fn test_card_testing_rule() {
+        let testing = |card_testing: CardTesting| {
+            let factors = evaluate_user(&UserSignals {
+                card_testing,
+                ..UserSignals::default()
+            });
+            factors
+                .into_iter()
+                .find(|factor| factor.code == "CARD_TESTING")
+        };
+        let sequential = testing(CardTesting {
+            attempts: 3,
+            distinct_bins: 1,
+            distinct_cards: 3,
+            sequential_cards: 3,
+            ..CardTesting::default()
+        })
+        .unwrap();
+        assert!(rejects_outright(&sequential));
+        assert_eq!(
+            sequential.reason,
+            "3 cards with sequential numbers were tried from the device or IP address in 10 minutes"
+        );
+        let bins = testing(CardTesting {
+            distinct_bins: 4,
+            ..CardTesting::default()
+        })
+        .unwrap();
+        assert!(bins.reason.starts_with("Cards of 4 BINs"));
+        let declines = testing(CardTesting {
+            small_attempts: 5,
+            declined_small_attempts: 3,
+            ..CardTesting::default()
+        })
+        .unwrap();
+        assert_eq!(
+            declines.reason,
+            "5 small payments were tried from the device or IP address in 10 minutes, 3 of them \
+             declined"
+        );
+
+        // A customer retrying a card or two is not testing cards
+        assert!(
+            testing(CardTesting {
+                attempts: 3,
+                distinct_bins: 2,
+                distinct_cards: 2,
+                small_attempts: 3,
+                declined_small_attempts: 2,
+                sequential_cards: 2,
+            })
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_prepaid_and_virtual_card_rules() {
+        let codes = |user: &UserSignals| -> Vec<String> {
+            evaluate_user(user).into_iter().map(|f| f.code).collect()
+        };
+        let prepaid = card_bin(BinInfo {
+            is_prepaid: true,
+            ..BinInfo::default()
+        });
+        assert_eq!(codes(&prepaid), ["PREPAID_CARD"]);
+        let both = card_bin(BinInfo {
+            is_prepaid: true,
+            is_virtual: true,
+            ..BinInfo::default()
+        });
+        assert_eq!(codes(&both), ["PREPAID_CARD", "VIRTUAL_CARD"]);
+        assert!(codes(&card_bin(BinInfo::default())).is_empty());
+    }
+
+    #[test]
+    fn test_bin_country_and_business_card_rules() {
+        let request = |card: serde_json::Value| {
+            request(serde_json::json!({
+                "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+                "event": { "type": "purchase" },
+                "email": { "address": "jane@gmail.com" },
+                "billing": { "country": "US" },
+                "credit_card": card
+            }))
+        };
+        let mut business = card_bin(BinInfo {
+            country: Some("GB".to_string()),
+            is_business: true,
+            ..BinInfo::default()
+        });
+        let codes = |request: &TransactionRequest, user: &UserSignals| -> Vec<String> {
+            evaluate_context(request, user)
+                .into_iter()
+                .map(|f| f.code)
+                .collect()
+        };
+
+        let unknown_country = request(serde_json::json!({ "issuer_id_number": "400000" }));
+        assert_eq!(
+            codes(&unknown_country, &business),
+            ["CARD_COUNTRY_MISMATCH"]
+        );
+        // The request's own card country is left to the request rule
+        let known_country =
+            request(serde_json::json!({ "issuer_id_number": "400000", "country": "GB" }));
+        assert!(codes(&known_country, &business).is_empty());
+
+        business.email_traits.is_free = true;
+        assert_eq!(
+            codes(&unknown_country, &business),
+            ["CARD_COUNTRY_MISMATCH", "BUSINESS_CARD_FREE_EMAIL"]
+        );
+        let consumer = card_bin(BinInfo {
+            country: Some("US".to_string()),
+            ..BinInfo::default()
+        });
+        assert!(codes(&unknown_country, &consumer).is_empty());
+    }
+
+    #[test]
+    fn test_sanctions_match_rule() {
+        let hit = |role, name: &str, list| SanctionsHit {
+            role,
+            name: name.to_string(),
+            party: ScreeningMatch {
+                list,
+                entry_id: "36092".to_string(),
+                name: "PETROV, Ivan Ivanovich".to_string(),
+                matched_name: "PETROV, Ivan Ivanovich".to_string(),
+                party_type: Some("individual".to_string()),
+                programs: vec!["RUSSIA-EO14024".to_string()],
+                countries: vec!["RU".to_string()],
+                score: 0.9,
+            },
+        };
+        let screened = |sanctions_hits| {
+            evaluate_user(&UserSignals {
+                sanctions_hits,
+                ..UserSignals::default()
+            })
+        };
+
+        assert!(screened(Vec::new()).is_empty());
+        let single = screened(vec![hit("Billing", "Ivan Petrov", SanctionsList::Ofac)]);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].code, "SANCTIONS_MATCH");
+        assert_eq!(
+            single[0].reason,
+            "Billing name matches PETROV, Ivan Ivanovich on the OFAC sanctions list"
+        );
+        assert!(forces_review(&single[0]));
+        assert!(!rejects_outright(&single[0]));
+        let metadata = single[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata["matches"][0]["list"], "ofac");
+        assert_eq!(metadata["matches"][0]["programs"][0], "RUSSIA-EO14024");
+
+        let several = screened(vec![
+            hit("Billing", "Ivan Petrov", SanctionsList::Ofac),
+            hit("Cardholder", "IVAN PETROV", SanctionsList::Eu),
+        ]);
+        assert_eq!(
+            several[0].reason,
+            "2 names match parties on sanctions lists"
+        );
+    }
+}
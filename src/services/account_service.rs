@@ -0,0 +1,385 @@
+//! Account self-service
+
+use std::collections::HashMap;
+
+use chrono::{Days, Duration, NaiveDate, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    config::{LifecycleConfig, MeteringConfig},
+    database::{
+        Tenant,
+        repositories::{
+            AccountRecord, AccountRepo, AccountSettingsUpdate, NewStatusChange, OrganizationRepo,
+            OutboxRepo, UsageRepo,
+        },
+    },
+    models::{
+        account::{
+            Account, AccountStatus, AccountUpdate, AutoBlockSettings, BillingCycleUsage,
+            DailyUsage, DispositionPolicy, NotificationSettings, StatusChange, SubscriptionTier,
+            UsageHistory,
+        },
+        common::{Link, Links},
+    },
+    outbox::{ACCOUNT_STATUS_CHANGED, ACCOUNT_UPDATED, AccountStatusChanged},
+};
+
+impl From<AccountRecord> for Account {
+    fn from(record: AccountRecord) -> Self {
+        Account {
+            account_id: record.account_id,
+            subscription_tier: record.subscription_tier,
+            sandbox: record.sandbox,
+            status: record.status,
+            status_changed_at: record.status_changed_at,
+            deletion_scheduled_at: record.deletion_scheduled_at,
+            contact_email: record.contact_email,
+            disposition_policy: record.disposition_policy,
+            notifications: NotificationSettings {
+                key_expiry: record.notify_key_expiry,
+                anomalies: record.notify_anomalies,
+            },
+            auto_block: AutoBlockSettings {
+                enabled: record.auto_block_enabled,
+                ttl_minutes: record.auto_block_ttl_minutes,
+            },
+            sanctions_screening: record.sanctions_screening,
+            case_assignment: record.case_assignment,
+            funds_remaining: record.funds_remaining,
+            monthly_quota: record.monthly_quota,
+            queries_used_this_month: record.queries_used_this_month,
+            queries_remaining_this_month: (record.monthly_quota - record.queries_used_this_month)
+                .max(0),
+            billing_cycle_start: record.billing_cycle_start,
+            billing_cycle_end: record.billing_cycle_end,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            links: Account::links(),
+        }
+    }
+}
+
+/// Reads and updates the calling account's own settings and status
+#[derive(Debug, Clone)]
+pub struct AccountService {
+    pool: PgPool,
+    metering: MeteringConfig,
+    lifecycle: LifecycleConfig,
+}
+
+impl AccountService {
+    /// Create an account service backed by the given pool, pricing usage with `metering` and
+    /// scheduling deletion of closed accounts per `lifecycle`
+    pub fn new(pool: PgPool, metering: MeteringConfig, lifecycle: LifecycleConfig) -> Self {
+        Self {
+            pool,
+            metering,
+            lifecycle,
+        }
+    }
+
+    /// Fetch an account
+    pub async fn get_account(&self, tenant: Tenant) -> ServiceResult<Account> {
+        AccountRepo::find_by_id(&self.pool, tenant)
+            .await?
+            .map(Account::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Apply an update to an account's settings
+    ///
+    /// The change and its `account.updated` event are written in one database transaction. An
+    /// update that changes nothing returns the account as it is, without an event.
+    pub async fn update_account(
+        &self,
+        tenant: Tenant,
+        update: &AccountUpdate,
+    ) -> ServiceResult<Account> {
+        update.validate().map_err(ServiceError::Invalid)?;
+        if update.is_empty() {
+            return self.get_account(tenant).await;
+        }
+
+        let notifications = update.notifications.unwrap_or_default();
+        let auto_block = update.auto_block.unwrap_or_default();
+        let settings = AccountSettingsUpdate {
+            contact_email: update
+                .contact_email
+                .as_deref()
+                .map(|email| Some(email).filter(|email| !email.is_empty())),
+            disposition_policy: update.disposition_policy,
+            notify_key_expiry: notifications.key_expiry,
+            notify_anomalies: notifications.anomalies,
+            auto_block_enabled: auto_block.enabled,
+            auto_block_ttl_minutes: auto_block.ttl_minutes,
+            sanctions_screening: update.sanctions_screening,
+            case_assignment: update.case_assignment,
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let account = AccountRepo::update_settings(&mut *tx, tenant, &settings)
+            .await?
+            .map(Account::from)
+            .ok_or(ServiceError::NotFound)?;
+        let payload = serde_json::to_value(&account).unwrap_or_default();
+        OutboxRepo::insert(&mut *tx, tenant.id(), ACCOUNT_UPDATED, tenant.id(), payload).await?;
+        tx.commit().await?;
+
+        tracing::info!(account_id = %tenant, "Account settings updated");
+        Ok(account)
+    }
+
+    /// Suspend, close, or reactivate an account on behalf of `api_key_id`
+    ///
+    /// Closing schedules the account's deletion after the retention period and reactivating
+    /// cancels it. The change, its audit log entry, and its `account.status_changed` event are
+    /// written in one database transaction. Sandbox accounts follow their live account and
+    /// cannot change status themselves.
+    pub async fn change_status(
+        &self,
+        tenant: Tenant,
+        api_key_id: Option<Uuid>,
+        to: AccountStatus,
+        change: &StatusChange,
+    ) -> ServiceResult<Account> {
+        change.validate().map_err(ServiceError::Invalid)?;
+        let reason = change
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|reason| !reason.is_empty());
+
+        let mut tx = self.pool.begin().await?;
+        let account = AccountRepo::find_by_id(&mut *tx, tenant)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        if account.sandbox {
+            return Err(ServiceError::Forbidden(
+                "A sandbox account has the status of its live account; use a live API key"
+                    .to_string(),
+            ));
+        }
+        let from = account.status;
+        if from == to {
+            return Err(ServiceError::Conflict(format!(
+                "The account is already {to}"
+            )));
+        }
+        if !from.can_become(to) {
+            return Err(ServiceError::Conflict(format!(
+                "The account is {from} and cannot be {}",
+                transition_verb(to)
+            )));
+        }
+        if to == AccountStatus::Closed
+            && OrganizationRepo::members_billed_to(&mut *tx, tenant.id()).await? > 0
+        {
+            return Err(ServiceError::Conflict(
+                "The account pays for an organization with other members; move the \
+                 organization's billing to another member first"
+                    .to_string(),
+            ));
+        }
+
+        let deletion_scheduled_at = (to == AccountStatus::Closed).then(|| {
+            Utc::now() + Duration::days(i64::from(self.lifecycle.closed_account_retention_days))
+        });
+        if !AccountRepo::set_status(&mut *tx, tenant, from, to, deletion_scheduled_at).await? {
+            return Err(ServiceError::Conflict(
+                "The account's status changed while processing the request; retry".to_string(),
+            ));
+        }
+        AccountRepo::log_status_change(
+            &mut *tx,
+            &NewStatusChange {
+                account_id: tenant.id(),
+                public_account_id: &account.account_id,
+                from_status: from,
+                to_status: to.as_str(),
+                reason,
+                api_key_id,
+            },
+        )
+        .await?;
+        let payload = serde_json::to_value(AccountStatusChanged {
+            account_id: account.account_id.clone(),
+            previous_status: from,
+            status: to,
+            reason: reason.map(str::to_string),
+            deletion_scheduled_at,
+        })
+        .unwrap_or_default();
+        OutboxRepo::insert(
+            &mut *tx,
+            tenant.id(),
+            ACCOUNT_STATUS_CHANGED,
+            tenant.id(),
+            payload,
+        )
+        .await?;
+        let account = AccountRepo::find_by_id(&mut *tx, tenant)
+            .await?
+            .map(Account::from)
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+
+        tracing::warn!(
+            account_id = %tenant,
+            %from,
+            %to,
+            reason,
+            "Account status changed"
+        );
+        Ok(account)
+    }
+
+    /// Usage and estimated charges of the account's `cycles` most recent billing cycles
+    ///
+    /// With Redis metering, figures for the current cycle lag by up to the usage sync
+    /// interval.
+    pub async fn usage_history(&self, tenant: Tenant, cycles: i64) -> ServiceResult<UsageHistory> {
+        let account = AccountRepo::find_by_id(&self.pool, tenant)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let archived = UsageRepo::billing_cycles(&self.pool, tenant, cycles - 1).await?;
+
+        let today = Utc::now().date_naive();
+        let from = archived
+            .last()
+            .map_or(account.billing_cycle_start, |cycle| cycle.cycle_start);
+        let daily: HashMap<NaiveDate, i32> = UsageRepo::daily(
+            &self.pool,
+            tenant,
+            from,
+            account.billing_cycle_end.max(today + Days::new(1)),
+        )
+        .await?
+        .into_iter()
+        .map(|record| (record.day, record.queries))
+        .collect();
+
+        let current = CycleTotals {
+            cycle_start: account.billing_cycle_start,
+            cycle_end: account.billing_cycle_end,
+            subscription_tier: account.subscription_tier,
+            monthly_quota: account.monthly_quota,
+            queries_used: account.queries_used_this_month,
+        };
+        let mut usage = vec![cycle_usage(&self.metering, current, true, today, &daily)];
+        usage.extend(archived.into_iter().map(|cycle| {
+            let totals = CycleTotals {
+                cycle_start: cycle.cycle_start,
+                cycle_end: cycle.cycle_end,
+                subscription_tier: cycle.subscription_tier,
+                monthly_quota: cycle.monthly_quota,
+                queries_used: cycle.queries_used,
+            };
+            cycle_usage(&self.metering, totals, false, today, &daily)
+        }));
+
+        Ok(UsageHistory {
+            currency: self.metering.currency.clone(),
+            cycles: usage,
+            links: Links {
+                self_link: Some(Link::new("/v1/account/usage")),
+                ..Links::default()
+            },
+        })
+    }
+
+    /// How the account wants risk levels translated into dispositions
+    pub async fn disposition_policy(&self, tenant: Tenant) -> ServiceResult<DispositionPolicy> {
+        Ok(AccountRepo::disposition_policy(&self.pool, tenant).await?)
+    }
+
+    /// Whether the account screens the names its transactions give against sanctions lists
+    pub async fn sanctions_screening(&self, tenant: Tenant) -> ServiceResult<bool> {
+        Ok(AccountRepo::sanctions_screening(&self.pool, tenant).await?)
+    }
+}
+
+/// How a move to `status` is described in error messages
+fn transition_verb(status: AccountStatus) -> &'static str {
+    match status {
+        AccountStatus::Active => "reactivated",
+        AccountStatus::Suspended => "suspended",
+        AccountStatus::Closed => "closed",
+    }
+}
+
+/// Quota and usage of one billing cycle, current or archived
+struct CycleTotals {
+    cycle_start: NaiveDate,
+    cycle_end: NaiveDate,
+    subscription_tier: SubscriptionTier,
+    monthly_quota: i32,
+    queries_used: i32,
+}
+
+/// Usage of one cycle, with a count for every day of it up to today
+fn cycle_usage(
+    metering: &MeteringConfig,
+    totals: CycleTotals,
+    current: bool,
+    today: NaiveDate,
+    daily: &HashMap<NaiveDate, i32>,
+) -> BillingCycleUsage {
+    let overage = (totals.queries_used - totals.monthly_quota).max(0);
+    let last_day = if current {
+        totals.cycle_end.min(today + Days::new(1))
+    } else {
+        totals.cycle_end
+    };
+    BillingCycleUsage {
+        cycle_start: totals.cycle_start,
+        cycle_end: totals.cycle_end,
+        current,
+        subscription_tier: totals.subscription_tier,
+        monthly_quota: totals.monthly_quota,
+        queries_used: totals.queries_used,
+        overage,
+        estimated_charges: metering.estimate_charges(totals.subscription_tier, overage),
+        daily: totals
+            .cycle_start
+            .iter_days()
+            .take_while(|day| *day < last_day)
+            .map(|date| DailyUsage {
+                date,
+                queries: daily.get(&date).copied().unwrap_or(0),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_cycle_usage_fills_days_up_to_today() {
+        let date = |day| NaiveDate::from_ymd_opt(2026, 10, day).unwrap();
+        let totals = || CycleTotals {
+            cycle_start: date(1),
+            cycle_end: NaiveDate::from_ymd_opt(2026, 11, 1).unwrap(),
+            subscription_tier: SubscriptionTier::Pro,
+            monthly_quota: 100,
+            queries_used: 2_600,
+        };
+        let daily = HashMap::from([(date(2), 2_000), (date(3), 600)]);
+        let metering = Config::default().metering;
+
+        let current = cycle_usage(&metering, totals(), true, date(4), &daily);
+        assert_eq!(current.overage, 2_500);
+        assert_eq!(current.estimated_charges, 111.5);
+        let counts: Vec<i32> = current.daily.iter().map(|d| d.queries).collect();
+        assert_eq!(counts, [0, 2_000, 600, 0]);
+
+        let archived = cycle_usage(&metering, totals(), false, date(4), &daily);
+        assert_eq!(archived.daily.len(), 31);
+        assert_eq!(archived.daily.last().map(|d| d.date), Some(date(31)));
+    }
+}
@@ -0,0 +1,1179 @@
+//! Transaction analytics backed by ClickHouse
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Duration, DurationRound, Months, NaiveDate, NaiveTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use super::ServiceResult;
+use crate::{
+    database::{
+        Tenant,
+        clickhouse::{ClickHouseClient, datetime_param},
+        repositories::AnomalyRepo,
+    },
+    models::{
+        analytics::{
+            Analytics, AnalyticsGroup, AnalyticsGroupBy, AnalyticsQuery, AnalyticsRange,
+            AnalyticsSummary, Anomaly, Cohort, CohortAnalysis, CohortMonth, DispositionCounts,
+            DisputeLosses, EntityKind, Exposure, Granularity, OutcomeSummary, Outcomes,
+            OutcomesQuery, ReviewerQuality, ReviewerQualityQuery, ReviewerQualityReport,
+            RiskDistribution, RiskyEntity, RuleOutcome, ShopAnalytics, ShopAnalyticsQuery,
+            ShopSort, ShopStats, TimeSeriesPoint, TopEntities, TopEntitiesQuery,
+        },
+        common::{Link, Links},
+    },
+};
+
+/// Restricts a query to one account and the requested window
+const WINDOW_FILTER: &str = "account_id = {account_id:UUID}
+      AND event_time >= {start:DateTime64(3, 'UTC')}
+      AND event_time < {end:DateTime64(3, 'UTC')}";
+
+/// Narrows [`WINDOW_FILTER`] to a single shop
+const SHOP_FILTER: &str = "AND shop_id = {shop_id:String}";
+
+pub(crate) const AVERAGE_RISK_SCORE: &str =
+    "if(count() = 0, 0, round(avg(risk_score), 2)) AS average_risk_score";
+
+pub(crate) const RISK_LEVEL_COUNTS: &str = "countIf(risk_level = 'low') AS low,
+       countIf(risk_level = 'medium') AS medium,
+       countIf(risk_level = 'high') AS high,
+       countIf(risk_level = 'very_high') AS very_high";
+
+pub(crate) const DISPOSITION_COUNTS: &str = "countIf(disposition = 'accept') AS accept,
+       countIf(disposition = 'reject') AS reject,
+       countIf(disposition = 'review') AS review,
+       countIf(disposition = 'test') AS test";
+
+/// Latest reported outcome of each of the account's transactions
+///
+/// Joined with `LEFT JOIN`, unreported transactions get an empty `tag`.
+const OUTCOMES_CTE: &str = "WITH outcomes AS (
+        SELECT transaction_id, argMax(tag, reported_at) AS tag
+        FROM transaction_outcomes
+        WHERE account_id = {account_id:UUID}
+        GROUP BY transaction_id
+    )";
+
+/// Refunds and cancellations of the account, summed per purchase they undo
+///
+/// `whole` is set when one of them has no amount and so undoes the whole purchase.
+/// Redelivered events are counted once.
+const REVERSALS_CTE: &str = "WITH reversals AS (
+        SELECT purchase_id, max(isNull(amount)) AS whole, sum(ifNull(amount, 0)) AS amount
+        FROM (
+            SELECT transaction_id,
+                   assumeNotNull(any(original_transaction_id)) AS purchase_id,
+                   any(amount) AS amount
+            FROM transaction_events
+            WHERE account_id = {account_id:UUID}
+              AND event_type IN ('refund', 'cancellation')
+              AND original_transaction_id IS NOT NULL
+            GROUP BY transaction_id
+        )
+        GROUP BY purchase_id
+    )";
+
+/// Users whose first transaction falls on or after `{start}`, with the month of that transaction
+///
+/// A user's first transaction is taken as their signup, as that is when they are registered.
+const COHORTS_CTE: &str = "cohorts AS (
+        SELECT user_id, toStartOfMonth(min(event_time)) AS signup_month
+        FROM transaction_events
+        WHERE account_id = {account_id:UUID} AND user_id IS NOT NULL
+        GROUP BY user_id
+        HAVING signup_month >= toDate({start:DateTime64(3, 'UTC')})
+    )";
+
+/// Report tags that confirm fraud, matching
+/// [`ReportTag::is_fraud`](crate::models::transaction::ReportTag::is_fraud)
+const FRAUD_TAGS: &str = "('chargeback', 'suspected_fraud', 'spam_or_abuse')";
+
+#[derive(Debug, Deserialize)]
+struct SummaryRow {
+    total_transactions: u64,
+    total_users: u64,
+    average_risk_score: f64,
+    #[serde(flatten)]
+    risk_distribution: RiskDistribution,
+    #[serde(flatten)]
+    disposition_counts: DispositionCounts,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeSeriesRow {
+    bucket: DateTime<Utc>,
+    transaction_count: u64,
+    user_count: u64,
+    average_risk_score: f64,
+    #[serde(flatten)]
+    disposition_counts: DispositionCounts,
+}
+
+#[derive(Debug, Deserialize)]
+struct GroupRow {
+    key: String,
+    transaction_count: u64,
+    average_risk_score: f64,
+    #[serde(flatten)]
+    risk_distribution: RiskDistribution,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShopRow {
+    shop_id: String,
+    transaction_count: u64,
+    user_count: u64,
+    average_risk_score: f64,
+    fraud_reports: u64,
+    #[serde(flatten)]
+    risk_distribution: RiskDistribution,
+    #[serde(flatten)]
+    disposition_counts: DispositionCounts,
+}
+
+#[derive(Debug, Deserialize)]
+struct RiskyEntityRow {
+    value: String,
+    transaction_count: u64,
+    user_count: u64,
+    average_risk_score: f64,
+    max_risk_score: f64,
+    rule_hits: u64,
+    rejected: u64,
+    last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OutcomeRow {
+    total_transactions: u64,
+    reported_transactions: u64,
+    fraud_transactions: u64,
+    accepted: u64,
+    accepted_then_charged_back: u64,
+    rejected: u64,
+    rejected_then_reported_legitimate: u64,
+    reviewed: u64,
+    reviewed_with_outcome: u64,
+    review_overturned: u64,
+    flagged_with_outcome: u64,
+    flagged_fraud: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewerQualityRow {
+    reviewer: String,
+    decisions: u64,
+    approved: u64,
+    approved_then_charged_back: u64,
+    declined: u64,
+    declined_then_overturned: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CohortSizeRow {
+    signup_month: NaiveDate,
+    users: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CohortMonthRow {
+    signup_month: NaiveDate,
+    month: NaiveDate,
+    active_users: u64,
+    transaction_count: u64,
+    average_risk_score: f64,
+    accepted: u64,
+    chargebacks: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleOutcomeRow {
+    rule_code: String,
+    transactions: u64,
+    reported_transactions: u64,
+    fraud_transactions: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExposureRow {
+    currency: String,
+    purchases: u64,
+    purchase_amount: f64,
+    refunded_amount: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DisputeLossRow {
+    currency: Option<String>,
+    disputes: u64,
+    disputed_amount: f64,
+    won_amount: f64,
+    lost_amount: f64,
+    open_amount: f64,
+}
+
+/// Aggregates scored transactions per account
+#[derive(Debug, Clone)]
+pub struct AnalyticsService {
+    client: ClickHouseClient,
+    read_pool: PgPool,
+}
+
+impl AnalyticsService {
+    /// Create an analytics service querying ClickHouse through `client` and reading detected
+    /// anomalies from `read_pool`
+    pub fn new(client: ClickHouseClient, read_pool: PgPool) -> Self {
+        Self { client, read_pool }
+    }
+
+    /// Page through the anomalies detected for an account, most recent hour first
+    pub async fn list_anomalies(
+        &self,
+        tenant: Tenant,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<Anomaly>, i64)> {
+        let anomalies = AnomalyRepo::list(&self.read_pool, tenant, limit, offset).await?;
+        let total = AnomalyRepo::count(&self.read_pool, tenant).await?;
+        Ok((anomalies, total))
+    }
+
+    /// Summarise an account's transactions over the window ending at `now`
+    pub async fn analytics(
+        &self,
+        tenant: Tenant,
+        query: &AnalyticsQuery,
+        now: DateTime<Utc>,
+    ) -> ServiceResult<Analytics> {
+        let granularity = query.granularity.unwrap_or_default();
+        let end = now;
+        let start = end - query.period.unwrap_or_default().duration();
+        let mut params = vec![
+            ("account_id", tenant.to_string()),
+            ("start", datetime_param(start)),
+            ("end", datetime_param(end)),
+        ];
+        let shop_filter = match &query.shop_id {
+            Some(shop_id) => {
+                params.push(("shop_id", shop_id.clone()));
+                SHOP_FILTER
+            },
+            None => "",
+        };
+
+        let summary = self
+            .client
+            .query::<SummaryRow>(
+                &format!(
+                    "SELECT count() AS total_transactions,
+                            uniqExact(user_id) AS total_users,
+                            {AVERAGE_RISK_SCORE},
+                            {RISK_LEVEL_COUNTS},
+                            {DISPOSITION_COUNTS}
+                     FROM transaction_events FINAL
+                     WHERE {WINDOW_FILTER} {shop_filter}"
+                ),
+                &params,
+            )
+            .await?
+            .into_iter()
+            .next()
+            .map(|row| AnalyticsSummary {
+                total_transactions: row.total_transactions,
+                total_users: row.total_users,
+                average_risk_score: row.average_risk_score,
+                risk_distribution: row.risk_distribution,
+                disposition_counts: row.disposition_counts,
+            })
+            .unwrap_or_default();
+
+        let series = self
+            .client
+            .query::<TimeSeriesRow>(
+                &format!(
+                    "SELECT {bucket} AS bucket,
+                            count() AS transaction_count,
+                            uniqExact(user_id) AS user_count,
+                            {AVERAGE_RISK_SCORE},
+                            {DISPOSITION_COUNTS}
+                     FROM transaction_events FINAL
+                     WHERE {WINDOW_FILTER} {shop_filter}
+                     GROUP BY bucket
+                     ORDER BY bucket",
+                    bucket = bucket_expression(granularity)
+                ),
+                &params,
+            )
+            .await?;
+
+        let groups = match query.group_by {
+            Some(group_by) => Some(
+                self.client
+                    .query::<GroupRow>(
+                        &format!(
+                            "SELECT {key} AS key,
+                                    count() AS transaction_count,
+                                    {AVERAGE_RISK_SCORE},
+                                    {RISK_LEVEL_COUNTS}
+                             FROM transaction_events FINAL
+                             WHERE {WINDOW_FILTER} {shop_filter}
+                             GROUP BY key
+                             ORDER BY transaction_count DESC, key",
+                            key = group_key_expression(group_by)
+                        ),
+                        &params,
+                    )
+                    .await?
+                    .into_iter()
+                    .map(|row| AnalyticsGroup {
+                        key: row.key,
+                        transaction_count: row.transaction_count,
+                        average_risk_score: row.average_risk_score,
+                        risk_distribution: row.risk_distribution,
+                    })
+                    .collect(),
+            ),
+            None => None,
+        };
+
+        let exposure = self
+            .client
+            .query::<ExposureRow>(
+                &format!(
+                    "{REVERSALS_CTE}
+                     SELECT e.currency AS currency,
+                            count() AS purchases,
+                            round(sum(e.amount), 2) AS purchase_amount,
+                            round(sum(if(r.whole = 1, e.amount, least(r.amount, e.amount))), 2)
+                                AS refunded_amount
+                     FROM (
+                         SELECT transaction_id,
+                                assumeNotNull(amount) AS amount,
+                                assumeNotNull(currency) AS currency
+                         FROM transaction_events FINAL
+                         WHERE {WINDOW_FILTER} {shop_filter}
+                           AND event_type IN ('purchase', 'recurring_purchase')
+                           AND disposition = 'accept'
+                           AND amount IS NOT NULL AND currency IS NOT NULL
+                     ) AS e
+                     LEFT JOIN reversals AS r ON r.purchase_id = e.transaction_id
+                     GROUP BY currency
+                     ORDER BY purchase_amount DESC, currency"
+                ),
+                &params,
+            )
+            .await?;
+
+        Ok(Analytics {
+            period: AnalyticsRange { start, end },
+            granularity,
+            summary,
+            time_series: fill_time_series(series, start, end, granularity),
+            exposure: exposure.into_iter().map(exposure_in_currency).collect(),
+            groups,
+            links: Links {
+                self_link: Some(Link::new("/v1/analytics".to_string())),
+                ..Links::default()
+            },
+        })
+    }
+
+    /// Break the account's transactions in the window ending at `now` down by shop
+    ///
+    /// `limit` must already be validated; it is interpolated into the query.
+    pub async fn shops(
+        &self,
+        tenant: Tenant,
+        query: &ShopAnalyticsQuery,
+        limit: u32,
+        now: DateTime<Utc>,
+    ) -> ServiceResult<ShopAnalytics> {
+        let end = now;
+        let start = end - query.period.unwrap_or_default().duration();
+        let params = [
+            ("account_id", tenant.to_string()),
+            ("start", datetime_param(start)),
+            ("end", datetime_param(end)),
+            (
+                "min_transactions",
+                query.min_transactions.unwrap_or(1).to_string(),
+            ),
+        ];
+
+        let shops = self
+            .client
+            .query::<ShopRow>(
+                &format!(
+                    "{OUTCOMES_CTE}
+                     SELECT e.shop AS shop_id,
+                            count() AS transaction_count,
+                            uniqExact(user_id) AS user_count,
+                            {AVERAGE_RISK_SCORE},
+                            countIf(o.tag IN {FRAUD_TAGS}) AS fraud_reports,
+                            {RISK_LEVEL_COUNTS},
+                            {DISPOSITION_COUNTS}
+                     FROM (
+                         SELECT transaction_id, assumeNotNull(shop_id) AS shop, user_id,
+                                risk_score, risk_level, disposition
+                         FROM transaction_events FINAL
+                         WHERE {WINDOW_FILTER} AND shop_id IS NOT NULL
+                     ) AS e
+                     LEFT JOIN outcomes AS o ON o.transaction_id = e.transaction_id
+                     GROUP BY e.shop
+                     HAVING transaction_count >= {{min_transactions:UInt64}}
+                     ORDER BY {order}
+                     LIMIT {limit}",
+                    order = shop_order(query.sort.unwrap_or_default())
+                ),
+                &params,
+            )
+            .await?;
+
+        Ok(ShopAnalytics {
+            period: AnalyticsRange { start, end },
+            shops: shops.into_iter().map(shop_stats).collect(),
+            links: Links {
+                self_link: Some(Link::new("/v1/analytics/shops".to_string())),
+                ..Links::default()
+            },
+        })
+    }
+
+    /// Rank the entities of one kind seen in the account's transactions in the window ending
+    /// at `now` by average risk score
+    ///
+    /// `limit` must already be validated; it is interpolated into the query.
+    pub async fn top_entities(
+        &self,
+        tenant: Tenant,
+        query: &TopEntitiesQuery,
+        limit: u32,
+        now: DateTime<Utc>,
+    ) -> ServiceResult<TopEntities> {
+        let end = now;
+        let start = end - query.period.unwrap_or_default().duration();
+        let params = [
+            ("account_id", tenant.to_string()),
+            ("start", datetime_param(start)),
+            ("end", datetime_param(end)),
+            (
+                "min_transactions",
+                query.min_transactions.unwrap_or(1).to_string(),
+            ),
+        ];
+
+        let rows = self
+            .client
+            .query::<RiskyEntityRow>(
+                &format!(
+                    "SELECT toString(assumeNotNull({column})) AS value,
+                            count() AS transaction_count,
+                            uniqExact(user_id) AS user_count,
+                            {AVERAGE_RISK_SCORE},
+                            max(risk_score) AS max_risk_score,
+                            sum(length(rule_codes)) AS rule_hits,
+                            countIf(disposition = 'reject') AS rejected,
+                            max(event_time) AS last_seen
+                     FROM transaction_events FINAL
+                     WHERE {WINDOW_FILTER} AND {column} IS NOT NULL
+                     GROUP BY value
+                     HAVING transaction_count >= {{min_transactions:UInt64}}
+                     ORDER BY average_risk_score DESC, transaction_count DESC, value
+                     LIMIT {limit}",
+                    column = entity_column(query.entity)
+                ),
+                &params,
+            )
+            .await?;
+
+        Ok(TopEntities {
+            period: AnalyticsRange { start, end },
+            entity: query.entity,
+            entities: rows
+                .into_iter()
+                .map(|row| RiskyEntity {
+                    value: row.value,
+                    transaction_count: row.transaction_count,
+                    user_count: row.user_count,
+                    average_risk_score: row.average_risk_score,
+                    max_risk_score: row.max_risk_score,
+                    rule_hits: row.rule_hits,
+                    rejected: row.rejected,
+                    last_seen: row.last_seen,
+                })
+                .collect(),
+            links: Links {
+                self_link: Some(Link::new("/v1/analytics/top-entities".to_string())),
+                ..Links::default()
+            },
+        })
+    }
+
+    /// Follow the account's users who signed up in the last `months` calendar months, up to
+    /// and including the one containing `now`, through each month since their signup
+    ///
+    /// `months` must already be validated and at least 1.
+    pub async fn cohorts(
+        &self,
+        tenant: Tenant,
+        months: u32,
+        now: DateTime<Utc>,
+    ) -> ServiceResult<CohortAnalysis> {
+        let current_month = month_start(now.date_naive());
+        let start_month = current_month
+            .checked_sub_months(Months::new(months - 1))
+            .unwrap_or(current_month);
+        let start = start_month.and_time(NaiveTime::MIN).and_utc();
+        let params = [
+            ("account_id", tenant.to_string()),
+            ("start", datetime_param(start)),
+            ("end", datetime_param(now)),
+        ];
+
+        let sizes = self
+            .client
+            .query::<CohortSizeRow>(
+                &format!(
+                    "WITH {COHORTS_CTE}
+                     SELECT signup_month, count() AS users
+                     FROM cohorts
+                     GROUP BY signup_month
+                     ORDER BY signup_month"
+                ),
+                &params,
+            )
+            .await?;
+
+        let activity = self
+            .client
+            .query::<CohortMonthRow>(
+                &format!(
+                    "{OUTCOMES_CTE}, {COHORTS_CTE}
+                     SELECT c.signup_month AS signup_month,
+                            toStartOfMonth(e.event_time) AS month,
+                            uniqExact(e.user_id) AS active_users,
+                            count() AS transaction_count,
+                            {AVERAGE_RISK_SCORE},
+                            countIf(e.disposition = 'accept') AS accepted,
+                            countIf(o.tag = 'chargeback') AS chargebacks
+                     FROM (
+                         SELECT transaction_id, user_id, event_time, risk_score, disposition
+                         FROM transaction_events FINAL
+                         WHERE {WINDOW_FILTER} AND user_id IS NOT NULL
+                     ) AS e
+                     INNER JOIN cohorts AS c ON c.user_id = e.user_id
+                     LEFT JOIN outcomes AS o ON o.transaction_id = e.transaction_id
+                     GROUP BY signup_month, month
+                     ORDER BY signup_month, month"
+                ),
+                &params,
+            )
+            .await?;
+
+        Ok(CohortAnalysis {
+            start_month,
+            cohorts: build_cohorts(sizes, activity, current_month),
+            links: Links {
+                self_link: Some(Link::new("/v1/analytics/cohorts".to_string())),
+                ..Links::default()
+            },
+        })
+    }
+
+    /// Measure the account's decisions in the window ending at `now` against the outcomes
+    /// reported for those transactions
+    pub async fn outcomes(
+        &self,
+        tenant: Tenant,
+        query: &OutcomesQuery,
+        now: DateTime<Utc>,
+    ) -> ServiceResult<Outcomes> {
+        let end = now;
+        let start = end - query.period.unwrap_or_default().duration();
+        let params = [
+            ("account_id", tenant.to_string()),
+            ("start", datetime_param(start)),
+            ("end", datetime_param(end)),
+        ];
+
+        let summary = self
+            .client
+            .query::<OutcomeRow>(
+                &format!(
+                    "{OUTCOMES_CTE}
+                     SELECT count() AS total_transactions,
+                            countIf(o.tag != '') AS reported_transactions,
+                            countIf(o.tag IN {FRAUD_TAGS}) AS fraud_transactions,
+                            countIf(e.disposition = 'accept') AS accepted,
+                            countIf(e.disposition = 'accept' AND o.tag = 'chargeback')
+                                AS accepted_then_charged_back,
+                            countIf(e.disposition = 'reject') AS rejected,
+                            countIf(e.disposition = 'reject' AND o.tag = 'not_fraud')
+                                AS rejected_then_reported_legitimate,
+                            countIf(e.disposition = 'review') AS reviewed,
+                            countIf(e.disposition = 'review' AND o.tag != '')
+                                AS reviewed_with_outcome,
+                            countIf(e.disposition = 'review' AND o.tag = 'not_fraud')
+                                AS review_overturned,
+                            countIf(e.disposition IN ('reject', 'review') AND o.tag != '')
+                                AS flagged_with_outcome,
+                            countIf(e.disposition IN ('reject', 'review') AND o.tag IN {FRAUD_TAGS})
+                                AS flagged_fraud
+                     FROM (
+                         SELECT transaction_id, disposition
+                         FROM transaction_events FINAL
+                         WHERE {WINDOW_FILTER}
+                     ) AS e
+                     LEFT JOIN outcomes AS o ON o.transaction_id = e.transaction_id"
+                ),
+                &params,
+            )
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+
+        let rules = self
+            .client
+            .query::<RuleOutcomeRow>(
+                &format!(
+                    "{OUTCOMES_CTE}
+                     SELECT e.rule_code AS rule_code,
+                            count() AS transactions,
+                            countIf(o.tag != '') AS reported_transactions,
+                            countIf(o.tag IN {FRAUD_TAGS}) AS fraud_transactions
+                     FROM (
+                         SELECT transaction_id, rule_code
+                         FROM transaction_events FINAL
+                         ARRAY JOIN rule_codes AS rule_code
+                         WHERE {WINDOW_FILTER}
+                     ) AS e
+                     LEFT JOIN outcomes AS o ON o.transaction_id = e.transaction_id
+                     GROUP BY rule_code
+                     ORDER BY transactions DESC, rule_code"
+                ),
+                &params,
+            )
+            .await?;
+
+        let disputes = self
+            .client
+            .query::<DisputeLossRow>(
+                "SELECT currency,
+                        count() AS disputes,
+                        round(sum(ifNull(amount, 0)), 2) AS disputed_amount,
+                        round(sumIf(ifNull(amount, 0), status = 'won'), 2) AS won_amount,
+                        round(sumIf(ifNull(amount, 0), status = 'lost'), 2) AS lost_amount,
+                        round(sumIf(ifNull(amount, 0), status IN ('received', 'representment')), 2)
+                            AS open_amount
+                 FROM (
+                     SELECT dispute_id,
+                            argMax(status, updated_at) AS status,
+                            argMax(amount, updated_at) AS amount,
+                            argMax(currency, updated_at) AS currency,
+                            min(received_at) AS received_at
+                     FROM disputes
+                     WHERE account_id = {account_id:UUID}
+                     GROUP BY dispute_id
+                 )
+                 WHERE received_at >= {start:DateTime64(3, 'UTC')}
+                   AND received_at < {end:DateTime64(3, 'UTC')}
+                 GROUP BY currency
+                 ORDER BY disputed_amount DESC, currency",
+                &params,
+            )
+            .await?;
+
+        Ok(Outcomes {
+            period: AnalyticsRange { start, end },
+            rules: rules
+                .into_iter()
+                .map(|row| rule_outcome(row, summary.fraud_transactions))
+                .collect(),
+            dispute_losses: disputes.into_iter().map(dispute_losses).collect(),
+            summary: outcome_summary(&summary),
+            links: Links {
+                self_link: Some(Link::new("/v1/analytics/outcomes".to_string())),
+                ..Links::default()
+            },
+        })
+    }
+
+    /// Compare each reviewer's case decisions over the window ending at `now` with the
+    /// outcomes reported for those transactions afterwards
+    pub async fn reviewer_quality(
+        &self,
+        tenant: Tenant,
+        query: &ReviewerQualityQuery,
+        now: DateTime<Utc>,
+    ) -> ServiceResult<ReviewerQualityReport> {
+        let end = now;
+        let start = end - query.period.unwrap_or_default().duration();
+        let params = [
+            ("account_id", tenant.to_string()),
+            ("start", datetime_param(start)),
+            ("end", datetime_param(end)),
+        ];
+
+        let reviewers = self
+            .client
+            .query::<ReviewerQualityRow>(
+                &format!(
+                    "{OUTCOMES_CTE}
+                     SELECT d.reviewer AS reviewer,
+                            count() AS decisions,
+                            countIf(d.decision = 'approve') AS approved,
+                            countIf(d.decision = 'approve' AND o.tag = 'chargeback')
+                                AS approved_then_charged_back,
+                            countIf(d.decision = 'decline') AS declined,
+                            countIf(d.decision = 'decline' AND o.tag = 'not_fraud')
+                                AS declined_then_overturned
+                     FROM (
+                         SELECT transaction_id, reviewer, decision
+                         FROM case_decisions FINAL
+                         WHERE account_id = {{account_id:UUID}}
+                           AND resolved_at >= {{start:DateTime64(3, 'UTC')}}
+                           AND resolved_at < {{end:DateTime64(3, 'UTC')}}
+                     ) AS d
+                     LEFT JOIN outcomes AS o ON o.transaction_id = d.transaction_id
+                     GROUP BY reviewer
+                     ORDER BY decisions DESC, reviewer"
+                ),
+                &params,
+            )
+            .await?;
+
+        Ok(ReviewerQualityReport {
+            period: AnalyticsRange { start, end },
+            reviewers: reviewers.into_iter().map(reviewer_quality).collect(),
+            links: Links {
+                self_link: Some(Link::new("/v1/analytics/reviewers".to_string())),
+                ..Links::default()
+            },
+        })
+    }
+}
+
+/// `transaction_events` column holding an entity kind
+fn entity_column(entity: EntityKind) -> &'static str {
+    match entity {
+        EntityKind::Ip => "ip_address",
+        EntityKind::Device => "device_id",
+        EntityKind::EmailDomain => "email_domain",
+        EntityKind::Bin => "card_bin",
+    }
+}
+
+/// ClickHouse `ORDER BY` clause for the per-shop breakdown
+fn shop_order(sort: ShopSort) -> &'static str {
+    match sort {
+        ShopSort::TransactionCount => "transaction_count DESC, shop_id",
+        ShopSort::RejectRate => "reject / transaction_count DESC, transaction_count DESC, shop_id",
+        ShopSort::FraudRate => {
+            "fraud_reports / transaction_count DESC, transaction_count DESC, shop_id"
+        },
+        ShopSort::AverageRiskScore => "average_risk_score DESC, transaction_count DESC, shop_id",
+    }
+}
+
+fn shop_stats(row: ShopRow) -> ShopStats {
+    let transactions = row.transaction_count;
+    ShopStats {
+        reject_rate: rate(row.disposition_counts.reject, transactions).unwrap_or_default(),
+        review_rate: rate(row.disposition_counts.review, transactions).unwrap_or_default(),
+        fraud_rate: rate(row.fraud_reports, transactions).unwrap_or_default(),
+        shop_id: row.shop_id,
+        transaction_count: transactions,
+        user_count: row.user_count,
+        average_risk_score: row.average_risk_score,
+        fraud_reports: row.fraud_reports,
+        risk_distribution: row.risk_distribution,
+        disposition_counts: row.disposition_counts,
+    }
+}
+
+/// `numerator / denominator` to four decimal places, or `None` when nothing was counted
+fn rate(numerator: u64, denominator: u64) -> Option<f64> {
+    (denominator > 0).then(|| (numerator as f64 / denominator as f64 * 10_000.0).round() / 10_000.0)
+}
+
+fn outcome_summary(row: &OutcomeRow) -> OutcomeSummary {
+    OutcomeSummary {
+        total_transactions: row.total_transactions,
+        reported_transactions: row.reported_transactions,
+        fraud_transactions: row.fraud_transactions,
+        accepted: row.accepted,
+        accepted_then_charged_back: row.accepted_then_charged_back,
+        accepted_chargeback_rate: rate(row.accepted_then_charged_back, row.accepted),
+        rejected: row.rejected,
+        rejected_then_reported_legitimate: row.rejected_then_reported_legitimate,
+        reviewed: row.reviewed,
+        reviewed_with_outcome: row.reviewed_with_outcome,
+        review_overturn_rate: rate(row.review_overturned, row.reviewed_with_outcome),
+        precision: rate(row.flagged_fraud, row.flagged_with_outcome),
+        recall: rate(row.flagged_fraud, row.fraud_transactions),
+    }
+}
+
+fn rule_outcome(row: RuleOutcomeRow, total_fraud: u64) -> RuleOutcome {
+    RuleOutcome {
+        precision: rate(row.fraud_transactions, row.reported_transactions),
+        recall: rate(row.fraud_transactions, total_fraud),
+        rule_code: row.rule_code,
+        transactions: row.transactions,
+        reported_transactions: row.reported_transactions,
+        fraud_transactions: row.fraud_transactions,
+    }
+}
+
+fn exposure_in_currency(row: ExposureRow) -> Exposure {
+    Exposure {
+        exposure: ((row.purchase_amount - row.refunded_amount) * 100.0).round() / 100.0,
+        currency: row.currency,
+        purchases: row.purchases,
+        purchase_amount: row.purchase_amount,
+        refunded_amount: row.refunded_amount,
+    }
+}
+
+fn dispute_losses(row: DisputeLossRow) -> DisputeLosses {
+    DisputeLosses {
+        net_loss: ((row.disputed_amount - row.won_amount) * 100.0).round() / 100.0,
+        currency: row.currency,
+        disputes: row.disputes,
+        disputed_amount: row.disputed_amount,
+        won_amount: row.won_amount,
+        lost_amount: row.lost_amount,
+        open_amount: row.open_amount,
+    }
+}
+
+fn reviewer_quality(row: ReviewerQualityRow) -> ReviewerQuality {
+    ReviewerQuality {
+        approved_chargeback_rate: rate(row.approved_then_charged_back, row.approved),
+        declined_overturn_rate: rate(row.declined_then_overturned, row.declined),
+        overturn_rate: rate(
+            row.approved_then_charged_back + row.declined_then_overturned,
+            row.decisions,
+        ),
+        reviewer: row.reviewer,
+        decisions: row.decisions,
+        approved: row.approved,
+        approved_then_charged_back: row.approved_then_charged_back,
+        declined: row.declined,
+        declined_then_overturned: row.declined_then_overturned,
+    }
+}
+
+/// First day of the month containing `date`
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Whole calendar months from `from` to `to`
+fn months_between(from: NaiveDate, to: NaiveDate) -> u32 {
+    let months = (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32;
+    months.max(0) as u32
+}
+
+/// Assemble cohorts from their sizes and sparse monthly activity, giving each one an entry for
+/// every month from its signup month through `current_month`
+fn build_cohorts(
+    sizes: Vec<CohortSizeRow>,
+    activity: Vec<CohortMonthRow>,
+    current_month: NaiveDate,
+) -> Vec<Cohort> {
+    let mut activity: HashMap<(NaiveDate, NaiveDate), CohortMonthRow> = activity
+        .into_iter()
+        .map(|row| ((row.signup_month, row.month), row))
+        .collect();
+
+    sizes
+        .into_iter()
+        .map(|size| {
+            let mut months = Vec::new();
+            let mut month = size.signup_month;
+            while month <= current_month {
+                let months_since_signup = months_between(size.signup_month, month);
+                months.push(match activity.remove(&(size.signup_month, month)) {
+                    Some(row) => CohortMonth {
+                        month,
+                        months_since_signup,
+                        active_users: row.active_users,
+                        transaction_count: row.transaction_count,
+                        average_risk_score: row.average_risk_score,
+                        success_rate: rate(row.accepted, row.transaction_count).unwrap_or_default(),
+                        chargebacks: row.chargebacks,
+                    },
+                    None => CohortMonth {
+                        month,
+                        months_since_signup,
+                        ..CohortMonth::default()
+                    },
+                });
+                let Some(next) = month.checked_add_months(Months::new(1)) else {
+                    break;
+                };
+                month = next;
+            }
+            Cohort {
+                signup_month: size.signup_month,
+                users: size.users,
+                months,
+            }
+        })
+        .collect()
+}
+
+/// ClickHouse expression truncating `event_time` to its bucket
+fn bucket_expression(granularity: Granularity) -> &'static str {
+    match granularity {
+        Granularity::Hour => "toDateTime(toStartOfHour(event_time), 'UTC')",
+        Granularity::Day => "toDateTime(toStartOfDay(event_time), 'UTC')",
+        Granularity::Week => "toDateTime(toMonday(event_time), 'UTC')",
+    }
+}
+
+/// ClickHouse expression producing the grouping key
+fn group_key_expression(group_by: AnalyticsGroupBy) -> &'static str {
+    match group_by {
+        AnalyticsGroupBy::EventType => "toString(event_type)",
+        AnalyticsGroupBy::RiskLevel => "toString(risk_level)",
+        AnalyticsGroupBy::Disposition => "toString(disposition)",
+        AnalyticsGroupBy::ShopId => "ifNull(shop_id, '')",
+    }
+}
+
+/// Start of the bucket containing `time`, matching [`bucket_expression`]
+fn bucket_start(time: DateTime<Utc>, granularity: Granularity) -> DateTime<Utc> {
+    match granularity {
+        Granularity::Hour => time.duration_trunc(Duration::hours(1)).unwrap_or(time),
+        Granularity::Day => time.duration_trunc(Duration::days(1)).unwrap_or(time),
+        Granularity::Week => {
+            let day = time.duration_trunc(Duration::days(1)).unwrap_or(time);
+            day - Duration::days(i64::from(day.weekday().num_days_from_monday()))
+        },
+    }
+}
+
+fn bucket_width(granularity: Granularity) -> Duration {
+    match granularity {
+        Granularity::Hour => Duration::hours(1),
+        Granularity::Day => Duration::days(1),
+        Granularity::Week => Duration::weeks(1),
+    }
+}
+
+/// Turn sparse per-bucket rows into a contiguous series covering `[start, end)`
+fn fill_time_series(
+    rows: Vec<TimeSeriesRow>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    granularity: Granularity,
+) -> Vec<TimeSeriesPoint> {
+    let mut rows: HashMap<DateTime<Utc>, TimeSeriesRow> =
+        rows.into_iter().map(|row| (row.bucket, row)).collect();
+    let mut points = Vec::new();
+    let mut bucket = bucket_start(start, granularity);
+
+    while bucket < end {
+        points.push(match rows.remove(&bucket) {
+            Some(row) => TimeSeriesPoint {
+                timestamp: bucket,
+                transaction_count: row.transaction_count,
+                user_count: row.user_count,
+                average_risk_score: row.average_risk_score,
+                disposition_counts: row.disposition_counts,
+            },
+            None => TimeSeriesPoint {
+                timestamp: bucket,
+                ..TimeSeriesPoint::default()
+            },
+        });
+        bucket += bucket_width(granularity);
+    }
+
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_bucket_start() {
+        let time = Utc.with_ymd_and_hms(2025, 6, 13, 10, 42, 7).unwrap();
+        assert_eq!(bucket_start(time, Granularity::Hour), at(13, 10));
+        assert_eq!(bucket_start(time, Granularity::Day), at(13, 0));
+        // 13 June 2025 is a Friday
+        assert_eq!(bucket_start(time, Granularity::Week), at(9, 0));
+    }
+
+    #[test]
+    fn test_fill_time_series_includes_empty_buckets() {
+        let row: TimeSeriesRow = serde_json::from_value(serde_json::json!({
+            "bucket": "2025-06-12T00:00:00Z",
+            "transaction_count": 4,
+            "user_count": 3,
+            "average_risk_score": 21.5,
+            "accept": 3,
+            "reject": 1,
+            "review": 0,
+            "test": 0
+        }))
+        .unwrap();
+
+        let series = fill_time_series(vec![row], at(10, 15), at(13, 15), Granularity::Day);
+
+        let timestamps: Vec<_> = series.iter().map(|p| p.timestamp).collect();
+        assert_eq!(timestamps, vec![at(10, 0), at(11, 0), at(12, 0), at(13, 0)]);
+        assert_eq!(series[1].transaction_count, 0);
+        assert_eq!(series[2].transaction_count, 4);
+        assert_eq!(series[2].disposition_counts.reject, 1);
+    }
+
+    #[test]
+    fn test_outcome_summary_rates() {
+        let row = OutcomeRow {
+            total_transactions: 1000,
+            reported_transactions: 60,
+            fraud_transactions: 40,
+            accepted: 800,
+            accepted_then_charged_back: 8,
+            rejected: 120,
+            rejected_then_reported_legitimate: 5,
+            reviewed: 80,
+            reviewed_with_outcome: 20,
+            review_overturned: 5,
+            flagged_with_outcome: 40,
+            flagged_fraud: 30,
+        };
+
+        let summary = outcome_summary(&row);
+        assert_eq!(summary.accepted_chargeback_rate, Some(0.01));
+        assert_eq!(summary.review_overturn_rate, Some(0.25));
+        assert_eq!(summary.precision, Some(0.75));
+        assert_eq!(summary.recall, Some(0.75));
+    }
+
+    #[test]
+    fn test_rates_are_null_without_outcomes() {
+        let summary = outcome_summary(&OutcomeRow {
+            total_transactions: 10,
+            accepted: 10,
+            ..OutcomeRow::default()
+        });
+        assert_eq!(summary.accepted_chargeback_rate, Some(0.0));
+        assert_eq!(summary.review_overturn_rate, None);
+        assert_eq!(summary.precision, None);
+        assert_eq!(summary.recall, None);
+
+        let rule = rule_outcome(
+            RuleOutcomeRow {
+                rule_code: "CVV_MISMATCH".to_string(),
+                transactions: 3,
+                reported_transactions: 0,
+                fraud_transactions: 0,
+            },
+            0,
+        );
+        assert_eq!(rule.precision, None);
+        assert_eq!(rule.recall, None);
+    }
+
+    #[test]
+    fn test_dispute_losses_exclude_won_disputes() {
+        let losses = dispute_losses(DisputeLossRow {
+            currency: Some("USD".to_string()),
+            disputes: 4,
+            disputed_amount: 300.3,
+            won_amount: 100.1,
+            lost_amount: 150.15,
+            open_amount: 50.05,
+        });
+        assert_eq!(losses.net_loss, 200.2);
+        assert_eq!(losses.currency.as_deref(), Some("USD"));
+    }
+
+    #[test]
+    fn test_reviewer_quality_rates() {
+        let quality = reviewer_quality(ReviewerQualityRow {
+            reviewer: "analyst@example.com".to_string(),
+            decisions: 40,
+            approved: 40,
+            approved_then_charged_back: 2,
+            declined: 0,
+            declined_then_overturned: 0,
+        });
+        assert_eq!(quality.approved_chargeback_rate, Some(0.05));
+        assert_eq!(quality.declined_overturn_rate, None);
+        assert_eq!(quality.overturn_rate, Some(0.05));
+    }
+
+    #[test]
+    fn test_build_cohorts_fills_quiet_months() {
+        let day = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        let sizes = vec![CohortSizeRow {
+            signup_month: day(4, 1),
+            users: 50,
+        }];
+        let activity = vec![CohortMonthRow {
+            signup_month: day(4, 1),
+            month: day(6, 1),
+            active_users: 12,
+            transaction_count: 40,
+            average_risk_score: 22.5,
+            accepted: 36,
+            chargebacks: 2,
+        }];
+
+        let cohorts = build_cohorts(sizes, activity, month_start(day(6, 13)));
+
+        assert_eq!(cohorts.len(), 1);
+        let months = &cohorts[0].months;
+        assert_eq!(
+            months.iter().map(|m| m.month).collect::<Vec<_>>(),
+            vec![day(4, 1), day(5, 1), day(6, 1)]
+        );
+        assert_eq!(months[1].transaction_count, 0);
+        assert_eq!(months[2].months_since_signup, 2);
+        assert_eq!(months[2].success_rate, 0.9);
+        assert_eq!(months[2].chargebacks, 2);
+    }
+
+    #[test]
+    fn test_months_between_crosses_years() {
+        let day = |year, month| NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        assert_eq!(months_between(day(2024, 11), day(2025, 2)), 3);
+        assert_eq!(months_between(day(2025, 2), day(2025, 2)), 0);
+    }
+
+    #[test]
+    fn test_shop_stats_rates() {
+        let row: ShopRow = serde_json::from_value(serde_json::json!({
+            "shop_id": "shop_eu_42",
+            "transaction_count": 200,
+            "user_count": 150,
+            "average_risk_score": 17.8,
+            "fraud_reports": 3,
+            "low": 160, "medium": 30, "high": 8, "very_high": 2,
+            "accept": 170, "reject": 10, "review": 20, "test": 0
+        }))
+        .unwrap();
+
+        let stats = shop_stats(row);
+        assert_eq!(stats.reject_rate, 0.05);
+        assert_eq!(stats.review_rate, 0.1);
+        assert_eq!(stats.fraud_rate, 0.015);
+        assert_eq!(stats.risk_distribution.very_high, 2);
+    }
+}
@@ -0,0 +1,388 @@
+//! Card metadata from BIN tables
+//!
+//! The leading digits of a card number, its issuer ID number or BIN, identify the range of cards
+//! it belongs to: their network, whether they are credit, debit, or charge cards, the country
+//! and bank that issue them, and whether they are prepaid, issued to businesses, or virtual.
+//! Cards are looked up in the account's own BIN table, imported from a licensed provider, and
+//! then in a starter table bundled with the service that covers the test cards payment
+//! processors publish. Within a table, the range with the longest prefix of the issuer ID
+//! number wins.
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use sqlx::PgExecutor;
+
+use crate::{
+    database::{
+        Tenant,
+        repositories::{BinRangeRecord, ListRepo},
+    },
+    scoring::BinInfo,
+};
+
+/// BIN table bundled with the service
+const BUNDLED_TABLE: &str = include_str!("../../data/bin_ranges.csv");
+
+/// The bundled BIN table, parsed on first use
+static TABLE: LazyLock<BinTable> =
+    LazyLock::new(|| BinTable::new(parse_csv(BUNDLED_TABLE).unwrap_or_default()));
+
+/// Fewest digits in a range prefix
+pub const MIN_PREFIX_DIGITS: usize = 4;
+/// Most digits in a range prefix, and in the issuer ID numbers transactions carry
+pub const MAX_PREFIX_DIGITS: usize = 8;
+
+/// Columns of a BIN table, of which only `prefix` is required
+const COLUMNS: &[&str] = &[
+    "prefix",
+    "brand",
+    "card_type",
+    "country",
+    "bank_name",
+    "is_prepaid",
+    "is_business",
+    "is_virtual",
+];
+
+/// Kinds of card a range can hold
+const CARD_TYPES: &[&str] = &["credit", "debit", "charge"];
+
+/// Longest card network name
+const MAX_BRAND_CHARS: usize = 50;
+/// Longest issuing bank name
+const MAX_BANK_NAME_CHARS: usize = 255;
+
+impl From<BinRangeRecord> for BinInfo {
+    fn from(record: BinRangeRecord) -> Self {
+        BinInfo {
+            prefix: record.prefix,
+            brand: record.brand,
+            card_type: record.card_type,
+            country: record.country,
+            bank_name: record.bank_name,
+            is_prepaid: record.is_prepaid,
+            is_business: record.is_business,
+            is_virtual: record.is_virtual,
+        }
+    }
+}
+
+/// Ranges of cards, by prefix
+#[derive(Debug, Clone, Default)]
+pub struct BinTable {
+    ranges: HashMap<String, BinInfo>,
+}
+
+impl BinTable {
+    /// A table of `ranges`; of ranges with the same prefix, the last is kept
+    pub fn new(ranges: impl IntoIterator<Item = BinInfo>) -> Self {
+        Self {
+            ranges: ranges
+                .into_iter()
+                .map(|range| (range.prefix.clone(), range))
+                .collect(),
+        }
+    }
+
+    /// Ranges in the table
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Whether the table has no ranges
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The range with the longest prefix of `issuer_id_number`, if the table has one
+    pub fn lookup(&self, issuer_id_number: &str) -> Option<&BinInfo> {
+        prefixes(issuer_id_number)
+            .into_iter()
+            .find_map(|prefix| self.ranges.get(prefix))
+    }
+}
+
+/// Prefixes of `issuer_id_number` a range can have, longest first; empty unless it is all
+/// digits
+pub fn prefixes(issuer_id_number: &str) -> Vec<&str> {
+    let digits = issuer_id_number.trim();
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Vec::new();
+    }
+    (MIN_PREFIX_DIGITS..=MAX_PREFIX_DIGITS.min(digits.len()))
+        .rev()
+        .map(|len| &digits[..len])
+        .collect()
+}
+
+/// What the bundled BIN table says about `issuer_id_number`
+pub fn lookup(issuer_id_number: &str) -> Option<BinInfo> {
+    TABLE.lookup(issuer_id_number).cloned()
+}
+
+/// What the account's BIN table, or else the bundled one, says about `issuer_id_number`
+pub async fn lookup_card(
+    executor: impl PgExecutor<'_>,
+    tenant: Tenant,
+    issuer_id_number: &str,
+) -> sqlx::Result<Option<BinInfo>> {
+    let prefixes: Vec<String> = prefixes(issuer_id_number)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    if prefixes.is_empty() {
+        return Ok(None);
+    }
+    if let Some(range) = ListRepo::find_bin_range(executor, tenant, &prefixes).await? {
+        return Ok(Some(range.into()));
+    }
+    Ok(lookup(issuer_id_number))
+}
+
+/// Parse a BIN table in CSV form
+///
+/// The first line that is not blank or a `#` comment is a header naming the columns, in any
+/// order: `prefix`, and optionally `brand`, `card_type`, `country`, `bank_name`, `is_prepaid`,
+/// `is_business`, and `is_virtual`. Fields may be double-quoted. Fails on the first malformed
+/// line, naming it, and on prefixes listed twice.
+pub fn parse_csv(csv: &str) -> Result<Vec<BinInfo>, String> {
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    let (header_line, header) = lines.next().ok_or("the table has no header")?;
+    let columns = split_record(header).map_err(|e| format!("line {header_line}: {e}"))?;
+    let mut indexes = HashMap::new();
+    for (index, column) in columns.iter().enumerate() {
+        let column = column.to_ascii_lowercase();
+        if !COLUMNS.contains(&column.as_str()) {
+            return Err(format!(
+                "line {header_line}: unknown column {column}; columns are {}",
+                COLUMNS.join(", ")
+            ));
+        }
+        if indexes.insert(column.clone(), index).is_some() {
+            return Err(format!("line {header_line}: column {column} is repeated"));
+        }
+    }
+    if !indexes.contains_key("prefix") {
+        return Err(format!(
+            "line {header_line}: the header has no prefix column"
+        ));
+    }
+
+    let mut ranges = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (line_number, line) in lines {
+        let fields = split_record(line).map_err(|e| format!("line {line_number}: {e}"))?;
+        if fields.len() != columns.len() {
+            return Err(format!(
+                "line {line_number}: expected {} fields, found {}",
+                columns.len(),
+                fields.len()
+            ));
+        }
+        let field = |column: &str| {
+            indexes
+                .get(column)
+                .map(|&index| fields[index].trim())
+                .filter(|field| !field.is_empty())
+        };
+        let range = parse_range(field).map_err(|e| format!("line {line_number}: {e}"))?;
+        if let Some(first) = seen.insert(range.prefix.clone(), line_number) {
+            return Err(format!(
+                "line {line_number}: prefix {} is already listed on line {first}",
+                range.prefix
+            ));
+        }
+        ranges.push(range);
+    }
+    Ok(ranges)
+}
+
+/// A range from the fields of a line, each looked up by column and `None` if blank
+fn parse_range<'a>(field: impl Fn(&str) -> Option<&'a str>) -> Result<BinInfo, String> {
+    let prefix = field("prefix").ok_or("prefix is required")?;
+    if !(MIN_PREFIX_DIGITS..=MAX_PREFIX_DIGITS).contains(&prefix.len())
+        || !prefix.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(format!(
+            "prefix must be {MIN_PREFIX_DIGITS} to {MAX_PREFIX_DIGITS} digits"
+        ));
+    }
+    let brand = field("brand").map(str::to_lowercase);
+    if brand
+        .as_ref()
+        .is_some_and(|brand| brand.chars().count() > MAX_BRAND_CHARS)
+    {
+        return Err(format!(
+            "brand must be at most {MAX_BRAND_CHARS} characters"
+        ));
+    }
+    let card_type = field("card_type").map(str::to_lowercase);
+    if card_type
+        .as_ref()
+        .is_some_and(|card_type| !CARD_TYPES.contains(&card_type.as_str()))
+    {
+        return Err(format!(
+            "card_type must be one of {}",
+            CARD_TYPES.join(", ")
+        ));
+    }
+    let country = field("country").map(str::to_uppercase);
+    if country.as_ref().is_some_and(|country| {
+        country.len() != 2 || !country.bytes().all(|b| b.is_ascii_uppercase())
+    }) {
+        return Err("country must be an ISO 3166-1 alpha-2 country code".to_string());
+    }
+    let bank_name = field("bank_name").map(str::to_string);
+    if bank_name
+        .as_ref()
+        .is_some_and(|bank_name| bank_name.chars().count() > MAX_BANK_NAME_CHARS)
+    {
+        return Err(format!(
+            "bank_name must be at most {MAX_BANK_NAME_CHARS} characters"
+        ));
+    }
+    let flag = |column: &str| match field(column).map(str::to_ascii_lowercase).as_deref() {
+        None | Some("false" | "0" | "no" | "n") => Ok(false),
+        Some("true" | "1" | "yes" | "y") => Ok(true),
+        Some(_) => Err(format!("{column} must be true or false")),
+    };
+    Ok(BinInfo {
+        prefix: prefix.to_string(),
+        brand,
+        card_type,
+        country,
+        bank_name,
+        is_prepaid: flag("is_prepaid")?,
+        is_business: flag("is_business")?,
+        is_virtual: flag("is_virtual")?,
+    })
+}
+
+/// Fields of a CSV record on one line, unquoting double-quoted ones
+pub(crate) fn split_record(line: &str) -> Result<Vec<String>, String> {
+    split_delimited(line, ',')
+}
+
+/// Fields of a record on one line separated by `delimiter`, unquoting double-quoted ones
+pub(crate) fn split_delimited(line: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    },
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("a quoted field is not closed".to_string()),
+                }
+            }
+            if chars.peek().is_some_and(|&c| c != delimiter) {
+                return Err(format!(
+                    "a quoted field is followed by more than a {}",
+                    match delimiter {
+                        ',' => "comma",
+                        ';' => "semicolon",
+                        _ => "delimiter",
+                    }
+                ));
+            }
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != delimiter) {
+                field.push(c);
+            }
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_table_parses() {
+        let ranges = BUNDLED_TABLE
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .count();
+        // Less the header
+        assert_eq!(TABLE.len(), ranges - 1);
+
+        let prepaid = lookup("51051051").unwrap();
+        assert_eq!(prepaid.prefix, "510510");
+        assert_eq!(prepaid.brand.as_deref(), Some("mastercard"));
+        assert!(prepaid.is_prepaid);
+        assert_eq!(lookup("999999"), None);
+    }
+
+    #[test]
+    fn test_lookup_takes_the_longest_prefix() {
+        let table = BinTable::new(
+            parse_csv(
+                "prefix,brand,card_type\n\
+                 4111,visa,credit\n\
+                 41111122,visa,debit\n",
+            )
+            .unwrap(),
+        );
+        assert_eq!(table.lookup("41111122").unwrap().prefix, "41111122");
+        assert_eq!(
+            table.lookup("41111133").unwrap().card_type.as_deref(),
+            Some("credit")
+        );
+        assert_eq!(table.lookup("411"), None);
+        assert_eq!(table.lookup("4111-11"), None);
+        assert_eq!(prefixes("411111"), ["411111", "41111", "4111"]);
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let ranges = parse_csv(
+            "# Licensed table\n\
+             Country,PREFIX,bank_name,is_prepaid,is_virtual,brand\n\
+             \n\
+             us,400000,\"First Bank, N.A.\",yes,0,VISA\n\
+             GB,5500,\"The \"\"Other\"\" Bank\",,true,\n",
+        )
+        .unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0].country.as_deref(), Some("US"));
+        assert_eq!(ranges[0].bank_name.as_deref(), Some("First Bank, N.A."));
+        assert_eq!(ranges[0].brand.as_deref(), Some("visa"));
+        assert!(ranges[0].is_prepaid && !ranges[0].is_virtual && !ranges[0].is_business);
+        assert_eq!(ranges[1].bank_name.as_deref(), Some("The \"Other\" Bank"));
+        assert_eq!(ranges[1].brand, None);
+        assert!(ranges[1].is_virtual);
+    }
+
+    #[test]
+    fn test_parse_csv_rejects_malformed_tables() {
+        let error = |csv| parse_csv(csv).unwrap_err();
+        assert_eq!(error(""), "the table has no header");
+        assert!(error("brand\nvisa\n").contains("no prefix column"));
+        assert!(error("prefix,issuer\n").contains("unknown column issuer"));
+        assert!(error("prefix\n411\n").starts_with("line 2: prefix must be"));
+        assert!(error("prefix,card_type\n411111,prepaid\n").contains("card_type must be"));
+        assert!(error("prefix,country\n411111,USA\n").contains("country must be"));
+        assert!(error("prefix,is_prepaid\n411111,maybe\n").contains("is_prepaid must be"));
+        assert!(error("prefix,brand\n411111\n").contains("expected 2 fields, found 1"));
+        assert!(error("prefix,brand\n411111,\"visa\n").contains("not closed"));
+        assert_eq!(
+            error("prefix\n411111\n411111\n"),
+            "line 3: prefix 411111 is already listed on line 2"
+        );
+    }
+}
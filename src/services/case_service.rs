@@ -0,0 +1,1454 @@
+//! Manual review cases
+//!
+//! Every transaction given the review disposition opens a case, as it is stored or rescored.
+//! A reviewer claims the case, leaves annotations on it, and resolves it by approving or
+//! declining the transaction. Resolution feeds back into the history scoring draws on: the
+//! decision is recorded as the transaction's outcome and training label unless one was already
+//! reported, the user is queued for rescoring, and a decline can block the device the
+//! transaction came from. The decision is also published, so analytics can hold each
+//! reviewer's decisions up against the outcomes reported later.
+//!
+//! Accounts may also register their reviewers and have new cases handed to them, round-robin
+//! or to whoever holds the fewest, instead of waiting to be claimed. Cases can be reassigned
+//! one at a time or all at once when a reviewer is away. Everything done to a case is kept in
+//! its history.
+//!
+//! New cases are routed into the account's named queues by the event type, order amount, and
+//! fired rules of their transaction. A queue sets the time its cases are due by, and may limit
+//! the reviewers its cases are assigned to.
+//!
+//! Merchants may appeal a rejected transaction, which opens a second case of the appeal kind
+//! carrying the scoring being appealed. Appeals are assigned away from whoever declined the
+//! review case, and an upheld appeal replaces the transaction's outcome with not fraud.
+//!
+//! Fetching a single case gathers the context of the investigation with it: the user, device,
+//! IP address, email address, and card of its transaction as they stand now, linked to where
+//! more is known about each, and the links reviewers attached to the case.
+
+use chrono::Utc;
+use sqlx::{PgConnection, PgPool, types::Json};
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    database::{
+        Tenant,
+        repositories::{
+            AccountRepo, CaseAnnotationRecord, CaseEntitiesRecord, CaseEventRecord,
+            CaseQueueRecord, CaseRecord, CaseReferenceRecord, CaseRepo, CaseReviewerRecord,
+            DeviceRepo, LabelRepo, LatestScoringRecord, OutboxRepo, ScoringRevisionRepo,
+            TransactionRepo, UserRepo,
+        },
+    },
+    models::{
+        case::{
+            AppealRequest, CaseAction, CaseAnnotation, CaseAnnotationRequest, CaseAppeal,
+            CaseAssignment, CaseAssignmentRequest, CaseClaim, CaseCreditCard, CaseDecision,
+            CaseDevice, CaseEmail, CaseEntities, CaseEvent, CaseIpAddress, CaseKind, CaseQueue,
+            CaseQueueRequest, CaseReference, CaseReferenceRequest, CaseResolution, CaseRouting,
+            CaseStatus, CaseUser, ListCasesQuery, ReassignedCases, ReviewCase, Reviewer,
+            ReviewerReassignment, ReviewerUpdate, ScoringSnapshot, SnapshotFactor,
+            validate_reviewer,
+        },
+        common::{Link, Links},
+        device::DeviceStatus,
+        label::{Label, LabelSource},
+        transaction::{Disposition, ReportTag, RiskLevel},
+    },
+    outbox::{CASE_RESOLVED, CaseResolved, TRANSACTION_REPORTED, TransactionReported},
+};
+
+impl From<CaseRecord> for ReviewCase {
+    fn from(record: CaseRecord) -> Self {
+        let appeal =
+            record
+                .appealed_by
+                .zip(record.scoring_snapshot)
+                .map(|(appealed_by, Json(scoring))| CaseAppeal {
+                    appealed_by,
+                    reason: record.appeal_reason.unwrap_or_default(),
+                    scoring,
+                });
+        ReviewCase {
+            id: record.id,
+            kind: record.kind,
+            transaction_id: record.transaction_id,
+            user_id: record.user_id,
+            device_id: record.device_id,
+            risk_score: record.risk_score,
+            risk_level: record.risk_level,
+            status: record.status,
+            claimed_by: record.claimed_by,
+            claimed_at: record.claimed_at,
+            decision: record.decision,
+            decision_reason: record.decision_reason,
+            resolved_by: record.resolved_by,
+            resolved_at: record.resolved_at,
+            queue: record.queue,
+            due_at: record.due_at,
+            appeal,
+            annotations: None,
+            references: None,
+            entities: None,
+            history: None,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            links: ReviewCase::links(record.id),
+        }
+    }
+}
+
+impl From<LatestScoringRecord> for ScoringSnapshot {
+    fn from(record: LatestScoringRecord) -> Self {
+        ScoringSnapshot {
+            revision: record.revision,
+            risk_score: record.risk_score,
+            risk_level: record.risk_level,
+            disposition: record.disposition,
+            factors: record
+                .factors
+                .0
+                .into_iter()
+                .map(|factor| SnapshotFactor {
+                    code: factor.code,
+                    factor_type: factor.factor_type,
+                    score: factor.score,
+                    reason: factor.reason,
+                })
+                .collect(),
+            scored_at: record.scored_at,
+        }
+    }
+}
+
+impl From<CaseAnnotationRecord> for CaseAnnotation {
+    fn from(record: CaseAnnotationRecord) -> Self {
+        CaseAnnotation {
+            id: record.id,
+            author: record.author,
+            note: record.note,
+            created_at: record.created_at,
+        }
+    }
+}
+
+impl From<CaseReferenceRecord> for CaseReference {
+    fn from(record: CaseReferenceRecord) -> Self {
+        CaseReference {
+            id: record.id,
+            url: record.url,
+            label: record.label,
+            added_by: record.added_by,
+            created_at: record.created_at,
+        }
+    }
+}
+
+impl From<CaseEntitiesRecord> for CaseEntities {
+    fn from(record: CaseEntitiesRecord) -> Self {
+        let link = |href: String| Links {
+            self_link: Some(Link::new(href)),
+            ..Links::default()
+        };
+        CaseEntities {
+            user: record.user_id.map(|id| CaseUser {
+                id,
+                external_user_id: record.external_user_id,
+                risk_score: record.user_risk_score.unwrap_or_default(),
+                risk_level: record.user_risk_level.unwrap_or(RiskLevel::Low),
+                chargeback_count: record.user_chargeback_count.unwrap_or_default(),
+                is_flagged: record.user_flagged.unwrap_or_default(),
+                links: link(format!("/v1/users/{id}")),
+            }),
+            device: record.device_id.map(|id| CaseDevice {
+                id,
+                status: record.device_status.unwrap_or_default(),
+                risk_score: record.device_risk_score,
+                links: link(format!("/v1/devices/{id}")),
+            }),
+            ip_address: record.ip_address.map(|ip_address| CaseIpAddress {
+                risk_score: record.ip_risk_score,
+                transaction_count: record.ip_transaction_count.unwrap_or_default(),
+                chargeback_count: record.ip_chargeback_count.unwrap_or_default(),
+                links: link(format!("/v1/ip/{ip_address}")),
+                ip_address,
+            }),
+            email: record.email_hash.map(|email_hash| CaseEmail {
+                domain: record.email_domain,
+                is_disposable: record.email_disposable.unwrap_or_default(),
+                links: link(format!("/v1/emails/{email_hash}")),
+                email_hash,
+            }),
+            credit_card: record.card_prepaid.map(|is_prepaid| CaseCreditCard {
+                issuer_id_number: record.card_issuer_id_number,
+                last_digits: record.card_last_digits,
+                brand: record.card_brand,
+                country: record.card_country,
+                is_prepaid,
+            }),
+        }
+    }
+}
+
+impl From<CaseEventRecord> for CaseEvent {
+    fn from(record: CaseEventRecord) -> Self {
+        CaseEvent {
+            action: record.action,
+            actor: record.actor,
+            reviewer: record.reviewer,
+            created_at: record.created_at,
+        }
+    }
+}
+
+impl From<CaseReviewerRecord> for Reviewer {
+    fn from(record: CaseReviewerRecord) -> Self {
+        Reviewer {
+            reviewer: record.reviewer,
+            active: record.active,
+            open_cases: record.open_cases,
+            last_assigned_at: record.last_assigned_at,
+            created_at: record.created_at,
+        }
+    }
+}
+
+impl From<CaseQueueRecord> for CaseQueue {
+    fn from(record: CaseQueueRecord) -> Self {
+        CaseQueue {
+            links: CaseQueue::links(record.id),
+            id: record.id,
+            name: record.name,
+            priority: record.priority,
+            sla_minutes: record.sla_minutes,
+            assignees: record.assignees,
+            routing: CaseRouting {
+                event_types: record.event_types,
+                min_amount: record.min_amount,
+                max_amount: record.max_amount,
+                rule_codes: record.rule_codes,
+            },
+            open_cases: record.open_cases,
+            overdue_cases: record.overdue_cases,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+/// Open a case for a transaction sent to review unless it already has one, route it into a
+/// queue given the codes of the rules that fired, and hand it to a reviewer when the account
+/// assigns cases automatically or its queue names assignees
+///
+/// Queue assignees are picked by the account's strategy, or the least loaded when assignment
+/// is manual. Cases no active reviewer can take are left open to be claimed.
+pub async fn open_case(
+    conn: &mut PgConnection,
+    tenant: Tenant,
+    transaction_id: Uuid,
+    rule_codes: &[String],
+) -> sqlx::Result<()> {
+    let Some(case_id) = CaseRepo::open(&mut *conn, tenant, transaction_id).await? else {
+        return Ok(());
+    };
+    CaseRepo::log_event(&mut *conn, case_id, CaseAction::Opened, None, None).await?;
+    CaseRepo::route(&mut *conn, tenant, case_id, transaction_id, rule_codes).await?;
+
+    let assignees = CaseRepo::queue_assignees(&mut *conn, tenant, case_id).await?;
+    let strategy = AccountRepo::case_assignment(&mut *conn, tenant).await?;
+    if strategy == CaseAssignment::Manual && assignees.is_empty() {
+        return Ok(());
+    }
+    if let Some(reviewer) =
+        CaseRepo::next_reviewer(&mut *conn, tenant, strategy, None, &assignees).await?
+    {
+        hand_over(conn, tenant, case_id, None, None, &reviewer).await?;
+    }
+    Ok(())
+}
+
+/// Give a case to `reviewer`, recording who did it in its history
+async fn hand_over(
+    conn: &mut PgConnection,
+    tenant: Tenant,
+    case_id: Uuid,
+    holder: Option<&str>,
+    actor: Option<&str>,
+    reviewer: &str,
+) -> sqlx::Result<()> {
+    CaseRepo::claim(&mut *conn, tenant, case_id, reviewer).await?;
+    CaseRepo::mark_assigned(&mut *conn, tenant, reviewer).await?;
+    let action = match holder {
+        Some(_) => CaseAction::Reassigned,
+        None => CaseAction::Assigned,
+    };
+    CaseRepo::log_event(&mut *conn, case_id, action, actor, Some(reviewer)).await
+}
+
+/// Review case management backed by PostgreSQL
+#[derive(Debug, Clone)]
+pub struct CaseService {
+    pool: PgPool,
+}
+
+impl CaseService {
+    /// Create a service over the given pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Page through an account's cases, returning the page and the total number of matches
+    pub async fn list_cases(
+        &self,
+        tenant: Tenant,
+        query: &ListCasesQuery,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<ReviewCase>, i64)> {
+        let cases = CaseRepo::list(&self.pool, tenant, query, limit, offset)
+            .await?
+            .into_iter()
+            .map(ReviewCase::from)
+            .collect();
+        let total = CaseRepo::count(&self.pool, tenant, query).await?;
+        Ok((cases, total))
+    }
+
+    /// Fetch one of an account's cases with its annotations
+    pub async fn get_case(&self, tenant: Tenant, case_id: Uuid) -> ServiceResult<ReviewCase> {
+        let mut tx = self.pool.begin().await?;
+        let case = self.full_case(&mut tx, tenant, case_id).await?;
+        tx.commit().await?;
+        Ok(case)
+    }
+
+    /// Hand an open case to a reviewer
+    ///
+    /// Claiming a case the reviewer already holds changes nothing. Fails with a conflict when
+    /// another reviewer holds the case or it is resolved.
+    pub async fn claim(
+        &self,
+        tenant: Tenant,
+        case_id: Uuid,
+        claim: &CaseClaim,
+    ) -> ServiceResult<ReviewCase> {
+        claim.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let case = CaseRepo::find(&mut *tx, tenant, case_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        match case.status {
+            CaseStatus::Open => {
+                CaseRepo::claim(&mut *tx, tenant, case_id, &claim.reviewer).await?;
+                CaseRepo::log_event(
+                    &mut *tx,
+                    case_id,
+                    CaseAction::Claimed,
+                    Some(&claim.reviewer),
+                    Some(&claim.reviewer),
+                )
+                .await?;
+            },
+            CaseStatus::Claimed if case.claimed_by.as_deref() == Some(&claim.reviewer) => {},
+            CaseStatus::Claimed => return Err(claimed_by_another(&case)),
+            CaseStatus::Resolved => {
+                return Err(ServiceError::Conflict(
+                    "Case is already resolved".to_string(),
+                ));
+            },
+        }
+        let case = self.full_case(&mut tx, tenant, case_id).await?;
+        tx.commit().await?;
+        Ok(case)
+    }
+
+    /// Leave a remark on a case, whatever its status
+    pub async fn annotate(
+        &self,
+        tenant: Tenant,
+        case_id: Uuid,
+        annotation: &CaseAnnotationRequest,
+    ) -> ServiceResult<CaseAnnotation> {
+        annotation.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let case = CaseRepo::find(&mut *tx, tenant, case_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let record =
+            CaseRepo::annotate(&mut *tx, case_id, &annotation.author, &annotation.note).await?;
+        CaseRepo::log_event(
+            &mut *tx,
+            case_id,
+            CaseAction::Annotated,
+            Some(&annotation.author),
+            case.claimed_by.as_deref(),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(record.into())
+    }
+
+    /// Attach a link to a case, whatever its status
+    pub async fn add_reference(
+        &self,
+        tenant: Tenant,
+        case_id: Uuid,
+        reference: &CaseReferenceRequest,
+    ) -> ServiceResult<CaseReference> {
+        reference.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let case = CaseRepo::find(&mut *tx, tenant, case_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let record = CaseRepo::add_reference(
+            &mut *tx,
+            case_id,
+            &reference.added_by,
+            &reference.url,
+            reference.label.as_deref(),
+        )
+        .await?;
+        CaseRepo::log_event(
+            &mut *tx,
+            case_id,
+            CaseAction::Referenced,
+            Some(&reference.added_by),
+            case.claimed_by.as_deref(),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(record.into())
+    }
+
+    /// Appeal a rejected transaction, opening a case that carries its latest scoring
+    ///
+    /// A transaction counts as rejected when its latest scoring rejected it or a reviewer
+    /// declined its review case. The appeal is assigned by the account's strategy unless
+    /// assignment is manual, to anyone but the reviewer who declined the review case. Fails
+    /// with a conflict when the transaction was not rejected or was already appealed.
+    pub async fn appeal(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        appeal: &AppealRequest,
+    ) -> ServiceResult<ReviewCase> {
+        appeal.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let scoring = ScoringRevisionRepo::latest(&mut *tx, tenant, transaction_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let review = match CaseRepo::find_id_by_transaction(
+            &mut *tx,
+            tenant,
+            transaction_id,
+            CaseKind::Review,
+        )
+        .await?
+        {
+            Some(case_id) => CaseRepo::find(&mut *tx, tenant, case_id).await?,
+            None => None,
+        };
+        let declined_by = review
+            .filter(|case| case.decision == Some(CaseDecision::Decline))
+            .and_then(|case| case.resolved_by);
+        if scoring.disposition != Disposition::Reject && declined_by.is_none() {
+            return Err(ServiceError::Conflict(
+                "Only rejected transactions can be appealed".to_string(),
+            ));
+        }
+
+        let case_id = CaseRepo::open_appeal(
+            &mut *tx,
+            tenant,
+            transaction_id,
+            &appeal.appealed_by,
+            &appeal.reason,
+            &scoring.into(),
+        )
+        .await?
+        .ok_or_else(|| ServiceError::Conflict("Transaction was already appealed".to_string()))?;
+        CaseRepo::log_event(
+            &mut *tx,
+            case_id,
+            CaseAction::Opened,
+            Some(&appeal.appealed_by),
+            None,
+        )
+        .await?;
+        let strategy = AccountRepo::case_assignment(&mut *tx, tenant).await?;
+        if strategy != CaseAssignment::Manual
+            && let Some(reviewer) =
+                CaseRepo::next_reviewer(&mut *tx, tenant, strategy, declined_by.as_deref(), &[])
+                    .await?
+        {
+            hand_over(&mut tx, tenant, case_id, None, None, &reviewer).await?;
+        }
+
+        let case = self.full_case(&mut tx, tenant, case_id).await?;
+        tx.commit().await?;
+        Ok(case)
+    }
+
+    /// Close a case with a reviewer's decision and feed it back into the history of the
+    /// transaction's user and device
+    ///
+    /// An open case may be resolved directly. Fails with a conflict when another reviewer
+    /// holds the case or it is already resolved.
+    pub async fn resolve(
+        &self,
+        tenant: Tenant,
+        case_id: Uuid,
+        resolution: &CaseResolution,
+    ) -> ServiceResult<ReviewCase> {
+        resolution.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let case = CaseRepo::find(&mut *tx, tenant, case_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        match case.status {
+            CaseStatus::Resolved => {
+                return Err(ServiceError::Conflict(
+                    "Case is already resolved".to_string(),
+                ));
+            },
+            CaseStatus::Claimed if case.claimed_by.as_deref() != Some(&resolution.reviewer) => {
+                return Err(claimed_by_another(&case));
+            },
+            CaseStatus::Open | CaseStatus::Claimed => {},
+        }
+        CaseRepo::resolve(
+            &mut *tx,
+            tenant,
+            case_id,
+            &resolution.reviewer,
+            resolution.decision,
+            resolution.reason.as_deref(),
+        )
+        .await?;
+        CaseRepo::log_event(
+            &mut *tx,
+            case_id,
+            CaseAction::Resolved,
+            Some(&resolution.reviewer),
+            Some(&resolution.reviewer),
+        )
+        .await?;
+
+        // An outcome the customer reported outranks the reviewer's judgement, except that an
+        // upheld appeal overturns the rejection and whatever was recorded with it
+        let tag = resolution.decision.report_tag();
+        let occurred_at = Utc::now();
+        let reported = if case.kind == CaseKind::Appeal && tag == ReportTag::NotFraud {
+            TransactionRepo::replace_report(
+                &mut *tx,
+                case.transaction_id,
+                tag,
+                None,
+                resolution.reason.as_deref(),
+                occurred_at,
+            )
+            .await?;
+            true
+        } else {
+            TransactionRepo::insert_report(
+                &mut *tx,
+                case.transaction_id,
+                tag,
+                resolution.reason.as_deref(),
+                occurred_at,
+            )
+            .await?
+        };
+        if reported {
+            LabelRepo::upsert_derived(
+                &mut *tx,
+                case.transaction_id,
+                Label::from_outcome(tag, None),
+                LabelSource::Case,
+            )
+            .await?;
+            let payload = serde_json::to_value(TransactionReported {
+                transaction_id: case.transaction_id,
+                tag,
+                chargeback_code: None,
+                occurred_at,
+            })
+            .unwrap_or_default();
+            OutboxRepo::insert(
+                &mut *tx,
+                tenant.id(),
+                TRANSACTION_REPORTED,
+                case.transaction_id,
+                payload,
+            )
+            .await?;
+        }
+        if let Some(user_id) = case.user_id {
+            UserRepo::mark_for_risk_scoring(&mut *tx, tenant, user_id).await?;
+        }
+        if resolution.block_device
+            && let Some(device_id) = case.device_id
+        {
+            DeviceRepo::set_status(&mut *tx, tenant, device_id, DeviceStatus::Blocked).await?;
+        }
+
+        let case = self.full_case(&mut tx, tenant, case_id).await?;
+        let payload = serde_json::to_value(CaseResolved {
+            case_id,
+            kind: case.kind,
+            transaction_id: case.transaction_id,
+            reviewer: resolution.reviewer.clone(),
+            decision: resolution.decision,
+            resolved_at: case.resolved_at.unwrap_or(occurred_at),
+        })
+        .unwrap_or_default();
+        OutboxRepo::insert(&mut *tx, tenant.id(), CASE_RESOLVED, case_id, payload).await?;
+        tx.commit().await?;
+        Ok(case)
+    }
+
+    /// Hand a case to a reviewer, taking it from whoever holds it
+    ///
+    /// The reviewer must be registered and active. Without one, the account's assignment
+    /// strategy picks an active reviewer other than the current holder, among the assignees of
+    /// the case's queue if it names any. Fails with a conflict
+    /// when the case is resolved or there is no reviewer to pick.
+    pub async fn assign(
+        &self,
+        tenant: Tenant,
+        case_id: Uuid,
+        assignment: &CaseAssignmentRequest,
+    ) -> ServiceResult<ReviewCase> {
+        assignment.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let case = CaseRepo::find(&mut *tx, tenant, case_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        if case.status == CaseStatus::Resolved {
+            return Err(ServiceError::Conflict(
+                "Case is already resolved".to_string(),
+            ));
+        }
+        let holder = case.claimed_by.as_deref();
+        let reviewer = match &assignment.reviewer {
+            Some(reviewer) => {
+                let active = CaseRepo::find_reviewer(&mut *tx, tenant, reviewer)
+                    .await?
+                    .is_some_and(|record| record.active);
+                if !active {
+                    return Err(ServiceError::Invalid(format!(
+                        "{reviewer} is not an active reviewer"
+                    )));
+                }
+                reviewer.clone()
+            },
+            None => {
+                let strategy = AccountRepo::case_assignment(&mut *tx, tenant).await?;
+                let assignees = CaseRepo::queue_assignees(&mut *tx, tenant, case_id).await?;
+                CaseRepo::next_reviewer(&mut *tx, tenant, strategy, holder, &assignees)
+                    .await?
+                    .ok_or_else(no_reviewer)?
+            },
+        };
+        if holder != Some(&reviewer) {
+            hand_over(
+                &mut tx,
+                tenant,
+                case_id,
+                holder,
+                Some(&assignment.assigned_by),
+                &reviewer,
+            )
+            .await?;
+        }
+        let case = self.full_case(&mut tx, tenant, case_id).await?;
+        tx.commit().await?;
+        Ok(case)
+    }
+
+    /// Move every unresolved case a reviewer holds to other active reviewers, picked by the
+    /// account's assignment strategy from the assignees of each case's queue if it names any
+    ///
+    /// Fails with a conflict when the reviewer holds cases and no one else is active.
+    pub async fn reassign_reviewer_cases(
+        &self,
+        tenant: Tenant,
+        reviewer: &str,
+        reassignment: &ReviewerReassignment,
+    ) -> ServiceResult<ReassignedCases> {
+        validate_reviewer("reviewer", reviewer).map_err(ServiceError::Invalid)?;
+        reassignment.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let strategy = AccountRepo::case_assignment(&mut *tx, tenant).await?;
+        let mut cases = Vec::new();
+        for case_id in CaseRepo::held_by(&mut *tx, tenant, reviewer).await? {
+            let assignees = CaseRepo::queue_assignees(&mut *tx, tenant, case_id).await?;
+            let next =
+                CaseRepo::next_reviewer(&mut *tx, tenant, strategy, Some(reviewer), &assignees)
+                    .await?
+                    .ok_or_else(no_reviewer)?;
+            hand_over(
+                &mut tx,
+                tenant,
+                case_id,
+                Some(reviewer),
+                Some(&reassignment.assigned_by),
+                &next,
+            )
+            .await?;
+            cases.push(self.full_case(&mut tx, tenant, case_id).await?);
+        }
+        tx.commit().await?;
+        Ok(ReassignedCases { cases })
+    }
+
+    /// An account's reviewers, by name
+    pub async fn list_reviewers(&self, tenant: Tenant) -> ServiceResult<Vec<Reviewer>> {
+        Ok(CaseRepo::reviewers(&self.pool, tenant)
+            .await?
+            .into_iter()
+            .map(Reviewer::from)
+            .collect())
+    }
+
+    /// Register a reviewer cases can be assigned to, or change whether they are active
+    pub async fn set_reviewer(
+        &self,
+        tenant: Tenant,
+        reviewer: &str,
+        update: &ReviewerUpdate,
+    ) -> ServiceResult<Reviewer> {
+        validate_reviewer("reviewer", reviewer).map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        CaseRepo::upsert_reviewer(&mut *tx, tenant, reviewer, update.active).await?;
+        let record = CaseRepo::find_reviewer(&mut *tx, tenant, reviewer)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+        Ok(record.into())
+    }
+
+    /// Stop assigning cases to a reviewer; the cases they hold stay with them
+    pub async fn remove_reviewer(&self, tenant: Tenant, reviewer: &str) -> ServiceResult<()> {
+        if CaseRepo::delete_reviewer(&self.pool, tenant, reviewer).await? {
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound)
+        }
+    }
+
+    /// An account's queues, in routing order
+    pub async fn list_queues(&self, tenant: Tenant) -> ServiceResult<Vec<CaseQueue>> {
+        Ok(CaseRepo::queues(&self.pool, tenant)
+            .await?
+            .into_iter()
+            .map(CaseQueue::from)
+            .collect())
+    }
+
+    /// Fetch one of an account's queues
+    pub async fn get_queue(&self, tenant: Tenant, queue_id: Uuid) -> ServiceResult<CaseQueue> {
+        CaseRepo::find_queue(&self.pool, tenant, queue_id)
+            .await?
+            .map(CaseQueue::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Create a queue new cases can be routed into
+    pub async fn create_queue(
+        &self,
+        tenant: Tenant,
+        queue: &CaseQueueRequest,
+    ) -> ServiceResult<CaseQueue> {
+        queue.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let queue_id = CaseRepo::insert_queue(&mut *tx, tenant, queue)
+            .await
+            .map_err(conflict_on_queue_name)?;
+        let record = CaseRepo::find_queue(&mut *tx, tenant, queue_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+        Ok(record.into())
+    }
+
+    /// Replace a queue's name, SLA, assignees, and routing; routes and due times of cases
+    /// already opened are left as they are
+    pub async fn update_queue(
+        &self,
+        tenant: Tenant,
+        queue_id: Uuid,
+        queue: &CaseQueueRequest,
+    ) -> ServiceResult<CaseQueue> {
+        queue.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let updated = CaseRepo::update_queue(&mut *tx, tenant, queue_id, queue)
+            .await
+            .map_err(conflict_on_queue_name)?;
+        if !updated {
+            return Err(ServiceError::NotFound);
+        }
+        let record = CaseRepo::find_queue(&mut *tx, tenant, queue_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+        Ok(record.into())
+    }
+
+    /// Delete a queue; its cases stay where they are, outside any queue
+    pub async fn delete_queue(&self, tenant: Tenant, queue_id: Uuid) -> ServiceResult<()> {
+        if CaseRepo::delete_queue(&self.pool, tenant, queue_id).await? {
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound)
+        }
+    }
+
+    async fn full_case(
+        &self,
+        conn: &mut PgConnection,
+        tenant: Tenant,
+        case_id: Uuid,
+    ) -> ServiceResult<ReviewCase> {
+        let record = CaseRepo::find(&mut *conn, tenant, case_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let annotations = CaseRepo::annotations(&mut *conn, case_id).await?;
+        let references = CaseRepo::references(&mut *conn, case_id).await?;
+        let entities = CaseRepo::entities(&mut *conn, tenant, record.transaction_id).await?;
+        let history = CaseRepo::events(&mut *conn, case_id).await?;
+        Ok(ReviewCase {
+            annotations: Some(annotations.into_iter().map(CaseAnnotation::from).collect()),
+            references: Some(references.into_iter().map(CaseReference::from).collect()),
+            entities: Some(entities.map(CaseEntities::from).unwrap_or_default()),
+            history: Some(history.into_iter().map(CaseEvent::from).collect()),
+            ..record.into()
+        })
+    }
+}
+
+fn no_reviewer() -> ServiceError {
+    ServiceError::Conflict("No other active reviewer to assign the case to".to_string())
+}
+
+/// Report a queue name the account already uses as a conflict
+fn conflict_on_queue_name(e: sqlx::Error) -> ServiceError {
+    if e.as_database_error()
+        .is_some_and(|db| db.is_unique_violation())
+    {
+        ServiceError::Conflict("A queue with this name already exists".to_string())
+    } else {
+        ServiceError::Database(e)
+    }
+}
+
+fn claimed_by_another(case: &CaseRecord) -> ServiceError {
+    ServiceError::Conflict(format!(
+        "Case is claimed by {}",
+        case.claimed_by.as_deref().unwrap_or("another reviewer")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        database::repositories::{AccountRepo, AccountSettingsUpdate},
+        models::{
+            account::SubscriptionTier,
+            case::CaseDecision,
+            transaction::{Disposition, EventType, ReportTag, TransactionRequest},
+        },
+        scoring::RiskEngine,
+        services::TransactionService,
+        test_support::{create_account, test_pool},
+    };
+
+    #[tokio::test]
+    async fn test_review_case_is_claimed_annotated_and_resolved() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let cases = CaseService::new(pool.clone());
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "203.0.113.9" },
+            "event": { "type": "purchase" },
+            "account": { "user_id": "case-user" },
+            "email": { "address": "buyer@example.com" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        let mut assessment = RiskEngine::new().assess(&request, &user);
+        assessment.disposition = Disposition::Accept;
+        transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        assessment.disposition = Disposition::Review;
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+
+        // Only the transaction sent to review has a case
+        let query = ListCasesQuery::default();
+        let (listed, total) = cases.list_cases(tenant, &query, 20, 0).await.unwrap();
+        assert_eq!(total, 1);
+        let case = &listed[0];
+        assert_eq!(case.transaction_id, stored.id);
+        assert_eq!(case.status, CaseStatus::Open);
+        assert!(case.device_id.is_some());
+
+        let claim = |reviewer: &str| CaseClaim {
+            reviewer: reviewer.to_string(),
+        };
+        let claimed = cases.claim(tenant, case.id, &claim("ana")).await.unwrap();
+        assert_eq!(claimed.status, CaseStatus::Claimed);
+        assert_eq!(claimed.claimed_by.as_deref(), Some("ana"));
+        assert!(cases.claim(tenant, case.id, &claim("ana")).await.is_ok());
+        assert!(matches!(
+            cases.claim(tenant, case.id, &claim("ben")).await,
+            Err(ServiceError::Conflict(_))
+        ));
+
+        let annotation = CaseAnnotationRequest {
+            author: "ben".to_string(),
+            note: "Shipping address is a freight forwarder".to_string(),
+        };
+        cases.annotate(tenant, case.id, &annotation).await.unwrap();
+        let reference = CaseReferenceRequest {
+            added_by: "ben".to_string(),
+            url: "https://support.example.com/tickets/4821".to_string(),
+            label: Some("Support ticket".to_string()),
+        };
+        cases
+            .add_reference(tenant, case.id, &reference)
+            .await
+            .unwrap();
+
+        // The case carries its investigation context
+        let fetched = cases.get_case(tenant, case.id).await.unwrap();
+        assert_eq!(fetched.references.map(|r| r.len()), Some(1));
+        let entities = fetched.entities.unwrap();
+        let user = entities.user.unwrap();
+        assert_eq!(user.external_user_id.as_deref(), Some("case-user"));
+        assert_eq!(
+            user.links.self_link.map(|link| link.href),
+            Some(format!("/v1/users/{}", user.id))
+        );
+        assert_eq!(entities.device.map(|device| device.id), fetched.device_id);
+        assert_eq!(
+            entities.ip_address.map(|ip| ip.ip_address).as_deref(),
+            Some("203.0.113.9")
+        );
+        assert_eq!(
+            entities.email.and_then(|email| email.domain).as_deref(),
+            Some("example.com")
+        );
+        assert!(entities.credit_card.is_none());
+
+        let resolution = |reviewer: &str| CaseResolution {
+            reviewer: reviewer.to_string(),
+            decision: CaseDecision::Decline,
+            reason: Some("Freight forwarder".to_string()),
+            block_device: true,
+        };
+        assert!(matches!(
+            cases.resolve(tenant, case.id, &resolution("ben")).await,
+            Err(ServiceError::Conflict(_))
+        ));
+        let resolved = cases
+            .resolve(tenant, case.id, &resolution("ana"))
+            .await
+            .unwrap();
+        assert_eq!(resolved.status, CaseStatus::Resolved);
+        assert_eq!(resolved.decision, Some(CaseDecision::Decline));
+        assert_eq!(resolved.resolved_by.as_deref(), Some("ana"));
+        assert_eq!(resolved.annotations.map(|a| a.len()), Some(1));
+        assert!(matches!(
+            cases.resolve(tenant, case.id, &resolution("ana")).await,
+            Err(ServiceError::Conflict(_))
+        ));
+
+        // The decision became the transaction's outcome and the device is blocked
+        let tag: ReportTag =
+            sqlx::query_scalar("SELECT tag FROM transaction_reports WHERE transaction_id = $1")
+                .bind(stored.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(tag, ReportTag::SuspectedFraud);
+        let status: DeviceStatus = sqlx::query_scalar("SELECT status FROM devices WHERE id = $1")
+            .bind(resolved.device_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(status, DeviceStatus::Blocked);
+        let rescore_pending: bool =
+            sqlx::query_scalar("SELECT risk_scored_at IS NULL FROM users WHERE id = $1")
+                .bind(resolved.user_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(rescore_pending);
+
+        // Cases belong to their account
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(matches!(
+            cases.get_case(other, case.id).await,
+            Err(ServiceError::NotFound)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+    #[tokio::test]
+    async fn test_cases_are_assigned_to_reviewers_and_reassigned() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let cases = CaseService::new(pool.clone());
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "203.0.113.10" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        let mut assessment = RiskEngine::new().assess(&request, &user);
+        assessment.disposition = Disposition::Review;
+        let review = || async {
+            let stored = transactions
+                .store_transaction(tenant, &request, &assessment, &[])
+                .await
+                .unwrap();
+            let query = ListCasesQuery::default();
+            let (listed, _) = cases.list_cases(tenant, &query, 100, 0).await.unwrap();
+            listed
+                .into_iter()
+                .find(|case| case.transaction_id == stored.id)
+                .unwrap()
+        };
+        let active = ReviewerUpdate { active: None };
+        for reviewer in ["ana", "ben"] {
+            cases.set_reviewer(tenant, reviewer, &active).await.unwrap();
+        }
+
+        // Manual assignment leaves cases to be claimed
+        let waiting = review().await;
+        assert_eq!(waiting.status, CaseStatus::Open);
+
+        // Round-robin takes turns
+        AccountRepo::update_settings(
+            &pool,
+            tenant,
+            &AccountSettingsUpdate {
+                case_assignment: Some(CaseAssignment::RoundRobin),
+                ..AccountSettingsUpdate::default()
+            },
+        )
+        .await
+        .unwrap();
+        let first = review().await;
+        let second = review().await;
+        let third = review().await;
+        assert_eq!(first.claimed_by.as_deref(), Some("ana"));
+        assert_eq!(second.claimed_by.as_deref(), Some("ben"));
+        assert_eq!(third.claimed_by.as_deref(), Some("ana"));
+
+        // Least-loaded picks whoever holds the fewest, so ben catches up
+        AccountRepo::update_settings(
+            &pool,
+            tenant,
+            &AccountSettingsUpdate {
+                case_assignment: Some(CaseAssignment::LeastLoaded),
+                ..AccountSettingsUpdate::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(review().await.claimed_by.as_deref(), Some("ben"));
+        let loads: Vec<(String, i64)> = cases
+            .list_reviewers(tenant)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.reviewer, r.open_cases))
+            .collect();
+        assert_eq!(loads, [("ana".to_string(), 2), ("ben".to_string(), 2)]);
+
+        // Explicit assignment only goes to active reviewers
+        let assignment = |reviewer: Option<&str>| CaseAssignmentRequest {
+            reviewer: reviewer.map(str::to_string),
+            assigned_by: "lead".to_string(),
+        };
+        assert!(matches!(
+            cases
+                .assign(tenant, waiting.id, &assignment(Some("cy")))
+                .await,
+            Err(ServiceError::Invalid(_))
+        ));
+        let assigned = cases
+            .assign(tenant, waiting.id, &assignment(Some("ana")))
+            .await
+            .unwrap();
+        assert_eq!(assigned.claimed_by.as_deref(), Some("ana"));
+        let reassigned = cases
+            .assign(tenant, waiting.id, &assignment(None))
+            .await
+            .unwrap();
+        assert_eq!(reassigned.claimed_by.as_deref(), Some("ben"));
+        let history: Vec<(CaseAction, Option<String>, Option<String>)> = reassigned
+            .history
+            .unwrap()
+            .into_iter()
+            .map(|event| (event.action, event.actor, event.reviewer))
+            .collect();
+        assert_eq!(
+            history,
+            [
+                (CaseAction::Opened, None, None),
+                (
+                    CaseAction::Assigned,
+                    Some("lead".to_string()),
+                    Some("ana".to_string())
+                ),
+                (
+                    CaseAction::Reassigned,
+                    Some("lead".to_string()),
+                    Some("ben".to_string())
+                ),
+            ]
+        );
+
+        // A reviewer going away hands every case they hold to the others
+        cases.set_reviewer(tenant, "cy", &active).await.unwrap();
+        cases
+            .set_reviewer(
+                tenant,
+                "ana",
+                &ReviewerUpdate {
+                    active: Some(false),
+                },
+            )
+            .await
+            .unwrap();
+        let moved = cases
+            .reassign_reviewer_cases(
+                tenant,
+                "ana",
+                &ReviewerReassignment {
+                    assigned_by: "lead".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(moved.cases.len(), 2);
+        assert!(
+            moved
+                .cases
+                .iter()
+                .all(|case| case.claimed_by.as_deref() == Some("cy"))
+        );
+        assert!(matches!(
+            cases
+                .reassign_reviewer_cases(
+                    tenant,
+                    "ben",
+                    &ReviewerReassignment {
+                        assigned_by: "lead".to_string(),
+                    },
+                )
+                .await
+                .map(|moved| moved.cases.len()),
+            Ok(3)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cases_are_routed_into_queues() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let cases = CaseService::new(pool.clone());
+        let review = |request: serde_json::Value| {
+            let transactions = &transactions;
+            let cases = &cases;
+            async move {
+                let request: TransactionRequest = serde_json::from_value(request).unwrap();
+                let user = transactions.user_signals(tenant, &request).await.unwrap();
+                let mut assessment = RiskEngine::new().assess(&request, &user);
+                assessment.disposition = Disposition::Review;
+                let stored = transactions
+                    .store_transaction(tenant, &request, &assessment, &[])
+                    .await
+                    .unwrap();
+                let query = ListCasesQuery::default();
+                let (listed, _) = cases.list_cases(tenant, &query, 100, 0).await.unwrap();
+                listed
+                    .into_iter()
+                    .find(|case| case.transaction_id == stored.id)
+                    .unwrap()
+            }
+        };
+        let purchase = |amount: f64| {
+            json!({
+                "device": { "ip_address": "203.0.113.10" },
+                "event": { "type": "purchase" },
+                "order": { "amount": amount, "currency": "USD" }
+            })
+        };
+        let active = ReviewerUpdate { active: None };
+        for reviewer in ["ana", "ben"] {
+            cases.set_reviewer(tenant, reviewer, &active).await.unwrap();
+        }
+
+        let high_value = CaseQueueRequest {
+            name: "high-value".to_string(),
+            priority: 0,
+            sla_minutes: 60,
+            assignees: vec!["ben".to_string()],
+            routing: CaseRouting {
+                min_amount: Some(500.0),
+                ..CaseRouting::default()
+            },
+        };
+        let high_value = cases.create_queue(tenant, &high_value).await.unwrap();
+        let logins = CaseQueueRequest {
+            name: "logins".to_string(),
+            priority: 1,
+            sla_minutes: 240,
+            assignees: Vec::new(),
+            routing: CaseRouting {
+                event_types: vec![EventType::AccountLogin],
+                ..CaseRouting::default()
+            },
+        };
+        cases.create_queue(tenant, &logins).await.unwrap();
+        assert!(matches!(
+            cases.create_queue(tenant, &logins).await,
+            Err(ServiceError::Conflict(_))
+        ));
+
+        // Queue assignees take the queue's cases even when assignment is manual
+        let large = review(purchase(1200.0)).await;
+        assert_eq!(large.queue.as_deref(), Some("high-value"));
+        assert_eq!(large.claimed_by.as_deref(), Some("ben"));
+        assert_eq!(
+            large.due_at,
+            Some(large.created_at + chrono::Duration::minutes(60))
+        );
+
+        let small = review(purchase(20.0)).await;
+        assert_eq!(small.queue, None);
+        assert_eq!(small.due_at, None);
+        assert_eq!(small.status, CaseStatus::Open);
+
+        let login = review(json!({
+            "device": { "ip_address": "203.0.113.10" },
+            "event": { "type": "account_login" }
+        }))
+        .await;
+        assert_eq!(login.queue.as_deref(), Some("logins"));
+        assert_eq!(login.status, CaseStatus::Open);
+
+        let query = ListCasesQuery {
+            queue: Some("high-value".to_string()),
+            ..ListCasesQuery::default()
+        };
+        let (listed, total) = cases.list_cases(tenant, &query, 100, 0).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(listed[0].id, large.id);
+        let query = ListCasesQuery {
+            overdue: Some(true),
+            ..ListCasesQuery::default()
+        };
+        assert_eq!(cases.list_cases(tenant, &query, 100, 0).await.unwrap().1, 0);
+
+        let queues: Vec<(String, i64)> = cases
+            .list_queues(tenant)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|queue| (queue.name, queue.open_cases))
+            .collect();
+        assert_eq!(
+            queues,
+            [("high-value".to_string(), 1), ("logins".to_string(), 1)]
+        );
+
+        // Cases past their queue's SLA are flagged once
+        let after_sla = large.created_at + chrono::Duration::minutes(61);
+        let breaches: Vec<_> = CaseRepo::claim_sla_breaches(&pool, after_sla)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|breach| breach.account_id == account_id)
+            .collect();
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].id, large.id);
+        assert_eq!(breaches[0].queue_name, "high-value");
+        assert_eq!(breaches[0].claimed_by.as_deref(), Some("ben"));
+        assert!(
+            CaseRepo::claim_sla_breaches(&pool, after_sla)
+                .await
+                .unwrap()
+                .iter()
+                .all(|breach| breach.account_id != account_id)
+        );
+
+        // Deleting a queue leaves its cases outside any queue
+        cases.delete_queue(tenant, high_value.id).await.unwrap();
+        let large = cases.get_case(tenant, large.id).await.unwrap();
+        assert_eq!(large.queue, None);
+        assert!(matches!(
+            cases.get_queue(tenant, high_value.id).await,
+            Err(ServiceError::NotFound)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejected_transactions_are_appealed() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let cases = CaseService::new(pool.clone());
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "203.0.113.11" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        let mut assessment = RiskEngine::new().assess(&request, &user);
+        let active = ReviewerUpdate { active: None };
+        for reviewer in ["ana", "ben"] {
+            cases.set_reviewer(tenant, reviewer, &active).await.unwrap();
+        }
+        AccountRepo::update_settings(
+            &pool,
+            tenant,
+            &AccountSettingsUpdate {
+                case_assignment: Some(CaseAssignment::RoundRobin),
+                ..AccountSettingsUpdate::default()
+            },
+        )
+        .await
+        .unwrap();
+        let appeal = AppealRequest {
+            appealed_by: "merchant-ops".to_string(),
+            reason: "Order confirmed by phone".to_string(),
+        };
+
+        // Accepted transactions have nothing to appeal
+        assessment.disposition = Disposition::Accept;
+        let accepted = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        assert!(matches!(
+            cases.appeal(tenant, accepted.id, &appeal).await,
+            Err(ServiceError::Conflict(_))
+        ));
+        assert!(matches!(
+            cases.appeal(tenant, Uuid::new_v4(), &appeal).await,
+            Err(ServiceError::NotFound)
+        ));
+
+        // A rejection carries the scoring being appealed, and is appealed once
+        assessment.disposition = Disposition::Reject;
+        let rejected = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        let appealed = cases.appeal(tenant, rejected.id, &appeal).await.unwrap();
+        assert_eq!(appealed.kind, CaseKind::Appeal);
+        let details = appealed.appeal.unwrap();
+        assert_eq!(details.appealed_by, "merchant-ops");
+        assert_eq!(details.scoring.revision, 1);
+        assert_eq!(details.scoring.disposition, Disposition::Reject);
+        assert_eq!(details.scoring.factors.len(), assessment.factors.len());
+        assert!(matches!(
+            cases.appeal(tenant, rejected.id, &appeal).await,
+            Err(ServiceError::Conflict(_))
+        ));
+
+        // A declined review is appealed to another reviewer, who can overturn it
+        assessment.disposition = Disposition::Review;
+        let reviewed = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        let query = ListCasesQuery {
+            kind: Some(CaseKind::Review),
+            ..ListCasesQuery::default()
+        };
+        let (listed, _) = cases.list_cases(tenant, &query, 100, 0).await.unwrap();
+        let review = listed
+            .into_iter()
+            .find(|case| case.transaction_id == reviewed.id)
+            .unwrap();
+        let decliner = review.claimed_by.clone().unwrap();
+        let resolution = |reviewer: &str, decision| CaseResolution {
+            reviewer: reviewer.to_string(),
+            decision,
+            reason: None,
+            block_device: false,
+        };
+        cases
+            .resolve(
+                tenant,
+                review.id,
+                &resolution(&decliner, CaseDecision::Decline),
+            )
+            .await
+            .unwrap();
+        let appealed = cases.appeal(tenant, reviewed.id, &appeal).await.unwrap();
+        let reviewer = appealed.claimed_by.unwrap();
+        assert_ne!(reviewer, decliner);
+        cases
+            .resolve(
+                tenant,
+                appealed.id,
+                &resolution(&reviewer, CaseDecision::Approve),
+            )
+            .await
+            .unwrap();
+        let tag: ReportTag =
+            sqlx::query_scalar("SELECT tag FROM transaction_reports WHERE transaction_id = $1")
+                .bind(reviewed.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(tag, ReportTag::NotFraud);
+
+        let query = ListCasesQuery {
+            kind: Some(CaseKind::Appeal),
+            ..ListCasesQuery::default()
+        };
+        assert_eq!(cases.list_cases(tenant, &query, 100, 0).await.unwrap().1, 2);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
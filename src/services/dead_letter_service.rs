@@ -0,0 +1,233 @@
+//! Inspection, redrive, and purging of an account's dead letters
+//!
+//! An event becomes a dead letter when its last delivery attempt fails. Redriving hands it
+//! back to the dispatcher with a fresh set of attempts, to be delivered to every consumer
+//! again under its original ID; purging deletes it along with its webhook delivery log.
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    database::{
+        Tenant,
+        repositories::{DeadLetterRecord, OutboxRepo},
+    },
+    models::dead_letter::{DeadLetter, ListDeadLettersQuery},
+};
+
+impl From<DeadLetterRecord> for DeadLetter {
+    fn from(record: DeadLetterRecord) -> Self {
+        DeadLetter {
+            id: record.id,
+            event_type: record.event_type,
+            aggregate_id: record.aggregate_id,
+            payload: record.payload.0,
+            attempts: record.attempts,
+            last_error: record.last_error,
+            created_at: record.created_at,
+            dead_lettered_at: record.dead_lettered_at,
+            links: DeadLetter::links(record.id),
+        }
+    }
+}
+
+/// Dead letter management
+#[derive(Debug, Clone)]
+pub struct DeadLetterService {
+    pool: PgPool,
+}
+
+impl DeadLetterService {
+    /// Create a new dead letter service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Page of an account's dead letters, newest first, with the total matching the query
+    pub async fn list_dead_letters(
+        &self,
+        tenant: Tenant,
+        query: &ListDeadLettersQuery,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<DeadLetter>, i64)> {
+        query.validate().map_err(ServiceError::Invalid)?;
+        let event_type = query.event_type.as_deref();
+        let records =
+            OutboxRepo::dead_letters(&self.pool, tenant, event_type, limit, offset).await?;
+        let total = OutboxRepo::count_dead_letters(&self.pool, tenant, event_type).await?;
+        Ok((records.into_iter().map(DeadLetter::from).collect(), total))
+    }
+
+    /// Fetch one of an account's dead letters
+    pub async fn get_dead_letter(
+        &self,
+        tenant: Tenant,
+        event_id: Uuid,
+    ) -> ServiceResult<DeadLetter> {
+        OutboxRepo::find_dead_letter(&self.pool, tenant, event_id)
+            .await?
+            .map(DeadLetter::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Hand a dead letter back to the dispatcher
+    pub async fn redrive(&self, tenant: Tenant, event_id: Uuid) -> ServiceResult<()> {
+        if OutboxRepo::redrive_dead_letters(&self.pool, tenant, Some(event_id), None).await? == 0 {
+            return Err(ServiceError::NotFound);
+        }
+        tracing::info!(%event_id, account_id = %tenant, "Dead letter redriven");
+        Ok(())
+    }
+
+    /// Hand every dead letter of an account, or those of one event type, back to the
+    /// dispatcher, returning how many there were
+    pub async fn redrive_all(
+        &self,
+        tenant: Tenant,
+        event_type: Option<&str>,
+    ) -> ServiceResult<u64> {
+        let redriven =
+            OutboxRepo::redrive_dead_letters(&self.pool, tenant, None, event_type).await?;
+        tracing::info!(redriven, event_type, account_id = %tenant, "Dead letters redriven");
+        Ok(redriven)
+    }
+
+    /// Delete a dead letter
+    pub async fn purge(&self, tenant: Tenant, event_id: Uuid) -> ServiceResult<()> {
+        if OutboxRepo::purge_dead_letters(&self.pool, tenant, Some(event_id), None).await? == 0 {
+            return Err(ServiceError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Delete every dead letter of an account, or those of one event type, returning how many
+    /// there were
+    pub async fn purge_all(&self, tenant: Tenant, event_type: Option<&str>) -> ServiceResult<u64> {
+        let purged = OutboxRepo::purge_dead_letters(&self.pool, tenant, None, event_type).await?;
+        tracing::info!(purged, event_type, account_id = %tenant, "Dead letters purged");
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::OutboxConfig,
+        database::repositories::AccountRepo,
+        models::account::SubscriptionTier,
+        outbox::{CASE_RESOLVED, EventPublisher, OutboxRecord, USER_MERGED, dispatcher},
+        test_support::{create_account, test_pool},
+    };
+
+    /// Publisher refusing every event of one account
+    struct Refusing(Uuid);
+
+    impl EventPublisher for Refusing {
+        async fn publish(&self, event: &OutboxRecord) -> anyhow::Result<()> {
+            if event.account_id == self.0 {
+                anyhow::bail!("endpoint down");
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_events_are_dead_lettered_and_redriven() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let dead_letters = DeadLetterService::new(pool.clone());
+        let mut event_ids = Vec::new();
+        for event_type in [CASE_RESOLVED, CASE_RESOLVED, USER_MERGED] {
+            let event_id = OutboxRepo::insert(
+                &pool,
+                account_id,
+                event_type,
+                Uuid::new_v4(),
+                serde_json::json!({}),
+            )
+            .await
+            .unwrap();
+            event_ids.push(event_id);
+        }
+
+        // A single attempt is allowed, so the first failure dead-letters each event
+        let config = OutboxConfig {
+            poll_interval_ms: 1000,
+            batch_size: 1000,
+            max_attempts: 1,
+            webhook_timeout_seconds: 1,
+            dead_letter_check_interval_seconds: 300,
+            dead_letter_alert_threshold: 1,
+        };
+        let refusing = Refusing(account_id);
+        while dispatcher::dispatch_batch(&pool, &refusing, &config)
+            .await
+            .unwrap()
+            > 0
+        {}
+        let (letters, total) = dead_letters
+            .list_dead_letters(tenant, &ListDeadLettersQuery::default(), 20, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(letters[0].attempts, 1);
+        assert_eq!(letters[0].last_error.as_deref(), Some("endpoint down"));
+        let only_resolved = ListDeadLettersQuery {
+            event_type: Some(CASE_RESOLVED.to_string()),
+            ..ListDeadLettersQuery::default()
+        };
+        let (_, total) = dead_letters
+            .list_dead_letters(tenant, &only_resolved, 20, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 2);
+
+        // A redriven dead letter is delivered again; one purged is gone
+        dead_letters.redrive(tenant, event_ids[0]).await.unwrap();
+        assert!(matches!(
+            dead_letters.get_dead_letter(tenant, event_ids[0]).await,
+            Err(ServiceError::NotFound)
+        ));
+        let redriven = OutboxRepo::find(&pool, tenant, event_ids[0])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(redriven.attempts, 0);
+        dead_letters.purge(tenant, event_ids[1]).await.unwrap();
+        assert!(
+            OutboxRepo::find(&pool, tenant, event_ids[1])
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // Dead letters belong to their account
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(matches!(
+            dead_letters.redrive(other, event_ids[2]).await,
+            Err(ServiceError::NotFound)
+        ));
+        assert_eq!(dead_letters.purge_all(other, None).await.unwrap(), 0);
+        assert_eq!(
+            dead_letters
+                .redrive_all(tenant, Some(USER_MERGED))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            OutboxRepo::count_dead_letters(&pool, tenant, None)
+                .await
+                .unwrap(),
+            0
+        );
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
@@ -0,0 +1,759 @@
+//! Devices and browser fingerprinting
+//!
+//! Transactions identify their device by IP address, user agent, and accept-language header,
+//! so the same browser on a new network counts as a new device. Integrators who run the
+//! fingerprinting script submit its signals here instead and pass the returned device token
+//! with each transaction, which then resolves to one device wherever the browser connects
+//! from.
+//!
+//! Each device also carries a composite risk score, recomputed whenever it is seen, that sums
+//! up what is known about it independently of any one transaction.
+
+use chrono::TimeDelta;
+use sqlx::{PgConnection, PgPool, types::Json};
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    database::{
+        Tenant,
+        repositories::{DeviceRecord, DeviceRepo, DeviceRiskInputsRecord, NewDevice},
+    },
+    models::device::{
+        Device, DeviceFingerprintRequest, DeviceSignals, DeviceUpdate, ListDevicesQuery,
+        device_token,
+    },
+    scoring::combine_scores,
+    utils::{sha256_hex, ua},
+};
+
+/// Version of the fingerprint computation, hashed in so a changed computation cannot collide
+/// with fingerprints computed the old way
+const FINGERPRINT_VERSION: &str = "fp1";
+
+/// Hours from a device's first to its latest sighting during which it still counts as new
+const NEW_DEVICE_HOURS: i64 = 24;
+/// Browser signals a fingerprinted device must report before it can be told apart from others
+const MIN_IDENTIFYING_SIGNALS: usize = 5;
+/// Device risk contribution of a device that is new
+const NEW_DEVICE_SCORE: f64 = 15.0;
+/// Device risk contribution of an IP address at a hosting provider
+const HOSTING_IP_SCORE: f64 = 35.0;
+/// Device risk contribution of a user agent naming an automation tool
+const AUTOMATION_SCORE: f64 = 50.0;
+/// Device risk contribution of too few identifying browser signals
+const LOW_ENTROPY_SCORE: f64 = 25.0;
+/// Device risk contribution of each user beyond the first seen with the device
+const SHARED_DEVICE_SCORE_PER_USER: f64 = 10.0;
+/// Cap on the device risk contribution of users seen with the device
+const MAX_SHARED_DEVICE_SCORE: f64 = 40.0;
+
+impl From<DeviceRecord> for Device {
+    fn from(record: DeviceRecord) -> Self {
+        let signals = stored_signals(&record.traits_data);
+        Device {
+            id: record.id,
+            device_token: device_token(&record.fingerprint_hash),
+            user_id: record.user_id,
+            ip_address: record.ip_address,
+            asn: record.asn,
+            isp: record.isp,
+            user_agent: record.user_agent,
+            user_agent_details: record.user_agent_details.map(|Json(details)| details),
+            accept_language: record.accept_language,
+            ja3: record.ja3,
+            ja4: record.ja4,
+            header_order_hash: record.header_order_hash,
+            signals,
+            status: record.status,
+            status_changed_at: record.status_changed_at,
+            risk_score: record.risk_score,
+            first_seen: record.first_seen,
+            last_seen: record.last_seen,
+            transaction_count: record.transaction_count,
+            user_count: record.user_count,
+            suspicious: record.suspicious,
+        }
+    }
+}
+
+/// Device management backed by PostgreSQL
+#[derive(Debug, Clone)]
+pub struct DeviceService {
+    pool: PgPool,
+}
+
+impl DeviceService {
+    /// Create a service over the given pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Find or create the device a browser's signals identify, storing the latest signals and
+    /// network details
+    pub async fn register_fingerprint(
+        &self,
+        tenant: Tenant,
+        request: &DeviceFingerprintRequest,
+    ) -> ServiceResult<Device> {
+        request.validate().map_err(ServiceError::Invalid)?;
+        let fingerprint = fingerprint_hash(&request.signals);
+        let traits = serde_json::to_value(&request.signals).unwrap_or_default();
+        let details = request.user_agent.as_deref().map(ua::parse);
+
+        let mut tx = self.pool.begin().await?;
+        let device_id = DeviceRepo::upsert(
+            &mut *tx,
+            NewDevice {
+                tenant,
+                user_id: None,
+                fingerprint_hash: &fingerprint,
+                ip_address: &request.ip_address,
+                asn: None,
+                isp: None,
+                user_agent: request.user_agent.as_deref(),
+                user_agent_details: details.as_ref(),
+                accept_language: request.accept_language.as_deref(),
+                ja3: None,
+                ja4: None,
+                header_order_hash: None,
+                session_id: None,
+                session_age: None,
+                traits_data: Some(&traits),
+            },
+        )
+        .await?
+        .ok_or_else(|| ServiceError::Conflict("The device has been deleted".to_string()))?;
+        refresh_device_risk_score(&mut tx, tenant, device_id).await?;
+        let record = DeviceRepo::find(&mut *tx, tenant, device_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+
+        tracing::info!(account_id = %tenant, %device_id, "Device fingerprinted");
+        Ok(record.into())
+    }
+
+    /// Fetch a live device of an account
+    pub async fn get_device(&self, tenant: Tenant, device_id: Uuid) -> ServiceResult<Device> {
+        DeviceRepo::find(&self.pool, tenant, device_id)
+            .await?
+            .map(Device::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Apply an update to a live device of an account
+    pub async fn update_device(
+        &self,
+        tenant: Tenant,
+        device_id: Uuid,
+        update: &DeviceUpdate,
+    ) -> ServiceResult<Device> {
+        if let Some(status) = update.status {
+            if !DeviceRepo::set_status(&self.pool, tenant, device_id, status).await? {
+                return Err(ServiceError::NotFound);
+            }
+            tracing::info!(account_id = %tenant, %device_id, ?status, "Device status changed");
+        }
+        self.get_device(tenant, device_id).await
+    }
+
+    /// Page of an account's devices matching the listing filters, with the total count
+    pub async fn list_devices(
+        &self,
+        tenant: Tenant,
+        query: &ListDevicesQuery,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<Device>, i64)> {
+        let records = DeviceRepo::list(&self.pool, tenant, query, limit, offset).await?;
+        let total = DeviceRepo::count(&self.pool, tenant, query).await?;
+        Ok((records.into_iter().map(Device::from).collect(), total))
+    }
+}
+
+/// Fingerprint of a browser, stable across networks, browser updates, and page zoom
+///
+/// Hashes the rendering, hardware, and locale signals that identify a browser install, after
+/// normalizing them so that font detection order, letter case, and screen rotation do not
+/// matter. The user agent, IP address, pixel ratio, memory, and language preferences are left
+/// out, since they change without the device changing.
+pub fn fingerprint_hash(signals: &DeviceSignals) -> String {
+    let text = |value: Option<&str>| value.unwrap_or_default().trim().to_lowercase();
+    let number = |value: Option<u32>| value.map(|n| n.to_string()).unwrap_or_default();
+
+    let webgl = signals.webgl.as_ref();
+    let screen = signals
+        .screen
+        .as_ref()
+        .map(|screen| {
+            format!(
+                "{}x{}x{}",
+                screen.width.max(screen.height),
+                screen.width.min(screen.height),
+                number(screen.color_depth)
+            )
+        })
+        .unwrap_or_default();
+    let mut fonts: Vec<String> = signals
+        .fonts
+        .iter()
+        .map(|font| font.trim().to_lowercase())
+        .filter(|font| !font.is_empty())
+        .collect();
+    fonts.sort_unstable();
+    fonts.dedup();
+
+    sha256_hex(
+        &[
+            FINGERPRINT_VERSION.to_string(),
+            text(signals.canvas_hash.as_deref()),
+            text(signals.audio_hash.as_deref()),
+            text(webgl.and_then(|w| w.vendor.as_deref())),
+            text(webgl.and_then(|w| w.renderer.as_deref())),
+            screen,
+            text(signals.timezone.as_deref()),
+            text(signals.platform.as_deref()),
+            number(signals.hardware_concurrency),
+            number(signals.touch_points),
+            fonts.join(","),
+        ]
+        .join("|"),
+    )
+}
+
+/// Browser signals stored with a device, for devices fingerprinted from them
+fn stored_signals(traits: &serde_json::Value) -> Option<DeviceSignals> {
+    match traits {
+        serde_json::Value::Object(traits) if traits.is_empty() => None,
+        traits => serde_json::from_value(traits.clone()).ok(),
+    }
+}
+
+/// Recompute and store the composite risk score of a live device
+pub async fn refresh_device_risk_score(
+    conn: &mut PgConnection,
+    tenant: Tenant,
+    device_id: Uuid,
+) -> sqlx::Result<()> {
+    if let Some(inputs) = DeviceRepo::risk_inputs(&mut *conn, tenant, device_id).await? {
+        DeviceRepo::set_risk_score(conn, tenant, device_id, device_risk_score(&inputs)).await?;
+    }
+    Ok(())
+}
+
+/// Composite risk of a device from 0 to 100
+///
+/// A device that is new, uses an IP address at a hosting provider, sends an automated user
+/// agent, reports too few browser signals to be told apart, or has been used by several users
+/// scores higher; contributions are combined like transaction risk factors. Devices identified
+/// without browser signals are not judged on them.
+fn device_risk_score(inputs: &DeviceRiskInputsRecord) -> f64 {
+    let new = inputs.last_seen - inputs.first_seen < TimeDelta::hours(NEW_DEVICE_HOURS);
+    let automated = inputs
+        .user_agent_details
+        .as_ref()
+        .is_some_and(|Json(details)| details.automation.is_some());
+    let low_entropy = stored_signals(&inputs.traits_data)
+        .is_some_and(|signals| identifying_signals(&signals) < MIN_IDENTIFYING_SIGNALS);
+    let other_users = (inputs.user_count - 1).max(0) as f64;
+    combine_scores([
+        if new { NEW_DEVICE_SCORE } else { 0.0 },
+        if inputs.hosting_ip {
+            HOSTING_IP_SCORE
+        } else {
+            0.0
+        },
+        if automated { AUTOMATION_SCORE } else { 0.0 },
+        if low_entropy { LOW_ENTROPY_SCORE } else { 0.0 },
+        (other_users * SHARED_DEVICE_SCORE_PER_USER).min(MAX_SHARED_DEVICE_SCORE),
+    ])
+}
+
+/// How many of the browser signals that tell devices apart were reported
+fn identifying_signals(signals: &DeviceSignals) -> usize {
+    [
+        signals.canvas_hash.is_some(),
+        signals.audio_hash.is_some(),
+        signals.webgl.is_some(),
+        signals.screen.is_some(),
+        signals.timezone.is_some(),
+        !signals.languages.is_empty(),
+        signals.platform.is_some(),
+        signals.hardware_concurrency.is_some(),
+        signals.device_memory.is_some(),
+        signals.touch_points.is_some(),
+        !signals.fonts.is_empty(),
+    ]
+    .into_iter()
+    .filter(|&reported| reported)
+    .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        database::repositories::AccountRepo,
+        models::{
+            account::SubscriptionTier,
+            device::DeviceStatus,
+            transaction::{ListTransactionsQuery, TransactionRequest},
+        },
+        scoring::{RiskEngine, UserSignals},
+        services::TransactionService,
+        test_support::{create_account, test_pool},
+    };
+
+    fn signals() -> DeviceSignals {
+        serde_json::from_value(json!({
+            "canvas_hash": "2b6f0cc904d137be2e1730235f5664094b831186",
+            "webgl": { "vendor": "Apple Inc.", "renderer": "Apple M1" },
+            "screen": { "width": 1512, "height": 982, "color_depth": 30, "pixel_ratio": 2.0 },
+            "timezone": "Europe/Berlin",
+            "languages": ["de-DE", "en-US"],
+            "fonts": ["Arial", "Menlo", "Helvetica Neue"]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_volatile_signals() {
+        let base = fingerprint_hash(&signals());
+
+        let mut same = signals();
+        same.fonts = vec![
+            "menlo ".into(),
+            "Helvetica Neue".into(),
+            "ARIAL".into(),
+            "Arial".into(),
+        ];
+        let screen = same.screen.as_mut().unwrap();
+        (screen.width, screen.height) = (982, 1512);
+        screen.pixel_ratio = Some(1.0);
+        same.languages = vec!["en-US".into()];
+        same.device_memory = Some(8.0);
+        assert_eq!(fingerprint_hash(&same), base);
+
+        let mut other = signals();
+        other.canvas_hash = Some("a94a8fe5ccb19ba61c4c0873d391e987982fbbd3".into());
+        assert_ne!(fingerprint_hash(&other), base);
+        let mut other = signals();
+        other.fonts.push("Comic Sans MS".into());
+        assert_ne!(fingerprint_hash(&other), base);
+    }
+
+    #[test]
+    fn test_device_risk_score_combines_signals() {
+        let seen = Utc::now();
+        let inputs =
+            |user_agent: &str, traits: serde_json::Value, users: i64| DeviceRiskInputsRecord {
+                first_seen: seen - TimeDelta::days(30),
+                last_seen: seen,
+                user_agent_details: Some(Json(ua::parse(user_agent))),
+                traits_data: traits,
+                user_count: users,
+                hosting_ip: false,
+            };
+        let browser = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36";
+        let traits = serde_json::to_value(signals()).unwrap();
+
+        assert_eq!(device_risk_score(&inputs(browser, traits.clone(), 1)), 0.0);
+        assert_eq!(device_risk_score(&inputs(browser, json!({}), 0)), 0.0);
+        assert_eq!(
+            device_risk_score(&inputs(browser, json!({ "canvas_hash": "2b6f" }), 1)),
+            LOW_ENTROPY_SCORE
+        );
+        assert_eq!(
+            device_risk_score(&inputs(browser, traits.clone(), 9)),
+            MAX_SHARED_DEVICE_SCORE
+        );
+
+        let new = DeviceRiskInputsRecord {
+            first_seen: seen,
+            hosting_ip: true,
+            ..inputs("HeadlessChrome/126.0", traits, 1)
+        };
+        assert_eq!(
+            device_risk_score(&new),
+            combine_scores([NEW_DEVICE_SCORE, HOSTING_IP_SCORE, AUTOMATION_SCORE])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_device_risk_score_reaches_insights() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let devices = DeviceService::new(pool.clone());
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+
+        let device = devices
+            .register_fingerprint(
+                tenant,
+                &DeviceFingerprintRequest {
+                    ip_address: "198.51.100.1".to_string(),
+                    user_agent: Some("Mozilla/5.0".to_string()),
+                    accept_language: None,
+                    signals: signals(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(device.risk_score, Some(NEW_DEVICE_SCORE));
+
+        let ip_address = "192.0.2.77";
+        sqlx::query(
+            "INSERT INTO ip_risk_cache (ip_address, traits_data, expires_at)
+             VALUES ($1::inet, '{\"is_hosting_provider\": true}', NOW() + INTERVAL '1 hour')
+             ON CONFLICT (ip_address) DO UPDATE SET traits_data = EXCLUDED.traits_data,
+                 expires_at = EXCLUDED.expires_at",
+        )
+        .bind(ip_address)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let transaction: TransactionRequest = serde_json::from_value(json!({
+            "device": {
+                "ip_address": ip_address,
+                "user_agent": "Mozilla/5.0",
+                "device_token": device.device_token
+            },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let assessment = RiskEngine::new().assess(&transaction, &UserSignals::default());
+        let stored = transactions
+            .store_transaction(tenant, &transaction, &assessment, &[])
+            .await
+            .unwrap();
+
+        let expected = combine_scores([NEW_DEVICE_SCORE, HOSTING_IP_SCORE]);
+        let seen = devices.get_device(tenant, device.id).await.unwrap();
+        assert_eq!(seen.risk_score, Some(expected));
+        let insights = transactions.insights(tenant, stored.id).await.unwrap();
+        assert_eq!(insights.device.unwrap().risk_score, Some(expected));
+
+        sqlx::query("DELETE FROM ip_risk_cache WHERE ip_address = $1::inet")
+            .bind(ip_address)
+            .execute(&pool)
+            .await
+            .unwrap();
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_device_tokens_follow_the_browser_across_networks() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let devices = DeviceService::new(pool.clone());
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+
+        let request = DeviceFingerprintRequest {
+            ip_address: "198.51.100.1".to_string(),
+            user_agent: Some("Mozilla/5.0".to_string()),
+            accept_language: None,
+            signals: signals(),
+        };
+        let device = devices
+            .register_fingerprint(tenant, &request)
+            .await
+            .unwrap();
+        assert_eq!(device.signals, Some(signals()));
+        assert_eq!(device.user_agent_details, Some(ua::parse("Mozilla/5.0")));
+        let moved = DeviceFingerprintRequest {
+            ip_address: "203.0.113.9".to_string(),
+            ..request
+        };
+        let again = devices.register_fingerprint(tenant, &moved).await.unwrap();
+        assert_eq!(again.id, device.id);
+        assert_eq!(again.ip_address, "203.0.113.9");
+
+        let transaction: TransactionRequest = serde_json::from_value(json!({
+            "device": {
+                "ip_address": "192.0.2.44",
+                "user_agent": "Mozilla/5.0 (updated)",
+                "device_token": device.device_token,
+                "ja3": "CD08E31494F9531F560D64C695473DA9"
+            },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        transaction.validate().unwrap();
+        let assessment = RiskEngine::new().assess(&transaction, &UserSignals::default());
+        let stored = transactions
+            .store_transaction(tenant, &transaction, &assessment, &[])
+            .await
+            .unwrap();
+        let device_ids: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT device_id FROM transaction_devices WHERE transaction_id = $1",
+        )
+        .bind(stored.id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(device_ids, [device.id]);
+        let seen = devices.get_device(tenant, device.id).await.unwrap();
+        assert_eq!(
+            seen.ja3.as_deref(),
+            Some("cd08e31494f9531f560d64c695473da9")
+        );
+
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(
+            DeviceRepo::find(&pool, other, device.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_device_listing_filters() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let devices = DeviceService::new(pool.clone());
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+
+        let scripted = devices
+            .register_fingerprint(
+                tenant,
+                &DeviceFingerprintRequest {
+                    ip_address: "198.51.100.1".to_string(),
+                    user_agent: Some("python-requests/2.32.3".to_string()),
+                    accept_language: None,
+                    signals: signals(),
+                },
+            )
+            .await
+            .unwrap();
+        assert!(scripted.suspicious);
+        let mut browser_signals = signals();
+        browser_signals.canvas_hash = Some("a94a8fe5ccb19ba61c4c0873d391e987982fbbd3".into());
+        let browser = devices
+            .register_fingerprint(
+                tenant,
+                &DeviceFingerprintRequest {
+                    ip_address: "203.0.113.9".to_string(),
+                    user_agent: Some("Mozilla/5.0".to_string()),
+                    accept_language: None,
+                    signals: browser_signals,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(!browser.suspicious);
+
+        let transaction: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "203.0.113.9", "device_token": browser.device_token },
+            "event": { "type": "purchase" },
+            "account": { "user_id": "customer-1" }
+        }))
+        .unwrap();
+        let assessment = RiskEngine::new().assess(&transaction, &UserSignals::default());
+        let stored = transactions
+            .store_transaction(tenant, &transaction, &assessment, &[])
+            .await
+            .unwrap();
+
+        let list = |query: ListDevicesQuery| {
+            let devices = devices.clone();
+            async move {
+                let (page, total) = devices.list_devices(tenant, &query, 20, 0).await.unwrap();
+                assert_eq!(page.len() as i64, total);
+                page.into_iter().map(|device| device.id).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(list(ListDevicesQuery::default()).await.len(), 2);
+        let suspicious = ListDevicesQuery {
+            suspicious: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(list(suspicious).await, [scripted.id]);
+        let in_range = ListDevicesQuery {
+            ip_address: Some("203.0.113.0/24".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(list(in_range).await, [browser.id]);
+        let of_user = ListDevicesQuery {
+            user_id: stored.user_id,
+            ..Default::default()
+        };
+        assert_eq!(list(of_user).await, [browser.id]);
+
+        let browser = devices.get_device(tenant, browser.id).await.unwrap();
+        assert_eq!((browser.transaction_count, browser.user_count), (1, 1));
+        let from_device = |device_id| ListTransactionsQuery {
+            device_id: Some(device_id),
+            ..Default::default()
+        };
+        let (records, total) = transactions
+            .list_transactions(tenant, &from_device(browser.id), 20, 0)
+            .await
+            .unwrap();
+        assert_eq!((records[0].id, total), (stored.id, 1));
+        let (_, total) = transactions
+            .list_transactions(tenant, &from_device(scripted.id), 20, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 0);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_device_user_links_reach_scoring() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let devices = DeviceService::new(pool.clone());
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let engine = RiskEngine::new();
+
+        let transaction = |user: &str| -> TransactionRequest {
+            serde_json::from_value(json!({
+                "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+                "event": { "type": "purchase" },
+                "account": { "user_id": user }
+            }))
+            .unwrap()
+        };
+        for user in ["customer-1", "customer-2", "customer-1"] {
+            let request = transaction(user);
+            let signals = transactions.user_signals(tenant, &request).await.unwrap();
+            let assessment = engine.assess(&request, &signals);
+            transactions
+                .store_transaction(tenant, &request, &assessment, &[])
+                .await
+                .unwrap();
+        }
+        let (page, _) = devices
+            .list_devices(tenant, &ListDevicesQuery::default(), 20, 0)
+            .await
+            .unwrap();
+        assert_eq!((page[0].transaction_count, page[0].user_count), (3, 2));
+
+        let signals = transactions
+            .user_signals(tenant, &transaction("customer-3"))
+            .await
+            .unwrap();
+        assert_eq!(signals.device_user_count, 3);
+        assert!(!signals.device_chargebacks);
+
+        sqlx::query(
+            "UPDATE users SET chargeback_count = 1 WHERE account_id = $1 AND external_user_id = $2",
+        )
+        .bind(account_id)
+        .bind("customer-2")
+        .execute(&pool)
+        .await
+        .unwrap();
+        let request = transaction("customer-3");
+        let signals = transactions.user_signals(tenant, &request).await.unwrap();
+        assert!(signals.device_chargebacks);
+        let codes: Vec<String> = engine
+            .assess(&request, &signals)
+            .factors
+            .into_iter()
+            .map(|factor| factor.code)
+            .collect();
+        assert!(codes.contains(&"CHARGEBACK_DEVICE".to_string()));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_device_status_reaches_scoring() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let other = create_account(&pool, SubscriptionTier::Pro).await;
+        let devices = DeviceService::new(pool.clone());
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let engine = RiskEngine::new();
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "account": { "user_id": "customer-1" }
+        }))
+        .unwrap();
+        for tenant in [tenant, other] {
+            let signals = transactions.user_signals(tenant, &request).await.unwrap();
+            let assessment = engine.assess(&request, &signals);
+            transactions
+                .store_transaction(tenant, &request, &assessment, &[])
+                .await
+                .unwrap();
+        }
+        let (page, _) = devices
+            .list_devices(tenant, &ListDevicesQuery::default(), 20, 0)
+            .await
+            .unwrap();
+        let device_id = page[0].id;
+        assert_eq!(page[0].status, DeviceStatus::Normal);
+
+        let block = DeviceUpdate {
+            status: Some(DeviceStatus::Blocked),
+        };
+        assert!(matches!(
+            devices.update_device(other, device_id, &block).await,
+            Err(ServiceError::NotFound)
+        ));
+        let device = devices
+            .update_device(tenant, device_id, &block)
+            .await
+            .unwrap();
+        assert_eq!(device.status, DeviceStatus::Blocked);
+        assert!(device.status_changed_at.is_some());
+
+        let signals = transactions.user_signals(tenant, &request).await.unwrap();
+        assert_eq!(signals.device_status, DeviceStatus::Blocked);
+        assert!(engine.assess(&request, &signals).hard_reject);
+        let signals = transactions.user_signals(other, &request).await.unwrap();
+        assert_eq!(signals.device_status, DeviceStatus::Normal);
+
+        let trust = DeviceUpdate {
+            status: Some(DeviceStatus::Trusted),
+        };
+        devices
+            .update_device(tenant, device_id, &trust)
+            .await
+            .unwrap();
+        let signals = transactions.user_signals(tenant, &request).await.unwrap();
+        let assessment = engine.assess(&request, &signals);
+        assert!(!assessment.hard_reject);
+        assert!(
+            assessment
+                .factors
+                .iter()
+                .any(|factor| factor.code == "TRUSTED_DEVICE")
+        );
+
+        for tenant in [tenant, other] {
+            AccountRepo::delete(&pool, tenant.id()).await.unwrap();
+        }
+    }
+}
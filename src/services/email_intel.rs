@@ -0,0 +1,400 @@
+//! Email domain intelligence
+//!
+//! Free email providers and disposable address services are recognized by their domain. A copy
+//! of each domain list is bundled with the service and merged with the lists downloaded
+//! periodically from the configured URLs, so detection works offline and improves once a
+//! download succeeds. Domains on neither list have their mail servers looked up: disposable
+//! services register fresh domains faster than any list keeps up, but point them at the same
+//! mail servers. Lists are kept in memory by each instance.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use hickory_resolver::TokioResolver;
+
+use crate::{
+    config::EmailIntelConfig, models::transaction::TransactionEmail, scheduler::ScheduledTask,
+    scoring::EmailTraits,
+};
+
+/// Disposable address services bundled with the service
+const BUNDLED_DISPOSABLE: &str = include_str!("../../data/disposable_email_domains.txt");
+
+/// Free email providers bundled with the service
+const BUNDLED_FREE: &str = include_str!("../../data/free_email_domains.txt");
+
+/// A kind of email domain list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DomainList {
+    /// Disposable and temporary address services
+    Disposable,
+    /// Free email providers
+    Free,
+}
+
+impl DomainList {
+    /// Every list
+    pub const ALL: [DomainList; 2] = [DomainList::Disposable, DomainList::Free];
+
+    /// Name of the list in logs
+    pub fn name(self) -> &'static str {
+        match self {
+            DomainList::Disposable => "disposable",
+            DomainList::Free => "free",
+        }
+    }
+
+    /// URLs the list is downloaded from
+    pub fn urls(self, config: &EmailIntelConfig) -> &[String] {
+        match self {
+            DomainList::Disposable => &config.disposable_urls,
+            DomainList::Free => &config.free_urls,
+        }
+    }
+
+    fn bundled(self) -> &'static str {
+        match self {
+            DomainList::Disposable => BUNDLED_DISPOSABLE,
+            DomainList::Free => BUNDLED_FREE,
+        }
+    }
+}
+
+/// Canonical form of a list entry or mail server name, or `None` if it is not a domain name
+pub fn parse_domain(entry: &str) -> Option<String> {
+    let domain = entry
+        .trim()
+        .trim_start_matches("*.")
+        .trim_matches('.')
+        .to_ascii_lowercase();
+    let valid = domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        });
+    valid.then_some(domain)
+}
+
+/// Domains of a downloaded list, skipping comments and lines that are not domain names
+pub fn parse_list(body: &str) -> impl Iterator<Item = String> + '_ {
+    body.lines().filter_map(|line| {
+        line.split('#')
+            .next()?
+            .split_whitespace()
+            .next()
+            .and_then(parse_domain)
+    })
+}
+
+/// `domain` and every parent domain of it, down to the one below the top-level domain
+fn parent_domains(domain: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(domain), |domain| {
+        domain
+            .split_once('.')
+            .map(|(_, parent)| parent)
+            .filter(|parent| parent.contains('.'))
+    })
+}
+
+/// The domains of each list currently in use
+#[derive(Debug, Default)]
+struct DomainLists {
+    disposable: HashSet<String>,
+    free: HashSet<String>,
+}
+
+impl DomainLists {
+    /// The bundled lists
+    fn bundled() -> Self {
+        let mut lists = Self::default();
+        for list in DomainList::ALL {
+            *lists.get_mut(list) = parse_list(list.bundled()).collect();
+        }
+        lists
+    }
+
+    fn get(&self, list: DomainList) -> &HashSet<String> {
+        match list {
+            DomainList::Disposable => &self.disposable,
+            DomainList::Free => &self.free,
+        }
+    }
+
+    fn get_mut(&mut self, list: DomainList) -> &mut HashSet<String> {
+        match list {
+            DomainList::Disposable => &mut self.disposable,
+            DomainList::Free => &mut self.free,
+        }
+    }
+
+    /// Whether `list` names `domain` or a parent domain of it
+    fn contains(&self, list: DomainList, domain: &str) -> bool {
+        let domains = self.get(list);
+        parent_domains(domain).any(|domain| domains.contains(domain))
+    }
+}
+
+/// Recognizes free and disposable email domains
+#[derive(Clone)]
+pub struct EmailIntelService {
+    lists: Arc<RwLock<DomainLists>>,
+    resolver: Option<TokioResolver>,
+    mx_timeout: Duration,
+}
+
+impl EmailIntelService {
+    /// Create an email intelligence service with the bundled lists, looking up mail servers
+    /// unless `config` disables it or the system resolver configuration cannot be read
+    pub fn new(config: &EmailIntelConfig) -> Self {
+        let mx_timeout = Duration::from_millis(config.mx_timeout_ms);
+        let resolver = config
+            .mx_lookups_enabled
+            .then(|| match TokioResolver::builder_tokio() {
+                Ok(mut builder) => {
+                    builder.options_mut().timeout = mx_timeout;
+                    Some(builder.build())
+                },
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to read resolver configuration, email mail servers will not be looked up");
+                    None
+                },
+            })
+            .flatten();
+        Self {
+            lists: Arc::new(RwLock::new(DomainLists::bundled())),
+            resolver,
+            mx_timeout,
+        }
+    }
+
+    /// Replace the downloaded domains of `list`, keeping the bundled ones
+    pub fn replace_list(&self, list: DomainList, mut domains: HashSet<String>) {
+        domains.extend(parse_list(list.bundled()));
+        *self
+            .lists
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(list) = domains;
+    }
+
+    /// Which lists name `domain` or a parent domain of it
+    pub fn listed_traits(&self, domain: &str) -> EmailTraits {
+        let lists = self.lists.read().unwrap_or_else(|e| e.into_inner());
+        EmailTraits {
+            is_free: lists.contains(DomainList::Free, domain),
+            is_disposable: lists.contains(DomainList::Disposable, domain),
+        }
+    }
+
+    /// Whether any of the mail servers `hosts` belongs to a disposable address service
+    pub fn has_disposable_mail_server<'a>(&self, hosts: impl IntoIterator<Item = &'a str>) -> bool {
+        let lists = self.lists.read().unwrap_or_else(|e| e.into_inner());
+        hosts
+            .into_iter()
+            .filter_map(parse_domain)
+            .any(|host| lists.contains(DomainList::Disposable, &host))
+    }
+
+    /// Names of the mail servers of `domain`, empty if it has none or they cannot be looked up
+    /// in time
+    async fn mail_servers(&self, domain: &str) -> Vec<String> {
+        let Some(resolver) = &self.resolver else {
+            return Vec::new();
+        };
+        match tokio::time::timeout(self.mx_timeout, resolver.mx_lookup(domain)).await {
+            Ok(Ok(lookup)) => lookup.iter().map(|mx| mx.exchange().to_ascii()).collect(),
+            Ok(Err(e)) if e.is_no_records_found() => Vec::new(),
+            Ok(Err(e)) => {
+                tracing::debug!(error = %e, domain, "Mail server lookup failed");
+                Vec::new()
+            },
+            Err(_) => {
+                tracing::debug!(domain, "Mail server lookup timed out");
+                Vec::new()
+            },
+        }
+    }
+
+    /// What is known about the domain of `domain`
+    ///
+    /// Mail servers are only looked up for domains on neither list.
+    pub async fn traits(&self, domain: &str) -> EmailTraits {
+        let Some(domain) = parse_domain(domain) else {
+            return EmailTraits::default();
+        };
+        let mut traits = self.listed_traits(&domain);
+        if !traits.is_free && !traits.is_disposable {
+            let hosts = self.mail_servers(&domain).await;
+            traits.is_disposable =
+                self.has_disposable_mail_server(hosts.iter().map(String::as_str));
+        }
+        traits
+    }
+
+    /// Traits of the domain of `email`, or none if the transaction has no email domain
+    pub async fn lookup(&self, email: Option<&TransactionEmail>) -> EmailTraits {
+        match email.and_then(TransactionEmail::resolved_domain) {
+            Some(domain) => self.traits(&domain).await,
+            None => EmailTraits::default(),
+        }
+    }
+}
+
+impl fmt::Debug for EmailIntelService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lists = self.lists.read().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("EmailIntelService")
+            .field("disposable_domains", &lists.disposable.len())
+            .field("free_domains", &lists.free.len())
+            .field("mx_lookups", &self.resolver.is_some())
+            .finish()
+    }
+}
+
+/// Download every URL of a list, failing if any of them cannot be fetched
+async fn fetch_list(client: &reqwest::Client, urls: &[String]) -> reqwest::Result<HashSet<String>> {
+    let mut domains = HashSet::new();
+    for url in urls {
+        let body = client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        domains.extend(parse_list(&body));
+    }
+    Ok(domains)
+}
+
+/// Task that downloads the configured lists on `refresh_schedule`, and once at startup
+///
+/// A list that fails to download, or names nothing, keeps its previous domains and fails the
+/// run.
+pub fn email_intel_refresh_task(
+    intel: EmailIntelService,
+    config: EmailIntelConfig,
+) -> reqwest::Result<ScheduledTask> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.fetch_timeout_seconds))
+        .build()?;
+    let schedule = config.refresh_schedule.clone();
+    let task = ScheduledTask::new("email_intel_refresh", schedule, move || {
+        let (intel, client, config) = (intel.clone(), client.clone(), config.clone());
+        async move { refresh_lists(&intel, &client, &config).await }
+    });
+    Ok(task.run_on_start())
+}
+
+/// Download every configured list, naming the lists that could not be refreshed
+async fn refresh_lists(
+    intel: &EmailIntelService,
+    client: &reqwest::Client,
+    config: &EmailIntelConfig,
+) -> anyhow::Result<()> {
+    let mut failed = Vec::new();
+    for list in DomainList::ALL {
+        let urls = list.urls(config);
+        if urls.is_empty() {
+            continue;
+        }
+        match fetch_list(client, urls).await {
+            Ok(domains) if domains.is_empty() => {
+                tracing::warn!(list = list.name(), "Email domain list named nothing");
+                failed.push(list.name());
+            },
+            Ok(domains) => {
+                let count = domains.len();
+                intel.replace_list(list, domains);
+                tracing::info!(
+                    list = list.name(),
+                    domains = count,
+                    "Email domain list refreshed"
+                );
+            },
+            Err(e) => {
+                tracing::warn!(error = %e, list = list.name(), "Email domain list download failed");
+                failed.push(list.name());
+            },
+        }
+    }
+    if !failed.is_empty() {
+        anyhow::bail!("lists not refreshed: {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn intel() -> EmailIntelService {
+        EmailIntelService::new(&EmailIntelConfig {
+            mx_lookups_enabled: false,
+            ..Config::default().email_intel
+        })
+    }
+
+    #[test]
+    fn test_list_entries_are_normalized() {
+        let body = "# Disposable domains\nMailinator.com\n*.trashmail.de  # wildcard\n\
+                    .yopmail.fr\nlocalhost\nnot a domain\n-bad-.com\n";
+        let domains: Vec<String> = parse_list(body).collect();
+        assert_eq!(domains, ["mailinator.com", "trashmail.de", "yopmail.fr"]);
+    }
+
+    #[test]
+    fn test_bundled_lists_cover_subdomains() {
+        let intel = intel();
+        assert_eq!(
+            intel.listed_traits("gmail.com"),
+            EmailTraits {
+                is_free: true,
+                is_disposable: false
+            }
+        );
+        assert!(intel.listed_traits("eu.mailinator.com").is_disposable);
+        // Only the domain and its parents count, not lookalikes
+        assert_eq!(
+            intel.listed_traits("notmailinator.com"),
+            EmailTraits::default()
+        );
+        assert_eq!(intel.listed_traits("com"), EmailTraits::default());
+    }
+
+    #[tokio::test]
+    async fn test_downloaded_lists_extend_the_bundled_ones() {
+        let intel = intel();
+        assert!(!intel.listed_traits("fresh-burner.test").is_disposable);
+        intel.replace_list(
+            DomainList::Disposable,
+            parse_list("fresh-burner.test\n").collect(),
+        );
+        assert!(intel.listed_traits("fresh-burner.test").is_disposable);
+        assert!(intel.listed_traits("mailinator.com").is_disposable);
+
+        let email = TransactionEmail {
+            address: Some("Someone@Fresh-Burner.test".to_string()),
+            domain: None,
+        };
+        assert!(intel.lookup(Some(&email)).await.is_disposable);
+        assert_eq!(intel.lookup(None).await, EmailTraits::default());
+    }
+
+    #[test]
+    fn test_disposable_mail_servers() {
+        let intel = intel();
+        assert!(intel.has_disposable_mail_server(["mail.mailinator.com."]));
+        assert!(!intel.has_disposable_mail_server(["aspmx.l.google.com.", "mx.example.com"]));
+        assert!(!intel.has_disposable_mail_server([]));
+    }
+}
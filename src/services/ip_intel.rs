@@ -0,0 +1,453 @@
+//! Anonymous IP intelligence
+//!
+//! Public and commercial feeds list the addresses of Tor exit nodes, VPN services, open
+//! proxies, and datacenters. They are downloaded periodically and kept as sets of normalized
+//! CIDR ranges, one set per feed: a lookup asks each set for every range that could cover the
+//! address, so single addresses and overlapping ranges of any length match alike. Redis shares
+//! the sets across instances; without it each instance keeps its own copy in memory.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    net::IpAddr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use redis::{RedisResult, aio::ConnectionManager};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    config::IpIntelConfig,
+    models::{insights::IpTraits, transaction::is_reserved_ip},
+    scheduler::ScheduledTask,
+    utils::ip::{max_prefix_len, network},
+};
+
+/// How long looked-up traits are cached in `ip_risk_cache`
+const IP_TRAITS_TTL_HOURS: i32 = 24;
+
+/// Members added to a Redis set per command when a feed is replaced
+const REDIS_CHUNK_SIZE: usize = 1000;
+
+/// Caches the traits of an IP address alongside whatever else is known about it
+const UPSERT_IP_TRAITS_SQL: &str = r#"
+INSERT INTO ip_risk_cache (ip_address, traits_data, expires_at)
+VALUES ($1::inet, $2, NOW() + make_interval(hours => $3))
+ON CONFLICT (ip_address) DO UPDATE SET
+    traits_data = ip_risk_cache.traits_data || EXCLUDED.traits_data,
+    expires_at = GREATEST(ip_risk_cache.expires_at, EXCLUDED.expires_at)
+"#;
+
+/// A kind of anonymous IP feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feed {
+    /// Tor exit nodes
+    TorExit,
+    /// Datacenters and hosting providers
+    Hosting,
+    /// Commercial VPN services
+    Vpn,
+    /// Open and public proxies
+    Proxy,
+}
+
+impl Feed {
+    /// Every feed, in lookup order
+    pub const ALL: [Feed; 4] = [Feed::TorExit, Feed::Hosting, Feed::Vpn, Feed::Proxy];
+
+    /// Name of the feed in logs and Redis keys
+    pub fn name(self) -> &'static str {
+        match self {
+            Feed::TorExit => "tor_exit",
+            Feed::Hosting => "hosting",
+            Feed::Vpn => "vpn",
+            Feed::Proxy => "proxy",
+        }
+    }
+
+    /// URLs the feed is downloaded from
+    pub fn urls(self, config: &IpIntelConfig) -> &[String] {
+        match self {
+            Feed::TorExit => &config.tor_exit_urls,
+            Feed::Hosting => &config.hosting_urls,
+            Feed::Vpn => &config.vpn_urls,
+            Feed::Proxy => &config.proxy_urls,
+        }
+    }
+
+    fn key(self) -> String {
+        format!("fusegu:ip_intel:{}", self.name())
+    }
+}
+
+/// Canonical range of a feed entry, a single address or a CIDR range, or `None` if it is
+/// neither
+pub fn parse_network(entry: &str) -> Option<String> {
+    let (address, prefix_len) = match entry.split_once('/') {
+        Some((address, prefix_len)) => (address, Some(prefix_len.parse().ok()?)),
+        None => (entry, None),
+    };
+    let ip: IpAddr = address.parse().ok()?;
+    let prefix_len = prefix_len.unwrap_or_else(|| max_prefix_len(ip));
+    (prefix_len <= max_prefix_len(ip)).then(|| network(ip, prefix_len))
+}
+
+/// Ranges of a downloaded feed, skipping comments and lines that are not addresses
+pub fn parse_feed(body: &str) -> impl Iterator<Item = String> + '_ {
+    body.lines().filter_map(|line| {
+        line.split(['#', ';'])
+            .next()?
+            .split_whitespace()
+            .next()
+            .and_then(parse_network)
+    })
+}
+
+/// Every range that covers `ip`, from the whole address space down to the address itself
+fn covering_networks(ip: IpAddr) -> Vec<String> {
+    let ip = ip.to_canonical();
+    (0..=max_prefix_len(ip))
+        .map(|prefix_len| network(ip, prefix_len))
+        .collect()
+}
+
+/// Traits of an address found in the feeds at the same positions of [`Feed::ALL`]
+fn traits(found: [bool; 4]) -> IpTraits {
+    let [tor_exit, hosting, vpn, proxy] = found;
+    IpTraits {
+        is_anonymous: tor_exit || vpn || proxy,
+        is_tor_exit_node: tor_exit,
+        is_anonymous_vpn: vpn,
+        is_public_proxy: proxy,
+        is_hosting_provider: hosting,
+    }
+}
+
+/// Where feed ranges are kept
+#[derive(Clone)]
+enum FeedStore {
+    /// Shared through Redis
+    Redis(ConnectionManager),
+    /// Local to this process
+    Memory(Arc<RwLock<HashMap<Feed, HashSet<String>>>>),
+}
+
+/// Looks IP addresses up in anonymous IP feeds
+#[derive(Clone)]
+pub struct IpIntelService {
+    pool: PgPool,
+    feeds: FeedStore,
+    enabled: bool,
+}
+
+impl IpIntelService {
+    /// Create an IP intelligence service keeping feeds in `redis`, or in memory when it is
+    /// `None`
+    ///
+    /// Without any feed configured in `config`, lookups find nothing.
+    pub fn new(pool: PgPool, redis: Option<ConnectionManager>, config: &IpIntelConfig) -> Self {
+        Self {
+            pool,
+            feeds: redis.map_or_else(|| FeedStore::Memory(Arc::default()), FeedStore::Redis),
+            enabled: config.is_enabled(),
+        }
+    }
+
+    /// Replace the ranges of `feed`
+    pub async fn replace_feed(&self, feed: Feed, networks: &HashSet<String>) -> RedisResult<()> {
+        match &self.feeds {
+            FeedStore::Redis(connection) => {
+                let key = feed.key();
+                let staging = format!("{key}:staging");
+                let mut pipe = redis::pipe();
+                pipe.atomic().del(&staging).ignore();
+                if networks.is_empty() {
+                    pipe.del(&key).ignore();
+                } else {
+                    let networks: Vec<&String> = networks.iter().collect();
+                    for chunk in networks.chunks(REDIS_CHUNK_SIZE) {
+                        pipe.sadd(&staging, chunk).ignore();
+                    }
+                    pipe.rename(&staging, &key).ignore();
+                }
+                pipe.query_async::<()>(&mut connection.clone()).await
+            },
+            FeedStore::Memory(feeds) => {
+                feeds
+                    .write()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(feed, networks.clone());
+                Ok(())
+            },
+        }
+    }
+
+    /// Which feeds list `ip`
+    pub async fn traits(&self, ip: IpAddr) -> RedisResult<IpTraits> {
+        let candidates = covering_networks(ip);
+        let mut found = [false; 4];
+        match &self.feeds {
+            FeedStore::Redis(connection) => {
+                let mut pipe = redis::pipe();
+                for feed in Feed::ALL {
+                    pipe.cmd("SMISMEMBER").arg(feed.key()).arg(&candidates);
+                }
+                let members: Vec<Vec<bool>> = pipe.query_async(&mut connection.clone()).await?;
+                for (found, members) in found.iter_mut().zip(members) {
+                    *found = members.contains(&true);
+                }
+            },
+            FeedStore::Memory(feeds) => {
+                let feeds = feeds.read().unwrap_or_else(|e| e.into_inner());
+                for (found, feed) in found.iter_mut().zip(Feed::ALL) {
+                    *found = feeds.get(&feed).is_some_and(|networks| {
+                        candidates.iter().any(|network| networks.contains(network))
+                    });
+                }
+            },
+        }
+        Ok(traits(found))
+    }
+
+    /// Whether `ip` is a Tor exit node, VPN, or public proxy
+    pub async fn is_anonymous_ip(&self, ip: IpAddr) -> RedisResult<bool> {
+        Ok(self.traits(ip).await?.is_anonymous)
+    }
+
+    /// Traits of `ip_address`, cached in `ip_risk_cache` for transaction insights
+    ///
+    /// Reserved addresses, and every address when no feed is configured, have no traits.
+    /// Failures are logged and score the transaction as if nothing were known.
+    pub async fn lookup(&self, ip_address: &str) -> Option<IpTraits> {
+        let ip = ip_address
+            .parse::<IpAddr>()
+            .ok()
+            .filter(|ip| self.enabled && !is_reserved_ip(*ip))?;
+        let traits = match self.traits(ip).await {
+            Ok(traits) => traits,
+            Err(e) => {
+                tracing::warn!(error = %e, ip_address, "IP intelligence lookup failed");
+                return None;
+            },
+        };
+        let cached = sqlx::query(UPSERT_IP_TRAITS_SQL)
+            .bind(ip_address)
+            .bind(json!(traits))
+            .bind(IP_TRAITS_TTL_HOURS)
+            .execute(&self.pool)
+            .await;
+        if let Err(e) = cached {
+            tracing::warn!(error = %e, ip_address, "Failed to cache IP traits");
+        }
+        Some(traits)
+    }
+}
+
+impl fmt::Debug for IpIntelService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let feeds = match self.feeds {
+            FeedStore::Redis(_) => "Redis",
+            FeedStore::Memory(_) => "Memory",
+        };
+        f.debug_struct("IpIntelService")
+            .field("feeds", &feeds)
+            .field("enabled", &self.enabled)
+            .finish()
+    }
+}
+
+/// Download every URL of a feed, failing if any of them cannot be fetched
+async fn fetch_feed(client: &reqwest::Client, urls: &[String]) -> reqwest::Result<HashSet<String>> {
+    let mut networks = HashSet::new();
+    for url in urls {
+        let body = client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        networks.extend(parse_feed(&body));
+    }
+    Ok(networks)
+}
+
+/// Task that downloads the configured feeds on `refresh_schedule`, and once at startup
+///
+/// A feed that fails to download, or lists nothing, keeps its previous ranges and fails the
+/// run.
+pub fn ip_intel_refresh_task(
+    intel: IpIntelService,
+    config: IpIntelConfig,
+) -> reqwest::Result<ScheduledTask> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.fetch_timeout_seconds))
+        .build()?;
+    let schedule = config.refresh_schedule.clone();
+    let task = ScheduledTask::new("ip_intel_refresh", schedule, move || {
+        let (intel, client, config) = (intel.clone(), client.clone(), config.clone());
+        async move { refresh_feeds(&intel, &client, &config).await }
+    });
+    Ok(task.run_on_start())
+}
+
+/// Download and store every configured feed, naming the feeds that could not be refreshed
+async fn refresh_feeds(
+    intel: &IpIntelService,
+    client: &reqwest::Client,
+    config: &IpIntelConfig,
+) -> anyhow::Result<()> {
+    let mut failed = Vec::new();
+    for feed in Feed::ALL {
+        let urls = feed.urls(config);
+        if urls.is_empty() {
+            continue;
+        }
+        let networks = match fetch_feed(client, urls).await {
+            Ok(networks) if networks.is_empty() => {
+                tracing::warn!(feed = feed.name(), "IP intelligence feed listed nothing");
+                failed.push(feed.name());
+                continue;
+            },
+            Ok(networks) => networks,
+            Err(e) => {
+                tracing::warn!(error = %e, feed = feed.name(), "IP intelligence feed download failed");
+                failed.push(feed.name());
+                continue;
+            },
+        };
+        match intel.replace_feed(feed, &networks).await {
+            Ok(()) => tracing::info!(
+                feed = feed.name(),
+                ranges = networks.len(),
+                "IP intelligence feed refreshed"
+            ),
+            Err(e) => {
+                tracing::error!(error = %e, feed = feed.name(), "Failed to store IP intelligence feed");
+                failed.push(feed.name());
+            },
+        }
+    }
+    if !failed.is_empty() {
+        anyhow::bail!("feeds not refreshed: {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{config::Config, test_support::test_pool};
+
+    #[test]
+    fn test_feed_entries_are_normalized() {
+        let body = "# Tor exit nodes\n185.220.101.7\n10.1.2.3/8  ; private\n\
+                    2001:db8::1/32\nnot an address\n1.2.3.4/33\n";
+        let networks: Vec<String> = parse_feed(body).collect();
+        assert_eq!(
+            networks,
+            ["185.220.101.7/32", "10.0.0.0/8", "2001:db8::/32"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_traits_match_covering_ranges() {
+        let pool = PgPool::connect_lazy("postgresql://fusegu@127.0.0.1:1/fusegu").unwrap();
+        let intel = IpIntelService::new(pool, None, &Config::default().ip_intel);
+        let networks = |entries: &str| parse_feed(entries).collect::<HashSet<_>>();
+        intel
+            .replace_feed(Feed::TorExit, &networks("185.220.101.7"))
+            .await
+            .unwrap();
+        intel
+            .replace_feed(Feed::Hosting, &networks("185.220.0.0/16\n2a05:d000::/25"))
+            .await
+            .unwrap();
+
+        let tor = intel
+            .traits("185.220.101.7".parse().unwrap())
+            .await
+            .unwrap();
+        assert!(tor.is_anonymous && tor.is_tor_exit_node && tor.is_hosting_provider);
+        assert!(!tor.is_anonymous_vpn && !tor.is_public_proxy);
+
+        let hosted = intel.traits("185.220.9.9".parse().unwrap()).await.unwrap();
+        assert_eq!(
+            hosted,
+            IpTraits {
+                is_hosting_provider: true,
+                ..IpTraits::default()
+            }
+        );
+        assert!(
+            intel
+                .traits("2a05:d014::1".parse().unwrap())
+                .await
+                .unwrap()
+                .is_hosting_provider
+        );
+        assert!(
+            intel
+                .is_anonymous_ip("::ffff:185.220.101.7".parse().unwrap())
+                .await
+                .unwrap()
+        );
+        assert!(
+            !intel
+                .is_anonymous_ip("198.51.100.1".parse().unwrap())
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookups_are_cached_beside_location_traits() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let octets = Uuid::new_v4().into_bytes();
+        let ip = format!("45.{}.{}.{}", octets[0], octets[1], octets[2]);
+        sqlx::query(
+            "INSERT INTO ip_risk_cache (ip_address, traits_data, expires_at)
+             VALUES ($1::inet, '{\"is_satellite_provider\": true}', NOW() + INTERVAL '1 hour')",
+        )
+        .bind(&ip)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let mut config = Config::default().ip_intel;
+        let intel = IpIntelService::new(pool.clone(), None, &config);
+        intel
+            .replace_feed(Feed::Proxy, &parse_feed(&ip).collect())
+            .await
+            .unwrap();
+        assert_eq!(intel.lookup("10.0.0.1").await, None);
+        let traits = intel.lookup(&ip).await.unwrap();
+        assert!(traits.is_anonymous && traits.is_public_proxy);
+
+        let (cached,): (serde_json::Value,) =
+            sqlx::query_as("SELECT traits_data FROM ip_risk_cache WHERE ip_address = $1::inet")
+                .bind(&ip)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(cached["is_satellite_provider"], true);
+        assert_eq!(serde_json::from_value::<IpTraits>(cached).unwrap(), traits);
+
+        config.tor_exit_urls.clear();
+        config.hosting_urls.clear();
+        let disabled = IpIntelService::new(pool.clone(), None, &config);
+        assert_eq!(disabled.lookup(&ip).await, None);
+
+        sqlx::query("DELETE FROM ip_risk_cache WHERE ip_address = $1::inet")
+            .bind(&ip)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
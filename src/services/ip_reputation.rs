@@ -0,0 +1,191 @@
+//! IP address reputation
+//!
+//! Each account's history with an IP address — how many of its transactions were rejected or
+//! charged back, and how many users and cards they spanned — is kept in `ip_addresses` and
+//! recomputed whenever the address is seen. Rules read the history of the account scoring a
+//! transaction and, as weaker evidence, of every other account.
+
+use sqlx::PgConnection;
+
+use crate::{
+    database::{
+        Tenant,
+        repositories::{IpAddressRepo, IpHistoryRecord},
+    },
+    scoring::{IpHistory, combine_scores},
+};
+
+/// IP risk contribution of a chargeback from the address
+const CHARGEBACK_SCORE: f64 = 50.0;
+/// IP risk contribution of mostly rejected transactions from the address
+const REJECTED_SCORE: f64 = 30.0;
+/// IP risk contribution of more cards from the address than a household plausibly owns
+const MANY_CARDS_SCORE: f64 = 30.0;
+
+impl From<IpHistoryRecord> for IpHistory {
+    fn from(record: IpHistoryRecord) -> Self {
+        IpHistory {
+            transactions: record.transaction_count,
+            rejects: record.reject_count,
+            chargebacks: record.chargeback_count,
+            users: record.user_count,
+            cards: record.card_count,
+        }
+    }
+}
+
+/// Recompute and store the history and reputation risk score of an IP address on an account
+pub async fn refresh_ip_reputation(
+    conn: &mut PgConnection,
+    tenant: Tenant,
+    ip_address: &str,
+) -> sqlx::Result<()> {
+    if let Some(history) = IpAddressRepo::refresh(&mut *conn, tenant, ip_address).await? {
+        let risk_score = ip_risk_score(&history.into());
+        IpAddressRepo::set_risk_score(conn, tenant, ip_address, risk_score).await?;
+    }
+    Ok(())
+}
+
+/// Reputation risk of an IP address from 0 to 100
+///
+/// Chargebacks, mostly rejected transactions, and many cards from the address raise the
+/// score; contributions are combined like transaction risk factors.
+fn ip_risk_score(history: &IpHistory) -> f64 {
+    combine_scores([
+        if history.chargebacks > 0 {
+            CHARGEBACK_SCORE
+        } else {
+            0.0
+        },
+        if history.mostly_rejected() {
+            REJECTED_SCORE
+        } else {
+            0.0
+        },
+        if history.many_cards() {
+            MANY_CARDS_SCORE
+        } else {
+            0.0
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use chrono::Utc;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        database::repositories::{AccountRepo, NewTransaction, TransactionRepo},
+        models::{
+            account::SubscriptionTier,
+            transaction::{Disposition, EventType, MatchKeys, RiskLevel},
+        },
+        test_support::{create_account, test_pool},
+    };
+
+    #[test]
+    fn test_ip_risk_score_combines_history() {
+        let quiet = IpHistory {
+            transactions: 12,
+            rejects: 1,
+            users: 4,
+            cards: 2,
+            ..IpHistory::default()
+        };
+        assert_eq!(ip_risk_score(&quiet), 0.0);
+        assert_eq!(
+            ip_risk_score(&IpHistory {
+                chargebacks: 1,
+                ..quiet
+            }),
+            CHARGEBACK_SCORE
+        );
+        assert_eq!(
+            ip_risk_score(&IpHistory {
+                rejects: 6,
+                chargebacks: 2,
+                cards: 9,
+                ..quiet
+            }),
+            combine_scores([CHARGEBACK_SCORE, REJECTED_SCORE, MANY_CARDS_SCORE])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_history_is_stored_and_shared_across_accounts() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let seen = create_account(&pool, SubscriptionTier::Free).await;
+        let other = create_account(&pool, SubscriptionTier::Free).await;
+        let ip_address = Ipv6Addr::from(Uuid::new_v4().as_u128()).to_string();
+
+        for _ in 0..3 {
+            TransactionRepo::insert(
+                &pool,
+                NewTransaction {
+                    tenant: seen,
+                    user_id: None,
+                    external_transaction_id: None,
+                    risk_score: 80.0,
+                    risk_level: RiskLevel::High,
+                    disposition: Disposition::Reject,
+                    event_type: EventType::Purchase,
+                    shop_id: None,
+                    event_time: Utc::now(),
+                    original_transaction_id: None,
+                    ip_address: &ip_address,
+                    asn: None,
+                    isp: None,
+                    local_hour: None,
+                    device_data: serde_json::json!({}),
+                    custom_inputs: serde_json::json!({}),
+                    warnings: &[],
+                    raw_request: serde_json::json!({}),
+                    match_keys: &MatchKeys::default(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let mut conn = pool.acquire().await.unwrap();
+        refresh_ip_reputation(&mut conn, seen, &ip_address)
+            .await
+            .unwrap();
+        refresh_ip_reputation(&mut conn, other, &ip_address)
+            .await
+            .unwrap();
+
+        let risk_score: Option<f64> = sqlx::query_scalar(
+            "SELECT risk_score FROM ip_addresses WHERE account_id = $1 AND ip_address = $2::inet",
+        )
+        .bind(seen.id())
+        .bind(&ip_address)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(risk_score, Some(REJECTED_SCORE));
+
+        let own = IpAddressRepo::reputation(&pool, seen, &ip_address)
+            .await
+            .unwrap();
+        assert_eq!(own.account.reject_count, 3);
+        assert_eq!(own.global, IpHistoryRecord::default());
+
+        let shared = IpAddressRepo::reputation(&pool, other, &ip_address)
+            .await
+            .unwrap();
+        assert_eq!(shared.account, IpHistoryRecord::default());
+        assert_eq!(shared.global.transaction_count, 3);
+        assert!(IpHistory::from(shared.global).mostly_rejected());
+
+        AccountRepo::delete(&pool, seen.id()).await.unwrap();
+        AccountRepo::delete(&pool, other.id()).await.unwrap();
+    }
+}
@@ -0,0 +1,1131 @@
+//! Background jobs
+//!
+//! Work too long for a request, such as scoring a transaction submitted with `mode=async` or
+//! rescoring many transactions, is queued in `background_jobs` and run by a pool of `workers`
+//! tasks in each server. A job is claimed by
+//! one worker, which records its output in chunks as it goes, refreshing the job's heartbeat.
+//! A worker that dies mid-job therefore leaves the job resumable: once its heartbeat is stale,
+//! another worker picks it up from the last recorded chunk. Every claim counts an attempt, and
+//! a worker only records anything while the job is still at the attempt it claimed, so a
+//! worker that was merely slow stops once its job has been taken over.
+//!
+//! Jobs that produce a file, such as batch scoring jobs, write it in chunks to
+//! `background_job_output` alongside their progress, downloaded with
+//! `GET /v1/jobs/{job_id}/output` once the job has finished.
+//!
+//! Each [`JobKind`] has a [`RetryPolicy`]. Database and analytics errors put the job back in
+//! the queue with exponential backoff until the policy's attempts are used up; any other error
+//! fails the job with the error body the equivalent request would have received. Clients
+//! follow a job with `GET /v1/jobs/{job_id}`, or are told once it has finished: a `job.completed`
+//! or `job.failed` outbox event is recorded in the same database transaction as the job's
+//! outcome, delivered to webhooks and to the job's callback URL, if it was queued with one.
+//! Imports keep their own tables and workers.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool, types::Json};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult, TransactionService};
+use crate::{
+    api::ApiError,
+    config::JobsConfig,
+    database::{
+        Tenant,
+        repositories::{
+            AccountRepo, BackgroundJobRecord, BackgroundJobRepo, BatchScoreTargetRecord,
+            ClaimedBackgroundJobRecord, OutboxRepo, RuleRepo, TransactionRepo,
+        },
+    },
+    models::{
+        common::{Cursor, Link, Links},
+        job::{
+            BackgroundJob, BatchScoreFailure, BatchScoreFormat, BatchScoreJobRequest,
+            BatchScoreJobResult, BatchScoreRow, BatchScoreTarget, JobKind, JobStatus,
+            RescoreItemResult, RescoreJobRequest, RescoreJobResult, ScoreJobResult,
+        },
+        transaction::{Disposition, TransactionRequest},
+    },
+    outbox::{JOB_COMPLETED, JOB_FAILED},
+    scoring::RiskEngine,
+    utils::{backoff, csv::push_row},
+};
+
+/// Seconds without a heartbeat after which a running job is taken over
+const STALE_AFTER_SECS: f64 = 5.0 * 60.0;
+/// Transactions rescored between recordings of a rescore job's progress
+const RESCORE_CHUNK_SIZE: usize = 50;
+/// Transactions scored per chunk of a batch scoring job's output
+const BATCH_SCORE_CHUNK_SIZE: usize = 200;
+/// Failed transactions listed in a batch scoring job's result; all of them are in its output
+const MAX_REPORTED_FAILURES: usize = 100;
+/// Columns of a batch scoring job's CSV output
+const CSV_COLUMNS: [&str; 8] = [
+    "transaction_id",
+    "user_id",
+    "previous_risk_score",
+    "risk_score",
+    "risk_level",
+    "disposition",
+    "rule_codes",
+    "error",
+];
+
+/// How often, and how soon, a job that hit a transient error is tried again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts, counting the first, before the job is marked failed
+    pub max_attempts: i32,
+    /// Delay after the first failed attempt, doubled after each further one
+    pub base_delay: Duration,
+    /// Longest delay between attempts
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Policy jobs of `kind` are retried under
+    pub fn for_kind(kind: JobKind) -> Self {
+        match kind {
+            // Scored in one go while the client waits for the result, so retried soon
+            JobKind::Score => Self {
+                max_attempts: 5,
+                base_delay: Duration::from_secs(1),
+                max_delay: Duration::from_secs(5 * 60),
+            },
+            // Both record their work as they go, so retries resume cheaply
+            JobKind::Rescore | JobKind::BatchScore => Self {
+                max_attempts: 5,
+                base_delay: Duration::from_secs(10),
+                max_delay: Duration::from_secs(10 * 60),
+            },
+        }
+    }
+
+    /// Delay before the next attempt after the given number of failed attempts
+    pub fn delay(&self, attempt: i32) -> Duration {
+        backoff(attempt.max(1) as u32, self.base_delay, self.max_delay)
+    }
+}
+
+/// Build a background job response from its stored row
+pub fn background_job(record: BackgroundJobRecord) -> BackgroundJob {
+    BackgroundJob {
+        id: record.id,
+        kind: record.kind,
+        status: record.status,
+        attempts: record.attempts,
+        result: record.result,
+        error: record.error.map(|Json(error)| error),
+        callback_url: record.callback_url,
+        created_at: record.created_at,
+        started_at: record.started_at,
+        completed_at: record.completed_at,
+        links: Links {
+            self_link: Some(Link::new(format!("/v1/jobs/{}", record.id))),
+            ..Links::default()
+        },
+    }
+}
+
+/// Queues background jobs and reports on them
+#[derive(Debug, Clone)]
+pub struct JobService {
+    pool: PgPool,
+}
+
+impl JobService {
+    /// Create a new job service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Queue a job of `kind` working on `payload`, to be POSTed to `callback_url` once it has
+    /// finished
+    pub async fn enqueue(
+        &self,
+        tenant: Tenant,
+        kind: JobKind,
+        payload: &impl Serialize,
+        callback_url: Option<&str>,
+    ) -> ServiceResult<BackgroundJob> {
+        let payload = serde_json::to_value(payload)
+            .map_err(|e| ServiceError::Invalid(format!("Invalid job payload: {e}")))?;
+        let record =
+            BackgroundJobRepo::insert(&self.pool, tenant, kind, &payload, callback_url).await?;
+        Ok(background_job(record))
+    }
+
+    /// Fetch one of an account's background jobs
+    pub async fn get(&self, tenant: Tenant, job_id: Uuid) -> ServiceResult<BackgroundJob> {
+        BackgroundJobRepo::find_by_id(&self.pool, tenant, job_id)
+            .await?
+            .map(background_job)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Count the transactions or users a batch scoring job would go through, before its
+    /// limit
+    pub async fn count_batch_score_targets(
+        &self,
+        tenant: Tenant,
+        request: &BatchScoreJobRequest,
+    ) -> ServiceResult<usize> {
+        let count = match request.target {
+            BatchScoreTarget::Transactions => {
+                TransactionRepo::count_batch_score_transactions(
+                    &self.pool,
+                    tenant,
+                    request.created_after,
+                    request.created_before,
+                )
+                .await?
+            },
+            BatchScoreTarget::Users => {
+                TransactionRepo::count_batch_score_users(
+                    &self.pool,
+                    tenant,
+                    request.created_after,
+                    request.created_before,
+                    request.inactive_since,
+                )
+                .await?
+            },
+        };
+        Ok(usize::try_from(count).unwrap_or_default())
+    }
+
+    /// Output file of one of an account's batch scoring jobs, and its format
+    ///
+    /// Available once the job has finished; a failed job's file holds what it scored before
+    /// failing.
+    pub async fn output(
+        &self,
+        tenant: Tenant,
+        job_id: Uuid,
+    ) -> ServiceResult<(BatchScoreFormat, String)> {
+        let job = BackgroundJobRepo::find_by_id(&self.pool, tenant, job_id)
+            .await?
+            .filter(|job| job.kind == JobKind::BatchScore)
+            .ok_or(ServiceError::NotFound)?;
+        if !matches!(job.status, JobStatus::Completed | JobStatus::Failed) {
+            return Err(ServiceError::Conflict(
+                "Job has not finished; its output is available once it has".to_string(),
+            ));
+        }
+        let format = job
+            .result
+            .and_then(|result| serde_json::from_value::<BatchScoreJobResult>(result).ok())
+            .map(|result| result.format)
+            .unwrap_or_default();
+        let chunks = BackgroundJobRepo::output(&self.pool, tenant, job_id).await?;
+        Ok((format, chunks.concat()))
+    }
+}
+
+/// Runs claimed jobs, dispatching on their kind
+#[derive(Debug, Clone)]
+pub struct JobRunner {
+    pool: PgPool,
+    transactions: TransactionService,
+    engine: RiskEngine,
+}
+
+impl JobRunner {
+    /// Create a runner scoring and rescoring through `transactions`
+    pub fn new(pool: PgPool, transactions: TransactionService) -> Self {
+        Self {
+            pool,
+            transactions,
+            engine: RiskEngine::new(),
+        }
+    }
+
+    /// Run a job to the end, returning its final output, or `None` if another worker took
+    /// the job over meanwhile
+    pub async fn run(
+        &self,
+        job: &ClaimedBackgroundJobRecord,
+    ) -> ServiceResult<Option<serde_json::Value>> {
+        match job.kind {
+            JobKind::Score => self.score(job).await,
+            JobKind::Rescore => self.rescore(job).await,
+            JobKind::BatchScore => self.batch_score(job).await,
+        }
+    }
+
+    /// Score and store a transaction submitted with `mode=async`, as `POST /v1/transactions`
+    /// would
+    ///
+    /// The transaction is stored in one database transaction with the job's progress, so a
+    /// resumed job returns the transaction already stored rather than scoring it twice.
+    /// Requests the synchronous endpoint would have refused, such as one naming an unknown
+    /// `user_id`, fail the job with the same error body. The results do not appear on live
+    /// dashboard streams, which only see transactions scored by the serving process.
+    async fn score(
+        &self,
+        job: &ClaimedBackgroundJobRecord,
+    ) -> ServiceResult<Option<serde_json::Value>> {
+        if let Some(result) = job.result.clone() {
+            return Ok(Some(result));
+        }
+        let tenant = Tenant::trusted(job.account_id);
+        let request: TransactionRequest = serde_json::from_value(job.payload.clone())
+            .map_err(|e| ServiceError::Invalid(format!("Invalid scoring job: {e}")))?;
+
+        let (user, ip_location) = self.transactions.gather_signals(tenant, &request).await?;
+        let weights = RuleRepo::weights(&self.pool, tenant).await?;
+        let mut assessment = self.engine.assess_weighted(&request, &user, &weights);
+        assessment.disposition = if job.sandbox {
+            Disposition::Test
+        } else {
+            let policy = AccountRepo::disposition_policy(&self.pool, tenant).await?;
+            assessment.disposition_under(policy)
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let transaction = self
+            .transactions
+            .insert_transaction(&mut tx, tenant, &request, &assessment, &request.warnings())
+            .await?;
+        let result = ScoreJobResult {
+            transaction: transaction.clone().into(),
+        };
+        let result = serde_json::to_value(&result).unwrap_or_default();
+        // Dropping the database transaction discards the stored transaction
+        if !BackgroundJobRepo::record_progress(&mut *tx, job.id, job.attempts, &result).await? {
+            return Ok(None);
+        }
+        tx.commit().await?;
+
+        // Only once committed, as the user may have been created with the transaction
+        self.transactions
+            .record_location(
+                transaction.user_id,
+                ip_location.as_ref(),
+                transaction.event_time,
+            )
+            .await;
+        self.transactions
+            .block_breaching_entities(tenant, transaction.id, &assessment)
+            .await;
+        tracing::info!(
+            job_id = %job.id,
+            transaction_id = %transaction.id,
+            account_id = %job.account_id,
+            risk_score = transaction.risk_score,
+            "Queued transaction scored"
+        );
+        Ok(Some(result))
+    }
+
+    /// Rescore each named transaction under the account's current weights and policy
+    ///
+    /// A transaction that cannot be rescored is reported in its result item without affecting
+    /// the others, as `POST /v1/transactions/batch` reports failed transactions. Each chunk's
+    /// revisions are written in one database transaction with the job's progress, so a resumed
+    /// job never rescores a transaction twice.
+    async fn rescore(
+        &self,
+        job: &ClaimedBackgroundJobRecord,
+    ) -> ServiceResult<Option<serde_json::Value>> {
+        let tenant = Tenant::trusted(job.account_id);
+        let request: RescoreJobRequest = serde_json::from_value(job.payload.clone())
+            .map_err(|e| ServiceError::Invalid(format!("Invalid rescore job: {e}")))?;
+        let mut result: RescoreJobResult = job
+            .result
+            .clone()
+            .and_then(|result| serde_json::from_value(result).ok())
+            .unwrap_or_default();
+
+        let policy = AccountRepo::disposition_policy(&self.pool, tenant).await?;
+        let weights = RuleRepo::weights(&self.pool, tenant).await?;
+        let resumed_at = result.results.len();
+        for chunk in request
+            .transaction_ids
+            .get(resumed_at..)
+            .unwrap_or_default()
+            .chunks(RESCORE_CHUNK_SIZE)
+        {
+            let mut tx = self.pool.begin().await?;
+            for &transaction_id in chunk {
+                let rescored = self
+                    .transactions
+                    .rescore_on(&mut tx, tenant, transaction_id, |request, user| {
+                        let mut assessment = self.engine.assess_weighted(request, user, &weights);
+                        assessment.disposition = if job.sandbox {
+                            Disposition::Test
+                        } else {
+                            assessment.disposition_under(policy)
+                        };
+                        assessment
+                    })
+                    .await;
+                let item = match rescored {
+                    Ok(revision) => {
+                        result.succeeded += 1;
+                        RescoreItemResult {
+                            transaction_id,
+                            status: 201,
+                            revision: Some(revision),
+                            error: None,
+                        }
+                    },
+                    // The database transaction is aborted, so a retry rescores the whole chunk
+                    Err(e @ ServiceError::Database(_)) => return Err(e),
+                    Err(e) => {
+                        result.failed += 1;
+                        let (status, error) = ApiError::from(e).to_response();
+                        RescoreItemResult {
+                            transaction_id,
+                            status: status.as_u16(),
+                            revision: None,
+                            error: Some(error),
+                        }
+                    },
+                };
+                result.results.push(item);
+            }
+            let progress = serde_json::to_value(&result).unwrap_or_default();
+            // Dropping the database transaction discards the chunk's revisions
+            if !BackgroundJobRepo::record_progress(&mut *tx, job.id, job.attempts, &progress)
+                .await?
+            {
+                return Ok(None);
+            }
+            tx.commit().await?;
+        }
+
+        tracing::info!(
+            job_id = %job.id,
+            account_id = %job.account_id,
+            succeeded = result.succeeded,
+            failed = result.failed,
+            "Transactions rescored"
+        );
+        Ok(Some(serde_json::to_value(&result).unwrap_or_default()))
+    }
+
+    /// Score the requested transactions or users under the account's current weights and
+    /// policy, recording nothing but the job's output file
+    ///
+    /// Targets are gone through in creation order, one chunk of the file at a time, so a
+    /// resumed job continues after the last chunk recorded. A transaction that cannot be scored
+    /// is a line of the file with its error, without affecting the others.
+    async fn batch_score(
+        &self,
+        job: &ClaimedBackgroundJobRecord,
+    ) -> ServiceResult<Option<serde_json::Value>> {
+        let tenant = Tenant::trusted(job.account_id);
+        let request: BatchScoreJobRequest = serde_json::from_value(job.payload.clone())
+            .map_err(|e| ServiceError::Invalid(format!("Invalid batch scoring job: {e}")))?;
+        let limit = request.limit.unwrap_or_default();
+        let mut result = job
+            .result
+            .clone()
+            .and_then(|result| serde_json::from_value(result).ok())
+            .unwrap_or_else(|| BatchScoreJobResult {
+                total: limit,
+                format: request.format,
+                output: format!("/v1/jobs/{}/output", job.id),
+                ..BatchScoreJobResult::default()
+            });
+
+        let policy = AccountRepo::disposition_policy(&self.pool, tenant).await?;
+        let weights = RuleRepo::weights(&self.pool, tenant).await?;
+        while result.processed < limit {
+            let cursor = result.resume_after.as_deref().and_then(Cursor::decode);
+            let size = BATCH_SCORE_CHUNK_SIZE.min(limit - result.processed);
+            let targets = self
+                .batch_score_targets(tenant, &request, cursor, size)
+                .await?;
+            let Some(last) = targets.last() else {
+                break;
+            };
+            let next = Cursor {
+                created_at: last.created_at,
+                id: last.id,
+            };
+
+            let mut rows = Vec::with_capacity(targets.len());
+            for target in &targets {
+                let scored = self
+                    .transactions
+                    .assess_stored(tenant, target.transaction_id, |request, user| {
+                        let mut assessment = self.engine.assess_weighted(request, user, &weights);
+                        assessment.disposition = if job.sandbox {
+                            Disposition::Test
+                        } else {
+                            assessment.disposition_under(policy)
+                        };
+                        assessment
+                    })
+                    .await;
+                let row = match scored {
+                    Ok((source, assessment)) => {
+                        result.succeeded += 1;
+                        BatchScoreRow {
+                            transaction_id: target.transaction_id,
+                            user_id: source.user_id,
+                            previous_risk_score: Some(source.risk_score),
+                            risk_score: Some(assessment.risk_score),
+                            risk_level: Some(assessment.risk_level),
+                            disposition: Some(assessment.disposition),
+                            rule_codes: assessment.factors.into_iter().map(|f| f.code).collect(),
+                            error: None,
+                        }
+                    },
+                    // Nothing of the chunk is recorded yet, so a retry scores it again
+                    Err(e @ ServiceError::Database(_)) => return Err(e),
+                    Err(e) => {
+                        result.failed += 1;
+                        let (status, error) = ApiError::from(e).to_response();
+                        if result.failures.len() < MAX_REPORTED_FAILURES {
+                            result.failures.push(BatchScoreFailure {
+                                transaction_id: target.transaction_id,
+                                status: status.as_u16(),
+                                message: error.message.clone(),
+                            });
+                        }
+                        BatchScoreRow {
+                            transaction_id: target.transaction_id,
+                            user_id: None,
+                            previous_risk_score: None,
+                            risk_score: None,
+                            risk_level: None,
+                            disposition: None,
+                            rule_codes: Vec::new(),
+                            error: Some(error.message),
+                        }
+                    },
+                };
+                rows.push(row);
+            }
+
+            let content = match request.format {
+                BatchScoreFormat::Csv => batch_score_csv(&rows, result.chunks == 0),
+                BatchScoreFormat::Ndjson => batch_score_ndjson(&rows),
+            };
+            result.processed += rows.len();
+            result.resume_after = Some(next.encode());
+            result.chunks += 1;
+            let progress = serde_json::to_value(&result).unwrap_or_default();
+            let mut tx = self.pool.begin().await?;
+            if !BackgroundJobRepo::record_progress(&mut *tx, job.id, job.attempts, &progress)
+                .await?
+            {
+                return Ok(None);
+            }
+            BackgroundJobRepo::append_output(&mut *tx, job.id, result.chunks - 1, &content).await?;
+            tx.commit().await?;
+        }
+
+        tracing::info!(
+            job_id = %job.id,
+            account_id = %job.account_id,
+            succeeded = result.succeeded,
+            failed = result.failed,
+            "Batch scoring job finished"
+        );
+        Ok(Some(serde_json::to_value(&result).unwrap_or_default()))
+    }
+
+    /// Next targets of a batch scoring job after `cursor`
+    async fn batch_score_targets(
+        &self,
+        tenant: Tenant,
+        request: &BatchScoreJobRequest,
+        cursor: Option<Cursor>,
+        limit: usize,
+    ) -> sqlx::Result<Vec<BatchScoreTargetRecord>> {
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        match request.target {
+            BatchScoreTarget::Transactions => {
+                TransactionRepo::batch_score_transactions(
+                    &self.pool,
+                    tenant,
+                    request.created_after,
+                    request.created_before,
+                    cursor,
+                    limit,
+                )
+                .await
+            },
+            BatchScoreTarget::Users => {
+                TransactionRepo::batch_score_users(
+                    &self.pool,
+                    tenant,
+                    request.created_after,
+                    request.created_before,
+                    request.inactive_since,
+                    cursor,
+                    limit,
+                )
+                .await
+            },
+        }
+    }
+}
+
+/// Rows of a batch scoring job as CSV, led by a header line on the first chunk
+fn batch_score_csv(rows: &[BatchScoreRow], header: bool) -> String {
+    let mut csv = String::new();
+    if header {
+        csv.push_str(&CSV_COLUMNS.join(","));
+        csv.push('\n');
+    }
+    for row in rows {
+        let fields = [
+            row.transaction_id.to_string(),
+            row.user_id.map(|id| id.to_string()).unwrap_or_default(),
+            row.previous_risk_score
+                .map(|score| score.to_string())
+                .unwrap_or_default(),
+            row.risk_score
+                .map(|score| score.to_string())
+                .unwrap_or_default(),
+            row.risk_level
+                .as_ref()
+                .map(variant_name)
+                .unwrap_or_default(),
+            row.disposition
+                .as_ref()
+                .map(variant_name)
+                .unwrap_or_default(),
+            row.rule_codes.join(" "),
+            row.error.clone().unwrap_or_default(),
+        ];
+        push_row(&mut csv, fields);
+    }
+    csv
+}
+
+/// Rows of a batch scoring job as one JSON object per line
+fn batch_score_ndjson(rows: &[BatchScoreRow]) -> String {
+    rows.iter()
+        .filter_map(|row| serde_json::to_string(row).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+/// Serialized name of an enum variant
+fn variant_name(value: &impl Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Spawn `workers` background tasks that keep running queued jobs
+pub fn spawn_job_workers(
+    pool: PgPool,
+    config: JobsConfig,
+    runner: JobRunner,
+) -> Vec<JoinHandle<()>> {
+    (0..config.workers)
+        .map(|_| {
+            let (pool, runner) = (pool.clone(), runner.clone());
+            let idle = Duration::from_millis(config.poll_interval_ms);
+            tokio::spawn(async move {
+                loop {
+                    match process_next_job(&pool, &runner).await {
+                        // Keep going while there is a backlog
+                        Ok(true) => continue,
+                        Ok(false) => {},
+                        Err(e) => tracing::error!(error = %e, "Background job processing failed"),
+                    }
+                    tokio::time::sleep(idle).await;
+                }
+            })
+        })
+        .collect()
+}
+
+/// Run the oldest due job, or resume an abandoned one, returning `false` if there was none
+///
+/// A job abandoned more often than its [`RetryPolicy`] allows is failed without running it
+/// again.
+pub async fn process_next_job(pool: &PgPool, runner: &JobRunner) -> sqlx::Result<bool> {
+    let Some(job) = BackgroundJobRepo::claim_next(pool, STALE_AFTER_SECS).await? else {
+        return Ok(false);
+    };
+    let policy = RetryPolicy::for_kind(job.kind);
+    if job.attempts > policy.max_attempts {
+        tracing::warn!(job_id = %job.id, attempts = job.attempts, "Background job abandoned");
+        let (_, error) = ApiError::Internal(anyhow::anyhow!("job abandoned")).to_response();
+        let mut tx = pool.begin().await?;
+        let failed = BackgroundJobRepo::fail(&mut *tx, job.id, job.attempts, &error).await?;
+        announce(&mut tx, failed, JOB_FAILED).await?;
+        tx.commit().await?;
+        return Ok(true);
+    }
+
+    let held = match runner.run(&job).await {
+        Ok(Some(result)) => {
+            let mut tx = pool.begin().await?;
+            let completed =
+                BackgroundJobRepo::complete(&mut *tx, job.id, job.attempts, &result).await?;
+            let completed = announce(&mut tx, completed, JOB_COMPLETED).await?;
+            tx.commit().await?;
+            if completed {
+                tracing::info!(
+                    job_id = %job.id,
+                    account_id = %job.account_id,
+                    kind = ?job.kind,
+                    "Background job completed"
+                );
+            }
+            completed
+        },
+        Ok(None) => false,
+        Err(e @ (ServiceError::Database(_) | ServiceError::Analytics(_)))
+            if job.attempts < policy.max_attempts =>
+        {
+            tracing::warn!(
+                job_id = %job.id,
+                attempts = job.attempts,
+                error = %e,
+                "Background job failed; will retry"
+            );
+            let retry_at = Utc::now() + policy.delay(job.attempts);
+            BackgroundJobRepo::retry_later(pool, job.id, job.attempts, &e.to_string(), retry_at)
+                .await?
+        },
+        Err(e) => {
+            tracing::warn!(job_id = %job.id, error = %e, "Background job failed");
+            let (_, error) = ApiError::from(e).to_response();
+            let mut tx = pool.begin().await?;
+            let failed = BackgroundJobRepo::fail(&mut *tx, job.id, job.attempts, &error).await?;
+            let failed = announce(&mut tx, failed, JOB_FAILED).await?;
+            tx.commit().await?;
+            failed
+        },
+    };
+    if !held {
+        tracing::warn!(
+            job_id = %job.id,
+            attempts = job.attempts,
+            "Background job was taken over by another worker; dropped this attempt"
+        );
+    }
+    Ok(true)
+}
+
+/// Record the `event_type` outbox event of a job that has just finished on `conn`, returning
+/// `false` if there is none because the job was no longer held by the worker
+async fn announce(
+    conn: &mut PgConnection,
+    finished: Option<BackgroundJobRecord>,
+    event_type: &str,
+) -> sqlx::Result<bool> {
+    let Some(record) = finished else {
+        return Ok(false);
+    };
+    let account_id = record.account_id;
+    let job = background_job(record);
+    let payload = serde_json::to_value(&job).unwrap_or_default();
+    OutboxRepo::insert(&mut *conn, account_id, event_type, job.id, payload).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        database::repositories::{ScoringRevisionRepo, UserRepo},
+        features::FeatureStore,
+        models::account::SubscriptionTier,
+        services::{
+            EmailIntelService, IpIntelService, ScreeningService, transaction_service::SignalSources,
+        },
+        sessions::SessionStore,
+        test_support::{create_account, test_pool},
+    };
+
+    #[tokio::test]
+    async fn test_score_jobs_complete_or_fail_with_an_outbox_event() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction)
+                .with_signal_sources(SignalSources {
+                    sessions: SessionStore::memory(),
+                    features: FeatureStore::new(pool.clone()),
+                    ip_intel: IpIntelService::new(pool.clone(), None, &Config::default().ip_intel),
+                    email_intel: EmailIntelService::new(&Config::default().email_intel),
+                    screening: ScreeningService::new(&Config::default().screening),
+                });
+
+        let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let jobs = JobService::new(pool.clone());
+        let callback_url = "https://merchant.example.com/fusegu/callback";
+        let good = jobs
+            .enqueue(tenant, JobKind::Score, &request, Some(callback_url))
+            .await
+            .unwrap();
+        let unknown_user = TransactionRequest {
+            user_id: Some(Uuid::new_v4()),
+            ..request
+        };
+        let bad = jobs
+            .enqueue(tenant, JobKind::Score, &unknown_user, None)
+            .await
+            .unwrap();
+        assert_eq!(good.status, JobStatus::Pending);
+
+        let runner = JobRunner::new(pool.clone(), transactions);
+        // Other tests' jobs may be queued too, so run until both of these have finished
+        for _ in 0..100 {
+            let good = jobs.get(tenant, good.id).await.unwrap();
+            let bad = jobs.get(tenant, bad.id).await.unwrap();
+            if good.completed_at.is_some() && bad.completed_at.is_some() {
+                break;
+            }
+            process_next_job(&pool, &runner).await.unwrap();
+        }
+
+        let good = jobs.get(tenant, good.id).await.unwrap();
+        assert_eq!(good.status, JobStatus::Completed);
+        let result: ScoreJobResult = serde_json::from_value(good.result.unwrap()).unwrap();
+        assert!(
+            TransactionRepo::find_by_id(&pool, tenant, result.transaction.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        let bad = jobs.get(tenant, bad.id).await.unwrap();
+        assert_eq!(bad.status, JobStatus::Failed);
+        assert!(bad.result.is_none());
+        assert!(bad.error.is_some());
+
+        // The request is not kept once the job has finished
+        let payload: serde_json::Value =
+            sqlx::query_scalar("SELECT payload FROM background_jobs WHERE id = $1")
+                .bind(good.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(payload, serde_json::json!({}));
+
+        let (event_type, payload): (String, serde_json::Value) =
+            sqlx::query_as("SELECT event_type, payload FROM outbox_events WHERE aggregate_id = $1")
+                .bind(good.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(event_type, JOB_COMPLETED);
+        let delivered: BackgroundJob = serde_json::from_value(payload).unwrap();
+        assert_eq!(delivered.status, JobStatus::Completed);
+        assert_eq!(delivered.callback_url.as_deref(), Some(callback_url));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rescore_jobs_report_each_transaction() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+
+        let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        let assessment = RiskEngine::new().assess(&request, &user);
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+
+        let jobs = JobService::new(pool.clone());
+        let unknown = Uuid::new_v4();
+        let job = jobs
+            .enqueue(
+                tenant,
+                JobKind::Rescore,
+                &RescoreJobRequest {
+                    transaction_ids: vec![stored.id, unknown],
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Pending);
+
+        let runner = JobRunner::new(pool.clone(), transactions);
+        // Other tests' jobs may be queued too, so run until this one has finished
+        for _ in 0..100 {
+            if jobs.get(tenant, job.id).await.unwrap().status == JobStatus::Completed {
+                break;
+            }
+            process_next_job(&pool, &runner).await.unwrap();
+        }
+
+        let job = jobs.get(tenant, job.id).await.unwrap();
+        assert_eq!(job.status, JobStatus::Completed);
+        assert_eq!(job.attempts, 1);
+        let result: RescoreJobResult = serde_json::from_value(job.result.unwrap()).unwrap();
+        assert_eq!((result.succeeded, result.failed), (1, 1));
+        assert_eq!(result.results[0].transaction_id, stored.id);
+        assert_eq!(result.results[0].revision.as_ref().unwrap().revision, 2);
+        assert_eq!(result.results[1].transaction_id, unknown);
+        assert_eq!(result.results[1].status, 404);
+
+        assert!(matches!(
+            jobs.get(Tenant::trusted(Uuid::new_v4()), job.id).await,
+            Err(ServiceError::NotFound)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_score_jobs_write_a_file_without_recording_scores() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+
+        let mut stored = Vec::new();
+        for ip_address in ["198.51.100.1", "198.51.100.2"] {
+            let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+                "device": { "ip_address": ip_address, "user_agent": "Mozilla/5.0" },
+                "event": { "type": "purchase" }
+            }))
+            .unwrap();
+            let user = transactions.user_signals(tenant, &request).await.unwrap();
+            let assessment = RiskEngine::new().assess(&request, &user);
+            let transaction = transactions
+                .store_transaction(tenant, &request, &assessment, &[])
+                .await
+                .unwrap();
+            stored.push(transaction.id);
+        }
+
+        let jobs = JobService::new(pool.clone());
+        let request = BatchScoreJobRequest {
+            target: BatchScoreTarget::Transactions,
+            created_after: None,
+            created_before: Some(Utc::now()),
+            inactive_since: None,
+            limit: None,
+            format: BatchScoreFormat::Csv,
+        };
+        assert_eq!(
+            jobs.count_batch_score_targets(tenant, &request)
+                .await
+                .unwrap(),
+            2
+        );
+        let job = jobs
+            .enqueue(
+                tenant,
+                JobKind::BatchScore,
+                &BatchScoreJobRequest {
+                    limit: Some(2),
+                    ..request
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            jobs.output(tenant, job.id).await,
+            Err(ServiceError::Conflict(_))
+        ));
+
+        let runner = JobRunner::new(pool.clone(), transactions.clone());
+        for _ in 0..100 {
+            if jobs.get(tenant, job.id).await.unwrap().status == JobStatus::Completed {
+                break;
+            }
+            process_next_job(&pool, &runner).await.unwrap();
+        }
+
+        let job = jobs.get(tenant, job.id).await.unwrap();
+        assert_eq!(job.status, JobStatus::Completed);
+        let result: BatchScoreJobResult = serde_json::from_value(job.result.unwrap()).unwrap();
+        assert_eq!((result.total, result.processed), (2, 2));
+        assert_eq!((result.succeeded, result.failed), (2, 0));
+        assert_eq!(result.output, format!("/v1/jobs/{}/output", job.id));
+
+        let (format, output) = jobs.output(tenant, job.id).await.unwrap();
+        assert_eq!(format, BatchScoreFormat::Csv);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], CSV_COLUMNS.join(","));
+        assert_eq!(lines.len(), 3);
+        for id in &stored {
+            assert!(output.contains(&id.to_string()));
+        }
+
+        // Nothing was recorded for the transactions scored
+        let scoring = ScoringRevisionRepo::find_source(&pool, tenant, stored[0])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(scoring.revision, 1);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_score_jobs_skip_deleted_users() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+
+        let mut stored = Vec::new();
+        for user_id in ["user_kept", "user_deleted"] {
+            let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+                "account": { "user_id": user_id },
+                "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+                "event": { "type": "purchase" }
+            }))
+            .unwrap();
+            let user = transactions.user_signals(tenant, &request).await.unwrap();
+            let assessment = RiskEngine::new().assess(&request, &user);
+            let transaction = transactions
+                .store_transaction(tenant, &request, &assessment, &[])
+                .await
+                .unwrap();
+            stored.push(transaction);
+        }
+        assert!(
+            UserRepo::soft_delete(&pool, tenant, stored[1].user_id.unwrap())
+                .await
+                .unwrap()
+        );
+
+        let jobs = JobService::new(pool.clone());
+        let request = BatchScoreJobRequest {
+            target: BatchScoreTarget::Users,
+            created_after: None,
+            created_before: Some(Utc::now()),
+            inactive_since: None,
+            limit: None,
+            format: BatchScoreFormat::Ndjson,
+        };
+        assert_eq!(
+            jobs.count_batch_score_targets(tenant, &request)
+                .await
+                .unwrap(),
+            1
+        );
+        let job = jobs
+            .enqueue(
+                tenant,
+                JobKind::BatchScore,
+                &BatchScoreJobRequest {
+                    limit: Some(1),
+                    ..request
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let runner = JobRunner::new(pool.clone(), transactions);
+        for _ in 0..100 {
+            if jobs.get(tenant, job.id).await.unwrap().status == JobStatus::Completed {
+                break;
+            }
+            process_next_job(&pool, &runner).await.unwrap();
+        }
+
+        let (_, output) = jobs.output(tenant, job.id).await.unwrap();
+        assert_eq!(output.lines().count(), 1);
+        assert!(output.contains(&stored[0].id.to_string()));
+        assert!(!output.contains(&stored[1].id.to_string()));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_updates_from_a_superseded_attempt_are_refused() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let job = BackgroundJobRepo::insert(
+            &pool,
+            tenant,
+            JobKind::Rescore,
+            &serde_json::json!({ "transaction_ids": [] }),
+            None,
+        )
+        .await
+        .unwrap();
+
+        // The first worker's heartbeat went stale and a second worker claimed the job
+        sqlx::query("UPDATE background_jobs SET status = 'running', attempts = 2 WHERE id = $1")
+            .bind(job.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let result = serde_json::json!({ "succeeded": 0 });
+        let (_, error) = ApiError::Internal(anyhow::anyhow!("stale worker")).to_response();
+        assert!(
+            !BackgroundJobRepo::record_progress(&pool, job.id, 1, &result)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !BackgroundJobRepo::retry_later(&pool, job.id, 1, "timeout", Utc::now())
+                .await
+                .unwrap()
+        );
+        assert!(
+            BackgroundJobRepo::fail(&pool, job.id, 1, &error)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            BackgroundJobRepo::complete(&pool, job.id, 1, &result)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let completed = BackgroundJobRepo::complete(&pool, job.id, 2, &result)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(completed.status, JobStatus::Completed);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[test]
+    fn test_retry_policy_backs_off_and_is_capped() {
+        let policy = RetryPolicy::for_kind(JobKind::Rescore);
+        assert_eq!(policy.delay(1), policy.base_delay);
+        assert_eq!(policy.delay(3), policy.base_delay * 4);
+        assert_eq!(policy.delay(i32::MAX), policy.max_delay);
+    }
+}
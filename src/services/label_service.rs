@@ -0,0 +1,289 @@
+//! Ground-truth transaction labels for model training
+//!
+//! A transaction is labeled when an outcome is recorded for it, from the merchant's report,
+//! a processor's chargeback, or a reviewer's decision, and relabeled when the outcome changes.
+//! Labels set by hand are kept whatever is reported later. Labeled datasets are exported with
+//! the scoring of each transaction, to be joined with the feature export on the transaction ID.
+
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    database::{
+        Tenant,
+        repositories::{LabelRecord, LabelRepo, LabeledTransactionRecord},
+    },
+    models::label::{
+        LabelExportFormat, LabelExportQuery, LabelRequest, LabeledTransaction, ListLabelsQuery,
+        TransactionLabel,
+    },
+    utils::csv::push_row,
+};
+
+/// Columns of a CSV export, in order
+const CSV_COLUMNS: [&str; 12] = [
+    "transaction_id",
+    "external_transaction_id",
+    "event_type",
+    "event_time",
+    "scored_at",
+    "risk_score",
+    "risk_level",
+    "disposition",
+    "rule_codes",
+    "label",
+    "label_source",
+    "labeled_at",
+];
+
+impl From<LabelRecord> for TransactionLabel {
+    fn from(record: LabelRecord) -> Self {
+        TransactionLabel {
+            transaction_id: record.transaction_id,
+            label: record.label,
+            source: record.source,
+            notes: record.notes,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            links: TransactionLabel::links(record.transaction_id),
+        }
+    }
+}
+
+impl From<LabeledTransactionRecord> for LabeledTransaction {
+    fn from(record: LabeledTransactionRecord) -> Self {
+        LabeledTransaction {
+            transaction_id: record.transaction_id,
+            external_transaction_id: record.external_transaction_id,
+            event_type: record.event_type,
+            event_time: record.event_time,
+            scored_at: record.scored_at,
+            risk_score: record.risk_score,
+            risk_level: record.risk_level,
+            disposition: record.disposition,
+            rule_codes: record.rule_codes,
+            label: record.label,
+            label_source: record.source,
+            labeled_at: record.updated_at,
+        }
+    }
+}
+
+/// Transaction label management
+#[derive(Debug, Clone)]
+pub struct LabelService {
+    pool: PgPool,
+    read_pool: PgPool,
+}
+
+impl LabelService {
+    /// Create a new label service, reading listings and exports from `read_pool`
+    pub fn new(pool: PgPool, read_pool: PgPool) -> Self {
+        Self { pool, read_pool }
+    }
+
+    /// A page of the account's labels, with the number matching the filter
+    pub async fn list_labels(
+        &self,
+        tenant: Tenant,
+        query: &ListLabelsQuery,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<TransactionLabel>, i64)> {
+        let records = LabelRepo::list(&self.read_pool, tenant, query, limit, offset).await?;
+        let total = LabelRepo::count(&self.read_pool, tenant, query).await?;
+        Ok((records.into_iter().map(Into::into).collect(), total))
+    }
+
+    /// The label of one of the account's transactions
+    pub async fn get_label(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> ServiceResult<TransactionLabel> {
+        LabelRepo::find(&self.pool, tenant, transaction_id)
+            .await?
+            .map(Into::into)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Label one of the account's transactions by hand
+    pub async fn set_label(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        request: &LabelRequest,
+    ) -> ServiceResult<TransactionLabel> {
+        request.validate().map_err(ServiceError::Invalid)?;
+        let record = LabelRepo::set_manual(
+            &self.pool,
+            tenant,
+            transaction_id,
+            request.label,
+            request.notes.as_deref(),
+        )
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+        tracing::info!(
+            account_id = %tenant,
+            transaction_id = %transaction_id,
+            label = request.label.as_str(),
+            "Transaction labeled by hand"
+        );
+        Ok(record.into())
+    }
+
+    /// The account's labeled transactions matching the filter, in the requested form
+    pub async fn export(&self, tenant: Tenant, query: &LabelExportQuery) -> ServiceResult<String> {
+        let rows: Vec<LabeledTransaction> = LabelRepo::export(&self.read_pool, tenant, query)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        Ok(match query.format {
+            LabelExportFormat::Csv => export_csv(&rows),
+            LabelExportFormat::Ndjson => export_ndjson(&rows),
+        })
+    }
+}
+
+/// Labeled transactions as CSV with a header line
+fn export_csv(rows: &[LabeledTransaction]) -> String {
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push('\n');
+    for row in rows {
+        let fields = [
+            row.transaction_id.to_string(),
+            row.external_transaction_id.clone().unwrap_or_default(),
+            variant_name(&row.event_type),
+            row.event_time.to_rfc3339(),
+            row.scored_at.to_rfc3339(),
+            row.risk_score.to_string(),
+            variant_name(&row.risk_level),
+            variant_name(&row.disposition),
+            row.rule_codes.join(" "),
+            row.label.as_str().to_string(),
+            row.label_source.as_str().to_string(),
+            row.labeled_at.to_rfc3339(),
+        ];
+        push_row(&mut csv, fields);
+    }
+    csv
+}
+
+/// Serialized name of an enum variant
+fn variant_name(value: &impl Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Labeled transactions as one JSON object per line
+fn export_ndjson(rows: &[LabeledTransaction]) -> String {
+    rows.iter()
+        .filter_map(|row| serde_json::to_string(row).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        Config,
+        database::repositories::AccountRepo,
+        models::{
+            account::SubscriptionTier,
+            label::{Label, LabelSource},
+            outcome::OutcomeReportRequest,
+            transaction::{ReportTag, TransactionRequest},
+        },
+        scoring::RiskEngine,
+        services::{OutcomeService, TransactionService},
+        test_support::{create_account, test_pool},
+    };
+
+    #[tokio::test]
+    async fn test_reported_outcomes_label_transactions_until_labeled_by_hand() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let outcomes = OutcomeService::new(pool.clone());
+        let labels = LabelService::new(pool.clone(), pool.clone());
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "198.51.100.1" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let assessment = RiskEngine::new().assess(&request, &Default::default());
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        let report = |tag, chargeback_code: Option<&str>| OutcomeReportRequest {
+            transaction_id: stored.id,
+            tag,
+            chargeback_code: chargeback_code.map(str::to_string),
+            notes: None,
+            occurred_at: None,
+        };
+
+        // A chargeback for goods not received is friendly fraud
+        outcomes
+            .report(tenant, &report(ReportTag::Chargeback, Some("13.1")))
+            .await
+            .unwrap();
+        let label = labels.get_label(tenant, stored.id).await.unwrap();
+        assert_eq!(label.label, Label::FriendlyFraud);
+        assert_eq!(label.source, LabelSource::Report);
+
+        // A manual label sticks when a different outcome is reported later
+        let manual = LabelRequest {
+            label: Label::Fraud,
+            notes: Some("Same card as a confirmed takeover".to_string()),
+        };
+        labels.set_label(tenant, stored.id, &manual).await.unwrap();
+        outcomes
+            .report(tenant, &report(ReportTag::NotFraud, None))
+            .await
+            .unwrap();
+        let label = labels.get_label(tenant, stored.id).await.unwrap();
+        assert_eq!(label.label, Label::Fraud);
+        assert_eq!(label.source, LabelSource::Manual);
+
+        let (listed, total) = labels
+            .list_labels(tenant, &ListLabelsQuery::default(), 20, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(listed[0].transaction_id, stored.id);
+
+        let csv = labels
+            .export(tenant, &LabelExportQuery::default())
+            .await
+            .unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with(&stored.id.to_string()));
+        assert!(lines[1].contains(",fraud,manual,"));
+
+        // Labels belong to their account
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(matches!(
+            labels.set_label(other, stored.id, &manual).await,
+            Err(ServiceError::NotFound)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
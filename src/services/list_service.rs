@@ -0,0 +1,1536 @@
+//! Lists of entities an account treats specially when scoring
+//!
+//! Networks on an account's ASN list are blocked outright or score higher, so known
+//! bulletproof hosters and similar networks can be dealt with without waiting for their
+//! transactions to build a bad history. Countries on an account's country list are blocked,
+//! sent to review, or score higher wherever they turn up in a transaction: its billing,
+//! shipping, or card country, or the country of its IP address. An account's BIN table
+//! describes the ranges of cards it sees, taking precedence over the starter table bundled with
+//! the service; see [`super::bin_intel`].
+//!
+//! Beyond those, each account keeps a blocklist, an allowlist, and a watchlist of email
+//! addresses and domains, IP addresses and ranges, devices, cards, users, and countries, each
+//! entry optionally expiring. The entries naming an entity are cached in Redis, when there is
+//! one, so scoring does not go to the database for the entities of every transaction. Entries
+//! can be imported in bulk, as when migrating from another fraud vendor, and are then written
+//! by the background worker in [`crate::imports`]; a list can be exported in the same forms.
+//! Members of an organization sharing blocklists see each other's blocklist entries when
+//! scoring and listing, each naming the account that added it, but manage only their own.
+//!
+//! Accounts can have IP addresses, devices, and cards whose transactions keep breaching
+//! critical velocity rules blocklisted automatically, for as long as they choose. Such blocks
+//! are ordinary blocklist entries that expire, recorded for audit in `auto_blocks`; they never
+//! replace an entry already in force.
+
+use std::fmt;
+
+use chrono::{Duration, Utc};
+use redis::{RedisResult, aio::ConnectionManager};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult, bin_intel};
+use crate::{
+    config::AutoBlockConfig,
+    database::{
+        Tenant,
+        repositories::{
+            AccountRepo, AsnListEntryRecord, AutoBlockRecord, AutoBlockRepo, BinRangeRecord,
+            CountryListEntryRecord, ListEntryRecord, ListImportRecord, ListImportRepo, ListRepo,
+            NewAutoBlock, NewListEntry, OrganizationRepo,
+        },
+    },
+    models::list::{
+        AsnListEntries, AsnListEntry, AsnListEntryRequest, AutoBlock, BinRange, BinRangeImport,
+        CountryListEntries, CountryListEntry, CountryListEntryRequest, ListEntityType, ListEntry,
+        ListEntryRequest, ListExportFormat, ListExportQuery, ListImport, ListType,
+    },
+    scoring::RiskFactor,
+    utils::csv::push_row,
+};
+
+/// Critical velocity factors, each with the kinds of entity of a transaction it implicates
+const AUTO_BLOCK_FACTORS: &[(&str, &[ListEntityType])] = &[
+    (
+        "CARD_TESTING",
+        &[
+            ListEntityType::Ip,
+            ListEntityType::Device,
+            ListEntityType::CardHash,
+        ],
+    ),
+    ("IP_VELOCITY", &[ListEntityType::Ip]),
+    ("IP_MANY_CARDS", &[ListEntityType::Ip]),
+];
+
+/// Columns of a list in CSV form, as exported
+const CSV_COLUMNS: [&str; 4] = ["entity_type", "value", "reason", "expires_at"];
+
+/// Ranges of a BIN table import written per statement
+const BIN_IMPORT_CHUNK: usize = 1000;
+
+/// How long the list entries naming an entity stay cached
+const LIST_CACHE_TTL_SECONDS: u64 = 300;
+
+impl From<AsnListEntryRecord> for AsnListEntry {
+    fn from(record: AsnListEntryRecord) -> Self {
+        AsnListEntry {
+            asn: record.asn,
+            action: record.action,
+            score: record.score,
+            reason: record.reason,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+impl From<CountryListEntryRecord> for CountryListEntry {
+    fn from(record: CountryListEntryRecord) -> Self {
+        CountryListEntry {
+            country: record.country,
+            action: record.action,
+            score: record.score,
+            reason: record.reason,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+impl From<ListEntryRecord> for ListEntry {
+    fn from(record: ListEntryRecord) -> Self {
+        ListEntry {
+            id: record.id,
+            list_type: record.list_type,
+            entity_type: record.entity_type,
+            value: record.value,
+            reason: record.reason,
+            expires_at: record.expires_at,
+            added_by: record.added_by,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+impl From<ListImportRecord> for ListImport {
+    fn from(record: ListImportRecord) -> Self {
+        ListImport {
+            id: record.id,
+            list_type: record.list_type,
+            status: record.status,
+            total_entries: record.total_entries,
+            processed_entries: record.processed_entries,
+            created_entries: record.created_entries,
+            updated_entries: record.updated_entries,
+            failed_entries: record.failed_entries,
+            errors: record.errors.0,
+            created_at: record.created_at,
+            started_at: record.started_at,
+            completed_at: record.completed_at,
+            links: ListImport::links(record.id),
+        }
+    }
+}
+
+impl From<AutoBlockRecord> for AutoBlock {
+    fn from(record: AutoBlockRecord) -> Self {
+        AutoBlock {
+            id: record.id,
+            list_entry_id: record.list_entry_id,
+            entity_type: record.entity_type,
+            value: record.value,
+            factor_codes: record.factor_codes,
+            breaches: record.breaches,
+            transaction_id: record.transaction_id,
+            expires_at: record.expires_at,
+            created_at: record.created_at,
+        }
+    }
+}
+
+impl From<BinRangeRecord> for BinRange {
+    fn from(record: BinRangeRecord) -> Self {
+        BinRange {
+            prefix: record.prefix,
+            brand: record.brand,
+            card_type: record.card_type,
+            country: record.country,
+            bank_name: record.bank_name,
+            is_prepaid: record.is_prepaid,
+            is_business: record.is_business,
+            is_virtual: record.is_virtual,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+/// Redis key caching the account's entries naming an entity
+fn cache_key(tenant: Tenant, entity_type: ListEntityType, value: &str) -> String {
+    format!("fusegu:lists:{tenant}:{}:{value}", entity_type.name())
+}
+
+/// List management backed by PostgreSQL
+#[derive(Clone)]
+pub struct ListService {
+    pool: PgPool,
+    cache: Option<ConnectionManager>,
+    auto_block: Option<AutoBlockConfig>,
+}
+
+impl ListService {
+    /// Create a list service backed by the given pool, without automatic blocks
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            cache: None,
+            auto_block: None,
+        }
+    }
+
+    /// Cache the list entries naming each entity in `redis`, when there is one
+    pub fn with_cache(mut self, redis: Option<ConnectionManager>) -> Self {
+        self.cache = redis;
+        self
+    }
+
+    /// Block entities automatically for accounts that enable it, once they breach as often as
+    /// `config` says
+    pub fn with_auto_block(mut self, config: AutoBlockConfig) -> Self {
+        self.auto_block = Some(config);
+        self
+    }
+
+    /// Every network on the account's ASN list
+    pub async fn asn_entries(&self, tenant: Tenant) -> ServiceResult<AsnListEntries> {
+        let entries = ListRepo::asn_entries(&self.pool, tenant).await?;
+        Ok(AsnListEntries {
+            entries: entries.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    /// List a network, replacing the account's existing entry for it
+    pub async fn set_asn_entry(
+        &self,
+        tenant: Tenant,
+        request: &AsnListEntryRequest,
+    ) -> ServiceResult<AsnListEntry> {
+        request.validate().map_err(ServiceError::Invalid)?;
+        let entry = ListRepo::upsert_asn_entry(&self.pool, tenant, request).await?;
+        tracing::info!(account_id = %tenant, asn = entry.asn, action = ?entry.action, "ASN listed");
+        Ok(entry.into())
+    }
+
+    /// Remove a network from the account's ASN list
+    pub async fn delete_asn_entry(&self, tenant: Tenant, asn: i64) -> ServiceResult<()> {
+        if !ListRepo::delete_asn_entry(&self.pool, tenant, asn).await? {
+            return Err(ServiceError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Every country on the account's country list
+    pub async fn country_entries(&self, tenant: Tenant) -> ServiceResult<CountryListEntries> {
+        let entries = ListRepo::country_entries(&self.pool, tenant).await?;
+        Ok(CountryListEntries {
+            entries: entries.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    /// List a country, replacing the account's existing entry for it
+    pub async fn set_country_entry(
+        &self,
+        tenant: Tenant,
+        request: &CountryListEntryRequest,
+    ) -> ServiceResult<CountryListEntry> {
+        request.validate().map_err(ServiceError::Invalid)?;
+        let entry = ListRepo::upsert_country_entry(&self.pool, tenant, request).await?;
+        tracing::info!(
+            account_id = %tenant,
+            country = %entry.country,
+            action = ?entry.action,
+            "Country listed"
+        );
+        Ok(entry.into())
+    }
+
+    /// Remove a country from the account's country list
+    pub async fn delete_country_entry(&self, tenant: Tenant, country: &str) -> ServiceResult<()> {
+        if !ListRepo::delete_country_entry(&self.pool, tenant, country).await? {
+            return Err(ServiceError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// A page of the entries of one of the account's lists still in force, with those of the
+    /// blocklist its organization shares, optionally only those naming `entity_type`, with
+    /// the number of them
+    pub async fn entries(
+        &self,
+        tenant: Tenant,
+        list_type: ListType,
+        entity_type: Option<ListEntityType>,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<ListEntry>, i64)> {
+        let entries =
+            ListRepo::list_entries(&self.pool, tenant, list_type, entity_type, limit, offset)
+                .await?;
+        let total =
+            ListRepo::count_list_entries(&self.pool, tenant, list_type, entity_type).await?;
+        Ok((entries.into_iter().map(Into::into).collect(), total))
+    }
+
+    /// Put an entity on one of the account's lists, replacing its existing entry there
+    pub async fn set_entry(
+        &self,
+        tenant: Tenant,
+        list_type: ListType,
+        request: &ListEntryRequest,
+    ) -> ServiceResult<ListEntry> {
+        let value = request
+            .validate(Utc::now())
+            .map_err(ServiceError::Invalid)?;
+        let entry = ListRepo::upsert_list_entry(
+            &self.pool,
+            NewListEntry {
+                tenant,
+                list_type,
+                entity_type: request.entity_type,
+                value: &value,
+                reason: request.reason.as_deref(),
+                expires_at: request.expires_at,
+            },
+        )
+        .await?;
+        self.invalidate(tenant, &[(request.entity_type, value.as_str())])
+            .await;
+        tracing::info!(
+            account_id = %tenant,
+            list_type = ?list_type,
+            entity_type = entry.entity_type.name(),
+            "Entity listed"
+        );
+        Ok(entry.into())
+    }
+
+    /// Remove an entity, given in any form accepted when listing it, from one of the
+    /// account's lists
+    pub async fn delete_entry(
+        &self,
+        tenant: Tenant,
+        list_type: ListType,
+        entity_type: ListEntityType,
+        value: &str,
+    ) -> ServiceResult<()> {
+        let value = entity_type
+            .normalize(value)
+            .map_err(ServiceError::Invalid)?;
+        let deleted =
+            ListRepo::delete_list_entry(&self.pool, tenant, list_type, entity_type, &value).await?;
+        self.invalidate(tenant, &[(entity_type, value.as_str())])
+            .await;
+        if !deleted {
+            return Err(ServiceError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// The account's entries in force, on any list, and those of the blocklist its
+    /// organization shares, naming any of `entities`, given as pairs of entity type and
+    /// normalized value
+    ///
+    /// Entries come from the cache where it has them. A failing cache is logged and passed
+    /// over for the database.
+    pub async fn find_entries(
+        &self,
+        tenant: Tenant,
+        entities: &[(ListEntityType, String)],
+    ) -> sqlx::Result<Vec<ListEntry>> {
+        if entities.is_empty() {
+            return Ok(Vec::new());
+        }
+        let Some(connection) = &self.cache else {
+            let entries = ListRepo::find_list_entries(&self.pool, tenant, entities).await?;
+            return Ok(entries.into_iter().map(Into::into).collect());
+        };
+        let keys: Vec<String> = entities
+            .iter()
+            .map(|(entity_type, value)| cache_key(tenant, *entity_type, value))
+            .collect();
+        let cached: Vec<Option<String>> = match redis::cmd("MGET")
+            .arg(&keys)
+            .query_async(&mut connection.clone())
+            .await
+        {
+            Ok(cached) => cached,
+            Err(e) => {
+                tracing::warn!(error = %e, account_id = %tenant, "List cache lookup failed");
+                vec![None; keys.len()]
+            },
+        };
+
+        let mut found = Vec::new();
+        let mut missing = Vec::new();
+        for ((entity, key), cached) in entities.iter().zip(&keys).zip(cached) {
+            match cached.and_then(|json| serde_json::from_str::<Vec<ListEntry>>(&json).ok()) {
+                Some(entries) => found.extend(entries),
+                None => missing.push((entity.clone(), key)),
+            }
+        }
+        if !missing.is_empty() {
+            let entities: Vec<(ListEntityType, String)> =
+                missing.iter().map(|(entity, _)| entity.clone()).collect();
+            let entries: Vec<ListEntry> =
+                ListRepo::find_list_entries(&self.pool, tenant, &entities)
+                    .await?
+                    .into_iter()
+                    .map(Into::into)
+                    .collect();
+            let mut pipe = redis::pipe();
+            for ((entity_type, value), key) in &missing {
+                let naming: Vec<&ListEntry> = entries
+                    .iter()
+                    .filter(|entry| entry.entity_type == *entity_type && entry.value == *value)
+                    .collect();
+                if let Ok(json) = serde_json::to_string(&naming) {
+                    pipe.set_ex(key.as_str(), json, LIST_CACHE_TTL_SECONDS)
+                        .ignore();
+                }
+            }
+            let stored: RedisResult<()> = pipe.query_async(&mut connection.clone()).await;
+            if let Err(e) = stored {
+                tracing::warn!(error = %e, account_id = %tenant, "Failed to cache list entries");
+            }
+            found.extend(entries);
+        }
+        let now = Utc::now();
+        found.retain(|entry| entry.is_active(now));
+        Ok(found)
+    }
+
+    /// Drop the cached entries naming `entities`, given as pairs of entity type and normalized
+    /// value, after their entries change
+    ///
+    /// The caches of the members the account shares its blocklist with are dropped too.
+    pub(crate) async fn invalidate(&self, tenant: Tenant, entities: &[(ListEntityType, &str)]) {
+        let Some(connection) = &self.cache else {
+            return;
+        };
+        if entities.is_empty() {
+            return;
+        }
+        let mut tenants = vec![tenant];
+        match OrganizationRepo::blocklist_sharing_accounts(&self.pool, tenant.id()).await {
+            Ok(accounts) => tenants.extend(accounts.into_iter().map(Tenant::trusted)),
+            Err(e) => {
+                tracing::warn!(error = %e, account_id = %tenant, "Failed to find blocklist sharers");
+            },
+        }
+        let keys: Vec<String> = tenants
+            .iter()
+            .flat_map(|tenant| {
+                entities
+                    .iter()
+                    .map(|(entity_type, value)| cache_key(*tenant, *entity_type, value))
+            })
+            .collect();
+        let dropped: RedisResult<()> = redis::cmd("DEL")
+            .arg(&keys)
+            .query_async(&mut connection.clone())
+            .await;
+        if let Err(e) = dropped {
+            tracing::warn!(error = %e, account_id = %tenant, "Failed to drop cached list entries");
+        }
+    }
+
+    /// Blocklist the IP address, device, and card of a stored transaction with critical
+    /// velocity `factors` where the account's recent transactions from them have breached
+    /// often enough, returning the blocks added
+    ///
+    /// Nothing is blocked unless the account enables automatic blocks. Entities already on the
+    /// blocklist are left as they are.
+    pub async fn block_breaching_entities(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        factors: &[RiskFactor],
+    ) -> sqlx::Result<Vec<AutoBlock>> {
+        let Some(config) = &self.auto_block else {
+            return Ok(Vec::new());
+        };
+        let fired: Vec<(&str, &[ListEntityType])> = AUTO_BLOCK_FACTORS
+            .iter()
+            .copied()
+            .filter(|(code, _)| factors.iter().any(|factor| factor.code == *code))
+            .collect();
+        if fired.is_empty() {
+            return Ok(Vec::new());
+        }
+        let Some(ttl_minutes) = AccountRepo::auto_block_ttl_minutes(&self.pool, tenant).await?
+        else {
+            return Ok(Vec::new());
+        };
+
+        // Every critical factor counts against the kinds of entity a fired one implicates
+        let codes = |entity_type: ListEntityType| -> Vec<&str> {
+            if !fired.iter().any(|(_, types)| types.contains(&entity_type)) {
+                return Vec::new();
+            }
+            AUTO_BLOCK_FACTORS
+                .iter()
+                .filter(|(_, types)| types.contains(&entity_type))
+                .map(|(code, _)| *code)
+                .collect()
+        };
+        let now = Utc::now();
+        let counts = AutoBlockRepo::count_breaches(
+            &self.pool,
+            tenant,
+            transaction_id,
+            &codes(ListEntityType::Ip),
+            &codes(ListEntityType::Device),
+            &codes(ListEntityType::CardHash),
+            now - Duration::minutes(config.window_minutes),
+        )
+        .await?;
+
+        let factor_codes: Vec<String> = fired.iter().map(|(code, _)| code.to_string()).collect();
+        let expires_at = now + Duration::minutes(ttl_minutes.into());
+        let mut blocks = Vec::new();
+        for count in counts {
+            if count.breaches < config.min_breaches {
+                continue;
+            }
+            let Ok(value) = count.entity_type.normalize(&count.value) else {
+                continue;
+            };
+            let reason = format!(
+                "Automatic block: {} on {} transactions in {} minutes",
+                factor_codes.join(", "),
+                count.breaches,
+                config.window_minutes
+            );
+            let block = AutoBlockRepo::block(
+                &self.pool,
+                NewAutoBlock {
+                    tenant,
+                    entity_type: count.entity_type,
+                    value: &value,
+                    reason: &reason,
+                    factor_codes: &factor_codes,
+                    breaches: i32::try_from(count.breaches).unwrap_or(i32::MAX),
+                    transaction_id,
+                    expires_at,
+                },
+            )
+            .await?;
+            let Some(block) = block else {
+                continue;
+            };
+            self.invalidate(tenant, &[(block.entity_type, block.value.as_str())])
+                .await;
+            tracing::info!(
+                account_id = %tenant,
+                entity_type = block.entity_type.name(),
+                breaches = block.breaches,
+                %expires_at,
+                "Entity blocked automatically"
+            );
+            blocks.push(block.into());
+        }
+        Ok(blocks)
+    }
+
+    /// A page of the account's automatic blocks, newest first, with the number of them
+    pub async fn auto_blocks(
+        &self,
+        tenant: Tenant,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<AutoBlock>, i64)> {
+        let blocks = AutoBlockRepo::list(&self.pool, tenant, limit, offset).await?;
+        let total = AutoBlockRepo::count(&self.pool, tenant).await?;
+        Ok((blocks.into_iter().map(Into::into).collect(), total))
+    }
+
+    /// Queue entries for import into one of the account's lists by the background worker
+    ///
+    /// Entries are stored as submitted and only parsed and validated as they are imported, so
+    /// one malformed entry fails on its own rather than refusing the whole import.
+    pub async fn import_entries(
+        &self,
+        tenant: Tenant,
+        list_type: ListType,
+        entries: &[serde_json::Value],
+    ) -> ServiceResult<ListImport> {
+        let record = ListImportRepo::insert(&self.pool, tenant, list_type, entries).await?;
+        tracing::info!(
+            import_id = %record.id,
+            account_id = %tenant,
+            list_type = ?list_type,
+            total_entries = record.total_entries,
+            "List import queued"
+        );
+        Ok(record.into())
+    }
+
+    /// Fetch one of an account's list imports
+    pub async fn get_import(&self, tenant: Tenant, import_id: Uuid) -> ServiceResult<ListImport> {
+        ListImportRepo::find_by_id(&self.pool, tenant, import_id)
+            .await?
+            .map(ListImport::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Every entry of one of the account's lists still in force, in the form they are
+    /// imported in
+    pub async fn export_entries(
+        &self,
+        tenant: Tenant,
+        list_type: ListType,
+        query: &ListExportQuery,
+    ) -> ServiceResult<String> {
+        let entries: Vec<ListEntryRequest> =
+            ListRepo::all_list_entries(&self.pool, tenant, list_type, query.entity_type)
+                .await?
+                .into_iter()
+                .map(|record| ListEntryRequest {
+                    entity_type: record.entity_type,
+                    value: record.value,
+                    reason: record.reason,
+                    expires_at: record.expires_at,
+                })
+                .collect();
+        Ok(match query.format {
+            ListExportFormat::Csv => export_csv(&entries),
+            ListExportFormat::Ndjson => export_ndjson(&entries),
+        })
+    }
+
+    /// A page of the account's BIN table, with the number of ranges in it
+    pub async fn bin_ranges(
+        &self,
+        tenant: Tenant,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<BinRange>, i64)> {
+        let ranges = ListRepo::bin_ranges(&self.pool, tenant, limit, offset).await?;
+        let total = ListRepo::count_bin_ranges(&self.pool, tenant).await?;
+        Ok((ranges.into_iter().map(Into::into).collect(), total))
+    }
+
+    /// Import a BIN table in CSV form, replacing existing ranges with the same prefix, and with
+    /// `replace` removing the ranges it does not list
+    ///
+    /// Nothing is imported from a table with a malformed line.
+    pub async fn import_bin_ranges(
+        &self,
+        tenant: Tenant,
+        csv: &str,
+        replace: bool,
+    ) -> ServiceResult<BinRangeImport> {
+        let ranges = bin_intel::parse_csv(csv).map_err(ServiceError::Invalid)?;
+        if ranges.is_empty() {
+            return Err(ServiceError::Invalid("the table has no ranges".to_string()));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let removed = if replace {
+            let prefixes: Vec<String> = ranges.iter().map(|range| range.prefix.clone()).collect();
+            ListRepo::delete_bin_ranges_except(&mut *tx, tenant, &prefixes).await?
+        } else {
+            0
+        };
+        let mut imported = 0;
+        for chunk in ranges.chunks(BIN_IMPORT_CHUNK) {
+            imported += ListRepo::upsert_bin_ranges(&mut *tx, tenant, chunk).await?;
+        }
+        tx.commit().await?;
+        tracing::info!(account_id = %tenant, imported, removed, "BIN table imported");
+        Ok(BinRangeImport { imported, removed })
+    }
+
+    /// Remove the range with `prefix` from the account's BIN table
+    pub async fn delete_bin_range(&self, tenant: Tenant, prefix: &str) -> ServiceResult<()> {
+        if !ListRepo::delete_bin_range(&self.pool, tenant, prefix).await? {
+            return Err(ServiceError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+/// Entries of a list import in CSV form, as JSON objects of their non-blank fields
+///
+/// The first non-blank line is a header naming the columns, in any order: `entity_type` and
+/// `value`, and optionally `reason` and `expires_at`. The entries themselves are validated as
+/// they are imported.
+pub fn parse_entries_csv(csv: &str) -> Result<Vec<serde_json::Value>, String> {
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+    let (header_line, header) = lines.next().ok_or("the file has no header")?;
+    let columns =
+        bin_intel::split_record(header).map_err(|e| format!("line {header_line}: {e}"))?;
+    let mut names = Vec::with_capacity(columns.len());
+    for column in &columns {
+        let column = column.trim().to_ascii_lowercase();
+        if !CSV_COLUMNS.contains(&column.as_str()) {
+            return Err(format!(
+                "line {header_line}: unknown column {column}; columns are {}",
+                CSV_COLUMNS.join(", ")
+            ));
+        }
+        if names.contains(&column) {
+            return Err(format!("line {header_line}: column {column} is repeated"));
+        }
+        names.push(column);
+    }
+    for required in ["entity_type", "value"] {
+        if !names.iter().any(|name| name == required) {
+            return Err(format!(
+                "line {header_line}: the header has no {required} column"
+            ));
+        }
+    }
+
+    lines
+        .map(|(line_number, line)| {
+            let fields =
+                bin_intel::split_record(line).map_err(|e| format!("line {line_number}: {e}"))?;
+            if fields.len() != names.len() {
+                return Err(format!(
+                    "line {line_number}: expected {} fields, found {}",
+                    names.len(),
+                    fields.len()
+                ));
+            }
+            let entry: serde_json::Map<String, serde_json::Value> = names
+                .iter()
+                .zip(fields)
+                .map(|(name, field)| (name, field.trim().to_string()))
+                .filter(|(_, field)| !field.is_empty())
+                .map(|(name, field)| (name.clone(), serde_json::Value::String(field)))
+                .collect();
+            Ok(serde_json::Value::Object(entry))
+        })
+        .collect()
+}
+
+/// Entries as CSV with a header line
+fn export_csv(entries: &[ListEntryRequest]) -> String {
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push('\n');
+    for entry in entries {
+        let expires_at = entry
+            .expires_at
+            .map(|expires_at| expires_at.to_rfc3339())
+            .unwrap_or_default();
+        let fields = [
+            entry.entity_type.name(),
+            entry.value.as_str(),
+            entry.reason.as_deref().unwrap_or_default(),
+            expires_at.as_str(),
+        ];
+        push_row(&mut csv, fields);
+    }
+    csv
+}
+
+/// Entries as one JSON object per line
+fn export_ndjson(entries: &[ListEntryRequest]) -> String {
+    entries
+        .iter()
+        .filter_map(|entry| serde_json::to_string(entry).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+impl fmt::Debug for ListService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ListService")
+            .field("cached", &self.cache.is_some())
+            .field("auto_block", &self.auto_block)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        database::repositories::AccountRepo,
+        models::{
+            account::{DispositionPolicy, SubscriptionTier},
+            list::{AsnListAction, CountryListAction, ListEntityType, ListType},
+            organization::{CreateOrganization, MemberInvitation, OrganizationUpdate},
+            transaction::{Disposition, TransactionRequest},
+        },
+        scoring::RiskEngine,
+        services::{OrganizationService, TransactionService},
+        test_support::{create_account, test_pool},
+        utils::geo::{
+            GeoIpDatabase,
+            tests::{asn_mmdb, mmdb},
+        },
+    };
+
+    fn entry(value: serde_json::Value) -> AsnListEntryRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_listed_networks_reach_scoring_and_are_stored() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let lists = ListService::new(pool.clone());
+
+        assert!(matches!(
+            lists
+                .set_asn_entry(tenant, &entry(json!({ "asn": 64500, "action": "boost" })))
+                .await,
+            Err(ServiceError::Invalid(_))
+        ));
+        lists
+            .set_asn_entry(
+                tenant,
+                &entry(json!({ "asn": 64500, "action": "boost", "score": 40.0 })),
+            )
+            .await
+            .unwrap();
+        let blocked = lists
+            .set_asn_entry(
+                tenant,
+                &entry(json!({ "asn": 64500, "action": "block", "reason": "Bulletproof" })),
+            )
+            .await
+            .unwrap();
+        assert_eq!(blocked.action, AsnListAction::Block);
+        assert_eq!(blocked.score, None);
+        assert_eq!(lists.asn_entries(tenant).await.unwrap().entries, [blocked]);
+
+        let path = std::env::temp_dir().join(format!("asn-{}.mmdb", Uuid::new_v4()));
+        std::fs::write(&path, asn_mmdb(64_500, "Example Hosting")).unwrap();
+        let geoip = GeoIpDatabase::disabled().with_asn_database(Some(path.clone()));
+        geoip.reload().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction)
+                .with_geoip(geoip);
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "1.2.3.4", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        assert_eq!(
+            user.asn_listing.as_ref().map(|entry| entry.action),
+            Some(AsnListAction::Block)
+        );
+        let assessment = RiskEngine::new().assess(&request, &user);
+        assert!(
+            assessment
+                .factors
+                .iter()
+                .any(|factor| factor.code == "BLOCKED_ASN")
+        );
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        let (asn, isp, device_asn): (Option<i64>, Option<String>, Option<i64>) = sqlx::query_as(
+            "SELECT t.asn, t.isp, d.asn FROM transactions t
+             JOIN transaction_devices td ON td.transaction_id = t.id
+             JOIN devices d ON d.id = td.device_id
+             WHERE t.id = $1",
+        )
+        .bind(stored.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(asn, Some(64_500));
+        assert_eq!(isp.as_deref(), Some("Example Hosting"));
+        assert_eq!(device_asn, Some(64_500));
+
+        lists.delete_asn_entry(tenant, 64_500).await.unwrap();
+        assert!(matches!(
+            lists.delete_asn_entry(tenant, 64_500).await,
+            Err(ServiceError::NotFound)
+        ));
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        assert_eq!(user.asn_listing, None);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+    #[tokio::test]
+    async fn test_listed_countries_reach_scoring_and_are_stored() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let lists = ListService::new(pool.clone());
+        let country = |value| serde_json::from_value::<CountryListEntryRequest>(value).unwrap();
+
+        assert!(matches!(
+            lists
+                .set_country_entry(
+                    tenant,
+                    &country(json!({ "country": "ng", "action": "block" }))
+                )
+                .await,
+            Err(ServiceError::Invalid(_))
+        ));
+        lists
+            .set_country_entry(
+                tenant,
+                &country(json!({ "country": "RO", "action": "boost", "score": 30.0 })),
+            )
+            .await
+            .unwrap();
+        let review = lists
+            .set_country_entry(
+                tenant,
+                &country(json!({ "country": "NG", "action": "review", "reason": "Chargebacks" })),
+            )
+            .await
+            .unwrap();
+        assert_eq!(review.action, CountryListAction::Review);
+        let entries = lists.country_entries(tenant).await.unwrap().entries;
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry.country.as_str())
+                .collect::<Vec<_>>(),
+            ["NG", "RO"]
+        );
+
+        let path = std::env::temp_dir().join(format!("city-{}.mmdb", Uuid::new_v4()));
+        std::fs::write(&path, mmdb("NG")).unwrap();
+        let geoip = GeoIpDatabase::new(Some(path.clone()));
+        geoip.reload().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction)
+                .with_geoip(geoip);
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "1.2.3.4", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "billing": { "country": "US" },
+            "credit_card": { "country": "US" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        assert_eq!(user.ip_country.as_deref(), Some("NG"));
+        assert_eq!(user.country_listings, [review]);
+        let assessment = RiskEngine::new().assess(&request, &user);
+        let codes: Vec<&str> = assessment
+            .factors
+            .iter()
+            .map(|factor| factor.code.as_str())
+            .collect();
+        assert_eq!(codes, ["REVIEW_COUNTRY", "BILLING_IP_COUNTRY_MISMATCH"]);
+        assert_eq!(
+            assessment.disposition_under(DispositionPolicy::Monitor),
+            Disposition::Review
+        );
+
+        lists.delete_country_entry(tenant, "NG").await.unwrap();
+        assert!(matches!(
+            lists.delete_country_entry(tenant, "NG").await,
+            Err(ServiceError::NotFound)
+        ));
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        assert!(user.country_listings.is_empty());
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_imported_bin_ranges_reach_scoring_and_card_insights() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let lists = ListService::new(pool.clone());
+
+        assert!(matches!(
+            lists
+                .import_bin_ranges(tenant, "prefix,brand\n123\n", false)
+                .await,
+            Err(ServiceError::Invalid(_))
+        ));
+        let csv = "prefix,brand,card_type,country,bank_name,is_prepaid,is_business,is_virtual\n\
+                   4000,visa,credit,US,,false,false,false\n\
+                   400012,visa,debit,GB,\"Example Bank, PLC\",true,true,false\n";
+        let import = lists.import_bin_ranges(tenant, csv, false).await.unwrap();
+        assert_eq!(import.imported, 2);
+        let (ranges, total) = lists.bin_ranges(tenant, 10, 0).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(ranges[1].bank_name.as_deref(), Some("Example Bank, PLC"));
+
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "email": { "address": "jane@gmail.com" },
+            "billing": { "country": "US" },
+            "credit_card": { "issuer_id_number": "40001234", "last_digits": "4242" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        assert_eq!(user.card_bin.as_ref().unwrap().prefix, "400012");
+        let assessment = RiskEngine::new().assess(&request, &user);
+        let codes: Vec<&str> = assessment
+            .factors
+            .iter()
+            .map(|factor| factor.code.as_str())
+            .collect();
+        assert!(codes.contains(&"PREPAID_CARD"));
+        assert!(codes.contains(&"CARD_COUNTRY_MISMATCH"));
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        let card = transactions
+            .insights(tenant, stored.id)
+            .await
+            .unwrap()
+            .credit_card
+            .unwrap();
+        assert_eq!(card.brand.as_deref(), Some("visa"));
+        assert_eq!(card.card_type.as_deref(), Some("debit"));
+        assert_eq!(card.country.as_deref(), Some("GB"));
+        assert_eq!(card.bank_name.as_deref(), Some("Example Bank, PLC"));
+        assert_eq!(card.matches_billing_country, Some(false));
+        assert!(card.is_prepaid && card.is_business && !card.is_virtual);
+
+        // Replacing the table drops the longer range, leaving the shorter one and then the
+        // bundled table to describe cards
+        let import = lists
+            .import_bin_ranges(tenant, "prefix,card_type\n4000,charge\n", true)
+            .await
+            .unwrap();
+        assert_eq!((import.imported, import.removed), (1, 1));
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        assert_eq!(
+            user.card_bin.as_ref().unwrap().card_type.as_deref(),
+            Some("charge")
+        );
+        lists.delete_bin_range(tenant, "4000").await.unwrap();
+        assert!(matches!(
+            lists.delete_bin_range(tenant, "4000").await,
+            Err(ServiceError::NotFound)
+        ));
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        assert_eq!(user.card_bin, None);
+        let mut bundled = request.clone();
+        bundled.credit_card.as_mut().unwrap().issuer_id_number = Some("510510".to_string());
+        let user = transactions.user_signals(tenant, &bundled).await.unwrap();
+        assert!(user.card_bin.is_some_and(|bin| bin.is_prepaid));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_entries_are_normalized_found_and_expire() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let lists = ListService::new(pool.clone());
+        let request = |value| serde_json::from_value::<ListEntryRequest>(value).unwrap();
+
+        assert!(matches!(
+            lists
+                .set_entry(
+                    tenant,
+                    ListType::Blocklist,
+                    &request(json!({ "entity_type": "cidr", "value": "not a range" }))
+                )
+                .await,
+            Err(ServiceError::Invalid(_))
+        ));
+        let email = lists
+            .set_entry(
+                tenant,
+                ListType::Blocklist,
+                &request(json!({
+                    "entity_type": "email",
+                    "value": " Fraud@Example.com ",
+                    "reason": "Chargebacks"
+                })),
+            )
+            .await
+            .unwrap();
+        assert_eq!(email.value, crate::utils::sha256_hex("fraud@example.com"));
+        let range = lists
+            .set_entry(
+                tenant,
+                ListType::Watchlist,
+                &request(json!({ "entity_type": "cidr", "value": "203.0.113.7/24" })),
+            )
+            .await
+            .unwrap();
+        assert_eq!(range.value, "203.0.113.0/24");
+        lists
+            .set_entry(
+                tenant,
+                ListType::Blocklist,
+                &request(json!({ "entity_type": "country", "value": "ng" })),
+            )
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE list_entries SET expires_at = NOW() - INTERVAL '1 minute'
+             WHERE account_id = $1 AND entity_type = 'country'",
+        )
+        .bind(account_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let (entries, total) = lists
+            .entries(tenant, ListType::Blocklist, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(entries, std::slice::from_ref(&email));
+        let found = lists
+            .find_entries(
+                tenant,
+                &[
+                    (ListEntityType::Email, email.value.clone()),
+                    (ListEntityType::Cidr, range.value.clone()),
+                    (ListEntityType::Country, "NG".to_string()),
+                    (ListEntityType::Ip, "203.0.113.7".to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(found, [email.clone(), range]);
+
+        lists
+            .delete_entry(
+                tenant,
+                ListType::Blocklist,
+                ListEntityType::Email,
+                "fraud@example.com",
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            lists
+                .delete_entry(
+                    tenant,
+                    ListType::Blocklist,
+                    ListEntityType::Email,
+                    &email.value
+                )
+                .await,
+            Err(ServiceError::NotFound)
+        ));
+        assert!(matches!(
+            lists
+                .delete_entry(tenant, ListType::Blocklist, ListEntityType::Country, "NG")
+                .await,
+            Err(ServiceError::NotFound)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_listed_entities_reach_scoring() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let lists = ListService::new(pool.clone());
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction)
+                .with_lists(lists.clone());
+        let listing = |value| serde_json::from_value::<ListEntryRequest>(value).unwrap();
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "203.0.113.7", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "email": { "address": "Jane@Example.com" },
+            "credit_card": { "token": "tok_4242" }
+        }))
+        .unwrap();
+
+        lists
+            .set_entry(
+                tenant,
+                ListType::Watchlist,
+                &listing(json!({ "entity_type": "cidr", "value": "203.0.112.0/23" })),
+            )
+            .await
+            .unwrap();
+        lists
+            .set_entry(
+                tenant,
+                ListType::Allowlist,
+                &listing(json!({ "entity_type": "email_domain", "value": "example.com" })),
+            )
+            .await
+            .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        assert_eq!(user.list_entries.len(), 2);
+        let assessment = RiskEngine::new().assess(&request, &user);
+        let codes: Vec<&str> = assessment
+            .factors
+            .iter()
+            .map(|factor| factor.code.as_str())
+            .collect();
+        assert_eq!(codes, ["ALLOWLISTED", "WATCHLISTED"]);
+        assert!(!assessment.hard_reject);
+
+        let blocked = lists
+            .set_entry(
+                tenant,
+                ListType::Blocklist,
+                &listing(json!({
+                    "entity_type": "card_hash",
+                    "value": "tok_4242",
+                    "reason": "Stolen card"
+                })),
+            )
+            .await
+            .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        let assessment = RiskEngine::new().assess(&request, &user);
+        assert!(assessment.hard_reject);
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        let (reason, metadata): (String, serde_json::Value) = sqlx::query_as(
+            "SELECT reason, metadata FROM risk_factors
+             WHERE transaction_id = $1 AND factor_code = 'BLOCKLISTED'",
+        )
+        .bind(stored.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(reason, "Card is on the account's blocklist: Stolen card");
+        assert_eq!(metadata["matches"][0]["entry_id"], json!(blocked.id));
+
+        // The kept request only holds the email address and card hashed again, so rescoring
+        // matches them on the hashes stored with the transaction
+        lists
+            .set_entry(
+                tenant,
+                ListType::Blocklist,
+                &listing(json!({ "entity_type": "email", "value": "jane@example.com" })),
+            )
+            .await
+            .unwrap();
+        let (_, rescored) = transactions
+            .assess_stored(tenant, stored.id, |request, user| {
+                assert_eq!(user.list_entries.len(), 4);
+                RiskEngine::new().assess(request, user)
+            })
+            .await
+            .unwrap();
+        assert!(rescored.hard_reject);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_repeated_velocity_breaches_block_entities_for_a_while() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let lists = ListService::new(pool.clone()).with_auto_block(Config::default().auto_block);
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction)
+                .with_lists(lists.clone());
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "198.51.100.23" },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let mut assessment = RiskEngine::new().assess(&request, &Default::default());
+        assessment.factors.push(RiskFactor::new(
+            "IP_VELOCITY",
+            "velocity",
+            60.0,
+            "Many transactions from this IP address",
+        ));
+
+        // Accounts have to opt in
+        for _ in 0..3 {
+            transactions
+                .store_transaction(tenant, &request, &assessment, &[])
+                .await
+                .unwrap();
+        }
+        let (blocks, total) = lists.auto_blocks(tenant, 10, 0).await.unwrap();
+        assert!(blocks.is_empty());
+        assert_eq!(total, 0);
+
+        sqlx::query(
+            "UPDATE accounts SET auto_block_enabled = true, auto_block_ttl_minutes = 30
+             WHERE id = $1",
+        )
+        .bind(account_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        let (blocks, total) = lists.auto_blocks(tenant, 10, 0).await.unwrap();
+        assert_eq!(total, 1);
+        let block = &blocks[0];
+        assert_eq!(block.entity_type, ListEntityType::Ip);
+        assert_eq!(block.value, "198.51.100.23");
+        assert_eq!(block.factor_codes, ["IP_VELOCITY"]);
+        assert_eq!(block.breaches, 4);
+        assert_eq!(block.transaction_id, Some(stored.id));
+        let ttl = block.expires_at - block.created_at;
+        assert!((29..=30).contains(&ttl.num_minutes()), "{ttl}");
+
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        assert_eq!(user.list_entries.len(), 1);
+        assert_eq!(user.list_entries[0].list_type, ListType::Blocklist);
+        assert_eq!(Some(user.list_entries[0].id), block.list_entry_id);
+        assert!(RiskEngine::new().assess(&request, &user).hard_reject);
+
+        // A block in force is not extended by further breaches
+        transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        let (_, total) = lists.auto_blocks(tenant, 10, 0).await.unwrap();
+        assert_eq!(total, 1);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_organizations_share_blocklist_entries() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let mut accounts = Vec::new();
+        for _ in 0..2 {
+            let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+            let account = AccountRepo::find_by_id(&pool, tenant)
+                .await
+                .unwrap()
+                .unwrap();
+            accounts.push((tenant.id(), account.account_id));
+        }
+        let [(acme, acme_public), (globex, globex_public)] = accounts.as_slice() else {
+            unreachable!();
+        };
+        let organizations = OrganizationService::new(pool.clone());
+        let organization = organizations
+            .create_organization(
+                *acme,
+                false,
+                &CreateOrganization {
+                    name: "Acme Group".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        organizations
+            .invite_member(
+                *acme,
+                &MemberInvitation {
+                    account_id: globex_public.clone(),
+                    role: None,
+                    user_lookup: None,
+                },
+            )
+            .await
+            .unwrap();
+        organizations
+            .accept_invitation(*globex, organization.organization_id)
+            .await
+            .unwrap();
+
+        let lists = ListService::new(pool.clone());
+        let listing = |list_type| {
+            serde_json::from_value::<ListEntryRequest>(json!({
+                "entity_type": "ip",
+                "value": "198.51.100.7",
+                "reason": format!("Chargebacks at Acme ({list_type:?})")
+            }))
+        };
+        for list_type in [ListType::Blocklist, ListType::Watchlist] {
+            lists
+                .set_entry(
+                    Tenant::trusted(*acme),
+                    list_type,
+                    &listing(list_type).unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+        let entities = [(ListEntityType::Ip, "198.51.100.7".to_string())];
+        let globex_tenant = Tenant::trusted(*globex);
+        assert!(
+            lists
+                .find_entries(globex_tenant, &entities)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let organization = organizations
+            .update_organization(
+                *globex,
+                &OrganizationUpdate {
+                    share_blocklist: Some(true),
+                    ..OrganizationUpdate::default()
+                },
+            )
+            .await;
+        assert!(matches!(organization, Err(ServiceError::Forbidden(_))));
+        let organization = organizations
+            .update_organization(
+                *acme,
+                &OrganizationUpdate {
+                    share_blocklist: Some(true),
+                    ..OrganizationUpdate::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(organization.share_blocklist);
+
+        // Only blocklist entries are shared, naming the member that added them
+        let found = lists.find_entries(globex_tenant, &entities).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].list_type, ListType::Blocklist);
+        assert_eq!(&found[0].added_by, acme_public);
+        let (entries, total) = lists
+            .entries(globex_tenant, ListType::Blocklist, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(entries, found);
+        let (_, total) = lists
+            .entries(globex_tenant, ListType::Watchlist, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 0);
+
+        // Members manage only their own entries
+        let deleted = lists
+            .delete_entry(
+                globex_tenant,
+                ListType::Blocklist,
+                ListEntityType::Ip,
+                "198.51.100.7",
+            )
+            .await;
+        assert!(matches!(deleted, Err(ServiceError::NotFound)));
+        let own = lists
+            .set_entry(
+                globex_tenant,
+                ListType::Blocklist,
+                &listing(ListType::Blocklist).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(&own.added_by, globex_public);
+        let found = lists
+            .find_entries(Tenant::trusted(*acme), &entities)
+            .await
+            .unwrap();
+        let added_by: Vec<&str> = found.iter().map(|entry| entry.added_by.as_str()).collect();
+        assert_eq!(added_by, [acme_public, acme_public, globex_public]);
+
+        OrganizationRepo::delete_billed_to(&pool, *acme)
+            .await
+            .unwrap();
+        for (account_id, _) in &accounts {
+            AccountRepo::delete(&pool, *account_id).await.unwrap();
+        }
+    }
+
+    #[test]
+    fn test_entries_csv_parses_and_exports() {
+        let entries = parse_entries_csv(
+            "\nValue,entity_type,reason\n203.0.113.0/24,cidr,\"Chargebacks, mostly\"\n\nng,country,\n",
+        )
+        .unwrap();
+        assert_eq!(
+            entries,
+            [
+                json!({ "entity_type": "cidr", "value": "203.0.113.0/24", "reason": "Chargebacks, mostly" }),
+                json!({ "entity_type": "country", "value": "ng" }),
+            ]
+        );
+
+        for (csv, error) in [
+            ("", "the file has no header"),
+            ("entity_type,value,score\n", "line 1: unknown column score"),
+            (
+                "entity_type,value,value\n",
+                "line 1: column value is repeated",
+            ),
+            ("value\n", "line 1: the header has no entity_type column"),
+            (
+                "entity_type,value\nip\n",
+                "line 2: expected 2 fields, found 1",
+            ),
+            (
+                "entity_type,value\n\nip,\"1.2.3.4\n",
+                "line 3: a quoted field is not closed",
+            ),
+        ] {
+            let message = parse_entries_csv(csv).unwrap_err();
+            assert!(message.starts_with(error), "{csv:?}: {message}");
+        }
+
+        let exported: Vec<ListEntryRequest> = entries
+            .into_iter()
+            .map(|entry| serde_json::from_value(entry).unwrap())
+            .collect();
+        let csv = export_csv(&exported);
+        assert_eq!(
+            csv,
+            "entity_type,value,reason,expires_at\n\
+             cidr,203.0.113.0/24,\"Chargebacks, mostly\",\n\
+             country,ng,,\n"
+        );
+        let reparsed: Vec<ListEntryRequest> = parse_entries_csv(&csv)
+            .unwrap()
+            .into_iter()
+            .map(|entry| serde_json::from_value(entry).unwrap())
+            .collect();
+        assert_eq!(reparsed.len(), 2);
+        assert_eq!(reparsed[0].reason.as_deref(), Some("Chargebacks, mostly"));
+        assert_eq!(
+            export_ndjson(&exported),
+            "{\"entity_type\":\"cidr\",\"value\":\"203.0.113.0/24\",\"reason\":\"Chargebacks, mostly\"}\n\
+             {\"entity_type\":\"country\",\"value\":\"ng\"}\n"
+        );
+    }
+}
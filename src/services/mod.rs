@@ -0,0 +1,81 @@
+//! Business logic services
+
+pub mod account_service;
+pub mod analytics_service;
+pub mod bin_intel;
+pub mod case_service;
+pub mod dead_letter_service;
+pub mod device_service;
+pub mod email_intel;
+pub mod ip_intel;
+pub mod ip_reputation;
+pub mod jobs;
+pub mod label_service;
+pub mod list_service;
+pub mod notification_service;
+pub mod organization_service;
+pub mod outcome_service;
+pub mod phone_intel;
+pub mod processor_event_service;
+pub mod report_service;
+pub mod rule_service;
+pub mod screening;
+pub mod transaction_service;
+pub mod user_service;
+pub mod webhook_service;
+
+use thiserror::Error;
+
+use crate::database::clickhouse::ClickHouseError;
+
+pub use account_service::AccountService;
+pub use analytics_service::AnalyticsService;
+pub use case_service::CaseService;
+pub use dead_letter_service::DeadLetterService;
+pub use device_service::DeviceService;
+pub use email_intel::EmailIntelService;
+pub use ip_intel::IpIntelService;
+pub use jobs::JobService;
+pub use label_service::LabelService;
+pub use list_service::ListService;
+pub use notification_service::NotificationService;
+pub use organization_service::OrganizationService;
+pub use outcome_service::OutcomeService;
+pub use processor_event_service::ProcessorEventService;
+pub use report_service::ReportService;
+pub use rule_service::RuleService;
+pub use screening::ScreeningService;
+pub use transaction_service::TransactionService;
+pub use user_service::UserService;
+pub use webhook_service::WebhookService;
+
+/// Service layer result type alias
+pub type ServiceResult<T> = Result<T, ServiceError>;
+
+/// Errors raised by the service layer
+#[derive(Error, Debug)]
+pub enum ServiceError {
+    /// The requested entity does not exist for the calling account
+    #[error("Not found")]
+    NotFound,
+
+    /// The request references data that is missing or inconsistent
+    #[error("Invalid request: {0}")]
+    Invalid(String),
+
+    /// The request clashes with existing data
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// The caller may not perform the operation on this entity
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    /// Database failure
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    /// Analytics store failure
+    #[error("Analytics error: {0}")]
+    Analytics(#[from] ClickHouseError),
+}
@@ -0,0 +1,210 @@
+//! Email and Slack channels accounts register to hear about high-severity events
+//!
+//! Channels take anomaly alerts, exhausted quotas, and review cases past their SLA; the
+//! outbox dispatcher announces those events on them.
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    database::{
+        Tenant,
+        repositories::{NotificationChannelRecord, NotificationChannelRepo},
+    },
+    models::notification::{NotificationChannel, NotificationChannelRequest},
+};
+
+impl From<NotificationChannelRecord> for NotificationChannel {
+    fn from(record: NotificationChannelRecord) -> Self {
+        NotificationChannel {
+            id: record.id,
+            name: record.name,
+            kind: record.kind,
+            target: record.target,
+            events: record.events.0,
+            active: record.is_active,
+            last_notified_at: record.last_notified_at,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            links: NotificationChannel::links(record.id),
+        }
+    }
+}
+
+/// Notification channel management
+#[derive(Debug, Clone)]
+pub struct NotificationService {
+    pool: PgPool,
+}
+
+impl NotificationService {
+    /// Create a new notification service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// An account's channels, oldest first
+    pub async fn list_channels(&self, tenant: Tenant) -> ServiceResult<Vec<NotificationChannel>> {
+        let records = NotificationChannelRepo::list(&self.pool, tenant).await?;
+        Ok(records.into_iter().map(NotificationChannel::from).collect())
+    }
+
+    /// Fetch one of an account's channels
+    pub async fn get_channel(
+        &self,
+        tenant: Tenant,
+        channel_id: Uuid,
+    ) -> ServiceResult<NotificationChannel> {
+        NotificationChannelRepo::find(&self.pool, tenant, channel_id)
+            .await?
+            .map(NotificationChannel::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Register a channel
+    pub async fn create_channel(
+        &self,
+        tenant: Tenant,
+        channel: &NotificationChannelRequest,
+    ) -> ServiceResult<NotificationChannel> {
+        channel.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let channel_id = NotificationChannelRepo::insert(&mut *tx, tenant, channel)
+            .await
+            .map_err(conflict_on_channel_name)?;
+        let record = NotificationChannelRepo::find(&mut *tx, tenant, channel_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+        Ok(record.into())
+    }
+
+    /// Replace a channel's name, kind, target, events, and whether it is active
+    pub async fn update_channel(
+        &self,
+        tenant: Tenant,
+        channel_id: Uuid,
+        channel: &NotificationChannelRequest,
+    ) -> ServiceResult<NotificationChannel> {
+        channel.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let updated = NotificationChannelRepo::update(&mut *tx, tenant, channel_id, channel)
+            .await
+            .map_err(conflict_on_channel_name)?;
+        if !updated {
+            return Err(ServiceError::NotFound);
+        }
+        let record = NotificationChannelRepo::find(&mut *tx, tenant, channel_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+        Ok(record.into())
+    }
+
+    /// Delete a channel
+    pub async fn delete_channel(&self, tenant: Tenant, channel_id: Uuid) -> ServiceResult<()> {
+        if NotificationChannelRepo::delete(&self.pool, tenant, channel_id).await? {
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound)
+        }
+    }
+}
+
+/// Report a channel name the account already uses as a conflict
+fn conflict_on_channel_name(e: sqlx::Error) -> ServiceError {
+    if e.as_database_error()
+        .is_some_and(|db| db.is_unique_violation())
+    {
+        ServiceError::Conflict("A notification channel with this name already exists".to_string())
+    } else {
+        ServiceError::Database(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::repositories::AccountRepo,
+        metering::Meter,
+        models::{account::SubscriptionTier, notification::ChannelKind},
+        outbox::{ANOMALY_DETECTED, QUOTA_EXHAUSTED},
+        test_support::{create_account_with_quota, test_pool},
+    };
+
+    #[tokio::test]
+    async fn test_channels_take_high_severity_events() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account_with_quota(&pool, SubscriptionTier::Free, 2).await;
+        let account_id = tenant.id();
+        let notifications = NotificationService::new(pool.clone());
+
+        let request = NotificationChannelRequest {
+            name: "fraud-oncall".to_string(),
+            kind: ChannelKind::Slack,
+            target: "https://hooks.slack.com/services/T000/B000/XXXX".to_string(),
+            events: vec![QUOTA_EXHAUSTED.to_string()],
+            active: true,
+        };
+        let channel = notifications
+            .create_channel(tenant, &request)
+            .await
+            .unwrap();
+        assert!(matches!(
+            notifications.create_channel(tenant, &request).await,
+            Err(ServiceError::Conflict(_))
+        ));
+        let recipients =
+            |event_type| NotificationChannelRepo::recipients(&pool, tenant, event_type);
+        assert_eq!(recipients(QUOTA_EXHAUSTED).await.unwrap().len(), 1);
+        assert!(recipients(ANOMALY_DETECTED).await.unwrap().is_empty());
+
+        // Using up the quota is announced once per cycle
+        let meter = Meter::new(pool.clone(), None, true);
+        for _ in 0..4 {
+            meter.consume(tenant, 1).await.unwrap();
+        }
+        let exhausted: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM outbox_events WHERE account_id = $1 AND event_type = $2",
+        )
+        .bind(account_id)
+        .bind(QUOTA_EXHAUSTED)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(exhausted, 1);
+
+        // Inactive channels are not announced on, and channels belong to their account
+        let inactive = NotificationChannelRequest {
+            active: false,
+            ..request
+        };
+        notifications
+            .update_channel(tenant, channel.id, &inactive)
+            .await
+            .unwrap();
+        assert!(recipients(QUOTA_EXHAUSTED).await.unwrap().is_empty());
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(matches!(
+            notifications.get_channel(other, channel.id).await,
+            Err(ServiceError::NotFound)
+        ));
+
+        notifications
+            .delete_channel(tenant, channel.id)
+            .await
+            .unwrap();
+        assert!(
+            notifications
+                .list_channels(tenant)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
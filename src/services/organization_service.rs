@@ -0,0 +1,499 @@
+//! Organizations and member management
+//!
+//! Members are accounts. An owner or admin invites an account by its public identifier, and
+//! the invited account joins by accepting with one of its own API keys, so no account can be
+//! pulled into an organization without its consent. An account belongs to at most one
+//! organization; sandbox accounts belong to none.
+//!
+//! An organization may share blocklists: each active member's blocklist entries then apply to
+//! the others too, see [`super::ListService`].
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    database::repositories::{
+        AccountRepo, InvitationRecord, MemberRecord, MembershipRecord, OrganizationRepo, OutboxRepo,
+    },
+    models::organization::{
+        CreateOrganization, Invitation, MemberInvitation, MemberRole, MemberUpdate,
+        MembershipStatus, Organization, OrganizationMember, OrganizationUpdate, validate_name,
+    },
+    outbox::{ORGANIZATION_MEMBER_INVITED, ORGANIZATION_MEMBER_JOINED, OrganizationMembership},
+};
+
+impl From<MemberRecord> for OrganizationMember {
+    fn from(record: MemberRecord) -> Self {
+        OrganizationMember {
+            account_id: record.public_account_id,
+            role: record.role,
+            status: record.status,
+            user_lookup: record.user_lookup,
+            subscription_tier: record.subscription_tier,
+            queries_used_this_month: record.queries_used_this_month,
+            invited_at: record.invited_at,
+            joined_at: record.joined_at,
+        }
+    }
+}
+
+impl From<InvitationRecord> for Invitation {
+    fn from(record: InvitationRecord) -> Self {
+        Invitation {
+            organization_id: record.organization_id,
+            organization_name: record.organization_name,
+            role: record.role,
+            user_lookup: record.user_lookup,
+            invited_at: record.invited_at,
+        }
+    }
+}
+
+/// Manages organizations on behalf of their member accounts
+#[derive(Debug, Clone)]
+pub struct OrganizationService {
+    pool: PgPool,
+}
+
+impl OrganizationService {
+    /// Create an organization service backed by the given pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Fetch the organization the account belongs to
+    pub async fn get_organization(&self, account_id: Uuid) -> ServiceResult<Organization> {
+        let membership = self.membership(account_id).await?;
+        self.load(membership).await
+    }
+
+    /// Create an organization owned and billed to the calling account
+    pub async fn create_organization(
+        &self,
+        account_id: Uuid,
+        sandbox: bool,
+        request: &CreateOrganization,
+    ) -> ServiceResult<Organization> {
+        let name = validate_name(&request.name).map_err(ServiceError::Invalid)?;
+        if sandbox {
+            return Err(ServiceError::Invalid(
+                "Sandbox accounts cannot belong to an organization".to_string(),
+            ));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        if OrganizationRepo::membership(&mut *tx, account_id)
+            .await?
+            .is_some()
+        {
+            return Err(already_member());
+        }
+        let organization_id = OrganizationRepo::create(&mut *tx, name, account_id).await?;
+        OrganizationRepo::add_member(
+            &mut *tx,
+            organization_id,
+            account_id,
+            MemberRole::Owner,
+            MembershipStatus::Active,
+            true,
+        )
+        .await
+        .map_err(conflict_on_unique_violation)?;
+        tx.commit().await?;
+
+        tracing::info!(%account_id, %organization_id, "Organization created");
+        self.load(MembershipRecord {
+            organization_id,
+            role: MemberRole::Owner,
+        })
+        .await
+    }
+
+    /// Rename the organization or move its billing to another member
+    ///
+    /// Any owner or admin may rename; only the owner may change the billing account.
+    pub async fn update_organization(
+        &self,
+        account_id: Uuid,
+        update: &OrganizationUpdate,
+    ) -> ServiceResult<Organization> {
+        let name = update
+            .name
+            .as_deref()
+            .map(validate_name)
+            .transpose()
+            .map_err(ServiceError::Invalid)?;
+        let membership = self.membership(account_id).await?;
+        require_manager(membership.role)?;
+
+        let billing_account_id = match update.billing_account_id.as_deref() {
+            None => None,
+            Some(_) if membership.role != MemberRole::Owner => {
+                return Err(ServiceError::Forbidden(
+                    "Only the owner may change the billing account".to_string(),
+                ));
+            },
+            Some(public_id) => {
+                let member = self
+                    .find_member(membership.organization_id, public_id)
+                    .await?
+                    .filter(|member| member.status == MembershipStatus::Active)
+                    .ok_or_else(|| {
+                        ServiceError::Invalid(format!(
+                            "Account '{public_id}' is not an active member of the organization"
+                        ))
+                    })?;
+                Some(member.account_id)
+            },
+        };
+
+        OrganizationRepo::update(
+            &self.pool,
+            membership.organization_id,
+            name,
+            billing_account_id,
+            update.share_blocklist,
+        )
+        .await?;
+        tracing::info!(
+            %account_id,
+            organization_id = %membership.organization_id,
+            "Organization updated"
+        );
+        self.load(membership).await
+    }
+
+    /// Invite an account to the caller's organization
+    ///
+    /// The invited account is notified with an `organization.member_invited` event.
+    pub async fn invite_member(
+        &self,
+        account_id: Uuid,
+        invitation: &MemberInvitation,
+    ) -> ServiceResult<OrganizationMember> {
+        let membership = self.membership(account_id).await?;
+        require_manager(membership.role)?;
+        let role = invitation.role.unwrap_or(MemberRole::Member);
+        check_role_change(membership.role, None, role)?;
+
+        let invitee = AccountRepo::find_live_id(&self.pool, &invitation.account_id)
+            .await?
+            .ok_or_else(|| {
+                ServiceError::Invalid(format!(
+                    "Account '{}' does not exist",
+                    invitation.account_id
+                ))
+            })?;
+        let organization = OrganizationRepo::find(&self.pool, membership.organization_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+
+        let mut tx = self.pool.begin().await?;
+        let added = OrganizationRepo::add_member(
+            &mut *tx,
+            membership.organization_id,
+            invitee,
+            role,
+            MembershipStatus::Invited,
+            invitation.user_lookup.unwrap_or(false),
+        )
+        .await?;
+        if !added {
+            return Err(ServiceError::Conflict(format!(
+                "Account '{}' is already a member or invited",
+                invitation.account_id
+            )));
+        }
+        let payload = OrganizationMembership {
+            organization_id: organization.id,
+            organization_name: organization.name,
+            account_id: invitation.account_id.clone(),
+            role,
+        };
+        OutboxRepo::insert(
+            &mut *tx,
+            invitee,
+            ORGANIZATION_MEMBER_INVITED,
+            organization.id,
+            serde_json::to_value(&payload).unwrap_or_default(),
+        )
+        .await?;
+        let member = OrganizationRepo::find_member(&mut *tx, organization.id, invitee)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+
+        tracing::info!(%account_id, organization_id = %organization.id, %invitee, "Account invited");
+        Ok(member.into())
+    }
+
+    /// Change a member's role or user lookup permission
+    pub async fn update_member(
+        &self,
+        account_id: Uuid,
+        member_id: &str,
+        update: &MemberUpdate,
+    ) -> ServiceResult<OrganizationMember> {
+        let membership = self.membership(account_id).await?;
+        require_manager(membership.role)?;
+        let member = self
+            .find_member(membership.organization_id, member_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        if let Some(role) = update.role {
+            check_role_change(membership.role, Some(member.role), role)?;
+        } else if member.role == MemberRole::Admin && membership.role != MemberRole::Owner {
+            return Err(owner_only());
+        }
+
+        OrganizationRepo::update_member(
+            &self.pool,
+            membership.organization_id,
+            member.account_id,
+            update.role,
+            update.user_lookup,
+        )
+        .await?;
+        OrganizationRepo::find_member(&self.pool, membership.organization_id, member.account_id)
+            .await?
+            .map(OrganizationMember::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Remove a member or withdraw an invitation
+    ///
+    /// Owners and admins may remove others (only the owner may remove admins); any member but
+    /// the owner may remove itself to leave.
+    pub async fn remove_member(&self, account_id: Uuid, member_id: &str) -> ServiceResult<()> {
+        let membership = self.membership(account_id).await?;
+        let member = self
+            .find_member(membership.organization_id, member_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        match member.role {
+            MemberRole::Owner => {
+                return Err(ServiceError::Invalid(
+                    "The owner cannot be removed from the organization".to_string(),
+                ));
+            },
+            _ if member.account_id == account_id => {},
+            MemberRole::Admin if membership.role != MemberRole::Owner => return Err(owner_only()),
+            _ => require_manager(membership.role)?,
+        }
+
+        let organization = OrganizationRepo::find(&self.pool, membership.organization_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        if organization.billing_account_id == member.account_id {
+            return Err(ServiceError::Invalid(
+                "Move billing to another member before removing the billing account".to_string(),
+            ));
+        }
+
+        OrganizationRepo::remove_member(&self.pool, organization.id, member.account_id).await?;
+        tracing::info!(
+            %account_id,
+            organization_id = %organization.id,
+            member = %member.account_id,
+            "Organization member removed"
+        );
+        Ok(())
+    }
+
+    /// Pending invitations of the account
+    pub async fn invitations(&self, account_id: Uuid) -> ServiceResult<Vec<Invitation>> {
+        Ok(OrganizationRepo::invitations(&self.pool, account_id)
+            .await?
+            .into_iter()
+            .map(Invitation::from)
+            .collect())
+    }
+
+    /// Join an organization the account was invited to
+    ///
+    /// The organization's billing account is notified with an `organization.member_joined`
+    /// event.
+    pub async fn accept_invitation(
+        &self,
+        account_id: Uuid,
+        organization_id: Uuid,
+    ) -> ServiceResult<Organization> {
+        let mut tx = self.pool.begin().await?;
+        if OrganizationRepo::membership(&mut *tx, account_id)
+            .await?
+            .is_some()
+        {
+            return Err(already_member());
+        }
+        let accepted = OrganizationRepo::accept_invitation(&mut *tx, organization_id, account_id)
+            .await
+            .map_err(conflict_on_unique_violation)?;
+        if !accepted {
+            return Err(ServiceError::NotFound);
+        }
+        let organization = OrganizationRepo::find(&mut *tx, organization_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let member = OrganizationRepo::find_member(&mut *tx, organization_id, account_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let payload = OrganizationMembership {
+            organization_id,
+            organization_name: organization.name,
+            account_id: member.public_account_id,
+            role: member.role,
+        };
+        OutboxRepo::insert(
+            &mut *tx,
+            organization.billing_account_id,
+            ORGANIZATION_MEMBER_JOINED,
+            organization_id,
+            serde_json::to_value(&payload).unwrap_or_default(),
+        )
+        .await?;
+        tx.commit().await?;
+
+        tracing::info!(%account_id, %organization_id, "Joined organization");
+        self.load(MembershipRecord {
+            organization_id,
+            role: member.role,
+        })
+        .await
+    }
+
+    /// Decline an invitation
+    pub async fn decline_invitation(
+        &self,
+        account_id: Uuid,
+        organization_id: Uuid,
+    ) -> ServiceResult<()> {
+        let invited = OrganizationRepo::invitations(&self.pool, account_id)
+            .await?
+            .iter()
+            .any(|invitation| invitation.organization_id == organization_id);
+        if !invited {
+            return Err(ServiceError::NotFound);
+        }
+        OrganizationRepo::remove_member(&self.pool, organization_id, account_id).await?;
+        Ok(())
+    }
+
+    /// Accounts whose users `account_id` may look up: itself, plus the other members of its
+    /// organization if it holds the user lookup permission
+    pub async fn user_lookup_accounts(&self, account_id: Uuid) -> ServiceResult<Vec<Uuid>> {
+        Ok(OrganizationRepo::user_lookup_accounts(&self.pool, account_id).await?)
+    }
+
+    async fn membership(&self, account_id: Uuid) -> ServiceResult<MembershipRecord> {
+        OrganizationRepo::membership(&self.pool, account_id)
+            .await?
+            .ok_or(ServiceError::NotFound)
+    }
+
+    async fn find_member(
+        &self,
+        organization_id: Uuid,
+        public_id: &str,
+    ) -> ServiceResult<Option<MemberRecord>> {
+        let Some(account_id) = AccountRepo::find_live_id(&self.pool, public_id).await? else {
+            return Ok(None);
+        };
+        Ok(OrganizationRepo::find_member(&self.pool, organization_id, account_id).await?)
+    }
+
+    async fn load(&self, membership: MembershipRecord) -> ServiceResult<Organization> {
+        let organization = OrganizationRepo::find(&self.pool, membership.organization_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let members = OrganizationRepo::members(&self.pool, organization.id).await?;
+        let total_queries_used_this_month = members
+            .iter()
+            .filter(|member| member.status == MembershipStatus::Active)
+            .map(|member| i64::from(member.queries_used_this_month))
+            .sum();
+        Ok(Organization {
+            organization_id: organization.id,
+            name: organization.name,
+            billing_account_id: organization.billing_account,
+            role: membership.role,
+            share_blocklist: organization.share_blocklist,
+            total_queries_used_this_month,
+            members: members.into_iter().map(OrganizationMember::from).collect(),
+            created_at: organization.created_at,
+            updated_at: organization.updated_at,
+            links: Organization::links(),
+        })
+    }
+}
+
+/// Fail unless `role` may manage members
+fn require_manager(role: MemberRole) -> ServiceResult<()> {
+    if role.can_manage_members() {
+        Ok(())
+    } else {
+        Err(ServiceError::Forbidden(
+            "Only the owner and admins may manage the organization".to_string(),
+        ))
+    }
+}
+
+/// Check that a caller with role `caller` may give a member holding `current` the role `new`
+///
+/// Nobody may become or stop being the owner, and only the owner may grant or revoke admin.
+fn check_role_change(
+    caller: MemberRole,
+    current: Option<MemberRole>,
+    new: MemberRole,
+) -> ServiceResult<()> {
+    if new == MemberRole::Owner || current == Some(MemberRole::Owner) {
+        if current == Some(new) {
+            return Ok(());
+        }
+        return Err(ServiceError::Invalid(
+            "The owner role cannot be given or taken away".to_string(),
+        ));
+    }
+    let touches_admin = new == MemberRole::Admin || current == Some(MemberRole::Admin);
+    if touches_admin && caller != MemberRole::Owner {
+        return Err(owner_only());
+    }
+    Ok(())
+}
+
+fn owner_only() -> ServiceError {
+    ServiceError::Forbidden("Only the owner may manage admins".to_string())
+}
+
+fn already_member() -> ServiceError {
+    ServiceError::Conflict("The account already belongs to an organization".to_string())
+}
+
+/// Report a concurrent join of another organization as a conflict
+fn conflict_on_unique_violation(e: sqlx::Error) -> ServiceError {
+    if e.as_database_error()
+        .is_some_and(|db| db.is_unique_violation())
+    {
+        already_member()
+    } else {
+        ServiceError::Database(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_changes() {
+        use MemberRole::*;
+        assert!(check_role_change(Admin, None, Member).is_ok());
+        assert!(check_role_change(Admin, None, Admin).is_err());
+        assert!(check_role_change(Owner, None, Admin).is_ok());
+        assert!(check_role_change(Owner, Some(Admin), Member).is_ok());
+        assert!(check_role_change(Admin, Some(Admin), Member).is_err());
+        assert!(check_role_change(Owner, None, Owner).is_err());
+        assert!(check_role_change(Owner, Some(Owner), Admin).is_err());
+        assert!(check_role_change(Owner, Some(Owner), Owner).is_ok());
+    }
+}
@@ -0,0 +1,282 @@
+//! Recording of transaction outcomes merchants and processors report
+//!
+//! A transaction has one outcome on record. Recording a different one replaces it and adjusts
+//! the chargeback counts of the transaction's user, cards, and devices, and the reputation of
+//! its IP address, when a chargeback is added or withdrawn. Every recorded outcome is published
+//! as a `transaction.reported` event, which outcome analytics are built from, labels the
+//! transaction for model training unless it was labeled by hand, and has the user's risk score
+//! recalculated.
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+
+use super::{ServiceError, ServiceResult, ip_reputation::refresh_ip_reputation};
+use crate::{
+    database::{
+        Tenant,
+        repositories::{
+            DeviceRepo, LabelRepo, OutboxRepo, ReportTargetRecord, TransactionRepo,
+            TransactionReportRecord, UserRepo,
+        },
+    },
+    models::{
+        label::{Label, LabelSource},
+        outcome::{OutcomeReportRequest, TransactionOutcome},
+        transaction::ReportTag,
+    },
+    outbox::{TRANSACTION_REPORTED, TransactionReported},
+};
+
+impl From<TransactionReportRecord> for TransactionOutcome {
+    fn from(record: TransactionReportRecord) -> Self {
+        TransactionOutcome {
+            id: record.id,
+            transaction_id: record.transaction_id,
+            tag: record.tag,
+            chargeback_code: record.chargeback_code,
+            notes: record.notes,
+            occurred_at: record.occurred_at,
+            submitted_at: record.updated_at,
+            links: TransactionOutcome::links(record.transaction_id),
+        }
+    }
+}
+
+/// Outcome to record for a transaction
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Outcome<'a> {
+    /// What happened
+    pub tag: ReportTag,
+    /// Processor reason code, for chargebacks
+    pub chargeback_code: Option<&'a str>,
+    /// Free-form notes
+    pub notes: Option<&'a str>,
+    /// When the outcome occurred
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Record `outcome` for a transaction, returning the stored outcome, or `None` if one with the
+/// same tag is on record already
+///
+/// An outcome with another tag is replaced only if `replace` is set. Must run inside a
+/// database transaction, which locks the outcome on record until it commits.
+pub(crate) async fn record_outcome(
+    conn: &mut PgConnection,
+    tenant: Tenant,
+    transaction: &ReportTargetRecord,
+    outcome: Outcome<'_>,
+    replace: bool,
+) -> sqlx::Result<Option<TransactionReportRecord>> {
+    let previous = TransactionRepo::lock_report(&mut *conn, transaction.id)
+        .await?
+        .map(|report| report.tag);
+    match previous {
+        Some(tag) if tag == outcome.tag => return Ok(None),
+        Some(_) if !replace => return Ok(None),
+        _ => {},
+    }
+    let report = TransactionRepo::replace_report(
+        &mut *conn,
+        transaction.id,
+        outcome.tag,
+        outcome.chargeback_code,
+        outcome.notes,
+        outcome.occurred_at,
+    )
+    .await?;
+    LabelRepo::upsert_derived(
+        &mut *conn,
+        transaction.id,
+        Label::from_outcome(outcome.tag, outcome.chargeback_code),
+        LabelSource::Report,
+    )
+    .await?;
+
+    let delta = i32::from(outcome.tag == ReportTag::Chargeback)
+        - i32::from(previous == Some(ReportTag::Chargeback));
+    if let Some(user_id) = transaction.user_id {
+        UserRepo::add_chargebacks(&mut *conn, tenant, user_id, delta).await?;
+    }
+    if delta != 0 {
+        TransactionRepo::add_card_chargebacks(&mut *conn, transaction.id, delta).await?;
+        DeviceRepo::add_chargebacks(&mut *conn, transaction.id, delta).await?;
+        if let Some(ip_address) = &transaction.ip_address {
+            refresh_ip_reputation(&mut *conn, tenant, ip_address).await?;
+        }
+    }
+    let payload = serde_json::to_value(TransactionReported {
+        transaction_id: transaction.id,
+        tag: outcome.tag,
+        chargeback_code: outcome.chargeback_code.map(str::to_string),
+        occurred_at: outcome.occurred_at,
+    })
+    .unwrap_or_default();
+    OutboxRepo::insert(
+        &mut *conn,
+        tenant.id(),
+        TRANSACTION_REPORTED,
+        transaction.id,
+        payload,
+    )
+    .await?;
+    Ok(Some(report))
+}
+
+/// Outcome reporting
+#[derive(Debug, Clone)]
+pub struct OutcomeService {
+    pool: PgPool,
+}
+
+impl OutcomeService {
+    /// Create a new outcome service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record the outcome a merchant reports for one of its transactions
+    ///
+    /// The merchant's word outranks whatever was on record, so a different outcome is
+    /// replaced; reporting the outcome already on record is a conflict.
+    pub async fn report(
+        &self,
+        tenant: Tenant,
+        request: &OutcomeReportRequest,
+    ) -> ServiceResult<TransactionOutcome> {
+        request.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        let transaction =
+            TransactionRepo::find_report_target(&mut *tx, tenant, request.transaction_id)
+                .await?
+                .ok_or(ServiceError::NotFound)?;
+        let outcome = Outcome {
+            tag: request.tag,
+            chargeback_code: request.chargeback_code.as_deref().map(str::trim),
+            notes: request.notes.as_deref(),
+            occurred_at: request.occurred_at.unwrap_or_else(Utc::now),
+        };
+        let report = record_outcome(&mut tx, tenant, &transaction, outcome, true)
+            .await?
+            .ok_or_else(|| {
+                ServiceError::Conflict(
+                    "The transaction already has this outcome on record".to_string(),
+                )
+            })?;
+        tx.commit().await?;
+        tracing::info!(
+            transaction_id = %transaction.id,
+            tag = ?report.tag,
+            account_id = %tenant,
+            "Transaction outcome reported"
+        );
+        Ok(report.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        Config,
+        database::repositories::AccountRepo,
+        models::{account::SubscriptionTier, transaction::TransactionRequest},
+        scoring::RiskEngine,
+        services::TransactionService,
+        test_support::{create_account, test_pool},
+    };
+
+    /// Chargeback counts of the account's user, card, and device
+    async fn chargeback_counts(pool: &PgPool, account_id: Uuid) -> (i32, i32, i32) {
+        sqlx::query_as(
+            "SELECT \
+                (SELECT chargeback_count FROM users WHERE account_id = $1), \
+                (SELECT chargeback_count FROM credit_cards WHERE account_id = $1), \
+                (SELECT chargeback_count FROM devices WHERE account_id = $1)",
+        )
+        .bind(account_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reported_chargebacks_are_counted_and_withdrawn() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let outcomes = OutcomeService::new(pool.clone());
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "203.0.113.45" },
+            "event": { "type": "purchase" },
+            "account": { "user_id": "reported-user" },
+            "credit_card": { "issuer_id_number": "411111", "last_digits": "1111" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        let assessment = RiskEngine::new().assess(&request, &user);
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+
+        let report = |tag, chargeback_code: Option<&str>| OutcomeReportRequest {
+            transaction_id: stored.id,
+            tag,
+            chargeback_code: chargeback_code.map(str::to_string),
+            notes: None,
+            occurred_at: None,
+        };
+        let outcome = outcomes
+            .report(tenant, &report(ReportTag::Chargeback, Some("4837")))
+            .await
+            .unwrap();
+        assert_eq!(outcome.tag, ReportTag::Chargeback);
+        assert_eq!(outcome.chargeback_code.as_deref(), Some("4837"));
+        assert_eq!(chargeback_counts(&pool, account_id).await, (1, 1, 1));
+
+        // The same outcome again is a conflict and counted once
+        assert!(matches!(
+            outcomes
+                .report(tenant, &report(ReportTag::Chargeback, None))
+                .await,
+            Err(ServiceError::Conflict(_))
+        ));
+        assert_eq!(chargeback_counts(&pool, account_id).await, (1, 1, 1));
+
+        // A false positive withdraws the chargeback
+        let outcome = outcomes
+            .report(tenant, &report(ReportTag::NotFraud, None))
+            .await
+            .unwrap();
+        assert_eq!(outcome.chargeback_code, None);
+        assert_eq!(chargeback_counts(&pool, account_id).await, (0, 0, 0));
+        let reported: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM outbox_events WHERE account_id = $1 AND event_type = $2",
+        )
+        .bind(account_id)
+        .bind(TRANSACTION_REPORTED)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(reported, 2);
+
+        // Transactions belong to their account
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(matches!(
+            outcomes
+                .report(other, &report(ReportTag::Chargeback, None))
+                .await,
+            Err(ServiceError::NotFound)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
@@ -0,0 +1,261 @@
+//! Phone number intelligence
+//!
+//! Numbers are checked against a numbering plan bundled with the service, listing the ranges
+//! each country calling code allocates with their country, lengths, line type, and for some the
+//! operator they were allocated to. That tells valid numbers from mistyped or made-up ones, and
+//! VoIP numbers, which anyone can get without a SIM card or an address, from mobile and fixed
+//! lines. The North American plan does not tell mobile, fixed, and VoIP numbers apart.
+
+use std::{collections::HashMap, ops::RangeInclusive, sync::LazyLock};
+
+use crate::{
+    models::{insights::PhoneLineType, transaction::TransactionRequest},
+    scoring::PhoneNumberInfo,
+};
+
+/// Numbering plan bundled with the service
+const BUNDLED_PLAN: &str = include_str!("../../data/phone_numbering_plan.csv");
+
+/// The bundled numbering plan, parsed on first use
+static PLAN: LazyLock<NumberingPlan> = LazyLock::new(|| NumberingPlan::parse(BUNDLED_PLAN));
+
+/// Most digits in a country calling code
+const MAX_CALLING_CODE_DIGITS: usize = 3;
+
+/// A range of numbers allocated under a country calling code
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NumberRange {
+    /// Country the range is allocated to, unless it is shared
+    country: Option<String>,
+    /// Leading digits of the national significant numbers in the range
+    prefix: String,
+    /// Allowed lengths of the national significant numbers in the range
+    lengths: RangeInclusive<usize>,
+    /// Kind of line
+    line_type: PhoneLineType,
+    /// Operator the range was originally allocated to
+    operator: Option<String>,
+}
+
+/// Ranges of phone numbers, by country calling code
+#[derive(Debug, Clone, Default)]
+pub struct NumberingPlan {
+    ranges: HashMap<String, Vec<NumberRange>>,
+}
+
+impl NumberingPlan {
+    /// Parse a numbering plan in the format of the bundled one, skipping its header, comments,
+    /// and malformed lines
+    pub fn parse(csv: &str) -> Self {
+        let mut ranges: HashMap<String, Vec<NumberRange>> = HashMap::new();
+        for (calling_code, range) in csv.lines().filter_map(parse_range) {
+            ranges.entry(calling_code).or_default().push(range);
+        }
+        Self { ranges }
+    }
+
+    /// Ranges in the plan
+    pub fn len(&self) -> usize {
+        self.ranges.values().map(Vec::len).sum()
+    }
+
+    /// Whether the plan has no ranges
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// What the plan says about `number`, whose country calling code is `country_code`
+    ///
+    /// A number in international form, starting with `+` or `00`, carries its own calling code,
+    /// which takes precedence. National numbers may include their trunk prefix, such as the
+    /// leading 0 of most European numbers, or their calling code. Returns `None` for numbers
+    /// without digits and for calling codes that are unknown or that the plan does not cover.
+    pub fn lookup(&self, number: &str, country_code: Option<&str>) -> Option<PhoneNumberInfo> {
+        let number = number.trim();
+        let mut digits: String = number.chars().filter(char::is_ascii_digit).collect();
+        let calling_code = if number.starts_with('+') || number.starts_with("00") {
+            if number.starts_with("00") {
+                digits.drain(..2);
+            }
+            let len = (1..=MAX_CALLING_CODE_DIGITS.min(digits.len()))
+                .find(|&len| self.ranges.contains_key(&digits[..len]))?;
+            digits.drain(..len).collect()
+        } else {
+            country_code?
+                .chars()
+                .filter(char::is_ascii_digit)
+                .collect::<String>()
+        };
+        let ranges = self.ranges.get(&calling_code)?;
+        if digits.is_empty() {
+            return None;
+        }
+
+        let trunk = trunk_prefix(&calling_code);
+        let candidates = [
+            Some(digits.as_str()),
+            digits.strip_prefix(trunk),
+            digits.strip_prefix(calling_code.as_str()),
+        ];
+        let matched = candidates
+            .into_iter()
+            .flatten()
+            .find_map(|national| Some((national, best_range(ranges, national)?)));
+        Some(match matched {
+            Some((national, range)) => PhoneNumberInfo {
+                e164: format!("+{calling_code}{national}"),
+                is_valid: true,
+                country: range.country.clone(),
+                line_type: Some(range.line_type),
+                operator: range.operator.clone(),
+            },
+            None => PhoneNumberInfo {
+                e164: format!(
+                    "+{calling_code}{}",
+                    digits.strip_prefix(trunk).unwrap_or(&digits)
+                ),
+                is_valid: false,
+                country: None,
+                line_type: None,
+                operator: None,
+            },
+        })
+    }
+}
+
+/// What the bundled numbering plan says about `number`; see [`NumberingPlan::lookup`]
+pub fn lookup(number: &str, country_code: Option<&str>) -> Option<PhoneNumberInfo> {
+    PLAN.lookup(number, country_code)
+}
+
+/// What the bundled numbering plan says about the phone number of a transaction, if it has one
+pub fn lookup_request(request: &TransactionRequest) -> Option<PhoneNumberInfo> {
+    let address = request.phone_address()?;
+    lookup(
+        address.phone_number.as_deref()?,
+        address.phone_country_code.as_deref(),
+    )
+}
+
+/// Parse a line of a numbering plan into its calling code and range
+fn parse_range(line: &str) -> Option<(String, NumberRange)> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return None;
+    }
+    let mut fields = line.split(',').map(str::trim);
+    let calling_code = fields.next()?;
+    if calling_code.is_empty() || !calling_code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let country = fields.next()?;
+    let prefix = fields.next()?;
+    let lengths = fields.next()?;
+    let line_type = PhoneLineType::parse(fields.next()?)?;
+    let operator = fields.next().unwrap_or_default();
+
+    let (min, max) = lengths.split_once('-').unwrap_or((lengths, lengths));
+    let non_empty = |field: &str| (!field.is_empty()).then(|| field.to_string());
+    Some((
+        calling_code.to_string(),
+        NumberRange {
+            country: non_empty(country),
+            prefix: prefix.to_string(),
+            lengths: min.parse().ok()?..=max.parse().ok()?,
+            line_type,
+            operator: non_empty(operator),
+        },
+    ))
+}
+
+/// The range with the longest prefix among those `national` fits
+fn best_range<'a>(ranges: &'a [NumberRange], national: &str) -> Option<&'a NumberRange> {
+    ranges
+        .iter()
+        .filter(|range| {
+            national.starts_with(&range.prefix) && range.lengths.contains(&national.len())
+        })
+        .max_by_key(|range| range.prefix.len())
+}
+
+/// Digits dialled before national numbers within the countries of `calling_code`
+fn trunk_prefix(calling_code: &str) -> &'static str {
+    match calling_code {
+        "1" => "1",
+        "7" => "8",
+        _ => "0",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_plan_parses_every_range() {
+        let ranges = BUNDLED_PLAN
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .count();
+        // Less the header
+        assert_eq!(PLAN.len(), ranges - 1);
+    }
+
+    #[test]
+    fn test_lookup_national_and_international_forms() {
+        let mobile = lookup("07700 900123", Some("44")).unwrap();
+        assert_eq!(mobile.e164, "+447700900123");
+        assert!(mobile.is_valid);
+        assert_eq!(mobile.country.as_deref(), Some("GB"));
+        assert_eq!(mobile.line_type, Some(PhoneLineType::Mobile));
+
+        // The number's own calling code wins over the address's
+        for number in ["+44 (0)7700 900123", "0044 7700 900123", "447700900123"] {
+            assert_eq!(lookup(number, Some("+44")).unwrap().e164, "+447700900123");
+        }
+        assert_eq!(
+            lookup("+44 7700 900123", Some("1")).unwrap().e164,
+            "+447700900123"
+        );
+
+        // Trunk prefixes other than 0, and the Italian 0 that is part of the number
+        let moscow = lookup("8 (495) 123-45-67", Some("7")).unwrap();
+        assert_eq!(moscow.e164, "+74951234567");
+        assert_eq!(moscow.country.as_deref(), Some("RU"));
+        let rome = lookup("06 1234 5678", Some("39")).unwrap();
+        assert_eq!(rome.e164, "+390612345678");
+        assert_eq!(rome.line_type, Some(PhoneLineType::FixedLine));
+    }
+
+    #[test]
+    fn test_lookup_line_types_countries_and_operators() {
+        let voip = lookup("056 1234 5678", Some("44")).unwrap();
+        assert_eq!(voip.line_type, Some(PhoneLineType::Voip));
+        let ip_phone = lookup("+81 50 1234 5678", None).unwrap();
+        assert_eq!(ip_phone.line_type, Some(PhoneLineType::Voip));
+
+        let toronto = lookup("1 (416) 555-0123", Some("1")).unwrap();
+        assert_eq!(toronto.country.as_deref(), Some("CA"));
+        assert_eq!(toronto.line_type, Some(PhoneLineType::FixedLineOrMobile));
+        let toll_free = lookup("800-555-0123", Some("1")).unwrap();
+        assert_eq!(toll_free.line_type, Some(PhoneLineType::TollFree));
+        assert_eq!(toll_free.country, None);
+
+        let german = lookup("0171 2345678", Some("49")).unwrap();
+        assert_eq!(german.operator.as_deref(), Some("Telekom"));
+        assert_eq!(german.line_type, Some(PhoneLineType::Mobile));
+    }
+
+    #[test]
+    fn test_lookup_invalid_and_uncovered_numbers() {
+        let short = lookup("07700 9001", Some("44")).unwrap();
+        assert!(!short.is_valid);
+        assert_eq!(short.e164, "+4477009001");
+        assert_eq!(short.line_type, None);
+
+        // Calling codes outside the plan, and numbers without one, are not judged
+        assert_eq!(lookup("0912 345 678", Some("886")), None);
+        assert_eq!(lookup("212-555-0123", None), None);
+        assert_eq!(lookup("n/a", Some("44")), None);
+    }
+}
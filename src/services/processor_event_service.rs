@@ -0,0 +1,490 @@
+//! Recording of the disputes and refunds payment processors report
+//!
+//! A dispute records a chargeback for its transaction, replacing any other outcome on record:
+//! the processor's word outranks a reviewer's judgement or the customer's earlier report. A
+//! refund the processor flags as fraudulent records suspected fraud unless an outcome is on
+//! record already. Outcomes are recorded like those merchants report, counters and events
+//! included. Processors redeliver webhooks, so an outcome already on record is left alone.
+//!
+//! Disputes are also followed through their lifecycle, from receipt through the merchant's
+//! representment to being won or lost, with the disputed amount, so analytics can weigh fraud
+//! losses by what was actually lost. Every change of stage is published as a
+//! `dispute.updated` event.
+//!
+//! Webhooks are accepted only when signed with the secret the account stored for the
+//! processor.
+
+use std::time::Duration;
+
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use super::{
+    ServiceError, ServiceResult,
+    outcome_service::{Outcome, record_outcome},
+};
+use crate::{
+    database::{
+        Tenant,
+        repositories::{
+            DisputeProgress, DisputeRecord, DisputeRepo, OutboxRepo, ProcessorSecretRepo,
+            TransactionRepo,
+        },
+    },
+    ingest::{self, ProcessorEvent},
+    models::{
+        dispute::{Dispute, DisputeStatus},
+        processor_event::{
+            IngestOutcome, IngestedEvent, Processor, ProcessorEventKind, ProcessorEventReceipt,
+        },
+        transaction::ReportTag,
+    },
+    outbox::DISPUTE_UPDATED,
+};
+
+impl From<DisputeRecord> for Dispute {
+    fn from(record: DisputeRecord) -> Self {
+        Dispute {
+            id: record.id,
+            transaction_id: record.transaction_id,
+            processor: record.processor,
+            reference: record.reference,
+            status: record.status,
+            amount: record.amount,
+            currency: record.currency,
+            reason_code: record.reason_code,
+            received_at: record.received_at,
+            representment_at: record.representment_at,
+            resolved_at: record.resolved_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+/// Processor webhook ingestion
+#[derive(Debug, Clone)]
+pub struct ProcessorEventService {
+    pool: PgPool,
+}
+
+impl ProcessorEventService {
+    /// Create a new processor event service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store the secret `processor` signs the account's webhooks with
+    pub async fn set_secret(
+        &self,
+        tenant: Tenant,
+        processor: Processor,
+        secret: &str,
+    ) -> ServiceResult<()> {
+        let secret = secret.trim();
+        if secret.is_empty() {
+            return Err(ServiceError::Invalid(
+                "secret must not be empty".to_string(),
+            ));
+        }
+        if processor == Processor::Adyen && hex::decode(secret).is_err() {
+            return Err(ServiceError::Invalid(
+                "An Adyen HMAC key must be hex".to_string(),
+            ));
+        }
+        ProcessorSecretRepo::upsert(&self.pool, tenant, processor, secret).await?;
+        Ok(())
+    }
+
+    /// Forget the secret of `processor`, refusing its webhooks from then on
+    pub async fn remove_secret(&self, tenant: Tenant, processor: Processor) -> ServiceResult<()> {
+        if !ProcessorSecretRepo::delete(&self.pool, tenant, processor).await? {
+            return Err(ServiceError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Whether a webhook sent to an account was signed by `processor` with the account's secret
+    ///
+    /// `signature` is the `Stripe-Signature` header, if sent. An account that stored no secret
+    /// for the processor accepts none of its webhooks.
+    pub async fn verify(
+        &self,
+        tenant: Tenant,
+        processor: Processor,
+        signature: Option<&str>,
+        body: &[u8],
+        tolerance: Duration,
+    ) -> ServiceResult<bool> {
+        let Some(secret) = ProcessorSecretRepo::find(&self.pool, tenant, processor).await? else {
+            tracing::debug!(account_id = %tenant.id(), ?processor, "No processor secret stored");
+            return Ok(false);
+        };
+        match ingest::verify_signature(processor, &secret, signature, body, tolerance) {
+            Ok(()) => Ok(true),
+            Err(reason) => {
+                tracing::debug!(account_id = %tenant.id(), ?processor, reason, "Processor webhook signature mismatch");
+                Ok(false)
+            },
+        }
+    }
+
+    /// Record the outcomes a processor webhook reports for an account's transactions
+    ///
+    /// The processor is detected from the body unless given.
+    pub async fn ingest(
+        &self,
+        tenant: Tenant,
+        processor: Option<Processor>,
+        body: serde_json::Value,
+    ) -> ServiceResult<ProcessorEventReceipt> {
+        let processor = processor
+            .or_else(|| Processor::detect(&body))
+            .ok_or_else(|| {
+                ServiceError::Invalid("Body is not a webhook of a supported processor".to_string())
+            })?;
+        let events = ingest::parse(processor, body).map_err(ServiceError::Invalid)?;
+        let mut ingested = Vec::with_capacity(events.len());
+        for event in events {
+            ingested.push(self.record(tenant, processor, event).await?);
+        }
+        Ok(ProcessorEventReceipt {
+            processor,
+            events: ingested,
+        })
+    }
+
+    /// Disputes of one of an account's transactions, oldest first
+    pub async fn disputes(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> ServiceResult<Vec<Dispute>> {
+        TransactionRepo::find_report_target(&self.pool, tenant, transaction_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let records = DisputeRepo::for_transaction(&self.pool, tenant, transaction_id).await?;
+        Ok(records.into_iter().map(Dispute::from).collect())
+    }
+
+    async fn record(
+        &self,
+        tenant: Tenant,
+        processor: Processor,
+        event: ProcessorEvent,
+    ) -> ServiceResult<IngestedEvent> {
+        let mut ingested = IngestedEvent {
+            event_id: event.id,
+            kind: event.kind,
+            outcome: IngestOutcome::Ignored,
+            transaction_id: None,
+            tag: None,
+            dispute_status: None,
+        };
+        let tag = match event.kind {
+            ProcessorEventKind::Dispute => Some(ReportTag::Chargeback),
+            ProcessorEventKind::Refund if event.fraudulent => Some(ReportTag::SuspectedFraud),
+            ProcessorEventKind::DisputeUpdate if event.dispute.is_some() => None,
+            ProcessorEventKind::Refund
+            | ProcessorEventKind::DisputeUpdate
+            | ProcessorEventKind::Other => return Ok(ingested),
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let Some(transaction) =
+            TransactionRepo::find_by_external_ids(&mut *tx, tenant, &event.external_ids).await?
+        else {
+            ingested.outcome = IngestOutcome::Unmatched;
+            return Ok(ingested);
+        };
+        ingested.transaction_id = Some(transaction.id);
+        ingested.tag = tag;
+        let mut recorded = false;
+        if let Some(tag) = tag {
+            let outcome = Outcome {
+                tag,
+                chargeback_code: event.reason.as_deref(),
+                notes: None,
+                occurred_at: event.occurred_at,
+            };
+            // A dispute replaces whatever was on record; a fraudulent refund only fills a gap
+            let replace = tag == ReportTag::Chargeback;
+            recorded = record_outcome(&mut tx, tenant, &transaction, outcome, replace)
+                .await?
+                .is_some();
+        }
+        if let Some(dispute) = &event.dispute {
+            let progress = DisputeProgress {
+                transaction_id: transaction.id,
+                processor,
+                reference: &dispute.reference,
+                status: dispute.status,
+                amount: dispute.amount,
+                currency: dispute.currency.as_deref(),
+                reason_code: event.reason.as_deref(),
+                occurred_at: event.occurred_at,
+            };
+            let status = record_dispute(&mut tx, tenant, &progress).await?;
+            recorded |= status.is_some();
+            ingested.dispute_status = status;
+        }
+        if !recorded {
+            ingested.outcome = IngestOutcome::AlreadyRecorded;
+            return Ok(ingested);
+        }
+        tx.commit().await?;
+        tracing::info!(
+            ?processor,
+            event_id = %ingested.event_id,
+            transaction_id = %transaction.id,
+            ?tag,
+            dispute_status = ?ingested.dispute_status,
+            account_id = %tenant,
+            "Processor event recorded"
+        );
+        ingested.outcome = IngestOutcome::Recorded;
+        Ok(ingested)
+    }
+}
+
+/// Record the stage a dispute reached and publish the change, returning the dispute's stage, or
+/// `None` if nothing changed
+async fn record_dispute(
+    conn: &mut PgConnection,
+    tenant: Tenant,
+    progress: &DisputeProgress<'_>,
+) -> sqlx::Result<Option<DisputeStatus>> {
+    let Some(record) = DisputeRepo::record_progress(&mut *conn, tenant, progress).await? else {
+        return Ok(None);
+    };
+    let dispute = Dispute::from(record);
+    let payload = serde_json::to_value(&dispute).unwrap_or_default();
+    OutboxRepo::insert(
+        &mut *conn,
+        tenant.id(),
+        DISPUTE_UPDATED,
+        dispute.id,
+        payload,
+    )
+    .await?;
+    Ok(Some(dispute.status))
+}
+
+#[cfg(test)]
+mod tests {
+    use hmac::{Hmac, Mac};
+    use serde_json::json;
+    use sha2::Sha256;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        Config,
+        database::repositories::{AccountRepo, IpAddressRepo},
+        models::{account::SubscriptionTier, transaction::TransactionRequest},
+        outbox::TRANSACTION_REPORTED,
+        scoring::RiskEngine,
+        services::TransactionService,
+        test_support::{create_account, test_pool},
+    };
+
+    fn dispute(event_id: &str, payment_intent: &str) -> serde_json::Value {
+        dispute_event(
+            event_id,
+            "charge.dispute.created",
+            "needs_response",
+            payment_intent,
+        )
+    }
+
+    fn dispute_event(
+        event_id: &str,
+        event_type: &str,
+        status: &str,
+        payment_intent: &str,
+    ) -> serde_json::Value {
+        json!({
+            "id": event_id,
+            "object": "event",
+            "type": event_type,
+            "created": 1_750_000_000,
+            "data": { "object": {
+                "id": "dp_1",
+                "object": "dispute",
+                "amount": 14999,
+                "currency": "usd",
+                "charge": "ch_unknown",
+                "payment_intent": payment_intent,
+                "reason": "fraudulent",
+                "status": status
+            } }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_webhooks_need_the_stored_secret() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let processor_events = ProcessorEventService::new(pool.clone());
+        let tolerance = Duration::from_secs(300);
+        let body = serde_json::to_vec(&dispute("evt_signed", "pi_signed")).unwrap();
+        let timestamp = chrono::Utc::now().timestamp();
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"whsec_test").unwrap();
+        mac.update(format!("{timestamp}.").as_bytes());
+        mac.update(&body);
+        let signature = format!(
+            "t={timestamp},v1={}",
+            hex::encode(mac.finalize().into_bytes())
+        );
+        let verify = |signature| {
+            processor_events.verify(tenant, Processor::Stripe, signature, &body, tolerance)
+        };
+
+        assert!(!verify(Some(&signature)).await.unwrap());
+        processor_events
+            .set_secret(tenant, Processor::Stripe, "whsec_test")
+            .await
+            .unwrap();
+        assert!(verify(Some(&signature)).await.unwrap());
+        assert!(!verify(None).await.unwrap());
+        assert!(matches!(
+            processor_events
+                .set_secret(tenant, Processor::Adyen, "not hex")
+                .await,
+            Err(ServiceError::Invalid(_))
+        ));
+
+        processor_events
+            .remove_secret(tenant, Processor::Stripe)
+            .await
+            .unwrap();
+        assert!(!verify(Some(&signature)).await.unwrap());
+
+        AccountRepo::delete(&pool, tenant.id()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disputes_record_chargebacks_once() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let processor_events = ProcessorEventService::new(pool.clone());
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "203.0.113.44" },
+            "event": { "type": "purchase", "transaction_id": "pi_disputed" },
+            "account": { "user_id": "disputed-user" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        let assessment = RiskEngine::new().assess(&request, &user);
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+
+        let receipt = processor_events
+            .ingest(tenant, None, dispute("evt_1", "pi_disputed"))
+            .await
+            .unwrap();
+        assert_eq!(receipt.processor, Processor::Stripe);
+        let event = &receipt.events[0];
+        assert_eq!(event.outcome, IngestOutcome::Recorded);
+        assert_eq!(event.transaction_id, Some(stored.id));
+        assert_eq!(event.tag, Some(ReportTag::Chargeback));
+        let ip_history = IpAddressRepo::find(&pool, tenant, "203.0.113.44")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ip_history.history.chargeback_count, 1);
+
+        // A redelivered dispute is not counted twice
+        let receipt = processor_events
+            .ingest(
+                tenant,
+                Some(Processor::Stripe),
+                dispute("evt_1", "pi_disputed"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(receipt.events[0].outcome, IngestOutcome::AlreadyRecorded);
+        let chargebacks: i32 =
+            sqlx::query_scalar("SELECT chargeback_count FROM users WHERE account_id = $1")
+                .bind(account_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(chargebacks, 1);
+        let reported: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM outbox_events WHERE account_id = $1 AND event_type = $2",
+        )
+        .bind(account_id)
+        .bind(TRANSACTION_REPORTED)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(reported, 1);
+
+        // The dispute is followed to its outcome
+        let won = dispute_event("evt_3", "charge.dispute.closed", "won", "pi_disputed");
+        let receipt = processor_events
+            .ingest(tenant, None, won.clone())
+            .await
+            .unwrap();
+        let event = &receipt.events[0];
+        assert_eq!(event.kind, ProcessorEventKind::DisputeUpdate);
+        assert_eq!(event.outcome, IngestOutcome::Recorded);
+        assert_eq!(event.dispute_status, Some(DisputeStatus::Won));
+        let disputes = processor_events.disputes(tenant, stored.id).await.unwrap();
+        assert_eq!(disputes.len(), 1);
+        assert_eq!(disputes[0].reference, "dp_1");
+        assert_eq!(disputes[0].status, DisputeStatus::Won);
+        assert_eq!(disputes[0].amount, Some(149.99));
+        assert_eq!(disputes[0].currency.as_deref(), Some("USD"));
+        assert!(disputes[0].resolved_at.is_some());
+        let receipt = processor_events.ingest(tenant, None, won).await.unwrap();
+        assert_eq!(receipt.events[0].outcome, IngestOutcome::AlreadyRecorded);
+        // A late copy of the opening event does not reopen it
+        processor_events
+            .ingest(tenant, None, dispute("evt_1", "pi_disputed"))
+            .await
+            .unwrap();
+        let disputes = processor_events.disputes(tenant, stored.id).await.unwrap();
+        assert_eq!(disputes[0].status, DisputeStatus::Won);
+        let updates: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM outbox_events WHERE account_id = $1 AND event_type = $2",
+        )
+        .bind(account_id)
+        .bind(DISPUTE_UPDATED)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(updates, 2);
+
+        // Payments scored under other IDs, or by other accounts, are not matched
+        let receipt = processor_events
+            .ingest(tenant, None, dispute("evt_2", "pi_elsewhere"))
+            .await
+            .unwrap();
+        assert_eq!(receipt.events[0].outcome, IngestOutcome::Unmatched);
+        let other = Tenant::trusted(Uuid::new_v4());
+        let receipt = processor_events
+            .ingest(other, None, dispute("evt_1", "pi_disputed"))
+            .await
+            .unwrap();
+        assert_eq!(receipt.events[0].outcome, IngestOutcome::Unmatched);
+        assert!(matches!(
+            processor_events
+                .ingest(tenant, None, json!({ "object": "charge" }))
+                .await,
+            Err(ServiceError::Invalid(_))
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+//! Access to generated reports
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    database::{
+        Tenant,
+        repositories::{ReportRecord, ReportRepo},
+    },
+    models::{
+        common::{Link, Links},
+        report::{Report, ReportFrequency},
+    },
+};
+
+impl From<ReportRecord> for Report {
+    fn from(record: ReportRecord) -> Self {
+        let content = record.content.0;
+        Report {
+            id: record.id,
+            frequency: record.frequency,
+            period_start: record.period_start,
+            period_end: record.period_end,
+            summary: content.summary,
+            top_rules: content.top_rules,
+            top_risky_users: content.top_risky_users,
+            export_location: record.export_location,
+            created_at: record.created_at,
+            links: Links {
+                self_link: Some(Link::new(format!("/v1/reports/{}", record.id))),
+                ..Links::default()
+            },
+        }
+    }
+}
+
+/// Serves the reports generated for each account
+#[derive(Debug, Clone)]
+pub struct ReportService {
+    read_pool: PgPool,
+}
+
+impl ReportService {
+    /// Create a report service reading from `read_pool`
+    pub fn new(read_pool: PgPool) -> Self {
+        Self { read_pool }
+    }
+
+    /// Fetch one of an account's reports
+    pub async fn get_report(&self, tenant: Tenant, report_id: Uuid) -> ServiceResult<Report> {
+        ReportRepo::find(&self.read_pool, tenant, report_id)
+            .await?
+            .map(Report::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Page through an account's reports, returning the page and the total number of matches
+    pub async fn list_reports(
+        &self,
+        tenant: Tenant,
+        frequency: Option<ReportFrequency>,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<Report>, i64)> {
+        let reports = ReportRepo::list(&self.read_pool, tenant, frequency, limit, offset)
+            .await?
+            .into_iter()
+            .map(Report::from)
+            .collect();
+        let total = ReportRepo::count(&self.read_pool, tenant, frequency).await?;
+        Ok((reports, total))
+    }
+}
@@ -0,0 +1,222 @@
+//! Rule weight versions and the suggestions they are tuned from
+//!
+//! An account scores under the latest version of its rule weights. Versions are never edited:
+//! applying a suggestion creates the next version, carrying over the weights of the one before
+//! with the suggested rule reweighted, so earlier weights stay on record.
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    database::{
+        Tenant,
+        repositories::{RuleRepo, RuleSuggestionRecord, RuleVersionRecord},
+    },
+    models::rule::{RuleSuggestion, RuleVersion},
+    scoring::RuleWeights,
+};
+
+impl From<RuleSuggestionRecord> for RuleSuggestion {
+    fn from(record: RuleSuggestionRecord) -> Self {
+        RuleSuggestion {
+            id: record.id,
+            rule_code: record.rule_code,
+            current_weight: record.current_weight,
+            suggested_weight: record.suggested_weight,
+            reported_transactions: record.reported_count,
+            fraudulent_transactions: record.fraud_count,
+            precision: f64::from(record.fraud_count) / f64::from(record.reported_count.max(1)),
+            baseline_precision: record.baseline_precision,
+            computed_at: record.created_at,
+        }
+    }
+}
+
+impl From<RuleVersionRecord> for RuleVersion {
+    fn from(record: RuleVersionRecord) -> Self {
+        RuleVersion {
+            version: record.version,
+            weights: record.weights.0.into_iter().collect(),
+            suggestion_id: record.suggestion_id,
+            created_at: record.created_at,
+        }
+    }
+}
+
+/// Rule weight management
+#[derive(Debug, Clone)]
+pub struct RuleService {
+    pool: PgPool,
+}
+
+impl RuleService {
+    /// Create a new rule service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Weights an account's transactions are scored under
+    pub async fn weights(&self, tenant: Tenant) -> ServiceResult<RuleWeights> {
+        Ok(RuleRepo::weights(&self.pool, tenant).await?)
+    }
+
+    /// An account's pending suggestions
+    pub async fn list_suggestions(&self, tenant: Tenant) -> ServiceResult<Vec<RuleSuggestion>> {
+        let records = RuleRepo::pending_suggestions(&self.pool, tenant).await?;
+        Ok(records.into_iter().map(RuleSuggestion::from).collect())
+    }
+
+    /// Every version of an account's rule weights, newest first
+    pub async fn list_versions(&self, tenant: Tenant) -> ServiceResult<Vec<RuleVersion>> {
+        let records = RuleRepo::versions(&self.pool, tenant).await?;
+        Ok(records.into_iter().map(RuleVersion::from).collect())
+    }
+
+    /// Apply a pending suggestion, creating the next version of the account's rule weights
+    pub async fn apply_suggestion(
+        &self,
+        tenant: Tenant,
+        suggestion_id: Uuid,
+    ) -> ServiceResult<RuleVersion> {
+        let mut tx = self.pool.begin().await?;
+        let suggestion = RuleRepo::lock_suggestion(&mut *tx, tenant, suggestion_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        if suggestion.applied_at.is_some() {
+            return Err(ServiceError::Conflict(
+                "The suggestion was applied already".to_string(),
+            ));
+        }
+        let RuleWeights {
+            version,
+            mut weights,
+        } = RuleRepo::weights(&mut *tx, tenant).await?;
+        if suggestion.suggested_weight == 1.0 {
+            weights.remove(&suggestion.rule_code);
+        } else {
+            weights.insert(suggestion.rule_code.clone(), suggestion.suggested_weight);
+        }
+        let record =
+            RuleRepo::insert_version(&mut *tx, tenant, version + 1, &weights, Some(suggestion.id))
+                .await
+                .map_err(|e| {
+                    if e.as_database_error()
+                        .is_some_and(|db| db.is_unique_violation())
+                    {
+                        ServiceError::Conflict(
+                            "The rule weights changed while the suggestion was applied; retry"
+                                .to_string(),
+                        )
+                    } else {
+                        ServiceError::Database(e)
+                    }
+                })?;
+        RuleRepo::mark_applied(&mut *tx, suggestion.id, record.version).await?;
+        tx.commit().await?;
+        tracing::info!(
+            account_id = %tenant,
+            rule_code = %suggestion.rule_code,
+            weight = suggestion.suggested_weight,
+            version = record.version,
+            "Rule weight suggestion applied"
+        );
+        Ok(record.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        Config,
+        database::repositories::{AccountRepo, TransactionRepo},
+        models::{
+            account::SubscriptionTier,
+            transaction::{ReportTag, TransactionRequest},
+        },
+        rule_tuning::recompute_suggestions,
+        scoring::RiskEngine,
+        services::TransactionService,
+        test_support::{create_account, test_pool},
+    };
+
+    #[tokio::test]
+    async fn test_applied_suggestions_create_rule_versions() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let rules = RuleService::new(pool.clone());
+
+        // Orders without a user agent are all fraud, the others half of them
+        for i in 0..4 {
+            let request: TransactionRequest = serde_json::from_value(json!({
+                "device": if i < 2 {
+                    json!({ "ip_address": "198.51.100.1" })
+                } else {
+                    json!({ "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" })
+                },
+                "event": { "type": "purchase" }
+            }))
+            .unwrap();
+            let assessment = RiskEngine::new().assess(&request, &Default::default());
+            let stored = transactions
+                .store_transaction(tenant, &request, &assessment, &[])
+                .await
+                .unwrap();
+            let tag = if i < 3 {
+                ReportTag::Chargeback
+            } else {
+                ReportTag::NotFraud
+            };
+            TransactionRepo::replace_report(&pool, stored.id, tag, None, None, chrono::Utc::now())
+                .await
+                .unwrap();
+        }
+
+        let config = crate::config::RuleSuggestionsConfig {
+            min_reported: 2,
+            ..Config::default().rule_suggestions
+        };
+        recompute_suggestions(&pool, &config, chrono::Utc::now())
+            .await
+            .unwrap();
+        let suggestions = rules.list_suggestions(tenant).await.unwrap();
+        assert_eq!(suggestions.len(), 1);
+        let suggestion = &suggestions[0];
+        assert_eq!(suggestion.rule_code, "MISSING_USER_AGENT");
+        assert_eq!(suggestion.precision, 1.0);
+        assert_eq!(suggestion.suggested_weight, 1.33);
+
+        let version = rules.apply_suggestion(tenant, suggestion.id).await.unwrap();
+        assert_eq!(version.version, 1);
+        assert_eq!(version.weights["MISSING_USER_AGENT"], 1.33);
+        assert_eq!(rules.weights(tenant).await.unwrap().version, 1);
+        assert!(rules.list_suggestions(tenant).await.unwrap().is_empty());
+        assert!(matches!(
+            rules.apply_suggestion(tenant, suggestion.id).await,
+            Err(ServiceError::Conflict(_))
+        ));
+
+        // The weight in force is not suggested again
+        recompute_suggestions(&pool, &config, chrono::Utc::now())
+            .await
+            .unwrap();
+        assert!(rules.list_suggestions(tenant).await.unwrap().is_empty());
+
+        // Suggestions belong to their account
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(matches!(
+            rules.apply_suggestion(other, suggestion.id).await,
+            Err(ServiceError::NotFound)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
@@ -0,0 +1,722 @@
+//! Sanctions screening
+//!
+//! Names, optionally with a country, are screened against sanctions lists in the formats their
+//! publishers distribute them in: OFAC's SDN and consolidated lists as CSV files of primary
+//! names (`sdn.csv`, `cons_prim.csv`) with companion files of aliases (`alt.csv`) and addresses
+//! (`add.csv`), and the EU consolidated financial sanctions list as its semicolon-separated CSV
+//! file. Lists are downloaded periodically from the configured URLs and kept in memory by each
+//! instance. Nothing is bundled, so screening is unavailable until a download succeeds.
+//!
+//! Names are compared word by word with [`matching::full_name_match`], so words only one of
+//! the names has count against a match. Only listed names sharing a word, or its Soundex code,
+//! with the screened name are compared. Where both the screened party and the listed one have
+//! countries, a match also needs a country in common.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use super::bin_intel::split_delimited;
+use crate::{
+    config::ScreeningConfig,
+    models::{
+        screening::{SanctionsList, ScreeningListStatus, ScreeningMatch, ScreeningResult},
+        transaction::TransactionRequest,
+    },
+    scheduler::ScheduledTask,
+    scoring::SanctionsHit,
+    utils::matching,
+};
+
+/// Country names bundled with the service, for lists that name countries
+const BUNDLED_COUNTRY_NAMES: &str = include_str!("../../data/country_names.csv");
+
+/// Most matches reported for one name
+const MAX_MATCHES: usize = 10;
+
+/// Placeholder OFAC files use for empty fields
+const OFAC_NULL: &str = "-0-";
+
+/// A party on a sanctions list
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanctionedParty {
+    /// Identifier of the party on its list
+    pub id: String,
+    /// Name the party is listed under
+    pub name: String,
+    /// Other names the party is known by
+    pub aliases: Vec<String>,
+    /// Kind of party, as the list describes it: individual, entity, vessel, ...
+    pub party_type: Option<String>,
+    /// Sanctions programs or regimes the party is listed under
+    pub programs: Vec<String>,
+    /// ISO 3166-1 alpha-2 codes of the countries of its addresses and citizenships
+    pub countries: Vec<String>,
+}
+
+/// One of the files an OFAC list is distributed as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfacFile {
+    /// Primary names: `sdn.csv` or `cons_prim.csv`
+    Primary,
+    /// Aliases: `alt.csv` or `cons_alt.csv`
+    Aliases,
+    /// Addresses: `add.csv` or `cons_add.csv`
+    Addresses,
+}
+
+impl OfacFile {
+    /// The file a URL serves, going by its file name
+    pub fn from_url(url: &str) -> Self {
+        let name = url
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if name.ends_with("alt.csv") {
+            OfacFile::Aliases
+        } else if name.ends_with("add.csv") {
+            OfacFile::Addresses
+        } else {
+            OfacFile::Primary
+        }
+    }
+}
+
+/// Records of a CSV file with `delimiter`, skipping blank lines and lines that do not parse
+fn records(body: &str, delimiter: char) -> impl Iterator<Item = Vec<String>> + '_ {
+    body.lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(move |line| split_delimited(line, delimiter).ok())
+}
+
+/// A field of an OFAC record, or `None` if it is missing or empty
+fn ofac_field(record: &[String], index: usize) -> Option<&str> {
+    record
+        .get(index)
+        .map(|field| field.trim())
+        .filter(|field| !field.is_empty() && *field != OFAC_NULL)
+}
+
+/// Parties of an OFAC list, from its files of primary names, aliases, and addresses
+///
+/// Aliases and addresses of parties missing from the primary names are ignored.
+pub fn parse_ofac(files: &[(OfacFile, String)]) -> Vec<SanctionedParty> {
+    let countries = CountryNames::bundled();
+    let mut parties = Vec::new();
+    let mut by_id = HashMap::new();
+    for (_, body) in files.iter().filter(|(file, _)| *file == OfacFile::Primary) {
+        for record in records(body, ',') {
+            let (Some(id), Some(name)) = (ofac_field(&record, 0), ofac_field(&record, 1)) else {
+                continue;
+            };
+            let programs = ofac_field(&record, 3)
+                .map(|programs| {
+                    programs
+                        .split(['[', ']'])
+                        .map(str::trim)
+                        .filter(|program| !program.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
+            by_id.insert(id.to_string(), parties.len());
+            parties.push(SanctionedParty {
+                id: id.to_string(),
+                name: name.to_string(),
+                aliases: Vec::new(),
+                // OFAC leaves the type of entities empty
+                party_type: Some(ofac_field(&record, 2).unwrap_or("entity").to_lowercase()),
+                programs,
+                countries: Vec::new(),
+            });
+        }
+    }
+    for (file, body) in files {
+        let field = match file {
+            OfacFile::Primary => continue,
+            OfacFile::Aliases => 3,
+            OfacFile::Addresses => 4,
+        };
+        for record in records(body, ',') {
+            let party = ofac_field(&record, 0).and_then(|id| by_id.get(id));
+            let (Some(&party), Some(value)) = (party, ofac_field(&record, field)) else {
+                continue;
+            };
+            let party: &mut SanctionedParty = &mut parties[party];
+            match file {
+                OfacFile::Aliases => push_unique(&mut party.aliases, value.to_string()),
+                _ => {
+                    if let Some(code) = countries.code(value) {
+                        push_unique(&mut party.countries, code.to_string());
+                    }
+                },
+            }
+        }
+    }
+    parties
+}
+
+/// Parties of the EU consolidated financial sanctions list, in its CSV form, or a
+/// description of why the file cannot be read
+///
+/// The file has a row per combination of a party's names, addresses, citizenships, and other
+/// details, with the party's logical ID on each.
+pub fn parse_eu(body: &str) -> Result<Vec<SanctionedParty>, String> {
+    let mut records = records(body, ';');
+    let header = records.next().ok_or("the file has no header")?;
+    let column = |name: &str| header.iter().position(|column| column.trim() == name);
+    let id_column = column("Entity_LogicalId").ok_or("the header has no Entity_LogicalId")?;
+    let name_column =
+        column("NameAlias_WholeName").ok_or("the header has no NameAlias_WholeName")?;
+    let type_column = column("Entity_SubjectType_ClassificationCode");
+    let program_column = column("Entity_Regulation_Programme");
+    let country_columns: Vec<usize> = ["Address_CountryIso2Code", "Citizenship_CountryIso2Code"]
+        .into_iter()
+        .filter_map(column)
+        .collect();
+
+    let mut parties: Vec<SanctionedParty> = Vec::new();
+    let mut by_id = HashMap::new();
+    for record in records {
+        let field = |index: usize| {
+            record
+                .get(index)
+                .map(|field| field.trim())
+                .filter(|field| !field.is_empty())
+        };
+        let Some(id) = field(id_column) else {
+            continue;
+        };
+        let party = *by_id.entry(id.to_string()).or_insert_with(|| {
+            parties.push(SanctionedParty {
+                id: id.to_string(),
+                name: String::new(),
+                aliases: Vec::new(),
+                party_type: type_column.and_then(field).map(|code| {
+                    match code {
+                        "P" => "individual",
+                        "E" => "entity",
+                        other => other,
+                    }
+                    .to_string()
+                }),
+                programs: Vec::new(),
+                countries: Vec::new(),
+            });
+            parties.len() - 1
+        });
+        let party = &mut parties[party];
+        if let Some(name) = field(name_column) {
+            if party.name.is_empty() {
+                party.name = name.to_string();
+            } else if party.name != name {
+                push_unique(&mut party.aliases, name.to_string());
+            }
+        }
+        if let Some(program) = program_column.and_then(field) {
+            push_unique(&mut party.programs, program.to_string());
+        }
+        for &column in &country_columns {
+            if let Some(country) = field(column).filter(|code| is_country_code(code)) {
+                push_unique(&mut party.countries, country.to_ascii_uppercase());
+            }
+        }
+    }
+    parties.retain(|party| !party.name.is_empty());
+    Ok(parties)
+}
+
+fn push_unique(values: &mut Vec<String>, value: String) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+fn is_country_code(code: &str) -> bool {
+    code.len() == 2 && code.bytes().all(|b| b.is_ascii_alphabetic()) && code != "00"
+}
+
+/// Country codes by the names lists give countries by
+struct CountryNames(HashMap<String, String>);
+
+impl CountryNames {
+    fn bundled() -> Self {
+        let names = records(BUNDLED_COUNTRY_NAMES, ',')
+            .filter(|record| !record[0].starts_with('#'))
+            .filter_map(|record| {
+                let [code, name] = record.as_slice() else {
+                    return None;
+                };
+                Some((name.to_lowercase(), code.clone()))
+            })
+            .collect();
+        Self(names)
+    }
+
+    /// ISO 3166-1 alpha-2 code of a country given by name or code
+    fn code(&self, country: &str) -> Option<&str> {
+        let country = country.trim();
+        if let Some(code) = self.0.get(&country.to_lowercase()) {
+            return Some(code);
+        }
+        self.0
+            .values()
+            .find(|code| code.eq_ignore_ascii_case(country))
+            .map(String::as_str)
+    }
+}
+
+/// A list's parties with their names indexed for screening
+struct IndexedList {
+    parties: Vec<SanctionedParty>,
+    /// Every name and alias, with the party it belongs to
+    names: Vec<(usize, String)>,
+    /// Names by each word of them, and by each word's Soundex code
+    by_word: HashMap<String, Vec<usize>>,
+    updated_at: DateTime<Utc>,
+}
+
+impl IndexedList {
+    fn new(parties: Vec<SanctionedParty>) -> Self {
+        let mut names = Vec::new();
+        let mut by_word: HashMap<String, Vec<usize>> = HashMap::new();
+        for (party_index, party) in parties.iter().enumerate() {
+            for name in std::iter::once(&party.name).chain(&party.aliases) {
+                let name_index = names.len();
+                names.push((party_index, name.clone()));
+                for key in index_keys(name) {
+                    let indexed = by_word.entry(key).or_default();
+                    if indexed.last() != Some(&name_index) {
+                        indexed.push(name_index);
+                    }
+                }
+            }
+        }
+        Self {
+            parties,
+            names,
+            by_word,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Parties whose names match `name` at least as closely as `min_score`, each with its best
+    /// matching name and score
+    fn screen(&self, name: &str, country: Option<&str>, min_score: f64) -> Vec<(usize, &str, f64)> {
+        let candidates: BTreeSet<usize> = index_keys(name)
+            .filter_map(|key| self.by_word.get(&key))
+            .flatten()
+            .copied()
+            .collect();
+        let mut best: HashMap<usize, (&str, f64)> = HashMap::new();
+        for name_index in candidates {
+            let (party, listed) = &self.names[name_index];
+            let Some(score) = matching::full_name_match(name, listed) else {
+                continue;
+            };
+            if score < min_score {
+                continue;
+            }
+            let countries = &self.parties[*party].countries;
+            if let Some(country) = country
+                && !countries.is_empty()
+                && !countries.iter().any(|listed| listed == country)
+            {
+                continue;
+            }
+            let entry = best.entry(*party).or_insert((listed, score));
+            if score > entry.1 {
+                *entry = (listed, score);
+            }
+        }
+        best.into_iter()
+            .map(|(party, (listed, score))| (party, listed, score))
+            .collect()
+    }
+}
+
+/// Words of a name and their Soundex codes, which names are looked up by
+fn index_keys(name: &str) -> impl Iterator<Item = String> {
+    matching::name_words(name).into_iter().flat_map(|word| {
+        let code = matching::soundex(&word).map(|code| format!("#{code}"));
+        std::iter::once(word).chain(code)
+    })
+}
+
+/// Screens names against the sanctions lists downloaded so far
+#[derive(Clone)]
+pub struct ScreeningService {
+    lists: Arc<RwLock<HashMap<SanctionsList, IndexedList>>>,
+    min_score: f64,
+}
+
+impl ScreeningService {
+    /// Create a screening service without lists, matching names as closely as `config` says
+    pub fn new(config: &ScreeningConfig) -> Self {
+        Self {
+            lists: Arc::default(),
+            min_score: config.min_score,
+        }
+    }
+
+    /// Replace the parties of `list`
+    pub fn replace_list(&self, list: SanctionsList, parties: Vec<SanctionedParty>) {
+        let indexed = IndexedList::new(parties);
+        self.lists
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(list, indexed);
+    }
+
+    /// Whether any list has been downloaded
+    pub fn is_available(&self) -> bool {
+        !self
+            .lists
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    /// Screen a name, and the country of the party it names if known, against every list,
+    /// or `None` if no list has been downloaded yet
+    ///
+    /// `country` is an ISO 3166-1 alpha-2 code. Matches are ordered by score, closest first.
+    pub fn screen(&self, name: &str, country: Option<&str>) -> Option<ScreeningResult> {
+        let lists = self.lists.read().unwrap_or_else(|e| e.into_inner());
+        if lists.is_empty() {
+            return None;
+        }
+        let country = country.map(str::to_ascii_uppercase);
+        let mut matches = Vec::new();
+        let mut statuses = Vec::new();
+        for list in SanctionsList::ALL {
+            let Some(indexed) = lists.get(&list) else {
+                continue;
+            };
+            statuses.push(ScreeningListStatus {
+                list,
+                parties: indexed.parties.len(),
+                updated_at: indexed.updated_at,
+            });
+            for (party, matched_name, score) in
+                indexed.screen(name, country.as_deref(), self.min_score)
+            {
+                let party = &indexed.parties[party];
+                matches.push(ScreeningMatch {
+                    list,
+                    entry_id: party.id.clone(),
+                    name: party.name.clone(),
+                    matched_name: matched_name.to_string(),
+                    party_type: party.party_type.clone(),
+                    programs: party.programs.clone(),
+                    countries: party.countries.clone(),
+                    score: (score * 1000.0).round() / 1000.0,
+                });
+            }
+        }
+        matches.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.entry_id.cmp(&b.entry_id))
+        });
+        matches.truncate(MAX_MATCHES);
+        Some(ScreeningResult {
+            name: name.to_string(),
+            country,
+            matched: !matches.is_empty(),
+            matches,
+            lists: statuses,
+            screened_at: Utc::now(),
+        })
+    }
+}
+
+impl ScreeningService {
+    /// Screen the names a transaction gives: the billing name and company and the cardholder
+    /// against the billing country, and the shipping name against the shipping country
+    ///
+    /// Each name yields its closest match, if any. Nothing matches before a list is downloaded.
+    pub fn screen_transaction(&self, request: &TransactionRequest) -> Vec<SanctionsHit> {
+        let billing = request.billing.as_ref();
+        let billing_country = billing.and_then(|b| b.country.as_deref());
+        let shipping = request.shipping.as_ref().map(|s| &s.address);
+        let names = [
+            (
+                "Billing",
+                billing.and_then(|b| b.full_name()),
+                billing_country,
+            ),
+            (
+                "Billing company",
+                billing.and_then(|b| b.company.clone()),
+                billing_country,
+            ),
+            (
+                "Shipping",
+                shipping.and_then(|s| s.full_name()),
+                shipping.and_then(|s| s.country.as_deref()),
+            ),
+            (
+                "Cardholder",
+                request
+                    .credit_card
+                    .as_ref()
+                    .and_then(|c| c.holder_name.clone()),
+                billing_country,
+            ),
+        ];
+        let mut hits: Vec<SanctionsHit> = Vec::new();
+        for (role, name, country) in names {
+            let Some(name) = name.filter(|name| !name.trim().is_empty()) else {
+                continue;
+            };
+            // The same person often appears under several roles; one hit is enough
+            if hits.iter().any(|hit| hit.name.eq_ignore_ascii_case(&name)) {
+                continue;
+            }
+            let Some(party) = self
+                .screen(&name, country)
+                .and_then(|result| result.matches.into_iter().next())
+            else {
+                continue;
+            };
+            hits.push(SanctionsHit { role, name, party });
+        }
+        hits
+    }
+}
+
+impl fmt::Debug for ScreeningService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lists = self.lists.read().unwrap_or_else(|e| e.into_inner());
+        let mut debug = f.debug_struct("ScreeningService");
+        for (list, indexed) in lists.iter() {
+            debug.field(list.name(), &indexed.parties.len());
+        }
+        debug.field("min_score", &self.min_score).finish()
+    }
+}
+
+/// URLs `list` is downloaded from
+fn list_urls(list: SanctionsList, config: &ScreeningConfig) -> &[String] {
+    match list {
+        SanctionsList::Ofac => &config.ofac_urls,
+        SanctionsList::Eu => &config.eu_urls,
+    }
+}
+
+/// Download every URL of a list, failing if any of them cannot be fetched
+async fn fetch(
+    client: &reqwest::Client,
+    urls: &[String],
+) -> reqwest::Result<Vec<(String, String)>> {
+    let mut bodies = Vec::new();
+    for url in urls {
+        let body = client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        bodies.push((url.clone(), body));
+    }
+    Ok(bodies)
+}
+
+/// Parties of a downloaded list, or a description of why it cannot be read
+fn parse_list(
+    list: SanctionsList,
+    bodies: Vec<(String, String)>,
+) -> Result<Vec<SanctionedParty>, String> {
+    match list {
+        SanctionsList::Ofac => {
+            let files: Vec<(OfacFile, String)> = bodies
+                .into_iter()
+                .map(|(url, body)| (OfacFile::from_url(&url), body))
+                .collect();
+            Ok(parse_ofac(&files))
+        },
+        SanctionsList::Eu => {
+            let mut parties = Vec::new();
+            for (url, body) in bodies {
+                parties.extend(parse_eu(&body).map_err(|e| format!("{url}: {e}"))?);
+            }
+            Ok(parties)
+        },
+    }
+}
+
+/// Task that downloads the configured lists on `refresh_schedule`, and once at startup
+///
+/// A list that fails to download or parse, or names nobody, keeps its previous parties and
+/// fails the run.
+pub fn screening_refresh_task(
+    screening: ScreeningService,
+    config: ScreeningConfig,
+) -> reqwest::Result<ScheduledTask> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.fetch_timeout_seconds))
+        .build()?;
+    let schedule = config.refresh_schedule.clone();
+    let task = ScheduledTask::new("screening_refresh", schedule, move || {
+        let (screening, client, config) = (screening.clone(), client.clone(), config.clone());
+        async move { refresh_lists(&screening, &client, &config).await }
+    });
+    Ok(task.run_on_start())
+}
+
+/// Download and parse every configured list, naming the lists that could not be refreshed
+async fn refresh_lists(
+    screening: &ScreeningService,
+    client: &reqwest::Client,
+    config: &ScreeningConfig,
+) -> anyhow::Result<()> {
+    let mut failed = Vec::new();
+    for list in SanctionsList::ALL {
+        let urls = list_urls(list, config);
+        if urls.is_empty() {
+            continue;
+        }
+        let bodies = match fetch(client, urls).await {
+            Ok(bodies) => bodies,
+            Err(e) => {
+                tracing::warn!(error = %e, list = list.name(), "Sanctions list download failed");
+                failed.push(list.name());
+                continue;
+            },
+        };
+        match parse_list(list, bodies) {
+            Ok(parties) if parties.is_empty() => {
+                tracing::warn!(list = list.name(), "Sanctions list named nobody");
+                failed.push(list.name());
+            },
+            Ok(parties) => {
+                let count = parties.len();
+                screening.replace_list(list, parties);
+                tracing::info!(
+                    list = list.name(),
+                    parties = count,
+                    "Sanctions list refreshed"
+                );
+            },
+            Err(e) => {
+                tracing::warn!(error = %e, list = list.name(), "Sanctions list could not be read");
+                failed.push(list.name());
+            },
+        }
+    }
+    if !failed.is_empty() {
+        anyhow::bail!("lists not refreshed: {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    const SDN: &str = "36092,\"PETROV, Ivan Ivanovich\",\"individual\",\"RUSSIA-EO14024\",-0- ,-0- \n\
+                       36093,\"BLUE OCEAN SHIPPING LLC\",-0- ,\"IRAN] [SDGT\",-0- ,-0- \n";
+    const ALT: &str = "36092,1001,\"aka\",\"PETROFF, Ivan\",-0- \n";
+    const ADD: &str = "36092,2001,-0- ,\"Moscow\",\"Russia\",-0- \n\
+                       36093,2002,-0- ,\"Dubai\",\"United Arab Emirates\",-0- \n";
+    const EU: &str = "Entity_LogicalId;Entity_SubjectType_ClassificationCode;\
+                      Entity_Regulation_Programme;NameAlias_WholeName;Address_CountryIso2Code;\
+                      Citizenship_CountryIso2Code\n\
+                      13;P;SYR;Ali Hassan Mahmoud;;SY\n\
+                      13;P;SYR;Ali Hasan Mahmud;SY;\n\
+                      14;E;RUS;Northern Star Trading;RU;\n";
+
+    fn screening() -> ScreeningService {
+        let screening = ScreeningService::new(&Config::default().screening);
+        screening.replace_list(
+            SanctionsList::Ofac,
+            parse_ofac(&[
+                (OfacFile::Primary, SDN.to_string()),
+                (OfacFile::Aliases, ALT.to_string()),
+                (OfacFile::Addresses, ADD.to_string()),
+            ]),
+        );
+        screening.replace_list(SanctionsList::Eu, parse_eu(EU).unwrap());
+        screening
+    }
+
+    #[test]
+    fn test_lists_are_parsed() {
+        assert_eq!(
+            OfacFile::from_url("https://example.test/alt.csv"),
+            OfacFile::Aliases
+        );
+        assert_eq!(
+            OfacFile::from_url("https://example.test/sdn.csv"),
+            OfacFile::Primary
+        );
+
+        let ofac = parse_ofac(&[
+            (OfacFile::Primary, SDN.to_string()),
+            (OfacFile::Aliases, ALT.to_string()),
+            (OfacFile::Addresses, ADD.to_string()),
+        ]);
+        assert_eq!(ofac.len(), 2);
+        assert_eq!(ofac[0].aliases, ["PETROFF, Ivan"]);
+        assert_eq!(ofac[0].countries, ["RU"]);
+        assert_eq!(ofac[1].party_type.as_deref(), Some("entity"));
+        assert_eq!(ofac[1].programs, ["IRAN", "SDGT"]);
+        assert_eq!(ofac[1].countries, ["AE"]);
+
+        let eu = parse_eu(EU).unwrap();
+        assert_eq!(eu.len(), 2);
+        assert_eq!(eu[0].name, "Ali Hassan Mahmoud");
+        assert_eq!(eu[0].aliases, ["Ali Hasan Mahmud"]);
+        assert_eq!(eu[0].party_type.as_deref(), Some("individual"));
+        assert_eq!(eu[0].countries, ["SY"]);
+        assert!(parse_eu("id;name\n1;x\n").is_err());
+    }
+
+    #[test]
+    fn test_names_are_screened() {
+        assert!(
+            ScreeningService::new(&Config::default().screening)
+                .screen("Ivan Petrov", None)
+                .is_none()
+        );
+        let screening = screening();
+
+        let result = screening.screen("Ivan Petrov", Some("ru")).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.matches[0].entry_id, "36092");
+        assert_eq!(result.matches[0].list, SanctionsList::Ofac);
+        assert_eq!(result.lists.len(), 2);
+        // Listed countries must include the screened one
+        assert!(!screening.screen("Ivan Petrov", Some("US")).unwrap().matched);
+        // Spelling variants are found through their Soundex codes
+        let variant = screening.screen("Ali Hassan Mahmud", None).unwrap();
+        assert_eq!(variant.matches[0].entry_id, "13");
+        assert!(!screening.screen("Jane Doe", None).unwrap().matched);
+    }
+
+    #[test]
+    fn test_transaction_names_are_screened() {
+        let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+            "device": { "ip_address": "203.0.113.7" },
+            "event": { "type": "purchase" },
+            "billing": { "first_name": "Ivan", "last_name": "Petrov", "country": "RU" },
+            "shipping": { "first_name": "Jane", "last_name": "Doe", "country": "US" },
+            "credit_card": { "holder_name": "IVAN PETROV" }
+        }))
+        .unwrap();
+        let hits = screening().screen_transaction(&request);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].role, "Billing");
+        assert_eq!(hits[0].party.entry_id, "36092");
+    }
+}
@@ -0,0 +1,1927 @@
+//! Transaction persistence
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use sqlx::{PgConnection, PgPool, types::Json};
+use uuid::Uuid;
+
+use super::{
+    EmailIntelService, IpIntelService, ListService, ScreeningService, ServiceError, ServiceResult,
+    bin_intel, case_service::open_case, device_service::refresh_device_risk_score,
+    ip_reputation::refresh_ip_reputation, phone_intel,
+};
+use crate::{
+    config::RedactionConfig,
+    database::{
+        Tenant,
+        repositories::{
+            AccountRepo, AddressInsightRecord, CreditCardInsightRecord, DeviceHistoryRecord,
+            DeviceInsightRecord, DeviceRepo, EmailAddressRecord, EmailAddressRepo,
+            EmailInsightRecord, EmailVariantsRecord, InsightsRepo, IpAddressRecord, IpAddressRepo,
+            IpReputationRecord, ListRepo, NewCreditCard, NewDevice, NewScoringRevision,
+            NewTransaction, OutboxRepo, RescoreSourceRecord, ScoringRevisionRepo,
+            TransactionRecord, TransactionRepo, UserFlagsRecord, UserRepo,
+        },
+    },
+    features::FeatureStore,
+    models::{
+        common::{Cursor, Link, Links},
+        device::token_fingerprint,
+        insights::{
+            AddressInsights, CreditCardInsights, DeviceInsights, EmailAddressInsights,
+            EmailDomainInsights, EmailHistoryInsights, EmailInsights, IpAddressInsights,
+            IpHistoryInsights, PhoneInsights, TransactionInsights, card_brand,
+        },
+        list::ListEntityType,
+        transaction::{
+            Address, Disposition, ListTransactionsQuery, MatchKeys, ScoringRevision,
+            StoredTransactionRequest, TransactionDevice, TransactionEmail, TransactionRequest,
+            TransactionResponse, Warning, is_reserved_ip,
+        },
+    },
+    outbox::{TRANSACTION_SCORED, TransactionScored},
+    scoring::{
+        DEVICE_USERS_WINDOW_HOURS, EmailAge, EmailTraits, EmailVariants, RefundHistory,
+        RiskAssessment, UserSignals, rules,
+    },
+    sessions::SessionStore,
+    utils::{
+        address::generate_address_hash,
+        geo::{
+            AsnInfo, GeoIpDatabase, IpAddressInfo, distance_km, get_location_risk_score, local_time,
+        },
+        ip::{max_prefix_len, network},
+        sha256_hex, ua,
+    },
+};
+
+/// Most recent transactions from an IP address included in its insights
+const RECENT_IP_TRANSACTIONS: i64 = 10;
+/// Most recent transactions with an email address included in its insights
+const RECENT_EMAIL_TRANSACTIONS: i64 = 10;
+
+impl From<TransactionRecord> for TransactionResponse {
+    fn from(record: TransactionRecord) -> Self {
+        TransactionResponse {
+            id: record.id,
+            user_id: record.user_id,
+            external_transaction_id: record.external_transaction_id,
+            original_transaction_id: record.original_transaction_id,
+            risk_score: record.risk_score,
+            risk_level: record.risk_level,
+            disposition: record.disposition,
+            event_type: record.event_type,
+            created_at: record.created_at,
+            warnings: record.warnings.0,
+            queries_remaining: None,
+            links: Links {
+                self_link: Some(Link::new(format!("/v1/transactions/{}", record.id))),
+                ..Links::default()
+            },
+        }
+    }
+}
+
+/// Where the signals of a transaction kept outside the database are gathered from
+#[derive(Debug, Clone)]
+pub struct SignalSources {
+    /// Event history of recent sessions
+    pub sessions: SessionStore,
+    /// Precomputed risk features and IP geolocation
+    pub features: FeatureStore,
+    /// Anonymous IP feeds
+    pub ip_intel: IpIntelService,
+    /// Free and disposable email domains
+    pub email_intel: EmailIntelService,
+    /// Sanctions lists names are screened against
+    pub screening: ScreeningService,
+}
+
+/// Stores scored transactions and the entities they reference
+#[derive(Debug, Clone)]
+pub struct TransactionService {
+    pool: PgPool,
+    read_pool: PgPool,
+    redaction: RedactionConfig,
+    geoip: GeoIpDatabase,
+    lists: ListService,
+    sources: Option<SignalSources>,
+}
+
+impl TransactionService {
+    /// Create a transaction service writing to `pool` and listing from `read_pool`, keeping
+    /// requests redacted as `redaction` says
+    pub fn new(pool: PgPool, read_pool: PgPool, redaction: RedactionConfig) -> Self {
+        Self {
+            read_pool,
+            redaction,
+            geoip: GeoIpDatabase::disabled(),
+            lists: ListService::new(pool.clone()),
+            sources: None,
+            pool,
+        }
+    }
+
+    /// Resolve the networks of IP addresses with `geoip`
+    pub fn with_geoip(mut self, geoip: GeoIpDatabase) -> Self {
+        self.geoip = geoip;
+        self
+    }
+
+    /// Look the transaction's entities up on the account's lists through `lists`, sharing its
+    /// cache
+    pub fn with_lists(mut self, lists: ListService) -> Self {
+        self.lists = lists;
+        self
+    }
+
+    /// Gather the signals of [`TransactionService::gather_signals`] kept outside the database
+    /// from `sources`
+    pub fn with_signal_sources(mut self, sources: SignalSources) -> Self {
+        self.sources = Some(sources);
+        self
+    }
+
+    /// Network `ip_address` belongs to, if the ASN database knows it
+    fn network(&self, ip_address: &str) -> Option<AsnInfo> {
+        self.geoip.lookup_asn(ip_address.parse().ok()?)
+    }
+
+    /// Where `ip_address` is located, if the GeoIP database knows it
+    fn ip_location(&self, ip_address: &str) -> Option<IpAddressInfo> {
+        self.geoip.lookup(ip_address.parse().ok()?)
+    }
+
+    /// Centroid of the postal code of `address`, if the postal code dump lists it
+    fn locate_address(&self, address: &Address) -> Option<(f64, f64)> {
+        self.geoip
+            .locate_postal(address.country.as_deref()?, address.postal.as_deref()?)
+    }
+
+    /// Distance in kilometres from `address` to `ip_location`, if both could be located
+    fn distance_to_ip(
+        &self,
+        address: Option<&Address>,
+        ip_location: Option<&IpAddressInfo>,
+    ) -> Option<f64> {
+        let address = self.locate_address(address?)?;
+        Some(distance_km(address, ip_location?.coordinates()?))
+    }
+
+    /// Persist a scored transaction together with its user, device, and related entities
+    ///
+    /// Everything, including the `transaction.scored` outbox event, is written in a single
+    /// database transaction so a failure never leaves a partially recorded event behind. Once
+    /// it is committed, entities of the transaction that keep breaching critical velocity rules
+    /// are blocked if the account asks for it.
+    pub async fn store_transaction(
+        &self,
+        tenant: Tenant,
+        request: &TransactionRequest,
+        assessment: &RiskAssessment,
+        warnings: &[Warning],
+    ) -> ServiceResult<TransactionRecord> {
+        let mut tx = self.pool.begin().await?;
+        let record = self
+            .insert_transaction(&mut tx, tenant, request, assessment, warnings)
+            .await?;
+        tx.commit().await?;
+        self.block_breaching_entities(tenant, record.id, assessment)
+            .await;
+        Ok(record)
+    }
+
+    /// Blocklist the entities of a committed transaction that keep breaching critical
+    /// velocity rules, for accounts that enable automatic blocks
+    ///
+    /// The transaction is already stored, so a failure is only logged.
+    pub async fn block_breaching_entities(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        assessment: &RiskAssessment,
+    ) {
+        if let Err(e) = self
+            .lists
+            .block_breaching_entities(tenant, transaction_id, &assessment.factors)
+            .await
+        {
+            tracing::warn!(
+                error = %e,
+                account_id = %tenant,
+                %transaction_id,
+                "Automatic blocking failed"
+            );
+        }
+    }
+
+    /// Write a scored transaction, its entities, and its outbox event on `conn`
+    ///
+    /// The caller owns the database transaction, so it can record more alongside, as the
+    /// scoring job worker does with the job's own outcome.
+    pub async fn insert_transaction(
+        &self,
+        conn: &mut PgConnection,
+        tenant: Tenant,
+        request: &TransactionRequest,
+        assessment: &RiskAssessment,
+        warnings: &[Warning],
+    ) -> ServiceResult<TransactionRecord> {
+        let user_id = self.get_or_create_user(&mut *conn, tenant, request).await?;
+        let device_id = self
+            .get_or_create_device(&mut *conn, tenant, user_id, &request.device)
+            .await?;
+        let event_time = request.event.time.unwrap_or_else(Utc::now);
+        let network = self.network(&request.device.ip_address);
+        let original_transaction_id = match &request.event.original_transaction_id {
+            Some(original) => {
+                TransactionRepo::find_purchase_by_external_id(&mut *conn, tenant, original).await?
+            },
+            None => None,
+        };
+        // A refund of a purchase scored before the account integrated is kept unlinked
+        let mut warnings = warnings.to_vec();
+        if request.event.original_transaction_id.is_some() && original_transaction_id.is_none() {
+            warnings.push(Warning {
+                code: "ORIGINAL_TRANSACTION_NOT_FOUND".to_string(),
+                message: "No purchase was scored under the original transaction ID".to_string(),
+                input_path: Some("/event/original_transaction_id".to_string()),
+            });
+        }
+
+        let record = TransactionRepo::insert(
+            &mut *conn,
+            NewTransaction {
+                tenant,
+                user_id,
+                external_transaction_id: request.event.transaction_id.as_deref(),
+                risk_score: assessment.risk_score,
+                risk_level: assessment.risk_level,
+                disposition: assessment.disposition,
+                event_type: request.event.event_type,
+                shop_id: request.event.shop_id.as_deref(),
+                event_time,
+                original_transaction_id,
+                ip_address: &request.device.ip_address,
+                asn: network.as_ref().map(|network| network.asn.into()),
+                isp: network.as_ref().and_then(AsnInfo::provider),
+                local_hour: assessment
+                    .features
+                    .local_hour
+                    .and_then(|hour| i16::try_from(hour).ok()),
+                device_data: serde_json::to_value(&request.device).unwrap_or_default(),
+                custom_inputs: request
+                    .custom_inputs
+                    .clone()
+                    .unwrap_or_else(|| serde_json::json!({})),
+                warnings: &warnings,
+                raw_request: serde_json::to_value(request.redacted(&self.redaction))
+                    .unwrap_or_default(),
+                match_keys: &request.match_keys(),
+            },
+        )
+        .await?;
+
+        if let Some(device_id) = device_id {
+            TransactionRepo::link_device(&mut *conn, record.id, device_id).await?;
+        }
+
+        if let Some(order) = &request.order {
+            let order_id = TransactionRepo::insert_order(&mut *conn, record.id, order).await?;
+            for item in &request.shopping_cart {
+                TransactionRepo::insert_cart_item(&mut *conn, order_id, item).await?;
+            }
+        }
+        if let Some(email) = &request.email {
+            let traits = EmailTraits {
+                is_free: assessment.features.email_free,
+                is_disposable: assessment.features.email_disposable,
+            };
+            store_email(&mut *conn, tenant, user_id, record.id, email, traits).await?;
+        }
+        if let Some(billing) = &request.billing {
+            let location = self.locate_address(billing);
+            let address_id = TransactionRepo::insert_address(
+                &mut *conn,
+                tenant,
+                user_id,
+                billing,
+                generate_address_hash(billing).as_deref(),
+                location,
+            )
+            .await?;
+            TransactionRepo::link_address(&mut *conn, record.id, address_id, "billing", None)
+                .await?;
+        }
+        if let Some(shipping) = &request.shipping {
+            let location = self.locate_address(&shipping.address);
+            let address_id = TransactionRepo::insert_address(
+                &mut *conn,
+                tenant,
+                user_id,
+                &shipping.address,
+                generate_address_hash(&shipping.address).as_deref(),
+                location,
+            )
+            .await?;
+            TransactionRepo::link_address(
+                &mut *conn,
+                record.id,
+                address_id,
+                "shipping",
+                shipping.delivery_speed,
+            )
+            .await?;
+        }
+        if let Some(card) = &request.credit_card {
+            let token_hash = card.token.as_deref().map(sha256_hex);
+            let bin = match card.issuer_id_number.as_deref() {
+                Some(iin) => bin_intel::lookup_card(&mut *conn, tenant, iin).await?,
+                None => None,
+            };
+            let card_id = TransactionRepo::insert_credit_card(
+                &mut *conn,
+                NewCreditCard {
+                    tenant,
+                    user_id,
+                    card,
+                    token_hash: token_hash.as_deref(),
+                    bin: bin.as_ref(),
+                    billing_name_match: request.cardholder_billing_match(),
+                    email_name_match: request.cardholder_email_match(),
+                },
+            )
+            .await?;
+            TransactionRepo::link_credit_card(&mut *conn, record.id, card_id).await?;
+        }
+
+        for factor in &assessment.factors {
+            TransactionRepo::insert_risk_factor(&mut *conn, record.id, factor).await?;
+        }
+        if assessment.disposition == Disposition::Review {
+            let rule_codes: Vec<String> =
+                assessment.factors.iter().map(|f| f.code.clone()).collect();
+            open_case(&mut *conn, tenant, record.id, &rule_codes).await?;
+        }
+
+        let payload = serde_json::to_value(TransactionScored::new(
+            record.clone(),
+            assessment,
+            request,
+            device_id,
+        ))
+        .unwrap_or_default();
+        OutboxRepo::insert(
+            &mut *conn,
+            tenant.id(),
+            TRANSACTION_SCORED,
+            record.id,
+            payload,
+        )
+        .await?;
+
+        if let Some(user_id) = user_id {
+            UserRepo::record_transaction(&mut *conn, tenant, user_id, event_time).await?;
+            if let Some(device_id) = device_id {
+                DeviceRepo::link_user(&mut *conn, tenant, device_id, user_id).await?;
+            }
+        }
+        if let Some(device_id) = device_id {
+            refresh_device_risk_score(&mut *conn, tenant, device_id).await?;
+        }
+        refresh_ip_reputation(&mut *conn, tenant, &request.device.ip_address).await?;
+
+        Ok(record)
+    }
+
+    /// Signals of the user a request names and the device it comes from, as of now, for
+    /// scoring the request
+    ///
+    /// The user and device are looked up by the identifiers
+    /// [`TransactionService::get_or_create_user`] and
+    /// [`TransactionService::get_or_create_device`] would use, without being created.
+    /// Anonymous requests, new users and devices, and deleted ones have no signals.
+    pub async fn user_signals(
+        &self,
+        tenant: Tenant,
+        request: &TransactionRequest,
+    ) -> sqlx::Result<UserSignals> {
+        let mut conn = self.pool.acquire().await?;
+        let account = request.account.as_ref();
+        let user = if let Some(user_id) = request.user_id {
+            UserRepo::find_flags(&mut *conn, tenant, Some(user_id), None, None).await?
+        } else if let Some(external_user_id) = account.and_then(|a| a.user_id.as_deref()) {
+            UserRepo::find_flags(&mut *conn, tenant, None, Some(external_user_id), None).await?
+        } else if let Some(user_hash) = account.and_then(|a| a.user_hash.as_deref()) {
+            UserRepo::find_flags(&mut *conn, tenant, None, None, Some(user_hash)).await?
+        } else {
+            None
+        };
+        let fingerprint = request_device_fingerprint(&request.device);
+        let device = DeviceRepo::history(
+            &mut *conn,
+            tenant,
+            None,
+            Some(&fingerprint),
+            user.as_ref().map(|user| user.id),
+            device_users_since(),
+        )
+        .await?;
+        let known_device = device.is_some();
+
+        // A user about to be created is one more distinct user of the device
+        let new_user =
+            user.is_none() && account.is_some_and(|a| a.user_id.is_some() || a.user_hash.is_some());
+        let mut signals = self
+            .request_signals(
+                &mut conn,
+                tenant,
+                request,
+                &request.match_keys(),
+                user,
+                device,
+            )
+            .await?;
+        if new_user && known_device {
+            signals.device_user_count += 1;
+        }
+        Ok(signals)
+    }
+
+    /// Current signals of a stored transaction, attributed to the user and device it was
+    /// stored with, and matched on the hashes stored with it
+    ///
+    /// Gathered like [`TransactionService::gather_signals`], but for session signals.
+    async fn stored_signals(
+        &self,
+        conn: &mut PgConnection,
+        tenant: Tenant,
+        source: &RescoreSourceRecord,
+        request: &TransactionRequest,
+    ) -> sqlx::Result<UserSignals> {
+        let user = match source.user_id {
+            Some(user_id) => {
+                UserRepo::find_flags(&mut *conn, tenant, Some(user_id), None, None).await?
+            },
+            None => None,
+        };
+        let device = match source.device_id {
+            Some(device_id) => {
+                DeviceRepo::history(
+                    &mut *conn,
+                    tenant,
+                    Some(device_id),
+                    None,
+                    user.as_ref().map(|user| user.id),
+                    device_users_since(),
+                )
+                .await?
+            },
+            None => None,
+        };
+        let keys = MatchKeys {
+            email_hash: source.email_hash.clone(),
+            mailbox_hash: source.mailbox_hash.clone(),
+            card_hash: source.card_hash.clone(),
+        };
+        let mut signals = self
+            .request_signals(conn, tenant, request, &keys, user, device)
+            .await?;
+        self.source_signals(tenant, request, &mut signals).await?;
+        Ok(signals)
+    }
+
+    /// Database signals of a request from the given user and device, its email address and
+    /// card matched by `keys`
+    ///
+    /// Shared by [`TransactionService::user_signals`] and the rescoring of stored transactions,
+    /// whose kept requests no longer yield `keys`: IP reputation and network, locations,
+    /// email variants and age, BIN, list matches, refunds, and phone.
+    async fn request_signals(
+        &self,
+        conn: &mut PgConnection,
+        tenant: Tenant,
+        request: &TransactionRequest,
+        keys: &MatchKeys,
+        user: Option<UserFlagsRecord>,
+        device: Option<DeviceHistoryRecord>,
+    ) -> sqlx::Result<UserSignals> {
+        let ip = IpAddressRepo::reputation(&mut *conn, tenant, &request.device.ip_address).await?;
+        let asn_listing = match self.network(&request.device.ip_address) {
+            Some(network) => {
+                ListRepo::find_asn_entry(&mut *conn, tenant, network.asn.into()).await?
+            },
+            None => None,
+        };
+        let ip_location = self.ip_location(&request.device.ip_address);
+        let ip_country = ip_location.as_ref().and_then(|info| info.country.clone());
+        let billing_ip_distance_km =
+            self.distance_to_ip(request.billing.as_ref(), ip_location.as_ref());
+        let shipping_ip_distance_km = self.distance_to_ip(
+            request.shipping.as_ref().map(|shipping| &shipping.address),
+            ip_location.as_ref(),
+        );
+        let email_variants = match (&keys.email_hash, &keys.mailbox_hash) {
+            (Some(email_hash), Some(mailbox_hash)) => {
+                EmailAddressRepo::variants(
+                    &mut *conn,
+                    tenant,
+                    mailbox_hash,
+                    email_hash,
+                    user.as_ref().map(|user| user.id),
+                )
+                .await?
+            },
+            _ => EmailVariantsRecord::default(),
+        };
+        let email_age = match &keys.email_hash {
+            Some(email_hash) => Some(EmailAddressRepo::age(&mut *conn, tenant, email_hash).await?),
+            None => None,
+        };
+        let card_bin = match request
+            .credit_card
+            .as_ref()
+            .and_then(|card| card.issuer_id_number.as_deref())
+        {
+            Some(iin) => bin_intel::lookup_card(&mut *conn, tenant, iin).await?,
+            None => None,
+        };
+        let countries: Vec<String> = rules::transaction_countries(request, ip_country.as_deref())
+            .map(|(_, country)| country.to_string())
+            .collect();
+        let country_listings = if countries.is_empty() {
+            Vec::new()
+        } else {
+            ListRepo::find_country_entries(&mut *conn, tenant, &countries).await?
+        };
+        let entities = list_entities(
+            request,
+            keys,
+            user.as_ref().map(|user| user.id),
+            device.map(|device| device.id),
+            &countries,
+        );
+        let list_entries = self.lists.find_entries(tenant, &entities).await?;
+        let refunds = match &user {
+            Some(user) => TransactionRepo::refund_history(&mut *conn, tenant, user.id).await?,
+            None => RefundHistory::default(),
+        };
+
+        let mut signals = user_signals(user, device, ip);
+        signals.refunds = refunds;
+        signals.asn_listing = asn_listing.map(Into::into);
+        signals.ip_country = ip_country;
+        signals.billing_ip_distance_km = billing_ip_distance_km;
+        signals.shipping_ip_distance_km = shipping_ip_distance_km;
+        signals.country_listings = country_listings.into_iter().map(Into::into).collect();
+        signals.list_entries = list_entries;
+        signals.email_variants = email_variants.into();
+        signals.email_age = email_age.map(|age| {
+            EmailAge::new(
+                age.first_seen,
+                age.global_first_seen,
+                age.good_transaction_count,
+                Utc::now().date_naive(),
+            )
+        });
+        signals.phone = phone_intel::lookup_request(request);
+        signals.card_bin = card_bin;
+        Ok(signals)
+    }
+
+    /// Every signal scoring a request takes, with the location of its IP address for
+    /// recording once the transaction is stored
+    ///
+    /// Adds to [`TransactionService::user_signals`] the session signals and those of
+    /// [`TransactionService::source_signals`]; without the sources given by
+    /// [`TransactionService::with_signal_sources`], those signals are left empty.
+    pub async fn gather_signals(
+        &self,
+        tenant: Tenant,
+        request: &TransactionRequest,
+    ) -> ServiceResult<(UserSignals, Option<IpAddressInfo>)> {
+        let mut user = self.user_signals(tenant, request).await?;
+        let ip_location = self.source_signals(tenant, request, &mut user).await?;
+        if let Some(sources) = &self.sources {
+            let ip_country = ip_location
+                .as_ref()
+                .and_then(|info| info.country.as_deref());
+            user.session = sources.sessions.signals(tenant, request, ip_country).await;
+        }
+        Ok((user, ip_location))
+    }
+
+    /// Add the IP and email intelligence, sanctions, velocity, card testing, travel, and local
+    /// time signals of the sources given by [`TransactionService::with_signal_sources`] to
+    /// `user`, returning the location of the request's IP address
+    ///
+    /// Session signals are not among them, as only a request as it arrives belongs to a
+    /// session. Names are only screened for accounts that turned sanctions screening on.
+    async fn source_signals(
+        &self,
+        tenant: Tenant,
+        request: &TransactionRequest,
+        user: &mut UserSignals,
+    ) -> sqlx::Result<Option<IpAddressInfo>> {
+        let Some(sources) = &self.sources else {
+            return Ok(None);
+        };
+        let SignalSources {
+            features,
+            ip_intel,
+            email_intel,
+            screening,
+            ..
+        } = sources;
+        let ip_location = features.locate_ip(&request.device.ip_address).await;
+        user.ip_traits = ip_intel
+            .lookup(&request.device.ip_address)
+            .await
+            .unwrap_or_default();
+        user.email_traits = email_intel.lookup(request.email.as_ref()).await;
+        if screening.is_available() && AccountRepo::sanctions_screening(&self.pool, tenant).await? {
+            user.sanctions_hits = screening.screen_transaction(request);
+        }
+        user.ip_velocity = features
+            .ip_velocity(tenant, &request.device.ip_address)
+            .await;
+        user.address_velocity = features.address_velocity(tenant, request).await;
+        user.card_testing = features
+            .card_testing(
+                tenant,
+                request,
+                &request_device_fingerprint(&request.device),
+            )
+            .await;
+        let event_time = request.event.time.unwrap_or_else(Utc::now);
+        user.travel = features
+            .get_travel(user.user_id, ip_location.as_ref(), event_time)
+            .await;
+        user.local_time = features
+            .get_local_time(user.user_id, ip_location.as_ref(), event_time)
+            .await;
+        Ok(ip_location)
+    }
+
+    /// Remember `location` as where a user's transaction at `at` came from, for the travel
+    /// signals of their later transactions
+    ///
+    /// Nothing is recorded without the sources of [`TransactionService::with_signal_sources`].
+    pub async fn record_location(
+        &self,
+        user_id: Option<Uuid>,
+        location: Option<&IpAddressInfo>,
+        at: DateTime<Utc>,
+    ) {
+        if let Some(sources) = &self.sources {
+            sources
+                .features
+                .record_location(user_id, location, at)
+                .await;
+        }
+    }
+
+    /// Resolve the user a transaction belongs to, creating it on first sight
+    ///
+    /// The first identifier present wins: an existing fusegu user ID, then the customer's
+    /// external user ID, then the user hash. Identifiers of a merged user resolve to the user
+    /// it was merged into. Returns `None` for anonymous events and for identifiers that belong
+    /// to a deleted user, so deleted users never accrue new history.
+    pub async fn get_or_create_user(
+        &self,
+        conn: &mut PgConnection,
+        tenant: Tenant,
+        request: &TransactionRequest,
+    ) -> ServiceResult<Option<Uuid>> {
+        if let Some(user_id) = request.user_id {
+            let found = UserRepo::resolve_id(&mut *conn, tenant, user_id).await?;
+            return found.map(Some).ok_or_else(|| {
+                ServiceError::Invalid("user_id does not reference a known user".to_string())
+            });
+        }
+
+        let Some(account) = &request.account else {
+            return Ok(None);
+        };
+
+        if let Some(external_user_id) = &account.user_id {
+            return Ok(
+                UserRepo::upsert_by_external_id(&mut *conn, tenant, external_user_id).await?,
+            );
+        }
+
+        if let Some(user_hash) = &account.user_hash {
+            return Ok(UserRepo::upsert_by_hash(&mut *conn, tenant, user_hash).await?);
+        }
+
+        Ok(None)
+    }
+
+    /// Resolve the device a transaction came from, creating it on first sight
+    ///
+    /// Devices are identified per account by the fingerprint their device token names, or else
+    /// by a fingerprint of their IP address, user agent, and accept-language header. Returns
+    /// `None` if the device has been deleted.
+    pub async fn get_or_create_device(
+        &self,
+        conn: &mut PgConnection,
+        tenant: Tenant,
+        user_id: Option<Uuid>,
+        device: &TransactionDevice,
+    ) -> ServiceResult<Option<Uuid>> {
+        let fingerprint = request_device_fingerprint(device);
+        let details = device.user_agent.as_deref().map(ua::parse);
+        let ja3 = device.ja3.as_deref().map(str::to_ascii_lowercase);
+        let network = self.network(&device.ip_address);
+        let id = DeviceRepo::upsert(
+            conn,
+            NewDevice {
+                tenant,
+                user_id,
+                fingerprint_hash: &fingerprint,
+                ip_address: &device.ip_address,
+                asn: network.as_ref().map(|network| network.asn.into()),
+                isp: network.as_ref().and_then(AsnInfo::provider),
+                user_agent: device.user_agent.as_deref(),
+                user_agent_details: details.as_ref(),
+                accept_language: device.accept_language.as_deref(),
+                ja3: ja3.as_deref(),
+                ja4: device.ja4.as_deref(),
+                header_order_hash: device.header_order_hash.as_deref(),
+                session_id: device.session_id.as_deref(),
+                session_age: device.session_age,
+                traits_data: None,
+            },
+        )
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Fetch a single transaction belonging to an account
+    ///
+    /// Reads from the primary so a transaction is visible immediately after it is created.
+    pub async fn get_transaction(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> ServiceResult<TransactionRecord> {
+        TransactionRepo::find_by_id(&self.pool, tenant, transaction_id)
+            .await?
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Fetch the redacted request a transaction of an account was scored on
+    pub async fn get_raw_request(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> ServiceResult<StoredTransactionRequest> {
+        let raw_request = TransactionRepo::find_raw_request(&self.pool, tenant, transaction_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let Some(Json(request)) = raw_request else {
+            return Err(ServiceError::Conflict(
+                "Transaction was scored before requests were kept".to_string(),
+            ));
+        };
+        Ok(StoredTransactionRequest {
+            transaction_id,
+            request,
+            links: Links {
+                self_link: Some(Link::new(format!(
+                    "/v1/transactions/{transaction_id}/request"
+                ))),
+                ..Links::default()
+            },
+        })
+    }
+
+    /// List an account's transactions, returning the page and the total number of matches
+    ///
+    /// Listings tolerate replica lag and are served from the read pool.
+    pub async fn list_transactions(
+        &self,
+        tenant: Tenant,
+        query: &ListTransactionsQuery,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<TransactionRecord>, i64)> {
+        let total = TransactionRepo::count(&self.read_pool, tenant, query).await?;
+        let records = TransactionRepo::list(&self.read_pool, tenant, query, limit, offset).await?;
+        Ok((records, total))
+    }
+
+    /// List up to `limit` of an account's transactions after `cursor`, in creation order
+    ///
+    /// See [`TransactionRepo::list_after`]; served from the read pool like
+    /// [`TransactionService::list_transactions`], without counting matches.
+    pub async fn list_transactions_after(
+        &self,
+        tenant: Tenant,
+        query: &ListTransactionsQuery,
+        cursor: Option<Cursor>,
+        ascending: bool,
+        limit: i64,
+    ) -> ServiceResult<Vec<TransactionRecord>> {
+        Ok(
+            TransactionRepo::list_after(&self.read_pool, tenant, query, cursor, ascending, limit)
+                .await?,
+        )
+    }
+
+    /// Score a stored transaction again with `assess`, recording the result as a new revision
+    ///
+    /// `assess` is given the current signals of the transaction, gathered as for a new one
+    /// but for session signals, since session history is not replayed. The transaction row
+    /// and its earlier scoring are left untouched. Fails with a conflict for transactions
+    /// stored before requests were kept for rescoring.
+    pub async fn rescore(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        assess: impl FnOnce(&TransactionRequest, &UserSignals) -> RiskAssessment,
+    ) -> ServiceResult<ScoringRevision> {
+        let mut tx = self.pool.begin().await?;
+        let revision = self
+            .rescore_on(&mut tx, tenant, transaction_id, assess)
+            .await?;
+        tx.commit().await?;
+        Ok(revision)
+    }
+
+    /// [`TransactionService::rescore`] on `conn`
+    ///
+    /// The caller owns the database transaction, so it can record more alongside, as the
+    /// rescore job worker does with the job's progress.
+    pub async fn rescore_on(
+        &self,
+        conn: &mut PgConnection,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        assess: impl FnOnce(&TransactionRequest, &UserSignals) -> RiskAssessment,
+    ) -> ServiceResult<ScoringRevision> {
+        let source = ScoringRevisionRepo::lock_source(&mut *conn, tenant, transaction_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let request = kept_request(&source)?;
+        let signals = self
+            .stored_signals(&mut *conn, tenant, &source, request)
+            .await?;
+        let assessment = assess(request, &signals);
+
+        let record = ScoringRevisionRepo::insert(
+            &mut *conn,
+            NewScoringRevision {
+                transaction_id: source.transaction_id,
+                revision: source.revision + 1,
+                risk_score: assessment.risk_score,
+                risk_level: assessment.risk_level,
+                disposition: assessment.disposition,
+                factors: &assessment.factors,
+            },
+        )
+        .await?;
+        if assessment.disposition == Disposition::Review {
+            let rule_codes: Vec<String> =
+                assessment.factors.iter().map(|f| f.code.clone()).collect();
+            open_case(&mut *conn, tenant, source.transaction_id, &rule_codes).await?;
+        }
+
+        Ok(ScoringRevision {
+            transaction_id: record.transaction_id,
+            revision: record.revision,
+            risk_score: record.risk_score,
+            risk_level: record.risk_level,
+            disposition: record.disposition,
+            previous_risk_score: source.risk_score,
+            created_at: record.created_at,
+            queries_remaining: None,
+        })
+    }
+
+    /// Score a stored transaction again with `assess` without recording the result
+    ///
+    /// Like [`TransactionService::rescore`], but nothing is written, so the transaction is
+    /// neither locked nor given a revision, and no case is opened. Reads from the read pool.
+    /// Returns the transaction's stored scoring alongside the new assessment.
+    pub async fn assess_stored(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+        assess: impl FnOnce(&TransactionRequest, &UserSignals) -> RiskAssessment,
+    ) -> ServiceResult<(RescoreSourceRecord, RiskAssessment)> {
+        let mut conn = self.read_pool.acquire().await?;
+        let source = ScoringRevisionRepo::find_source(&mut *conn, tenant, transaction_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let request = kept_request(&source)?;
+        let signals = self
+            .stored_signals(&mut conn, tenant, &source, request)
+            .await?;
+        let assessment = assess(request, &signals);
+        Ok((source, assessment))
+    }
+
+    /// Assemble insights into the device, email, addresses, phone, and card of a transaction
+    ///
+    /// Reads from the primary, like [`TransactionService::get_transaction`].
+    pub async fn insights(
+        &self,
+        tenant: Tenant,
+        transaction_id: Uuid,
+    ) -> ServiceResult<TransactionInsights> {
+        let record = self.get_transaction(tenant, transaction_id).await?;
+        let device = InsightsRepo::device(&self.pool, tenant, record.id).await?;
+        let email = InsightsRepo::email(&self.pool, tenant, record.id).await?;
+        let card = InsightsRepo::credit_card(&self.pool, tenant, record.id).await?;
+
+        let mut billing = None;
+        let mut shipping = None;
+        for address in InsightsRepo::addresses(&self.pool, tenant, record.id).await? {
+            match address.address_type.as_str() {
+                "billing" => billing = Some(address),
+                _ => shipping = Some(address),
+            }
+        }
+
+        let phone_source = [&billing, &shipping]
+            .into_iter()
+            .flatten()
+            .find_map(|a| Some((a.phone_number.clone()?, a.phone_country_code.clone())));
+        let phone = match phone_source {
+            Some((number, country_code)) => {
+                let usage =
+                    InsightsRepo::phone_usage(&self.pool, tenant, &number, country_code.as_deref())
+                        .await?;
+                let info = phone_intel::lookup(&number, country_code.as_deref());
+                Some(PhoneInsights {
+                    e164: info.as_ref().map(|info| info.e164.clone()),
+                    is_valid: info.as_ref().map(|info| info.is_valid),
+                    country: info.as_ref().and_then(|info| info.country.clone()),
+                    line_type: info.as_ref().and_then(|info| info.line_type),
+                    operator: info.and_then(|info| info.operator),
+                    number,
+                    country_code,
+                    transaction_count: usage.transaction_count,
+                    user_count: usage.user_count,
+                })
+            },
+            None => None,
+        };
+
+        let billing_country = billing.as_ref().and_then(|a| a.country.clone());
+        let ip_location = device
+            .as_ref()
+            .and_then(|device| self.ip_location(&device.ip_address));
+        Ok(TransactionInsights {
+            transaction_id: record.id,
+            device: device.map(Into::into),
+            email: email.map(Into::into),
+            billing_address: billing.map(|a| address_insights(a, ip_location.as_ref())),
+            shipping_address: shipping.map(|a| address_insights(a, ip_location.as_ref())),
+            phone,
+            credit_card: card.map(|card| card_insights(card, billing_country.as_deref())),
+            links: Links {
+                self_link: Some(Link::new(format!(
+                    "/v1/transactions/{}/insights",
+                    record.id
+                ))),
+                ..Links::default()
+            },
+        })
+    }
+
+    /// Assemble what is known about `ip`: its location and network, and its history and most
+    /// recent transactions on the account
+    ///
+    /// Reserved addresses are not located. Traits from IP intelligence feeds are left for the
+    /// caller to look up.
+    pub async fn ip_insights(
+        &self,
+        tenant: Tenant,
+        ip: IpAddr,
+    ) -> ServiceResult<IpAddressInsights> {
+        let ip_address = ip.to_string();
+        let ip_reserved = is_reserved_ip(ip);
+        let mut location = (!ip_reserved).then(|| self.geoip.lookup(ip)).flatten();
+        if let Some(location) = &mut location
+            && let Some(time_zone) = location.time_zone.clone()
+        {
+            location.local_time = local_time(&self.read_pool, &time_zone, Utc::now()).await?;
+        }
+        let network = (!ip_reserved).then(|| self.geoip.lookup_asn(ip)).flatten();
+        let reputation = IpAddressRepo::find(&self.read_pool, tenant, &ip_address).await?;
+        let query = ListTransactionsQuery {
+            ip_address: Some(ip_address.clone()),
+            ..ListTransactionsQuery::default()
+        };
+        let recent = TransactionRepo::list_after(
+            &self.read_pool,
+            tenant,
+            &query,
+            None,
+            false,
+            RECENT_IP_TRANSACTIONS,
+        )
+        .await?;
+
+        Ok(IpAddressInsights {
+            links: Links {
+                self_link: Some(Link::new(format!("/v1/ip/{ip_address}"))),
+                ..Links::default()
+            },
+            ip_address,
+            ip_reserved,
+            location_risk: location.as_ref().map(get_location_risk_score),
+            location: location.and_then(|info| serde_json::to_value(info).ok()),
+            asn: network.as_ref().map(|network| network.asn.into()),
+            isp: network
+                .as_ref()
+                .and_then(AsnInfo::provider)
+                .map(str::to_string),
+            traits: None,
+            reputation: reputation.map(Into::into),
+            recent_transactions: recent.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    /// Assemble what is known about the email address hashed to `email_hash`: its domain, and
+    /// its history and most recent transactions on the account
+    ///
+    /// `domain` is the domain of the address when the caller has the address itself; otherwise
+    /// the stored domain is used, if the account has seen the address. The domain's traits are
+    /// those the address was last scored with; looking them up afresh is left to the caller.
+    pub async fn email_insights(
+        &self,
+        tenant: Tenant,
+        email_hash: &str,
+        domain: Option<String>,
+    ) -> ServiceResult<EmailAddressInsights> {
+        let record = EmailAddressRepo::find(&self.read_pool, tenant, email_hash).await?;
+        let recent = EmailAddressRepo::recent_transactions(
+            &self.read_pool,
+            tenant,
+            email_hash,
+            RECENT_EMAIL_TRANSACTIONS,
+        )
+        .await?;
+        let domain = match domain.or_else(|| record.as_ref()?.domain.clone()) {
+            Some(domain) => Some(EmailDomainInsights {
+                address_count: EmailAddressRepo::count_by_domain(&self.read_pool, tenant, &domain)
+                    .await?,
+                is_free: record.as_ref().is_some_and(|record| record.is_free),
+                is_disposable: record.as_ref().is_some_and(|record| record.is_disposable),
+                domain,
+            }),
+            None => None,
+        };
+
+        Ok(EmailAddressInsights {
+            email_hash: email_hash.to_string(),
+            domain,
+            history: record.map(Into::into),
+            recent_transactions: recent.into_iter().map(Into::into).collect(),
+            links: Links {
+                self_link: Some(Link::new(format!("/v1/emails/{email_hash}"))),
+                ..Links::default()
+            },
+        })
+    }
+}
+
+impl From<EmailVariantsRecord> for EmailVariants {
+    fn from(record: EmailVariantsRecord) -> Self {
+        EmailVariants {
+            addresses: record.address_count,
+            users: record.user_count,
+        }
+    }
+}
+
+impl From<EmailAddressRecord> for EmailHistoryInsights {
+    fn from(record: EmailAddressRecord) -> Self {
+        EmailHistoryInsights {
+            transaction_count: record.transaction_count,
+            reject_count: record.reject_count,
+            chargeback_count: record.chargeback_count,
+            user_count: record.user_count,
+            card_count: record.card_count,
+            is_high_risk: record.is_high_risk,
+            first_seen: record.first_seen,
+            last_seen: record.last_seen,
+        }
+    }
+}
+
+impl From<IpAddressRecord> for IpHistoryInsights {
+    fn from(record: IpAddressRecord) -> Self {
+        IpHistoryInsights {
+            risk: record.risk_score,
+            transaction_count: record.history.transaction_count,
+            reject_count: record.history.reject_count,
+            chargeback_count: record.history.chargeback_count,
+            user_count: record.history.user_count,
+            card_count: record.history.card_count,
+            first_seen: record.first_seen,
+            last_seen: record.last_seen,
+        }
+    }
+}
+
+impl From<DeviceInsightRecord> for DeviceInsights {
+    fn from(record: DeviceInsightRecord) -> Self {
+        DeviceInsights {
+            ip_reserved: record.ip_address.parse().is_ok_and(is_reserved_ip),
+            ip_address: record.ip_address,
+            asn: record.asn,
+            isp: record.isp,
+            ip_risk_score: record.ip_risk_score,
+            ip_location: record.ip_location,
+            ip_traits: record
+                .ip_traits
+                .and_then(|traits| serde_json::from_value(traits).ok()),
+            ip_history: record.ip_history.map(|Json(history)| history),
+            risk_score: record.risk_score,
+            user_agent: record.user_agent,
+            accept_language: record.accept_language,
+            first_seen: record.first_seen,
+            last_seen: record.last_seen,
+            transaction_count: record.transaction_count,
+        }
+    }
+}
+
+impl From<EmailInsightRecord> for EmailInsights {
+    fn from(record: EmailInsightRecord) -> Self {
+        EmailInsights {
+            domain: record.domain,
+            first_seen: record.first_seen,
+            is_free: record.is_free,
+            is_disposable: record.is_disposable,
+            is_high_risk: record.is_high_risk,
+            transaction_count: record.transaction_count,
+        }
+    }
+}
+
+/// Address insights, compared against the location of the transaction's IP address
+fn address_insights(
+    record: AddressInsightRecord,
+    ip_location: Option<&IpAddressInfo>,
+) -> AddressInsights {
+    let is_in_ip_country = record
+        .country
+        .as_deref()
+        .zip(ip_location.and_then(|ip| ip.country.as_deref()))
+        .map(|(country, ip_country)| country == ip_country);
+    let distance_to_ip_location = record
+        .latitude
+        .zip(record.longitude)
+        .zip(ip_location.and_then(IpAddressInfo::coordinates))
+        .map(|(address, ip)| distance_km(address, ip).round());
+    AddressInsights {
+        city: record.city,
+        region: record.region,
+        postal: record.postal_code,
+        country: record.country,
+        latitude: record.latitude,
+        longitude: record.longitude,
+        is_in_ip_country,
+        distance_to_ip_location,
+        is_high_risk: record.is_high_risk,
+        delivery_speed: record.delivery_speed,
+        transaction_count: record.transaction_count,
+    }
+}
+
+/// Card insights, deriving the brand from the issuer ID number when it is not stored
+fn card_insights(
+    record: CreditCardInsightRecord,
+    billing_country: Option<&str>,
+) -> CreditCardInsights {
+    let brand = record.brand.or_else(|| {
+        let iin = record.issuer_id_number.as_deref()?;
+        card_brand(iin).map(str::to_string)
+    });
+    let matches_billing_country = record
+        .country
+        .as_deref()
+        .zip(billing_country)
+        .map(|(card, billing)| card == billing);
+    CreditCardInsights {
+        issuer_id_number: record.issuer_id_number,
+        last_digits: record.last_digits,
+        brand,
+        card_type: record.card_type,
+        bank_name: record.bank_name,
+        country: record.country,
+        matches_billing_country,
+        avs_result: record.avs_result,
+        cvv_result: record.cvv_result,
+        was_3d_secure_successful: record.was_3d_secure_successful,
+        is_business: record.is_business,
+        is_prepaid: record.is_prepaid,
+        is_virtual: record.is_virtual,
+        billing_name_match: record.billing_name_match,
+        email_name_match: record.email_name_match,
+        transaction_count: record.transaction_count,
+    }
+}
+
+/// Fingerprint identifying a transaction's device: the one its device token names, or else
+/// one of its IP address and headers
+pub fn request_device_fingerprint(device: &TransactionDevice) -> String {
+    match device.device_token.as_deref().and_then(token_fingerprint) {
+        Some(fingerprint) => fingerprint.to_string(),
+        None => device_fingerprint(device),
+    }
+}
+
+/// Start of the window over which the distinct users of a device are counted for scoring
+fn device_users_since() -> DateTime<Utc> {
+    Utc::now() - TimeDelta::hours(DEVICE_USERS_WINDOW_HOURS)
+}
+
+/// Request a stored transaction was scored on, or a conflict if it was not kept
+fn kept_request(source: &RescoreSourceRecord) -> ServiceResult<&TransactionRequest> {
+    match &source.raw_request {
+        Some(Json(request)) => Ok(request),
+        None => Err(ServiceError::Conflict(
+            "Transaction was scored before requests were kept for rescoring".to_string(),
+        )),
+    }
+}
+
+/// Scoring signals from a user's flags and the history of the transaction's device and IP
+/// address
+fn user_signals(
+    user: Option<UserFlagsRecord>,
+    device: Option<DeviceHistoryRecord>,
+    ip: IpReputationRecord,
+) -> UserSignals {
+    let user_id = user.as_ref().map(|user| user.id);
+    let flags = user.map(|user| user.flags.0).unwrap_or_default();
+    let mut signals = UserSignals::new(flags, Utc::now());
+    signals.user_id = user_id;
+    signals.ip_history = ip.account.into();
+    signals.ip_global_history = ip.global.into();
+    if let Some(device) = device {
+        signals.device_status = device.status;
+        signals.device_user_count = device.recent_users;
+        signals.device_chargebacks = device.chargebacks;
+    }
+    signals
+}
+
+/// Entities of a transaction the account's lists may name, as pairs of entity type and
+/// normalized value: its email address and domain, its IP address and every range around it,
+/// its existing user and device, its card, and the countries it involves
+///
+/// The email address and card are named by `keys`, as a stored transaction's kept request
+/// only holds them hashed again.
+fn list_entities(
+    request: &TransactionRequest,
+    keys: &MatchKeys,
+    user_id: Option<Uuid>,
+    device_id: Option<Uuid>,
+    countries: &[String],
+) -> Vec<(ListEntityType, String)> {
+    let mut entities = Vec::new();
+    entities.extend(
+        keys.email_hash
+            .clone()
+            .map(|hash| (ListEntityType::Email, hash)),
+    );
+    if let Some(email) = &request.email {
+        let domain = email
+            .resolved_domain()
+            .and_then(|domain| ListEntityType::EmailDomain.normalize(&domain).ok());
+        entities.extend(domain.map(|domain| (ListEntityType::EmailDomain, domain)));
+    }
+    if let Ok(ip) = request.device.ip_address.trim().parse::<IpAddr>() {
+        let ip = ip.to_canonical();
+        entities.push((ListEntityType::Ip, ip.to_string()));
+        entities.extend(
+            (0..=max_prefix_len(ip))
+                .map(|prefix_len| (ListEntityType::Cidr, network(ip, prefix_len))),
+        );
+    }
+    entities.extend(user_id.map(|id| (ListEntityType::User, id.to_string())));
+    entities.extend(device_id.map(|id| (ListEntityType::Device, id.to_string())));
+    entities.extend(
+        keys.card_hash
+            .clone()
+            .map(|hash| (ListEntityType::CardHash, hash)),
+    );
+    for country in countries {
+        if let Ok(country) = ListEntityType::Country.normalize(country)
+            && !entities.contains(&(ListEntityType::Country, country.clone()))
+        {
+            entities.push((ListEntityType::Country, country));
+        }
+    }
+    entities
+}
+
+/// Stable per-account identity of a device
+fn device_fingerprint(device: &TransactionDevice) -> String {
+    sha256_hex(&format!(
+        "{}|{}|{}",
+        device.ip_address,
+        device.user_agent.as_deref().unwrap_or_default(),
+        device.accept_language.as_deref().unwrap_or_default()
+    ))
+}
+
+/// Record a transaction's email address, stored only as hashes of its normalized form and of
+/// the mailbox it delivers to, with the traits of its domain it was scored with
+async fn store_email(
+    conn: &mut PgConnection,
+    tenant: Tenant,
+    user_id: Option<Uuid>,
+    transaction_id: Uuid,
+    email: &TransactionEmail,
+    traits: EmailTraits,
+) -> ServiceResult<()> {
+    let (Some(email_hash), Some(mailbox_hash)) = (email.address_hash(), email.mailbox_hash())
+    else {
+        return Ok(());
+    };
+    let domain = email.resolved_domain();
+
+    let email_id = TransactionRepo::upsert_email(
+        &mut *conn,
+        tenant,
+        user_id,
+        &email_hash,
+        &mailbox_hash,
+        domain.as_deref(),
+        traits,
+    )
+    .await?;
+    TransactionRepo::link_email(&mut *conn, transaction_id, email_id).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::{Config, EmailIntelConfig},
+        database::repositories::AccountRepo,
+        models::{account::SubscriptionTier, transaction::Disposition},
+        scoring::RiskEngine,
+        test_support::{create_account, test_pool},
+        utils::geo::tests::{asn_mmdb, located_mmdb},
+    };
+
+    #[test]
+    fn test_device_fingerprint_is_stable() {
+        let device = TransactionDevice {
+            ip_address: "198.51.100.1".to_string(),
+            user_agent: Some("Mozilla/5.0".to_string()),
+            accept_language: None,
+            session_id: Some("a".to_string()),
+            session_age: None,
+            device_token: None,
+            ja3: None,
+            ja4: None,
+            header_order_hash: None,
+        };
+        let mut other_session = device.clone();
+        other_session.session_id = Some("b".to_string());
+        assert_eq!(
+            device_fingerprint(&device),
+            device_fingerprint(&other_session)
+        );
+
+        let mut other_ip = device.clone();
+        other_ip.ip_address = "198.51.100.2".to_string();
+        assert_ne!(device_fingerprint(&device), device_fingerprint(&other_ip));
+    }
+
+    #[tokio::test]
+    async fn test_ip_insights_cover_network_history_and_recent_transactions() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+
+        let path = std::env::temp_dir().join(format!("asn-{}.mmdb", Uuid::new_v4()));
+        std::fs::write(&path, asn_mmdb(64_500, "Example Hosting")).unwrap();
+        let geoip = GeoIpDatabase::disabled().with_asn_database(Some(path.clone()));
+        geoip.reload().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction)
+                .with_geoip(geoip);
+
+        let ip: IpAddr = "45.1.2.3".parse().unwrap();
+        let unseen = transactions.ip_insights(tenant, ip).await.unwrap();
+        assert!(!unseen.ip_reserved);
+        assert_eq!(unseen.asn, Some(64_500));
+        assert_eq!(unseen.isp.as_deref(), Some("Example Hosting"));
+        assert!(unseen.reputation.is_none());
+        assert!(unseen.recent_transactions.is_empty());
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": ip.to_string() },
+            "event": { "type": "purchase" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        let assessment = RiskEngine::new().assess(&request, &user);
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+
+        let seen = transactions.ip_insights(tenant, ip).await.unwrap();
+        assert_eq!(seen.reputation.map(|r| r.transaction_count), Some(1));
+        let recent: Vec<Uuid> = seen.recent_transactions.iter().map(|t| t.id).collect();
+        assert_eq!(recent, [stored.id]);
+        assert_eq!(
+            seen.links.self_link.map(|link| link.href),
+            Some(format!("/v1/ip/{ip}"))
+        );
+
+        let reserved = transactions
+            .ip_insights(tenant, "10.0.0.1".parse().unwrap())
+            .await
+            .unwrap();
+        assert!(reserved.ip_reserved);
+        assert_eq!(reserved.asn, None);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_addresses_are_located_and_compared_with_the_ip_location() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+
+        let city = std::env::temp_dir().join(format!("city-{}.mmdb", Uuid::new_v4()));
+        std::fs::write(&city, located_mmdb("US", 34.05, -118.24)).unwrap();
+        let postal = std::env::temp_dir().join(format!("postal-{}.txt", Uuid::new_v4()));
+        std::fs::write(
+            &postal,
+            "US\t10001\tNew York\t\t\t\t\t\t\t40.7484\t-73.9967\t4\n\
+             US\t90012\tLos Angeles\t\t\t\t\t\t\t34.0614\t-118.2385\t4\n",
+        )
+        .unwrap();
+        let geoip =
+            GeoIpDatabase::new(Some(city.clone())).with_postal_centroids(Some(postal.clone()));
+        geoip.reload().unwrap();
+        std::fs::remove_file(&city).unwrap();
+        std::fs::remove_file(&postal).unwrap();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction)
+                .with_geoip(geoip);
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "1.2.3.4", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "billing": { "country": "US", "postal": "10001" },
+            "shipping": { "country": "US", "postal": "90012-3456" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        assert_eq!(user.ip_country.as_deref(), Some("US"));
+        assert!(user.billing_ip_distance_km.is_some_and(|km| km > 3_900.0));
+        assert!(user.shipping_ip_distance_km.is_some_and(|km| km < 5.0));
+        let assessment = RiskEngine::new().assess(&request, &user);
+        let codes: Vec<&str> = assessment
+            .factors
+            .iter()
+            .map(|factor| factor.code.as_str())
+            .collect();
+        assert!(codes.contains(&"BILLING_FAR_FROM_IP"));
+        assert!(!codes.contains(&"SHIPPING_FAR_FROM_IP"));
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+
+        let insights = transactions.insights(tenant, stored.id).await.unwrap();
+        let billing = insights.billing_address.unwrap();
+        assert_eq!(billing.latitude, Some(40.7484));
+        assert_eq!(billing.longitude, Some(-73.9967));
+        assert_eq!(billing.is_in_ip_country, Some(true));
+        assert!(
+            billing
+                .distance_to_ip_location
+                .is_some_and(|km| km > 3_900.0)
+        );
+        let shipping = insights.shipping_address.unwrap();
+        assert_eq!(shipping.latitude, Some(34.0614));
+        assert!(shipping.distance_to_ip_location.is_some_and(|km| km < 5.0));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refunds_link_to_their_purchase_and_count_against_the_user() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let store = |event: serde_json::Value| {
+            let transactions = transactions.clone();
+            async move {
+                let request: TransactionRequest = serde_json::from_value(json!({
+                    "device": { "ip_address": "198.51.100.7", "user_agent": "Mozilla/5.0" },
+                    "event": event,
+                    "account": { "user_id": "refunding-user" },
+                    "order": { "amount": 40.0, "currency": "USD" }
+                }))
+                .unwrap();
+                request.validate().unwrap();
+                let user = transactions.user_signals(tenant, &request).await.unwrap();
+                let assessment = RiskEngine::new().assess(&request, &user);
+                let stored = transactions
+                    .store_transaction(tenant, &request, &assessment, &[])
+                    .await
+                    .unwrap();
+                (stored, assessment)
+            }
+        };
+
+        let mut purchases = Vec::new();
+        for i in 0..4 {
+            let (stored, _) =
+                store(json!({ "type": "purchase", "transaction_id": format!("order-{i}") })).await;
+            purchases.push(stored.id);
+        }
+        let (refund, _) = store(json!({
+            "type": "refund",
+            "transaction_id": "refund-0",
+            "original_transaction_id": "order-0"
+        }))
+        .await;
+        assert_eq!(refund.original_transaction_id, Some(purchases[0]));
+        assert!(refund.warnings.0.is_empty());
+
+        // A refund of a purchase never scored is kept with a warning
+        let (unlinked, _) = store(json!({
+            "type": "refund",
+            "original_transaction_id": "order-elsewhere"
+        }))
+        .await;
+        assert_eq!(unlinked.original_transaction_id, None);
+        assert_eq!(
+            unlinked.warnings.0[0].code,
+            "ORIGINAL_TRANSACTION_NOT_FOUND"
+        );
+
+        // One of four purchases refunded is within the usual share; two of five are not
+        let (_, assessment) = store(json!({ "type": "purchase" })).await;
+        assert!(!assessment.factors.iter().any(|f| f.code == "REFUND_ABUSE"));
+        store(json!({
+            "type": "cancellation",
+            "original_transaction_id": "order-1"
+        }))
+        .await;
+        store(json!({
+            "type": "refund",
+            "original_transaction_id": "order-2"
+        }))
+        .await;
+        let (_, assessment) = store(json!({ "type": "purchase" })).await;
+        assert!(assessment.factors.iter().any(|f| f.code == "REFUND_ABUSE"));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disposable_email_domains_are_scored_and_stored() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let config = Config::default();
+        let transactions = TransactionService::new(pool.clone(), pool.clone(), config.redaction)
+            .with_signal_sources(SignalSources {
+                sessions: SessionStore::memory(),
+                features: FeatureStore::new(pool.clone()),
+                ip_intel: IpIntelService::new(pool.clone(), None, &config.ip_intel),
+                email_intel: EmailIntelService::new(&EmailIntelConfig {
+                    mx_lookups_enabled: false,
+                    ..config.email_intel
+                }),
+                screening: ScreeningService::new(&config.screening),
+            });
+        let disposable = |assessment: &RiskAssessment| {
+            assessment
+                .factors
+                .iter()
+                .any(|factor| factor.code == "DISPOSABLE_EMAIL")
+        };
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "email": { "address": "burner@yopmail.com" }
+        }))
+        .unwrap();
+        let (user, _) = transactions.gather_signals(tenant, &request).await.unwrap();
+        let assessment = RiskEngine::new().assess(&request, &user);
+        assert!(disposable(&assessment));
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        // Rescoring looks the kept domain up again
+        let (_, rescored) = transactions
+            .assess_stored(tenant, stored.id, |request, user| {
+                RiskEngine::new().assess(request, user)
+            })
+            .await
+            .unwrap();
+        assert!(disposable(&rescored));
+
+        let email = transactions
+            .insights(tenant, stored.id)
+            .await
+            .unwrap()
+            .email
+            .unwrap();
+        assert_eq!(email.domain.as_deref(), Some("yopmail.com"));
+        assert!(email.is_disposable);
+        assert!(!email.is_free);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cardholder_name_matches_reach_card_insights() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "email": { "address": "jsmith87@example.com" },
+            "billing": { "first_name": "Maria", "last_name": "Garcia" },
+            "credit_card": { "issuer_id_number": "411111", "holder_name": "JONATHAN SMITH" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        let assessment = RiskEngine::new().assess(&request, &user);
+        assert!(
+            assessment
+                .factors
+                .iter()
+                .any(|factor| factor.code == "CARDHOLDER_NAME_MISMATCH")
+        );
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+
+        let card = transactions
+            .insights(tenant, stored.id)
+            .await
+            .unwrap()
+            .credit_card
+            .unwrap();
+        assert!(card.billing_name_match.is_some_and(|score| score < 0.5));
+        assert_eq!(card.email_name_match, Some(1.0));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_email_insights_cover_domain_history_and_recent_transactions() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let email = TransactionEmail {
+            address: Some(" Jane@Example.com".to_string()),
+            domain: None,
+        };
+        let email_hash = email.address_hash().unwrap();
+        assert_eq!(email_hash, sha256_hex("jane@example.com"));
+
+        let unseen = transactions
+            .email_insights(tenant, &email_hash, email.resolved_domain())
+            .await
+            .unwrap();
+        let domain = unseen.domain.unwrap();
+        assert_eq!(domain.domain, "example.com");
+        assert_eq!(domain.address_count, 0);
+        assert!(unseen.history.is_none());
+        assert!(unseen.recent_transactions.is_empty());
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "198.51.100.1" },
+            "event": { "type": "purchase" },
+            "email": { "address": "jane@example.com" }
+        }))
+        .unwrap();
+        let user = transactions.user_signals(tenant, &request).await.unwrap();
+        let assessment = RiskEngine::new().assess(&request, &user);
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+
+        let seen = transactions
+            .email_insights(tenant, &email_hash, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            seen.domain
+                .map(|domain| (domain.domain, domain.address_count)),
+            Some(("example.com".to_string(), 1))
+        );
+        let history = seen.history.unwrap();
+        assert_eq!(history.transaction_count, 1);
+        assert_eq!(history.last_seen, Some(stored.created_at));
+        let recent: Vec<Uuid> = seen.recent_transactions.iter().map(|t| t.id).collect();
+        assert_eq!(recent, [stored.id]);
+        assert_eq!(
+            seen.links.self_link.map(|link| link.href),
+            Some(format!("/v1/emails/{email_hash}"))
+        );
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_variants_of_one_mailbox_across_users_are_scored() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let score = async |user_id: &str, address: &str| {
+            let request: TransactionRequest = serde_json::from_value(json!({
+                "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+                "event": { "type": "account_creation" },
+                "account": { "user_id": user_id },
+                "email": { "address": address }
+            }))
+            .unwrap();
+            let user = transactions.user_signals(tenant, &request).await.unwrap();
+            let assessment = RiskEngine::new().assess(&request, &user);
+            transactions
+                .store_transaction(tenant, &request, &assessment, &[])
+                .await
+                .unwrap();
+            (user.email_variants, assessment)
+        };
+
+        let (variants, _) = score("promo-1", "jane.doe@gmail.com").await;
+        assert_eq!(variants, EmailVariants::default());
+        // The same user coming back with another variant is no one else
+        let (variants, _) = score("promo-1", "janedoe+1@gmail.com").await;
+        assert_eq!(variants.addresses, 1);
+        assert_eq!(variants.users, 0);
+        score("promo-2", "j.anedoe+2@googlemail.com").await;
+        score("unrelated", "jane.doe@example.com").await;
+
+        let (variants, assessment) = score("promo-3", "JaneDoe+3@gmail.com").await;
+        assert_eq!(
+            variants,
+            EmailVariants {
+                addresses: 3,
+                users: 2
+            }
+        );
+        assert!(
+            assessment
+                .factors
+                .iter()
+                .any(|factor| factor.code == "EMAIL_VARIANTS")
+        );
+        assert_eq!(assessment.features.email_variant_users, 2);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_email_age_spans_accounts_and_counts_good_history() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let other = create_account(&pool, SubscriptionTier::Pro).await;
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let address = format!("{}@example.com", Uuid::new_v4());
+        let request = |amount: f64| -> TransactionRequest {
+            serde_json::from_value(json!({
+                "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+                "event": { "type": "purchase" },
+                "email": { "address": address },
+                "order": { "amount": amount, "currency": "USD" }
+            }))
+            .unwrap()
+        };
+
+        // Nobody has seen the address, so a large order with it is scored
+        let user = transactions
+            .user_signals(tenant, &request(1500.0))
+            .await
+            .unwrap();
+        assert_eq!(user.email_age, Some(EmailAge::default()));
+        let assessment = RiskEngine::new().assess(&request(1500.0), &user);
+        assert!(
+            assessment
+                .factors
+                .iter()
+                .any(|factor| factor.code == "NEW_EMAIL_LARGE_AMOUNT")
+        );
+        assert_eq!(assessment.features.email_age_days, Some(0));
+
+        // Two accepted purchases, one of them later charged back
+        let mut stored = Vec::new();
+        for _ in 0..2 {
+            let user = transactions
+                .user_signals(tenant, &request(20.0))
+                .await
+                .unwrap();
+            let assessment = RiskEngine::new().assess(&request(20.0), &user);
+            assert_eq!(assessment.disposition, Disposition::Accept);
+            stored.push(
+                transactions
+                    .store_transaction(tenant, &request(20.0), &assessment, &[])
+                    .await
+                    .unwrap(),
+            );
+        }
+        sqlx::query(
+            "INSERT INTO transaction_reports (transaction_id, tag, occurred_at) \
+             VALUES ($1, 'chargeback', NOW())",
+        )
+        .bind(stored[0].id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "UPDATE email_addresses SET first_seen = CURRENT_DATE - 10 WHERE account_id = $1",
+        )
+        .bind(tenant.id())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let age = transactions
+            .user_signals(tenant, &request(1500.0))
+            .await
+            .unwrap()
+            .email_age
+            .unwrap();
+        assert_eq!(
+            age,
+            EmailAge {
+                days: 10,
+                account_days: 10,
+                good_transactions: 1,
+            }
+        );
+        // Another account has never seen the address, but it has been around for a while
+        let age = transactions
+            .user_signals(other, &request(1500.0))
+            .await
+            .unwrap()
+            .email_age
+            .unwrap();
+        assert_eq!(
+            age,
+            EmailAge {
+                days: 10,
+                account_days: 0,
+                good_transactions: 0,
+            }
+        );
+
+        for tenant in [tenant, other] {
+            AccountRepo::delete(&pool, tenant.id()).await.unwrap();
+        }
+    }
+}
@@ -0,0 +1,897 @@
+//! User management
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    config::UserRiskConfig,
+    database::{
+        Tenant,
+        repositories::{
+            CountryCountRecord, IdentityLinkRepo, NewUser, OutboxRepo, UserDeviceUsageRecord,
+            UserImportRecord, UserImportRepo, UserRecord, UserRepo, UserRiskInputsRecord,
+            UserVelocityRecord,
+        },
+    },
+    features::{FeatureStore, UserProfile},
+    models::{
+        transaction::RiskLevel,
+        user::{
+            BehavioralBaseline, BehavioralPatterns, CountryActivity, CreateUser, DeviceConsistency,
+            LinkedUser, LinkedUserList, LocationPatterns, MergeUsers, RiskIndicator, User,
+            UserImport, UserLookupQuery, UserRiskAnalysis, UserUpdate, VelocityAnalysis,
+        },
+    },
+    outbox::{USER_MERGED, UserMerged},
+    scoring::{MAX_RISK_SCORE, combine_scores},
+};
+
+/// Fewest transactions in the last 24 hours that can count as a velocity spike
+const SPIKE_MIN_TRANSACTIONS: i64 = 5;
+/// How many times the 30-day daily average the last 24 hours must exceed to count as a spike
+const SPIKE_FACTOR: f64 = 3.0;
+/// Weight, in fresh transactions, of the neutral prior recent transaction scores are averaged
+/// with, so that a user's score decays towards 0 as their transactions age
+const RISK_PRIOR_WEIGHT: f64 = 1.0;
+/// Score contributed by a fresh report of fraud, abuse, or a chargeback
+const REPORTED_FRAUD_SCORE: f64 = 40.0;
+/// Score contributed by each chargeback on record, such as imported history
+const CHARGEBACK_SCORE: f64 = 10.0;
+/// Most chargebacks on record that count towards the score
+const MAX_COUNTED_CHARGEBACKS: usize = 20;
+
+impl From<UserRecord> for User {
+    fn from(record: UserRecord) -> Self {
+        User {
+            id: record.id,
+            external_user_id: record.external_user_id,
+            user_hash: record.user_hash,
+            risk_score: record.risk_score,
+            risk_level: record.risk_level,
+            total_transactions: record.total_transactions,
+            successful_transactions: record.successful_transactions,
+            failed_transactions: record.failed_transactions,
+            chargeback_count: record.chargeback_count,
+            first_transaction_at: record.first_transaction_at,
+            last_transaction_at: record.last_transaction_at,
+            is_verified: record.is_verified,
+            is_flagged: record.is_flagged,
+            flags: record.flags.0,
+            metadata: record.metadata,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            links: User::links(record.id),
+        }
+    }
+}
+
+impl From<UserImportRecord> for UserImport {
+    fn from(record: UserImportRecord) -> Self {
+        UserImport {
+            id: record.id,
+            status: record.status,
+            total_users: record.total_users,
+            processed_users: record.processed_users,
+            created_users: record.created_users,
+            updated_users: record.updated_users,
+            failed_users: record.failed_users,
+            errors: record.errors.0,
+            created_at: record.created_at,
+            started_at: record.started_at,
+            completed_at: record.completed_at,
+            links: UserImport::links(record.id),
+        }
+    }
+}
+
+/// Manages the end users tracked for each account
+#[derive(Debug, Clone)]
+pub struct UserService {
+    pool: PgPool,
+    features: FeatureStore,
+    risk: UserRiskConfig,
+}
+
+impl UserService {
+    /// Create a user service backed by the given pool, recalculating risk scores as `risk`
+    /// says
+    pub fn new(pool: PgPool, risk: UserRiskConfig) -> Self {
+        let features = FeatureStore::new(pool.clone());
+        Self {
+            pool,
+            features,
+            risk,
+        }
+    }
+
+    /// Fetch a live user of an account
+    pub async fn get_user(&self, tenant: Tenant, user_id: Uuid) -> ServiceResult<User> {
+        UserRepo::find(&self.pool, tenant, user_id)
+            .await?
+            .map(User::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Find the live user an external user ID or user hash stands for
+    ///
+    /// Identifiers of a merged user find the user it was merged into.
+    pub async fn lookup_user(
+        &self,
+        tenant: Tenant,
+        query: &UserLookupQuery,
+    ) -> ServiceResult<User> {
+        UserRepo::find_by_identifier(
+            &self.pool,
+            tenant,
+            query.external_user_id.as_deref(),
+            query.user_hash.as_deref(),
+        )
+        .await?
+        .map(User::from)
+        .ok_or(ServiceError::NotFound)
+    }
+
+    /// Register a user under the customer's own user ID
+    ///
+    /// Transactions naming the same `account.user_id` are then attributed to this user. The
+    /// ID must be new to the account, including among deleted users.
+    pub async fn create_user(&self, tenant: Tenant, request: &CreateUser) -> ServiceResult<User> {
+        request.validate().map_err(ServiceError::Invalid)?;
+        let metadata = request
+            .metadata
+            .clone()
+            .unwrap_or_else(|| serde_json::json!({}));
+        let record = UserRepo::insert(
+            &self.pool,
+            NewUser {
+                tenant,
+                external_user_id: request.external_user_id.trim(),
+                is_verified: request.is_verified.unwrap_or(false),
+                is_flagged: request.is_flagged.unwrap_or(false),
+                flags: request.flags.as_deref().unwrap_or_default(),
+                metadata: &metadata,
+            },
+        )
+        .await
+        .map_err(|e| {
+            if e.as_database_error()
+                .is_some_and(|db| db.is_unique_violation())
+            {
+                ServiceError::Conflict(
+                    "A user with this external_user_id already exists".to_string(),
+                )
+            } else {
+                ServiceError::Database(e)
+            }
+        })?;
+
+        tracing::info!(account_id = %tenant, user_id = %record.id, "User created");
+        Ok(record.into())
+    }
+
+    /// Change a live user's verification, flags, and metadata
+    pub async fn update_user(
+        &self,
+        tenant: Tenant,
+        user_id: Uuid,
+        update: &UserUpdate,
+    ) -> ServiceResult<User> {
+        update.validate().map_err(ServiceError::Invalid)?;
+        if update.is_empty() {
+            return self.get_user(tenant, user_id).await;
+        }
+        UserRepo::update(&self.pool, tenant, user_id, update)
+            .await?
+            .map(User::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Analyze a live user's recent velocity, devices, and locations against their profile
+    ///
+    /// Windows end at the time of analysis and cover the user's stored transactions. The
+    /// feature store's profile, when one has been computed, is returned as the baseline and
+    /// used to decide which indicators apply.
+    pub async fn risk_analysis(
+        &self,
+        tenant: Tenant,
+        user_id: Uuid,
+    ) -> ServiceResult<UserRiskAnalysis> {
+        let user = UserRepo::find(&self.pool, tenant, user_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let now = Utc::now();
+        let velocity = UserRepo::velocity(&self.pool, tenant, user_id, now).await?;
+        let devices = UserRepo::device_usage(&self.pool, tenant, user_id, now).await?;
+        let countries = UserRepo::billing_countries(&self.pool, tenant, user_id, now).await?;
+        let mismatches = UserRepo::country_mismatches(&self.pool, tenant, user_id, now).await?;
+        // The user was found under the tenant above, so the profile is the tenant's too
+        let profile = self.features.get_user_profile(user_id).await?;
+
+        Ok(build_risk_analysis(
+            &user,
+            velocity,
+            devices,
+            countries,
+            mismatches,
+            profile.as_ref(),
+            now,
+        ))
+    }
+
+    /// Merge a duplicate user into `user_id`
+    ///
+    /// The duplicate's transactions and entities move to `user_id`, which absorbs its totals,
+    /// flags, and metadata (see [`UserRepo::absorb`]). The duplicate is then soft-deleted as
+    /// merged, so transactions naming its identifiers are attributed to `user_id` from then
+    /// on. Everything, including the `user.merged` outbox event, happens in one database
+    /// transaction.
+    pub async fn merge_users(
+        &self,
+        tenant: Tenant,
+        user_id: Uuid,
+        request: &MergeUsers,
+    ) -> ServiceResult<User> {
+        let source = request.source_user_id;
+        if source == user_id {
+            return Err(ServiceError::Invalid(
+                "A user cannot be merged into itself".to_string(),
+            ));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let locked = UserRepo::lock_live(&mut *tx, tenant, &[user_id, source]).await?;
+        if !locked.contains(&user_id) {
+            return Err(ServiceError::NotFound);
+        }
+        if !locked.contains(&source) {
+            return Err(ServiceError::Invalid(
+                "source_user_id does not reference a known user".to_string(),
+            ));
+        }
+
+        let record = UserRepo::absorb(&mut *tx, tenant, user_id, source).await?;
+        let transactions_moved = UserRepo::move_history(&mut *tx, tenant, source, user_id).await?;
+        UserRepo::mark_merged(&mut *tx, tenant, source, user_id).await?;
+        IdentityLinkRepo::delete_for_user(&mut *tx, tenant, source).await?;
+
+        let payload = serde_json::to_value(UserMerged {
+            user_id,
+            merged_user_id: source,
+            transactions_moved,
+        })
+        .unwrap_or_default();
+        OutboxRepo::insert(&mut *tx, tenant.id(), USER_MERGED, user_id, payload).await?;
+        tx.commit().await?;
+
+        tracing::info!(
+            account_id = %tenant,
+            %user_id,
+            merged_user_id = %source,
+            transactions_moved,
+            "Users merged"
+        );
+        Ok(record.into())
+    }
+
+    /// Recalculate a live user's risk score now, returning the user updated
+    pub async fn recalculate_risk(&self, tenant: Tenant, user_id: Uuid) -> ServiceResult<User> {
+        if UserRepo::find_id(&self.pool, tenant, user_id)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::NotFound);
+        }
+        self.recalculate_risk_scores(&[user_id], Utc::now()).await?;
+        tracing::info!(account_id = %tenant, %user_id, "User risk score recalculated");
+        self.get_user(tenant, user_id).await
+    }
+
+    /// Recalculate and store the risk scores of `user_ids` as of `now`
+    ///
+    /// A score combines the user's recent transaction scores, averaged with decay towards 0,
+    /// with recent reports of fraud and the chargebacks on record; see [`user_risk_score`].
+    /// Callers check that the users belong to the tenant concerned.
+    pub async fn recalculate_risk_scores(
+        &self,
+        user_ids: &[Uuid],
+        now: DateTime<Utc>,
+    ) -> sqlx::Result<()> {
+        let lookback_days = i32::try_from(self.risk.lookback_days).unwrap_or(i32::MAX);
+        let half_life_days = f64::from(self.risk.half_life_days);
+        let inputs =
+            UserRepo::risk_inputs(&self.pool, user_ids, now, lookback_days, half_life_days).await?;
+
+        let mut tx = self.pool.begin().await?;
+        for input in &inputs {
+            let risk_score = user_risk_score(input);
+            let risk_level = RiskLevel::from_score(risk_score);
+            UserRepo::set_risk_score(&mut *tx, input.user_id, risk_score, risk_level, now).await?;
+        }
+        tx.commit().await
+    }
+
+    /// List the users identity resolution linked to a live user
+    pub async fn linked_users(
+        &self,
+        tenant: Tenant,
+        user_id: Uuid,
+    ) -> ServiceResult<LinkedUserList> {
+        if UserRepo::find_id(&self.pool, tenant, user_id)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::NotFound);
+        }
+        let linked_users = IdentityLinkRepo::for_user(&self.pool, tenant, user_id)
+            .await?
+            .into_iter()
+            .map(|record| LinkedUser {
+                links: User::links(record.user_id),
+                user_id: record.user_id,
+                external_user_id: record.external_user_id,
+                link_type: record.link_type,
+                shared_count: record.shared_count,
+                first_detected_at: record.first_detected_at,
+                last_detected_at: record.last_detected_at,
+            })
+            .collect();
+        Ok(LinkedUserList { linked_users })
+    }
+
+    /// Queue users for import by the background worker
+    ///
+    /// Users are stored as submitted and only parsed and validated as they are imported, so
+    /// one malformed user fails on its own rather than refusing the whole batch.
+    pub async fn import_users(
+        &self,
+        tenant: Tenant,
+        users: &[serde_json::Value],
+    ) -> ServiceResult<UserImport> {
+        let record = UserImportRepo::insert(&self.pool, tenant, users).await?;
+        tracing::info!(
+            import_id = %record.id,
+            account_id = %tenant,
+            total_users = record.total_users,
+            "User import queued"
+        );
+        Ok(record.into())
+    }
+
+    /// Fetch one of an account's user imports
+    pub async fn get_import(&self, tenant: Tenant, import_id: Uuid) -> ServiceResult<UserImport> {
+        UserImportRepo::find_by_id(&self.pool, tenant, import_id)
+            .await?
+            .map(UserImport::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Soft-delete a user
+    ///
+    /// The user's row and transaction history are kept for audit, but the user is no longer
+    /// matched by new transactions and no longer contributes to profiles or risk features.
+    pub async fn delete_user(&self, tenant: Tenant, user_id: Uuid) -> ServiceResult<()> {
+        if UserRepo::soft_delete(&self.pool, tenant, user_id).await? {
+            tracing::info!(account_id = %tenant, %user_id, "User soft-deleted");
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound)
+        }
+    }
+}
+
+/// Risk score of a user, on the 0-100 scale
+///
+/// Recent transaction scores are averaged by decay weight together with a neutral prior, so a
+/// user with little or aging activity drifts towards 0. Reports of fraud add to that in
+/// proportion to their own decay weight, and each chargeback on record adds a fixed amount;
+/// contributions are combined like transaction risk factors.
+fn user_risk_score(inputs: &UserRiskInputsRecord) -> f64 {
+    let transactions = inputs.weighted_risk_sum / (inputs.transaction_weight + RISK_PRIOR_WEIGHT);
+    let reports = inputs
+        .fraud_report_weights
+        .iter()
+        .map(|weight| REPORTED_FRAUD_SCORE * weight);
+    let chargebacks = usize::try_from(inputs.chargeback_count).unwrap_or(0);
+    let chargebacks =
+        std::iter::repeat_n(CHARGEBACK_SCORE, chargebacks.min(MAX_COUNTED_CHARGEBACKS));
+    combine_scores(
+        std::iter::once(transactions)
+            .chain(reports)
+            .chain(chargebacks),
+    )
+    .min(MAX_RISK_SCORE)
+}
+
+/// Assemble a risk analysis from the user's activity and, if computed, their profile
+fn build_risk_analysis(
+    user: &UserRecord,
+    velocity: UserVelocityRecord,
+    devices: UserDeviceUsageRecord,
+    countries: Vec<CountryCountRecord>,
+    billing_shipping_mismatches: i64,
+    profile: Option<&UserProfile>,
+    analyzed_at: DateTime<Utc>,
+) -> UserRiskAnalysis {
+    let velocity = VelocityAnalysis {
+        last_hour: velocity.last_hour,
+        last_24_hours: velocity.last_24_hours,
+        last_7_days: velocity.last_7_days,
+        last_30_days: velocity.last_30_days,
+        daily_average_30_days: velocity.last_30_days as f64 / 30.0,
+        rejected_last_24_hours: velocity.rejected_last_24_hours,
+        devices_last_24_hours: velocity.devices_last_24_hours,
+    };
+    let device_consistency = DeviceConsistency {
+        distinct_devices: devices.distinct_devices,
+        primary_device_share: if devices.device_transactions > 0 {
+            devices.primary_device_transactions as f64 / devices.device_transactions as f64
+        } else {
+            0.0
+        },
+        new_devices_last_7_days: devices.new_devices,
+        distinct_ip_addresses: devices.distinct_ip_addresses,
+    };
+    let location = LocationPatterns {
+        billing_countries: countries
+            .into_iter()
+            .map(|c| CountryActivity {
+                country: c.country,
+                transactions: c.transactions,
+            })
+            .collect(),
+        billing_shipping_mismatches,
+    };
+
+    let mut indicators = Vec::new();
+    if velocity.is_spike(SPIKE_MIN_TRANSACTIONS, SPIKE_FACTOR) {
+        indicators.push(RiskIndicator::VelocitySpike);
+    }
+    if profile.is_some_and(|p| {
+        p.exceeds_typical_devices(
+            i32::try_from(device_consistency.distinct_devices).unwrap_or(i32::MAX),
+        )
+    }) {
+        indicators.push(RiskIndicator::ExcessiveDevices);
+    }
+    // A new device only stands out against history from before it appeared
+    if device_consistency.new_devices_last_7_days > 0
+        && velocity.last_30_days > velocity.last_7_days
+    {
+        indicators.push(RiskIndicator::NewDevice);
+    }
+    if profile.is_some_and(|p| {
+        location
+            .billing_countries
+            .iter()
+            .any(|c| p.is_unusual_country(&c.country))
+    }) {
+        indicators.push(RiskIndicator::UnusualCountry);
+    }
+    if location.billing_shipping_mismatches > 0 {
+        indicators.push(RiskIndicator::CountryMismatch);
+    }
+
+    UserRiskAnalysis {
+        user_id: user.id,
+        risk_score: user.risk_score,
+        risk_level: user.risk_level,
+        velocity,
+        behavioral_patterns: BehavioralPatterns {
+            device_consistency,
+            location,
+            baseline: profile.map(|p| BehavioralBaseline {
+                transaction_count: p.transaction_count,
+                avg_order_amount: p.avg_order_amount,
+                usual_purchase_hours: p.usual_purchase_hours.clone(),
+                usual_local_purchase_hours: p.usual_local_purchase_hours.clone(),
+                usual_countries: p.usual_countries.clone(),
+                typical_device_count: p.typical_device_count,
+                computed_at: p.computed_at,
+            }),
+        },
+        indicators,
+        analyzed_at,
+        links: UserRiskAnalysis::links(user.id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        database::repositories::AccountRepo,
+        identity::resolve_identities,
+        models::{account::SubscriptionTier, transaction::TransactionRequest, user::LinkType},
+        scoring::{RiskEngine, UserSignals},
+        services::TransactionService,
+        test_support::{create_account, test_pool},
+    };
+
+    #[tokio::test]
+    async fn test_user_lifecycle() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Free).await;
+        let account_id = tenant.id();
+        let users = UserService::new(pool.clone(), Config::default().user_risk);
+
+        let request: CreateUser = serde_json::from_value(json!({
+            "external_user_id": "customer-1",
+            "metadata": { "segment": "loyalty" }
+        }))
+        .unwrap();
+        let created = users.create_user(tenant, &request).await.unwrap();
+        assert_eq!(created.external_user_id.as_deref(), Some("customer-1"));
+        assert!(matches!(
+            users.create_user(tenant, &request).await,
+            Err(ServiceError::Conflict(_))
+        ));
+
+        let update: UserUpdate = serde_json::from_value(json!({
+            "is_flagged": true,
+            "flags": [{ "type": "manual_review", "actor": "analyst@example.com" }],
+            "metadata": { "segment": "wholesale" }
+        }))
+        .unwrap();
+        users
+            .update_user(tenant, created.id, &update)
+            .await
+            .unwrap();
+        let fetched = users.get_user(tenant, created.id).await.unwrap();
+        assert!(fetched.is_flagged);
+        assert!(!fetched.is_verified);
+        assert_eq!(Some(fetched.flags), update.flags);
+        assert_eq!(fetched.metadata, json!({ "segment": "wholesale" }));
+
+        let lookup = UserLookupQuery {
+            external_user_id: Some("customer-1".to_string()),
+            user_hash: None,
+        };
+        assert_eq!(
+            users.lookup_user(tenant, &lookup).await.unwrap().id,
+            created.id
+        );
+
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(matches!(
+            users.get_user(other, created.id).await,
+            Err(ServiceError::NotFound)
+        ));
+        assert!(matches!(
+            users.lookup_user(other, &lookup).await,
+            Err(ServiceError::NotFound)
+        ));
+        users.delete_user(tenant, created.id).await.unwrap();
+        assert!(matches!(
+            users.lookup_user(tenant, &lookup).await,
+            Err(ServiceError::NotFound)
+        ));
+        assert!(matches!(
+            users.update_user(tenant, created.id, &update).await,
+            Err(ServiceError::NotFound)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_only_unexpired_flags_reach_scoring() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Free).await;
+        let account_id = tenant.id();
+        let users = UserService::new(pool.clone(), Config::default().user_risk);
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+
+        let request: CreateUser = serde_json::from_value(json!({
+            "external_user_id": "customer-1",
+            "flags": [
+                { "type": "watch", "reason": "Linked to a chargeback ring" },
+                { "type": "manual_review", "expires_at": "2020-01-01T00:00:00Z" }
+            ]
+        }))
+        .unwrap();
+        let user = users.create_user(tenant, &request).await.unwrap();
+        assert_eq!(user.flags.len(), 2);
+
+        let transaction = |account: serde_json::Value| -> TransactionRequest {
+            serde_json::from_value(json!({
+                "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+                "event": { "type": "purchase" },
+                "account": account
+            }))
+            .unwrap()
+        };
+        let flagged = transactions
+            .user_signals(tenant, &transaction(json!({ "user_id": "customer-1" })))
+            .await
+            .unwrap();
+        let types: Vec<&str> = flagged
+            .active_flags
+            .iter()
+            .map(|flag| flag.flag_type.as_str())
+            .collect();
+        assert_eq!(types, ["watch"]);
+
+        let unknown = transactions
+            .user_signals(tenant, &transaction(json!({ "user_id": "customer-2" })))
+            .await
+            .unwrap();
+        assert_eq!(unknown, UserSignals::default());
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_risk_analysis_counts_recent_activity() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let users = UserService::new(pool.clone(), Config::default().user_risk);
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let engine = RiskEngine::new();
+
+        let now = Utc::now();
+        let events = [
+            (now - chrono::Duration::minutes(5), "198.51.100.1", "US"),
+            (now - chrono::Duration::hours(3), "198.51.100.1", "US"),
+            (now - chrono::Duration::days(10), "203.0.113.9", "CA"),
+            (now - chrono::Duration::days(40), "203.0.113.9", "US"),
+        ];
+        let mut user_id = None;
+        for (time, ip_address, shipping_country) in events {
+            let request: TransactionRequest = serde_json::from_value(json!({
+                "device": { "ip_address": ip_address, "user_agent": "Mozilla/5.0" },
+                "event": { "type": "purchase", "time": time },
+                "account": { "user_id": "customer-1" },
+                "billing": { "country": "US" },
+                "shipping": { "country": shipping_country }
+            }))
+            .unwrap();
+            let assessment = engine.assess(&request, &UserSignals::default());
+            let stored = transactions
+                .store_transaction(tenant, &request, &assessment, &[])
+                .await
+                .unwrap();
+            user_id = stored.user_id;
+        }
+        let user_id = user_id.unwrap();
+
+        let analysis = users.risk_analysis(tenant, user_id).await.unwrap();
+        assert_eq!(analysis.velocity.last_hour, 1);
+        assert_eq!(analysis.velocity.last_24_hours, 2);
+        assert_eq!(analysis.velocity.last_7_days, 2);
+        assert_eq!(analysis.velocity.last_30_days, 3);
+        assert_eq!(analysis.velocity.devices_last_24_hours, 1);
+        let devices = &analysis.behavioral_patterns.device_consistency;
+        assert_eq!(devices.distinct_devices, 2);
+        assert!((devices.primary_device_share - 2.0 / 3.0).abs() < 1e-9);
+        let location = &analysis.behavioral_patterns.location;
+        assert_eq!(location.billing_countries.len(), 1);
+        assert_eq!(location.billing_countries[0].country, "US");
+        assert_eq!(location.billing_countries[0].transactions, 3);
+        assert_eq!(location.billing_shipping_mismatches, 1);
+        assert!(
+            analysis
+                .indicators
+                .contains(&RiskIndicator::CountryMismatch)
+        );
+        assert!(analysis.behavioral_patterns.baseline.is_none());
+
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(matches!(
+            users.risk_analysis(other, user_id).await,
+            Err(ServiceError::NotFound)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_linked_users_merge_into_one() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let users = UserService::new(pool.clone(), Config::default().user_risk);
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let engine = RiskEngine::new();
+        let store = async |account: serde_json::Value| {
+            let request: TransactionRequest = serde_json::from_value(json!({
+                "device": { "ip_address": "198.51.100.7", "user_agent": "Mozilla/5.0" },
+                "event": { "type": "purchase" },
+                "account": account,
+                "email": { "address": "shared@example.com" }
+            }))
+            .unwrap();
+            let assessment = engine.assess(&request, &UserSignals::default());
+            transactions
+                .store_transaction(tenant, &request, &assessment, &[])
+                .await
+                .unwrap()
+                .user_id
+                .unwrap()
+        };
+        let target = store(json!({ "user_id": "customer-1" })).await;
+        let source = store(json!({ "user_hash": "hash-1" })).await;
+        assert_ne!(target, source);
+
+        resolve_identities(&pool, &Config::default().identity)
+            .await
+            .unwrap();
+        let linked = users.linked_users(tenant, target).await.unwrap();
+        assert!(
+            linked
+                .linked_users
+                .iter()
+                .any(|l| l.user_id == source && l.link_type == LinkType::Email)
+        );
+
+        let merge = |source_user_id| MergeUsers { source_user_id };
+        assert!(matches!(
+            users.merge_users(tenant, target, &merge(target)).await,
+            Err(ServiceError::Invalid(_))
+        ));
+        assert!(matches!(
+            users
+                .merge_users(tenant, target, &merge(Uuid::new_v4()))
+                .await,
+            Err(ServiceError::Invalid(_))
+        ));
+        let merged = users
+            .merge_users(tenant, target, &merge(source))
+            .await
+            .unwrap();
+        assert_eq!(merged.total_transactions, 2);
+        assert!(matches!(
+            users.get_user(tenant, source).await,
+            Err(ServiceError::NotFound)
+        ));
+        assert!(
+            users
+                .linked_users(tenant, target)
+                .await
+                .unwrap()
+                .linked_users
+                .is_empty()
+        );
+
+        // The duplicate's identifier now resolves to the surviving user
+        assert_eq!(store(json!({ "user_hash": "hash-1" })).await, target);
+        let lookup = UserLookupQuery {
+            external_user_id: None,
+            user_hash: Some("hash-1".to_string()),
+        };
+        assert_eq!(users.lookup_user(tenant, &lookup).await.unwrap().id, target);
+        assert_eq!(
+            users
+                .get_user(tenant, target)
+                .await
+                .unwrap()
+                .total_transactions,
+            3
+        );
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[test]
+    fn test_user_risk_score_shrinks_towards_zero_and_caps_chargebacks() {
+        let inputs = |transaction_weight, weighted_risk_sum, reports: &[f64], chargebacks| {
+            UserRiskInputsRecord {
+                user_id: Uuid::nil(),
+                chargeback_count: chargebacks,
+                transaction_weight,
+                weighted_risk_sum,
+                fraud_report_weights: reports.to_vec(),
+            }
+        };
+        assert_eq!(user_risk_score(&inputs(0.0, 0.0, &[], 0)), 0.0);
+        // A single fresh transaction counts half against the prior, nine count for 90%
+        assert_eq!(user_risk_score(&inputs(1.0, 80.0, &[], 0)), 40.0);
+        assert_eq!(user_risk_score(&inputs(9.0, 720.0, &[], 0)), 72.0);
+        assert_eq!(user_risk_score(&inputs(0.0, 0.0, &[1.0], 0)), 40.0);
+        assert_eq!(user_risk_score(&inputs(0.0, 0.0, &[0.5], 0)), 20.0);
+        assert_eq!(user_risk_score(&inputs(0.0, 0.0, &[], 1)), 10.0);
+        assert_eq!(
+            user_risk_score(&inputs(0.0, 0.0, &[], 100)),
+            user_risk_score(&inputs(0.0, 0.0, &[], MAX_COUNTED_CHARGEBACKS as i32)),
+        );
+    }
+
+    #[test]
+    fn test_risk_indicators_compare_against_the_profile() {
+        let now = Utc::now();
+        let user = UserRecord {
+            id: Uuid::new_v4(),
+            account_id: Uuid::new_v4(),
+            external_user_id: None,
+            user_hash: None,
+            risk_score: 0.0,
+            risk_level: RiskLevel::Low,
+            total_transactions: 0,
+            successful_transactions: 0,
+            failed_transactions: 0,
+            chargeback_count: 0,
+            first_transaction_at: None,
+            last_transaction_at: None,
+            is_verified: false,
+            is_flagged: false,
+            flags: sqlx::types::Json(Vec::new()),
+            metadata: json!({}),
+            created_at: now,
+            updated_at: now,
+        };
+        let velocity = UserVelocityRecord {
+            last_hour: 4,
+            last_24_hours: 8,
+            last_7_days: 9,
+            last_30_days: 15,
+            rejected_last_24_hours: 0,
+            devices_last_24_hours: 3,
+        };
+        let devices = UserDeviceUsageRecord {
+            device_transactions: 15,
+            distinct_devices: 3,
+            primary_device_transactions: 10,
+            new_devices: 1,
+            distinct_ip_addresses: 3,
+        };
+        let countries = vec![CountryCountRecord {
+            country: "FR".to_string(),
+            transactions: 15,
+        }];
+        let profile = UserProfile {
+            user_id: user.id,
+            account_id: user.account_id,
+            transaction_count: 20,
+            avg_order_amount: Some(40.0),
+            order_amount_stddev: Some(5.0),
+            usual_purchase_hours: vec![9],
+            usual_local_purchase_hours: vec![10],
+            usual_countries: vec!["US".to_string()],
+            typical_device_count: 1,
+            computed_at: now,
+        };
+
+        let analysis = build_risk_analysis(
+            &user,
+            velocity.clone(),
+            devices.clone(),
+            countries.clone(),
+            0,
+            Some(&profile),
+            now,
+        );
+        assert_eq!(
+            analysis.indicators,
+            vec![
+                RiskIndicator::VelocitySpike,
+                RiskIndicator::ExcessiveDevices,
+                RiskIndicator::NewDevice,
+                RiskIndicator::UnusualCountry,
+            ]
+        );
+        assert!(analysis.behavioral_patterns.baseline.is_some());
+
+        // Without a profile only the indicators that need no baseline remain
+        let analysis = build_risk_analysis(&user, velocity, devices, countries, 0, None, now);
+        assert_eq!(
+            analysis.indicators,
+            vec![RiskIndicator::VelocitySpike, RiskIndicator::NewDevice]
+        );
+    }
+}
@@ -0,0 +1,490 @@
+//! Webhooks accounts register for their events, rotation of their signing secrets, and the
+//! log of deliveries
+//!
+//! Secrets are only ever returned when they are made: when the webhook is created and when
+//! its secret is rotated. A rotation keeps signing deliveries with the replaced secret for
+//! an overlap window as well, so the endpoint can switch secrets without refusing events.
+//!
+//! Every attempt at delivering an event is logged. A failed one can be redriven: the event
+//! is sent again, to the same webhook, as a new attempt pointing back at the failed one.
+
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ServiceError, ServiceResult};
+use crate::{
+    database::{
+        Tenant,
+        repositories::{OutboxRepo, WebhookDeliveryRecord, WebhookRecord, WebhookRepo},
+    },
+    models::webhook::{
+        DeliveryStatus, ListDeliveriesQuery, SecretRotationRequest, Webhook, WebhookDelivery,
+        WebhookRequest,
+    },
+    outbox::webhooks::WebhookSender,
+};
+
+impl From<WebhookRecord> for Webhook {
+    fn from(record: WebhookRecord) -> Self {
+        Webhook {
+            id: record.id,
+            url: record.url,
+            description: record.description,
+            events: record.events.0,
+            filters: record.filters.0,
+            active: record.is_active,
+            secret: None,
+            secret_rotated_at: record.secret_rotated_at,
+            previous_secret_expires_at: record.previous_secret_expires_at,
+            last_triggered: record.last_triggered,
+            success_count: record.success_count,
+            failure_count: record.failure_count,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            links: Webhook::links(record.id),
+        }
+    }
+}
+
+impl From<WebhookDeliveryRecord> for WebhookDelivery {
+    fn from(record: WebhookDeliveryRecord) -> Self {
+        WebhookDelivery {
+            id: record.id,
+            webhook_id: record.webhook_id,
+            event_id: record.event_id,
+            event_type: record.event_type,
+            status: record.status,
+            response_status: record.response_status,
+            latency_ms: record.latency_ms,
+            error: record.error,
+            redrive_of: record.redrive_of,
+            attempted_at: record.attempted_at,
+        }
+    }
+}
+
+/// Webhook management
+#[derive(Debug, Clone)]
+pub struct WebhookService {
+    pool: PgPool,
+    sender: Option<WebhookSender>,
+}
+
+impl WebhookService {
+    /// Create a new webhook service
+    ///
+    /// Deliveries cannot be redriven until [`WebhookService::with_sender`] gives them a client.
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, sender: None }
+    }
+
+    /// Redrive deliveries with `sender`
+    pub fn with_sender(mut self, sender: WebhookSender) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    /// An account's webhooks, oldest first
+    pub async fn list_webhooks(&self, tenant: Tenant) -> ServiceResult<Vec<Webhook>> {
+        let records = WebhookRepo::list(&self.pool, tenant).await?;
+        Ok(records.into_iter().map(Webhook::from).collect())
+    }
+
+    /// Fetch one of an account's webhooks
+    pub async fn get_webhook(&self, tenant: Tenant, webhook_id: Uuid) -> ServiceResult<Webhook> {
+        WebhookRepo::find(&self.pool, tenant, webhook_id)
+            .await?
+            .map(Webhook::from)
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// Register a webhook, returning it with its newly made secret
+    pub async fn create_webhook(
+        &self,
+        tenant: Tenant,
+        webhook: &WebhookRequest,
+    ) -> ServiceResult<Webhook> {
+        webhook.validate().map_err(ServiceError::Invalid)?;
+        let secret = generate_secret();
+        let mut tx = self.pool.begin().await?;
+        let webhook_id = WebhookRepo::insert(&mut *tx, tenant, webhook, &secret).await?;
+        let record = WebhookRepo::find(&mut *tx, tenant, webhook_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+        Ok(Webhook {
+            secret: Some(secret),
+            ..record.into()
+        })
+    }
+
+    /// Replace a webhook's URL, description, events, filters, and whether it is active; its secret
+    /// is kept
+    pub async fn update_webhook(
+        &self,
+        tenant: Tenant,
+        webhook_id: Uuid,
+        webhook: &WebhookRequest,
+    ) -> ServiceResult<Webhook> {
+        webhook.validate().map_err(ServiceError::Invalid)?;
+        let mut tx = self.pool.begin().await?;
+        if !WebhookRepo::update(&mut *tx, tenant, webhook_id, webhook).await? {
+            return Err(ServiceError::NotFound);
+        }
+        let record = WebhookRepo::find(&mut *tx, tenant, webhook_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+        Ok(record.into())
+    }
+
+    /// Delete a webhook; events not yet delivered to it are not
+    pub async fn delete_webhook(&self, tenant: Tenant, webhook_id: Uuid) -> ServiceResult<()> {
+        if WebhookRepo::delete(&self.pool, tenant, webhook_id).await? {
+            Ok(())
+        } else {
+            Err(ServiceError::NotFound)
+        }
+    }
+
+    /// Give a webhook a new secret, returning it with the secret
+    ///
+    /// Deliveries are signed with the replaced secret too until the rotation's overlap
+    /// window closes.
+    pub async fn rotate_secret(
+        &self,
+        tenant: Tenant,
+        webhook_id: Uuid,
+        rotation: &SecretRotationRequest,
+    ) -> ServiceResult<Webhook> {
+        rotation.validate().map_err(ServiceError::Invalid)?;
+        let secret = generate_secret();
+        let overlap_until = Utc::now() + Duration::hours(rotation.overlap_hours());
+        let mut tx = self.pool.begin().await?;
+        if !WebhookRepo::rotate_secret(&mut *tx, tenant, webhook_id, &secret, overlap_until).await?
+        {
+            return Err(ServiceError::NotFound);
+        }
+        let record = WebhookRepo::find(&mut *tx, tenant, webhook_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tx.commit().await?;
+        tracing::info!(%webhook_id, account_id = %tenant, "Webhook secret rotated");
+        Ok(Webhook {
+            secret: Some(secret),
+            ..record.into()
+        })
+    }
+
+    /// Page of a webhook's deliveries, newest first, with the total matching the query
+    pub async fn list_deliveries(
+        &self,
+        tenant: Tenant,
+        webhook_id: Uuid,
+        query: &ListDeliveriesQuery,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<(Vec<WebhookDelivery>, i64)> {
+        if WebhookRepo::find(&self.pool, tenant, webhook_id)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::NotFound);
+        }
+        let records =
+            WebhookRepo::deliveries(&self.pool, tenant, webhook_id, query, limit, offset).await?;
+        let total = WebhookRepo::count_deliveries(&self.pool, tenant, webhook_id, query).await?;
+        Ok((
+            records.into_iter().map(WebhookDelivery::from).collect(),
+            total,
+        ))
+    }
+
+    /// Send a failed delivery's event to its webhook again, returning the new attempt
+    ///
+    /// The webhook must still be active. The event goes out signed with the webhook's
+    /// current secrets, and the attempt is logged whether or not it succeeds.
+    pub async fn redrive(
+        &self,
+        tenant: Tenant,
+        delivery_id: Uuid,
+    ) -> ServiceResult<WebhookDelivery> {
+        let Some(sender) = &self.sender else {
+            return Err(ServiceError::Conflict(
+                "Webhook deliveries cannot be redriven here".to_string(),
+            ));
+        };
+        let delivery = WebhookRepo::find_delivery(&self.pool, tenant, delivery_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        if delivery.status == DeliveryStatus::Succeeded {
+            return Err(ServiceError::Conflict(
+                "Delivery succeeded and cannot be redriven".to_string(),
+            ));
+        }
+        let event = OutboxRepo::find(&self.pool, tenant, delivery.event_id)
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        let target = WebhookRepo::target(&self.pool, tenant, delivery.webhook_id)
+            .await?
+            .ok_or_else(|| ServiceError::Conflict("Webhook is not active".to_string()))?;
+        let redriven = sender
+            .send(&target, &event, Some(delivery.id))
+            .await?
+            .ok_or(ServiceError::NotFound)?;
+        tracing::info!(
+            %delivery_id,
+            redrive_id = %redriven.id,
+            status = ?redriven.status,
+            account_id = %tenant,
+            "Webhook delivery redriven"
+        );
+        Ok(redriven.into())
+    }
+}
+
+/// Random signing secret, drawn from the operating system's generator by way of v4 UUIDs
+fn generate_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::OutboxConfig,
+        database::repositories::{AccountRepo, NewWebhookDelivery, OutboxRepo, WebhookRepo},
+        models::{account::SubscriptionTier, webhook::WebhookFilters},
+        outbox::{CASE_RESOLVED, TRANSACTION_SCORED},
+        test_support::{create_account, test_pool},
+    };
+
+    fn service(pool: &PgPool) -> WebhookService {
+        let config = OutboxConfig {
+            poll_interval_ms: 1000,
+            batch_size: 100,
+            max_attempts: 10,
+            webhook_timeout_seconds: 1,
+            dead_letter_check_interval_seconds: 300,
+            dead_letter_alert_threshold: 1,
+        };
+        WebhookService::new(pool.clone())
+            .with_sender(WebhookSender::new(pool.clone(), &config).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_webhook_secrets_are_rotated_with_overlap() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let webhooks = service(&pool);
+
+        let request = WebhookRequest {
+            url: "https://merchant.example.com/fusegu/events".to_string(),
+            description: None,
+            events: vec![CASE_RESOLVED.to_string()],
+            filters: WebhookFilters {
+                shop_ids: vec!["shop-eu-1".to_string()],
+                ..WebhookFilters::default()
+            },
+            active: true,
+        };
+        let created = webhooks.create_webhook(tenant, &request).await.unwrap();
+        let first_secret = created.secret.unwrap();
+        assert!(first_secret.starts_with("whsec_"));
+        // The secret is not shown again
+        let fetched = webhooks.get_webhook(tenant, created.id).await.unwrap();
+        assert_eq!(fetched.secret, None);
+        assert_eq!(fetched.filters, request.filters);
+
+        // Only webhooks taking the event's type are delivered to
+        let targets = WebhookRepo::targets(&pool, tenant, CASE_RESOLVED)
+            .await
+            .unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].secret, first_secret);
+        assert_eq!(targets[0].previous_secret, None);
+        assert!(
+            targets[0]
+                .filters
+                .matches(&serde_json::json!({ "shop_id": "shop-eu-1" }))
+        );
+        assert!(
+            WebhookRepo::targets(&pool, tenant, TRANSACTION_SCORED)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // The replaced secret keeps signing through the overlap window
+        let rotation = |overlap_hours| SecretRotationRequest {
+            overlap_hours: Some(overlap_hours),
+        };
+        let rotated = webhooks
+            .rotate_secret(tenant, created.id, &rotation(24))
+            .await
+            .unwrap();
+        let second_secret = rotated.secret.unwrap();
+        assert_ne!(second_secret, first_secret);
+        assert!(rotated.previous_secret_expires_at.is_some());
+        let targets = WebhookRepo::targets(&pool, tenant, CASE_RESOLVED)
+            .await
+            .unwrap();
+        assert_eq!(targets[0].secret, second_secret);
+        assert_eq!(targets[0].previous_secret.as_deref(), Some(&*first_secret));
+
+        // Rotating without overlap retires the old secret at once
+        webhooks
+            .rotate_secret(tenant, created.id, &rotation(0))
+            .await
+            .unwrap();
+        let targets = WebhookRepo::targets(&pool, tenant, CASE_RESOLVED)
+            .await
+            .unwrap();
+        assert_eq!(targets[0].previous_secret, None);
+
+        // Inactive webhooks are not delivered to, and webhooks belong to their account
+        let inactive = WebhookRequest {
+            active: false,
+            ..request
+        };
+        webhooks
+            .update_webhook(tenant, created.id, &inactive)
+            .await
+            .unwrap();
+        assert!(
+            WebhookRepo::targets(&pool, tenant, CASE_RESOLVED)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(matches!(
+            webhooks
+                .rotate_secret(other, created.id, &rotation(0))
+                .await,
+            Err(ServiceError::NotFound)
+        ));
+
+        webhooks.delete_webhook(tenant, created.id).await.unwrap();
+        assert!(webhooks.list_webhooks(tenant).await.unwrap().is_empty());
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_deliveries_are_logged_and_redriven() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let webhooks = service(&pool);
+        let request = WebhookRequest {
+            url: "https://merchant.example.com/fusegu/events".to_string(),
+            description: None,
+            events: Vec::new(),
+            filters: WebhookFilters::default(),
+            active: true,
+        };
+        let webhook = webhooks.create_webhook(tenant, &request).await.unwrap();
+        let event_id = OutboxRepo::insert(
+            &pool,
+            account_id,
+            CASE_RESOLVED,
+            Uuid::new_v4(),
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+        let attempt = |status, response_status| NewWebhookDelivery {
+            webhook_id: webhook.id,
+            event_id,
+            event_type: CASE_RESOLVED,
+            status,
+            response_status,
+            latency_ms: 40,
+            error: (status == DeliveryStatus::Failed).then_some("HTTP status server error"),
+            redrive_of: None,
+        };
+        let failed = WebhookRepo::insert_delivery(
+            &pool,
+            tenant,
+            &attempt(DeliveryStatus::Failed, Some(503)),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let succeeded = WebhookRepo::insert_delivery(
+            &pool,
+            tenant,
+            &attempt(DeliveryStatus::Succeeded, Some(200)),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        // Attempts count towards the webhook and can be filtered by outcome
+        let fetched = webhooks.get_webhook(tenant, webhook.id).await.unwrap();
+        assert_eq!((fetched.success_count, fetched.failure_count), (1, 1));
+        assert!(fetched.last_triggered.is_some());
+        let only_failed = ListDeliveriesQuery {
+            status: Some(DeliveryStatus::Failed),
+            ..ListDeliveriesQuery::default()
+        };
+        let (deliveries, total) = webhooks
+            .list_deliveries(tenant, webhook.id, &only_failed, 20, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(deliveries[0].id, failed);
+        assert_eq!(deliveries[0].response_status, Some(503));
+
+        // Only failed deliveries are redriven, as new attempts pointing back at them
+        assert!(matches!(
+            webhooks.redrive(tenant, succeeded).await,
+            Err(ServiceError::Conflict(_))
+        ));
+        let redriven = webhooks.redrive(tenant, failed).await.unwrap();
+        assert_eq!(redriven.redrive_of, Some(failed));
+        assert_eq!(redriven.event_id, event_id);
+        let (_, total) = webhooks
+            .list_deliveries(tenant, webhook.id, &ListDeliveriesQuery::default(), 20, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 3);
+
+        // Inactive webhooks are not redriven to, and deliveries belong to their account
+        let inactive = WebhookRequest {
+            active: false,
+            ..request
+        };
+        webhooks
+            .update_webhook(tenant, webhook.id, &inactive)
+            .await
+            .unwrap();
+        assert!(matches!(
+            webhooks.redrive(tenant, failed).await,
+            Err(ServiceError::Conflict(_))
+        ));
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert!(matches!(
+            webhooks.redrive(other, failed).await,
+            Err(ServiceError::NotFound)
+        ));
+        assert!(matches!(
+            webhooks
+                .list_deliveries(other, webhook.id, &ListDeliveriesQuery::default(), 20, 0)
+                .await,
+            Err(ServiceError::NotFound)
+        ));
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
@@ -0,0 +1,305 @@
+//! Per-session event history
+//!
+//! Merchants pass the ID of the customer's web or app session with each event. The events of a
+//! session are remembered until it has been idle for [`SESSION_TTL`], so later events can be
+//! scored on what happened earlier in the same session: a purchase moments after sign-up, or an
+//! IP address or country that changes mid-flow. Redis shares sessions across instances; the
+//! in-memory fallback only sees the events scored by this process.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use redis::{RedisResult, aio::ConnectionManager};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::Tenant,
+    models::transaction::{EventType, TransactionRequest},
+};
+
+/// How long a session is remembered after its latest event
+pub const SESSION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Most recent events kept per session
+const MAX_SESSION_EVENTS: isize = 50;
+
+/// Sessions kept in memory before expired ones are swept
+const MEMORY_SWEEP_THRESHOLD: usize = 10_000;
+
+/// An event as remembered in its session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionEvent {
+    /// Type of event
+    pub event_type: EventType,
+    /// When the event happened
+    pub at: DateTime<Utc>,
+    /// IP address the event came from
+    pub ip_address: String,
+    /// Country the event's IP address is located in, falling back to the country of its
+    /// billing address, then of its shipping address
+    pub country: Option<String>,
+}
+
+impl SessionEvent {
+    /// The event `request` describes, at its event time or now, from an IP address located in
+    /// `ip_country`
+    pub fn from_request(request: &TransactionRequest, ip_country: Option<&str>) -> Self {
+        let country = ip_country
+            .map(str::to_string)
+            .or_else(|| {
+                request
+                    .billing
+                    .as_ref()
+                    .and_then(|billing| billing.country.clone())
+            })
+            .or_else(|| {
+                request
+                    .shipping
+                    .as_ref()
+                    .and_then(|shipping| shipping.address.country.clone())
+            });
+        Self {
+            event_type: request.event.event_type,
+            at: request.event.time.unwrap_or_else(Utc::now),
+            ip_address: request.device.ip_address.clone(),
+            country: country.map(|country| country.to_ascii_uppercase()),
+        }
+    }
+}
+
+/// What the session of a transaction says about it
+///
+/// Transactions without a session ID, and the first event of a session, have no signals.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionSignals {
+    /// Seconds since the account was created in the same session, when the transaction is a
+    /// purchase
+    pub purchase_seconds_after_signup: Option<f64>,
+    /// Distinct IP addresses the session's events came from, counting the transaction's own
+    pub ip_addresses: usize,
+    /// Distinct countries among the session's events, counting the transaction's own
+    pub countries: usize,
+}
+
+impl SessionSignals {
+    /// Signals of `event`, following the `earlier` events of its session
+    pub fn from_events(earlier: &[SessionEvent], event: &SessionEvent) -> Self {
+        if earlier.is_empty() {
+            return Self::default();
+        }
+        let events = || earlier.iter().chain([event]);
+        let signup = earlier
+            .iter()
+            .filter(|e| e.event_type == EventType::AccountCreation && e.at <= event.at)
+            .map(|e| e.at)
+            .max();
+        Self {
+            purchase_seconds_after_signup: signup
+                .filter(|_| event.event_type.is_purchase())
+                .map(|signup| (event.at - signup).num_milliseconds() as f64 / 1000.0),
+            ip_addresses: events()
+                .map(|e| e.ip_address.as_str())
+                .collect::<HashSet<_>>()
+                .len(),
+            countries: events()
+                .filter_map(|e| e.country.as_deref())
+                .collect::<HashSet<_>>()
+                .len(),
+        }
+    }
+}
+
+/// In-memory sessions by key, with when each expires
+type MemorySessions = HashMap<String, (Instant, Vec<SessionEvent>)>;
+
+/// Event history of recent sessions
+#[derive(Clone)]
+pub enum SessionStore {
+    /// Shared through Redis
+    Redis(ConnectionManager),
+    /// Local to this process
+    Memory(Arc<Mutex<MemorySessions>>),
+}
+
+impl SessionStore {
+    /// Share sessions through `redis`, or keep them in memory when it is `None`
+    pub fn new(redis: Option<ConnectionManager>) -> Self {
+        redis.map_or_else(Self::memory, Self::Redis)
+    }
+
+    /// Store local to this process
+    pub fn memory() -> Self {
+        Self::Memory(Arc::default())
+    }
+
+    /// Append `event` to an account's session, returning the events recorded in it before
+    pub async fn record(
+        &self,
+        tenant: Tenant,
+        session_id: &str,
+        event: &SessionEvent,
+    ) -> RedisResult<Vec<SessionEvent>> {
+        let key = format!("fusegu:session:{tenant}:{session_id}");
+        match self {
+            Self::Redis(connection) => {
+                let encoded = serde_json::to_string(event).expect("session events serialize");
+                let (earlier,): (Vec<String>,) = redis::pipe()
+                    .atomic()
+                    .cmd("LRANGE")
+                    .arg(&key)
+                    .arg(0)
+                    .arg(-1)
+                    .cmd("RPUSH")
+                    .arg(&key)
+                    .arg(encoded)
+                    .ignore()
+                    .cmd("LTRIM")
+                    .arg(&key)
+                    .arg(-MAX_SESSION_EVENTS)
+                    .arg(-1)
+                    .ignore()
+                    .cmd("EXPIRE")
+                    .arg(&key)
+                    .arg(SESSION_TTL.as_secs())
+                    .ignore()
+                    .query_async(&mut connection.clone())
+                    .await?;
+                Ok(earlier
+                    .iter()
+                    .filter_map(|encoded| serde_json::from_str(encoded).ok())
+                    .collect())
+            },
+            Self::Memory(sessions) => {
+                let now = Instant::now();
+                let mut sessions = sessions.lock().unwrap_or_else(|e| e.into_inner());
+                if sessions.len() >= MEMORY_SWEEP_THRESHOLD {
+                    sessions.retain(|_, (expires_at, _)| *expires_at > now);
+                }
+                let (expires_at, events) = sessions.entry(key).or_insert((now, Vec::new()));
+                if *expires_at <= now {
+                    events.clear();
+                }
+                let earlier = events.clone();
+                events.push(event.clone());
+                let excess = events.len().saturating_sub(MAX_SESSION_EVENTS as usize);
+                events.drain(..excess);
+                *expires_at = now + SESSION_TTL;
+                Ok(earlier)
+            },
+        }
+    }
+
+    /// Signals of the session `request` belongs to, recording the request's event in it
+    ///
+    /// `ip_country` is where the request's IP address is located, if known. Store failures are
+    /// logged and score the transaction as if it had no session.
+    pub async fn signals(
+        &self,
+        tenant: Tenant,
+        request: &TransactionRequest,
+        ip_country: Option<&str>,
+    ) -> SessionSignals {
+        let Some(session_id) = request.device.session_id.as_deref() else {
+            return SessionSignals::default();
+        };
+        let event = SessionEvent::from_request(request, ip_country);
+        match self.record(tenant, session_id, &event).await {
+            Ok(earlier) => SessionSignals::from_events(&earlier, &event),
+            Err(e) => {
+                tracing::warn!(error = %e, account_id = %tenant, "Session lookup failed");
+                SessionSignals::default()
+            },
+        }
+    }
+}
+
+impl fmt::Debug for SessionStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Redis(_) => f.write_str("SessionStore::Redis"),
+            Self::Memory(_) => f.write_str("SessionStore::Memory"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn request(event_type: &str, ip: &str, country: &str, time: &str) -> TransactionRequest {
+        serde_json::from_value(json!({
+            "device": { "ip_address": ip, "session_id": "sess_1" },
+            "event": { "type": event_type, "time": time },
+            "billing": { "country": country }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sessions_follow_events_per_account() {
+        let store = SessionStore::memory();
+        let tenant = Tenant::trusted(Uuid::new_v4());
+
+        let signup = request(
+            "account_creation",
+            "198.51.100.1",
+            "DE",
+            "2025-07-13T10:00:00Z",
+        );
+        assert_eq!(
+            store.signals(tenant, &signup, None).await,
+            SessionSignals::default()
+        );
+
+        let purchase = request("purchase", "198.51.100.1", "DE", "2025-07-13T10:00:12Z");
+        let signals = store.signals(tenant, &purchase, None).await;
+        assert_eq!(signals.purchase_seconds_after_signup, Some(12.0));
+        assert_eq!((signals.ip_addresses, signals.countries), (1, 1));
+
+        let moved = request("purchase", "203.0.113.9", "NG", "2025-07-13T10:05:00Z");
+        let signals = store.signals(tenant, &moved, None).await;
+        assert_eq!(signals.purchase_seconds_after_signup, Some(300.0));
+        assert_eq!((signals.ip_addresses, signals.countries), (2, 2));
+
+        let other = Tenant::trusted(Uuid::new_v4());
+        assert_eq!(
+            store.signals(other, &moved, None).await,
+            SessionSignals::default()
+        );
+    }
+
+    #[test]
+    fn test_login_is_not_a_purchase_after_signup() {
+        let signup = SessionEvent::from_request(
+            &request(
+                "account_creation",
+                "198.51.100.1",
+                "de",
+                "2025-07-13T10:00:00Z",
+            ),
+            None,
+        );
+        assert_eq!(signup.country.as_deref(), Some("DE"));
+        let login = SessionEvent::from_request(
+            &request(
+                "account_login",
+                "198.51.100.1",
+                "FR",
+                "2025-07-13T10:00:05Z",
+            ),
+            Some("DE"),
+        );
+        assert_eq!(login.country.as_deref(), Some("DE"));
+        let signals = SessionSignals::from_events(&[signup], &login);
+        assert_eq!(signals.purchase_seconds_after_signup, None);
+        assert_eq!((signals.ip_addresses, signals.countries), (1, 1));
+    }
+}
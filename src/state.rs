@@ -0,0 +1,213 @@
+//! Shared application state
+
+use redis::aio::ConnectionManager;
+
+use crate::{
+    analytics::live::LiveFeed,
+    auth::NonceCache,
+    config::Config,
+    database::{Database, clickhouse::ClickHouseClient},
+    features::FeatureStore,
+    metering::Meter,
+    outbox::webhooks::WebhookSender,
+    rate_limit::RateLimiter,
+    scoring::RiskEngine,
+    services::{
+        AccountService, AnalyticsService, CaseService, DeadLetterService, DeviceService,
+        EmailIntelService, IpIntelService, JobService, LabelService, ListService,
+        NotificationService, OrganizationService, OutcomeService, ProcessorEventService,
+        ReportService, RuleService, ScreeningService, TransactionService, UserService,
+        WebhookService, transaction_service::SignalSources,
+    },
+    sessions::SessionStore,
+    utils::geo::GeoIpDatabase,
+};
+
+/// State shared by all request handlers
+#[derive(Debug, Clone)]
+pub struct AppState {
+    /// Application configuration
+    pub config: Config,
+    /// PostgreSQL handle
+    pub database: Database,
+    /// Risk scoring engine
+    pub risk_engine: RiskEngine,
+    /// Transaction persistence
+    pub transactions: TransactionService,
+    /// Background jobs
+    pub jobs: JobService,
+    /// User management
+    pub users: UserService,
+    /// Devices and browser fingerprinting
+    pub devices: DeviceService,
+    /// Entities accounts block or score higher
+    pub lists: ListService,
+    /// Manual review cases
+    pub cases: CaseService,
+    /// Webhook endpoints
+    pub webhooks: WebhookService,
+    /// Email and Slack notification channels
+    pub notifications: NotificationService,
+    /// Events that could not be delivered
+    pub dead_letters: DeadLetterService,
+    /// Disputes and refunds reported by payment processors
+    pub processor_events: ProcessorEventService,
+    /// Account self-service
+    pub accounts: AccountService,
+    /// Organizations and their members
+    pub organizations: OrganizationService,
+    /// Analytics, when ClickHouse is enabled
+    pub analytics: Option<AnalyticsService>,
+    /// Generated reports
+    pub reports: ReportService,
+    /// Outcomes merchants report for their transactions
+    pub outcomes: OutcomeService,
+    /// Rule weight versions and suggestions
+    pub rules: RuleService,
+    /// Ground-truth transaction labels
+    pub labels: LabelService,
+    /// Scored transactions, for live dashboard streams
+    pub live: LiveFeed,
+    /// Signatures already accepted, for replay protection
+    pub nonces: NonceCache,
+    /// Event history of recent sessions, for session rules
+    pub sessions: SessionStore,
+    /// Precomputed risk features and IP geolocation
+    pub features: FeatureStore,
+    /// Anonymous IP feeds
+    pub ip_intel: IpIntelService,
+    /// Free and disposable email domains
+    pub email_intel: EmailIntelService,
+    /// Sanctions lists names are screened against
+    pub screening: ScreeningService,
+    /// Quota usage metering
+    pub meter: Meter,
+    /// Per-account request rate limits
+    pub rate_limiter: RateLimiter,
+}
+
+impl AppState {
+    /// Build the handler state from configuration, a database handle, an optional ClickHouse
+    /// client, and an optional Redis connection shared by replay protection, metering, rate
+    /// limiting, session history, and the list cache, locating IP addresses with `geoip`,
+    /// looking them up in the anonymous IP feeds of `ip_intel`, and recognizing email domains
+    /// with `email_intel`
+    ///
+    /// Names are screened against no sanctions lists until [`AppState::with_screening`] shares
+    /// a downloading service, and webhook deliveries cannot be redriven until
+    /// [`AppState::with_webhook_sender`] gives them a client.
+    pub fn new(
+        config: Config,
+        database: Database,
+        clickhouse: Option<ClickHouseClient>,
+        redis: Option<ConnectionManager>,
+        geoip: GeoIpDatabase,
+        ip_intel: IpIntelService,
+        email_intel: EmailIntelService,
+    ) -> Self {
+        let users = UserService::new(database.pool().clone(), config.user_risk.clone());
+        let devices = DeviceService::new(database.pool().clone());
+        let lists = ListService::new(database.pool().clone())
+            .with_cache(redis.clone())
+            .with_auto_block(config.auto_block.clone());
+        let transactions = TransactionService::new(
+            database.pool().clone(),
+            database.read_pool().clone(),
+            config.redaction.clone(),
+        )
+        .with_geoip(geoip.clone())
+        .with_lists(lists.clone());
+        let accounts = AccountService::new(
+            database.pool().clone(),
+            config.metering.clone(),
+            config.lifecycle.clone(),
+        );
+        let cases = CaseService::new(database.pool().clone());
+        let webhooks = WebhookService::new(database.pool().clone());
+        let notifications = NotificationService::new(database.pool().clone());
+        let dead_letters = DeadLetterService::new(database.pool().clone());
+        let processor_events = ProcessorEventService::new(database.pool().clone());
+        let organizations = OrganizationService::new(database.pool().clone());
+        let analytics =
+            clickhouse.map(|client| AnalyticsService::new(client, database.read_pool().clone()));
+        let reports = ReportService::new(database.read_pool().clone());
+        let outcomes = OutcomeService::new(database.pool().clone());
+        let jobs = JobService::new(database.pool().clone());
+        let rules = RuleService::new(database.pool().clone());
+        let labels = LabelService::new(database.pool().clone(), database.read_pool().clone());
+        let meter = Meter::new(
+            database.pool().clone(),
+            redis.clone(),
+            config.metering.enforce_quotas,
+        );
+        let rate_limiter = RateLimiter::new(config.rate_limit.clone(), redis.clone());
+        let features = FeatureStore::new(database.pool().clone())
+            .with_geoip(geoip)
+            .with_velocity_subnets(config.features.ip_velocity_subnets());
+        let screening = ScreeningService::new(&config.screening);
+        let state = Self {
+            config,
+            database,
+            risk_engine: RiskEngine::new(),
+            transactions,
+            jobs,
+            users,
+            devices,
+            lists,
+            cases,
+            webhooks,
+            notifications,
+            dead_letters,
+            processor_events,
+            accounts,
+            organizations,
+            analytics,
+            reports,
+            outcomes,
+            rules,
+            labels,
+            live: LiveFeed::new(),
+            nonces: NonceCache::new(redis.clone()),
+            sessions: SessionStore::new(redis),
+            features,
+            ip_intel,
+            email_intel,
+            screening,
+            meter,
+            rate_limiter,
+        };
+        Self {
+            transactions: state
+                .transactions
+                .clone()
+                .with_signal_sources(state.signal_sources()),
+            ..state
+        }
+    }
+
+    /// Screen names against the lists of `screening`
+    pub fn with_screening(mut self, screening: ScreeningService) -> Self {
+        self.screening = screening;
+        let sources = self.signal_sources();
+        self.transactions = self.transactions.with_signal_sources(sources);
+        self
+    }
+
+    /// Redrive webhook deliveries with `sender`
+    pub fn with_webhook_sender(mut self, sender: WebhookSender) -> Self {
+        self.webhooks = self.webhooks.with_sender(sender);
+        self
+    }
+
+    /// Sources of the signals kept outside the database, which scoring through `transactions`
+    /// gathers from too
+    fn signal_sources(&self) -> SignalSources {
+        SignalSources {
+            sessions: self.sessions.clone(),
+            features: self.features.clone(),
+            ip_intel: self.ip_intel.clone(),
+            email_intel: self.email_intel.clone(),
+            screening: self.screening.clone(),
+        }
+    }
+}
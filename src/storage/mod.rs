@@ -0,0 +1,3 @@
+//! Object storage for exported files
+
+pub mod s3;
@@ -0,0 +1,38 @@
+//! Database fixtures shared by the tests that run against `TEST_DATABASE_URL`
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    database::{repositories::AccountRepo, run_migrations, tenant::Tenant},
+    models::account::SubscriptionTier,
+};
+
+/// Pool on the database named by `TEST_DATABASE_URL`, or `None` to skip the test
+pub(crate) async fn test_pool() -> Option<PgPool> {
+    let url = std::env::var("TEST_DATABASE_URL").ok()?;
+    let pool = PgPool::connect(&url)
+        .await
+        .expect("connect to TEST_DATABASE_URL");
+    run_migrations(&pool).await.expect("apply migrations");
+    Some(pool)
+}
+
+/// Fresh account on `tier` with a monthly quota of 1000
+pub(crate) async fn create_account(pool: &PgPool, tier: SubscriptionTier) -> Tenant {
+    create_account_with_quota(pool, tier, 1000).await
+}
+
+/// Fresh account on `tier` with the given monthly quota
+pub(crate) async fn create_account_with_quota(
+    pool: &PgPool,
+    tier: SubscriptionTier,
+    monthly_quota: i32,
+) -> Tenant {
+    let public_id = format!("test-{}", Uuid::new_v4());
+    let id = AccountRepo::create(pool, &public_id, tier, monthly_quota)
+        .await
+        .unwrap()
+        .unwrap();
+    Tenant::trusted(id)
+}
@@ -0,0 +1,148 @@
+//! HTTPS serving with optional client certificates
+//!
+//! Used when `TLS_CERT_PATH` and `TLS_KEY_PATH` are set, for deployments without a
+//! TLS-terminating proxy in front. With `MTLS_CLIENT_CA_PATH` the server also requests client
+//! certificates: clients presenting one must chain to that CA, while clients without one can
+//! still connect and authenticate with an API key. A verified certificate is attached to every
+//! request on its connection as a [`ClientCertificate`].
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use axum::{Router, body::Body};
+use hyper::{Request, body::Incoming, service::service_fn};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{conn::auto::Builder, graceful::GracefulShutdown},
+};
+use rustls::{
+    RootCertStore, ServerConfig as TlsConfig,
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    server::WebPkiClientVerifier,
+};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+
+use crate::{
+    auth::ClientCertificate,
+    config::{AuthConfig, ServerConfig},
+};
+
+/// Time a client gets to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time open connections get to finish once shutdown starts
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Build the TLS configuration from the certificate, key, and client CA paths
+pub fn tls_config(server: &ServerConfig, auth: &AuthConfig) -> anyhow::Result<Arc<TlsConfig>> {
+    let (Some(cert_path), Some(key_path)) = (&server.tls_cert_path, &server.tls_key_path) else {
+        anyhow::bail!("TLS_CERT_PATH and TLS_KEY_PATH must both be set");
+    };
+    let chain = load_certificates(cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("Failed to read TLS key from {key_path}"))?;
+
+    let provider = Arc::new(ring::default_provider());
+    let builder =
+        TlsConfig::builder_with_provider(provider.clone()).with_safe_default_protocol_versions()?;
+    let builder = match &auth.mtls_client_ca_path {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for certificate in load_certificates(ca_path)? {
+                roots.add(certificate)?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .allow_unauthenticated()
+                .build()?;
+            builder.with_client_cert_verifier(verifier)
+        },
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder.with_single_cert(chain, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// Serve `app` over TLS until `shutdown` completes, then let open connections finish
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    config: Arc<TlsConfig>,
+    shutdown: impl Future<Output = ()>,
+) {
+    let acceptor = TlsAcceptor::from(config);
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(connection) => connection,
+                Err(e) => {
+                    // Usually running out of file descriptors; back off instead of spinning
+                    tracing::warn!(error = %e, "Failed to accept connection");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                },
+            },
+            () = &mut shutdown => break,
+        };
+
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        tracing::debug!(%peer, error = %e, "TLS handshake failed");
+                        return;
+                    },
+                    Err(_) => {
+                        tracing::debug!(%peer, "TLS handshake timed out");
+                        return;
+                    },
+                };
+            let certificate = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .map(|der| ClientCertificate::from_der(der));
+
+            let service = service_fn(move |mut request: Request<Incoming>| {
+                if let Some(certificate) = &certificate {
+                    request.extensions_mut().insert(certificate.clone());
+                }
+                app.clone().oneshot(request.map(Body::new))
+            });
+            let builder = Builder::new(TokioExecutor::new());
+            let connection = builder.serve_connection(TokioIo::new(stream), service);
+            if let Err(e) = watcher.watch(connection).await {
+                tracing::debug!(%peer, error = %e, "Connection closed with error");
+            }
+        });
+    }
+
+    if tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, graceful.shutdown())
+        .await
+        .is_err()
+    {
+        tracing::warn!("Connections still open after the shutdown grace period");
+    }
+}
+
+fn load_certificates(path: &str) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let certificates = CertificateDer::pem_file_iter(path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .with_context(|| format!("Failed to read certificates from {path}"))?;
+    if certificates.is_empty() {
+        anyhow::bail!("No certificates found in {path}");
+    }
+    Ok(certificates)
+}
@@ -0,0 +1,128 @@
+//! Recalculation of user risk scores
+//!
+//! A user's stored risk score summarizes their recent history: the scores of their
+//! transactions, the outcomes customers reported for them, and their chargebacks on record.
+//! Recording a transaction, importing, or merging a user marks the user due, and this pass
+//! recalculates due users shortly after. Every user is also recalculated once
+//! `stale_after_hours` have passed since their last calculation, so scores decay as activity
+//! ages even for users who stopped transacting.
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    config::UserRiskConfig, database::repositories::UserRepo, scheduler::ScheduledTask,
+    services::UserService,
+};
+
+/// Task that recalculates users due for it on `recalculation_schedule`
+pub fn user_risk_recalculation_task(pool: PgPool, config: UserRiskConfig) -> ScheduledTask {
+    let users = UserService::new(pool.clone(), config.clone());
+    let schedule = config.recalculation_schedule.clone();
+    ScheduledTask::new("user_risk_recalculation", schedule, move || {
+        let (pool, users, config) = (pool.clone(), users.clone(), config.clone());
+        async move {
+            let recalculated = recalculate_due_users(&pool, &users, &config, Utc::now()).await?;
+            if recalculated > 0 {
+                tracing::info!(recalculated, "User risk scores recalculated");
+            }
+            Ok(())
+        }
+    })
+}
+
+/// Recalculate, in batches, every user due at `now`, returning how many were recalculated
+pub async fn recalculate_due_users(
+    pool: &PgPool,
+    users: &UserService,
+    config: &UserRiskConfig,
+    now: DateTime<Utc>,
+) -> sqlx::Result<usize> {
+    let scored_before = now - ChronoDuration::hours(i64::from(config.stale_after_hours));
+    let mut recalculated = 0;
+    loop {
+        let user_ids =
+            UserRepo::due_for_risk_scoring(pool, scored_before, config.batch_size).await?;
+        if user_ids.is_empty() {
+            return Ok(recalculated);
+        }
+        users.recalculate_risk_scores(&user_ids, now).await?;
+        recalculated += user_ids.len();
+        if (user_ids.len() as i64) < config.batch_size {
+            return Ok(recalculated);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        database::repositories::{AccountRepo, UserRepo},
+        models::{account::SubscriptionTier, transaction::TransactionRequest},
+        scoring::{RiskEngine, UserSignals},
+        services::TransactionService,
+        test_support::{create_account, test_pool},
+    };
+
+    #[tokio::test]
+    async fn test_users_are_recalculated_after_activity_and_when_stale() {
+        let Some(pool) = test_pool().await else {
+            return;
+        };
+        let tenant = create_account(&pool, SubscriptionTier::Pro).await;
+        let account_id = tenant.id();
+        let config = Config::default().user_risk;
+        let users = UserService::new(pool.clone(), config.clone());
+        let transactions =
+            TransactionService::new(pool.clone(), pool.clone(), Config::default().redaction);
+        let engine = RiskEngine::new();
+
+        let request: TransactionRequest = serde_json::from_value(json!({
+            "device": { "ip_address": "198.51.100.1", "user_agent": "Mozilla/5.0" },
+            "event": { "type": "purchase" },
+            "account": { "user_id": "customer-1" },
+            "billing": { "country": "US" },
+            "shipping": { "country": "CA" }
+        }))
+        .unwrap();
+        let assessment = engine.assess(&request, &UserSignals::default());
+        assert!(assessment.risk_score > 0.0);
+        let stored = transactions
+            .store_transaction(tenant, &request, &assessment, &[])
+            .await
+            .unwrap();
+        let user_id = stored.user_id.unwrap();
+        assert_eq!(
+            users.get_user(tenant, user_id).await.unwrap().risk_score,
+            0.0
+        );
+
+        let now = Utc::now();
+        recalculate_due_users(&pool, &users, &config, now)
+            .await
+            .unwrap();
+        let scored = users.get_user(tenant, user_id).await.unwrap().risk_score;
+        assert!(scored > 0.0 && scored < assessment.risk_score);
+        let due = UserRepo::due_for_risk_scoring(&pool, now - ChronoDuration::hours(1), 1000)
+            .await
+            .unwrap();
+        assert!(!due.contains(&user_id));
+
+        // A month on, with nothing new, the user is stale and their score has decayed
+        let later = now + ChronoDuration::days(30);
+        recalculate_due_users(&pool, &users, &config, later)
+            .await
+            .unwrap();
+        let decayed = users.get_user(tenant, user_id).await.unwrap().risk_score;
+        assert!(decayed > 0.0 && decayed < scored);
+
+        let recalculated = users.recalculate_risk(tenant, user_id).await.unwrap();
+        assert_eq!(recalculated.risk_score, scored);
+
+        AccountRepo::delete(&pool, account_id).await.unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+//! Small shared helpers
+
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+pub mod address;
+pub mod csv;
+pub mod geo;
+pub mod ip;
+pub mod matching;
+pub mod tls;
+pub mod ua;
+
+/// Lowercase hex SHA-256 digest of `input`
+pub fn sha256_hex(input: &str) -> String {
+    hex::encode(Sha256::digest(input.as_bytes()))
+}
+
+/// Exponential backoff after `attempt` failed attempts: `base` after the first, doubled after
+/// each further one, and never longer than `max`
+pub fn backoff(attempt: u32, base: Duration, max: Duration) -> Duration {
+    base.saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_is_capped() {
+        let (base, max) = (Duration::from_millis(500), Duration::from_secs(30));
+        assert_eq!(backoff(0, base, max), base);
+        assert_eq!(backoff(1, base, max), base);
+        assert_eq!(backoff(3, base, max), Duration::from_secs(2));
+        assert_eq!(backoff(7, base, max), max);
+        assert_eq!(backoff(u32::MAX, base, max), max);
+    }
+}
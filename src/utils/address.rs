@@ -0,0 +1,232 @@
+//! Postal address normalization
+//!
+//! Customers write one address many ways: "123 Main St., Apt 4B" and "123 main street
+//! apartment 4b" are the same place. Addresses are reduced to a normal form, casefolded with
+//! punctuation dropped, common street type, unit, and direction abbreviations expanded, and
+//! postal codes written without spacing, and hashed, so repeat uses of an address can be found
+//! without comparing free text. Names, companies, and phone numbers say who rather than where
+//! and are left out.
+
+use crate::{models::transaction::Address, utils::sha256_hex};
+
+/// Abbreviations of street types, units, and directions, with the words they stand for
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("aly", "alley"),
+    ("apt", "apartment"),
+    ("av", "avenue"),
+    ("ave", "avenue"),
+    ("bldg", "building"),
+    ("blvd", "boulevard"),
+    ("cir", "circle"),
+    ("cres", "crescent"),
+    ("ct", "court"),
+    ("dept", "department"),
+    ("dr", "drive"),
+    ("e", "east"),
+    ("expy", "expressway"),
+    ("fl", "floor"),
+    ("hwy", "highway"),
+    ("ln", "lane"),
+    ("n", "north"),
+    ("ne", "northeast"),
+    ("nw", "northwest"),
+    ("pkwy", "parkway"),
+    ("pl", "place"),
+    ("rd", "road"),
+    ("rm", "room"),
+    ("s", "south"),
+    ("se", "southeast"),
+    ("sq", "square"),
+    ("st", "street"),
+    ("ste", "suite"),
+    ("sw", "southwest"),
+    ("ter", "terrace"),
+    ("trl", "trail"),
+    ("w", "west"),
+];
+
+/// A postal address in normal form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedAddress {
+    /// Street address lines, joined
+    pub street: String,
+    /// City name
+    pub city: Option<String>,
+    /// Uppercase subdivision code
+    pub region: Option<String>,
+    /// Uppercase postal code, letters and digits only
+    pub postal_code: Option<String>,
+    /// Uppercase ISO 3166-1 alpha-2 country code
+    pub country: Option<String>,
+}
+
+impl NormalizedAddress {
+    /// SHA-256 hash of the normal form
+    pub fn hash(&self) -> String {
+        let parts = [
+            Some(self.street.as_str()),
+            self.city.as_deref(),
+            self.region.as_deref(),
+            self.postal_code.as_deref(),
+            self.country.as_deref(),
+        ];
+        sha256_hex(&parts.map(Option::unwrap_or_default).join("|"))
+    }
+}
+
+/// `address` in normal form, or `None` if it has no street address to tell it apart from the
+/// rest of its city
+pub fn normalize_address(address: &Address) -> Option<NormalizedAddress> {
+    let street = [address.address.as_deref(), address.address_2.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(normalize_street)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if street.is_empty() {
+        return None;
+    }
+    let country = address.country.as_deref().and_then(normalize_code);
+    Some(NormalizedAddress {
+        street,
+        city: address
+            .city
+            .as_deref()
+            .map(normalize_words)
+            .filter(|city| !city.is_empty()),
+        region: address.region.as_deref().and_then(normalize_code),
+        postal_code: address
+            .postal
+            .as_deref()
+            .map(|postal| normalize_postal_code(postal, country.as_deref()))
+            .filter(|postal| !postal.is_empty()),
+        country,
+    })
+}
+
+/// Hash of `address` in normal form, identifying it among an account's addresses however it was
+/// written; see [`normalize_address`]
+pub fn generate_address_hash(address: &Address) -> Option<String> {
+    normalize_address(address).map(|address| address.hash())
+}
+
+/// `text` casefolded, split into words at whitespace and punctuation, and joined by single spaces
+fn normalize_words(text: &str) -> String {
+    text.to_lowercase()
+        .replace('ß', "ss")
+        .split(|c: char| c.is_whitespace() || matches!(c, '.' | ',' | ';' | '#'))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A street address line in normal form, with abbreviations expanded
+fn normalize_street(line: &str) -> String {
+    normalize_words(line)
+        .split(' ')
+        .map(|word| {
+            ABBREVIATIONS
+                .iter()
+                .find(|(abbreviation, _)| *abbreviation == word)
+                .map_or(word, |(_, expansion)| expansion)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `postal` in uppercase without spacing or punctuation; US ZIP+4 codes are cut to the ZIP code
+fn normalize_postal_code(postal: &str, country: Option<&str>) -> String {
+    let mut postal: String = postal
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if country == Some("US") && postal.len() == 9 {
+        postal.truncate(5);
+    }
+    postal
+}
+
+/// A region or country code in uppercase, or `None` if it is blank
+fn normalize_code(code: &str) -> Option<String> {
+    let code = code.trim();
+    (!code.is_empty()).then(|| code.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(line: &str, line_2: Option<&str>, postal: &str, country: &str) -> Address {
+        Address {
+            address: Some(line.to_string()),
+            address_2: line_2.map(str::to_string),
+            city: Some("New York".to_string()),
+            region: Some("ny".to_string()),
+            postal: Some(postal.to_string()),
+            country: Some(country.to_string()),
+            ..Address::default()
+        }
+    }
+
+    #[test]
+    fn test_normalize_address() {
+        let normalized =
+            normalize_address(&address("123 Main St., Apt #4B", None, "10001-1234", "us")).unwrap();
+        assert_eq!(normalized.street, "123 main street apartment 4b");
+        assert_eq!(normalized.city.as_deref(), Some("new york"));
+        assert_eq!(normalized.region.as_deref(), Some("NY"));
+        assert_eq!(normalized.postal_code.as_deref(), Some("10001"));
+        assert_eq!(normalized.country.as_deref(), Some("US"));
+
+        let london = Address {
+            address: Some("10 Downing  St".to_string()),
+            postal: Some("sw1a 2aa".to_string()),
+            country: Some("GB".to_string()),
+            ..Address::default()
+        };
+        let normalized = normalize_address(&london).unwrap();
+        assert_eq!(normalized.street, "10 downing street");
+        assert_eq!(normalized.city, None);
+        assert_eq!(normalized.postal_code.as_deref(), Some("SW1A2AA"));
+
+        // Without a street, an address is only a city
+        let city_only = Address {
+            address: Some(" , ".to_string()),
+            city: Some("Berlin".to_string()),
+            ..Address::default()
+        };
+        assert_eq!(normalize_address(&city_only), None);
+    }
+
+    #[test]
+    fn test_generate_address_hash() {
+        let hash = generate_address_hash(&address("123 Main St.", Some("Apt 4B"), "10001", "US"));
+        assert!(hash.is_some());
+        assert_eq!(
+            hash,
+            generate_address_hash(&address(
+                "123 MAIN STREET APARTMENT 4B",
+                None,
+                "10001-1234",
+                "us"
+            ))
+        );
+
+        // Who an address is for does not change where it is
+        let mut named = address("123 Main St.", Some("Apt 4B"), "10001", "US");
+        named.first_name = Some("Jane".to_string());
+        named.phone_number = Some("212-555-0123".to_string());
+        assert_eq!(generate_address_hash(&named), hash);
+
+        assert_ne!(
+            generate_address_hash(&address("123 Main St.", Some("Apt 4C"), "10001", "US")),
+            hash
+        );
+        assert_ne!(
+            generate_address_hash(&address("123 Main St.", Some("Apt 4B"), "10002", "US")),
+            hash
+        );
+    }
+}
@@ -0,0 +1,46 @@
+//! CSV rendering for exports
+
+/// A CSV field, quoted if it holds a comma, quote, or line break
+pub fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// A customer-supplied CSV field, also defusing a value a spreadsheet would otherwise evaluate
+/// as a formula
+pub fn spreadsheet_field(value: &str) -> String {
+    if value.starts_with(['=', '+', '-', '@']) {
+        field(&format!("'{value}"))
+    } else {
+        field(value)
+    }
+}
+
+/// Append `fields` to `csv` as one line
+pub fn push_row<S: AsRef<str>>(csv: &mut String, fields: impl IntoIterator<Item = S>) {
+    let fields: Vec<String> = fields.into_iter().map(|f| field(f.as_ref())).collect();
+    csv.push_str(&fields.join(","));
+    csv.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_are_quoted_when_needed() {
+        assert_eq!(field("plain"), "plain");
+        assert_eq!(field("a,b"), "\"a,b\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(spreadsheet_field("=1+1"), "'=1+1");
+        assert_eq!(spreadsheet_field("-5,0"), "\"'-5,0\"");
+
+        let mut csv = String::new();
+        push_row(&mut csv, ["a", "b,c", ""]);
+        assert_eq!(csv, "a,\"b,c\",\n");
+    }
+}
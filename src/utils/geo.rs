@@ -0,0 +1,776 @@
+//! IP geolocation from a local MaxMind database
+//!
+//! Reads GeoIP2 or GeoLite2 City and Country databases in the MMDB format, and optionally a
+//! GeoLite2 ASN or GeoIP2 ISP database for the network an address belongs to. Billing and
+//! shipping addresses are placed at the centroid of their postal code, from a GeoNames postal
+//! code dump. Files are loaded into memory and swapped for the new version whenever they
+//! change on disk, so MaxMind's weekly updates take effect without a restart. Without a
+//! configured or loadable file every lookup finds nothing. Local times in an address's time
+//! zone come from PostgreSQL's time zone database, so none has to be bundled.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use maxminddb::{MaxMindDBError, Reader, geoip2};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use tokio::task::JoinHandle;
+
+use crate::scoring::combine_scores;
+
+/// Risk contribution of an IP address of an anonymizing proxy
+const ANONYMOUS_PROXY_SCORE: f64 = 60.0;
+/// Risk contribution of an IP address of a satellite provider, which may serve any country
+const SATELLITE_PROVIDER_SCORE: f64 = 30.0;
+/// Risk contribution of an IP address used in another country than it is registered to
+const FOREIGN_REGISTRATION_SCORE: f64 = 15.0;
+/// Risk contribution of an IP address that can only be located very roughly
+const IMPRECISE_LOCATION_SCORE: f64 = 10.0;
+/// Accuracy radius in kilometres beyond which a location counts as imprecise
+const IMPRECISE_LOCATION_RADIUS_KM: u16 = 500;
+/// Mean radius of the Earth in kilometres
+const EARTH_RADIUS_KM: f64 = 6_371.0;
+/// Distance in kilometres below which travel between two IP locations is put down to
+/// geolocation error
+const MIN_TRAVEL_DISTANCE_KM: f64 = 500.0;
+/// Shortest time in hours travel is measured over, so near-simultaneous events do not divide
+/// by zero
+const MIN_TRAVEL_HOURS: f64 = 1.0 / 60.0;
+/// Speed in km/h above which travel is only possible by air
+const FLIGHT_SPEED_KMH: f64 = 500.0;
+/// Speed in km/h above which travel is not possible at all
+const IMPOSSIBLE_SPEED_KMH: f64 = 1_000.0;
+/// Risk contribution of travel only possible by air
+const FLIGHT_TRAVEL_SCORE: f64 = 30.0;
+/// Risk contribution of travel faster than any airliner
+const IMPOSSIBLE_TRAVEL_SCORE: f64 = 60.0;
+/// SQLSTATE PostgreSQL raises for an unknown time zone
+const INVALID_PARAMETER_VALUE: &str = "22023";
+
+/// What the GeoIP database says about an IP address
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IpAddressInfo {
+    /// ISO 3166-1 alpha-2 code of the country the address is used in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// ISO 3166-1 alpha-2 code of the country the address is registered to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registered_country: Option<String>,
+    /// Two-letter continent code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continent: Option<String>,
+    /// ISO 3166-2 code of the largest subdivision, without the country prefix
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subdivision: Option<String>,
+    /// City name, in English
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// Postal code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postal_code: Option<String>,
+    /// Approximate latitude
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    /// Approximate longitude
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+    /// Radius in kilometres around the coordinates the address is likely within
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accuracy_radius: Option<u16>,
+    /// IANA time zone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    /// Current time in the time zone, when looked up for display
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_time: Option<DateTime<FixedOffset>>,
+    /// Whether the address belongs to an anonymizing proxy
+    pub is_anonymous_proxy: bool,
+    /// Whether the address belongs to a satellite internet provider
+    pub is_satellite_provider: bool,
+}
+
+impl IpAddressInfo {
+    /// Latitude and longitude of the address, if the database has both
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        Some((self.latitude?, self.longitude?))
+    }
+}
+
+impl From<geoip2::City<'_>> for IpAddressInfo {
+    fn from(record: geoip2::City<'_>) -> Self {
+        let location = record.location.as_ref();
+        let traits = record.traits.as_ref();
+        Self {
+            country: record.country.and_then(|c| c.iso_code).map(str::to_string),
+            registered_country: record
+                .registered_country
+                .and_then(|c| c.iso_code)
+                .map(str::to_string),
+            continent: record.continent.and_then(|c| c.code).map(str::to_string),
+            subdivision: record
+                .subdivisions
+                .and_then(|subdivisions| subdivisions.into_iter().next())
+                .and_then(|s| s.iso_code)
+                .map(str::to_string),
+            city: record
+                .city
+                .and_then(|city| city.names)
+                .and_then(|names| names.get("en").map(|name| name.to_string())),
+            postal_code: record.postal.and_then(|p| p.code).map(str::to_string),
+            latitude: location.and_then(|l| l.latitude),
+            longitude: location.and_then(|l| l.longitude),
+            accuracy_radius: location.and_then(|l| l.accuracy_radius),
+            time_zone: location.and_then(|l| l.time_zone).map(str::to_string),
+            local_time: None,
+            is_anonymous_proxy: traits.and_then(|t| t.is_anonymous_proxy) == Some(true),
+            is_satellite_provider: traits.and_then(|t| t.is_satellite_provider) == Some(true),
+        }
+    }
+}
+
+/// Reasons an IP address's location makes it riskier, with their risk contributions
+pub fn location_risk_reasons(info: &IpAddressInfo) -> Vec<(f64, &'static str)> {
+    let foreign = matches!(
+        (&info.country, &info.registered_country),
+        (Some(country), Some(registered)) if country != registered
+    );
+    let imprecise = info
+        .accuracy_radius
+        .is_some_and(|radius| radius > IMPRECISE_LOCATION_RADIUS_KM);
+    [
+        (
+            info.is_anonymous_proxy,
+            ANONYMOUS_PROXY_SCORE,
+            "Address belongs to an anonymizing proxy",
+        ),
+        (
+            info.is_satellite_provider,
+            SATELLITE_PROVIDER_SCORE,
+            "Address belongs to a satellite provider",
+        ),
+        (
+            foreign,
+            FOREIGN_REGISTRATION_SCORE,
+            "Address is used outside the country it is registered to",
+        ),
+        (
+            imprecise,
+            IMPRECISE_LOCATION_SCORE,
+            "Address can only be located roughly",
+        ),
+    ]
+    .into_iter()
+    .filter(|(applies, ..)| *applies)
+    .map(|(_, score, reason)| (score, reason))
+    .collect()
+}
+
+/// Risk of an IP address's location from 0 to 100, combining the reasons from
+/// [`location_risk_reasons`] like transaction risk factors
+pub fn get_location_risk_score(info: &IpAddressInfo) -> f64 {
+    combine_scores(
+        location_risk_reasons(info)
+            .into_iter()
+            .map(|(score, _)| score),
+    )
+}
+
+/// Great-circle distance in kilometres between two `(latitude, longitude)` points
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Risk from 0 to 100 of covering `distance_km` in `hours`
+///
+/// Short distances score nothing, as IP locations are only accurate to tens or hundreds of
+/// kilometres. Beyond that, travel only possible by air is suspicious and travel faster than
+/// an airliner is impossible.
+pub fn calculate_velocity_risk(distance_km: f64, hours: f64) -> f64 {
+    if distance_km < MIN_TRAVEL_DISTANCE_KM {
+        return 0.0;
+    }
+    let speed_kmh = distance_km / hours.max(MIN_TRAVEL_HOURS);
+    if speed_kmh > IMPOSSIBLE_SPEED_KMH {
+        IMPOSSIBLE_TRAVEL_SCORE
+    } else if speed_kmh > FLIGHT_SPEED_KMH {
+        FLIGHT_TRAVEL_SCORE
+    } else {
+        0.0
+    }
+}
+
+/// `at` as the wall-clock time in the IANA time zone `time_zone`
+///
+/// Returns `None` when PostgreSQL does not know the time zone.
+pub async fn local_time(
+    executor: impl PgExecutor<'_>,
+    time_zone: &str,
+    at: DateTime<Utc>,
+) -> sqlx::Result<Option<DateTime<FixedOffset>>> {
+    let offset = sqlx::query_scalar::<_, i32>(
+        "SELECT EXTRACT(EPOCH FROM ($1::timestamptz AT TIME ZONE $2) - ($1 AT TIME ZONE 'UTC'))::int4",
+    )
+    .bind(at)
+    .bind(time_zone)
+    .fetch_one(executor)
+    .await;
+    match offset {
+        Ok(offset) => Ok(FixedOffset::east_opt(offset).map(|offset| at.with_timezone(&offset))),
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some(INVALID_PARAMETER_VALUE) => {
+            Ok(None)
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// Loaded contents and the modification time of the file they were loaded from
+struct LoadedFile<T> {
+    contents: T,
+    modified: Option<SystemTime>,
+}
+
+/// One data file, reloaded in place when it changes
+struct DataFile<T> {
+    path: Option<PathBuf>,
+    loaded: Arc<RwLock<Option<LoadedFile<T>>>>,
+}
+
+/// A MaxMind database file
+type MmdbFile = DataFile<Reader<Vec<u8>>>;
+
+impl<T> DataFile<T> {
+    fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            loaded: Arc::default(),
+        }
+    }
+
+    /// Load the file with `load` if it changed since it was last loaded, returning whether it
+    /// was loaded
+    fn reload(
+        &self,
+        load: impl FnOnce(&Path) -> Result<T, MaxMindDBError>,
+    ) -> Result<bool, MaxMindDBError> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+        let modified = std::fs::metadata(path)?.modified().ok();
+        let unchanged = self
+            .loaded
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .is_some_and(|loaded| modified.is_some() && loaded.modified == modified);
+        if unchanged {
+            return Ok(false);
+        }
+        let contents = load(path)?;
+        *self.loaded.write().unwrap_or_else(|e| e.into_inner()) =
+            Some(LoadedFile { contents, modified });
+        Ok(true)
+    }
+
+    /// Read the loaded file with `read`, or `None` if nothing is loaded
+    fn read<R>(&self, read: impl FnOnce(&T) -> Option<R>) -> Option<R> {
+        let loaded = self.loaded.read().unwrap_or_else(|e| e.into_inner());
+        read(&loaded.as_ref()?.contents)
+    }
+}
+
+impl<T> Clone for DataFile<T> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            loaded: Arc::clone(&self.loaded),
+        }
+    }
+}
+
+impl<T> Default for DataFile<T> {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Record found for `ip`, or `None` if there is none; other failures are logged
+fn found<T>(ip: IpAddr, result: Result<T, MaxMindDBError>) -> Option<T> {
+    match result {
+        Ok(record) => Some(record),
+        Err(MaxMindDBError::AddressNotFoundError(_)) => None,
+        Err(e) => {
+            tracing::warn!(error = %e, %ip, "GeoIP lookup failed");
+            None
+        },
+    }
+}
+
+impl fmt::Debug for MmdbFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let loaded = self.loaded.read().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("MmdbFile")
+            .field("path", &self.path)
+            .field(
+                "database_type",
+                &loaded
+                    .as_ref()
+                    .map(|loaded| &loaded.contents.metadata.database_type),
+            )
+            .finish()
+    }
+}
+
+/// Centroids of postal codes, by country and normalized postal code
+#[derive(Debug, Default)]
+struct PostalCentroids(HashMap<(String, String), (f64, f64)>);
+
+impl PostalCentroids {
+    /// Parse a GeoNames postal code dump
+    ///
+    /// Lines are tab-separated, with the country code first, the postal code second, and the
+    /// latitude and longitude of its centroid tenth and eleventh. Lines without a usable
+    /// centroid are skipped; of repeated postal codes the first is kept.
+    fn parse(text: &str) -> Self {
+        let mut centroids = HashMap::new();
+        for line in text.lines() {
+            let columns: Vec<&str> = line.split('\t').collect();
+            let [country, postal, ..] = columns[..] else {
+                continue;
+            };
+            let coordinates = columns
+                .get(9)
+                .zip(columns.get(10))
+                .and_then(|(lat, lon)| Some((lat.trim().parse().ok()?, lon.trim().parse().ok()?)));
+            if let Some(coordinates) = coordinates {
+                centroids
+                    .entry((
+                        country.trim().to_ascii_uppercase(),
+                        normalize_postal(postal),
+                    ))
+                    .or_insert(coordinates);
+            }
+        }
+        Self(centroids)
+    }
+
+    /// Read and parse the postal code dump at `path`
+    fn load(path: &Path) -> Result<Self, MaxMindDBError> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&text))
+    }
+
+    /// Centroid of `postal` in `country`, falling back to the part of the postal code before
+    /// its first space or hyphen, such as the outward code of a UK postcode or the five-digit
+    /// ZIP code of a ZIP+4
+    fn locate(&self, country: &str, postal: &str) -> Option<(f64, f64)> {
+        let country = country.trim().to_ascii_uppercase();
+        let full = normalize_postal(postal);
+        let prefix = postal
+            .trim()
+            .split([' ', '-'])
+            .next()
+            .map(normalize_postal)
+            .filter(|prefix| !prefix.is_empty() && *prefix != full);
+        [Some(full), prefix]
+            .into_iter()
+            .flatten()
+            .find_map(|postal| self.0.get(&(country.clone(), postal)).copied())
+    }
+}
+
+/// Postal code in upper case without whitespace, so `sw1a 1aa` and `SW1A1AA` match
+fn normalize_postal(postal: &str) -> String {
+    postal
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+impl fmt::Debug for DataFile<PostalCentroids> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let loaded = self.loaded.read().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("PostalFile")
+            .field("path", &self.path)
+            .field(
+                "postal_codes",
+                &loaded.as_ref().map(|loaded| loaded.contents.0.len()),
+            )
+            .finish()
+    }
+}
+
+/// Network an IP address belongs to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AsnInfo {
+    /// Autonomous system number
+    pub asn: u32,
+    /// Organization the autonomous system is registered to
+    pub organization: Option<String>,
+    /// Internet service provider, from GeoIP2 ISP databases only
+    pub isp: Option<String>,
+}
+
+impl AsnInfo {
+    /// Internet service provider, or the organization the autonomous system is registered to
+    /// if the database does not name one
+    pub fn provider(&self) -> Option<&str> {
+        self.isp.as_deref().or(self.organization.as_deref())
+    }
+}
+
+/// Shared handle on the GeoIP databases, reloaded in place when their files change
+#[derive(Debug, Clone, Default)]
+pub struct GeoIpDatabase {
+    city: MmdbFile,
+    asn: MmdbFile,
+    postal: DataFile<PostalCentroids>,
+}
+
+impl GeoIpDatabase {
+    /// Handle on the location database at `path`, not loaded until [`GeoIpDatabase::reload`]
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            city: MmdbFile::new(path),
+            ..Self::default()
+        }
+    }
+
+    /// Handle without a database, whose lookups find nothing
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Also resolve networks with the ASN or ISP database at `path`
+    pub fn with_asn_database(mut self, path: Option<PathBuf>) -> Self {
+        self.asn = MmdbFile::new(path);
+        self
+    }
+
+    /// Also place addresses at their postal code with the GeoNames postal code dump at `path`
+    pub fn with_postal_centroids(mut self, path: Option<PathBuf>) -> Self {
+        self.postal = DataFile::new(path);
+        self
+    }
+
+    /// Load each database whose file changed since it was last loaded, returning whether any
+    /// was loaded
+    ///
+    /// On failure the previously loaded version stays in use.
+    pub fn reload(&self) -> Result<bool, MaxMindDBError> {
+        let city = self.city.reload(|path| Reader::open_readfile(path));
+        let asn = self.asn.reload(|path| Reader::open_readfile(path));
+        let postal = self.postal.reload(PostalCentroids::load);
+        Ok(city? | asn? | postal?)
+    }
+
+    /// What the location database says about `ip`, or `None` if it has no record of it or no
+    /// database is loaded
+    pub fn lookup(&self, ip: IpAddr) -> Option<IpAddressInfo> {
+        self.city
+            .read(|reader| found(ip, reader.lookup::<geoip2::City<'_>>(ip)).map(Into::into))
+    }
+
+    /// Network `ip` belongs to, or `None` if the ASN database has no record of it or none is
+    /// loaded
+    pub fn lookup_asn(&self, ip: IpAddr) -> Option<AsnInfo> {
+        self.asn.read(|reader| {
+            let record = found(ip, reader.lookup::<geoip2::Isp<'_>>(ip))?;
+            Some(AsnInfo {
+                asn: record.autonomous_system_number?,
+                organization: record.autonomous_system_organization.map(str::to_string),
+                isp: record.isp.map(str::to_string),
+            })
+        })
+    }
+
+    /// Latitude and longitude of the centroid of `postal` in `country`, or `None` if the
+    /// postal code dump does not list it or none is loaded
+    pub fn locate_postal(&self, country: &str, postal: &str) -> Option<(f64, f64)> {
+        self.postal
+            .read(|centroids| centroids.locate(country, postal))
+    }
+}
+
+/// Spawn a background task that reloads the GeoIP database whenever its file changes
+pub fn spawn_geoip_reload(database: GeoIpDatabase, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let handle = database.clone();
+            match tokio::task::spawn_blocking(move || handle.reload()).await {
+                Ok(Ok(true)) => tracing::info!("GeoIP database reloaded"),
+                Ok(Ok(false)) => {},
+                Ok(Err(e)) => tracing::warn!(error = %e, "GeoIP database reload failed"),
+                Err(e) => tracing::error!(error = %e, "GeoIP database reload panicked"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    /// Encoded MMDB data field of type `kind` and size `size`, up to 284
+    fn field(kind: u8, size: usize, payload: &[u8]) -> Vec<u8> {
+        let (size, extended_size) = if size < 29 {
+            (size as u8, None)
+        } else {
+            (29, Some((size - 29) as u8))
+        };
+        let mut bytes = if kind <= 7 {
+            vec![(kind << 5) | size]
+        } else {
+            vec![size, kind - 7]
+        };
+        bytes.extend(extended_size);
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        field(2, value.len(), value.as_bytes())
+    }
+
+    fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = field(7, entries.len(), &[]);
+        for (key, value) in entries {
+            bytes.extend(string(key));
+            bytes.extend_from_slice(value);
+        }
+        bytes
+    }
+
+    fn uint(kind: u8, value: u64, width: usize) -> Vec<u8> {
+        field(kind, width, &value.to_be_bytes()[8 - width..])
+    }
+
+    fn double(value: f64) -> Vec<u8> {
+        field(3, 8, &value.to_be_bytes())
+    }
+
+    /// IPv4 database placing 0.0.0.0/1 in `country` and knowing nothing of 128.0.0.0/1
+    pub(crate) fn mmdb(country: &str) -> Vec<u8> {
+        city_database(country, vec![])
+    }
+
+    /// IPv4 database placing 0.0.0.0/1 in `country` at `latitude` and `longitude`, and
+    /// knowing nothing of 128.0.0.0/1
+    pub(crate) fn located_mmdb(country: &str, latitude: f64, longitude: f64) -> Vec<u8> {
+        city_database(
+            country,
+            vec![
+                ("latitude", double(latitude)),
+                ("longitude", double(longitude)),
+            ],
+        )
+    }
+
+    fn city_database(country: &str, coordinates: Vec<(&str, Vec<u8>)>) -> Vec<u8> {
+        let mut location = vec![
+            ("accuracy_radius", uint(5, 1000, 2)),
+            ("time_zone", string("Europe/Berlin")),
+        ];
+        location.extend(coordinates);
+        database(
+            "GeoLite2-City",
+            map(&[
+                ("country", map(&[("iso_code", string(country))])),
+                ("registered_country", map(&[("iso_code", string("NL"))])),
+                ("location", map(&location)),
+                ("traits", map(&[("is_anonymous_proxy", field(14, 1, &[]))])),
+            ]),
+        )
+    }
+
+    /// IPv4 ASN database placing 0.0.0.0/1 in `asn`, registered to `organization`
+    pub(crate) fn asn_mmdb(asn: u32, organization: &str) -> Vec<u8> {
+        database(
+            "GeoLite2-ASN",
+            map(&[
+                ("autonomous_system_number", uint(6, asn.into(), 4)),
+                ("autonomous_system_organization", string(organization)),
+            ]),
+        )
+    }
+
+    /// IPv4 database of `database_type` holding `record` for 0.0.0.0/1 and nothing for
+    /// 128.0.0.0/1
+    fn database(database_type: &str, record: Vec<u8>) -> Vec<u8> {
+        // One node: the left record points at the start of the data section, the right one
+        // at "no data"
+        let node_count = 1u32;
+        let data_pointer = (node_count + 16).to_be_bytes();
+        let right = node_count.to_be_bytes();
+        let mut bytes = [&data_pointer[1..], &right[1..]].concat();
+        bytes.extend([0; 16]);
+        bytes.extend(record);
+        bytes.extend(b"\xAB\xCD\xEFMaxMind.com");
+        bytes.extend(map(&[
+            ("binary_format_major_version", uint(5, 2, 2)),
+            ("binary_format_minor_version", uint(5, 0, 2)),
+            ("build_epoch", uint(9, 1_720_000_000, 8)),
+            ("database_type", string(database_type)),
+            ("description", map(&[])),
+            ("ip_version", uint(5, 4, 2)),
+            ("languages", field(11, 0, &[])),
+            ("node_count", uint(6, node_count.into(), 4)),
+            ("record_size", uint(5, 24, 2)),
+        ]));
+        bytes
+    }
+
+    #[test]
+    fn test_location_risk_score() {
+        assert_eq!(get_location_risk_score(&IpAddressInfo::default()), 0.0);
+        let info = IpAddressInfo {
+            country: Some("DE".to_string()),
+            registered_country: Some("NL".to_string()),
+            accuracy_radius: Some(20),
+            ..IpAddressInfo::default()
+        };
+        assert_eq!(get_location_risk_score(&info), FOREIGN_REGISTRATION_SCORE);
+        let info = IpAddressInfo {
+            is_anonymous_proxy: true,
+            accuracy_radius: Some(1000),
+            ..info
+        };
+        assert_eq!(
+            get_location_risk_score(&info),
+            combine_scores([
+                ANONYMOUS_PROXY_SCORE,
+                FOREIGN_REGISTRATION_SCORE,
+                IMPRECISE_LOCATION_SCORE
+            ])
+        );
+    }
+
+    #[test]
+    fn test_velocity_risk() {
+        let berlin = (52.52, 13.405);
+        let new_york = (40.713, -74.006);
+        let distance = distance_km(berlin, new_york);
+        assert!((distance - 6_385.0).abs() < 10.0, "{distance}");
+        assert_eq!(distance_km(berlin, berlin), 0.0);
+
+        // Too close to tell apart from geolocation error, however fast
+        assert_eq!(calculate_velocity_risk(300.0, 0.0), 0.0);
+        assert_eq!(calculate_velocity_risk(distance, 24.0), 0.0);
+        assert_eq!(calculate_velocity_risk(distance, 9.0), FLIGHT_TRAVEL_SCORE);
+        assert_eq!(
+            calculate_velocity_risk(distance, 2.0),
+            IMPOSSIBLE_TRAVEL_SCORE
+        );
+        assert_eq!(
+            calculate_velocity_risk(distance, 0.0),
+            IMPOSSIBLE_TRAVEL_SCORE
+        );
+    }
+
+    #[test]
+    fn test_database_is_reloaded_when_the_file_changes() {
+        let path = std::env::temp_dir().join(format!("geoip-{}.mmdb", uuid::Uuid::new_v4()));
+        let ip: IpAddr = "1.2.3.4".parse().unwrap();
+        let database = GeoIpDatabase::new(Some(path.clone()));
+        assert!(database.reload().is_err());
+        assert_eq!(database.lookup(ip), None);
+
+        std::fs::write(&path, mmdb("DE")).unwrap();
+        assert!(database.reload().unwrap());
+        assert!(!database.reload().unwrap());
+        let info = database.lookup(ip).unwrap();
+        assert_eq!(info.country.as_deref(), Some("DE"));
+        assert_eq!(info.registered_country.as_deref(), Some("NL"));
+        assert_eq!(info.time_zone.as_deref(), Some("Europe/Berlin"));
+        assert!(info.is_anonymous_proxy);
+        assert_eq!(database.lookup("200.1.1.1".parse().unwrap()), None);
+
+        std::fs::write(&path, b"not a database").unwrap();
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert!(database.reload().is_err());
+        assert_eq!(database.lookup(ip).unwrap().country.as_deref(), Some("DE"));
+
+        std::fs::write(&path, mmdb("FR")).unwrap();
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(120))
+            .unwrap();
+        assert!(database.reload().unwrap());
+        assert_eq!(database.lookup(ip).unwrap().country.as_deref(), Some("FR"));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(GeoIpDatabase::disabled().lookup(ip), None);
+    }
+
+    #[test]
+    fn test_asn_lookup() {
+        let path = std::env::temp_dir().join(format!("asn-{}.mmdb", uuid::Uuid::new_v4()));
+        std::fs::write(&path, asn_mmdb(64_500, "Example Hosting")).unwrap();
+        let ip: IpAddr = "1.2.3.4".parse().unwrap();
+        let database = GeoIpDatabase::disabled().with_asn_database(Some(path.clone()));
+        assert!(database.reload().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            database.lookup_asn(ip),
+            Some(AsnInfo {
+                asn: 64_500,
+                organization: Some("Example Hosting".to_string()),
+                isp: None,
+            })
+        );
+        assert_eq!(database.lookup_asn("200.1.1.1".parse().unwrap()), None);
+        // Without a location database, addresses are still resolved to their network only
+        assert_eq!(database.lookup(ip), None);
+        assert_eq!(GeoIpDatabase::disabled().lookup_asn(ip), None);
+    }
+    #[test]
+    fn test_postal_centroids() {
+        let centroids = PostalCentroids::parse(
+            "US\t10001\tNew York\tNew York\tNY\t\t\t\t\t40.7484\t-73.9967\t4\n\
+             US\t10001\tDuplicate\t\t\t\t\t\t\t0.0\t0.0\t4\n\
+             GB\tSW1A\tLondon\t\t\t\t\t\t\t51.5010\t-0.1416\t4\n\
+             DE\t10115\tBerlin\t\t\t\t\t\t\t\t\t\n\
+             malformed line\n",
+        );
+        assert_eq!(centroids.0.len(), 2);
+        assert_eq!(centroids.locate("US", "10001"), Some((40.7484, -73.9967)));
+        assert_eq!(
+            centroids.locate("us", "10001-1234"),
+            Some((40.7484, -73.9967))
+        );
+        assert_eq!(centroids.locate("GB", "sw1a 1aa"), Some((51.5010, -0.1416)));
+        assert_eq!(centroids.locate("DE", "10115"), None);
+        assert_eq!(centroids.locate("CA", "10001"), None);
+
+        let path = std::env::temp_dir().join(format!("postal-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "US\t10001\tNew York\t\t\t\t\t\t\t40.7484\t-73.9967\t4\n",
+        )
+        .unwrap();
+        let database = GeoIpDatabase::disabled().with_postal_centroids(Some(path.clone()));
+        assert_eq!(database.locate_postal("US", "10001"), None);
+        assert!(database.reload().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            database.locate_postal("US", "10001"),
+            Some((40.7484, -73.9967))
+        );
+    }
+}
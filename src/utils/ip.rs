@@ -0,0 +1,88 @@
+//! IP address normalization
+//!
+//! IPv6 clients rotate through the addresses of their /64 at will, and some IPv4 clients
+//! through a /24, so counting their transactions per address undercounts them. Velocity is
+//! therefore keyed on the subnet an address belongs to, with configurable prefix lengths.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Canonical form of the range `ip/prefix_len`, with the host bits cleared
+pub fn network(ip: IpAddr, prefix_len: u32) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(32 - prefix_len).unwrap_or(0);
+            format!("{}/{prefix_len}", Ipv4Addr::from(u32::from(v4) & mask))
+        },
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(128 - prefix_len).unwrap_or(0);
+            format!("{}/{prefix_len}", Ipv6Addr::from(u128::from(v6) & mask))
+        },
+    }
+}
+
+/// Longest prefix of an address of the same family as `ip`
+pub fn max_prefix_len(ip: IpAddr) -> u32 {
+    if ip.is_ipv4() { 32 } else { 128 }
+}
+
+/// Prefix lengths that group IP addresses into subnets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubnetPrefixes {
+    /// Prefix length of IPv4 subnets, 32 to key on single addresses
+    pub ipv4: u32,
+    /// Prefix length of IPv6 subnets
+    pub ipv6: u32,
+}
+
+impl SubnetPrefixes {
+    /// Subnets of `ipv4` and `ipv6` bits, capped at the length of an address
+    pub fn new(ipv4: u32, ipv6: u32) -> Self {
+        Self {
+            ipv4: ipv4.min(32),
+            ipv6: ipv6.min(128),
+        }
+    }
+
+    /// Subnet `ip` belongs to, in CIDR notation
+    ///
+    /// IPv4-mapped IPv6 addresses belong to the IPv4 subnet of the address they map.
+    pub fn subnet(&self, ip: IpAddr) -> String {
+        let ip = ip.to_canonical();
+        let prefix_len = if ip.is_ipv4() { self.ipv4 } else { self.ipv6 };
+        network(ip, prefix_len)
+    }
+}
+
+impl Default for SubnetPrefixes {
+    /// Single IPv4 addresses and IPv6 /64s, the block a single IPv6 customer is assigned
+    fn default() -> Self {
+        Self::new(32, 64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subnets_group_rotating_addresses() {
+        let subnets = SubnetPrefixes::default();
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert_eq!(
+            subnets.subnet(ip("2001:DB8:0:1:aaaa::1")),
+            subnets.subnet(ip("2001:db8::1:ffff:ffff:ffff:ffff"))
+        );
+        assert_eq!(subnets.subnet(ip("2001:db8::1")), "2001:db8::/64");
+        assert_ne!(
+            subnets.subnet(ip("2001:db8:0:1::1")),
+            subnets.subnet(ip("2001:db8:0:2::1"))
+        );
+        assert_eq!(subnets.subnet(ip("198.51.100.7")), "198.51.100.7/32");
+        assert_eq!(subnets.subnet(ip("::ffff:198.51.100.7")), "198.51.100.7/32");
+
+        let wide = SubnetPrefixes::new(24, 48);
+        assert_eq!(wide.subnet(ip("198.51.100.7")), "198.51.100.0/24");
+        assert_eq!(wide.subnet(ip("2001:db8:1:2::1")), "2001:db8:1::/48");
+        assert_eq!(SubnetPrefixes::new(40, 200), SubnetPrefixes::new(32, 128));
+    }
+}
@@ -0,0 +1,262 @@
+//! Fuzzy matching of personal names
+//!
+//! One person's name is written differently on their card, their billing address, and in their
+//! email address: "SMITH/JONATHAN", "Jon Smith", "jsmith87@example.com". Names are compared
+//! word by word in any order, each pair of words scoring the best of an exact match, an initial,
+//! a Soundex match, and their Levenshtein similarity. Scores run from 0 for unrelated names to 1
+//! for the same name.
+
+/// Words dropped from names before they are compared
+const HONORIFICS: &[&str] = &["mr", "mrs", "ms", "miss", "dr", "jr", "sr"];
+
+/// Score of an initial against a word starting with it
+const INITIAL_SCORE: f64 = 0.9;
+/// Score of two different words with the same Soundex code
+const PHONETIC_SCORE: f64 = 0.85;
+/// Score of an email local part containing a word of the name
+const CONTAINED_WORD_SCORE: f64 = 0.9;
+/// Shortest name word an email local part is searched for
+const MIN_CONTAINED_WORD_CHARS: usize = 3;
+/// Soundex codes with fewer significant characters are too common to match on
+const MIN_SOUNDEX_SIGNIFICANT_CHARS: usize = 3;
+
+/// Edits of one character that turn `a` into `b`
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Levenshtein distance of `a` and `b` as a similarity from 0 to 1, relative to the longer
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+/// American Soundex code of the ASCII letters in `word`, or `None` if it has none
+pub fn soundex(word: &str) -> Option<String> {
+    let mut letters = word
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase());
+    let first = letters.next()?;
+    let mut code = String::from(first);
+    let mut last = soundex_digit(first);
+    for letter in letters {
+        let digit = soundex_digit(letter);
+        if digit.is_some() && digit != last {
+            code.extend(digit);
+            if code.len() == 4 {
+                break;
+            }
+        }
+        // Vowels separate consonants with the same code; H and W do not
+        if !matches!(letter, 'H' | 'W') {
+            last = digit;
+        }
+    }
+    while code.len() < 4 {
+        code.push('0');
+    }
+    Some(code)
+}
+
+/// How closely names `a` and `b` match, from 0 to 1, or `None` if either has no words
+///
+/// Every word of the name with fewer words is matched against its best match in the other, so
+/// a missing middle name does not count against a match.
+pub fn name_match(a: &str, b: &str) -> Option<f64> {
+    let (a, b) = (name_words(a), name_words(b));
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let (fewer, more) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    let total: f64 = fewer
+        .iter()
+        .map(|x| more.iter().map(|y| word_match(x, y)).fold(0.0, f64::max))
+        .sum();
+    Some(total / fewer.len() as f64)
+}
+
+/// How closely names `a` and `b` match, from 0 to 1, or `None` if either has no words
+///
+/// Every word of each name is matched against its best match in the other, so unlike
+/// [`name_match`] words only one of the names has count against the match: "John" is not
+/// "John Smith". Suited to screening, where a partial name must not pass for a full one.
+pub fn full_name_match(a: &str, b: &str) -> Option<f64> {
+    let (a, b) = (name_words(a), name_words(b));
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let best = |words: &[String], others: &[String]| -> f64 {
+        words
+            .iter()
+            .map(|x| others.iter().map(|y| word_match(x, y)).fold(0.0, f64::max))
+            .sum()
+    };
+    Some((best(&a, &b) + best(&b, &a)) / (a.len() + b.len()) as f64)
+}
+
+/// How closely the local part of `email` matches `name`, from 0 to 1, or `None` if either has
+/// no letters
+///
+/// Local parts spell names in many ways: `jon.smith`, `smithj`, `jsmith87`, or a single name.
+/// The best of a word-by-word match, a match against those spellings, and the local part
+/// containing a word of the name is taken.
+pub fn email_name_match(name: &str, email: &str) -> Option<f64> {
+    let local = email.trim().rsplit_once('@')?.0.to_lowercase();
+    let local = local
+        .split_once('+')
+        .map_or(local.as_str(), |(base, _)| base);
+    let letters: String = local.chars().filter(|c| c.is_alphabetic()).collect();
+    let words = name_words(name);
+    let (first, last) = (words.first()?, words.last()?);
+    if letters.is_empty() {
+        return None;
+    }
+
+    let initial = |word: &str| word.chars().take(1).collect::<String>();
+    let spellings = [
+        format!("{first}{last}"),
+        format!("{last}{first}"),
+        format!("{}{last}", initial(first)),
+        format!("{first}{}", initial(last)),
+        format!("{last}{}", initial(first)),
+        first.clone(),
+        last.clone(),
+    ];
+    let spelled = spellings
+        .iter()
+        .map(|spelling| similarity(&letters, spelling))
+        .fold(0.0, f64::max);
+    let by_word = name_match(local, name).unwrap_or_default();
+    let contained = words.iter().any(|word| {
+        word.chars().count() >= MIN_CONTAINED_WORD_CHARS && letters.contains(word.as_str())
+    });
+    let contained = if contained { CONTAINED_WORD_SCORE } else { 0.0 };
+    Some(spelled.max(by_word).max(contained))
+}
+
+/// Lowercase words of a name, without punctuation and honorifics
+pub fn name_words(name: &str) -> Vec<String> {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty() && !HONORIFICS.contains(word))
+        .map(str::to_string)
+        .collect()
+}
+
+/// How closely two words of names match, from 0 to 1
+fn word_match(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let (shorter, longer) = if a.chars().count() <= b.chars().count() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    if shorter.chars().count() == 1 {
+        return if longer.starts_with(shorter) {
+            INITIAL_SCORE
+        } else {
+            0.0
+        };
+    }
+    let phonetic = match (soundex(a), soundex(b)) {
+        (Some(a), Some(b))
+            if a == b && a.trim_end_matches('0').len() >= MIN_SOUNDEX_SIGNIFICANT_CHARS =>
+        {
+            PHONETIC_SCORE
+        },
+        _ => 0.0,
+    };
+    similarity(a, b).max(phonetic)
+}
+
+/// Soundex digit of an uppercase letter, or `None` for vowels and H, W, and Y
+fn soundex_digit(letter: char) -> Option<char> {
+    match letter {
+        'B' | 'F' | 'P' | 'V' => Some('1'),
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+        'D' | 'T' => Some('3'),
+        'L' => Some('4'),
+        'M' | 'N' => Some('5'),
+        'R' => Some('6'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein_and_similarity() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("josé", "jose"), 1);
+        assert_eq!(similarity("smith", "smith"), 1.0);
+        assert_eq!(similarity("smith", "smyth"), 0.8);
+        assert_eq!(similarity("", ""), 1.0);
+    }
+
+    #[test]
+    fn test_soundex() {
+        assert_eq!(soundex("Robert").as_deref(), Some("R163"));
+        assert_eq!(soundex("Rupert").as_deref(), Some("R163"));
+        assert_eq!(soundex("Ashcraft").as_deref(), Some("A261"));
+        assert_eq!(soundex("Tymczak").as_deref(), Some("T522"));
+        assert_eq!(soundex("Pfister").as_deref(), Some("P236"));
+        assert_eq!(soundex("Lee").as_deref(), Some("L000"));
+        assert_eq!(soundex("123"), None);
+    }
+
+    #[test]
+    fn test_name_match() {
+        let score = |a, b| name_match(a, b).unwrap();
+        assert_eq!(score("John Smith", "SMITH/JOHN"), 1.0);
+        assert_eq!(score("John Q. Smith", "Mr John Smith"), 1.0);
+        assert!((score("J Smith", "John Smith") - 0.95).abs() < 1e-9);
+        assert!(score("Steven Smith", "Stephen Smith") >= 0.9);
+        assert!(score("Jon Smith", "Jonathan Smith") > 0.6);
+        assert!(score("John Smith", "Jane Doe") < 0.3);
+        assert_eq!(name_match("John Smith", " - "), None);
+    }
+
+    #[test]
+    fn test_full_name_match() {
+        let score = |a, b| full_name_match(a, b).unwrap();
+        assert_eq!(score("Ivan Petrov", "PETROV, Ivan"), 1.0);
+        let patronymic = score("Ivan Petrov", "PETROV, Ivan Ivanovich");
+        assert!(patronymic > 0.85 && patronymic < 1.0, "{patronymic}");
+        assert!(score("Ivan Petrov", "Ivan Petrof") > 0.9);
+        assert!((score("John", "John Smith") - 2.0 / 3.0).abs() < 1e-9);
+        assert!(name_match("John", "John Smith").unwrap() > score("John", "John Smith"));
+        assert_eq!(full_name_match("", "John Smith"), None);
+    }
+
+    #[test]
+    fn test_email_name_match() {
+        let score = |email| email_name_match("Jonathan Smith", email).unwrap();
+        assert_eq!(score("jonathan.smith@example.com"), 1.0);
+        assert_eq!(score("jsmith87@example.com"), 1.0);
+        assert_eq!(score("smithj+shop@example.com"), 1.0);
+        assert_eq!(score("the_smith_family@example.com"), CONTAINED_WORD_SCORE);
+        assert!(score("xk42qz@example.com") < 0.3);
+        assert_eq!(email_name_match("Jonathan Smith", "1987@example.com"), None);
+        assert_eq!(email_name_match("Jonathan Smith", "not an address"), None);
+    }
+}
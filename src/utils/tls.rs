@@ -0,0 +1,125 @@
+//! TLS client fingerprints
+//!
+//! Merchants whose edge terminates TLS can pass the JA3 and JA4 fingerprints of each visitor's
+//! TLS handshake. Unlike a user agent, a handshake is hard to fake: it is shaped by the TLS
+//! library the client was built with, so scripts posing as browsers give themselves away.
+
+/// JA3 fingerprints that public threat intelligence blocklists attribute to malware families
+/// and anonymizing clients, with the name they are reported under
+const KNOWN_BAD_JA3: &[(&str, &str)] = &[
+    ("e7d705a3286e19ea42f587b344ee6865", "Tor client"),
+    ("6734f37431670b3ab4292b8f60f29984", "Trickbot"),
+    ("51c64c77e60f3980eea90869b68c58a8", "Dridex"),
+];
+
+/// The parts of a JA4 fingerprint that say what kind of client made the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ja4<'a> {
+    /// Transport: `t` for TCP, `q` for QUIC, `d` for DTLS
+    pub protocol: char,
+    /// Highest TLS version offered, e.g. `13` for TLS 1.3
+    pub version: &'a str,
+    /// Whether the client named the server it connected to
+    pub sni: bool,
+    /// First and last characters of the first ALPN protocol offered, `00` for none
+    pub alpn: &'a str,
+}
+
+/// Whether `value` is a JA3 fingerprint: an MD5 hash in hex
+pub fn is_ja3(value: &str) -> bool {
+    value.len() == 32 && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Parse a JA4 fingerprint such as `t13d1516h2_8daaf6152771_e5627efa2ab1`, or `None` if it is
+/// malformed
+pub fn parse_ja4(value: &str) -> Option<Ja4<'_>> {
+    let mut sections = value.split('_');
+    let (a, b, c) = (sections.next()?, sections.next()?, sections.next()?);
+    if sections.next().is_some()
+        || a.len() != 10
+        || !a.is_ascii()
+        || ![b, c]
+            .iter()
+            .all(|hash| hash.len() == 12 && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+    {
+        return None;
+    }
+    let protocol = a.chars().next()?;
+    let sni = match &a[3..4] {
+        "d" => true,
+        "i" => false,
+        _ => return None,
+    };
+    if !matches!(protocol, 't' | 'q' | 'd') || !a[4..8].bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(Ja4 {
+        protocol,
+        version: &a[1..3],
+        sni,
+        alpn: &a[8..10],
+    })
+}
+
+/// Name of the known-bad client a JA3 fingerprint belongs to
+pub fn known_bad_client(ja3: &str) -> Option<&'static str> {
+    KNOWN_BAD_JA3
+        .iter()
+        .find(|(hash, _)| hash.eq_ignore_ascii_case(ja3))
+        .map(|(_, name)| *name)
+}
+
+/// Why a handshake could not have come from a current web browser, if it could not
+///
+/// Browsers offer TLS 1.2 or later, name the server they connect to, and negotiate HTTP/2 or
+/// HTTP/1.1 by ALPN; HTTP libraries often skip the last two.
+pub fn non_browser_reason(ja4: &Ja4<'_>) -> Option<&'static str> {
+    if !matches!(ja4.version, "12" | "13") {
+        Some("offers an outdated TLS version")
+    } else if !ja4.sni {
+        Some("does not name the server")
+    } else if ja4.alpn == "00" {
+        Some("does not negotiate an application protocol")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ja4() {
+        let chrome = parse_ja4("t13d1516h2_8daaf6152771_e5627efa2ab1").unwrap();
+        assert_eq!(
+            chrome,
+            Ja4 {
+                protocol: 't',
+                version: "13",
+                sni: true,
+                alpn: "h2",
+            }
+        );
+        assert_eq!(non_browser_reason(&chrome), None);
+
+        let script = parse_ja4("t12i190800_d83cc789557e_7af1ed941c26").unwrap();
+        assert!(!script.sni);
+        assert!(non_browser_reason(&script).is_some());
+
+        assert!(parse_ja4("t13d1516h2_8daaf6152771").is_none());
+        assert!(parse_ja4("x13d1516h2_8daaf6152771_e5627efa2ab1").is_none());
+        assert!(parse_ja4("t13x1516h2_8daaf6152771_e5627efa2ab1").is_none());
+    }
+
+    #[test]
+    fn test_known_bad_client() {
+        assert!(is_ja3("E7D705A3286E19EA42F587B344EE6865"));
+        assert!(!is_ja3("e7d705a3"));
+        assert_eq!(
+            known_bad_client("E7D705A3286E19EA42F587B344EE6865"),
+            Some("Tor client")
+        );
+        assert_eq!(known_bad_client("cd08e31494f9531f560d64c695473da9"), None);
+    }
+}
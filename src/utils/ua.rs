@@ -0,0 +1,224 @@
+//! User-agent parsing
+//!
+//! Recognizes the browsers, operating systems, and automation tools that matter for fraud
+//! scoring by the tokens their user agents carry. Unrecognized user agents parse to empty
+//! details rather than failing.
+
+use crate::models::device::{DeviceClass, UserAgentDetails};
+
+/// Tokens of headless browsers, browser automation frameworks, and HTTP libraries, with the
+/// name they are reported under, checked in order
+const AUTOMATION_TOKENS: &[(&str, &str)] = &[
+    ("headlesschrome", "HeadlessChrome"),
+    ("phantomjs", "PhantomJS"),
+    ("slimerjs", "SlimerJS"),
+    ("selenium", "Selenium"),
+    ("webdriver", "WebDriver"),
+    ("puppeteer", "Puppeteer"),
+    ("playwright", "Playwright"),
+    ("nightmare", "Nightmare"),
+    ("cypress", "Cypress"),
+    ("python-requests", "python-requests"),
+    ("python-urllib", "Python urllib"),
+    ("aiohttp", "aiohttp"),
+    ("scrapy", "Scrapy"),
+    ("curl/", "curl"),
+    ("wget/", "Wget"),
+    ("go-http-client", "Go http client"),
+    ("apache-httpclient", "Apache HttpClient"),
+    ("java/", "Java"),
+    ("libwww-perl", "libwww-perl"),
+    ("node-fetch", "node-fetch"),
+    ("axios/", "axios"),
+    ("postmanruntime", "Postman"),
+    ("insomnia", "Insomnia"),
+    ("headless", "Headless browser"),
+];
+
+/// Tokens of crawlers and other self-declared bots
+const CRAWLER_TOKENS: &[&str] = &["bot/", "bot;", "bot)", "crawler", "spider", "slurp"];
+
+/// Browser tokens, with the name they are reported under, checked in order since most
+/// browsers also claim to be the ones they are built on
+const BROWSER_TOKENS: &[(&str, &str)] = &[
+    ("edg/", "Edge"),
+    ("edga/", "Edge"),
+    ("edgios/", "Edge"),
+    ("edge/", "Edge"),
+    ("opr/", "Opera"),
+    ("opera/", "Opera"),
+    ("samsungbrowser/", "Samsung Internet"),
+    ("yabrowser/", "Yandex Browser"),
+    ("firefox/", "Firefox"),
+    ("fxios/", "Firefox"),
+    ("headlesschrome/", "Chrome"),
+    ("crios/", "Chrome"),
+    ("chrome/", "Chrome"),
+    ("msie ", "Internet Explorer"),
+    ("trident/", "Internet Explorer"),
+];
+
+/// Parse a user agent string into the browser, operating system, device class, and any
+/// automation tool it names
+pub fn parse(user_agent: &str) -> UserAgentDetails {
+    let ua = user_agent.to_ascii_lowercase();
+    let automation = AUTOMATION_TOKENS
+        .iter()
+        .find(|(token, _)| ua.contains(token))
+        .map(|(_, name)| (*name).to_string())
+        .or_else(|| {
+            CRAWLER_TOKENS
+                .iter()
+                .any(|token| ua.contains(token))
+                .then(|| crawler_name(user_agent))
+        });
+
+    let (browser, browser_version) =
+        match BROWSER_TOKENS.iter().find(|(token, _)| ua.contains(token)) {
+            Some((token, name)) => (Some((*name).to_string()), version_after(&ua, token)),
+            // Safari reports its own version under "Version/"
+            None if ua.contains("safari/") && ua.contains("version/") => {
+                (Some("Safari".to_string()), version_after(&ua, "version/"))
+            },
+            None => (None, None),
+        };
+
+    let os = if ua.contains("windows") {
+        Some("Windows")
+    } else if ua.contains("android") {
+        Some("Android")
+    } else if ua.contains("iphone") || ua.contains("ipad") || ua.contains("ipod") {
+        Some("iOS")
+    } else if ua.contains("cros ") {
+        Some("ChromeOS")
+    } else if ua.contains("mac os x") || ua.contains("macintosh") {
+        Some("macOS")
+    } else if ua.contains("linux") {
+        Some("Linux")
+    } else {
+        None
+    };
+
+    let device_class = if automation.is_some() {
+        DeviceClass::Bot
+    } else if ua.contains("ipad")
+        || ua.contains("tablet")
+        || (ua.contains("android") && !ua.contains("mobile"))
+    {
+        DeviceClass::Tablet
+    } else if ua.contains("mobi") || ua.contains("iphone") || ua.contains("ipod") {
+        DeviceClass::Mobile
+    } else if os.is_some() {
+        DeviceClass::Desktop
+    } else {
+        DeviceClass::Unknown
+    };
+
+    UserAgentDetails {
+        browser,
+        browser_version,
+        os: os.map(str::to_string),
+        device_class,
+        automation,
+    }
+}
+
+/// Version number following `token`, e.g. `126.0.6478.61` after `chrome/`
+fn version_after(ua: &str, token: &str) -> Option<String> {
+    let start = ua.find(token)? + token.len();
+    let version: String = ua[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let version = version.trim_end_matches('.');
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+/// Name a crawler gives itself: the product token naming the bot, e.g. `Googlebot`, or
+/// `Crawler` if there is none
+fn crawler_name(user_agent: &str) -> String {
+    user_agent
+        .split(|c: char| c.is_whitespace() || matches!(c, ';' | '(' | ')'))
+        .map(|product| product.split('/').next().unwrap_or_default())
+        .find(|name| {
+            let name = name.to_ascii_lowercase();
+            ["bot", "crawler", "spider", "slurp"]
+                .iter()
+                .any(|token| name.contains(token))
+        })
+        .map_or_else(|| "Crawler".to_string(), str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_common_browsers() {
+        let chrome = parse(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+             Chrome/126.0.6478.61 Safari/537.36",
+        );
+        assert_eq!(chrome.browser.as_deref(), Some("Chrome"));
+        assert_eq!(chrome.browser_version.as_deref(), Some("126.0.6478.61"));
+        assert_eq!(chrome.os.as_deref(), Some("Windows"));
+        assert_eq!(chrome.device_class, DeviceClass::Desktop);
+        assert!(chrome.automation.is_none());
+
+        let safari = parse(
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 \
+             (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1",
+        );
+        assert_eq!(safari.browser.as_deref(), Some("Safari"));
+        assert_eq!(safari.browser_version.as_deref(), Some("17.5"));
+        assert_eq!(safari.os.as_deref(), Some("iOS"));
+        assert_eq!(safari.device_class, DeviceClass::Mobile);
+
+        let edge = parse(
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like \
+             Gecko) Chrome/126.0.0.0 Safari/537.36 Edg/126.0.2592.68",
+        );
+        assert_eq!(edge.browser.as_deref(), Some("Edge"));
+        assert_eq!(edge.os.as_deref(), Some("macOS"));
+
+        let tablet = parse(
+            "Mozilla/5.0 (Linux; Android 14; SM-X710) AppleWebKit/537.36 (KHTML, like Gecko) \
+             Chrome/126.0.0.0 Safari/537.36",
+        );
+        assert_eq!(tablet.os.as_deref(), Some("Android"));
+        assert_eq!(tablet.device_class, DeviceClass::Tablet);
+
+        let unknown = parse("Mozilla/5.0");
+        assert_eq!(unknown, UserAgentDetails::default());
+    }
+
+    #[test]
+    fn test_parse_detects_automation_and_crawlers() {
+        let headless = parse(
+            "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) \
+             HeadlessChrome/126.0.0.0 Safari/537.36",
+        );
+        assert_eq!(headless.automation.as_deref(), Some("HeadlessChrome"));
+        assert_eq!(headless.browser.as_deref(), Some("Chrome"));
+        assert_eq!(headless.device_class, DeviceClass::Bot);
+
+        assert_eq!(
+            parse("python-requests/2.32.3").automation.as_deref(),
+            Some("python-requests")
+        );
+        assert_eq!(parse("curl/8.7.1").automation.as_deref(), Some("curl"));
+        assert_eq!(
+            parse("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)")
+                .automation
+                .as_deref(),
+            Some("Googlebot")
+        );
+        // Phone brands that merely contain "bot" are not crawlers
+        let cubot = parse(
+            "Mozilla/5.0 (Linux; Android 13; CUBOT_X30 Build/TP1A) AppleWebKit/537.36 (KHTML, \
+             like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36",
+        );
+        assert!(cubot.automation.is_none());
+        assert_eq!(cubot.device_class, DeviceClass::Mobile);
+    }
+}